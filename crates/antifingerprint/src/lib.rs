@@ -13,7 +13,9 @@ mod webgl;
 // mod timezone;
 mod metrics;
 
-use citadel_security::context::{FingerprintProtection, SecurityContext};
+use citadel_security::context::{
+    FingerprintProtection, FingerprintProtectionLevel, SecurityContext,
+};
 use citadel_security::privacy::{PrivacyEvent, PrivacyEventSender};
 use log::info;
 use metrics::FingerprintMetrics;
@@ -24,6 +26,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::watch;
 
 /// Errors that can occur during anti-fingerprinting operations
 #[derive(Error, Debug)]
@@ -50,24 +53,74 @@ pub struct FingerprintManager {
     session_seed: u64,
     /// Whether this browser session should have consistent fingerprints
     consistent_within_session: bool,
+    /// Whether the session seed was fixed by the caller, for tests
+    deterministic: bool,
     /// Optional privacy event sender for the scoreboard
     privacy_sender: Option<PrivacyEventSender>,
+    /// Protection level changes from the settings store
+    level_updates: Option<watch::Receiver<FingerprintProtectionLevel>>,
 }
 
 impl FingerprintManager {
     /// Create a new fingerprint manager with the provided security context
     pub fn new(security_context: SecurityContext) -> Self {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-
         Self {
             security_context,
-            session_seed: rng.gen(),
+            session_seed: rand::random(),
             consistent_within_session: true,
+            deterministic: false,
             privacy_sender: None,
+            level_updates: None,
         }
     }
 
+    /// A manager whose noise is fixed by `session_seed`: every run with the
+    /// same seed spoofs the same values, for golden tests of canvas, WebGL
+    /// and audio output. Never use it outside tests; a browser session must
+    /// draw its own seed, or sessions could be linked by their noise.
+    pub fn with_session_seed(security_context: SecurityContext, session_seed: u64) -> Self {
+        Self {
+            session_seed,
+            deterministic: true,
+            ..Self::new(security_context)
+        }
+    }
+
+    /// Whether the session seed was fixed with [`Self::with_session_seed`]
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Follow protection level changes from a settings channel; they apply
+    /// at the next [`Self::refresh_settings`]
+    pub fn follow_protection_level(
+        &mut self,
+        mut updates: watch::Receiver<FingerprintProtectionLevel>,
+    ) {
+        updates.mark_changed();
+        self.level_updates = Some(updates);
+    }
+
+    /// Apply a pending protection level change. Call at navigation so a
+    /// page never sees its protections change while it runs. Returns
+    /// whether the configuration changed.
+    pub fn refresh_settings(&mut self) -> bool {
+        let Some(updates) = &mut self.level_updates else {
+            return false;
+        };
+        if !updates.has_changed().unwrap_or(false) {
+            return false;
+        }
+        let level = *updates.borrow_and_update();
+        if level == self.protection_config().level {
+            return false;
+        }
+        info!("Fingerprint protection level changed to {:?}", level);
+        self.security_context
+            .set_fingerprint_protection_level(level);
+        true
+    }
+
     /// Set the privacy event sender for scoreboard integration
     pub fn set_privacy_sender(&mut self, sender: PrivacyEventSender) {
         self.privacy_sender = Some(sender);
@@ -88,20 +141,30 @@ impl FingerprintManager {
         self.security_context.fingerprint_protection()
     }
 
-    /// Set whether fingerprints should be consistent within a session
+    /// Set whether fingerprints should be consistent within a session.
+    /// Deterministic managers stay consistent regardless.
     pub fn set_consistent_within_session(&mut self, consistent: bool) {
         self.consistent_within_session = consistent;
     }
 
-    /// Generate a domain-specific seed for deterministic randomization
+    /// Generate a domain-specific seed for deterministic randomization.
+    ///
+    /// FNV-1a over the domain, starting from the session seed, finished with
+    /// SplitMix64. Unlike `DefaultHasher` this is the same on every platform
+    /// and Rust release, so a fixed session seed pins the noise everywhere.
     pub fn domain_seed(&self, domain: &str) -> u64 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
 
-        let mut hasher = DefaultHasher::new();
-        domain.hash(&mut hasher);
-        self.session_seed.hash(&mut hasher);
-        hasher.finish()
+        let hash = domain
+            .bytes()
+            .fold(FNV_OFFSET ^ self.session_seed, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            });
+        let mut z = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
     }
 
     /// Apply a subtle random noise to a numeric value
@@ -113,7 +176,7 @@ impl FingerprintManager {
         let domain_seed = self.domain_seed(domain);
 
         // Use ChaCha20Rng which is cryptographically secure instead of StdRng
-        let mut rng = if self.consistent_within_session {
+        let mut rng = if self.consistent_within_session || self.deterministic {
             ChaCha20Rng::seed_from_u64(domain_seed)
         } else {
             ChaCha20Rng::from_entropy()
@@ -368,12 +431,40 @@ impl AntiFingerprintManager {
         AudioProtection,
         NavigatorProtection,
     ) {
-        let metrics = self.metrics();
-        let _sc = self.config.enabled;
-
         // Create security context for fingerprint manager
         let security_context = SecurityContext::new(10);
-        let fp_manager = FingerprintManager::new(security_context);
+        self.protection_modules_for(FingerprintManager::new(security_context))
+    }
+
+    /// Like [`Self::create_protection_modules`], with noise fixed by
+    /// `session_seed` (see [`FingerprintManager::with_session_seed`]); for
+    /// tests only
+    pub fn create_deterministic_protection_modules(
+        &self,
+        session_seed: u64,
+    ) -> (
+        CanvasProtection,
+        WebGLProtection,
+        AudioProtection,
+        NavigatorProtection,
+    ) {
+        let security_context = SecurityContext::new(10);
+        self.protection_modules_for(FingerprintManager::with_session_seed(
+            security_context,
+            session_seed,
+        ))
+    }
+
+    fn protection_modules_for(
+        &self,
+        fp_manager: FingerprintManager,
+    ) -> (
+        CanvasProtection,
+        WebGLProtection,
+        AudioProtection,
+        NavigatorProtection,
+    ) {
+        let metrics = self.metrics();
 
         // Create all protection modules with metrics attached
         let canvas_protection =
@@ -467,6 +558,49 @@ pub struct FingerprintMetricsSummary {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_protection_level_follows_settings() {
+        let (settings, updates) = watch::channel(FingerprintProtectionLevel::Maximum);
+        let mut manager = FingerprintManager::new(SecurityContext::new(10));
+        manager.follow_protection_level(updates);
+        assert_eq!(
+            manager.protection_config().level,
+            FingerprintProtectionLevel::Medium
+        );
+
+        assert!(manager.refresh_settings());
+        assert_eq!(
+            manager.protection_config().level,
+            FingerprintProtectionLevel::Maximum
+        );
+        assert!(!manager.refresh_settings());
+
+        settings.send_replace(FingerprintProtectionLevel::None);
+        assert!(manager.refresh_settings());
+        assert!(!manager.protection_config().canvas_noise);
+    }
+
+    #[test]
+    fn test_fixed_session_seed_pins_domain_seeds() {
+        let manager = FingerprintManager::with_session_seed(SecurityContext::new(10), 42);
+        assert!(manager.is_deterministic());
+        assert!(!FingerprintManager::new(SecurityContext::new(10)).is_deterministic());
+
+        // Golden values: these must not change between builds
+        assert_eq!(manager.domain_seed("example.com"), 0x534c_f166_ef72_bd85);
+        assert_eq!(manager.domain_seed("example.org"), 0x5869_0f14_a3cd_9bef);
+        let other = FingerprintManager::with_session_seed(SecurityContext::new(10), 43);
+        assert_eq!(other.domain_seed("example.com"), 0x959c_4820_da30_f9a1);
+
+        // Asking for fresh noise per call does not undo a fixed seed
+        let mut manager = manager;
+        manager.set_consistent_within_session(false);
+        assert_eq!(
+            manager.apply_noise(100.0f64, 0.1, "example.com"),
+            manager.apply_noise(100.0f64, 0.1, "example.com")
+        );
+    }
+
     #[test]
     fn test_apply_noise() {
         // Test basic noise application
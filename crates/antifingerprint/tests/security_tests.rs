@@ -208,6 +208,57 @@ mod fingerprint_manager_tests {
     }
 }
 
+#[cfg(test)]
+mod deterministic_mode_tests {
+    use super::*;
+
+    /// Everything each module spoofs for `domain`, from managers seeded with `seed`
+    fn spoofed_outputs(seed: u64, domain: &str) -> (Vec<u8>, Vec<f32>, Vec<f32>, Vec<u8>) {
+        let mut security_context = SecurityContext::new(10);
+        security_context.set_fingerprint_protection_level(FingerprintProtectionLevel::Maximum);
+        let manager = FingerprintManager::with_session_seed(security_context, seed);
+
+        let mut image = test_utils::create_test_canvas_data(8, 8);
+        CanvasProtection::new(manager.clone())
+            .protect_image_data(&mut image, 8, 8, domain)
+            .unwrap();
+        let mut vertices = vec![0.0f32, 0.5, 1.0, -0.5];
+        WebGLProtection::new(manager.clone())
+            .normalize_vertices(&mut vertices, domain)
+            .unwrap();
+        let audio = AudioProtection::new(manager);
+        let mut samples = vec![0.25f32, -0.5, 0.75];
+        audio.protect_audio_buffer(&mut samples, domain).unwrap();
+        let mut frequencies = vec![128u8; 16];
+        audio
+            .protect_frequency_data(&mut frequencies, domain)
+            .unwrap();
+
+        (image, vertices, samples, frequencies)
+    }
+
+    #[test]
+    fn test_fixed_seed_gives_stable_noise_across_modules() {
+        let golden = spoofed_outputs(7, "example.com");
+        assert_eq!(golden, spoofed_outputs(7, "example.com"));
+        // The noise is still there, and still keyed by seed and site
+        assert_ne!(golden.0, test_utils::create_test_canvas_data(8, 8));
+        assert_ne!(golden, spoofed_outputs(8, "example.com"));
+        assert_ne!(golden, spoofed_outputs(7, "example.org"));
+    }
+
+    #[test]
+    fn test_deterministic_protection_modules() {
+        let manager = AntiFingerprintManager::new(AntiFingerprintConfig::default());
+        let (canvas_a, ..) = manager.create_deterministic_protection_modules(7);
+        let (canvas_b, ..) = manager.create_deterministic_protection_modules(7);
+        assert_eq!(
+            canvas_a.get_text_position_noise(10.0, 20.0, "example.com"),
+            canvas_b.get_text_position_noise(10.0, 20.0, "example.com")
+        );
+    }
+}
+
 #[cfg(test)]
 mod canvas_protection_tests {
     use super::*;
@@ -0,0 +1,545 @@
+//! Accessibility bridge to the platform screen reader APIs
+//!
+//! Converts the page accessibility tree into AccessKit tree updates. Two sources
+//! feed it: the parser's [`AccessibilityTree`] (DOM + layout path) and the
+//! sanitized display list returned by a tab's ZKVM boundary, which never exposes
+//! the raw DOM to the host. Every content change produces a fresh full update so
+//! the platform adapter can diff against what it announced previously.
+//!
+//! Each browser window has a [`WindowAdapter`] for the platform's screen reader
+//! API (NSAccessibility on macOS, AT-SPI on Linux and the BSDs). The bridge is
+//! attached to the focused window's adapter and pushes every rebuild and focus
+//! move to it; what the screen reader asks for comes back through
+//! [`WindowAdapters::drain`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use accesskit::{
+    Action, ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, Node, NodeId,
+    Rect, Role, Toggled, TreeId, TreeInfo, TreeUpdate,
+};
+use citadel_parser::accessibility::{AccessibilityTree, Role as PageRole};
+use citadel_parser::LayoutRect;
+use citadel_tabs::{DisplayKind, RenderedContent};
+use iced::window;
+
+/// Node ID used for the document root of display-list trees; items use their
+/// display-list index so focus targets map onto nodes directly
+const DISPLAY_LIST_ROOT: NodeId = NodeId(u64::MAX);
+
+/// Holds the latest accessibility snapshot for the active page
+#[derive(Debug, Default)]
+pub struct AccessibilityBridge {
+    /// Latest full tree update, if any page content is loaded
+    current: Option<TreeUpdate>,
+    /// Node that currently has keyboard focus
+    focus: Option<NodeId>,
+    /// Incremented on every content change
+    generation: u64,
+    /// Adapter of the window showing the page, which gets every update
+    adapter: Option<SharedAdapter>,
+}
+
+impl AccessibilityBridge {
+    /// Create an empty bridge
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild from a parser accessibility tree
+    pub fn update_from_tree(&mut self, tree: &AccessibilityTree) {
+        let mut nodes = Vec::with_capacity(tree.len());
+        for page_node in tree.nodes() {
+            let mut node = Node::new(map_role(page_node.role));
+            if let PageRole::Heading(level) = page_node.role {
+                node.set_level(level as usize);
+            }
+            if !page_node.name.is_empty() {
+                node.set_label(page_node.name.as_str());
+            }
+            if let Some(value) = &page_node.value {
+                node.set_value(value.as_str());
+            }
+            if let Some(url) = &page_node.url {
+                node.set_url(url.as_str());
+            }
+            if let Some(bounds) = &page_node.bounds {
+                node.set_bounds(to_rect(bounds));
+            }
+            if page_node.state.disabled {
+                node.set_disabled();
+            }
+            if page_node.state.required {
+                node.set_required();
+            }
+            if let Some(checked) = page_node.state.checked {
+                node.set_toggled(Toggled::from(checked));
+            }
+            if page_node.state.focusable {
+                node.add_action(Action::Focus);
+            }
+            if page_node.role.is_interactive() {
+                node.add_action(Action::Click);
+            }
+            node.set_children(
+                page_node
+                    .children
+                    .iter()
+                    .map(|id| NodeId(*id as u64))
+                    .collect::<Vec<_>>(),
+            );
+            nodes.push((NodeId(page_node.id as u64), node));
+        }
+
+        self.install(nodes, NodeId(tree.root() as u64));
+    }
+
+    /// Rebuild from the sanitized display list of a ZKVM-rendered page
+    pub fn update_from_display_list(&mut self, content: &RenderedContent) {
+        let mut nodes = Vec::with_capacity(content.display_list.len() + 1);
+        let mut children = Vec::with_capacity(content.display_list.len());
+
+        for (index, item) in content.display_list.iter().enumerate() {
+            if item.text.is_empty() {
+                continue;
+            }
+            let id = NodeId(index as u64);
+            let mut node = match item.kind {
+                DisplayKind::Heading => Node::new(Role::Heading),
+                DisplayKind::Link => Node::new(Role::Link),
+                DisplayKind::Paragraph => Node::new(Role::Paragraph),
+                DisplayKind::Generic => Node::new(Role::Label),
+            };
+            node.set_label(item.text.as_str());
+            if let Some(href) = &item.href {
+                node.set_url(href.as_str());
+                node.add_action(Action::Focus);
+                node.add_action(Action::Click);
+            }
+            node.set_bounds(Rect::new(
+                item.x as f64,
+                item.y as f64,
+                (item.x + item.width) as f64,
+                (item.y + item.height) as f64,
+            ));
+            children.push(id);
+            nodes.push((id, node));
+        }
+
+        let mut root = Node::new(Role::Document);
+        if !content.title.is_empty() {
+            root.set_label(content.title.as_str());
+        }
+        root.set_bounds(Rect::new(
+            0.0,
+            0.0,
+            content.width as f64,
+            content.height as f64,
+        ));
+        root.set_children(children);
+        nodes.push((DISPLAY_LIST_ROOT, root));
+
+        self.install(nodes, DISPLAY_LIST_ROOT);
+    }
+
+    /// Drop the current snapshot (page unloaded or tab closed)
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.focus = None;
+        self.generation += 1;
+        self.push();
+    }
+
+    /// Send updates to `adapter` from now on, starting with the current tree
+    pub fn attach(&mut self, adapter: Option<SharedAdapter>) {
+        self.adapter = adapter;
+        self.push();
+    }
+
+    /// Move keyboard focus to a node of the current tree
+    pub fn set_focus(&mut self, id: u32) {
+        let id = NodeId(id as u64);
+        if let Some(update) = &mut self.current {
+            if update.nodes.iter().any(|(node_id, _)| *node_id == id) {
+                update.focus = id;
+                self.focus = Some(id);
+            }
+        }
+        self.push();
+    }
+
+    /// Latest tree update to hand to the platform adapter
+    pub fn tree_update(&self) -> Option<TreeUpdate> {
+        self.current.clone()
+    }
+
+    /// Generation counter, bumped on every content change
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of nodes in the current snapshot
+    pub fn node_count(&self) -> usize {
+        self.current.as_ref().map(|u| u.nodes.len()).unwrap_or(0)
+    }
+
+    fn install(&mut self, nodes: Vec<(NodeId, Node)>, root: NodeId) {
+        // Keep focus only if the focused node survived the rebuild
+        let focus = self
+            .focus
+            .filter(|id| nodes.iter().any(|(node_id, _)| node_id == id))
+            .unwrap_or(root);
+        self.focus = Some(focus).filter(|id| *id != root);
+        self.current = Some(TreeUpdate {
+            nodes,
+            tree: Some(TreeInfo::new(root)),
+            tree_id: TreeId::ROOT,
+            focus,
+        });
+        self.generation += 1;
+        log::debug!(
+            "♿ Accessibility tree updated: {} nodes (generation {})",
+            self.node_count(),
+            self.generation
+        );
+        self.push();
+    }
+
+    /// Hand the current tree to the attached adapter, if a screen reader is
+    /// listening; a blank document stands in while no page is loaded
+    fn push(&self) {
+        if let Some(adapter) = &self.adapter {
+            adapter
+                .borrow_mut()
+                .update(|| self.tree_update().unwrap_or_else(blank_tree));
+        }
+    }
+}
+
+/// A window's adapter, owned by [`WindowAdapters`] and shared with the
+/// bridge while the window has focus
+pub type SharedAdapter = Rc<RefCell<WindowAdapter>>;
+
+/// What a screen reader asked of a window
+#[derive(Debug)]
+pub enum AdapterRequest {
+    /// It started listening and wants the current tree
+    InitialTree,
+    /// It wants an action performed on a node, e.g. focus or click a link
+    Action(ActionRequest),
+}
+
+/// Handle to a window's native view, which the macOS adapter attaches to.
+/// Other platforms' adapters need none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NativeView(Option<usize>);
+
+impl NativeView {
+    /// The `NSView` behind a window
+    #[cfg(target_os = "macos")]
+    pub fn of(handle: &raw_window_handle::WindowHandle<'_>) -> Self {
+        match handle.as_raw() {
+            raw_window_handle::RawWindowHandle::AppKit(appkit) => {
+                Self(Some(appkit.ns_view.as_ptr() as usize))
+            }
+            _ => Self(None),
+        }
+    }
+}
+
+/// The platform screen reader adapter of one window
+pub struct WindowAdapter(platform::Adapter);
+
+impl WindowAdapter {
+    /// Push a tree update, built only if a screen reader is listening
+    pub fn update(&mut self, build: impl FnOnce() -> TreeUpdate) {
+        self.0.update(build);
+    }
+
+    /// Tell the screen reader whether the window has focus
+    pub fn set_focused(&mut self, focused: bool) {
+        self.0.set_focused(focused);
+    }
+}
+
+impl fmt::Debug for WindowAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowAdapter").finish_non_exhaustive()
+    }
+}
+
+/// The adapters of all open browser windows
+#[derive(Debug)]
+pub struct WindowAdapters {
+    adapters: HashMap<window::Id, SharedAdapter>,
+    sender: Sender<AdapterRequest>,
+    requests: Receiver<AdapterRequest>,
+}
+
+impl Default for WindowAdapters {
+    fn default() -> Self {
+        let (sender, requests) = mpsc::channel();
+        Self {
+            adapters: HashMap::new(),
+            sender,
+            requests,
+        }
+    }
+}
+
+impl WindowAdapters {
+    /// Create an adapter for a newly opened window. `None` where the platform
+    /// has no adapter (or the window no native view).
+    pub fn open(&mut self, id: window::Id, view: NativeView) -> Option<SharedAdapter> {
+        let handlers = Handlers {
+            requests: self.sender.clone(),
+        };
+        let adapter = Rc::new(RefCell::new(WindowAdapter(platform::Adapter::new(
+            view, handlers,
+        )?)));
+        self.adapters.insert(id, adapter.clone());
+        Some(adapter)
+    }
+
+    /// Mark `id` as the focused window, returning its adapter
+    pub fn focus(&mut self, id: window::Id) -> Option<SharedAdapter> {
+        for (window, adapter) in &self.adapters {
+            adapter.borrow_mut().set_focused(*window == id);
+        }
+        self.adapters.get(&id).cloned()
+    }
+
+    /// Drop a closed window's adapter
+    pub fn close(&mut self, id: window::Id) {
+        self.adapters.remove(&id);
+    }
+
+    /// Whether any window has an adapter, so requests can arrive
+    pub fn is_empty(&self) -> bool {
+        self.adapters.is_empty()
+    }
+
+    /// Requests screen readers made since the last call
+    pub fn drain(&self) -> Vec<AdapterRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+/// Forwards a window's screen reader requests to [`WindowAdapters::drain`]
+#[derive(Clone)]
+struct Handlers {
+    requests: Sender<AdapterRequest>,
+}
+
+impl ActivationHandler for Handlers {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        // The tree lives with the renderer; the app pushes it once it drains
+        // this request, and the adapter shows a placeholder until then
+        let _ = self.requests.send(AdapterRequest::InitialTree);
+        None
+    }
+}
+
+impl ActionHandler for Handlers {
+    fn do_action(&mut self, request: ActionRequest) {
+        let _ = self.requests.send(AdapterRequest::Action(request));
+    }
+}
+
+impl DeactivationHandler for Handlers {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Handlers, NativeView};
+    use accesskit::TreeUpdate;
+    use accesskit_macos::SubclassingAdapter;
+
+    pub struct Adapter(SubclassingAdapter);
+
+    impl Adapter {
+        pub fn new(view: NativeView, handlers: Handlers) -> Option<Self> {
+            let view = view.0? as *mut std::ffi::c_void;
+            // SAFETY: the view is the content view of a window iced keeps
+            // open until it reports the window closed, when the app drops
+            // this adapter
+            Some(Self(unsafe {
+                SubclassingAdapter::new(view, handlers.clone(), handlers)
+            }))
+        }
+
+        pub fn update(&mut self, build: impl FnOnce() -> TreeUpdate) {
+            if let Some(events) = self.0.update_if_active(build) {
+                events.raise();
+            }
+        }
+
+        pub fn set_focused(&mut self, focused: bool) {
+            if let Some(events) = self.0.update_view_focus_state(focused) {
+                events.raise();
+            }
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::{Handlers, NativeView};
+    use accesskit::TreeUpdate;
+
+    pub struct Adapter(accesskit_unix::Adapter);
+
+    impl Adapter {
+        pub fn new(_view: NativeView, handlers: Handlers) -> Option<Self> {
+            Some(Self(accesskit_unix::Adapter::new(
+                handlers.clone(),
+                handlers.clone(),
+                handlers,
+            )))
+        }
+
+        pub fn update(&mut self, build: impl FnOnce() -> TreeUpdate) {
+            self.0.update_if_active(build);
+        }
+
+        pub fn set_focused(&mut self, focused: bool) {
+            self.0.update_window_focus_state(focused);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::{Handlers, NativeView};
+    use accesskit::TreeUpdate;
+
+    /// No adapter on this platform
+    pub enum Adapter {}
+
+    impl Adapter {
+        pub fn new(_view: NativeView, _handlers: Handlers) -> Option<Self> {
+            None
+        }
+
+        pub fn update(&mut self, _build: impl FnOnce() -> TreeUpdate) {
+            match *self {}
+        }
+
+        pub fn set_focused(&mut self, _focused: bool) {
+            match *self {}
+        }
+    }
+}
+
+/// What a window announces with no page loaded
+fn blank_tree() -> TreeUpdate {
+    TreeUpdate {
+        nodes: vec![(DISPLAY_LIST_ROOT, Node::new(Role::Document))],
+        tree: Some(TreeInfo::new(DISPLAY_LIST_ROOT)),
+        tree_id: TreeId::ROOT,
+        focus: DISPLAY_LIST_ROOT,
+    }
+}
+
+/// Map a page role onto the AccessKit role set
+fn map_role(role: PageRole) -> Role {
+    match role {
+        PageRole::Document => Role::Document,
+        PageRole::Article => Role::Article,
+        PageRole::Banner => Role::Banner,
+        PageRole::Button => Role::Button,
+        PageRole::Cell => Role::Cell,
+        PageRole::CheckBox => Role::CheckBox,
+        PageRole::ColumnHeader => Role::ColumnHeader,
+        PageRole::ComboBox => Role::ComboBox,
+        PageRole::Complementary => Role::Complementary,
+        PageRole::ContentInfo => Role::ContentInfo,
+        PageRole::Figure => Role::Figure,
+        PageRole::Form => Role::Form,
+        PageRole::Generic => Role::GenericContainer,
+        PageRole::Heading(_) => Role::Heading,
+        PageRole::Image => Role::Image,
+        PageRole::Link => Role::Link,
+        PageRole::List => Role::List,
+        PageRole::ListItem => Role::ListItem,
+        PageRole::Main => Role::Main,
+        PageRole::Navigation => Role::Navigation,
+        PageRole::Paragraph => Role::Paragraph,
+        PageRole::RadioButton => Role::RadioButton,
+        PageRole::Region => Role::Region,
+        PageRole::Row => Role::Row,
+        PageRole::Separator => Role::Splitter,
+        PageRole::StaticText => Role::Label,
+        PageRole::Table => Role::Table,
+        PageRole::TextInput => Role::TextInput,
+    }
+}
+
+fn to_rect(bounds: &LayoutRect) -> Rect {
+    Rect::new(
+        bounds.x as f64,
+        bounds.y as f64,
+        (bounds.x + bounds.width) as f64,
+        (bounds.y + bounds.height) as f64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::parse_html;
+    use citadel_parser::security::SecurityContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_update_from_dom_tree() {
+        let dom = parse_html(
+            "<html><body><h1>Title</h1><a href=\"https://example.com/\">Go</a></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+        let tree = AccessibilityTree::build(&dom, None);
+
+        let mut bridge = AccessibilityBridge::new();
+        bridge.update_from_tree(&tree);
+
+        let update = bridge.tree_update().unwrap();
+        assert_eq!(update.nodes.len(), tree.len());
+        assert_eq!(update.tree.unwrap().root, NodeId(tree.root() as u64));
+        let link = update
+            .nodes
+            .iter()
+            .find(|(_, n)| n.role() == Role::Link)
+            .map(|(_, n)| n)
+            .unwrap();
+        assert_eq!(link.label(), Some("Go"));
+        assert!(link.supports_action(Action::Focus));
+    }
+
+    #[test]
+    fn test_focus_survives_rebuild_only_if_node_exists() {
+        let dom = parse_html(
+            "<html><body><a href=\"https://example.com/\">Go</a></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+        let tree = AccessibilityTree::build(&dom, None);
+        let link_id = tree.focusable_nodes()[0];
+
+        let mut bridge = AccessibilityBridge::new();
+        bridge.update_from_tree(&tree);
+        bridge.set_focus(link_id);
+        assert_eq!(bridge.tree_update().unwrap().focus, NodeId(link_id as u64));
+
+        bridge.update_from_tree(&tree);
+        assert_eq!(bridge.tree_update().unwrap().focus, NodeId(link_id as u64));
+
+        bridge.clear();
+        assert!(bridge.tree_update().is_none());
+    }
+}
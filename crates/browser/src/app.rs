@@ -3,24 +3,84 @@
 //! This module implements the main browser application with security-first design,
 //! ZKVM tab isolation, and privacy-preserving features.
 
+use accesskit::Action;
 use iced::keyboard::Key;
-use iced::{Application, Command, Element, Subscription, Theme};
+use iced::multi_window::Application;
+use iced::{window, Command, Element, Event, Subscription, Theme};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use url::Url;
-
+use zeroize::Zeroizing;
+
+use crate::accessibility::{AdapterRequest, NativeView, WindowAdapters};
+use crate::assets::{self, IconSet};
+use crate::clipboard::{self, ClipboardPolicy, CopyKind, PendingClear};
+use crate::content_budget::ContentTruncation;
+use crate::cookies;
+use crate::downloads::{self, DownloadBody, DownloadManager, DownloadState};
+use crate::dropped_content::{search_url, DroppedContent};
 use crate::engine::BrowserEngine;
+use crate::extensions::{self, Extensions};
+use crate::external_protocols::{self, ExternalProtocolPrefs, SchemeDispatch};
+use crate::filter_allowlist::{self, FilterAllowlist};
+use crate::focus::FocusActivation;
+use crate::history::{self, HistoryManager};
+use crate::image_decoder::{DecodedImage, ImageRequest};
+use crate::image_viewer::{self, ImageViewer, ViewerAction};
+use crate::keychain::{self, SecretStore};
+#[cfg(feature = "devtools")]
+use crate::layout_debug::LayoutDebugOverlay;
+use crate::link_preview;
+use crate::memory_profile::{self, MemorySettings};
+use crate::overlay_cleanup::{self, OverlayCleanup};
+use crate::page_escalation::PageEscalation;
+use crate::page_menu::{self, PageAction, PageHit};
+use crate::panic::{self, PanicOptions};
+use crate::performance::{MemoryConfig, PerformanceMonitor};
+use crate::power_profile::{self, PowerProfile};
+use crate::profile::{self, RecoveryKey};
 use crate::renderer::{CitadelRenderer, FormMessage, FormSubmission};
-use crate::ui::{CitadelUI, UIMessage};
+use crate::resource_caches;
+use crate::resource_loader::ResourceLoader;
+use crate::session::{self, SessionSnapshot};
+use crate::settings::{RuntimeSettings, SettingsStore};
+use crate::shutdown::{self, Shutdown, ShutdownStep};
+use crate::startup::{StartupPhase, StartupTimings};
+use crate::suggestions::{Bookmarks, SuggestionEngine};
+use crate::tab_menu::{self, TabAction};
+use crate::ui::{CitadelUI, UIMessage, WindowView};
+use crate::user_styles::{self, UserStylesheets};
+use crate::web_app::{self, AppLaunch, WebAppManifest};
+use crate::web_fonts::{self, FontRequest};
+use crate::windows::{DetachedMode, WindowKind, WindowManager};
 // WORKAROUND: Use explicit paths to break circular import
 // Import performance types directly to avoid circular dependency with lib.rs re-exports
-use citadel_networking::{DnsMode, NetworkConfig, PrivacyLevel};
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_networking::filter_list::{self, FilterListCache, FilterLists};
+use citadel_networking::filter_update::{self, FilterUpdater, UpdateSettings};
+use citadel_networking::{
+    BlockingLevel, BlocklistEngine, CosmeticFilter, DnsMode, LoadErrorCategory, NetworkConfig,
+    NetworkError, PrivacyLevel, SecurityHeaderReport, SocksProxy,
+};
 use citadel_parser::{CitadelStylesheet, Dom};
 use citadel_security::{
     PrivacyEvent, PrivacyEventReceiver, PrivacyEventSender, PrivacyStats, SecurityContext,
+    ViolationSource,
 };
-use citadel_tabs::{PageContent, SendSafeTabManager as TabManager, TabType};
+use citadel_tabs::{PageContent, SendSafeTabManager as TabManager, TabMatch, TabType};
+#[cfg(feature = "zkvm-isolation")]
+use citadel_tabs::{ResourceBroker, VmPolicy};
+
+/// Environment variable naming a SOCKS5 proxy (`host:port`, e.g. Tor's
+/// `127.0.0.1:9050`) for every page load
+pub const SOCKS_PROXY_ENV: &str = "CITADEL_SOCKS_PROXY";
+
+/// Shortest passphrase accepted for encrypting the profile
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Most tabs listed by the tab switcher
+const TAB_SWITCHER_MATCHES: usize = 12;
 
 /// Main Citadel Browser application
 pub struct CitadelBrowser {
@@ -35,17 +95,85 @@ pub struct CitadelBrowser {
     renderer: CitadelRenderer,
     /// Tab management with ZKVM isolation
     tab_manager: Arc<TabManager>,
+    /// Open windows, each with its own tab strip over `tab_manager`
+    windows: WindowManager,
+    /// Screen reader adapter of each browser window
+    accessibility: WindowAdapters,
+    /// Tab picked up from a tab strip, waiting to be dropped on a window
+    dragged_tab: Option<uuid::Uuid>,
+    /// History of container tabs, for omnibox suggestions
+    history: HistoryManager,
+    /// Bookmarked pages
+    bookmarks: Bookmarks,
+    /// Local-only omnibox suggestions over `history` and `bookmarks`
+    suggestions: SuggestionEngine,
+    /// Per-site user CSS from the settings file
+    user_styles: UserStylesheets,
+    /// Locally installed content script extensions
+    extensions: Extensions,
+    /// Element-hiding rules, injected as user CSS
+    cosmetic_filter: CosmeticFilter,
+    /// Installed filter lists, shared with the tab resource manager
+    filter_lists: FilterLists,
+    /// Blocks tab requests by the filter lists, allowlisted sites excepted
+    blocklist: BlocklistEngine,
     /// Network configuration for privacy
     network_config: NetworkConfig,
     /// Security context for all operations
     #[allow(dead_code)] // Will be used when implementing security policy enforcement
     security_context: Arc<SecurityContext>,
+    /// Privacy settings that apply without a restart
+    settings: Arc<SettingsStore>,
+    /// External schemes the user always hands off without asking
+    external_protocols: ExternalProtocolPrefs,
+    /// External-scheme link waiting for the user to confirm the hand-off
+    pending_external: Option<Url>,
+    /// Sites where full-screen overlays are hidden
+    overlay_cleanup: OverlayCleanup,
+    /// Status-bar preview of the link under the pointer
+    hovered_link: Option<String>,
+    /// Web app to open once the engine is ready, when started from a shortcut
+    app_launch: Option<AppLaunch>,
+    /// What the panic button wipes beyond the current session
+    panic_options: PanicOptions,
+    /// Tracking-link scrubbing and secret clearing for copies
+    clipboard_policy: ClipboardPolicy,
+    /// Copied secret to clear from the clipboard once its delay is up
+    pending_clear: PendingClear,
+    /// Passphrase prompt for unlocking or encrypting the profile
+    profile_prompt: Option<ProfilePrompt>,
+    /// Quick tab switcher, while open
+    tab_switcher: Option<TabSwitcher>,
+    /// Tab whose context menu is open
+    tab_menu: Option<uuid::Uuid>,
+    /// What was right-clicked on a tab's page, while its context menu is
+    /// open
+    page_menu: Option<(uuid::Uuid, PageHit)>,
+    /// Markup shown by "View selection source"
+    selection_source: Option<String>,
+    /// Files being downloaded, and those done this session
+    downloads: DownloadManager,
+    /// Shutdown under way since the last window was closed
+    shutdown: Option<Shutdown>,
+    /// Whether the profile's settings were read and the engine started;
+    /// with an encrypted profile this waits for the first unlock
+    profile_loaded: bool,
+    /// Recovery key being shown to the user to write down
+    shown_recovery_key: Option<Zeroizing<String>>,
+    /// OS keychain, or the file fallback, for the profile key and site
+    /// credentials
+    secrets: Option<Arc<dyn SecretStore>>,
     /// Error states for better user feedback
     error_states: HashMap<uuid::Uuid, String>,
+    /// Why each tab's last load failed, for its error page
+    tab_load_failures: HashMap<uuid::Uuid, LoadErrorCategory>,
     /// Loading states for tab operations
     loading_states: HashMap<uuid::Uuid, LoadingState>,
     /// Store DOM and stylesheet per tab for renderer state
     tab_render_data: HashMap<uuid::Uuid, (Arc<Dom>, Arc<CitadelStylesheet>)>,
+    /// Whether box model outlines are drawn over the page
+    #[cfg(feature = "devtools")]
+    layout_debug: bool,
     /// Per-tab sanitized render output from the ZKVM boundary, so switching tabs
     /// shows each tab's own content (not the last-rendered tab's).
     tab_rendered: HashMap<uuid::Uuid, citadel_tabs::RenderedContent>,
@@ -55,14 +183,34 @@ pub struct CitadelBrowser {
     history_suppress: bool,
     /// Viewport information and state
     viewport_info: ViewportInfo,
-    /// Performance monitoring system (TODO: Fix circular import)
-    // performance_monitor: Arc<PerformanceMonitor>,
+    /// Page timings and the power profile the browser runs with
+    performance_monitor: Arc<PerformanceMonitor>,
+    /// When each tab's load started, until the load first paints content
+    tab_load_started: HashMap<uuid::Uuid, std::time::Instant>,
     /// Memory cleanup timer
     last_memory_cleanup: std::time::Instant,
     /// Scroll state per tab
     tab_scroll_states: HashMap<uuid::Uuid, ScrollState>,
     /// Zoom level per tab
     tab_zoom_levels: HashMap<uuid::Uuid, ZoomLevel>,
+    /// Security header audit of each tab's page
+    tab_security_headers: HashMap<uuid::Uuid, SecurityHeaderReport>,
+    /// Locally detected language of each tab's page
+    tab_languages: HashMap<uuid::Uuid, citadel_parser::LanguageHints>,
+    /// Content budgets each tab's page ran over, for pages that did
+    tab_truncations: HashMap<uuid::Uuid, ContentTruncation>,
+    /// Escalation against each tab's site, for tabs past a threshold
+    tab_escalations: HashMap<uuid::Uuid, PageEscalation>,
+    /// Text-only page of each tab shown through the fast path, restored
+    /// when switching tabs like `tab_rendered`
+    tab_text_blocks: HashMap<uuid::Uuid, Vec<citadel_parser::TextBlock>>,
+    /// Image each tab navigated to, with its zoom and pan, restored when
+    /// switching tabs like `tab_rendered`
+    tab_images: HashMap<uuid::Uuid, ImageViewer>,
+    /// Profile from the last power supply probe
+    power_profile: PowerProfile,
+    /// When each startup phase finished
+    startup: StartupTimings,
     /// Aggregated privacy statistics for the scoreboard
     privacy_stats: PrivacyStats,
     /// Receiver for privacy events from the engine
@@ -284,6 +432,60 @@ impl ScrollState {
     }
 }
 
+/// What the profile passphrase prompt asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePromptMode {
+    /// The passphrase, to unlock the profile
+    Unlock,
+    /// The recovery key and a new passphrase, to unlock the profile
+    Recover,
+    /// A new passphrase, to encrypt the profile
+    Enable,
+}
+
+/// Input of the profile passphrase prompt
+pub struct ProfilePrompt {
+    pub mode: ProfilePromptMode,
+    /// The passphrase, or the recovery key in `Recover` mode
+    pub secret: Zeroizing<String>,
+    /// The new passphrase in `Recover` mode, its confirmation in `Enable` mode
+    pub second: Zeroizing<String>,
+    pub error: Option<String>,
+    /// Key derivation is running
+    pub busy: bool,
+    /// Let the OS keychain unlock the profile at later launches
+    pub remember: bool,
+}
+
+impl ProfilePrompt {
+    fn new(mode: ProfilePromptMode) -> Self {
+        Self {
+            mode,
+            secret: Zeroizing::default(),
+            second: Zeroizing::default(),
+            error: None,
+            busy: false,
+            remember: false,
+        }
+    }
+}
+
+/// The quick tab switcher's query and the tabs matching it
+#[derive(Debug, Default)]
+pub struct TabSwitcher {
+    pub query: String,
+    pub matches: Vec<TabMatch>,
+    /// Index of the highlighted match
+    pub selected: usize,
+}
+
+impl TabSwitcher {
+    /// The highlighted match
+    pub fn selected_match(&self) -> Option<&TabMatch> {
+        self.matches.get(self.selected)
+    }
+}
+
 /// Messages that can be sent to the browser application
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -302,6 +504,72 @@ pub enum Message {
     CloseTab(uuid::Uuid),
     /// Switch to a tab
     SwitchTab(uuid::Uuid),
+    /// Open a new browser window with a fresh tab
+    NewWindow,
+    /// A window gained focus
+    WindowFocused(window::Id),
+    /// The user asked to close a window
+    WindowCloseRequested(window::Id),
+    /// A step of the shutdown begun by closing the last window finished
+    ShutdownStepFinished(ShutdownStep),
+    /// A tab was picked up from its tab strip
+    TabDragStarted(uuid::Uuid),
+    /// The picked-up tab was dropped on a window's tab strip
+    TabDropped(window::Id),
+    /// The picked-up tab was dropped outside any window
+    TabDroppedInNewWindow,
+    /// Show a tab's rendered content in an always-on-top auxiliary window
+    DetachTab(uuid::Uuid, DetachedMode),
+    /// Bookmark the active tab's page, or remove its bookmark
+    ToggleBookmark,
+    /// Turn overlay cleanup on or off for the active tab's site and reload
+    ToggleOverlayCleanup,
+    /// Allowlist the active tab's site, or filter it again, and reload
+    ToggleSiteFiltering,
+    /// Switch the active tab into or out of text-only mode and reload
+    ToggleTextOnly,
+    /// Switch every tab into or out of text-only mode and reload the active
+    /// one (Ctrl+Shift+X)
+    ToggleGlobalTextOnly,
+    /// Open the tab switcher, or close it if open (Ctrl+Shift+A)
+    ToggleTabSwitcher,
+    /// Draw box model outlines over the page, or stop (Ctrl+Shift+D)
+    #[cfg(feature = "devtools")]
+    ToggleLayoutDebug,
+    /// The tab switcher's query changed
+    TabSwitcherQueryChanged(String),
+    /// Move the tab switcher's highlight by this many matches
+    TabSwitcherMove(isize),
+    /// Activate the highlighted tab switcher match
+    TabSwitcherSubmit,
+    /// Activate a tab picked in the switcher, waking it if it expired
+    TabSwitcherActivate(uuid::Uuid),
+    /// Mute a tab, or unmute it if muted
+    ToggleTabMute(uuid::Uuid),
+    /// Tab right-clicked in the strip; open its context menu
+    TabMenuOpened(uuid::Uuid),
+    /// Close the tab context menu
+    TabMenuClosed,
+    /// Entry picked from a tab's context menu
+    TabMenuAction(uuid::Uuid, TabAction),
+    /// Rendered content right-clicked; open the page context menu
+    PageMenuOpened(PageHit),
+    /// Close the page context menu
+    PageMenuClosed,
+    /// Entry picked from the page context menu
+    PageMenuAction(PageAction),
+    /// Close the selection source panel
+    SelectionSourceClosed,
+    /// Zoom or pan in the active tab's image viewer
+    ImageViewer(ViewerAction),
+    /// Save the image the active tab shows, without its metadata
+    SaveViewedImage,
+    /// Image from the viewer written to disk, or why it was not
+    ViewedImageSaved(Result<std::path::PathBuf, String>),
+    /// Reopen a tab that expired while idle
+    ReopenTab(uuid::Uuid),
+    /// Expired tab got a fresh VM; reload its page
+    TabReopened(uuid::Uuid, String),
     /// Update privacy settings
     UpdatePrivacy(PrivacyLevel),
     /// Engine initialization completed
@@ -360,8 +628,152 @@ pub enum Message {
     PrivacyTick(PrivacyEvent),
     /// Drain pending privacy events from the channel
     DrainPrivacyEvents,
+    /// The window shell was drawn for the first time
+    FirstFrame,
+    /// The built-in cosmetic filter list was compiled in the background
+    CosmeticFilterCompiled(CosmeticFilter),
+    /// Check the installed filter lists for changes
+    RefreshFilterLists,
+    /// Filter lists were refreshed; the new cosmetic filter if any changed
+    FilterListsRefreshed(Option<CosmeticFilter>),
+    /// Fetch due updates of subscribed filter lists
+    UpdateFilterLists,
+    /// Subscribed lists were updated; how many new versions were installed
+    FilterListsUpdated(usize),
+    /// Extensions were read in the background
+    ExtensionsLoaded(Extensions),
+    /// The tab manager was asked to keep VMs ready
+    VmPoolWarming(Result<(), String>),
+    /// Probe the power supply and switch power profiles if it changed
+    ProbePower,
+    /// Tab VMs were told about a new power profile
+    PowerProfileApplied(Result<(), String>),
     /// Toggle the privacy panel expanded/collapsed state
     TogglePrivacyPanel,
+    /// A key press that no widget captured
+    KeyPressed(Key, iced::keyboard::Modifiers),
+    /// Move keyboard focus to the next focusable page element
+    FocusNext,
+    /// Move keyboard focus to the previous focusable page element
+    FocusPrevious,
+    /// Activate the focused page element (Enter)
+    ActivateFocus,
+    /// A browser window's native view is available for its screen reader
+    /// adapter
+    AccessibilityReady(window::Id, NativeView),
+    /// Apply what screen readers asked for since the last tick
+    AccessibilityRequests,
+    /// A file was dropped on a window
+    FileDropped(window::Id, std::path::PathBuf),
+    /// Put text on the clipboard under the clipboard policy
+    Copy(CopyKind, String),
+    /// Copy the active tab's URL, without tracking parameters (Ctrl+Shift+C)
+    CopyPageUrl,
+    /// The delay for clearing a copied secret is up
+    ClipboardClearDue(u64),
+    /// Clipboard read to check it still holds the copied secret
+    ClipboardReadForClear(Option<String>),
+    /// Download a file from the active tab, checking it against a digest
+    /// (SRI metadata or a hex SHA-256) if one is given
+    StartDownload {
+        url: String,
+        integrity: Option<String>,
+    },
+    /// Save the active tab's page as a download (Ctrl+S)
+    SavePage,
+    /// A download's transfer stopped: completed, paused, cancelled or failed
+    DownloadFinished(uuid::Uuid, Result<DownloadState, String>),
+    PauseDownload(uuid::Uuid),
+    ResumeDownload(uuid::Uuid),
+    /// Stop a download and delete its partial file
+    CancelDownload(uuid::Uuid),
+    /// Drop completed and cancelled downloads from the list
+    ClearDownloads,
+    /// Redraw download progress
+    DownloadsTick,
+    /// Paste into the content area (Ctrl+V outside any text field)
+    PasteIntoPage,
+    /// Clipboard text read for a paste
+    Pasted(Option<String>),
+    /// Pasted or dropped content, classified
+    ContentDropped(DroppedContent),
+    /// Hand the pending external-scheme link to the OS, optionally
+    /// remembering the choice for its scheme
+    ConfirmExternalProtocol {
+        always_allow: bool,
+    },
+    /// Drop the pending external-scheme link
+    CancelExternalProtocol,
+    /// The pointer entered a link with this `href`, or left it
+    LinkHovered(Option<String>),
+    /// Install the active tab's web app as a desktop shortcut
+    InstallWebApp,
+    /// The active tab's web app manifest was fetched, with the app's icons
+    /// if they could be made
+    WebAppManifestLoaded(Result<(WebAppManifest, Option<IconSet>), String>),
+    /// A desktop shortcut was written, or failed to be
+    WebAppInstalled(Result<std::path::PathBuf, String>),
+    /// A web font of the page was fetched, or failed to be
+    WebFontFetched(FontRequest, Result<Vec<u8>, String>),
+    /// A web font went into the text system under the family name inside
+    /// it, or was refused
+    WebFontLoaded(FontRequest, String, bool),
+    /// An image of the page was fetched and decoded, or failed to be
+    ImageDecoded(ImageRequest, Result<DecodedImage, String>),
+    /// Wipe all session state and close every window (Ctrl+Shift+Delete)
+    Panic,
+    /// Every tab was wiped; close the windows
+    PanicWiped,
+    /// Text typed into the profile prompt's first field
+    ProfileSecretChanged(String),
+    /// Text typed into the profile prompt's second field
+    ProfileSecondChanged(String),
+    /// Switch the profile prompt between passphrase and recovery key
+    SetProfilePromptMode(ProfilePromptMode),
+    /// Unlock or encrypt the profile with the prompt's input
+    SubmitProfilePrompt,
+    /// Close the prompt for encrypting the profile
+    CancelProfilePrompt,
+    /// The profile key was unwrapped, or was not
+    ProfileUnlocked(Result<(), String>),
+    /// Drop the profile key and ask for the passphrase again (Ctrl+Shift+L)
+    LockProfile,
+    /// Forget the last hour of history (Ctrl+Shift+H)
+    ClearRecentHistory,
+    /// Encrypt the profile, or export a new recovery key for an encrypted
+    /// one (Ctrl+Shift+E)
+    EncryptProfile,
+    /// A recovery key to show the user, from encrypting the profile or
+    /// exporting a new key
+    RecoveryKeyReady(Result<Arc<RecoveryKey>, String>),
+    /// The user wrote the recovery key down
+    DismissRecoveryKey,
+    /// Toggle letting the OS keychain unlock the profile
+    ProfileRememberToggled(bool),
+    /// The OS keychain was asked for the profile key at launch; whether it
+    /// unlocked the profile
+    ProfileKeychainChecked(bool),
+}
+
+impl Message {
+    /// Whether the message would load a page or open a tab, which stops
+    /// once shutdown begins
+    fn starts_navigation(&self) -> bool {
+        matches!(
+            self,
+            Message::Navigate(_)
+                | Message::NewTab { .. }
+                | Message::NewWindow
+                | Message::TabOpened { .. }
+                | Message::RefreshTab
+                | Message::GoBack
+                | Message::GoForward
+                | Message::ReopenTab(_)
+                | Message::TabReopened(..)
+                | Message::FormSubmit(_)
+                | Message::InstallWebApp
+        )
+    }
 }
 
 /// Detailed loading error information
@@ -375,6 +787,34 @@ pub struct LoadingError {
     pub timestamp: std::time::SystemTime,
     #[allow(dead_code)] // Will be used when implementing retry functionality
     pub retry_possible: bool,
+    /// What kind of network failure it was, for loads that reached the network
+    pub category: Option<LoadErrorCategory>,
+}
+
+impl LoadingError {
+    /// A loading error for `url` from a classified error of any crate
+    pub fn from_error(error: impl Into<CitadelError>, url: impl Into<String>) -> Self {
+        let error = error.into();
+        Self {
+            error_type: ErrorType::from(error.kind()),
+            message: format!("{} {}", error.user_message(), error),
+            url: url.into(),
+            timestamp: std::time::SystemTime::now(),
+            retry_possible: error.is_retryable(),
+            category: None,
+        }
+    }
+
+    /// A loading error for `url` from a failed request; retrying is offered
+    /// only where the failure's category allows it
+    pub fn from_network_error(error: NetworkError, url: impl Into<String>) -> Self {
+        let category = error.category();
+        Self {
+            retry_possible: category.retry_policy().allows_retry(),
+            category: Some(category),
+            ..Self::from_error(error, url)
+        }
+    }
 }
 
 /// Types of loading errors for better categorization
@@ -387,13 +827,25 @@ pub enum ErrorType {
     /// Invalid or malformed content
     Content,
     /// Resource exhaustion or limits exceeded
-    #[allow(dead_code)] // Will be used when implementing resource limit errors
     Resource,
     /// Internal browser errors
-    #[allow(dead_code)] // Will be used when implementing internal error handling
     Internal,
 }
 
+impl From<ErrorKind> for ErrorType {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Network | ErrorKind::Dns | ErrorKind::Tls | ErrorKind::Timeout => {
+                ErrorType::Network
+            }
+            ErrorKind::Privacy | ErrorKind::Security => ErrorType::Security,
+            ErrorKind::Content => ErrorType::Content,
+            ErrorKind::Resource => ErrorType::Resource,
+            ErrorKind::Isolation | ErrorKind::Internal => ErrorType::Internal,
+        }
+    }
+}
+
 /// Structured page data from the engine
 #[derive(Debug, Clone)]
 pub struct ParsedPageData {
@@ -411,6 +863,30 @@ pub struct ParsedPageData {
     /// The raw, untrusted HTML bytes — handed to the tab's ZKVM boundary for
     /// isolated parsing/layout. The host never parses these for display.
     pub raw_html: String,
+    /// Graded audit of the response's security headers; `None` for pages
+    /// that were not fetched over the network
+    pub security_headers: Option<citadel_networking::SecurityHeaderReport>,
+    /// Language and encoding detected locally from the page
+    pub language: citadel_parser::LanguageHints,
+    /// Content budgets the page ran over
+    pub truncation: ContentTruncation,
+    /// How far the browser escalated against the page's site
+    pub escalation: PageEscalation,
+    /// The page's text, headings and links when it is shown text-only,
+    /// through the renderer's fast path instead of the ZKVM boundary
+    pub text_blocks: Option<Vec<citadel_parser::TextBlock>>,
+    /// The image, when the page navigated to is one; it is shown in the
+    /// image viewer instead of being rendered
+    pub image: Option<ImageViewer>,
+}
+
+impl ParsedPageData {
+    /// Links, heading outline and metadata of the page, with URLs resolved
+    /// against the page URL; `None` without a DOM or a valid URL
+    pub fn extract(&self) -> Option<citadel_parser::PageExtract> {
+        let base = Url::parse(&self.url).ok()?;
+        Some(self.dom.as_ref()?.extract(&base))
+    }
 }
 
 impl Application for CitadelBrowser {
@@ -421,6 +897,7 @@ impl Application for CitadelBrowser {
 
     fn new(runtime: Arc<Runtime>) -> (Self, Command<Message>) {
         log::info!("🚀 Initializing Citadel Browser application with enhanced security");
+        let startup = StartupTimings::new();
 
         // Initialize security context with maximum privacy by default
         let security_context = Arc::new(SecurityContext::new(10));
@@ -433,8 +910,18 @@ impl Application for CitadelBrowser {
             randomize_user_agent: true,
             strip_tracking_params: true,
             tracker_blocking: citadel_networking::BlocklistConfig::default(),
+            socks_proxy: std::env::var(SOCKS_PROXY_ENV)
+                .ok()
+                .filter(|address| !address.trim().is_empty())
+                .map(|address| SocksProxy::new(address.trim())),
         };
 
+        let settings = Arc::new(SettingsStore::new(RuntimeSettings {
+            privacy_level: network_config.privacy_level,
+            dns_mode: network_config.dns_mode.clone(),
+            fingerprint_protection: security_context.fingerprint_protection().level,
+        }));
+
         // Initialize tab manager with ZKVM isolation
         let tab_manager = Arc::new(TabManager::new());
 
@@ -444,59 +931,216 @@ impl Application for CitadelBrowser {
         // Initialize HTML/CSS renderer
         let renderer = CitadelRenderer::new();
 
+        // Omnibox suggestions come only from local history and bookmarks
+        let history = HistoryManager::new();
+        let bookmarks = Bookmarks::new();
+
+        // An encrypted profile is not read until it is unlocked
+        let profile_encrypted = profile::default_key_path().is_some_and(|path| path.exists());
+        if profile_encrypted {
+            log::info!("🔐 Profile is encrypted; waiting for the passphrase");
+            profile::set_locked();
+        }
+
+        // Extensions are read and the filter list compiled on blocking
+        // threads, so neither delays the window
+        let load_extensions = {
+            let runtime = runtime.clone();
+            Command::perform(
+                async move {
+                    let load = || {
+                        extensions::default_dir()
+                            .map(|dir| Extensions::load_dir(&dir))
+                            .unwrap_or_default()
+                    };
+                    runtime.spawn_blocking(load).await.unwrap_or_default()
+                },
+                Message::ExtensionsLoaded,
+            )
+        };
+        // Filter lists and cosmetic filtering follow the tracker blocking
+        // switch
+        let filter_lists = FilterLists::new();
+        let compile_cosmetic_filter = {
+            let runtime = runtime.clone();
+            let filter_lists = filter_lists.clone();
+            let enabled = network_config.tracker_blocking.blocking_level != BlockingLevel::Disabled;
+            Command::perform(
+                async move {
+                    if !enabled {
+                        return CosmeticFilter::new();
+                    }
+                    let compile = move || {
+                        refresh_filter_lists(&filter_lists);
+                        cosmetic_filter(&filter_lists)
+                    };
+                    runtime.spawn_blocking(compile).await.unwrap_or_default()
+                },
+                Message::CosmeticFilterCompiled,
+            )
+        };
+
         // Create privacy event channel for the scoreboard
         let (privacy_sender, privacy_receiver) = citadel_security::create_privacy_channel();
-
-        let browser = Self {
-            runtime: runtime.clone(),
+        let blocklist =
+            BlocklistEngine::new(filter_lists.clone()).with_privacy_sender(privacy_sender.clone());
+
+        // A desktop shortcut starts the main window as the app's window
+        let app_launch = AppLaunch::from_args(std::env::args().skip(1));
+        let mut windows = WindowManager::new(window::Id::MAIN);
+        if let Some(launch) = &app_launch {
+            log::info!("📲 Starting as web app {}", launch.start_url);
+            windows.set_app_origin(window::Id::MAIN, launch.start_url.origin());
+        }
+        // An installed app's window shows the icon installed with its shortcut
+        let app_icon = app_launch
+            .as_ref()
+            .and_then(|launch| assets::installed_window_icon(&launch.start_url))
+            .map(|icon| window::change_icon(window::Id::MAIN, icon))
+            .unwrap_or_else(Command::none);
+
+        let mut browser = Self {
+            runtime,
             engine: None,
             ui,
             renderer,
             tab_manager,
-            network_config: network_config.clone(),
-            security_context: security_context.clone(),
+            windows,
+            accessibility: WindowAdapters::default(),
+            dragged_tab: None,
+            history: history.clone(),
+            bookmarks: bookmarks.clone(),
+            suggestions: SuggestionEngine::local(history, bookmarks),
+            user_styles: UserStylesheets::default(),
+            extensions: Extensions::default(),
+            cosmetic_filter: CosmeticFilter::new(),
+            filter_lists,
+            blocklist,
+            network_config,
+            security_context,
+            settings,
+            external_protocols: ExternalProtocolPrefs::default(),
+            pending_external: None,
+            overlay_cleanup: OverlayCleanup::default(),
+            hovered_link: None,
+            app_launch,
+            panic_options: PanicOptions::default(),
+            clipboard_policy: ClipboardPolicy::default(),
+            pending_clear: PendingClear::default(),
+            profile_prompt: profile_encrypted
+                .then(|| ProfilePrompt::new(ProfilePromptMode::Unlock)),
+            tab_switcher: None,
+            tab_menu: None,
+            page_menu: None,
+            selection_source: None,
+            downloads: DownloadManager::new(
+                downloads::downloads_dir().unwrap_or_else(std::env::temp_dir),
+            ),
+            shutdown: None,
+            profile_loaded: false,
+            shown_recovery_key: None,
+            secrets: keychain::default_fallback_path().map(|path| Arc::from(keychain::open(path))),
             error_states: HashMap::new(),
+            tab_load_failures: HashMap::new(),
             loading_states: HashMap::new(),
             tab_render_data: HashMap::new(),
+            #[cfg(feature = "devtools")]
+            layout_debug: false,
             tab_rendered: HashMap::new(),
             tab_history: HashMap::new(),
             history_suppress: false,
             viewport_info: ViewportInfo::default(),
             tab_scroll_states: HashMap::new(),
             tab_zoom_levels: HashMap::new(),
+            tab_security_headers: HashMap::new(),
+            tab_languages: HashMap::new(),
+            tab_truncations: HashMap::new(),
+            tab_escalations: HashMap::new(),
+            tab_text_blocks: HashMap::new(),
+            tab_images: HashMap::new(),
+            power_profile: PowerProfile::default(),
+            startup,
             privacy_stats: PrivacyStats::default(),
             privacy_receiver: Some(privacy_receiver),
             privacy_sender,
             privacy_panel_expanded: false,
-            // performance_monitor,
+            performance_monitor: Arc::new(PerformanceMonitor::new(MemoryConfig::default())),
+            tab_load_started: HashMap::new(),
             last_memory_cleanup: std::time::Instant::now(),
         };
 
-        // Initialize browser engine asynchronously with detailed error handling
-        let init_command = Command::perform(
-            BrowserEngine::new(runtime, network_config, security_context),
-            |result| match result {
-                Ok(engine) => {
-                    log::info!("✅ Browser engine initialized successfully");
-                    Message::EngineInitialized(engine)
-                }
-                Err(e) => {
-                    log::error!("❌ Engine initialization failed: {}", e);
-                    Message::InitializationError(format!("Failed to initialize engine: {}", e))
+        let os_keychain = browser
+            .secrets
+            .clone()
+            .filter(|secrets| secrets.is_os_protected());
+        let init_command = match (os_keychain, profile::default_key_path()) {
+            _ if !profile_encrypted => browser.load_profile(),
+            // The OS keychain may unlock the profile without the passphrase
+            (Some(secrets), Some(key_path)) => {
+                if let Some(prompt) = &mut browser.profile_prompt {
+                    prompt.busy = true;
                 }
-            },
-        );
-
-        (browser, init_command)
+                Command::perform(
+                    async move {
+                        profile::unlock_from_keychain(&key_path, secrets.as_ref()).unwrap_or_else(
+                            |e| {
+                                log::warn!("🔐 OS keychain did not unlock the profile: {}", e);
+                                false
+                            },
+                        )
+                    },
+                    Message::ProfileKeychainChecked,
+                )
+            }
+            _ => Command::none(),
+        };
+        browser.record_startup(StartupPhase::Shell);
+        (
+            browser,
+            Command::batch([
+                init_command,
+                load_extensions,
+                compile_cosmetic_filter,
+                app_icon,
+                Self::open_accessibility(window::Id::MAIN),
+            ]),
+        )
     }
 
-    fn title(&self) -> String {
+    fn title(&self, window: window::Id) -> String {
         let version = env!("CARGO_PKG_VERSION");
         let base_title = format!("Citadel Browser v{} (Alpha) - Privacy First", version);
 
-        // Get active tab from tab states
+        // Each window is titled after its own selected tab
         let tab_states = self.tab_manager.get_tab_states();
-        if let Some(active_tab) = tab_states.iter().find(|tab| tab.is_active) {
+        let selected = match self.windows.kind(window) {
+            Some(WindowKind::Browser(browser_window)) => browser_window.active_tab(),
+            Some(WindowKind::Detached(detached)) => {
+                let title = tab_states
+                    .iter()
+                    .find(|tab| tab.id == detached.tab_id)
+                    .map(|tab| tab.title.clone())
+                    .unwrap_or_default();
+                return match detached.mode {
+                    DetachedMode::Reader => format!("📖 {} - Reader", title),
+                    #[cfg(feature = "media")]
+                    DetachedMode::Media => format!("🎞️ {} - Picture-in-Picture", title),
+                };
+            }
+            None => None,
+        };
+        if let Some(active_tab) = tab_states.iter().find(|tab| Some(tab.id) == selected) {
+            // A load still waiting for a slot shows its place in line
+            let queued = self.engine.as_ref().and_then(|engine| {
+                engine
+                    .load_queue()
+                    .into_iter()
+                    .find(|(tab_id, _)| *tab_id == active_tab.id)
+            });
+            if let Some((_, position)) = queued {
+                return format!("⏳ Queued #{} - {}", position, base_title);
+            }
+
             // Show loading state in title if applicable
             if let Some(loading_state) = self.loading_states.get(&active_tab.id) {
                 match loading_state {
@@ -542,6 +1186,11 @@ impl Application for CitadelBrowser {
         // Periodic memory cleanup and performance monitoring
         self.periodic_memory_cleanup();
 
+        if self.shutdown.is_some() && message.starts_navigation() {
+            log::debug!("Ignoring navigation during shutdown: {:?}", message);
+            return Command::none();
+        }
+
         match message {
             Message::UI(ui_message) => {
                 match &ui_message {
@@ -551,6 +1200,9 @@ impl Application for CitadelBrowser {
                             return self.update(Message::Navigate(url));
                         }
                     }
+                    UIMessage::AddressBarChanged(input) => {
+                        self.ui.set_suggestions(self.suggestions.suggest(input));
+                    }
                     _ => {}
                 }
                 self.ui.update(ui_message)
@@ -558,6 +1210,7 @@ impl Application for CitadelBrowser {
 
             Message::Navigate(url_str) => {
                 log::info!("🧭 Navigating to: {}", url_str);
+                self.ui.set_suggestions(Vec::new());
 
                 // Check if engine is initialized
                 if self.engine.is_none() {
@@ -569,6 +1222,34 @@ impl Application for CitadelBrowser {
                 let normalized_url = self.normalize_url(&url_str);
                 match Url::parse(&normalized_url) {
                     Ok(url) => {
+                        match SchemeDispatch::for_url(&url) {
+                            SchemeDispatch::Engine => {}
+                            SchemeDispatch::External(scheme) => {
+                                if self.external_protocols.is_always_allowed(&scheme) {
+                                    self.open_externally(&url);
+                                } else {
+                                    log::info!("🔗 Asking before opening {}: link", scheme);
+                                    self.pending_external = Some(url);
+                                }
+                                return Command::none();
+                            }
+                            SchemeDispatch::Refused(scheme) => {
+                                log::warn!("🚫 Refusing to open {}: link", scheme);
+                                return Command::none();
+                            }
+                        }
+
+                        // App windows only show their own origin
+                        let leaves_app = self
+                            .windows
+                            .get(self.windows.focused())
+                            .and_then(|window| window.app_origin())
+                            .is_some_and(|origin| *origin != url.origin());
+                        if leaves_app {
+                            log::info!("📲 {} leaves the app, opening a browser window", url);
+                            return self.open_window(Some(url.to_string()));
+                        }
+
                         // Get or create active tab
                         let tab_states = self.tab_manager.get_tab_states();
                         if tab_states.is_empty() {
@@ -581,6 +1262,7 @@ impl Application for CitadelBrowser {
 
                         if let Some(active_tab) = tab_states.iter().find(|tab| tab.is_active) {
                             let tab_id = active_tab.id;
+                            let tab_type = active_tab.tab_type;
 
                             // Record in history unless this navigation came from
                             // back/forward (which only moves the cursor).
@@ -594,12 +1276,17 @@ impl Application for CitadelBrowser {
                             }
 
                             // Reflect the resolved URL in the address bar.
-                            self.ui.set_address_bar_value(normalized_url.clone());
+                            self.ui
+                                .set_address_bar_value(citadel_networking::display_url(
+                                    &normalized_url,
+                                ));
 
                             // Clear any existing error state
                             self.error_states.remove(&tab_id);
 
                             // Set initial loading state
+                            self.tab_load_started
+                                .insert(tab_id, std::time::Instant::now());
                             self.loading_states.insert(
                                 tab_id,
                                 LoadingState::ResolvingDns {
@@ -618,6 +1305,7 @@ impl Application for CitadelBrowser {
                                 log::error!("Browser engine not available for page loading");
                                 return Command::none();
                             };
+                            engine.focus_tab(tab_id);
                             return Command::batch([
                                 // Set loading state in tab
                                 Command::perform(
@@ -639,7 +1327,9 @@ impl Application for CitadelBrowser {
                                 ),
                                 // Start loading the page
                                 Command::perform(
-                                    async move { engine.load_page_with_progress(url, tab_id).await },
+                                    async move {
+                                        engine.load_page_with_progress(url, tab_id, tab_type).await
+                                    },
                                     move |result| Message::PageLoaded(tab_id, result),
                                 ),
                             ]);
@@ -663,6 +1353,7 @@ impl Application for CitadelBrowser {
                                 url: url_str,
                                 timestamp: std::time::SystemTime::now(),
                                 retry_possible: true,
+                                category: None,
                             };
                             self.error_states
                                 .insert(active_tab.id, error.message.clone());
@@ -688,6 +1379,22 @@ impl Application for CitadelBrowser {
 
                         // Clear any error state
                         self.error_states.remove(&tab_id);
+                        self.tab_load_failures.remove(&tab_id);
+
+                        // Container tabs feed local history; ephemeral visits are dropped
+                        if let Some(tab) = self
+                            .tab_manager
+                            .get_tab_states()
+                            .iter()
+                            .find(|tab| tab.id == tab_id)
+                        {
+                            self.history.record_visit(
+                                &page_data.url,
+                                &page_data.title,
+                                tab.tab_type,
+                            );
+                            self.save_history();
+                        }
 
                         // Keep DOM/stylesheet for diagnostics only. The host does NOT
                         // render them — the raw bytes are handed to the tab's ZKVM
@@ -699,6 +1406,27 @@ impl Application for CitadelBrowser {
                                 .insert(tab_id, (dom.clone(), stylesheet.clone()));
                         }
                         self.renderer.clear_zkvm_content();
+                        match &page_data.security_headers {
+                            Some(report) => {
+                                self.tab_security_headers.insert(tab_id, report.clone());
+                            }
+                            None => {
+                                self.tab_security_headers.remove(&tab_id);
+                            }
+                        }
+                        self.tab_languages
+                            .insert(tab_id, page_data.language.clone());
+                        if page_data.truncation.any() {
+                            self.tab_truncations.insert(tab_id, page_data.truncation);
+                        } else {
+                            self.tab_truncations.remove(&tab_id);
+                        }
+                        let escalation = page_data.escalation;
+                        if escalation.banner().is_some() {
+                            self.tab_escalations.insert(tab_id, escalation);
+                        } else {
+                            self.tab_escalations.remove(&tab_id);
+                        }
 
                         // Initialize scroll state for this tab
                         self.initialize_tab_scroll_state(tab_id);
@@ -713,9 +1441,78 @@ impl Application for CitadelBrowser {
                             size_bytes: page_data.size_bytes,
                         };
 
-                        let raw_html = page_data.raw_html.clone();
+                        let update_content = Command::perform(
+                            async move {
+                                let _ = tab_manager.update_page_content(tab_id, content).await;
+                            },
+                            move |_| Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
+                        );
+
+                        // An image navigated to is shown in the image viewer;
+                        // there is no page for the ZKVM boundary to render
+                        if let Some(viewer) = page_data.image {
+                            self.tab_rendered.remove(&tab_id);
+                            self.tab_text_blocks.remove(&tab_id);
+                            if self.get_active_tab_id() == Some(tab_id) {
+                                self.renderer.set_image_viewer(viewer.clone());
+                            }
+                            self.tab_images.insert(tab_id, viewer);
+                            self.error_states.remove(&tab_id);
+                            return update_content;
+                        }
+                        self.tab_images.remove(&tab_id);
+
+                        // Text-only pages (by choice, or for a site escalated
+                        // that far) skip the ZKVM render: nothing of the page
+                        // but its text, headings and links is shown
+                        if let Some(blocks) = page_data.text_blocks {
+                            log::info!(
+                                "📄 Showing tab {} text-only: {} blocks",
+                                tab_id,
+                                blocks.len()
+                            );
+                            self.tab_rendered.remove(&tab_id);
+                            if self.get_active_tab_id() == Some(tab_id) {
+                                self.renderer.set_text_blocks(blocks.clone());
+                                self.update_scroll_state_for_content(tab_id);
+                            }
+                            self.tab_text_blocks.insert(tab_id, blocks);
+                            self.error_states.remove(&tab_id);
+                            return update_content;
+                        }
+                        self.tab_text_blocks.remove(&tab_id);
+
                         let render_url = page_data.url.clone();
                         let viewport_width = self.viewport_info.width.max(320.0);
+                        let raw_html = page_data.raw_html.clone();
+                        let mut injection = self.extensions.for_page(&render_url);
+                        let mut user_css = self.user_css_for(&render_url, &injection.css);
+                        if let (Ok(url), Some(dom), Some(stylesheet)) = (
+                            Url::parse(&render_url),
+                            &page_data.dom,
+                            &page_data.stylesheet,
+                        ) {
+                            let overlay_css = self.overlay_cleanup.css_for(&url, dom, stylesheet);
+                            if !overlay_css.is_empty() {
+                                log::info!("🧹 Hiding overlays for tab {}", tab_id);
+                                user_css.push('\n');
+                                user_css.push_str(&overlay_css);
+                            }
+                        }
+                        let mut compat_script = self
+                            .engine
+                            .as_ref()
+                            .map(|engine| engine.compat_for(&render_url).script())
+                            .unwrap_or_default();
+                        if !escalation.level.allows_scripts() {
+                            log::warn!(
+                                "⏸️ Scripts paused for tab {}: {:?}",
+                                tab_id,
+                                escalation.level
+                            );
+                            injection.scripts.clear();
+                            compat_script.clear();
+                        }
 
                         log::info!(
                             "🔒 Handing {} bytes to the ZKVM boundary for tab {}",
@@ -723,19 +1520,36 @@ impl Application for CitadelBrowser {
                             tab_id
                         );
 
+                        // Render the page INSIDE the tab's zero-knowledge boundary.
+                        #[cfg(feature = "zkvm-isolation")]
+                        let render = Self::render_via_zkvm(
+                            tab_id,
+                            render_url,
+                            raw_html,
+                            viewport_width,
+                            user_css,
+                            injection.scripts,
+                            compat_script,
+                        );
+                        #[cfg(not(feature = "zkvm-isolation"))]
+                        let render = Self::render_in_process(
+                            tab_id,
+                            render_url,
+                            raw_html,
+                            viewport_width,
+                            user_css,
+                            injection.scripts,
+                            compat_script,
+                        );
+
                         return Command::batch([
                             // Persist loaded content + flip loading state to Idle.
-                            Command::perform(
-                                async move {
-                                    let _ = tab_manager.update_page_content(tab_id, content).await;
-                                },
-                                move |_| Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
-                            ),
-                            // Render the page INSIDE the tab's zero-knowledge boundary.
-                            Command::perform(
-                                Self::render_via_zkvm(tab_id, render_url, raw_html, viewport_width),
-                                |(tid, rendered)| Message::ZkVmRendered(tid, rendered),
-                            ),
+                            update_content,
+                            Command::perform(render, |(tid, rendered)| {
+                                Message::ZkVmRendered(tid, rendered)
+                            }),
+                            self.fetch_web_fonts(tab_id),
+                            self.fetch_images(tab_id),
                         ]);
                     }
                     Err(error) => {
@@ -743,6 +1557,14 @@ impl Application for CitadelBrowser {
 
                         // Store error state for user feedback
                         self.error_states.insert(tab_id, error.message.clone());
+                        match error.category {
+                            Some(category) => {
+                                self.tab_load_failures.insert(tab_id, category);
+                            }
+                            None => {
+                                self.tab_load_failures.remove(&tab_id);
+                            }
+                        }
 
                         // Update tab with error content
                         let tab_manager = self.tab_manager.clone();
@@ -802,22 +1624,25 @@ impl Application for CitadelBrowser {
                 log::info!("🗑️ Closing tab: {}", tab_id);
 
                 // Clean up state
-                self.error_states.remove(&tab_id);
-                self.loading_states.remove(&tab_id);
-                self.tab_render_data.remove(&tab_id);
-                self.tab_rendered.remove(&tab_id);
-                self.tab_history.remove(&tab_id);
-                self.tab_scroll_states.remove(&tab_id);
-                self.tab_zoom_levels.remove(&tab_id);
+                let close_detached = self.forget_tab(tab_id);
+
+                // The window that held the tab selects its neighbour; if that
+                // is the focused window, the tab manager follows it
+                let was_focused_tab = self.windows.focused_tab() == Some(tab_id);
+                self.windows.remove_tab(tab_id);
+                let next_tab = self.windows.focused_tab().filter(|_| was_focused_tab);
 
                 let tab_manager = self.tab_manager.clone();
-                return Command::perform(
+                let close = Command::perform(
                     async move { tab_manager.close_tab(tab_id).await },
                     move |result| match result {
                         Ok(_) => {
                             log::info!("✅ Tab closed successfully");
-                            Message::LoadingStateUpdate(tab_id, LoadingState::Idle)
-                            // Dummy message
+                            match next_tab {
+                                Some(next) => Message::SwitchTab(next),
+                                // Dummy message
+                                None => Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
+                            }
                         }
                         Err(e) => {
                             log::error!("❌ Failed to close tab: {}", e);
@@ -825,119 +1650,710 @@ impl Application for CitadelBrowser {
                         }
                     },
                 );
+                return Command::batch([close_detached, close]);
             }
 
-            Message::SwitchTab(tab_id) => {
-                log::info!("🔄 Switching to tab: {}", tab_id);
+            Message::ReopenTab(tab_id) => {
+                let Some(url) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| tab.id == tab_id)
+                    .and_then(|tab| match tab.content {
+                        PageContent::Expired { url } => Some(url),
+                        _ => None,
+                    })
+                else {
+                    return Command::none();
+                };
+                log::info!("♻️ Reopening expired tab: {}", tab_id);
 
-                // Restore this tab's own sanitized ZKVM render (or clear if it has
-                // none yet). This is what makes each tab show its own page.
-                match self.tab_rendered.get(&tab_id) {
-                    Some(content) => {
-                        self.renderer.set_zkvm_content(content.clone());
-                        self.update_scroll_state_for_content(tab_id);
-                        log::info!("✅ Restored ZKVM render for tab {}", tab_id);
-                    }
-                    None => {
-                        self.renderer.clear_zkvm_content();
-                        log::info!("No render stored for tab {} yet", tab_id);
-                    }
-                }
+                let tab_manager = self.tab_manager.clone();
+                Command::perform(
+                    async move { tab_manager.reopen_tab(tab_id).await },
+                    move |result| match result {
+                        Ok(()) => Message::TabReopened(tab_id, url),
+                        Err(e) => {
+                            log::error!("❌ Failed to reopen tab: {}", e);
+                            Message::InitializationError(format!("Failed to reopen tab: {}", e))
+                        }
+                    },
+                )
+            }
 
-                // Reflect the switched-to tab's URL in the address bar.
-                if let Some(url) = self
+            Message::ToggleTabMute(tab_id) => {
+                let muted = self
                     .tab_manager
                     .get_tab_states()
                     .iter()
-                    .find(|t| t.id == tab_id)
-                    .map(|t| t.url.clone())
-                {
-                    self.ui.set_address_bar_value(url);
-                }
-
+                    .any(|tab| tab.id == tab_id && tab.is_muted);
+                log::info!(
+                    "{} tab {}",
+                    if muted {
+                        "🔊 Unmuting"
+                    } else {
+                        "🔇 Muting"
+                    },
+                    tab_id
+                );
                 let tab_manager = self.tab_manager.clone();
-                let tab_id_copy = tab_id; // Copy the UUID
-                return Command::perform(
-                    async move { tab_manager.switch_tab(tab_id_copy).await },
+                Command::perform(
+                    async move { tab_manager.set_muted(tab_id, !muted).await },
                     move |result| match result {
-                        Ok(_) => {
-                            log::info!("✅ Tab switched successfully");
-                            Message::LoadingStateUpdate(tab_id_copy, LoadingState::Idle)
-                            // Dummy message
-                        }
+                        Ok(()) => Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
                         Err(e) => {
-                            log::error!("❌ Failed to switch tab: {}", e);
-                            Message::InitializationError(format!("Tab switch error: {}", e))
+                            Message::InitializationError(format!("Failed to mute tab: {}", e))
                         }
                     },
-                );
+                )
             }
 
-            Message::UpdatePrivacy(level) => {
-                log::info!("🔒 Updating privacy level to: {:?}", level);
-                self.network_config.privacy_level = level;
-
-                // TODO: Update engine configuration and reload if necessary
+            Message::TabMenuOpened(tab_id) => {
+                self.tab_menu = Some(tab_id);
                 Command::none()
             }
 
-            Message::EngineInitialized(engine) => {
-                log::info!("🎉 Engine initialized successfully");
-                self.engine = Some(engine);
+            Message::TabMenuClosed => {
+                self.tab_menu = None;
                 Command::none()
             }
 
-            Message::InitializationError(error) => {
-                log::error!("💥 Initialization error: {}", error);
-                // TODO: Show error in UI
-                Command::none()
+            Message::TabMenuAction(tab_id, action) => {
+                self.tab_menu = None;
+                let Some(tab) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| tab.id == tab_id)
+                else {
+                    return Command::none();
+                };
+                match action {
+                    TabAction::Duplicate => self.update(Message::NewTab {
+                        tab_type: tab.tab_type,
+                        initial_url: Some(tab.url),
+                    }),
+                    TabAction::ReopenEphemeral | TabAction::ReopenInContainer => {
+                        let tab_type = if action == TabAction::ReopenEphemeral {
+                            TabType::Ephemeral
+                        } else {
+                            TabType::Container {
+                                container_id: uuid::Uuid::new_v4(),
+                            }
+                        };
+                        log::info!("📑 Reopening tab {} as {:?}", tab_id, tab_type);
+                        let reopened = self.update(Message::NewTab {
+                            tab_type,
+                            initial_url: Some(tab.url),
+                        });
+                        Command::batch([reopened, self.update(Message::CloseTab(tab_id))])
+                    }
+                    TabAction::MoveToGroup(group) => {
+                        let tab_manager = self.tab_manager.clone();
+                        Command::perform(
+                            async move { tab_manager.set_tab_group(tab_id, group).await },
+                            move |result| match result {
+                                Ok(()) => Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
+                                Err(e) => Message::InitializationError(format!(
+                                    "Failed to move tab to group: {}",
+                                    e
+                                )),
+                            },
+                        )
+                    }
+                    TabAction::Hibernate => {
+                        log::info!("💤 Hibernating tab {}", tab_id);
+                        let tab_manager = self.tab_manager.clone();
+                        Command::perform(
+                            async move { tab_manager.hibernate_tab(tab_id).await },
+                            move |result| match result {
+                                Ok(()) => Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
+                                Err(e) => Message::InitializationError(format!(
+                                    "Failed to hibernate tab: {}",
+                                    e
+                                )),
+                            },
+                        )
+                    }
+                    TabAction::ClearSiteData => {
+                        if let (Some(engine), Ok(url)) = (&self.engine, Url::parse(&tab.url)) {
+                            let host = url.host_str().unwrap_or_default();
+                            let cleared = engine.clear_site_data(tab_id, tab.tab_type, &url)
+                                + self.history.purge_domain(host);
+                            self.save_history();
+                            log::info!("🧹 Cleared {} stored items for {}", cleared, host);
+                        }
+                        Command::none()
+                    }
+                    TabAction::CopyCleanUrl => match tab_menu::clean_url(&tab.url) {
+                        Some(url) => self.update(Message::Copy(CopyKind::Url, url)),
+                        None => Command::none(),
+                    },
+                }
             }
 
-            Message::LoadingStateUpdate(tab_id, state) => {
-                log::debug!("📊 Loading state update for tab {}: {:?}", tab_id, state);
-                self.loading_states.insert(tab_id, state);
+            Message::PageMenuOpened(hit) => {
+                self.page_menu = self.get_active_tab_id().map(|tab_id| (tab_id, hit));
                 Command::none()
             }
 
-            Message::ClearError(tab_id) => {
-                log::info!("🧹 Clearing error for tab: {}", tab_id);
-                self.error_states.remove(&tab_id);
+            Message::PageMenuClosed => {
+                self.page_menu = None;
                 Command::none()
             }
 
-            Message::RefreshTab => {
-                log::info!("🔄 Refreshing current tab");
-
-                if let Some(active_tab) = self
-                    .tab_manager
-                    .get_tab_states()
-                    .iter()
-                    .find(|tab| tab.is_active)
-                {
-                    // Get current URL from tab content
-                    match &active_tab.content {
-                        PageContent::Loaded { url, .. }
-                        | PageContent::Loading { url }
-                        | PageContent::Error { url, .. } => {
-                            return self.update(Message::Navigate(url.clone()));
-                        }
-                        PageContent::Empty => {
-                            // Nothing to refresh
-                            return Command::none();
+            Message::PageMenuAction(action) => {
+                let Some((tab_id, _)) = self.page_menu.take() else {
+                    return Command::none();
+                };
+                match action {
+                    PageAction::OpenInNewTab(url) => {
+                        let tab_type = self
+                            .tab_manager
+                            .get_tab_states()
+                            .into_iter()
+                            .find(|tab| tab.id == tab_id)
+                            .map(|tab| tab.tab_type)
+                            .unwrap_or(TabType::Ephemeral);
+                        self.update(Message::NewTab {
+                            tab_type,
+                            initial_url: Some(url.into()),
+                        })
+                    }
+                    PageAction::OpenInContainer(url) => self.update(Message::NewTab {
+                        tab_type: TabType::Container {
+                            container_id: uuid::Uuid::new_v4(),
+                        },
+                        initial_url: Some(url.into()),
+                    }),
+                    PageAction::CopyCleanLink(url) => {
+                        self.update(Message::Copy(CopyKind::Url, page_menu::clean_link(&url)))
+                    }
+                    PageAction::SaveImage(url) => self.update(Message::StartDownload {
+                        url: url.into(),
+                        integrity: None,
+                    }),
+                    PageAction::ViewSelectionSource(selection) => {
+                        self.selection_source = self
+                            .tab_render_data
+                            .get(&tab_id)
+                            .and_then(|(dom, _)| page_menu::selection_source(dom, &selection));
+                        if self.selection_source.is_none() {
+                            log::debug!("No element of the page holds the selected text");
                         }
+                        Command::none()
                     }
                 }
+            }
 
+            Message::SelectionSourceClosed => {
+                self.selection_source = None;
                 Command::none()
             }
 
-            Message::GoBack => {
-                let Some(tab_id) = self.get_active_tab_id() else {
+            Message::ImageViewer(action) => self
+                .image_viewer_action(action)
+                .unwrap_or_else(Command::none),
+
+            Message::SaveViewedImage => {
+                let Some((tab_id, viewer)) = self.get_active_tab_id().and_then(|tab_id| {
+                    self.tab_images
+                        .get(&tab_id)
+                        .map(|viewer| (tab_id, viewer.clone()))
+                }) else {
                     return Command::none();
                 };
-                if let Some(url) = self
-                    .tab_history
-                    .get_mut(&tab_id)
+                let downloads = self.downloads.clone();
+                Command::perform(
+                    async move {
+                        let png = viewer.png().ok_or("the image cannot be encoded")?;
+                        downloads
+                            .save(viewer.url.clone(), tab_id, &viewer.save_name(), png)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::ViewedImageSaved,
+                )
+            }
+
+            Message::ViewedImageSaved(result) => {
+                match result {
+                    Ok(path) => log::info!("🖼️ Saved image to {}", path.display()),
+                    Err(error) => log::warn!("Image not saved: {}", error),
+                }
+                Command::none()
+            }
+
+            Message::ToggleTabSwitcher => {
+                if self.tab_switcher.take().is_some() {
+                    return Command::none();
+                }
+                self.tab_switcher = Some(TabSwitcher {
+                    matches: self.tab_manager.search_tabs("", TAB_SWITCHER_MATCHES),
+                    ..TabSwitcher::default()
+                });
+                iced::widget::text_input::focus(CitadelUI::tab_switcher_input())
+            }
+
+            Message::TabSwitcherQueryChanged(query) => {
+                if let Some(switcher) = &mut self.tab_switcher {
+                    switcher.matches = self.tab_manager.search_tabs(&query, TAB_SWITCHER_MATCHES);
+                    switcher.selected = 0;
+                    switcher.query = query;
+                }
+                Command::none()
+            }
+
+            Message::TabSwitcherMove(delta) => {
+                if let Some(switcher) = &mut self.tab_switcher {
+                    let last = switcher.matches.len().saturating_sub(1);
+                    switcher.selected = switcher.selected.saturating_add_signed(delta).min(last);
+                }
+                Command::none()
+            }
+
+            Message::TabSwitcherSubmit => {
+                match self
+                    .tab_switcher
+                    .as_ref()
+                    .and_then(TabSwitcher::selected_match)
+                {
+                    Some(found) => self.update(Message::TabSwitcherActivate(found.tab_id)),
+                    None => Command::none(),
+                }
+            }
+
+            Message::TabSwitcherActivate(tab_id) => {
+                let hibernated = self
+                    .tab_switcher
+                    .take()
+                    .and_then(|switcher| switcher.matches.into_iter().find(|m| m.tab_id == tab_id))
+                    .is_some_and(|found| found.hibernated);
+
+                // A tab in another window brings that window forward
+                let focus = match self.windows.window_of(tab_id) {
+                    Some(id) if id != self.windows.focused() => {
+                        self.focus_window(id);
+                        window::gain_focus(id)
+                    }
+                    _ => Command::none(),
+                };
+                let switch = self.update(Message::SwitchTab(tab_id));
+
+                // An expired tab gets a fresh VM once it is the active tab
+                let wake = if hibernated {
+                    let tab_manager = self.tab_manager.clone();
+                    Command::perform(
+                        async move { tab_manager.switch_tab(tab_id).await },
+                        move |_| Message::ReopenTab(tab_id),
+                    )
+                } else {
+                    Command::none()
+                };
+                Command::batch([focus, switch, wake])
+            }
+
+            Message::TabReopened(tab_id, url) => {
+                if self.get_active_tab_id() == Some(tab_id) {
+                    return self.update(Message::Navigate(url));
+                }
+                Command::none()
+            }
+
+            Message::SwitchTab(tab_id) => {
+                log::info!("🔄 Switching to tab: {}", tab_id);
+                self.windows.select_tab(tab_id);
+                self.hovered_link = None;
+                self.page_menu = None;
+                if let Some(engine) = &self.engine {
+                    engine.focus_tab(tab_id);
+                }
+
+                // Restore this tab's own sanitized ZKVM render or text-only page
+                // (or clear if it has none yet). This is what makes each tab
+                // show its own page.
+                match self.tab_rendered.get(&tab_id) {
+                    None if self.tab_images.contains_key(&tab_id) => {
+                        let viewer = self.tab_images[&tab_id].clone();
+                        self.renderer.set_image_viewer(viewer);
+                        log::info!("✅ Restored image viewer for tab {}", tab_id);
+                    }
+                    None if self.tab_text_blocks.contains_key(&tab_id) => {
+                        let blocks = self.tab_text_blocks[&tab_id].clone();
+                        self.renderer.set_text_blocks(blocks);
+                        self.update_scroll_state_for_content(tab_id);
+                        log::info!("✅ Restored text-only page for tab {}", tab_id);
+                    }
+                    Some(content) => {
+                        self.renderer.set_zkvm_content(content.clone());
+                        self.update_scroll_state_for_content(tab_id);
+                        log::info!("✅ Restored ZKVM render for tab {}", tab_id);
+                    }
+                    None => {
+                        self.renderer.clear_zkvm_content();
+                        log::info!("No render stored for tab {} yet", tab_id);
+                    }
+                }
+                #[cfg(feature = "devtools")]
+                self.refresh_layout_debug();
+
+                // Reflect the switched-to tab's URL in the address bar.
+                if let Some(url) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .iter()
+                    .find(|t| t.id == tab_id)
+                    .map(|t| t.url.clone())
+                {
+                    self.ui
+                        .set_address_bar_value(citadel_networking::display_url(&url));
+                }
+
+                let tab_manager = self.tab_manager.clone();
+                let tab_id_copy = tab_id; // Copy the UUID
+                return Command::perform(
+                    async move { tab_manager.switch_tab(tab_id_copy).await },
+                    move |result| match result {
+                        Ok(_) => {
+                            log::info!("✅ Tab switched successfully");
+                            Message::LoadingStateUpdate(tab_id_copy, LoadingState::Idle)
+                            // Dummy message
+                        }
+                        Err(e) => {
+                            log::error!("❌ Failed to switch tab: {}", e);
+                            Message::InitializationError(format!("Tab switch error: {}", e))
+                        }
+                    },
+                );
+            }
+
+            Message::NewWindow => self.open_window(None),
+
+            Message::WindowFocused(id) => {
+                if self.windows.get(id).is_none() || self.windows.focused() == id {
+                    return Command::none();
+                }
+                // The tab manager's active tab follows the focused window
+                match self.focus_window(id) {
+                    Some(tab_id) if self.get_active_tab_id() != Some(tab_id) => {
+                        self.update(Message::SwitchTab(tab_id))
+                    }
+                    _ => Command::none(),
+                }
+            }
+
+            Message::WindowCloseRequested(id) => {
+                if self.windows.len() == 1 && self.windows.get(id).is_some() {
+                    if self.shutdown.is_some() {
+                        return Command::none();
+                    }
+                    // Last window: shut down in order before the app exits
+                    log::info!("🛑 Shutting down");
+                    let snapshot =
+                        SessionSnapshot::capture(&self.windows, &self.tab_manager.get_tab_states());
+                    self.shutdown = Some(Shutdown::new(id, snapshot, std::time::Instant::now()));
+                    if let Some(engine) = &self.engine {
+                        engine.stop_new_loads();
+                    }
+                    let tabs = self
+                        .windows
+                        .get(id)
+                        .map(|w| w.tabs().to_vec())
+                        .unwrap_or_default();
+                    // Detached windows close with the last browser window
+                    let mut commands: Vec<_> =
+                        tabs.iter().map(|tab_id| self.forget_tab(*tab_id)).collect();
+                    commands.push(self.run_shutdown_step(ShutdownStep::SavingSession));
+                    return Command::batch(commands);
+                }
+
+                if self.windows.close_detached(id).is_some() {
+                    log::info!("🪟 Closing detached window {:?}", id);
+                    return window::close(id);
+                }
+
+                let Some(closed) = self.windows.close(id) else {
+                    return window::close(id);
+                };
+                log::info!("🪟 Closing window {:?}", id);
+                self.accessibility.close(id);
+                self.focus_window(self.windows.focused());
+                if self
+                    .dragged_tab
+                    .is_some_and(|tab| closed.tabs().contains(&tab))
+                {
+                    self.dragged_tab = None;
+                }
+                let mut commands = Vec::new();
+                for tab_id in closed.tabs().iter().copied() {
+                    commands.push(self.forget_tab(tab_id));
+                    let tab_manager = self.tab_manager.clone();
+                    commands.push(Command::perform(
+                        async move { tab_manager.close_tab(tab_id).await },
+                        move |_| Message::LoadingStateUpdate(tab_id, LoadingState::Idle),
+                    ));
+                }
+                if let Some(tab_id) = self.windows.focused_tab() {
+                    commands.push(self.update(Message::SwitchTab(tab_id)));
+                }
+                commands.push(window::close(id));
+                Command::batch(commands)
+            }
+
+            Message::ShutdownStepFinished(step) => {
+                let Some(shutdown) = self.shutdown.as_mut() else {
+                    return Command::none();
+                };
+                if let Some(next) = shutdown.finish(step) {
+                    return self.run_shutdown_step(next);
+                }
+                if !shutdown.is_done() {
+                    return Command::none();
+                }
+                let id = shutdown.window();
+                profile::lock();
+                self.windows.close(id);
+                self.accessibility.close(id);
+                // The app exits once every window, detached ones included, is gone
+                let mut commands: Vec<_> = self
+                    .windows
+                    .close_all_detached()
+                    .into_iter()
+                    .map(window::close)
+                    .collect();
+                commands.push(window::close(id));
+                Command::batch(commands)
+            }
+
+            Message::ToggleBookmark => {
+                let Some(tab) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| Some(tab.id) == self.windows.focused_tab())
+                else {
+                    return Command::none();
+                };
+                if self.bookmarks.contains(&tab.url) {
+                    self.bookmarks.remove(&tab.url);
+                } else {
+                    self.bookmarks.add(&tab.url, &tab.title);
+                }
+                Command::none()
+            }
+
+            #[cfg(feature = "devtools")]
+            Message::ToggleLayoutDebug => {
+                self.layout_debug = !self.layout_debug;
+                self.refresh_layout_debug();
+                Command::none()
+            }
+
+            Message::ToggleOverlayCleanup => {
+                let Some(url) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| Some(tab.id) == self.windows.focused_tab())
+                    .and_then(|tab| Url::parse(&tab.url).ok())
+                else {
+                    return Command::none();
+                };
+                let enabled = self.overlay_cleanup.toggle(&url);
+                log::info!(
+                    "🧹 Overlay cleanup {} for {}",
+                    if enabled { "on" } else { "off" },
+                    url.host_str().unwrap_or_default()
+                );
+                if let Some(path) = overlay_cleanup::default_path() {
+                    let cleanup = self.overlay_cleanup.clone();
+                    self.runtime.spawn(async move {
+                        if let Err(e) = cleanup.save(&path).await {
+                            log::error!("❌ Failed to save overlay cleanup choices: {}", e);
+                        }
+                    });
+                }
+                self.update(Message::RefreshTab)
+            }
+
+            Message::ToggleSiteFiltering => {
+                let Some((url, site)) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| Some(tab.id) == self.windows.focused_tab())
+                    .and_then(|tab| Url::parse(&tab.url).ok())
+                    .and_then(|url| FilterAllowlist::site_of(&url).map(|site| (url, site)))
+                else {
+                    return Command::none();
+                };
+                let host = url.host_str().unwrap_or_default();
+                // A page may be covered by a parent domain allowlisted by hand
+                if self.blocklist.is_allowlisted(host) {
+                    for domain in self.blocklist.allowlist() {
+                        if host == domain || host.ends_with(&format!(".{}", domain)) {
+                            self.blocklist.disallow(&domain);
+                        }
+                    }
+                    log::info!("🧩 Filtering {} again", site);
+                } else {
+                    self.blocklist.allow(&site);
+                    log::info!("🧩 Allowlisted {}", site);
+                }
+                if let Some(path) = filter_allowlist::default_path() {
+                    let allowlist = FilterAllowlist {
+                        sites: self.blocklist.allowlist().into_iter().collect(),
+                    };
+                    self.runtime.spawn(async move {
+                        if let Err(e) = allowlist.save(&path).await {
+                            log::error!("❌ Failed to save the filter allowlist: {}", e);
+                        }
+                    });
+                }
+                self.update(Message::RefreshTab)
+            }
+
+            Message::ToggleTextOnly => {
+                let (Some(engine), Some(tab_id)) = (&self.engine, self.windows.focused_tab())
+                else {
+                    return Command::none();
+                };
+                let enabled = engine.text_only().toggle_tab(tab_id);
+                log::info!(
+                    "📄 Text-only mode {} for tab {}",
+                    if enabled { "on" } else { "off" },
+                    tab_id
+                );
+                self.update(Message::RefreshTab)
+            }
+
+            Message::ToggleGlobalTextOnly => {
+                let Some(engine) = &self.engine else {
+                    return Command::none();
+                };
+                let enabled = engine.text_only().toggle_global();
+                log::info!(
+                    "📄 Text-only mode {} for every tab",
+                    if enabled { "on" } else { "off" }
+                );
+                self.update(Message::RefreshTab)
+            }
+
+            Message::DetachTab(tab_id, mode) => {
+                let (id, spawn) = window::spawn(Self::detached_window_settings());
+                log::info!(
+                    "🪟 Detaching tab {} into {:?} window {:?}",
+                    tab_id,
+                    mode,
+                    id
+                );
+                self.windows.open_detached(id, tab_id, mode);
+                spawn
+            }
+
+            Message::TabDragStarted(tab_id) => {
+                self.dragged_tab = Some(tab_id);
+                Command::none()
+            }
+
+            Message::TabDropped(target) => {
+                let Some(tab_id) = self.dragged_tab.take() else {
+                    return Command::none();
+                };
+                self.move_tab_to_window(tab_id, target)
+            }
+
+            Message::TabDroppedInNewWindow => {
+                let Some(tab_id) = self.dragged_tab.take() else {
+                    return Command::none();
+                };
+                let (id, spawn) = window::spawn(Self::window_settings());
+                self.windows.open(id);
+                Command::batch([
+                    spawn,
+                    Self::open_accessibility(id),
+                    self.move_tab_to_window(tab_id, id),
+                ])
+            }
+
+            Message::UpdatePrivacy(level) => {
+                log::info!("🔒 Updating privacy level to: {:?}", level);
+                self.network_config.privacy_level = level;
+                // Each tab picks the new level up on its next navigation
+                self.settings.set_privacy_level(level);
+                Command::none()
+            }
+
+            Message::EngineInitialized(engine) => {
+                log::info!("🎉 Engine initialized successfully");
+                engine.set_power_saving(self.power_profile.is_power_saving());
+                self.engine = Some(engine);
+                self.record_startup(StartupPhase::Engine);
+                match self.app_launch.take() {
+                    Some(launch) => self.update(Message::NewTab {
+                        tab_type: TabType::Container {
+                            container_id: launch.container_id,
+                        },
+                        initial_url: Some(launch.start_url.to_string()),
+                    }),
+                    None => Command::none(),
+                }
+            }
+
+            Message::InitializationError(error) => {
+                log::error!("💥 Initialization error: {}", error);
+                // TODO: Show error in UI
+                Command::none()
+            }
+
+            Message::LoadingStateUpdate(tab_id, state) => {
+                log::debug!("📊 Loading state update for tab {}: {:?}", tab_id, state);
+                self.loading_states.insert(tab_id, state);
+                Command::none()
+            }
+
+            Message::ClearError(tab_id) => {
+                log::info!("🧹 Clearing error for tab: {}", tab_id);
+                self.error_states.remove(&tab_id);
+                Command::none()
+            }
+
+            Message::RefreshTab => {
+                log::info!("🔄 Refreshing current tab");
+
+                if let Some(active_tab) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .iter()
+                    .find(|tab| tab.is_active)
+                {
+                    // Get current URL from tab content
+                    match &active_tab.content {
+                        PageContent::Loaded { url, .. }
+                        | PageContent::Loading { url }
+                        | PageContent::Error { url, .. } => {
+                            return self.update(Message::Navigate(url.clone()));
+                        }
+                        PageContent::Expired { .. } => {
+                            return self.update(Message::ReopenTab(active_tab.id));
+                        }
+                        PageContent::Empty => {
+                            // Nothing to refresh
+                            return Command::none();
+                        }
+                    }
+                }
+
+                Command::none()
+            }
+
+            Message::GoBack => {
+                let Some(tab_id) = self.get_active_tab_id() else {
+                    return Command::none();
+                };
+                if let Some(url) = self
+                    .tab_history
+                    .get_mut(&tab_id)
                     .and_then(TabHistory::go_back)
                 {
                     log::info!("⬅️ Back to {}", url);
@@ -974,6 +2390,7 @@ impl Application for CitadelBrowser {
             }
 
             Message::ZkVmRendered(tab_id, rendered) => {
+                let audible = rendered.as_ref().is_some_and(|content| content.plays_audio);
                 match rendered {
                     Some(content) => {
                         log::info!(
@@ -983,6 +2400,30 @@ impl Application for CitadelBrowser {
                             content.height as u32,
                             content.security_metadata.blocked_elements,
                         );
+                        // Scripts the cage refused count towards escalating
+                        // against the site at its next load
+                        let metadata = &content.security_metadata;
+                        let refused = metadata.scripts_errored + metadata.content_scripts_errored;
+                        if let Some(escalation) = self.engine.as_ref().and_then(|engine| {
+                            engine.report_violations(
+                                tab_id,
+                                &content.url,
+                                ViolationSource::ScriptPolicy,
+                                refused,
+                            )
+                        }) {
+                            if escalation.banner().is_some() {
+                                self.tab_escalations.insert(tab_id, escalation);
+                            }
+                        }
+                        // The load's first paint with something on it is its
+                        // first contentful render
+                        if !content.display_list.is_empty() {
+                            if let Some(started) = self.tab_load_started.remove(&tab_id) {
+                                self.performance_monitor
+                                    .record_first_contentful_render(tab_id, started.elapsed());
+                            }
+                        }
                         // Keep each tab's output so switching tabs restores it.
                         self.tab_rendered.insert(tab_id, content.clone());
                         // Only paint it if this tab is the one on screen — a slow
@@ -990,6 +2431,10 @@ impl Application for CitadelBrowser {
                         if self.get_active_tab_id() == Some(tab_id) {
                             self.renderer.set_zkvm_content(content);
                             self.update_scroll_state_for_content(tab_id);
+                            self.hovered_link = None;
+                            self.page_menu = None;
+                            #[cfg(feature = "devtools")]
+                            self.refresh_layout_debug();
                         }
                         self.error_states.remove(&tab_id);
                     }
@@ -1002,7 +2447,18 @@ impl Application for CitadelBrowser {
                     }
                 }
                 self.loading_states.insert(tab_id, LoadingState::Idle);
-                Command::none()
+
+                // The tab strip shows a speaker while the page would play sound
+                let tab_manager = self.tab_manager.clone();
+                Command::perform(
+                    async move { tab_manager.set_audible(tab_id, audible).await },
+                    move |result| {
+                        if let Err(e) = result {
+                            log::warn!("Failed to record audio state of tab {}: {}", tab_id, e);
+                        }
+                        Message::LoadingStateUpdate(tab_id, LoadingState::Idle)
+                    },
+                )
             }
 
             Message::TabOpened {
@@ -1011,14 +2467,24 @@ impl Application for CitadelBrowser {
             } => {
                 log::info!("🔗 Tab {} opened", tab_id);
 
+                // New tabs join the focused window's strip; the first tab of
+                // a new window is selected there right away
+                self.windows.add_tab(self.windows.focused(), tab_id);
+                let switch = match self.windows.focused_tab() {
+                    Some(selected) if self.get_active_tab_id() != Some(selected) => {
+                        self.update(Message::SwitchTab(selected))
+                    }
+                    _ => Command::none(),
+                };
+
                 // TODO: Get channel from tab manager and setup receiver
                 // For now, just navigate if URL provided
 
                 // Navigate if initial URL provided
                 if let Some(url) = initial_url {
-                    self.update(Message::Navigate(url))
+                    Command::batch([switch, self.update(Message::Navigate(url))])
                 } else {
-                    Command::none()
+                    switch
                 }
             }
 
@@ -1079,6 +2545,10 @@ impl Application for CitadelBrowser {
 
             // Viewport and scrolling message handlers
             Message::ZoomIn => {
+                // The image viewer zooms the image, not the page
+                if let Some(command) = self.image_viewer_action(ViewerAction::ZoomIn) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let current_zoom = self
                         .tab_zoom_levels
@@ -1098,6 +2568,9 @@ impl Application for CitadelBrowser {
             }
 
             Message::ZoomOut => {
+                if let Some(command) = self.image_viewer_action(ViewerAction::ZoomOut) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let current_zoom = self
                         .tab_zoom_levels
@@ -1117,6 +2590,9 @@ impl Application for CitadelBrowser {
             }
 
             Message::ZoomReset => {
+                if let Some(command) = self.image_viewer_action(ViewerAction::Fit) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     self.tab_zoom_levels
                         .insert(active_tab, ZoomLevel::Percent100);
@@ -1142,6 +2618,13 @@ impl Application for CitadelBrowser {
             }
 
             Message::ScrollUp => {
+                // The image viewer pans the image
+                if let Some(command) = self.image_viewer_action(ViewerAction::Pan {
+                    dx: 0.0,
+                    dy: -image_viewer::PAN_STEP,
+                }) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let scroll_state = self.tab_scroll_states.entry(active_tab).or_default();
                     scroll_state.scroll_by(0.0, -50.0); // Scroll up by 50px
@@ -1153,6 +2636,12 @@ impl Application for CitadelBrowser {
             }
 
             Message::ScrollDown => {
+                if let Some(command) = self.image_viewer_action(ViewerAction::Pan {
+                    dx: 0.0,
+                    dy: image_viewer::PAN_STEP,
+                }) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let scroll_state = self.tab_scroll_states.entry(active_tab).or_default();
                     scroll_state.scroll_by(0.0, 50.0); // Scroll down by 50px
@@ -1168,6 +2657,12 @@ impl Application for CitadelBrowser {
             }
 
             Message::ScrollLeft => {
+                if let Some(command) = self.image_viewer_action(ViewerAction::Pan {
+                    dx: -image_viewer::PAN_STEP,
+                    dy: 0.0,
+                }) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let scroll_state = self.tab_scroll_states.entry(active_tab).or_default();
                     scroll_state.scroll_by(-50.0, 0.0); // Scroll left by 50px
@@ -1183,6 +2678,12 @@ impl Application for CitadelBrowser {
             }
 
             Message::ScrollRight => {
+                if let Some(command) = self.image_viewer_action(ViewerAction::Pan {
+                    dx: image_viewer::PAN_STEP,
+                    dy: 0.0,
+                }) {
+                    return command;
+                }
                 if let Some(active_tab) = self.get_active_tab_id() {
                     let scroll_state = self.tab_scroll_states.entry(active_tab).or_default();
                     scroll_state.scroll_by(50.0, 0.0); // Scroll right by 50px
@@ -1317,55 +2818,1183 @@ impl Application for CitadelBrowser {
                         }
                     }
                 }
+                self.purge_expired_tabs();
+                self.renderer.flush_deferred_layout();
+                Command::none()
+            }
+
+            Message::FirstFrame => {
+                if self.startup.is_recorded(StartupPhase::FirstFrame) {
+                    return Command::none();
+                }
+                self.record_startup(StartupPhase::FirstFrame);
+                self.warm_vm_pool()
+            }
+
+            Message::CosmeticFilterCompiled(filter) => {
+                log::info!("🧹 {} cosmetic filter rules compiled", filter.len());
+                self.cosmetic_filter = filter;
+                self.record_startup(StartupPhase::CosmeticFilter);
+                Command::none()
+            }
+
+            Message::RefreshFilterLists => {
+                if self.network_config.tracker_blocking.blocking_level == BlockingLevel::Disabled {
+                    return Command::none();
+                }
+                let runtime = self.runtime.clone();
+                let filter_lists = self.filter_lists.clone();
+                Command::perform(
+                    async move {
+                        let refresh = move || {
+                            refresh_filter_lists(&filter_lists)
+                                .then(|| cosmetic_filter(&filter_lists))
+                        };
+                        runtime.spawn_blocking(refresh).await.ok().flatten()
+                    },
+                    Message::FilterListsRefreshed,
+                )
+            }
+
+            Message::FilterListsRefreshed(Some(filter)) => {
+                log::info!(
+                    "🧩 Filter lists changed: {} lists, {} cosmetic filter rules",
+                    self.filter_lists.len(),
+                    filter.len()
+                );
+                self.cosmetic_filter = filter;
+                Command::none()
+            }
+            Message::FilterListsRefreshed(None) => Command::none(),
+
+            Message::UpdateFilterLists => {
+                if self.network_config.tracker_blocking.blocking_level == BlockingLevel::Disabled {
+                    return Command::none();
+                }
+                let runtime = self.runtime.clone();
+                let proxy = self.network_config.socks_proxy.clone();
+                Command::perform(
+                    async move {
+                        let update = async move {
+                            match filter_updater(proxy) {
+                                Some(updater) => updater.update_due().await,
+                                None => 0,
+                            }
+                        };
+                        runtime.spawn(update).await.unwrap_or(0)
+                    },
+                    Message::FilterListsUpdated,
+                )
+            }
+            // Installed lists are picked up by the next refresh; run it now
+            Message::FilterListsUpdated(0) => Command::none(),
+            Message::FilterListsUpdated(_) => self.update(Message::RefreshFilterLists),
+
+            Message::ExtensionsLoaded(extensions) => {
+                self.extensions = extensions;
+                self.record_startup(StartupPhase::Extensions);
+                Command::none()
+            }
+
+            Message::VmPoolWarming(Ok(())) => Command::none(),
+            Message::VmPoolWarming(Err(e)) => {
+                log::warn!("Tabs will create their VMs as they open: {}", e);
+                Command::none()
+            }
+
+            Message::ProbePower => {
+                let profile = PowerProfile::for_source(power_profile::probe());
+                if profile == self.power_profile {
+                    return Command::none();
+                }
+                log::info!("🔋 Switching to the {:?} power profile", profile);
+                self.apply_power_profile(profile)
+            }
+
+            Message::PowerProfileApplied(Ok(())) => Command::none(),
+            Message::PowerProfileApplied(Err(e)) => {
+                log::warn!("Background tabs keep their throttling: {}", e);
+                Command::none()
+            }
+
+            Message::TogglePrivacyPanel => {
+                self.privacy_panel_expanded = !self.privacy_panel_expanded;
+                Command::none()
+            }
+
+            // Only the prompt takes input while the profile is locked
+            Message::KeyPressed(..) if self.profile_prompt.is_some() => Command::none(),
+            Message::KeyPressed(key, modifiers) if self.tab_switcher.is_some() => {
+                self.handle_tab_switcher_key(&key, modifiers)
+            }
+            Message::KeyPressed(key, modifiers) => self.handle_keyboard_event(&key, modifiers),
+
+            Message::FocusNext | Message::FocusPrevious => {
+                let focused_y = if matches!(message, Message::FocusNext) {
+                    self.renderer.focus_next()
+                } else {
+                    self.renderer.focus_previous()
+                };
+                match focused_y {
+                    // Keep the focused element in view
+                    Some(y) => {
+                        let x = self
+                            .get_active_tab_id()
+                            .and_then(|id| self.tab_scroll_states.get(&id))
+                            .map(|state| state.x)
+                            .unwrap_or(0.0);
+                        self.update(Message::ScrollTo { x, y })
+                    }
+                    None => Command::none(),
+                }
+            }
+
+            Message::ActivateFocus => match self.renderer.activate_focus() {
+                Some(FocusActivation::Navigate(url)) => {
+                    log::info!("⌨️ Activating focused link: {}", url);
+                    self.update(Message::Navigate(url))
+                }
+                Some(FocusActivation::Press(id)) => {
+                    log::debug!("⌨️ Focused control {} pressed", id);
+                    Command::none()
+                }
+                None => Command::none(),
+            },
+
+            Message::AccessibilityReady(id, view) => {
+                // The window may have closed before its view was handed over
+                if self.windows.get(id).is_some() && self.accessibility.open(id, view).is_some() {
+                    self.focus_window(self.windows.focused());
+                }
+                Command::none()
+            }
+
+            Message::AccessibilityRequests => {
+                let mut commands = Vec::new();
+                for request in self.accessibility.drain() {
+                    commands.push(self.handle_accessibility_request(request));
+                }
+                Command::batch(commands)
+            }
+
+            Message::FileDropped(window, path) => {
+                let focus = self.update(Message::WindowFocused(window));
+                match DroppedContent::from_path(&path) {
+                    Some(content) => {
+                        Command::batch([focus, self.update(Message::ContentDropped(content))])
+                    }
+                    None => {
+                        log::info!("Ignoring dropped file {}: not a page", path.display());
+                        focus
+                    }
+                }
+            }
+
+            Message::Copy(kind, text) => {
+                let copy = self.clipboard_policy.prepare(kind, &text);
+                let Some(delay) = copy.clear_after else {
+                    return iced::clipboard::write(copy.text);
+                };
+                let generation = self.pending_clear.arm(&copy.text);
+                Command::batch([
+                    iced::clipboard::write(copy.text),
+                    Command::perform(tokio::time::sleep(delay), move |_| {
+                        Message::ClipboardClearDue(generation)
+                    }),
+                ])
+            }
+
+            Message::CopyPageUrl => {
+                let Some(url) = self.get_active_tab_id().and_then(|id| {
+                    self.tab_manager
+                        .get_tab_states()
+                        .into_iter()
+                        .find(|tab| tab.id == id)
+                        .map(|tab| tab.url)
+                        .filter(|url| !url.is_empty())
+                }) else {
+                    return Command::none();
+                };
+                self.update(Message::Copy(CopyKind::Url, url))
+            }
+
+            Message::ClipboardClearDue(generation) => {
+                if self.pending_clear.is_due(generation) {
+                    iced::clipboard::read(Message::ClipboardReadForClear)
+                } else {
+                    Command::none()
+                }
+            }
+
+            Message::ClipboardReadForClear(current) => {
+                if self.pending_clear.take_if_unchanged(current.as_deref()) {
+                    log::info!("📋 Cleared a copied secret from the clipboard");
+                    iced::clipboard::write(String::new())
+                } else {
+                    Command::none()
+                }
+            }
+
+            Message::StartDownload { url, integrity } => {
+                let Some(tab_id) = self.get_active_tab_id() else {
+                    return Command::none();
+                };
+                let url = match Url::parse(&url) {
+                    Ok(url) if matches!(url.scheme(), "https" | "http") => url,
+                    _ => {
+                        log::warn!("Not downloading {}: not a web address", url);
+                        return Command::none();
+                    }
+                };
+                if self.engine.is_none() {
+                    log::warn!("Not downloading {}: engine not started", url);
+                    return Command::none();
+                }
+                let id = self.downloads.start(url, tab_id, integrity);
+                self.run_download(id)
+            }
+
+            Message::SavePage => {
+                // An image is saved from the viewer, without its metadata
+                if self
+                    .get_active_tab_id()
+                    .is_some_and(|tab_id| self.tab_images.contains_key(&tab_id))
+                {
+                    return self.update(Message::SaveViewedImage);
+                }
+                let Some(url) = self.get_active_tab_id().and_then(|id| {
+                    self.tab_manager
+                        .get_tab_states()
+                        .into_iter()
+                        .find(|tab| tab.id == id)
+                        .map(|tab| tab.url)
+                        .filter(|url| !url.is_empty())
+                }) else {
+                    return Command::none();
+                };
+                self.update(Message::StartDownload {
+                    url,
+                    integrity: None,
+                })
+            }
+
+            Message::DownloadFinished(id, result) => {
+                let name = self
+                    .downloads
+                    .get(id)
+                    .map(|download| download.file_name)
+                    .unwrap_or_default();
+                match result {
+                    Ok(DownloadState::Completed { verified: true }) => {
+                        log::info!("⬇️ Downloaded {} (digest verified)", name)
+                    }
+                    Ok(DownloadState::Completed { verified: false }) => {
+                        log::info!("⬇️ Downloaded {}", name)
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Download of {} failed: {}", name, e),
+                }
+                Command::none()
+            }
+
+            Message::PauseDownload(id) => {
+                self.downloads.pause(id);
+                Command::none()
+            }
+
+            Message::ResumeDownload(id) => {
+                if self.downloads.resume(id) {
+                    self.run_download(id)
+                } else {
+                    Command::none()
+                }
+            }
+
+            Message::CancelDownload(id) => {
+                self.downloads.cancel(id);
+                Command::none()
+            }
+
+            Message::ClearDownloads => {
+                self.downloads.clear_finished();
+                Command::none()
+            }
+
+            Message::DownloadsTick => Command::none(),
+
+            Message::PasteIntoPage => iced::clipboard::read(Message::Pasted),
+
+            Message::Pasted(text) => match text.as_deref().and_then(DroppedContent::from_text) {
+                Some(content) => self.update(Message::ContentDropped(content)),
+                None => Command::none(),
+            },
+
+            Message::ContentDropped(content) => match content {
+                DroppedContent::OpenFile(url) | DroppedContent::Navigate(url) => {
+                    self.update(Message::Navigate(url.to_string()))
+                }
+                DroppedContent::Search(query) => self.update(Message::NewTab {
+                    tab_type: TabType::Ephemeral,
+                    initial_url: Some(search_url(&query)),
+                }),
+            },
+
+            Message::ConfirmExternalProtocol { always_allow } => {
+                let Some(url) = self.pending_external.take() else {
+                    return Command::none();
+                };
+                if always_allow && self.external_protocols.allow_always(url.scheme()) {
+                    if let Some(path) = external_protocols::default_path() {
+                        let prefs = self.external_protocols.clone();
+                        self.runtime.spawn(async move {
+                            if let Err(e) = prefs.save(&path).await {
+                                log::error!("❌ Failed to save external protocol choices: {}", e);
+                            }
+                        });
+                    }
+                }
+                self.open_externally(&url);
+                Command::none()
+            }
+
+            Message::CancelExternalProtocol => {
+                self.pending_external = None;
+                Command::none()
+            }
+
+            Message::LinkHovered(href) => {
+                let page_url = self.get_active_tab_id().and_then(|id| {
+                    self.tab_manager
+                        .get_tab_states()
+                        .into_iter()
+                        .find(|tab| tab.id == id)
+                        .and_then(|tab| Url::parse(&tab.url).ok())
+                });
+                self.hovered_link =
+                    href.and_then(|href| link_preview::preview(&href, page_url.as_ref()));
+                Command::none()
+            }
+
+            Message::InstallWebApp => {
+                let Some(engine) = self.engine.clone() else {
+                    return Command::none();
+                };
+                let Some(tab) = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| tab.is_active)
+                else {
+                    return Command::none();
+                };
+                let Ok(page_url) = Url::parse(&tab.url) else {
+                    return Command::none();
+                };
+                let Some((manifest_url, favicon)) =
+                    self.tab_render_data.get(&tab.id).and_then(|(dom, _)| {
+                        let manifest_url = web_app::manifest_link(dom, &page_url)?;
+                        Some((manifest_url, assets::favicon_link(dom, &page_url)))
+                    })
+                else {
+                    log::warn!("📲 {} does not link a web app manifest", page_url);
+                    return Command::none();
+                };
+                log::info!("📲 Fetching web app manifest {}", manifest_url);
+                Command::perform(
+                    async move {
+                        let manifest = engine
+                            .fetch_manifest(&manifest_url, &page_url, tab.id, tab.tab_type)
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        // The app installs without an icon if none can be made
+                        let icons = match assets::icon_source(&manifest, favicon) {
+                            Some(icon_url) => engine
+                                .fetch_icon(&icon_url, &page_url, tab.id, tab.tab_type)
+                                .await
+                                .map_err(|e| log::warn!("📲 No icon from {}: {}", icon_url, e))
+                                .ok(),
+                            None => None,
+                        };
+                        Ok((manifest, icons))
+                    },
+                    Message::WebAppManifestLoaded,
+                )
+            }
+
+            Message::WebAppManifestLoaded(Ok((manifest, icons))) => {
+                let Some(dir) = web_app::shortcuts_dir() else {
+                    log::warn!(
+                        "⚠️ No applications directory, not installing {}",
+                        manifest.name
+                    );
+                    return Command::none();
+                };
+                // Each installed app gets a container of its own
+                let container_id = uuid::Uuid::new_v4();
+                let icons_dir = assets::icons_dir();
+                Command::perform(
+                    async move {
+                        let app_id = web_app::app_id(&manifest.start_url);
+                        let icon = match (icons, icons_dir) {
+                            (Some(icons), Some(icons_dir)) => {
+                                match assets::install_icons(&icons, &app_id, &icons_dir).await {
+                                    Ok(()) => Some(app_id),
+                                    Err(e) => {
+                                        log::warn!("📲 Installing without an icon: {}", e);
+                                        None
+                                    }
+                                }
+                            }
+                            _ => None,
+                        };
+                        web_app::install_shortcut(&manifest, container_id, &dir, icon.as_deref())
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::WebAppInstalled,
+                )
+            }
+
+            Message::WebAppManifestLoaded(Err(e)) => {
+                log::error!("❌ Failed to load web app manifest: {}", e);
+                Command::none()
+            }
+
+            Message::WebFontFetched(request, Ok(bytes)) => {
+                let family = match web_fonts::validate_font(&bytes) {
+                    Ok(family) => family,
+                    Err(e) => {
+                        log::warn!("🔤 Not loading font {}: {}", request.url, e);
+                        return Command::none();
+                    }
+                };
+                iced::font::load(bytes).map(move |loaded| {
+                    Message::WebFontLoaded(request.clone(), family.clone(), loaded.is_ok())
+                })
+            }
+
+            Message::WebFontFetched(request, Err(e)) => {
+                log::debug!("🔤 Font {} not fetched: {}", request.url, e);
+                Command::none()
+            }
+
+            Message::WebFontLoaded(request, family, true) => {
+                log::debug!("🔤 Loaded {} as {:?}", request.url, family);
+                self.renderer.register_web_font(&request, &family);
+                Command::none()
+            }
+
+            Message::WebFontLoaded(request, _, false) => {
+                log::warn!("🔤 The text system refused font {}", request.url);
+                Command::none()
+            }
+
+            Message::ImageDecoded(request, Ok(image)) => {
+                log::debug!(
+                    "🖼️ Decoded {} at {}x{}",
+                    request.url,
+                    image.width,
+                    image.height
+                );
+                self.renderer.register_image(&request, &image);
+                Command::none()
+            }
+
+            Message::ImageDecoded(request, Err(e)) => {
+                log::debug!("🖼️ Image {} not shown: {}", request.url, e);
+                Command::none()
+            }
+
+            Message::Panic => {
+                log::warn!("🚨 Panic: wiping all session state");
+                self.history.clear();
+                self.save_history();
+                self.tab_history.clear();
+                self.history_suppress = false;
+                self.error_states.clear();
+                self.tab_load_failures.clear();
+                self.loading_states.clear();
+                self.tab_render_data.clear();
+                self.tab_rendered.clear();
+                self.tab_scroll_states.clear();
+                self.tab_zoom_levels.clear();
+                self.tab_security_headers.clear();
+                self.tab_languages.clear();
+                self.tab_truncations.clear();
+                self.tab_escalations.clear();
+                self.tab_text_blocks.clear();
+                self.tab_images.clear();
+                self.privacy_stats = PrivacyStats::default();
+                self.dragged_tab = None;
+                self.pending_external = None;
+                self.hovered_link = None;
+                self.page_menu = None;
+                self.selection_source = None;
+                self.renderer.clear_zkvm_content();
+                self.renderer.clear_form_state();
+                self.ui.set_address_bar_value(String::new());
+                self.ui.set_suggestions(Vec::new());
+                if let Some(engine) = &self.engine {
+                    engine.wipe_session_state();
+                }
+
+                let tab_manager = self.tab_manager.clone();
+                let containers = self.panic_options.wipe_containers.clone();
+                let secrets = self.secrets.clone();
+                Command::perform(
+                    async move {
+                        match tab_manager.wipe_all_tabs().await {
+                            Ok(wiped) => log::warn!("🚨 Terminated {} tabs", wiped.len()),
+                            Err(e) => log::error!("❌ Failed to wipe tabs: {}", e),
+                        }
+                        // The profile locks too; the passphrase is needed to
+                        // open it again, even at the next launch
+                        if let (Some(secrets), Some(key_path)) =
+                            (secrets, profile::default_key_path())
+                        {
+                            if let Err(e) =
+                                profile::forget_keychain(&key_path, secrets.as_ref()).await
+                            {
+                                log::error!(
+                                    "❌ Failed to remove the profile key from {}: {}",
+                                    secrets.name(),
+                                    e
+                                );
+                            }
+                        }
+                        if let Some(path) = session::default_path() {
+                            match panic::wipe_containers(&path, &containers).await {
+                                Ok(removed) => {
+                                    log::warn!("🚨 Removed {} saved container tabs", removed)
+                                }
+                                Err(e) => log::error!("❌ Failed to wipe containers: {}", e),
+                            }
+                        }
+                    },
+                    |_| Message::PanicWiped,
+                )
+            }
+
+            Message::PanicWiped => {
+                profile::lock();
+                // Closed without the session save a normal exit runs
+                let mut commands: Vec<_> = self
+                    .windows
+                    .close_all_detached()
+                    .into_iter()
+                    .map(window::close)
+                    .collect();
+                let ids: Vec<_> = self.windows.windows().iter().map(|w| w.id()).collect();
+                for id in ids {
+                    self.windows.close(id);
+                    self.accessibility.close(id);
+                    commands.push(window::close(id));
+                }
+                Command::batch(commands)
+            }
+
+            Message::WebAppInstalled(result) => {
+                match result {
+                    Ok(path) => log::info!("📲 Installed desktop shortcut {}", path.display()),
+                    Err(e) => log::error!("❌ Failed to install desktop shortcut: {}", e),
+                }
+                Command::none()
+            }
+
+            Message::ProfileSecretChanged(value) => {
+                if let Some(prompt) = &mut self.profile_prompt {
+                    prompt.secret = Zeroizing::new(value);
+                }
+                Command::none()
+            }
+
+            Message::ProfileSecondChanged(value) => {
+                if let Some(prompt) = &mut self.profile_prompt {
+                    prompt.second = Zeroizing::new(value);
+                }
+                Command::none()
+            }
+
+            Message::SetProfilePromptMode(mode) => {
+                if let Some(prompt) = &mut self.profile_prompt {
+                    if !prompt.busy && prompt.mode != ProfilePromptMode::Enable {
+                        *prompt = ProfilePrompt::new(mode);
+                    }
+                }
                 Command::none()
             }
-
-            Message::TogglePrivacyPanel => {
-                self.privacy_panel_expanded = !self.privacy_panel_expanded;
-                Command::none()
+
+            Message::SubmitProfilePrompt => {
+                let Some(prompt) = &mut self.profile_prompt else {
+                    return Command::none();
+                };
+                if prompt.busy {
+                    return Command::none();
+                }
+                let Some(key_path) = profile::default_key_path() else {
+                    prompt.error = Some("No location for the profile key file".to_string());
+                    return Command::none();
+                };
+                let secret = prompt.secret.clone();
+                let second = prompt.second.clone();
+                prompt.error = None;
+                match prompt.mode {
+                    ProfilePromptMode::Unlock => {
+                        prompt.busy = true;
+                        Command::perform(
+                            async move {
+                                profile::unlock(&key_path, &secret, None)
+                                    .await
+                                    .map_err(|e| e.to_string())
+                            },
+                            Message::ProfileUnlocked,
+                        )
+                    }
+                    ProfilePromptMode::Recover => {
+                        let Some(recovery) = RecoveryKey::parse(&secret) else {
+                            prompt.error = Some("That is not a recovery key".to_string());
+                            return Command::none();
+                        };
+                        if second.is_empty() {
+                            prompt.error = Some("Choose a new passphrase".to_string());
+                            return Command::none();
+                        }
+                        prompt.busy = true;
+                        Command::perform(
+                            async move {
+                                profile::unlock(&key_path, &second, Some(recovery))
+                                    .await
+                                    .map_err(|e| e.to_string())
+                            },
+                            Message::ProfileUnlocked,
+                        )
+                    }
+                    ProfilePromptMode::Enable => {
+                        if secret.chars().count() < MIN_PASSPHRASE_CHARS {
+                            prompt.error =
+                                Some(format!("Use at least {} characters", MIN_PASSPHRASE_CHARS));
+                            return Command::none();
+                        }
+                        if secret != second {
+                            prompt.error = Some("The passphrases do not match".to_string());
+                            return Command::none();
+                        }
+                        prompt.busy = true;
+                        Command::perform(
+                            async move {
+                                profile::enable(&secret, &key_path, &profile::profile_files())
+                                    .await
+                                    .map(Arc::new)
+                                    .map_err(|e| e.to_string())
+                            },
+                            Message::RecoveryKeyReady,
+                        )
+                    }
+                }
+            }
+
+            Message::CancelProfilePrompt => {
+                if self
+                    .profile_prompt
+                    .as_ref()
+                    .is_some_and(|prompt| prompt.mode == ProfilePromptMode::Enable && !prompt.busy)
+                {
+                    self.profile_prompt = None;
+                }
+                Command::none()
+            }
+
+            Message::ProfileUnlocked(result) => match result {
+                Ok(()) => {
+                    log::info!("🔓 Profile unlocked");
+                    let remember = self.profile_prompt.take().is_some_and(|prompt| {
+                        prompt.remember && prompt.mode != ProfilePromptMode::Enable
+                    });
+                    if let (true, Some(secrets), Some(key_path)) =
+                        (remember, self.secrets.clone(), profile::default_key_path())
+                    {
+                        self.runtime.spawn(async move {
+                            match profile::remember_in_keychain(&key_path, secrets.as_ref()).await {
+                                Ok(()) => log::info!(
+                                    "🔐 {} will unlock the profile at launch",
+                                    secrets.name()
+                                ),
+                                Err(e) => log::error!(
+                                    "❌ Failed to store the profile key in {}: {}",
+                                    secrets.name(),
+                                    e
+                                ),
+                            }
+                        });
+                    }
+                    if self.profile_loaded {
+                        Command::none()
+                    } else {
+                        self.load_profile()
+                    }
+                }
+                Err(e) => {
+                    log::warn!("🔐 Profile unlock failed: {}", e);
+                    if let Some(prompt) = &mut self.profile_prompt {
+                        *prompt = ProfilePrompt {
+                            error: Some(e),
+                            ..ProfilePrompt::new(prompt.mode)
+                        };
+                    }
+                    Command::none()
+                }
+            },
+
+            Message::ClearRecentHistory => {
+                let forgotten = self.history.clear_last_hours(1);
+                self.save_history();
+                log::info!("🧹 Forgot {} visits from the last hour", forgotten);
+                Command::none()
+            }
+
+            Message::LockProfile => {
+                if profile::lock() {
+                    log::info!("🔐 Profile locked; key zeroized");
+                    self.shown_recovery_key = None;
+                    self.profile_prompt = Some(ProfilePrompt::new(ProfilePromptMode::Unlock));
+                }
+                Command::none()
+            }
+
+            Message::EncryptProfile => {
+                if self.profile_prompt.is_some() {
+                    return Command::none();
+                }
+                if !profile::is_encrypted() {
+                    self.profile_prompt = Some(ProfilePrompt::new(ProfilePromptMode::Enable));
+                    return Command::none();
+                }
+                let Some(key_path) = profile::default_key_path() else {
+                    return Command::none();
+                };
+                Command::perform(
+                    async move {
+                        profile::export_recovery_key(&key_path)
+                            .await
+                            .map(Arc::new)
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::RecoveryKeyReady,
+                )
+            }
+
+            Message::RecoveryKeyReady(result) => {
+                match result {
+                    Ok(recovery) => {
+                        log::info!("🔐 Profile encrypted; recovery key ready to export");
+                        self.profile_prompt = None;
+                        self.shown_recovery_key = Some(recovery.display());
+                    }
+                    Err(e) => {
+                        log::error!("❌ Failed to set up profile encryption: {}", e);
+                        if let Some(prompt) = &mut self.profile_prompt {
+                            prompt.busy = false;
+                            prompt.error = Some(e);
+                        }
+                    }
+                }
+                Command::none()
+            }
+
+            Message::DismissRecoveryKey => {
+                self.shown_recovery_key = None;
+                Command::none()
+            }
+
+            Message::ProfileRememberToggled(remember) => {
+                if let Some(prompt) = &mut self.profile_prompt {
+                    prompt.remember = remember;
+                }
+                Command::none()
+            }
+
+            Message::ProfileKeychainChecked(unlocked) => {
+                if unlocked {
+                    return self.update(Message::ProfileUnlocked(Ok(())));
+                }
+                if let Some(prompt) = &mut self.profile_prompt {
+                    prompt.busy = false;
+                }
+                Command::none()
+            }
+        }
+    }
+
+    fn view(&self, window: window::Id) -> Element<'_, Message> {
+        if let Some(prompt) = &self.profile_prompt {
+            let keychain = self
+                .secrets
+                .as_ref()
+                .filter(|secrets| secrets.is_os_protected())
+                .map(|secrets| secrets.name());
+            return CitadelUI::profile_prompt_view(prompt, keychain);
+        }
+        if let Some(shutdown) = self
+            .shutdown
+            .as_ref()
+            .filter(|shutdown| shutdown.show_progress(std::time::Instant::now()))
+        {
+            return CitadelUI::shutdown_view(shutdown.status());
+        }
+        let browser_window = match self.windows.kind(window) {
+            Some(WindowKind::Browser(browser_window)) => browser_window,
+            Some(WindowKind::Detached(detached)) => {
+                let tab = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| tab.id == detached.tab_id);
+                return CitadelUI::detached_view(
+                    detached.mode,
+                    tab.as_ref(),
+                    self.tab_rendered.get(&detached.tab_id),
+                    self.tab_languages.get(&detached.tab_id),
+                );
+            }
+            None => {
+                return iced::widget::Space::new(iced::Length::Fill, iced::Length::Fill).into();
             }
-        }
-    }
+        };
+        let load_queue = self
+            .engine
+            .as_ref()
+            .map(|engine| engine.load_queue())
+            .unwrap_or_default();
+        let window_view = WindowView {
+            id: window,
+            tabs: browser_window.tabs(),
+            active_tab: browser_window.active_tab(),
+            focused: self.windows.focused() == window,
+            dragged_tab: self.dragged_tab,
+            bookmarked: browser_window.active_tab().is_some_and(|id| {
+                self.tab_manager
+                    .get_tab_states()
+                    .iter()
+                    .any(|tab| tab.id == id && self.bookmarks.contains(&tab.url))
+            }),
+            overlay_cleanup: browser_window.active_tab().is_some_and(|id| {
+                self.tab_manager.get_tab_states().iter().any(|tab| {
+                    tab.id == id
+                        && Url::parse(&tab.url)
+                            .is_ok_and(|url| self.overlay_cleanup.is_enabled(&url))
+                })
+            }),
+            text_only: match (&self.engine, browser_window.active_tab()) {
+                (Some(engine), Some(tab_id)) => engine.text_only().is_enabled(tab_id),
+                _ => false,
+            },
+            text_only_global: self
+                .engine
+                .as_ref()
+                .is_some_and(|engine| engine.text_only().is_global()),
+            power_saving: self.power_profile.is_power_saving(),
+            app_origin: browser_window.app_origin(),
+            hovered_link: self.hovered_link.as_deref(),
+            load_failure: browser_window
+                .active_tab()
+                .and_then(|tab_id| self.tab_load_failures.get(&tab_id).copied()),
+            truncation: browser_window
+                .active_tab()
+                .and_then(|tab_id| self.tab_truncations.get(&tab_id).copied())
+                .unwrap_or_default(),
+            escalation: browser_window
+                .active_tab()
+                .and_then(|tab_id| self.tab_escalations.get(&tab_id).copied())
+                .unwrap_or_default(),
+            load_queue: &load_queue,
+            tab_menu: self.tab_menu,
+        };
 
-    fn view(&self) -> Element<'_, Message> {
-        self.ui.view(
+        let budget_usage = self.engine.as_ref().and_then(|engine| {
+            browser_window
+                .active_tab()
+                .and_then(|tab_id| engine.budget_usage(tab_id))
+        });
+        let security_headers = browser_window
+            .active_tab()
+            .and_then(|tab_id| self.tab_security_headers.get(&tab_id));
+        // CSS errors are a devtools aid; regular builds keep the panel short
+        let css_diagnostics = browser_window
+            .active_tab()
+            .filter(|_| cfg!(feature = "devtools"))
+            .and_then(|tab_id| self.tab_render_data.get(&tab_id))
+            .map(|(_, stylesheet)| &stylesheet.diagnostics)
+            .filter(|diagnostics| !diagnostics.is_empty());
+        // Whether the active page's site is allowlisted; `None` for pages
+        // without a host
+        let site_allowlisted = browser_window.active_tab().and_then(|id| {
+            self.tab_manager
+                .get_tab_states()
+                .into_iter()
+                .find(|tab| tab.id == id)
+                .and_then(|tab| Url::parse(&tab.url).ok())
+                .and_then(|url| {
+                    url.host_str()
+                        .map(|host| self.blocklist.is_allowlisted(host))
+                })
+        });
+        let page = self.ui.view(
+            &window_view,
             &self.tab_manager,
             &self.network_config,
             &self.renderer,
             &self.viewport_info,
             self.get_active_scroll_state(),
             &self.privacy_stats,
+            &self.blocklist.stats(),
+            site_allowlisted,
+            budget_usage.as_ref(),
+            security_headers,
+            css_diagnostics,
             self.privacy_panel_expanded,
-        )
+        );
+        // The hand-off prompt shows in the window the link was followed from
+        let page = match &self.pending_external {
+            Some(url) if window_view.focused => CitadelUI::with_external_protocol_prompt(page, url),
+            _ => page,
+        };
+        let page = match &self.shown_recovery_key {
+            Some(recovery_key) if window_view.focused => {
+                CitadelUI::with_recovery_key(page, recovery_key)
+            }
+            _ => page,
+        };
+        let page = match &self.page_menu {
+            Some((tab_id, hit))
+                if window_view.focused && browser_window.active_tab() == Some(*tab_id) =>
+            {
+                let page_url = self
+                    .tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|tab| tab.id == *tab_id)
+                    .and_then(|tab| Url::parse(&tab.url).ok());
+                CitadelUI::with_page_menu(page, page_menu::entries(hit, page_url.as_ref()))
+            }
+            _ => page,
+        };
+        let page = match &self.selection_source {
+            Some(source) if window_view.focused => CitadelUI::with_selection_source(page, source),
+            _ => page,
+        };
+        let downloads = self.downloads.list();
+        let page = if window_view.focused && !downloads.is_empty() {
+            CitadelUI::with_downloads(page, &downloads)
+        } else {
+            page
+        };
+        match &self.tab_switcher {
+            Some(switcher) if window_view.focused => CitadelUI::with_tab_switcher(page, switcher),
+            _ => page,
+        }
     }
 
     fn subscription(&self) -> Subscription<Message> {
         // Use a time subscription to periodically drain the privacy event channel.
         // Iced 0.12 supports iced::time::every for periodic ticks.
-        iced::time::every(std::time::Duration::from_millis(250))
-            .map(|_| Message::DrainPrivacyEvents)
+        Subscription::batch([
+            iced::time::every(self.power_profile.limits().tick_interval)
+                .map(|_| Message::DrainPrivacyEvents),
+            iced::time::every(power_profile::PROBE_INTERVAL).map(|_| Message::ProbePower),
+            iced::time::every(filter_list::REFRESH_INTERVAL).map(|_| Message::RefreshFilterLists),
+            iced::time::every(filter_update::CHECK_INTERVAL).map(|_| Message::UpdateFilterLists),
+            // Only once a window has a screen reader adapter
+            if self.accessibility.is_empty() {
+                Subscription::none()
+            } else {
+                iced::time::every(self.power_profile.limits().tick_interval)
+                    .map(|_| Message::AccessibilityRequests)
+            },
+            // Only while something is downloading
+            if self.downloads.is_active() {
+                iced::time::every(downloads::PROGRESS_INTERVAL).map(|_| Message::DownloadsTick)
+            } else {
+                Subscription::none()
+            },
+            // Only until the window shell is first drawn
+            if self.startup.is_recorded(StartupPhase::FirstFrame) {
+                Subscription::none()
+            } else {
+                window::frames().map(|_| Message::FirstFrame)
+            },
+            // Only keys no widget captured (e.g. not typed into the address bar)
+            iced::keyboard::on_key_press(|key, modifiers| {
+                Some(Message::KeyPressed(key, modifiers))
+            }),
+            iced::event::listen_with(|event, _status| match event {
+                Event::Window(id, window::Event::Focused) => Some(Message::WindowFocused(id)),
+                Event::Window(id, window::Event::CloseRequested) => {
+                    Some(Message::WindowCloseRequested(id))
+                }
+                Event::Window(id, window::Event::FileDropped(path)) => {
+                    Some(Message::FileDropped(id, path))
+                }
+                _ => None,
+            }),
+        ])
     }
 
-    fn theme(&self) -> Theme {
+    fn theme(&self, _window: window::Id) -> Theme {
         Theme::Dark // Privacy-focused dark theme by default
     }
 }
 
 impl CitadelBrowser {
+    /// User-origin CSS for a page: element hiding first, then the user's own
+    /// stylesheet and extension CSS, so later sources win ties
+    /// Lay out the active tab's page on the host and hand its boxes to the
+    /// renderer's debugging overlay; clears the overlay when it is off
+    #[cfg(feature = "devtools")]
+    fn refresh_layout_debug(&mut self) {
+        let overlay = self
+            .layout_debug
+            .then(|| self.get_active_tab_id())
+            .flatten()
+            .and_then(|tab_id| self.tab_render_data.get(&tab_id))
+            .and_then(|(dom, stylesheet)| {
+                citadel_parser::compute_layout(
+                    dom,
+                    stylesheet,
+                    self.viewport_info.width,
+                    self.viewport_info.height,
+                )
+                .map_err(|e| log::warn!("Layout debugging: {}", e))
+                .ok()
+                .map(|layout| LayoutDebugOverlay::build(dom, stylesheet, &layout))
+            });
+        self.renderer.set_layout_debug(overlay);
+    }
+
+    /// Fetch the web fonts the renderer's page offers that it has not
+    /// loaded yet, as subresources of the tab's page
+    fn fetch_web_fonts(&self, tab_id: uuid::Uuid) -> Command<Message> {
+        let Some(engine) = &self.engine else {
+            return Command::none();
+        };
+        let Some(tab) = self
+            .tab_manager
+            .get_tab_states()
+            .into_iter()
+            .find(|tab| tab.id == tab_id)
+        else {
+            return Command::none();
+        };
+        let Ok(page_url) = Url::parse(&tab.url) else {
+            return Command::none();
+        };
+        Command::batch(
+            self.renderer
+                .pending_web_fonts()
+                .into_iter()
+                .map(|request| {
+                    let engine = engine.clone();
+                    let page_url = page_url.clone();
+                    let tab_type = tab.tab_type;
+                    Command::perform(
+                        async move {
+                            let fetched = engine
+                                .fetch_font(&request.url, &page_url, tab_id, tab_type)
+                                .await
+                                .map_err(|e| e.to_string());
+                            (request, fetched)
+                        },
+                        |(request, fetched)| Message::WebFontFetched(request, fetched),
+                    )
+                }),
+        )
+    }
+
+    /// Fetch what a download still needs through the engine, with the
+    /// cookies and network partition of the tab it was started from
+    fn run_download(&self, id: uuid::Uuid) -> Command<Message> {
+        let (Some(engine), Some(request)) = (self.engine.clone(), self.downloads.request(id))
+        else {
+            return Command::none();
+        };
+        // A closed tab's download goes on as ephemeral
+        let tab_type = self
+            .tab_manager
+            .get_tab_states()
+            .into_iter()
+            .find(|tab| tab.id == request.tab_id)
+            .map(|tab| tab.tab_type)
+            .unwrap_or(TabType::Ephemeral);
+        let downloads = self.downloads.clone();
+        Command::perform(
+            async move {
+                let body = async {
+                    engine
+                        .open_download(&request.url, request.tab_id, tab_type, &request.headers)
+                        .await
+                        .map(DownloadBody::from_response)
+                };
+                downloads
+                    .run(&request, body)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            move |result| Message::DownloadFinished(id, result),
+        )
+    }
+
+    fn fetch_images(&self, tab_id: uuid::Uuid) -> Command<Message> {
+        let Some(engine) = &self.engine else {
+            return Command::none();
+        };
+        Command::batch(self.renderer.pending_images().into_iter().map(|request| {
+            let engine = engine.clone();
+            Command::perform(
+                async move {
+                    let decoded = engine
+                        .load_image(&request.url, tab_id)
+                        .await
+                        .map_err(|e| e.to_string());
+                    (request, decoded)
+                },
+                |(request, decoded)| Message::ImageDecoded(request, decoded),
+            )
+        }))
+    }
+
+    fn user_css_for(&self, url: &str, extension_css: &str) -> String {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        [
+            self.cosmetic_filter.stylesheet_for(&host),
+            self.user_styles.css_for(url),
+            extension_css.to_string(),
+        ]
+        .into_iter()
+        .filter(|css| !css.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
     /// Render a page inside the tab's zero-knowledge boundary.
     ///
     /// Spins up an isolated renderer task bound to a fresh AES-256-GCM encrypted
     /// channel, sends ONLY the raw untrusted bytes across, and awaits the
     /// sanitized display list. The host never parses the markup for display.
+    #[cfg(feature = "zkvm-isolation")]
     async fn render_via_zkvm(
         tab_id: uuid::Uuid,
         url: String,
         raw_html: String,
         viewport_width: f32,
+        user_css: String,
+        content_scripts: Vec<String>,
+        compat_script: String,
     ) -> (uuid::Uuid, Option<citadel_tabs::RenderedContent>) {
-        use citadel_zkvm::{Channel, ChannelMessage};
+        use citadel_zkvm::{ChannelMessage, MuxChannel, StreamId, DEFAULT_STREAM_WINDOW};
 
-        let (mut host_side, vm_side) = match Channel::new() {
+        let (host_side, vm_side) = match MuxChannel::pair(DEFAULT_STREAM_WINDOW) {
             Ok(pair) => pair,
             Err(e) => {
                 log::error!("🚨 ZKVM channel creation failed for tab {}: {}", tab_id, e);
@@ -1386,6 +4015,9 @@ impl CitadelBrowser {
             viewport_width,
             // JS stays opt-in; the live render path runs static HTML for now.
             enable_scripts: false,
+            user_css,
+            content_scripts,
+            compat_script,
         };
         let params = match serde_json::to_string(&request) {
             Ok(p) => p,
@@ -1396,17 +4028,22 @@ impl CitadelBrowser {
         };
 
         if let Err(e) = host_side
-            .send(ChannelMessage::Control {
-                command: "render_page".to_string(),
-                params,
-            })
+            .send_on(
+                StreamId::Renderer,
+                ChannelMessage::Control {
+                    command: "render_page".to_string(),
+                    params,
+                },
+            )
             .await
         {
             log::error!("🚨 ZKVM boundary send failed for tab {}: {}", tab_id, e);
             return (tab_id, None);
         }
 
-        match tokio::time::timeout(std::time::Duration::from_secs(15), host_side.receive()).await {
+        // Audio state arrives on the control stream; only the page comes back here
+        let rendered = host_side.receive(StreamId::Renderer);
+        match tokio::time::timeout(std::time::Duration::from_secs(15), rendered).await {
             Ok(Ok(ChannelMessage::Control { command, params }))
                 if command == "rendered_content" =>
             {
@@ -1433,6 +4070,37 @@ impl CitadelBrowser {
         }
     }
 
+    /// Render a page with the same sanitizing renderer, but in-process: builds
+    /// without `zkvm-isolation` have no VM boundary or encrypted channel.
+    #[cfg(not(feature = "zkvm-isolation"))]
+    async fn render_in_process(
+        tab_id: uuid::Uuid,
+        url: String,
+        raw_html: String,
+        viewport_width: f32,
+        user_css: String,
+        content_scripts: Vec<String>,
+        compat_script: String,
+    ) -> (uuid::Uuid, Option<citadel_tabs::RenderedContent>) {
+        let request = citadel_tabs::RenderRequest {
+            url,
+            html: raw_html,
+            viewport_width,
+            enable_scripts: false,
+            user_css,
+            content_scripts,
+            compat_script,
+        };
+        match tokio::task::spawn_blocking(move || citadel_tabs::render_in_isolation(&request)).await
+        {
+            Ok(content) => (tab_id, Some(content)),
+            Err(e) => {
+                log::error!("🚨 In-process render failed for tab {}: {}", tab_id, e);
+                (tab_id, None)
+            }
+        }
+    }
+
     /// Validate form submission security
     fn validate_form_security(&self, submission: &FormSubmission) -> bool {
         log::info!(
@@ -1493,6 +4161,230 @@ impl CitadelBrowser {
         self.privacy_sender.clone()
     }
 
+    /// Settings for every browser window. Close requests reach `update` as
+    /// `WindowCloseRequested` instead of closing the window directly.
+    pub fn window_settings() -> window::Settings {
+        window::Settings {
+            exit_on_close_request: false,
+            ..window::Settings::default()
+        }
+    }
+
+    /// Settings for detached reader / picture-in-picture windows: small and
+    /// always on top
+    pub fn detached_window_settings() -> window::Settings {
+        window::Settings {
+            size: iced::Size::new(420.0, 320.0),
+            level: window::Level::AlwaysOnTop,
+            ..Self::window_settings()
+        }
+    }
+
+    /// Run one step of the shutdown; it reports back when finished
+    fn run_shutdown_step(&mut self, step: ShutdownStep) -> Command<Message> {
+        let done = move |_| Message::ShutdownStepFinished(step);
+        match step {
+            ShutdownStep::SavingSession => {
+                let snapshot = self.shutdown.as_mut().and_then(Shutdown::take_session);
+                Command::perform(
+                    async move {
+                        let Some(snapshot) = snapshot else {
+                            return;
+                        };
+                        match session::default_path() {
+                            Some(path) => match session::save(&snapshot, &path).await {
+                                Ok(()) => log::info!("💾 Session saved to {}", path.display()),
+                                Err(e) => log::error!("❌ Failed to save session: {}", e),
+                            },
+                            None => log::warn!("⚠️ No session location, not saving session"),
+                        }
+                    },
+                    done,
+                )
+            }
+            ShutdownStep::ClosingTabs => {
+                let tab_manager = self.tab_manager.clone();
+                Command::perform(
+                    async move {
+                        match tab_manager.shutdown(shutdown::TAB_SHUTDOWN_TIMEOUT).await {
+                            Ok(report) if report.timed_out.is_empty() => {
+                                log::info!("🛑 Terminated {} tabs", report.terminated)
+                            }
+                            Ok(report) => log::warn!(
+                                "⚠️ Terminated {} tabs, {} did not stop in time",
+                                report.terminated,
+                                report.timed_out.len()
+                            ),
+                            Err(e) => log::error!("❌ Failed to shut down tabs: {}", e),
+                        }
+                    },
+                    done,
+                )
+            }
+            ShutdownStep::ClosingConnections => {
+                let engine = self.engine.clone();
+                Command::perform(
+                    async move {
+                        let Some(engine) = engine else {
+                            return;
+                        };
+                        if !engine
+                            .close_connections(shutdown::NETWORK_SHUTDOWN_TIMEOUT)
+                            .await
+                        {
+                            log::warn!("⚠️ Page loads still running at exit");
+                        }
+                        if let Some(path) = cookies::default_path() {
+                            if let Err(e) = cookies::save(engine.cookie_jar(), &path).await {
+                                log::error!("❌ Failed to save cookies: {}", e);
+                            }
+                        }
+                    },
+                    done,
+                )
+            }
+        }
+    }
+
+    /// Forget the host-side state kept for a tab; returns the command closing
+    /// any windows detached from it
+    fn forget_tab(&mut self, tab_id: uuid::Uuid) -> Command<Message> {
+        self.error_states.remove(&tab_id);
+        self.tab_load_failures.remove(&tab_id);
+        self.loading_states.remove(&tab_id);
+        self.tab_render_data.remove(&tab_id);
+        self.tab_rendered.remove(&tab_id);
+        self.tab_history.remove(&tab_id);
+        self.tab_scroll_states.remove(&tab_id);
+        self.tab_zoom_levels.remove(&tab_id);
+        self.tab_security_headers.remove(&tab_id);
+        self.tab_languages.remove(&tab_id);
+        self.tab_truncations.remove(&tab_id);
+        self.tab_escalations.remove(&tab_id);
+        self.tab_text_blocks.remove(&tab_id);
+        self.tab_images.remove(&tab_id);
+        self.tab_load_started.remove(&tab_id);
+        self.performance_monitor.remove_tab(&tab_id);
+        if let Some(engine) = &self.engine {
+            engine.release_tab_budget(tab_id);
+            engine.release_tab_sessions(tab_id);
+        }
+        Command::batch(
+            self.windows
+                .close_detached_for(tab_id)
+                .into_iter()
+                .map(window::close),
+        )
+    }
+
+    /// Make `id` the focused window and point screen readers at its page.
+    /// Returns the window's selected tab.
+    fn focus_window(&mut self, id: window::Id) -> Option<uuid::Uuid> {
+        let tab_id = self.windows.set_focused(id);
+        let adapter = self.accessibility.focus(self.windows.focused());
+        self.renderer.attach_accessibility(adapter);
+        tab_id
+    }
+
+    /// Ask for a new browser window's native view, which its screen reader
+    /// adapter attaches to
+    #[cfg(target_os = "macos")]
+    fn open_accessibility(id: window::Id) -> Command<Message> {
+        window::run_with_handle(id, move |handle| {
+            Message::AccessibilityReady(id, NativeView::of(handle))
+        })
+    }
+
+    /// Give a new browser window its screen reader adapter, which needs no
+    /// native view here
+    #[cfg(not(target_os = "macos"))]
+    fn open_accessibility(id: window::Id) -> Command<Message> {
+        Command::perform(async {}, move |_| {
+            Message::AccessibilityReady(id, NativeView::default())
+        })
+    }
+
+    /// Focus or click the page element a screen reader picked
+    fn handle_accessibility_request(&mut self, request: AdapterRequest) -> Command<Message> {
+        let request = match request {
+            AdapterRequest::InitialTree => {
+                self.focus_window(self.windows.focused());
+                return Command::none();
+            }
+            AdapterRequest::Action(request) => request,
+        };
+        let Ok(id) = u32::try_from(request.target_node.0) else {
+            return Command::none();
+        };
+        match request.action {
+            Action::Focus => match self.renderer.focus_element(id) {
+                Some(y) => {
+                    let x = self
+                        .get_active_tab_id()
+                        .and_then(|id| self.tab_scroll_states.get(&id))
+                        .map(|state| state.x)
+                        .unwrap_or(0.0);
+                    self.update(Message::ScrollTo { x, y })
+                }
+                None => Command::none(),
+            },
+            Action::Click => match self.renderer.focus_element(id) {
+                Some(_) => self.update(Message::ActivateFocus),
+                None => Command::none(),
+            },
+            _ => Command::none(),
+        }
+    }
+
+    /// Move a tab into another window's strip and focus it there. A window
+    /// left without tabs is closed.
+    fn move_tab_to_window(&mut self, tab_id: uuid::Uuid, target: window::Id) -> Command<Message> {
+        let Some(source) = self.windows.move_tab(tab_id, target) else {
+            return Command::none();
+        };
+        log::info!("🪟 Moved tab {} to window {:?}", tab_id, target);
+
+        let mut commands = vec![window::gain_focus(target)];
+        self.focus_window(target);
+        commands.push(self.update(Message::SwitchTab(tab_id)));
+        if self
+            .windows
+            .get(source)
+            .is_some_and(|w| w.tabs().is_empty())
+        {
+            self.windows.close(source);
+            self.accessibility.close(source);
+            commands.push(window::close(source));
+        }
+        Command::batch(commands)
+    }
+
+    /// Drop host-side render caches of tabs the tab manager expired, so
+    /// nothing of an expired page outlives its zeroized VM
+    fn purge_expired_tabs(&mut self) {
+        for tab in self.tab_manager.get_tab_states() {
+            if !matches!(tab.content, PageContent::Expired { .. }) {
+                continue;
+            }
+            self.tab_rendered.remove(&tab.id);
+            self.tab_render_data.remove(&tab.id);
+            self.tab_scroll_states.remove(&tab.id);
+            self.tab_security_headers.remove(&tab.id);
+            self.tab_languages.remove(&tab.id);
+            self.tab_truncations.remove(&tab.id);
+            self.tab_escalations.remove(&tab.id);
+            self.tab_text_blocks.remove(&tab.id);
+            self.tab_images.remove(&tab.id);
+            self.loading_states.remove(&tab.id);
+            self.error_states.remove(&tab.id);
+            self.tab_load_failures.remove(&tab.id);
+            if let Some(engine) = &self.engine {
+                engine.release_tab_budget(tab.id);
+                engine.release_tab_sessions(tab.id);
+            }
+        }
+    }
+
     /// Get the active tab ID
     fn get_active_tab_id(&self) -> Option<uuid::Uuid> {
         self.tab_manager
@@ -1565,14 +4457,63 @@ impl CitadelBrowser {
         }
     }
 
-    /// Handle keyboard shortcuts for scrolling and zoom
-    #[allow(dead_code)] // Will be used when implementing keyboard navigation
+    /// Carry out `action` in the active tab's image viewer, scrolling the
+    /// view if it moved. `None` when the tab does not show an image.
+    fn image_viewer_action(&mut self, action: ViewerAction) -> Option<Command<Message>> {
+        let tab_id = self.get_active_tab_id()?;
+        let viewer = self.tab_images.get_mut(&tab_id)?;
+        let area = image_viewer::image_area((self.viewport_info.width, self.viewport_info.height));
+        let moved = viewer.apply(action, area);
+        self.renderer.set_image_viewer(viewer.clone());
+        Some(match moved {
+            Some((x, y)) => iced::widget::scrollable::scroll_to(
+                image_viewer::scroll_id(),
+                iced::widget::scrollable::AbsoluteOffset { x, y },
+            ),
+            None => Command::none(),
+        })
+    }
+
+    /// Handle keyboard shortcuts for scrolling, zoom and focus navigation
     pub fn handle_keyboard_event(
         &mut self,
         key: &iced::keyboard::Key,
         modifiers: iced::keyboard::Modifiers,
     ) -> Command<Message> {
         match (key.as_ref(), modifiers.control()) {
+            // Panic button
+            (Key::Named(iced::keyboard::key::Named::Delete), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::Panic)
+            }
+
+            // Profile encryption
+            (Key::Character("l") | Key::Character("L"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::LockProfile)
+            }
+            (Key::Character("e") | Key::Character("E"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::EncryptProfile)
+            }
+
+            (Key::Character("h") | Key::Character("H"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::ClearRecentHistory)
+            }
+
+            // Tab switcher
+            (Key::Character("a") | Key::Character("A"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::ToggleTabSwitcher)
+            }
+
+            // Text-only mode for every tab
+            (Key::Character("x") | Key::Character("X"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::ToggleGlobalTextOnly)
+            }
+
+            // Layout debugging overlay
+            #[cfg(feature = "devtools")]
+            (Key::Character("d") | Key::Character("D"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::ToggleLayoutDebug)
+            }
+
             // Zoom shortcuts
             (Key::Character("=") | Key::Character("+"), true) => {
                 Command::perform(async {}, |_| Message::ZoomIn)
@@ -1580,6 +4521,22 @@ impl CitadelBrowser {
             (Key::Character("-"), true) => Command::perform(async {}, |_| Message::ZoomOut),
             (Key::Character("0"), true) => Command::perform(async {}, |_| Message::ZoomReset),
 
+            // Window shortcuts
+            (Key::Character("n"), true) => Command::perform(async {}, |_| Message::NewWindow),
+
+            // Copy the page URL, scrubbed of tracking parameters
+            (Key::Character("c") | Key::Character("C"), true) if modifiers.shift() => {
+                Command::perform(async {}, |_| Message::CopyPageUrl)
+            }
+
+            // Save the page as a download
+            (Key::Character("s") | Key::Character("S"), true) => {
+                Command::perform(async {}, |_| Message::SavePage)
+            }
+
+            // Paste into the page (text fields capture their own Ctrl+V)
+            (Key::Character("v"), true) => Command::perform(async {}, |_| Message::PasteIntoPage),
+
             // Scroll shortcuts
             (Key::Named(iced::keyboard::key::Named::ArrowUp), false) => {
                 Command::perform(async {}, |_| Message::ScrollUp)
@@ -1606,12 +4563,321 @@ impl CitadelBrowser {
                 Command::perform(async {}, |_| Message::End)
             }
 
+            // Focus navigation
+            (Key::Named(iced::keyboard::key::Named::Tab), false) => {
+                if modifiers.shift() {
+                    Command::perform(async {}, |_| Message::FocusPrevious)
+                } else {
+                    Command::perform(async {}, |_| Message::FocusNext)
+                }
+            }
+            (Key::Named(iced::keyboard::key::Named::Enter), false) => {
+                Command::perform(async {}, |_| Message::ActivateFocus)
+            }
+
+            _ => Command::none(),
+        }
+    }
+
+    /// Keys while the tab switcher is open: arrows move the highlight,
+    /// Escape and Ctrl+Shift+A close it; typing goes to its query field
+    fn handle_tab_switcher_key(
+        &mut self,
+        key: &iced::keyboard::Key,
+        modifiers: iced::keyboard::Modifiers,
+    ) -> Command<Message> {
+        use iced::keyboard::key::Named;
+        match key.as_ref() {
+            Key::Named(Named::ArrowUp) => self.update(Message::TabSwitcherMove(-1)),
+            Key::Named(Named::ArrowDown) => self.update(Message::TabSwitcherMove(1)),
+            Key::Named(Named::Escape) => {
+                self.tab_switcher = None;
+                Command::none()
+            }
+            Key::Character("a") | Key::Character("A")
+                if modifiers.control() && modifiers.shift() =>
+            {
+                self.update(Message::ToggleTabSwitcher)
+            }
             _ => Command::none(),
         }
     }
 
-    /// Normalize and validate URLs with security considerations
+    /// Write the history out in the background
+    fn save_history(&self) {
+        let history = self.history.clone();
+        self.runtime.spawn(async move {
+            if let Err(e) = history.save().await {
+                log::warn!("Failed to save history: {}", e);
+            }
+        });
+    }
+
+    /// Read the profile's settings and start the engine. Runs at startup,
+    /// or after the first unlock for an encrypted profile.
+    fn load_profile(&mut self) -> Command<Message> {
+        self.profile_loaded = true;
+
+        // Settings are read once; a broken file falls back to the defaults
+        self.user_styles = user_styles::default_path()
+            .map(|path| {
+                UserStylesheets::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring user stylesheets at {}: {}", path.display(), e);
+                    UserStylesheets::default()
+                })
+            })
+            .unwrap_or_default();
+        self.external_protocols = external_protocols::default_path()
+            .map(|path| {
+                ExternalProtocolPrefs::load(&path).unwrap_or_else(|e| {
+                    log::warn!(
+                        "Ignoring external protocol choices at {}: {}",
+                        path.display(),
+                        e
+                    );
+                    ExternalProtocolPrefs::default()
+                })
+            })
+            .unwrap_or_default();
+        let filter_allowlist = filter_allowlist::default_path()
+            .map(|path| {
+                FilterAllowlist::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring filter allowlist at {}: {}", path.display(), e);
+                    FilterAllowlist::default()
+                })
+            })
+            .unwrap_or_default();
+        self.blocklist.set_allowlist(filter_allowlist.sites);
+        self.overlay_cleanup = overlay_cleanup::default_path()
+            .map(|path| {
+                OverlayCleanup::load(&path).unwrap_or_else(|e| {
+                    log::warn!(
+                        "Ignoring overlay cleanup choices at {}: {}",
+                        path.display(),
+                        e
+                    );
+                    OverlayCleanup::default()
+                })
+            })
+            .unwrap_or_default();
+        self.panic_options = panic::default_path()
+            .map(|path| {
+                PanicOptions::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring panic settings at {}: {}", path.display(), e);
+                    PanicOptions::default()
+                })
+            })
+            .unwrap_or_default();
+        // History is read once the profile is readable, and the omnibox
+        // searches it from then on
+        if let (Some(path), Some(secrets)) = (history::default_path(), self.secrets.clone()) {
+            match HistoryManager::load(&path, secrets.as_ref()) {
+                Ok(history) => {
+                    self.history = history;
+                    self.suggestions =
+                        SuggestionEngine::local(self.history.clone(), self.bookmarks.clone());
+                }
+                Err(e) => log::warn!("Ignoring history at {}: {}", path.display(), e),
+            }
+        }
+        self.clipboard_policy = clipboard::default_path()
+            .map(|path| {
+                ClipboardPolicy::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring clipboard settings at {}: {}", path.display(), e);
+                    ClipboardPolicy::default()
+                })
+            })
+            .unwrap_or_default();
+        // The memory profile is picked once, from the settings or the
+        // system's memory
+        let memory_settings = memory_profile::default_path()
+            .map(|path| {
+                MemorySettings::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring memory settings at {}: {}", path.display(), e);
+                    MemorySettings::default()
+                })
+            })
+            .unwrap_or_default();
+        let memory_profile = memory_settings.profile(memory_profile::system_memory());
+        let memory_limits = memory_profile.limits();
+        log::info!("🧠 Running with the {:?} memory profile", memory_profile);
+        self.renderer
+            .set_widget_cache_size(memory_limits.widget_cache_entries);
+        self.renderer
+            .set_frame_batching(memory_limits.frame_batching);
+        let power_profile = PowerProfile::for_source(power_profile::probe());
+        log::info!("🔋 Running with the {:?} power profile", power_profile);
+        let apply_power_profile = self.apply_power_profile(power_profile);
+
+        // Initialize browser engine asynchronously with detailed error handling
+        let runtime = self.runtime.clone();
+        let network_config = self.network_config.clone();
+        let security_context = self.security_context.clone();
+        let settings = self.settings.clone();
+        let tab_manager = self.tab_manager.clone();
+        #[cfg(feature = "zkvm-isolation")]
+        let vm_policy = VmPolicy::from_security_context(&self.security_context);
+        #[cfg(feature = "zkvm-isolation")]
+        let vm_reuse = memory_settings.vm_reuse;
+        let privacy_sender = self.privacy_sender.clone();
+        let blocklist = self.blocklist.clone();
+        let secrets = self.secrets.clone();
+        let initialize_engine = Command::perform(
+            async move {
+                // Tab VMs enforce the browser's security settings themselves
+                #[cfg(feature = "zkvm-isolation")]
+                {
+                    if let Err(e) = tab_manager.set_vm_policy(vm_policy).await {
+                        log::warn!("Tab VMs will run on the default policy: {}", e);
+                    }
+                    if let Err(e) = tab_manager.set_vm_reuse(vm_reuse).await {
+                        log::warn!("Every tab will get a VM of its own: {}", e);
+                    }
+                }
+                if let Err(e) = tab_manager
+                    .set_expiry_policy(memory_limits.expiry_policy())
+                    .await
+                {
+                    log::warn!("Idle tabs will expire on the default timeout: {}", e);
+                }
+                let mut engine =
+                    BrowserEngine::new(runtime, network_config, security_context.clone())
+                        .await
+                        .map(|engine| {
+                            let engine = engine
+                                .with_settings(settings)
+                                .with_memory_limits(&memory_limits);
+                            match resource_caches::open_disk_storage(secrets.as_deref()) {
+                                Some(storage) => engine.with_cache_storage(storage),
+                                None => engine,
+                            }
+                        })?;
+                if let Some(path) = cookies::default_path() {
+                    match cookies::load(&path) {
+                        Ok(saved) => engine.cookie_jar().restore(saved),
+                        Err(e) => log::warn!("Starting without saved cookies: {}", e),
+                    }
+                }
+                // Tab VMs fetch through the host, under the engine's container
+                // policies and into its cookie jar
+                match engine.tab_resource_manager().await {
+                    Ok(manager) => {
+                        let manager = manager.with_privacy_sender(privacy_sender);
+                        manager.add_interceptor(Arc::new(blocklist));
+                        let manager = Arc::new(manager);
+                        #[cfg(feature = "zkvm-isolation")]
+                        if let Err(e) = tab_manager
+                            .set_resource_broker(ResourceBroker::new(manager.clone()))
+                            .await
+                        {
+                            log::warn!("Tab resource requests will go unanswered: {}", e);
+                        }
+                        // Page images load through the same manager, under
+                        // each tab's CSP and request budget
+                        engine = engine.with_image_loader(ResourceLoader::from_manager(
+                            manager,
+                            security_context,
+                        ));
+                    }
+                    Err(e) => log::warn!("Tab resource requests will go unanswered: {}", e),
+                }
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(engine)
+            },
+            |result| match result {
+                Ok(engine) => {
+                    log::info!("✅ Browser engine initialized successfully");
+                    Message::EngineInitialized(engine)
+                }
+                Err(e) => {
+                    log::error!("❌ Engine initialization failed: {}", e);
+                    Message::InitializationError(format!("Failed to initialize engine: {}", e))
+                }
+            },
+        );
+        self.record_startup(StartupPhase::Profile);
+        Command::batch([apply_power_profile, initialize_engine])
+    }
+
+    /// Record that a startup phase finished, reporting the timings once
+    /// every phase has
+    fn record_startup(&mut self, phase: StartupPhase) {
+        if self.startup.record(phase) {
+            self.startup.report();
+        }
+    }
+
+    /// Start warming the tab VM pool, so new tabs get ready VMs from here on
+    #[cfg(feature = "zkvm-isolation")]
+    fn warm_vm_pool(&self) -> Command<Message> {
+        let tab_manager = self.tab_manager.clone();
+        Command::perform(
+            async move { tab_manager.warm_vm_pool().await.map_err(|e| e.to_string()) },
+            Message::VmPoolWarming,
+        )
+    }
+
+    /// Builds without `zkvm-isolation` have no tab VMs to warm
+    #[cfg(not(feature = "zkvm-isolation"))]
+    fn warm_vm_pool(&self) -> Command<Message> {
+        Command::none()
+    }
+
+    /// Run under `profile`: the renderer's layout pace here, and the
+    /// background tabs' budget in the tab manager
+    fn apply_power_profile(&mut self, profile: PowerProfile) -> Command<Message> {
+        self.power_profile = profile;
+        self.renderer
+            .set_layout_interval(profile.limits().layout_interval);
+        self.performance_monitor.set_power_profile(profile);
+        if let Some(engine) = &self.engine {
+            engine.set_power_saving(profile.is_power_saving());
+        }
+        let tab_manager = self.tab_manager.clone();
+        Command::perform(
+            async move {
+                tab_manager
+                    .set_power_saving(profile.is_power_saving())
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            Message::PowerProfileApplied,
+        )
+    }
+
+    /// Open a browser window with a new ephemeral tab
+    fn open_window(&mut self, initial_url: Option<String>) -> Command<Message> {
+        let (id, spawn) = window::spawn(Self::window_settings());
+        log::info!("🪟 Opening window {:?}", id);
+        self.windows.open(id);
+        Command::batch([
+            spawn,
+            Self::open_accessibility(id),
+            self.update(Message::NewTab {
+                tab_type: TabType::Ephemeral,
+                initial_url,
+            }),
+        ])
+    }
+
+    /// Hand a confirmed external-scheme link to the OS default handler
+    fn open_externally(&self, url: &Url) {
+        match external_protocols::open_with_system(url) {
+            Ok(()) => log::info!("🔗 Opened {}: link with the system handler", url.scheme()),
+            Err(e) => log::error!("❌ Failed to open {}: link: {}", url.scheme(), e),
+        }
+    }
+
+    /// Normalize and validate URLs with security considerations. The result is
+    /// canonicalized so history and the cache see one spelling per page.
     fn normalize_url(&self, url_str: &str) -> String {
+        let url = self.expand_omnibox_input(url_str);
+        citadel_networking::canonicalize_str(&url)
+            .map(String::from)
+            .unwrap_or(url)
+    }
+
+    /// Turn omnibox input into an absolute URL (search, file path or domain)
+    fn expand_omnibox_input(&self, url_str: &str) -> String {
         let trimmed = url_str.trim();
 
         if trimmed.is_empty() {
@@ -1622,7 +4888,9 @@ impl CitadelBrowser {
         if trimmed.starts_with("http://")
             || trimmed.starts_with("https://")
             || trimmed.starts_with("about:")
+            || trimmed.starts_with("citadel://")
             || trimmed.starts_with("file://")
+            || external_protocols::is_external_url(trimmed)
         {
             return trimmed.to_string();
         }
@@ -1636,7 +4904,7 @@ impl CitadelBrowser {
 
         // If not a file, treat as a search query or domain.
         if !trimmed.contains('.') && !trimmed.contains('/') {
-            return format!("https://duckduckgo.com/?q={}", urlencoding::encode(trimmed));
+            return search_url(trimmed);
         }
 
         // Default to https for things that look like domains.
@@ -1685,6 +4953,8 @@ impl CitadelBrowser {
             .retain(|tab_id, _| active_tabs.contains(tab_id));
         self.error_states
             .retain(|tab_id, _| active_tabs.contains(tab_id));
+        self.tab_load_failures
+            .retain(|tab_id, _| active_tabs.contains(tab_id));
         self.loading_states
             .retain(|tab_id, _| active_tabs.contains(tab_id));
 
@@ -1797,6 +5067,52 @@ impl CitadelBrowser {
     }
 }
 
+/// Load new and changed filter lists from disk, through the compiled list
+/// cache; returns whether any list changed
+fn refresh_filter_lists(filter_lists: &FilterLists) -> bool {
+    let (Some(dir), Some(cache_dir)) =
+        (filter_list::default_dir(), filter_list::default_cache_dir())
+    else {
+        return false;
+    };
+    filter_lists.refresh(&dir, &FilterListCache::new(cache_dir))
+}
+
+/// The filter list updater, when the settings file enables it. Settings that
+/// cannot be read leave updates off.
+fn filter_updater(proxy: Option<SocksProxy>) -> Option<FilterUpdater> {
+    let path = filter_update::default_settings_path()?;
+    let settings = match profile::read(&path) {
+        Ok(bytes) => match UpdateSettings::parse(&bytes) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("Ignoring filter update settings {}: {}", path.display(), e);
+                return None;
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    if !settings.enabled {
+        return None;
+    }
+    let (lists_dir, versions_dir) = (
+        filter_list::default_dir()?,
+        filter_update::default_versions_dir()?,
+    );
+    Some(FilterUpdater::new(settings, lists_dir, versions_dir).with_proxy(proxy))
+}
+
+/// The built-in element-hiding rules and those of the installed lists
+fn cosmetic_filter(filter_lists: &FilterLists) -> CosmeticFilter {
+    let mut filter = CosmeticFilter::builtin();
+    filter_lists.add_cosmetic_rules(&mut filter);
+    filter
+}
+
 /// Extract title from HTML content (utility function)
 #[allow(dead_code)] // Will be used when implementing HTML title extraction
 fn extract_title(html: &str) -> Option<String> {
@@ -0,0 +1,296 @@
+//! Icons for desktop integration
+//!
+//! An installed web app shows up in the desktop's launcher and its window
+//! with the site's own icon: the largest raster icon its manifest lists,
+//! or else the page's favicon. The fetched file is decoded within small
+//! limits, padded square and resized to each size the desktop asks for.
+//! Only the pixels are kept, so whatever metadata the file carried is not
+//! written back out; the PNGs installed hold nothing but the image.
+//!
+//! Icons are installed into the user's hicolor icon theme under the app's
+//! id (see [`web_app::app_id`]), which is also the name its desktop entry
+//! and app-mode window look them up by.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_parser::Dom;
+use iced::window;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbaImage};
+use url::Url;
+
+use crate::image_decoder::{self, DecodeLimits, DecodedImage};
+use crate::web_app::{self, ManifestIcon, WebAppManifest};
+
+/// Square sizes icons are made at, smallest first
+pub const ICON_SIZES: &[u32] = &[16, 32, 48, 64, 128, 256];
+
+/// Size of the icon windows are shown with
+const WINDOW_ICON_SIZE: u32 = 64;
+
+/// Formats icons are read in
+const ICON_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Ico,
+    ImageFormat::WebP,
+    ImageFormat::Jpeg,
+];
+
+/// Limits on a fetched icon file
+pub const ICON_LIMITS: DecodeLimits = DecodeLimits {
+    max_bytes: 1024 * 1024,
+    max_width: 1024,
+    max_height: 1024,
+    max_pixels: 1024 * 1024,
+};
+
+/// The icon to make an app's icons from: the manifest's raster icon
+/// nearest the largest size, else the page's favicon
+pub fn icon_source(manifest: &WebAppManifest, favicon: Option<Url>) -> Option<Url> {
+    let largest = ICON_SIZES[ICON_SIZES.len() - 1];
+    manifest
+        .best_icon(largest)
+        .filter(|icon| is_raster(icon))
+        .or_else(|| manifest.icons.iter().find(|icon| is_raster(icon)))
+        .map(|icon| icon.src.clone())
+        .or(favicon)
+}
+
+/// Scalable icons would need an SVG renderer, which icons do not get
+fn is_raster(icon: &ManifestIcon) -> bool {
+    let svg = icon
+        .mime_type
+        .as_deref()
+        .is_some_and(|mime| mime.contains("svg"))
+        || icon.src.path().to_ascii_lowercase().ends_with(".svg");
+    !svg && !icon.sizes.contains(&None)
+}
+
+/// The favicon a page links with `<link rel="icon">`, the largest one if
+/// it lists several, else `/favicon.ico` on its origin. Onion pages get
+/// none: a favicon is a load the user did not ask for.
+pub fn favicon_link(dom: &Dom, page_url: &Url) -> Option<Url> {
+    if !citadel_networking::proxy::speculative_loads_allowed(page_url) {
+        return None;
+    }
+    dom.get_elements_by_tag_name("link")
+        .into_iter()
+        .filter_map(|handle| {
+            let node = handle.read().ok()?;
+            let element = node.as_element()?;
+            let rel = element.get_attribute("rel")?;
+            if !rel
+                .split_ascii_whitespace()
+                .any(|token| token.eq_ignore_ascii_case("icon"))
+            {
+                return None;
+            }
+            let size = element
+                .get_attribute("sizes")
+                .and_then(|sizes| {
+                    sizes
+                        .split_ascii_whitespace()
+                        .filter_map(|size| size.split_once(['x', 'X'])?.0.parse::<u32>().ok())
+                        .max()
+                })
+                .unwrap_or(0);
+            let url = dom.resolve_url(page_url, &element.get_attribute("href")?)?;
+            Some((size, url))
+        })
+        .max_by_key(|(size, _)| *size)
+        .map(|(_, url)| url)
+        .or_else(|| page_url.join("/favicon.ico").ok())
+        .filter(|url| matches!(url.scheme(), "https" | "http"))
+}
+
+/// An icon made at each of [`ICON_SIZES`]
+#[derive(Clone)]
+pub struct IconSet {
+    /// One image per size, in the order of [`ICON_SIZES`]
+    images: Vec<DecodedImage>,
+}
+
+impl std::fmt::Debug for IconSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IconSet")
+            .field("sizes", &ICON_SIZES)
+            .finish()
+    }
+}
+
+impl IconSet {
+    /// Make the icons from a fetched icon file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CitadelError> {
+        let decoded = image_decoder::decode_formats(bytes, &ICON_LIMITS, ICON_FORMATS)?;
+        let source = RgbaImage::from_raw(decoded.width, decoded.height, decoded.pixels)
+            .ok_or_else(|| {
+                CitadelError::new(ErrorKind::Content, "ICON_INVALID", "truncated pixels")
+            })?;
+
+        // Pad to a square, centered, so resizing does not stretch it
+        let side = source.width().max(source.height());
+        let mut square = RgbaImage::new(side, side);
+        imageops::overlay(
+            &mut square,
+            &source,
+            i64::from((side - source.width()) / 2),
+            i64::from((side - source.height()) / 2),
+        );
+
+        let images = ICON_SIZES
+            .iter()
+            .map(|&size| {
+                let resized = imageops::resize(&square, size, size, FilterType::Lanczos3);
+                DecodedImage {
+                    width: size,
+                    height: size,
+                    pixels: resized.into_raw(),
+                }
+            })
+            .collect();
+        Ok(Self { images })
+    }
+
+    /// The icon at `size`, one of [`ICON_SIZES`]
+    pub fn get(&self, size: u32) -> Option<&DecodedImage> {
+        ICON_SIZES
+            .iter()
+            .position(|&known| known == size)
+            .map(|at| &self.images[at])
+    }
+
+    /// The icon at `size` encoded as a PNG, with no metadata chunks
+    pub fn png(&self, size: u32) -> Option<Vec<u8>> {
+        let image = self.get(size)?;
+        let mut bytes = Cursor::new(Vec::new());
+        RgbaImage::from_raw(image.width, image.height, image.pixels.clone())?
+            .write_to(&mut bytes, ImageFormat::Png)
+            .ok()?;
+        Some(bytes.into_inner())
+    }
+
+    /// The icon windows are shown with
+    pub fn window_icon(&self) -> Option<window::Icon> {
+        let image = self.get(WINDOW_ICON_SIZE)?;
+        window::icon::from_rgba(image.pixels.clone(), image.width, image.height).ok()
+    }
+}
+
+/// The user's hicolor icon theme, under the XDG data directory
+pub fn icons_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("icons").join("hicolor"))
+}
+
+fn icon_path(dir: &Path, name: &str, size: u32) -> PathBuf {
+    dir.join(format!("{size}x{size}"))
+        .join("apps")
+        .join(format!("{}.png", name))
+}
+
+/// Install the icons into the icon theme at `dir` as `name`, replacing any
+/// installed earlier
+pub async fn install_icons(icons: &IconSet, name: &str, dir: &Path) -> std::io::Result<()> {
+    for &size in ICON_SIZES {
+        let Some(png) = icons.png(size) else {
+            continue;
+        };
+        let path = icon_path(dir, name, size);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, png).await?;
+    }
+    Ok(())
+}
+
+/// The window icon of an installed app, read back from the icon theme
+pub fn installed_window_icon(start_url: &Url) -> Option<window::Icon> {
+    let path = icon_path(&icons_dir()?, &web_app::app_id(start_url), WINDOW_ICON_SIZE);
+    let bytes = std::fs::read(path).ok()?;
+    let image = image_decoder::decode_formats(&bytes, &ICON_LIMITS, &[ImageFormat::Png]).ok()?;
+    window::icon::from_rgba(image.pixels, image.width, image.height).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, image::Rgba([0, 120, 240, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_icons_are_made_square_at_every_size() {
+        let icons = IconSet::from_bytes(&png_bytes(40, 20)).unwrap();
+        for &size in ICON_SIZES {
+            let image = icons.get(size).unwrap();
+            assert_eq!((image.width, image.height), (size, size));
+        }
+        // Padding above and below the wide source stays transparent
+        let image = icons.get(16).unwrap();
+        assert_eq!(image.pixels[3], 0);
+        let middle = ((8 * 16 + 8) * 4) as usize;
+        assert_eq!(image.pixels[middle + 3], 255);
+
+        let png = icons.png(32).unwrap();
+        let reread = image_decoder::decode(&png, &DecodeLimits::default()).unwrap();
+        assert_eq!((reread.width, reread.height), (32, 32));
+        assert!(icons.window_icon().is_some());
+
+        assert!(IconSet::from_bytes(&png_bytes(2048, 16)).is_err());
+        assert!(IconSet::from_bytes(b"<svg/>").is_err());
+    }
+
+    #[test]
+    fn test_icon_sources() {
+        let page = Url::parse("https://notes.example/app/").unwrap();
+        let dom = citadel_parser::parse_html(
+            "<html><head>\
+             <link rel=\"icon\" href=\"/small.png\" sizes=\"16x16\">\
+             <link rel=\"shortcut icon\" href=\"/large.png\" sizes=\"32x32 192x192\">\
+             </head></html>",
+            std::sync::Arc::new(citadel_parser::security::SecurityContext::new(10)),
+        )
+        .unwrap();
+        let favicon = favicon_link(&dom, &page).unwrap();
+        assert_eq!(favicon.as_str(), "https://notes.example/large.png");
+
+        let bare = citadel_parser::parse_html(
+            "<html></html>",
+            std::sync::Arc::new(citadel_parser::security::SecurityContext::new(10)),
+        )
+        .unwrap();
+        assert_eq!(
+            favicon_link(&bare, &page).unwrap().as_str(),
+            "https://notes.example/favicon.ico"
+        );
+        let onion = Url::parse("http://notesxyz.onion/app/").unwrap();
+        assert!(favicon_link(&dom, &onion).is_none());
+
+        let manifest = WebAppManifest::parse(
+            r#"{"name": "Notes", "icons": [
+                {"src": "logo.svg", "sizes": "any", "type": "image/svg+xml"},
+                {"src": "logo-96.png", "sizes": "96x96"}
+            ]}"#,
+            &Url::parse("https://notes.example/manifest.json").unwrap(),
+            &page,
+        )
+        .unwrap();
+        assert_eq!(
+            icon_source(&manifest, Some(favicon.clone()))
+                .unwrap()
+                .as_str(),
+            "https://notes.example/logo-96.png"
+        );
+        let plain = WebAppManifest::parse(r#"{"name": "Notes"}"#, &page, &page).unwrap();
+        assert_eq!(icon_source(&plain, Some(favicon.clone())), Some(favicon));
+    }
+}
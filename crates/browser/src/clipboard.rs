@@ -0,0 +1,226 @@
+//! Clipboard hygiene
+//!
+//! Everything Citadel copies goes through [`ClipboardPolicy::prepare`]:
+//!
+//! - Copied URLs lose their tracking parameters, so a shared link does not
+//!   carry `utm_*`, `fbclid` and the like (see
+//!   `citadel_networking::url_canon::strip_tracking_params`).
+//! - Secrets, such as values of password-type fields, are cleared from the
+//!   clipboard after a delay, but only if the clipboard still holds them; a
+//!   newer copy by the user is left alone.
+//!
+//! Both behaviors are on by default and can be changed in the settings file.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::profile;
+
+/// Environment variable overriding where clipboard settings are read from
+pub const CLIPBOARD_FILE_ENV: &str = "CITADEL_CLIPBOARD_FILE";
+
+/// Default delay before a copied secret is cleared
+const DEFAULT_CLEAR_SECS: u64 = 30;
+
+/// What is being copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyKind {
+    /// A page or link URL
+    Url,
+    /// Ordinary text
+    Text,
+    /// A secret, cleared after a delay
+    Secret,
+}
+
+impl CopyKind {
+    /// Kind of a value copied from a form field of `input_type`
+    pub fn for_input_type(input_type: &str) -> Self {
+        if input_type.eq_ignore_ascii_case("password") {
+            Self::Secret
+        } else {
+            Self::Text
+        }
+    }
+}
+
+/// How copies are cleaned up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardPolicy {
+    /// Strip tracking parameters from copied URLs
+    #[serde(default = "default_scrub")]
+    pub scrub_tracking_params: bool,
+    /// Seconds until a copied secret is cleared; `None` never clears it
+    #[serde(default = "default_clear_secs")]
+    pub clear_secrets_after_secs: Option<u64>,
+}
+
+fn default_scrub() -> bool {
+    true
+}
+
+fn default_clear_secs() -> Option<u64> {
+    Some(DEFAULT_CLEAR_SECS)
+}
+
+impl Default for ClipboardPolicy {
+    fn default() -> Self {
+        Self {
+            scrub_tracking_params: default_scrub(),
+            clear_secrets_after_secs: default_clear_secs(),
+        }
+    }
+}
+
+/// Text to put on the clipboard, and when to clear it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedCopy {
+    pub text: String,
+    pub clear_after: Option<Duration>,
+}
+
+impl ClipboardPolicy {
+    /// Apply the policy to a copy
+    pub fn prepare(&self, kind: CopyKind, text: &str) -> PreparedCopy {
+        match kind {
+            CopyKind::Url if self.scrub_tracking_params => PreparedCopy {
+                text: scrub_url(text),
+                clear_after: None,
+            },
+            CopyKind::Url | CopyKind::Text => PreparedCopy {
+                text: text.to_string(),
+                clear_after: None,
+            },
+            CopyKind::Secret => PreparedCopy {
+                text: text.to_string(),
+                clear_after: self.clear_secrets_after_secs.map(Duration::from_secs),
+            },
+        }
+    }
+
+    /// Read settings; a missing file means the defaults
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A web URL without its tracking parameters; other text is returned as is
+pub fn scrub_url(text: &str) -> String {
+    let trimmed = text.trim();
+    match Url::parse(trimmed) {
+        Ok(mut url) if matches!(url.scheme(), "http" | "https") => {
+            citadel_networking::url_canon::strip_tracking_params(&mut url);
+            url.into()
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// The secret most recently copied, waiting to be cleared
+#[derive(Default)]
+pub struct PendingClear {
+    generation: u64,
+    secret: Option<Zeroizing<String>>,
+}
+
+impl PendingClear {
+    /// Remember a copied secret. Returns the generation to pass to
+    /// [`PendingClear::is_due`] when its delay is up.
+    pub fn arm(&mut self, secret: &str) -> u64 {
+        self.generation += 1;
+        self.secret = Some(Zeroizing::new(secret.to_string()));
+        self.generation
+    }
+
+    /// Whether the clear for `generation` still applies; a later copy
+    /// supersedes it
+    pub fn is_due(&self, generation: u64) -> bool {
+        self.secret.is_some() && generation == self.generation
+    }
+
+    /// Whether the clipboard, read as `current`, still holds the secret and
+    /// should be cleared. Forgets the secret either way.
+    pub fn take_if_unchanged(&mut self, current: Option<&str>) -> bool {
+        let secret = self.secret.take();
+        matches!((secret, current), (Some(secret), Some(current)) if secret.as_str() == current)
+    }
+}
+
+/// Where clipboard settings live: `$CITADEL_CLIPBOARD_FILE`, otherwise
+/// `citadel/clipboard.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CLIPBOARD_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("clipboard.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copies_follow_policy() {
+        let policy = ClipboardPolicy::default();
+        assert_eq!(
+            policy
+                .prepare(
+                    CopyKind::Url,
+                    "https://shop.example/item?id=7&utm_source=news&fbclid=abc"
+                )
+                .text,
+            "https://shop.example/item?id=7"
+        );
+        assert_eq!(
+            policy.prepare(CopyKind::Text, "utm_source=x").text,
+            "utm_source=x"
+        );
+        assert_eq!(
+            policy.prepare(CopyKind::Url, "mailto:a@b.test?ref=x").text,
+            "mailto:a@b.test?ref=x"
+        );
+        assert_eq!(
+            policy
+                .prepare(CopyKind::for_input_type("PASSWORD"), "hunter2")
+                .clear_after,
+            Some(Duration::from_secs(DEFAULT_CLEAR_SECS))
+        );
+
+        let relaxed: ClipboardPolicy = serde_json::from_str(
+            r#"{"scrub_tracking_params": false, "clear_secrets_after_secs": null}"#,
+        )
+        .unwrap();
+        let copy = relaxed.prepare(CopyKind::Url, "https://a.test/?utm_source=x");
+        assert_eq!(copy.text, "https://a.test/?utm_source=x");
+        assert_eq!(relaxed.prepare(CopyKind::Secret, "pw").clear_after, None);
+    }
+
+    #[test]
+    fn test_only_an_unchanged_secret_is_cleared() {
+        let mut pending = PendingClear::default();
+        let first = pending.arm("hunter2");
+        let second = pending.arm("correct horse");
+        assert!(!pending.is_due(first));
+        assert!(pending.is_due(second));
+        // The user copied something else since
+        assert!(!pending.take_if_unchanged(Some("other text")));
+        assert!(!pending.is_due(second));
+
+        let third = pending.arm("s3cret");
+        assert!(pending.is_due(third));
+        assert!(pending.take_if_unchanged(Some("s3cret")));
+        assert!(!pending.take_if_unchanged(Some("s3cret")));
+    }
+}
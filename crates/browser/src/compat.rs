@@ -0,0 +1,257 @@
+//! Per-site web compatibility shims
+//!
+//! Some popular sites break under Citadel's defaults in small, specific ways:
+//! a script that assumes a global exists, markup that needs one extra
+//! attribute, a server that refuses unfamiliar user agents. Rather than
+//! weakening the defaults for every page, a local JSON ruleset lists narrow
+//! fixes per origin pattern:
+//!
+//! ```json
+//! { "shims": [
+//!   { "pattern": "*.example.com", "reason": "login form checks the UA",
+//!     "user_agent": "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0",
+//!     "globals": { "dataLayer": [] },
+//!     "allow_attributes": ["aria-expanded"] }
+//! ] }
+//! ```
+//!
+//! The engine applies the user agent to the page request and the extra
+//! attributes to its sanitizer policy; the globals become a prelude script
+//! that runs before the page's own scripts inside the JS cage. Shims can
+//! only add what is listed: event handler attributes are never allowed and
+//! globals are plain JSON values, never code.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use citadel_parser::security::{policy, SanitizerPolicy};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::profile;
+use crate::user_styles::pattern_matches;
+
+/// Environment variable overriding where the compat ruleset is read from
+pub const COMPAT_FILE_ENV: &str = "CITADEL_COMPAT_FILE";
+
+/// Longest user agent a shim may send
+const MAX_USER_AGENT_LEN: usize = 256;
+
+/// Attributes no shim may allow, on top of event handlers
+const NEVER_ALLOWED_ATTRIBUTES: &[&str] = &["srcdoc", "formaction", "style", "xlink:href"];
+
+/// Fixes for the pages matching one origin pattern
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompatShim {
+    /// Origin pattern, see [`pattern_matches`]
+    pub pattern: String,
+    /// Why the shim exists, for logs
+    #[serde(default)]
+    pub reason: String,
+    /// `User-Agent` sent for the page instead of the privacy level's
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Globals defined before page scripts run, when the page has not
+    /// defined them itself
+    #[serde(default)]
+    pub globals: BTreeMap<String, serde_json::Value>,
+    /// Attributes kept by the sanitizer on top of the default allowlist
+    #[serde(default)]
+    pub allow_attributes: Vec<String>,
+}
+
+impl CompatShim {
+    /// Whether the shim applies to a page
+    pub fn matches(&self, url: &Url) -> bool {
+        pattern_matches(&self.pattern, url)
+    }
+}
+
+/// The compat ruleset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompatShims {
+    pub shims: Vec<CompatShim>,
+}
+
+impl CompatShims {
+    /// Fixes for a page, merged across every matching shim in ruleset
+    /// order; later shims win for the user agent and for duplicate globals
+    pub fn for_url(&self, url: &Url) -> AppliedCompat {
+        let mut applied = AppliedCompat::default();
+        for shim in self.shims.iter().filter(|shim| shim.matches(url)) {
+            if let Some(user_agent) = shim
+                .user_agent
+                .as_deref()
+                .filter(|ua| is_valid_user_agent(ua))
+            {
+                applied.user_agent = Some(user_agent.to_string());
+            }
+            for (name, value) in &shim.globals {
+                if is_identifier(name) {
+                    applied.globals.insert(name.clone(), value.clone());
+                }
+            }
+            for attribute in &shim.allow_attributes {
+                let attribute = attribute.trim().to_ascii_lowercase();
+                if is_relaxable_attribute(&attribute)
+                    && !applied.allow_attributes.contains(&attribute)
+                {
+                    applied.allow_attributes.push(attribute);
+                }
+            }
+            if !shim.reason.is_empty() {
+                applied.reasons.push(shim.reason.clone());
+            }
+        }
+        applied
+    }
+
+    /// Read the ruleset from a JSON file; a missing file means no shims
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The fixes that apply to one page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppliedCompat {
+    pub user_agent: Option<String>,
+    pub globals: BTreeMap<String, serde_json::Value>,
+    pub allow_attributes: Vec<String>,
+    /// Reasons of the matching shims
+    pub reasons: Vec<String>,
+}
+
+impl AppliedCompat {
+    /// Whether no shim applies
+    pub fn is_empty(&self) -> bool {
+        self.user_agent.is_none() && self.globals.is_empty() && self.allow_attributes.is_empty()
+    }
+
+    /// Prelude defining the missing globals; empty when there are none
+    pub fn script(&self) -> String {
+        self.globals
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = serde_json::to_string(name).ok()?;
+                let value = serde_json::to_string(value).ok()?;
+                Some(format!(
+                    "if(typeof globalThis[{name}]==='undefined'){{globalThis[{name}]={value};}}"
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The default sanitizer policy plus the shims' extra attributes
+    pub fn sanitizer_policy(&self) -> SanitizerPolicy {
+        SanitizerPolicy::default()
+            .allow_attributes(self.allow_attributes.iter().map(String::as_str))
+    }
+}
+
+/// A user agent that is safe to put in a header
+fn is_valid_user_agent(user_agent: &str) -> bool {
+    !user_agent.trim().is_empty()
+        && user_agent.len() <= MAX_USER_AGENT_LEN
+        && user_agent.chars().all(|c| c == ' ' || c.is_ascii_graphic())
+}
+
+/// A plain JavaScript identifier, so a global cannot shadow syntax
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Whether a shim may allow an attribute: never event handlers or attributes
+/// that smuggle in markup, styles or navigation
+fn is_relaxable_attribute(attribute: &str) -> bool {
+    !attribute.is_empty()
+        && !policy::is_event_handler(attribute)
+        && !NEVER_ALLOWED_ATTRIBUTES.contains(&attribute)
+        && attribute
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Where the compat ruleset lives: `$CITADEL_COMPAT_FILE`, otherwise
+/// `citadel/compat.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(COMPAT_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("compat.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ruleset() -> CompatShims {
+        serde_json::from_str(
+            r#"{"shims": [
+                {"pattern": "*.example.com", "reason": "needs dataLayer",
+                 "globals": {"dataLayer": [], "bad name": 1, "__cfg": {"a": "</script>"}},
+                 "allow_attributes": ["ARIA-expanded", "onclick", "srcdoc", "style"]},
+                {"pattern": "https://shop.example.com",
+                 "user_agent": "Mozilla/5.0 (X11; Linux x86_64) Firefox/128.0"},
+                {"pattern": "*", "user_agent": "evil\r\nCookie: a=b"}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shims_apply_only_to_matching_sites() {
+        let shims = ruleset();
+        let page = shims.for_url(&Url::parse("https://shop.example.com/cart").unwrap());
+        assert_eq!(
+            page.user_agent.as_deref(),
+            Some("Mozilla/5.0 (X11; Linux x86_64) Firefox/128.0")
+        );
+        assert_eq!(page.allow_attributes, vec!["aria-expanded"]);
+        assert_eq!(page.reasons, vec!["needs dataLayer"]);
+        assert!(page
+            .sanitizer_policy()
+            .is_attribute_allowed("aria-expanded"));
+        assert!(!SanitizerPolicy::default().is_attribute_allowed("aria-expanded"));
+
+        let other = shims.for_url(&Url::parse("https://other.test/").unwrap());
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn test_globals_become_a_data_only_prelude() {
+        let page = ruleset().for_url(&Url::parse("https://www.example.com/").unwrap());
+        assert_eq!(page.globals.len(), 2);
+        let script = page.script();
+        assert!(script.contains(
+            r#"if(typeof globalThis["dataLayer"]==='undefined'){globalThis["dataLayer"]=[];}"#
+        ));
+        assert!(!script.contains("bad name"));
+        assert!(CompatShims::default()
+            .for_url(&Url::parse("https://www.example.com/").unwrap())
+            .script()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_missing_ruleset_has_no_shims() {
+        let path = std::env::temp_dir().join(format!("citadel-compat-{}", uuid::Uuid::new_v4()));
+        assert_eq!(CompatShims::load(&path).unwrap(), CompatShims::default());
+        std::fs::write(&path, r#"{"shims":[{"pattern":"*"}]}"#).unwrap();
+        assert_eq!(CompatShims::load(&path).unwrap().shims.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! Container host policy settings
+//!
+//! Users restrict what a container's tabs can reach in a JSON file mapping
+//! container ids to allow/block host patterns:
+//!
+//! ```json
+//! { "6f1c…": { "allow": ["*.corp.example"], "block": ["hr.corp.example"] } }
+//! ```
+//!
+//! The engine checks a container tab's navigations against its policy before
+//! the hostname is resolved (see `citadel_networking::host_policy`).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use citadel_networking::{ContainerPolicies, HostPolicy};
+
+use crate::profile;
+
+/// Environment variable overriding where container policies are read from
+pub const CONTAINER_POLICIES_FILE_ENV: &str = "CITADEL_CONTAINER_POLICIES_FILE";
+
+/// Read policies from a JSON file; a missing file means no restrictions
+pub fn load(path: &Path) -> std::io::Result<ContainerPolicies> {
+    match profile::read(path) {
+        Ok(bytes) => serde_json::from_slice::<HashMap<uuid::Uuid, HostPolicy>>(&bytes)
+            .map(ContainerPolicies::from)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ContainerPolicies::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Where container policies live: `$CITADEL_CONTAINER_POLICIES_FILE`,
+/// otherwise `citadel/container-policies.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONTAINER_POLICIES_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("container-policies.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn test_load_policies_file() {
+        let path = std::env::temp_dir().join(format!(
+            "citadel-container-policies-{}",
+            uuid::Uuid::new_v4()
+        ));
+        assert!(load(&path).unwrap().get(uuid::Uuid::new_v4()).is_none());
+
+        let work = uuid::Uuid::new_v4();
+        std::fs::write(
+            &path,
+            format!(r#"{{"{}": {{"allow": ["*.corp.example"]}}}}"#, work),
+        )
+        .unwrap();
+        let policies = load(&path).unwrap();
+        assert!(policies
+            .check(work, &Url::parse("https://news.test/").unwrap())
+            .is_some());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(
+            load(&path).err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+//! Per-page content budgets
+//!
+//! Each page loads within the budgets of its parser profile: at most
+//! `max_document_bytes` of HTML, `max_css_size` of stylesheet and
+//! `max_elements` DOM elements, tighter at the stricter privacy levels. A
+//! pathological page is cut down to its budgets instead of exhausting
+//! memory, and the page shows a banner saying it was truncated.
+//!
+//! Cuts always fall on a character boundary, so truncated text is still
+//! valid UTF-8, and never inside a tag or a CSS rule.
+
+use citadel_parser::ParserConfig;
+
+/// Shown above a page that ran over its budgets
+pub const TRUNCATED_BANNER: &str = "Content truncated for safety";
+
+/// Which budgets a page ran over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentTruncation {
+    /// The HTML was longer than `max_document_bytes`
+    pub html: bool,
+    /// The page's CSS was longer than `max_css_size`
+    pub stylesheet: bool,
+    /// The DOM hit its element, depth or text limits
+    pub dom: bool,
+}
+
+impl ContentTruncation {
+    /// Whether anything was left out
+    pub fn any(&self) -> bool {
+        self.html || self.stylesheet || self.dom
+    }
+
+    /// Banner text naming the budgets that were exceeded, `None` if none were
+    pub fn banner(&self) -> Option<String> {
+        let parts: Vec<&str> = [
+            (self.html, "page size"),
+            (self.stylesheet, "stylesheet size"),
+            (self.dom, "element limits"),
+        ]
+        .into_iter()
+        .filter_map(|(exceeded, what)| exceeded.then_some(what))
+        .collect();
+        (!parts.is_empty()).then(|| {
+            format!(
+                "{}: the page exceeded its {}",
+                TRUNCATED_BANNER,
+                parts.join(", ")
+            )
+        })
+    }
+}
+
+/// `html` cut to the profile's document budget, ending before any tag the
+/// cut would split. `None` if it fits.
+pub fn truncate_html<'a>(html: &'a str, config: &ParserConfig) -> Option<&'a str> {
+    let head = prefix(html, config.max_document_bytes)?;
+    // A `<` with no `>` after it starts a tag the cut split
+    Some(match head.rfind('<') {
+        Some(open) if !head[open..].contains('>') => &head[..open],
+        _ => head,
+    })
+}
+
+/// `css` cut to `max_bytes`, ending after the last complete rule. `None`
+/// if it fits.
+pub fn truncate_css(css: &str, max_bytes: usize) -> Option<&str> {
+    let head = prefix(css, max_bytes)?;
+    Some(head.rfind('}').map_or("", |end| &head[..=end]))
+}
+
+/// The longest prefix of `text` within `max_bytes` that ends on a character
+/// boundary, or `None` if all of `text` fits
+fn prefix(text: &str, max_bytes: usize) -> Option<&str> {
+    if text.len() <= max_bytes {
+        return None;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(&text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_keeps_characters_tags_and_rules_whole() {
+        let config = ParserConfig {
+            max_document_bytes: 12,
+            ..ParserConfig::default()
+        };
+        assert_eq!(truncate_html("<p>short</p>", &config), None);
+        // "é" straddles byte 12; the cut backs off to before it
+        assert_eq!(
+            truncate_html("<p>abcdefghé</p>", &config),
+            Some("<p>abcdefgh")
+        );
+        assert_eq!(
+            truncate_html("<p>ab</p><img src=x>", &config),
+            Some("<p>ab</p>")
+        );
+
+        assert_eq!(truncate_css("a{}", 8), None);
+        assert_eq!(truncate_css("a{b:c}d{e:f}", 8), Some("a{b:c}"));
+        assert_eq!(truncate_css("a{b:\"ü\"}", 6), Some(""));
+
+        let truncation = ContentTruncation {
+            html: true,
+            dom: true,
+            ..Default::default()
+        };
+        assert!(truncation.any());
+        assert_eq!(
+            truncation.banner().as_deref(),
+            Some("Content truncated for safety: the page exceeded its page size, element limits")
+        );
+        assert_eq!(ContentTruncation::default().banner(), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! Saved cookies
+//!
+//! Cookies with an expiry in the shared and container stores outlive the
+//! browser: they are written to the profile at exit and put back into the
+//! engine's jar when it starts. Session cookies and ephemeral tabs' cookies
+//! are never written. The file goes through [`crate::profile`], so it is
+//! sealed like the rest of the profile once encryption is on.
+
+use std::path::{Path, PathBuf};
+
+use citadel_networking::cookie_jar::{CookieJar, SavedCookie};
+
+use crate::profile;
+
+/// Environment variable overriding where cookies are saved
+pub const COOKIES_FILE_ENV: &str = "CITADEL_COOKIES_FILE";
+
+/// Read saved cookies; a missing file means none
+pub fn load(path: &Path) -> std::io::Result<Vec<SavedCookie>> {
+    match profile::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write the jar's persistent cookies, replacing what was saved before
+pub async fn save(jar: &CookieJar, path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let json = serde_json::to_vec(&jar.persistent_cookies())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    profile::write(path, json).await
+}
+
+/// Where cookies live: `$CITADEL_COOKIES_FILE`, otherwise
+/// `citadel/cookies.json` under the XDG data directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(COOKIES_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+    Some(data_dir.join("citadel").join("cookies.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_networking::cookie_jar::CookieStoreId;
+    use url::Url;
+
+    #[tokio::test]
+    async fn test_persistent_cookies_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("citadel-cookies-{}", uuid::Uuid::new_v4()));
+        assert!(load(&path).unwrap().is_empty());
+
+        let url = Url::parse("https://example.com/").unwrap();
+        let jar = CookieJar::new();
+        jar.set_cookies(
+            CookieStoreId::Shared,
+            &url,
+            &url,
+            ["kept=1; Max-Age=3600", "session=1"],
+        );
+        jar.set_cookies(
+            CookieStoreId::Ephemeral(uuid::Uuid::new_v4()),
+            &url,
+            &url,
+            ["ephemeral=1; Max-Age=3600"],
+        );
+        save(&jar, &path).await.unwrap();
+
+        let restored = CookieJar::new();
+        restored.restore(load(&path).unwrap());
+        assert_eq!(
+            restored
+                .cookie_header(CookieStoreId::Shared, &url, &url)
+                .as_deref(),
+            Some("kept=1")
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,147 @@
+//! `citadel://csp-reports`
+//!
+//! Shows the would-be violations of the `Content-Security-Policy-Report-Only`
+//! policy sent with the last web page of the tab the page is opened in.
+//! Reports are kept per tab in memory only: they are never sent to the
+//! page's `report-uri` or `report-to` endpoints.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use citadel_networking::{CspReport, ReportOnlyPolicy};
+use url::Url;
+use uuid::Uuid;
+
+use crate::html::escape;
+
+/// Address of the page
+pub const CSP_REPORTS_URL: &str = "citadel://csp-reports";
+
+/// Report-only findings for one page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageReports {
+    pub page_url: Url,
+    pub reports: Vec<CspReport>,
+    /// Endpoints the page asked for reports to go to
+    pub ignored_endpoints: Vec<String>,
+}
+
+impl PageReports {
+    /// Evaluate `policy` against a page's HTML
+    pub fn evaluate(policy: &ReportOnlyPolicy, html: &str, page_url: &Url) -> Self {
+        Self {
+            page_url: page_url.clone(),
+            reports: policy.evaluate(html, page_url),
+            ignored_endpoints: policy.ignored_endpoints.clone(),
+        }
+    }
+}
+
+/// The latest page reports of each tab
+#[derive(Debug, Clone, Default)]
+pub struct CspReportLog {
+    tabs: Arc<Mutex<HashMap<Uuid, PageReports>>>,
+}
+
+impl CspReportLog {
+    /// Replace a tab's reports with those of its new page; `None` when the
+    /// page sent no report-only policy
+    pub fn record(&self, tab_id: Uuid, reports: Option<PageReports>) {
+        if let Ok(mut tabs) = self.tabs.lock() {
+            match reports {
+                Some(reports) => tabs.insert(tab_id, reports),
+                None => tabs.remove(&tab_id),
+            };
+        }
+    }
+
+    /// Reports of a tab's last web page
+    pub fn get(&self, tab_id: Uuid) -> Option<PageReports> {
+        self.tabs.lock().ok()?.get(&tab_id).cloned()
+    }
+
+    /// Forget a closed tab
+    pub fn remove(&self, tab_id: Uuid) {
+        self.record(tab_id, None);
+    }
+
+    /// Forget every tab
+    pub fn clear(&self) {
+        if let Ok(mut tabs) = self.tabs.lock() {
+            tabs.clear();
+        }
+    }
+}
+
+/// Render the page for a tab's reports
+pub fn render(page: Option<&PageReports>) -> String {
+    let mut html = String::from(
+        "<!doctype html><html><head><title>CSP reports</title></head><body>\n\
+         <h1>CSP reports</h1>\n",
+    );
+    let Some(page) = page else {
+        html.push_str(
+            "<p>The last page in this tab sent no Content-Security-Policy-Report-Only \
+             header.</p>\n</body></html>\n",
+        );
+        return html;
+    };
+
+    html.push_str(&format!(
+        "<p>{}: {} would-be violations. Nothing was blocked.</p>\n",
+        escape(page.page_url.as_str()),
+        page.reports.len()
+    ));
+    if !page.ignored_endpoints.is_empty() {
+        html.push_str(&format!(
+            "<p>Not sent to: {}</p>\n",
+            escape(&page.ignored_endpoints.join(", "))
+        ));
+    }
+    for report in &page.reports {
+        let directive = if report.violated_directive == report.effective_directive {
+            report.effective_directive.clone()
+        } else {
+            format!(
+                "{} (via {})",
+                report.effective_directive, report.violated_directive
+            )
+        };
+        html.push_str(&format!(
+            "<p>{}: {}</p>\n",
+            escape(&directive),
+            escape(&report.blocked.to_string())
+        ));
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_are_kept_per_tab_and_never_leave_the_page() {
+        let policy = ReportOnlyPolicy::parse(
+            "script-src 'self'; report-uri https://collector.example/csp?a=1&b=2",
+        )
+        .unwrap();
+        let url = Url::parse("https://shop.example/").unwrap();
+        let html = r#"<script src="https://evil.example/x.js?<b>"></script>"#;
+
+        let log = CspReportLog::default();
+        let (tab, other) = (Uuid::new_v4(), Uuid::new_v4());
+        log.record(tab, Some(PageReports::evaluate(&policy, html, &url)));
+        assert!(log.get(other).is_none());
+
+        let page = render(log.get(tab).as_ref());
+        assert!(page.contains("1 would-be violations"));
+        assert!(page.contains("script-src: https://evil.example/x.js?%3Cb%3E"));
+        assert!(page.contains("Not sent to: https://collector.example/csp?a=1&amp;b=2"));
+
+        log.record(tab, None);
+        assert!(render(log.get(tab).as_ref()).contains("sent no"));
+    }
+}
@@ -0,0 +1,842 @@
+//! Downloads
+//!
+//! Files are streamed to disk as they arrive, into `<name>.part` next to
+//! where they end up, and renamed into place only once complete and
+//! verified, so a half-written or tampered file never sits under its final
+//! name. A paused or interrupted download resumes where its partial file
+//! ends with a `Range` request; `If-Range` makes the server send the whole
+//! file again if it changed in between. A digest given for the file (SRI
+//! metadata or a hex SHA-256, as download pages publish) is checked over
+//! all of it, and a file that does not match is deleted.
+//!
+//! The manager only keeps the state; requests go out through the engine so
+//! the starting tab's cookies, container policy and network partition
+//! apply. The UI reads progress from here on a timer while anything is
+//! downloading.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_networking::{IntegrityHasher, IntegrityResult, NetworkError, StreamingResponse};
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+use uuid::Uuid;
+
+/// Extension of files still being downloaded
+pub const PART_EXTENSION: &str = "part";
+
+/// Longest file name a download is saved under, in characters
+const MAX_FILE_NAME_CHARS: usize = 200;
+
+/// How often the UI reads progress while anything is downloading
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where a download is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadState {
+    /// Bytes are arriving
+    Downloading,
+    /// Stopped by the user; the partial file is kept for resuming
+    Paused,
+    /// In place under its final name; `verified` when a digest was checked
+    Completed { verified: bool },
+    /// Stopped by an error. The partial file is kept unless the download
+    /// failed verification.
+    Failed(String),
+    /// Stopped by the user and the partial file deleted
+    Cancelled,
+}
+
+/// A download and its progress
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub id: Uuid,
+    pub url: Url,
+    /// Tab the download was started from, whose cookies and network
+    /// partition it uses
+    pub tab_id: Uuid,
+    pub file_name: String,
+    /// Where the file ends up
+    pub path: PathBuf,
+    /// Bytes on disk so far
+    pub received: u64,
+    /// Size of the whole file, when the server gave it
+    pub total: Option<u64>,
+    pub state: DownloadState,
+    /// Digest the file has to match: SRI metadata or a hex SHA-256
+    pub integrity: Option<String>,
+    /// `ETag` or `Last-Modified` of the response, sent as `If-Range`
+    validator: Option<String>,
+    /// Whether the server's bytes can be asked for by range; not when they
+    /// arrive content-encoded
+    resumable: bool,
+    /// Bumped each time a transfer starts, so one left over from before a
+    /// pause stops writing
+    attempt: u64,
+}
+
+impl Download {
+    /// The partial file
+    pub fn part_path(&self) -> PathBuf {
+        part_path(&self.path)
+    }
+
+    /// Share of the file received, when its size is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.received as f64 / total as f64).min(1.0) as f32)
+    }
+
+    /// Whether the download can be carried on; after a failed
+    /// verification it starts over
+    pub fn can_resume(&self) -> bool {
+        matches!(self.state, DownloadState::Paused | DownloadState::Failed(_))
+    }
+
+    /// Whether the download is over, one way or another
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.state,
+            DownloadState::Completed { .. } | DownloadState::Cancelled
+        )
+    }
+}
+
+/// What to fetch to carry a download on
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+    pub id: Uuid,
+    pub url: Url,
+    pub tab_id: Uuid,
+    /// `Range` and `If-Range`, when part of the file is on disk already
+    pub headers: Vec<(String, String)>,
+    attempt: u64,
+}
+
+/// A response to write into a download
+pub struct DownloadBody {
+    pub status: u16,
+    /// `Content-Range`, on a partial response
+    pub content_range: Option<String>,
+    /// Length of the body as handed out
+    pub content_length: Option<u64>,
+    /// `ETag`, else `Last-Modified`
+    pub validator: Option<String>,
+    /// `Content-Disposition`
+    pub disposition: Option<String>,
+    /// Whether the body arrives content-encoded
+    pub encoded: bool,
+    pub chunks: Pin<Box<dyn Stream<Item = Result<Vec<u8>, NetworkError>> + Send>>,
+}
+
+impl std::fmt::Debug for DownloadBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadBody")
+            .field("status", &self.status)
+            .field("content_range", &self.content_range)
+            .field("content_length", &self.content_length)
+            .finish()
+    }
+}
+
+impl DownloadBody {
+    pub fn from_response(response: StreamingResponse) -> Self {
+        let header = |name: &str| response.header(name).map(str::to_string);
+        Self {
+            status: response.status,
+            content_range: header("content-range"),
+            content_length: response.content_length(),
+            validator: header("etag").or_else(|| header("last-modified")),
+            disposition: header("content-disposition"),
+            encoded: response.is_encoded(),
+            chunks: Box::pin(response.into_stream()),
+        }
+    }
+}
+
+/// Downloads of this session, oldest first
+#[derive(Debug, Clone)]
+pub struct DownloadManager {
+    dir: PathBuf,
+    downloads: Arc<Mutex<Vec<Download>>>,
+}
+
+impl DownloadManager {
+    /// A manager saving into `dir`
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            downloads: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Directory downloads are saved into
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Add a download of `url` for `tab_id`; [`Self::request`] says what to
+    /// fetch for it
+    pub fn start(&self, url: Url, tab_id: Uuid, integrity: Option<String>) -> Uuid {
+        let id = Uuid::new_v4();
+        let Ok(mut downloads) = self.downloads.lock() else {
+            return id;
+        };
+        let file_name = file_name(&url, None);
+        let path = unique_path(&self.dir, &file_name, &downloads);
+        downloads.push(Download {
+            id,
+            url,
+            tab_id,
+            file_name,
+            path,
+            received: 0,
+            total: None,
+            state: DownloadState::Downloading,
+            integrity: integrity.filter(|integrity| !integrity.trim().is_empty()),
+            validator: None,
+            resumable: true,
+            attempt: 1,
+        });
+        id
+    }
+
+    /// A copy of every download, for showing them
+    pub fn list(&self) -> Vec<Download> {
+        self.downloads
+            .lock()
+            .map(|downloads| downloads.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<Download> {
+        self.list().into_iter().find(|download| download.id == id)
+    }
+
+    /// Whether bytes are arriving for any download
+    pub fn is_active(&self) -> bool {
+        self.list()
+            .iter()
+            .any(|download| download.state == DownloadState::Downloading)
+    }
+
+    /// What to fetch to carry a downloading download on
+    pub fn request(&self, id: Uuid) -> Option<DownloadRequest> {
+        let download = self
+            .get(id)
+            .filter(|download| download.state == DownloadState::Downloading)?;
+        let on_disk = std::fs::metadata(download.part_path())
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        let mut headers = Vec::new();
+        if on_disk > 0 && download.resumable {
+            headers.push(("Range".to_string(), format!("bytes={}-", on_disk)));
+            if let Some(validator) = &download.validator {
+                headers.push(("If-Range".to_string(), validator.clone()));
+            }
+        }
+        Some(DownloadRequest {
+            id,
+            url: download.url,
+            tab_id: download.tab_id,
+            headers,
+            attempt: download.attempt,
+        })
+    }
+
+    /// Stop a download, keeping what arrived
+    pub fn pause(&self, id: Uuid) -> bool {
+        self.update(id, |download| {
+            if download.state != DownloadState::Downloading {
+                return false;
+            }
+            download.state = DownloadState::Paused;
+            true
+        })
+        .unwrap_or(false)
+    }
+
+    /// Carry a paused or failed download on; the caller then fetches what
+    /// [`Self::request`] says
+    pub fn resume(&self, id: Uuid) -> bool {
+        self.update(id, |download| {
+            if !download.can_resume() {
+                return false;
+            }
+            download.state = DownloadState::Downloading;
+            download.attempt += 1;
+            true
+        })
+        .unwrap_or(false)
+    }
+
+    /// Stop a download and delete its partial file
+    pub fn cancel(&self, id: Uuid) -> bool {
+        let part = self.update(id, |download| {
+            if download.is_finished() {
+                return None;
+            }
+            download.state = DownloadState::Cancelled;
+            download.received = 0;
+            Some(download.part_path())
+        });
+        match part.flatten() {
+            Some(part) => {
+                let _ = std::fs::remove_file(part);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget downloads that are over; their files stay where they are
+    pub fn clear_finished(&self) {
+        if let Ok(mut downloads) = self.downloads.lock() {
+            downloads.retain(|download| !download.is_finished());
+        }
+    }
+
+    /// Write the response `body` resolves to into the download `request` is
+    /// for, and record how that went
+    pub async fn run(
+        &self,
+        request: &DownloadRequest,
+        body: impl Future<Output = Result<DownloadBody, CitadelError>>,
+    ) -> Result<DownloadState, CitadelError> {
+        let result = match body.await {
+            Ok(body) => self.transfer(request, body).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = &result {
+            self.update(request.id, |download| {
+                if download.attempt == request.attempt
+                    && download.state == DownloadState::Downloading
+                {
+                    download.state = DownloadState::Failed(error.to_string());
+                }
+            });
+        }
+        result
+    }
+
+    /// Write `body` into the download; stops early when it is paused or
+    /// cancelled, returning the state it was put in
+    async fn transfer(
+        &self,
+        request: &DownloadRequest,
+        mut body: DownloadBody,
+    ) -> Result<DownloadState, CitadelError> {
+        let download = self
+            .get(request.id)
+            .ok_or_else(|| download_error("DOWNLOAD_UNKNOWN", "no such download"))?;
+        let offset = match body.status {
+            206 => {
+                let start = body.content_range.as_deref().and_then(content_range_start);
+                let on_disk = tokio::fs::metadata(download.part_path())
+                    .await
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0);
+                if start != Some(on_disk) {
+                    return Err(download_error(
+                        "DOWNLOAD_RANGE_MISMATCH",
+                        "the server sent a different part of the file",
+                    ));
+                }
+                on_disk
+            }
+            200 => 0,
+            status => {
+                return Err(CitadelError::from(NetworkError::HttpStatus(status)));
+            }
+        };
+        let total = match offset {
+            0 => body.content_length,
+            _ => body
+                .content_range
+                .as_deref()
+                .and_then(content_range_total)
+                .or_else(|| body.content_length.map(|length| offset + length)),
+        };
+
+        // A fresh response may name the file
+        let renamed = match file_name(&download.url, body.disposition.as_deref()) {
+            name if offset == 0 && name != download.file_name => {
+                let others: Vec<Download> = self
+                    .list()
+                    .into_iter()
+                    .filter(|other| other.id != download.id)
+                    .collect();
+                let path = unique_path(&self.dir, &name, &others);
+                Some((name, path))
+            }
+            _ => None,
+        };
+        let download = self
+            .update(request.id, |download| {
+                if let Some((name, path)) = renamed {
+                    download.file_name = name;
+                    download.path = path;
+                }
+                download.received = offset;
+                download.total = total;
+                download.validator = body.validator.clone();
+                download.resumable = !body.encoded;
+                download.clone()
+            })
+            .unwrap_or(download);
+        let part = download.part_path();
+
+        let mut hasher = download
+            .integrity
+            .as_deref()
+            .map(IntegrityHasher::new)
+            .transpose()
+            .map_err(|result| {
+                download_error(
+                    "DOWNLOAD_INTEGRITY",
+                    format!("the expected digest cannot be checked: {:?}", result),
+                )
+            })?;
+        if let Some(parent) = part.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        let mut file = if offset > 0 {
+            if let Some(hasher) = &mut hasher {
+                hash_file(&part, hasher).await?;
+            }
+            tokio::fs::OpenOptions::new().append(true).open(&part).await
+        } else {
+            tokio::fs::File::create(&part).await
+        }
+        .map_err(io_error)?;
+
+        while let Some(chunk) = body.chunks.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    // Keep what arrived for resuming
+                    let _ = file.flush().await;
+                    return Err(error.into());
+                }
+            };
+            file.write_all(&chunk).await.map_err(io_error)?;
+            if let Some(hasher) = &mut hasher {
+                hasher.update(&chunk);
+            }
+            let state = self.update(request.id, |download| {
+                download.received += chunk.len() as u64;
+                if download.attempt == request.attempt {
+                    download.state.clone()
+                } else {
+                    DownloadState::Paused
+                }
+            });
+            match state {
+                Some(DownloadState::Downloading) => {}
+                Some(DownloadState::Cancelled) | None => {
+                    drop(file);
+                    let _ = tokio::fs::remove_file(&part).await;
+                    return Ok(DownloadState::Cancelled);
+                }
+                Some(state) => {
+                    file.flush().await.map_err(io_error)?;
+                    return Ok(state);
+                }
+            }
+        }
+        file.flush().await.map_err(io_error)?;
+        file.sync_all().await.map_err(io_error)?;
+        drop(file);
+
+        let received = self.get(request.id).map_or(0, |download| download.received);
+        if total.is_some_and(|total| received != total) {
+            return Err(download_error(
+                "DOWNLOAD_TRUNCATED",
+                format!("received {} of {} bytes", received, total.unwrap_or(0)),
+            ));
+        }
+        let verified = match hasher.map(IntegrityHasher::finish) {
+            None => false,
+            Some(IntegrityResult::Valid) => true,
+            Some(_) => {
+                let _ = tokio::fs::remove_file(&part).await;
+                self.update(request.id, |download| download.received = 0);
+                return Err(download_error(
+                    "DOWNLOAD_INTEGRITY",
+                    "the file does not match its expected digest and was deleted",
+                ));
+            }
+        };
+        tokio::fs::rename(&part, &download.path)
+            .await
+            .map_err(io_error)?;
+
+        let state = DownloadState::Completed { verified };
+        self.update(request.id, |download| download.state = state.clone());
+        log::info!(
+            "⬇️ Downloaded {} ({} bytes{})",
+            download.file_name,
+            received,
+            if verified { ", verified" } else { "" }
+        );
+        Ok(state)
+    }
+
+    /// Save `bytes` the browser already holds, such as an image re-encoded
+    /// without its metadata, as a download of `url` named `name`. Written
+    /// through a partial file like any other download.
+    pub async fn save(
+        &self,
+        url: Url,
+        tab_id: Uuid,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> Result<PathBuf, CitadelError> {
+        let id = Uuid::new_v4();
+        let download = {
+            let mut downloads = self
+                .downloads
+                .lock()
+                .map_err(|_| download_error("DOWNLOAD_UNKNOWN", "download list unavailable"))?;
+            let file_name = match sanitize_file_name(name) {
+                name if name.is_empty() => "download".to_string(),
+                name => name,
+            };
+            let path = unique_path(&self.dir, &file_name, &downloads);
+            let download = Download {
+                id,
+                url,
+                tab_id,
+                file_name,
+                path,
+                received: 0,
+                total: Some(bytes.len() as u64),
+                state: DownloadState::Downloading,
+                integrity: None,
+                validator: None,
+                resumable: false,
+                attempt: 1,
+            };
+            downloads.push(download.clone());
+            download
+        };
+
+        let part = download.part_path();
+        let written = async {
+            if let Some(parent) = part.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&part, &bytes).await?;
+            tokio::fs::rename(&part, &download.path).await
+        }
+        .await;
+        let state = match &written {
+            Ok(()) => DownloadState::Completed { verified: false },
+            Err(error) => {
+                let _ = tokio::fs::remove_file(&part).await;
+                DownloadState::Failed(error.to_string())
+            }
+        };
+        self.update(id, |download| {
+            if written.is_ok() {
+                download.received = bytes.len() as u64;
+            }
+            download.state = state;
+        });
+        written.map_err(io_error)?;
+        Ok(download.path)
+    }
+
+    fn update<T>(&self, id: Uuid, change: impl FnOnce(&mut Download) -> T) -> Option<T> {
+        let mut downloads = self.downloads.lock().ok()?;
+        downloads
+            .iter_mut()
+            .find(|download| download.id == id)
+            .map(change)
+    }
+}
+
+/// The user's download directory: `XDG_DOWNLOAD_DIR`, else `~/Downloads`
+pub fn downloads_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DOWNLOAD_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join("Downloads")))
+}
+
+/// Name to save a download under: the `Content-Disposition` filename when
+/// there is one, else the last segment of the URL path, made safe to use
+pub fn file_name(url: &Url, disposition: Option<&str>) -> String {
+    let named = disposition.and_then(disposition_filename);
+    let from_url = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            urlencoding::decode(segment)
+                .map(|name| name.into_owned())
+                .unwrap_or_else(|_| segment.to_string())
+        });
+    let name = sanitize_file_name(&named.or(from_url).unwrap_or_default());
+    if name.is_empty() {
+        "download".to_string()
+    } else {
+        name
+    }
+}
+
+/// `filename*` (RFC 5987, UTF-8) or `filename` of a `Content-Disposition`
+fn disposition_filename(disposition: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = disposition
+        .split(';')
+        .skip(1)
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_ascii_lowercase(), value.trim()))
+        })
+        .collect();
+    let extended = params
+        .iter()
+        .find(|(key, _)| key == "filename*")
+        .and_then(|(_, value)| {
+            let (charset, rest) = value.split_once('\'')?;
+            let (_language, encoded) = rest.split_once('\'')?;
+            charset
+                .eq_ignore_ascii_case("utf-8")
+                .then(|| urlencoding::decode(encoded).ok())
+                .flatten()
+                .map(|name| name.into_owned())
+        });
+    extended.or_else(|| {
+        params
+            .iter()
+            .find(|(key, _)| key == "filename")
+            .map(|(_, value)| value.trim_matches('"').to_string())
+    })
+}
+
+/// Keep only the last path component, with no control or reserved
+/// characters and no leading dots, so a server cannot write outside the
+/// download directory or hide the file
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+    cleaned
+        .trim_start_matches(['.', ' '])
+        .trim_end_matches([' ', '.'])
+        .to_string()
+}
+
+/// `dir/name`, numbered `name (2).ext` and on while that is taken on disk
+/// or by another download
+fn unique_path(dir: &Path, name: &str, downloads: &[Download]) -> PathBuf {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    (1..)
+        .map(|n| {
+            let numbered = match (n, extension) {
+                (1, _) => name.to_string(),
+                (n, Some(extension)) => format!("{} ({}).{}", stem, n, extension),
+                (n, None) => format!("{} ({})", stem, n),
+            };
+            dir.join(numbered)
+        })
+        .find(|path| {
+            !path.exists()
+                && !part_path(path).exists()
+                && !downloads.iter().any(|download| download.path == *path)
+        })
+        .unwrap_or_else(|| dir.join(name))
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".");
+    part.push(PART_EXTENSION);
+    PathBuf::from(part)
+}
+
+/// First byte of a `Content-Range: bytes first-last/total`
+fn content_range_start(range: &str) -> Option<u64> {
+    let range = range.trim().strip_prefix("bytes")?.trim_start();
+    range.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Total length of a `Content-Range: bytes first-last/total`, unless `*`
+fn content_range_total(range: &str) -> Option<u64> {
+    range.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// Feed the partial file already on disk to `hasher`
+async fn hash_file(path: &Path, hasher: &mut IntegrityHasher) -> Result<(), CitadelError> {
+    let mut file = tokio::fs::File::open(path).await.map_err(io_error)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(io_error)?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(buf.get(..n).unwrap_or_default());
+    }
+}
+
+fn download_error(code: &'static str, message: impl Into<String>) -> CitadelError {
+    let kind = match code {
+        "DOWNLOAD_INTEGRITY" => ErrorKind::Security,
+        _ => ErrorKind::Network,
+    };
+    CitadelError::new(kind, code, message)
+}
+
+fn io_error(error: std::io::Error) -> CitadelError {
+    CitadelError::new(ErrorKind::Resource, "DOWNLOAD_IO", error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn body(
+        status: u16,
+        content_range: Option<&str>,
+        length: usize,
+        chunks: Vec<Result<Vec<u8>, NetworkError>>,
+    ) -> DownloadBody {
+        DownloadBody {
+            status,
+            content_range: content_range.map(str::to_string),
+            content_length: Some(length as u64),
+            validator: Some("\"v1\"".to_string()),
+            disposition: None,
+            encoded: false,
+            chunks: Box::pin(futures::stream::iter(chunks)),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("citadel-downloads-{}-{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_names_are_safe() {
+        let url = Url::parse("https://files.example/dl/My%20Report.pdf?x=1").unwrap();
+        assert_eq!(file_name(&url, None), "My Report.pdf");
+        assert_eq!(
+            file_name(&url, Some("attachment; filename=\"../../.bashrc\"")),
+            "bashrc"
+        );
+        assert_eq!(
+            file_name(
+                &url,
+                Some("attachment; filename=\"plain.txt\"; filename*=UTF-8''na%C3%AFve.txt")
+            ),
+            "naïve.txt"
+        );
+        let bare = Url::parse("https://files.example/").unwrap();
+        assert_eq!(file_name(&bare, None), "download");
+        assert_eq!(content_range_start("bytes 100-199/200"), Some(100));
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes 100-199/*"), None);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_resumes_and_verifies() {
+        let dir = temp_dir("resume");
+        let manager = DownloadManager::new(dir.clone());
+        let content = b"0123456789abcdefghij";
+        let digest = format!("{:x}", Sha256::digest(content));
+        let url = Url::parse("https://files.example/data.bin").unwrap();
+        let id = manager.start(url, Uuid::new_v4(), Some(digest));
+
+        // The connection drops after the first chunk; what arrived is kept
+        let request = manager.request(id).unwrap();
+        assert!(request.headers.is_empty());
+        let cut = body(
+            200,
+            None,
+            content.len(),
+            vec![
+                Ok(content[..8].to_vec()),
+                Err(NetworkError::ConnectionError("reset".into())),
+            ],
+        );
+        assert!(manager.run(&request, async { Ok(cut) }).await.is_err());
+        let download = manager.get(id).unwrap();
+        assert!(matches!(download.state, DownloadState::Failed(_)));
+        assert_eq!(download.received, 8);
+        assert!(download.part_path().exists());
+
+        // Resuming asks for the rest, and the whole file is verified
+        assert!(manager.resume(id));
+        let request = manager.request(id).unwrap();
+        assert_eq!(
+            request.headers,
+            [
+                ("Range".to_string(), "bytes=8-".to_string()),
+                ("If-Range".to_string(), "\"v1\"".to_string())
+            ]
+        );
+        let rest = body(
+            206,
+            Some("bytes 8-19/20"),
+            12,
+            vec![Ok(content[8..].to_vec())],
+        );
+        let state = manager.run(&request, async { Ok(rest) }).await.unwrap();
+        assert_eq!(state, DownloadState::Completed { verified: true });
+        let download = manager.get(id).unwrap();
+        assert_eq!(std::fs::read(&download.path).unwrap(), content);
+        assert!(!download.part_path().exists());
+        assert_eq!(download.fraction(), Some(1.0));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_digest_deletes_the_file() {
+        let dir = temp_dir("digest");
+        let manager = DownloadManager::new(dir.clone());
+        let url = Url::parse("https://files.example/tool.tar.gz").unwrap();
+        let wrong = format!("{:x}", Sha256::digest(b"something else"));
+        let id = manager.start(url.clone(), Uuid::new_v4(), Some(wrong));
+
+        let request = manager.request(id).unwrap();
+        let error = manager
+            .run(&request, async {
+                Ok(body(200, None, 8, vec![Ok(b"tampered".to_vec())]))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "DOWNLOAD_INTEGRITY");
+        let download = manager.get(id).unwrap();
+        assert!(!download.path.exists());
+        assert!(!download.part_path().exists());
+
+        // A second download of the same name does not clash with the first
+        let other = manager.start(url, Uuid::new_v4(), None);
+        assert_ne!(manager.get(other).unwrap().path, download.path);
+        assert!(manager.cancel(other));
+        assert_eq!(manager.get(other).unwrap().state, DownloadState::Cancelled);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+//! Paste and drag-and-drop into the content area
+//!
+//! Pasted or dropped content is classified before anything is loaded:
+//! HTML files open through the `file://` handler, URLs navigate the current
+//! tab, and any other text becomes a search. Only web URLs are navigated;
+//! `javascript:`, `data:` and similar schemes are searched for as text, so
+//! a paste can never run script or smuggle in a page.
+
+use std::path::Path;
+
+use url::Url;
+
+/// Extensions of files opened as pages when dropped
+const HTML_EXTENSIONS: &[&str] = &["html", "htm", "xhtml"];
+
+/// Longest text turned into a search; the rest is dropped
+const MAX_SEARCH_CHARS: usize = 512;
+
+/// What pasted or dropped content should do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedContent {
+    /// Open a local HTML file
+    OpenFile(Url),
+    /// Navigate the current tab
+    Navigate(Url),
+    /// Search for the text in a new tab
+    Search(String),
+}
+
+impl DroppedContent {
+    /// Classify a dropped file; `None` for files that are not pages
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        if !HTML_EXTENSIONS.contains(&extension.as_str()) {
+            return None;
+        }
+        let path = std::fs::canonicalize(path).ok()?;
+        Url::from_file_path(path).ok().map(Self::OpenFile)
+    }
+
+    /// Classify pasted or dropped text; `None` for blank text
+    pub fn from_text(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        if !text.contains(char::is_whitespace) {
+            if let Some(url) = web_url(text) {
+                return Some(Self::Navigate(url));
+            }
+        }
+        let query = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        Some(Self::Search(query.chars().take(MAX_SEARCH_CHARS).collect()))
+    }
+}
+
+/// A navigable web URL: an explicit http(s) URL, or a bare domain such as
+/// `example.com/page`
+fn web_url(text: &str) -> Option<Url> {
+    if let Ok(url) = Url::parse(text) {
+        return matches!(url.scheme(), "http" | "https").then_some(url);
+    }
+    let url = Url::parse(&format!("https://{}", text)).ok()?;
+    let host = url.host_str()?;
+    let tld = host.rsplit('.').next()?;
+    (host.contains('.') && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+        .then_some(url)
+}
+
+/// Search URL for a query
+pub fn search_url(query: &str) -> String {
+    format!("https://duckduckgo.com/?q={}", urlencoding::encode(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_classification() {
+        assert_eq!(
+            DroppedContent::from_text(" https://example.com/a?b=1\n"),
+            Some(DroppedContent::Navigate(
+                Url::parse("https://example.com/a?b=1").unwrap()
+            ))
+        );
+        assert_eq!(
+            DroppedContent::from_text("example.org/docs"),
+            Some(DroppedContent::Navigate(
+                Url::parse("https://example.org/docs").unwrap()
+            ))
+        );
+        assert_eq!(
+            DroppedContent::from_text("javascript:alert(1)"),
+            Some(DroppedContent::Search("javascript:alert(1)".into()))
+        );
+        assert_eq!(
+            DroppedContent::from_text("privacy  first\nbrowsers"),
+            Some(DroppedContent::Search("privacy first browsers".into()))
+        );
+        assert_eq!(
+            DroppedContent::from_text("v1.2"),
+            Some(DroppedContent::Search("v1.2".into()))
+        );
+        assert_eq!(DroppedContent::from_text("  \n"), None);
+    }
+
+    #[test]
+    fn test_only_html_files_open() {
+        let dir = std::env::temp_dir().join(format!("citadel-drop-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("page.HTML");
+        let binary = dir.join("tool.exe");
+        std::fs::write(&page, "<p>hi</p>").unwrap();
+        std::fs::write(&binary, "MZ").unwrap();
+
+        match DroppedContent::from_path(&page) {
+            Some(DroppedContent::OpenFile(url)) => assert_eq!(url.scheme(), "file"),
+            other => panic!("expected a file to open, got {:?}", other),
+        }
+        assert_eq!(DroppedContent::from_path(&binary), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
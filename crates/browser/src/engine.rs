@@ -1,18 +1,52 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
+use tokio::sync::watch;
 use url::Url;
 
-use citadel_networking::{CitadelDnsResolver, Method, NetworkConfig, Request};
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_networking::resource::ResourceType;
+use citadel_networking::{
+    security_headers, BudgetUsage, CachePartition, CacheStorage, CitadelDnsResolver,
+    ContainerPolicies, CookieJar, CookieStoreId, CspPolicies, EnforcedPolicy, HeaderMap, Method,
+    NetworkConfig, NetworkError, NetworkPartitionKey, PrivacyLevel, ReportOnlyPolicy, Request,
+    RequestBudget, RequestBuilder, RequestLedger, RequestOutcome, RequestRecord, ResourceCache,
+    ResourceCaches, ResourceManager, ResourceManagerConfig, Response, StreamRoute,
+    StreamingResponse, TabBudgets, TlsSessionCache,
+};
 use citadel_parser::{
-    parse_css, parse_html, security::SecurityContext as ParserSecurityContext, CitadelStylesheet,
-    Dom,
+    parse_css_with_config, parse_html_with_config, parse_html_with_resolver,
+    security::SecurityContext as ParserSecurityContext, BaseUrlResolver, CitadelStylesheet, Dom,
+    LanguageHints, ParserConfig, SecurityLevel, UrlResolver,
 };
-use citadel_security::SecurityContext;
+use citadel_security::{Escalation, SecurityContext, ViolationSource};
+use citadel_tabs::TabType;
 
 // Import structured types from app.rs
 use crate::app::{ErrorType, LoadingError, ParsedPageData};
+use crate::assets::IconSet;
+use crate::compat::{self, AppliedCompat, CompatShims};
+use crate::container_policies;
+use crate::content_budget::{self, ContentTruncation};
+use crate::csp_reports::{self, CspReportLog, PageReports};
+use crate::external_protocols::SchemeDispatch;
+use crate::image_decoder::{self, DecodeLimits, DecodedImage};
+use crate::image_viewer::{self, ImageViewer};
+use crate::load_scheduler::LoadScheduler;
+use crate::memory_profile::MemoryLimits;
+#[cfg(feature = "devtools")]
+use crate::net_internals;
+use crate::page_escalation::{self, PageEscalation, PageEscalations};
+use crate::parser_profile::{self, CustomParserProfile};
 use crate::renderer::FormSubmission;
+use crate::resource_caches;
+use crate::resource_loader::ResourceLoader;
+use crate::settings::{SettingsStore, TabPolicy};
+use crate::stylesheet_cache::{StylesheetCache, StylesheetCacheStats};
+use crate::text_only::TextOnlyMode;
+use crate::web_app::WebAppManifest;
+use crate::web_fonts;
 
 /// Browser engine responsible for loading and processing web pages
 #[derive(Debug, Clone)]
@@ -22,11 +56,52 @@ pub struct BrowserEngine {
     runtime: Arc<Runtime>,
     /// Network configuration
     network_config: NetworkConfig,
-    /// Security context for parsing
-    #[allow(dead_code)] // Will be used when implementing security policy enforcement
+    /// Security context each tab's policy starts from
     security_context: Arc<SecurityContext>,
     /// DNS resolver
     dns_resolver: Arc<CitadelDnsResolver>,
+    /// Per-tab request budgets, sized by the privacy level
+    budgets: TabBudgets,
+    /// TLS session tickets, partitioned by top-level site
+    tls_sessions: TlsSessionCache,
+    /// Cookies, per store and top-level site
+    cookies: CookieJar,
+    /// Hosts each container may reach
+    container_policies: ContainerPolicies,
+    /// Per-site compatibility shims
+    compat: Arc<CompatShims>,
+    /// Runtime settings, when the engine follows a settings store
+    settings: Option<Arc<SettingsStore>>,
+    /// Each tab's security context and privacy level, refreshed from the
+    /// settings at navigation
+    tab_policies: Arc<std::sync::Mutex<HashMap<uuid::Uuid, TabPolicy>>>,
+    /// Would-be violations of each tab's report-only CSP
+    csp_reports: CspReportLog,
+    /// Enforced CSP of each tab's page, checked by the tab's subresource
+    /// fetches
+    csp_policies: CspPolicies,
+    /// Violations of each tab's sites, and how far the browser escalated
+    escalations: PageEscalations,
+    /// Which tabs browse text-only
+    text_only: TextOnlyMode,
+    /// Parsed stylesheets reused across navigations
+    stylesheets: StylesheetCache,
+    /// Cache sizes and image dimensions of the memory profile
+    memory_limits: MemoryLimits,
+    /// Cached subresource responses, partitioned like the network state
+    resource_caches: ResourceCaches,
+    /// Parser limits of the Custom privacy level, from the user's profile
+    custom_parser: Arc<ParserConfig>,
+    /// Bounds how many tabs load at once, focused tab first
+    load_scheduler: Arc<LoadScheduler>,
+    /// Set at shutdown; requests in flight are dropped, closing their
+    /// connections
+    closing: Arc<watch::Sender<bool>>,
+    /// Loads page images, once a resource manager is shared with the engine
+    images: Option<Arc<ResourceLoader>>,
+    /// Log of each tab's requests, shared with the resource manager tab
+    /// VMs fetch through
+    requests: RequestLedger,
 }
 
 impl BrowserEngine {
@@ -38,12 +113,183 @@ impl BrowserEngine {
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // Initialize DNS resolver based on configuration
         let dns_resolver = Arc::new(CitadelDnsResolver::new().await?);
+        let budgets = TabBudgets::new(RequestBudget::for_privacy_level(
+            network_config.privacy_level,
+        ));
+        let container_policies = container_policies::default_path()
+            .map(|path| {
+                container_policies::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring container policies at {}: {}", path.display(), e);
+                    ContainerPolicies::new()
+                })
+            })
+            .unwrap_or_default();
+        let compat = compat::default_path()
+            .map(|path| {
+                CompatShims::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring compat shims at {}: {}", path.display(), e);
+                    CompatShims::default()
+                })
+            })
+            .unwrap_or_default();
+        let custom_parser = parser_profile::default_path()
+            .map(|path| {
+                CustomParserProfile::load(&path).unwrap_or_else(|e| {
+                    log::warn!("Ignoring parser profile at {}: {}", path.display(), e);
+                    CustomParserProfile::default()
+                })
+            })
+            .unwrap_or_default()
+            .config();
+        let escalation_thresholds = page_escalation::default_path()
+            .map(|path| {
+                page_escalation::load(&path).unwrap_or_else(|e| {
+                    log::warn!(
+                        "Ignoring escalation thresholds at {}: {}",
+                        path.display(),
+                        e
+                    );
+                    Default::default()
+                })
+            })
+            .unwrap_or_default();
 
         Ok(Self {
             runtime,
             network_config,
             security_context,
             dns_resolver,
+            budgets,
+            tls_sessions: TlsSessionCache::new(),
+            cookies: CookieJar::new(),
+            container_policies,
+            compat: Arc::new(compat),
+            settings: None,
+            tab_policies: Arc::default(),
+            csp_reports: CspReportLog::default(),
+            csp_policies: CspPolicies::new(),
+            escalations: PageEscalations::new(escalation_thresholds),
+            text_only: TextOnlyMode::default(),
+            stylesheets: StylesheetCache::default(),
+            memory_limits: MemoryLimits::default(),
+            resource_caches: ResourceCaches::default(),
+            custom_parser: Arc::new(custom_parser),
+            load_scheduler: Arc::default(),
+            closing: Arc::new(watch::channel(false).0),
+            images: None,
+            requests: RequestLedger::new(),
+        })
+    }
+
+    /// Follow a settings store: DNS mode changes apply at the next lookup,
+    /// privacy and fingerprint protection changes at each tab's next
+    /// navigation
+    pub fn with_settings(mut self, settings: Arc<SettingsStore>) -> Self {
+        let mut resolver = (*self.dns_resolver).clone();
+        resolver.follow_mode(settings.subscribe_dns_mode());
+        self.dns_resolver = Arc::new(resolver);
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Size the engine's caches and page images for the memory profile.
+    /// Stylesheets cached so far are dropped.
+    pub fn with_memory_limits(mut self, limits: &MemoryLimits) -> Self {
+        self.stylesheets = StylesheetCache::new(limits.stylesheet_cache_entries);
+        self.memory_limits = *limits;
+        self
+    }
+
+    /// Keep regular and container tabs' cached responses in `storage`, so
+    /// they outlive the session
+    pub fn with_cache_storage(mut self, storage: Arc<dyn CacheStorage>) -> Self {
+        self.resource_caches = self.resource_caches.with_storage(storage);
+        self
+    }
+
+    /// Load page images through `loader`
+    pub fn with_image_loader(mut self, loader: ResourceLoader) -> Self {
+        self.images = Some(Arc::new(loader));
+        self
+    }
+
+    /// Start a navigation in a tab, applying settings changed since its last
+    /// one, and return the privacy level to load with
+    fn begin_tab_navigation(&self, tab_id: uuid::Uuid) -> PrivacyLevel {
+        let Some(settings) = &self.settings else {
+            return self.network_config.privacy_level;
+        };
+        let Ok(mut policies) = self.tab_policies.lock() else {
+            return self.network_config.privacy_level;
+        };
+        let policy = policies
+            .entry(tab_id)
+            .or_insert_with(|| settings.tab_policy((*self.security_context).clone()));
+        if policy.begin_navigation() {
+            log::info!(
+                "Tab {} now loads with privacy level {:?}",
+                tab_id,
+                policy.privacy_level()
+            );
+        }
+        policy.privacy_level()
+    }
+
+    /// Parser limits pages load with at a privacy level
+    fn parser_config(&self, privacy_level: PrivacyLevel) -> ParserConfig {
+        match parser_profile::security_level(privacy_level) {
+            SecurityLevel::Custom => (*self.custom_parser).clone(),
+            level => ParserConfig::for_level(level),
+        }
+    }
+
+    /// Parse a page that did not come from the network (files, internal pages)
+    async fn local_page(
+        &self,
+        url: &Url,
+        content: String,
+        start_time: std::time::Instant,
+    ) -> Result<ParsedPageData, LoadingError> {
+        let parser_config = ParserConfig::default();
+        let raw_html = content_budget::truncate_html(&content, &parser_config)
+            .unwrap_or(&content)
+            .to_string();
+        let truncation = ContentTruncation {
+            html: raw_html.len() < content.len(),
+            ..Default::default()
+        };
+        let (title, content, element_count, security_warnings, dom, stylesheet, truncation) = self
+            .parse_html_content_enhanced(&raw_html, url.as_str(), &parser_config, truncation, false)
+            .await
+            .map_err(|e| LoadingError {
+                error_type: ErrorType::Content,
+                message: e,
+                url: url.to_string(),
+                timestamp: std::time::SystemTime::now(),
+                retry_possible: true,
+                category: None,
+            })?;
+
+        let language = LanguageHints::analyze(&raw_html, &content, None);
+        let load_time_ms = start_time.elapsed().as_millis() as u64;
+
+        Ok(ParsedPageData {
+            title,
+            content: content.clone(),
+            element_count,
+            size_bytes: content.len(),
+            url: url.to_string(),
+            load_time_ms,
+            security_warnings,
+            dom: Some(dom),
+            stylesheet: Some(stylesheet),
+            raw_html,
+            security_headers: None,
+            language,
+            truncation,
+            escalation: PageEscalation::default(),
+            text_blocks: None,
+            image: None,
         })
     }
 
@@ -52,6 +298,7 @@ impl BrowserEngine {
         mut self,
         config: NetworkConfig,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        self.budgets = TabBudgets::new(RequestBudget::for_privacy_level(config.privacy_level));
         self.network_config = config;
         // Update DNS resolver if mode changed
         self.dns_resolver = Arc::new(CitadelDnsResolver::new().await?);
@@ -63,6 +310,7 @@ impl BrowserEngine {
         &self,
         url: Url,
         tab_id: uuid::Uuid,
+        tab_type: TabType,
     ) -> Result<ParsedPageData, LoadingError> {
         let start_time = std::time::Instant::now();
         log::info!(
@@ -78,6 +326,7 @@ impl BrowserEngine {
                 url: url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: false,
+                category: None,
             })?;
 
             let content = std::fs::read_to_string(path).map_err(|e| LoadingError {
@@ -86,33 +335,48 @@ impl BrowserEngine {
                 url: url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: true,
+                category: None,
             })?;
 
-            let raw_html = content.clone();
-            let (title, content, element_count, security_warnings, dom, stylesheet) = self
-                .parse_html_content_enhanced(&content, url.as_str())
-                .await
-                .map_err(|e| LoadingError {
+            return self.local_page(&url, content, start_time).await;
+        }
+
+        if url.scheme() == "citadel" {
+            let content = match url.host_str() {
+                #[cfg(feature = "devtools")]
+                Some("net-internals") => Some(net_internals::handle(&self.dns_resolver, &url)),
+                Some("csp-reports") => {
+                    Some(csp_reports::render(self.csp_reports.get(tab_id).as_ref()))
+                }
+                Some("cache") => Some(resource_caches::handle(
+                    &self.resource_caches,
+                    self.stylesheet_cache_stats(),
+                    &url,
+                )),
+                _ => None,
+            };
+            let Some(content) = content else {
+                return Err(LoadingError {
                     error_type: ErrorType::Content,
-                    message: e,
+                    message: format!("Unknown internal page: {}", url),
                     url: url.to_string(),
                     timestamp: std::time::SystemTime::now(),
-                    retry_possible: true,
-                })?;
-
-            let load_time_ms = start_time.elapsed().as_millis() as u64;
+                    retry_possible: false,
+                    category: None,
+                });
+            };
+            return self.local_page(&url, content, start_time).await;
+        }
 
-            return Ok(ParsedPageData {
-                title,
-                content: content.clone(),
-                element_count,
-                size_bytes: content.len(),
+        // External schemes go to the OS only through the confirmation prompt
+        if let SchemeDispatch::External(scheme) = SchemeDispatch::for_url(&url) {
+            return Err(LoadingError {
+                error_type: ErrorType::Security,
+                message: format!("{}: links open in an external application", scheme),
                 url: url.to_string(),
-                load_time_ms,
-                security_warnings,
-                dom: Some(dom),
-                stylesheet: Some(stylesheet),
-                raw_html,
+                timestamp: std::time::SystemTime::now(),
+                retry_possible: false,
+                category: None,
             });
         }
 
@@ -124,6 +388,7 @@ impl BrowserEngine {
                 url: url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: false,
+                category: None,
             });
         }
 
@@ -136,6 +401,7 @@ impl BrowserEngine {
                 url: url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: true,
+                category: None,
             })?;
             log::info!("🔒 Upgraded HTTP to HTTPS: {}", https_url);
             https_url
@@ -143,17 +409,90 @@ impl BrowserEngine {
             url
         };
 
+        // Onion names must never fall through to a local resolver
+        if citadel_networking::is_onion_url(&final_url) && self.network_config.socks_proxy.is_none()
+        {
+            return Err(LoadingError {
+                error_type: ErrorType::Security,
+                message: "Onion services need a SOCKS proxy such as Tor".to_string(),
+                url: final_url.to_string(),
+                timestamp: std::time::SystemTime::now(),
+                retry_possible: false,
+                category: None,
+            });
+        }
+
+        // Network loads wait their turn; the slot is held until the page is parsed
+        let _slot = self
+            .load_scheduler
+            .acquire(tab_id)
+            .await
+            .ok_or_else(|| LoadingError {
+                error_type: ErrorType::Internal,
+                message: if self.load_scheduler.is_closed() {
+                    "Load cancelled: the browser is shutting down".to_string()
+                } else {
+                    "Load cancelled: the tab was closed".to_string()
+                },
+                url: final_url.to_string(),
+                timestamp: std::time::SystemTime::now(),
+                retry_possible: false,
+                category: None,
+            })?;
+
+        // A container's host policy is checked before the hostname is resolved
+        let privacy_level = self.begin_tab_navigation(tab_id);
+        let mut builder = Request::builder()
+            .method(Method::GET)
+            .url(final_url.as_str())
+            .privacy_level(privacy_level);
+        if let TabType::Container { container_id } = tab_type {
+            if let Some(reason) = self.container_policies.check(container_id, &final_url) {
+                log::warn!("🚫 {}", reason);
+                return Err(LoadingError {
+                    error_type: ErrorType::Security,
+                    message: reason,
+                    url: final_url.to_string(),
+                    timestamp: std::time::SystemTime::now(),
+                    retry_possible: false,
+                    category: None,
+                });
+            }
+            builder = builder.container(container_id);
+        }
+
+        // Ephemeral tabs resume TLS sessions only within their own lifetime
+        if let Some(key) = NetworkPartitionKey::for_url(&final_url) {
+            builder = builder.partition_key(match tab_type {
+                TabType::Ephemeral => key.in_ephemeral_tab(tab_id),
+                _ => key,
+            });
+        }
+
         // Create HTTP request with privacy settings
-        let request = Request::new(Method::GET, final_url.as_str())
+        let compat = self.compat_for(final_url.as_str());
+        if !compat.reasons.is_empty() {
+            log::info!(
+                "Compat shims for {}: {}",
+                final_url,
+                compat.reasons.join("; ")
+            );
+        }
+        let mut request = builder
+            .build()
             .map_err(|e| LoadingError {
                 error_type: ErrorType::Network,
                 message: format!("Failed to create request: {}", e),
                 url: final_url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: true,
+                category: None,
             })?
-            .with_privacy_level(self.network_config.privacy_level)
             .prepare();
+        // A shim's user agent replaces the privacy level's for this site only
+        if let Some(user_agent) = &compat.user_agent {
+            request.set_header("User-Agent", user_agent);
+        }
 
         // Perform DNS resolution
         let host = final_url.host_str().ok_or_else(|| LoadingError {
@@ -162,26 +501,60 @@ impl BrowserEngine {
             url: final_url.to_string(),
             timestamp: std::time::SystemTime::now(),
             retry_possible: false,
+            category: None,
         })?;
 
         // DNS resolution is handled by the std resolver (TcpStream::connect)
         log::debug!("📍 Using std system DNS resolution for host: {}", host);
 
+        // A new top-level document starts a fresh request budget for the tab
+        let budget = self.budgets.tab(tab_id);
+        budget.start_navigation(&final_url);
+        let budget_error =
+            |e: NetworkError| LoadingError::from_network_error(e, final_url.as_str());
+        let permit = budget
+            .begin(&final_url, ResourceType::Html)
+            .await
+            .map_err(budget_error)?;
+
         // Make HTTP request
-        let response = self
-            .make_http_request(request)
+        let (body, headers) = self
+            .fetch_bytes(request, None, Some(tab_id), ResourceType::Html)
             .await
-            .map_err(|e| LoadingError {
-                error_type: ErrorType::Network,
-                message: e,
-                url: final_url.to_string(),
-                timestamp: std::time::SystemTime::now(),
-                retry_possible: true,
-            })?;
+            .map_err(|e| LoadingError::from_network_error(e, final_url.as_str()))?;
+        drop(permit);
+        budget
+            .record_bytes(body.len() as u64)
+            .map_err(budget_error)?;
+
+        // Images navigated to go to the viewer, never through the HTML parser
+        if headers
+            .get("content-type")
+            .is_some_and(image_viewer::is_viewable)
+        {
+            return self.image_page(&final_url, &body, &headers, start_time);
+        }
+        let response = String::from_utf8_lossy(&body).into_owned();
+
+        // Pages over their HTML budget are cut down before anything parses
+        // them, the tab's isolated parse included
+        let parser_config = self.parser_config(privacy_level);
+        let html = content_budget::truncate_html(&response, &parser_config).unwrap_or(&response);
+        let truncation = ContentTruncation {
+            html: html.len() < response.len(),
+            ..Default::default()
+        };
 
         // Parse and sanitize the HTML content
-        let (title, content, element_count, security_warnings, dom, stylesheet) = self
-            .parse_html_content_enhanced(&response, final_url.as_str())
+        let text_only = self.text_only.is_enabled(tab_id);
+        let (title, content, element_count, security_warnings, dom, stylesheet, truncation) = self
+            .parse_html_content_enhanced(
+                html,
+                final_url.as_str(),
+                &parser_config,
+                truncation,
+                text_only,
+            )
             .await
             .map_err(|e| LoadingError {
                 error_type: ErrorType::Content,
@@ -189,8 +562,55 @@ impl BrowserEngine {
                 url: final_url.to_string(),
                 timestamp: std::time::SystemTime::now(),
                 retry_possible: true,
+                category: None,
             })?;
 
+        // Report-only policies are evaluated, never enforced or reported out
+        let csp_reports = ReportOnlyPolicy::from_headers(&headers)
+            .map(|policy| PageReports::evaluate(&policy, &response, &final_url));
+        if let Some(page) = &csp_reports {
+            if !page.reports.is_empty() {
+                log::info!(
+                    "CSP report-only: {} would-be violations on {}",
+                    page.reports.len(),
+                    final_url
+                );
+            }
+        }
+        self.csp_reports.record(tab_id, csp_reports);
+        // The enforced policy applies to everything the page fetches next
+        self.csp_policies.set(
+            tab_id,
+            &final_url,
+            EnforcedPolicy::from_response(&headers, &body),
+        );
+
+        // Sanitizer hits count towards escalating against the site
+        let escalation = self.escalations.record(
+            tab_id,
+            &final_url,
+            ViolationSource::Sanitizer,
+            dom.metrics.get_elements_blocked(),
+        );
+        if escalation.level > Escalation::None {
+            log::warn!(
+                "🚨 {:?} for {}: {} violations within the window",
+                escalation.level,
+                final_url,
+                escalation.recent
+            );
+        }
+
+        // Text-only tabs, and sites escalated that far, get the renderer's
+        // fast path
+        let text_blocks =
+            (text_only || escalation.level.text_only()).then(|| dom.text_blocks(&final_url));
+
+        let language = LanguageHints::analyze(&response, &content, headers.get("content-type"));
+        if let Some(code) = language.content_language() {
+            log::debug!("Page language {} ({:?})", code, language.source);
+        }
+
         let load_time_ms = start_time.elapsed().as_millis() as u64;
 
         log::info!(
@@ -210,10 +630,570 @@ impl BrowserEngine {
             security_warnings,
             dom: Some(dom),
             stylesheet: Some(stylesheet),
-            raw_html: response.clone(),
+            raw_html: html.to_string(),
+            security_headers: Some(security_headers::audit(&headers, &final_url)),
+            language,
+            truncation,
+            escalation,
+            text_blocks,
+            image: None,
+        })
+    }
+
+    /// An image navigated to, decoded for the image viewer
+    fn image_page(
+        &self,
+        url: &Url,
+        body: &[u8],
+        headers: &HeaderMap,
+        start_time: std::time::Instant,
+    ) -> Result<ParsedPageData, LoadingError> {
+        let image =
+            image_decoder::decode(body, &image_viewer::IMAGE_LIMITS).map_err(|e| LoadingError {
+                error_type: ErrorType::Content,
+                message: format!("Cannot show image: {}", e),
+                url: url.to_string(),
+                timestamp: std::time::SystemTime::now(),
+                retry_possible: false,
+                category: None,
+            })?;
+        let viewer = ImageViewer::new(url.clone(), image, body.len());
+        let load_time_ms = start_time.elapsed().as_millis() as u64;
+        log::info!(
+            "🖼️ Image loaded in {}ms: {} × {}, {} bytes",
+            load_time_ms,
+            viewer.width(),
+            viewer.height(),
+            body.len()
+        );
+
+        Ok(ParsedPageData {
+            title: viewer.title(),
+            content: String::new(),
+            element_count: 0,
+            size_bytes: body.len(),
+            url: url.to_string(),
+            load_time_ms,
+            security_warnings: Vec::new(),
+            dom: None,
+            stylesheet: None,
+            raw_html: String::new(),
+            security_headers: Some(security_headers::audit(headers, url)),
+            language: LanguageHints::default(),
+            truncation: ContentTruncation::default(),
+            escalation: PageEscalation::default(),
+            text_blocks: None,
+            image: Some(viewer),
+        })
+    }
+
+    /// Compatibility shims for a page; none for unparseable URLs
+    pub fn compat_for(&self, url: &str) -> AppliedCompat {
+        Url::parse(url)
+            .map(|url| self.compat.for_url(&url))
+            .unwrap_or_default()
+    }
+
+    /// Request budget consumption of a tab's current page
+    pub fn budget_usage(&self, tab_id: uuid::Uuid) -> Option<BudgetUsage> {
+        self.budgets.usage(tab_id)
+    }
+
+    /// Drop the request budget and log, CSP policy and reports, violation
+    /// counts, text-only choice and waiting load of a closed tab
+    pub fn release_tab_budget(&self, tab_id: uuid::Uuid) {
+        self.load_scheduler.cancel(tab_id);
+        self.budgets.remove(tab_id);
+        self.requests.remove(tab_id);
+        if let Ok(mut policies) = self.tab_policies.lock() {
+            policies.remove(&tab_id);
+        }
+        self.csp_reports.remove(tab_id);
+        self.csp_policies.remove(tab_id);
+        self.escalations.remove_tab(tab_id);
+        self.text_only.remove_tab(tab_id);
+    }
+
+    /// Which tabs browse text-only; a change applies at each tab's next load
+    pub fn text_only(&self) -> &TextOnlyMode {
+        &self.text_only
+    }
+
+    /// Count violations caught outside the engine, such as scripts the
+    /// render cage refused, against the site of a tab's page. Returns where
+    /// the page stands afterwards; `None` for unparseable URLs.
+    pub fn report_violations(
+        &self,
+        tab_id: uuid::Uuid,
+        url: &str,
+        source: ViolationSource,
+        count: usize,
+    ) -> Option<PageEscalation> {
+        let url = Url::parse(url).ok()?;
+        Some(self.escalations.record(tab_id, &url, source, count))
+    }
+
+    /// Let the load of the tab the user is looking at go next, and give its
+    /// requests full concurrency while other tabs' go one at a time
+    pub fn focus_tab(&self, tab_id: uuid::Uuid) {
+        self.load_scheduler.focus(tab_id);
+        self.budgets.set_active(tab_id);
+    }
+
+    /// Throttle tabs' fetches while the device saves power: images go one at
+    /// a time and background tabs share a single request between them
+    pub fn set_power_saving(&self, power_saving: bool) {
+        self.budgets.set_power_saving(power_saving);
+    }
+
+    /// Tabs waiting to load, with their place in line
+    pub fn load_queue(&self) -> Vec<(uuid::Uuid, usize)> {
+        self.load_scheduler.queue()
+    }
+
+    /// Start no more page loads; loads waiting for a slot are cancelled
+    pub fn stop_new_loads(&self) {
+        self.load_scheduler.close();
+    }
+
+    /// Drop every request in flight, closing its connection, and wait up to
+    /// `timeout` for the loads to wind down. Returns whether they all did.
+    pub async fn close_connections(&self, timeout: Duration) -> bool {
+        self.load_scheduler.close();
+        self.closing.send_replace(true);
+        tokio::time::timeout(timeout, self.load_scheduler.drained())
+            .await
+            .is_ok()
+    }
+
+    /// Host policies of containers
+    pub fn container_policies(&self) -> &ContainerPolicies {
+        &self.container_policies
+    }
+
+    /// Content-Security-Policy of each tab's page
+    pub fn csp_policies(&self) -> &CspPolicies {
+        &self.csp_policies
+    }
+
+    /// Log of each tab's requests
+    pub fn request_ledger(&self) -> &RequestLedger {
+        &self.requests
+    }
+
+    /// Drop the TLS session tickets, cookies and cached responses of a
+    /// closed ephemeral tab
+    pub fn release_tab_sessions(&self, tab_id: uuid::Uuid) {
+        self.tls_sessions.clear_tab(tab_id);
+        self.cookies.clear_store(CookieStoreId::Ephemeral(tab_id));
+        self.resource_caches.release_tab(tab_id);
+    }
+
+    /// Forget what the browser keeps about the site of `url` for a tab's
+    /// store: its cookies, TLS session tickets and cached responses.
+    /// Returns how many cookies and cached responses were dropped.
+    pub fn clear_site_data(&self, tab_id: uuid::Uuid, tab_type: TabType, url: &Url) -> usize {
+        let store = match tab_type {
+            TabType::Ephemeral => CookieStoreId::Ephemeral(tab_id),
+            TabType::Container { container_id } => CookieStoreId::Container(container_id),
+        };
+        let cookies = self.cookies.delete_for_site(store, url);
+        let cached = url
+            .host_str()
+            .map(|host| self.resource_caches.purge_host(host))
+            .unwrap_or(0);
+        if let Some(partition) = NetworkPartitionKey::for_url(url) {
+            self.tls_sessions.clear_site(&partition.top_level_site);
+        }
+        cookies + cached
+    }
+
+    /// Cookies of every tab, for listing and deleting them per origin
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// A resource manager for tab VMs' requests, sharing the engine's
+    /// container and CSP policies, request ledger, cookies, request budgets
+    /// and partitioned response caches, so that wiping the engine wipes what
+    /// tabs fetched too
+    pub async fn tab_resource_manager(&self) -> Result<ResourceManager, NetworkError> {
+        let config = ResourceManagerConfig {
+            network_config: self.network_config.clone(),
+            ..ResourceManagerConfig::default()
+        };
+        Ok(ResourceManager::with_config(config)
+            .await?
+            .with_container_policies(self.container_policies.clone())
+            .with_csp_policies(self.csp_policies.clone())
+            .with_request_ledger(self.requests.clone())
+            .with_cookie_jar(self.cookies.clone())
+            .with_budgets(self.budgets.clone())
+            .with_resource_caches(self.resource_caches.clone()))
+    }
+
+    /// Hit and miss counts of the parsed stylesheet cache
+    pub fn stylesheet_cache_stats(&self) -> StylesheetCacheStats {
+        self.stylesheets.stats()
+    }
+
+    /// Forget everything the engine learned this session: TLS session
+    /// tickets, cookies, cached DNS answers, request budgets, tab policies, CSP
+    /// policies and reports, violation counts, tabs' text-only choices, parsed
+    /// stylesheets and cached responses
+    pub fn wipe_session_state(&self) {
+        self.tls_sessions.clear();
+        self.cookies.clear();
+        self.dns_resolver.clear_cache();
+        self.budgets.clear();
+        if let Ok(mut policies) = self.tab_policies.lock() {
+            policies.clear();
+        }
+        self.csp_reports.clear();
+        self.csp_policies.clear();
+        self.escalations.clear();
+        self.text_only.clear_tabs();
+        self.stylesheets.clear();
+        self.resource_caches.clear();
+    }
+
+    /// Fetch and parse the web app manifest linked from a tab's page. The
+    /// request is made like a subresource of the page: under the tab's
+    /// privacy level, container policy, partition and request budget.
+    pub async fn fetch_manifest(
+        &self,
+        manifest_url: &Url,
+        document_url: &Url,
+        tab_id: uuid::Uuid,
+        tab_type: TabType,
+    ) -> Result<WebAppManifest, CitadelError> {
+        if manifest_url.scheme() != "https" {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "WEBAPP_INSECURE_MANIFEST",
+                format!("manifest is not served over HTTPS: {}", manifest_url),
+            ));
+        }
+
+        let (request, cache) = self
+            .subresource_request(manifest_url, document_url, tab_id, tab_type)
+            .map_err(|reason| {
+                CitadelError::new(ErrorKind::Security, "WEBAPP_CONTAINER_POLICY", reason)
+            })?;
+        let request = request.build()?.prepare();
+        let cached = cache
+            .as_ref()
+            .and_then(|cache| cache.get(manifest_url))
+            .and_then(|response| response.body_text().ok());
+        let body = match cached {
+            Some(body) => body,
+            None => {
+                let budget = self.budgets.tab(tab_id);
+                let permit = budget.begin(manifest_url, ResourceType::Json).await?;
+                let (body, headers) = self
+                    .make_http_request(
+                        request,
+                        Some(document_url),
+                        Some(tab_id),
+                        ResourceType::Json,
+                    )
+                    .await?;
+                drop(permit);
+                budget.record_bytes(body.len() as u64)?;
+                if let Some(cache) = &cache {
+                    let response = Response::new(
+                        200,
+                        headers,
+                        body.clone().into(),
+                        manifest_url.clone(),
+                        Method::GET,
+                    );
+                    if let Err(e) = cache.put(manifest_url, response) {
+                        log::debug!("Not caching manifest {}: {}", manifest_url, e);
+                    }
+                }
+                body
+            }
+        };
+
+        WebAppManifest::parse(&body, manifest_url, document_url).map_err(|e| {
+            CitadelError::new(ErrorKind::Content, "WEBAPP_INVALID_MANIFEST", e.to_string())
         })
     }
 
+    /// Fetch the icon of a tab's page, as a web app being installed shows
+    /// it, and make it into desktop icons. The request is made like any
+    /// subresource of the page, under its Content-Security-Policy
+    /// `img-src`.
+    pub async fn fetch_icon(
+        &self,
+        icon_url: &Url,
+        document_url: &Url,
+        tab_id: uuid::Uuid,
+        tab_type: TabType,
+    ) -> Result<IconSet, CitadelError> {
+        if icon_url.scheme() != "https" {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "ICON_INSECURE",
+                format!("icon is not served over HTTPS: {}", icon_url),
+            ));
+        }
+        if let Some(report) = self
+            .csp_policies
+            .check(tab_id, icon_url, ResourceType::Image)
+        {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "ICON_CSP_BLOCKED",
+                format!(
+                    "Blocked by Content-Security-Policy {}",
+                    report.violated_directive
+                ),
+            ));
+        }
+
+        let (request, cache) = self
+            .subresource_request(icon_url, document_url, tab_id, tab_type)
+            .map_err(|reason| {
+                CitadelError::new(ErrorKind::Security, "ICON_CONTAINER_POLICY", reason)
+            })?;
+        let request = request.build()?.prepare();
+        if let Some(response) = cache.as_ref().and_then(|cache| cache.get(icon_url)) {
+            return IconSet::from_bytes(response.body());
+        }
+
+        let budget = self.budgets.tab(tab_id);
+        let permit = budget.begin(icon_url, ResourceType::Image).await?;
+        let (body, _) = self
+            .fetch_bytes(
+                request,
+                Some(document_url),
+                Some(tab_id),
+                ResourceType::Image,
+            )
+            .await?;
+        drop(permit);
+        budget.record_bytes(body.len() as u64)?;
+        IconSet::from_bytes(&body)
+    }
+
+    /// Fetch and decode an image of a tab's page, under the page's
+    /// Content-Security-Policy `img-src`. Tabs browsing text-only load none.
+    pub async fn load_image(
+        &self,
+        image_url: &Url,
+        tab_id: uuid::Uuid,
+    ) -> Result<DecodedImage, CitadelError> {
+        let refused =
+            |reason: &str| CitadelError::new(ErrorKind::Resource, "IMAGE_NOT_LOADED", reason);
+        if self.text_only.is_enabled(tab_id) {
+            return Err(refused("tab browses text-only"));
+        }
+        let Some(loader) = &self.images else {
+            return Err(refused("no image loader"));
+        };
+        let image = loader
+            .load_image_for_tab(tab_id, image_url, &DecodeLimits::default())
+            .await
+            .map_err(|reason| refused(&reason))?;
+        // Large images are kept at no more than the profile's dimensions
+        let size = self.memory_limits.decoded_size(image.width, image.height);
+        Ok(image.downscaled(size))
+    }
+
+    /// Fetch a web font offered by a tab's page. The request is made like
+    /// any subresource of the page, and only if the page's
+    /// Content-Security-Policy allows fonts from `font_url`. The bytes are
+    /// checked to be a font before they are returned or cached.
+    pub async fn fetch_font(
+        &self,
+        font_url: &Url,
+        document_url: &Url,
+        tab_id: uuid::Uuid,
+        tab_type: TabType,
+    ) -> Result<Vec<u8>, CitadelError> {
+        if font_url.scheme() != "https" {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "FONT_INSECURE",
+                format!("font is not served over HTTPS: {}", font_url),
+            ));
+        }
+        if let Some(report) = self
+            .csp_policies
+            .check(tab_id, font_url, ResourceType::Font)
+        {
+            log::info!(
+                "🛡️ CSP {} blocked {}",
+                report.effective_directive,
+                report.blocked
+            );
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "FONT_CSP_BLOCKED",
+                format!(
+                    "Blocked by Content-Security-Policy {}",
+                    report.violated_directive
+                ),
+            ));
+        }
+
+        let (request, cache) = self
+            .subresource_request(font_url, document_url, tab_id, tab_type)
+            .map_err(|reason| {
+                CitadelError::new(ErrorKind::Security, "FONT_CONTAINER_POLICY", reason)
+            })?;
+        let request = request.build()?.prepare();
+        if let Some(response) = cache.as_ref().and_then(|cache| cache.get(font_url)) {
+            return Ok(response.body().to_vec());
+        }
+
+        let budget = self.budgets.tab(tab_id);
+        let permit = budget.begin(font_url, ResourceType::Font).await?;
+        let (body, headers) = self
+            .fetch_bytes(
+                request,
+                Some(document_url),
+                Some(tab_id),
+                ResourceType::Font,
+            )
+            .await?;
+        drop(permit);
+        budget.record_bytes(body.len() as u64)?;
+        web_fonts::validate_font(&body)?;
+        if let Some(cache) = &cache {
+            let response = Response::new(
+                200,
+                headers,
+                body.clone().into(),
+                font_url.clone(),
+                Method::GET,
+            );
+            if let Err(e) = cache.put(font_url, response) {
+                log::debug!("Not caching font {}: {}", font_url, e);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Start fetching a download for a tab. The file is requested as the
+    /// tab would navigate to it, with its cookies, container policy and
+    /// network partition, and its body is handed out as it arrives rather
+    /// than buffered. `range_headers` ask for the rest of a partial file.
+    pub async fn open_download(
+        &self,
+        url: &Url,
+        tab_id: uuid::Uuid,
+        tab_type: TabType,
+        range_headers: &[(String, String)],
+    ) -> Result<StreamingResponse, CitadelError> {
+        if url.scheme() != "https" {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "DOWNLOAD_INSECURE",
+                format!("download is not served over HTTPS: {}", url),
+            ));
+        }
+        if citadel_networking::is_onion_url(url) && self.network_config.socks_proxy.is_none() {
+            return Err(CitadelError::new(
+                ErrorKind::Security,
+                "DOWNLOAD_ONION_UNROUTED",
+                "Onion services need a SOCKS proxy such as Tor",
+            ));
+        }
+        let (request, _) = self
+            .subresource_request(url, url, tab_id, tab_type)
+            .map_err(|reason| {
+                CitadelError::new(ErrorKind::Security, "DOWNLOAD_CONTAINER_POLICY", reason)
+            })?;
+        let request = request.build()?.prepare();
+
+        let mut headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        headers.extend_from_slice(range_headers);
+        let cookie_store = CookieStoreId::for_request(&request);
+        if let Some(cookie) = self
+            .cookies
+            .cookie_header(cookie_store, request.url(), request.url())
+        {
+            headers.push(("Cookie".to_string(), cookie));
+        }
+
+        let isolation = tab_id.to_string();
+        let route = match (&self.network_config.socks_proxy, request.partition_key()) {
+            (Some(proxy), _) => StreamRoute::Proxy {
+                proxy,
+                isolation: Some(isolation.as_str()),
+            },
+            (None, Some(partition)) => StreamRoute::Partitioned {
+                sessions: &self.tls_sessions,
+                partition,
+            },
+            (None, None) => StreamRoute::Direct,
+        };
+        let response =
+            citadel_networking::https_fetch_streaming(request.url(), &headers, route).await?;
+        if !(200..300).contains(&response.status) {
+            return Err(NetworkError::HttpStatus(response.status).into());
+        }
+
+        let served_from = Url::parse(&response.final_url).unwrap_or_else(|_| request.url().clone());
+        self.cookies.set_cookies(
+            cookie_store,
+            &served_from,
+            &served_from,
+            HeaderMap::from(response.headers.clone()).get_all("set-cookie"),
+        );
+        Ok(response)
+    }
+
+    /// A request for `url` as a subresource of a tab's page at
+    /// `document_url`: under the tab's privacy level, container and
+    /// partition. Comes with the partition's response cache, and fails with
+    /// the reason if the container's policy refuses the host.
+    fn subresource_request(
+        &self,
+        url: &Url,
+        document_url: &Url,
+        tab_id: uuid::Uuid,
+        tab_type: TabType,
+    ) -> Result<(RequestBuilder, Option<Arc<ResourceCache>>), String> {
+        let privacy_level = self
+            .tab_policies
+            .lock()
+            .ok()
+            .and_then(|policies| policies.get(&tab_id).map(TabPolicy::privacy_level))
+            .unwrap_or(self.network_config.privacy_level);
+        let mut builder = Request::builder()
+            .method(Method::GET)
+            .url(url.as_str())
+            .privacy_level(privacy_level);
+        if let TabType::Container { container_id } = tab_type {
+            if let Some(reason) = self.container_policies.check(container_id, url) {
+                return Err(reason);
+            }
+            builder = builder.container(container_id);
+        }
+        if let Some(key) = NetworkPartitionKey::for_url(document_url) {
+            builder = builder.partition_key(match tab_type {
+                TabType::Ephemeral => key.in_ephemeral_tab(tab_id),
+                _ => key,
+            });
+        }
+
+        let cache = CachePartition::for_url(document_url).map(|partition| {
+            self.resource_caches.for_partition(match tab_type {
+                TabType::Ephemeral => partition.in_ephemeral_tab(tab_id),
+                TabType::Container { container_id } => partition.in_container(container_id),
+            })
+        });
+        Ok((builder, cache))
+    }
+
     /// Load a web page from the given URL (legacy method)
     pub async fn load_page(&self, url: Url) -> Result<String, String> {
         log::info!("Loading page: {}", url);
@@ -236,16 +1216,22 @@ impl BrowserEngine {
         };
 
         // Create HTTP request with privacy settings
-        let request = Request::new(Method::GET, final_url.as_str())
+        let request = Request::builder()
+            .method(Method::GET)
+            .url(final_url.as_str())
+            .privacy_level(self.network_config.privacy_level)
+            .build()
             .map_err(|e| format!("Failed to create request: {}", e))?
-            .with_privacy_level(self.network_config.privacy_level)
             .prepare();
 
         // DNS resolution is handled by the std resolver (TcpStream::connect)
         log::debug!("📍 Using std system DNS resolution");
 
         // Make HTTP request
-        let response = self.make_http_request(request).await?;
+        let (response, _) = self
+            .make_http_request(request, None, None, ResourceType::Html)
+            .await
+            .map_err(|e| e.to_string())?;
 
         // Parse and sanitize the HTML content
         let (title, content, element_count) = self
@@ -267,38 +1253,181 @@ impl BrowserEngine {
     }
 
     /// Make an HTTP request using the in-house HTTPS client (no reqwest/hyper).
-    async fn make_http_request(&self, request: Request) -> Result<String, String> {
+    /// Through a SOCKS proxy, `tab_id` keeps the tab's streams on their own
+    /// circuits. Failures are retried as their category's retry policy says.
+    /// A subresource names its page's `document_url`, whose site its cookies
+    /// are kept under; a document loaded for itself has none.
+    async fn make_http_request(
+        &self,
+        request: Request,
+        document_url: Option<&Url>,
+        tab_id: Option<uuid::Uuid>,
+        resource_type: ResourceType,
+    ) -> Result<(String, HeaderMap), NetworkError> {
+        let (body, headers) = self
+            .fetch_bytes(request, document_url, tab_id, resource_type)
+            .await?;
+        Ok((String::from_utf8_lossy(&body).into_owned(), headers))
+    }
+
+    /// [`Self::make_http_request`] for binary bodies, which are returned as
+    /// they came
+    async fn fetch_bytes(
+        &self,
+        request: Request,
+        document_url: Option<&Url>,
+        tab_id: Option<uuid::Uuid>,
+        resource_type: ResourceType,
+    ) -> Result<(Vec<u8>, HeaderMap), NetworkError> {
         // The in-house client is GET-only for now (page loads are GET). Form POST
         // will need a small extension to citadel_networking::http.
         if !matches!(request.method(), Method::GET) {
-            return Err("only GET is supported by the in-house HTTPS client".to_string());
+            return Err(NetworkError::ResourceError(
+                "only GET is supported by the in-house HTTPS client".to_string(),
+            ));
+        }
+
+        // Shutdown drops the request, and with it the connection
+        let mut closing = self.closing.subscribe();
+        tokio::select! {
+            result = self.fetch_with_retries(&request, document_url, tab_id, resource_type) => result,
+            _ = closing.wait_for(|closing| *closing) => Err(NetworkError::ConnectionError(
+                "the browser is shutting down".to_string(),
+            )),
         }
+    }
+
+    /// [`Self::fetch_once`], retried as the failure's category allows
+    async fn fetch_with_retries(
+        &self,
+        request: &Request,
+        document_url: Option<&Url>,
+        tab_id: Option<uuid::Uuid>,
+        resource_type: ResourceType,
+    ) -> Result<(Vec<u8>, HeaderMap), NetworkError> {
+        let mut attempt = 0;
+        loop {
+            let error = match self
+                .fetch_once(request, document_url, tab_id, resource_type)
+                .await
+            {
+                Ok(fetched) => return Ok(fetched),
+                Err(error) => error,
+            };
+            let category = error.category();
+            let Some(delay) = category.retry_policy().delay(attempt) else {
+                return Err(error);
+            };
+            log::info!(
+                "Retrying {} in {:?} after {:?} failure: {}",
+                request.url(),
+                delay,
+                category,
+                error
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// One attempt of [`Self::make_http_request`], logged to the tab's
+    /// request ledger
+    async fn fetch_once(
+        &self,
+        request: &Request,
+        document_url: Option<&Url>,
+        tab_id: Option<uuid::Uuid>,
+        resource_type: ResourceType,
+    ) -> Result<(Vec<u8>, HeaderMap), NetworkError> {
+        let log = |outcome: RequestOutcome| {
+            if let Some(tab_id) = tab_id {
+                self.requests.record(
+                    tab_id,
+                    RequestRecord::new(
+                        request.url().clone(),
+                        request.method().clone(),
+                        resource_type,
+                        outcome,
+                    ),
+                );
+            }
+        };
 
-        // Forward the privacy headers the Request was prepared with.
-        let headers: Vec<(String, String)> = request
+        // Forward the privacy headers the Request was prepared with, and
+        // the cookies kept under the page's site
+        let mut headers: Vec<(String, String)> = request
             .headers()
             .iter()
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
+        let cookie_store = CookieStoreId::for_request(request);
+        let top_level = document_url.unwrap_or(request.url());
+        if let Some(cookie) = self
+            .cookies
+            .cookie_header(cookie_store, top_level, request.url())
+        {
+            headers.push(("Cookie".to_string(), cookie));
+        }
 
-        let response = citadel_networking::https_fetch(request.url(), &headers)
-            .await
-            .map_err(|e| format!("HTTP request failed: {e}"))?;
+        let response = match (&self.network_config.socks_proxy, request.partition_key()) {
+            (Some(proxy), _) => {
+                let isolation = tab_id.map(|id| id.to_string());
+                citadel_networking::https_fetch_via_proxy(
+                    request.url(),
+                    &headers,
+                    proxy,
+                    isolation.as_deref(),
+                )
+                .await
+            }
+            (None, Some(partition)) => {
+                citadel_networking::https_fetch_partitioned(
+                    request.url(),
+                    &headers,
+                    &self.tls_sessions,
+                    partition,
+                )
+                .await
+            }
+            (None, None) => citadel_networking::https_fetch(request.url(), &headers).await,
+        }
+        .map_err(|e| {
+            log(RequestOutcome::Failed(e.to_string()));
+            e
+        })?;
 
+        log(RequestOutcome::Completed {
+            status: response.status,
+            bytes: response.body.len() as u64,
+            from_cache: false,
+        });
         if !(200..300).contains(&response.status) {
-            return Err(format!("HTTP error: status {}", response.status));
+            return Err(NetworkError::HttpStatus(response.status));
         }
 
-        let content = response.body_text();
-        log::info!("Successfully fetched {} bytes", content.len());
-        Ok(content)
+        log::info!("Successfully fetched {} bytes", response.body.len());
+        let headers = HeaderMap::from(response.headers);
+        let served_from = Url::parse(&response.final_url).unwrap_or_else(|_| request.url().clone());
+        self.cookies.set_cookies(
+            cookie_store,
+            document_url.unwrap_or(&served_from),
+            &served_from,
+            headers.get_all("set-cookie"),
+        );
+        Ok((response.body, headers))
     }
 
-    /// Parse HTML content with enhanced security and privacy protections
+    /// Parse HTML content with enhanced security and privacy protections.
+    /// The DOM and the page's CSS are held to the profile's content budgets;
+    /// `truncation` says whether the HTML already was. With `text_only` the
+    /// page's own CSS is left out.
     async fn parse_html_content_enhanced(
         &self,
         html: &str,
         url: &str,
+        parser_config: &ParserConfig,
+        mut truncation: ContentTruncation,
+        text_only: bool,
     ) -> Result<
         (
             String,
@@ -307,6 +1436,7 @@ impl BrowserEngine {
             Vec<String>,
             Arc<Dom>,
             Arc<CitadelStylesheet>,
+            ContentTruncation,
         ),
         String,
     > {
@@ -326,16 +1456,44 @@ impl BrowserEngine {
         }
 
         // Parse HTML using citadel-parser
-        // Convert security context from citadel-security to citadel-parser format
-        let parser_security_context = Arc::new(ParserSecurityContext::new(15)); // 15 max nesting depth
+        // Convert security context from citadel-security to citadel-parser format.
+        // Compat shims may keep extra attributes, for their own sites only.
+        let mut policy = self.compat_for(url).sanitizer_policy();
+        // URL attributes are made absolute against the page; internal and
+        // local pages keep their links within their own scheme
+        let base_url = Url::parse(url).ok();
+        if let Some(base_url) = base_url
+            .as_ref()
+            .filter(|base_url| !matches!(base_url.scheme(), "http" | "https"))
+        {
+            policy = policy.allow_schemes([base_url.scheme()]);
+        }
+        let resolver = base_url.map(|base_url| {
+            Arc::new(BaseUrlResolver::new(base_url)) as Arc<dyn UrlResolver + Send + Sync>
+        });
+        // Limits follow the tab's privacy level; the DOM is truncated at the
+        // profile's element depth and element count
+        let parser_security_context =
+            ParserSecurityContext::with_policy(parser_config.max_depth, policy);
+        let max_text_bytes = parser_security_context.max_text_bytes();
+        let parser_security_context = Arc::new(
+            parser_security_context.with_content_limits(parser_config.max_elements, max_text_bytes),
+        );
 
         log::info!(
-            "🔍 Starting HTML parsing for {} ({} bytes)",
+            "🔍 Starting HTML parsing for {} ({} bytes, {:?} profile)",
             url,
-            html.len()
+            html.len(),
+            parser_config.security_level
         );
-        let dom = parse_html(html, parser_security_context)
-            .map_err(|e| format!("HTML parsing failed: {}", e))?;
+        let dom = parse_off_thread(
+            html,
+            parser_security_context,
+            parser_config.clone(),
+            resolver,
+        )
+        .await?;
+        truncation.dom = dom.truncated;
         log::info!("✅ DOM parsing completed successfully");
 
         // Debug: Check DOM structure
@@ -410,7 +1568,7 @@ impl BrowserEngine {
                                                     log::info!("          Body child {}: <{}> with {} children", k, be.local_name(), body_child_node.children().len());
                                                 }
                                                 citadel_parser::dom::NodeData::Text(t) => {
-                                                    log::info!("          Body child {}: TEXT '{}' ({} chars)", k, t.trim(), t.len());
+                                                    log::debug!("          Body child {}: TEXT '{}' ({} chars)", k, t.trim(), t.len());
                                                 }
                                                 _ => {
                                                     log::info!(
@@ -429,7 +1587,7 @@ impl BrowserEngine {
                                     }
                                 }
                                 citadel_parser::dom::NodeData::Text(t) => {
-                                    log::info!(
+                                    log::debug!(
                                         "      HTML child {}: TEXT '{}' ({} chars)",
                                         j,
                                         t.trim(),
@@ -444,17 +1602,17 @@ impl BrowserEngine {
                     }
                 }
                 citadel_parser::dom::NodeData::Text(t) => {
-                    log::info!("  └─ Child {}: TEXT '{}' ({} chars)", i, t.trim(), t.len());
+                    log::debug!("  └─ Child {}: TEXT '{}' ({} chars)", i, t.trim(), t.len());
                 }
                 _ => {
-                    log::info!("  └─ Child {}: {:?}", i, child.data);
+                    log::debug!("  └─ Child {}: {:?}", i, child.data);
                 }
             }
         }
 
         // Extract page title from DOM
         let title = dom.get_title();
-        log::info!("📄 Extracted title: '{}'", title);
+        log::debug!("📄 Extracted title: '{}'", title);
         let title = if title.is_empty() {
             // Try to extract from URL as fallback
             if let Ok(parsed_url) = Url::parse(url) {
@@ -477,7 +1635,7 @@ impl BrowserEngine {
             } else {
                 content.clone()
             };
-            log::info!("📖 Content preview: {}", preview);
+            log::debug!("📖 Content preview: {}", preview);
         } else {
             log::warn!("⚠️  No content extracted from DOM!");
         }
@@ -486,8 +1644,13 @@ impl BrowserEngine {
         let element_count = self.count_elements(html);
 
         // Extract and parse actual CSS from the webpage
-        log::info!("🎨 Extracting CSS from website content");
-        let extracted_css = self.extract_css_from_dom(&dom);
+        let extracted_css = if text_only {
+            log::info!("📄 Text-only mode: leaving out website CSS");
+            String::new()
+        } else {
+            log::info!("🎨 Extracting CSS from website content");
+            self.extract_css_from_dom(&dom)
+        };
         log::info!("📋 Extracted {} bytes of CSS from DOM", extracted_css.len());
 
         // Create base CSS for proper rendering
@@ -514,27 +1677,61 @@ impl BrowserEngine {
             .quote { font-style: italic; color: #555; }
         "#;
 
+        // The website CSS gets what the stylesheet budget leaves after the base
+        const EXTRACTED_CSS_HEADER: &str = "\n\n/* Extracted Website CSS */\n";
+        let css_budget = parser_config
+            .max_css_size
+            .saturating_sub(base_css.len() + EXTRACTED_CSS_HEADER.len());
+        let extracted_css = match content_budget::truncate_css(&extracted_css, css_budget) {
+            Some(kept) => {
+                log::warn!(
+                    "✂️ Website CSS cut from {} to {} bytes",
+                    extracted_css.len(),
+                    kept.len()
+                );
+                truncation.stylesheet = true;
+                kept.to_string()
+            }
+            None => extracted_css,
+        };
+
         // Combine base CSS with extracted website CSS
         let combined_css = if extracted_css.is_empty() {
             log::info!("📝 Using base CSS only (no website CSS found)");
             base_css.to_string()
         } else {
             log::info!("🔗 Combining base CSS with extracted website CSS");
-            format!(
-                "{}\n\n/* Extracted Website CSS */\n{}",
-                base_css, extracted_css
-            )
+            format!("{}{}{}", base_css, EXTRACTED_CSS_HEADER, extracted_css)
         };
 
-        let parser_security_context_css = Arc::new(ParserSecurityContext::new(15));
-        let stylesheet = parse_css(&combined_css, parser_security_context_css)
+        // Inline CSS belongs to the page's origin, so pages of one site that
+        // share their <style> blocks share the parse
+        let css_source = Url::parse(url)
+            .map(|page| page.origin().ascii_serialization())
+            .unwrap_or_else(|_| url.to_string());
+        let stylesheet = self
+            .stylesheets
+            .get_or_parse(
+                &css_source,
+                &combined_css,
+                parser_config.security_level,
+                |css| parse_css_with_config(css, parser_config.clone()),
+            )
             .map_err(|e| format!("CSS parsing failed: {}", e))?;
 
+        let cache_stats = self.stylesheets.stats();
         log::info!(
-            "✅ CSS parsing completed: {} rules parsed",
-            stylesheet.rules.len()
+            "✅ CSS ready: {} rules (stylesheet cache: {} hits, {} misses)",
+            stylesheet.rules.len(),
+            cache_stats.hits,
+            cache_stats.misses
         );
 
+        if let Some(banner) = truncation.banner() {
+            log::warn!("✂️ {} for {}", banner, url);
+            security_warnings.push(banner);
+        }
+
         log::info!(
             "✅ Successfully parsed page: {} elements, {} bytes, {} warnings",
             element_count,
@@ -548,7 +1745,8 @@ impl BrowserEngine {
             element_count,
             security_warnings,
             Arc::new(dom),
-            Arc::new(stylesheet),
+            stylesheet,
+            truncation,
         ))
     }
 
@@ -562,9 +1760,9 @@ impl BrowserEngine {
 
         // Parse HTML using citadel-parser
         // Convert security context from citadel-security to citadel-parser format
-        let parser_security_context = Arc::new(ParserSecurityContext::new(15)); // 15 max nesting depth
-        let dom = parse_html(html, parser_security_context)
-            .map_err(|e| format!("HTML parsing failed: {}", e))?;
+        let parser_config = ParserConfig::default();
+        let parser_security_context = Arc::new(ParserSecurityContext::new(parser_config.max_depth));
+        let dom = parse_off_thread(html, parser_security_context, parser_config, None).await?;
 
         // Extract page title from DOM
         let title = dom.get_title();
@@ -861,7 +2059,10 @@ impl BrowserEngine {
             }
         };
 
-        let mut request = Request::new(method, target_url.as_str())
+        let mut request = Request::builder()
+            .method(method)
+            .url(target_url.as_str())
+            .build()
             .map_err(|e| format!("Failed to create request: {}", e))?;
 
         // NOTE: User-Agent / Accept / Accept-Language are NOT set here. The
@@ -914,7 +2115,10 @@ impl BrowserEngine {
 
                 #[allow(unused_assignments)] // Intentional request reassignment for GET with query
                 {
-                    request = Request::new(Method::GET, url_with_query.as_str())
+                    request = Request::builder()
+                        .method(Method::GET)
+                        .url(url_with_query.as_str())
+                        .build()
                         .map_err(|e| format!("Failed to create GET request: {}", e))?;
                 }
                 log::info!(
@@ -960,6 +2164,24 @@ impl BrowserEngine {
     // Note: HTTP client creation moved to networking layer for proper abstraction
 }
 
+/// Parse a document on the blocking pool so large pages do not stall the
+/// runtime's worker threads; the `Dom` is `Send`, so it comes straight back
+async fn parse_off_thread(
+    html: &str,
+    security_context: Arc<ParserSecurityContext>,
+    config: ParserConfig,
+    resolver: Option<Arc<dyn UrlResolver + Send + Sync>>,
+) -> Result<Dom, String> {
+    let html = html.to_string();
+    tokio::task::spawn_blocking(move || match resolver {
+        Some(resolver) => parse_html_with_resolver(&html, security_context, &config, resolver),
+        None => parse_html_with_config(&html, security_context, &config),
+    })
+    .await
+    .map_err(|e| format!("HTML parsing task failed: {}", e))?
+    .map_err(|e| format!("HTML parsing failed: {}", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -999,7 +2221,7 @@ mod tests {
             // Test invalid URL scheme
             let invalid_url = Url::parse("ftp://example.com").expect("URL parsing should succeed");
             let load_result = engine
-                .load_page_with_progress(invalid_url, uuid::Uuid::new_v4())
+                .load_page_with_progress(invalid_url, uuid::Uuid::new_v4(), TabType::Ephemeral)
                 .await;
 
             // Return both engine and load_result so we can drop engine outside the async context
@@ -1016,4 +2238,37 @@ mod tests {
             assert!(error.message.contains("Unsupported URL scheme"));
         }
     }
+
+    #[test]
+    fn test_wipe_session_state() {
+        let rt = tokio::runtime::Runtime::new().expect("Runtime creation should succeed in tests");
+        let engine = rt
+            .block_on(async {
+                let engine_rt =
+                    tokio::runtime::Runtime::new().expect("Engine runtime creation should succeed");
+                BrowserEngine::new(
+                    Arc::new(engine_rt),
+                    NetworkConfig::default(),
+                    Arc::new(SecurityContext::new(10)),
+                )
+                .await
+            })
+            .expect("Engine creation should succeed")
+            .with_settings(Arc::new(SettingsStore::default()));
+
+        let tab_id = uuid::Uuid::new_v4();
+        engine
+            .budgets
+            .tab(tab_id)
+            .start_navigation(&Url::parse("https://example.com/").unwrap());
+        engine.begin_tab_navigation(tab_id);
+        assert!(engine.budget_usage(tab_id).is_some());
+
+        engine.wipe_session_state();
+        assert!(engine.budget_usage(tab_id).is_none());
+        assert!(engine.tab_policies.lock().unwrap().is_empty());
+        assert_eq!(engine.tls_sessions.partition_count(), 0);
+        assert!(engine.dns_resolver.cached_entries().is_empty());
+        drop(engine);
+    }
 }
@@ -0,0 +1,266 @@
+//! Content script extensions
+//!
+//! A deliberately small alternative to a WebExtensions engine: an extension is
+//! a local directory holding a `manifest.json` that lists match patterns and
+//! JS/CSS files. For matching pages the CSS joins the user stylesheet and the
+//! JS runs at document-ready inside the tab's ZKVM boundary, in a JS context
+//! whose only capability is the page's DOM.
+//!
+//! Manifests are checked on load: the only permission that exists is `dom`,
+//! files must live inside the extension's directory, and each file is capped
+//! at [`MAX_FILE_BYTES`]. An extension failing any check is not loaded.
+
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::user_styles::pattern_matches;
+
+/// Environment variable overriding where extensions are installed
+pub const EXTENSIONS_DIR_ENV: &str = "CITADEL_EXTENSIONS_DIR";
+
+/// Name of the manifest inside an extension directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Largest script or stylesheet an extension may ship
+pub const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// Permissions a manifest may request
+pub const ALLOWED_PERMISSIONS: &[&str] = &["dom"];
+
+/// An extension's `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionManifest {
+    pub name: String,
+    pub version: String,
+    /// Origin patterns of the pages to inject into, see
+    /// [`pattern_matches`](crate::user_styles::pattern_matches)
+    pub matches: Vec<String>,
+    /// Scripts to run, relative to the extension directory
+    #[serde(default)]
+    pub js: Vec<String>,
+    /// Stylesheets to apply, relative to the extension directory
+    #[serde(default)]
+    pub css: Vec<String>,
+    /// Requested capabilities; only `dom` is granted
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// A checked extension with its files read into memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledExtension {
+    pub manifest: ExtensionManifest,
+    scripts: Vec<String>,
+    styles: Vec<String>,
+}
+
+impl InstalledExtension {
+    /// Read and check the extension in `dir`
+    pub fn load(dir: &Path) -> std::io::Result<Self> {
+        let manifest: ExtensionManifest =
+            serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE))?)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        if manifest.matches.is_empty() {
+            return Err(invalid(format!("{}: no match patterns", manifest.name)));
+        }
+        if let Some(permission) = manifest
+            .permissions
+            .iter()
+            .find(|p| !ALLOWED_PERMISSIONS.contains(&p.as_str()))
+        {
+            return Err(invalid(format!(
+                "{}: permission `{}` is not available to extensions",
+                manifest.name, permission
+            )));
+        }
+
+        let scripts = read_files(dir, &manifest.js)?;
+        let styles = read_files(dir, &manifest.css)?;
+        Ok(Self {
+            manifest,
+            scripts,
+            styles,
+        })
+    }
+
+    /// Whether the extension injects into a page
+    pub fn matches(&self, url: &Url) -> bool {
+        self.manifest
+            .matches
+            .iter()
+            .any(|pattern| pattern_matches(pattern, url))
+    }
+}
+
+/// What the installed extensions inject into one page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageInjection {
+    /// Content script sources, in extension then manifest order
+    pub scripts: Vec<String>,
+    /// Stylesheets joined for the user-origin cascade
+    pub css: String,
+}
+
+/// Every installed extension
+#[derive(Debug, Clone, Default)]
+pub struct Extensions {
+    installed: Vec<InstalledExtension>,
+}
+
+impl Extensions {
+    /// Load every extension directory under `root`. Extensions failing their
+    /// checks are skipped with a warning; a missing root means none.
+    pub fn load_dir(root: &Path) -> Self {
+        let mut installed = Vec::new();
+        let Ok(entries) = std::fs::read_dir(root) else {
+            return Self { installed };
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs {
+            match InstalledExtension::load(&dir) {
+                Ok(extension) => {
+                    log::info!(
+                        "🧩 Loaded extension {} {}",
+                        extension.manifest.name,
+                        extension.manifest.version
+                    );
+                    installed.push(extension);
+                }
+                Err(e) => log::warn!("Skipping extension at {}: {}", dir.display(), e),
+            }
+        }
+        Self { installed }
+    }
+
+    /// Installed extensions in load order
+    pub fn installed(&self) -> &[InstalledExtension] {
+        &self.installed
+    }
+
+    /// Scripts and CSS to inject into a page. Empty for unparseable URLs.
+    pub fn for_page(&self, url: &str) -> PageInjection {
+        let Ok(url) = Url::parse(url) else {
+            return PageInjection::default();
+        };
+        let matching = self.installed.iter().filter(|ext| ext.matches(&url));
+        let mut injection = PageInjection::default();
+        let mut styles = Vec::new();
+        for extension in matching {
+            injection.scripts.extend(extension.scripts.iter().cloned());
+            styles.extend(extension.styles.iter().map(String::as_str));
+        }
+        injection.css = styles.join("\n");
+        injection
+    }
+}
+
+/// Where extensions are installed: `$CITADEL_EXTENSIONS_DIR`, otherwise
+/// `citadel/extensions` under the XDG data directory
+pub fn default_dir() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(EXTENSIONS_DIR_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("citadel").join("extensions"))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Read manifest-listed files, refusing paths that leave the extension
+/// directory and files over the size cap
+fn read_files(dir: &Path, files: &[String]) -> std::io::Result<Vec<String>> {
+    files
+        .iter()
+        .map(|file| {
+            let relative = Path::new(file);
+            if !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+            {
+                return Err(invalid(format!("`{}` is outside the extension", file)));
+            }
+            let path = dir.join(relative);
+            if std::fs::metadata(&path)?.len() > MAX_FILE_BYTES {
+                return Err(invalid(format!(
+                    "`{}` is larger than {} bytes",
+                    file, MAX_FILE_BYTES
+                )));
+            }
+            std::fs::read_to_string(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(root: &Path, name: &str, manifest: &str, files: &[(&str, &str)]) {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_extensions_inject_into_matching_pages() {
+        let root = std::env::temp_dir().join(format!("citadel-ext-{}", uuid::Uuid::new_v4()));
+        install(
+            &root,
+            "a-dark",
+            r#"{"name":"dark","version":"1.0","matches":["*.example.com"],
+                "js":["dark.js"],"css":["dark.css"],"permissions":["dom"]}"#,
+            &[
+                ("dark.js", "document.body;"),
+                ("dark.css", "body { color: white; }"),
+            ],
+        );
+        install(
+            &root,
+            "b-spy",
+            r#"{"name":"spy","version":"1.0","matches":["*"],"js":["spy.js"],
+                "permissions":["network"]}"#,
+            &[("spy.js", "fetch('https://spy.test/');")],
+        );
+        install(
+            &root,
+            "c-escape",
+            r#"{"name":"escape","version":"1.0","matches":["*"],"js":["../a-dark/dark.js"]}"#,
+            &[],
+        );
+
+        let extensions = Extensions::load_dir(&root);
+        assert_eq!(
+            extensions.installed().len(),
+            1,
+            "only the audited one loads"
+        );
+
+        let injection = extensions.for_page("https://www.example.com/");
+        assert_eq!(injection.scripts, vec!["document.body;".to_string()]);
+        assert_eq!(injection.css, "body { color: white; }");
+        assert_eq!(
+            extensions.for_page("https://other.test/"),
+            PageInjection::default()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(Extensions::load_dir(&root).installed().is_empty());
+    }
+}
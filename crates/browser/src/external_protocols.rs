@@ -0,0 +1,212 @@
+//! Hand-off of external-scheme links to the operating system
+//!
+//! Links such as `mailto:` and `tel:` are not loaded by the engine. Each URL
+//! is sorted by [`SchemeDispatch::for_url`]: web and internal schemes load in
+//! the tab, allowlisted external schemes go to the OS default handler once
+//! the user confirms, and everything else is refused. A hand-off is never
+//! silent: it happens only after the user confirms it, or after they chose
+//! "always allow" for that scheme, which is remembered in the settings file.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::profile;
+
+/// Environment variable overriding where external protocol choices are saved
+pub const EXTERNAL_PROTOCOLS_FILE_ENV: &str = "CITADEL_EXTERNAL_PROTOCOLS_FILE";
+
+/// Schemes that may be handed to the OS
+pub const EXTERNAL_SCHEMES: &[&str] = &["mailto", "tel", "sms", "xmpp", "magnet", "webcal"];
+
+/// Schemes loaded by the engine itself
+const ENGINE_SCHEMES: &[&str] = &["http", "https", "file", "citadel", "about"];
+
+/// Whether a scheme may be handed to the OS
+pub fn is_external_scheme(scheme: &str) -> bool {
+    EXTERNAL_SCHEMES
+        .iter()
+        .any(|external| external.eq_ignore_ascii_case(scheme))
+}
+
+/// Whether text is an absolute URL with an allowlisted external scheme
+pub fn is_external_url(text: &str) -> bool {
+    text.split_once(':')
+        .is_some_and(|(scheme, _)| is_external_scheme(scheme))
+}
+
+/// Where a URL goes when navigated to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemeDispatch {
+    /// Loaded by the engine in the tab
+    Engine,
+    /// Handed to the OS default handler after confirmation
+    External(String),
+    /// Neither loaded nor handed off
+    Refused(String),
+}
+
+impl SchemeDispatch {
+    pub fn for_url(url: &Url) -> Self {
+        let scheme = url.scheme();
+        if ENGINE_SCHEMES.contains(&scheme) {
+            Self::Engine
+        } else if is_external_scheme(scheme) {
+            Self::External(scheme.to_string())
+        } else {
+            Self::Refused(scheme.to_string())
+        }
+    }
+}
+
+/// Schemes the user chose to always hand off without asking
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ExternalProtocolPrefs {
+    #[serde(default)]
+    always_allow: BTreeSet<String>,
+}
+
+impl ExternalProtocolPrefs {
+    /// Whether `scheme` may be handed off without a confirmation. Schemes
+    /// dropped from the allowlist since they were saved always ask.
+    pub fn is_always_allowed(&self, scheme: &str) -> bool {
+        is_external_scheme(scheme) && self.always_allow.contains(&scheme.to_ascii_lowercase())
+    }
+
+    /// Remember "always allow" for an allowlisted scheme. Returns whether
+    /// anything changed.
+    pub fn allow_always(&mut self, scheme: &str) -> bool {
+        is_external_scheme(scheme) && self.always_allow.insert(scheme.to_ascii_lowercase())
+    }
+
+    /// Ask again for `scheme`. Returns whether anything changed.
+    pub fn revoke(&mut self, scheme: &str) -> bool {
+        self.always_allow.remove(&scheme.to_ascii_lowercase())
+    }
+
+    /// Read saved choices; a missing file means none
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        profile::write(path, json).await
+    }
+}
+
+/// Where external protocol choices live: `$CITADEL_EXTERNAL_PROTOCOLS_FILE`,
+/// otherwise `citadel/external-protocols.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(EXTERNAL_PROTOCOLS_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("external-protocols.json"))
+}
+
+/// Open a URL with the OS default handler for its scheme.
+///
+/// The URL is passed as a single argument, never through a shell, and only
+/// allowlisted schemes are accepted.
+pub fn open_with_system(url: &Url) -> std::io::Result<()> {
+    if !is_external_scheme(url.scheme()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{}: is not an external scheme", url.scheme()),
+        ));
+    }
+
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    let mut child = command
+        .arg(url.as_str())
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    // Reap the launcher once it exits
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatch(url: &str) -> SchemeDispatch {
+        SchemeDispatch::for_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_scheme_dispatch() {
+        assert_eq!(dispatch("https://example.com/"), SchemeDispatch::Engine);
+        assert_eq!(dispatch("citadel://net-internals"), SchemeDispatch::Engine);
+        assert_eq!(
+            dispatch("mailto:someone@example.com"),
+            SchemeDispatch::External("mailto".into())
+        );
+        assert_eq!(
+            dispatch("TEL:+15551234567"),
+            SchemeDispatch::External("tel".into())
+        );
+        assert_eq!(
+            dispatch("javascript:alert(1)"),
+            SchemeDispatch::Refused("javascript".into())
+        );
+        assert_eq!(
+            dispatch("ms-settings:privacy"),
+            SchemeDispatch::Refused("ms-settings".into())
+        );
+        assert!(is_external_url("mailto:someone@example.com"));
+        assert!(!is_external_url("example.com/mailto:x"));
+        assert!(open_with_system(&Url::parse("file:///etc/passwd").unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_always_allow_is_remembered() {
+        let mut prefs = ExternalProtocolPrefs::default();
+        assert!(!prefs.is_always_allowed("mailto"));
+        assert!(prefs.allow_always("MailTo"));
+        assert!(!prefs.allow_always("mailto"));
+        assert!(!prefs.allow_always("javascript"));
+        assert!(prefs.is_always_allowed("mailto"));
+        assert!(!prefs.is_always_allowed("tel"));
+
+        let path = std::env::temp_dir()
+            .join(format!("citadel-protocols-{}", uuid::Uuid::new_v4()))
+            .join("external-protocols.json");
+        assert_eq!(
+            ExternalProtocolPrefs::load(&path).unwrap(),
+            ExternalProtocolPrefs::default()
+        );
+        prefs.save(&path).await.unwrap();
+        let mut loaded = ExternalProtocolPrefs::load(&path).unwrap();
+        assert_eq!(loaded, prefs);
+        assert!(loaded.revoke("mailto"));
+        assert!(!loaded.is_always_allowed("mailto"));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,64 @@
+//! Sites exempt from filter lists
+//!
+//! Some sites break, or are simply trusted, when their ads and trackers are
+//! blocked. The user can allowlist a page's site; the
+//! [`BlocklistEngine`](citadel_networking::BlocklistEngine) then lets through
+//! every request its pages make, subdomains included. The allowlisted sites
+//! are remembered in the settings file, which is sealed with the profile.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use citadel_networking::NetworkPartitionKey;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::profile;
+
+/// Environment variable overriding where allowlisted sites are saved
+pub const FILTER_ALLOWLIST_FILE_ENV: &str = "CITADEL_FILTER_ALLOWLIST_FILE";
+
+/// Sites whose pages are not filtered
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FilterAllowlist {
+    #[serde(default)]
+    pub sites: BTreeSet<String>,
+}
+
+impl FilterAllowlist {
+    /// The site a page would be allowlisted as: its registrable domain
+    pub fn site_of(url: &Url) -> Option<String> {
+        NetworkPartitionKey::for_url(url).map(|key| key.top_level_site)
+    }
+
+    /// Read saved sites; a missing file means none
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        profile::write(path, json).await
+    }
+}
+
+/// Where allowlisted sites live: `$CITADEL_FILTER_ALLOWLIST_FILE`,
+/// otherwise `citadel/filter-allowlist.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(FILTER_ALLOWLIST_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("filter-allowlist.json"))
+}
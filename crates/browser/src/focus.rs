@@ -0,0 +1,237 @@
+//! Keyboard focus management for page content
+//!
+//! Tracks the tab order of focusable page elements (links and form controls)
+//! and which one currently has focus, so pages can be navigated with Tab,
+//! Shift+Tab and Enter alone.
+
+use citadel_parser::accessibility::{AccessibilityTree, Role};
+use citadel_tabs::RenderedContent;
+use url::Url;
+
+/// A focusable element in tab order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusTarget {
+    /// Display-list index (ZKVM path) or DOM node ID (DOM path)
+    pub id: u32,
+    /// Link target, if the element is a link
+    pub href: Option<String>,
+    /// Top edge in document coordinates, used to scroll the target into view
+    pub y: f32,
+}
+
+/// What pressing Enter on the focused element should do
+#[derive(Debug, Clone, PartialEq)]
+pub enum FocusActivation {
+    /// Follow a link
+    Navigate(String),
+    /// Press a button or toggle a control
+    Press(u32),
+}
+
+/// Tab-order focus traversal over the current page
+#[derive(Debug, Default)]
+pub struct FocusManager {
+    targets: Vec<FocusTarget>,
+    current: Option<usize>,
+}
+
+impl FocusManager {
+    /// Create an empty focus manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the tab order from a ZKVM display list (links are focusable).
+    /// Relative link targets are resolved against the page URL.
+    pub fn set_targets_from_display_list(&mut self, content: &RenderedContent) {
+        let base = Url::parse(&content.url).ok();
+        self.targets = content
+            .display_list
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.href.is_some())
+            .map(|(index, item)| FocusTarget {
+                id: index as u32,
+                href: item
+                    .href
+                    .as_deref()
+                    .map(|href| resolve_href(base.as_ref(), href)),
+                y: item.y,
+            })
+            .collect();
+        self.current = None;
+    }
+
+    /// Rebuild the tab order from an accessibility tree (DOM path)
+    pub fn set_targets_from_tree(&mut self, tree: &AccessibilityTree) {
+        self.targets = tree
+            .focusable_nodes()
+            .into_iter()
+            .filter_map(|id| tree.get(id))
+            .map(|node| FocusTarget {
+                id: node.id,
+                href: match node.role {
+                    Role::Link => node.url.clone(),
+                    _ => None,
+                },
+                y: node.bounds.as_ref().map(|b| b.y).unwrap_or(0.0),
+            })
+            .collect();
+        self.current = None;
+    }
+
+    /// Forget all targets (page unloaded)
+    pub fn clear(&mut self) {
+        self.targets.clear();
+        self.current = None;
+    }
+
+    /// Move focus to the next target, wrapping at the end
+    pub fn focus_next(&mut self) -> Option<&FocusTarget> {
+        if self.targets.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            Some(index) => (index + 1) % self.targets.len(),
+            None => 0,
+        };
+        self.current = Some(next);
+        self.targets.get(next)
+    }
+
+    /// Move focus to the previous target, wrapping at the start
+    pub fn focus_previous(&mut self) -> Option<&FocusTarget> {
+        if self.targets.is_empty() {
+            return None;
+        }
+        let previous = match self.current {
+            Some(0) | None => self.targets.len() - 1,
+            Some(index) => index - 1,
+        };
+        self.current = Some(previous);
+        self.targets.get(previous)
+    }
+
+    /// Move focus to the target with this ID, e.g. one a screen reader
+    /// picked
+    pub fn focus_id(&mut self, id: u32) -> Option<&FocusTarget> {
+        let index = self.targets.iter().position(|target| target.id == id)?;
+        self.current = Some(index);
+        self.targets.get(index)
+    }
+
+    /// Currently focused target
+    pub fn focused(&self) -> Option<&FocusTarget> {
+        self.current.and_then(|index| self.targets.get(index))
+    }
+
+    /// Whether the element with this ID has focus
+    pub fn is_focused(&self, id: u32) -> bool {
+        self.focused().is_some_and(|target| target.id == id)
+    }
+
+    /// Action for Enter on the focused element
+    pub fn activate(&self) -> Option<FocusActivation> {
+        self.focused().map(|target| match &target.href {
+            Some(href) => FocusActivation::Navigate(href.clone()),
+            None => FocusActivation::Press(target.id),
+        })
+    }
+
+    /// Number of focusable targets on the page
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Whether the page has no focusable targets
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+}
+
+/// Resolve a link target against the page URL, leaving it untouched on failure
+fn resolve_href(base: Option<&Url>, href: &str) -> String {
+    base.and_then(|base| citadel_networking::url_canon::resolve(base, href).ok())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| href.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_tabs::{render_in_isolation, RenderRequest};
+
+    fn rendered(html: &str) -> RenderedContent {
+        render_in_isolation(&RenderRequest {
+            url: "https://focus.example/".to_string(),
+            html: html.to_string(),
+            viewport_width: 800.0,
+            enable_scripts: false,
+            user_css: String::new(),
+            content_scripts: Vec::new(),
+            compat_script: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_tab_order_wraps_in_both_directions() {
+        let content = rendered(
+            "<html><body><p><a href=\"https://a.example/\">A</a></p>\
+             <p>Text</p><p><a href=\"/b\">B</a></p></body></html>",
+        );
+        let mut focus = FocusManager::new();
+        focus.set_targets_from_display_list(&content);
+        assert_eq!(focus.len(), 2);
+
+        assert_eq!(
+            focus.focus_next().unwrap().href.as_deref(),
+            Some("https://a.example/")
+        );
+        assert_eq!(
+            focus.focus_next().unwrap().href.as_deref(),
+            Some("https://focus.example/b")
+        );
+        assert_eq!(
+            focus.focus_next().unwrap().href.as_deref(),
+            Some("https://a.example/")
+        );
+        assert_eq!(
+            focus.focus_previous().unwrap().href.as_deref(),
+            Some("https://focus.example/b")
+        );
+        assert_eq!(
+            focus.activate(),
+            Some(FocusActivation::Navigate(
+                "https://focus.example/b".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_focus_moves_to_a_picked_target() {
+        let content = rendered(
+            "<html><body><p><a href=\"https://a.example/\">A</a></p>\
+             <p><a href=\"/b\">B</a></p></body></html>",
+        );
+        let mut focus = FocusManager::new();
+        focus.set_targets_from_display_list(&content);
+        let second = focus.focus_previous().unwrap().id;
+        focus.focus_next();
+
+        assert_eq!(focus.focus_id(second).unwrap().id, second);
+        assert_eq!(
+            focus.activate(),
+            Some(FocusActivation::Navigate("https://focus.example/b".into()))
+        );
+        assert!(focus.focus_id(u32::MAX).is_none());
+        assert!(focus.is_focused(second));
+    }
+
+    #[test]
+    fn test_no_targets() {
+        let mut focus = FocusManager::new();
+        assert!(focus.focus_next().is_none());
+        assert!(focus.focus_previous().is_none());
+        assert!(focus.activate().is_none());
+    }
+}
@@ -0,0 +1,475 @@
+//! Browsing history
+//!
+//! Visits to container tabs are recorded with the page title, visit count and
+//! visit times; ephemeral tabs never contribute. The history is kept on disk
+//! sealed with AES-256-GCM under a history key kept in the secret store, the
+//! OS keychain where there is one (see [`crate::keychain`]), and is sealed
+//! again by the profile key when profile encryption is on. Without its key
+//! the file is unreadable.
+//!
+//! The address bar searches it by URL prefix or by full text, ranked by
+//! frecency. Visits to a site can be purged, the visits of the last hours
+//! forgotten, or everything cleared; each is written out on the next save.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use chrono::{DateTime, Duration, Utc};
+use citadel_security::secrets::SecretKey;
+use citadel_tabs::TabType;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::keychain::SecretStore;
+use crate::profile;
+use crate::suggestions::{self, Suggestion, SuggestionProvider, SuggestionSource};
+
+/// Environment variable overriding where history is kept
+pub const HISTORY_FILE_ENV: &str = "CITADEL_HISTORY_FILE";
+
+/// Keychain account holding the history key
+const HISTORY_KEY_ACCOUNT: &str = "history-key";
+
+/// Marks a sealed history file; also authenticated as associated data
+const SEALED_MAGIC: &[u8] = b"CTDLHIS1";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Most URLs kept; the lowest-frecency ones are evicted first
+const MAX_ENTRIES: usize = 10_000;
+
+/// Most visit times kept per URL; older visits still count
+const MAX_VISIT_TIMES: usize = 100;
+
+/// One URL's visits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Page URL
+    pub url: String,
+    /// Latest page title (may be empty)
+    pub title: String,
+    /// Visits ever recorded
+    pub visit_count: usize,
+    /// Times of the most recent visits, oldest first
+    pub visits: Vec<DateTime<Utc>>,
+}
+
+impl HistoryEntry {
+    /// When the page was last visited
+    pub fn last_visit(&self) -> Option<DateTime<Utc>> {
+        self.visits.last().copied()
+    }
+
+    fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        suggestions::frecency(self.visit_count, &self.visits, now)
+    }
+}
+
+/// Where and how history is written
+struct Store {
+    path: PathBuf,
+    key: SecretKey,
+    /// Serializes saves, so an older snapshot never overwrites a newer one
+    saving: tokio::sync::Mutex<()>,
+}
+
+/// The browsing history of container tabs. Clones share the same history.
+#[derive(Clone, Default)]
+pub struct HistoryManager {
+    entries: Arc<RwLock<HashMap<String, HistoryEntry>>>,
+    store: Option<Arc<Store>>,
+}
+
+impl std::fmt::Debug for HistoryManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HistoryManager")
+            .field("entries", &self.len())
+            .field("path", &self.store.as_ref().map(|store| &store.path))
+            .finish()
+    }
+}
+
+impl HistoryManager {
+    /// History kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History kept at `path`, sealed with the history key in `secrets`. A
+    /// missing file is an empty history; the key is created on first use.
+    pub fn load(path: &Path, secrets: &dyn SecretStore) -> std::io::Result<Self> {
+        let key = history_key(secrets)?;
+        let entries = match profile::read(path) {
+            Ok(bytes) => {
+                let plaintext = Zeroizing::new(open(&key, &bytes)?);
+                serde_json::from_slice::<Vec<HistoryEntry>>(&plaintext)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            entries: Arc::new(RwLock::new(
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.url.clone(), entry))
+                    .collect(),
+            )),
+            store: Some(Arc::new(Store {
+                path: path.to_path_buf(),
+                key,
+                saving: tokio::sync::Mutex::new(()),
+            })),
+        })
+    }
+
+    /// Write the history out; history kept in memory only is not written
+    pub async fn save(&self) -> std::io::Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let _saving = store.saving.lock().await;
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&self.entries())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+        );
+        let sealed = seal(&store.key, &plaintext)?;
+        if let Some(dir) = store.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        profile::write(&store.path, sealed).await
+    }
+
+    /// Record a visit. Visits from ephemeral tabs are dropped.
+    pub fn record_visit(&self, url: &str, title: &str, tab_type: TabType) {
+        self.record_visit_at(url, title, tab_type, Utc::now());
+    }
+
+    fn record_visit_at(&self, url: &str, title: &str, tab_type: TabType, at: DateTime<Utc>) {
+        if tab_type == TabType::Ephemeral || url.is_empty() || url == "about:blank" {
+            return;
+        }
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        let entry = entries
+            .entry(url.to_string())
+            .or_insert_with(|| HistoryEntry {
+                url: url.to_string(),
+                title: String::new(),
+                visit_count: 0,
+                visits: Vec::new(),
+            });
+        if !title.is_empty() {
+            entry.title = title.to_string();
+        }
+        entry.visit_count += 1;
+        let position = entry.visits.partition_point(|visit| *visit <= at);
+        entry.visits.insert(position, at);
+        if entry.visits.len() > MAX_VISIT_TIMES {
+            entry.visits.remove(0);
+        }
+
+        if entries.len() > MAX_ENTRIES {
+            let now = Utc::now();
+            if let Some(evict) = entries
+                .values()
+                .min_by(|a, b| a.frecency(now).total_cmp(&b.frecency(now)))
+                .map(|entry| entry.url.clone())
+            {
+                entries.remove(&evict);
+            }
+        }
+    }
+
+    /// Up to `limit` pages whose URL starts with `input`, best first. The
+    /// scheme and a leading `www.` may be left out, as typed in the address
+    /// bar.
+    pub fn search_prefix(&self, input: &str, limit: usize) -> Vec<HistoryEntry> {
+        let input = input.trim().to_lowercase();
+        if input.is_empty() {
+            return Vec::new();
+        }
+        self.ranked(limit, |entry| {
+            let url = entry.url.to_lowercase();
+            let bare = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+            url.starts_with(&input)
+                || bare.starts_with(&input)
+                || bare.trim_start_matches("www.").starts_with(&input)
+        })
+    }
+
+    /// Up to `limit` pages whose URL or title contains every word of
+    /// `query`, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<HistoryEntry> {
+        if query.trim().is_empty() {
+            return Vec::new();
+        }
+        self.ranked(limit, |entry| {
+            suggestions::matches(query, &entry.url, &entry.title)
+        })
+    }
+
+    fn ranked(&self, limit: usize, filter: impl Fn(&HistoryEntry) -> bool) -> Vec<HistoryEntry> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let mut found: Vec<(f64, &HistoryEntry)> = entries
+            .values()
+            .filter(|entry| filter(entry))
+            .map(|entry| (entry.frecency(now), entry))
+            .collect();
+        found.sort_by(|a, b| b.0.total_cmp(&a.0));
+        found
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
+    /// The visits to `url`, if any
+    pub fn get(&self, url: &str) -> Option<HistoryEntry> {
+        self.entries.read().ok()?.get(url).cloned()
+    }
+
+    /// Every page visited, most recently visited first
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        let mut all: Vec<HistoryEntry> = entries.values().cloned().collect();
+        all.sort_by(|a, b| b.last_visit().cmp(&a.last_visit()));
+        all
+    }
+
+    /// Forget every visit to `domain` and its subdomains. Returns how many
+    /// pages were forgotten.
+    pub fn purge_domain(&self, domain: &str) -> usize {
+        let domain = domain.trim().trim_end_matches('.').to_lowercase();
+        let suffix = format!(".{}", domain);
+        let Ok(mut entries) = self.entries.write() else {
+            return 0;
+        };
+        let before = entries.len();
+        entries.retain(|url, _| {
+            let host = url::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_lowercase));
+            !host.is_some_and(|host| host == domain || host.ends_with(&suffix))
+        });
+        before - entries.len()
+    }
+
+    /// Forget the visits of the last `hours` hours. Pages with no visits
+    /// left are forgotten entirely. Returns how many visits were forgotten.
+    pub fn clear_last_hours(&self, hours: u32) -> usize {
+        self.clear_since(Utc::now() - Duration::hours(hours.into()))
+    }
+
+    fn clear_since(&self, since: DateTime<Utc>) -> usize {
+        let Ok(mut entries) = self.entries.write() else {
+            return 0;
+        };
+        let mut forgotten = 0;
+        entries.retain(|_, entry| {
+            let kept = entry.visits.partition_point(|visit| *visit < since);
+            let recent = entry.visits.len() - kept;
+            entry.visits.truncate(kept);
+            entry.visit_count = entry.visit_count.saturating_sub(recent);
+            forgotten += recent;
+            entry.visit_count > 0 && !entry.visits.is_empty()
+        });
+        forgotten
+    }
+
+    /// Forget all history
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+    }
+
+    /// Number of distinct URLs recorded
+    pub fn len(&self) -> usize {
+        self.entries.read().map(|e| e.len()).unwrap_or(0)
+    }
+
+    /// Whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SuggestionProvider for HistoryManager {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn suggest(&self, input: &str, limit: usize) -> Vec<Suggestion> {
+        let now = Utc::now();
+        // Pages the input is the start of come first, so typing a URL is
+        // not crowded out by pages that merely mention it
+        let mut entries = self.search_prefix(input, limit);
+        for entry in self.search(input, limit) {
+            if entries.len() >= limit {
+                break;
+            }
+            if !entries.iter().any(|e| e.url == entry.url) {
+                entries.push(entry);
+            }
+        }
+        let mut suggestions: Vec<Suggestion> = entries
+            .into_iter()
+            .map(|entry| Suggestion {
+                score: entry.frecency(now) * suggestions::prefix_boost(input, &entry.url),
+                url: entry.url,
+                title: entry.title,
+                source: SuggestionSource::History,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions
+    }
+}
+
+/// The history key from `secrets`, created and stored on first use
+fn history_key(secrets: &dyn SecretStore) -> std::io::Result<SecretKey> {
+    let mut key = SecretKey::from_bytes([0u8; KEY_LEN]);
+    match secrets.get(HISTORY_KEY_ACCOUNT)? {
+        Some(stored) if stored.len() == KEY_LEN => key.expose_mut().copy_from_slice(&stored),
+        Some(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stored history key has the wrong length",
+            ))
+        }
+        None => {
+            key = SecretKey::generate();
+            secrets.set(HISTORY_KEY_ACCOUNT, key.expose())?;
+        }
+    }
+    Ok(key)
+}
+
+fn seal(key: &SecretKey, plaintext: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = Aes256Gcm::new(key.expose().into())
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: SEALED_MAGIC,
+            },
+        )
+        .map_err(|_| std::io::Error::other("history encryption failed"))?;
+    let mut sealed = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(key: &SecretKey, sealed: &[u8]) -> std::io::Result<Vec<u8>> {
+    let corrupt = |reason: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, reason);
+    let body = sealed
+        .strip_prefix(SEALED_MAGIC)
+        .filter(|body| body.len() >= NONCE_LEN)
+        .ok_or_else(|| corrupt("not a sealed history file"))?;
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    Aes256Gcm::new(key.expose().into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: SEALED_MAGIC,
+            },
+        )
+        .map_err(|_| corrupt("history file failed authentication"))
+}
+
+/// Where history lives: `$CITADEL_HISTORY_FILE`, otherwise
+/// `citadel/history.bin` under the XDG data directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(HISTORY_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+    Some(data_dir.join("citadel").join("history.bin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keychain::FileSecretStore;
+    use uuid::Uuid;
+
+    fn container() -> TabType {
+        TabType::Container {
+            container_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_search_purge_and_clear_recent() {
+        let history = HistoryManager::new();
+        let tab = container();
+        let now = Utc::now();
+        history.record_visit_at("https://www.rust-lang.org/learn", "Learn Rust", tab, now);
+        history.record_visit_at(
+            "https://docs.rs/tokio",
+            "tokio",
+            tab,
+            now - Duration::days(3),
+        );
+        history.record_visit_at("https://docs.rs/tokio", "tokio", tab, now);
+        history.record_visit_at("https://blog.docs.rs/", "", tab, now - Duration::days(3));
+        history.record_visit("https://private.test/", "Private", TabType::Ephemeral);
+
+        assert_eq!(history.search_prefix("rust-lang", 8)[0].title, "Learn Rust");
+        assert_eq!(history.search_prefix("https://docs", 8).len(), 1);
+        assert_eq!(history.search("learn RUST", 8).len(), 1);
+        assert!(history.search("private", 8).is_empty());
+        assert_eq!(history.get("https://docs.rs/tokio").unwrap().visit_count, 2);
+        // The address bar offers the page typed the start of first
+        assert_eq!(history.suggest("docs", 1)[0].url, "https://docs.rs/tokio");
+
+        assert_eq!(history.clear_last_hours(1), 2);
+        assert!(history.get("https://www.rust-lang.org/learn").is_none());
+        assert_eq!(history.get("https://docs.rs/tokio").unwrap().visit_count, 1);
+
+        assert_eq!(history.purge_domain("docs.rs"), 2);
+        assert!(history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_is_sealed_on_disk() {
+        let dir = std::env::temp_dir().join(format!("citadel-history-{}", Uuid::new_v4()));
+        let path = dir.join("history.bin");
+        let secrets = FileSecretStore::new(dir.join("secrets.json"));
+
+        let history = HistoryManager::load(&path, &secrets).unwrap();
+        history.record_visit("https://bank.test/statements", "Statements", container());
+        history.save().await.unwrap();
+
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("bank.test"));
+        let reloaded = HistoryManager::load(&path, &secrets).unwrap();
+        assert_eq!(reloaded.search_prefix("bank", 8)[0].title, "Statements");
+
+        // Without its key the history cannot be read
+        let other = FileSecretStore::new(dir.join("other.json"));
+        assert!(HistoryManager::load(&path, &other).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
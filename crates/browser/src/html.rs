@@ -0,0 +1,22 @@
+//! Helpers for the HTML of the built-in `citadel://` pages
+
+/// Escape `text` for use in element content or a quoted attribute value
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<a href="?q=1&r=2">"#),
+            "&lt;a href=&quot;?q=1&amp;r=2&quot;&gt;"
+        );
+    }
+}
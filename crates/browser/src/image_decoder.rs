@@ -0,0 +1,265 @@
+//! Page images
+//!
+//! `<img>` sources are fetched like any subresource of the page, under its
+//! Content-Security-Policy `img-src`, then decoded here. Only PNG, JPEG and
+//! WebP are read, told apart by their leading bytes rather than by what the
+//! server claims. Size limits are checked twice: against the file before
+//! anything is decoded, and against the dimensions in its header before any
+//! pixels are allocated, so a small file declaring a huge canvas is refused
+//! cheaply.
+//!
+//! A decoded image is nothing but RGBA pixels. EXIF, XMP and colour profile
+//! chunks are never carried over, so camera details and locations embedded
+//! in a photo go no further than the decoder.
+
+use std::collections::HashSet;
+use std::io::Cursor;
+
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_parser::dom::NodeData;
+use citadel_parser::Dom;
+use image::imageops::{self, FilterType};
+use image::io::{Limits, Reader};
+use image::{ImageBuffer, ImageFormat, Rgba};
+use url::Url;
+
+/// Formats pages may use
+const SUPPORTED_FORMATS: &[ImageFormat] = &[ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP];
+
+/// Most images decoded for one page
+pub const MAX_IMAGES_PER_PAGE: usize = 64;
+
+/// An image a page shows, and where to fetch it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageRequest {
+    /// `src` as written in the document, which layout sizes images by
+    pub src: String,
+    pub url: Url,
+}
+
+/// The images `dom` shows, at most [`MAX_IMAGES_PER_PAGE`] and one per
+/// `src`. Sources resolve against `base`; only HTTP(S) ones are fetched.
+pub fn image_requests(dom: &Dom, base: &Url) -> Vec<ImageRequest> {
+    let mut seen = HashSet::new();
+    dom.query_selector_all("img[src]")
+        .iter()
+        .filter_map(|handle| {
+            let node = handle.read().ok()?;
+            let NodeData::Element(element) = &node.data else {
+                return None;
+            };
+            let src = element.get_attribute("src")?;
+            if seen.contains(&src) {
+                return None;
+            }
+            let url = base
+                .join(src.trim())
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+            seen.insert(src.clone());
+            Some(ImageRequest { src, url })
+        })
+        .take(MAX_IMAGES_PER_PAGE)
+        .collect()
+}
+
+/// Bounds on what an image may cost to decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Largest file decoded
+    pub max_bytes: usize,
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Largest canvas, in pixels; decoded images take four bytes a pixel
+    pub max_pixels: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_width: 8192,
+            max_height: 8192,
+            max_pixels: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// An image decoded to RGBA pixels, row by row
+#[derive(Clone, PartialEq, Eq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl std::fmt::Debug for DecodedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecodedImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("bytes", &self.pixels.len())
+            .finish()
+    }
+}
+
+impl DecodedImage {
+    /// A handle the renderer can draw
+    pub fn handle(&self) -> iced::widget::image::Handle {
+        iced::widget::image::Handle::from_pixels(self.width, self.height, self.pixels.clone())
+    }
+
+    /// The image resized to `width`×`height`, or unchanged if it already is
+    pub fn downscaled(self, (width, height): (u32, u32)) -> Self {
+        let resized =
+            ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(self.width, self.height, &self.pixels)
+                .filter(|_| (width, height) != (self.width, self.height))
+                .map(|image| {
+                    imageops::resize(&image, width, height, FilterType::Triangle).into_raw()
+                });
+        match resized {
+            Some(pixels) => Self {
+                width,
+                height,
+                pixels,
+            },
+            None => self,
+        }
+    }
+}
+
+/// Decode fetched bytes within `limits`
+pub fn decode(bytes: &[u8], limits: &DecodeLimits) -> Result<DecodedImage, CitadelError> {
+    decode_formats(bytes, limits, SUPPORTED_FORMATS)
+}
+
+/// Decode fetched bytes within `limits`, if they are one of `formats`
+pub fn decode_formats(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+    formats: &[ImageFormat],
+) -> Result<DecodedImage, CitadelError> {
+    if bytes.len() > limits.max_bytes {
+        return Err(too_large(format!(
+            "image of {} bytes is over {}",
+            bytes.len(),
+            limits.max_bytes
+        )));
+    }
+    let unsupported =
+        |reason: String| CitadelError::new(ErrorKind::Content, "IMAGE_UNSUPPORTED", reason);
+    let format = image::guess_format(bytes).map_err(|e| unsupported(e.to_string()))?;
+    if !formats.contains(&format) {
+        return Err(unsupported(format!("{:?} images are not decoded", format)));
+    }
+
+    let invalid = |e: image::ImageError| {
+        CitadelError::new(ErrorKind::Content, "IMAGE_INVALID", e.to_string())
+    };
+    let reader = || {
+        let mut reader = Reader::with_format(Cursor::new(bytes), format);
+        reader.limits(decoder_limits(limits));
+        reader
+    };
+    let (width, height) = reader().into_dimensions().map_err(invalid)?;
+    if width > limits.max_width
+        || height > limits.max_height
+        || u64::from(width) * u64::from(height) > limits.max_pixels
+    {
+        return Err(too_large(format!(
+            "image of {}x{} is over the limit",
+            width, height
+        )));
+    }
+
+    let pixels = reader().decode().map_err(invalid)?.into_rgba8();
+    Ok(DecodedImage {
+        width,
+        height,
+        pixels: pixels.into_raw(),
+    })
+}
+
+/// The decoder's own limits, so a header lying about its size cannot make
+/// it allocate past ours either
+fn decoder_limits(limits: &DecodeLimits) -> Limits {
+    let mut decoder = Limits::default();
+    decoder.max_image_width = Some(limits.max_width);
+    decoder.max_image_height = Some(limits.max_height);
+    // Room for the decoded canvas and the RGBA copy of it
+    decoder.max_alloc = Some(limits.max_pixels.saturating_mul(8));
+    decoder
+}
+
+fn too_large(reason: String) -> CitadelError {
+    CitadelError::new(ErrorKind::Resource, "IMAGE_TOO_LARGE", reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([200, 10, 20, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_requests_resolve_each_source_once() {
+        let dom = citadel_parser::parse_html(
+            "<body><img src=\"/a.png\"><img src=\"/a.png\">\
+             <img src=\"data:image/png;base64,AA==\"><img alt=\"none\">\
+             <img src=\"https://cdn.test/b.webp\"></body>",
+            std::sync::Arc::new(citadel_parser::security::SecurityContext::new(10)),
+        )
+        .unwrap();
+        let base = Url::parse("https://site.test/page/").unwrap();
+
+        let requests = image_requests(&dom, &base);
+        let urls: Vec<&str> = requests
+            .iter()
+            .map(|request| request.url.as_str())
+            .collect();
+        assert_eq!(urls, ["https://site.test/a.png", "https://cdn.test/b.webp"]);
+        assert_eq!(requests[0].src, "/a.png");
+    }
+
+    #[test]
+    fn test_decodes_to_plain_pixels() {
+        let image = decode(&png(3, 2), &DecodeLimits::default()).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixels.len(), 3 * 2 * 4);
+        assert_eq!(&image.pixels[..4], &[200, 10, 20, 255]);
+
+        let small = image.clone().downscaled((2, 1));
+        assert_eq!((small.width, small.height), (2, 1));
+        assert_eq!(small.pixels.len(), 2 * 4);
+        assert_eq!(image.clone().downscaled((3, 2)), image);
+    }
+
+    #[test]
+    fn test_limits_and_formats_are_enforced() {
+        let limits = DecodeLimits {
+            max_width: 16,
+            ..DecodeLimits::default()
+        };
+        let error = decode(&png(32, 2), &limits).unwrap_err();
+        assert_eq!(error.code(), "IMAGE_TOO_LARGE");
+
+        let limits = DecodeLimits {
+            max_bytes: 8,
+            ..DecodeLimits::default()
+        };
+        assert_eq!(
+            decode(&png(1, 1), &limits).unwrap_err().code(),
+            "IMAGE_TOO_LARGE"
+        );
+
+        let gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;";
+        let error = decode(gif, &DecodeLimits::default()).unwrap_err();
+        assert_eq!(error.code(), "IMAGE_UNSUPPORTED");
+        assert!(decode(b"<svg/>", &DecodeLimits::default()).is_err());
+    }
+}
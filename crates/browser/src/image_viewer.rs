@@ -0,0 +1,402 @@
+//! Image viewer
+//!
+//! A top-level navigation whose response is a PNG, JPEG or WebP image, as
+//! its `Content-Type` says, skips the HTML pipeline: the engine decodes it
+//! within [`IMAGE_LIMITS`] and the tab shows it here instead of a page. The
+//! image starts fitted to the window, never enlarged past its own size, and
+//! can be zoomed in steps or shown at its actual size. When it is larger
+//! than the window it pans like a scrolled page, by wheel, scrollbars or
+//! arrow keys, and zooming keeps the middle of the view in place.
+//!
+//! Saving writes the decoded pixels back out as a PNG rather than the file
+//! the server sent, so EXIF, XMP and colour profile data embedded in the
+//! original, camera details and locations included, are not saved.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use iced::widget::image::Handle;
+use iced::widget::{button, container, scrollable, text, Column, Image, Row};
+use iced::{theme, Alignment, Element, Length};
+use image::{ImageFormat, RgbaImage};
+use url::Url;
+
+use crate::app::Message;
+use crate::downloads;
+use crate::image_decoder::{DecodeLimits, DecodedImage};
+use crate::ui::InfoBarStyle;
+
+/// Limits on an image navigated to; looser than a page's images, since it
+/// is the one thing the tab shows
+pub const IMAGE_LIMITS: DecodeLimits = DecodeLimits {
+    max_bytes: 32 * 1024 * 1024,
+    max_width: 16384,
+    max_height: 16384,
+    max_pixels: 64 * 1024 * 1024,
+};
+
+/// Each zoom step multiplies or divides the scale by this
+const ZOOM_STEP: f32 = 1.25;
+
+/// Smallest and largest scale
+const MIN_SCALE: f32 = 0.05;
+const MAX_SCALE: f32 = 16.0;
+
+/// How far an arrow key pans, in pixels
+pub const PAN_STEP: f32 = 50.0;
+
+/// Height of the viewer's toolbar, taken from the area the image fits in
+const TOOLBAR_HEIGHT: f32 = 36.0;
+
+/// Whether a response with this `Content-Type` opens in the viewer
+pub fn is_viewable(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    matches!(
+        essence.as_str(),
+        "image/png" | "image/apng" | "image/jpeg" | "image/jpg" | "image/pjpeg" | "image/webp"
+    )
+}
+
+/// The part of a `viewport` of that width and height the image is shown
+/// in, below the toolbar
+pub fn image_area(viewport: (f32, f32)) -> (f32, f32) {
+    (viewport.0, (viewport.1 - TOOLBAR_HEIGHT).max(1.0))
+}
+
+/// Id of the viewer's scrollable, which panning scrolls
+pub fn scroll_id() -> scrollable::Id {
+    scrollable::Id::new("image-viewer")
+}
+
+/// How large the image is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zoom {
+    /// As large as fits the window, up to its actual size
+    Fit,
+    /// Scaled by this factor; 1.0 is its actual size
+    Scale(f32),
+}
+
+/// Something done in the viewer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewerAction {
+    ZoomIn,
+    ZoomOut,
+    ActualSize,
+    Fit,
+    /// Pan by this many pixels
+    Pan {
+        dx: f32,
+        dy: f32,
+    },
+    /// The user scrolled the view to this offset
+    Scrolled {
+        x: f32,
+        y: f32,
+    },
+}
+
+/// An image shown in a tab, and how it is shown
+#[derive(Debug, Clone)]
+pub struct ImageViewer {
+    pub url: Url,
+    image: Arc<DecodedImage>,
+    handle: Handle,
+    /// Size of the file the server sent
+    pub size_bytes: usize,
+    zoom: Zoom,
+    /// Top left corner of the view over the shown image
+    offset: (f32, f32),
+}
+
+impl ImageViewer {
+    pub fn new(url: Url, image: DecodedImage, size_bytes: usize) -> Self {
+        let handle = image.handle();
+        Self {
+            url,
+            image: Arc::new(image),
+            handle,
+            size_bytes,
+            zoom: Zoom::Fit,
+            offset: (0.0, 0.0),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height
+    }
+
+    /// Tab title: the file name and the image's size
+    pub fn title(&self) -> String {
+        format!(
+            "{} ({} × {})",
+            downloads::file_name(&self.url, None),
+            self.image.width,
+            self.image.height
+        )
+    }
+
+    pub fn zoom(&self) -> Zoom {
+        self.zoom
+    }
+
+    /// Scale the image is shown at in a `viewport` of that width and height
+    pub fn scale(&self, viewport: (f32, f32)) -> f32 {
+        match self.zoom {
+            Zoom::Fit => self.fit_scale(viewport),
+            Zoom::Scale(scale) => scale,
+        }
+    }
+
+    fn fit_scale(&self, (width, height): (f32, f32)) -> f32 {
+        let (image_width, image_height) = (self.image.width as f32, self.image.height as f32);
+        if image_width <= 0.0 || image_height <= 0.0 {
+            return 1.0;
+        }
+        (width / image_width)
+            .min(height / image_height)
+            .min(1.0)
+            .max(MIN_SCALE)
+    }
+
+    /// Width and height of the image as shown
+    pub fn shown_size(&self, viewport: (f32, f32)) -> (f32, f32) {
+        let scale = self.scale(viewport);
+        (
+            (self.image.width as f32 * scale).round(),
+            (self.image.height as f32 * scale).round(),
+        )
+    }
+
+    /// Carry out `action` in a `viewport` of that size. Returns the offset
+    /// the view has to be scrolled to, if it moved.
+    pub fn apply(&mut self, action: ViewerAction, viewport: (f32, f32)) -> Option<(f32, f32)> {
+        let old_scale = self.scale(viewport);
+        match action {
+            ViewerAction::ZoomIn => {
+                self.zoom = Zoom::Scale((old_scale * ZOOM_STEP).min(MAX_SCALE));
+            }
+            ViewerAction::ZoomOut => {
+                self.zoom = Zoom::Scale((old_scale / ZOOM_STEP).max(MIN_SCALE));
+            }
+            ViewerAction::ActualSize => self.zoom = Zoom::Scale(1.0),
+            ViewerAction::Fit => self.zoom = Zoom::Fit,
+            ViewerAction::Pan { dx, dy } => {
+                let offset = (self.offset.0 + dx, self.offset.1 + dy);
+                return self.move_to(offset, viewport);
+            }
+            ViewerAction::Scrolled { x, y } => {
+                self.offset = (x, y);
+                return None;
+            }
+        }
+
+        // Keep the point in the middle of the view where it was
+        let ratio = self.scale(viewport) / old_scale;
+        let middle = |offset: f32, extent: f32| (offset + extent / 2.0) * ratio - extent / 2.0;
+        let offset = (
+            middle(self.offset.0, viewport.0),
+            middle(self.offset.1, viewport.1),
+        );
+        self.move_to(offset, viewport)
+    }
+
+    fn move_to(&mut self, (x, y): (f32, f32), viewport: (f32, f32)) -> Option<(f32, f32)> {
+        let (width, height) = self.shown_size(viewport);
+        let offset = (
+            x.clamp(0.0, (width - viewport.0).max(0.0)),
+            y.clamp(0.0, (height - viewport.1).max(0.0)),
+        );
+        let moved = offset != self.offset;
+        self.offset = offset;
+        moved.then_some(offset)
+    }
+
+    /// Name to save the image under: the file's name, as a PNG
+    pub fn save_name(&self) -> String {
+        let name = downloads::file_name(&self.url, None);
+        let stem = match name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem,
+            _ => name.as_str(),
+        };
+        format!("{}.png", stem)
+    }
+
+    /// The image encoded as a PNG holding nothing but its pixels
+    pub fn png(&self) -> Option<Vec<u8>> {
+        let mut bytes = Cursor::new(Vec::new());
+        RgbaImage::from_raw(
+            self.image.width,
+            self.image.height,
+            self.image.pixels.clone(),
+        )?
+        .write_to(&mut bytes, ImageFormat::Png)
+        .ok()?;
+        Some(bytes.into_inner())
+    }
+
+    /// The viewer in a `viewport` of that width and height: a toolbar over
+    /// the image
+    pub fn view(&self, viewport: (f32, f32)) -> Element<'_, Message> {
+        let area = image_area(viewport);
+        let (width, height) = self.shown_size(area);
+        let scale = self.scale(area);
+
+        let action = |label: &str, action: ViewerAction| {
+            button(text(label).size(12))
+                .padding([3, 8])
+                .style(theme::Button::Secondary)
+                .on_press(Message::ImageViewer(action))
+        };
+        let toolbar = Row::new()
+            .push(action("Fit", ViewerAction::Fit))
+            .push(action("100%", ViewerAction::ActualSize))
+            .push(action("−", ViewerAction::ZoomOut))
+            .push(action("+", ViewerAction::ZoomIn))
+            .push(text(format!("{:.0}%", scale * 100.0)).size(12))
+            .push(
+                text(format!(
+                    "{} × {} • {} bytes",
+                    self.image.width, self.image.height, self.size_bytes
+                ))
+                .size(12),
+            )
+            .push(
+                button(text("Save").size(12))
+                    .padding([3, 8])
+                    .on_press(Message::SaveViewedImage),
+            )
+            .spacing(8)
+            .align_items(Alignment::Center);
+
+        // Smaller than the view, the image sits in its middle
+        let picture = container(
+            Image::new(self.handle.clone())
+                .width(Length::Fixed(width))
+                .height(Length::Fixed(height)),
+        )
+        .width(Length::Fixed(width.max(area.0)))
+        .height(Length::Fixed(height.max(area.1)))
+        .center_x()
+        .center_y();
+        let view = scrollable(picture)
+            .id(scroll_id())
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .direction(scrollable::Direction::Both {
+                vertical: scrollable::Properties::new(),
+                horizontal: scrollable::Properties::new(),
+            })
+            .on_scroll(|viewport| {
+                let offset = viewport.absolute_offset();
+                Message::ImageViewer(ViewerAction::Scrolled {
+                    x: offset.x,
+                    y: offset.y,
+                })
+            });
+
+        Column::new()
+            .push(
+                container(toolbar)
+                    .width(Length::Fill)
+                    .height(Length::Fixed(TOOLBAR_HEIGHT))
+                    .padding([4, 10])
+                    .center_y()
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(view)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_decoder;
+
+    fn viewer(width: u32, height: u32) -> ImageViewer {
+        ImageViewer::new(
+            Url::parse("https://photos.example/trip/beach.jpg?size=full").unwrap(),
+            DecodedImage {
+                width,
+                height,
+                pixels: vec![200; (width * height * 4) as usize],
+            },
+            1234,
+        )
+    }
+
+    #[test]
+    fn test_viewable_types() {
+        assert!(is_viewable("image/jpeg"));
+        assert!(is_viewable("Image/PNG; charset=binary"));
+        assert!(is_viewable("image/webp"));
+        assert!(!is_viewable("image/svg+xml"));
+        assert!(!is_viewable("text/html; charset=utf-8"));
+        assert!(!is_viewable(""));
+    }
+
+    #[test]
+    fn test_fit_zoom_and_pan() {
+        let viewport = (400.0, 300.0);
+
+        // Fitted, a large image shrinks to the window; a small one does not grow
+        let mut large = viewer(1600, 600);
+        assert_eq!(large.scale(viewport), 0.25);
+        assert_eq!(large.shown_size(viewport), (400.0, 150.0));
+        assert_eq!(viewer(100, 50).scale(viewport), 1.0);
+
+        // Nothing to pan while it fits
+        assert_eq!(
+            large.apply(ViewerAction::Pan { dx: 50.0, dy: 0.0 }, viewport),
+            None
+        );
+
+        // At actual size the middle stays in the middle, and panning stops
+        // at the edges
+        assert_eq!(
+            large.apply(ViewerAction::ActualSize, viewport),
+            Some((600.0, 300.0))
+        );
+        assert_eq!(large.shown_size(viewport), (1600.0, 600.0));
+        assert_eq!(
+            large.apply(
+                ViewerAction::Pan {
+                    dx: 5000.0,
+                    dy: -500.0
+                },
+                viewport
+            ),
+            Some((1200.0, 0.0))
+        );
+        large.apply(ViewerAction::Scrolled { x: 0.0, y: 0.0 }, viewport);
+        large.apply(ViewerAction::ZoomIn, viewport);
+        assert_eq!(large.zoom(), Zoom::Scale(1.25));
+        large.apply(ViewerAction::Fit, viewport);
+        assert_eq!(large.zoom(), Zoom::Fit);
+        for _ in 0..100 {
+            large.apply(ViewerAction::ZoomOut, viewport);
+        }
+        assert_eq!(large.scale(viewport), MIN_SCALE);
+    }
+
+    #[test]
+    fn test_saved_as_bare_png() {
+        let viewer = viewer(8, 4);
+        assert_eq!(viewer.save_name(), "beach.png");
+        assert_eq!(viewer.title(), "beach.jpg (8 × 4)");
+        let png = viewer.png().unwrap();
+        let reread = image_decoder::decode(&png, &DecodeLimits::default()).unwrap();
+        assert_eq!((reread.width, reread.height), (8, 4));
+        assert_eq!(image::guess_format(&png).unwrap(), ImageFormat::Png);
+    }
+}
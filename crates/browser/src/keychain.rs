@@ -0,0 +1,463 @@
+//! OS keychain storage for secrets
+//!
+//! Secrets such as the profile key's keychain wrapping and site credentials
+//! are kept by the platform's secret store:
+//!
+//! - Linux and the BSDs: the Secret Service (GNOME Keyring, KWallet) through
+//!   `secret-tool`
+//! - macOS: the login Keychain through `security`
+//! - Windows: DPAPI through PowerShell, with the protected blobs stored under
+//!   the config directory
+//!
+//! Secrets are always passed to these tools on stdin, never on a command
+//! line. Without a platform store, [`FileSecretStore`] keeps secrets in a
+//! file only the user can read. That protects them no better than the file
+//! permissions, so the profile key is never stored there.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use citadel_security::secrets::SecretString;
+use zeroize::Zeroizing;
+
+/// Environment variable choosing the secret store; `file` forces the file
+/// fallback even when a platform store is available
+pub const SECRET_STORE_ENV: &str = "CITADEL_SECRET_STORE";
+
+/// Service name secrets are filed under in the platform store
+const SERVICE: &str = "citadel-browser";
+
+/// A place to keep secrets, addressed by account name
+pub trait SecretStore: Send + Sync {
+    /// Short name for logging
+    fn name(&self) -> &'static str;
+
+    /// Whether secrets are protected by the OS rather than only by file
+    /// permissions
+    fn is_os_protected(&self) -> bool;
+
+    /// The secret stored for `account`, if any
+    fn get(&self, account: &str) -> std::io::Result<Option<Zeroizing<Vec<u8>>>>;
+
+    /// Store a secret for `account`, replacing any previous one
+    fn set(&self, account: &str, secret: &[u8]) -> std::io::Result<()>;
+
+    /// Remove the secret for `account`; removing a missing secret succeeds
+    fn delete(&self, account: &str) -> std::io::Result<()>;
+}
+
+/// Account names are passed to external tools, so only a plain character
+/// set is accepted
+fn check_account(account: &str) -> std::io::Result<()> {
+    let valid = !account.is_empty()
+        && account
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/@+[]".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid secret account name {:?}", account),
+        ))
+    }
+}
+
+/// Account holding a site credential for `username` at `origin`
+pub fn credential_account(origin: &url::Origin, username: &str) -> String {
+    format!(
+        "credential:{}:{}",
+        origin.ascii_serialization(),
+        URL_SAFE_NO_PAD.encode(username)
+    )
+}
+
+/// Store a site password
+pub fn store_credential(
+    store: &dyn SecretStore,
+    origin: &url::Origin,
+    username: &str,
+    password: &str,
+) -> std::io::Result<()> {
+    store.set(&credential_account(origin, username), password.as_bytes())
+}
+
+/// The stored password for `username` at `origin`, if any
+pub fn credential(
+    store: &dyn SecretStore,
+    origin: &url::Origin,
+    username: &str,
+) -> std::io::Result<Option<Zeroizing<String>>> {
+    let Some(secret) = store.get(&credential_account(origin, username))? else {
+        return Ok(None);
+    };
+    String::from_utf8(secret.to_vec())
+        .map(|password| Some(Zeroizing::new(password)))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Run a tool with `input` on stdin. Returns whether it succeeded and its
+/// output, which is zeroized when dropped.
+fn run(program: &str, args: &[&str], input: &[u8]) -> std::io::Result<(bool, Zeroizing<Vec<u8>>)> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    Ok((output.status.success(), Zeroizing::new(output.stdout)))
+}
+
+fn tool_failed(program: &str) -> std::io::Error {
+    std::io::Error::other(format!("{} failed", program))
+}
+
+/// Decode a secret a tool printed as base64
+fn decode_output(output: &[u8]) -> std::io::Result<Zeroizing<Vec<u8>>> {
+    BASE64
+        .decode(output.trim_ascii())
+        .map(Zeroizing::new)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Whether `program` is on the `PATH`
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file()
+        })
+    })
+}
+
+/// The Secret Service, through `secret-tool` from libsecret
+#[derive(Debug, Default)]
+pub struct SecretService;
+
+impl SecretStore for SecretService {
+    fn name(&self) -> &'static str {
+        "Secret Service"
+    }
+
+    fn is_os_protected(&self) -> bool {
+        true
+    }
+
+    fn get(&self, account: &str) -> std::io::Result<Option<Zeroizing<Vec<u8>>>> {
+        check_account(account)?;
+        let (found, output) = run(
+            "secret-tool",
+            &["lookup", "service", SERVICE, "account", account],
+            &[],
+        )?;
+        found.then(|| decode_output(&output)).transpose()
+    }
+
+    fn set(&self, account: &str, secret: &[u8]) -> std::io::Result<()> {
+        check_account(account)?;
+        let label = format!("Citadel Browser: {}", account);
+        let encoded = Zeroizing::new(BASE64.encode(secret));
+        let (stored, _) = run(
+            "secret-tool",
+            &[
+                "store", "--label", &label, "service", SERVICE, "account", account,
+            ],
+            encoded.as_bytes(),
+        )?;
+        stored
+            .then_some(())
+            .ok_or_else(|| tool_failed("secret-tool"))
+    }
+
+    fn delete(&self, account: &str) -> std::io::Result<()> {
+        check_account(account)?;
+        // `clear` fails only when nothing matched
+        run(
+            "secret-tool",
+            &["clear", "service", SERVICE, "account", account],
+            &[],
+        )
+        .map(|_| ())
+    }
+}
+
+/// The macOS login Keychain, through `security`
+#[derive(Debug, Default)]
+pub struct MacKeychain;
+
+impl SecretStore for MacKeychain {
+    fn name(&self) -> &'static str {
+        "macOS Keychain"
+    }
+
+    fn is_os_protected(&self) -> bool {
+        true
+    }
+
+    fn get(&self, account: &str) -> std::io::Result<Option<Zeroizing<Vec<u8>>>> {
+        check_account(account)?;
+        let (found, output) = run(
+            "security",
+            &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+            &[],
+        )?;
+        found.then(|| decode_output(&output)).transpose()
+    }
+
+    fn set(&self, account: &str, secret: &[u8]) -> std::io::Result<()> {
+        check_account(account)?;
+        // `security -i` reads the command from stdin, keeping the secret off
+        // the command line
+        let command = Zeroizing::new(format!(
+            "add-generic-password -U -s {} -a \"{}\" -w {}\n",
+            SERVICE,
+            account,
+            BASE64.encode(secret)
+        ));
+        let (stored, _) = run("security", &["-i"], command.as_bytes())?;
+        stored.then_some(()).ok_or_else(|| tool_failed("security"))
+    }
+
+    fn delete(&self, account: &str) -> std::io::Result<()> {
+        check_account(account)?;
+        // Fails only when there was nothing to delete
+        run(
+            "security",
+            &["delete-generic-password", "-s", SERVICE, "-a", account],
+            &[],
+        )
+        .map(|_| ())
+    }
+}
+
+/// Windows DPAPI, through PowerShell. Secrets are protected for the current
+/// user and the protected blobs kept as files in `dir`.
+#[derive(Debug)]
+pub struct Dpapi {
+    dir: PathBuf,
+}
+
+impl Dpapi {
+    const PROTECT: &'static str = "Add-Type -AssemblyName System.Security; \
+        $data = [Convert]::FromBase64String([Console]::In.ReadToEnd().Trim()); \
+        [Convert]::ToBase64String([Security.Cryptography.ProtectedData]::Protect($data, $null, 'CurrentUser'))";
+    const UNPROTECT: &'static str = "Add-Type -AssemblyName System.Security; \
+        $data = [Convert]::FromBase64String([Console]::In.ReadToEnd().Trim()); \
+        [Convert]::ToBase64String([Security.Cryptography.ProtectedData]::Unprotect($data, $null, 'CurrentUser'))";
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn blob_path(&self, account: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.dpapi", URL_SAFE_NO_PAD.encode(account)))
+    }
+
+    fn powershell(script: &str, input: &[u8]) -> std::io::Result<Zeroizing<Vec<u8>>> {
+        let (ok, output) = run(
+            "powershell",
+            &["-NoProfile", "-NonInteractive", "-Command", script],
+            input,
+        )?;
+        if ok {
+            decode_output(&output)
+        } else {
+            Err(tool_failed("powershell"))
+        }
+    }
+}
+
+impl SecretStore for Dpapi {
+    fn name(&self) -> &'static str {
+        "Windows DPAPI"
+    }
+
+    fn is_os_protected(&self) -> bool {
+        true
+    }
+
+    fn get(&self, account: &str) -> std::io::Result<Option<Zeroizing<Vec<u8>>>> {
+        check_account(account)?;
+        match std::fs::read(self.blob_path(account)) {
+            Ok(blob) => Self::powershell(Self::UNPROTECT, &blob).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &[u8]) -> std::io::Result<()> {
+        check_account(account)?;
+        let encoded = Zeroizing::new(BASE64.encode(secret));
+        let blob = Self::powershell(Self::PROTECT, encoded.as_bytes())?;
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.blob_path(account), BASE64.encode(&blob[..]))
+    }
+
+    fn delete(&self, account: &str) -> std::io::Result<()> {
+        check_account(account)?;
+        match std::fs::remove_file(self.blob_path(account)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Secrets in a JSON file readable only by the user. Used when no platform
+/// store is available.
+#[derive(Debug)]
+pub struct FileSecretStore {
+    path: PathBuf,
+}
+
+impl FileSecretStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read(&self) -> std::io::Result<BTreeMap<String, SecretString>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&Zeroizing::new(bytes))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, SecretString>) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = Zeroizing::new(
+            serde_json::to_vec_pretty(secrets)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+        );
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&self.path)?.write_all(&json)
+    }
+}
+
+impl SecretStore for FileSecretStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn is_os_protected(&self) -> bool {
+        false
+    }
+
+    fn get(&self, account: &str) -> std::io::Result<Option<Zeroizing<Vec<u8>>>> {
+        check_account(account)?;
+        self.read()?
+            .get(account)
+            .map(|encoded| decode_output(encoded.expose().as_bytes()))
+            .transpose()
+    }
+
+    fn set(&self, account: &str, secret: &[u8]) -> std::io::Result<()> {
+        check_account(account)?;
+        let mut secrets = self.read()?;
+        secrets.insert(
+            account.to_string(),
+            SecretString::new(BASE64.encode(secret)),
+        );
+        self.write(&secrets)
+    }
+
+    fn delete(&self, account: &str) -> std::io::Result<()> {
+        check_account(account)?;
+        let mut secrets = self.read()?;
+        if secrets.remove(account).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// The platform's secret store, when its tool is installed
+pub fn system_store() -> Option<Box<dyn SecretStore>> {
+    if cfg!(target_os = "macos") {
+        on_path("security").then(|| Box::new(MacKeychain) as Box<dyn SecretStore>)
+    } else if cfg!(target_os = "windows") {
+        let dir = config_dir()?.join("citadel").join("secrets");
+        on_path("powershell").then(|| Box::new(Dpapi::new(dir)) as Box<dyn SecretStore>)
+    } else {
+        on_path("secret-tool").then(|| Box::new(SecretService) as Box<dyn SecretStore>)
+    }
+}
+
+/// The platform's secret store, or the file fallback at `fallback_path`.
+/// `$CITADEL_SECRET_STORE=file` always picks the fallback.
+pub fn open(fallback_path: PathBuf) -> Box<dyn SecretStore> {
+    let force_file = std::env::var(SECRET_STORE_ENV).is_ok_and(|store| store == "file");
+    let store = if force_file { None } else { system_store() };
+    store.unwrap_or_else(|| Box::new(FileSecretStore::new(fallback_path)))
+}
+
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
+
+/// Where the file fallback keeps secrets: `citadel/secrets.json` under the
+/// config directory
+pub fn default_fallback_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("citadel").join("secrets.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("citadel-secrets-{}", uuid::Uuid::new_v4()));
+        let store = FileSecretStore::new(dir.join("secrets.json"));
+        let origin = url::Url::parse("https://bank.example:8443/login")
+            .unwrap()
+            .origin();
+
+        assert!(credential(&store, &origin, "alice@example.com")
+            .unwrap()
+            .is_none());
+        store_credential(&store, &origin, "alice@example.com", "s3cret \"pw\"").unwrap();
+        assert_eq!(
+            credential(&store, &origin, "alice@example.com")
+                .unwrap()
+                .unwrap()
+                .as_str(),
+            "s3cret \"pw\""
+        );
+        assert!(credential(&store, &origin, "bob").unwrap().is_none());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join("secrets.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let account = credential_account(&origin, "alice@example.com");
+        store.delete(&account).unwrap();
+        store.delete(&account).unwrap();
+        assert!(store.get(&account).unwrap().is_none());
+        // Names that could escape a tool's arguments are refused
+        assert!(store.set("a\" -w x", b"secret").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
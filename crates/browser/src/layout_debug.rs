@@ -0,0 +1,420 @@
+//! Layout debugging overlay (box model visualizer)
+//!
+//! Draws every rect of a [`LayoutResult`] over the page: the border box in
+//! orange, the padding box in green and the content box in blue, each
+//! labelled with its tag. The box under the pointer is shaded and its
+//! dimensions shown. The rects come from the host's Taffy layout, so the
+//! overlay shows where that layout disagrees with what is painted.
+//!
+//! Toggled with Ctrl+Shift+D in devtools builds.
+
+use citadel_parser::css::LengthValue;
+use citadel_parser::dom::{NodeData, NodeHandle};
+use citadel_parser::{CitadelStylesheet, ComputedStyle, Dom, LayoutResult};
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::widget::{Operation, Tree, Widget};
+use iced::advanced::{overlay, renderer, Clipboard, Renderer as _, Shell};
+use iced::widget::canvas::{self, Frame, Path, Stroke};
+use iced::{event, mouse, Color, Element, Event, Length, Pixels, Point, Rectangle, Size};
+
+use crate::app::Message;
+
+/// Font size used to resolve `em` lengths
+const BASE_FONT_SIZE: f32 = 16.0;
+
+const BORDER_COLOR: Color = Color::from_rgb(0.95, 0.55, 0.1);
+const PADDING_COLOR: Color = Color::from_rgb(0.2, 0.7, 0.3);
+const CONTENT_COLOR: Color = Color::from_rgb(0.2, 0.45, 0.9);
+const LABEL_COLOR: Color = Color::from_rgb(0.35, 0.35, 0.35);
+
+/// One element's box model, in page coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugBox {
+    pub tag: String,
+    pub border: Rectangle,
+    pub padding: Rectangle,
+    pub content: Rectangle,
+}
+
+/// Every element box of a laid-out page
+#[derive(Debug, Clone, Default)]
+pub struct LayoutDebugOverlay {
+    boxes: Vec<DebugBox>,
+}
+
+impl LayoutDebugOverlay {
+    /// Collect the boxes of every laid-out element. Taffy positions are
+    /// relative to the parent, so offsets are accumulated down the tree.
+    pub fn build(dom: &Dom, stylesheet: &CitadelStylesheet, layout: &LayoutResult) -> Self {
+        let mut boxes = Vec::new();
+        collect(&dom.root(), Point::ORIGIN, stylesheet, layout, &mut boxes);
+        Self { boxes }
+    }
+
+    pub fn boxes(&self) -> &[DebugBox] {
+        &self.boxes
+    }
+
+    /// The innermost box under a point
+    pub fn box_at(&self, point: Point) -> Option<&DebugBox> {
+        self.boxes
+            .iter()
+            .filter(|debug_box| debug_box.border.contains(point))
+            .min_by(|a, b| {
+                let area = |r: &Rectangle| r.width * r.height;
+                area(&a.border).total_cmp(&area(&b.border))
+            })
+    }
+
+    /// Draw the overlay on top of `page`; the page keeps receiving input
+    pub fn over<'a>(&self, page: Element<'a, Message>) -> Element<'a, Message> {
+        let overlay = canvas::Canvas::new(self.clone())
+            .width(Length::Fill)
+            .height(Length::Fill);
+        Element::new(Layered {
+            base: page,
+            overlay: overlay.into(),
+        })
+    }
+}
+
+fn collect(
+    handle: &NodeHandle,
+    origin: Point,
+    stylesheet: &CitadelStylesheet,
+    layout: &LayoutResult,
+    boxes: &mut Vec<DebugBox>,
+) {
+    let Ok(node) = handle.read() else {
+        return;
+    };
+    let mut child_origin = origin;
+    if let Some(rect) = layout.node_layouts.get(&node.id()) {
+        let border = Rectangle::new(
+            Point::new(origin.x + rect.x, origin.y + rect.y),
+            Size::new(rect.width, rect.height),
+        );
+        child_origin = border.position();
+        if let NodeData::Element(element) = &node.data {
+            let tag = element.local_name().to_ascii_lowercase();
+            let classes = node.class_list();
+            let id = node.element_id();
+            let style = stylesheet.compute_styles(&tag, &classes, id.as_deref());
+            let padding = inset(border, border_widths(&style));
+            let content = inset(padding, padding_widths(&style));
+            boxes.push(DebugBox {
+                tag,
+                border,
+                padding,
+                content,
+            });
+        }
+    }
+    let children = node.children.clone();
+    drop(node);
+    for child in &children {
+        collect(child, child_origin, stylesheet, layout, boxes);
+    }
+}
+
+/// Top, right, bottom and left widths
+type Edges = [f32; 4];
+
+fn border_widths(style: &ComputedStyle) -> Edges {
+    [to_px(&style.border_width); 4]
+}
+
+fn padding_widths(style: &ComputedStyle) -> Edges {
+    [
+        to_px(&style.padding_top),
+        to_px(&style.padding_right),
+        to_px(&style.padding_bottom),
+        to_px(&style.padding_left),
+    ]
+}
+
+/// Absolute lengths in pixels; relative ones the overlay cannot resolve count
+/// as zero
+fn to_px(length: &Option<LengthValue>) -> f32 {
+    match length {
+        Some(LengthValue::Px(px)) => *px,
+        Some(LengthValue::Em(em) | LengthValue::Rem(em)) => em * BASE_FONT_SIZE,
+        _ => 0.0,
+    }
+    .max(0.0)
+}
+
+fn inset(rect: Rectangle, [top, right, bottom, left]: Edges) -> Rectangle {
+    Rectangle::new(
+        Point::new(rect.x + left, rect.y + top),
+        Size::new(
+            (rect.width - left - right).max(0.0),
+            (rect.height - top - bottom).max(0.0),
+        ),
+    )
+}
+
+impl canvas::Program<Message> for LayoutDebugOverlay {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &iced::Renderer,
+        _theme: &iced::Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let outline = |frame: &mut Frame, rect: &Rectangle, color: Color| {
+            frame.stroke(
+                &Path::rectangle(rect.position(), rect.size()),
+                Stroke::default().with_color(color).with_width(1.0),
+            );
+        };
+
+        for debug_box in &self.boxes {
+            outline(&mut frame, &debug_box.border, BORDER_COLOR);
+            if debug_box.padding != debug_box.border {
+                outline(&mut frame, &debug_box.padding, PADDING_COLOR);
+            }
+            outline(&mut frame, &debug_box.content, CONTENT_COLOR);
+            frame.fill_text(canvas::Text {
+                content: debug_box.tag.clone(),
+                position: Point::new(debug_box.border.x + 2.0, debug_box.border.y + 1.0),
+                color: LABEL_COLOR,
+                size: Pixels(10.0),
+                ..canvas::Text::default()
+            });
+        }
+
+        if let Some(hovered) = cursor
+            .position_in(bounds)
+            .and_then(|point| self.box_at(point))
+        {
+            let border = hovered.border;
+            frame.fill_rectangle(
+                border.position(),
+                border.size(),
+                Color {
+                    a: 0.15,
+                    ..BORDER_COLOR
+                },
+            );
+            frame.fill_rectangle(
+                hovered.content.position(),
+                hovered.content.size(),
+                Color {
+                    a: 0.2,
+                    ..CONTENT_COLOR
+                },
+            );
+            frame.fill_text(canvas::Text {
+                content: format!(
+                    "{}  {:.0} × {:.0}",
+                    hovered.tag, border.width, border.height
+                ),
+                position: Point::new(border.x, (border.y - 14.0).max(0.0)),
+                color: Color::BLACK,
+                size: Pixels(12.0),
+                ..canvas::Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Draws `overlay` on top of `base` at the same size. Input goes to `base`
+/// only, so the page stays usable underneath.
+struct Layered<'a> {
+    base: Element<'a, Message>,
+    overlay: Element<'a, Message>,
+}
+
+impl<'a> Widget<Message, iced::Theme, iced::Renderer> for Layered<'a> {
+    fn size(&self) -> Size<Length> {
+        self.base.as_widget().size()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.base), Tree::new(&self.overlay)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.base, &self.overlay]);
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let base = self
+            .base
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits);
+        let size = base.size();
+        let overlay = self.overlay.as_widget().layout(
+            &mut tree.children[1],
+            renderer,
+            &layout::Limits::new(Size::ZERO, size),
+        );
+        layout::Node::with_children(size, vec![base, overlay])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let mut children = layout.children();
+        let (Some(base), Some(overlay)) = (children.next(), children.next()) else {
+            return;
+        };
+        self.base.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            base,
+            cursor,
+            viewport,
+        );
+        renderer.with_layer(overlay.bounds(), |renderer| {
+            self.overlay.as_widget().draw(
+                &tree.children[1],
+                renderer,
+                theme,
+                style,
+                overlay,
+                cursor,
+                viewport,
+            );
+        });
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        if let Some(base) = layout.children().next() {
+            self.base
+                .as_widget()
+                .operate(&mut tree.children[0], base, renderer, operation);
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let Some(base) = layout.children().next() else {
+            return event::Status::Ignored;
+        };
+        self.base.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            base,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        layout
+            .children()
+            .next()
+            .map(|base| {
+                self.base.as_widget().mouse_interaction(
+                    &tree.children[0],
+                    base,
+                    cursor,
+                    viewport,
+                    renderer,
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+    ) -> Option<overlay::Element<'b, Message, iced::Theme, iced::Renderer>> {
+        let base = layout.children().next()?;
+        self.base
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], base, renderer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::security::SecurityContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_boxes_nest_and_hit_test_innermost() {
+        let context = Arc::new(SecurityContext::new(10));
+        let dom = citadel_parser::parse_html(
+            "<html><body><div class=\"card\"><p>Hello</p></div></body></html>",
+            context.clone(),
+        )
+        .unwrap();
+        let stylesheet = citadel_parser::parse_css(
+            ".card { padding: 10px; border-width: 2px; width: 200px; }",
+            context,
+        )
+        .unwrap();
+        let layout = citadel_parser::compute_layout(&dom, &stylesheet, 800.0, 600.0).unwrap();
+        let overlay = LayoutDebugOverlay::build(&dom, &stylesheet, &layout);
+
+        let card = overlay
+            .boxes()
+            .iter()
+            .find(|b| b.tag == "div")
+            .expect("div is laid out");
+        assert_eq!(card.padding.x, card.border.x + 2.0);
+        assert_eq!(card.content.x, card.padding.x + 10.0);
+        assert_eq!(card.content.width, card.border.width - 24.0);
+
+        let paragraph = overlay.boxes().iter().find(|b| b.tag == "p").unwrap();
+        let inside = Point::new(
+            paragraph.border.x + paragraph.border.width / 2.0,
+            paragraph.border.y + paragraph.border.height / 2.0,
+        );
+        if paragraph.border.width > 0.0 && paragraph.border.height > 0.0 {
+            assert_eq!(overlay.box_at(inside).map(|b| b.tag.as_str()), Some("p"));
+        }
+        assert!(overlay.box_at(Point::new(-1.0, -1.0)).is_none());
+    }
+}
@@ -7,22 +7,76 @@
 //! Homepage: https://citadelbrowser.com
 //! Author: Deep Fork Cyber - https://deepforkcyber.com
 
+pub mod accessibility;
 pub mod app;
+pub mod assets;
+pub mod clipboard;
+pub mod compat;
+pub mod container_policies;
+pub mod content_budget;
+pub mod cookies;
+pub mod csp_reports;
+pub mod downloads;
+pub mod dropped_content;
 pub mod engine;
+pub mod extensions;
+pub mod external_protocols;
+pub mod filter_allowlist;
+pub mod focus;
+pub mod history;
+pub mod html;
+pub mod image_decoder;
+pub mod image_viewer;
+pub mod keychain;
+#[cfg(feature = "devtools")]
+pub mod layout_debug;
+pub mod link_preview;
+pub mod load_scheduler;
+pub mod log_redaction;
+pub mod memory_profile;
 pub mod memory_protection;
+#[cfg(feature = "devtools")]
+pub mod net_internals;
+pub mod overlay_cleanup;
+pub mod page_escalation;
+pub mod page_menu;
+pub mod panic;
+pub mod parser_profile;
 pub mod performance;
+pub mod power_profile;
+pub mod profile;
 pub mod renderer;
+pub mod resource_caches;
 pub mod resource_loader;
+pub mod session;
+pub mod settings;
+pub mod shutdown;
+pub mod startup;
+pub mod stylesheet_cache;
+pub mod suggestions;
+pub mod tab_menu;
 pub mod tabs;
+pub mod text_only;
 pub mod ui;
+pub mod user_styles;
+pub mod web_app;
+pub mod web_fonts;
+pub mod windows;
 
 // Re-export the main application
 pub use app::CitadelBrowser;
 
 // Re-export common types
+pub use accessibility::AccessibilityBridge;
 pub use engine::BrowserEngine;
+pub use extensions::{Extensions, InstalledExtension};
+pub use focus::{FocusActivation, FocusManager};
+pub use history::{HistoryEntry, HistoryManager};
 pub use memory_protection::{BrowserMemoryManager, BrowserMemoryStatistics};
 pub use performance::{CleanupPriority, MemoryConfig, MemoryPressure, PerformanceMonitor};
 pub use renderer::CitadelRenderer;
 pub use resource_loader::ResourceLoader;
+pub use suggestions::{Suggestion, SuggestionEngine, SuggestionProvider};
 pub use ui::{CitadelUI, UIMessage};
+pub use user_styles::{UserStyleRule, UserStylesheets};
+pub use windows::{BrowserWindow, DetachedMode, DetachedWindow, WindowKind, WindowManager};
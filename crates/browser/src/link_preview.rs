@@ -0,0 +1,89 @@
+//! Status-bar preview of a hovered link's destination
+//!
+//! The preview is worked out from the link's `href` alone: nothing is
+//! fetched, resolved or prefetched while hovering. The destination is shown
+//! as it would be navigated to, with tracking parameters stripped, the host
+//! rendered under the IDN homograph policy and any `user:pass@` prefix
+//! dropped, so `https://bank.example@evil.example` cannot pass for the bank.
+
+use url::Url;
+
+/// Longest preview shown; longer destinations are elided in the middle
+const MAX_PREVIEW_CHARS: usize = 120;
+
+/// The text to show for a hovered link, or `None` if it leads nowhere
+/// (blank or script `href`s)
+pub fn preview(href: &str, page_url: Option<&Url>) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() {
+        return None;
+    }
+    let mut url = match page_url {
+        Some(base) => base.join(href).ok()?,
+        None => Url::parse(href).ok()?,
+    };
+    match url.scheme() {
+        "http" | "https" => {
+            citadel_networking::url_canon::strip_tracking_params(&mut url);
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+        }
+        "javascript" | "vbscript" | "data" | "blob" => return None,
+        _ => {}
+    }
+    Some(elide(&citadel_networking::display_url(url.as_str())))
+}
+
+/// `text` cut to [`MAX_PREVIEW_CHARS`], keeping both ends so the host and
+/// the final path segment stay visible
+fn elide(text: &str) -> String {
+    let count = text.chars().count();
+    if count <= MAX_PREVIEW_CHARS {
+        return text.to_string();
+    }
+    let head = MAX_PREVIEW_CHARS * 2 / 3;
+    let tail = MAX_PREVIEW_CHARS - head - 1;
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(count - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_resolves_and_scrubs() {
+        let page = Url::parse("https://example.com/blog/post").unwrap();
+        assert_eq!(
+            preview("../about?utm_source=x&id=3", Some(&page)).as_deref(),
+            Some("https://example.com/about?id=3")
+        );
+        assert_eq!(
+            preview("https://bank.example@evil.example/login", Some(&page)).as_deref(),
+            Some("https://evil.example/login")
+        );
+        assert_eq!(
+            preview("mailto:me@example.com", Some(&page)).as_deref(),
+            Some("mailto:me@example.com")
+        );
+        assert_eq!(preview("javascript:alert(1)", Some(&page)), None);
+        assert_eq!(preview("   ", Some(&page)), None);
+        assert_eq!(preview("/relative", None), None);
+    }
+
+    #[test]
+    fn test_preview_keeps_homographs_in_punycode() {
+        let preview = preview("https://аррӏе.com/", None).unwrap();
+        assert!(preview.starts_with("https://xn--"));
+    }
+
+    #[test]
+    fn test_long_destinations_are_elided() {
+        let long = format!("https://example.com/{}/end", "a".repeat(300));
+        let shown = preview(&long, None).unwrap();
+        assert_eq!(shown.chars().count(), MAX_PREVIEW_CHARS);
+        assert!(shown.starts_with("https://example.com/"));
+        assert!(shown.ends_with("/end"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Scheduling of concurrent page loads
+//!
+//! Loading many tabs at once, as when a session is restored, would start
+//! every fetch and parse together. The engine instead takes a slot from the
+//! load scheduler for each network load: at most `max_active` loads run at
+//! a time and the rest wait in line. The focused tab's load always goes
+//! next; the others start in the order they were queued, so a tab that
+//! navigates twice finishes with its latest page. A tab that closes gives
+//! up its place in line, and once the browser starts shutting down no load
+//! starts at all.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Loads running at once unless configured otherwise
+pub const DEFAULT_MAX_ACTIVE_LOADS: usize = 4;
+
+/// Bounds and orders the page loads of all tabs
+#[derive(Debug)]
+pub struct LoadScheduler {
+    max_active: usize,
+    state: Mutex<SchedulerState>,
+    /// Woken whenever a slot frees up, the focus moves or the line changes
+    changed: Notify,
+}
+
+#[derive(Debug, Default)]
+struct SchedulerState {
+    /// Tickets of the running loads
+    active: HashSet<u64>,
+    /// Waiting loads in the order they were queued
+    waiting: VecDeque<Waiting>,
+    focused: Option<Uuid>,
+    next_ticket: u64,
+    /// No more loads start
+    closed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Waiting {
+    ticket: u64,
+    tab_id: Uuid,
+}
+
+impl SchedulerState {
+    /// Waiting loads in the order they will start
+    fn service_order(&self) -> impl Iterator<Item = &Waiting> {
+        let focused = self.focused;
+        let is_focused = move |waiting: &&Waiting| Some(waiting.tab_id) == focused;
+        self.waiting
+            .iter()
+            .filter(is_focused)
+            .chain(self.waiting.iter().filter(move |w| !is_focused(w)))
+    }
+}
+
+/// A running load; dropping it frees the slot for the next one in line
+#[derive(Debug)]
+pub struct LoadSlot {
+    scheduler: Arc<LoadScheduler>,
+    ticket: u64,
+}
+
+impl Drop for LoadSlot {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            state.active.remove(&self.ticket);
+        }
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+/// A place in line; leaves the line when the waiting load is dropped
+struct Queued<'a> {
+    scheduler: &'a LoadScheduler,
+    ticket: u64,
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            state
+                .waiting
+                .retain(|waiting| waiting.ticket != self.ticket);
+        }
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+impl Default for LoadScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ACTIVE_LOADS)
+    }
+}
+
+impl LoadScheduler {
+    /// A scheduler running at most `max_active` loads at once (at least one)
+    pub fn new(max_active: usize) -> Self {
+        Self {
+            max_active: max_active.max(1),
+            state: Mutex::new(SchedulerState::default()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Wait for a slot to load a page in `tab_id`; `None` if the tab closes
+    /// or the scheduler is closed before its turn
+    pub async fn acquire(self: &Arc<Self>, tab_id: Uuid) -> Option<LoadSlot> {
+        let ticket = {
+            let mut state = self.state.lock().ok()?;
+            if state.closed {
+                return None;
+            }
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiting.push_back(Waiting { ticket, tab_id });
+            ticket
+        };
+        self.changed.notify_waiters();
+        let _queued = Queued {
+            scheduler: self,
+            ticket,
+        };
+
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            // Registered before the check, so no wakeup between the two is lost
+            changed.as_mut().enable();
+            {
+                let mut state = self.state.lock().ok()?;
+                if !state.waiting.iter().any(|w| w.ticket == ticket) {
+                    return None;
+                }
+                let next = state.service_order().next().map(|w| w.ticket);
+                if state.active.len() < self.max_active && next == Some(ticket) {
+                    state.waiting.retain(|w| w.ticket != ticket);
+                    state.active.insert(ticket);
+                    drop(state);
+                    // Another load may fit in the slots still free
+                    self.changed.notify_waiters();
+                    return Some(LoadSlot {
+                        scheduler: self.clone(),
+                        ticket,
+                    });
+                }
+            }
+            changed.await;
+        }
+    }
+
+    /// The tab the user is looking at; its load is next in line
+    pub fn focus(&self, tab_id: Uuid) {
+        if let Ok(mut state) = self.state.lock() {
+            state.focused = Some(tab_id);
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Give up the tab's place in line, for a tab that closed
+    pub fn cancel(&self, tab_id: Uuid) {
+        if let Ok(mut state) = self.state.lock() {
+            state.waiting.retain(|waiting| waiting.tab_id != tab_id);
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Waiting tabs with their place in line, starting at 1
+    pub fn queue(&self) -> Vec<(Uuid, usize)> {
+        self.state
+            .lock()
+            .map(|state| {
+                state
+                    .service_order()
+                    .enumerate()
+                    .map(|(index, waiting)| (waiting.tab_id, index + 1))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Loads running now
+    pub fn active_loads(&self) -> usize {
+        self.state.lock().map_or(0, |state| state.active.len())
+    }
+
+    /// Start no more loads: waiting ones are cancelled, running ones finish
+    pub fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+            state.waiting.clear();
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// Whether [`Self::close`] was called
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().map_or(true, |state| state.closed)
+    }
+
+    /// Wait until no load is running
+    pub async fn drained(&self) {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            if self.active_loads() == 0 {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_loads_are_bounded_and_focused_tab_goes_first() {
+        let scheduler = Arc::new(LoadScheduler::new(1));
+        let (first, second, focused) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let running = scheduler.acquire(first).await.unwrap();
+        let waiters: Vec<_> = [second, focused]
+            .into_iter()
+            .map(|tab| {
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move { scheduler.acquire(tab).await.map(|slot| (tab, slot)) })
+            })
+            .collect();
+        settle().await;
+        assert_eq!(scheduler.active_loads(), 1);
+        assert_eq!(scheduler.queue(), vec![(second, 1), (focused, 2)]);
+
+        scheduler.focus(focused);
+        assert_eq!(scheduler.queue(), vec![(focused, 1), (second, 2)]);
+        drop(running);
+        settle().await;
+        assert_eq!(scheduler.queue(), vec![(second, 1)]);
+        assert_eq!(scheduler.active_loads(), 1);
+
+        scheduler.cancel(second);
+        let mut results = Vec::new();
+        for waiter in waiters {
+            results.push(waiter.await.unwrap().map(|(tab, _)| tab));
+        }
+        assert_eq!(results, vec![None, Some(focused)]);
+        assert_eq!(scheduler.active_loads(), 0);
+
+        // Closing cancels the line but lets the running load finish
+        let running = scheduler.acquire(first).await.unwrap();
+        let waiter = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(second).await.is_some() })
+        };
+        settle().await;
+        scheduler.close();
+        assert!(!waiter.await.unwrap());
+        assert!(scheduler.acquire(focused).await.is_none());
+        let drained = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.drained().await })
+        };
+        settle().await;
+        assert!(!drained.is_finished());
+        drop(running);
+        drained.await.unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+//! Redacting what the browser logs
+//!
+//! Logs end up in terminals, bug reports and journald, so nothing in them
+//! should say where the user went or what they read. Every record passes
+//! through [`RedactingLogger`] before `env_logger` writes it:
+//!
+//! - URLs lose their credentials, query string and fragment, which is where
+//!   session tokens, search terms and email addresses sit.
+//! - In [`RedactionMode::Strict`] a URL is cut down to its scheme and host,
+//!   followed by a short hash of the whole URL. The hash is salted per run,
+//!   so lines about the same page can be matched up within one log but not
+//!   looked up against a list of known URLs.
+//! - In strict mode quoted text (`'…'` or `"…"`), which is how page titles,
+//!   previews and form values are logged, is replaced by its length.
+//!
+//! Page text itself is only logged at debug level or below. Strict mode is
+//! the default in release builds; debug builds default to the standard mode.
+//! `CITADEL_LOG_REDACTION=strict|standard` overrides either.
+
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+
+use log::{Log, Metadata, Record};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Environment variable choosing the redaction mode
+pub const LOG_REDACTION_ENV: &str = "CITADEL_LOG_REDACTION";
+
+/// How much of a URL or quoted text survives into the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionMode {
+    /// URLs keep their host and path; quoted text is kept
+    Standard,
+    /// URLs keep only their host plus a salted hash; quoted text is dropped
+    Strict,
+}
+
+impl RedactionMode {
+    /// The mode for this build, unless [`LOG_REDACTION_ENV`] names another
+    pub fn from_env() -> Self {
+        match std::env::var(LOG_REDACTION_ENV).as_deref() {
+            Ok("strict") => Self::Strict,
+            Ok("standard") => Self::Standard,
+            _ if cfg!(debug_assertions) => Self::Standard,
+            _ => Self::Strict,
+        }
+    }
+}
+
+/// A logger that redacts each record before handing it to `inner`
+pub struct RedactingLogger<L> {
+    inner: L,
+    mode: RedactionMode,
+}
+
+impl<L: Log> RedactingLogger<L> {
+    pub fn new(inner: L, mode: RedactionMode) -> Self {
+        Self { inner, mode }
+    }
+}
+
+impl<L: Log> Log for RedactingLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let message = redact(&record.args().to_string(), self.mode);
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!("{message}"))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the redacting logger in front of `env_logger`, which still reads
+/// `RUST_LOG` for what gets logged at all
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter();
+    let logger = RedactingLogger::new(inner, RedactionMode::from_env());
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// `message` with its URLs, and in strict mode its quoted text, redacted
+pub fn redact(message: &str, mode: RedactionMode) -> String {
+    let message = redact_urls(message, mode);
+    match mode {
+        RedactionMode::Standard => message,
+        RedactionMode::Strict => redact_quoted(&message),
+    }
+}
+
+/// Characters a URL scheme is made of
+fn is_scheme_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')
+}
+
+/// Characters that end a URL embedded in a log line
+fn ends_url(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '\'' | '"' | '<' | '>' | '`')
+}
+
+fn redact_urls(message: &str, mode: RedactionMode) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(at) = rest.find("://") {
+        let scheme_start = rest[..at]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_scheme_char(*c))
+            .last()
+            .map_or(at, |(i, _)| i);
+        let end = rest[at..]
+            .char_indices()
+            .find(|(_, c)| ends_url(*c))
+            .map_or(rest.len(), |(i, _)| at + i);
+        // Sentence punctuation after a URL is not part of it
+        // (never the separator's slashes, so the search always moves on)
+        let end = scheme_start
+            + rest[scheme_start..end]
+                .trim_end_matches(|c| matches!(c, '.' | ',' | ';' | ':' | ')' | ']' | '!' | '?'))
+                .len();
+        out.push_str(&rest[..scheme_start]);
+        match Url::parse(&rest[scheme_start..end]) {
+            Ok(url) => out.push_str(&redact_url(&url, mode)),
+            Err(_) => out.push_str(&rest[scheme_start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn redact_url(url: &Url, mode: RedactionMode) -> String {
+    let mut out = format!("{}://", url.scheme());
+    if let Some(host) = url.host_str() {
+        out.push_str(host);
+    }
+    if let Some(port) = url.port() {
+        let _ = write!(out, ":{port}");
+    }
+    match mode {
+        RedactionMode::Standard => out.push_str(url.path()),
+        RedactionMode::Strict => {
+            let _ = write!(out, "/[{}]", url_hash(url.as_str()));
+        }
+    }
+    out
+}
+
+/// Eight hex digits of `url` hashed with this run's salt
+fn url_hash(url: &str) -> String {
+    static SALT: OnceLock<[u8; 16]> = OnceLock::new();
+    let salt = SALT.get_or_init(rand::random);
+    let digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update(url.as_bytes())
+        .finalize();
+    digest[..4].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether a quote at this point opens quoted text, going by what precedes it
+fn opens_quote(before: Option<char>) -> bool {
+    before.is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '[' | '{' | ':' | '='))
+}
+
+/// Whether a quote at this point closes quoted text, going by what follows it
+fn closes_quote(after: Option<char>) -> bool {
+    after.is_none_or(|c| {
+        c.is_whitespace() || matches!(c, '.' | ',' | ';' | ':' | ')' | ']' | '}' | '!' | '?')
+    })
+}
+
+/// Replace each quoted run of text with its length in characters
+fn redact_quoted(message: &str) -> String {
+    let chars: Vec<char> = message.chars().collect();
+    let mut out = String::with_capacity(message.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let before = i.checked_sub(1).map(|j| chars[j]);
+        if matches!(c, '\'' | '"') && opens_quote(before) {
+            let close = (i + 1..chars.len())
+                .find(|&j| chars[j] == c && closes_quote(chars.get(j + 1).copied()));
+            if let Some(close) = close {
+                let _ = write!(out, "{c}[{} chars]{c}", close - i - 1);
+                i = close + 1;
+                continue;
+            }
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::sync::Mutex;
+
+    /// Keeps what it is asked to log
+    #[derive(Default)]
+    struct Capture(Mutex<Vec<String>>);
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn log_at(logger: &impl Log, level: Level, message: &str) {
+        logger.log(
+            &Record::builder()
+                .args(format_args!("{message}"))
+                .level(level)
+                .target("citadel")
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_urls_lose_queries_and_strict_mode_hashes_them() {
+        let line = "Loading page: https://user:pw@mail.example:8443/inbox?token=s3cret#msg-42.";
+        assert_eq!(
+            redact(line, RedactionMode::Standard),
+            "Loading page: https://mail.example:8443/inbox."
+        );
+        let strict = redact(line, RedactionMode::Strict);
+        assert!(strict.starts_with("Loading page: https://mail.example:8443/["));
+        assert!(!strict.contains("inbox"));
+        // The same URL hashes the same within a run
+        assert_eq!(strict, redact(line, RedactionMode::Strict));
+
+        // Things that only look like URLs are left alone
+        assert_eq!(
+            redact("separator :// alone, and no url", RedactionMode::Standard),
+            "separator :// alone, and no url"
+        );
+        assert_eq!(
+            redact("Can't load 'Hello world' (\"x\")", RedactionMode::Strict),
+            "Can't load '[11 chars]' (\"[1 chars]\")"
+        );
+    }
+
+    #[test]
+    fn test_no_sensitive_fields_at_info_level() {
+        let logger = RedactingLogger::new(Capture::default(), RedactionMode::Strict);
+        log_at(
+            &logger,
+            Level::Info,
+            "🧭 Navigating to: https://search.example/?q=my+medical+question",
+        );
+        log_at(
+            &logger,
+            Level::Info,
+            "📄 Extracted title: 'Your bank balance'",
+        );
+        log_at(
+            &logger,
+            Level::Error,
+            "Failed to fetch https://cdn.example/a.js?session=abc123: timed out",
+        );
+        log_at(&logger, Level::Debug, "not captured at all");
+
+        let logged = logger.inner.0.lock().unwrap();
+        assert_eq!(logged.len(), 3);
+        for line in logged.iter() {
+            for secret in ["medical", "bank balance", "session", "abc123"] {
+                assert!(!line.contains(secret), "{secret} leaked into {line}");
+            }
+        }
+        assert!(logged[0].contains("search.example"));
+        assert!(logged[2].ends_with(": timed out"));
+    }
+}
@@ -1,18 +1,13 @@
-use iced::{Application, Settings};
+use iced::multi_window::Application;
+use iced::Settings;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 
-mod app;
-mod engine;
-mod renderer;
-mod resource_loader;
-mod ui;
-
-use app::CitadelBrowser;
+use citadel_browser::{log_redaction, CitadelBrowser};
 
 fn main() -> iced::Result {
-    // Initialize logging
-    env_logger::init();
+    // Initialize logging, with URLs and page text redacted
+    log_redaction::init();
 
     log::info!(
         "Starting Citadel Browser v{} - Privacy-First Web Browser",
@@ -26,8 +21,12 @@ fn main() -> iced::Result {
     // Create the Tokio runtime for async operations
     let rt = Arc::new(Runtime::new().expect("Failed to create Tokio runtime"));
 
-    // Configure the application window
-    let settings = Settings::with_flags(rt);
+    // Configure the application windows. Close requests are handled by the
+    // app so closing the last window can run the session save flow first.
+    let settings = Settings {
+        window: CitadelBrowser::window_settings(),
+        ..Settings::with_flags(rt)
+    };
 
     CitadelBrowser::run(settings)
 }
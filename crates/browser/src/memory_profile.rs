@@ -0,0 +1,242 @@
+//! Low-memory mode for constrained devices
+//!
+//! The browser runs under one of two [`MemoryProfile`]s. The low-memory
+//! profile trades speed for footprint: the renderer keeps a small widget
+//! cache and no frame batching buffers, the engine keeps few parsed
+//! stylesheets, idle ephemeral tabs hibernate after minutes rather than
+//! half an hour, and decoded images are downscaled to a small maximum
+//! dimension. By default the profile is picked at startup from the
+//! system's memory; the settings file can force either one:
+//!
+//! ```json
+//! { "mode": "low" }
+//! ```
+//!
+//! `mode` is `auto`, `standard` or `low`; in `auto` mode the low-memory
+//! profile is used below `low_memory_below_mb` megabytes of system memory.
+//!
+//! `vm_reuse` lets container tabs share VMs to save memory, at a cost in
+//! isolation described in [`citadel_tabs::VmReuse`]: `"strict"`, the
+//! default, gives every tab its own VM, `"site_keyed"` shares one per site
+//! and container, and `{ "pooled": { "tabs_per_vm": 4 } }` fills VMs with
+//! tabs of one container.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use citadel_tabs::{ExpiryPolicy, VmReuse};
+use serde::{Deserialize, Serialize};
+
+use crate::profile;
+use crate::stylesheet_cache;
+
+/// Environment variable overriding where memory settings are read from
+pub const MEMORY_FILE_ENV: &str = "CITADEL_MEMORY_FILE";
+
+/// System memory below which `auto` mode picks the low-memory profile
+pub const LOW_MEMORY_BELOW_MB: u64 = 4 * 1024;
+
+/// How the memory profile is chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryMode {
+    /// From the system's memory at startup
+    #[default]
+    Auto,
+    /// Always the standard profile
+    Standard,
+    /// Always the low-memory profile
+    Low,
+}
+
+/// The user's memory settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemorySettings {
+    pub mode: MemoryMode,
+    /// System memory, in megabytes, below which `auto` picks low memory
+    pub low_memory_below_mb: u64,
+    /// Whether container tabs share VMs
+    pub vm_reuse: VmReuse,
+}
+
+impl Default for MemorySettings {
+    fn default() -> Self {
+        Self {
+            mode: MemoryMode::Auto,
+            low_memory_below_mb: LOW_MEMORY_BELOW_MB,
+            vm_reuse: VmReuse::Strict,
+        }
+    }
+}
+
+impl MemorySettings {
+    /// The profile to run under on a system with `system_memory` bytes;
+    /// `auto` keeps the standard profile when the amount is unknown
+    pub fn profile(&self, system_memory: Option<u64>) -> MemoryProfile {
+        match self.mode {
+            MemoryMode::Standard => MemoryProfile::Standard,
+            MemoryMode::Low => MemoryProfile::Low,
+            MemoryMode::Auto => match system_memory {
+                Some(bytes) if bytes < self.low_memory_below_mb.saturating_mul(1024 * 1024) => {
+                    MemoryProfile::Low
+                }
+                _ => MemoryProfile::Standard,
+            },
+        }
+    }
+
+    /// Read memory settings; a missing file means the defaults
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// How much memory the browser allows itself
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryProfile {
+    #[default]
+    Standard,
+    /// For constrained devices
+    Low,
+}
+
+impl MemoryProfile {
+    /// Cache sizes and thresholds of the profile
+    pub fn limits(self) -> MemoryLimits {
+        match self {
+            Self::Standard => MemoryLimits {
+                widget_cache_entries: 1000,
+                frame_batching: true,
+                stylesheet_cache_entries: stylesheet_cache::DEFAULT_CAPACITY,
+                tab_idle_timeout: ExpiryPolicy::default().idle_timeout,
+                max_image_dimension: 8192,
+            },
+            Self::Low => MemoryLimits {
+                widget_cache_entries: 100,
+                frame_batching: false,
+                stylesheet_cache_entries: 8,
+                tab_idle_timeout: Duration::from_secs(5 * 60),
+                max_image_dimension: 1024,
+            },
+        }
+    }
+}
+
+/// Cache sizes and thresholds the browser runs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimits {
+    /// Widgets the renderer keeps built between frames
+    pub widget_cache_entries: usize,
+    /// Whether the renderer buffers widget updates into batched frames
+    pub frame_batching: bool,
+    /// Parsed stylesheets the engine keeps across navigations
+    pub stylesheet_cache_entries: usize,
+    /// Idle time after which a background ephemeral tab hibernates
+    pub tab_idle_timeout: Duration,
+    /// Longest side, in pixels, of a decoded image; larger ones are
+    /// downscaled on decode
+    pub max_image_dimension: u32,
+}
+
+impl Default for MemoryLimits {
+    fn default() -> Self {
+        MemoryProfile::default().limits()
+    }
+}
+
+impl MemoryLimits {
+    /// Tab hibernation at the profile's idle timeout
+    pub fn expiry_policy(&self) -> ExpiryPolicy {
+        ExpiryPolicy::new(self.tab_idle_timeout)
+    }
+
+    /// Size to decode a `width`×`height` image at: the same aspect ratio,
+    /// with neither side over the maximum dimension
+    pub fn decoded_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let longest = width.max(height);
+        if longest <= self.max_image_dimension {
+            return (width, height);
+        }
+        let scale = |side: u32| {
+            (u64::from(side) * u64::from(self.max_image_dimension) / u64::from(longest)).max(1)
+                as u32
+        };
+        (scale(width), scale(height))
+    }
+}
+
+/// Total system memory in bytes, where the platform reports it
+pub fn system_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo_total(&meminfo)
+}
+
+/// `MemTotal` of `/proc/meminfo`, which is given in kilobytes
+fn meminfo_total(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("MemTotal:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    kilobytes.checked_mul(1024)
+}
+
+/// Where memory settings live: `$CITADEL_MEMORY_FILE`, otherwise
+/// `citadel/memory.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(MEMORY_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("memory.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_follows_system_memory_unless_forced() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let auto = MemorySettings::default();
+        assert_eq!(auto.profile(Some(2 * GIB)), MemoryProfile::Low);
+        assert_eq!(auto.profile(Some(16 * GIB)), MemoryProfile::Standard);
+        assert_eq!(auto.profile(None), MemoryProfile::Standard);
+
+        let forced: MemorySettings = serde_json::from_str(r#"{ "mode": "low" }"#).unwrap();
+        assert_eq!(forced.low_memory_below_mb, LOW_MEMORY_BELOW_MB);
+        assert_eq!(forced.vm_reuse, VmReuse::Strict);
+        assert_eq!(forced.profile(Some(16 * GIB)), MemoryProfile::Low);
+
+        assert_eq!(
+            meminfo_total("MemTotal:        2048000 kB\nMemFree: 1 kB\n"),
+            Some(2048000 * 1024)
+        );
+        assert_eq!(meminfo_total("MemFree: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn test_low_memory_limits_downscale_images() {
+        let low = MemoryProfile::Low.limits();
+        let standard = MemoryProfile::Standard.limits();
+        assert!(low.widget_cache_entries < standard.widget_cache_entries);
+        assert!(low.tab_idle_timeout < standard.tab_idle_timeout);
+        assert!(!low.frame_batching);
+
+        assert_eq!(low.decoded_size(4096, 2048), (1024, 512));
+        assert_eq!(low.decoded_size(800, 600), (800, 600));
+        assert_eq!(low.decoded_size(100_000, 1), (1024, 1));
+        assert_eq!(standard.decoded_size(4096, 2048), (4096, 2048));
+    }
+}
@@ -0,0 +1,127 @@
+//! `citadel://net-internals`
+//!
+//! Lists the DNS cache with remaining TTLs. Actions are plain links so the
+//! page works in the sandboxed renderer without scripts:
+//!
+//! - `citadel://net-internals/flush` empties the cache
+//! - `citadel://net-internals/flush?host=example.com` drops one host
+//! - `citadel://net-internals/pin?host=example.com&ip=192.0.2.1` pins a host
+//!   (for testing)
+
+use std::net::IpAddr;
+
+use citadel_networking::CitadelDnsResolver;
+use url::Url;
+
+use crate::html::escape;
+
+/// Address of the page
+pub const NET_INTERNALS_URL: &str = "citadel://net-internals";
+
+/// Apply the action in `url`, if any, and render the page
+pub fn handle(resolver: &CitadelDnsResolver, url: &Url) -> String {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let notice = match url.path() {
+        "/flush" => match param("host") {
+            Some(host) if resolver.flush_host(&host) => format!("Flushed {}.", host),
+            Some(host) => format!("{} was not cached.", host),
+            None => {
+                resolver.clear_cache();
+                "Flushed the DNS cache.".to_string()
+            }
+        },
+        "/pin" => {
+            let host = param("host").unwrap_or_default();
+            let addresses: Result<Vec<IpAddr>, _> = url
+                .query_pairs()
+                .filter(|(key, _)| key == "ip")
+                .map(|(_, value)| value.parse())
+                .collect();
+            match addresses.map_err(|e| e.to_string()).and_then(|addresses| {
+                resolver
+                    .pin_host(&host, addresses)
+                    .map_err(|e| e.to_string())
+            }) {
+                Ok(()) => format!("Pinned {}.", host),
+                Err(e) => format!("Could not pin {}: {}", host, e),
+            }
+        }
+        _ => String::new(),
+    };
+
+    render(resolver, &notice)
+}
+
+fn render(resolver: &CitadelDnsResolver, notice: &str) -> String {
+    let stats = resolver.get_stats();
+    let entries = resolver.cached_entries();
+
+    let mut html = String::from(
+        "<!doctype html><html><head><title>Net internals</title></head><body>\n\
+         <h1>Net internals</h1>\n",
+    );
+    if !notice.is_empty() {
+        html.push_str(&format!("<p><b>{}</b></p>\n", escape(notice)));
+    }
+    html.push_str(&format!(
+        "<h2>DNS cache</h2>\n<p>Mode: {:?}. {} entries, {} cache hits, {} queries blocked.</p>\n",
+        stats.current_mode,
+        entries.len(),
+        stats.cache_hits,
+        stats.queries_blocked
+    ));
+    html.push_str(&format!(
+        "<p><a href=\"{}/flush\">Flush all</a></p>\n",
+        NET_INTERNALS_URL
+    ));
+
+    if entries.is_empty() {
+        html.push_str("<p>The cache is empty.</p>\n");
+    }
+    for entry in entries {
+        let addresses: Vec<String> = entry.addresses.iter().map(IpAddr::to_string).collect();
+        let ttl = match entry.ttl_remaining {
+            Some(ttl) => format!("{}s left", ttl.as_secs()),
+            None => "pinned".to_string(),
+        };
+        let host = escape(&entry.hostname);
+        html.push_str(&format!(
+            "<p>{} → {} ({}) <a href=\"{}/flush?host={}\">flush</a></p>\n",
+            host,
+            addresses.join(", "),
+            ttl,
+            NET_INTERNALS_URL,
+            urlencoding::encode(&entry.hostname)
+        ));
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_page_pins_lists_and_flushes() {
+        let resolver = CitadelDnsResolver::new().await.unwrap();
+        let page = |path: &str| handle(&resolver, &Url::parse(path).unwrap());
+
+        let pinned = page("citadel://net-internals/pin?host=pinned.test&ip=192.0.2.1");
+        assert!(pinned.contains("Pinned pinned.test."));
+        assert!(pinned.contains("pinned.test → 192.0.2.1 (pinned)"));
+
+        let bad = page("citadel://net-internals/pin?host=x.test&ip=not-an-ip");
+        assert!(bad.contains("Could not pin x.test"));
+
+        let flushed = page("citadel://net-internals/flush?host=pinned.test");
+        assert!(flushed.contains("Flushed pinned.test."));
+        assert!(flushed.contains("The cache is empty."));
+    }
+}
@@ -0,0 +1,133 @@
+//! Per-site overlay cleanup
+//!
+//! On sites the user opts in, full-screen overlays found by
+//! [`citadel_parser::detect_overlays`] (paywall gates, newsletter nags,
+//! consent walls) are hidden with the same `display: none` user CSS as
+//! cosmetic filter rules, and a scroll-locked body is unlocked again. The
+//! heuristic can hit legitimate dialogs, so it is off unless the user turns
+//! it on for a site; the choice is remembered in the settings file.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use citadel_networking::cosmetic::{hiding_stylesheet, SCROLL_RESTORE_CSS};
+use citadel_parser::{CitadelStylesheet, Dom};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::profile;
+
+/// Environment variable overriding where overlay cleanup choices are saved
+pub const OVERLAY_CLEANUP_FILE_ENV: &str = "CITADEL_OVERLAY_CLEANUP_FILE";
+
+/// Sites with overlay cleanup turned on
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OverlayCleanup {
+    #[serde(default)]
+    sites: BTreeSet<String>,
+}
+
+impl OverlayCleanup {
+    /// Whether cleanup is on for the page's host
+    pub fn is_enabled(&self, url: &Url) -> bool {
+        url.host_str()
+            .is_some_and(|host| self.sites.contains(&host.to_ascii_lowercase()))
+    }
+
+    /// Turn cleanup on or off for the page's host. Returns whether it is now
+    /// on; pages without a host cannot be toggled.
+    pub fn toggle(&mut self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        if self.sites.remove(&host) {
+            false
+        } else {
+            self.sites.insert(host)
+        }
+    }
+
+    /// User CSS hiding the page's overlays and restoring scrolling; empty
+    /// when cleanup is off for the site or nothing was found
+    pub fn css_for(&self, url: &Url, dom: &Dom, stylesheet: &CitadelStylesheet) -> String {
+        if !self.is_enabled(url) {
+            return String::new();
+        }
+        let report = citadel_parser::detect_overlays(dom, stylesheet);
+        let mut css = hiding_stylesheet(report.selectors.iter().map(String::as_str));
+        if report.scroll_locked || !report.selectors.is_empty() {
+            css.push_str(SCROLL_RESTORE_CSS);
+        }
+        css
+    }
+
+    /// Read saved choices; a missing file means none
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        profile::write(path, json).await
+    }
+}
+
+/// Where overlay cleanup choices live: `$CITADEL_OVERLAY_CLEANUP_FILE`,
+/// otherwise `citadel/overlay-cleanup.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(OVERLAY_CLEANUP_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("overlay-cleanup.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::security::SecurityContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cleanup_is_per_site_and_off_by_default() {
+        let context = Arc::new(SecurityContext::new(10));
+        let dom = citadel_parser::parse_html(
+            "<html><body><p>Story</p><div id=\"paywall\">Subscribe</div></body></html>",
+            context.clone(),
+        )
+        .unwrap();
+        let stylesheet = citadel_parser::parse_css(
+            "body { overflow: hidden; }\
+             #paywall { position: fixed; top: 0; bottom: 0; left: 0; right: 0; z-index: 1000; }",
+            context,
+        )
+        .unwrap();
+        let news = Url::parse("https://news.example.com/story").unwrap();
+        let other = Url::parse("https://example.com/").unwrap();
+
+        let mut cleanup = OverlayCleanup::default();
+        assert!(cleanup.css_for(&news, &dom, &stylesheet).is_empty());
+
+        assert!(cleanup.toggle(&news));
+        assert!(cleanup.is_enabled(&news));
+        assert!(!cleanup.is_enabled(&other));
+        let css = cleanup.css_for(&news, &dom, &stylesheet);
+        assert!(css.contains("#paywall { display: none !important; }"));
+        assert!(css.contains(SCROLL_RESTORE_CSS));
+
+        assert!(!cleanup.toggle(&news));
+        assert!(!cleanup.is_enabled(&news));
+        assert!(!cleanup.toggle(&Url::parse("about:blank").unwrap()));
+    }
+}
@@ -0,0 +1,205 @@
+//! Escalation on pages that keep violating policy
+//!
+//! Every load of a page counts its sanitizer hits, and every render counts
+//! the scripts the cage refused, against the page's site in its tab. When a
+//! site runs up too many violations within the window (see
+//! [`EscalationThresholds`]) the tab first shows a warning bar, then runs
+//! the site with scripts paused, then shows it as [text only]. The levels
+//! relax once the site has been quiet for the cooldown. The defaults sit
+//! well above what ordinary pages, with their stripped scripts and
+//! trackers, run up on a few loads.
+//!
+//! [text only]: crate::text_only
+//!
+//! Thresholds can be tuned in a JSON file; fields left out keep their
+//! defaults:
+//!
+//! ```json
+//! { "window_secs": 60, "warn_after": 50, "pause_scripts_after": 150,
+//!   "text_only_after": 400, "cooldown_secs": 120 }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use citadel_security::{Escalation, EscalationThresholds, ViolationEscalator, ViolationSource};
+use url::Url;
+use uuid::Uuid;
+
+use crate::profile;
+
+/// Environment variable overriding where escalation thresholds are read from
+pub const ESCALATION_FILE_ENV: &str = "CITADEL_ESCALATION_FILE";
+
+/// Shown above a page the browser has escalated against
+pub const ESCALATION_BANNER: &str = "This page keeps triggering security protections";
+
+/// Where a tab's page stands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageEscalation {
+    /// How hard the browser clamps down on the page
+    pub level: Escalation,
+    /// Violations within the window
+    pub recent: u32,
+}
+
+impl PageEscalation {
+    /// Warning bar text, `None` below [`Escalation::Warn`]
+    pub fn banner(&self) -> Option<String> {
+        let action = match self.level {
+            Escalation::None => return None,
+            Escalation::Warn => "watching it closely",
+            Escalation::ScriptsPaused => "scripts are paused",
+            Escalation::TextOnly => "showing it as plain text",
+        };
+        Some(format!(
+            "{} ({} recent violations): {}",
+            ESCALATION_BANNER, self.recent, action
+        ))
+    }
+}
+
+/// Violations of each tab's sites, shared by the engine's clones
+#[derive(Debug, Clone, Default)]
+pub struct PageEscalations {
+    escalator: Arc<Mutex<ViolationEscalator<(Uuid, String)>>>,
+}
+
+impl PageEscalations {
+    /// Escalate at `thresholds`
+    pub fn new(thresholds: EscalationThresholds) -> Self {
+        Self {
+            escalator: Arc::new(Mutex::new(ViolationEscalator::new(thresholds))),
+        }
+    }
+
+    /// Count `count` violations from `source` against the site of `url` in
+    /// a tab; returns where the page stands afterwards
+    pub fn record(
+        &self,
+        tab_id: Uuid,
+        url: &Url,
+        source: ViolationSource,
+        count: usize,
+    ) -> PageEscalation {
+        let Ok(mut escalator) = self.escalator.lock() else {
+            return PageEscalation::default();
+        };
+        let key = (tab_id, site(url));
+        let now = Instant::now();
+        let level = escalator.record(
+            key.clone(),
+            source,
+            u32::try_from(count).unwrap_or(u32::MAX),
+            now,
+        );
+        PageEscalation {
+            level,
+            recent: escalator.recent(&key, now),
+        }
+    }
+
+    /// Where the site of `url` stands in a tab
+    pub fn status(&self, tab_id: Uuid, url: &Url) -> PageEscalation {
+        let Ok(mut escalator) = self.escalator.lock() else {
+            return PageEscalation::default();
+        };
+        let key = (tab_id, site(url));
+        let now = Instant::now();
+        PageEscalation {
+            level: escalator.level(&key, now),
+            recent: escalator.recent(&key, now),
+        }
+    }
+
+    /// Forget the violations of the site of `url` in a tab, when the user
+    /// chooses to trust it
+    pub fn trust(&self, tab_id: Uuid, url: &Url) {
+        if let Ok(mut escalator) = self.escalator.lock() {
+            escalator.remove(&(tab_id, site(url)));
+        }
+    }
+
+    /// Forget a closed tab
+    pub fn remove_tab(&self, tab_id: Uuid) {
+        if let Ok(mut escalator) = self.escalator.lock() {
+            escalator.retain(|(tab, _)| *tab != tab_id);
+        }
+    }
+
+    /// Forget every tab
+    pub fn clear(&self) {
+        if let Ok(mut escalator) = self.escalator.lock() {
+            escalator.clear();
+        }
+    }
+}
+
+/// Read the thresholds; a missing file means the defaults
+pub fn load(path: &Path) -> std::io::Result<EscalationThresholds> {
+    match profile::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(EscalationThresholds::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Where the thresholds live: `$CITADEL_ESCALATION_FILE`, otherwise
+/// `citadel/escalation.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(ESCALATION_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("escalation.json"))
+}
+
+/// Violations are counted per site, so navigating a tab away from a
+/// misbehaving site starts afresh
+fn site(url: &Url) -> String {
+    url.host_str()
+        .map_or_else(|| url.as_str().to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalation_is_per_tab_and_site() {
+        let escalations = PageEscalations::new(EscalationThresholds {
+            warn_after: 2,
+            pause_scripts_after: 4,
+            text_only_after: 6,
+            ..Default::default()
+        });
+        let tab = Uuid::new_v4();
+        let page = Url::parse("https://bad.example/a").unwrap();
+
+        let status = escalations.record(tab, &page, ViolationSource::Sanitizer, 3);
+        assert_eq!(status.level, Escalation::Warn);
+        assert_eq!(status.recent, 3);
+        assert!(status.banner().unwrap().contains("3 recent violations"));
+        let status = escalations.record(tab, &page, ViolationSource::ScriptPolicy, 3);
+        assert!(status.level.text_only());
+
+        let other_page = Url::parse("https://bad.example/b").unwrap();
+        assert!(escalations.status(tab, &other_page).level.text_only());
+        let other_site = Url::parse("https://good.example/").unwrap();
+        assert_eq!(
+            escalations.status(tab, &other_site),
+            PageEscalation::default()
+        );
+        assert_eq!(
+            escalations.status(Uuid::new_v4(), &page).level,
+            Escalation::None
+        );
+
+        escalations.remove_tab(tab);
+        assert!(escalations.status(tab, &page).banner().is_none());
+    }
+}
@@ -0,0 +1,353 @@
+//! Page context menu
+//!
+//! Right-clicking rendered content opens a menu of what can be done with
+//! what was under the pointer. The renderer hit tests as it paints: link
+//! runs, images and text blocks each sit in a mouse area that reports a
+//! [`PageHit`] naming the link, image or text it covers, and the menu
+//! offers only what applies to that hit. Links and images are resolved
+//! against the page and have to be web addresses; images are saved through
+//! the download manager like any other download of the tab.
+//!
+//! "View selection source" shows the markup of the smallest element of the
+//! tab's sanitized DOM holding the text that was hit, so what is shown is
+//! what the renderer got, not what the server sent.
+
+use std::ops::Range;
+
+use citadel_parser::dom::{Node, NodeData};
+use citadel_parser::Dom;
+use url::Url;
+
+use crate::html::escape;
+use crate::tab_menu;
+
+/// Longest source shown, in bytes
+pub const MAX_SOURCE_BYTES: usize = 64 * 1024;
+
+/// Elements with no content or end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// What a right-click on the page landed on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageHit {
+    /// Link target, as the page wrote it
+    pub link: Option<String>,
+    /// Image source, as the page wrote it
+    pub image: Option<String>,
+    /// Text of the block that was hit
+    pub text: Option<String>,
+}
+
+impl PageHit {
+    pub fn link(href: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            link: Some(href.into()),
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn image(src: impl Into<String>) -> Self {
+        Self {
+            image: Some(src.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Something done from the page's context menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageAction {
+    /// Open the link in a new tab of the same type, in the same container
+    OpenInNewTab(Url),
+    /// Open the link in a new container of its own
+    OpenInContainer(Url),
+    /// Copy the link without tracking parameters
+    CopyCleanLink(Url),
+    /// Download the image
+    SaveImage(Url),
+    /// Show the source of the element holding this text
+    ViewSelectionSource(String),
+}
+
+/// One entry of the menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageMenuEntry {
+    pub label: String,
+    pub action: PageAction,
+}
+
+impl PageMenuEntry {
+    fn new(label: impl Into<String>, action: PageAction) -> Self {
+        Self {
+            label: label.into(),
+            action,
+        }
+    }
+}
+
+/// The entries for `hit` on the page at `page_url`
+pub fn entries(hit: &PageHit, page_url: Option<&Url>) -> Vec<PageMenuEntry> {
+    let mut entries = Vec::new();
+    if let Some(link) = hit.link.as_deref().and_then(|link| resolve(link, page_url)) {
+        entries.push(PageMenuEntry::new(
+            "Open link in new tab",
+            PageAction::OpenInNewTab(link.clone()),
+        ));
+        entries.push(PageMenuEntry::new(
+            "Open link in new container",
+            PageAction::OpenInContainer(link.clone()),
+        ));
+        entries.push(PageMenuEntry::new(
+            "Copy clean link",
+            PageAction::CopyCleanLink(link),
+        ));
+    }
+    if let Some(image) = hit.image.as_deref().and_then(|src| resolve(src, page_url)) {
+        entries.push(PageMenuEntry::new(
+            "Save image",
+            PageAction::SaveImage(image),
+        ));
+    }
+    if let Some(text) = hit.text.as_deref().filter(|text| !text.trim().is_empty()) {
+        entries.push(PageMenuEntry::new(
+            "View selection source",
+            PageAction::ViewSelectionSource(text.to_string()),
+        ));
+    }
+    entries
+}
+
+/// `link` without tracking parameters
+pub fn clean_link(link: &Url) -> String {
+    tab_menu::clean_url(link.as_str()).unwrap_or_else(|| link.to_string())
+}
+
+fn resolve(reference: &str, page_url: Option<&Url>) -> Option<Url> {
+    let reference = reference.trim();
+    page_url
+        .map_or_else(|| Url::parse(reference), |base| base.join(reference))
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Markup of the smallest element of `dom` whose text holds `selection`,
+/// cut at [`MAX_SOURCE_BYTES`]
+pub fn selection_source(dom: &Dom, selection: &str) -> Option<String> {
+    let selection = collapse_whitespace(selection);
+    if selection.is_empty() {
+        return None;
+    }
+    let root = dom.root();
+    let root = root.read().ok()?;
+    let mut source = String::new();
+    innermost_holding(&root, &selection, &mut String::new(), &mut |node| {
+        write_source(node, &mut source)
+    })?;
+    if source.len() > MAX_SOURCE_BYTES {
+        let mut end = MAX_SOURCE_BYTES;
+        while !source.is_char_boundary(end) {
+            end -= 1;
+        }
+        source.truncate(end);
+        source.push('…');
+    }
+    Some(source)
+}
+
+/// Call `found` with the deepest element under `node` whose text holds
+/// `selection`. The walk is bottom-up: each node's text is appended to
+/// `text`, whitespace collapsed, once, and an element only searches the
+/// stretches its child elements have not already searched.
+fn innermost_holding(
+    node: &Node,
+    selection: &str,
+    text: &mut String,
+    found: &mut dyn FnMut(&Node),
+) -> Option<()> {
+    match &node.data {
+        NodeData::Text(content) => {
+            push_collapsed(text, content);
+            return None;
+        }
+        NodeData::Document | NodeData::Element(_) => {}
+        _ => return None,
+    }
+    let start = text.len();
+    let mut searched = Vec::new();
+    for child in node.children() {
+        let Ok(child) = child.read() else {
+            continue;
+        };
+        let child_start = text.len();
+        if innermost_holding(&child, selection, text, found).is_some() {
+            return Some(());
+        }
+        if child.is_element() {
+            searched.push(child_start..text.len());
+        }
+    }
+    if !node.is_element() || !holds_outside(text, start, &searched, selection) {
+        return None;
+    }
+    found(node);
+    Some(())
+}
+
+/// Whether `text[start..]` holds `selection` other than wholly inside one of
+/// the `searched` spans, which are in order and known not to hold it. Such a
+/// match touches a gap between spans, so only the gaps and `selection`'s
+/// length either side of them are searched.
+fn holds_outside(text: &str, start: usize, searched: &[Range<usize>], selection: &str) -> bool {
+    let reach = selection.len().saturating_sub(1);
+    let mut gap_start = start;
+    let gaps = searched
+        .iter()
+        .map(|span| (span.start, span.end))
+        .chain([(text.len(), text.len())]);
+    for (gap_end, next_start) in gaps {
+        let mut from = gap_start.saturating_sub(reach).max(start);
+        while !text.is_char_boundary(from) {
+            from -= 1;
+        }
+        let mut to = (gap_end + reach).min(text.len());
+        while !text.is_char_boundary(to) {
+            to += 1;
+        }
+        if text[from..to].contains(selection) {
+            return true;
+        }
+        gap_start = next_start;
+    }
+    false
+}
+
+/// Append `content` to `text` with runs of whitespace collapsed to one space
+fn push_collapsed(text: &mut String, content: &str) {
+    for c in content.chars() {
+        if !c.is_whitespace() {
+            text.push(c);
+        } else if !text.is_empty() && !text.ends_with(' ') {
+            text.push(' ');
+        }
+    }
+}
+
+fn write_source(node: &Node, out: &mut String) {
+    if out.len() > MAX_SOURCE_BYTES {
+        return;
+    }
+    match &node.data {
+        NodeData::Element(element) => {
+            let name = element.local_name();
+            out.push('<');
+            out.push_str(name);
+            for attribute in &element.attributes {
+                out.push(' ');
+                out.push_str(&attribute.name.local);
+                out.push_str("=\"");
+                out.push_str(&escape(&attribute.value));
+                out.push('"');
+            }
+            out.push('>');
+            if VOID_ELEMENTS.contains(&name) {
+                return;
+            }
+            for child in node.children() {
+                if let Ok(child) = child.read() {
+                    write_source(&child, out);
+                }
+            }
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+        NodeData::Text(text) => out.push_str(&escape(text)),
+        NodeData::Comment(comment) => {
+            out.push_str("<!--");
+            out.push_str(comment);
+            out.push_str("-->");
+        }
+        _ => {}
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::security::SecurityContext;
+    use std::sync::Arc;
+
+    fn actions(entries: &[PageMenuEntry]) -> Vec<&PageAction> {
+        entries.iter().map(|entry| &entry.action).collect()
+    }
+
+    #[test]
+    fn test_entries_fit_the_hit() {
+        let page = Url::parse("https://news.example/story/").unwrap();
+        let link = Url::parse("https://news.example/next?id=2").unwrap();
+
+        let found = entries(&PageHit::link("/next?id=2", "Next story"), Some(&page));
+        let found = actions(&found);
+        assert!(found.contains(&&PageAction::OpenInNewTab(link.clone())));
+        assert!(found.contains(&&PageAction::OpenInContainer(link.clone())));
+        assert!(found.contains(&&PageAction::CopyCleanLink(link)));
+        assert!(!found
+            .iter()
+            .any(|action| matches!(action, PageAction::SaveImage(_))));
+
+        let found = entries(&PageHit::image("photo.jpg"), Some(&page));
+        assert_eq!(
+            actions(&found),
+            [&PageAction::SaveImage(
+                Url::parse("https://news.example/story/photo.jpg").unwrap()
+            )]
+        );
+
+        // Links that are not web addresses, and blank text, offer nothing
+        assert!(entries(&PageHit::link("javascript:void(0)", " "), Some(&page)).is_empty());
+        assert!(entries(&PageHit::image("data:image/png;base64,AA=="), Some(&page)).is_empty());
+
+        let clean = clean_link(&Url::parse("https://shop.example/?utm_source=x&item=4").unwrap());
+        assert_eq!(clean, "https://shop.example/?item=4");
+    }
+
+    #[test]
+    fn test_selection_source_is_the_innermost_element() {
+        let dom = citadel_parser::parse_html(
+            "<html><body><div class=\"story\"><p id=\"lead\">The  quick\n fox &amp; <b>hound</b></p>\
+             <p>Other</p></div></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+
+        assert_eq!(
+            selection_source(&dom, "quick fox & hound").as_deref(),
+            Some("<p id=\"lead\">The  quick\n fox &amp; <b>hound</b></p>")
+        );
+        assert_eq!(
+            selection_source(&dom, "hound").as_deref(),
+            Some("<b>hound</b>")
+        );
+        // Text running across sibling elements belongs to their parent
+        assert!(selection_source(&dom, "houndOther")
+            .unwrap()
+            .starts_with("<div class=\"story\">"));
+        assert!(selection_source(&dom, "not on the page").is_none());
+        assert!(selection_source(&dom, "  ").is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Panic button: wipe all session state at once
+//!
+//! Ctrl+Shift+Delete tears the session down in one step, in this order:
+//!
+//! 1. The app forgets its per-tab state (history, renders, scroll and zoom
+//!    positions, form input) and clears the browsing history, on disk too;
+//!    the engine drops TLS session tickets, cached DNS answers and request
+//!    budgets.
+//! 2. The tab manager terminates every tab's VM, which zeroizes its memory,
+//!    without persisting container tabs.
+//! 3. Containers listed in the panic settings file are removed from the
+//!    saved session, so they are not restored.
+//! 4. Every window closes, without the session save that a normal exit runs.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{profile, session};
+
+/// Environment variable overriding where panic settings are read from
+pub const PANIC_FILE_ENV: &str = "CITADEL_PANIC_FILE";
+
+/// What the panic button wipes beyond the current session
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PanicOptions {
+    /// Containers whose saved tabs are wiped too
+    #[serde(default)]
+    pub wipe_containers: BTreeSet<Uuid>,
+}
+
+impl PanicOptions {
+    /// Read panic settings; a missing file means the defaults
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Where panic settings live: `$CITADEL_PANIC_FILE`, otherwise
+/// `citadel/panic.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(PANIC_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("panic.json"))
+}
+
+/// Remove the given containers' tabs from the session saved at `path`.
+/// Returns the number of tabs removed.
+pub async fn wipe_containers(path: &Path, containers: &BTreeSet<Uuid>) -> std::io::Result<usize> {
+    if containers.is_empty() {
+        return Ok(0);
+    }
+    let Some(mut snapshot) = session::load(path).await? else {
+        return Ok(0);
+    };
+    let removed = snapshot.remove_containers(containers);
+    if removed > 0 {
+        session::save(&snapshot, path).await?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{SavedTab, SessionSnapshot, WindowSnapshot};
+
+    fn tab(container_id: Uuid, url: &str) -> SavedTab {
+        SavedTab {
+            container_id,
+            url: url.to_string(),
+            title: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_selected_containers_do_not_survive() {
+        let (work, bank) = (Uuid::new_v4(), Uuid::new_v4());
+        let snapshot = SessionSnapshot {
+            saved_at: chrono::Utc::now(),
+            windows: vec![
+                WindowSnapshot {
+                    tabs: vec![
+                        tab(work, "https://work.test/"),
+                        tab(bank, "https://bank.test/"),
+                    ],
+                },
+                WindowSnapshot {
+                    tabs: vec![tab(bank, "https://bank.test/statements")],
+                },
+            ],
+        };
+        let path = std::env::temp_dir()
+            .join(format!("citadel-panic-{}", Uuid::new_v4()))
+            .join("session.json");
+        session::save(&snapshot, &path).await.unwrap();
+
+        let options: PanicOptions =
+            serde_json::from_str(&format!(r#"{{"wipe_containers": ["{}"]}}"#, bank)).unwrap();
+        assert_eq!(
+            wipe_containers(&path, &options.wipe_containers)
+                .await
+                .unwrap(),
+            2
+        );
+        let written = tokio::fs::read_to_string(&path).await.unwrap();
+        assert!(written.contains("work.test"));
+        assert!(!written.contains("bank.test"));
+        assert!(!written.contains(&bank.to_string()));
+
+        // Wiping the last container removes the session file
+        let work_only = BTreeSet::from([work]);
+        assert_eq!(wipe_containers(&path, &work_only).await.unwrap(), 1);
+        assert!(!path.exists());
+        assert_eq!(wipe_containers(&path, &work_only).await.unwrap(), 0);
+        let _ = std::fs::remove_dir(path.parent().unwrap());
+    }
+}
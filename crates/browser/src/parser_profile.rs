@@ -0,0 +1,152 @@
+//! Parser limits for the Custom privacy level
+//!
+//! Each privacy level parses pages with the limits of its parser profile
+//! (`ParserConfig::for_level`). The Custom level starts from the balanced
+//! profile and applies the overrides in a JSON file; limits left out keep
+//! the balanced values:
+//!
+//! ```json
+//! { "max_depth": 200, "max_attr_length": 4096, "allow_comments": false }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use citadel_networking::PrivacyLevel;
+use citadel_parser::{ParserConfig, SecurityLevel};
+use serde::{Deserialize, Serialize};
+
+use crate::profile;
+
+/// Environment variable overriding where the custom parser profile is read from
+pub const PARSER_PROFILE_FILE_ENV: &str = "CITADEL_PARSER_PROFILE_FILE";
+
+/// Overrides of the balanced parser limits
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomParserProfile {
+    pub max_depth: Option<usize>,
+    pub max_attr_length: Option<usize>,
+    pub max_tokens: Option<usize>,
+    pub max_document_bytes: Option<usize>,
+    pub max_elements: Option<usize>,
+    pub allow_comments: Option<bool>,
+    pub max_nesting_depth: Option<usize>,
+    pub max_css_size: Option<usize>,
+}
+
+impl CustomParserProfile {
+    /// Read the profile; a missing file means no overrides
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        profile::write(path, json).await
+    }
+
+    /// The parser configuration of the Custom level
+    pub fn config(&self) -> ParserConfig {
+        let mut config = ParserConfig::for_level(SecurityLevel::Custom);
+        if let Some(max_depth) = self.max_depth {
+            config.max_depth = max_depth;
+        }
+        if let Some(max_attr_length) = self.max_attr_length {
+            config.max_attr_length = max_attr_length;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            config.max_tokens = max_tokens;
+        }
+        if let Some(max_document_bytes) = self.max_document_bytes {
+            config.max_document_bytes = max_document_bytes;
+        }
+        if let Some(max_elements) = self.max_elements {
+            config.max_elements = max_elements;
+        }
+        if let Some(allow_comments) = self.allow_comments {
+            config.allow_comments = allow_comments;
+        }
+        if let Some(max_nesting_depth) = self.max_nesting_depth {
+            config.max_nesting_depth = max_nesting_depth;
+        }
+        if let Some(max_css_size) = self.max_css_size {
+            config.max_css_size = max_css_size;
+        }
+        config
+    }
+}
+
+/// Parser security level a privacy level loads pages with
+pub fn security_level(privacy_level: PrivacyLevel) -> SecurityLevel {
+    match privacy_level {
+        PrivacyLevel::Maximum => SecurityLevel::Maximum,
+        PrivacyLevel::High => SecurityLevel::High,
+        PrivacyLevel::Balanced => SecurityLevel::Balanced,
+        PrivacyLevel::Custom => SecurityLevel::Custom,
+    }
+}
+
+/// Where the custom parser profile lives: `$CITADEL_PARSER_PROFILE_FILE`,
+/// otherwise `citadel/parser-profile.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(PARSER_PROFILE_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("parser-profile.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_custom_profile_overrides_balanced_limits() {
+        let path = std::env::temp_dir().join(format!(
+            "citadel-parser-profile-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let defaults = CustomParserProfile::load(&path).unwrap();
+        assert_eq!(defaults, CustomParserProfile::default());
+        assert_eq!(
+            defaults.config().max_depth,
+            ParserConfig::for_level(SecurityLevel::Balanced).max_depth
+        );
+
+        let profile = CustomParserProfile {
+            max_attr_length: Some(4096),
+            max_tokens: Some(1_000_000),
+            allow_comments: Some(false),
+            ..Default::default()
+        };
+        profile.save(&path).await.unwrap();
+        let config = CustomParserProfile::load(&path).unwrap().config();
+        assert_eq!(config.security_level, SecurityLevel::Custom);
+        assert_eq!(config.max_attr_length, 4096);
+        assert_eq!(config.max_tokens, 1_000_000);
+        assert_eq!(
+            config.max_document_bytes,
+            ParserConfig::for_level(SecurityLevel::Balanced).max_document_bytes
+        );
+        assert!(!config.allow_comments);
+        assert_eq!(config.max_nesting_depth, 32);
+
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert_eq!(
+            CustomParserProfile::load(&path).err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
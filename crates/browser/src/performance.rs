@@ -9,6 +9,8 @@ use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use crate::power_profile::PowerProfile;
+
 /// Memory usage tracking for different browser components
 #[derive(Debug, Clone, Default)]
 pub struct MemoryUsage {
@@ -73,8 +75,12 @@ pub struct PerformanceMetrics {
     pub frame_rates: VecDeque<f64>,
     /// Cache hit ratios
     pub cache_hit_ratios: HashMap<String, f64>,
+    /// Latest first contentful render per tab, in milliseconds
+    pub first_contentful_renders: HashMap<uuid::Uuid, u64>,
     /// Memory pressure events
     pub memory_pressure_events: usize,
+    /// Power profile the browser runs under
+    pub power_profile: PowerProfile,
     /// Last measurement timestamp
     pub last_measurement: Instant,
 }
@@ -89,7 +95,9 @@ impl Default for PerformanceMetrics {
             network_times: VecDeque::new(),
             frame_rates: VecDeque::new(),
             cache_hit_ratios: HashMap::new(),
+            first_contentful_renders: HashMap::new(),
             memory_pressure_events: 0,
+            power_profile: PowerProfile::default(),
             last_measurement: Instant::now(),
         }
     }
@@ -159,6 +167,18 @@ impl PerformanceMetrics {
         self.last_measurement = Instant::now();
     }
 
+    /// Record when a tab's page could first paint, replacing the tab's
+    /// previous page
+    pub fn set_first_contentful_render(&mut self, tab_id: uuid::Uuid, time_ms: u64) {
+        self.first_contentful_renders.insert(tab_id, time_ms);
+        self.last_measurement = Instant::now();
+    }
+
+    /// Forget a closed tab
+    pub fn remove_tab(&mut self, tab_id: &uuid::Uuid) {
+        self.first_contentful_renders.remove(tab_id);
+    }
+
     /// Record memory pressure event
     pub fn record_memory_pressure(&mut self) {
         self.memory_pressure_events += 1;
@@ -214,6 +234,16 @@ impl PerformanceMetrics {
         }
     }
 
+    /// Calculate average first contentful render over open tabs
+    pub fn average_first_contentful_render(&self) -> Option<f64> {
+        if self.first_contentful_renders.is_empty() {
+            None
+        } else {
+            let sum: u64 = self.first_contentful_renders.values().sum();
+            Some(sum as f64 / self.first_contentful_renders.len() as f64)
+        }
+    }
+
     /// Get performance summary
     pub fn get_summary(&self) -> PerformanceSummary {
         PerformanceSummary {
@@ -221,11 +251,16 @@ impl PerformanceMetrics {
             average_layout_ms: self.average_layout_time().unwrap_or(0.0),
             average_render_ms: self.average_render_time().unwrap_or(0.0),
             average_fps: self.average_frame_rate().unwrap_or(0.0),
+            average_first_contentful_render_ms: self
+                .average_first_contentful_render()
+                .unwrap_or(0.0),
+            first_contentful_render_ms: self.first_contentful_renders.clone(),
             total_measurements: self.page_load_times.len()
                 + self.layout_times.len()
                 + self.render_times.len(),
             memory_pressure_events: self.memory_pressure_events,
             cache_hit_ratios: self.cache_hit_ratios.clone(),
+            power_profile: self.power_profile,
         }
     }
 }
@@ -237,9 +272,12 @@ pub struct PerformanceSummary {
     pub average_layout_ms: f64,
     pub average_render_ms: f64,
     pub average_fps: f64,
+    pub average_first_contentful_render_ms: f64,
+    pub first_contentful_render_ms: HashMap<uuid::Uuid, u64>,
     pub total_measurements: usize,
     pub memory_pressure_events: usize,
     pub cache_hit_ratios: HashMap<String, f64>,
+    pub power_profile: PowerProfile,
 }
 
 /// Memory pressure levels
@@ -403,11 +441,38 @@ impl PerformanceMonitor {
         }
     }
 
+    /// Record a tab's first contentful render: the time from the start of
+    /// its load to its first paint with content
+    pub fn record_first_contentful_render(&self, tab_id: uuid::Uuid, elapsed: Duration) {
+        if !self.monitoring_enabled {
+            return;
+        }
+
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.set_first_contentful_render(tab_id, elapsed.as_millis() as u64);
+        }
+    }
+
+    /// Forget a closed tab's timings
+    pub fn remove_tab(&self, tab_id: &uuid::Uuid) {
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.remove_tab(tab_id);
+        }
+    }
+
     /// Get current memory usage
     pub fn get_memory_usage(&self) -> MemoryUsage {
         self.memory_usage.lock().unwrap().clone()
     }
 
+    /// Record the power profile the browser switched to; kept even while
+    /// monitoring is off, as it is state rather than a measurement
+    pub fn set_power_profile(&self, profile: PowerProfile) {
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.power_profile = profile;
+        }
+    }
+
     /// Get performance summary
     pub fn get_performance_summary(&self) -> PerformanceSummary {
         self.metrics.lock().unwrap().get_summary()
@@ -619,6 +684,37 @@ mod tests {
         assert!((avg - 1100.0).abs() < 0.1);
     }
 
+    #[test]
+    fn test_first_contentful_render_per_tab() {
+        let monitor = PerformanceMonitor::new(MemoryConfig::default());
+        let (first, second) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+
+        monitor.record_first_contentful_render(first, Duration::from_millis(300));
+        monitor.record_first_contentful_render(first, Duration::from_millis(100));
+        monitor.record_first_contentful_render(second, Duration::from_millis(500));
+
+        let summary = monitor.get_performance_summary();
+        assert_eq!(summary.first_contentful_render_ms.get(&first), Some(&100));
+        assert!((summary.average_first_contentful_render_ms - 300.0).abs() < 0.1);
+        assert_eq!(summary.power_profile, PowerProfile::Balanced);
+
+        monitor.remove_tab(&second);
+        assert!(
+            (monitor
+                .get_performance_summary()
+                .average_first_contentful_render_ms
+                - 100.0)
+                .abs()
+                < 0.1
+        );
+
+        monitor.set_power_profile(PowerProfile::PowerSaving);
+        assert_eq!(
+            monitor.get_performance_summary().power_profile,
+            PowerProfile::PowerSaving
+        );
+    }
+
     #[test]
     fn test_memory_pressure_assessment() {
         let config = MemoryConfig::default();
@@ -0,0 +1,171 @@
+//! Power-saving profile for devices on battery
+//!
+//! The browser probes the platform's power supply at startup and every
+//! [`PROBE_INTERVAL`] after. On battery it switches to the power-saving
+//! [`PowerProfile`]: the host ticks less often, window resizes are laid out
+//! at most every [`PowerLimits::layout_interval`], background tabs run
+//! under the tighter power-saving execution budget, and tabs' fetches are
+//! held back: each tab's images load one at a time and background tabs
+//! share a single request between them. Back on mains power the balanced
+//! profile returns.
+//!
+//! Only Linux reports its power supply (through `/sys/class/power_supply`);
+//! elsewhere the source is unknown and the balanced profile stays.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the power supply is probed
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where Linux lists power supplies
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// What the device runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    /// Plugged in (or a battery that is charging or full)
+    Mains,
+    /// A discharging battery
+    Battery,
+    /// The platform does not say
+    Unknown,
+}
+
+/// The device's power source, where the platform reports it
+pub fn probe() -> PowerSource {
+    probe_dir(Path::new(POWER_SUPPLY_DIR))
+}
+
+/// Power source from a `/sys/class/power_supply`-like directory: on battery
+/// when a battery discharges and no mains or USB supply is online
+fn probe_dir(dir: &Path) -> PowerSource {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return PowerSource::Unknown;
+    };
+    let read = |supply: &Path, attribute: &str| {
+        std::fs::read_to_string(supply.join(attribute))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let (mut discharging, mut online, mut reported) = (false, false, false);
+    for entry in entries.flatten() {
+        let supply = entry.path();
+        match read(&supply, "type").as_str() {
+            "Battery" => {
+                reported = true;
+                discharging |= read(&supply, "status") == "Discharging";
+            }
+            "Mains" | "USB" => {
+                reported = true;
+                online |= read(&supply, "online") == "1";
+            }
+            _ => {}
+        }
+    }
+
+    match (reported, discharging && !online) {
+        (false, _) => PowerSource::Unknown,
+        (true, true) => PowerSource::Battery,
+        (true, false) => PowerSource::Mains,
+    }
+}
+
+/// How much work the browser does in the background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PowerProfile {
+    #[default]
+    Balanced,
+    /// On battery
+    PowerSaving,
+}
+
+impl PowerProfile {
+    /// The profile to run under on `source`
+    pub fn for_source(source: PowerSource) -> Self {
+        match source {
+            PowerSource::Battery => Self::PowerSaving,
+            PowerSource::Mains | PowerSource::Unknown => Self::Balanced,
+        }
+    }
+
+    /// Whether background work is cut back
+    pub fn is_power_saving(self) -> bool {
+        self == Self::PowerSaving
+    }
+
+    /// Intervals the profile runs at
+    pub fn limits(self) -> PowerLimits {
+        match self {
+            Self::Balanced => PowerLimits {
+                tick_interval: Duration::from_millis(250),
+                layout_interval: Duration::ZERO,
+            },
+            Self::PowerSaving => PowerLimits {
+                tick_interval: Duration::from_secs(1),
+                layout_interval: Duration::from_millis(500),
+            },
+        }
+    }
+}
+
+/// Intervals the browser runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerLimits {
+    /// Between the host's housekeeping ticks (event draining, tab expiry,
+    /// deferred layout)
+    pub tick_interval: Duration,
+    /// Shortest time between viewport layouts
+    pub layout_interval: Duration,
+}
+
+impl Default for PowerLimits {
+    fn default() -> Self {
+        PowerProfile::default().limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(dir: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let supply = dir.join(name);
+        std::fs::create_dir_all(&supply).unwrap();
+        for (attribute, value) in attributes {
+            std::fs::write(supply.join(attribute), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_probe_reads_battery_and_mains() {
+        let dir = std::env::temp_dir().join(format!("citadel-power-{}", uuid::Uuid::new_v4()));
+        assert_eq!(probe_dir(&dir), PowerSource::Unknown);
+
+        supply(
+            &dir,
+            "BAT0",
+            &[("type", "Battery"), ("status", "Discharging")],
+        );
+        supply(&dir, "AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(probe_dir(&dir), PowerSource::Battery);
+        assert_eq!(
+            PowerProfile::for_source(probe_dir(&dir)),
+            PowerProfile::PowerSaving
+        );
+
+        supply(&dir, "AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(probe_dir(&dir), PowerSource::Mains);
+        assert_eq!(
+            PowerProfile::for_source(PowerSource::Unknown),
+            PowerProfile::Balanced
+        );
+
+        let saving = PowerProfile::PowerSaving.limits();
+        assert!(saving.tick_interval > PowerLimits::default().tick_interval);
+        assert!(!saving.layout_interval.is_zero());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
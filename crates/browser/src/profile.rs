@@ -0,0 +1,817 @@
+//! Profile encryption at rest
+//!
+//! Once enabled, every settings and session file Citadel writes is sealed
+//! with AES-256-GCM under a random profile key. That key is never written
+//! out in the clear. The key file stores it twice, wrapped:
+//!
+//! - under a key derived from the user's passphrase with Argon2id, and
+//! - under a recovery key, shown once so the user can write it down.
+//!
+//! The user may also let the OS keychain unlock the profile at launch. A
+//! random keychain key then wraps the profile key a third time and is
+//! stored in the platform's secret store (see [`crate::keychain`]).
+//!
+//! The passphrase is asked for once per launch, before any profile file is
+//! read. Locking drops the profile key, which zeroizes it. While the
+//! profile is locked, sealed files can be neither read nor written; a save
+//! fails instead of falling back to plaintext. Each file is sealed to its
+//! name, so one sealed file cannot be swapped in for another.
+//!
+//! [`enable`] seals the existing profile files straight away. After that
+//! an unsealed file is refused as corrupt rather than read, so plaintext
+//! dropped into the profile cannot stand in for a sealed file.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use citadel_security::secrets::SecretKey;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::keychain::SecretStore;
+
+/// Environment variable overriding where the profile key file is kept
+pub const PROFILE_KEY_FILE_ENV: &str = "CITADEL_PROFILE_KEY_FILE";
+
+/// Marks a sealed file; also authenticated as associated data, followed by
+/// the file's name
+const SEALED_MAGIC: &[u8] = b"CTDLENC1";
+
+/// Associated data binding wrapped profile keys to their purpose
+const WRAP_AAD: &[u8] = b"citadel-profile-key";
+
+/// Keychain account holding the keychain key
+const KEYCHAIN_ACCOUNT: &str = "profile-key";
+
+const KEY_FILE_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// Why the profile could not be unlocked or a file opened
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The passphrase or recovery key does not match the key file
+    WrongKey,
+    /// The profile is encrypted and not unlocked
+    Locked,
+    /// A key file or sealed file is malformed or was tampered with
+    Corrupt(String),
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongKey => write!(f, "wrong passphrase or recovery key"),
+            Self::Locked => write!(f, "profile is locked"),
+            Self::Corrupt(reason) => write!(f, "corrupt profile data: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<ProfileError> for std::io::Error {
+    fn from(error: ProfileError) -> Self {
+        let kind = match error {
+            ProfileError::WrongKey | ProfileError::Locked => std::io::ErrorKind::PermissionDenied,
+            ProfileError::Corrupt(_) => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+
+/// Argon2id cost parameters, stored with the key file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+/// The profile key sealed under another key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct WrappedKey {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Contents of the key file: the profile key wrapped under the passphrase,
+/// under the recovery key, and optionally under the keychain key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileKeyFile {
+    version: u32,
+    salt: String,
+    kdf: KdfParams,
+    passphrase_key: WrappedKey,
+    recovery_key: WrappedKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keychain_key: Option<WrappedKey>,
+}
+
+impl ProfileKeyFile {
+    /// Read the key file; `None` when the profile is not encrypted
+    pub fn load(path: &Path) -> std::io::Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        tokio::fs::write(path, json).await
+    }
+
+    fn passphrase_kek(&self, passphrase: &str) -> Result<SecretKey, ProfileError> {
+        if self.version != KEY_FILE_VERSION {
+            return Err(ProfileError::Corrupt(format!(
+                "unsupported key file version {}",
+                self.version
+            )));
+        }
+        derive_key(passphrase, &decode(&self.salt)?, self.kdf)
+    }
+}
+
+/// Key that unwraps the profile key when the passphrase is lost. Shown to
+/// the user as 16 groups of 4 hex digits.
+#[derive(PartialEq, Eq)]
+pub struct RecoveryKey(SecretKey);
+
+impl RecoveryKey {
+    fn generate() -> Self {
+        Self(SecretKey::generate())
+    }
+
+    /// Parse a recovery key as shown by [`RecoveryKey::display`]; spaces and
+    /// dashes are ignored
+    pub fn parse(text: &str) -> Option<Self> {
+        let digits: Zeroizing<String> = Zeroizing::new(
+            text.chars()
+                .filter(|c| !c.is_whitespace() && *c != '-')
+                .collect(),
+        );
+        if digits.len() != KEY_LEN * 2 || !digits.is_ascii() {
+            return None;
+        }
+        let mut key = SecretKey::from_bytes([0u8; KEY_LEN]);
+        for (byte, pair) in key.expose_mut().iter_mut().zip(digits.as_bytes().chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+        }
+        Some(Self(key))
+    }
+
+    /// The key as the user writes it down
+    pub fn display(&self) -> Zeroizing<String> {
+        let hex: Zeroizing<String> = Zeroizing::new(
+            self.0
+                .expose()
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect(),
+        );
+        let groups: Vec<&str> = (0..hex.len())
+            .step_by(4)
+            .map(|start| &hex[start..start + 4])
+            .collect();
+        Zeroizing::new(groups.join("-"))
+    }
+}
+
+impl std::fmt::Debug for RecoveryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RecoveryKey(..)")
+    }
+}
+
+/// The unlocked profile key. Dropping the vault zeroizes the key.
+pub struct ProfileVault {
+    key: SecretKey,
+}
+
+impl std::fmt::Debug for ProfileVault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProfileVault(..)")
+    }
+}
+
+impl ProfileVault {
+    /// Create a new profile key protected by `passphrase`. Returns the key
+    /// file to save and the recovery key to show the user.
+    pub fn create(
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> Result<(Self, ProfileKeyFile, RecoveryKey), ProfileError> {
+        let vault = Self {
+            key: SecretKey::generate(),
+        };
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let kek = derive_key(passphrase, &salt, kdf)?;
+        let recovery = RecoveryKey::generate();
+        let file = ProfileKeyFile {
+            version: KEY_FILE_VERSION,
+            salt: BASE64.encode(salt),
+            kdf,
+            passphrase_key: vault.wrap(&kek)?,
+            recovery_key: vault.wrap(&recovery.0)?,
+            keychain_key: None,
+        };
+        Ok((vault, file, recovery))
+    }
+
+    /// Unwrap the profile key with the passphrase
+    pub fn unlock(file: &ProfileKeyFile, passphrase: &str) -> Result<Self, ProfileError> {
+        let kek = file.passphrase_kek(passphrase)?;
+        Self::unwrap(&file.passphrase_key, &kek)
+    }
+
+    /// Unwrap the profile key with the recovery key
+    pub fn unlock_with_recovery(
+        file: &ProfileKeyFile,
+        recovery: &RecoveryKey,
+    ) -> Result<Self, ProfileError> {
+        Self::unwrap(&file.recovery_key, &recovery.0)
+    }
+
+    /// Protect the profile key with a new passphrase, e.g. after unlocking
+    /// with the recovery key. The old passphrase stops working.
+    pub fn set_passphrase(
+        &self,
+        file: &mut ProfileKeyFile,
+        passphrase: &str,
+    ) -> Result<(), ProfileError> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let kek = derive_key(passphrase, &salt, file.kdf)?;
+        file.salt = BASE64.encode(salt);
+        file.passphrase_key = self.wrap(&kek)?;
+        Ok(())
+    }
+
+    /// Replace the recovery key; the previous one stops working
+    pub fn new_recovery_key(&self, file: &mut ProfileKeyFile) -> Result<RecoveryKey, ProfileError> {
+        let recovery = RecoveryKey::generate();
+        file.recovery_key = self.wrap(&recovery.0)?;
+        Ok(recovery)
+    }
+
+    /// Let a new keychain key unwrap the profile key. Returns the keychain
+    /// key to store in the OS keychain; any previous one stops working.
+    pub fn enroll_keychain(&self, file: &mut ProfileKeyFile) -> Result<SecretKey, ProfileError> {
+        let keychain_key = SecretKey::generate();
+        file.keychain_key = Some(self.wrap(&keychain_key)?);
+        Ok(keychain_key)
+    }
+
+    /// Unwrap the profile key with the key from the OS keychain
+    pub fn unlock_with_keychain(
+        file: &ProfileKeyFile,
+        keychain_key: &[u8],
+    ) -> Result<Self, ProfileError> {
+        let wrapped = file.keychain_key.as_ref().ok_or(ProfileError::WrongKey)?;
+        if keychain_key.len() != KEY_LEN {
+            return Err(ProfileError::WrongKey);
+        }
+        let mut kek = SecretKey::from_bytes([0u8; KEY_LEN]);
+        kek.expose_mut().copy_from_slice(keychain_key);
+        Self::unwrap(wrapped, &kek)
+    }
+
+    /// Encrypt the contents of the file named `name`
+    pub fn seal(&self, name: &str, plaintext: &[u8]) -> Result<Vec<u8>, ProfileError> {
+        let nonce = random_nonce();
+        let ciphertext = encrypt(&self.key, &nonce, plaintext, &sealed_aad(name))?;
+        let mut sealed = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(SEALED_MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a file sealed by [`ProfileVault::seal`] under the same name
+    pub fn open(&self, name: &str, sealed: &[u8]) -> Result<Vec<u8>, ProfileError> {
+        let body = sealed
+            .strip_prefix(SEALED_MAGIC)
+            .filter(|body| body.len() >= NONCE_LEN)
+            .ok_or_else(|| ProfileError::Corrupt("not a sealed file".into()))?;
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        decrypt(&self.key, nonce, ciphertext, &sealed_aad(name))
+            .map_err(|_| ProfileError::Corrupt("sealed file failed authentication".into()))
+    }
+
+    fn wrap(&self, kek: &SecretKey) -> Result<WrappedKey, ProfileError> {
+        let nonce = random_nonce();
+        Ok(WrappedKey {
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(encrypt(kek, &nonce, self.key.expose(), WRAP_AAD)?),
+        })
+    }
+
+    fn unwrap(wrapped: &WrappedKey, kek: &SecretKey) -> Result<Self, ProfileError> {
+        let nonce = decode(&wrapped.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(ProfileError::Corrupt("bad nonce length".into()));
+        }
+        let key = Zeroizing::new(decrypt(
+            kek,
+            &nonce,
+            &decode(&wrapped.ciphertext)?,
+            WRAP_AAD,
+        )?);
+        if key.len() != KEY_LEN {
+            return Err(ProfileError::Corrupt("bad profile key length".into()));
+        }
+        let mut vault = Self {
+            key: SecretKey::from_bytes([0u8; KEY_LEN]),
+        };
+        vault.key.expose_mut().copy_from_slice(&key);
+        Ok(vault)
+    }
+}
+
+/// Whether bytes read from disk are a sealed file
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(SEALED_MAGIC)
+}
+
+/// Associated data of a sealed file: the magic and the file's name
+fn sealed_aad(name: &str) -> Vec<u8> {
+    [SEALED_MAGIC, name.as_bytes()].concat()
+}
+
+/// The name a profile file is sealed under
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<SecretKey, ProfileError> {
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| ProfileError::Corrupt(format!("bad key derivation parameters: {}", e)))?;
+    let mut key = SecretKey::from_bytes([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.expose_mut())
+        .map_err(|e| ProfileError::Corrupt(format!("key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+fn encrypt(
+    key: &SecretKey,
+    nonce: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, ProfileError> {
+    Aes256Gcm::new(key.expose().into())
+        .encrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| ProfileError::Corrupt("encryption failed".into()))
+}
+
+/// Decrypt; a failed tag check means the key did not match
+fn decrypt(
+    key: &SecretKey,
+    nonce: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, ProfileError> {
+    Aes256Gcm::new(key.expose().into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| ProfileError::WrongKey)
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, ProfileError> {
+    BASE64
+        .decode(text)
+        .map_err(|e| ProfileError::Corrupt(e.to_string()))
+}
+
+fn random_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+/// Whether this process's profile is encrypted, and its key if unlocked
+enum ProfileState {
+    Plain,
+    Locked,
+    Unlocked(ProfileVault),
+}
+
+static STATE: RwLock<ProfileState> = RwLock::new(ProfileState::Plain);
+
+/// Mark the profile as encrypted but locked, before any file is read
+pub fn set_locked() {
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = ProfileState::Locked;
+}
+
+/// Use an unlocked vault for all profile reads and writes
+pub fn install(vault: ProfileVault) {
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = ProfileState::Unlocked(vault);
+}
+
+/// Drop the profile key, zeroizing it. Returns whether a key was held.
+pub fn lock() -> bool {
+    let mut state = STATE.write().unwrap_or_else(|e| e.into_inner());
+    if matches!(*state, ProfileState::Unlocked(_)) {
+        *state = ProfileState::Locked;
+        true
+    } else {
+        false
+    }
+}
+
+/// Whether the profile is encrypted
+pub fn is_encrypted() -> bool {
+    !matches!(
+        *STATE.read().unwrap_or_else(|e| e.into_inner()),
+        ProfileState::Plain
+    )
+}
+
+/// Whether the profile is encrypted and its key is not held
+pub fn is_locked() -> bool {
+    matches!(
+        *STATE.read().unwrap_or_else(|e| e.into_inner()),
+        ProfileState::Locked
+    )
+}
+
+/// Run `f` with the unlocked vault
+fn with_vault<T>(
+    f: impl FnOnce(&ProfileVault) -> Result<T, ProfileError>,
+) -> Result<T, ProfileError> {
+    match &*STATE.read().unwrap_or_else(|e| e.into_inner()) {
+        ProfileState::Unlocked(vault) => f(vault),
+        _ => Err(ProfileError::Locked),
+    }
+}
+
+/// Contents of a profile file as read from disk, decrypted if sealed
+fn open_contents(path: &Path, bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    Ok(open_in(&state, &file_name(path), bytes)?)
+}
+
+/// Open file contents in a given profile state. An encrypted profile only
+/// holds sealed files, so plaintext there has been planted or left behind
+/// and is refused rather than trusted.
+fn open_in(state: &ProfileState, name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, ProfileError> {
+    if is_sealed(&bytes) {
+        match state {
+            ProfileState::Unlocked(vault) => vault.open(name, &bytes),
+            _ => Err(ProfileError::Locked),
+        }
+    } else if matches!(state, ProfileState::Plain) {
+        Ok(bytes)
+    } else {
+        Err(ProfileError::Corrupt(format!(
+            "{} is not sealed in an encrypted profile",
+            name
+        )))
+    }
+}
+
+/// Contents to write for a profile file, sealed if the profile is encrypted
+fn seal_contents(path: &Path, bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    if is_encrypted() {
+        Ok(with_vault(|vault| vault.seal(&file_name(path), &bytes))?)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// Read a profile file, decrypting it if sealed
+pub fn read(path: &Path) -> std::io::Result<Vec<u8>> {
+    open_contents(path, std::fs::read(path)?)
+}
+
+/// Read a profile file without blocking, decrypting it if sealed
+pub async fn read_async(path: &Path) -> std::io::Result<Vec<u8>> {
+    open_contents(path, tokio::fs::read(path).await?)
+}
+
+/// Write a profile file, sealing it if the profile is encrypted
+pub async fn write(path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+    tokio::fs::write(path, seal_contents(path, contents)?).await
+}
+
+/// Where the key file lives: `$CITADEL_PROFILE_KEY_FILE`, otherwise
+/// `citadel/profile-key.json` under the XDG config directory
+pub fn default_key_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(PROFILE_KEY_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("profile-key.json"))
+}
+
+/// The profile files sealed when encryption is enabled
+pub fn profile_files() -> Vec<PathBuf> {
+    [
+        crate::session::default_path(),
+        crate::clipboard::default_path(),
+        crate::compat::default_path(),
+        crate::container_policies::default_path(),
+        crate::cookies::default_path(),
+        crate::user_styles::default_path(),
+        crate::external_protocols::default_path(),
+        crate::filter_allowlist::default_path(),
+        citadel_networking::filter_update::default_settings_path(),
+        crate::history::default_path(),
+        crate::memory_profile::default_path(),
+        crate::overlay_cleanup::default_path(),
+        crate::page_escalation::default_path(),
+        crate::panic::default_path(),
+        crate::parser_profile::default_path(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Encrypt the profile: create a key protected by `passphrase`, save the
+/// key file, seal the existing `files` and unlock the profile. Returns the
+/// recovery key to show the user.
+pub async fn enable(
+    passphrase: &str,
+    key_path: &Path,
+    files: &[PathBuf],
+) -> std::io::Result<RecoveryKey> {
+    if is_encrypted() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "profile is already encrypted",
+        ));
+    }
+    let (vault, key_file, recovery) = ProfileVault::create(passphrase, KdfParams::default())?;
+    key_file.save(key_path).await?;
+    for path in files {
+        match tokio::fs::read(path).await {
+            Ok(bytes) if !is_sealed(&bytes) => {
+                tokio::fs::write(path, vault.seal(&file_name(path), &bytes)?).await?
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    install(vault);
+    Ok(recovery)
+}
+
+/// Unlock the profile with the passphrase, or with the recovery key and a
+/// new passphrase to replace the forgotten one
+pub async fn unlock(
+    key_path: &Path,
+    passphrase: &str,
+    recovery: Option<RecoveryKey>,
+) -> std::io::Result<()> {
+    let mut key_file = ProfileKeyFile::load(key_path)?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "profile is not encrypted")
+    })?;
+    let vault = match recovery {
+        Some(recovery) => {
+            let vault = ProfileVault::unlock_with_recovery(&key_file, &recovery)?;
+            vault.set_passphrase(&mut key_file, passphrase)?;
+            key_file.save(key_path).await?;
+            vault
+        }
+        None => ProfileVault::unlock(&key_file, passphrase)?,
+    };
+    install(vault);
+    Ok(())
+}
+
+/// Replace the recovery key of the unlocked profile and return the new
+/// one; the previous recovery key stops working
+pub async fn export_recovery_key(key_path: &Path) -> std::io::Result<RecoveryKey> {
+    let mut key_file = ProfileKeyFile::load(key_path)?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "profile is not encrypted")
+    })?;
+    let recovery = with_vault(|vault| vault.new_recovery_key(&mut key_file))?;
+    key_file.save(key_path).await?;
+    Ok(recovery)
+}
+
+/// Let the OS keychain unlock the unlocked profile at launch. Refuses
+/// stores that are not protected by the OS.
+pub async fn remember_in_keychain(key_path: &Path, store: &dyn SecretStore) -> std::io::Result<()> {
+    if !store.is_os_protected() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{} store is not protected by the OS", store.name()),
+        ));
+    }
+    let mut key_file = ProfileKeyFile::load(key_path)?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "profile is not encrypted")
+    })?;
+    let keychain_key = with_vault(|vault| vault.enroll_keychain(&mut key_file))?;
+    store.set(KEYCHAIN_ACCOUNT, keychain_key.expose())?;
+    key_file.save(key_path).await
+}
+
+/// Unlock the profile with the key held by the OS keychain. Returns whether
+/// the keychain had a key that unlocked it.
+pub fn unlock_from_keychain(key_path: &Path, store: &dyn SecretStore) -> std::io::Result<bool> {
+    if !store.is_os_protected() {
+        return Ok(false);
+    }
+    let Some(key_file) = ProfileKeyFile::load(key_path)? else {
+        return Ok(false);
+    };
+    if key_file.keychain_key.is_none() {
+        return Ok(false);
+    }
+    let Some(keychain_key) = store.get(KEYCHAIN_ACCOUNT)? else {
+        return Ok(false);
+    };
+    match ProfileVault::unlock_with_keychain(&key_file, &keychain_key) {
+        Ok(vault) => {
+            install(vault);
+            Ok(true)
+        }
+        Err(ProfileError::WrongKey) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stop the OS keychain from unlocking the profile
+pub async fn forget_keychain(key_path: &Path, store: &dyn SecretStore) -> std::io::Result<()> {
+    store.delete(KEYCHAIN_ACCOUNT)?;
+    if let Some(mut key_file) = ProfileKeyFile::load(key_path)? {
+        if key_file.keychain_key.take().is_some() {
+            key_file.save(key_path).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so tests do not spend seconds in Argon2
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_passphrase_and_recovery_unlock_same_key() {
+        let (vault, mut file, recovery) = ProfileVault::create("hunter2", TEST_KDF).unwrap();
+        let sealed = vault.seal("session.json", br#"{"windows": []}"#).unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(7).any(|w| w == b"windows"));
+
+        let unlocked = ProfileVault::unlock(&file, "hunter2").unwrap();
+        assert_eq!(
+            unlocked.open("session.json", &sealed).unwrap(),
+            br#"{"windows": []}"#
+        );
+        assert!(matches!(
+            ProfileVault::unlock(&file, "hunter3"),
+            Err(ProfileError::WrongKey)
+        ));
+
+        // The written-down key parses back, whatever the grouping
+        let shown = recovery.display();
+        assert_eq!(shown.len(), 16 * 4 + 15);
+        let typed = RecoveryKey::parse(&shown.replace('-', " ").to_lowercase()).unwrap();
+        assert_eq!(typed, recovery);
+        let recovered = ProfileVault::unlock_with_recovery(&file, &typed).unwrap();
+        assert_eq!(
+            recovered.open("session.json", &sealed).unwrap(),
+            br#"{"windows": []}"#
+        );
+        assert!(RecoveryKey::parse("ABCD-EFGH").is_none());
+
+        // A new passphrase replaces the forgotten one
+        recovered
+            .set_passphrase(&mut file, "correct horse")
+            .unwrap();
+        assert!(ProfileVault::unlock(&file, "hunter2").is_err());
+        assert!(ProfileVault::unlock(&file, "correct horse").is_ok());
+
+        // A new recovery key revokes the old one
+        let replaced = recovered.new_recovery_key(&mut file).unwrap();
+        assert_ne!(replaced, typed);
+        assert!(ProfileVault::unlock_with_recovery(&file, &typed).is_err());
+        assert!(ProfileVault::unlock_with_recovery(&file, &replaced).is_ok());
+    }
+
+    #[test]
+    fn test_keychain_key_unlocks_until_replaced() {
+        let (vault, mut file, _) = ProfileVault::create("pass", TEST_KDF).unwrap();
+        assert!(ProfileVault::unlock_with_keychain(&file, &[0; KEY_LEN]).is_err());
+        let first = vault.enroll_keychain(&mut file).unwrap();
+        let sealed = vault.seal("history.bin", b"history").unwrap();
+        let unlocked = ProfileVault::unlock_with_keychain(&file, first.expose()).unwrap();
+        assert_eq!(unlocked.open("history.bin", &sealed).unwrap(), b"history");
+
+        let second = vault.enroll_keychain(&mut file).unwrap();
+        assert!(ProfileVault::unlock_with_keychain(&file, first.expose()).is_err());
+        assert!(ProfileVault::unlock_with_keychain(&file, second.expose()).is_ok());
+        assert!(ProfileVault::unlock_with_keychain(&file, &second.expose()[..16]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_files_are_rejected() {
+        let (vault, _, _) = ProfileVault::create("pass", TEST_KDF).unwrap();
+        let (other, _, _) = ProfileVault::create("pass", TEST_KDF).unwrap();
+        let mut sealed = vault.seal("bookmarks.json", b"bookmarks").unwrap();
+        assert!(matches!(
+            other.open("bookmarks.json", &sealed),
+            Err(ProfileError::Corrupt(_))
+        ));
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(vault.open("bookmarks.json", &sealed).is_err());
+        assert!(vault.open("bookmarks.json", b"plain json").is_err());
+    }
+
+    #[test]
+    fn test_plaintext_is_refused_once_encrypted() {
+        let (vault, _, _) = ProfileVault::create("pass", TEST_KDF).unwrap();
+        let sealed = vault.seal("session.json", b"{}").unwrap();
+        assert_eq!(
+            open_in(&ProfileState::Plain, "session.json", b"{}".to_vec()).unwrap(),
+            b"{}"
+        );
+        assert!(matches!(
+            open_in(&ProfileState::Plain, "session.json", sealed.clone()),
+            Err(ProfileError::Locked)
+        ));
+
+        let unlocked = ProfileState::Unlocked(vault);
+        assert_eq!(open_in(&unlocked, "session.json", sealed).unwrap(), b"{}");
+        assert!(matches!(
+            open_in(&unlocked, "session.json", b"{}".to_vec()),
+            Err(ProfileError::Corrupt(_))
+        ));
+        assert!(matches!(
+            open_in(&ProfileState::Locked, "session.json", b"{}".to_vec()),
+            Err(ProfileError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn test_swapped_files_are_rejected() {
+        let (vault, _, _) = ProfileVault::create("pass", TEST_KDF).unwrap();
+        let policies = vault.seal("container-policies.json", b"{}").unwrap();
+        assert!(vault.open("container-policies.json", &policies).is_ok());
+        assert!(matches!(
+            vault.open("filter-allowlist.json", &policies),
+            Err(ProfileError::Corrupt(_))
+        ));
+        assert_eq!(
+            file_name(Path::new("/home/u/.config/citadel/session.json")),
+            "session.json"
+        );
+    }
+}
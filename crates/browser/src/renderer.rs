@@ -4,15 +4,26 @@
 //! computed layout positions from Taffy and applying CSS styles to Iced widgets.
 //! This brings the DESIGN.md vision to life with proper web page rendering.
 
+use crate::accessibility::{AccessibilityBridge, SharedAdapter};
 use crate::app::Message;
+use crate::focus::{FocusActivation, FocusManager};
+use crate::image_decoder::{self, DecodedImage, ImageRequest};
+use crate::image_viewer::ImageViewer;
+#[cfg(feature = "devtools")]
+use crate::layout_debug::LayoutDebugOverlay;
+use crate::page_menu::PageHit;
+use crate::web_fonts::{self, FontKey, FontRequest};
+use citadel_parser::accessibility::AccessibilityTree;
 use citadel_parser::dom::{Node, NodeData};
-use citadel_parser::layout::LayoutRect;
-use citadel_parser::{compute_layout, CitadelStylesheet, ComputedStyle, Dom, LayoutResult};
+use citadel_parser::layout::{LayoutRect, LayoutSize};
+use citadel_parser::{
+    compute_layout_with_images, CitadelStylesheet, ComputedStyle, Dom, LayoutResult, TextBlock,
+};
 use iced::{
     theme,
     widget::{
-        button, checkbox, container, container::Appearance, container::StyleSheet, pick_list,
-        scrollable, text, text_input, Column, Space,
+        button, checkbox, container, container::Appearance, container::StyleSheet, mouse_area,
+        pick_list, scrollable, text, text_input, Column, Space,
     },
     Background, Color, Element, Font, Length, Padding,
 };
@@ -20,7 +31,8 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use url::Url;
 // WORKAROUND: Remove performance imports for now to fix build
 use citadel_parser::css::{ColorValue, LengthValue, PositionType as CssPositionType};
 
@@ -93,6 +105,17 @@ impl StyleSheet for EnhancedContainerStyle {
     }
 }
 
+/// Width of the keyboard focus ring in logical pixels.
+const FOCUS_RING_WIDTH: f32 = 2.0;
+
+/// Column width of text-only pages in logical pixels.
+const TEXT_ONLY_WIDTH: f32 = 720.0;
+
+/// Characters per line and line height of text-only pages, for sizing the
+/// scroll area.
+const TEXT_ONLY_LINE_CHARS: usize = 90;
+const TEXT_ONLY_LINE_HEIGHT: f32 = 24.0;
+
 /// Per-block box decoration (CSS background + border) for ZKVM display items.
 struct BlockBoxStyle {
     background: Option<Color>,
@@ -193,6 +216,15 @@ pub struct ContentSize {
     pub height: f32,
 }
 
+/// A decoded image of the page
+struct CachedImage {
+    handle: iced::widget::image::Handle,
+    width: u32,
+    height: u32,
+    /// Size of the decoded pixels
+    bytes: usize,
+}
+
 /// Widget cache entry for render tree caching
 struct WidgetCacheEntry {
     element: Element<'static, Message>,
@@ -262,10 +294,10 @@ pub struct CitadelRenderer {
     security_violations: Vec<String>,
     /// Resource loading base URL for images and fonts
     base_url: Option<String>,
-    /// Font cache for web fonts
-    font_cache: std::collections::HashMap<String, Font>,
-    /// Image cache for loaded images
-    image_cache: std::collections::HashMap<String, iced::widget::image::Handle>,
+    /// Web fonts loaded for the page, by family and weight
+    font_cache: std::collections::HashMap<FontKey, Font>,
+    /// Decoded images of the page, by `src` as written in the document
+    image_cache: std::collections::HashMap<String, CachedImage>,
     /// Form state management
     form_state: FormState,
     /// Form element counter for generating unique IDs
@@ -294,10 +326,29 @@ pub struct CitadelRenderer {
     frame_batching_enabled: bool,
     /// Pending widget updates for batching
     pending_widget_updates: Vec<u32>,
+    /// Shortest time between viewport layouts; resizes within it are
+    /// coalesced into one deferred layout
+    layout_interval: Duration,
+    /// When the viewport was last laid out
+    last_viewport_layout: Option<Instant>,
+    /// Whether a resize is waiting for its layout
+    layout_deferred: bool,
     /// Sanitized display list received from the tab's ZKVM isolation boundary.
     /// When present, the host paints THIS (never the raw DOM) — the zero-knowledge
     /// rendering path.
     zkvm_content: Option<citadel_tabs::RenderedContent>,
+    /// Text, headings and links of a text-only page, painted by the fast
+    /// path instead of any display list
+    text_blocks: Option<Vec<TextBlock>>,
+    /// Image navigated to, shown in the image viewer instead of a page
+    image_viewer: Option<ImageViewer>,
+    /// Accessibility snapshot of the current page for screen readers
+    accessibility: AccessibilityBridge,
+    /// Keyboard focus traversal over the current page
+    focus: FocusManager,
+    /// Box model outlines drawn over the page while layout debugging is on
+    #[cfg(feature = "devtools")]
+    layout_debug: Option<LayoutDebugOverlay>,
 }
 
 impl CitadelRenderer {
@@ -332,7 +383,16 @@ impl CitadelRenderer {
             last_layout_hash: None,
             frame_batching_enabled: true,
             pending_widget_updates: Vec::new(),
+            layout_interval: Duration::ZERO,
+            last_viewport_layout: None,
+            layout_deferred: false,
             zkvm_content: None,
+            text_blocks: None,
+            image_viewer: None,
+            accessibility: AccessibilityBridge::new(),
+            focus: FocusManager::new(),
+            #[cfg(feature = "devtools")]
+            layout_debug: None,
         }
     }
 
@@ -344,12 +404,103 @@ impl CitadelRenderer {
             width: content.width,
             height: content.height,
         };
+        self.accessibility.update_from_display_list(&content);
+        self.focus.set_targets_from_display_list(&content);
         self.zkvm_content = Some(content);
+        self.text_blocks = None;
+        self.image_viewer = None;
+    }
+
+    /// Show a text-only page: its blocks are painted as plain text, headings
+    /// and links, with no layout, styles or display list involved
+    pub fn set_text_blocks(&mut self, blocks: Vec<TextBlock>) {
+        self.clear_zkvm_content();
+        let lines: usize = blocks
+            .iter()
+            .map(|block| match block {
+                TextBlock::Paragraph(text) => text.chars().count() / TEXT_ONLY_LINE_CHARS + 1,
+                _ => 1,
+            })
+            .sum();
+        self.content_size = ContentSize {
+            width: TEXT_ONLY_WIDTH,
+            height: lines as f32 * TEXT_ONLY_LINE_HEIGHT,
+        };
+        self.text_blocks = Some(blocks);
     }
 
-    /// Drop any ZKVM display list (e.g. on navigation / new tab).
+    /// Show an image navigated to in the image viewer. Called again with
+    /// the viewer's new state after each zoom or pan.
+    pub fn set_image_viewer(&mut self, viewer: ImageViewer) {
+        if self.image_viewer.is_none() {
+            self.clear_zkvm_content();
+        }
+        self.content_size = ContentSize {
+            width: viewer.width() as f32,
+            height: viewer.height() as f32,
+        };
+        self.image_viewer = Some(viewer);
+    }
+
+    /// Whether the image viewer is showing, which scrolls itself
+    pub fn shows_image(&self) -> bool {
+        self.image_viewer.is_some()
+    }
+
+    /// Show box model outlines over the page, or stop with `None`
+    #[cfg(feature = "devtools")]
+    pub fn set_layout_debug(&mut self, overlay: Option<LayoutDebugOverlay>) {
+        self.layout_debug = overlay;
+    }
+
+    /// Drop any ZKVM display list, text-only page or image (e.g. on
+    /// navigation / new tab).
     pub fn clear_zkvm_content(&mut self) {
         self.zkvm_content = None;
+        self.text_blocks = None;
+        self.image_viewer = None;
+        self.accessibility.clear();
+        self.focus.clear();
+    }
+
+    /// Move keyboard focus to the next focusable element. Returns the element's
+    /// top edge so the caller can scroll it into view.
+    pub fn focus_next(&mut self) -> Option<f32> {
+        let (id, y) = self.focus.focus_next().map(|t| (t.id, t.y))?;
+        self.accessibility.set_focus(id);
+        Some(y)
+    }
+
+    /// Move keyboard focus to the previous focusable element. Returns the
+    /// element's top edge so the caller can scroll it into view.
+    pub fn focus_previous(&mut self) -> Option<f32> {
+        let (id, y) = self.focus.focus_previous().map(|t| (t.id, t.y))?;
+        self.accessibility.set_focus(id);
+        Some(y)
+    }
+
+    /// Move keyboard focus to the element with this ID. Returns its top edge
+    /// so the caller can scroll it into view.
+    pub fn focus_element(&mut self, id: u32) -> Option<f32> {
+        let y = self.focus.focus_id(id)?.y;
+        self.accessibility.set_focus(id);
+        Some(y)
+    }
+
+    /// Push the page's accessibility tree to `adapter` from now on, e.g.
+    /// when its window gains focus
+    pub fn attach_accessibility(&mut self, adapter: Option<SharedAdapter>) {
+        self.accessibility.attach(adapter);
+    }
+
+    /// Action for Enter on the focused element
+    pub fn activate_focus(&self) -> Option<FocusActivation> {
+        self.focus.activate()
+    }
+
+    /// Accessibility snapshot of the current page
+    pub fn accessibility(&self) -> &AccessibilityBridge {
+        &self.accessibility
     }
 
     /// Paint a ZKVM-sanitized display list into iced widgets.
@@ -363,13 +514,18 @@ impl CitadelRenderer {
         use citadel_tabs::DisplayKind;
         let mut col = Column::new().spacing(0).padding(16).width(Length::Fill);
 
-        for item in &content.display_list {
+        for (index, item) in content.display_list.iter().enumerate() {
             // CSS top margin (transparent gap above the box).
             if item.margin_top > 0.0 {
                 col = col.push(Space::with_height(Length::Fixed(item.margin_top)));
             }
 
-            let color = Color::from_rgb8(item.color[0], item.color[1], item.color[2]);
+            // Keyboard focus swaps in the boundary-resolved `:focus` colours.
+            let focus_style = item
+                .focus_style
+                .filter(|_| self.focus.is_focused(index as u32));
+            let rgb = focus_style.map(|f| f.color).unwrap_or(item.color);
+            let color = Color::from_rgb8(rgb[0], rgb[1], rgb[2]);
             let label = match (item.kind, &item.href) {
                 (DisplayKind::Link, Some(href)) => format!("{}  ({})", item.text, href),
                 _ => item.text.clone(),
@@ -382,11 +538,39 @@ impl CitadelRenderer {
                     ..Font::DEFAULT
                 });
             }
+            // Basic shaping maps one glyph per char, which breaks Arabic joining
+            // and Indic conjuncts; the advanced shaper also applies bidi reordering.
+            if item.complex_shaping || item.rtl {
+                widget = widget.shaping(iced::widget::text::Shaping::Advanced);
+            }
+            if item.rtl {
+                widget = widget
+                    .width(Length::Fill)
+                    .horizontal_alignment(iced::alignment::Horizontal::Right);
+            }
 
             // Wrap in a box with CSS background / border / padding when present.
             let has_box =
                 item.background.is_some() || item.border_width > 0.0 || item.padding > 0.0;
-            let block: Element<Message> = if has_box {
+            let block: Element<Message> = if let Some(focus) = focus_style {
+                // Focus ring around the item's laid-out box.
+                container(widget)
+                    .width(Length::Fill)
+                    .padding(item.padding.max(2.0) as u16)
+                    .style(theme::Container::Custom(Box::new(BlockBoxStyle {
+                        background: focus
+                            .background
+                            .or(item.background)
+                            .map(|c| Color::from_rgb8(c[0], c[1], c[2])),
+                        border_color: Some(Color::from_rgb8(
+                            focus.outline[0],
+                            focus.outline[1],
+                            focus.outline[2],
+                        )),
+                        border_width: FOCUS_RING_WIDTH,
+                    })))
+                    .into()
+            } else if has_box {
                 container(widget)
                     .width(Length::Fill)
                     .padding(item.padding.max(0.0) as u16)
@@ -397,6 +581,22 @@ impl CitadelRenderer {
             } else {
                 container(widget).width(Length::Fill).into()
             };
+            // Hovering a link previews its destination in the status bar and
+            // right-clicking opens the page menu for what was hit; the hit
+            // test is local and nothing is fetched
+            let block = match (item.kind, &item.href) {
+                (DisplayKind::Link, Some(href)) => mouse_area(block)
+                    .on_enter(Message::LinkHovered(Some(href.clone())))
+                    .on_exit(Message::LinkHovered(None))
+                    .on_right_press(Message::PageMenuOpened(PageHit::link(
+                        href.clone(),
+                        item.text.clone(),
+                    )))
+                    .into(),
+                _ => mouse_area(block)
+                    .on_right_press(Message::PageMenuOpened(PageHit::text(item.text.clone())))
+                    .into(),
+            };
             col = col.push(block);
         }
 
@@ -410,6 +610,68 @@ impl CitadelRenderer {
             .into()
     }
 
+    /// Paint a text-only page: headings in bold at their level's size,
+    /// paragraphs as wrapped text and links as buttons that navigate the tab
+    fn render_text_blocks(blocks: &[TextBlock]) -> Element<'_, Message> {
+        let col = blocks.iter().fold(
+            Column::new().spacing(8).padding(16).width(Length::Fill),
+            |col, block| match block {
+                TextBlock::Heading {
+                    level,
+                    text: heading,
+                } => col.push(
+                    mouse_area(
+                        text(heading)
+                            .size(28.0 - f32::from((*level).clamp(1, 6)) * 2.0)
+                            .font(Font {
+                                weight: iced::font::Weight::Bold,
+                                ..Font::DEFAULT
+                            })
+                            .shaping(iced::widget::text::Shaping::Advanced),
+                    )
+                    .on_right_press(Message::PageMenuOpened(PageHit::text(heading.clone()))),
+                ),
+                TextBlock::Paragraph(paragraph) => col.push(
+                    mouse_area(
+                        text(paragraph)
+                            .size(16)
+                            .shaping(iced::widget::text::Shaping::Advanced),
+                    )
+                    .on_right_press(Message::PageMenuOpened(PageHit::text(paragraph.clone()))),
+                ),
+                TextBlock::Link(link) => {
+                    let label = if link.text.is_empty() {
+                        link.url.to_string()
+                    } else {
+                        link.text.clone()
+                    };
+                    col.push(
+                        mouse_area(
+                            button(
+                                text(label)
+                                    .size(16)
+                                    .style(Color::from_rgb8(0x00, 0x66, 0xcc)),
+                            )
+                            .padding(0)
+                            .style(theme::Button::Text)
+                            .on_press(Message::Navigate(link.url.to_string())),
+                        )
+                        .on_enter(Message::LinkHovered(Some(link.url.to_string())))
+                        .on_exit(Message::LinkHovered(None))
+                        .on_right_press(Message::PageMenuOpened(PageHit::link(
+                            link.url.to_string(),
+                            link.text.clone(),
+                        ))),
+                    )
+                }
+            },
+        );
+        container(container(col).max_width(TEXT_ONLY_WIDTH))
+            .width(Length::Fill)
+            .center_x()
+            .into()
+    }
+
     /// The CSS-derived page background colour for the current ZKVM content, if any.
     /// The host paints this behind the content (see ui.rs page canvas).
     pub fn zkvm_background(&self) -> Option<Color> {
@@ -430,6 +692,78 @@ impl CitadelRenderer {
         self.base_url = Some(url);
     }
 
+    /// Web fonts the current stylesheet offers that are not loaded yet,
+    /// resolved against the base URL
+    pub fn pending_web_fonts(&self) -> Vec<FontRequest> {
+        let (Some(stylesheet), Some(base)) = (
+            &self.current_stylesheet,
+            self.base_url
+                .as_deref()
+                .and_then(|url| Url::parse(url).ok()),
+        ) else {
+            return Vec::new();
+        };
+        web_fonts::font_requests(stylesheet, &base)
+            .into_iter()
+            .filter(|request| !self.font_cache.contains_key(&request.key))
+            .collect()
+    }
+
+    /// Use a face loaded into the text system for text asking for its
+    /// family. `family` is the name inside the font file.
+    pub fn register_web_font(&mut self, request: &FontRequest, family: &str) {
+        let font = web_fonts::loaded_font(family, &request.key, request.italic);
+        self.font_cache.insert(request.key.clone(), font);
+        // Text drawn before the face arrived used a fallback
+        self.clear_widget_cache();
+    }
+
+    /// Images the current page shows that are not decoded yet, resolved
+    /// against the base URL
+    pub fn pending_images(&self) -> Vec<ImageRequest> {
+        let (Some(dom), Some(base)) = (
+            &self.current_dom,
+            self.base_url
+                .as_deref()
+                .and_then(|url| Url::parse(url).ok()),
+        ) else {
+            return Vec::new();
+        };
+        image_decoder::image_requests(dom, &base)
+            .into_iter()
+            .filter(|request| !self.image_cache.contains_key(&request.src))
+            .collect()
+    }
+
+    /// Show a decoded image in place of its placeholder, and lay the page
+    /// out again at the image's size
+    pub fn register_image(&mut self, request: &ImageRequest, image: &DecodedImage) {
+        self.image_cache.insert(
+            request.src.clone(),
+            CachedImage {
+                handle: image.handle(),
+                width: image.width,
+                height: image.height,
+                bytes: image.pixels.len(),
+            },
+        );
+        self.clear_widget_cache();
+        self.layout_viewport();
+    }
+
+    /// Decoded sizes of the page's images, for layout
+    fn image_sizes(&self) -> HashMap<String, LayoutSize> {
+        self.image_cache
+            .iter()
+            .map(|(src, image)| {
+                (
+                    src.clone(),
+                    LayoutSize::new(image.width as f32, image.height as f32),
+                )
+            })
+            .collect()
+    }
+
     /// Update the content to render with full layout computation and caching
     pub fn update_content(
         &mut self,
@@ -459,11 +793,12 @@ impl CitadelRenderer {
         }
 
         // Compute layout using Taffy engine
-        let layout_result = compute_layout(
+        let layout_result = compute_layout_with_images(
             &dom,
             &stylesheet,
             self.viewport_size.0,
             self.viewport_size.1,
+            self.image_sizes(),
         )
         .map_err(|e| format!("Advanced layout computation failed: {}", e))?;
 
@@ -478,6 +813,11 @@ impl CitadelRenderer {
         // Update content size based on new layout
         self.update_content_size_from_layout(&layout_result);
 
+        // Keep the accessibility tree in sync with the new content
+        let accessibility_tree = AccessibilityTree::build(&dom, Some(&layout_result));
+        self.accessibility.update_from_tree(&accessibility_tree);
+        self.focus.set_targets_from_tree(&accessibility_tree);
+
         // Update performance metrics
         let render_time = start_time.elapsed();
         self.update_render_metrics(&layout_result, render_time);
@@ -516,11 +856,49 @@ impl CitadelRenderer {
         // Invalidate viewport-dependent cache entries
         self.invalidate_viewport_dependent_cache();
 
+        // Coalesce a burst of resizes into one later layout
+        if self
+            .last_viewport_layout
+            .is_some_and(|last| last.elapsed() < self.layout_interval)
+        {
+            self.layout_deferred = true;
+            return;
+        }
+        self.layout_viewport();
+    }
+
+    /// Lay out a resize deferred by the layout interval once the interval
+    /// has passed; called from the host's periodic tick
+    pub fn flush_deferred_layout(&mut self) {
+        if self.layout_deferred
+            && self
+                .last_viewport_layout
+                .map_or(true, |last| last.elapsed() >= self.layout_interval)
+        {
+            self.layout_viewport();
+        }
+    }
+
+    /// Set the shortest time between viewport layouts; zero lays out on
+    /// every resize
+    pub fn set_layout_interval(&mut self, interval: Duration) {
+        self.layout_interval = interval;
+        if interval.is_zero() {
+            self.flush_deferred_layout();
+        }
+    }
+
+    /// Recompute layout for the current viewport size
+    fn layout_viewport(&mut self) {
+        let (width, height) = self.viewport_size;
+        self.layout_deferred = false;
+        self.last_viewport_layout = Some(Instant::now());
+
         // Recompute layout if we have content
         if let (Some(dom), Some(stylesheet)) = (&self.current_dom, &self.current_stylesheet) {
             let start_time = Instant::now();
 
-            match compute_layout(dom, stylesheet, width, height) {
+            match compute_layout_with_images(dom, stylesheet, width, height, self.image_sizes()) {
                 Ok(layout_result) => {
                     // Update content size based on layout
                     self.update_content_size_from_layout(&layout_result);
@@ -558,7 +936,13 @@ impl CitadelRenderer {
             let effective_width = self.viewport_size.0 / zoom_factor;
             let effective_height = self.viewport_size.1 / zoom_factor;
 
-            match compute_layout(dom, stylesheet, effective_width, effective_height) {
+            match compute_layout_with_images(
+                dom,
+                stylesheet,
+                effective_width,
+                effective_height,
+                self.image_sizes(),
+            ) {
                 Ok(layout_result) => {
                     self.update_content_size_from_layout(&layout_result);
                     self.current_layout = Some(layout_result);
@@ -618,7 +1002,7 @@ impl CitadelRenderer {
         log::info!("📝 Form message received: {:?}", message);
         match message {
             FormMessage::TextInputChanged(element_id, value) => {
-                log::info!("📝 Text input changed: {} = '{}'", element_id, value);
+                log::debug!("📝 Text input changed: {} = '{}'", element_id, value);
                 self.form_state.input_values.insert(element_id, value);
             }
             FormMessage::CheckboxToggled(element_id, checked) => {
@@ -626,11 +1010,11 @@ impl CitadelRenderer {
                 self.form_state.checkbox_states.insert(element_id, checked);
             }
             FormMessage::RadioSelected(group_name, value) => {
-                log::info!("🔘 Radio selected: {} = '{}'", group_name, value);
+                log::debug!("🔘 Radio selected: {} = '{}'", group_name, value);
                 self.form_state.radio_selections.insert(group_name, value);
             }
             FormMessage::SelectChanged(element_id, value) => {
-                log::info!("📋 Select changed: {} = '{}'", element_id, value);
+                log::debug!("📋 Select changed: {} = '{}'", element_id, value);
                 self.form_state.select_selections.insert(element_id, value);
             }
             FormMessage::ButtonClicked(element_id) => {
@@ -661,8 +1045,29 @@ impl CitadelRenderer {
         };
     }
 
-    /// Render the current content using computed layout positions
+    /// Render the current content, with the layout debugging overlay on top
+    /// when it is on
     pub fn render(&self) -> Element<'_, Message> {
+        let page = self.render_page();
+        #[cfg(feature = "devtools")]
+        if let Some(overlay) = &self.layout_debug {
+            return overlay.over(page);
+        }
+        page
+    }
+
+    /// Render the current content using computed layout positions
+    fn render_page(&self) -> Element<'_, Message> {
+        // An image navigated to has no page to lay out
+        if let Some(viewer) = &self.image_viewer {
+            return viewer.view(self.viewport_size);
+        }
+
+        // Text-only fast path: the page's blocks as plain widgets
+        if let Some(blocks) = &self.text_blocks {
+            return Self::render_text_blocks(blocks);
+        }
+
         // Zero-knowledge path: if the tab's ZKVM boundary returned a sanitized
         // display list, paint that and nothing else. The raw DOM is never touched.
         if let Some(content) = &self.zkvm_content {
@@ -1137,7 +1542,13 @@ impl CitadelRenderer {
 
         // Handle special elements first
         match tag_name {
-            "img" => return self.create_image_widget(element, computed_style),
+            "img" => {
+                return self.create_image_widget(
+                    element,
+                    computed_style,
+                    layout_result.node_layouts.get(&node.id()),
+                )
+            }
             "br" => {
                 return Space::with_height(self.get_line_height_from_style(computed_style) as u16)
                     .into()
@@ -1272,7 +1683,7 @@ impl CitadelRenderer {
                                 e.local_name(),
                                 child_node.children().len()
                             ),
-                            NodeData::Text(t) => log::info!(
+                            NodeData::Text(t) => log::debug!(
                                 "  Body child {}: Text '{}' ({} chars)",
                                 i,
                                 t.trim(),
@@ -1292,7 +1703,7 @@ impl CitadelRenderer {
                     );
                     let text_content = self.extract_text_content(&node, dom);
                     if !text_content.is_empty() {
-                        log::info!("  📄 {} text content: '{}'", tag_name, text_content);
+                        log::debug!("  📄 {} text content: '{}'", tag_name, text_content);
                     } else {
                         log::warn!("  ⚠️ {} element has no text content!", tag_name);
                     }
@@ -1522,7 +1933,11 @@ impl CitadelRenderer {
                 }
             }
             "br" => Space::with_height(14).into(),
-            "img" => self.create_image_widget(element, &computed_style),
+            "img" => self.create_image_widget(
+                element,
+                &computed_style,
+                layout_result.node_layouts.get(&node.id()),
+            ),
             "form" => {
                 log::debug!(
                     "🏗️ Rendering form element with {} children",
@@ -1970,10 +2385,10 @@ impl CitadelRenderer {
         TextDecoration::None
     }
 
-    /// Get font from computed style
-    fn get_font_from_style(&self, _computed_style: &ComputedStyle) -> Option<Font> {
-        // TODO: Implement font-family parsing and web font loading
-        None
+    /// Get font from computed style: its `font-family` list resolved
+    /// against the loaded web fonts and the generic families
+    fn get_font_from_style(&self, computed_style: &ComputedStyle) -> Option<Font> {
+        web_fonts::resolve(&self.font_cache, computed_style)
     }
 
     /// Get line height from computed style
@@ -2065,31 +2480,42 @@ impl CitadelRenderer {
         None
     }
 
-    /// Create image widget with comprehensive styling
+    /// Create an image widget at its laid-out size, or a placeholder with
+    /// the alt text until the image is decoded
     fn create_image_widget<'a>(
         &'a self,
         element: &citadel_parser::dom::Element,
         computed_style: &ComputedStyle,
+        layout: Option<&LayoutRect>,
     ) -> Element<'a, Message> {
         let alt_text = element
             .get_attribute("alt")
             .unwrap_or_else(|| "Image".to_string());
         let src = element.get_attribute("src");
-
-        // Check if we have the image in cache or can load it
-        if let Some(src_url) = src {
-            if let Some(_image_handle) = self.image_cache.get(&src_url) {
-                // TODO: Create actual image widget when image is cached
-                log::info!("🖼️ Image found in cache: {}", src_url);
-            } else {
-                log::info!(
-                    "🖼️ Image not in cache, showing placeholder for: {}",
-                    src_url
-                );
+        // Right-clicking the image or its placeholder opens the page menu
+        let hit_area = |widget: Element<'a, Message>| -> Element<'a, Message> {
+            match &src {
+                Some(src) => mouse_area(widget)
+                    .on_right_press(Message::PageMenuOpened(PageHit::image(src.clone())))
+                    .into(),
+                None => widget,
             }
+        };
+
+        if let Some(cached) = src.as_ref().and_then(|src| self.image_cache.get(src)) {
+            let (width, height) = layout
+                .filter(|rect| rect.width > 0.0 && rect.height > 0.0)
+                .map(|rect| (rect.width, rect.height))
+                .unwrap_or((cached.width as f32, cached.height as f32));
+            return hit_area(
+                iced::widget::image(cached.handle.clone())
+                    .width(Length::Fixed(width))
+                    .height(Length::Fixed(height))
+                    .content_fit(iced::ContentFit::Fill)
+                    .into(),
+            );
         }
 
-        // For now, create styled placeholder
         let enhanced_style = EnhancedContainerStyle {
             background: Some(Background::Color(Color::from_rgb(0.95, 0.95, 0.95))),
             border: iced::Border {
@@ -2103,14 +2529,16 @@ impl CitadelRenderer {
 
         let padding = self.get_comprehensive_padding("img", computed_style);
 
-        container(
-            text(format!("🖼️ [{}]", alt_text))
-                .size(14)
-                .style(Color::from_rgb(0.5, 0.5, 0.5)),
+        hit_area(
+            container(
+                text(format!("🖼️ [{}]", alt_text))
+                    .size(14)
+                    .style(Color::from_rgb(0.5, 0.5, 0.5)),
+            )
+            .padding(padding)
+            .style(theme::Container::Custom(Box::new(enhanced_style)))
+            .into(),
         )
-        .padding(padding)
-        .style(theme::Container::Custom(Box::new(enhanced_style)))
-        .into()
     }
 
     /// Create viewport-aware container with scroll and zoom support
@@ -3049,8 +3477,12 @@ impl CitadelRenderer {
         // Font cache memory
         total_memory += self.font_cache.len() * std::mem::size_of::<Font>();
 
-        // Image cache memory (estimated)
-        total_memory += self.image_cache.len() * 1024 * 100; // Estimate 100KB per image
+        // Decoded image pixels
+        total_memory += self
+            .image_cache
+            .values()
+            .map(|image| image.bytes)
+            .sum::<usize>();
 
         // Widget cache memory
         total_memory += self.widget_cache.len() * std::mem::size_of::<WidgetCacheEntry>();
@@ -3073,9 +3505,13 @@ impl CitadelRenderer {
         );
     }
 
-    /// Enable or disable frame batching
+    /// Enable or disable frame batching; disabling it frees the buffer of
+    /// pending updates
     pub fn set_frame_batching(&mut self, enabled: bool) {
         self.frame_batching_enabled = enabled;
+        if !enabled {
+            self.pending_widget_updates = Vec::new();
+        }
         log::info!(
             "Frame batching {}",
             if enabled { "enabled" } else { "disabled" }
@@ -0,0 +1,271 @@
+//! `citadel://cache`
+//!
+//! Lists every response the engine keeps cached, grouped by the partition it
+//! was stored under, with its size, age and how often it was served, so
+//! users can see exactly what the browser remembers about their browsing.
+//! Actions are plain links:
+//!
+//! - `citadel://cache/purge` empties every partition
+//! - `citadel://cache/purge?host=cdn.example` drops one host's responses
+//! - `citadel://cache/purge?url=https://cdn.example/app.js` drops one response
+//!
+//! Regular and container tabs' responses are also kept on disk
+//! ([`open_disk_storage`]), so they outlive the session, container tabs'
+//! sealed under a key kept in the secret store; ephemeral tabs' never leave
+//! memory.
+
+use std::sync::Arc;
+
+use citadel_networking::cache_storage::default_dir;
+use citadel_networking::{CacheStorage, DiskStorage, ResourceCaches};
+use citadel_security::secrets::SecretKey;
+use url::Url;
+
+use crate::html::escape;
+use crate::keychain::SecretStore;
+use crate::stylesheet_cache::StylesheetCacheStats;
+
+/// Address of the page
+pub const CACHE_URL: &str = "citadel://cache";
+/// Largest the disk cache grows before evicting, in bytes
+pub const DISK_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Keychain account holding the key container partitions are sealed under
+const CACHE_KEY_ACCOUNT: &str = "cache-container-key";
+
+const KEY_LEN: usize = 32;
+
+/// The disk cache in its default directory, if there is one and it opens.
+/// Container tabs' responses are sealed under the cache key kept in
+/// `secrets`; without one they are not written to disk.
+pub fn open_disk_storage(secrets: Option<&dyn SecretStore>) -> Option<Arc<dyn CacheStorage>> {
+    let dir = default_dir()?;
+    let storage = match DiskStorage::open(&dir, DISK_CACHE_BYTES) {
+        Ok(storage) => storage,
+        Err(e) => {
+            log::warn!(
+                "Responses will be cached in memory only; {} did not open: {}",
+                dir.display(),
+                e
+            );
+            return None;
+        }
+    };
+    Some(Arc::new(match secrets.map(container_key) {
+        Some(Ok(key)) => storage.with_container_key(key),
+        Some(Err(e)) => {
+            log::warn!(
+                "Container tabs' responses will be cached in memory only: {}",
+                e
+            );
+            storage
+        }
+        None => storage,
+    }))
+}
+
+/// The key container partitions are sealed under, created and stored in
+/// `secrets` on first use
+fn container_key(secrets: &dyn SecretStore) -> std::io::Result<SecretKey> {
+    let mut key = SecretKey::from_bytes([0u8; KEY_LEN]);
+    match secrets.get(CACHE_KEY_ACCOUNT)? {
+        Some(stored) if stored.len() == KEY_LEN => key.expose_mut().copy_from_slice(&stored),
+        Some(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stored cache key has the wrong length",
+            ))
+        }
+        None => {
+            key = SecretKey::generate();
+            secrets.set(CACHE_KEY_ACCOUNT, key.expose())?;
+        }
+    }
+    Ok(key)
+}
+
+/// Apply the action in `url`, if any, and render the page
+pub fn handle(caches: &ResourceCaches, stylesheets: StylesheetCacheStats, url: &Url) -> String {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let notice = match url.path() {
+        "/purge" => match (param("url"), param("host")) {
+            (Some(target), _) => match Url::parse(&target) {
+                Ok(target) if caches.remove(&target) > 0 => format!("Purged {}.", target),
+                Ok(target) => format!("{} was not cached.", target),
+                Err(_) => format!("Not a URL: {}", target),
+            },
+            (None, Some(host)) => {
+                let purged = caches.purge_host(&host);
+                format!("Purged {} responses from {}.", purged, host)
+            }
+            (None, None) => {
+                caches.clear();
+                "Purged the cache.".to_string()
+            }
+        },
+        _ => String::new(),
+    };
+
+    render(caches, stylesheets, &notice)
+}
+
+fn render(caches: &ResourceCaches, stylesheets: StylesheetCacheStats, notice: &str) -> String {
+    let partitions = caches.snapshot();
+    let (count, bytes) = partitions
+        .iter()
+        .flat_map(|(_, entries)| entries)
+        .fold((0, 0), |(count, bytes), entry| {
+            (count + 1, bytes + entry.size_bytes)
+        });
+
+    let mut html = String::from(
+        "<!doctype html><html><head><title>Cache</title></head><body>\n<h1>Cache</h1>\n",
+    );
+    if !notice.is_empty() {
+        html.push_str(&format!("<p><b>{}</b></p>\n", escape(notice)));
+    }
+    html.push_str(&format!(
+        "<p>{} responses, {} bytes, in {} partitions. Nothing is kept beyond this session.</p>\n\
+         <p><a href=\"{}/purge\">Purge all</a></p>\n",
+        count,
+        bytes,
+        partitions.len(),
+        CACHE_URL
+    ));
+    html.push_str(&format!(
+        "<p>Parsed stylesheets: {} kept, {} hits, {} misses ({:.0}% hit rate).</p>\n",
+        stylesheets.entries,
+        stylesheets.hits,
+        stylesheets.misses,
+        stylesheets.hit_rate()
+    ));
+    if partitions.is_empty() {
+        html.push_str("<p>The cache is empty.</p>\n");
+    }
+
+    for (partition, entries) in &partitions {
+        html.push_str(&format!("<h2>{}</h2>\n", escape(&partition.to_string())));
+        for entry in entries {
+            let expiry = match entry.expires_in {
+                Some(left) => format!("{}s left", left.as_secs()),
+                None => "stale".to_string(),
+            };
+            let host = entry.url.host_str().unwrap_or_default();
+            html.push_str(&format!(
+                "<p>{} ({} bytes, {}s old, {} hits, {}) \
+                 <a href=\"{}/purge?url={}\">purge</a> \
+                 <a href=\"{}/purge?host={}\">purge host</a></p>\n",
+                escape(entry.url.as_str()),
+                entry.size_bytes,
+                entry.age.as_secs(),
+                entry.hits,
+                expiry,
+                CACHE_URL,
+                urlencoding::encode(entry.url.as_str()),
+                CACHE_URL,
+                urlencoding::encode(host)
+            ));
+        }
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_networking::{CachePartition, Method, ResourceCache, Response};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn store(cache: &ResourceCache, url: &str) {
+        let url = Url::parse(url).unwrap();
+        let response = Response::new(
+            200,
+            HashMap::new(),
+            "cached".into(),
+            url.clone(),
+            Method::GET,
+        );
+        cache.put(&url, response).unwrap();
+    }
+
+    #[test]
+    fn test_page_lists_and_purges_per_partition() {
+        let caches = ResourceCaches::default();
+        let site = CachePartition::for_url(&Url::parse("https://news.test/").unwrap()).unwrap();
+        let tab = Uuid::new_v4();
+        let shared = caches.for_partition(site.clone());
+        store(&shared, "https://cdn.test/a.js?x=<1>");
+        store(&shared, "https://news.test/app.css");
+        store(
+            &caches.for_partition(site.in_ephemeral_tab(tab)),
+            "https://cdn.test/a.js",
+        );
+
+        let page = |path: &str| {
+            handle(
+                &caches,
+                StylesheetCacheStats::default(),
+                &Url::parse(path).unwrap(),
+            )
+        };
+        let listing = page(CACHE_URL);
+        assert!(listing.contains("3 responses"));
+        assert!(listing.contains("0 misses (0% hit rate)"));
+        assert!(listing.contains("<h2>news.test</h2>"));
+        assert!(listing.contains(&format!("news.test in ephemeral tab {}", tab)));
+        assert!(listing.contains("https://cdn.test/a.js?x=%3C1%3E"));
+
+        let purged = page("citadel://cache/purge?host=cdn.test");
+        assert!(purged.contains("Purged 2 responses from cdn.test."));
+        assert!(purged.contains("1 responses"));
+
+        let one = page("citadel://cache/purge?url=https%3A%2F%2Fnews.test%2Fapp.css");
+        assert!(one.contains("Purged https://news.test/app.css."));
+        assert!(one.contains("The cache is empty."));
+
+        store(
+            &caches.for_partition(
+                CachePartition::for_url(&Url::parse("https://other.test/").unwrap())
+                    .unwrap()
+                    .in_ephemeral_tab(tab),
+            ),
+            "https://other.test/",
+        );
+        caches.release_tab(tab);
+        assert!(caches.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_persistent_partitions_survive_reopening_the_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("citadel-cache-test-{}", Uuid::new_v4()));
+        let open = || {
+            ResourceCaches::default()
+                .with_storage(Arc::new(DiskStorage::open(&dir, DISK_CACHE_BYTES).unwrap()))
+        };
+        let site = CachePartition::for_url(&Url::parse("https://news.test/").unwrap()).unwrap();
+        let ephemeral = site.clone().in_ephemeral_tab(Uuid::new_v4());
+        let kept = Url::parse("https://news.test/app.css").unwrap();
+        let private = Url::parse("https://news.test/private.css").unwrap();
+
+        let caches = open();
+        store(&caches.for_partition(site.clone()), kept.as_str());
+        store(&caches.for_partition(ephemeral.clone()), private.as_str());
+        drop(caches);
+
+        let reopened = open();
+        assert!(reopened.for_partition(site.clone()).get(&kept).is_some());
+        assert!(reopened.for_partition(ephemeral).get(&private).is_none());
+
+        reopened.clear();
+        assert!(open().for_partition(site).get(&kept).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -3,6 +3,8 @@ use citadel_security::SecurityContext;
 use std::sync::Arc;
 use url::Url;
 
+use crate::image_decoder::{self, DecodeLimits, DecodedImage};
+
 /// Resource loader for fetching web resources (HTML, CSS, JS, images, etc.)
 #[allow(dead_code)] // Will be used when implementing resource loading
 pub struct ResourceLoader {
@@ -27,6 +29,18 @@ impl ResourceLoader {
         })
     }
 
+    /// A loader fetching through an existing resource manager, sharing its
+    /// policies and budgets
+    pub fn from_manager(
+        resource_manager: Arc<ResourceManager>,
+        security_context: Arc<SecurityContext>,
+    ) -> Self {
+        Self {
+            resource_manager,
+            security_context,
+        }
+    }
+
     /// Load a resource from the given URL
     pub async fn load_resource(&self, url: Url) -> Result<Vec<u8>, String> {
         // Use ResourceManager's fetch method which handles caching internally
@@ -74,11 +88,12 @@ impl ResourceLoader {
         }
     }
 
-    /// Load CSS content
-    pub async fn load_css(&self, url: Url) -> Result<String, String> {
+    /// Load CSS content for a tab's page, under the tab's CSP `style-src` and
+    /// request budget
+    pub async fn load_css(&self, tab_id: uuid::Uuid, url: Url) -> Result<String, String> {
         match self
             .resource_manager
-            .fetch(url.as_str(), Some(ResourceType::Css))
+            .fetch_for_tab(tab_id, url.as_str(), Some(ResourceType::Css))
             .await
         {
             Ok(response) => {
@@ -104,11 +119,12 @@ impl ResourceLoader {
         }
     }
 
-    /// Load JavaScript content
-    pub async fn load_javascript(&self, url: Url) -> Result<String, String> {
+    /// Load JavaScript content for a tab's page, under the tab's CSP `script-src` and
+    /// request budget
+    pub async fn load_javascript(&self, tab_id: uuid::Uuid, url: Url) -> Result<String, String> {
         match self
             .resource_manager
-            .fetch(url.as_str(), Some(ResourceType::Script))
+            .fetch_for_tab(tab_id, url.as_str(), Some(ResourceType::Script))
             .await
         {
             Ok(response) => {
@@ -156,6 +172,31 @@ impl ResourceLoader {
             }
         }
     }
+
+    /// Load an image for a tab's page, under the tab's CSP `img-src` and
+    /// request budget, and decode it within `limits`
+    pub async fn load_image_for_tab(
+        &self,
+        tab_id: uuid::Uuid,
+        url: &Url,
+        limits: &DecodeLimits,
+    ) -> Result<DecodedImage, String> {
+        let response = self
+            .resource_manager
+            .fetch_for_tab(tab_id, url.as_str(), Some(ResourceType::Image))
+            .await
+            .map_err(|e| format!("Failed to fetch image: {}", e))?;
+        image_decoder::decode(response.body(), limits).map_err(|e| {
+            log::debug!("Not showing image {}: {}", url, e);
+            format!("Failed to decode image: {}", e)
+        })
+    }
+}
+
+impl std::fmt::Debug for ResourceLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceLoader").finish_non_exhaustive()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,194 @@
+//! Session save on exit
+//!
+//! When the last window closes the open windows are recorded so container
+//! tabs can be restored next time. Ephemeral tabs are never written to disk;
+//! a window holding only ephemeral tabs leaves no trace in the session file.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use citadel_tabs::{TabState, TabType};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::profile;
+use crate::windows::WindowManager;
+
+/// Environment variable overriding where the session is saved
+pub const SESSION_FILE_ENV: &str = "CITADEL_SESSION_FILE";
+
+/// A container tab worth restoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTab {
+    /// Container the tab belongs to
+    pub container_id: Uuid,
+    /// Page URL
+    pub url: String,
+    /// Page title
+    pub title: String,
+}
+
+/// One window's restorable tabs, in strip order
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub tabs: Vec<SavedTab>,
+}
+
+/// Everything written by the session save flow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// When the session was captured
+    pub saved_at: DateTime<Utc>,
+    /// Windows that had at least one container tab
+    pub windows: Vec<WindowSnapshot>,
+}
+
+impl SessionSnapshot {
+    /// Record the container tabs of every open window
+    pub fn capture(windows: &WindowManager, states: &[TabState]) -> Self {
+        let windows = windows
+            .windows()
+            .iter()
+            .map(|window| WindowSnapshot {
+                tabs: window
+                    .tabs()
+                    .iter()
+                    .filter_map(|id| states.iter().find(|state| state.id == *id))
+                    .filter_map(|state| match state.tab_type {
+                        TabType::Container { container_id } => Some(SavedTab {
+                            container_id,
+                            url: state.url.clone(),
+                            title: state.title.clone(),
+                        }),
+                        TabType::Ephemeral => None,
+                    })
+                    .collect(),
+            })
+            .filter(|window| !window.tabs.is_empty())
+            .collect();
+
+        Self {
+            saved_at: Utc::now(),
+            windows,
+        }
+    }
+
+    /// Whether there is nothing to restore
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Drop the tabs of the given containers, and any window left empty.
+    /// Returns the number of tabs dropped.
+    pub fn remove_containers(&mut self, containers: &BTreeSet<Uuid>) -> usize {
+        let mut removed = 0;
+        for window in &mut self.windows {
+            let before = window.tabs.len();
+            window
+                .tabs
+                .retain(|tab| !containers.contains(&tab.container_id));
+            removed += before - window.tabs.len();
+        }
+        self.windows.retain(|window| !window.tabs.is_empty());
+        removed
+    }
+}
+
+/// Where the session is saved: `$CITADEL_SESSION_FILE`, otherwise
+/// `citadel/session.json` under the XDG state directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SESSION_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))?;
+    Some(state_dir.join("citadel").join("session.json"))
+}
+
+/// Read a saved session; `None` when there is none
+pub async fn load(path: &Path) -> std::io::Result<Option<SessionSnapshot>> {
+    match profile::read_async(path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write a snapshot, or remove a stale session file when there is nothing
+/// to restore
+pub async fn save(snapshot: &SessionSnapshot, path: &Path) -> std::io::Result<()> {
+    if snapshot.is_empty() {
+        return match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let json = serde_json::to_vec_pretty(snapshot)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    profile::write(path, json).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_tabs::PageContent;
+    use iced::window;
+
+    fn state(tab_type: TabType, url: &str) -> TabState {
+        TabState {
+            id: Uuid::new_v4(),
+            title: url.to_string(),
+            url: url.to_string(),
+            tab_type,
+            is_active: false,
+            created_at: Utc::now(),
+            last_active_at: Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content: PageContent::Empty,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_container_tabs_are_saved() {
+        let container_id = Uuid::new_v4();
+        let kept = state(TabType::Container { container_id }, "https://kept.test/");
+        let private = state(TabType::Ephemeral, "https://private.test/");
+        let lone = state(TabType::Ephemeral, "https://lone.test/");
+
+        let second = window::Id::unique();
+        let mut windows = WindowManager::new(window::Id::MAIN);
+        windows.add_tab(window::Id::MAIN, private.id);
+        windows.add_tab(window::Id::MAIN, kept.id);
+        windows.open(second);
+        windows.add_tab(second, lone.id);
+
+        let snapshot = SessionSnapshot::capture(&windows, &[kept, private, lone]);
+        assert_eq!(snapshot.windows.len(), 1);
+        assert_eq!(snapshot.windows[0].tabs.len(), 1);
+        assert_eq!(snapshot.windows[0].tabs[0].container_id, container_id);
+
+        let path = std::env::temp_dir()
+            .join(format!("citadel-session-{}", Uuid::new_v4()))
+            .join("session.json");
+        save(&snapshot, &path).await.unwrap();
+        let written = tokio::fs::read_to_string(&path).await.unwrap();
+        assert!(written.contains("https://kept.test/"));
+        assert!(!written.contains("private.test"));
+
+        // An all-ephemeral session removes the previous file
+        let empty = SessionSnapshot::capture(&WindowManager::new(window::Id::MAIN), &[]);
+        save(&empty, &path).await.unwrap();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,204 @@
+//! Runtime privacy settings with change notification
+//!
+//! The privacy level, DNS mode and fingerprint protection level can change
+//! while the browser runs. [`SettingsStore`] publishes each on its own
+//! `watch` channel, so a consumer wakes only for the setting it uses:
+//!
+//! - `CitadelDnsResolver` follows the DNS mode and applies it at its next
+//!   lookup.
+//! - `FingerprintManager` follows the protection level and applies it on
+//!   `refresh_settings`.
+//! - Each tab's [`TabPolicy`] follows the privacy and protection levels and
+//!   applies them when the tab next navigates, so a loaded page keeps the
+//!   policy it was loaded under.
+
+use citadel_networking::{DnsMode, PrivacyLevel};
+use citadel_security::context::FingerprintProtectionLevel;
+use citadel_security::SecurityContext;
+use tokio::sync::watch;
+
+/// The settings that can change at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeSettings {
+    pub privacy_level: PrivacyLevel,
+    pub dns_mode: DnsMode,
+    pub fingerprint_protection: FingerprintProtectionLevel,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            privacy_level: PrivacyLevel::High,
+            dns_mode: DnsMode::LocalCache,
+            fingerprint_protection: FingerprintProtectionLevel::default(),
+        }
+    }
+}
+
+/// Holds the current settings and notifies subscribers of changes
+#[derive(Debug)]
+pub struct SettingsStore {
+    privacy_level: watch::Sender<PrivacyLevel>,
+    dns_mode: watch::Sender<DnsMode>,
+    fingerprint_protection: watch::Sender<FingerprintProtectionLevel>,
+}
+
+impl SettingsStore {
+    pub fn new(settings: RuntimeSettings) -> Self {
+        Self {
+            privacy_level: watch::Sender::new(settings.privacy_level),
+            dns_mode: watch::Sender::new(settings.dns_mode),
+            fingerprint_protection: watch::Sender::new(settings.fingerprint_protection),
+        }
+    }
+
+    /// Snapshot of the current settings
+    pub fn current(&self) -> RuntimeSettings {
+        RuntimeSettings {
+            privacy_level: *self.privacy_level.borrow(),
+            dns_mode: self.dns_mode.borrow().clone(),
+            fingerprint_protection: *self.fingerprint_protection.borrow(),
+        }
+    }
+
+    /// Replace all settings; subscribers are notified only of values that
+    /// actually changed. Returns whether anything changed.
+    pub fn update(&self, settings: RuntimeSettings) -> bool {
+        let privacy = self.set_privacy_level(settings.privacy_level);
+        let dns = self.set_dns_mode(settings.dns_mode);
+        let fingerprint = self.set_fingerprint_protection(settings.fingerprint_protection);
+        privacy || dns || fingerprint
+    }
+
+    pub fn set_privacy_level(&self, level: PrivacyLevel) -> bool {
+        replace_if_changed(&self.privacy_level, level)
+    }
+
+    pub fn set_dns_mode(&self, mode: DnsMode) -> bool {
+        replace_if_changed(&self.dns_mode, mode)
+    }
+
+    pub fn set_fingerprint_protection(&self, level: FingerprintProtectionLevel) -> bool {
+        replace_if_changed(&self.fingerprint_protection, level)
+    }
+
+    /// Channel for `CitadelDnsResolver::follow_mode`
+    pub fn subscribe_dns_mode(&self) -> watch::Receiver<DnsMode> {
+        self.dns_mode.subscribe()
+    }
+
+    /// Channel for `FingerprintManager::follow_protection_level`
+    pub fn subscribe_fingerprint_protection(&self) -> watch::Receiver<FingerprintProtectionLevel> {
+        self.fingerprint_protection.subscribe()
+    }
+
+    /// Policy for a newly opened tab, starting from the current settings
+    pub fn tab_policy(&self, base: SecurityContext) -> TabPolicy {
+        let mut policy = TabPolicy {
+            security_context: base,
+            privacy_level: *self.privacy_level.borrow(),
+            privacy_updates: self.privacy_level.subscribe(),
+            fingerprint_updates: self.fingerprint_protection.subscribe(),
+        };
+        policy
+            .security_context
+            .set_fingerprint_protection_level(*self.fingerprint_protection.borrow());
+        policy
+    }
+}
+
+impl Default for SettingsStore {
+    fn default() -> Self {
+        Self::new(RuntimeSettings::default())
+    }
+}
+
+fn replace_if_changed<T: PartialEq>(sender: &watch::Sender<T>, value: T) -> bool {
+    sender.send_if_modified(|current| {
+        if *current == value {
+            false
+        } else {
+            *current = value;
+            true
+        }
+    })
+}
+
+/// A tab's security context and privacy level, updated from the settings
+/// store when the tab navigates
+#[derive(Debug, Clone)]
+pub struct TabPolicy {
+    security_context: SecurityContext,
+    privacy_level: PrivacyLevel,
+    privacy_updates: watch::Receiver<PrivacyLevel>,
+    fingerprint_updates: watch::Receiver<FingerprintProtectionLevel>,
+}
+
+impl TabPolicy {
+    /// Apply settings changed since the last navigation. Returns whether
+    /// the policy changed.
+    pub fn begin_navigation(&mut self) -> bool {
+        let mut changed = false;
+        if self.privacy_updates.has_changed().unwrap_or(false) {
+            let level = *self.privacy_updates.borrow_and_update();
+            changed |= level != self.privacy_level;
+            self.privacy_level = level;
+        }
+        if self.fingerprint_updates.has_changed().unwrap_or(false) {
+            let level = *self.fingerprint_updates.borrow_and_update();
+            if level != self.security_context.fingerprint_protection().level {
+                self.security_context
+                    .set_fingerprint_protection_level(level);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn security_context(&self) -> &SecurityContext {
+        &self.security_context
+    }
+
+    pub fn privacy_level(&self) -> PrivacyLevel {
+        self.privacy_level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_policy_applies_on_next_navigation() {
+        let store = SettingsStore::default();
+        let mut tab = store.tab_policy(SecurityContext::new(10));
+        assert_eq!(tab.privacy_level(), PrivacyLevel::High);
+        assert!(!tab.begin_navigation());
+
+        assert!(store.update(RuntimeSettings {
+            privacy_level: PrivacyLevel::Maximum,
+            fingerprint_protection: FingerprintProtectionLevel::Maximum,
+            ..store.current()
+        }));
+        // The loaded page keeps its policy until the tab navigates
+        assert_eq!(tab.privacy_level(), PrivacyLevel::High);
+        assert!(tab.begin_navigation());
+        assert_eq!(tab.privacy_level(), PrivacyLevel::Maximum);
+        assert_eq!(
+            tab.security_context().fingerprint_protection().level,
+            FingerprintProtectionLevel::Maximum
+        );
+        assert!(!tab.begin_navigation());
+    }
+
+    #[test]
+    fn test_unchanged_values_do_not_notify() {
+        let store = SettingsStore::default();
+        let dns = store.subscribe_dns_mode();
+        assert!(!store.set_dns_mode(DnsMode::LocalCache));
+        assert!(!dns.has_changed().unwrap());
+        assert!(store.set_dns_mode(DnsMode::DoT("1.1.1.1:853".into())));
+        assert!(dns.has_changed().unwrap());
+        assert!(!store.update(store.current()));
+    }
+}
@@ -0,0 +1,177 @@
+//! Orderly shutdown when the last browser window closes
+//!
+//! Closing the last window tears the browser down in this order:
+//!
+//! 1. No new navigation starts, and loads waiting for a slot are cancelled.
+//! 2. The session is saved.
+//! 3. The tab manager flushes container tabs, then terminates every tab's
+//!    VM, zeroizing its memory, within [`TAB_SHUTDOWN_TIMEOUT`]. A VM that
+//!    does not stop in time is left behind instead of holding up the exit.
+//! 4. Requests still in flight are dropped, closing their connections,
+//!    within [`NETWORK_SHUTDOWN_TIMEOUT`].
+//! 5. The windows close and the app exits.
+//!
+//! The window shows which step is running once shutdown has taken longer
+//! than [`PROGRESS_DELAY`].
+
+use std::time::{Duration, Instant};
+
+use iced::window;
+
+use crate::session::SessionSnapshot;
+
+/// How long shutdown runs before the window shows its progress
+pub const PROGRESS_DELAY: Duration = Duration::from_secs(1);
+
+/// Time for flushing container tabs and terminating every VM
+pub const TAB_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Time for requests in flight to wind down once dropped
+pub const NETWORK_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A step of the shutdown, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownStep {
+    SavingSession,
+    ClosingTabs,
+    ClosingConnections,
+}
+
+impl ShutdownStep {
+    /// Every step, in order
+    pub const ALL: [Self; 3] = [
+        Self::SavingSession,
+        Self::ClosingTabs,
+        Self::ClosingConnections,
+    ];
+
+    /// What the progress indicator says while the step runs
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::SavingSession => "Saving session",
+            Self::ClosingTabs => "Closing tabs",
+            Self::ClosingConnections => "Closing connections",
+        }
+    }
+
+    /// The step after this one, `None` after the last
+    pub fn next(self) -> Option<Self> {
+        let index = Self::ALL.iter().position(|step| *step == self)?;
+        Self::ALL.get(index + 1).copied()
+    }
+}
+
+/// A shutdown in progress
+#[derive(Debug)]
+pub struct Shutdown {
+    /// The last browser window, closed at the end
+    window: window::Id,
+    started: Instant,
+    step: ShutdownStep,
+    /// Every step has finished
+    done: bool,
+    /// Session to save; taken by the step that saves it
+    session: Option<SessionSnapshot>,
+}
+
+impl Shutdown {
+    /// Start shutting down at `now`, saving `session` first
+    pub fn new(window: window::Id, session: SessionSnapshot, now: Instant) -> Self {
+        Self {
+            window,
+            started: now,
+            step: ShutdownStep::SavingSession,
+            done: false,
+            session: Some(session),
+        }
+    }
+
+    /// The last browser window
+    pub fn window(&self) -> window::Id {
+        self.window
+    }
+
+    /// The step running now
+    pub fn step(&self) -> ShutdownStep {
+        self.step
+    }
+
+    /// The session to save, once
+    pub fn take_session(&mut self) -> Option<SessionSnapshot> {
+        self.session.take()
+    }
+
+    /// Move past the `finished` step. Returns the step to run next; `None`
+    /// after the last step, or for a step that is not the one running.
+    pub fn finish(&mut self, finished: ShutdownStep) -> Option<ShutdownStep> {
+        if self.done || finished != self.step {
+            return None;
+        }
+        match finished.next() {
+            Some(next) => self.step = next,
+            None => self.done = true,
+        }
+        finished.next()
+    }
+
+    /// Whether every step has finished, so the app can exit
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Whether shutdown has run long enough to show its progress
+    pub fn show_progress(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) >= PROGRESS_DELAY
+    }
+
+    /// Progress text, such as "Closing tabs… (2 of 3)"
+    pub fn status(&self) -> String {
+        let position = ShutdownStep::ALL
+            .iter()
+            .position(|step| *step == self.step)
+            .unwrap_or_default();
+        format!(
+            "{}… ({} of {})",
+            self.step.label(),
+            position + 1,
+            ShutdownStep::ALL.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_run_in_order_and_progress_shows_after_delay() {
+        let start = Instant::now();
+        let session = SessionSnapshot {
+            saved_at: chrono::Utc::now(),
+            windows: Vec::new(),
+        };
+        let mut shutdown = Shutdown::new(window::Id::MAIN, session, start);
+        assert_eq!(shutdown.step(), ShutdownStep::SavingSession);
+        assert!(shutdown.take_session().is_some());
+        assert!(shutdown.take_session().is_none());
+        assert!(!shutdown.show_progress(start + Duration::from_millis(500)));
+        assert!(shutdown.show_progress(start + PROGRESS_DELAY));
+
+        assert_eq!(
+            shutdown.finish(ShutdownStep::SavingSession),
+            Some(ShutdownStep::ClosingTabs)
+        );
+        assert_eq!(shutdown.status(), "Closing tabs… (2 of 3)");
+        // A late report of a finished step does not skip ahead
+        assert_eq!(shutdown.finish(ShutdownStep::SavingSession), None);
+        assert_eq!(shutdown.step(), ShutdownStep::ClosingTabs);
+        assert!(!shutdown.is_done());
+
+        assert_eq!(
+            shutdown.finish(ShutdownStep::ClosingTabs),
+            Some(ShutdownStep::ClosingConnections)
+        );
+        assert_eq!(shutdown.finish(ShutdownStep::ClosingConnections), None);
+        assert!(shutdown.is_done());
+    }
+}
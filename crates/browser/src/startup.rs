@@ -0,0 +1,184 @@
+//! Startup phases and their timings
+//!
+//! The browser paints its window shell before anything slow. The rest is
+//! done in the background or on first use: the engine (DNS resolver and its
+//! settings files) initializes in a task, the built-in cosmetic filter list
+//! is compiled and extensions are read on blocking threads, the tab VM pool
+//! starts warming after the first frame, and a JS engine is only created when
+//! a page runs scripts.
+//!
+//! Each [`StartupPhase`] is timed from the start of startup and logged. When
+//! `CITADEL_STARTUP_TIMINGS` names a file, the timings are written there as
+//! JSON once every phase is done, for the benchmark harness:
+//!
+//! ```json
+//! { "phases": [ { "phase": "shell", "ms": 12 }, { "phase": "first_frame", "ms": 48 } ] }
+//! ```
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Environment variable naming the file startup timings are written to
+pub const STARTUP_TIMINGS_ENV: &str = "CITADEL_STARTUP_TIMINGS";
+
+/// A step of startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    /// The application state exists and the window can be drawn
+    Shell,
+    /// The window shell was first drawn
+    FirstFrame,
+    /// Settings files were read
+    Profile,
+    /// The built-in cosmetic filter list was compiled
+    CosmeticFilter,
+    /// Extensions were read and checked
+    Extensions,
+    /// The browser engine can load pages
+    Engine,
+}
+
+impl StartupPhase {
+    /// Every phase, in the order they usually finish
+    pub const ALL: [Self; 6] = [
+        Self::Shell,
+        Self::FirstFrame,
+        Self::Profile,
+        Self::CosmeticFilter,
+        Self::Extensions,
+        Self::Engine,
+    ];
+}
+
+/// When each startup phase finished
+#[derive(Debug, Clone)]
+pub struct StartupTimings {
+    started: Instant,
+    phases: Vec<(StartupPhase, Duration)>,
+}
+
+#[derive(Serialize)]
+struct PhaseTiming {
+    phase: StartupPhase,
+    ms: u128,
+}
+
+#[derive(Serialize)]
+struct TimingsReport {
+    phases: Vec<PhaseTiming>,
+}
+
+impl Default for StartupTimings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StartupTimings {
+    /// Start timing now
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record that `phase` finished; only its first finish counts. Returns
+    /// whether this completed startup.
+    pub fn record(&mut self, phase: StartupPhase) -> bool {
+        if self.is_recorded(phase) {
+            return false;
+        }
+        let elapsed = self.started.elapsed();
+        log::info!("⏱️ Startup: {:?} after {} ms", phase, elapsed.as_millis());
+        self.phases.push((phase, elapsed));
+        self.is_complete()
+    }
+
+    /// Whether `phase` finished
+    pub fn is_recorded(&self, phase: StartupPhase) -> bool {
+        self.get(phase).is_some()
+    }
+
+    /// Time from the start of startup until `phase` finished
+    pub fn get(&self, phase: StartupPhase) -> Option<Duration> {
+        self.phases
+            .iter()
+            .find(|(recorded, _)| *recorded == phase)
+            .map(|(_, elapsed)| *elapsed)
+    }
+
+    /// Whether every phase finished
+    pub fn is_complete(&self) -> bool {
+        StartupPhase::ALL
+            .iter()
+            .all(|phase| self.is_recorded(*phase))
+    }
+
+    /// The timings as JSON, in the order phases finished
+    pub fn to_json(&self) -> String {
+        let report = TimingsReport {
+            phases: self
+                .phases
+                .iter()
+                .map(|(phase, elapsed)| PhaseTiming {
+                    phase: *phase,
+                    ms: elapsed.as_millis(),
+                })
+                .collect(),
+        };
+        serde_json::to_string(&report).unwrap_or_default()
+    }
+
+    /// Write the timings to `path`
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Write the timings where `CITADEL_STARTUP_TIMINGS` says, if it is set
+    pub fn report(&self) {
+        let Some(path) = std::env::var_os(STARTUP_TIMINGS_ENV) else {
+            return;
+        };
+        let path = Path::new(&path);
+        if let Err(e) = self.write(path) {
+            log::warn!(
+                "Failed to write startup timings to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_count_once_and_complete_startup() {
+        let mut timings = StartupTimings::new();
+        assert!(!timings.record(StartupPhase::Shell));
+        let shell = timings.get(StartupPhase::Shell).unwrap();
+        assert!(!timings.record(StartupPhase::Shell));
+        assert_eq!(timings.get(StartupPhase::Shell), Some(shell));
+        assert!(!timings.is_recorded(StartupPhase::Engine));
+
+        let mut completed = false;
+        for phase in StartupPhase::ALL {
+            completed = timings.record(phase);
+        }
+        assert!(completed);
+        assert!(timings.is_complete());
+
+        let json: serde_json::Value = serde_json::from_str(&timings.to_json()).unwrap();
+        let phases = json["phases"].as_array().unwrap();
+        assert_eq!(phases.len(), StartupPhase::ALL.len());
+        assert_eq!(phases[0]["phase"], "shell");
+        assert_eq!(phases[1]["phase"], "first_frame");
+        assert!(phases[0]["ms"].is_u64());
+    }
+}
@@ -0,0 +1,282 @@
+//! Parsed stylesheet cache
+//!
+//! Pages of one site tend to carry the same CSS, yet every navigation used to
+//! parse it again. Parsed sheets are kept here keyed by where the CSS came
+//! from, a SHA-256 of its text and the parser security level it was parsed
+//! at, so a hit is only ever the exact same input parsed the same way. The
+//! cache is a small LRU; a source whose content changes replaces its old
+//! entry, and callers invalidate a source when the resource behind it is
+//! evicted or wiped.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use citadel_parser::{CitadelStylesheet, SecurityLevel};
+use sha2::{Digest, Sha256};
+
+/// Stylesheets kept by default
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// What a parsed stylesheet was parsed from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StylesheetKey {
+    /// URL of the stylesheet; inline `<style>` CSS uses the page's origin
+    pub source: String,
+    /// SHA-256 of the CSS text
+    pub content_hash: [u8; 32],
+    pub security_level: SecurityLevel,
+}
+
+impl StylesheetKey {
+    pub fn new(source: &str, css: &str, security_level: SecurityLevel) -> Self {
+        Self {
+            source: source.to_string(),
+            content_hash: Sha256::digest(css.as_bytes()).into(),
+            security_level,
+        }
+    }
+}
+
+/// Hit and miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StylesheetCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room
+    pub evictions: u64,
+    /// Entries dropped because their source changed or was invalidated
+    pub invalidations: u64,
+    pub entries: usize,
+}
+
+impl StylesheetCacheStats {
+    /// Share of lookups served from the cache, in percent
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64 * 100.0
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    sheet: Arc<CitadelStylesheet>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<StylesheetKey, Entry>,
+    clock: u64,
+    stats: StylesheetCacheStats,
+}
+
+/// LRU cache of parsed stylesheets, shared by clones
+#[derive(Debug, Clone)]
+pub struct StylesheetCache {
+    inner: Arc<Mutex<Inner>>,
+    capacity: usize,
+}
+
+impl Default for StylesheetCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl StylesheetCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner::default())),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// The parsed sheet for `css` from `source`, parsing it with `parse` on a
+    /// miss. Parse errors are returned and nothing is cached.
+    pub fn get_or_parse<E>(
+        &self,
+        source: &str,
+        css: &str,
+        security_level: SecurityLevel,
+        parse: impl FnOnce(&str) -> Result<CitadelStylesheet, E>,
+    ) -> Result<Arc<CitadelStylesheet>, E> {
+        let key = StylesheetKey::new(source, css, security_level);
+        if let Some(sheet) = self.lookup(&key) {
+            return Ok(sheet);
+        }
+        // Parse without holding the lock; a racing parse of the same sheet
+        // only costs the duplicate work
+        let sheet = Arc::new(parse(css)?);
+        self.insert(key, sheet.clone());
+        Ok(sheet)
+    }
+
+    /// Drop every sheet parsed from `source`
+    pub fn invalidate(&self, source: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let before = inner.entries.len();
+            inner.entries.retain(|key, _| key.source != source);
+            let dropped = before - inner.entries.len();
+            inner.stats.invalidations += dropped as u64;
+        }
+    }
+
+    /// Drop every sheet
+    pub fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let dropped = inner.entries.len();
+            inner.entries.clear();
+            inner.stats.invalidations += dropped as u64;
+        }
+    }
+
+    pub fn stats(&self) -> StylesheetCacheStats {
+        self.inner
+            .lock()
+            .map(|inner| StylesheetCacheStats {
+                entries: inner.entries.len(),
+                ..inner.stats
+            })
+            .unwrap_or_default()
+    }
+
+    fn lookup(&self, key: &StylesheetKey) -> Option<Arc<CitadelStylesheet>> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.clock += 1;
+        let now = inner.clock;
+        match inner.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = now;
+                let sheet = entry.sheet.clone();
+                inner.stats.hits += 1;
+                Some(sheet)
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: StylesheetKey, sheet: Arc<CitadelStylesheet>) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        // A source holds one version at a time: new content makes the old
+        // parse unreachable
+        let before = inner.entries.len();
+        inner.entries.retain(|other, _| {
+            other.source != key.source || other.security_level != key.security_level
+        });
+        let replaced = before - inner.entries.len();
+        inner.stats.invalidations += replaced as u64;
+
+        while inner.entries.len() >= self.capacity {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+            inner.stats.evictions += 1;
+        }
+
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.entries.insert(key, Entry { sheet, last_used });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::security::SecurityContext;
+
+    fn parse(css: &str) -> Result<CitadelStylesheet, String> {
+        citadel_parser::parse_css(css, Arc::new(SecurityContext::new(10)))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_hits_only_for_identical_input() {
+        let cache = StylesheetCache::default();
+        let origin = "https://example.com";
+        let first = cache
+            .get_or_parse(origin, "p { color: red; }", SecurityLevel::Balanced, parse)
+            .unwrap();
+        let again = cache
+            .get_or_parse(origin, "p { color: red; }", SecurityLevel::Balanced, |_| {
+                Err("should not reparse".to_string())
+            })
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        let stricter = cache
+            .get_or_parse(origin, "p { color: red; }", SecurityLevel::Maximum, parse)
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &stricter));
+
+        // New content for the source replaces the old parse
+        cache
+            .get_or_parse(origin, "p { color: blue; }", SecurityLevel::Balanced, parse)
+            .unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 3));
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.invalidations, 1);
+
+        cache.invalidate(origin);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = StylesheetCache::new(2);
+        let level = SecurityLevel::Balanced;
+        cache
+            .get_or_parse("https://a.test", "a {}", level, parse)
+            .unwrap();
+        cache
+            .get_or_parse("https://b.test", "b {}", level, parse)
+            .unwrap();
+        cache
+            .get_or_parse("https://a.test", "a {}", level, parse)
+            .unwrap();
+        cache
+            .get_or_parse("https://c.test", "c {}", level, parse)
+            .unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entries, 2);
+        cache
+            .get_or_parse("https://a.test", "a {}", level, |_| {
+                Err("evicted".to_string())
+            })
+            .unwrap();
+        assert!(cache
+            .get_or_parse("https://b.test", "b {}", level, |_| Err(
+                "evicted".to_string()
+            ))
+            .is_err());
+        assert!(cache.stats().hit_rate() > 0.0);
+    }
+
+    #[test]
+    fn test_parse_errors_are_not_cached() {
+        let cache = StylesheetCache::default();
+        let failed: Result<_, String> =
+            cache.get_or_parse("https://a.test", "x", SecurityLevel::High, |_| {
+                Err("bad".into())
+            });
+        assert!(failed.is_err());
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
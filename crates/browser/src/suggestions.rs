@@ -0,0 +1,426 @@
+//! Private omnibox suggestions
+//!
+//! Suggestions are computed only from data already on this machine: bookmarks
+//! and the history of container tabs, ranked by frecency (visit count weighted
+//! by how recently the visits happened). Nothing typed into the address bar is
+//! sent to a suggestion endpoint, and ephemeral tabs never contribute history.
+//! Sources plug in through [`SuggestionProvider`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use citadel_tabs::TabType;
+
+/// Most suggestions produced for a single keystroke
+pub const MAX_SUGGESTIONS_PER_KEYSTROKE: usize = 8;
+
+/// Most history entries kept; the lowest-frecency entries are evicted first
+const MAX_HISTORY_ENTRIES: usize = 5_000;
+
+/// Frecency bonus for bookmarked pages
+const BOOKMARK_BONUS: f64 = 150.0;
+
+/// Where a suggestion came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionSource {
+    History,
+    Bookmark,
+}
+
+/// One omnibox suggestion
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Page URL
+    pub url: String,
+    /// Page title (may be empty)
+    pub title: String,
+    /// Ranking score; higher is better
+    pub score: f64,
+    /// Provider that produced it
+    pub source: SuggestionSource,
+}
+
+/// A local source of omnibox suggestions
+pub trait SuggestionProvider: Send + Sync {
+    /// Short name for logging
+    fn name(&self) -> &'static str;
+
+    /// Up to `limit` suggestions matching `input`, best first
+    fn suggest(&self, input: &str, limit: usize) -> Vec<Suggestion>;
+}
+
+/// Whether every whitespace-separated term of `input` occurs in the URL or
+/// title (case-insensitive)
+pub(crate) fn matches(input: &str, url: &str, title: &str) -> bool {
+    let url = url.to_lowercase();
+    let title = title.to_lowercase();
+    input
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .all(|term| url.contains(&term) || title.contains(&term))
+}
+
+/// Boost for input that starts the host (typing "exa" for example.com)
+pub(crate) fn prefix_boost(input: &str, url: &str) -> f64 {
+    let host = url::Url::parse(url).ok().and_then(|u| {
+        u.host_str()
+            .map(|h| h.trim_start_matches("www.").to_string())
+    });
+    match host {
+        Some(host) if host.starts_with(&input.trim().to_lowercase()) => 2.0,
+        _ => 1.0,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    title: String,
+    visits: Vec<DateTime<Utc>>,
+}
+
+impl HistoryEntry {
+    fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        frecency(self.visits.len(), &self.visits, now)
+    }
+}
+
+/// Most recent visits considered when scoring
+const SAMPLED_VISITS: usize = 10;
+
+/// `visit_count` weighted by the recency of the latest of `visits` (oldest
+/// first), in the style of Firefox's frecency
+pub(crate) fn frecency(visit_count: usize, visits: &[DateTime<Utc>], now: DateTime<Utc>) -> f64 {
+    let sampled = visits.iter().rev().take(SAMPLED_VISITS);
+    let weight: f64 = sampled
+        .clone()
+        .map(|visit| recency_weight(now - *visit))
+        .sum();
+    let sample_len = sampled.count().max(1) as f64;
+    visit_count as f64 * weight / sample_len
+}
+
+fn recency_weight(age: Duration) -> f64 {
+    match age.num_days() {
+        d if d < 4 => 100.0,
+        d if d < 14 => 70.0,
+        d if d < 31 => 50.0,
+        d if d < 90 => 30.0,
+        _ => 10.0,
+    }
+}
+
+/// Browsing history of container tabs, kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct LocalHistory {
+    entries: Arc<RwLock<HashMap<String, HistoryEntry>>>,
+}
+
+impl LocalHistory {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a visit. Visits from ephemeral tabs are dropped.
+    pub fn record_visit(&self, url: &str, title: &str, tab_type: TabType) {
+        self.record_visit_at(url, title, tab_type, Utc::now());
+    }
+
+    fn record_visit_at(&self, url: &str, title: &str, tab_type: TabType, at: DateTime<Utc>) {
+        if tab_type == TabType::Ephemeral || url.is_empty() || url == "about:blank" {
+            return;
+        }
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        let entry = entries
+            .entry(url.to_string())
+            .or_insert_with(|| HistoryEntry {
+                title: String::new(),
+                visits: Vec::new(),
+            });
+        if !title.is_empty() {
+            entry.title = title.to_string();
+        }
+        entry.visits.push(at);
+
+        if entries.len() > MAX_HISTORY_ENTRIES {
+            let now = Utc::now();
+            if let Some(evict) = entries
+                .iter()
+                .min_by(|a, b| a.1.frecency(now).total_cmp(&b.1.frecency(now)))
+                .map(|(url, _)| url.clone())
+            {
+                entries.remove(&evict);
+            }
+        }
+    }
+
+    /// Forget every visit to a URL
+    pub fn remove(&self, url: &str) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.remove(url);
+        }
+    }
+
+    /// Forget all history
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+    }
+
+    /// Number of distinct URLs recorded
+    pub fn len(&self) -> usize {
+        self.entries.read().map(|e| e.len()).unwrap_or(0)
+    }
+
+    /// Whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SuggestionProvider for LocalHistory {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn suggest(&self, input: &str, limit: usize) -> Vec<Suggestion> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let mut suggestions: Vec<Suggestion> = entries
+            .iter()
+            .filter(|(url, entry)| matches(input, url, &entry.title))
+            .map(|(url, entry)| Suggestion {
+                url: url.clone(),
+                title: entry.title.clone(),
+                score: entry.frecency(now) * prefix_boost(input, url),
+                source: SuggestionSource::History,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// Bookmarked pages, kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    entries: Arc<RwLock<Vec<(String, String)>>>,
+}
+
+impl Bookmarks {
+    /// Create an empty bookmark list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bookmark a page, updating the title if it is already bookmarked
+    pub fn add(&self, url: &str, title: &str) {
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        match entries.iter_mut().find(|(u, _)| u == url) {
+            Some(entry) => entry.1 = title.to_string(),
+            None => entries.push((url.to_string(), title.to_string())),
+        }
+    }
+
+    /// Remove a bookmark
+    pub fn remove(&self, url: &str) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.retain(|(u, _)| u != url);
+        }
+    }
+
+    /// Whether a page is bookmarked
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries
+            .read()
+            .map(|entries| entries.iter().any(|(u, _)| u == url))
+            .unwrap_or(false)
+    }
+}
+
+impl SuggestionProvider for Bookmarks {
+    fn name(&self) -> &'static str {
+        "bookmarks"
+    }
+
+    fn suggest(&self, input: &str, limit: usize) -> Vec<Suggestion> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        let mut suggestions: Vec<Suggestion> = entries
+            .iter()
+            .filter(|(url, title)| matches(input, url, title))
+            .map(|(url, title)| Suggestion {
+                url: url.clone(),
+                title: title.clone(),
+                score: BOOKMARK_BONUS * prefix_boost(input, url),
+                source: SuggestionSource::Bookmark,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// Merges suggestions from every provider
+pub struct SuggestionEngine {
+    providers: Vec<Box<dyn SuggestionProvider>>,
+    max_per_keystroke: usize,
+}
+
+impl SuggestionEngine {
+    /// Engine with no providers and the default per-keystroke cap
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            max_per_keystroke: MAX_SUGGESTIONS_PER_KEYSTROKE,
+        }
+    }
+
+    /// Engine over local history and bookmarks
+    pub fn local(history: impl SuggestionProvider + 'static, bookmarks: Bookmarks) -> Self {
+        let mut engine = Self::new();
+        engine.add_provider(Box::new(history));
+        engine.add_provider(Box::new(bookmarks));
+        engine
+    }
+
+    /// Plug in another provider
+    pub fn add_provider(&mut self, provider: Box<dyn SuggestionProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// Change how many suggestions one keystroke may produce
+    pub fn set_max_per_keystroke(&mut self, max: usize) {
+        self.max_per_keystroke = max;
+    }
+
+    /// Ranked suggestions for the current omnibox input. A URL offered by
+    /// several providers appears once, with the scores added.
+    pub fn suggest(&self, input: &str) -> Vec<Suggestion> {
+        if input.trim().is_empty() || self.max_per_keystroke == 0 {
+            return Vec::new();
+        }
+
+        let mut merged: Vec<Suggestion> = Vec::new();
+        for provider in &self.providers {
+            for suggestion in provider.suggest(input, self.max_per_keystroke) {
+                match merged.iter_mut().find(|s| s.url == suggestion.url) {
+                    Some(existing) => {
+                        existing.score += suggestion.score;
+                        if existing.title.is_empty() {
+                            existing.title = suggestion.title;
+                        }
+                        if suggestion.source == SuggestionSource::Bookmark {
+                            existing.source = SuggestionSource::Bookmark;
+                        }
+                    }
+                    None => merged.push(suggestion),
+                }
+            }
+        }
+
+        merged.sort_by(|a, b| b.score.total_cmp(&a.score));
+        merged.truncate(self.max_per_keystroke);
+        merged
+    }
+}
+
+impl Default for SuggestionEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn container() -> TabType {
+        TabType::Container {
+            container_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_ephemeral_history_is_excluded() {
+        let history = LocalHistory::new();
+        history.record_visit("https://private.test/", "Private", TabType::Ephemeral);
+        history.record_visit("https://kept.test/", "Kept", container());
+
+        assert_eq!(history.len(), 1);
+        assert!(history.suggest("private", 8).is_empty());
+        assert_eq!(history.suggest("kept", 8)[0].url, "https://kept.test/");
+    }
+
+    #[test]
+    fn test_frecency_prefers_frequent_and_recent_visits() {
+        let history = LocalHistory::new();
+        let now = Utc::now();
+        let tab = container();
+        for _ in 0..3 {
+            history.record_visit_at("https://docs.rs/a", "a", tab, now);
+        }
+        history.record_visit_at("https://docs.rs/b", "b", tab, now);
+        for _ in 0..3 {
+            history.record_visit_at("https://docs.rs/old", "old", tab, now - Duration::days(200));
+        }
+
+        let urls: Vec<_> = history
+            .suggest("docs", 8)
+            .into_iter()
+            .map(|s| s.url)
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://docs.rs/a",
+                "https://docs.rs/b",
+                "https://docs.rs/old"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_engine_merges_providers_and_caps_results() {
+        let history = LocalHistory::new();
+        let bookmarks = Bookmarks::new();
+        let tab = container();
+        for i in 0..20 {
+            history.record_visit(&format!("https://example.com/{}", i), "", tab);
+        }
+        history.record_visit("https://rust-lang.org/", "Rust", tab);
+        bookmarks.add("https://rust-lang.org/", "Rust Programming Language");
+        bookmarks.add("https://crates.io/", "crates.io");
+
+        let mut engine = SuggestionEngine::local(history, bookmarks);
+        assert_eq!(
+            engine.suggest("example").len(),
+            MAX_SUGGESTIONS_PER_KEYSTROKE
+        );
+        assert!(engine.suggest("   ").is_empty());
+
+        let rust = engine.suggest("rust");
+        assert_eq!(rust.len(), 1);
+        assert_eq!(rust[0].source, SuggestionSource::Bookmark);
+        assert_eq!(rust[0].title, "Rust");
+
+        // Typing the start of the host ranks that site first
+        let top = engine.suggest("crates");
+        assert_eq!(top[0].url, "https://crates.io/");
+
+        engine.set_max_per_keystroke(2);
+        assert_eq!(engine.suggest("example").len(), 2);
+    }
+}
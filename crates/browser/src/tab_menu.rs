@@ -0,0 +1,199 @@
+//! Tab context menu
+//!
+//! Right-clicking a tab in the strip opens a menu of what can be done with
+//! it. Most entries are about privacy: opening the page again with fresh
+//! state in an ephemeral tab or a container of its own, forgetting what the
+//! browser keeps about the site, terminating an idle tab's VM early, and
+//! copying the address without its tracking parameters. Entries that do not
+//! apply to the tab (hibernating the tab in front, reopening an ephemeral
+//! tab as ephemeral) are left out rather than shown disabled.
+
+use citadel_networking::url_canon::strip_tracking_params;
+use citadel_tabs::{PageContent, TabState, TabType};
+use url::Url;
+
+/// Something done to a tab from its context menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TabAction {
+    /// Open the page in a new tab of the same type, in the same container
+    Duplicate,
+    /// Open the page in a new ephemeral tab in place of this one
+    ReopenEphemeral,
+    /// Open the page in a new container of its own in place of this tab
+    ReopenInContainer,
+    /// Put the tab in the named group; `None` takes it out of its group
+    MoveToGroup(Option<String>),
+    /// Terminate the tab's VM until the tab is reopened
+    Hibernate,
+    /// Forget the site's cookies, TLS session tickets and cached responses
+    ClearSiteData,
+    /// Copy the page's URL without tracking parameters
+    CopyCleanUrl,
+}
+
+/// One entry of the menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabMenuEntry {
+    pub label: String,
+    pub action: TabAction,
+}
+
+impl TabMenuEntry {
+    fn new(label: impl Into<String>, action: TabAction) -> Self {
+        Self {
+            label: label.into(),
+            action,
+        }
+    }
+}
+
+/// The entries for `tab`, given the groups tabs are in now
+pub fn entries(tab: &TabState, groups: &[String]) -> Vec<TabMenuEntry> {
+    let web_page = is_web_url(&tab.url);
+    let mut entries = Vec::new();
+
+    if web_page {
+        entries.push(TabMenuEntry::new("Duplicate", TabAction::Duplicate));
+        if matches!(tab.tab_type, TabType::Container { .. }) {
+            entries.push(TabMenuEntry::new(
+                "Reopen as ephemeral",
+                TabAction::ReopenEphemeral,
+            ));
+        }
+        entries.push(TabMenuEntry::new(
+            "Reopen in new container",
+            TabAction::ReopenInContainer,
+        ));
+    }
+
+    for group in groups {
+        if tab.group.as_ref() != Some(group) {
+            entries.push(TabMenuEntry::new(
+                format!("Move to {}", group),
+                TabAction::MoveToGroup(Some(group.clone())),
+            ));
+        }
+    }
+    entries.push(TabMenuEntry::new(
+        "Move to new group",
+        TabAction::MoveToGroup(Some(new_group_name(groups))),
+    ));
+    if tab.group.is_some() {
+        entries.push(TabMenuEntry::new(
+            "Remove from group",
+            TabAction::MoveToGroup(None),
+        ));
+    }
+
+    if !tab.is_active && !matches!(tab.content, PageContent::Expired { .. }) {
+        entries.push(TabMenuEntry::new("Hibernate now", TabAction::Hibernate));
+    }
+    if web_page {
+        entries.push(TabMenuEntry::new(
+            "Clear this site's data",
+            TabAction::ClearSiteData,
+        ));
+        entries.push(TabMenuEntry::new("Copy clean URL", TabAction::CopyCleanUrl));
+    }
+    entries
+}
+
+/// Names of the groups `tabs` are in, sorted
+pub fn groups(tabs: &[TabState]) -> Vec<String> {
+    let mut groups: Vec<String> = tabs.iter().filter_map(|tab| tab.group.clone()).collect();
+    groups.sort();
+    groups.dedup();
+    groups
+}
+
+/// The first "Group N" not taken yet
+pub fn new_group_name(groups: &[String]) -> String {
+    (1..)
+        .map(|n| format!("Group {}", n))
+        .find(|name| !groups.contains(name))
+        .unwrap_or_default()
+}
+
+/// `url` without tracking parameters, whatever the clipboard settings say
+pub fn clean_url(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    strip_tracking_params(&mut url);
+    Some(url.into())
+}
+
+fn is_web_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn tab(url: &str, tab_type: TabType, is_active: bool, group: Option<&str>) -> TabState {
+        TabState {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            url: url.to_string(),
+            tab_type,
+            is_active,
+            created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: group.map(str::to_string),
+            content: PageContent::Empty,
+        }
+    }
+
+    fn actions(entries: &[TabMenuEntry]) -> Vec<&TabAction> {
+        entries.iter().map(|entry| &entry.action).collect()
+    }
+
+    #[test]
+    fn test_entries_fit_the_tab() {
+        let groups = vec!["Group 1".to_string(), "Work".to_string()];
+
+        let active = tab("https://example.com/", TabType::Ephemeral, true, None);
+        let found = entries(&active, &groups);
+        assert!(!actions(&found).contains(&&TabAction::Hibernate));
+        assert!(!actions(&found).contains(&&TabAction::ReopenEphemeral));
+        assert!(actions(&found).contains(&&TabAction::MoveToGroup(Some("Group 2".to_string()))));
+
+        let container = TabType::Container {
+            container_id: Uuid::new_v4(),
+        };
+        let grouped = tab("https://example.com/", container, false, Some("Work"));
+        let found = entries(&grouped, &groups);
+        let found = actions(&found);
+        assert!(found.contains(&&TabAction::Hibernate));
+        assert!(found.contains(&&TabAction::ReopenEphemeral));
+        assert!(found.contains(&&TabAction::MoveToGroup(None)));
+        assert!(!found.contains(&&TabAction::MoveToGroup(Some("Work".to_string()))));
+
+        let internal = tab("citadel://settings", TabType::Ephemeral, false, None);
+        let found = entries(&internal, &[]);
+        assert!(!actions(&found).contains(&&TabAction::ClearSiteData));
+        assert!(!actions(&found).contains(&&TabAction::Duplicate));
+    }
+
+    #[test]
+    fn test_clean_url_and_groups() {
+        assert_eq!(
+            clean_url("https://shop.example/item?id=7&utm_source=mail&fbclid=abc").as_deref(),
+            Some("https://shop.example/item?id=7")
+        );
+        let tabs = [
+            tab("https://a.example/", TabType::Ephemeral, true, Some("Work")),
+            tab("https://b.example/", TabType::Ephemeral, false, None),
+            tab(
+                "https://c.example/",
+                TabType::Ephemeral,
+                false,
+                Some("Work"),
+            ),
+        ];
+        assert_eq!(groups(&tabs), ["Work"]);
+        assert_eq!(new_group_name(&groups(&tabs)), "Group 1");
+    }
+}
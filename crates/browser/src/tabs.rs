@@ -2,4 +2,6 @@
 //! using the full ZKVM-based tab manager for proper isolation.
 
 // Re-export ZKVM tab types for proper security isolation
-pub use citadel_tabs::{SendSafeTabManager as TabManager, Tab, TabState, TabType};
+#[cfg(feature = "zkvm-isolation")]
+pub use citadel_tabs::Tab;
+pub use citadel_tabs::{SendSafeTabManager as TabManager, TabState, TabType};
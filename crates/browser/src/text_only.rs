@@ -0,0 +1,107 @@
+//! Text-only browsing
+//!
+//! With text-only mode on, a page is shown as its sanitized text, headings
+//! and links, painted by the renderer's fast path from
+//! [`citadel_parser::Dom::text_blocks`]. The page's styles are not
+//! extracted and nothing it links to (stylesheets, images, fonts) is
+//! fetched, which suits slow networks and leaves a site the least to learn.
+//! Requests are sent with the same headers as in normal mode, so a server
+//! cannot tell the mode apart.
+//!
+//! The mode is on or off for every tab, and each tab can be switched on its
+//! own; switching every tab clears the tabs' own choices.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+/// Which tabs browse text-only, shared by the engine's clones
+#[derive(Debug, Clone, Default)]
+pub struct TextOnlyMode {
+    state: Arc<Mutex<TextOnlyState>>,
+}
+
+#[derive(Debug, Default)]
+struct TextOnlyState {
+    /// Whether tabs without a choice of their own browse text-only
+    global: bool,
+    /// Tabs switched apart from the global setting
+    tabs: HashMap<Uuid, bool>,
+}
+
+impl TextOnlyMode {
+    /// Whether a tab browses text-only
+    pub fn is_enabled(&self, tab_id: Uuid) -> bool {
+        self.state
+            .lock()
+            .is_ok_and(|state| state.tabs.get(&tab_id).copied().unwrap_or(state.global))
+    }
+
+    /// Whether every tab browses text-only unless switched on its own
+    pub fn is_global(&self) -> bool {
+        self.state.lock().is_ok_and(|state| state.global)
+    }
+
+    /// Switch a tab; returns whether it now browses text-only
+    pub fn toggle_tab(&self, tab_id: Uuid) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let enabled = !state.tabs.get(&tab_id).copied().unwrap_or(state.global);
+        state.tabs.insert(tab_id, enabled);
+        enabled
+    }
+
+    /// Switch every tab, dropping their own choices; returns whether tabs
+    /// now browse text-only
+    pub fn toggle_global(&self) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        state.global = !state.global;
+        state.tabs.clear();
+        state.global
+    }
+
+    /// Forget a closed tab's choice
+    pub fn remove_tab(&self, tab_id: Uuid) {
+        if let Ok(mut state) = self.state.lock() {
+            state.tabs.remove(&tab_id);
+        }
+    }
+
+    /// Forget every tab's choice; the global setting stays
+    pub fn clear_tabs(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.tabs.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_choice_overrides_global_until_global_switches() {
+        let mode = TextOnlyMode::default();
+        let (tab, other) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(!mode.is_enabled(tab));
+
+        assert!(mode.toggle_tab(tab));
+        assert!(mode.is_enabled(tab));
+        assert!(!mode.is_enabled(other));
+
+        assert!(mode.toggle_global());
+        assert!(mode.is_enabled(other));
+        assert!(!mode.toggle_tab(other));
+        assert!(!mode.is_enabled(other));
+        assert!(mode.clone().is_enabled(tab));
+
+        mode.remove_tab(other);
+        assert!(mode.is_enabled(other));
+        assert!(!mode.toggle_global());
+        assert!(!mode.is_enabled(tab));
+    }
+}
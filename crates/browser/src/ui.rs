@@ -1,19 +1,37 @@
-use crate::app::{Message, ScrollState, ViewportInfo, ZoomLevel};
+use crate::app::{
+    Message, ProfilePrompt, ProfilePromptMode, ScrollState, TabSwitcher, ViewportInfo, ZoomLevel,
+};
+use crate::clipboard::CopyKind;
+use crate::content_budget::ContentTruncation;
+use crate::downloads::{Download, DownloadState};
+use crate::page_escalation::PageEscalation;
+use crate::page_menu::PageMenuEntry;
 use crate::renderer::CitadelRenderer;
-use citadel_networking::{NetworkConfig, PrivacyLevel};
+use crate::suggestions::{Suggestion, SuggestionSource};
+use crate::tab_menu;
+use crate::windows::DetachedMode;
+use citadel_networking::{
+    BlocklistStats, BudgetUsage, HeaderStatus, LoadErrorCategory, NetworkConfig, PrivacyLevel,
+    SecurityGrade, SecurityHeaderReport,
+};
+use citadel_parser::{LanguageHints, StylesheetDiagnostics};
 use citadel_security::{PrivacyEvent, PrivacyStats};
-use citadel_tabs::SendSafeTabManager as TabManager;
+use citadel_tabs::{DisplayKind, RenderedContent, SendSafeTabManager as TabManager, TabState};
 use iced::{
     theme,
     widget::container::{Appearance, StyleSheet},
-    widget::{button, container, scrollable, text, text_input, Column, Row, Space},
-    Alignment, Background, Color, Element, Length,
+    widget::{
+        button, checkbox, container, mouse_area, progress_bar, scrollable, text, text_input,
+        Column, Row, Space,
+    },
+    window, Alignment, Background, Color, Element, Length,
 };
 use std::sync::Arc;
+use url::Url;
 
 /// Custom style for the info bar
 #[derive(Clone, Copy, Debug)]
-struct InfoBarStyle;
+pub(crate) struct InfoBarStyle;
 
 impl StyleSheet for InfoBarStyle {
     type Style = iced::Theme;
@@ -98,6 +116,45 @@ impl StyleSheet for PrivacyStatRowStyle {
     }
 }
 
+/// The window being drawn and its tab strip
+#[derive(Debug, Clone, Copy)]
+pub struct WindowView<'a> {
+    /// Window identifier
+    pub id: window::Id,
+    /// Tabs in this window's strip
+    pub tabs: &'a [uuid::Uuid],
+    /// Tab selected in this window
+    pub active_tab: Option<uuid::Uuid>,
+    /// Whether this window has focus; only the focused window paints its page
+    pub focused: bool,
+    /// Tab currently picked up for moving between windows
+    pub dragged_tab: Option<uuid::Uuid>,
+    /// Whether the selected tab's page is bookmarked
+    pub bookmarked: bool,
+    /// Whether overlay cleanup is on for the selected tab's site
+    pub overlay_cleanup: bool,
+    /// Whether the selected tab browses text-only
+    pub text_only: bool,
+    /// Whether every tab browses text-only unless switched on its own
+    pub text_only_global: bool,
+    /// Whether the browser runs the power-saving profile (on battery)
+    pub power_saving: bool,
+    /// Origin of the web app this window shows, for app windows
+    pub app_origin: Option<&'a url::Origin>,
+    /// Destination preview of the link under the pointer
+    pub hovered_link: Option<&'a str>,
+    /// Why the selected tab's last load failed, when it did
+    pub load_failure: Option<LoadErrorCategory>,
+    /// Content budgets the selected tab's page ran over
+    pub truncation: ContentTruncation,
+    /// How far the browser escalated against the selected tab's site
+    pub escalation: PageEscalation,
+    /// Tabs whose load waits for a slot, with their place in line
+    pub load_queue: &'a [(uuid::Uuid, usize)],
+    /// Tab whose context menu is open
+    pub tab_menu: Option<uuid::Uuid>,
+}
+
 /// Main UI state and components
 #[derive(Debug, Clone)]
 pub struct CitadelUI {
@@ -105,6 +162,8 @@ pub struct CitadelUI {
     address_bar_value: String,
     /// Whether the address bar is focused
     address_bar_focused: bool,
+    /// Local suggestions for the current address bar input
+    suggestions: Vec<Suggestion>,
 }
 
 /// Messages specific to the UI layer
@@ -134,9 +193,15 @@ impl CitadelUI {
         Self {
             address_bar_value: String::new(),
             address_bar_focused: false,
+            suggestions: Vec::new(),
         }
     }
 
+    /// Replace the omnibox suggestions shown under the address bar
+    pub fn set_suggestions(&mut self, suggestions: Vec<Suggestion>) {
+        self.suggestions = suggestions;
+    }
+
     /// Get the current address bar value
     pub fn address_bar_value(&self) -> &str {
         &self.address_bar_value
@@ -181,18 +246,32 @@ impl CitadelUI {
     /// Create the main UI view
     pub fn view<'a>(
         &'a self,
+        window: &WindowView<'_>,
         tab_manager: &Arc<TabManager>,
         network_config: &NetworkConfig,
         renderer: &'a CitadelRenderer,
         viewport_info: &ViewportInfo,
         scroll_state: Option<&ScrollState>,
         privacy_stats: &PrivacyStats,
+        blocklist_stats: &BlocklistStats,
+        site_allowlisted: Option<bool>,
+        budget_usage: Option<&BudgetUsage>,
+        security_headers: Option<&SecurityHeaderReport>,
+        css_diagnostics: Option<&StylesheetDiagnostics>,
         privacy_panel_expanded: bool,
     ) -> Element<'a, Message> {
-        let toolbar = self.create_toolbar(tab_manager, network_config, viewport_info);
+        let toolbar = self.create_toolbar(window, tab_manager, network_config, viewport_info);
         let main_content =
-            self.create_content_area(tab_manager, renderer, viewport_info, scroll_state);
-        let privacy_panel = Self::privacy_scoreboard_view(privacy_stats, privacy_panel_expanded);
+            self.create_content_area(window, tab_manager, renderer, viewport_info, scroll_state);
+        let privacy_panel = Self::privacy_scoreboard_view(
+            privacy_stats,
+            blocklist_stats,
+            site_allowlisted,
+            budget_usage,
+            security_headers,
+            css_diagnostics,
+            privacy_panel_expanded,
+        );
 
         let body = Row::new()
             .push(
@@ -218,7 +297,8 @@ impl CitadelUI {
     /// Create the browser toolbar
     fn create_toolbar(
         &self,
-        _tab_manager: &Arc<TabManager>,
+        window: &WindowView<'_>,
+        tab_manager: &Arc<TabManager>,
         network_config: &NetworkConfig,
         viewport_info: &ViewportInfo,
     ) -> Element<'_, Message> {
@@ -228,35 +308,187 @@ impl CitadelUI {
             .push(button("⟳").padding(8).on_press(Message::RefreshTab))
             .spacing(4);
 
-        let address_bar = text_input("Enter URL...", &self.address_bar_value)
-            .on_input(|value| Message::UI(UIMessage::AddressBarChanged(value)))
-            .on_submit(Message::UI(UIMessage::AddressBarSubmitted))
-            .padding(8)
-            .width(Length::Fill);
-
         let privacy_indicator = self.create_privacy_indicator(network_config);
 
         let zoom_controls = self.create_zoom_controls(viewport_info);
 
+        // App windows show which site they belong to instead of an address bar
+        if let Some(origin) = window.app_origin {
+            let toolbar = Row::new()
+                .push(navigation_buttons)
+                .push(Space::with_width(8))
+                .push(
+                    text(origin.ascii_serialization())
+                        .size(13)
+                        .style(Color::from_rgb(0.7, 0.7, 0.7)),
+                )
+                .push(Space::with_width(Length::Fill))
+                .push(zoom_controls)
+                .push(Space::with_width(8))
+                .push(privacy_indicator)
+                .align_items(Alignment::Center)
+                .padding(8);
+            return container(toolbar).width(Length::Fill).into();
+        }
+
+        // Unfocused windows show their own tab's URL; editing happens in the
+        // focused window, whose address bar state lives here
+        let address_bar = if window.focused {
+            text_input("Enter URL...", &self.address_bar_value)
+                .on_input(|value| Message::UI(UIMessage::AddressBarChanged(value)))
+                .on_submit(Message::UI(UIMessage::AddressBarSubmitted))
+        } else {
+            let url = window
+                .active_tab
+                .and_then(|id| {
+                    tab_manager
+                        .get_tab_states()
+                        .into_iter()
+                        .find(|t| t.id == id)
+                })
+                .map(|tab| citadel_networking::display_url(&tab.url))
+                .unwrap_or_default();
+            text_input("Enter URL...", &url)
+        }
+        .padding(8)
+        .width(Length::Fill);
+
+        // Onion sites are badged so they can't pass for clearnet look-alikes
+        let onion = window.active_tab.is_some_and(|id| {
+            tab_manager.get_tab_states().iter().any(|tab| {
+                tab.id == id
+                    && url::Url::parse(&tab.url)
+                        .is_ok_and(|url| citadel_networking::is_onion_url(&url))
+            })
+        });
+        let address_bar = Row::new()
+            .push_maybe(onion.then(|| {
+                container(
+                    text("🧅 Onion")
+                        .size(12)
+                        .style(Color::from_rgb(0.7, 0.5, 0.9)),
+                )
+                .padding([0, 6])
+            }))
+            .push(address_bar)
+            .align_items(Alignment::Center)
+            .width(Length::Fill);
+
         let new_tab_button = button("+").padding(8).on_press(Message::NewTab {
             tab_type: citadel_tabs::TabType::Ephemeral,
             initial_url: None,
         });
 
+        let new_window_button = button("⧉").padding(8).on_press(Message::NewWindow);
+
+        let detach_buttons = Row::new()
+            .push(
+                button("📖").padding(8).on_press_maybe(
+                    window
+                        .active_tab
+                        .map(|tab| Message::DetachTab(tab, DetachedMode::Reader)),
+                ),
+            )
+            .spacing(4);
+        #[cfg(feature = "media")]
+        let detach_buttons = detach_buttons.push(
+            button("🎞️").padding(8).on_press_maybe(
+                window
+                    .active_tab
+                    .map(|tab| Message::DetachTab(tab, DetachedMode::Media)),
+            ),
+        );
+
+        let bookmark_button = button(if window.bookmarked { "★" } else { "☆" })
+            .padding(8)
+            .on_press_maybe(window.active_tab.map(|_| Message::ToggleBookmark));
+
+        let overlay_button = button(if window.overlay_cleanup {
+            "🧹✓"
+        } else {
+            "🧹"
+        })
+        .padding(8)
+        .on_press_maybe(window.active_tab.map(|_| Message::ToggleOverlayCleanup));
+
+        let text_only_button = button(match (window.text_only, window.text_only_global) {
+            (true, true) => "📄✓✓",
+            (true, false) => "📄✓",
+            (false, _) => "📄",
+        })
+        .padding(8)
+        .on_press_maybe(window.active_tab.map(|_| Message::ToggleTextOnly));
+
+        let install_button = button("📲")
+            .padding(8)
+            .on_press_maybe(window.active_tab.map(|_| Message::InstallWebApp));
+
+        let copy_link_button = button("🔗")
+            .padding(8)
+            .on_press_maybe(window.active_tab.map(|_| Message::CopyPageUrl));
+
         let toolbar = Row::new()
             .push(navigation_buttons)
             .push(Space::with_width(8))
             .push(address_bar)
+            .push(bookmark_button)
+            .push(copy_link_button)
+            .push(overlay_button)
+            .push(text_only_button)
+            .push(install_button)
             .push(Space::with_width(8))
             .push(zoom_controls)
             .push(Space::with_width(8))
+            .push_maybe(
+                window
+                    .power_saving
+                    .then(|| text("🔋").size(14).style(Color::from_rgb(0.7, 0.7, 0.7))),
+            )
             .push(privacy_indicator)
             .push(Space::with_width(8))
             .push(new_tab_button)
+            .push(new_window_button)
+            .push(Space::with_width(8))
+            .push(detach_buttons)
             .align_items(Alignment::Center)
             .padding(8);
 
-        container(toolbar).width(Length::Fill).into()
+        if !window.focused || self.suggestions.is_empty() {
+            return container(toolbar).width(Length::Fill).into();
+        }
+
+        let suggestions =
+            self.suggestions
+                .iter()
+                .fold(Column::new().spacing(2), |column, suggestion| {
+                    let icon = match suggestion.source {
+                        SuggestionSource::Bookmark => "★",
+                        SuggestionSource::History => "🕘",
+                    };
+                    let label = if suggestion.title.is_empty() {
+                        format!("{} {}", icon, suggestion.url)
+                    } else {
+                        format!("{} {} — {}", icon, suggestion.title, suggestion.url)
+                    };
+                    column.push(
+                        button(text(label).size(13))
+                            .padding([4, 8])
+                            .width(Length::Fill)
+                            .style(theme::Button::Text)
+                            .on_press(Message::Navigate(suggestion.url.clone())),
+                    )
+                });
+
+        Column::new()
+            .push(toolbar)
+            .push(
+                container(suggestions)
+                    .padding([0, 8, 8, 8])
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .width(Length::Fill)
+            .into()
     }
 
     /// Create privacy level indicator
@@ -276,64 +508,807 @@ impl CitadelUI {
     /// Create the main content area
     fn create_content_area<'a>(
         &'a self,
+        window: &WindowView<'_>,
         tab_manager: &Arc<TabManager>,
         renderer: &'a CitadelRenderer,
         viewport_info: &ViewportInfo,
         scroll_state: Option<&ScrollState>,
     ) -> Element<'a, Message> {
-        let tabs_bar = self.create_tabs_bar(tab_manager);
-        let page_content =
-            self.create_page_content(tab_manager, renderer, viewport_info, scroll_state);
+        // App windows hold a single tab and have no tab strip
+        let tabs_bar = window
+            .app_origin
+            .is_none()
+            .then(|| self.create_tabs_bar(window, tab_manager));
+        let page_content = if window.focused {
+            self.create_page_content(window, tab_manager, renderer, viewport_info, scroll_state)
+        } else {
+            Self::unfocused_page_placeholder(window, tab_manager)
+        };
+
+        // Only the focused window's page takes the pointer
+        let status_bar = window
+            .hovered_link
+            .filter(|_| window.focused)
+            .map(Self::link_status_bar);
 
         Column::new()
-            .push(tabs_bar)
+            .push_maybe(tabs_bar)
             .push(page_content)
+            .push_maybe(status_bar)
             .spacing(0)
             .into()
     }
 
+    /// Strip above a page that was cut down to its content budgets
+    fn truncation_banner<'a>(banner: String) -> Element<'a, Message> {
+        container(
+            text(format!("✂️ {}", banner))
+                .size(12)
+                .style(Color::from_rgb(1.0, 0.6, 0.0)),
+        )
+        .padding([4, 10])
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    /// Warning bar above a page whose site keeps triggering protections
+    fn escalation_banner<'a>(banner: String) -> Element<'a, Message> {
+        container(
+            text(format!("🚨 {}", banner))
+                .size(12)
+                .style(Color::from_rgb(1.0, 0.3, 0.3)),
+        )
+        .padding([4, 10])
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+    }
+
+    /// Strip under the page showing where the hovered link leads
+    fn link_status_bar<'a>(destination: &str) -> Element<'a, Message> {
+        container(
+            text(destination.to_string())
+                .size(12)
+                .style(Color::from_rgb(0.8, 0.8, 0.8)),
+        )
+        .padding([2, 8])
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+    }
+
     /// Create the tabs bar
-    fn create_tabs_bar(&self, tab_manager: &Arc<TabManager>) -> Element<'_, Message> {
+    fn create_tabs_bar(
+        &self,
+        window: &WindowView<'_>,
+        tab_manager: &Arc<TabManager>,
+    ) -> Element<'_, Message> {
         let tab_states = tab_manager.get_tab_states();
 
         let mut tab_buttons = Row::new().spacing(2);
 
-        for tab_state in tab_states {
+        // Only this window's tabs, in strip order
+        for tab_state in window
+            .tabs
+            .iter()
+            .filter_map(|id| tab_states.iter().find(|t| t.id == *id))
+        {
             let tab_title = if tab_state.title.is_empty() {
                 "New Tab".to_string()
             } else {
                 tab_state.title.clone()
             };
+            let tab_title = match &tab_state.group {
+                Some(group) => format!("[{}] {}", group, tab_title),
+                None => tab_title,
+            };
+            // Per-tab load progress: waiting for a slot, or loading
+            let queued = window
+                .load_queue
+                .iter()
+                .find(|(tab_id, _)| *tab_id == tab_state.id);
+            let tab_title = match (queued, &tab_state.content) {
+                (Some((_, position)), _) => format!("⏳{} {}", position, tab_title),
+                (None, citadel_tabs::PageContent::Loading { .. }) => format!("🔄 {}", tab_title),
+                _ => tab_title,
+            };
+
+            let speaker: Element<'_, Message> = match citadel_tabs::speaker_indicator(tab_state) {
+                Some(icon) => button(icon)
+                    .padding(2)
+                    .style(theme::Button::Text)
+                    .on_press(Message::ToggleTabMute(tab_state.id))
+                    .into(),
+                None => Space::with_width(0).into(),
+            };
 
             let tab_button = button(
                 Row::new()
+                    .push(speaker)
                     .push(text(tab_title).width(Length::Fixed(150.0)))
+                    .push(
+                        button("⇄")
+                            .padding(2)
+                            .on_press(Message::TabDragStarted(tab_state.id)),
+                    )
                     .push(
                         button("×")
                             .padding(2)
                             .on_press(Message::CloseTab(tab_state.id)),
                     )
+                    .spacing(2)
                     .align_items(Alignment::Center),
             )
             .padding(8)
+            .style(if window.active_tab == Some(tab_state.id) {
+                theme::Button::Primary
+            } else {
+                theme::Button::Secondary
+            })
             .on_press(Message::SwitchTab(tab_state.id));
 
-            tab_buttons = tab_buttons.push(tab_button);
+            tab_buttons = tab_buttons
+                .push(mouse_area(tab_button).on_right_press(Message::TabMenuOpened(tab_state.id)));
+        }
+
+        // While a tab is being moved, every window offers a drop target; the
+        // window it came from can send it to a new window or cancel the move
+        if let Some(dragged) = window.dragged_tab {
+            if window.tabs.contains(&dragged) {
+                tab_buttons = tab_buttons
+                    .push(
+                        button("⧉ Move to new window")
+                            .padding(8)
+                            .on_press(Message::TabDroppedInNewWindow),
+                    )
+                    .push(
+                        button("Cancel")
+                            .padding(8)
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::TabDropped(window.id)),
+                    );
+            } else {
+                tab_buttons = tab_buttons.push(
+                    button("⬇ Drop tab here")
+                        .padding(8)
+                        .on_press(Message::TabDropped(window.id)),
+                );
+            }
         }
 
+        let strip = scrollable(tab_buttons).direction(scrollable::Direction::Horizontal(
+            scrollable::Properties::default(),
+        ));
+
+        // The context menu of a tab in this window, under the strip
+        let menu_tab = window
+            .tab_menu
+            .filter(|id| window.tabs.contains(id))
+            .and_then(|id| tab_states.iter().find(|t| t.id == id));
+        let Some(menu_tab) = menu_tab else {
+            return container(strip).width(Length::Fill).padding(4).into();
+        };
+        let groups = tab_menu::groups(&tab_states);
+        let menu = tab_menu::entries(menu_tab, &groups).into_iter().fold(
+            Row::new().spacing(4).align_items(Alignment::Center),
+            |menu, entry| {
+                menu.push(
+                    button(text(entry.label).size(12))
+                        .padding([4, 8])
+                        .style(theme::Button::Secondary)
+                        .on_press(Message::TabMenuAction(menu_tab.id, entry.action)),
+                )
+            },
+        );
+        let menu = menu.push(
+            button(text("✕").size(12))
+                .padding([4, 8])
+                .style(theme::Button::Text)
+                .on_press(Message::TabMenuClosed),
+        );
+
+        container(Column::new().push(strip).push(scrollable(menu).direction(
+            scrollable::Direction::Horizontal(scrollable::Properties::default()),
+        )))
+        .width(Length::Fill)
+        .padding(4)
+        .into()
+    }
+
+    /// Contents of a detached reader or picture-in-picture window. Paints the
+    /// tab's existing ZKVM render; nothing is parsed or rendered again.
+    pub fn detached_view<'a>(
+        mode: DetachedMode,
+        tab: Option<&TabState>,
+        rendered: Option<&RenderedContent>,
+        language: Option<&LanguageHints>,
+    ) -> Element<'a, Message> {
+        let title = tab
+            .map(|tab| {
+                if tab.title.is_empty() {
+                    citadel_networking::display_url(&tab.url)
+                } else {
+                    tab.title.clone()
+                }
+            })
+            .unwrap_or_else(|| "Tab closed".to_string());
+
+        let body: Element<'a, Message> = match (mode, rendered) {
+            (DetachedMode::Reader, Some(content)) => {
+                let mut column = Column::new().spacing(10).padding(16);
+                let unspaced = language.is_some_and(LanguageHints::breaks_without_spaces);
+                for (kind, line) in Self::reader_lines(content, unspaced) {
+                    let (size, color) = match kind {
+                        DisplayKind::Heading => (20, Color::from_rgb(0.95, 0.95, 0.95)),
+                        DisplayKind::Link => (15, Color::from_rgb(0.4, 0.7, 1.0)),
+                        DisplayKind::Paragraph | DisplayKind::Generic => {
+                            (15, Color::from_rgb(0.85, 0.85, 0.85))
+                        }
+                    };
+                    column = column.push(text(line).size(size).style(color));
+                }
+                scrollable(column).height(Length::Fill).into()
+            }
+            (DetachedMode::Reader, None) => text("Nothing rendered in this tab yet")
+                .size(14)
+                .style(Color::from_rgb(0.5, 0.5, 0.5))
+                .into(),
+            #[cfg(feature = "media")]
+            (DetachedMode::Media, _) => Column::new()
+                .push(Space::with_height(40))
+                .push(text("▶").size(48).style(Color::from_rgb(0.6, 0.6, 0.6)))
+                .push(Space::with_height(10))
+                .push(
+                    text("Media placeholder")
+                        .size(14)
+                        .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                )
+                .align_items(Alignment::Center)
+                .width(Length::Fill)
+                .into(),
+        };
+
+        let content = Column::new()
+            .push(
+                container(text(title).size(14))
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(body);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// Put the confirmation for an external-scheme link above a window's view
+    pub fn with_external_protocol_prompt<'a>(
+        page: Element<'a, Message>,
+        url: &Url,
+    ) -> Element<'a, Message> {
+        const MAX_SHOWN_CHARS: usize = 120;
+        let mut shown: String = url.as_str().chars().take(MAX_SHOWN_CHARS).collect();
+        if shown.len() < url.as_str().len() {
+            shown.push('…');
+        }
+
+        let prompt = Row::new()
+            .push(
+                Column::new()
+                    .push(text("Open this link in an external application?").size(14))
+                    .push(text(shown).size(12).style(Color::from_rgb(0.7, 0.7, 0.7)))
+                    .spacing(2)
+                    .width(Length::Fill),
+            )
+            .push(
+                button(text("Open").size(13)).on_press(Message::ConfirmExternalProtocol {
+                    always_allow: false,
+                }),
+            )
+            .push(
+                button(text(format!("Always open {}: links", url.scheme())).size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::ConfirmExternalProtocol { always_allow: true }),
+            )
+            .push(
+                button(text("Cancel").size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::CancelExternalProtocol),
+            )
+            .spacing(8)
+            .align_items(Alignment::Center);
+
+        Column::new()
+            .push(
+                container(prompt)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(page)
+            .into()
+    }
+
+    /// Shown in place of the browser while a slow shutdown finishes
+    pub fn shutdown_view<'a>(status: String) -> Element<'a, Message> {
+        let dim = Color::from_rgb(0.7, 0.7, 0.7);
         container(
-            scrollable(tab_buttons).direction(scrollable::Direction::Horizontal(
-                scrollable::Properties::default(),
-            )),
+            Column::new()
+                .push(text("🛑 Closing Citadel").size(22))
+                .push(text(status).size(14).style(dim))
+                .spacing(12)
+                .align_items(Alignment::Center),
         )
         .width(Length::Fill)
-        .padding(4)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
         .into()
     }
 
+    /// Full-window prompt for the profile passphrase, shown instead of every
+    /// window's content while the profile is locked or being encrypted.
+    /// `keychain` names the OS keychain that may remember the profile key.
+    pub fn profile_prompt_view<'a>(
+        prompt: &'a ProfilePrompt,
+        keychain: Option<&str>,
+    ) -> Element<'a, Message> {
+        let (title, detail, first, second) = match prompt.mode {
+            ProfilePromptMode::Unlock => (
+                "🔐 Your profile is encrypted",
+                "Enter your passphrase to unlock it.",
+                "Passphrase",
+                None,
+            ),
+            ProfilePromptMode::Recover => (
+                "🔐 Recover your profile",
+                "Enter your recovery key and choose a new passphrase.",
+                "Recovery key",
+                Some("New passphrase"),
+            ),
+            ProfilePromptMode::Enable => (
+                "🔐 Encrypt your profile",
+                "Settings and saved sessions will be encrypted under this passphrase.",
+                "Passphrase",
+                Some("Confirm passphrase"),
+            ),
+        };
+        let dim = Color::from_rgb(0.7, 0.7, 0.7);
+
+        let mut first_input = text_input(first, &prompt.secret)
+            .secure(prompt.mode != ProfilePromptMode::Recover)
+            .padding(8);
+        let mut second_input = second.map(|placeholder| {
+            text_input(placeholder, &prompt.second)
+                .secure(true)
+                .padding(8)
+        });
+        if !prompt.busy {
+            first_input = first_input.on_input(Message::ProfileSecretChanged);
+            second_input = second_input.map(|input| {
+                input
+                    .on_input(Message::ProfileSecondChanged)
+                    .on_submit(Message::SubmitProfilePrompt)
+            });
+            if second_input.is_none() {
+                first_input = first_input.on_submit(Message::SubmitProfilePrompt);
+            }
+        }
+
+        let mut form = Column::new()
+            .push(text(title).size(22))
+            .push(text(detail).size(14).style(dim))
+            .push(first_input)
+            .spacing(12)
+            .max_width(420);
+        if let Some(input) = second_input {
+            form = form.push(input);
+        }
+        if let (Some(keychain), ProfilePromptMode::Unlock | ProfilePromptMode::Recover) =
+            (keychain, prompt.mode)
+        {
+            let label = format!("Unlock with {} on this device", keychain);
+            let mut remember = checkbox(label, prompt.remember).size(16).text_size(13);
+            if !prompt.busy {
+                remember = remember.on_toggle(Message::ProfileRememberToggled);
+            }
+            form = form.push(remember);
+        }
+        if let Some(error) = &prompt.error {
+            form = form.push(text(error).size(13).style(Color::from_rgb(1.0, 0.4, 0.4)));
+        }
+
+        let submit_label = match (prompt.busy, prompt.mode) {
+            (true, _) => "Working…",
+            (false, ProfilePromptMode::Enable) => "Encrypt",
+            (false, _) => "Unlock",
+        };
+        let mut buttons = Row::new()
+            .push(
+                button(text(submit_label).size(13))
+                    .on_press_maybe((!prompt.busy).then_some(Message::SubmitProfilePrompt)),
+            )
+            .spacing(8);
+        let alternative = match prompt.mode {
+            ProfilePromptMode::Unlock => Some((
+                "Use recovery key",
+                Message::SetProfilePromptMode(ProfilePromptMode::Recover),
+            )),
+            ProfilePromptMode::Recover => Some((
+                "Use passphrase",
+                Message::SetProfilePromptMode(ProfilePromptMode::Unlock),
+            )),
+            ProfilePromptMode::Enable => Some(("Cancel", Message::CancelProfilePrompt)),
+        };
+        if let Some((label, message)) = alternative {
+            buttons = buttons.push(
+                button(text(label).size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press_maybe((!prompt.busy).then_some(message)),
+            );
+        }
+
+        container(form.push(buttons))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .into()
+    }
+
+    /// Put a newly exported recovery key above a window's view until the
+    /// user confirms they wrote it down
+    pub fn with_recovery_key<'a>(
+        page: Element<'a, Message>,
+        recovery_key: &str,
+    ) -> Element<'a, Message> {
+        let bar = Row::new()
+            .push(
+                Column::new()
+                    .push(
+                        text("Write down your recovery key. It unlocks your profile if you forget your passphrase, and is not shown again.")
+                            .size(14),
+                    )
+                    .push(text(recovery_key.to_string()).size(14).font(iced::Font::MONOSPACE))
+                    .spacing(4)
+                    .width(Length::Fill),
+            )
+            .push(
+                button(text("Copy").size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::Copy(CopyKind::Secret, recovery_key.to_string())),
+            )
+            .push(button(text("I wrote it down").size(13)).on_press(Message::DismissRecoveryKey))
+            .spacing(8)
+            .align_items(Alignment::Center);
+
+        Column::new()
+            .push(
+                container(bar)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(page)
+            .into()
+    }
+
+    /// Downloads bar below the page: one row per download with its progress
+    /// and what can be done with it, and a button clearing finished ones
+    pub fn with_downloads<'a>(
+        page: Element<'a, Message>,
+        downloads: &[Download],
+    ) -> Element<'a, Message> {
+        let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let rows = downloads
+            .iter()
+            .fold(Column::new().spacing(4), |rows, download| {
+                let size = match download.total {
+                    Some(total) => {
+                        format!("{:.1} of {:.1} MiB", mib(download.received), mib(total))
+                    }
+                    None => format!("{:.1} MiB", mib(download.received)),
+                };
+                let status = match &download.state {
+                    DownloadState::Downloading => size,
+                    DownloadState::Paused => format!("Paused, {}", size),
+                    DownloadState::Completed { verified: true } => {
+                        "Done, digest verified".to_string()
+                    }
+                    DownloadState::Completed { verified: false } => "Done".to_string(),
+                    DownloadState::Failed(reason) => format!("Failed: {}", reason),
+                    DownloadState::Cancelled => "Cancelled".to_string(),
+                };
+
+                let mut row = Row::new()
+                    .push(
+                        text(download.file_name.clone())
+                            .size(13)
+                            .width(Length::Fill),
+                    )
+                    .push(
+                        progress_bar(0.0..=1.0, download.fraction().unwrap_or(0.0))
+                            .width(Length::Fixed(120.0))
+                            .height(Length::Fixed(6.0)),
+                    )
+                    .push(text(status).size(12).width(Length::Fixed(220.0)))
+                    .spacing(8)
+                    .align_items(Alignment::Center);
+                if download.state == DownloadState::Downloading {
+                    row = row.push(
+                        button(text("Pause").size(12))
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::PauseDownload(download.id)),
+                    );
+                }
+                if download.can_resume() {
+                    row = row.push(
+                        button(text("Resume").size(12))
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::ResumeDownload(download.id)),
+                    );
+                }
+                if !download.is_finished() {
+                    row = row.push(
+                        button(text("Cancel").size(12))
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::CancelDownload(download.id)),
+                    );
+                }
+                rows.push(row)
+            });
+
+        let bar = Row::new()
+            .push(rows.width(Length::Fill))
+            .push(
+                button(text("Clear").size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::ClearDownloads),
+            )
+            .spacing(8)
+            .align_items(Alignment::Start);
+
+        Column::new()
+            .push(page)
+            .push(
+                container(bar)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .into()
+    }
+
+    /// Page context menu above the page, with the entries for what was
+    /// right-clicked
+    pub fn with_page_menu<'a>(
+        page: Element<'a, Message>,
+        entries: Vec<PageMenuEntry>,
+    ) -> Element<'a, Message> {
+        let menu = entries.into_iter().fold(
+            Row::new().spacing(4).align_items(Alignment::Center),
+            |menu, entry| {
+                menu.push(
+                    button(text(entry.label).size(12))
+                        .padding([4, 8])
+                        .style(theme::Button::Secondary)
+                        .on_press(Message::PageMenuAction(entry.action)),
+                )
+            },
+        );
+        let menu = menu.push(
+            button(text("✕").size(12))
+                .padding([4, 8])
+                .style(theme::Button::Text)
+                .on_press(Message::PageMenuClosed),
+        );
+
+        Column::new()
+            .push(
+                container(
+                    scrollable(menu).direction(scrollable::Direction::Horizontal(
+                        scrollable::Properties::default(),
+                    )),
+                )
+                .padding(4)
+                .width(Length::Fill)
+                .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(page)
+            .into()
+    }
+
+    /// Panel above the page showing the markup "View selection source"
+    /// found, with buttons to copy it and close the panel
+    pub fn with_selection_source<'a>(
+        page: Element<'a, Message>,
+        source: &'a str,
+    ) -> Element<'a, Message> {
+        let header = Row::new()
+            .push(text("Selection source").size(14).width(Length::Fill))
+            .push(
+                button(text("Copy").size(13))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::Copy(CopyKind::Text, source.to_string())),
+            )
+            .push(button(text("Close").size(13)).on_press(Message::SelectionSourceClosed))
+            .spacing(8)
+            .align_items(Alignment::Center);
+
+        let panel = Column::new()
+            .push(header)
+            .push(
+                scrollable(text(source).size(12).font(iced::Font::MONOSPACE))
+                    .height(Length::Fixed(200.0))
+                    .width(Length::Fill),
+            )
+            .spacing(6);
+
+        Column::new()
+            .push(
+                container(panel)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(page)
+            .into()
+    }
+
+    /// Query field of the tab switcher, focused when it opens
+    pub fn tab_switcher_input() -> text_input::Id {
+        text_input::Id::new("tab-switcher")
+    }
+
+    /// Tab switcher panel above the page: a query field, the matching tabs
+    /// and a preview of the highlighted one
+    pub fn with_tab_switcher<'a>(
+        page: Element<'a, Message>,
+        switcher: &'a TabSwitcher,
+    ) -> Element<'a, Message> {
+        let query = text_input("Search open tabs…", &switcher.query)
+            .id(Self::tab_switcher_input())
+            .on_input(Message::TabSwitcherQueryChanged)
+            .on_submit(Message::TabSwitcherSubmit)
+            .size(14)
+            .padding(6);
+
+        let matches = switcher.matches.iter().enumerate().fold(
+            Column::new().spacing(2),
+            |column, (index, found)| {
+                let title = if found.title.is_empty() {
+                    found.url.as_str()
+                } else {
+                    found.title.as_str()
+                };
+                let icon = if found.hibernated { "💤" } else { "🗂" };
+                let style = if index == switcher.selected {
+                    theme::Button::Primary
+                } else {
+                    theme::Button::Text
+                };
+                column.push(
+                    button(text(format!("{} {}", icon, title)).size(13))
+                        .padding([4, 8])
+                        .width(Length::Fill)
+                        .style(style)
+                        .on_press(Message::TabSwitcherActivate(found.tab_id)),
+                )
+            },
+        );
+
+        let preview: Element<'a, Message> = match switcher.selected_match() {
+            Some(found) => {
+                let detail = if found.hibernated {
+                    "Hibernated; selecting it reloads the page in a fresh VM."
+                } else {
+                    found.preview.as_str()
+                };
+                Column::new()
+                    .push(
+                        text(found.url.as_str())
+                            .size(12)
+                            .style(Color::from_rgb(0.7, 0.7, 0.7)),
+                    )
+                    .push(text(detail).size(12))
+                    .spacing(2)
+                    .into()
+            }
+            None => text("No open tab matches.").size(12).into(),
+        };
+
+        let panel = Column::new()
+            .push(query)
+            .push(matches)
+            .push(preview)
+            .spacing(6);
+
+        Column::new()
+            .push(
+                container(panel)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(theme::Container::Custom(Box::new(InfoBarStyle))),
+            )
+            .push(page)
+            .into()
+    }
+
+    /// Text runs of a render joined into reading lines: runs laid out on the
+    /// same row become one line, taking the kind of the row's first run.
+    /// Runs of languages written without spaces (Chinese, Japanese, Thai...)
+    /// are joined as they are.
+    fn reader_lines(content: &RenderedContent, unspaced: bool) -> Vec<(DisplayKind, String)> {
+        let mut lines: Vec<(DisplayKind, f32, String)> = Vec::new();
+        for item in content
+            .display_list
+            .iter()
+            .filter(|item| !item.text.trim().is_empty())
+        {
+            match lines.last_mut() {
+                Some((_, y, line)) if (item.y - *y).abs() < 1.0 => {
+                    if !unspaced {
+                        line.push(' ');
+                    }
+                    line.push_str(item.text.trim());
+                }
+                _ => lines.push((item.kind, item.y, item.text.trim().to_string())),
+            }
+        }
+        lines
+            .into_iter()
+            .map(|(kind, _, line)| (kind, line))
+            .collect()
+    }
+
+    /// Stand-in for the page in a window without focus. Only the focused
+    /// window's tab is rendered; focusing this window switches to its tab.
+    fn unfocused_page_placeholder<'a>(
+        window: &WindowView<'_>,
+        tab_manager: &Arc<TabManager>,
+    ) -> Element<'a, Message> {
+        let title = window
+            .active_tab
+            .and_then(|id| {
+                tab_manager
+                    .get_tab_states()
+                    .into_iter()
+                    .find(|t| t.id == id)
+            })
+            .map(|tab| {
+                if tab.title.is_empty() {
+                    citadel_networking::display_url(&tab.url)
+                } else {
+                    tab.title
+                }
+            })
+            .unwrap_or_else(|| "No tabs".to_string());
+
+        let content = Column::new()
+            .push(Space::with_height(50))
+            .push(text(title).size(20).style(Color::from_rgb(0.6, 0.6, 0.6)))
+            .push(Space::with_height(10))
+            .push(
+                text("Focus this window to view the page")
+                    .size(14)
+                    .style(Color::from_rgb(0.5, 0.5, 0.5)),
+            )
+            .align_items(Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+
     /// Create the page content area
     fn create_page_content<'a>(
         &'a self,
+        window: &WindowView<'_>,
         tab_manager: &Arc<TabManager>,
         renderer: &'a CitadelRenderer,
         viewport_info: &ViewportInfo,
@@ -341,7 +1316,10 @@ impl CitadelUI {
     ) -> Element<'a, Message> {
         let tab_states = tab_manager.get_tab_states();
 
-        if let Some(active_tab) = tab_states.iter().find(|tab| tab.is_active) {
+        if let Some(active_tab) = tab_states
+            .iter()
+            .find(|tab| Some(tab.id) == window.active_tab)
+        {
             // Render content based on the page content state
             match &active_tab.content {
                 citadel_tabs::PageContent::Loading { url } => {
@@ -433,12 +1411,17 @@ impl CitadelUI {
                                 .style(Color::from_rgb(0.0, 0.6, 0.8)),
                         );
 
-                    // Create scrollable content with viewport-aware rendering
-                    let scrollable_content = self.create_scrollable_content(
-                        rendered_content,
-                        viewport_info,
-                        scroll_state,
-                    );
+                    // Create scrollable content with viewport-aware rendering;
+                    // the image viewer pans its own scrollable
+                    let scrollable_content = if renderer.shows_image() {
+                        rendered_content
+                    } else {
+                        self.create_scrollable_content(
+                            rendered_content,
+                            viewport_info,
+                            scroll_state,
+                        )
+                    };
 
                     // Prioritize the rendered content with comprehensive header.
                     // The page region sits on a light canvas that fills the pane so
@@ -450,6 +1433,8 @@ impl CitadelUI {
                                 .padding([5, 10, 5, 10])
                                 .style(theme::Container::Custom(Box::new(InfoBarStyle))),
                         )
+                        .push_maybe(window.escalation.banner().map(Self::escalation_banner))
+                        .push_maybe(window.truncation.banner().map(Self::truncation_banner))
                         .push(
                             container(scrollable_content)
                                 .width(Length::Fill)
@@ -466,10 +1451,21 @@ impl CitadelUI {
                         .into()
                 }
                 citadel_tabs::PageContent::Error { url, error } => {
+                    let title = window
+                        .load_failure
+                        .map_or("Failed to Load Page", LoadErrorCategory::title);
+                    let hint = window.load_failure.map_or(
+                        "Your browser prevented potentially harmful content from loading",
+                        LoadErrorCategory::hint,
+                    );
+                    let retry = window
+                        .load_failure
+                        .is_some_and(|category| category.retry_policy().allows_retry())
+                        .then(|| button("Try again").padding(8).on_press(Message::RefreshTab));
                     let content = Column::new()
                         .push(Space::with_height(50))
                         .push(
-                            text("❌ Failed to Load Page")
+                            text(format!("❌ {}", title))
                                 .size(24)
                                 .style(Color::from_rgb(1.0, 0.3, 0.3)),
                         )
@@ -492,10 +1488,39 @@ impl CitadelUI {
                                 .style(Color::from_rgb(0.0, 0.6, 0.8)),
                         )
                         .push(Space::with_height(10))
+                        .push(text(hint).size(11).style(Color::from_rgb(0.5, 0.5, 0.5)))
+                        .push(Space::with_height(20))
+                        .push_maybe(retry)
+                        .align_items(Alignment::Center);
+
+                    container(content)
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .center_x()
+                        .into()
+                }
+                citadel_tabs::PageContent::Expired { url } => {
+                    let content = Column::new()
+                        .push(Space::with_height(50))
                         .push(
-                            text("Your browser prevented potentially harmful content from loading")
-                                .size(11)
-                                .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                            text("⏳ Tab expired for your privacy")
+                                .size(20)
+                                .style(Color::from_rgb(0.6, 0.6, 0.6)),
+                        )
+                        .push(Space::with_height(10))
+                        .push(
+                            text(format!(
+                                "{} was closed after being idle and its memory wiped",
+                                url
+                            ))
+                            .size(12)
+                            .style(Color::from_rgb(0.5, 0.5, 0.5)),
+                        )
+                        .push(Space::with_height(20))
+                        .push(
+                            button("Reopen")
+                                .padding(8)
+                                .on_press(Message::ReopenTab(active_tab.id)),
                         )
                         .align_items(Alignment::Center);
 
@@ -744,7 +1769,15 @@ impl CitadelUI {
     /// Shows live counters for trackers blocked, fingerprints neutralized,
     /// local DNS queries, and other privacy actions.  An expandable section
     /// lists the most recent events.
-    fn privacy_scoreboard_view(stats: &PrivacyStats, expanded: bool) -> Element<'static, Message> {
+    fn privacy_scoreboard_view(
+        stats: &PrivacyStats,
+        blocklist_stats: &BlocklistStats,
+        site_allowlisted: Option<bool>,
+        budget_usage: Option<&BudgetUsage>,
+        security_headers: Option<&SecurityHeaderReport>,
+        css_diagnostics: Option<&StylesheetDiagnostics>,
+        expanded: bool,
+    ) -> Element<'static, Message> {
         // ── Header ──────────────────────────────────────────────────
         let total = stats.total_actions();
         let header = Row::new()
@@ -816,6 +1849,32 @@ impl CitadelUI {
             .push(csp_row)
             .spacing(0);
 
+        // ── Filter lists ────────────────────────────────────────────
+        panel = panel
+            .push(Space::with_height(10))
+            .push(Self::filter_lists_view(blocklist_stats, site_allowlisted));
+
+        // ── Tab request budget ──────────────────────────────────────
+        if let Some(usage) = budget_usage {
+            panel = panel
+                .push(Space::with_height(10))
+                .push(Self::budget_view(usage));
+        }
+
+        // ── Page security headers ───────────────────────────────────
+        if let Some(report) = security_headers {
+            panel = panel
+                .push(Space::with_height(10))
+                .push(Self::security_headers_view(report));
+        }
+
+        // ── Page CSS errors ─────────────────────────────────────────
+        if let Some(diagnostics) = css_diagnostics {
+            panel = panel
+                .push(Space::with_height(10))
+                .push(Self::css_diagnostics_view(diagnostics));
+        }
+
         // ── Dropped events warning ──────────────────────────────────
         if stats.events_dropped > 0 {
             panel = panel.push(Space::with_height(6)).push(
@@ -874,6 +1933,278 @@ impl CitadelUI {
             .into()
     }
 
+    /// Render the filter list counters and the active site's allowlist
+    /// switch.
+    fn filter_lists_view(
+        stats: &BlocklistStats,
+        site_allowlisted: Option<bool>,
+    ) -> Element<'static, Message> {
+        let filter_row = |label: &str, count: u64| -> Element<'static, Message> {
+            container(
+                Row::new()
+                    .push(text(label).size(12).style(Color::from_rgb(0.7, 0.7, 0.7)))
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        text(format!("{}", count))
+                            .size(12)
+                            .style(Color::from_rgb(0.9, 0.9, 0.9)),
+                    )
+                    .align_items(Alignment::Center)
+                    .padding([4, 6]),
+            )
+            .style(theme::Container::Custom(Box::new(PrivacyStatRowStyle)))
+            .width(Length::Fill)
+            .into()
+        };
+
+        let mut column = Column::new()
+            .push(
+                text("Filter Lists")
+                    .size(13)
+                    .style(Color::from_rgb(0.0, 0.75, 0.55)),
+            )
+            .push(Space::with_height(6))
+            .push(filter_row("Requests Checked", stats.checked))
+            .push(Space::with_height(4))
+            .push(filter_row("Blocked", stats.blocked))
+            .push(Space::with_height(4))
+            .push(filter_row("Allowed By Exception", stats.excepted))
+            .push(Space::with_height(4))
+            .push(filter_row(
+                "Allowed On Allowlisted Sites",
+                stats.allowlisted,
+            ))
+            .spacing(0);
+
+        if let Some(allowlisted) = site_allowlisted {
+            let label = if allowlisted {
+                "Filter This Site"
+            } else {
+                "Allowlist This Site"
+            };
+            column = column.push(Space::with_height(6)).push(
+                button(text(label).size(11))
+                    .padding([4, 8])
+                    .on_press(Message::ToggleSiteFiltering)
+                    .style(theme::Button::Secondary),
+            );
+        }
+
+        column.into()
+    }
+
+    /// Render the active tab's request budget consumption.
+    fn budget_view(usage: &BudgetUsage) -> Element<'static, Message> {
+        let budget_row =
+            |label: &str, value: String, exhausted: bool| -> Element<'static, Message> {
+                let color = if exhausted {
+                    Color::from_rgb(1.0, 0.35, 0.35)
+                } else {
+                    Color::from_rgb(0.9, 0.9, 0.9)
+                };
+                container(
+                    Row::new()
+                        .push(text(label).size(12).style(Color::from_rgb(0.7, 0.7, 0.7)))
+                        .push(Space::with_width(Length::Fill))
+                        .push(text(value).size(12).style(color))
+                        .align_items(Alignment::Center)
+                        .padding([4, 6]),
+                )
+                .style(theme::Container::Custom(Box::new(PrivacyStatRowStyle)))
+                .width(Length::Fill)
+                .into()
+            };
+
+        let limits = usage.limits;
+        let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+
+        let mut column = Column::new()
+            .push(
+                text("Tab Budget")
+                    .size(13)
+                    .style(Color::from_rgb(0.0, 0.75, 0.55)),
+            )
+            .push(Space::with_height(6))
+            .push(budget_row(
+                "Requests In Flight",
+                format!(
+                    "{}/{}",
+                    usage.active_requests, limits.max_concurrent_requests
+                ),
+                usage.active_requests >= limits.max_concurrent_requests,
+            ))
+            .push(Space::with_height(4))
+            .push(budget_row(
+                "Data",
+                format!(
+                    "{:.1}/{:.0} MiB",
+                    mib(usage.total_bytes),
+                    mib(limits.max_total_bytes)
+                ),
+                usage.total_bytes >= limits.max_total_bytes,
+            ))
+            .push(Space::with_height(4))
+            .push(budget_row(
+                "Third-Party Origins",
+                format!(
+                    "{}/{}",
+                    usage.third_party_origins, limits.max_third_party_origins
+                ),
+                usage.third_party_origins >= limits.max_third_party_origins,
+            ))
+            .spacing(0);
+
+        if usage.is_exhausted() {
+            column = column.push(Space::with_height(4)).push(
+                text(format!(
+                    "{} requests refused by budget",
+                    usage.rejected_requests
+                ))
+                .size(10)
+                .style(Color::from_rgb(1.0, 0.6, 0.0)),
+            );
+        }
+
+        column.into()
+    }
+
+    /// Render the graded security header audit of the active tab's page.
+    fn security_headers_view(report: &SecurityHeaderReport) -> Element<'static, Message> {
+        let grade_color = match report.grade() {
+            SecurityGrade::A | SecurityGrade::B => Color::from_rgb(0.0, 0.75, 0.55),
+            SecurityGrade::C => Color::from_rgb(0.95, 0.65, 0.1),
+            SecurityGrade::D | SecurityGrade::F => Color::from_rgb(1.0, 0.35, 0.35),
+        };
+
+        let mut column = Column::new()
+            .push(
+                Row::new()
+                    .push(
+                        text("Security Headers")
+                            .size(13)
+                            .style(Color::from_rgb(0.0, 0.75, 0.55)),
+                    )
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        text(format!("{} ({})", report.grade(), report.score()))
+                            .size(13)
+                            .style(grade_color),
+                    )
+                    .align_items(Alignment::Center),
+            )
+            .push(Space::with_height(6))
+            .spacing(0);
+
+        for finding in &report.findings {
+            let (status, color) = match finding.status {
+                HeaderStatus::Good => ("✓", Color::from_rgb(0.0, 0.75, 0.55)),
+                HeaderStatus::Weak => ("!", Color::from_rgb(0.95, 0.65, 0.1)),
+                HeaderStatus::Missing => ("✗", Color::from_rgb(1.0, 0.35, 0.35)),
+            };
+            column = column
+                .push(
+                    container(
+                        Column::new()
+                            .push(
+                                Row::new()
+                                    .push(
+                                        text(finding.header)
+                                            .size(11)
+                                            .style(Color::from_rgb(0.7, 0.7, 0.7)),
+                                    )
+                                    .push(Space::with_width(Length::Fill))
+                                    .push(text(status).size(11).style(color))
+                                    .align_items(Alignment::Center),
+                            )
+                            .push(
+                                text(&finding.detail)
+                                    .size(10)
+                                    .style(Color::from_rgb(0.55, 0.55, 0.55)),
+                            )
+                            .padding([4, 6]),
+                    )
+                    .style(theme::Container::Custom(Box::new(PrivacyStatRowStyle)))
+                    .width(Length::Fill),
+                )
+                .push(Space::with_height(4));
+        }
+
+        column.into()
+    }
+
+    /// Render the recoverable CSS errors of the active tab's page, with their
+    /// source positions.
+    fn css_diagnostics_view(diagnostics: &StylesheetDiagnostics) -> Element<'static, Message> {
+        const SHOWN: usize = 12;
+
+        let mut column = Column::new()
+            .push(
+                Row::new()
+                    .push(
+                        text("CSS Errors")
+                            .size(13)
+                            .style(Color::from_rgb(0.0, 0.75, 0.55)),
+                    )
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        text(format!("{}", diagnostics.len()))
+                            .size(13)
+                            .style(Color::from_rgb(0.95, 0.65, 0.1)),
+                    )
+                    .align_items(Alignment::Center),
+            )
+            .push(Space::with_height(6))
+            .spacing(0);
+
+        for diagnostic in diagnostics.iter().take(SHOWN) {
+            let location = match &diagnostic.rule {
+                Some(rule) => format!("{}:{} in {}", diagnostic.line, diagnostic.column, rule),
+                None => format!("{}:{}", diagnostic.line, diagnostic.column),
+            };
+            column = column
+                .push(
+                    container(
+                        Column::new()
+                            .push(
+                                Row::new()
+                                    .push(
+                                        text(diagnostic.kind.to_string())
+                                            .size(11)
+                                            .style(Color::from_rgb(0.7, 0.7, 0.7)),
+                                    )
+                                    .push(Space::with_width(Length::Fill))
+                                    .push(
+                                        text(location)
+                                            .size(10)
+                                            .style(Color::from_rgb(0.55, 0.55, 0.55)),
+                                    )
+                                    .align_items(Alignment::Center),
+                            )
+                            .push(
+                                text(&diagnostic.message)
+                                    .size(10)
+                                    .style(Color::from_rgb(0.95, 0.65, 0.1)),
+                            )
+                            .padding([4, 6]),
+                    )
+                    .style(theme::Container::Custom(Box::new(PrivacyStatRowStyle)))
+                    .width(Length::Fill),
+                )
+                .push(Space::with_height(4));
+        }
+
+        let hidden = diagnostics.len().saturating_sub(SHOWN);
+        if hidden > 0 {
+            column = column.push(
+                text(format!("…and {} more", hidden))
+                    .size(10)
+                    .style(Color::from_rgb(0.55, 0.55, 0.55)),
+            );
+        }
+
+        column.into()
+    }
+
     /// Format a single privacy event into (icon, summary_text, color).
     fn format_privacy_event(event: &PrivacyEvent) -> (&'static str, String, Color) {
         match event {
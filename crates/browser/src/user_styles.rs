@@ -0,0 +1,156 @@
+//! Per-site user stylesheets
+//!
+//! Users keep a list of (origin pattern → CSS) rules in their settings to fix
+//! broken dark modes or hide page elements without extensions. The host picks
+//! the snippets matching a page and sends them into the ZKVM boundary with the
+//! page, where they are parsed like page CSS and cascaded with user-origin
+//! priority (see `CitadelStylesheet::add_user_css`).
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::profile;
+
+/// Environment variable overriding where user stylesheets are read from
+pub const USER_STYLES_FILE_ENV: &str = "CITADEL_USER_STYLES_FILE";
+
+/// One user CSS snippet and the pages it applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStyleRule {
+    /// Origin pattern, see [`pattern_matches`]
+    pub pattern: String,
+    /// CSS appended to matching pages
+    pub css: String,
+}
+
+impl UserStyleRule {
+    /// Whether the rule applies to a page
+    pub fn matches(&self, url: &Url) -> bool {
+        pattern_matches(&self.pattern, url)
+    }
+}
+
+/// Match a page against an origin pattern: `*` for every site,
+/// `example.com` for one host, `*.example.com` for a host and its
+/// subdomains, optionally prefixed with `scheme://`
+pub fn pattern_matches(pattern: &str, url: &Url) -> bool {
+    let (scheme, host_pattern) = match pattern.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, pattern),
+    };
+    if scheme.is_some_and(|scheme| !scheme.eq_ignore_ascii_case(url.scheme())) {
+        return false;
+    }
+
+    let host_pattern = host_pattern.trim_end_matches('/').to_ascii_lowercase();
+    if host_pattern == "*" {
+        return true;
+    }
+    let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+        return false;
+    };
+    match host_pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => host == host_pattern,
+    }
+}
+
+/// The user's stylesheet settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStylesheets {
+    pub rules: Vec<UserStyleRule>,
+}
+
+impl UserStylesheets {
+    /// Add a rule after the existing ones
+    pub fn add(&mut self, pattern: impl Into<String>, css: impl Into<String>) {
+        self.rules.push(UserStyleRule {
+            pattern: pattern.into(),
+            css: css.into(),
+        });
+    }
+
+    /// CSS of every rule matching a page, in settings order. Empty for
+    /// unparseable URLs.
+    pub fn css_for(&self, url: &str) -> String {
+        let Ok(url) = Url::parse(url) else {
+            return String::new();
+        };
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(&url))
+            .map(|rule| rule.css.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Read settings from a JSON file; a missing file means no rules
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match profile::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Where user stylesheets live: `$CITADEL_USER_STYLES_FILE`, otherwise
+/// `citadel/user-styles.json` under the XDG config directory
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(USER_STYLES_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("user-styles.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_match_by_origin_pattern() {
+        let mut styles = UserStylesheets::default();
+        styles.add("*", "a { color: red; }");
+        styles.add("*.example.com", ".ad { display: none; }");
+        styles.add("https://news.test", "body { background-color: black; }");
+
+        let css = styles.css_for("https://www.example.com/page");
+        assert!(css.contains("color: red"));
+        assert!(css.contains(".ad"));
+        assert!(!css.contains("background-color"));
+
+        assert!(styles.css_for("http://example.com/").contains(".ad"));
+        assert!(!styles.css_for("https://notexample.com/").contains(".ad"));
+        assert!(styles
+            .css_for("https://news.test/today")
+            .contains("background-color"));
+        assert!(!styles
+            .css_for("http://news.test/today")
+            .contains("background-color"));
+        assert!(styles.css_for("not a url").is_empty());
+    }
+
+    #[test]
+    fn test_missing_settings_file_has_no_rules() {
+        let path =
+            std::env::temp_dir().join(format!("citadel-user-styles-{}", uuid::Uuid::new_v4()));
+        assert_eq!(
+            UserStylesheets::load(&path).unwrap(),
+            UserStylesheets::default()
+        );
+
+        std::fs::write(
+            &path,
+            r#"{"rules":[{"pattern":"*","css":"p { color: red; }"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(UserStylesheets::load(&path).unwrap().rules.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
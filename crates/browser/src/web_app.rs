@@ -0,0 +1,398 @@
+//! Web app manifests and desktop shortcuts
+//!
+//! A page that links a web app manifest can be installed as a desktop
+//! shortcut. The shortcut starts Citadel in app-window mode: a single window
+//! without an address bar or tab strip, showing the app's start URL in a
+//! container of its own, so the app's cookies and sessions never mix with
+//! ordinary browsing. Navigations that leave the app's origin open in a
+//! regular browser window.
+//!
+//! Only the fields needed for this are read: `name`/`short_name`,
+//! `start_url`, `theme_color` and `icons`. Everything else in the
+//! manifest is ignored.
+
+use std::path::{Path, PathBuf};
+
+use citadel_parser::Dom;
+use serde::Deserialize;
+use url::Url;
+use uuid::Uuid;
+
+/// Command-line flag carrying an app's start URL
+pub const APP_FLAG: &str = "--app";
+/// Command-line flag carrying an app's container
+pub const CONTAINER_FLAG: &str = "--container";
+
+/// Longest app name kept from a manifest
+const MAX_NAME_CHARS: usize = 64;
+
+#[derive(Debug, Deserialize)]
+struct RawManifest {
+    name: Option<String>,
+    short_name: Option<String>,
+    start_url: Option<String>,
+    theme_color: Option<String>,
+    #[serde(default)]
+    icons: Vec<RawIcon>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawIcon {
+    src: String,
+    sizes: Option<String>,
+    #[serde(rename = "type")]
+    mime_type: Option<String>,
+}
+
+/// An icon listed in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestIcon {
+    pub src: Url,
+    /// Square sizes in pixels; `None` for a scalable (`any`) icon
+    pub sizes: Vec<Option<u32>>,
+    pub mime_type: Option<String>,
+}
+
+/// The parts of a web app manifest Citadel uses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebAppManifest {
+    /// Display name, from `name`, `short_name` or the site's host
+    pub name: String,
+    /// Where the app opens; always same-origin with the installing page
+    pub start_url: Url,
+    /// `#rgb`/`#rrggbb` style color; named and functional colors are dropped
+    pub theme_color: Option<String>,
+    pub icons: Vec<ManifestIcon>,
+}
+
+impl WebAppManifest {
+    /// Parse a manifest fetched from `manifest_url` for the page at
+    /// `document_url`. An invalid or foreign-origin `start_url` falls back
+    /// to the document, as browsers do.
+    pub fn parse(
+        json: &str,
+        manifest_url: &Url,
+        document_url: &Url,
+    ) -> Result<Self, serde_json::Error> {
+        let raw: RawManifest = serde_json::from_str(json)?;
+
+        let start_url = raw
+            .start_url
+            .and_then(|start| manifest_url.join(&start).ok())
+            .filter(|start| start.origin() == document_url.origin())
+            .unwrap_or_else(|| document_url.clone());
+
+        let name = [raw.name, raw.short_name]
+            .into_iter()
+            .flatten()
+            .map(|name| clean_name(&name))
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| document_url.host_str().unwrap_or_default().to_string());
+
+        let icons = raw
+            .icons
+            .into_iter()
+            .filter_map(|icon| {
+                let src = manifest_url.join(&icon.src).ok()?;
+                matches!(src.scheme(), "https" | "http").then(|| ManifestIcon {
+                    src,
+                    sizes: parse_sizes(icon.sizes.as_deref().unwrap_or_default()),
+                    mime_type: icon.mime_type,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            name,
+            start_url,
+            theme_color: raw.theme_color.filter(|color| is_hex_color(color)),
+            icons,
+        })
+    }
+
+    /// The icon to use at `size` pixels: the smallest one at least that
+    /// large, otherwise the largest. Scalable icons count as large enough.
+    pub fn best_icon(&self, size: u32) -> Option<&ManifestIcon> {
+        let largest = |icon: &ManifestIcon| {
+            icon.sizes
+                .iter()
+                .map(|size| size.unwrap_or(u32::MAX))
+                .max()
+                .unwrap_or(0)
+        };
+        self.icons
+            .iter()
+            .filter(|icon| largest(icon) >= size)
+            .min_by_key(|icon| largest(icon))
+            .or_else(|| self.icons.iter().max_by_key(|icon| largest(icon)))
+    }
+}
+
+/// Strip control characters and runs of whitespace, which would otherwise
+/// end up in a desktop entry
+fn clean_name(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_NAME_CHARS)
+        .collect()
+}
+
+fn parse_sizes(sizes: &str) -> Vec<Option<u32>> {
+    sizes
+        .split_ascii_whitespace()
+        .filter_map(|size| {
+            if size.eq_ignore_ascii_case("any") {
+                return Some(None);
+            }
+            let (width, height) = size
+                .to_ascii_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>()))?;
+            Some(Some(width.ok()?.min(height.ok()?)))
+        })
+        .collect()
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|digits| {
+        matches!(digits.len(), 3 | 4 | 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// The manifest a page links with `<link rel="manifest">`, if any
+pub fn manifest_link(dom: &Dom, page_url: &Url) -> Option<Url> {
+    dom.get_elements_by_tag_name("link")
+        .into_iter()
+        .find_map(|handle| {
+            let node = handle.read().ok()?;
+            let element = node.as_element()?;
+            let rel = element.get_attribute("rel")?;
+            if !rel
+                .split_ascii_whitespace()
+                .any(|token| token.eq_ignore_ascii_case("manifest"))
+            {
+                return None;
+            }
+            dom.resolve_url(page_url, &element.get_attribute("href")?)
+        })
+        .filter(|url| matches!(url.scheme(), "https" | "http"))
+}
+
+/// How the browser was asked to start in app-window mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppLaunch {
+    pub start_url: Url,
+    pub container_id: Uuid,
+}
+
+impl AppLaunch {
+    /// Read `--app <url> --container <uuid>` from the command line. Both
+    /// flags are required and the URL must be http(s).
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut start_url = None;
+        let mut container_id = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let target = match flag.as_str() {
+                APP_FLAG => &mut start_url,
+                CONTAINER_FLAG => &mut container_id,
+                _ => continue,
+            };
+            *target = value.or_else(|| args.next());
+        }
+
+        let start_url = Url::parse(&start_url?).ok()?;
+        if !matches!(start_url.scheme(), "https" | "http") {
+            return None;
+        }
+        Some(Self {
+            start_url,
+            container_id: Uuid::parse_str(&container_id?).ok()?,
+        })
+    }
+}
+
+/// Quote a desktop entry `Exec` argument
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Name of an installed app's shortcut and icons, from its host
+pub fn app_id(start_url: &Url) -> String {
+    let host: String = start_url
+        .host_str()
+        .unwrap_or("app")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("citadel-app-{}", host)
+}
+
+/// A freedesktop.org desktop entry launching the app in its container,
+/// shown with the icon theme's `icon` if one was installed
+pub fn desktop_entry(
+    manifest: &WebAppManifest,
+    container_id: Uuid,
+    exe: &Path,
+    icon: Option<&str>,
+) -> String {
+    let origin = manifest.start_url.origin().ascii_serialization();
+    let mut entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Version=1.0\n\
+         Name={name}\n\
+         Comment={origin} in Citadel\n\
+         Exec={exe} {APP_FLAG} {url} {CONTAINER_FLAG} {container_id}\n\
+         Terminal=false\n\
+         Categories=Network;\n\
+         X-Citadel-Container={container_id}\n",
+        name = manifest.name,
+        exe = quote_exec_arg(&exe.to_string_lossy()),
+        url = quote_exec_arg(manifest.start_url.as_str()),
+    );
+    if let Some(icon) = icon {
+        entry.push_str(&format!("Icon={}\n", icon));
+    }
+    entry
+}
+
+/// Where desktop entries are installed: `applications` under the XDG data
+/// directory
+pub fn shortcuts_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("applications"))
+}
+
+/// Write a desktop shortcut for the app into `dir`, replacing any earlier
+/// shortcut for the same host, and return its path. `icon` names the app's
+/// icons if they were installed.
+pub async fn install_shortcut(
+    manifest: &WebAppManifest,
+    container_id: Uuid,
+    dir: &Path,
+    icon: Option<&str>,
+) -> std::io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let path = dir.join(format!("{}.desktop", app_id(&manifest.start_url)));
+
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(&path, desktop_entry(manifest, container_id, &exe, icon)).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_manifest_parsing() {
+        let manifest = WebAppManifest::parse(
+            r##"{
+                "name": "Notes\nApp",
+                "start_url": "../app/?source=pwa",
+                "theme_color": "#1a2b3c",
+                "icons": [
+                    {"src": "icon-48.png", "sizes": "48x48"},
+                    {"src": "icon-192.png", "sizes": "192x192 256x256", "type": "image/png"},
+                    {"src": "javascript:alert(1)", "sizes": "512x512"}
+                ]
+            }"##,
+            &url("https://notes.example/static/manifest.json"),
+            &url("https://notes.example/app/home"),
+        )
+        .unwrap();
+        assert_eq!(manifest.name, "Notes App");
+        assert_eq!(
+            manifest.start_url.as_str(),
+            "https://notes.example/app/?source=pwa"
+        );
+        assert_eq!(manifest.theme_color.as_deref(), Some("#1a2b3c"));
+        assert_eq!(manifest.icons.len(), 2);
+        assert!(manifest
+            .best_icon(96)
+            .unwrap()
+            .src
+            .path()
+            .ends_with("icon-192.png"));
+        assert!(manifest
+            .best_icon(1024)
+            .unwrap()
+            .src
+            .path()
+            .ends_with("icon-192.png"));
+
+        // A start URL on another origin is ignored
+        let foreign = WebAppManifest::parse(
+            r#"{"short_name": "X", "start_url": "https://evil.example/", "theme_color": "red"}"#,
+            &url("https://site.example/manifest.json"),
+            &url("https://site.example/page"),
+        )
+        .unwrap();
+        assert_eq!(foreign.start_url.as_str(), "https://site.example/page");
+        assert_eq!(foreign.name, "X");
+        assert_eq!(foreign.theme_color, None);
+    }
+
+    #[test]
+    fn test_app_launch_and_shortcut() {
+        let container_id = Uuid::new_v4();
+        let launch = AppLaunch::from_args([
+            "--app=https://notes.example/app/".to_string(),
+            "--container".to_string(),
+            container_id.to_string(),
+        ])
+        .unwrap();
+        assert_eq!(launch.container_id, container_id);
+        assert_eq!(launch.start_url.as_str(), "https://notes.example/app/");
+        assert!(
+            AppLaunch::from_args(["--app".to_string(), "file:///etc/passwd".to_string()]).is_none()
+        );
+
+        let manifest = WebAppManifest::parse(
+            r#"{"name": "Notes", "start_url": "/app/?q=100%25"}"#,
+            &url("https://notes.example/manifest.json"),
+            &url("https://notes.example/"),
+        )
+        .unwrap();
+        let icon = app_id(&manifest.start_url);
+        assert_eq!(icon, "citadel-app-notes-example");
+        let entry = desktop_entry(
+            &manifest,
+            container_id,
+            Path::new("/opt/cit$del/citadel"),
+            Some(&icon),
+        );
+        assert!(entry.contains("Name=Notes\n"));
+        assert!(entry.contains("Icon=citadel-app-notes-example\n"));
+        assert!(entry.contains(&format!(
+            "Exec=\"/opt/cit\\$del/citadel\" --app \"https://notes.example/app/?q=100%%25\" --container {}\n",
+            container_id
+        )));
+    }
+}
@@ -0,0 +1,283 @@
+//! Web fonts
+//!
+//! Pages name fonts in `font-family` lists and offer their own with
+//! `@font-face`. A list resolves to the first of its families that is
+//! either a web font the page offered and the renderer has loaded, or a
+//! generic family (`serif`, `monospace`, ...). Fonts installed on the
+//! system are never looked up by name: a page could otherwise probe which
+//! ones are there and fingerprint the machine with the answer.
+//!
+//! Faces are fetched like any subresource of the page, under its
+//! Content-Security-Policy `font-src`, and have to parse as TrueType or
+//! OpenType within [`MAX_FONT_BYTES`] before they reach the text system.
+//! Loaded faces are kept by family and weight.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use citadel_errors::{CitadelError, ErrorKind};
+use citadel_parser::css::{font_family_names, font_weight_value};
+use citadel_parser::{CitadelStylesheet, ComputedStyle, FontSource};
+use iced::font::{Family, Weight};
+use iced::Font;
+use url::Url;
+
+/// Largest font file loaded
+pub const MAX_FONT_BYTES: usize = 4 * 1024 * 1024;
+
+/// Most faces fetched for one page
+pub const MAX_FACES_PER_PAGE: usize = 16;
+
+/// `format()` hints of files the text system can load. WOFF and WOFF2 are
+/// compressed containers it does not read.
+const LOADABLE_FORMATS: &[&str] = &[
+    "truetype",
+    "opentype",
+    "collection",
+    "truetype-variations",
+    "opentype-variations",
+];
+
+/// A face as pages ask for it: a family name and a weight
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FontKey {
+    /// Family name, lowercased; family names match case-insensitively
+    pub family: String,
+    pub weight: u16,
+}
+
+impl FontKey {
+    pub fn new(family: &str, weight: u16) -> Self {
+        Self {
+            family: family.to_lowercase(),
+            weight,
+        }
+    }
+}
+
+/// A face a page offers, and where to fetch it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontRequest {
+    pub key: FontKey,
+    pub url: Url,
+    pub italic: bool,
+}
+
+/// The faces `stylesheet` offers that can be loaded, at most
+/// [`MAX_FACES_PER_PAGE`] and one per family and weight. Sources resolve
+/// against `base`; only HTTPS ones are fetched.
+pub fn font_requests(stylesheet: &CitadelStylesheet, base: &Url) -> Vec<FontRequest> {
+    let mut seen = HashSet::new();
+    stylesheet
+        .font_faces
+        .iter()
+        .filter_map(|face| {
+            let key = FontKey::new(&face.family, face.weight);
+            if seen.contains(&key) {
+                return None;
+            }
+            let url = face
+                .sources
+                .iter()
+                .filter(|source| is_loadable(source))
+                .filter_map(|source| base.join(&source.url).ok())
+                .find(|url| url.scheme() == "https")?;
+            seen.insert(key.clone());
+            Some(FontRequest {
+                key,
+                url,
+                italic: face.italic,
+            })
+        })
+        .take(MAX_FACES_PER_PAGE)
+        .collect()
+}
+
+/// Whether a source is a format the text system reads, going by its hint
+/// or, without one, its file extension
+fn is_loadable(source: &FontSource) -> bool {
+    match &source.format {
+        Some(format) => LOADABLE_FORMATS.contains(&format.as_str()),
+        None => {
+            let path = source.url.split(['?', '#']).next().unwrap_or_default();
+            let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+            matches!(
+                extension.map(str::to_ascii_lowercase).as_deref(),
+                Some("ttf" | "otf" | "ttc")
+            )
+        }
+    }
+}
+
+/// Check fetched bytes are a font the text system can load, and return the
+/// family name inside it, which is what the text system knows it by
+pub fn validate_font(bytes: &[u8]) -> Result<String, CitadelError> {
+    if bytes.len() > MAX_FONT_BYTES {
+        return Err(CitadelError::new(
+            ErrorKind::Resource,
+            "FONT_TOO_LARGE",
+            format!("font of {} bytes is over {}", bytes.len(), MAX_FONT_BYTES),
+        ));
+    }
+    let invalid = |reason: String| CitadelError::new(ErrorKind::Content, "FONT_INVALID", reason);
+    let face = ttf_parser::Face::parse(bytes, 0).map_err(|e| invalid(e.to_string()))?;
+    [
+        ttf_parser::name_id::TYPOGRAPHIC_FAMILY,
+        ttf_parser::name_id::FAMILY,
+    ]
+    .into_iter()
+    .find_map(|id| {
+        face.names()
+            .into_iter()
+            .filter(|name| name.name_id == id && name.is_unicode())
+            .find_map(|name| name.to_string())
+    })
+    .filter(|family| !family.trim().is_empty())
+    .ok_or_else(|| invalid("font has no family name".to_string()))
+}
+
+/// The font to draw a loaded face with. `family` is the name inside the
+/// font file, from [`validate_font`].
+pub fn loaded_font(family: &str, key: &FontKey, italic: bool) -> Font {
+    Font {
+        family: Family::Name(static_family(family)),
+        weight: weight(key.weight),
+        style: if italic {
+            iced::font::Style::Italic
+        } else {
+            iced::font::Style::Normal
+        },
+        ..Font::DEFAULT
+    }
+}
+
+/// The font a computed style asks for: its `font-family` list resolved
+/// against the loaded faces. `None` leaves the default font.
+pub fn resolve(loaded: &HashMap<FontKey, Font>, style: &ComputedStyle) -> Option<Font> {
+    let families = font_family_names(style.font_family.as_deref()?);
+    let wanted = style
+        .font_weight
+        .as_deref()
+        .and_then(font_weight_value)
+        .unwrap_or(400);
+    families.iter().find_map(|name| {
+        let generic = match name.to_ascii_lowercase().as_str() {
+            "serif" => Some(Family::Serif),
+            "sans-serif" | "system-ui" => Some(Family::SansSerif),
+            "monospace" => Some(Family::Monospace),
+            "cursive" => Some(Family::Cursive),
+            "fantasy" => Some(Family::Fantasy),
+            _ => None,
+        };
+        if let Some(family) = generic {
+            return Some(Font {
+                family,
+                weight: weight(wanted),
+                ..Font::DEFAULT
+            });
+        }
+        // The face nearest the weight asked for
+        let family = name.to_lowercase();
+        loaded
+            .iter()
+            .filter(|(key, _)| key.family == family)
+            .min_by_key(|(key, _)| key.weight.abs_diff(wanted))
+            .map(|(_, font)| *font)
+    })
+}
+
+fn weight(weight: u16) -> Weight {
+    match weight {
+        0..=149 => Weight::Thin,
+        150..=249 => Weight::ExtraLight,
+        250..=349 => Weight::Light,
+        350..=449 => Weight::Normal,
+        450..=549 => Weight::Medium,
+        550..=649 => Weight::Semibold,
+        650..=749 => Weight::Bold,
+        750..=849 => Weight::ExtraBold,
+        _ => Weight::Black,
+    }
+}
+
+/// `family` with a static lifetime, as fonts name their family. Each
+/// distinct name is leaked once, so loading a face again costs nothing.
+fn static_family(family: &str) -> &'static str {
+    static FAMILIES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let Ok(mut families) = FAMILIES.get_or_init(Default::default).lock() else {
+        return Box::leak(family.to_string().into_boxed_str());
+    };
+    if let Some(known) = families.get(family) {
+        return known;
+    }
+    let leaked: &'static str = Box::leak(family.to_string().into_boxed_str());
+    families.insert(leaked);
+    leaked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use citadel_parser::{parse_css, security::SecurityContext};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_requests_pick_loadable_https_sources() {
+        let stylesheet = parse_css(
+            r#"
+            @font-face {
+                font-family: Brand;
+                src: url(/f/brand.woff2) format("woff2"), url(/f/brand.ttf);
+            }
+            @font-face { font-family: "brand"; src: url(/f/again.ttf); }
+            @font-face { font-family: Bold; src: url(/f/b.otf); font-weight: bold; }
+            @font-face { font-family: Plain; src: url(http://cdn.test/p.ttf); }
+            @font-face { font-family: Packed; src: url(/f/packed.woff); }
+            "#,
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+        let base = Url::parse("https://site.test/page/").unwrap();
+
+        let requests = font_requests(&stylesheet, &base);
+        let found: Vec<(&str, u16, &str)> = requests
+            .iter()
+            .map(|request| {
+                (
+                    request.key.family.as_str(),
+                    request.key.weight,
+                    request.url.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("brand", 400, "https://site.test/f/brand.ttf"),
+                ("bold", 700, "https://site.test/f/b.otf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolution_never_names_system_fonts() {
+        let brand = FontKey::new("Brand", 300);
+        let mut loaded = HashMap::new();
+        loaded.insert(brand.clone(), loaded_font("Brand Sans", &brand, false));
+        let style = |family: &str, weight: Option<&str>| ComputedStyle {
+            font_family: Some(family.to_string()),
+            font_weight: weight.map(str::to_string),
+            ..ComputedStyle::default()
+        };
+
+        let font = resolve(&loaded, &style("\"BRAND\", serif", Some("bold"))).unwrap();
+        assert_eq!(font.family, Family::Name("Brand Sans"));
+        assert_eq!(font.weight, Weight::Light);
+        let font = resolve(&loaded, &style("Arial, monospace", None)).unwrap();
+        assert_eq!(font.family, Family::Monospace);
+        assert!(resolve(&loaded, &style("Arial, Helvetica", None)).is_none());
+
+        assert!(validate_font(b"<html>not a font</html>").is_err());
+        assert!(validate_font(&vec![0; MAX_FONT_BYTES + 1]).is_err());
+    }
+}
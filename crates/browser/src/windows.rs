@@ -0,0 +1,382 @@
+//! Browser windows
+//!
+//! Every window has its own tab strip over the shared `SendSafeTabManager`:
+//! a window only records which tabs it shows and which of them is selected,
+//! while the tabs themselves (and their VMs) stay in the tab manager. The tab
+//! manager's single active tab is the selected tab of the focused window, so
+//! focusing a window switches the manager to that window's selection.
+//!
+//! A tab can also be detached into a small always-on-top auxiliary window
+//! (reader or picture-in-picture). Detached windows have no tab strip; they
+//! repaint the tab's existing ZKVM render, so no second VM is started.
+//!
+//! A browser window started for an installed web app is scoped to that app:
+//! it has no address bar or tab strip, and navigations that leave the app's
+//! origin open in a regular window.
+
+use iced::window;
+use url::Origin;
+use uuid::Uuid;
+
+/// One window's tab strip
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserWindow {
+    id: window::Id,
+    tabs: Vec<Uuid>,
+    active_tab: Option<Uuid>,
+    app_origin: Option<Origin>,
+}
+
+impl BrowserWindow {
+    fn new(id: window::Id) -> Self {
+        Self {
+            id,
+            tabs: Vec::new(),
+            active_tab: None,
+            app_origin: None,
+        }
+    }
+
+    /// Window identifier
+    pub fn id(&self) -> window::Id {
+        self.id
+    }
+
+    /// Tabs in strip order
+    pub fn tabs(&self) -> &[Uuid] {
+        &self.tabs
+    }
+
+    /// Selected tab, if the window has any tabs
+    pub fn active_tab(&self) -> Option<Uuid> {
+        self.active_tab
+    }
+
+    /// Origin of the web app shown in this window, if it is an app window
+    pub fn app_origin(&self) -> Option<&Origin> {
+        self.app_origin.as_ref()
+    }
+
+    /// Remove a tab, selecting its neighbour if it was selected
+    fn remove_tab(&mut self, tab_id: Uuid) -> bool {
+        let Some(index) = self.tabs.iter().position(|id| *id == tab_id) else {
+            return false;
+        };
+        self.tabs.remove(index);
+        if self.active_tab == Some(tab_id) {
+            self.active_tab = self.tabs.get(index).or_else(|| self.tabs.last()).copied();
+        }
+        true
+    }
+}
+
+/// What a detached window shows of its tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetachedMode {
+    /// The page's text as a single readable column
+    Reader,
+    /// Picture-in-picture placeholder for the page's media
+    #[cfg(feature = "media")]
+    Media,
+}
+
+/// Auxiliary window showing one tab's rendered content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetachedWindow {
+    /// Window identifier
+    pub id: window::Id,
+    /// Tab whose render is shown
+    pub tab_id: Uuid,
+    /// Reader or media view
+    pub mode: DetachedMode,
+}
+
+/// Kind of an open window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowKind<'a> {
+    /// Full browser window with a tab strip
+    Browser(&'a BrowserWindow),
+    /// Always-on-top view of a single tab
+    Detached(DetachedWindow),
+}
+
+/// All open windows and which one has focus
+#[derive(Debug, Clone)]
+pub struct WindowManager {
+    windows: Vec<BrowserWindow>,
+    detached: Vec<DetachedWindow>,
+    focused: window::Id,
+}
+
+impl WindowManager {
+    /// Track the application's initial window
+    pub fn new(main: window::Id) -> Self {
+        Self {
+            windows: vec![BrowserWindow::new(main)],
+            detached: Vec::new(),
+            focused: main,
+        }
+    }
+
+    /// Register a newly spawned window and focus it
+    pub fn open(&mut self, id: window::Id) {
+        if self.get(id).is_none() {
+            self.windows.push(BrowserWindow::new(id));
+        }
+        self.focused = id;
+    }
+
+    /// Forget a window, returning its tab strip. Focus moves to the first
+    /// remaining window.
+    pub fn close(&mut self, id: window::Id) -> Option<BrowserWindow> {
+        let index = self.windows.iter().position(|w| w.id == id)?;
+        let closed = self.windows.remove(index);
+        if self.focused == id {
+            if let Some(first) = self.windows.first() {
+                self.focused = first.id;
+            }
+        }
+        Some(closed)
+    }
+
+    /// Turn a browser window into an app window for `origin`
+    pub fn set_app_origin(&mut self, id: window::Id, origin: Origin) -> bool {
+        let Some(window) = self.get_mut(id) else {
+            return false;
+        };
+        window.app_origin = Some(origin);
+        true
+    }
+
+    /// Register a detached window for a tab
+    pub fn open_detached(&mut self, id: window::Id, tab_id: Uuid, mode: DetachedMode) {
+        self.detached.push(DetachedWindow { id, tab_id, mode });
+    }
+
+    /// Forget a detached window
+    pub fn close_detached(&mut self, id: window::Id) -> Option<DetachedWindow> {
+        let index = self.detached.iter().position(|d| d.id == id)?;
+        Some(self.detached.remove(index))
+    }
+
+    /// Forget the detached windows showing a tab; returns their ids
+    pub fn close_detached_for(&mut self, tab_id: Uuid) -> Vec<window::Id> {
+        let (closed, kept): (Vec<_>, Vec<_>) =
+            self.detached.drain(..).partition(|d| d.tab_id == tab_id);
+        self.detached = kept;
+        closed.into_iter().map(|d| d.id).collect()
+    }
+
+    /// Forget every detached window; returns their ids
+    pub fn close_all_detached(&mut self) -> Vec<window::Id> {
+        self.detached.drain(..).map(|d| d.id).collect()
+    }
+
+    /// Detached windows in opening order
+    pub fn detached(&self) -> &[DetachedWindow] {
+        &self.detached
+    }
+
+    /// Look up any window
+    pub fn kind(&self, id: window::Id) -> Option<WindowKind<'_>> {
+        self.get(id).map(WindowKind::Browser).or_else(|| {
+            self.detached
+                .iter()
+                .find(|d| d.id == id)
+                .map(|d| WindowKind::Detached(*d))
+        })
+    }
+
+    /// Number of open browser windows (detached windows not included)
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Whether every browser window has been closed
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// All windows in opening order
+    pub fn windows(&self) -> &[BrowserWindow] {
+        &self.windows
+    }
+
+    /// Look up a browser window
+    pub fn get(&self, id: window::Id) -> Option<&BrowserWindow> {
+        self.windows.iter().find(|w| w.id == id)
+    }
+
+    fn get_mut(&mut self, id: window::Id) -> Option<&mut BrowserWindow> {
+        self.windows.iter_mut().find(|w| w.id == id)
+    }
+
+    /// Window that currently has focus
+    pub fn focused(&self) -> window::Id {
+        self.focused
+    }
+
+    /// Selected tab of the focused window
+    pub fn focused_tab(&self) -> Option<Uuid> {
+        self.get(self.focused).and_then(BrowserWindow::active_tab)
+    }
+
+    /// Record that a window gained focus; returns its selected tab
+    pub fn set_focused(&mut self, id: window::Id) -> Option<Uuid> {
+        let window = self.get(id)?;
+        let active = window.active_tab;
+        self.focused = id;
+        active
+    }
+
+    /// Window whose strip holds a tab
+    pub fn window_of(&self, tab_id: Uuid) -> Option<window::Id> {
+        self.windows
+            .iter()
+            .find(|w| w.tabs.contains(&tab_id))
+            .map(|w| w.id)
+    }
+
+    /// Append a tab to a window's strip. The tab is selected if the window
+    /// had no selection yet. Returns false for an unknown window.
+    pub fn add_tab(&mut self, id: window::Id, tab_id: Uuid) -> bool {
+        self.remove_tab(tab_id);
+        let Some(window) = self.get_mut(id) else {
+            return false;
+        };
+        window.tabs.push(tab_id);
+        window.active_tab.get_or_insert(tab_id);
+        true
+    }
+
+    /// Select a tab in whichever window holds it
+    pub fn select_tab(&mut self, tab_id: Uuid) {
+        if let Some(window) = self.windows.iter_mut().find(|w| w.tabs.contains(&tab_id)) {
+            window.active_tab = Some(tab_id);
+        }
+    }
+
+    /// Take a tab out of its window; returns the window it was in
+    pub fn remove_tab(&mut self, tab_id: Uuid) -> Option<window::Id> {
+        self.windows
+            .iter_mut()
+            .find_map(|w| w.remove_tab(tab_id).then_some(w.id))
+    }
+
+    /// Move a tab to another window's strip and select it there. Returns the
+    /// window it came from, or `None` if nothing moved.
+    pub fn move_tab(&mut self, tab_id: Uuid, to: window::Id) -> Option<window::Id> {
+        let from = self.window_of(tab_id)?;
+        if from == to || self.get(to).is_none() {
+            return None;
+        }
+        self.remove_tab(tab_id);
+        self.add_tab(to, tab_id);
+        self.select_tab(tab_id);
+        Some(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_window_keeps_its_own_strip_and_selection() {
+        let main = window::Id::MAIN;
+        let second = window::Id::unique();
+        let mut windows = WindowManager::new(main);
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        windows.add_tab(main, a);
+        windows.add_tab(main, b);
+        windows.open(second);
+        windows.add_tab(second, c);
+
+        assert_eq!(windows.get(main).unwrap().tabs(), &[a, b]);
+        assert_eq!(windows.get(main).unwrap().active_tab(), Some(a));
+        assert_eq!(windows.focused(), second);
+        assert_eq!(windows.focused_tab(), Some(c));
+        assert_eq!(windows.window_of(b), Some(main));
+
+        windows.select_tab(b);
+        assert_eq!(windows.set_focused(main), Some(b));
+        assert_eq!(windows.focused_tab(), Some(b));
+    }
+
+    #[test]
+    fn test_moving_tabs_between_windows() {
+        let main = window::Id::MAIN;
+        let second = window::Id::unique();
+        let mut windows = WindowManager::new(main);
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        windows.add_tab(main, a);
+        windows.add_tab(main, b);
+        windows.open(second);
+
+        assert_eq!(windows.move_tab(a, second), Some(main));
+        assert_eq!(windows.get(second).unwrap().active_tab(), Some(a));
+        // The source window selects the neighbour of the tab that left
+        assert_eq!(windows.get(main).unwrap().active_tab(), Some(b));
+        assert_eq!(windows.move_tab(a, second), None);
+
+        assert_eq!(windows.remove_tab(b), Some(main));
+        assert!(windows.get(main).unwrap().tabs().is_empty());
+        assert_eq!(windows.get(main).unwrap().active_tab(), None);
+    }
+
+    #[test]
+    fn test_closing_windows_moves_focus() {
+        let main = window::Id::MAIN;
+        let second = window::Id::unique();
+        let mut windows = WindowManager::new(main);
+        windows.open(second);
+        let tab = Uuid::new_v4();
+        windows.add_tab(second, tab);
+
+        let closed = windows.close(second).unwrap();
+        assert_eq!(closed.tabs(), &[tab]);
+        assert_eq!(windows.focused(), main);
+        assert_eq!(windows.len(), 1);
+
+        windows.close(main);
+        assert!(windows.is_empty());
+        assert!(windows.close(main).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "media")]
+    fn test_detached_windows_follow_their_tab() {
+        let main = window::Id::MAIN;
+        let mut windows = WindowManager::new(main);
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        windows.add_tab(main, a);
+        windows.add_tab(main, b);
+
+        let reader = window::Id::unique();
+        let pip = window::Id::unique();
+        let other = window::Id::unique();
+        windows.open_detached(reader, a, DetachedMode::Reader);
+        windows.open_detached(pip, a, DetachedMode::Media);
+        windows.open_detached(other, b, DetachedMode::Reader);
+
+        // Detached windows are not browser windows and never take focus
+        assert_eq!(windows.len(), 1);
+        assert!(windows.get(reader).is_none());
+        assert_eq!(windows.set_focused(reader), None);
+        assert_eq!(windows.focused(), main);
+        assert!(matches!(
+            windows.kind(pip),
+            Some(WindowKind::Detached(DetachedWindow {
+                mode: DetachedMode::Media,
+                ..
+            }))
+        ));
+        assert!(matches!(windows.kind(main), Some(WindowKind::Browser(_))));
+
+        assert_eq!(windows.close_detached_for(a), vec![reader, pip]);
+        assert_eq!(windows.detached().len(), 1);
+        assert_eq!(windows.close_detached(other).map(|d| d.tab_id), Some(b));
+        assert!(windows.kind(other).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+//! Drives the engine through navigations against local fixture pages.
+//!
+//! [`run`] hands each test a fresh [`Harness`]: a [`BrowserEngine`] and a tab
+//! manager wired together the way the app wires them, without the window.
+//! Pages come from `citadel_networking::test_server`, whose certificate the
+//! client trusts in `test-support` builds.
+
+use std::sync::Arc;
+
+use citadel_browser::app::{LoadingError, ParsedPageData};
+use citadel_browser::BrowserEngine;
+use citadel_networking::NetworkConfig;
+use citadel_security::SecurityContext;
+use citadel_tabs::{PageContent, SendSafeTabManager, TabState, TabType};
+use tokio::runtime::Runtime;
+use url::Url;
+use uuid::Uuid;
+
+pub struct Harness {
+    engine: BrowserEngine,
+    tabs: SendSafeTabManager,
+}
+
+/// Run `test` against a fresh harness
+pub fn run(test: impl AsyncFnOnce(&Harness)) {
+    let runtime = Runtime::new().expect("test runtime");
+    let harness = runtime.block_on(Harness::new());
+    runtime.block_on(test(&harness));
+    // The engine owns a runtime, which must not be dropped from async code
+    drop(harness);
+}
+
+impl Harness {
+    async fn new() -> Self {
+        let engine_runtime = Arc::new(Runtime::new().expect("engine runtime"));
+        let engine = BrowserEngine::new(
+            engine_runtime,
+            NetworkConfig::default(),
+            Arc::new(SecurityContext::new(10)),
+        )
+        .await
+        .expect("engine starts");
+        Self {
+            engine,
+            tabs: SendSafeTabManager::new(),
+        }
+    }
+
+    pub fn engine(&self) -> &BrowserEngine {
+        &self.engine
+    }
+
+    /// Open an empty ephemeral tab
+    pub async fn open_tab(&self) -> Uuid {
+        self.tabs
+            .open_tab("about:blank".to_string(), TabType::Ephemeral)
+            .await
+            .expect("tab opens")
+    }
+
+    /// Load `url` into the tab, updating its state as the app does
+    pub async fn navigate(&self, tab_id: Uuid, url: Url) -> Result<ParsedPageData, LoadingError> {
+        self.update(
+            tab_id,
+            PageContent::Loading {
+                url: url.to_string(),
+            },
+        )
+        .await;
+        let result = self
+            .engine
+            .load_page_with_progress(url, tab_id, TabType::Ephemeral)
+            .await;
+        let content = match &result {
+            Ok(page) => PageContent::Loaded {
+                url: page.url.clone(),
+                title: page.title.clone(),
+                content: page.content.clone(),
+                element_count: page.element_count,
+                size_bytes: page.size_bytes,
+            },
+            Err(error) => PageContent::Error {
+                url: error.url.clone(),
+                error: error.message.clone(),
+            },
+        };
+        self.update(tab_id, content).await;
+        result
+    }
+
+    /// The tab's current state
+    pub fn tab(&self, tab_id: Uuid) -> TabState {
+        self.tabs
+            .get_tab_states()
+            .into_iter()
+            .find(|tab| tab.id == tab_id)
+            .expect("tab is open")
+    }
+
+    async fn update(&self, tab_id: Uuid, content: PageContent) {
+        self.tabs
+            .update_page_content(tab_id, content)
+            .await
+            .expect("tab is open");
+    }
+}
@@ -0,0 +1,278 @@
+//! Navigation flows end to end: engine, tab manager and the local fixture
+//! server, with no network needed.
+
+mod harness;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use citadel_browser::app::ErrorType;
+use citadel_browser::renderer::FormSubmission;
+use citadel_networking::resource::ResourceType;
+use citadel_networking::test_server::{Fixture, TestServer};
+use citadel_networking::{CookieStoreId, LoadErrorCategory, Request};
+use citadel_tabs::{PageContent, TabType};
+use harness::run;
+use url::Url;
+
+const PAGE: &str = "<!DOCTYPE html><html><head><title>Fixture</title></head>\
+    <body><h1>Hello</h1><p>From the fixture server.</p>\
+    <script>document.title = 'Rewritten';</script></body></html>";
+
+#[test]
+fn page_load_updates_tab_state_and_metrics() {
+    run(async |h| {
+        let server = TestServer::start(&[("/", Fixture::html(PAGE))])
+            .await
+            .expect("start server");
+        let tab = h.open_tab().await;
+
+        let page = h.navigate(tab, server.url("/")).await.expect("page loads");
+        assert_eq!(page.title, "Fixture");
+        assert_eq!(page.size_bytes, PAGE.len());
+
+        let state = h.tab(tab);
+        assert_eq!(state.title, "Fixture");
+        assert!(matches!(
+            &state.content,
+            PageContent::Loaded { url, .. } if url == server.url("/").as_str()
+        ));
+
+        let summary = h.engine().request_ledger().summary(tab);
+        assert_eq!((summary.requests, summary.failed), (1, 0));
+        assert_eq!(summary.bytes, PAGE.len() as u64);
+
+        // The sanitizer dropped the script and counted it against the site
+        assert!(page
+            .security_warnings
+            .iter()
+            .any(|warning| warning.contains("JavaScript")));
+        assert!(page.escalation.recent > 0);
+        assert_eq!(server.paths(), ["/"]);
+    });
+}
+
+#[test]
+fn redirects_are_followed() {
+    run(async |h| {
+        let server = TestServer::start(&[
+            ("/moved", Fixture::redirect(301, "/here")),
+            ("/here", Fixture::html("<title>Here</title>")),
+        ])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+
+        let page = h
+            .navigate(tab, server.url("/moved"))
+            .await
+            .expect("redirect followed");
+        assert_eq!(page.title, "Here");
+        assert_eq!(h.tab(tab).title, "Here");
+        assert_eq!(server.paths(), ["/moved", "/here"]);
+    });
+}
+
+#[test]
+fn redirect_off_https_fails_closed() {
+    run(async |h| {
+        let server = TestServer::start(&[("/", Fixture::redirect(302, "http://localhost/"))])
+            .await
+            .expect("start server");
+        let tab = h.open_tab().await;
+
+        let error = h.navigate(tab, server.url("/")).await.unwrap_err();
+        assert_eq!(error.category, Some(LoadErrorCategory::InsecureConnection));
+        assert!(!error.retry_possible);
+        assert!(matches!(h.tab(tab).content, PageContent::Error { .. }));
+    });
+}
+
+#[test]
+fn untrusted_certificate_is_an_error_tab() {
+    run(async |h| {
+        let server = TestServer::untrusted().await.expect("start server");
+        let tab = h.open_tab().await;
+
+        let error = h.navigate(tab, server.url("/")).await.unwrap_err();
+        assert_eq!(error.category, Some(LoadErrorCategory::Tls));
+        assert_eq!(error.error_type, ErrorType::Network);
+        assert!(!error.retry_possible);
+        assert!(matches!(h.tab(tab).content, PageContent::Error { .. }));
+
+        // One failed attempt, never retried, and nothing reached the server
+        let summary = h.engine().request_ledger().summary(tab);
+        assert_eq!((summary.requests, summary.failed), (1, 1));
+        assert!(server.requests().is_empty());
+    });
+}
+
+#[test]
+fn server_errors_are_retried_once() {
+    run(async |h| {
+        let server = TestServer::start(&[("/busy", Fixture::status(503))])
+            .await
+            .expect("start server");
+        let tab = h.open_tab().await;
+
+        let error = h.navigate(tab, server.url("/busy")).await.unwrap_err();
+        assert_eq!(error.category, Some(LoadErrorCategory::ServerBusy));
+        assert_eq!(server.paths(), ["/busy", "/busy"]);
+        assert_eq!(h.engine().request_ledger().summary(tab).requests, 2);
+    });
+}
+
+#[test]
+fn csp_header_governs_subresources() {
+    run(async |h| {
+        let server = TestServer::start(&[(
+            "/",
+            Fixture::html("<title>Locked down</title>")
+                .with_header("Content-Security-Policy", "img-src 'self'"),
+        )])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+        h.navigate(tab, server.url("/")).await.expect("page loads");
+
+        let csp = h.engine().csp_policies();
+        let tracker = Url::parse("https://tracker.example/pixel.gif").unwrap();
+        let report = csp
+            .check(tab, &tracker, ResourceType::Image)
+            .expect("third-party image refused");
+        assert_eq!(report.effective_directive, "img-src");
+        assert!(csp
+            .check(tab, &server.url("/logo.png"), ResourceType::Image)
+            .is_none());
+    });
+}
+
+#[test]
+fn form_submission_navigates_to_the_action() {
+    run(async |h| {
+        let server = TestServer::start(&[
+            (
+                "/",
+                Fixture::html(
+                    "<title>Search</title><form action=\"/search\"><input name=\"q\"></form>",
+                ),
+            ),
+            ("/search", Fixture::html("<title>Results</title>")),
+        ])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+        h.navigate(tab, server.url("/")).await.expect("page loads");
+
+        let target = h
+            .engine()
+            .submit_form(FormSubmission {
+                action: server.url("/search").to_string(),
+                method: "GET".to_string(),
+                data: HashMap::from([("q".to_string(), "citadel".to_string())]),
+                form_id: "search".to_string(),
+            })
+            .await
+            .expect("form submits");
+        let page = h
+            .navigate(tab, Url::parse(&target).unwrap())
+            .await
+            .expect("results load");
+        assert_eq!(page.title, "Results");
+        assert_eq!(h.tab(tab).title, "Results");
+        assert_eq!(server.paths(), ["/", "/search"]);
+    });
+}
+
+#[test]
+fn slow_responses_are_timed() {
+    run(async |h| {
+        let server = TestServer::start(&[(
+            "/",
+            Fixture::html("<title>Slow</title>").delayed(Duration::from_millis(300)),
+        )])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+
+        let page = h.navigate(tab, server.url("/")).await.expect("page loads");
+        assert!(page.load_time_ms >= 300, "{}ms", page.load_time_ms);
+        assert_eq!(h.tab(tab).title, "Slow");
+    });
+}
+
+#[test]
+fn cross_site_subresources_neither_send_nor_store_cookies() {
+    run(async |h| {
+        let server = TestServer::start(&[(
+            "/font.woff2",
+            Fixture::html("not a font").with_header("Set-Cookie", "tracker=1; Secure"),
+        )])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+        let store = CookieStoreId::Ephemeral(tab);
+        let jar = h.engine().cookie_jar();
+        let own_site = server.url("/");
+        jar.set_cookies(store, &own_site, &own_site, ["sid=1; Secure"]);
+
+        // Embedded by a page of another site, the server is third party
+        let document = Url::parse("https://news.example/").unwrap();
+        let _ = h
+            .engine()
+            .fetch_font(
+                &server.url("/font.woff2"),
+                &document,
+                tab,
+                TabType::Ephemeral,
+            )
+            .await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("cookie"), None);
+        let names: Vec<String> = jar
+            .cookies_for_origin(store, &own_site)
+            .into_iter()
+            .map(|cookie| cookie.name)
+            .collect();
+        assert_eq!(names, ["sid"]);
+    });
+}
+
+#[test]
+fn panic_wipe_clears_cookies_set_for_tab_vms() {
+    run(async |h| {
+        let server = TestServer::start(&[(
+            "/data.json",
+            Fixture::html("{}").with_header("Set-Cookie", "session=1; Secure"),
+        )])
+        .await
+        .expect("start server");
+        let tab = h.open_tab().await;
+        let manager = h
+            .engine()
+            .tab_resource_manager()
+            .await
+            .expect("manager starts");
+        let url = server.url("/data.json");
+
+        let request = Request::builder()
+            .url(url.as_str())
+            .build()
+            .expect("request builds");
+        manager
+            .fetch_request_for_tab(tab, request, ResourceType::Fetch)
+            .await
+            .expect("fetched");
+        let jar = h.engine().cookie_jar();
+        assert_eq!(jar.cookies_for_origin(CookieStoreId::Shared, &url).len(), 1);
+
+        // The panic button wipes the engine, and the tab VMs' jar with it
+        h.engine().wipe_session_state();
+        assert!(manager
+            .cookie_jar()
+            .cookies_for_origin(CookieStoreId::Shared, &url)
+            .is_empty());
+    });
+}
@@ -0,0 +1,254 @@
+//! Shared error taxonomy for Citadel
+//!
+//! Each crate keeps its own error enum and implements [`Classify`] for it,
+//! giving every error a broad [`ErrorKind`], a [`Severity`], a stable
+//! machine-readable code and a message fit to show the user. Any classified
+//! error converts into [`CitadelError`] with `?`, so errors cross crate
+//! boundaries with that structure intact instead of as formatted strings,
+//! and the UI renders every error page the same way.
+
+use std::error::Error;
+use std::fmt;
+
+/// How serious an error is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth noting; the operation still completed
+    Info,
+    /// The operation was degraded (e.g. a subresource was dropped)
+    Warning,
+    /// The operation failed
+    Error,
+    /// A protection was breached or isolation was lost
+    Critical,
+}
+
+/// Broad category of an error, independent of the crate it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Connection failures and HTTP errors
+    Network,
+    /// Name resolution failures
+    Dns,
+    /// Certificate and handshake failures
+    Tls,
+    /// An operation ran out of time
+    Timeout,
+    /// A request was refused to protect the user's privacy
+    Privacy,
+    /// A security policy was violated
+    Security,
+    /// Malformed or unparseable content
+    Content,
+    /// A resource limit or budget was exceeded
+    Resource,
+    /// Failure of a tab's isolated VM or its channels
+    Isolation,
+    /// A bug or unexpected state in the browser itself
+    Internal,
+}
+
+impl ErrorKind {
+    /// Severity of errors of this kind unless the error says otherwise
+    pub fn default_severity(self) -> Severity {
+        match self {
+            ErrorKind::Security | ErrorKind::Isolation => Severity::Critical,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Whether errors of this kind are usually transient
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Network | ErrorKind::Dns | ErrorKind::Timeout
+        )
+    }
+
+    /// Title for an error page
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorKind::Network => "Can't reach this page",
+            ErrorKind::Dns => "Site not found",
+            ErrorKind::Tls => "Connection not secure",
+            ErrorKind::Timeout => "Page took too long to respond",
+            ErrorKind::Privacy => "Blocked to protect your privacy",
+            ErrorKind::Security => "Blocked for your security",
+            ErrorKind::Content => "Page can't be displayed",
+            ErrorKind::Resource => "Page is too large",
+            ErrorKind::Isolation => "Tab isolation failed",
+            ErrorKind::Internal => "Something went wrong",
+        }
+    }
+
+    /// One-sentence explanation for an error page
+    pub fn user_message(self) -> &'static str {
+        match self {
+            ErrorKind::Network => "The connection to the site failed.",
+            ErrorKind::Dns => "The site's address could not be found.",
+            ErrorKind::Tls => "The site's certificate or encryption could not be verified.",
+            ErrorKind::Timeout => "The site did not respond in time.",
+            ErrorKind::Privacy => "Loading this would have exposed information about you.",
+            ErrorKind::Security => "The page tried to do something Citadel does not allow.",
+            ErrorKind::Content => "The page's content is malformed.",
+            ErrorKind::Resource => "The page exceeded Citadel's resource limits.",
+            ErrorKind::Isolation => "The tab's sandbox stopped working; close and reopen it.",
+            ErrorKind::Internal => "Citadel hit an unexpected error.",
+        }
+    }
+}
+
+/// Classification of a crate's error type
+pub trait Classify: Error {
+    /// Broad category
+    fn kind(&self) -> ErrorKind;
+
+    /// Stable machine-readable code, e.g. `"NET_DNS"`
+    fn code(&self) -> &'static str;
+
+    /// How serious the error is
+    fn severity(&self) -> Severity {
+        self.kind().default_severity()
+    }
+
+    /// Whether retrying the operation may succeed
+    fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+}
+
+/// A classified error from any Citadel crate
+#[derive(Debug)]
+pub struct CitadelError {
+    kind: ErrorKind,
+    code: &'static str,
+    severity: Severity,
+    retryable: bool,
+    message: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl CitadelError {
+    /// An error raised directly rather than converted from a crate error
+    pub fn new(kind: ErrorKind, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            code,
+            severity: kind.default_severity(),
+            retryable: kind.is_retryable(),
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    /// Technical detail, for logs and the error page's details section
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Title for an error page
+    pub fn title(&self) -> &'static str {
+        self.kind.title()
+    }
+
+    /// Explanation for an error page
+    pub fn user_message(&self) -> &'static str {
+        self.kind.user_message()
+    }
+}
+
+impl<E: Classify + Send + Sync + 'static> From<E> for CitadelError {
+    fn from(error: E) -> Self {
+        Self {
+            kind: error.kind(),
+            code: error.code(),
+            severity: error.severity(),
+            retryable: error.is_retryable(),
+            message: error.to_string(),
+            source: Some(Box::new(error)),
+        }
+    }
+}
+
+impl fmt::Display for CitadelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+impl Error for CitadelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+/// Result type for code that mixes errors from several crates
+pub type CitadelResult<T> = Result<T, CitadelError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Refused;
+
+    impl fmt::Display for Refused {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "connection refused")
+        }
+    }
+
+    impl Error for Refused {}
+
+    impl Classify for Refused {
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Network
+        }
+
+        fn code(&self) -> &'static str {
+            "TEST_REFUSED"
+        }
+    }
+
+    fn connect() -> CitadelResult<()> {
+        Err(Refused)?
+    }
+
+    #[test]
+    fn test_conversion_keeps_classification() {
+        let error = connect().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Network);
+        assert_eq!(error.code(), "TEST_REFUSED");
+        assert_eq!(error.severity(), Severity::Error);
+        assert!(error.is_retryable());
+        assert_eq!(error.to_string(), "[TEST_REFUSED] connection refused");
+        assert!(error.source().unwrap().is::<Refused>());
+    }
+
+    #[test]
+    fn test_direct_errors() {
+        let error = CitadelError::new(ErrorKind::Security, "SEC_TEST", "blocked");
+        assert_eq!(error.severity(), Severity::Critical);
+        assert!(!error.is_retryable());
+        assert!(error.source().is_none());
+        assert_eq!(error.title(), "Blocked for your security");
+    }
+}
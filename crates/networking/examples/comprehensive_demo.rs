@@ -6,7 +6,7 @@ use citadel_networking::{
     integrity::{HashAlgorithm, IntegrityValidator},
     resource_loader::{LoadOptions, ResourceLoader},
     resource_manager::{CachePolicy, ResourceManager, ResourceManagerConfig, ResourcePolicy},
-    NetworkConfig, PrivacyLevel,
+    NetworkConfig, PrivacyLevel, RequestBudget,
 };
 use tokio::sync::mpsc;
 use url::Url;
@@ -265,6 +265,7 @@ async fn demo_resource_manager_privacy() -> Result<(), Box<dyn std::error::Error
         cache_policy: CachePolicy::AlwaysValidate,
         max_cache_size_mb: 50,
         default_cache_ttl: Duration::from_secs(1800),
+        request_budget: RequestBudget::for_privacy_level(PrivacyLevel::Maximum),
     };
 
     let manager = ResourceManager::with_config(config).await?;
@@ -23,6 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         randomize_user_agent: true,
         strip_tracking_params: true,
         tracker_blocking: citadel_networking::BlocklistConfig::default(),
+        socks_proxy: None,
     };
 
     // Configure resource loading with reasonable limits
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -9,6 +10,8 @@ use url::Url;
 
 use crate::cache::ResourceCache;
 use crate::error::NetworkError;
+use crate::preload_scanner::PreloadScanner;
+use crate::proxy;
 use crate::resource::{Resource, ResourceType};
 use crate::resource_discovery::{ResourceContext, ResourceDiscovery, ResourceRef};
 use crate::resource_loader::{LoadOptions, LoadProgress, LoadResult};
@@ -30,6 +33,18 @@ pub enum Priority {
     Preload = 4,
 }
 
+impl Priority {
+    /// One level lower, used for tabs loading in the background
+    pub fn demoted(self) -> Self {
+        match self {
+            Priority::Critical => Priority::High,
+            Priority::High => Priority::Medium,
+            Priority::Medium => Priority::Low,
+            Priority::Low | Priority::Preload => Priority::Preload,
+        }
+    }
+}
+
 /// Resource loading strategy
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LoadingStrategy {
@@ -127,6 +142,9 @@ pub struct AdvancedProgress {
     pub estimated_completion: Option<Duration>,
     /// Critical path blocking resources
     pub critical_blocking: usize,
+    /// Time from the start of the load until the render-blocking resources
+    /// were in, once they are
+    pub first_contentful_render: Option<Duration>,
 }
 
 impl AdvancedProgress {
@@ -138,6 +156,7 @@ impl AdvancedProgress {
             priority_breakdown: HashMap::new(),
             estimated_completion: None,
             critical_blocking: 0,
+            first_contentful_render: None,
         }
     }
 
@@ -182,6 +201,16 @@ pub struct AdvancedResourceLoader {
 
     /// Preload queue for future resources  
     preload_queue: Arc<Mutex<VecDeque<ResourceRef>>>,
+
+    /// Whether the owning tab is in the background (demoted, one request at a time)
+    background: Arc<AtomicBool>,
+
+    /// Whether the device saves power (no speculative loads, non-critical
+    /// resources demoted, preloads deferred)
+    power_saving: Arc<AtomicBool>,
+
+    /// When the owning tab's last load could first paint
+    first_contentful_render: Arc<Mutex<Option<Duration>>>,
 }
 
 impl AdvancedResourceLoader {
@@ -212,6 +241,9 @@ impl AdvancedResourceLoader {
             priority_queue: Arc::new(Mutex::new(HashMap::new())),
             progress_tx: None,
             preload_queue: Arc::new(Mutex::new(VecDeque::new())),
+            background: Arc::new(AtomicBool::new(false)),
+            power_saving: Arc::new(AtomicBool::new(false)),
+            first_contentful_render: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -221,6 +253,49 @@ impl AdvancedResourceLoader {
         self
     }
 
+    /// Downgrade network priority while the owning tab is in the background;
+    /// passing `false` restores full priority and concurrency
+    pub fn set_background(&self, background: bool) {
+        self.background.store(background, Ordering::Relaxed);
+    }
+
+    /// Whether loads are currently throttled for a background tab
+    pub fn is_background(&self) -> bool {
+        self.background.load(Ordering::Relaxed)
+    }
+
+    /// Defer what a page does not need to show while the device saves
+    /// power: speculative loads are skipped, resources not marked critical
+    /// are demoted and the preload queue is held until this is turned off
+    pub fn set_power_saving(&self, power_saving: bool) {
+        self.power_saving.store(power_saving, Ordering::Relaxed);
+    }
+
+    /// Whether non-critical fetching is deferred to save power
+    pub fn is_power_saving(&self) -> bool {
+        self.power_saving.load(Ordering::Relaxed)
+    }
+
+    /// Concurrent request limit for a priority level
+    fn max_concurrent(&self, priority: Priority) -> usize {
+        if self.is_background() {
+            return 1;
+        }
+        self.max_concurrent_per_priority
+            .get(&priority)
+            .copied()
+            .unwrap_or(4)
+    }
+
+    /// Time from the start of the last load until its render-blocking
+    /// resources were in, for the performance dashboard
+    pub fn first_contentful_render(&self) -> Option<Duration> {
+        self.first_contentful_render
+            .lock()
+            .ok()
+            .and_then(|recorded| *recorded)
+    }
+
     /// Load resources with advanced prioritization and adaptive loading
     pub async fn load_with_strategy(
         &self,
@@ -228,6 +303,10 @@ impl AdvancedResourceLoader {
         base_url: Url,
         options: LoadOptions,
     ) -> Result<LoadResult, NetworkError> {
+        let started = Instant::now();
+        if let Ok(mut recorded) = self.first_contentful_render.lock() {
+            *recorded = None;
+        }
         let context = ResourceContext::new(base_url.clone());
 
         // Discover all resources
@@ -236,7 +315,22 @@ impl AdvancedResourceLoader {
         // Prioritize resources based on type and context
         let prioritized = self.prioritize_resources(discovered, &base_url);
 
-        // Execute loading strategy
+        // First paint waits on the render-blocking resources alone; everything
+        // else starts once they are in
+        let (blocking, deferred) = self.split_render_blocking(prioritized, &base_url);
+        let first_paint = self.run_strategy(blocking, options.clone()).await?;
+        self.record_first_contentful_render(started.elapsed());
+        let rest = self.run_strategy(deferred, options).await?;
+
+        Ok(merge_load_results(first_paint, rest, started.elapsed()))
+    }
+
+    /// Execute the loading strategy over one set of resources
+    async fn run_strategy(
+        &self,
+        prioritized: HashMap<Priority, Vec<ResourceRef>>,
+        options: LoadOptions,
+    ) -> Result<LoadResult, NetworkError> {
         match self.strategy {
             LoadingStrategy::Sequential => self.load_sequential(prioritized, options).await,
             LoadingStrategy::Parallel => self.load_parallel(prioritized, options).await,
@@ -245,6 +339,51 @@ impl AdvancedResourceLoader {
         }
     }
 
+    /// Whether first paint has to wait for the resource: stylesheets that
+    /// apply to the screen, and fonts from the page's own origin
+    fn blocks_render(&self, resource: &ResourceRef, base_url: &Url) -> bool {
+        if is_speculative(resource) {
+            return false;
+        }
+        match resource.resource_type {
+            ResourceType::Css => resource.is_critical,
+            ResourceType::Font => resource.url.origin() == base_url.origin(),
+            _ => false,
+        }
+    }
+
+    /// Separate the render-blocking resources from those that can load after
+    /// first paint
+    fn split_render_blocking(
+        &self,
+        prioritized: HashMap<Priority, Vec<ResourceRef>>,
+        base_url: &Url,
+    ) -> (
+        HashMap<Priority, Vec<ResourceRef>>,
+        HashMap<Priority, Vec<ResourceRef>>,
+    ) {
+        let mut blocking: HashMap<Priority, Vec<ResourceRef>> = HashMap::new();
+        let mut deferred: HashMap<Priority, Vec<ResourceRef>> = HashMap::new();
+        for (priority, resources) in prioritized {
+            for resource in resources {
+                let target = if self.blocks_render(&resource, base_url) {
+                    &mut blocking
+                } else {
+                    &mut deferred
+                };
+                target.entry(priority).or_default().push(resource);
+            }
+        }
+        (blocking, deferred)
+    }
+
+    fn record_first_contentful_render(&self, elapsed: Duration) {
+        log::debug!("Render-blocking resources loaded after {:?}", elapsed);
+        if let Ok(mut recorded) = self.first_contentful_render.lock() {
+            *recorded = Some(elapsed);
+        }
+    }
+
     /// Prioritize resources based on type, location, and user interaction patterns
     fn prioritize_resources(
         &self,
@@ -252,9 +391,17 @@ impl AdvancedResourceLoader {
         base_url: &Url,
     ) -> HashMap<Priority, Vec<ResourceRef>> {
         let mut prioritized: HashMap<Priority, Vec<ResourceRef>> = HashMap::new();
+        let speculative_allowed =
+            proxy::speculative_loads_allowed(base_url) && !self.is_power_saving();
 
         for resource in resources {
-            let priority = self.calculate_priority(&resource, base_url);
+            if !speculative_allowed && is_speculative(&resource) {
+                continue;
+            }
+            let mut priority = self.calculate_priority(&resource, base_url);
+            if self.is_background() || (self.is_power_saving() && !resource.is_critical) {
+                priority = priority.demoted();
+            }
             prioritized
                 .entry(priority)
                 .or_insert_with(Vec::new)
@@ -294,11 +441,7 @@ impl AdvancedResourceLoader {
     fn get_next_priority_batch(&self, priority: Priority) -> Vec<ResourceRef> {
         if let Ok(mut queue) = self.priority_queue.lock() {
             if let Some(resources) = queue.get_mut(&priority) {
-                let batch_size = self
-                    .max_concurrent_per_priority
-                    .get(&priority)
-                    .copied()
-                    .unwrap_or(4);
+                let batch_size = self.max_concurrent(priority);
                 resources.drain(..resources.len().min(batch_size)).collect()
             } else {
                 Vec::new()
@@ -318,11 +461,17 @@ impl AdvancedResourceLoader {
 
     /// Calculate resource priority based on multiple factors
     fn calculate_priority(&self, resource: &ResourceRef, base_url: &Url) -> Priority {
+        // Everything first paint waits on comes right after the document
+        if self.blocks_render(resource, base_url) {
+            return Priority::High;
+        }
+
         // Start with basic type-based priority
         let base_priority = match resource.resource_type {
             ResourceType::Html => Priority::Critical,
-            ResourceType::Css => Priority::Critical,
-            ResourceType::Font => Priority::High,
+            // Stylesheets for other media and third-party fonts
+            ResourceType::Css | ResourceType::Font => Priority::Medium,
+            ResourceType::Script if is_async_script(resource) => Priority::Low,
             ResourceType::Script => Priority::Medium,
             ResourceType::Image => Priority::Low,
             _ => Priority::Low,
@@ -365,11 +514,6 @@ impl AdvancedResourceLoader {
         // Simple heuristics - in a real implementation this would be more sophisticated
         let url_str = resource.url.as_str();
 
-        // CSS files are typically above the fold
-        if resource.resource_type == ResourceType::Css {
-            return true;
-        }
-
         // Images with certain patterns are likely above fold
         if resource.resource_type == ResourceType::Image {
             return url_str.contains("logo")
@@ -470,11 +614,7 @@ impl AdvancedResourceLoader {
             Priority::Preload,
         ] {
             if let Some(resources) = prioritized.get(&priority) {
-                let max_concurrent = self
-                    .max_concurrent_per_priority
-                    .get(&priority)
-                    .unwrap_or(&4);
-                let semaphore = Arc::new(Semaphore::new(*max_concurrent));
+                let semaphore = Arc::new(Semaphore::new(self.max_concurrent(priority)));
 
                 let tasks: Vec<_> = resources
                     .iter()
@@ -566,11 +706,7 @@ impl AdvancedResourceLoader {
 
         for priority in remaining_priorities {
             if let Some(resources) = prioritized.get(&priority) {
-                let max_concurrent = self
-                    .max_concurrent_per_priority
-                    .get(&priority)
-                    .unwrap_or(&4);
-                let semaphore = Arc::new(Semaphore::new(*max_concurrent));
+                let semaphore = Arc::new(Semaphore::new(self.max_concurrent(priority)));
 
                 for resource in resources {
                     let semaphore = Arc::clone(&semaphore);
@@ -705,7 +841,9 @@ impl AdvancedResourceLoader {
     /// Send progress update if channel is available
     fn send_progress_update(&self, progress: &AdvancedProgress) {
         if let Some(tx) = &self.progress_tx {
-            let _ = tx.send(progress.clone());
+            let mut progress = progress.clone();
+            progress.first_contentful_render = self.first_contentful_render();
+            let _ = tx.send(progress);
         }
     }
 
@@ -718,8 +856,37 @@ impl AdvancedResourceLoader {
         }
     }
 
-    /// Process preload queue in background
+    /// Feed the next chunk of a document that is still arriving to `scanner`
+    /// and queue what it finds for early loading, most urgent first.
+    /// Returns how many resources were queued.
+    pub fn scan_for_preloads(&self, scanner: &mut PreloadScanner, chunk: &[u8]) -> usize {
+        let base_url = scanner.base_url().clone();
+        let speculative_allowed =
+            proxy::speculative_loads_allowed(&base_url) && !self.is_power_saving();
+        let found: Vec<ResourceRef> = scanner
+            .feed(chunk, &self.discovery)
+            .into_iter()
+            .filter(|resource| speculative_allowed || !is_speculative(resource))
+            .collect();
+        let queued = found.len();
+        if queued > 0 {
+            if let Ok(mut queue) = self.preload_queue.lock() {
+                queue.extend(found);
+                // Stable, so equal priorities keep document order
+                queue
+                    .make_contiguous()
+                    .sort_by_key(|resource| self.calculate_priority(resource, &base_url));
+            }
+        }
+        queued
+    }
+
+    /// Process preload queue in background; while saving power the queue
+    /// is left for a later call
     pub async fn process_preload_queue(&self, options: LoadOptions) {
+        if self.is_power_saving() {
+            return;
+        }
         let mut processed = 0;
         const MAX_PRELOAD_BATCH: usize = 10;
 
@@ -759,6 +926,45 @@ impl AdvancedResourceLoader {
     }
 }
 
+/// Whether a script was marked `async` or `defer` and so never holds up
+/// rendering
+fn is_async_script(resource: &ResourceRef) -> bool {
+    resource
+        .metadata
+        .get("loading")
+        .is_some_and(|loading| loading == "async" || loading == "defer")
+}
+
+/// Combine the render-blocking phase of a load with the rest of it
+fn merge_load_results(mut first: LoadResult, rest: LoadResult, total_time: Duration) -> LoadResult {
+    let progress = &mut first.progress;
+    progress.total += rest.progress.total;
+    progress.loaded += rest.progress.loaded;
+    progress.failed += rest.progress.failed;
+    progress.cached += rest.progress.cached;
+    progress.bytes_loaded += rest.progress.bytes_loaded;
+    progress.phase = rest.progress.phase;
+    progress
+        .resource_details
+        .extend(rest.progress.resource_details);
+    first.responses.extend(rest.responses);
+    first.errors.extend(rest.errors);
+    first.total_time = total_time;
+    first
+}
+
+/// Whether a resource was only hinted at (prefetch, icons) rather than
+/// needed to render the page
+fn is_speculative(resource: &ResourceRef) -> bool {
+    resource.metadata.get("rel").is_some_and(|rel| {
+        rel.split_ascii_whitespace().any(|rel| {
+            ["prefetch", "dns-prefetch", "prerender", "icon"]
+                .iter()
+                .any(|hint| rel.eq_ignore_ascii_case(hint))
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -788,4 +994,122 @@ mod tests {
         let loader = AdvancedResourceLoader::new(config, LoadingStrategy::Adaptive).await;
         assert!(loader.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_background_throttling() {
+        let loader =
+            AdvancedResourceLoader::new(NetworkConfig::default(), LoadingStrategy::Parallel)
+                .await
+                .unwrap();
+        assert_eq!(loader.max_concurrent(Priority::Critical), 8);
+
+        loader.set_background(true);
+        assert!(loader.is_background());
+        assert_eq!(loader.max_concurrent(Priority::Critical), 1);
+        assert_eq!(Priority::Critical.demoted(), Priority::High);
+        assert_eq!(Priority::Preload.demoted(), Priority::Preload);
+
+        loader.set_background(false);
+        assert_eq!(loader.max_concurrent(Priority::Critical), 8);
+    }
+
+    #[tokio::test]
+    async fn test_onion_pages_skip_speculative_loads() {
+        let loader =
+            AdvancedResourceLoader::new(NetworkConfig::default(), LoadingStrategy::Parallel)
+                .await
+                .unwrap();
+        let resources = || {
+            vec![
+                ResourceRef::new(
+                    Url::parse("https://abc.onion/style.css").unwrap(),
+                    ResourceType::Css,
+                ),
+                ResourceRef::new(
+                    Url::parse("https://abc.onion/next.html").unwrap(),
+                    ResourceType::Other,
+                )
+                .with_metadata("rel", "prefetch"),
+            ]
+        };
+        let count = |base: &str| {
+            loader
+                .prioritize_resources(resources(), &Url::parse(base).unwrap())
+                .values()
+                .map(Vec::len)
+                .sum::<usize>()
+        };
+        assert_eq!(count("https://abc.onion/"), 1);
+        assert_eq!(count("https://example.com/"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_power_saving_defers_non_critical_loads() {
+        let loader =
+            AdvancedResourceLoader::new(NetworkConfig::default(), LoadingStrategy::Parallel)
+                .await
+                .unwrap();
+        let base = Url::parse("https://example.com/").unwrap();
+        let prefetch = ResourceRef::new(
+            Url::parse("https://example.com/next.html").unwrap(),
+            ResourceType::Other,
+        )
+        .with_metadata("rel", "prefetch");
+        let image = ResourceRef::new(
+            Url::parse("https://example.com/photo.png").unwrap(),
+            ResourceType::Image,
+        );
+        let normal = loader.calculate_priority(&image, &base);
+
+        loader.set_power_saving(true);
+        assert!(loader.is_power_saving());
+        let prioritized = loader.prioritize_resources(vec![prefetch, image.clone()], &base);
+        assert_eq!(prioritized.values().map(Vec::len).sum::<usize>(), 1);
+        assert_eq!(prioritized.get(&normal.demoted()).map(Vec::len), Some(1));
+
+        loader.queue_preload(vec![image]);
+        loader.process_preload_queue(LoadOptions::default()).await;
+        assert_eq!(loader.preload_queue.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_render_blocking_resources_come_first() {
+        let loader =
+            AdvancedResourceLoader::new(NetworkConfig::default(), LoadingStrategy::Parallel)
+                .await
+                .unwrap();
+        let base = Url::parse("https://example.com/").unwrap();
+        let discovered = loader
+            .discovery
+            .discover_all(
+                "<link rel=\"stylesheet\" href=\"/site.css\">\
+                 <link rel=\"preload\" as=\"font\" href=\"/body.woff2\">\
+                 <link rel=\"preload\" as=\"font\" href=\"https://fonts.test/x.woff2\">\
+                 <script async src=\"/analytics.js\"></script>\
+                 <img src=\"/photo.jpg\">",
+                &ResourceContext::new(base.clone()),
+            )
+            .unwrap();
+        let priority = |path: &str| {
+            let resource = discovered
+                .iter()
+                .find(|r| r.url.as_str().ends_with(path))
+                .unwrap();
+            (
+                loader.calculate_priority(resource, &base),
+                loader.blocks_render(resource, &base),
+            )
+        };
+        assert_eq!(priority("/site.css"), (Priority::High, true));
+        assert_eq!(priority("/body.woff2"), (Priority::High, true));
+        assert!(!priority("/x.woff2").1);
+        assert_eq!(priority("/analytics.js"), (Priority::Low, false));
+        assert_eq!(priority("/photo.jpg"), (Priority::Low, false));
+
+        let prioritized = loader.prioritize_resources(discovered, &base);
+        let (blocking, deferred) = loader.split_render_blocking(prioritized, &base);
+        assert_eq!(blocking.values().map(Vec::len).sum::<usize>(), 2);
+        assert_eq!(deferred.values().map(Vec::len).sum::<usize>(), 3);
+        assert_eq!(loader.first_contentful_render(), None);
+    }
 }
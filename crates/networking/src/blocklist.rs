@@ -0,0 +1,853 @@
+//! Blocking requests by filter list rules
+//!
+//! The [`BlocklistEngine`] decides whether the installed [`FilterLists`]
+//! block a request. It understands the subset of EasyList / uBlock Origin
+//! network filter syntax that most lists are written in:
+//!
+//! - `||ads.example^` domain anchors, `|https://` and `.gif|` start and end
+//!   anchors, `*` wildcards and `^` separators,
+//! - `@@` exceptions,
+//! - the options `third-party` (`3p`), `first-party` (`1p`,
+//!   `~third-party`), resource types (`script`, `image`, `stylesheet`,
+//!   `font`, `media`, `xmlhttprequest`, `subdocument`, `document`, `other`,
+//!   each negatable with `~`), `domain=a.example|~b.example`, `match-case`
+//!   and `important`.
+//!
+//! Rules with other options, and regular expression rules, are skipped
+//! rather than guessed at. Plain domain rules live in the lists' domain
+//! tries; the others are compiled into [`NetworkRule`]s and indexed by a
+//! token each one must contain, so a request is only tested against rules
+//! sharing a token with its URL.
+//!
+//! An exception lets through what a rule blocks, unless the blocking rule
+//! is `important`. Sites can be allowlisted: requests made by their pages
+//! are never blocked. The engine counts the requests it checked, blocked
+//! and let through by exception or allowlist, and reports blocks to the
+//! privacy scoreboard.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use citadel_security::privacy::{PrivacyEvent, PrivacyEventSender, TrackerCategory};
+use url::{Position, Url};
+
+use crate::filter_list::FilterLists;
+use crate::resource::ResourceType;
+use crate::resource_manager::ResourceManager;
+
+/// URL tokens too common to narrow down the rules worth testing
+const COMMON_TOKENS: &[&str] = &["http", "https", "www", "com"];
+
+/// One piece of a rule's URL pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// `*`: any run of characters
+    Wildcard,
+    /// `^`: one character that cannot be in a host or path word, or the
+    /// end of the URL
+    Separator,
+}
+
+/// Where a pattern must start matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    /// Anywhere in the URL
+    None,
+    /// `||`: at the start of the host or one of its labels
+    Host,
+    /// `|`: at the start of the URL
+    Start,
+}
+
+/// Bit of a resource type in a rule's type mask
+fn type_bit(resource_type: ResourceType) -> u16 {
+    1 << match resource_type {
+        ResourceType::Html => 0,
+        ResourceType::Css => 1,
+        ResourceType::Script => 2,
+        ResourceType::Image => 3,
+        ResourceType::Font => 4,
+        ResourceType::Json => 5,
+        ResourceType::Xml => 6,
+        ResourceType::Text => 7,
+        ResourceType::Binary => 8,
+        ResourceType::Other => 9,
+        ResourceType::Fetch => 10,
+        ResourceType::WebSocket => 11,
+    }
+}
+
+/// Every resource type
+const ALL_TYPES: u16 = (1 << 12) - 1;
+
+/// Resource types a type option names. Requests made by scripts load as
+/// data, and media as binary.
+fn option_types(name: &str) -> Option<u16> {
+    let types: &[ResourceType] = match name {
+        "script" => &[ResourceType::Script],
+        "image" => &[ResourceType::Image],
+        "stylesheet" | "css" => &[ResourceType::Css],
+        "font" => &[ResourceType::Font],
+        "media" => &[ResourceType::Binary],
+        "xmlhttprequest" | "xhr" => &[
+            ResourceType::Json,
+            ResourceType::Xml,
+            ResourceType::Text,
+            ResourceType::Fetch,
+        ],
+        "document" | "doc" | "subdocument" | "frame" => &[ResourceType::Html],
+        "websocket" => &[ResourceType::WebSocket],
+        "other" => &[ResourceType::Other],
+        _ => return None,
+    };
+    Some(types.iter().fold(0, |mask, t| mask | type_bit(*t)))
+}
+
+/// Whether `byte` is one a `^` matches
+fn is_separator(byte: u8) -> bool {
+    !(byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn on_domain(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// A network filter rule with a URL pattern or options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkRule {
+    /// The rule as written in its list
+    source: String,
+    exception: bool,
+    important: bool,
+    anchor: Anchor,
+    end_anchor: bool,
+    parts: Vec<Part>,
+    match_case: bool,
+    /// `Some(true)` for third-party requests only, `Some(false)` for
+    /// first-party ones only
+    third_party: Option<bool>,
+    /// Resource types the rule applies to
+    types: u16,
+    /// Sites whose pages the rule applies to; any when empty
+    domains: Vec<String>,
+    /// Sites whose pages the rule does not apply to
+    excluded_domains: Vec<String>,
+}
+
+impl NetworkRule {
+    /// Parse a rule line; `None` for comments, element-hiding rules and
+    /// rules using syntax outside the supported subset
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        // Hosts file lines and comments have spaces or start with `#`
+        if line.is_empty()
+            || line.starts_with(['!', '[', '#'])
+            || line.contains(char::is_whitespace)
+            || ["##", "#@#", "#?#", "#$#"]
+                .iter()
+                .any(|marker| line.contains(marker))
+        {
+            return None;
+        }
+        let source = line.to_string();
+        let (line, exception) = match line.strip_prefix("@@") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        let (pattern, options) = match line.rsplit_once('$') {
+            Some((pattern, options)) => (pattern, Some(options)),
+            None => (line, None),
+        };
+
+        let mut rule = Self {
+            source,
+            exception,
+            important: false,
+            anchor: Anchor::None,
+            end_anchor: false,
+            parts: Vec::new(),
+            match_case: false,
+            third_party: None,
+            types: ALL_TYPES,
+            domains: Vec::new(),
+            excluded_domains: Vec::new(),
+        };
+        if let Some(options) = options {
+            rule.parse_options(options)?;
+        }
+
+        // Regular expressions are not supported
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            return None;
+        }
+        let pattern = if let Some(rest) = pattern.strip_prefix("||") {
+            rule.anchor = Anchor::Host;
+            rest
+        } else if let Some(rest) = pattern.strip_prefix('|') {
+            rule.anchor = Anchor::Start;
+            rest
+        } else {
+            pattern
+        };
+        let pattern = match pattern.strip_suffix('|') {
+            Some(rest) => {
+                rule.end_anchor = true;
+                rest
+            }
+            None => pattern,
+        };
+        if !pattern.is_ascii() {
+            return None;
+        }
+        rule.parts = rule.compile_pattern(pattern);
+
+        // A rule matching every request of every page is a list mistake
+        let has_literal = rule.parts.iter().any(|p| matches!(p, Part::Literal(_)));
+        if !has_literal && rule.domains.is_empty() {
+            return None;
+        }
+        Some(rule)
+    }
+
+    /// Apply the options after `$`; `None` if one is not understood
+    fn parse_options(&mut self, options: &str) -> Option<()> {
+        let (mut included, mut excluded) = (0, 0);
+        for option in options.split(',').map(str::trim) {
+            let (negated, name) = match option.strip_prefix('~') {
+                Some(name) => (true, name),
+                None => (false, option),
+            };
+            match (negated, name) {
+                (false, "third-party" | "3p") => self.third_party = Some(true),
+                (true, "third-party" | "3p") | (false, "first-party" | "1p") => {
+                    self.third_party = Some(false)
+                }
+                (true, "first-party" | "1p") => self.third_party = Some(true),
+                (false, "match-case") => self.match_case = true,
+                (false, "important") => self.important = true,
+                (false, _) if name.starts_with("domain=") => {
+                    for domain in name["domain=".len()..].split('|') {
+                        let (list, domain) = match domain.strip_prefix('~') {
+                            Some(domain) => (&mut self.excluded_domains, domain),
+                            None => (&mut self.domains, domain),
+                        };
+                        if domain.is_empty() {
+                            return None;
+                        }
+                        list.push(domain.to_ascii_lowercase());
+                    }
+                }
+                _ => {
+                    let types = option_types(name)?;
+                    match negated {
+                        true => excluded |= types,
+                        false => included |= types,
+                    }
+                }
+            }
+        }
+        if included != 0 {
+            self.types = included;
+        }
+        self.types &= !excluded;
+        (self.types != 0).then_some(())
+    }
+
+    fn compile_pattern(&self, pattern: &str) -> Vec<Part> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        for c in pattern.chars() {
+            let part = match c {
+                '*' => Part::Wildcard,
+                '^' => Part::Separator,
+                _ => {
+                    literal.push(if self.match_case {
+                        c
+                    } else {
+                        c.to_ascii_lowercase()
+                    });
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            // Runs of wildcards match what one does
+            if !(part == Part::Wildcard && parts.last() == Some(&Part::Wildcard)) {
+                parts.push(part);
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        // Unanchored ends match anything already
+        if self.anchor == Anchor::None && parts.first() == Some(&Part::Wildcard) {
+            parts.remove(0);
+        }
+        if !self.end_anchor && parts.last() == Some(&Part::Wildcard) {
+            parts.pop();
+        }
+        parts
+    }
+
+    /// The rule as written in its list
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the rule lets through what others block
+    pub fn is_exception(&self) -> bool {
+        self.exception
+    }
+
+    /// Whether the rule blocks despite exceptions
+    pub fn is_important(&self) -> bool {
+        self.important
+    }
+
+    /// A lowercase word every URL the rule matches contains, with no letter
+    /// or digit on either side; `None` when the pattern pins none down
+    fn token(&self) -> Option<String> {
+        let mut tokens = Vec::new();
+        for (i, part) in self.parts.iter().enumerate() {
+            let Part::Literal(literal) = part else {
+                continue;
+            };
+            let bytes = literal.as_bytes();
+            let bounded_before = match i {
+                0 => self.anchor != Anchor::None,
+                _ => self.parts[i - 1] == Part::Separator,
+            };
+            let bounded_after = match self.parts.get(i + 1) {
+                None => self.end_anchor,
+                Some(next) => *next == Part::Separator,
+            };
+            let mut start = 0;
+            while start < bytes.len() {
+                if !bytes[start].is_ascii_alphanumeric() {
+                    start += 1;
+                    continue;
+                }
+                let end = bytes[start..]
+                    .iter()
+                    .position(|b| !b.is_ascii_alphanumeric())
+                    .map_or(bytes.len(), |len| start + len);
+                if (start > 0 || bounded_before) && (end < bytes.len() || bounded_after) {
+                    tokens.push(literal[start..end].to_ascii_lowercase());
+                }
+                start = end;
+            }
+        }
+        tokens
+            .into_iter()
+            .max_by_key(|token| (!COMMON_TOKENS.contains(&token.as_str()), token.len()))
+    }
+
+    /// Whether the rule matches `request`
+    pub fn matches(&self, request: &RuleRequest) -> bool {
+        if self.types & type_bit(request.resource_type) == 0 {
+            return false;
+        }
+        if self
+            .third_party
+            .is_some_and(|third_party| third_party != request.third_party)
+        {
+            return false;
+        }
+        if !self.domains.is_empty() || !self.excluded_domains.is_empty() {
+            let Some(page) = request.page_host.as_deref() else {
+                return false;
+            };
+            if self.excluded_domains.iter().any(|d| on_domain(page, d)) {
+                return false;
+            }
+            if !self.domains.is_empty() && !self.domains.iter().any(|d| on_domain(page, d)) {
+                return false;
+            }
+        }
+
+        let url = match self.match_case {
+            true => request.url.as_bytes(),
+            false => request.lowercase.as_bytes(),
+        };
+        match self.anchor {
+            Anchor::Start => self.matches_at(url, 0),
+            Anchor::Host => {
+                let host = request.host.clone();
+                host.clone()
+                    .filter(|at| *at == host.start || url[at - 1] == b'.')
+                    .any(|at| self.matches_at(url, at))
+            }
+            Anchor::None => (0..=url.len()).any(|at| self.matches_at(url, at)),
+        }
+    }
+
+    fn matches_at(&self, url: &[u8], at: usize) -> bool {
+        match_parts(&self.parts, url, at, self.end_anchor)
+    }
+}
+
+/// Whether `parts` match `url` from `at`, to its end if `end_anchor`
+fn match_parts(parts: &[Part], url: &[u8], at: usize, end_anchor: bool) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return !end_anchor || at == url.len();
+    };
+    match part {
+        Part::Literal(literal) => {
+            url[at..].starts_with(literal.as_bytes())
+                && match_parts(rest, url, at + literal.len(), end_anchor)
+        }
+        Part::Separator => match url.get(at) {
+            Some(byte) if is_separator(*byte) => match_parts(rest, url, at + 1, end_anchor),
+            Some(_) => false,
+            None => match_parts(rest, url, at, end_anchor),
+        },
+        Part::Wildcard => (at..=url.len()).any(|at| match_parts(rest, url, at, end_anchor)),
+    }
+}
+
+/// A list's [`NetworkRule`]s, indexed by token
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkRules {
+    rules: Vec<NetworkRule>,
+    by_token: HashMap<String, Vec<u32>>,
+    /// Rules without a token, tested against every request
+    untokened: Vec<u32>,
+}
+
+impl NetworkRules {
+    pub fn new(rules: Vec<NetworkRule>) -> Self {
+        let mut by_token: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut untokened = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            match rule.token() {
+                Some(token) => by_token.entry(token).or_default().push(index as u32),
+                None => untokened.push(index as u32),
+            }
+        }
+        Self {
+            rules,
+            by_token,
+            untokened,
+        }
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules, in list order
+    pub fn rules(&self) -> &[NetworkRule] {
+        &self.rules
+    }
+
+    /// The first rule satisfying `wanted` that matches `request`
+    pub fn find(
+        &self,
+        request: &RuleRequest,
+        wanted: impl Fn(&NetworkRule) -> bool,
+    ) -> Option<&NetworkRule> {
+        request
+            .tokens
+            .iter()
+            .filter_map(|token| self.by_token.get(token))
+            .flatten()
+            .chain(&self.untokened)
+            .map(|index| &self.rules[*index as usize])
+            .find(|rule| wanted(rule) && rule.matches(request))
+    }
+}
+
+/// A request as rules see it
+#[derive(Debug, Clone)]
+pub struct RuleRequest {
+    /// The URL as serialized, which is ASCII
+    url: String,
+    lowercase: String,
+    /// Byte range of the host in the URL
+    host: std::ops::Range<usize>,
+    /// Words of the lowercased URL
+    tokens: BTreeSet<String>,
+    resource_type: ResourceType,
+    /// Host of the page making the request
+    page_host: Option<String>,
+    third_party: bool,
+}
+
+impl RuleRequest {
+    /// A request for `url` by the page at `page`; without a page, it counts
+    /// as first-party
+    pub fn new(url: &Url, resource_type: ResourceType, page: Option<&Url>) -> Self {
+        let lowercase = url.as_str().to_ascii_lowercase();
+        let tokens = lowercase
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+            .collect();
+        let host = url[..Position::BeforeHost].len()..url[..Position::AfterHost].len();
+        let site = |url: &Url| {
+            url.host_str()
+                .map(|host| ResourceManager::extract_domain(&host.to_ascii_lowercase()))
+        };
+        let page_host = page
+            .and_then(Url::host_str)
+            .map(|host| host.trim_end_matches('.').to_ascii_lowercase());
+        let third_party = page.is_some_and(|page| site(page) != site(url));
+        Self {
+            url: url.as_str().to_string(),
+            lowercase,
+            host,
+            tokens,
+            resource_type,
+            page_host,
+            third_party,
+        }
+    }
+
+    /// The request's host, lowercased
+    pub fn host(&self) -> &str {
+        &self.lowercase[self.host.clone()]
+    }
+}
+
+/// What the engine decided about a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// No rule blocks it
+    Pass,
+    /// Blocked; the reason names the rule
+    Blocked(String),
+    /// A rule blocks it, but an exception lets it through
+    Excepted(String),
+    /// A rule blocks it, but the page's site is allowlisted
+    Allowlisted,
+}
+
+/// Counts since the engine was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlocklistStats {
+    /// Requests checked against the rules
+    pub checked: u64,
+    pub blocked: u64,
+    /// Blocked by a rule but let through by an exception
+    pub excepted: u64,
+    /// Blocked by a rule but made by a page of an allowlisted site
+    pub allowlisted: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    checked: AtomicU64,
+    blocked: AtomicU64,
+    excepted: AtomicU64,
+    allowlisted: AtomicU64,
+}
+
+/// Blocks requests by the installed filter lists, sites on the allowlist
+/// excepted
+#[derive(Debug, Clone)]
+pub struct BlocklistEngine {
+    lists: FilterLists,
+    /// Domains whose pages, and their subdomains' pages, are not filtered
+    allowlist: Arc<RwLock<BTreeSet<String>>>,
+    counters: Arc<Counters>,
+    privacy_sender: Option<PrivacyEventSender>,
+}
+
+impl BlocklistEngine {
+    /// An engine blocking by `lists`, which may be refreshed while it runs
+    pub fn new(lists: FilterLists) -> Self {
+        Self {
+            lists,
+            allowlist: Arc::default(),
+            counters: Arc::default(),
+            privacy_sender: None,
+        }
+    }
+
+    /// Report blocked requests to the privacy scoreboard
+    pub fn with_privacy_sender(mut self, sender: PrivacyEventSender) -> Self {
+        self.privacy_sender = Some(sender);
+        self
+    }
+
+    /// The lists the engine blocks by
+    pub fn lists(&self) -> &FilterLists {
+        &self.lists
+    }
+
+    /// What the rules make of `request`; the counters are not touched
+    pub fn evaluate(&self, request: &RuleRequest) -> Verdict {
+        let lists = self.lists.compiled();
+        let host = request.host();
+
+        // Important rules win over exceptions, so they are looked for first
+        let important = lists.iter().find_map(|list| {
+            list.network_rules()
+                .find(request, |rule| !rule.is_exception() && rule.is_important())
+        });
+        let blocking = match important {
+            Some(rule) => Some(rule.source().to_string()),
+            None => lists.iter().find_map(|list| {
+                list.blocking_domain(host)
+                    .map(|domain| format!("||{}^", domain))
+                    .or_else(|| {
+                        list.network_rules()
+                            .find(request, |rule| !rule.is_exception())
+                            .map(|rule| rule.source().to_string())
+                    })
+            }),
+        };
+        let Some(rule) = blocking else {
+            return Verdict::Pass;
+        };
+
+        if self.is_allowlisted_page(request) {
+            return Verdict::Allowlisted;
+        }
+        // A domain exception lets through every request to the domain
+        let excepted = important.is_none()
+            && (self.lists.excepts_host(host)
+                || lists.iter().any(|list| {
+                    list.network_rules()
+                        .find(request, NetworkRule::is_exception)
+                        .is_some()
+                }));
+        match excepted {
+            true => Verdict::Excepted(rule),
+            false => Verdict::Blocked(rule),
+        }
+    }
+
+    /// Evaluate `request`, count the verdict and report a block
+    pub fn check(&self, request: &RuleRequest) -> Verdict {
+        let verdict = self.evaluate(request);
+        let counters = &self.counters;
+        counters.checked.fetch_add(1, Ordering::Relaxed);
+        let counter = match &verdict {
+            Verdict::Pass => None,
+            Verdict::Blocked(rule) => {
+                log::debug!("🚫 Filter rule {} blocked {}", rule, request.url);
+                if let Some(sender) = &self.privacy_sender {
+                    sender.emit(PrivacyEvent::TrackerBlocked {
+                        url: request.url.clone(),
+                        rule: rule.clone(),
+                        category: TrackerCategory::Unknown,
+                    });
+                }
+                Some(&counters.blocked)
+            }
+            Verdict::Excepted(_) => Some(&counters.excepted),
+            Verdict::Allowlisted => Some(&counters.allowlisted),
+        };
+        if let Some(counter) = counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        verdict
+    }
+
+    /// Counts since the engine was created
+    pub fn stats(&self) -> BlocklistStats {
+        let counters = &self.counters;
+        BlocklistStats {
+            checked: counters.checked.load(Ordering::Relaxed),
+            blocked: counters.blocked.load(Ordering::Relaxed),
+            excepted: counters.excepted.load(Ordering::Relaxed),
+            allowlisted: counters.allowlisted.load(Ordering::Relaxed),
+        }
+    }
+
+    fn is_allowlisted_page(&self, request: &RuleRequest) -> bool {
+        request
+            .page_host
+            .as_deref()
+            .is_some_and(|page| self.is_allowlisted(page))
+    }
+
+    /// Whether pages on `host` go unfiltered
+    pub fn is_allowlisted(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.allowlist
+            .read()
+            .is_ok_and(|allowlist| allowlist.iter().any(|domain| on_domain(&host, domain)))
+    }
+
+    /// Stop filtering the pages of `domain` and its subdomains
+    pub fn allow(&self, domain: &str) {
+        if let Ok(mut allowlist) = self.allowlist.write() {
+            allowlist.insert(domain.trim_end_matches('.').to_ascii_lowercase());
+        }
+    }
+
+    /// Filter the pages of `domain` again. Returns whether it was
+    /// allowlisted.
+    pub fn disallow(&self, domain: &str) -> bool {
+        self.allowlist.write().is_ok_and(|mut allowlist| {
+            allowlist.remove(&domain.trim_end_matches('.').to_ascii_lowercase())
+        })
+    }
+
+    /// The allowlisted domains, sorted
+    pub fn allowlist(&self) -> Vec<String> {
+        self.allowlist
+            .read()
+            .map(|allowlist| allowlist.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Replace the allowlist with `domains`
+    pub fn set_allowlist(&self, domains: impl IntoIterator<Item = String>) {
+        if let Ok(mut allowlist) = self.allowlist.write() {
+            *allowlist = domains
+                .into_iter()
+                .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, resource_type: ResourceType, page: &str) -> RuleRequest {
+        RuleRequest::new(
+            &Url::parse(url).unwrap(),
+            resource_type,
+            Some(&Url::parse(page).unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_rules_match_patterns_and_options() {
+        let rule = |line: &str| NetworkRule::parse(line).unwrap();
+        let page = "https://news.example/";
+        let script = |url: &str| request(url, ResourceType::Script, page);
+
+        let banner = rule("/banner/*.gif");
+        assert!(banner.matches(&script("https://cdn.test/img/banner/top.GIF")));
+        assert!(!banner.matches(&script("https://cdn.test/banner.gif")));
+        assert_eq!(banner.token().as_deref(), Some("banner"));
+
+        let anchored = rule("||ads.test/track^$script,third-party");
+        assert!(anchored.matches(&script("https://x.ads.test/track?id=1")));
+        assert!(anchored.matches(&script("https://ads.test/track")));
+        assert!(!anchored.matches(&script("https://badads.test/track")));
+        assert!(!anchored.matches(&script("https://ads.test/tracking")));
+        assert!(!anchored.matches(&request(
+            "https://ads.test/track",
+            ResourceType::Image,
+            page
+        )));
+        assert!(!anchored.matches(&request(
+            "https://ads.test/track",
+            ResourceType::Script,
+            "https://www.ads.test/"
+        )));
+
+        let ends = rule("|https://*.example/pixel.png|$~image,domain=example|~shop.example");
+        assert!(ends.matches(&script("https://cdn.news.example/pixel.png")));
+        assert!(!ends.matches(&script("https://cdn.news.example/pixel.png?x")));
+        assert!(!ends.matches(&request(
+            "https://cdn.news.example/pixel.png",
+            ResourceType::Script,
+            "https://shop.example/"
+        )));
+
+        let case = rule("/AdServer/$match-case");
+        assert!(case.matches(&script("https://cdn.test/AdServer/x")));
+        assert!(!case.matches(&script("https://cdn.test/adserver/x")));
+
+        assert!(rule("@@||cdn.test^$script").is_exception());
+        for skipped in [
+            "||ads.test^$popup",
+            "/ad[0-9]+/",
+            "example.com##.promo",
+            "*$third-party",
+            "||ads.test^$~script,~image,~css,~font,~media,~xhr,~doc,~other",
+            "! comment",
+        ] {
+            assert_eq!(NetworkRule::parse(skipped), None, "{}", skipped);
+        }
+    }
+
+    #[test]
+    fn test_engine_weighs_exceptions_allowlist_and_counts() {
+        let root = std::env::temp_dir().join(format!("citadel-blocklist-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("lists");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("list.txt"),
+            "||tracker.test^\n\
+             /ads/*$script\n\
+             @@/ads/allowed.js\n\
+             ||beacon.test/collect$important\n\
+             @@||beacon.test^\n",
+        )
+        .unwrap();
+        let lists = FilterLists::new();
+        lists.refresh(&dir, &crate::FilterListCache::new(root.join("cache")));
+        let engine = BlocklistEngine::new(lists);
+        let page = "https://news.example/article";
+        let script = |url: &str| request(url, ResourceType::Script, page);
+
+        assert_eq!(
+            engine.check(&script("https://cdn.tracker.test/t.js")),
+            Verdict::Blocked("||tracker.test^".into())
+        );
+        assert_eq!(
+            engine.check(&script("https://news.example/ads/banner.js")),
+            Verdict::Blocked("/ads/*$script".into())
+        );
+        assert_eq!(
+            engine.check(&script("https://news.example/ads/allowed.js")),
+            Verdict::Excepted("/ads/*$script".into())
+        );
+        assert_eq!(
+            engine.check(&script("https://beacon.test/collect")),
+            Verdict::Blocked("||beacon.test/collect$important".into())
+        );
+        assert_eq!(
+            engine.check(&script("https://beacon.test/other")),
+            Verdict::Pass
+        );
+        assert_eq!(
+            engine.check(&script("https://news.example/app.js")),
+            Verdict::Pass
+        );
+
+        engine.allow("example");
+        assert!(engine.is_allowlisted("news.example"));
+        assert_eq!(
+            engine.check(&script("https://cdn.tracker.test/t.js")),
+            Verdict::Allowlisted
+        );
+        assert_eq!(
+            engine.stats(),
+            BlocklistStats {
+                checked: 7,
+                blocked: 3,
+                excepted: 1,
+                allowlisted: 1,
+            }
+        );
+
+        assert!(engine.disallow("example"));
+        assert!(!engine.is_allowlisted("news.example"));
+        engine.set_allowlist(["News.Example.".to_string()]);
+        assert_eq!(engine.allowlist(), ["news.example"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,707 @@
+//! Per-tab network budgets
+//!
+//! Caps how much network activity a single tab may generate: requests in
+//! flight, total bytes downloaded, and distinct third-party origins contacted.
+//! Requests beyond the concurrency cap wait for a slot; a page that exceeds
+//! its byte or origin budget gets `NetworkError::BudgetExceeded` instead of
+//! more bandwidth, which protects against resource exhaustion and pages that
+//! fan out to dozens of ad and tracking hosts. Budgets reset when the tab
+//! navigates to a new top-level document. Tabs other than the active one
+//! fetch one request at a time, and a page's images wait while its
+//! render-blocking stylesheets and fonts are in flight, so first paint comes
+//! first.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use url::Url;
+use uuid::Uuid;
+
+use crate::error::NetworkError;
+use crate::resource::ResourceType;
+use crate::resource_manager::ResourceManager;
+use crate::PrivacyLevel;
+
+/// Limits applied to a single tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestBudget {
+    /// Maximum requests in flight at once
+    pub max_concurrent_requests: usize,
+    /// Maximum response bytes per page load
+    pub max_total_bytes: u64,
+    /// Maximum distinct third-party origins per page load
+    pub max_third_party_origins: usize,
+}
+
+impl RequestBudget {
+    /// Budget matching a privacy level; stricter levels contact fewer origins
+    pub fn for_privacy_level(level: PrivacyLevel) -> Self {
+        match level {
+            PrivacyLevel::Maximum => Self {
+                max_concurrent_requests: 6,
+                max_total_bytes: 25 * 1024 * 1024,
+                max_third_party_origins: 5,
+            },
+            PrivacyLevel::High => Self {
+                max_concurrent_requests: 10,
+                max_total_bytes: 50 * 1024 * 1024,
+                max_third_party_origins: 15,
+            },
+            PrivacyLevel::Balanced | PrivacyLevel::Custom => Self {
+                max_concurrent_requests: 16,
+                max_total_bytes: 100 * 1024 * 1024,
+                max_third_party_origins: 40,
+            },
+        }
+    }
+}
+
+impl Default for RequestBudget {
+    fn default() -> Self {
+        Self::for_privacy_level(PrivacyLevel::High)
+    }
+}
+
+/// Snapshot of a tab's budget consumption
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetUsage {
+    /// Limits in force
+    pub limits: RequestBudget,
+    /// Requests currently in flight
+    pub active_requests: usize,
+    /// Requests admitted since the last navigation
+    pub total_requests: usize,
+    /// Response bytes received since the last navigation
+    pub total_bytes: u64,
+    /// Distinct third-party origins contacted since the last navigation
+    pub third_party_origins: usize,
+    /// Requests refused because the budget was exhausted
+    pub rejected_requests: usize,
+}
+
+impl BudgetUsage {
+    /// Whether any limit has been hit for this page load
+    pub fn is_exhausted(&self) -> bool {
+        self.rejected_requests > 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    first_party: Option<String>,
+    active_requests: usize,
+    total_requests: usize,
+    total_bytes: u64,
+    third_party_origins: HashSet<String>,
+    rejected_requests: usize,
+    /// Render-blocking requests in flight
+    render_blocking: usize,
+}
+
+/// Budget accounting for one tab
+#[derive(Debug)]
+pub struct TabBudget {
+    limits: RequestBudget,
+    slots: Arc<Semaphore>,
+    /// The single lane a background tab's requests take turns on
+    background_lane: Arc<Semaphore>,
+    background: AtomicBool,
+    /// Set while the device saves power; shared by every tab of a registry
+    power_saving: Arc<AtomicBool>,
+    /// The lane background tabs share while the device saves power
+    shared_lane: Arc<Semaphore>,
+    /// Woken when the last render-blocking request finishes
+    render_unblocked: Notify,
+    state: Mutex<BudgetState>,
+}
+
+impl TabBudget {
+    /// Create a budget with the given limits
+    pub fn new(limits: RequestBudget) -> Self {
+        Self {
+            limits,
+            slots: Arc::new(Semaphore::new(limits.max_concurrent_requests.max(1))),
+            background_lane: Arc::new(Semaphore::new(1)),
+            background: AtomicBool::new(false),
+            power_saving: Arc::new(AtomicBool::new(false)),
+            shared_lane: Arc::new(Semaphore::new(1)),
+            render_unblocked: Notify::new(),
+            state: Mutex::new(BudgetState::default()),
+        }
+    }
+
+    /// Hold the tab to one request at a time while it is in the background;
+    /// `false` restores its full concurrency
+    pub fn set_background(&self, background: bool) {
+        self.background.store(background, Ordering::Relaxed);
+    }
+
+    /// Whether the tab's requests are throttled for being in the background
+    pub fn is_background(&self) -> bool {
+        self.background.load(Ordering::Relaxed)
+    }
+
+    /// Hold images to one at a time and put background tabs on a single
+    /// lane shared with the registry's other tabs; `false` lifts both
+    pub fn set_power_saving(&self, power_saving: bool) {
+        self.power_saving.store(power_saving, Ordering::Relaxed);
+    }
+
+    /// Whether the tab's requests are throttled to save power
+    pub fn is_power_saving(&self) -> bool {
+        self.power_saving.load(Ordering::Relaxed)
+    }
+
+    /// Reset consumption for a new top-level document at `url`
+    pub fn start_navigation(&self, url: &Url) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = BudgetState {
+                first_party: url.host_str().map(ResourceManager::extract_domain),
+                ..BudgetState::default()
+            };
+        }
+        self.render_unblocked.notify_waiters();
+    }
+
+    /// Admit a request to `url` for a `resource_type`, or refuse it if it
+    /// would exceed the byte or origin budget. With the concurrency cap
+    /// reached, or another request in flight while the tab is in the
+    /// background, it waits for an earlier request to finish; images also
+    /// wait for the page's render-blocking requests. While saving power,
+    /// images take turns within the tab and background tabs take turns with
+    /// each other. The returned permit
+    /// counts as in flight until dropped.
+    pub async fn begin(
+        self: &Arc<Self>,
+        url: &Url,
+        resource_type: ResourceType,
+    ) -> Result<BudgetPermit, NetworkError> {
+        self.admit(url)?;
+        let blocking = self.blocks_render(url, resource_type);
+        if matches!(resource_type, ResourceType::Image | ResourceType::Binary) {
+            self.wait_for_render_blocking().await;
+        }
+        let closed = |_| NetworkError::UnknownError("budget closed".to_string());
+        let deferrable = matches!(resource_type, ResourceType::Image | ResourceType::Binary);
+        let lane = match (self.is_background(), self.is_power_saving()) {
+            (true, true) => Some(&self.shared_lane),
+            (true, false) => Some(&self.background_lane),
+            (false, true) if deferrable => Some(&self.background_lane),
+            (false, _) => None,
+        };
+        let lane = match lane {
+            Some(lane) => Some(Arc::clone(lane).acquire_owned().await.map_err(closed)?),
+            None => None,
+        };
+        let slot = Arc::clone(&self.slots)
+            .acquire_owned()
+            .await
+            .map_err(closed)?;
+        if let Ok(mut state) = self.state.lock() {
+            state.active_requests += 1;
+            state.render_blocking += usize::from(blocking);
+        }
+        Ok(BudgetPermit {
+            budget: Arc::clone(self),
+            blocking,
+            _slot: slot,
+            _lane: lane,
+        })
+    }
+
+    /// Whether first paint waits for the request: stylesheets, and fonts
+    /// from the page's own site
+    fn blocks_render(&self, url: &Url, resource_type: ResourceType) -> bool {
+        match resource_type {
+            ResourceType::Css => true,
+            ResourceType::Font => {
+                let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                match (&state.first_party, url.host_str()) {
+                    (Some(first_party), Some(host)) => {
+                        ResourceManager::extract_domain(host) == *first_party
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    async fn wait_for_render_blocking(&self) {
+        loop {
+            // Registered before checking, so a finish in between still wakes it
+            let unblocked = self.render_unblocked.notified();
+            let blocking = self.state.lock().map_or(0, |state| state.render_blocking);
+            if blocking == 0 {
+                return;
+            }
+            unblocked.await;
+        }
+    }
+
+    fn admit(&self, url: &Url) -> Result<(), NetworkError> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| NetworkError::UnknownError("budget lock poisoned".to_string()))?;
+
+        let refusal = if state.total_bytes >= self.limits.max_total_bytes {
+            Some(format!("{} bytes already received", state.total_bytes))
+        } else {
+            None
+        };
+
+        let third_party = match (&state.first_party, url.host_str()) {
+            (Some(first_party), Some(host))
+                if ResourceManager::extract_domain(host) != *first_party =>
+            {
+                Some(url.origin().ascii_serialization())
+            }
+            _ => None,
+        };
+        let refusal = refusal.or_else(|| match &third_party {
+            Some(origin)
+                if !state.third_party_origins.contains(origin)
+                    && state.third_party_origins.len() >= self.limits.max_third_party_origins =>
+            {
+                Some(format!(
+                    "third-party origin limit of {} reached ({})",
+                    self.limits.max_third_party_origins, origin
+                ))
+            }
+            _ => None,
+        });
+
+        if let Some(reason) = refusal {
+            state.rejected_requests += 1;
+            log::warn!("⛔ Request budget refused {}: {}", url, reason);
+            return Err(NetworkError::BudgetExceeded(reason));
+        }
+
+        if let Some(origin) = third_party {
+            state.third_party_origins.insert(origin);
+        }
+        state.total_requests += 1;
+        Ok(())
+    }
+
+    /// Account for received response bytes; errors once the byte limit is passed
+    pub fn record_bytes(&self, bytes: u64) -> Result<(), NetworkError> {
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(());
+        };
+        state.total_bytes = state.total_bytes.saturating_add(bytes);
+        if state.total_bytes > self.limits.max_total_bytes {
+            state.rejected_requests += 1;
+            return Err(NetworkError::BudgetExceeded(format!(
+                "byte limit of {} exceeded",
+                self.limits.max_total_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Current consumption
+    pub fn usage(&self) -> BudgetUsage {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        BudgetUsage {
+            limits: self.limits,
+            active_requests: state.active_requests,
+            total_requests: state.total_requests,
+            total_bytes: state.total_bytes,
+            third_party_origins: state.third_party_origins.len(),
+            rejected_requests: state.rejected_requests,
+        }
+    }
+
+    fn finish(&self, blocking: bool) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.active_requests = state.active_requests.saturating_sub(1);
+        if blocking {
+            state.render_blocking = state.render_blocking.saturating_sub(1);
+            if state.render_blocking == 0 {
+                drop(state);
+                self.render_unblocked.notify_waiters();
+            }
+        }
+    }
+}
+
+/// An admitted request; releases its concurrency slot when dropped
+#[derive(Debug)]
+pub struct BudgetPermit {
+    budget: Arc<TabBudget>,
+    blocking: bool,
+    _slot: OwnedSemaphorePermit,
+    _lane: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for BudgetPermit {
+    fn drop(&mut self) {
+        self.budget.finish(self.blocking);
+    }
+}
+
+/// Budgets for every open tab, sharing one set of limits
+#[derive(Debug, Clone)]
+pub struct TabBudgets {
+    limits: RequestBudget,
+    tabs: Arc<RwLock<HashMap<Uuid, Arc<TabBudget>>>>,
+    /// The tab the user is looking at; every other tab is in the background
+    active: Arc<RwLock<Option<Uuid>>>,
+    power_saving: Arc<AtomicBool>,
+    shared_lane: Arc<Semaphore>,
+}
+
+impl TabBudgets {
+    /// Create an empty registry with the given per-tab limits
+    pub fn new(limits: RequestBudget) -> Self {
+        Self {
+            limits,
+            tabs: Arc::new(RwLock::new(HashMap::new())),
+            active: Arc::new(RwLock::new(None)),
+            power_saving: Arc::new(AtomicBool::new(false)),
+            shared_lane: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    /// Limits applied to each tab
+    pub fn limits(&self) -> RequestBudget {
+        self.limits
+    }
+
+    /// Budget for a tab, created on first use
+    pub fn tab(&self, tab_id: Uuid) -> Arc<TabBudget> {
+        if let Some(budget) = self.tabs.read().ok().and_then(|t| t.get(&tab_id).cloned()) {
+            return budget;
+        }
+        let mut tabs = self.tabs.write().unwrap_or_else(|e| e.into_inner());
+        tabs.entry(tab_id)
+            .or_insert_with(|| {
+                let budget = TabBudget {
+                    power_saving: Arc::clone(&self.power_saving),
+                    shared_lane: Arc::clone(&self.shared_lane),
+                    ..TabBudget::new(self.limits)
+                };
+                budget.set_background(self.active().is_some_and(|active| active != tab_id));
+                Arc::new(budget)
+            })
+            .clone()
+    }
+
+    /// Make `tab_id` the active tab, moving every other tab to the background
+    pub fn set_active(&self, tab_id: Uuid) {
+        *self.active.write().unwrap_or_else(|e| e.into_inner()) = Some(tab_id);
+        if let Ok(tabs) = self.tabs.read() {
+            for (id, budget) in tabs.iter() {
+                budget.set_background(*id != tab_id);
+            }
+        }
+    }
+
+    /// The tab whose requests run at full concurrency, once one is chosen
+    pub fn active(&self) -> Option<Uuid> {
+        *self.active.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Throttle every tab, open or opened later, to save power
+    pub fn set_power_saving(&self, power_saving: bool) {
+        self.power_saving.store(power_saving, Ordering::Relaxed);
+    }
+
+    /// Whether tabs' requests are throttled to save power
+    pub fn is_power_saving(&self) -> bool {
+        self.power_saving.load(Ordering::Relaxed)
+    }
+
+    /// Consumption for a tab, if it has made any requests
+    pub fn usage(&self, tab_id: Uuid) -> Option<BudgetUsage> {
+        self.tabs
+            .read()
+            .ok()
+            .and_then(|tabs| tabs.get(&tab_id).map(|budget| budget.usage()))
+    }
+
+    /// Forget a closed tab
+    pub fn remove(&self, tab_id: Uuid) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.remove(&tab_id);
+        }
+    }
+
+    /// Forget every tab
+    pub fn clear(&self) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.clear();
+        }
+    }
+}
+
+impl Default for TabBudgets {
+    fn default() -> Self {
+        Self::new(RequestBudget::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_waits_for_a_slot() {
+        let budget = Arc::new(TabBudget::new(RequestBudget {
+            max_concurrent_requests: 2,
+            ..RequestBudget::default()
+        }));
+        budget.start_navigation(&url("https://example.com/"));
+
+        let a = budget
+            .begin(&url("https://example.com/a.css"), ResourceType::Css)
+            .await
+            .unwrap();
+        let _b = budget
+            .begin(&url("https://example.com/b.css"), ResourceType::Css)
+            .await
+            .unwrap();
+        let third = {
+            let budget = Arc::clone(&budget);
+            tokio::spawn(async move {
+                budget
+                    .begin(&url("https://example.com/c.css"), ResourceType::Css)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!third.is_finished());
+        assert_eq!(budget.usage().active_requests, 2);
+
+        drop(a);
+        let _c = third.await.unwrap().unwrap();
+        let usage = budget.usage();
+        assert_eq!(usage.active_requests, 2);
+        assert_eq!(usage.total_requests, 3);
+        assert_eq!(usage.rejected_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_background_tabs_fetch_one_request_at_a_time() {
+        let budgets = TabBudgets::default();
+        let (front, back) = (Uuid::new_v4(), Uuid::new_v4());
+        budgets.set_active(front);
+        let background = budgets.tab(back);
+        assert!(background.is_background());
+
+        let first = background
+            .begin(&url("https://example.com/a"), ResourceType::Other)
+            .await
+            .unwrap();
+        let second = {
+            let background = Arc::clone(&background);
+            tokio::spawn(async move {
+                background
+                    .begin(&url("https://example.com/b"), ResourceType::Other)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+
+        // The active tab is not held up
+        let foreground = budgets.tab(front);
+        let _a = foreground
+            .begin(&url("https://example.com/a"), ResourceType::Other)
+            .await
+            .unwrap();
+        let _b = foreground
+            .begin(&url("https://example.com/b"), ResourceType::Other)
+            .await
+            .unwrap();
+
+        drop(first);
+        assert!(second.await.unwrap().is_ok());
+
+        budgets.set_active(back);
+        assert!(!background.is_background());
+        assert!(foreground.is_background());
+        assert_eq!(budgets.active(), Some(back));
+    }
+
+    #[tokio::test]
+    async fn test_images_wait_for_render_blocking_requests() {
+        let budget = Arc::new(TabBudget::new(RequestBudget::default()));
+        budget.start_navigation(&url("https://example.com/"));
+        let css = budget
+            .begin(&url("https://example.com/site.css"), ResourceType::Css)
+            .await
+            .unwrap();
+        let font = budget
+            .begin(
+                &url("https://static.example.com/a.woff2"),
+                ResourceType::Font,
+            )
+            .await
+            .unwrap();
+        // Other sites' fonts do not hold up first paint
+        let third_party_font = budget
+            .begin(&url("https://fonts.test/b.woff2"), ResourceType::Font)
+            .await
+            .unwrap();
+        let image = {
+            let budget = Arc::clone(&budget);
+            tokio::spawn(async move {
+                budget
+                    .begin(&url("https://example.com/hero.png"), ResourceType::Image)
+                    .await
+            })
+        };
+        // Scripts are not held back
+        let _script = budget
+            .begin(&url("https://example.com/app.js"), ResourceType::Script)
+            .await
+            .unwrap();
+        drop(third_party_font);
+        drop(css);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!image.is_finished());
+
+        drop(font);
+        assert!(image.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_power_saving_serializes_images_and_background_tabs() {
+        let budgets = TabBudgets::default();
+        let (front, back, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        budgets.set_active(front);
+        budgets.set_power_saving(true);
+        let foreground = budgets.tab(front);
+        assert!(foreground.is_power_saving());
+
+        // Images in the active tab go one at a time; scripts are not held
+        let first = foreground
+            .begin(&url("https://example.com/a.png"), ResourceType::Image)
+            .await
+            .unwrap();
+        let second = {
+            let foreground = Arc::clone(&foreground);
+            tokio::spawn(async move {
+                foreground
+                    .begin(&url("https://example.com/b.png"), ResourceType::Image)
+                    .await
+            })
+        };
+        let _script = foreground
+            .begin(&url("https://example.com/app.js"), ResourceType::Script)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+        drop(first);
+        assert!(second.await.unwrap().is_ok());
+
+        // Background tabs share a single lane
+        let held = budgets
+            .tab(back)
+            .begin(&url("https://example.com/a"), ResourceType::Other)
+            .await
+            .unwrap();
+        let waiting = {
+            let background = budgets.tab(other);
+            tokio::spawn(async move {
+                background
+                    .begin(&url("https://example.com/b"), ResourceType::Other)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        drop(held);
+        assert!(waiting.await.unwrap().is_ok());
+
+        budgets.set_power_saving(false);
+        assert!(!foreground.is_power_saving());
+    }
+
+    #[tokio::test]
+    async fn test_third_party_origin_limit() {
+        let budget = Arc::new(TabBudget::new(RequestBudget {
+            max_third_party_origins: 2,
+            ..RequestBudget::default()
+        }));
+        budget.start_navigation(&url("https://www.example.com/"));
+
+        // First-party subdomains never count
+        budget
+            .begin(&url("https://cdn.example.com/x.js"), ResourceType::Other)
+            .await
+            .unwrap();
+        budget
+            .begin(&url("https://a.test/1"), ResourceType::Other)
+            .await
+            .unwrap();
+        budget
+            .begin(&url("https://a.test/2"), ResourceType::Other)
+            .await
+            .unwrap();
+        budget
+            .begin(&url("https://b.test/"), ResourceType::Other)
+            .await
+            .unwrap();
+        assert!(budget
+            .begin(&url("https://c.test/"), ResourceType::Other)
+            .await
+            .is_err());
+        // Already-contacted origins stay reachable
+        assert!(budget
+            .begin(&url("https://b.test/more"), ResourceType::Other)
+            .await
+            .is_ok());
+        assert_eq!(budget.usage().third_party_origins, 2);
+
+        budget.start_navigation(&url("https://other.org/"));
+        assert!(budget
+            .begin(&url("https://c.test/"), ResourceType::Other)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_byte_limit() {
+        let budget = Arc::new(TabBudget::new(RequestBudget {
+            max_total_bytes: 1000,
+            ..RequestBudget::default()
+        }));
+        budget.start_navigation(&url("https://example.com/"));
+        assert!(budget.record_bytes(600).is_ok());
+        assert!(budget.record_bytes(600).is_err());
+        assert!(budget
+            .begin(&url("https://example.com/next"), ResourceType::Other)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_stricter_levels_have_smaller_budgets() {
+        let max = RequestBudget::for_privacy_level(PrivacyLevel::Maximum);
+        let balanced = RequestBudget::for_privacy_level(PrivacyLevel::Balanced);
+        assert!(max.max_third_party_origins < balanced.max_third_party_origins);
+        assert!(max.max_concurrent_requests < balanced.max_concurrent_requests);
+
+        let budgets = TabBudgets::new(max);
+        let tab = Uuid::new_v4();
+        assert!(budgets.usage(tab).is_none());
+        budgets.tab(tab);
+        assert_eq!(budgets.usage(tab).unwrap().limits, max);
+        budgets.remove(tab);
+        assert!(budgets.usage(tab).is_none());
+    }
+}
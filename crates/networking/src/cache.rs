@@ -1,9 +1,13 @@
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
 use url::Url;
+use uuid::Uuid;
 
+use crate::cache_storage::{CachePartition, CacheStorage, StoredResponse};
 use crate::error::NetworkError;
 use crate::response::Response;
 
@@ -72,8 +76,8 @@ impl CacheEntry {
                 .sum::<usize>()
             + response.url().as_str().len();
 
-        let etag = response.header("etag").cloned();
-        let last_modified = response.header("last-modified").cloned();
+        let etag = response.header("etag").map(str::to_string);
+        let last_modified = response.header("last-modified").map(str::to_string);
 
         Self {
             response,
@@ -115,6 +119,83 @@ impl CacheEntry {
     }
 }
 
+/// What the cache holds for one URL, as shown to the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntryInfo {
+    pub url: Url,
+    /// Partition of the backing store the entry is written to, if any
+    pub partition: Option<CachePartition>,
+    pub size_bytes: usize,
+    pub age: Duration,
+    /// Times the entry was served after it was stored
+    pub hits: u64,
+    /// Time left before the entry goes stale; `None` once it has
+    pub expires_in: Option<Duration>,
+}
+
+/// Response bodies shared by content across caches
+///
+/// Caches holding the same store keep one copy of each distinct body in
+/// memory, however many partitions cached it. Only the bytes are shared:
+/// each cache still has its own entries, so one partition cannot find out
+/// what another cached. A body is dropped once no entry holds it.
+#[derive(Debug, Clone, Default)]
+pub struct BodyStore {
+    inner: Arc<Mutex<InternedBodies>>,
+}
+
+#[derive(Debug, Default)]
+struct InternedBodies {
+    bodies: HashMap<[u8; 32], Bytes>,
+    /// Bodies held after the last prune; pruning again waits until that has
+    /// doubled
+    pruned_at: usize,
+}
+
+impl BodyStore {
+    /// The shared copy of `body`, stored if it is the first of its content
+    pub fn intern(&self, body: Bytes) -> Bytes {
+        if body.is_empty() {
+            return body;
+        }
+        let digest: [u8; 32] = Sha256::digest(&body).into();
+        let Ok(mut inner) = self.inner.lock() else {
+            return body;
+        };
+        if let Some(shared) = inner.bodies.get(&digest) {
+            return shared.clone();
+        }
+        if inner.bodies.len() >= (inner.pruned_at * 2).max(64) {
+            // Held only by the store: every entry using it is gone
+            inner.bodies.retain(|_, shared| !shared.is_unique());
+            inner.pruned_at = inner.bodies.len();
+        }
+        inner.bodies.insert(digest, body.clone());
+        body
+    }
+
+    /// Number of distinct bodies held
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .map(|inner| inner.bodies.len())
+            .unwrap_or(0)
+    }
+
+    /// Whether no body is held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes held, each distinct body counted once
+    pub fn size_bytes(&self) -> usize {
+        self.inner
+            .lock()
+            .map(|inner| inner.bodies.values().map(Bytes::len).sum())
+            .unwrap_or(0)
+    }
+}
+
 /// Privacy-preserving resource cache with LRU eviction
 #[derive(Debug)]
 pub struct ResourceCache {
@@ -124,6 +205,10 @@ pub struct ResourceCache {
     config: CacheConfig,
     /// Current cache size in bytes
     current_size: Arc<RwLock<usize>>,
+    /// Backing store and the partition this cache reads and writes there
+    storage: Option<(Arc<dyn CacheStorage>, CachePartition)>,
+    /// Where bodies are shared with other caches
+    bodies: Option<BodyStore>,
 }
 
 impl ResourceCache {
@@ -133,9 +218,29 @@ impl ResourceCache {
             entries: Arc::new(RwLock::new(HashMap::new())),
             config,
             current_size: Arc::new(RwLock::new(0)),
+            storage: None,
+            bodies: None,
         }
     }
 
+    /// Back the cache with `storage`, so entries outlive the process. Misses
+    /// in memory are looked up there and stores are written through.
+    pub fn with_storage(
+        mut self,
+        storage: Arc<dyn CacheStorage>,
+        partition: CachePartition,
+    ) -> Self {
+        self.storage = Some((storage, partition));
+        self
+    }
+
+    /// Share bodies with the other caches using `bodies`, so a resource
+    /// cached under several partitions is held in memory once
+    pub fn with_body_store(mut self, bodies: BodyStore) -> Self {
+        self.bodies = Some(bodies);
+        self
+    }
+
     /// Create a new resource cache with default configuration
     pub fn default() -> Self {
         Self::new(CacheConfig::default())
@@ -163,11 +268,25 @@ impl ResourceCache {
                         *size = size.saturating_sub(entry.size_bytes);
                     }
                     entries.remove(&key);
+                    if let Some((storage, partition)) = &self.storage {
+                        storage.remove(partition, &key);
+                    }
+                    return None;
                 }
             }
         }
 
-        None
+        self.load_from_storage(&key)
+    }
+
+    /// Bring an entry from the backing store into memory
+    fn load_from_storage(&self, key: &str) -> Option<Response> {
+        let (storage, partition) = self.storage.as_ref()?;
+        let stored = storage.get(partition, key)?;
+        let ttl = stored.remaining_ttl()?;
+        let response = stored.into_response()?;
+        self.insert_entry(key.to_string(), CacheEntry::new(response.clone(), ttl));
+        Some(response)
     }
 
     /// Get a cached entry for validation (even if expired)
@@ -204,6 +323,28 @@ impl ResourceCache {
             )));
         }
 
+        if let Some((storage, partition)) = &self.storage {
+            if let Err(e) = storage.put(partition, &key, StoredResponse::from_entry(&entry)) {
+                log::debug!("Not persisting cache entry {}: {}", key, e);
+            }
+        }
+        self.insert_entry(key, entry);
+
+        Ok(())
+    }
+
+    /// Swap the entry's body for the shared copy, if bodies are shared
+    fn share_body(&self, mut entry: CacheEntry) -> CacheEntry {
+        if let Some(bodies) = &self.bodies {
+            let body = bodies.intern(entry.response.body().clone());
+            entry.response.set_body(body);
+        }
+        entry
+    }
+
+    /// Add an entry to memory, evicting as needed
+    fn insert_entry(&self, key: String, entry: CacheEntry) {
+        let entry = self.share_body(entry);
         // Hold BOTH locks for the whole update and evict on the held guards. std
         // RwLock is not reentrant, so re-locking inside (as the old ensure_space_for
         // did) deadlocked every put.
@@ -222,8 +363,6 @@ impl ResourceCache {
             *current_size = current_size.saturating_add(entry.size_bytes);
             entries.insert(key, entry);
         }
-
-        Ok(())
     }
 
     /// Update an existing cache entry after validation
@@ -250,19 +389,27 @@ impl ResourceCache {
                 updated_entry.access_count = entry.access_count + 1;
                 updated_entry.last_accessed = Instant::now();
 
+                if let Some((storage, partition)) = &self.storage {
+                    let stored = StoredResponse::from_entry(&updated_entry);
+                    if let Err(e) = storage.put(partition, &key, stored) {
+                        log::debug!("Not persisting cache entry {}: {}", key, e);
+                    }
+                }
+
                 // Update size tracking
                 if let Ok(mut size) = self.current_size.write() {
                     *size += updated_entry.size_bytes;
                 }
 
-                entries.insert(key, updated_entry);
+                entries.insert(key, self.share_body(updated_entry));
             }
         }
 
         Ok(())
     }
 
-    /// Clear the entire cache
+    /// Clear the entire cache, including this cache's partition of the
+    /// backing store
     pub fn clear(&self) {
         if let Ok(mut entries) = self.entries.write() {
             entries.clear();
@@ -270,6 +417,86 @@ impl ResourceCache {
         if let Ok(mut size) = self.current_size.write() {
             *size = 0;
         }
+        if let Some((storage, partition)) = &self.storage {
+            storage.clear_partition(partition);
+        }
+    }
+
+    /// The partition this cache writes to in its backing store
+    pub fn partition(&self) -> Option<&CachePartition> {
+        self.storage.as_ref().map(|(_, partition)| partition)
+    }
+
+    /// Every entry held in memory, ordered by URL
+    pub fn entries(&self) -> Vec<CacheEntryInfo> {
+        let Ok(entries) = self.entries.read() else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let mut infos: Vec<_> = entries
+            .iter()
+            .map(|(key, entry)| self.entry_info(key, entry, now))
+            .collect();
+        infos.sort_by(|a, b| a.url.as_str().cmp(b.url.as_str()));
+        infos
+    }
+
+    fn entry_info(&self, key: &str, entry: &CacheEntry, now: Instant) -> CacheEntryInfo {
+        CacheEntryInfo {
+            url: Url::parse(key).unwrap_or_else(|_| entry.response.url().clone()),
+            partition: self.partition().cloned(),
+            size_bytes: entry.size_bytes,
+            age: now.saturating_duration_since(entry.created_at),
+            hits: entry.access_count.saturating_sub(1),
+            expires_in: entry
+                .expires_at
+                .checked_duration_since(now)
+                .filter(|left| !left.is_zero()),
+        }
+    }
+
+    /// Drop the entry for `url`, from memory and the backing store
+    pub fn remove(&self, url: &Url) -> bool {
+        let key = self.cache_key(url);
+        self.purge_keys(|candidate, _| candidate == key) > 0
+    }
+
+    /// Drop every entry matching `predicate`, from memory and the backing
+    /// store. Returns how many were dropped.
+    pub fn purge(&self, mut predicate: impl FnMut(&CacheEntryInfo) -> bool) -> usize {
+        let now = Instant::now();
+        self.purge_keys(|key, entry| predicate(&self.entry_info(key, entry, now)))
+    }
+
+    /// Drop every entry whose URL is on `host`
+    pub fn purge_host(&self, host: &str) -> usize {
+        self.purge(|info| {
+            info.url
+                .host_str()
+                .is_some_and(|entry_host| entry_host.eq_ignore_ascii_case(host))
+        })
+    }
+
+    fn purge_keys(&self, mut matches: impl FnMut(&str, &CacheEntry) -> bool) -> usize {
+        let (Ok(mut entries), Ok(mut current_size)) =
+            (self.entries.write(), self.current_size.write())
+        else {
+            return 0;
+        };
+        let doomed: Vec<String> = entries
+            .iter()
+            .filter(|(key, entry)| matches(key, entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &doomed {
+            if let Some(removed) = entries.remove(key) {
+                *current_size = current_size.saturating_sub(removed.size_bytes);
+            }
+            if let Some((storage, partition)) = &self.storage {
+                storage.remove(partition, key);
+            }
+        }
+        doomed.len()
     }
 
     /// Remove expired entries from the cache
@@ -316,9 +543,8 @@ impl ResourceCache {
 
     /// Generate a cache key for a URL
     fn cache_key(&self, url: &Url) -> String {
-        // Use the full URL as the cache key
-        // In a more sophisticated implementation, we might normalize URLs
-        url.as_str().to_string()
+        // Canonical form, so equivalent spellings of a URL share one entry
+        crate::url_canon::cache_key(url)
     }
 
     /// Calculate TTL for a response based on headers and configuration
@@ -407,6 +633,109 @@ impl ResourceCache {
     }
 }
 
+/// Response caches, one per partition, shared by the browser engine and the
+/// resource managers fetching for its tabs. Partitions keep their own
+/// entries but hold identical bodies once.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceCaches {
+    partitions: Arc<Mutex<HashMap<CachePartition, Arc<ResourceCache>>>>,
+    bodies: BodyStore,
+    /// Where persistent partitions are written through to
+    storage: Option<Arc<dyn CacheStorage>>,
+}
+
+impl ResourceCaches {
+    /// Write the persistent partitions through to `storage`, and look their
+    /// misses up there
+    pub fn with_storage(mut self, storage: Arc<dyn CacheStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// The cache of a partition, created empty on first use, or backed by
+    /// what the disk holds for it
+    pub fn for_partition(&self, partition: CachePartition) -> Arc<ResourceCache> {
+        match self.partitions.lock() {
+            Ok(mut partitions) => partitions
+                .entry(partition.clone())
+                .or_insert_with(|| {
+                    let cache = ResourceCache::default().with_body_store(self.bodies.clone());
+                    Arc::new(match &self.storage {
+                        Some(storage) if partition.is_persistent() => {
+                            cache.with_storage(storage.clone(), partition)
+                        }
+                        _ => cache,
+                    })
+                })
+                .clone(),
+            // A poisoned registry still answers, it just stops sharing
+            Err(_) => Arc::new(ResourceCache::default()),
+        }
+    }
+
+    /// Entries of every non-empty partition, ordered by partition
+    pub fn snapshot(&self) -> Vec<(CachePartition, Vec<CacheEntryInfo>)> {
+        let mut partitions: Vec<_> = self
+            .caches()
+            .into_iter()
+            .map(|(partition, cache)| (partition, cache.entries()))
+            .filter(|(_, entries)| !entries.is_empty())
+            .collect();
+        partitions.sort_by_key(|(partition, _)| partition.to_string());
+        partitions
+    }
+
+    /// Drop the response for `url` from every partition
+    pub fn remove(&self, url: &Url) -> usize {
+        self.caches()
+            .iter()
+            .filter(|(_, cache)| cache.remove(url))
+            .count()
+    }
+
+    /// Drop every response from `host`, in every partition
+    pub fn purge_host(&self, host: &str) -> usize {
+        self.caches()
+            .iter()
+            .map(|(_, cache)| cache.purge_host(host))
+            .sum()
+    }
+
+    /// Drop the partitions of a closed ephemeral tab
+    pub fn release_tab(&self, tab_id: Uuid) {
+        if let Ok(mut partitions) = self.partitions.lock() {
+            partitions.retain(|partition, _| {
+                !matches!(partition, CachePartition::Ephemeral { tab_id: id, .. } if *id == tab_id)
+            });
+        }
+    }
+
+    /// Drop every partition, on disk too
+    pub fn clear(&self) {
+        if let Ok(mut partitions) = self.partitions.lock() {
+            for cache in partitions.values() {
+                cache.clear();
+            }
+            partitions.clear();
+        }
+        if let Some(storage) = &self.storage {
+            storage.clear();
+        }
+    }
+
+    fn caches(&self) -> Vec<(CachePartition, Arc<ResourceCache>)> {
+        self.partitions
+            .lock()
+            .map(|partitions| {
+                partitions
+                    .iter()
+                    .map(|(partition, cache)| (partition.clone(), cache.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
 /// Cache statistics
 #[derive(Debug, Clone, Default)]
 pub struct CacheStats {
@@ -580,6 +909,81 @@ mod tests {
         assert!(stats.total_size_bytes > 0);
     }
 
+    #[test]
+    fn test_cold_start_reads_backing_store() {
+        use crate::cache_storage::MemoryStorage;
+
+        let storage: Arc<dyn CacheStorage> = Arc::new(MemoryStorage::default());
+        let page = Url::parse("https://example.com/").expect("Test URL should be valid");
+        let partition = CachePartition::for_url(&page).unwrap();
+        let url = Url::parse("https://example.com/app.css").expect("Test URL should be valid");
+
+        let first = ResourceCache::default().with_storage(storage.clone(), partition.clone());
+        first
+            .put(&url, create_test_response(url.as_str(), "body {}"))
+            .expect("Cache put should succeed");
+        drop(first);
+
+        let restarted = ResourceCache::default().with_storage(storage.clone(), partition.clone());
+        let cached = restarted.get(&url).expect("Entry should come from storage");
+        assert!(cached.from_cache());
+        assert_eq!(restarted.stats().entry_count, 1);
+
+        let other_site =
+            CachePartition::for_url(&Url::parse("https://other.test/").unwrap()).unwrap();
+        assert!(ResourceCache::default()
+            .with_storage(storage.clone(), other_site)
+            .get(&url)
+            .is_none());
+
+        restarted.clear();
+        assert!(ResourceCache::default()
+            .with_storage(storage, partition)
+            .get(&url)
+            .is_none());
+    }
+
+    #[test]
+    fn test_enumerate_and_purge_entries() {
+        use crate::cache_storage::MemoryStorage;
+
+        let storage: Arc<dyn CacheStorage> = Arc::new(MemoryStorage::default());
+        let partition =
+            CachePartition::for_url(&Url::parse("https://example.com/").unwrap()).unwrap();
+        let cache = ResourceCache::default().with_storage(storage.clone(), partition.clone());
+        for url in [
+            "https://example.com/app.css",
+            "https://cdn.test/lib.js",
+            "https://cdn.test/font.woff2",
+        ] {
+            let url = Url::parse(url).unwrap();
+            cache
+                .put(&url, create_test_response(url.as_str(), "cached"))
+                .unwrap();
+        }
+        let css = Url::parse("https://example.com/app.css").unwrap();
+        cache.get(&css);
+
+        let entries = cache.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].url.as_str(), "https://cdn.test/font.woff2");
+        let css_info = entries.iter().find(|info| info.url == css).unwrap();
+        assert_eq!(css_info.hits, 1);
+        assert_eq!(css_info.partition.as_ref(), Some(&partition));
+        assert!(css_info.expires_in.is_some());
+
+        assert_eq!(cache.purge_host("CDN.test"), 2);
+        assert_eq!(cache.entries().len(), 1);
+        assert!(cache.remove(&css));
+        assert!(!cache.remove(&css));
+        assert_eq!(cache.stats().total_size_bytes, 0);
+        // Purged entries do not come back from the backing store
+        assert!(ResourceCache::default()
+            .with_storage(storage, partition)
+            .get(&css)
+            .is_none());
+    }
+
     #[test]
     fn test_cache_clear() {
         let cache = ResourceCache::default();
@@ -601,4 +1005,26 @@ mod tests {
         assert_eq!(stats.entry_count, 0);
         assert_eq!(stats.total_size_bytes, 0);
     }
+    #[test]
+    fn test_caches_share_identical_bodies() {
+        let bodies = BodyStore::default();
+        let news = ResourceCache::default().with_body_store(bodies.clone());
+        let shop = ResourceCache::default().with_body_store(bodies.clone());
+        let url = Url::parse("https://cdn.example.com/framework.js").unwrap();
+        let body = "function framework(){}".repeat(10);
+
+        for cache in [&news, &shop] {
+            let response = create_test_response(url.as_str(), &body);
+            cache.put(&url, response).expect("Cache put should succeed");
+        }
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies.size_bytes(), body.len());
+        let (a, b) = (news.get(&url).unwrap(), shop.get(&url).unwrap());
+        assert_eq!(a.body().as_ptr(), b.body().as_ptr());
+
+        // Each cache keeps its own entries
+        news.clear();
+        assert!(news.get(&url).is_none());
+        assert_eq!(shop.get(&url).unwrap().body_text().unwrap(), body);
+    }
 }
@@ -0,0 +1,1117 @@
+//! Storage backends for the resource cache
+//!
+//! [`ResourceCache`](crate::cache::ResourceCache) keeps hot entries in memory;
+//! a [`CacheStorage`] behind it keeps them across restarts so a cold start
+//! does not refetch everything. Entries are stored per [`CachePartition`]:
+//!
+//! - regular tabs share one directory per top-level site,
+//! - container tabs get their own directories, and their entries are sealed
+//!   with AES-256-GCM under a key derived for the container; without a key
+//!   they are not written at all,
+//! - ephemeral tabs never touch the disk.
+//!
+//! Directory and file names are hashes, so the cache directory does not list
+//! the sites visited. The store is capped in bytes and evicts the least
+//! recently used entries first.
+//!
+//! Bodies of regular entries are stored once by content: the entry keeps its
+//! partition's metadata and the SHA-256 of its body, which lives in a shared
+//! `blobs` directory for as long as some entry refers to it. A framework
+//! script cached under ten sites takes its size once. Lookups still go
+//! through the partition's own entry, so a site only hits on what was
+//! cached under it, whatever other partitions hold. Sealed container
+//! entries keep their bodies inline.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use bytes::Bytes;
+use citadel_security::secrets::SecretKey;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+
+use crate::cache::CacheEntry;
+use crate::error::NetworkError;
+use crate::request::{Method, Request};
+use crate::resource_manager::ResourceManager;
+use crate::response::Response;
+
+/// Environment variable overriding where the disk cache lives
+pub const CACHE_DIR_ENV: &str = "CITADEL_CACHE_DIR";
+
+/// Default cap for the disk cache
+pub const DEFAULT_DISK_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Default cap for ephemeral entries held in memory
+pub const DEFAULT_MEMORY_CACHE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Start of every entry file
+const ENTRY_MAGIC: &[u8] = b"CTDLCCH1";
+const PLAIN: u8 = 0;
+const SEALED: u8 = 1;
+/// Metadata only; the body is a shared blob named by its digest
+const DEDUPED: u8 = 2;
+const NONCE_LEN: usize = 12;
+const DIGEST_LEN: usize = 32;
+
+/// Directory under the root holding shared bodies
+const BLOB_DIR: &str = "blobs";
+
+/// SHA-256 of a body, naming its blob
+type BodyDigest = [u8; DIGEST_LEN];
+
+/// Which cached state a load may share
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CachePartition {
+    /// Regular tabs on a top-level site
+    Shared { top_level_site: String },
+    /// Tabs of one container on a top-level site; encrypted at rest
+    Container {
+        container_id: Uuid,
+        top_level_site: String,
+    },
+    /// One ephemeral tab; memory only
+    Ephemeral {
+        tab_id: Uuid,
+        top_level_site: String,
+    },
+}
+
+impl CachePartition {
+    /// Shared partition of a top-level page. `None` for URLs without a host.
+    pub fn for_url(top_level: &Url) -> Option<Self> {
+        let host = top_level.host_str()?.to_ascii_lowercase();
+        Some(Self::Shared {
+            top_level_site: ResourceManager::extract_domain(&host),
+        })
+    }
+
+    /// Partition a request's response is cached under: its network
+    /// partition's site, confined to its ephemeral tab or container. `None`
+    /// for requests that name no partition.
+    pub fn for_request(request: &Request) -> Option<Self> {
+        let key = request.partition_key()?;
+        let shared = Self::Shared {
+            top_level_site: key.top_level_site.clone(),
+        };
+        Some(match (key.ephemeral_tab, request.container()) {
+            (Some(tab_id), _) => shared.in_ephemeral_tab(tab_id),
+            (None, Some(container_id)) => shared.in_container(container_id),
+            (None, None) => shared,
+        })
+    }
+
+    /// The same site, confined to a container
+    pub fn in_container(self, container_id: Uuid) -> Self {
+        Self::Container {
+            container_id,
+            top_level_site: self.into_site(),
+        }
+    }
+
+    /// The same site, confined to an ephemeral tab
+    pub fn in_ephemeral_tab(self, tab_id: Uuid) -> Self {
+        Self::Ephemeral {
+            tab_id,
+            top_level_site: self.into_site(),
+        }
+    }
+
+    /// Whether entries may be written to disk
+    pub fn is_persistent(&self) -> bool {
+        !matches!(self, Self::Ephemeral { .. })
+    }
+
+    /// The top-level site the partition belongs to
+    pub fn top_level_site(&self) -> &str {
+        match self {
+            Self::Shared { top_level_site }
+            | Self::Container { top_level_site, .. }
+            | Self::Ephemeral { top_level_site, .. } => top_level_site,
+        }
+    }
+
+    fn into_site(self) -> String {
+        match self {
+            Self::Shared { top_level_site }
+            | Self::Container { top_level_site, .. }
+            | Self::Ephemeral { top_level_site, .. } => top_level_site,
+        }
+    }
+
+    /// Directory of the partition, named so it does not reveal the site
+    fn directory_name(&self) -> String {
+        let label = match self {
+            Self::Shared { top_level_site } => format!("shared\n{top_level_site}"),
+            Self::Container {
+                container_id,
+                top_level_site,
+            } => format!("container\n{container_id}\n{top_level_site}"),
+            Self::Ephemeral {
+                tab_id,
+                top_level_site,
+            } => format!("ephemeral\n{tab_id}\n{top_level_site}"),
+        };
+        hex_digest(label.as_bytes())
+    }
+}
+
+impl fmt::Display for CachePartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shared { top_level_site } => write!(f, "{top_level_site}"),
+            Self::Container {
+                container_id,
+                top_level_site,
+            } => write!(f, "{top_level_site} in container {container_id}"),
+            Self::Ephemeral {
+                tab_id,
+                top_level_site,
+            } => write!(f, "{top_level_site} in ephemeral tab {tab_id}"),
+        }
+    }
+}
+
+/// A cached response as kept by a storage backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub url: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+    /// Seconds since the Unix epoch after which the entry is stale
+    pub expires_at: u64,
+}
+
+impl StoredResponse {
+    /// Snapshot of a cache entry
+    pub fn from_entry(entry: &CacheEntry) -> Self {
+        let remaining = entry.expires_at.saturating_duration_since(Instant::now());
+        let response = &entry.response;
+        Self {
+            url: response.url().to_string(),
+            status: response.status(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: response.body().to_vec(),
+            expires_at: unix_seconds(SystemTime::now() + remaining),
+        }
+    }
+
+    /// Time left before the entry goes stale; `None` once it has
+    pub fn remaining_ttl(&self) -> Option<Duration> {
+        let remaining = self
+            .expires_at
+            .checked_sub(unix_seconds(SystemTime::now()))?;
+        (remaining > 0).then_some(Duration::from_secs(remaining))
+    }
+
+    /// The response again, marked as served from cache
+    pub fn into_response(self) -> Option<Response> {
+        let url = Url::parse(&self.url).ok()?;
+        let mut response = Response::new(
+            self.status,
+            self.headers,
+            Bytes::from(self.body),
+            url,
+            Method::GET,
+        );
+        response.set_from_cache(true);
+        Some(response)
+    }
+
+    fn size_bytes(&self) -> u64 {
+        let headers: usize = self.headers.iter().map(|(k, v)| k.len() + v.len()).sum();
+        (self.body.len() + headers + self.url.len()) as u64
+    }
+
+    /// Metadata length, metadata as JSON, then the raw body
+    fn encode(&self) -> Result<Vec<u8>, NetworkError> {
+        let meta = serde_json::to_vec(self)?;
+        let meta_len = u32::try_from(meta.len())
+            .map_err(|_| NetworkError::ResourceError("cache metadata too large".into()))?;
+        let mut out = Vec::with_capacity(4 + meta.len() + self.body.len());
+        out.extend_from_slice(&meta_len.to_le_bytes());
+        out.extend_from_slice(&meta);
+        out.extend_from_slice(&self.body);
+        Ok(out)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (len, rest) = bytes.split_at_checked(4)?;
+        let meta_len = u32::from_le_bytes(len.try_into().ok()?) as usize;
+        let (meta, body) = rest.split_at_checked(meta_len)?;
+        let mut stored: Self = serde_json::from_slice(meta).ok()?;
+        stored.body = body.to_vec();
+        Some(stored)
+    }
+}
+
+/// Where cached responses are kept beyond the in-memory working set
+pub trait CacheStorage: fmt::Debug + Send + Sync {
+    /// A fresh entry; stale entries are dropped and not returned
+    fn get(&self, partition: &CachePartition, key: &str) -> Option<StoredResponse>;
+
+    /// Store or replace an entry
+    fn put(
+        &self,
+        partition: &CachePartition,
+        key: &str,
+        entry: StoredResponse,
+    ) -> Result<(), NetworkError>;
+
+    fn remove(&self, partition: &CachePartition, key: &str);
+
+    /// Drop everything stored for one partition, e.g. a closed ephemeral tab
+    fn clear_partition(&self, partition: &CachePartition);
+
+    fn clear(&self);
+
+    /// Bytes currently stored
+    fn size_bytes(&self) -> u64;
+}
+
+/// Recency-ordered index shared by both backends
+#[derive(Debug)]
+struct LruIndex<K, V> {
+    /// Value, size in bytes and last use of each entry
+    entries: HashMap<K, (V, u64, u64)>,
+    clock: u64,
+    total: u64,
+}
+
+impl<K, V> Default for LruIndex<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            clock: 0,
+            total: 0,
+        }
+    }
+}
+
+impl<K: Clone + Eq + std::hash::Hash, V> LruIndex<K, V> {
+    fn touch(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let now = self.clock;
+        let (value, _, last_used) = self.entries.get_mut(key)?;
+        *last_used = now;
+        Some(value)
+    }
+
+    fn insert(&mut self, key: K, value: V, size: u64) {
+        self.remove(&key);
+        self.clock += 1;
+        self.total += size;
+        self.entries.insert(key, (value, size, self.clock));
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, size, _) = self.entries.remove(key)?;
+        self.total = self.total.saturating_sub(size);
+        Some(value)
+    }
+
+    /// Least recently used entries to drop so `incoming` more bytes fit in
+    /// `max_bytes`
+    fn evict_for(&mut self, incoming: u64, max_bytes: u64) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.total + incoming > max_bytes {
+            let Some(oldest) = self.pop_oldest() else {
+                break;
+            };
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    /// Remove the least recently used entry
+    fn pop_oldest(&mut self) -> Option<(K, V)> {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, _, last_used))| *last_used)
+            .map(|(key, _)| key.clone())?;
+        let value = self.remove(&oldest)?;
+        Some((oldest, value))
+    }
+
+    /// Keep only entries `keep` accepts; returns the others
+    fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) -> Vec<(K, V)> {
+        let doomed: Vec<K> = self
+            .entries
+            .keys()
+            .filter(|key| !keep(key))
+            .cloned()
+            .collect();
+        doomed
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.remove(&key)?;
+                Some((key, value))
+            })
+            .collect()
+    }
+}
+
+/// Memory-only storage, used for ephemeral partitions
+#[derive(Debug)]
+pub struct MemoryStorage {
+    max_bytes: u64,
+    index: Mutex<LruIndex<(CachePartition, String), StoredResponse>>,
+}
+
+impl MemoryStorage {
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            index: Mutex::new(LruIndex::default()),
+        }
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_CACHE_BYTES)
+    }
+}
+
+impl CacheStorage for MemoryStorage {
+    fn get(&self, partition: &CachePartition, key: &str) -> Option<StoredResponse> {
+        let mut index = self.index.lock().ok()?;
+        let id = (partition.clone(), key.to_string());
+        let stored = index.touch(&id)?.clone();
+        if stored.remaining_ttl().is_none() {
+            index.remove(&id);
+            return None;
+        }
+        Some(stored)
+    }
+
+    fn put(
+        &self,
+        partition: &CachePartition,
+        key: &str,
+        entry: StoredResponse,
+    ) -> Result<(), NetworkError> {
+        let size = entry.size_bytes();
+        if size > self.max_bytes {
+            return Err(NetworkError::ResourceError(format!(
+                "Response too large to cache: {} bytes",
+                size
+            )));
+        }
+        if let Ok(mut index) = self.index.lock() {
+            let id = (partition.clone(), key.to_string());
+            index.remove(&id);
+            index.evict_for(size, self.max_bytes);
+            index.insert(id, entry, size);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, partition: &CachePartition, key: &str) {
+        if let Ok(mut index) = self.index.lock() {
+            index.remove(&(partition.clone(), key.to_string()));
+        }
+    }
+
+    fn clear_partition(&self, partition: &CachePartition) {
+        if let Ok(mut index) = self.index.lock() {
+            index.retain(|(other, _)| other != partition);
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut index) = self.index.lock() {
+            *index = LruIndex::default();
+        }
+    }
+
+    fn size_bytes(&self) -> u64 {
+        self.index.lock().map(|index| index.total).unwrap_or(0)
+    }
+}
+
+/// A shared body and how many entries refer to it
+#[derive(Debug, Clone, Copy)]
+struct Blob {
+    refs: usize,
+    size: u64,
+}
+
+/// Disk-backed storage: one directory per partition under `root`, one file
+/// per entry, and shared bodies under `root/blobs`. Ephemeral partitions are
+/// kept in memory instead.
+pub struct DiskStorage {
+    root: PathBuf,
+    max_bytes: u64,
+    /// Seals container entries; without it they are not persisted
+    container_key: Option<SecretKey>,
+    /// Entry files by path, with the blob each refers to, for the size cap
+    /// and recency
+    index: Mutex<LruIndex<PathBuf, Option<BodyDigest>>>,
+    /// Shared bodies by digest
+    blobs: Mutex<HashMap<BodyDigest, Blob>>,
+    ephemeral: MemoryStorage,
+}
+
+impl fmt::Debug for DiskStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskStorage")
+            .field("root", &self.root)
+            .field("max_bytes", &self.max_bytes)
+            .field("encrypts_containers", &self.container_key.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DiskStorage {
+    /// Open (creating if needed) the store at `root`, indexing what an
+    /// earlier run left there
+    pub fn open(root: impl Into<PathBuf>, max_bytes: u64) -> Result<Self, NetworkError> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+
+        let mut files = Vec::new();
+        for partition in std::fs::read_dir(&root)?.flatten() {
+            if !partition.file_type().is_ok_and(|kind| kind.is_dir())
+                || partition.file_name() == BLOB_DIR
+            {
+                continue;
+            }
+            for entry in std::fs::read_dir(partition.path())?.flatten() {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+                // Leftovers of interrupted writes
+                if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+                    let _ = std::fs::remove_file(entry.path());
+                    continue;
+                }
+                let used = metadata.modified().unwrap_or(UNIX_EPOCH);
+                let blob = blob_reference(&entry.path());
+                files.push((used, entry.path(), metadata.len(), blob));
+            }
+        }
+        let blobs = index_blobs(&root.join(BLOB_DIR), &files)?;
+        // Oldest first, so recency survives the restart
+        files.sort();
+        let mut index = LruIndex::default();
+        for (_, path, size, blob) in files {
+            // An entry whose blob is gone fails its next read and is dropped
+            let blob = blob.filter(|digest| blobs.contains_key(digest));
+            index.insert(path, blob, size);
+        }
+
+        let storage = Self {
+            root,
+            max_bytes,
+            container_key: None,
+            index: Mutex::new(index),
+            blobs: Mutex::new(blobs),
+            ephemeral: MemoryStorage::default(),
+        };
+        storage.evict(0);
+        Ok(storage)
+    }
+
+    /// Encrypt container entries with keys derived from `master_key`
+    pub fn with_container_key(mut self, master_key: impl Into<SecretKey>) -> Self {
+        self.container_key = Some(master_key.into());
+        self
+    }
+
+    fn entry_path(&self, partition: &CachePartition, key: &str) -> PathBuf {
+        self.root
+            .join(partition.directory_name())
+            .join(hex_digest(key.as_bytes()))
+    }
+
+    /// Key sealing the entries of one container
+    fn partition_key(&self, partition: &CachePartition) -> Option<Option<SecretKey>> {
+        match partition {
+            CachePartition::Container { container_id, .. } => {
+                let master = self.container_key.as_ref()?;
+                let mut hasher = Sha256::new();
+                hasher.update(b"citadel-cache-container");
+                hasher.update(master.expose());
+                hasher.update(container_id.as_bytes());
+                let mut key = SecretKey::from_bytes([0u8; 32]);
+                key.expose_mut().copy_from_slice(&hasher.finalize());
+                Some(Some(key))
+            }
+            _ => Some(None),
+        }
+    }
+
+    fn blob_path(&self, digest: &BodyDigest) -> PathBuf {
+        self.root.join(BLOB_DIR).join(hex(digest))
+    }
+
+    fn read(&self, path: &Path, key: Option<&SecretKey>) -> Option<StoredResponse> {
+        let bytes = std::fs::read(path).ok()?;
+        let (flag, body) = bytes.strip_prefix(ENTRY_MAGIC)?.split_first()?;
+        let payload = match (*flag, key) {
+            (PLAIN, None) => body.to_vec(),
+            (DEDUPED, None) => {
+                if body.len() < DIGEST_LEN {
+                    return None;
+                }
+                let (digest, payload) = body.split_at(DIGEST_LEN);
+                let digest: BodyDigest = digest.try_into().ok()?;
+                let mut stored = StoredResponse::decode(payload)?;
+                let shared = std::fs::read(self.blob_path(&digest)).ok()?;
+                // Shared bodies are checked, so one bad blob fails loudly
+                // rather than serving the wrong content
+                if Sha256::digest(&shared)[..] != digest[..] {
+                    return None;
+                }
+                stored.body = shared;
+                return Some(stored);
+            }
+            (SEALED, Some(key)) => {
+                let (nonce, ciphertext) = body.split_at_checked(NONCE_LEN)?;
+                Aes256Gcm::new(key.expose().into())
+                    .decrypt(
+                        Nonce::from_slice(nonce),
+                        Payload {
+                            msg: ciphertext,
+                            aad: &aad(path),
+                        },
+                    )
+                    .ok()?
+            }
+            _ => return None,
+        };
+        StoredResponse::decode(&payload)
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        entry: &StoredResponse,
+        key: Option<&SecretKey>,
+        blob: Option<&BodyDigest>,
+    ) -> Result<u64, NetworkError> {
+        let mut bytes = ENTRY_MAGIC.to_vec();
+        match (key, blob) {
+            (None, Some(digest)) => {
+                let metadata = StoredResponse {
+                    body: Vec::new(),
+                    ..entry.clone()
+                };
+                bytes.push(DEDUPED);
+                bytes.extend_from_slice(digest);
+                bytes.extend_from_slice(&metadata.encode()?);
+            }
+            (None, None) => {
+                bytes.push(PLAIN);
+                bytes.extend_from_slice(&entry.encode()?);
+            }
+            (Some(key), _) => {
+                let payload = entry.encode()?;
+                let mut nonce = [0u8; NONCE_LEN];
+                rand::thread_rng().fill_bytes(&mut nonce);
+                let ciphertext = Aes256Gcm::new(key.expose().into())
+                    .encrypt(
+                        Nonce::from_slice(&nonce),
+                        Payload {
+                            msg: &payload,
+                            aad: &aad(path),
+                        },
+                    )
+                    .map_err(|_| NetworkError::ResourceError("cache encryption failed".into()))?;
+                bytes.push(SEALED);
+                bytes.extend_from_slice(&nonce);
+                bytes.extend_from_slice(&ciphertext);
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a crash never leaves a torn entry behind
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(bytes.len() as u64)
+    }
+
+    fn delete(&self, path: &Path) {
+        let blob = match self.index.lock() {
+            Ok(mut index) => index.remove(&path.to_path_buf()).flatten(),
+            Err(_) => None,
+        };
+        let _ = std::fs::remove_file(path);
+        if let Some(digest) = blob {
+            self.release_blob(&digest);
+        }
+    }
+
+    /// Refer to the blob holding `body`, writing it if no entry did yet
+    fn retain_blob(&self, body: &[u8]) -> Result<BodyDigest, NetworkError> {
+        let digest: BodyDigest = Sha256::digest(body).into();
+        let mut blobs = self
+            .blobs
+            .lock()
+            .map_err(|_| NetworkError::ResourceError("cache blob index poisoned".into()))?;
+        if let Some(blob) = blobs.get_mut(&digest) {
+            blob.refs += 1;
+            return Ok(digest);
+        }
+        let path = self.blob_path(&digest);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, body)?;
+        std::fs::rename(&tmp, &path)?;
+        blobs.insert(
+            digest,
+            Blob {
+                refs: 1,
+                size: body.len() as u64,
+            },
+        );
+        Ok(digest)
+    }
+
+    /// Drop a reference to a blob, and the blob with its last one
+    fn release_blob(&self, digest: &BodyDigest) {
+        let Ok(mut blobs) = self.blobs.lock() else {
+            return;
+        };
+        let Some(blob) = blobs.get_mut(digest) else {
+            return;
+        };
+        blob.refs = blob.refs.saturating_sub(1);
+        if blob.refs == 0 {
+            blobs.remove(digest);
+            let _ = std::fs::remove_file(self.blob_path(digest));
+        }
+    }
+
+    fn blob_bytes(&self) -> u64 {
+        self.blobs
+            .lock()
+            .map(|blobs| blobs.values().map(|blob| blob.size).sum())
+            .unwrap_or(0)
+    }
+
+    fn disk_bytes(&self) -> u64 {
+        let entries = self.index.lock().map(|index| index.total).unwrap_or(0);
+        entries + self.blob_bytes()
+    }
+
+    /// Drop least recently used entries, and blobs no entry refers to any
+    /// more, until `incoming` more bytes fit
+    fn evict(&self, incoming: u64) {
+        while self.disk_bytes() + incoming > self.max_bytes {
+            let oldest = match self.index.lock() {
+                Ok(mut index) => index.pop_oldest(),
+                Err(_) => return,
+            };
+            let Some((path, blob)) = oldest else {
+                return;
+            };
+            log::debug!("Evicted disk cache entry {}", path.display());
+            let _ = std::fs::remove_file(path);
+            if let Some(digest) = blob {
+                self.release_blob(&digest);
+            }
+        }
+    }
+}
+
+/// The blob an entry file refers to, if it is a deduplicated entry
+fn blob_reference(path: &Path) -> Option<BodyDigest> {
+    use std::io::Read;
+
+    let mut header = [0u8; 8 + 1 + DIGEST_LEN];
+    std::fs::File::open(path)
+        .ok()?
+        .read_exact(&mut header)
+        .ok()?;
+    let (flag, digest) = header.strip_prefix(ENTRY_MAGIC)?.split_first()?;
+    (*flag == DEDUPED).then(|| digest.try_into().ok()).flatten()
+}
+
+/// Blobs referred to by `files`, with their reference counts. Blobs no entry
+/// refers to are left over from an interrupted run and deleted.
+fn index_blobs(
+    dir: &Path,
+    files: &[(SystemTime, PathBuf, u64, Option<BodyDigest>)],
+) -> std::io::Result<HashMap<BodyDigest, Blob>> {
+    let mut refs: HashMap<BodyDigest, usize> = HashMap::new();
+    for digest in files.iter().filter_map(|(_, _, _, blob)| *blob) {
+        *refs.entry(digest).or_default() += 1;
+    }
+    let mut blobs = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(blobs);
+    };
+    for entry in entries.flatten() {
+        let digest = entry
+            .file_name()
+            .to_str()
+            .and_then(parse_hex)
+            .filter(|digest| refs.contains_key(digest));
+        match (digest, entry.metadata()) {
+            (Some(digest), Ok(metadata)) if metadata.is_file() => {
+                blobs.insert(
+                    digest,
+                    Blob {
+                        refs: refs[&digest],
+                        size: metadata.len(),
+                    },
+                );
+            }
+            _ => {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    Ok(blobs)
+}
+
+impl CacheStorage for DiskStorage {
+    fn get(&self, partition: &CachePartition, key: &str) -> Option<StoredResponse> {
+        if !partition.is_persistent() {
+            return self.ephemeral.get(partition, key);
+        }
+        let sealing_key = self.partition_key(partition)?;
+        let path = self.entry_path(partition, key);
+        self.index.lock().ok()?.touch(&path)?;
+
+        let Some(stored) = self.read(&path, sealing_key.as_ref()) else {
+            // Corrupt, or sealed under a key we no longer have
+            self.delete(&path);
+            return None;
+        };
+        if stored.remaining_ttl().is_none() {
+            self.delete(&path);
+            return None;
+        }
+        // Keep recency across restarts; best effort
+        if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(stored)
+    }
+
+    fn put(
+        &self,
+        partition: &CachePartition,
+        key: &str,
+        entry: StoredResponse,
+    ) -> Result<(), NetworkError> {
+        if !partition.is_persistent() {
+            return self.ephemeral.put(partition, key, entry);
+        }
+        let Some(sealing_key) = self.partition_key(partition) else {
+            log::debug!("No container key; keeping container entries off disk");
+            return Ok(());
+        };
+        let size = entry.size_bytes();
+        if size > self.max_bytes {
+            return Err(NetworkError::ResourceError(format!(
+                "Response too large to cache: {} bytes",
+                size
+            )));
+        }
+
+        let path = self.entry_path(partition, key);
+        self.delete(&path);
+        // Sealed bodies stay inline; a shared blob would be plaintext
+        let blob = match sealing_key {
+            Some(_) => None,
+            None => Some(self.retain_blob(&entry.body)?),
+        };
+        let written = match self.write(&path, &entry, sealing_key.as_ref(), blob.as_ref()) {
+            Ok(written) => written,
+            Err(e) => {
+                if let Some(digest) = &blob {
+                    self.release_blob(digest);
+                }
+                return Err(e);
+            }
+        };
+        if let Ok(mut index) = self.index.lock() {
+            index.insert(path, blob, written);
+        }
+        // The new entry is the most recent, so older ones go first
+        self.evict(0);
+        Ok(())
+    }
+
+    fn remove(&self, partition: &CachePartition, key: &str) {
+        if !partition.is_persistent() {
+            return self.ephemeral.remove(partition, key);
+        }
+        self.delete(&self.entry_path(partition, key));
+    }
+
+    fn clear_partition(&self, partition: &CachePartition) {
+        if !partition.is_persistent() {
+            return self.ephemeral.clear_partition(partition);
+        }
+        let directory = self.root.join(partition.directory_name());
+        let removed = match self.index.lock() {
+            Ok(mut index) => index.retain(|path| !path.starts_with(&directory)),
+            Err(_) => Vec::new(),
+        };
+        let _ = std::fs::remove_dir_all(directory);
+        for digest in removed.iter().filter_map(|(_, blob)| blob.as_ref()) {
+            self.release_blob(digest);
+        }
+    }
+
+    fn clear(&self) {
+        self.ephemeral.clear();
+        if let Ok(mut index) = self.index.lock() {
+            *index = LruIndex::default();
+        }
+        if let Ok(mut blobs) = self.blobs.lock() {
+            blobs.clear();
+        }
+        if let Ok(partitions) = std::fs::read_dir(&self.root) {
+            for partition in partitions.flatten() {
+                let _ = std::fs::remove_dir_all(partition.path());
+            }
+        }
+    }
+
+    fn size_bytes(&self) -> u64 {
+        self.disk_bytes() + self.ephemeral.size_bytes()
+    }
+}
+
+/// Where the disk cache lives: `$CITADEL_CACHE_DIR`, otherwise
+/// `citadel/http` under the XDG cache directory
+pub fn default_dir() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CACHE_DIR_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_dir.join("citadel").join("http"))
+}
+
+/// Binds a sealed entry to its location, so files cannot be swapped
+/// between entries or containers
+fn aad(path: &Path) -> Vec<u8> {
+    let mut aad = ENTRY_MAGIC.to_vec();
+    for part in path.iter().rev().take(2) {
+        aad.extend_from_slice(part.as_encoded_bytes());
+    }
+    aad
+}
+
+fn hex_digest(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn parse_hex(name: &str) -> Option<BodyDigest> {
+    if name.len() != DIGEST_LEN * 2 || !name.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; DIGEST_LEN];
+    for (byte, pair) in digest.iter_mut().zip(name.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("citadel-cache-test-{}", Uuid::new_v4()))
+    }
+
+    fn stored(url: &str, body: &str) -> StoredResponse {
+        StoredResponse {
+            url: url.to_string(),
+            status: 200,
+            headers: vec![("content-type".into(), "text/css".into())],
+            body: body.as_bytes().to_vec(),
+            expires_at: unix_seconds(SystemTime::now()) + 3600,
+        }
+    }
+
+    fn files_under(root: &Path) -> Vec<Vec<u8>> {
+        std::fs::read_dir(root)
+            .unwrap()
+            .flatten()
+            .flat_map(|dir| std::fs::read_dir(dir.path()).unwrap().flatten())
+            .map(|file| std::fs::read(file.path()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_entries_survive_restart_per_partition() {
+        let root = temp_root();
+        let page = Url::parse("https://news.example.com/").unwrap();
+        let shared = CachePartition::for_url(&page).unwrap();
+        let ephemeral = shared.clone().in_ephemeral_tab(Uuid::new_v4());
+        let key = "https://cdn.example.com/site.css";
+
+        let storage = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES).unwrap();
+        storage.put(&shared, key, stored(key, "body{}")).unwrap();
+        storage
+            .put(&ephemeral, key, stored(key, "secret{}"))
+            .unwrap();
+        assert_eq!(storage.get(&ephemeral, key).unwrap().body, b"secret{}");
+        drop(storage);
+
+        let reopened = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES).unwrap();
+        let hit = reopened.get(&shared, key).unwrap();
+        assert_eq!(hit.body, b"body{}");
+        assert!(hit.into_response().unwrap().from_cache());
+        assert!(reopened.get(&ephemeral, key).is_none());
+        let other = CachePartition::for_url(&Url::parse("https://other.test/").unwrap()).unwrap();
+        assert!(reopened.get(&other, key).is_none());
+        assert!(files_under(&root)
+            .iter()
+            .all(|file| !file.windows(8).any(|w| w == b"secret{}")));
+
+        reopened.clear();
+        assert_eq!(reopened.size_bytes(), 0);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_container_entries_are_sealed() {
+        let root = temp_root();
+        let page = Url::parse("https://bank.example/").unwrap();
+        let container = CachePartition::for_url(&page)
+            .unwrap()
+            .in_container(Uuid::new_v4());
+        let key = "https://bank.example/account.css";
+
+        let unkeyed = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES).unwrap();
+        unkeyed
+            .put(&container, key, stored(key, "balance{}"))
+            .unwrap();
+        assert!(unkeyed.get(&container, key).is_none());
+        assert_eq!(unkeyed.size_bytes(), 0);
+
+        let keyed = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES)
+            .unwrap()
+            .with_container_key([7; 32]);
+        keyed
+            .put(&container, key, stored(key, "balance{}"))
+            .unwrap();
+        assert_eq!(keyed.get(&container, key).unwrap().body, b"balance{}");
+        assert!(files_under(&root)
+            .iter()
+            .all(|file| !file.windows(9).any(|w| w == b"balance{}")));
+
+        let wrong_key = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES)
+            .unwrap()
+            .with_container_key([8; 32]);
+        assert!(wrong_key.get(&container, key).is_none());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_size_cap_evicts_least_recently_used() {
+        let root = temp_root();
+        let partition = CachePartition::for_url(&Url::parse("https://a.test/").unwrap()).unwrap();
+        // Distinct bodies, so none of them is shared
+        let body = |name: &str| name.repeat(400);
+        let entry_size = {
+            let storage = DiskStorage::open(&root, u64::MAX).unwrap();
+            storage
+                .put(
+                    &partition,
+                    "probe",
+                    stored("https://a.test/probe", &body("0")),
+                )
+                .unwrap();
+            let size = storage.size_bytes();
+            storage.clear();
+            size
+        };
+
+        let storage = DiskStorage::open(&root, entry_size * 2).unwrap();
+        for name in ["1", "2"] {
+            let url = format!("https://a.test/{name}");
+            storage
+                .put(&partition, &url, stored(&url, &body(name)))
+                .unwrap();
+        }
+        assert!(storage.get(&partition, "https://a.test/1").is_some());
+        storage
+            .put(
+                &partition,
+                "https://a.test/3",
+                stored("https://a.test/3", &body("3")),
+            )
+            .unwrap();
+
+        assert!(storage.get(&partition, "https://a.test/1").is_some());
+        assert!(storage.get(&partition, "https://a.test/2").is_none());
+        assert!(storage.get(&partition, "https://a.test/3").is_some());
+        assert!(storage.size_bytes() <= entry_size * 2);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_identical_bodies_are_stored_once_across_partitions() {
+        let root = temp_root();
+        let news = CachePartition::for_url(&Url::parse("https://news.test/").unwrap()).unwrap();
+        let shop = CachePartition::for_url(&Url::parse("https://shop.test/").unwrap()).unwrap();
+        let key = "https://cdn.example.com/framework.js";
+        let body = "function framework(){}".repeat(50);
+        let blobs = |root: &Path| std::fs::read_dir(root.join(BLOB_DIR)).unwrap().count();
+
+        let storage = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES).unwrap();
+        storage.put(&news, key, stored(key, &body)).unwrap();
+        let single = storage.size_bytes();
+        storage.put(&shop, key, stored(key, &body)).unwrap();
+        assert_eq!(blobs(&root), 1);
+        assert!(storage.size_bytes() - single < body.len() as u64);
+        drop(storage);
+
+        // References are counted again on restart; each partition still only
+        // sees its own entry
+        let reopened = DiskStorage::open(&root, DEFAULT_DISK_CACHE_BYTES).unwrap();
+        reopened.clear_partition(&news);
+        assert!(reopened.get(&news, key).is_none());
+        assert_eq!(reopened.get(&shop, key).unwrap().body, body.as_bytes());
+        assert_eq!(blobs(&root), 1);
+        reopened.remove(&shop, key);
+        assert_eq!(blobs(&root), 0);
+        assert_eq!(reopened.size_bytes(), 0);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
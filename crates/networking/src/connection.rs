@@ -0,0 +1,248 @@
+//! Happy-eyeballs connection establishment (RFC 8305)
+//!
+//! On dual-stack networks a host's IPv6 route may be broken while IPv4 works,
+//! or the other way round. Connecting to one address at a time then stalls a
+//! page load for a full connect timeout before falling back. Instead the
+//! resolved addresses are interleaved by family and raced: a new attempt
+//! starts every [`CONNECTION_ATTEMPT_DELAY`] (or as soon as the previous one
+//! fails), and the first connection to succeed wins while the others are
+//! dropped.
+//!
+//! The family that won is remembered per host, so later connections to that
+//! host try it first.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::net::TcpStream;
+
+use crate::error::NetworkError;
+use crate::proxy;
+
+/// Delay before starting the next attempt while one is pending (RFC 8305 §5)
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+/// Time allowed for each individual address
+pub const PER_ADDRESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Address family of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    V6,
+    V4,
+}
+
+impl AddressFamily {
+    /// Family of an IP address
+    pub fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V6(_) => Self::V6,
+            IpAddr::V4(_) => Self::V4,
+        }
+    }
+}
+
+/// Races connection attempts and remembers which family worked for each host
+#[derive(Debug)]
+pub struct HappyEyeballs {
+    attempt_delay: Duration,
+    per_address_timeout: Duration,
+    preferred: Mutex<HashMap<String, AddressFamily>>,
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self::new(CONNECTION_ATTEMPT_DELAY, PER_ADDRESS_TIMEOUT)
+    }
+}
+
+impl HappyEyeballs {
+    /// Racer with custom timings
+    pub fn new(attempt_delay: Duration, per_address_timeout: Duration) -> Self {
+        Self {
+            attempt_delay,
+            per_address_timeout,
+            preferred: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The racer shared by every HTTPS fetch in the process
+    pub fn shared() -> &'static Self {
+        static SHARED: OnceLock<HappyEyeballs> = OnceLock::new();
+        SHARED.get_or_init(Self::default)
+    }
+
+    /// Family that last succeeded for a host, if any
+    pub fn preferred_family(&self, host: &str) -> Option<AddressFamily> {
+        self.preferred
+            .lock()
+            .ok()
+            .and_then(|preferred| preferred.get(&host.to_ascii_lowercase()).copied())
+    }
+
+    /// Resolve `host` and connect to the first address that answers
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TcpStream, NetworkError> {
+        let addrs = resolve(host, port).await?;
+        self.connect_addrs(host, &addrs).await
+    }
+
+    /// Race connections to already-resolved addresses of `host`
+    pub async fn connect_addrs(
+        &self,
+        host: &str,
+        addrs: &[SocketAddr],
+    ) -> Result<TcpStream, NetworkError> {
+        let ordered = sort_addresses(addrs, self.preferred_family(host));
+        let mut pending = ordered.into_iter();
+        let mut attempts = FuturesUnordered::new();
+        let mut last_error = None;
+
+        loop {
+            if let Some(addr) = pending.next() {
+                attempts.push(self.attempt(addr));
+            } else if attempts.is_empty() {
+                break;
+            }
+
+            // Wait for an attempt to finish, or for the stagger delay to start
+            // the next one while more addresses are left. A failure starts the
+            // next attempt immediately.
+            let outcome = if pending.len() > 0 {
+                match tokio::time::timeout(self.attempt_delay, attempts.next()).await {
+                    Ok(outcome) => outcome,
+                    Err(_) => continue,
+                }
+            } else {
+                attempts.next().await
+            };
+            match outcome {
+                Some((addr, Ok(stream))) => {
+                    self.remember(host, AddressFamily::of(&addr.ip()));
+                    log::debug!("Connected to {host} via {addr}");
+                    return Ok(stream);
+                }
+                Some((addr, Err(e))) => {
+                    log::debug!("Connection to {host} via {addr} failed: {e}");
+                    last_error = Some(e);
+                }
+                None => {}
+            }
+        }
+
+        Err(NetworkError::ConnectionError(match last_error {
+            Some(e) => format!("could not connect to {host}: {e}"),
+            None => format!("no addresses for {host}"),
+        }))
+    }
+
+    async fn attempt(&self, addr: SocketAddr) -> (SocketAddr, std::io::Result<TcpStream>) {
+        let result = tokio::time::timeout(self.per_address_timeout, TcpStream::connect(addr))
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "connect timed out",
+                ))
+            });
+        (addr, result)
+    }
+
+    fn remember(&self, host: &str, family: AddressFamily) {
+        if let Ok(mut preferred) = self.preferred.lock() {
+            preferred.insert(host.to_ascii_lowercase(), family);
+        }
+    }
+}
+
+/// Resolve `host` with the system resolver. Onion names are refused
+/// without a lookup.
+pub async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, NetworkError> {
+    if proxy::is_onion_host(host) {
+        return Err(proxy::onion_resolution_error(host));
+    }
+    Ok(tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| NetworkError::DnsError(format!("{host}: {e}")))?
+        .collect())
+}
+
+/// Interleave addresses by family, starting with `first` (IPv6 when unknown),
+/// keeping the resolver's order within each family (RFC 8305 §4)
+pub fn sort_addresses(addrs: &[SocketAddr], first: Option<AddressFamily>) -> Vec<SocketAddr> {
+    let first = first.unwrap_or(AddressFamily::V6);
+    let (mut leading, mut trailing): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .iter()
+        .partition(|addr| AddressFamily::of(&addr.ip()) == first);
+    if leading.is_empty() {
+        std::mem::swap(&mut leading, &mut trailing);
+    }
+
+    let mut ordered = Vec::with_capacity(addrs.len());
+    let mut leading = leading.into_iter();
+    let mut trailing = trailing.into_iter();
+    loop {
+        match (leading.next(), trailing.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_sort_interleaves_families() {
+        let addrs = [
+            addr("192.0.2.1:443"),
+            addr("192.0.2.2:443"),
+            addr("[2001:db8::1]:443"),
+            addr("[2001:db8::2]:443"),
+        ];
+        assert_eq!(
+            sort_addresses(&addrs, None),
+            vec![addrs[2], addrs[0], addrs[3], addrs[1]]
+        );
+        assert_eq!(
+            sort_addresses(&addrs, Some(AddressFamily::V4)),
+            vec![addrs[0], addrs[2], addrs[1], addrs[3]]
+        );
+        assert_eq!(
+            sort_addresses(&addrs[..2], Some(AddressFamily::V6)),
+            addrs[..2].to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_and_remembers_working_family() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let working = listener.local_addr().unwrap();
+        // Nothing listens on this one, so the IPv6 attempt fails first.
+        let dead = TcpListener::bind("[::1]:0")
+            .await
+            .map(|l| l.local_addr().unwrap())
+            .unwrap_or_else(|_| addr("[::1]:1"));
+
+        let racer = HappyEyeballs::new(Duration::from_millis(50), Duration::from_secs(2));
+        assert_eq!(racer.preferred_family("dual.test"), None);
+
+        let stream = racer
+            .connect_addrs("dual.test", &[dead, working])
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), working);
+        assert_eq!(racer.preferred_family("DUAL.test"), Some(AddressFamily::V4));
+
+        let err = racer.connect_addrs("none.test", &[]).await.unwrap_err();
+        assert!(err.to_string().contains("no addresses"));
+    }
+}
@@ -0,0 +1,744 @@
+//! Cookies, isolated by store and top-level site
+//!
+//! Cookies live in a [`CookieStoreId`]: regular tabs share one store,
+//! each container has its own, and each ephemeral tab (one ZKVM) gets a
+//! store that dies with it. Within a store, cookies are further partitioned
+//! by the top-level site they were set under, so no cookie set while
+//! visiting one site is ever sent while visiting another.
+//!
+//! Third-party cookies are refused by default: a request to a site other
+//! than the top-level page's neither sends nor stores cookies. With
+//! [`ThirdPartyCookies::Partitioned`] they are kept, but only within the
+//! top-level site's partition, and only `SameSite=None; Secure` cookies are
+//! sent cross-site.
+//!
+//! `Set-Cookie` is parsed per RFC 6265: `Domain` must cover the request
+//! host without widening to a public suffix, `Secure` cookies and the
+//! `__Secure-`/`__Host-` prefixes need HTTPS, and `Max-Age` takes precedence
+//! over `Expires`. Cookie values are [`SecretString`]s, so they zeroize on
+//! drop and never show up in `Debug` output.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use citadel_security::secrets::SecretString;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::headers::HeaderMap;
+use crate::request::Request;
+use crate::resource_manager::ResourceManager;
+
+/// Cookies kept per top-level site partition; the oldest go first
+pub const MAX_COOKIES_PER_PARTITION: usize = 180;
+
+/// Longest name plus value accepted, in bytes
+pub const MAX_COOKIE_BYTES: usize = 4096;
+
+/// Which cookie store a tab uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CookieStoreId {
+    /// Regular tabs
+    Shared,
+    /// Tabs of one container; persists like the shared store
+    Container(Uuid),
+    /// One ephemeral tab; never persisted
+    Ephemeral(Uuid),
+}
+
+impl CookieStoreId {
+    /// The store a request is made from: its ephemeral tab's, its
+    /// container's, or the shared one
+    pub fn for_request(request: &Request) -> Self {
+        if let Some(tab_id) = request.partition_key().and_then(|key| key.ephemeral_tab) {
+            return Self::Ephemeral(tab_id);
+        }
+        match request.container() {
+            Some(container_id) => Self::Container(container_id),
+            None => Self::Shared,
+        }
+    }
+
+    /// Whether the store's cookies may outlive the session
+    pub fn is_persistent(&self) -> bool {
+        !matches!(self, Self::Ephemeral(_))
+    }
+}
+
+/// The `SameSite` attribute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SameSite {
+    /// Only sent on same-site requests
+    Strict,
+    /// Only sent on same-site requests and top-level navigations
+    #[default]
+    Lax,
+    /// Sent cross-site too, where third-party cookies are allowed
+    None,
+}
+
+/// What to do with cookies on requests to other sites than the top-level
+/// page's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThirdPartyCookies {
+    /// Neither send nor store them
+    #[default]
+    Block,
+    /// Keep them within the top-level site's partition
+    Partitioned,
+}
+
+/// One cookie
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: SecretString,
+    /// Lowercase host or domain, without a leading dot
+    pub domain: String,
+    /// Only sent to `domain` itself, not its subdomains
+    pub host_only: bool,
+    pub path: String,
+    /// Only sent over HTTPS
+    pub secure: bool,
+    /// Hidden from page scripts
+    pub http_only: bool,
+    pub same_site: SameSite,
+    /// Expiry in seconds since the Unix epoch; `None` for session cookies
+    pub expires: Option<u64>,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` value received from `url`. `None` for cookies
+    /// the request may not set.
+    pub fn parse(set_cookie: &str, url: &Url, now: SystemTime) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let https = url.scheme() == "https";
+        let mut parts = set_cookie.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty()
+            || name.len() + value.len() > MAX_COOKIE_BYTES
+            || name.chars().chain(value.chars()).any(char::is_control)
+        {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: SecretString::new(value.trim_matches('"').to_string()),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            secure: false,
+            http_only: false,
+            same_site: SameSite::default(),
+            expires: None,
+        };
+        let now = unix_secs(now);
+        let mut max_age = None;
+        let mut expires = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    // The domain must cover the host, and be no wider than
+                    // the host's registrable domain: a public suffix is only
+                    // accepted as the host itself, and then stays host-only.
+                    // IP addresses have no subdomains to widen to.
+                    let is_ip = host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok();
+                    if !domain_matches(&host, &domain) || (is_ip && domain != host) {
+                        return None;
+                    }
+                    if ResourceManager::is_public_suffix(&domain) {
+                        if domain != host {
+                            return None;
+                        }
+                        continue;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => SameSite::Strict,
+                        "none" => SameSite::None,
+                        _ => SameSite::Lax,
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = value.parse::<i64>() {
+                        max_age = Some(if seconds <= 0 {
+                            0
+                        } else {
+                            now.saturating_add(seconds as u64)
+                        });
+                    }
+                }
+                "expires" => expires = parse_cookie_date(value),
+                _ => {}
+            }
+        }
+        cookie.expires = max_age.or(expires);
+
+        if cookie.secure && !https {
+            return None;
+        }
+        if cookie.same_site == SameSite::None && !cookie.secure {
+            return None;
+        }
+        if cookie.name.starts_with("__Secure-") && !cookie.secure {
+            return None;
+        }
+        if cookie.name.starts_with("__Host-")
+            && !(cookie.secure && cookie.host_only && cookie.path == "/")
+        {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    /// Whether the cookie has expired at `now`
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= unix_secs(now))
+    }
+
+    /// Whether the cookie goes with a request to `url`
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+
+    /// Whether `other` replaces this cookie
+    fn same_key(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// A cookie of a persistent store, as saved to disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedCookie {
+    pub store: CookieStoreId,
+    /// Site of the top-level page the cookie was set under
+    pub top_level_site: String,
+    pub cookie: Cookie,
+}
+
+/// Every store's cookies, partitioned by top-level site
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    partitions: Arc<RwLock<HashMap<(CookieStoreId, String), Vec<Cookie>>>>,
+    third_party: ThirdPartyCookies,
+}
+
+impl CookieJar {
+    /// An empty jar refusing third-party cookies
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat third-party cookies per `policy`
+    pub fn with_third_party(mut self, policy: ThirdPartyCookies) -> Self {
+        self.third_party = policy;
+        self
+    }
+
+    /// Store the `Set-Cookie` values of a response from `url`, loaded under
+    /// the top-level page `top_level`. Returns how many were stored.
+    pub fn set_cookies<'a>(
+        &self,
+        store: CookieStoreId,
+        top_level: &Url,
+        url: &Url,
+        set_cookies: impl IntoIterator<Item = &'a str>,
+    ) -> usize {
+        let Some(partition) = self.partition(store, top_level, url) else {
+            return 0;
+        };
+        let now = SystemTime::now();
+        let Ok(mut partitions) = self.partitions.write() else {
+            return 0;
+        };
+        let cookies = partitions.entry(partition).or_default();
+        let mut stored = 0;
+        for header in set_cookies {
+            let Some(cookie) = Cookie::parse(header, url, now) else {
+                log::debug!("🍪 Refused a cookie from {}", url);
+                continue;
+            };
+            cookies.retain(|existing| !existing.same_key(&cookie));
+            // A cookie set already expired is how sites delete one
+            if cookie.is_expired(now) {
+                continue;
+            }
+            cookies.push(cookie);
+            stored += 1;
+        }
+        cookies.retain(|cookie| !cookie.is_expired(now));
+        if cookies.len() > MAX_COOKIES_PER_PARTITION {
+            let excess = cookies.len() - MAX_COOKIES_PER_PARTITION;
+            cookies.drain(..excess);
+        }
+        stored
+    }
+
+    /// The `Cookie` header for a request to `url` under the top-level page
+    /// `top_level`; `None` when no cookie goes with it
+    pub fn cookie_header(
+        &self,
+        store: CookieStoreId,
+        top_level: &Url,
+        url: &Url,
+    ) -> Option<String> {
+        let partition = self.partition(store, top_level, url)?;
+        let cross_site = site_of(top_level) != site_of(url);
+        let now = SystemTime::now();
+        let partitions = self.partitions.read().ok()?;
+        let mut cookies: Vec<&Cookie> = partitions
+            .get(&partition)?
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .filter(|cookie| !cross_site || cookie.same_site == SameSite::None)
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        // Longer paths first; otherwise in the order they were set
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        Some(
+            cookies
+                .iter()
+                .map(|cookie| format!("{}={}", cookie.name, cookie.value.expose()))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// Add the `Cookie` header to a request made under the top-level page
+    /// `top_level`
+    pub fn attach(&self, request: &mut Request, top_level: &Url) {
+        let store = CookieStoreId::for_request(request);
+        if let Some(header) = self.cookie_header(store, top_level, request.url()) {
+            request.set_header("Cookie", &header);
+        }
+    }
+
+    /// Store the cookies of the response to `request`, served from `url`
+    /// (the request's URL unless it was redirected)
+    pub fn store_response(
+        &self,
+        request: &Request,
+        top_level: &Url,
+        url: &Url,
+        headers: &HeaderMap,
+    ) -> usize {
+        self.set_cookies(
+            CookieStoreId::for_request(request),
+            top_level,
+            url,
+            headers.get_all("set-cookie"),
+        )
+    }
+
+    /// Cookies of a store that would go with a request to `origin`, across
+    /// the top-level sites they were set under
+    pub fn cookies_for_origin(&self, store: CookieStoreId, origin: &Url) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let Ok(partitions) = self.partitions.read() else {
+            return Vec::new();
+        };
+        partitions
+            .iter()
+            .filter(|((id, _), _)| *id == store)
+            .flat_map(|(_, cookies)| cookies)
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(origin))
+            .cloned()
+            .collect()
+    }
+
+    /// Delete a store's cookies that would go with a request to `origin`;
+    /// returns how many were deleted
+    pub fn delete_for_origin(&self, store: CookieStoreId, origin: &Url) -> usize {
+        self.delete_where(store, |cookie| cookie.matches(origin))
+    }
+
+    /// Delete the cookie `name` that would go with a request to `origin`
+    pub fn delete(&self, store: CookieStoreId, origin: &Url, name: &str) -> usize {
+        self.delete_where(store, |cookie| {
+            cookie.name == name && cookie.matches(origin)
+        })
+    }
+
+    /// Delete a store's cookies of the site of `url`: its own, and those
+    /// other sites set while it was the top-level page. Returns how many
+    /// were deleted.
+    pub fn delete_for_site(&self, store: CookieStoreId, url: &Url) -> usize {
+        let Some(site) = site_of(url) else {
+            return 0;
+        };
+        let Ok(mut partitions) = self.partitions.write() else {
+            return 0;
+        };
+        let mut deleted = 0;
+        partitions.retain(|(id, top_level_site), cookies| {
+            if *id != store {
+                return true;
+            }
+            if *top_level_site == site {
+                deleted += cookies.len();
+                return false;
+            }
+            let before = cookies.len();
+            cookies.retain(|cookie| !domain_matches(&cookie.domain, &site));
+            deleted += before - cookies.len();
+            !cookies.is_empty()
+        });
+        deleted
+    }
+
+    /// Drop a store, as when its ephemeral tab closes
+    pub fn clear_store(&self, store: CookieStoreId) {
+        if let Ok(mut partitions) = self.partitions.write() {
+            partitions.retain(|(id, _), _| *id != store);
+        }
+    }
+
+    /// Drop every cookie
+    pub fn clear(&self) {
+        if let Ok(mut partitions) = self.partitions.write() {
+            partitions.clear();
+        }
+    }
+
+    /// Unexpired cookies with an expiry in persistent stores, for saving.
+    /// Session cookies and ephemeral stores are left out.
+    pub fn persistent_cookies(&self) -> Vec<SavedCookie> {
+        let now = SystemTime::now();
+        let Ok(partitions) = self.partitions.read() else {
+            return Vec::new();
+        };
+        partitions
+            .iter()
+            .filter(|((store, _), _)| store.is_persistent())
+            .flat_map(|((store, site), cookies)| {
+                cookies
+                    .iter()
+                    .filter(|cookie| cookie.expires.is_some() && !cookie.is_expired(now))
+                    .map(|cookie| SavedCookie {
+                        store: *store,
+                        top_level_site: site.clone(),
+                        cookie: cookie.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Put back saved cookies; expired and ephemeral ones are skipped
+    pub fn restore(&self, saved: impl IntoIterator<Item = SavedCookie>) {
+        let now = SystemTime::now();
+        let Ok(mut partitions) = self.partitions.write() else {
+            return;
+        };
+        for saved in saved {
+            if !saved.store.is_persistent() || saved.cookie.is_expired(now) {
+                continue;
+            }
+            let cookies = partitions
+                .entry((saved.store, saved.top_level_site))
+                .or_default();
+            cookies.retain(|existing| !existing.same_key(&saved.cookie));
+            cookies.push(saved.cookie);
+        }
+    }
+
+    fn delete_where(&self, store: CookieStoreId, mut delete: impl FnMut(&Cookie) -> bool) -> usize {
+        let Ok(mut partitions) = self.partitions.write() else {
+            return 0;
+        };
+        let mut deleted = 0;
+        for ((id, _), cookies) in partitions.iter_mut() {
+            if *id != store {
+                continue;
+            }
+            let before = cookies.len();
+            cookies.retain(|cookie| !delete(cookie));
+            deleted += before - cookies.len();
+        }
+        partitions.retain(|_, cookies| !cookies.is_empty());
+        deleted
+    }
+
+    /// The partition a request to `url` under `top_level` uses; `None` when
+    /// it may not use cookies at all
+    fn partition(
+        &self,
+        store: CookieStoreId,
+        top_level: &Url,
+        url: &Url,
+    ) -> Option<(CookieStoreId, String)> {
+        let top_level_site = site_of(top_level)?;
+        if site_of(url)? != top_level_site && self.third_party == ThirdPartyCookies::Block {
+            return None;
+        }
+        Some((store, top_level_site))
+    }
+}
+
+/// Registrable domain of a URL's host, by the public suffix list; an IP
+/// address is its own site
+fn site_of(url: &Url) -> Option<String> {
+    Some(ResourceManager::extract_domain(
+        &url.host_str()?.to_ascii_lowercase(),
+    ))
+}
+
+/// Whether `host` is `domain` or a subdomain of it
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// Whether `path` is within the cookie path `cookie_path`
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The path a cookie without a `Path` attribute gets: the request path up
+/// to its last `/`
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(end) => url.path()[..end].to_string(),
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Seconds since the Unix epoch of an `Expires` date, per the lenient
+/// algorithm of RFC 6265 section 5.1.1
+fn parse_cookie_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in date
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != ':')
+        .filter(|token| !token.is_empty())
+    {
+        if time.is_none() {
+            let fields: Vec<_> = token.split(':').collect();
+            if let [h, m, s] = fields[..] {
+                if let (Ok(h), Ok(m), Ok(s)) =
+                    (h.parse::<u64>(), m.parse::<u64>(), s.parse::<u64>())
+                {
+                    time = Some((h, m, s));
+                    continue;
+                }
+            }
+        }
+        if day.is_none() && (1..=2).contains(&token.len()) {
+            if let Ok(d) = token.parse::<u64>() {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() && token.len() >= 3 {
+            let prefix = token[..3].to_ascii_lowercase();
+            if let Some(index) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(index as u64 + 1);
+                continue;
+            }
+        }
+        if year.is_none() && (2..=4).contains(&token.len()) {
+            if let Ok(y) = token.parse::<u64>() {
+                year = Some(match y {
+                    0..=69 => y + 2000,
+                    70..=99 => y + 1900,
+                    _ => y,
+                });
+            }
+        }
+    }
+    let ((hour, minute, second), day, month, year) = (time?, day?, month?, year?);
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year as i64, month as i64, day as i64);
+    let seconds = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64;
+    u64::try_from(seconds).ok()
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_set_cookie_parsing() {
+        let now = SystemTime::now();
+        let page = url("https://www.example.com/account/login");
+
+        let cookie = Cookie::parse("sid=abc; Path=/; Secure; HttpOnly", &page, now).unwrap();
+        assert_eq!(cookie.domain, "www.example.com");
+        assert!(cookie.host_only && cookie.secure && cookie.http_only);
+        assert!(cookie.matches(&url("https://www.example.com/other")));
+        assert!(!cookie.matches(&url("http://www.example.com/")));
+        assert!(!cookie.matches(&url("https://api.example.com/")));
+        assert!(!format!("{:?}", cookie).contains("abc"));
+
+        let wide = Cookie::parse("a=1; Domain=.example.com", &page, now).unwrap();
+        assert!(wide.matches(&url("https://api.example.com/account")));
+        assert_eq!(wide.path, "/account");
+        assert!(!wide.matches(&url("https://api.example.com/accounts")));
+
+        assert!(Cookie::parse("a=1; Domain=com", &page, now).is_none());
+        assert!(Cookie::parse("a=1; Domain=other.test", &page, now).is_none());
+        let uk = url("https://shop.example.co.uk/");
+        assert!(Cookie::parse("a=1; Domain=co.uk", &uk, now).is_none());
+        assert!(Cookie::parse("a=1; Domain=example.co.uk", &uk, now).is_some());
+        let pages = url("https://alice.github.io/");
+        assert!(Cookie::parse("a=1; Domain=github.io", &pages, now).is_none());
+        let ip = url("https://10.0.0.1/");
+        assert!(Cookie::parse("a=1; Domain=0.0.1", &ip, now).is_none());
+        assert!(Cookie::parse("a=1; Domain=10.0.0.1", &ip, now).is_some());
+        assert!(Cookie::parse("a=1; Secure", &url("http://example.com/"), now).is_none());
+        assert!(Cookie::parse("a=1; SameSite=None", &page, now).is_none());
+        assert!(Cookie::parse("__Host-a=1; Secure; Path=/docs", &page, now).is_none());
+        assert!(Cookie::parse("__Host-a=1; Secure; Path=/", &page, now).is_some());
+
+        let dated = Cookie::parse("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", &page, now);
+        assert_eq!(dated.unwrap().expires, Some(1_445_412_480));
+        let max_age = Cookie::parse(
+            "a=1; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            &page,
+            now,
+        )
+        .unwrap();
+        assert!(!max_age.is_expired(now));
+    }
+
+    #[test]
+    fn test_first_party_isolation_and_stores() {
+        let jar = CookieJar::new();
+        let site = url("https://example.com/");
+        let tracker = url("https://tracker.test/pixel");
+        let store = CookieStoreId::Shared;
+
+        assert_eq!(
+            jar.set_cookies(store, &site, &site, ["sid=1", "theme=dark"]),
+            2
+        );
+        assert_eq!(
+            jar.cookie_header(store, &site, &url("https://example.com/page"))
+                .as_deref(),
+            Some("sid=1; theme=dark")
+        );
+
+        // Third parties neither store nor receive cookies
+        assert_eq!(jar.set_cookies(store, &site, &tracker, ["id=42"]), 0);
+        assert!(jar.cookie_header(store, &site, &tracker).is_none());
+        // Nor does another top-level site see this one's cookies
+        let other = url("https://other.test/");
+        assert!(jar.cookie_header(store, &other, &site).is_none());
+        // Sites under a public suffix, and IP hosts, are each their own site
+        let alice = url("https://alice.github.io/");
+        let bob = url("https://bob.github.io/");
+        assert_eq!(jar.set_cookies(store, &alice, &bob, ["id=7"]), 0);
+        let ip = url("http://192.168.1.1/");
+        assert_eq!(jar.set_cookies(store, &ip, &ip, ["ip=1"]), 1);
+        assert_eq!(
+            jar.set_cookies(store, &ip, &url("http://192.168.1.2/"), ["ip=2"]),
+            0
+        );
+
+        // Containers and ephemeral tabs have stores of their own
+        let container = CookieStoreId::Container(Uuid::new_v4());
+        let ephemeral = CookieStoreId::Ephemeral(Uuid::new_v4());
+        assert!(jar.cookie_header(container, &site, &site).is_none());
+        jar.set_cookies(ephemeral, &site, &site, ["tmp=1"]);
+        jar.set_cookies(container, &site, &site, ["c=1; Max-Age=3600"]);
+        assert_eq!(jar.cookies_for_origin(ephemeral, &site).len(), 1);
+        jar.clear_store(ephemeral);
+        assert!(jar.cookies_for_origin(ephemeral, &site).is_empty());
+
+        // Only dated cookies of persistent stores are saved
+        let saved = jar.persistent_cookies();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].store, container);
+        let restored = CookieJar::new();
+        restored.restore(saved);
+        assert_eq!(restored.cookies_for_origin(container, &site).len(), 1);
+
+        // Deleting, directly or by setting an expired cookie
+        assert_eq!(jar.delete(store, &site, "theme"), 1);
+        jar.set_cookies(store, &site, &site, ["sid=; Max-Age=0"]);
+        assert!(jar.cookie_header(store, &site, &site).is_none());
+        jar.set_cookies(store, &site, &site, ["a=1", "b=2"]);
+        assert_eq!(jar.delete_for_origin(store, &site), 2);
+
+        // Partitioned third-party cookies: only SameSite=None goes cross-site
+        let jar = CookieJar::new().with_third_party(ThirdPartyCookies::Partitioned);
+        let widget = url("https://widget.test/");
+        jar.set_cookies(
+            store,
+            &site,
+            &widget,
+            ["lax=1", "none=1; SameSite=None; Secure"],
+        );
+        assert_eq!(
+            jar.cookie_header(store, &site, &widget).as_deref(),
+            Some("none=1")
+        );
+        assert!(jar.cookie_header(store, &other, &widget).is_none());
+
+        // Clearing a site drops its cookies under every top-level site
+        jar.set_cookies(store, &widget, &widget, ["own=1"]);
+        jar.set_cookies(store, &site, &site, ["mine=1"]);
+        assert!(jar.delete_for_site(store, &widget) >= 2);
+        assert!(jar.cookie_header(store, &site, &widget).is_none());
+        assert!(jar.cookie_header(store, &widget, &widget).is_none());
+        assert_eq!(
+            jar.cookie_header(store, &site, &site).as_deref(),
+            Some("mine=1")
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Cosmetic filtering (element hiding)
+//!
+//! Network blocking stops tracker requests, but leaves behind the empty ad
+//! slots and cookie banners that pages lay out for them. Element-hiding rules
+//! from filter lists hide those too:
+//!
+//! ```text
+//! ##.ad-banner                 hide everywhere
+//! example.com##.promo          hide on example.com and its subdomains
+//! a.com,~shop.a.com##.sidebar  hide on a.com except shop.a.com
+//! example.com#@#.ad-banner     do not hide .ad-banner on example.com
+//! ```
+//!
+//! Rules are compiled per site into a stylesheet of `display: none` rules,
+//! which the browser injects as user CSS so it beats the page's own styles.
+//! Network rules and procedural or scriptlet extensions (`#?#`, `#$#`,
+//! `##+js`) are skipped.
+
+use std::collections::HashSet;
+
+/// Element-hiding rules compiled into the binary: generic ad placeholders and
+/// cookie consent banners.
+const BUILTIN_RULES: &str = "\
+! Ad placeholders
+##.ad-banner
+##.ad-container
+##.ad-slot
+##.adsbygoogle
+##.advertisement
+##.sponsored-content
+! Cookie consent banners
+##.cookie-banner
+##.cookie-consent
+##.cookie-notice
+##.gdpr-banner
+###cookie-banner
+###cookie-consent
+###onetrust-banner-sdk
+";
+
+/// Longest selector accepted from a filter list
+const MAX_SELECTOR_LEN: usize = 512;
+
+/// Undoes the scroll lock pages put on the document while an overlay is up
+pub const SCROLL_RESTORE_CSS: &str =
+    "html, body { overflow: auto !important; position: static !important; }\n";
+
+/// One parsed element-hiding rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CosmeticRule {
+    /// Sites the rule applies to; empty means every site
+    pub domains: Vec<String>,
+    /// Sites excluded with `~domain`
+    pub excluded: Vec<String>,
+    /// CSS selector of the elements to hide
+    pub selector: String,
+    /// `#@#` exception un-hiding the selector on `domains`
+    pub exception: bool,
+}
+
+impl CosmeticRule {
+    /// Parse one filter list line. Returns `None` for comments, network rules,
+    /// unsupported extensions and selectors that could break out of a CSS rule.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+            return None;
+        }
+        let (domains, selector, exception) = if let Some((d, s)) = line.split_once("#@#") {
+            (d, s, true)
+        } else if let Some((d, s)) = line.split_once("##") {
+            (d, s, false)
+        } else {
+            return None;
+        };
+
+        let selector = selector.trim();
+        if selector.is_empty()
+            || selector.len() > MAX_SELECTOR_LEN
+            || selector.starts_with('+')
+            || domains.ends_with(['?', '$', '@'])
+            || selector.contains(['{', '}'])
+            || selector.contains("/*")
+        {
+            return None;
+        }
+
+        let mut rule = Self {
+            domains: Vec::new(),
+            excluded: Vec::new(),
+            selector: selector.to_string(),
+            exception,
+        };
+        for domain in domains.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match domain.strip_prefix('~') {
+                Some(excluded) => rule.excluded.push(excluded.to_ascii_lowercase()),
+                None => rule.domains.push(domain.to_ascii_lowercase()),
+            }
+        }
+        Some(rule)
+    }
+
+    /// Whether the rule applies on a host
+    pub fn applies_to(&self, host: &str) -> bool {
+        let included = self.domains.is_empty() || self.domains.iter().any(|d| on_domain(host, d));
+        included && !self.excluded.iter().any(|d| on_domain(host, d))
+    }
+}
+
+/// A host is on a domain if it is the domain or one of its subdomains
+fn on_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Element-hiding rules from any number of filter lists
+#[derive(Debug, Clone, Default)]
+pub struct CosmeticFilter {
+    rules: Vec<CosmeticRule>,
+}
+
+impl CosmeticFilter {
+    /// An empty filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in ad placeholder and cookie banner rules
+    pub fn builtin() -> Self {
+        let mut filter = Self::new();
+        filter.add_list(BUILTIN_RULES);
+        filter
+    }
+
+    /// Parse a filter list and add its element-hiding rules; returns how many
+    /// were added
+    pub fn add_list(&mut self, list: &str) -> usize {
+        let before = self.rules.len();
+        self.rules
+            .extend(list.lines().filter_map(CosmeticRule::parse));
+        self.rules.len() - before
+    }
+
+    /// Add already parsed rules, such as those of a compiled filter list
+    pub fn add_rules(&mut self, rules: impl IntoIterator<Item = CosmeticRule>) {
+        self.rules.extend(rules);
+    }
+
+    /// Number of parsed rules, exceptions included
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether no rules have been added
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Selectors to hide on a host, in list order, without duplicates or
+    /// selectors excepted for the host
+    pub fn selectors_for(&self, host: &str) -> Vec<&str> {
+        let host = host.to_ascii_lowercase();
+        let excepted: HashSet<&str> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.exception && rule.applies_to(&host))
+            .map(|rule| rule.selector.as_str())
+            .collect();
+
+        let mut seen = HashSet::new();
+        self.rules
+            .iter()
+            .filter(|rule| !rule.exception && rule.applies_to(&host))
+            .map(|rule| rule.selector.as_str())
+            .filter(|selector| !excepted.contains(selector) && seen.insert(*selector))
+            .collect()
+    }
+
+    /// Compile the rules for a host into a hiding stylesheet; empty when
+    /// nothing applies
+    pub fn stylesheet_for(&self, host: &str) -> String {
+        hiding_stylesheet(self.selectors_for(host))
+    }
+}
+
+/// A stylesheet of `display: none` rules for the given selectors. Selectors
+/// are vetted like filter list rules, so ones that could break out of their
+/// CSS rule are dropped.
+pub fn hiding_stylesheet<'a>(selectors: impl IntoIterator<Item = &'a str>) -> String {
+    selectors
+        .into_iter()
+        .filter(|selector| CosmeticRule::parse(&format!("##{}", selector)).is_some())
+        .map(|selector| format!("{} {{ display: none !important; }}\n", selector.trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_element_hiding_rules() {
+        let rule = CosmeticRule::parse("a.com,~shop.a.com##.sidebar").unwrap();
+        assert_eq!(rule.domains, vec!["a.com"]);
+        assert_eq!(rule.excluded, vec!["shop.a.com"]);
+        assert!(rule.applies_to("www.a.com"));
+        assert!(!rule.applies_to("shop.a.com"));
+        assert!(!rule.applies_to("aa.com"));
+
+        assert!(CosmeticRule::parse("example.com#@#.ad").unwrap().exception);
+        assert!(CosmeticRule::parse("! comment").is_none());
+        assert!(CosmeticRule::parse("||tracker.test^").is_none());
+        assert!(CosmeticRule::parse("example.com#?#div:has(> .ad)").is_none());
+        assert!(CosmeticRule::parse("example.com##+js(noeval)").is_none());
+        assert!(CosmeticRule::parse("##.x} body { display: none").is_none());
+    }
+
+    #[test]
+    fn test_per_site_stylesheets_honour_exceptions() {
+        let mut filter = CosmeticFilter::builtin();
+        let added = filter.add_list(
+            "example.com##.promo\n\
+             example.com#@#.cookie-banner\n\
+             ##.promo\n\
+             ||ads.test^\n",
+        );
+        assert_eq!(added, 3);
+
+        let example = filter.selectors_for("news.example.com");
+        assert!(example.contains(&".promo"));
+        assert!(example.contains(&".ad-banner"));
+        assert!(!example.contains(&".cookie-banner"));
+        assert_eq!(example.iter().filter(|s| **s == ".promo").count(), 1);
+
+        let other = filter.stylesheet_for("other.test");
+        assert!(other.contains(".cookie-banner { display: none !important; }"));
+        assert!(CosmeticFilter::new()
+            .stylesheet_for("other.test")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hiding_stylesheet_drops_unsafe_selectors() {
+        let css = hiding_stylesheet(["#gate", "div.x} body { color: red", " .modal "]);
+        assert_eq!(
+            css,
+            "#gate { display: none !important; }\n.modal { display: none !important; }\n"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Content-Security-Policy enforcement
+//!
+//! The `Content-Security-Policy` header of a tab's page is kept per tab in
+//! [`CspPolicies`], shared by whoever loads the page and whoever fetches
+//! its subresources. Each subresource request is checked against the
+//! policy before it is sent: a refused URL is never requested, and the
+//! refusal comes back as a [`CspReport`]. Directives, sources and their
+//! matching are those of report-only policies; the difference is only
+//! that violations are blocked. A page sending several policies, in headers
+//! or in `<meta http-equiv>` tags of its head, must satisfy all of them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use url::Url;
+use uuid::Uuid;
+
+use crate::csp_report::{directive_for, BlockedResource, CspReport, ReportOnlyPolicy};
+use crate::headers::HeaderMap;
+use crate::resource::ResourceType;
+
+/// Header carrying the enforced policy
+pub const CSP_HEADER: &str = "content-security-policy";
+/// How far into a document `<meta>` policies are looked for
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// The enforced policies of one page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnforcedPolicy {
+    policies: Vec<ReportOnlyPolicy>,
+}
+
+impl EnforcedPolicy {
+    /// Policies from the page's response headers, if it sent any
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let policies: Vec<ReportOnlyPolicy> = headers
+            .get_all(CSP_HEADER)
+            .filter_map(ReportOnlyPolicy::parse)
+            .collect();
+        (!policies.is_empty()).then_some(Self { policies })
+    }
+
+    /// Policies from the page's response headers and from the
+    /// `<meta http-equiv="Content-Security-Policy">` tags of its `<head>`
+    pub fn from_response(headers: &HeaderMap, body: &[u8]) -> Option<Self> {
+        let head = String::from_utf8_lossy(&body[..body.len().min(MAX_HEAD_BYTES)]);
+        let policies: Vec<ReportOnlyPolicy> = headers
+            .get_all(CSP_HEADER)
+            .map(str::to_string)
+            .chain(meta_policies(&head))
+            .filter_map(|policy| ReportOnlyPolicy::parse(&policy))
+            .collect();
+        (!policies.is_empty()).then_some(Self { policies })
+    }
+
+    /// Parse one header value; `None` when it has no directives
+    pub fn parse(header: &str) -> Option<Self> {
+        ReportOnlyPolicy::parse(header).map(|policy| Self {
+            policies: vec![policy],
+        })
+    }
+
+    /// The violation of loading `url` as `resource_type` into a page served
+    /// from `document`, or `None` if every policy allows it
+    pub fn check(
+        &self,
+        url: &Url,
+        resource_type: ResourceType,
+        document: &Url,
+    ) -> Option<CspReport> {
+        let directive = directive_for(resource_type)?;
+        self.policies.iter().find_map(|policy| {
+            if policy.allows_url(directive, url, document) {
+                return None;
+            }
+            let (violated, _) = policy.sources_for(directive)?;
+            Some(CspReport {
+                effective_directive: directive.to_string(),
+                violated_directive: violated.to_string(),
+                blocked: BlockedResource::Url(url.clone()),
+            })
+        })
+    }
+}
+
+/// The `content` of each CSP `<meta>` tag before the document's `<body>`.
+/// Tags are found by a scan, not a parse: a policy hidden in a comment or a
+/// script counts too, which can only make the page stricter.
+fn meta_policies(html: &str) -> Vec<String> {
+    // ASCII lowercasing keeps byte offsets, so `html` can be sliced with them
+    let lower = html.to_ascii_lowercase();
+    let end = ["<body", "</head"]
+        .iter()
+        .filter_map(|tag| lower.find(tag))
+        .min()
+        .unwrap_or(lower.len());
+    let mut policies = Vec::new();
+    let mut at = 0;
+    while let Some(start) = lower[at..end].find("<meta").map(|i| at + i) {
+        let close = lower[start..].find('>').map_or(lower.len(), |i| start + i);
+        let attributes = tag_attributes(&html[start + "<meta".len()..close]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        if attribute("http-equiv").is_some_and(|v| v.trim().eq_ignore_ascii_case(CSP_HEADER)) {
+            if let Some(content) = attribute("content") {
+                policies.push(content.to_string());
+            }
+        }
+        at = close;
+        if at >= end {
+            break;
+        }
+    }
+    policies
+}
+
+/// Name and value of each attribute in the inside of a tag
+fn tag_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (found, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    after.split_at(end)
+                }
+            };
+            value = found;
+            rest = remaining;
+        }
+        if !name.is_empty() {
+            attributes.push((name.to_string(), value.to_string()));
+        }
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+    }
+    attributes
+}
+
+/// The enforced policy of each tab's page, shared between loaders
+#[derive(Debug, Clone, Default)]
+pub struct CspPolicies {
+    tabs: Arc<RwLock<HashMap<Uuid, (Url, EnforcedPolicy)>>>,
+}
+
+impl CspPolicies {
+    /// No policies: every tab loads what its page asks for
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a tab's policy with that of its new page at `document`;
+    /// `None` when the page sent no policy
+    pub fn set(&self, tab_id: Uuid, document: &Url, policy: Option<EnforcedPolicy>) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            match policy {
+                Some(policy) => tabs.insert(tab_id, (document.clone(), policy)),
+                None => tabs.remove(&tab_id),
+            };
+        }
+    }
+
+    /// The violation of a tab loading `url` as `resource_type`, or `None`
+    /// if its page's policy allows it (or it has none)
+    pub fn check(&self, tab_id: Uuid, url: &Url, resource_type: ResourceType) -> Option<CspReport> {
+        let tabs = self.tabs.read().ok()?;
+        let (document, policy) = tabs.get(&tab_id)?;
+        policy.check(url, resource_type, document)
+    }
+
+    /// Forget a closed tab
+    pub fn remove(&self, tab_id: Uuid) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.remove(&tab_id);
+        }
+    }
+
+    /// Forget every tab
+    pub fn clear(&self) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_policy_of_a_tab_is_enforced() {
+        let mut headers = HeaderMap::new();
+        headers.append(CSP_HEADER, "default-src 'self'; img-src *");
+        headers.append(CSP_HEADER, "img-src https://img.example");
+        let policy = EnforcedPolicy::from_headers(&headers).unwrap();
+
+        let document = Url::parse("https://news.example/article").unwrap();
+        let url = |s: &str| Url::parse(s).unwrap();
+        let policies = CspPolicies::new();
+        let (tab, other) = (Uuid::new_v4(), Uuid::new_v4());
+        policies.set(tab, &document, Some(policy));
+
+        let script = url("https://cdn.example/app.js");
+        let report = policies.check(tab, &script, ResourceType::Script).unwrap();
+        assert_eq!(report.effective_directive, "script-src");
+        assert_eq!(report.violated_directive, "default-src");
+        assert_eq!(report.blocked, BlockedResource::Url(script.clone()));
+        assert!(policies
+            .check(other, &script, ResourceType::Script)
+            .is_none());
+
+        let own = url("https://news.example/app.js");
+        assert!(policies.check(tab, &own, ResourceType::Script).is_none());
+        // Allowed by the first policy, refused by the second
+        let image = url("https://cdn.example/photo.png");
+        assert!(policies.check(tab, &image, ResourceType::Image).is_some());
+        let image = url("https://img.example/photo.png");
+        assert!(policies.check(tab, &image, ResourceType::Image).is_none());
+        // Types no directive governs load as before
+        assert!(policies.check(tab, &script, ResourceType::Other).is_none());
+
+        policies.set(tab, &document, None);
+        assert!(policies.check(tab, &script, ResourceType::Script).is_none());
+    }
+
+    #[test]
+    fn test_meta_policies_in_the_head_are_enforced() {
+        let mut headers = HeaderMap::new();
+        headers.append(CSP_HEADER, "img-src *");
+        let html = br#"<!doctype html><html><head>
+            <meta charset="utf-8">
+            <META HTTP-EQUIV='Content-Security-Policy' CONTENT="script-src 'self'">
+            <meta name=description content="default-src 'none'">
+            </head><body>
+            <meta http-equiv="Content-Security-Policy" content="img-src 'none'">
+            </body></html>"#;
+        let policy = EnforcedPolicy::from_response(&headers, html).unwrap();
+
+        let document = Url::parse("https://news.example/").unwrap();
+        let url = |s: &str| Url::parse(s).unwrap();
+        let script = url("https://cdn.example/app.js");
+        assert!(policy
+            .check(&script, ResourceType::Script, &document)
+            .is_some());
+        // The header's policy still applies; the tag in the body does not
+        let image = url("https://cdn.example/photo.png");
+        assert!(policy
+            .check(&image, ResourceType::Image, &document)
+            .is_none());
+        assert!(EnforcedPolicy::from_response(&HeaderMap::new(), b"<p>no policy</p>").is_none());
+    }
+}
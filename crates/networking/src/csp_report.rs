@@ -0,0 +1,484 @@
+//! Content-Security-Policy-Report-Only evaluation
+//!
+//! A report-only policy never blocks anything: the page loads as usual and
+//! every resource or inline block the policy would have refused becomes a
+//! [`CspReport`]. Reports stay in the browser for the `citadel://csp-reports`
+//! page; `report-uri` and `report-to` are parsed only so they can be shown as
+//! ignored, and nothing is ever sent to them.
+
+use base64::{engine::general_purpose, Engine as _};
+use regex::Regex;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use url::Url;
+
+use crate::headers::HeaderMap;
+use crate::resource::ResourceType;
+use crate::resource_discovery::{ResourceContext, ResourceDiscovery};
+
+/// Header carrying the report-only policy
+pub const REPORT_ONLY_HEADER: &str = "content-security-policy-report-only";
+
+/// Most reports kept for one page
+const MAX_REPORTS: usize = 200;
+
+/// What a report is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockedResource {
+    /// A subresource URL
+    Url(Url),
+    /// An inline `<script>` block
+    InlineScript,
+    /// An inline `<style>` block or `style` attribute
+    InlineStyle,
+}
+
+impl std::fmt::Display for BlockedResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(url) => write!(f, "{}", url),
+            Self::InlineScript => write!(f, "inline script"),
+            Self::InlineStyle => write!(f, "inline style"),
+        }
+    }
+}
+
+/// A would-be violation of a report-only policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspReport {
+    /// Directive the resource fell under, e.g. `script-src`
+    pub effective_directive: String,
+    /// Directive whose source list was applied; differs from the effective
+    /// one when it fell back to `default-src`
+    pub violated_directive: String,
+    pub blocked: BlockedResource,
+}
+
+/// A parsed report-only policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportOnlyPolicy {
+    /// Directive names, lowercased, with their sources
+    directives: Vec<(String, Vec<String>)>,
+    /// Report endpoints named by the page, never contacted
+    pub ignored_endpoints: Vec<String>,
+}
+
+impl ReportOnlyPolicy {
+    /// Policy from the page's response headers, if it sent one
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers.get(REPORT_ONLY_HEADER).and_then(Self::parse)
+    }
+
+    /// Parse a header value; `None` when it has no directives
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut directives: Vec<(String, Vec<String>)> = Vec::new();
+        let mut ignored_endpoints = Vec::new();
+        for directive in header.split(';') {
+            let mut parts = directive.split_whitespace();
+            let Some(name) = parts.next().map(str::to_ascii_lowercase) else {
+                continue;
+            };
+            let sources: Vec<String> = parts.map(str::to_string).collect();
+            match name.as_str() {
+                "report-uri" | "report-to" => ignored_endpoints.extend(sources),
+                // The first occurrence of a directive wins
+                _ if directives.iter().any(|(n, _)| *n == name) => {}
+                _ => directives.push((name, sources)),
+            }
+        }
+        if directives.is_empty() {
+            return None;
+        }
+        Some(Self {
+            directives,
+            ignored_endpoints,
+        })
+    }
+
+    /// Source list applying to `directive`, falling back to `default-src`
+    pub(crate) fn sources_for(&self, directive: &str) -> Option<(&str, &[String])> {
+        [directive, "default-src"].into_iter().find_map(|name| {
+            self.directives
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(n, sources)| (n.as_str(), sources.as_slice()))
+        })
+    }
+
+    /// Would-be violations of a page served from `document` with `html`
+    pub fn evaluate(&self, html: &str, document: &Url) -> Vec<CspReport> {
+        let mut reports = Vec::new();
+        let mut report = |directive: &str, blocked: BlockedResource| {
+            if let Some((violated, _)) = self.sources_for(directive) {
+                if reports.len() < MAX_REPORTS {
+                    reports.push(CspReport {
+                        effective_directive: directive.to_string(),
+                        violated_directive: violated.to_string(),
+                        blocked,
+                    });
+                }
+            }
+        };
+
+        let context = ResourceContext::new(document.clone()).max_resources(Some(MAX_REPORTS));
+        let resources = ResourceDiscovery::new()
+            .and_then(|discovery| discovery.discover_all(html, &context))
+            .unwrap_or_default();
+        for resource in resources {
+            let Some(directive) = directive_for(resource.resource_type) else {
+                continue;
+            };
+            if !self.allows_url(directive, &resource.url, document) {
+                report(directive, BlockedResource::Url(resource.url));
+            }
+        }
+
+        for (nonce, body) in inline_blocks(html, "script") {
+            if !self.allows_inline("script-src", nonce.as_deref(), &body) {
+                report("script-src", BlockedResource::InlineScript);
+            }
+        }
+        for (nonce, body) in inline_blocks(html, "style") {
+            if !self.allows_inline("style-src", nonce.as_deref(), &body) {
+                report("style-src", BlockedResource::InlineStyle);
+            }
+        }
+        let style_attributes = style_attribute_count(html);
+        if style_attributes > 0 && !self.allows_inline("style-src", None, "") {
+            for _ in 0..style_attributes {
+                report("style-src", BlockedResource::InlineStyle);
+            }
+        }
+
+        reports
+    }
+
+    /// Whether `url` may load under `directive`
+    pub(crate) fn allows_url(&self, directive: &str, url: &Url, document: &Url) -> bool {
+        match self.sources_for(directive) {
+            None => true,
+            Some((_, sources)) => sources
+                .iter()
+                .any(|source| source_matches(source, url, document)),
+        }
+    }
+
+    /// Whether an inline block with `nonce` and `body` may run under
+    /// `directive`
+    fn allows_inline(&self, directive: &str, nonce: Option<&str>, body: &str) -> bool {
+        let Some((_, sources)) = self.sources_for(directive) else {
+            return true;
+        };
+        let mut unsafe_inline = false;
+        for source in sources {
+            let lower = source.to_ascii_lowercase();
+            let Some(keyword) = lower.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) else {
+                continue;
+            };
+            if keyword == "unsafe-inline" {
+                unsafe_inline = true;
+            } else if let Some(expected) = source[1..source.len() - 1].strip_prefix("nonce-") {
+                if nonce == Some(expected) {
+                    return true;
+                }
+            } else if hash_matches(&source[1..source.len() - 1], body) {
+                return true;
+            }
+        }
+        // A nonce or hash in the list disables 'unsafe-inline'
+        unsafe_inline
+            && !sources.iter().any(|source| {
+                let lower = source.to_ascii_lowercase();
+                ["'nonce-", "'sha256-", "'sha384-", "'sha512-"]
+                    .iter()
+                    .any(|prefix| lower.starts_with(prefix))
+            })
+    }
+}
+
+/// Directive governing a fetched resource type
+pub(crate) fn directive_for(resource_type: ResourceType) -> Option<&'static str> {
+    match resource_type {
+        ResourceType::Script => Some("script-src"),
+        ResourceType::Css => Some("style-src"),
+        ResourceType::Image => Some("img-src"),
+        ResourceType::Font => Some("font-src"),
+        ResourceType::Fetch | ResourceType::WebSocket => Some("connect-src"),
+        _ => None,
+    }
+}
+
+/// Whether one source expression matches `url`
+fn source_matches(source: &str, url: &Url, document: &Url) -> bool {
+    let source = source.to_ascii_lowercase();
+    match source.as_str() {
+        "'none'" => false,
+        "'self'" => {
+            url.origin() == document.origin()
+                || (document.scheme() == "http"
+                    && url.scheme() == "https"
+                    && url.host_str() == document.host_str())
+                // A page's own WebSocket endpoint is itself too
+                || (matches!(document.scheme(), "http" | "https")
+                    && url.scheme() == "wss"
+                    && url.host_str() == document.host_str()
+                    && url.port_or_known_default() == document.port_or_known_default())
+        }
+        "*" => !matches!(url.scheme(), "data" | "blob" | "filesystem"),
+        _ if source.starts_with('\'') => false,
+        _ => match source.strip_suffix(':') {
+            Some(scheme) if !scheme.contains('/') => scheme_part_matches(scheme, url.scheme()),
+            _ => host_source_matches(&source, url, document),
+        },
+    }
+}
+
+/// Whether a source's `scheme` allows a URL of scheme `actual`: the same
+/// one, or its secure counterpart, with `ws:` also covering HTTP(S) and
+/// `wss:` covering HTTPS
+fn scheme_part_matches(scheme: &str, actual: &str) -> bool {
+    scheme == actual
+        || matches!(
+            (scheme, actual),
+            ("http", "https") | ("ws", "wss" | "http" | "https") | ("wss", "https")
+        )
+}
+
+/// `[scheme://]host[:port][/path]`, where the host may start with `*.`
+fn host_source_matches(source: &str, url: &Url, document: &Url) -> bool {
+    let (scheme, rest) = match source.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, source),
+    };
+    match scheme {
+        Some(scheme) => {
+            if !scheme_part_matches(scheme, url.scheme()) {
+                return false;
+            }
+        }
+        // Without a scheme, the document's scheme applies, upgrades included
+        None => {
+            if !scheme_part_matches(document.scheme(), url.scheme()) {
+                return false;
+            }
+        }
+    }
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], Some(&rest[i..])),
+        None => (rest, None),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+
+    let Some(url_host) = url.host_str() else {
+        return false;
+    };
+    let host_ok = match host.strip_prefix("*.") {
+        Some(suffix) => {
+            url_host.len() > suffix.len() && url_host.ends_with(&format!(".{}", suffix))
+        }
+        None => url_host == host,
+    };
+    if !host_ok {
+        return false;
+    }
+
+    let port_ok = match port {
+        Some("*") => true,
+        Some(port) => port.parse::<u16>().ok() == url.port_or_known_default(),
+        None => url.port().is_none(),
+    };
+    if !port_ok {
+        return false;
+    }
+
+    match path {
+        None | Some("/") => true,
+        Some(path) if path.ends_with('/') => url.path().starts_with(path),
+        Some(path) => url.path() == path,
+    }
+}
+
+/// Whether a `'sha256-...'` style source (quotes removed) hashes `body`
+fn hash_matches(source: &str, body: &str) -> bool {
+    let Some((algorithm, expected)) = source.split_once('-') else {
+        return false;
+    };
+    let digest = match algorithm.to_ascii_lowercase().as_str() {
+        "sha256" => Sha256::digest(body.as_bytes()).to_vec(),
+        "sha384" => Sha384::digest(body.as_bytes()).to_vec(),
+        "sha512" => Sha512::digest(body.as_bytes()).to_vec(),
+        _ => return false,
+    };
+    general_purpose::STANDARD.encode(digest) == expected
+}
+
+/// Inline `<tag>` blocks without a `src`, with their nonce and body
+fn inline_blocks(html: &str, tag: &str) -> Vec<(Option<String>, String)> {
+    let Ok(block) = Regex::new(&format!(r"(?is)<{0}\b([^>]*)>(.*?)</{0}\s*>", tag)) else {
+        return Vec::new();
+    };
+    let Ok(nonce) = Regex::new(r#"(?i)\bnonce\s*=\s*["']?([^"'\s>]+)"#) else {
+        return Vec::new();
+    };
+    let Ok(src) = Regex::new(r"(?i)\bsrc\s*=") else {
+        return Vec::new();
+    };
+    block
+        .captures_iter(html)
+        .filter(|captures| !src.is_match(&captures[1]))
+        .filter(|captures| !captures[2].trim().is_empty())
+        .map(|captures| {
+            let nonce = nonce
+                .captures(&captures[1])
+                .map(|nonce| nonce[1].to_string());
+            (nonce, captures[2].to_string())
+        })
+        .take(MAX_REPORTS)
+        .collect()
+}
+
+/// Number of elements carrying a `style` attribute
+fn style_attribute_count(html: &str) -> usize {
+    Regex::new(r#"(?i)<[a-z][^>]*\sstyle\s*=\s*["']"#)
+        .map(|attribute| attribute.find_iter(html).count())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page() -> Url {
+        Url::parse("https://news.example/article").unwrap()
+    }
+
+    #[test]
+    fn test_sources_match_per_csp_rules() {
+        let doc = page();
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(source_matches(
+            "'self'",
+            &url("https://news.example/a.js"),
+            &doc
+        ));
+        assert!(!source_matches(
+            "'self'",
+            &url("https://cdn.example/a.js"),
+            &doc
+        ));
+        assert!(source_matches(
+            "https:",
+            &url("https://cdn.example/a.js"),
+            &doc
+        ));
+        assert!(source_matches(
+            "*.example",
+            &url("https://cdn.example/a.js"),
+            &doc
+        ));
+        assert!(!source_matches(
+            "*.example",
+            &url("https://example/a.js"),
+            &doc
+        ));
+        assert!(source_matches(
+            "https://cdn.example/js/",
+            &url("https://cdn.example/js/app.js"),
+            &doc
+        ));
+        assert!(!source_matches(
+            "https://cdn.example/js/",
+            &url("https://cdn.example/css/app.css"),
+            &doc
+        ));
+        assert!(!source_matches(
+            "cdn.example:8443",
+            &url("https://cdn.example/"),
+            &doc
+        ));
+        assert!(!source_matches(
+            "'none'",
+            &url("https://news.example/"),
+            &doc
+        ));
+    }
+
+    #[test]
+    fn test_report_only_policy_collects_would_be_violations() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Content-Security-Policy-Report-Only",
+            "default-src 'self'; img-src *; style-src 'self' 'nonce-abc'; \
+             report-uri https://collector.example/csp",
+        );
+        let policy = ReportOnlyPolicy::from_headers(&headers).unwrap();
+        assert_eq!(
+            policy.ignored_endpoints,
+            vec!["https://collector.example/csp".to_string()]
+        );
+
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/site.css">
+            <script src="https://tracker.example/t.js"></script>
+            <script>console.log(1)</script>
+            <style nonce="abc">p { color: red }</style>
+            <style>h1 { color: blue }</style>
+            </head><body>
+            <img src="https://images.example/cat.png">
+            <p style="margin: 0">hi</p>
+            </body></html>"#;
+        let reports = policy.evaluate(html, &page());
+        let blocked: Vec<(String, String, String)> = reports
+            .iter()
+            .map(|r| {
+                (
+                    r.effective_directive.clone(),
+                    r.violated_directive.clone(),
+                    r.blocked.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            blocked,
+            vec![
+                (
+                    "script-src".to_string(),
+                    "default-src".to_string(),
+                    "https://tracker.example/t.js".to_string()
+                ),
+                (
+                    "script-src".to_string(),
+                    "default-src".to_string(),
+                    "inline script".to_string()
+                ),
+                (
+                    "style-src".to_string(),
+                    "style-src".to_string(),
+                    "inline style".to_string()
+                ),
+                (
+                    "style-src".to_string(),
+                    "style-src".to_string(),
+                    "inline style".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hashes_allow_inline_blocks() {
+        let body = "alert(1)";
+        let hash = general_purpose::STANDARD.encode(Sha256::digest(body.as_bytes()));
+        let policy =
+            ReportOnlyPolicy::parse(&format!("script-src 'unsafe-inline' 'sha256-{}'", hash))
+                .unwrap();
+        let html = format!("<script>{}</script><script>alert(2)</script>", body);
+        let reports = policy.evaluate(&html, &page());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].blocked, BlockedResource::InlineScript);
+        assert!(ReportOnlyPolicy::parse("report-uri /x").is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use serde_json::Value;
+use tokio::sync::watch;
 use url::Url;
 
 use citadel_security::privacy::{PrivacyEvent, PrivacyEventSender};
@@ -35,6 +36,19 @@ struct DnsCacheEntry {
     addresses: Vec<IpAddr>,
     /// When this entry expires
     expires: Instant,
+    /// Pinned entries never expire
+    pinned: bool,
+}
+
+/// A cached resolution, as listed by [`CitadelDnsResolver::cached_entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsCacheRecord {
+    pub hostname: String,
+    pub addresses: Vec<IpAddr>,
+    /// Time left before the entry expires; `None` for pinned entries
+    pub ttl_remaining: Option<Duration>,
+    /// Whether the mapping was pinned with [`CitadelDnsResolver::pin_host`]
+    pub pinned: bool,
 }
 
 /// DNS resolution statistics
@@ -53,7 +67,10 @@ pub struct CitadelDnsResolver {
     cache: Arc<RwLock<HashMap<String, DnsCacheEntry>>>,
 
     /// Current DNS resolution mode
-    mode: DnsMode,
+    mode: Arc<RwLock<DnsMode>>,
+
+    /// Mode changes published by the settings store, applied on next lookup
+    mode_updates: Option<Arc<Mutex<watch::Receiver<DnsMode>>>>,
 
     /// Default TTL for cached entries
     default_ttl: Duration,
@@ -72,7 +89,7 @@ pub struct CitadelDnsResolver {
 impl std::fmt::Debug for CitadelDnsResolver {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CitadelDnsResolver")
-            .field("mode", &self.mode)
+            .field("mode", &self.get_mode())
             .field("default_ttl", &self.default_ttl)
             .field(
                 "cache_entries",
@@ -116,7 +133,8 @@ impl CitadelDnsResolver {
 
         Ok(Self {
             cache: Arc::new(RwLock::new(HashMap::new())),
-            mode: DnsMode::LocalCache,
+            mode: Arc::new(RwLock::new(DnsMode::LocalCache)),
+            mode_updates: None,
             default_ttl: Duration::from_secs(3600), // 1 hour default TTL
             dns_queries_blocked: Arc::new(RwLock::new(0)),
             dns_cache_hits: Arc::new(RwLock::new(0)),
@@ -159,6 +177,7 @@ impl CitadelDnsResolver {
     /// Resolve a hostname to IP addresses with privacy protections
     pub async fn resolve(&self, hostname: &str) -> Result<Vec<IpAddr>, NetworkError> {
         log::debug!("🔍 Resolving hostname: {}", hostname);
+        self.sync_mode();
 
         // Validate hostname for security
         if !Self::is_valid_hostname(hostname) {
@@ -168,6 +187,11 @@ impl CitadelDnsResolver {
             )));
         }
 
+        // Onion names must never reach a DNS resolver
+        if crate::proxy::is_onion_host(hostname) {
+            return Err(crate::proxy::onion_resolution_error(hostname));
+        }
+
         // Check if hostname should be blocked for privacy
         if let Some(blocked) = self.should_block_hostname_advanced(hostname).await {
             // Record the blocked request if we have a tracker blocker
@@ -204,7 +228,7 @@ impl CitadelDnsResolver {
         log::debug!("📡 Cache miss for {} - performing DNS lookup", hostname);
 
         // Perform actual DNS resolution based on configured mode
-        let addresses = match &self.mode {
+        let addresses = match &self.get_mode() {
             DnsMode::LocalCache => self.resolve_with_system(hostname).await?,
             DnsMode::DoH(url) => self.resolve_with_doh(hostname, url).await?,
             DnsMode::DoT(server) => self.resolve_with_dot(hostname, server).await?,
@@ -384,7 +408,7 @@ impl CitadelDnsResolver {
     fn check_cache(&self, hostname: &str) -> Option<Vec<IpAddr>> {
         if let Ok(cache) = self.cache.read() {
             if let Some(entry) = cache.get(hostname) {
-                if entry.expires > Instant::now() {
+                if entry.pinned || entry.expires > Instant::now() {
                     return Some(entry.addresses.clone());
                 }
             }
@@ -400,6 +424,7 @@ impl CitadelDnsResolver {
                 DnsCacheEntry {
                     addresses,
                     expires: Instant::now() + self.default_ttl,
+                    pinned: false,
                 },
             );
         }
@@ -407,6 +432,37 @@ impl CitadelDnsResolver {
 
     /// Change the DNS resolution mode
     pub async fn set_mode(&mut self, mode: DnsMode) -> Result<(), NetworkError> {
+        self.apply_mode(mode)
+    }
+
+    /// Follow mode changes from a settings channel. A new mode takes effect
+    /// (and empties the cache) at the next lookup; an invalid one is logged
+    /// and the current mode kept.
+    pub fn follow_mode(&mut self, mut updates: watch::Receiver<DnsMode>) {
+        updates.mark_changed();
+        self.mode_updates = Some(Arc::new(Mutex::new(updates)));
+    }
+
+    /// Apply a pending mode change from the settings channel, if any
+    fn sync_mode(&self) {
+        let Some(updates) = &self.mode_updates else {
+            return;
+        };
+        let Ok(mut updates) = updates.lock() else {
+            return;
+        };
+        if !updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let mode = updates.borrow_and_update().clone();
+        if mode != self.get_mode() {
+            if let Err(e) = self.apply_mode(mode) {
+                log::warn!("Keeping current DNS mode: {}", e);
+            }
+        }
+    }
+
+    fn apply_mode(&self, mode: DnsMode) -> Result<(), NetworkError> {
         log::info!("🔄 Updating DNS resolver mode to: {:?}", mode);
 
         // Validate mode-specific settings
@@ -428,7 +484,9 @@ impl CitadelDnsResolver {
             }
         }
 
-        self.mode = mode;
+        if let Ok(mut current) = self.mode.write() {
+            *current = mode;
+        }
 
         // Clear cache on mode change for privacy reasons
         if let Ok(mut cache) = self.cache.write() {
@@ -442,7 +500,10 @@ impl CitadelDnsResolver {
 
     /// Get the current DNS mode
     pub fn get_mode(&self) -> DnsMode {
-        self.mode.clone()
+        self.mode
+            .read()
+            .map(|mode| mode.clone())
+            .unwrap_or(DnsMode::LocalCache)
     }
 
     /// Clear the DNS cache
@@ -454,6 +515,64 @@ impl CitadelDnsResolver {
         }
     }
 
+    /// Live cache entries sorted by hostname; expired entries are left out
+    pub fn cached_entries(&self) -> Vec<DnsCacheRecord> {
+        let now = Instant::now();
+        let mut records: Vec<DnsCacheRecord> = self
+            .cache
+            .read()
+            .map(|cache| {
+                cache
+                    .iter()
+                    .filter(|(_, entry)| entry.pinned || entry.expires > now)
+                    .map(|(hostname, entry)| DnsCacheRecord {
+                        hostname: hostname.clone(),
+                        addresses: entry.addresses.clone(),
+                        ttl_remaining: (!entry.pinned).then(|| entry.expires - now),
+                        pinned: entry.pinned,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        records.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+        records
+    }
+
+    /// Drop one host from the cache, pinned or not; returns whether it was cached
+    pub fn flush_host(&self, hostname: &str) -> bool {
+        let removed = self
+            .cache
+            .write()
+            .map(|mut cache| cache.remove(hostname).is_some())
+            .unwrap_or(false);
+        if removed {
+            log::info!("🧹 Flushed DNS cache entry for {}", hostname);
+        }
+        removed
+    }
+
+    /// Pin a host to fixed addresses until flushed (for testing). Pinned
+    /// hosts still go through tracker blocking.
+    pub fn pin_host(&self, hostname: &str, addresses: Vec<IpAddr>) -> Result<(), NetworkError> {
+        if !Self::is_valid_hostname(hostname) || addresses.is_empty() {
+            return Err(NetworkError::DnsError(format!(
+                "Cannot pin {} to {:?}",
+                hostname, addresses
+            )));
+        }
+        if let Ok(mut cache) = self.cache.write() {
+            cache.insert(
+                hostname.to_string(),
+                DnsCacheEntry {
+                    addresses,
+                    expires: Instant::now(),
+                    pinned: true,
+                },
+            );
+        }
+        Ok(())
+    }
+
     /// Set custom TTL for cached entries
     pub fn set_ttl(&mut self, ttl: Duration) {
         log::debug!("⏰ Setting DNS TTL to: {:?}", ttl);
@@ -480,7 +599,7 @@ impl CitadelDnsResolver {
             cache_hits,
             queries_blocked,
             cache_entries,
-            current_mode: self.mode.clone(),
+            current_mode: self.get_mode(),
         }
     }
 
@@ -611,6 +730,29 @@ mod tests {
         assert_eq!(resolver.get_mode(), DnsMode::LocalCache);
     }
 
+    #[tokio::test]
+    async fn test_follows_mode_changes_on_next_lookup() {
+        let (settings, updates) = watch::channel(DnsMode::LocalCache);
+        let mut resolver = CitadelDnsResolver::new().await.unwrap();
+        resolver.follow_mode(updates);
+
+        settings.send_replace(DnsMode::DoH(DohProviders::QUAD9.to_string()));
+        assert_eq!(resolver.get_mode(), DnsMode::LocalCache);
+        // The hostname is rejected, but the pending mode is applied first
+        let _ = resolver.resolve("").await;
+        assert_eq!(
+            resolver.get_mode(),
+            DnsMode::DoH(DohProviders::QUAD9.to_string())
+        );
+
+        settings.send_replace(DnsMode::DoH("not a url".to_string()));
+        let _ = resolver.resolve("").await;
+        assert_eq!(
+            resolver.get_mode(),
+            DnsMode::DoH(DohProviders::QUAD9.to_string())
+        );
+    }
+
     #[tokio::test]
     async fn test_cache_expiration() {
         let mut resolver = CitadelDnsResolver::new().await.unwrap();
@@ -710,4 +852,33 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_cache_inspection_pinning_and_flush() {
+        let mut resolver = CitadelDnsResolver::new().await.unwrap();
+        resolver.set_ttl(Duration::from_secs(60));
+        let pinned: Vec<IpAddr> = vec!["192.0.2.7".parse().unwrap()];
+
+        resolver.pin_host("pinned.test", pinned.clone()).unwrap();
+        resolver.update_cache(
+            "cached.test".to_string(),
+            vec!["192.0.2.8".parse().unwrap()],
+        );
+        assert!(resolver.pin_host("bad host", pinned.clone()).is_err());
+        assert!(resolver.pin_host("empty.test", Vec::new()).is_err());
+
+        let entries = resolver.cached_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].hostname, "cached.test");
+        assert!(entries[0].ttl_remaining.unwrap() <= Duration::from_secs(60));
+        assert!(entries[1].pinned && entries[1].ttl_remaining.is_none());
+
+        // Pinned hosts resolve without a lookup
+        assert_eq!(resolver.resolve("pinned.test").await.unwrap(), pinned);
+
+        assert!(resolver.flush_host("pinned.test"));
+        assert!(!resolver.flush_host("pinned.test"));
+        resolver.clear_cache();
+        assert!(resolver.cached_entries().is_empty());
+    }
 }
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use citadel_errors::{Classify, ErrorKind};
 use thiserror::Error;
 
 /// NetworkError represents all possible errors that can occur within the networking layer
@@ -31,6 +34,18 @@ pub enum NetworkError {
     #[error("Privacy violation: {0}")]
     PrivacyViolationError(String),
 
+    /// A signature over downloaded content did not verify
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+
+    /// Per-tab request budget exhausted
+    #[error("Request budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    /// The server answered with a non-success status
+    #[error("HTTP error: status {0}")]
+    HttpStatus(u16),
+
     /// Resource loading errors
     #[error("Resource loading error: {0}")]
     ResourceError(String),
@@ -67,3 +82,255 @@ impl NetworkError {
         )
     }
 }
+
+impl NetworkError {
+    /// What went wrong, in terms of what the user or the loader can do about it
+    pub fn category(&self) -> LoadErrorCategory {
+        match self {
+            NetworkError::DnsError(_) => LoadErrorCategory::Dns,
+            NetworkError::TlsError(_) => LoadErrorCategory::Tls,
+            NetworkError::TimeoutError(_) => LoadErrorCategory::Timeout,
+            NetworkError::ConnectionError(_) | NetworkError::IoError(_) => {
+                LoadErrorCategory::Connection
+            }
+            NetworkError::PrivacyViolationError(_) => LoadErrorCategory::BlockedByFilter,
+            NetworkError::HttpsEnforcementError(_) => LoadErrorCategory::InsecureConnection,
+            NetworkError::BudgetExceeded(_) => LoadErrorCategory::Quota,
+            NetworkError::HttpStatus(status) if *status >= 500 || *status == 429 => {
+                LoadErrorCategory::ServerBusy
+            }
+            NetworkError::HttpStatus(_) => LoadErrorCategory::HttpError,
+            NetworkError::UrlError(_)
+            | NetworkError::SerializationError(_)
+            | NetworkError::SignatureError(_) => LoadErrorCategory::Invalid,
+            NetworkError::ResourceError(_) | NetworkError::UnknownError(_) => {
+                LoadErrorCategory::Other
+            }
+        }
+    }
+}
+
+/// Actionable category of a failed load. Each has its own error page and
+/// its own [`RetryPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadErrorCategory {
+    /// The host name did not resolve
+    Dns,
+    /// The certificate or handshake was rejected
+    Tls,
+    /// The server did not answer in time
+    Timeout,
+    /// The connection could not be made or was dropped
+    Connection,
+    /// A tracker filter or policy refused the request
+    BlockedByFilter,
+    /// Plain HTTP refused by HTTPS enforcement
+    InsecureConnection,
+    /// The tab's request budget ran out
+    Quota,
+    /// The server is overloaded or failing (5xx, 429)
+    ServerBusy,
+    /// Any other non-success status
+    HttpError,
+    /// The URL or data was malformed
+    Invalid,
+    Other,
+}
+
+/// Whether and how a failed load is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Retrying gives the same answer
+    Never,
+    /// Only when the user asks, e.g. after fixing their connection
+    Manual,
+    /// Up to `attempts` more times, doubling the delay from `initial_delay`
+    Automatic {
+        attempts: u32,
+        initial_delay: Duration,
+    },
+}
+
+impl RetryPolicy {
+    /// Delay before automatic retry number `attempt` (from 0), or `None`
+    /// once retries are used up
+    pub fn delay(self, attempt: u32) -> Option<Duration> {
+        match self {
+            RetryPolicy::Automatic {
+                attempts,
+                initial_delay,
+            } if attempt < attempts => Some(initial_delay.saturating_mul(1 << attempt.min(16))),
+            _ => None,
+        }
+    }
+
+    /// Whether offering the user a retry makes sense
+    pub fn allows_retry(self) -> bool {
+        !matches!(self, RetryPolicy::Never)
+    }
+}
+
+impl LoadErrorCategory {
+    pub fn retry_policy(self) -> RetryPolicy {
+        match self {
+            // Resolvers and networks flap; a short wait often fixes it
+            LoadErrorCategory::Dns => RetryPolicy::Automatic {
+                attempts: 1,
+                initial_delay: Duration::from_millis(500),
+            },
+            LoadErrorCategory::Connection => RetryPolicy::Automatic {
+                attempts: 2,
+                initial_delay: Duration::from_millis(250),
+            },
+            LoadErrorCategory::Timeout => RetryPolicy::Automatic {
+                attempts: 1,
+                initial_delay: Duration::from_secs(1),
+            },
+            // Back off harder from a server asking for less load
+            LoadErrorCategory::ServerBusy => RetryPolicy::Automatic {
+                attempts: 1,
+                initial_delay: Duration::from_secs(2),
+            },
+            // A new navigation starts a new budget; a 404 may be fixed later
+            LoadErrorCategory::Quota | LoadErrorCategory::HttpError | LoadErrorCategory::Other => {
+                RetryPolicy::Manual
+            }
+            // Retrying a rejected certificate or a filtered request only
+            // repeats the refusal
+            LoadErrorCategory::Tls
+            | LoadErrorCategory::BlockedByFilter
+            | LoadErrorCategory::InsecureConnection
+            | LoadErrorCategory::Invalid => RetryPolicy::Never,
+        }
+    }
+
+    /// Title for the error page
+    pub fn title(self) -> &'static str {
+        match self {
+            LoadErrorCategory::Dns => "Site not found",
+            LoadErrorCategory::Tls => "Connection not secure",
+            LoadErrorCategory::Timeout => "Page took too long to respond",
+            LoadErrorCategory::Connection => "Can't reach this page",
+            LoadErrorCategory::BlockedByFilter => "Blocked by a filter",
+            LoadErrorCategory::InsecureConnection => "Only secure connections allowed",
+            LoadErrorCategory::Quota => "Page made too many requests",
+            LoadErrorCategory::ServerBusy => "Site is having trouble",
+            LoadErrorCategory::HttpError => "Page not available",
+            LoadErrorCategory::Invalid => "Invalid address",
+            LoadErrorCategory::Other => "Page failed to load",
+        }
+    }
+
+    /// What the user can do about it
+    pub fn hint(self) -> &'static str {
+        match self {
+            LoadErrorCategory::Dns => "Check the address for typos, or your DNS settings.",
+            LoadErrorCategory::Tls => {
+                "The site's certificate could not be verified. Citadel will not load it."
+            }
+            LoadErrorCategory::Timeout => "The site may be overloaded. Try again later.",
+            LoadErrorCategory::Connection => "Check your network connection or proxy.",
+            LoadErrorCategory::BlockedByFilter => {
+                "A tracker filter or container policy refused this address."
+            }
+            LoadErrorCategory::InsecureConnection => {
+                "The site is not offered over HTTPS. Citadel does not load plain HTTP."
+            }
+            LoadErrorCategory::Quota => "Reloading starts a fresh request budget.",
+            LoadErrorCategory::ServerBusy => "The server reported an error. Try again later.",
+            LoadErrorCategory::HttpError => "The server refused or could not find the page.",
+            LoadErrorCategory::Invalid => "The address or response was malformed.",
+            LoadErrorCategory::Other => "Something unexpected went wrong.",
+        }
+    }
+}
+
+impl Classify for NetworkError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            NetworkError::DnsError(_) => ErrorKind::Dns,
+            NetworkError::TlsError(_) => ErrorKind::Tls,
+            NetworkError::ConnectionError(_)
+            | NetworkError::HttpStatus(_)
+            | NetworkError::ResourceError(_)
+            | NetworkError::IoError(_) => ErrorKind::Network,
+            NetworkError::UrlError(_) | NetworkError::SerializationError(_) => ErrorKind::Content,
+            NetworkError::TimeoutError(_) => ErrorKind::Timeout,
+            NetworkError::HttpsEnforcementError(_) | NetworkError::PrivacyViolationError(_) => {
+                ErrorKind::Privacy
+            }
+            NetworkError::SignatureError(_) => ErrorKind::Security,
+            NetworkError::BudgetExceeded(_) => ErrorKind::Resource,
+            NetworkError::UnknownError(_) => ErrorKind::Internal,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            NetworkError::DnsError(_) => "NET_DNS",
+            NetworkError::TlsError(_) => "NET_TLS",
+            NetworkError::ConnectionError(_) => "NET_CONNECTION",
+            NetworkError::UrlError(_) => "NET_INVALID_URL",
+            NetworkError::TimeoutError(_) => "NET_TIMEOUT",
+            NetworkError::HttpsEnforcementError(_) => "NET_HTTPS_REQUIRED",
+            NetworkError::PrivacyViolationError(_) => "NET_PRIVACY",
+            NetworkError::SignatureError(_) => "NET_SIGNATURE",
+            NetworkError::BudgetExceeded(_) => "NET_BUDGET",
+            NetworkError::HttpStatus(_) => "NET_HTTP_STATUS",
+            NetworkError::ResourceError(_) => "NET_RESOURCE",
+            NetworkError::IoError(_) => "NET_IO",
+            NetworkError::SerializationError(_) => "NET_SERIALIZATION",
+            NetworkError::UnknownError(_) => "NET_UNKNOWN",
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            NetworkError::HttpStatus(status) => *status >= 500 || *status == 429,
+            _ => NetworkError::is_retryable(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories_choose_retry_policies() {
+        let dns = NetworkError::DnsError("nxdomain".into()).category();
+        assert_eq!(dns, LoadErrorCategory::Dns);
+        assert_eq!(
+            dns.retry_policy().delay(0),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(dns.retry_policy().delay(1), None);
+
+        let connection = NetworkError::ConnectionError("reset".into()).category();
+        assert_eq!(
+            connection.retry_policy().delay(1),
+            Some(Duration::from_millis(500))
+        );
+
+        assert_eq!(
+            NetworkError::HttpStatus(503).category(),
+            LoadErrorCategory::ServerBusy
+        );
+        assert_eq!(
+            NetworkError::HttpStatus(404).category().retry_policy(),
+            RetryPolicy::Manual
+        );
+        for never in [
+            NetworkError::TlsError("bad certificate".into()),
+            NetworkError::PrivacyViolationError("tracker".into()),
+        ] {
+            let policy = never.category().retry_policy();
+            assert_eq!(policy.delay(0), None);
+            assert!(!policy.allows_retry());
+        }
+        assert_eq!(
+            NetworkError::BudgetExceeded("requests".into()).category(),
+            LoadErrorCategory::Quota
+        );
+    }
+}
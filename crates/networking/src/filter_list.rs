@@ -0,0 +1,825 @@
+//! Filter lists compiled into a binary form and cached on disk
+//!
+//! Parsing text filter lists on every start is slow once they run to tens of
+//! thousands of lines. Each list is compiled once instead, and the compiled
+//! form cached by a [`FilterListCache`]:
+//!
+//! - blocked and excepted domains go into tries keyed by reversed labels, so
+//!   a lookup walks a host from its TLD and a rule covers the subdomains of
+//!   its domain,
+//! - a Bloom filter over the blocked domains answers most lookups, which are
+//!   misses, without walking the trie,
+//! - element-hiding rules are kept parsed for the
+//!   [`CosmeticFilter`](crate::cosmetic::CosmeticFilter).
+//!
+//! A cache entry is used while its list is unchanged: the same size and
+//! modification time, or failing that the same SHA-256. Entries record the
+//! [`FORMAT_VERSION`] they were written in and are recompiled when it
+//! changes. [`FilterLists`] refreshes incrementally: only lists whose files
+//! changed are recompiled, and removed files drop out.
+//!
+//! Domain rules (`||ads.example^`), their exceptions
+//! (`@@||cdn.ads.example^`), hosts file lines (`0.0.0.0 ads.example`) and
+//! plain domains, one per line, go into the tries. Rules with `$` options or
+//! URL patterns are compiled into [`NetworkRules`] for the
+//! [`BlocklistEngine`](crate::blocklist::BlocklistEngine); the syntax it
+//! does not support is skipped.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::blocklist::{NetworkRule, NetworkRules};
+use crate::cosmetic::{CosmeticFilter, CosmeticRule};
+
+/// Environment variable overriding where filter lists are installed
+pub const FILTER_LISTS_DIR_ENV: &str = "CITADEL_FILTER_LISTS_DIR";
+
+/// Version of the compiled format; entries of other versions are recompiled
+pub const FORMAT_VERSION: u32 = 2;
+
+/// How often installed lists are checked for changes
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Extension of the list files picked up from the lists directory
+pub const LIST_EXTENSION: &str = "txt";
+
+/// Start of every cache entry
+const ENTRY_MAGIC: &[u8] = b"CTDLFLT\0";
+
+/// Bloom filter bits per blocked domain, for about 1% false positives
+const BLOOM_BITS_PER_DOMAIN: usize = 10;
+
+/// Bloom filter probes per lookup
+const BLOOM_HASHES: u32 = 7;
+
+/// Lowercased domain of a network rule line, if it is one
+fn domain_rule(line: &str) -> Option<(String, bool)> {
+    let (line, exception) = match line.strip_prefix("@@") {
+        Some(rest) => (rest, true),
+        None => (line, false),
+    };
+    let domain = if let Some(anchored) = line.strip_prefix("||") {
+        anchored.strip_suffix('^').unwrap_or(anchored)
+    } else if exception {
+        return None;
+    } else {
+        // Hosts files put comments after the entry
+        let entry = line.split(" #").next().unwrap_or_default();
+        let mut fields = entry.split_whitespace();
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("0.0.0.0" | "127.0.0.1" | "::" | "::1"), Some(host), None) => host,
+            (Some(host), None, None) => host,
+            _ => return None,
+        }
+    };
+
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let valid = domain.contains('.')
+        && domain != "localhost"
+        && !domain.starts_with(['.', '-'])
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+        && !domain.split('.').any(str::is_empty);
+    valid.then_some((domain, exception))
+}
+
+/// Domains keyed by their labels from the TLD down; a domain covers its
+/// subdomains
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DomainTrie {
+    /// `nodes[0]` is the root
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TrieNode {
+    /// Children by label, sorted for binary search
+    children: Vec<(String, u32)>,
+    /// A domain ends here
+    terminal: bool,
+}
+
+impl Default for DomainTrie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl DomainTrie {
+    /// Parents go in before their subdomains, which they then cover, so
+    /// every node is reachable and comes after its parent
+    fn from_domains(mut domains: Vec<String>) -> Self {
+        domains.sort_by_key(|domain| domain.matches('.').count());
+        let mut trie = Self::default();
+        for domain in &domains {
+            trie.insert(domain);
+        }
+        trie
+    }
+
+    fn insert(&mut self, domain: &str) {
+        let mut node = 0;
+        for label in domain.rsplit('.') {
+            if self.nodes[node].terminal {
+                // Already covered by a parent domain
+                return;
+            }
+            node = match self.nodes[node]
+                .children
+                .binary_search_by(|(child, _)| child.as_str().cmp(label))
+            {
+                Ok(found) => self.nodes[node].children[found].1 as usize,
+                Err(at) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node]
+                        .children
+                        .insert(at, (label.to_string(), child as u32));
+                    child
+                }
+            };
+        }
+        self.nodes[node].terminal = true;
+    }
+
+    /// Whether `host` or one of its parent domains is in the trie
+    fn covers(&self, host: &str) -> bool {
+        self.covering(host).is_some()
+    }
+
+    /// The domain in the trie that is `host` or one of its parents
+    fn covering<'a>(&self, host: &'a str) -> Option<&'a str> {
+        let mut node = &self.nodes[0];
+        let mut start = host.len();
+        for label in host.rsplit('.') {
+            let found = node
+                .children
+                .binary_search_by(|(child, _)| child.as_str().cmp(label))
+                .ok()?;
+            node = &self.nodes[node.children[found].1 as usize];
+            start -= label.len();
+            if node.terminal {
+                return Some(&host[start..]);
+            }
+            start = start.saturating_sub(1);
+        }
+        None
+    }
+
+    /// Number of domains in the trie
+    fn len(&self) -> usize {
+        self.nodes.iter().filter(|node| node.terminal).count()
+    }
+}
+
+/// Bloom filter over domains, hashed with FNV-1a so the bits stay valid
+/// across builds
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    fn with_capacity(domains: usize) -> Self {
+        let words = (domains * BLOOM_BITS_PER_DOMAIN).div_ceil(64).max(1);
+        Self {
+            bits: vec![0; words],
+        }
+    }
+
+    /// Bit positions of `domain`, by double hashing
+    fn positions(&self, domain: &str) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            domain.bytes().fold(seed, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+        };
+        let (h1, h2) = (hash(0xcbf2_9ce4_8422_2325), hash(0x8422_2325_cbf2_9ce4) | 1);
+        let bits = (self.bits.len() * 64) as u64;
+        (0..u64::from(BLOOM_HASHES))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+
+    fn insert(&mut self, domain: &str) {
+        for bit in self.positions(domain) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn may_contain(&self, domain: &str) -> bool {
+        self.positions(domain)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// One filter list, compiled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledFilterList {
+    /// The list's `! Version:` header
+    version: Option<String>,
+    blocked: DomainTrie,
+    excepted: DomainTrie,
+    bloom: BloomFilter,
+    cosmetic: Vec<CosmeticRule>,
+    network: NetworkRules,
+}
+
+impl CompiledFilterList {
+    /// Compile the text of a filter list
+    pub fn compile(list: &str) -> Self {
+        let mut version = None;
+        let (mut blocked, mut excepted) = (Vec::new(), Vec::new());
+        let (mut cosmetic, mut network) = (Vec::new(), Vec::new());
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('!') {
+                if let Some((key, value)) = comment.split_once(':') {
+                    if key.trim().eq_ignore_ascii_case("version") && version.is_none() {
+                        version = Some(value.trim().to_string());
+                    }
+                }
+            } else if let Some(rule) = CosmeticRule::parse(line) {
+                cosmetic.push(rule);
+            } else if let Some((domain, exception)) = domain_rule(line) {
+                match exception {
+                    true => excepted.push(domain),
+                    false => blocked.push(domain),
+                }
+            } else if let Some(rule) = NetworkRule::parse(line) {
+                network.push(rule);
+            }
+        }
+
+        let mut bloom = BloomFilter::with_capacity(blocked.len());
+        for domain in &blocked {
+            bloom.insert(domain);
+        }
+        Self {
+            version,
+            blocked: DomainTrie::from_domains(blocked),
+            excepted: DomainTrie::from_domains(excepted),
+            bloom,
+            cosmetic,
+            network: NetworkRules::new(network),
+        }
+    }
+
+    /// The version the list declares in its header
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Number of blocked domains, not counting subdomains of other entries
+    pub fn blocked_domains(&self) -> usize {
+        self.blocked.len()
+    }
+
+    /// Element-hiding rules of the list
+    pub fn cosmetic_rules(&self) -> &[CosmeticRule] {
+        &self.cosmetic
+    }
+
+    /// Rules with URL patterns or options
+    pub fn network_rules(&self) -> &NetworkRules {
+        &self.network
+    }
+
+    /// Whether a rule blocks `host` or a parent domain; exceptions are not
+    /// considered
+    pub fn blocks(&self, host: &str) -> bool {
+        self.blocking_domain(host).is_some()
+    }
+
+    /// The blocked domain covering `host`, itself or a parent domain;
+    /// exceptions are not considered
+    pub fn blocking_domain(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        // Most hosts are in no list; the filter rules them out cheaply
+        let mut suffixes = host
+            .match_indices('.')
+            .map(|(dot, _)| &host[dot + 1..])
+            .chain(std::iter::once(host.as_str()));
+        if !suffixes.any(|suffix| self.bloom.may_contain(suffix)) {
+            return None;
+        }
+        self.blocked.covering(&host).map(str::to_string)
+    }
+
+    /// Whether an exception rule covers `host`
+    pub fn excepts(&self, host: &str) -> bool {
+        self.excepted
+            .covers(&host.trim_end_matches('.').to_ascii_lowercase())
+    }
+
+    fn encode(&self, out: &mut Encoder) {
+        out.str(self.version.as_deref().unwrap_or_default());
+        for trie in [&self.blocked, &self.excepted] {
+            out.u32(trie.nodes.len() as u32);
+            for node in &trie.nodes {
+                out.u8(node.terminal as u8);
+                out.u32(node.children.len() as u32);
+                for (label, child) in &node.children {
+                    out.str(label);
+                    out.u32(*child);
+                }
+            }
+        }
+        out.u32(self.bloom.bits.len() as u32);
+        for word in &self.bloom.bits {
+            out.u64(*word);
+        }
+        out.u32(self.cosmetic.len() as u32);
+        for rule in &self.cosmetic {
+            out.u8(rule.exception as u8);
+            out.str(&rule.selector);
+            for domains in [&rule.domains, &rule.excluded] {
+                out.u32(domains.len() as u32);
+                for domain in domains {
+                    out.str(domain);
+                }
+            }
+        }
+        // Rules are stored as written and parsed again when read
+        out.u32(self.network.len() as u32);
+        for rule in self.network.rules() {
+            out.str(rule.source());
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Option<Self> {
+        let version = Some(input.str()?).filter(|version| !version.is_empty());
+        let mut tries = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let count = input.len()?;
+            let mut nodes = Vec::with_capacity(count);
+            for index in 0..count {
+                let terminal = input.u8()? != 0;
+                let mut children = Vec::new();
+                for _ in 0..input.len()? {
+                    let label = input.str()?;
+                    let child = input.u32()?;
+                    // Children come after their parent, so lookups end
+                    if child as usize <= index || child as usize >= count {
+                        return None;
+                    }
+                    children.push((label, child));
+                }
+                nodes.push(TrieNode { children, terminal });
+            }
+            if nodes.is_empty() {
+                return None;
+            }
+            tries.push(DomainTrie { nodes });
+        }
+        let words = input.len()?;
+        let bits = (0..words)
+            .map(|_| input.u64())
+            .collect::<Option<Vec<_>>>()?;
+        if bits.is_empty() {
+            return None;
+        }
+        let rules = input.len()?;
+        let mut cosmetic = Vec::with_capacity(rules);
+        for _ in 0..rules {
+            let exception = input.u8()? != 0;
+            let selector = input.str()?;
+            let mut lists = [Vec::new(), Vec::new()];
+            for list in &mut lists {
+                for _ in 0..input.len()? {
+                    list.push(input.str()?);
+                }
+            }
+            let [domains, excluded] = lists;
+            cosmetic.push(CosmeticRule {
+                domains,
+                excluded,
+                selector,
+                exception,
+            });
+        }
+        let network = (0..input.len()?)
+            .map(|_| NetworkRule::parse(&input.str()?))
+            .collect::<Option<Vec<_>>>()?;
+        let excepted = tries.pop()?;
+        let blocked = tries.pop()?;
+        Some(Self {
+            version,
+            blocked,
+            excepted,
+            bloom: BloomFilter { bits },
+            cosmetic,
+            network: NetworkRules::new(network),
+        })
+    }
+}
+
+/// Little-endian writer for cache entries
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Reader matching [`Encoder`]; every read fails on truncated input
+struct Decoder<'a>(&'a [u8]);
+
+impl Decoder<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let (taken, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// A count, bounded by the bytes left so corrupt input cannot make
+    /// huge allocations
+    fn len(&mut self) -> Option<usize> {
+        let len = self.u32()? as usize;
+        (len <= self.0.len()).then_some(len)
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
+/// Size and modification time of a list file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    len: u64,
+    /// Nanoseconds since the Unix epoch; 0 when the platform has no
+    /// modification times, and then the stamp never matches
+    modified: u64,
+}
+
+impl SourceStamp {
+    fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64);
+        Ok(Self {
+            len: metadata.len(),
+            modified,
+        })
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        self.modified != 0 && self == other
+    }
+}
+
+/// Compiled lists kept on disk between runs
+#[derive(Debug, Clone)]
+pub struct FilterListCache {
+    dir: PathBuf,
+}
+
+impl FilterListCache {
+    /// A cache in `dir`, created when first written
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The compiled list at `path`: from the cache when the file is
+    /// unchanged, otherwise compiled and cached
+    pub fn load(&self, path: &Path) -> std::io::Result<CompiledFilterList> {
+        let stamp = SourceStamp::of(path)?;
+        let entry_path = self.entry_path(path);
+        let cached = std::fs::read(&entry_path)
+            .ok()
+            .and_then(|bytes| decode_entry(&bytes));
+        if let Some((cached_stamp, _, list)) = &cached {
+            if cached_stamp.matches(&stamp) {
+                return Ok(list.clone());
+            }
+        }
+
+        let source = std::fs::read(path)?;
+        let digest: [u8; 32] = Sha256::digest(&source).into();
+        let list = match cached {
+            // Touched but not edited
+            Some((_, cached_digest, list)) if cached_digest == digest => list,
+            _ => {
+                log::info!("🧩 Compiling filter list {}", path.display());
+                CompiledFilterList::compile(&String::from_utf8_lossy(&source))
+            }
+        };
+        if let Err(e) = self.write_entry(&entry_path, stamp, &digest, &list) {
+            log::warn!(
+                "Failed to cache compiled filter list {}: {}",
+                path.display(),
+                e
+            );
+        }
+        Ok(list)
+    }
+
+    /// Entry names are hashes of the list paths
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let digest = Sha256::digest(path.as_os_str().as_encoded_bytes());
+        let name: String = digest[..16].iter().map(|b| format!("{b:02x}")).collect();
+        self.dir.join(format!("{name}.bin"))
+    }
+
+    fn write_entry(
+        &self,
+        entry_path: &Path,
+        stamp: SourceStamp,
+        digest: &[u8; 32],
+        list: &CompiledFilterList,
+    ) -> std::io::Result<()> {
+        let mut out = Encoder(ENTRY_MAGIC.to_vec());
+        out.u32(FORMAT_VERSION);
+        out.u64(stamp.len);
+        out.u64(stamp.modified);
+        out.0.extend_from_slice(digest);
+        list.encode(&mut out);
+
+        std::fs::create_dir_all(&self.dir)?;
+        // Readers never see a partly written entry
+        let partial = entry_path.with_extension("tmp");
+        std::fs::write(&partial, &out.0)?;
+        std::fs::rename(&partial, entry_path)
+    }
+}
+
+/// Stamp, source digest and list of a cache entry in the current format
+fn decode_entry(bytes: &[u8]) -> Option<(SourceStamp, [u8; 32], CompiledFilterList)> {
+    let mut input = Decoder(bytes.strip_prefix(ENTRY_MAGIC)?);
+    if input.u32()? != FORMAT_VERSION {
+        return None;
+    }
+    let stamp = SourceStamp {
+        len: input.u64()?,
+        modified: input.u64()?,
+    };
+    let digest = input.take(32)?.try_into().ok()?;
+    let list = CompiledFilterList::decode(&mut input)?;
+    input.0.is_empty().then_some((stamp, digest, list))
+}
+
+#[derive(Debug, Clone)]
+struct InstalledList {
+    stamp: SourceStamp,
+    list: Arc<CompiledFilterList>,
+}
+
+/// The installed filter lists, shared between the loaders that block with
+/// them and whoever refreshes them
+#[derive(Debug, Clone, Default)]
+pub struct FilterLists {
+    lists: Arc<RwLock<BTreeMap<PathBuf, InstalledList>>>,
+}
+
+impl FilterLists {
+    /// No lists: nothing is blocked
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the lists in line with the `.txt` files in `dir`: new and
+    /// changed files are loaded through `cache`, unchanged ones kept and
+    /// removed ones dropped. A file that fails to load keeps its previous
+    /// version. Returns whether any list changed.
+    pub fn refresh(&self, dir: &Path, cache: &FilterListCache) -> bool {
+        let mut files = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == LIST_EXTENSION) && path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+        let installed = self.snapshot();
+
+        let mut changed = installed.keys().any(|path| !files.contains(path));
+        let mut refreshed = BTreeMap::new();
+        for path in files {
+            let previous = installed.get(&path);
+            let stamp = match SourceStamp::of(&path) {
+                Ok(stamp) => stamp,
+                Err(_) => continue,
+            };
+            if let Some(previous) = previous.filter(|p| p.stamp.matches(&stamp)) {
+                refreshed.insert(path, previous.clone());
+                continue;
+            }
+            match cache.load(&path) {
+                Ok(list) => {
+                    changed |= !matches!(previous, Some(p) if *p.list == list);
+                    let list = Arc::new(list);
+                    refreshed.insert(path, InstalledList { stamp, list });
+                }
+                Err(e) => {
+                    log::warn!("Failed to load filter list {}: {}", path.display(), e);
+                    if let Some(previous) = previous {
+                        refreshed.insert(path, previous.clone());
+                    }
+                }
+            }
+        }
+
+        if let Ok(mut lists) = self.lists.write() {
+            *lists = refreshed;
+        }
+        changed
+    }
+
+    fn snapshot(&self) -> BTreeMap<PathBuf, InstalledList> {
+        self.lists
+            .read()
+            .map(|lists| lists.clone())
+            .unwrap_or_default()
+    }
+
+    /// Number of installed lists
+    pub fn len(&self) -> usize {
+        self.lists.read().map(|lists| lists.len()).unwrap_or(0)
+    }
+
+    /// Whether no lists are installed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether a list blocks `host` and no list excepts it
+    pub fn blocks_host(&self, host: &str) -> bool {
+        let Ok(lists) = self.lists.read() else {
+            return false;
+        };
+        lists.values().any(|installed| installed.list.blocks(host))
+            && !lists.values().any(|installed| installed.list.excepts(host))
+    }
+
+    /// Whether a list has an exception rule covering `host`
+    pub fn excepts_host(&self, host: &str) -> bool {
+        self.lists
+            .read()
+            .is_ok_and(|lists| lists.values().any(|installed| installed.list.excepts(host)))
+    }
+
+    /// The installed lists, compiled
+    pub fn compiled(&self) -> Vec<Arc<CompiledFilterList>> {
+        self.lists
+            .read()
+            .map(|lists| {
+                lists
+                    .values()
+                    .map(|installed| installed.list.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Add the element-hiding rules of every list to `filter`
+    pub fn add_cosmetic_rules(&self, filter: &mut CosmeticFilter) {
+        if let Ok(lists) = self.lists.read() {
+            for installed in lists.values() {
+                filter.add_rules(installed.list.cosmetic_rules().iter().cloned());
+            }
+        }
+    }
+}
+
+/// Where filter lists are installed: `$CITADEL_FILTER_LISTS_DIR`, otherwise
+/// `citadel/filters` under the XDG data directory
+pub fn default_dir() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(FILTER_LISTS_DIR_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("citadel").join("filters"))
+}
+
+/// Where compiled lists are cached: `citadel/filters` under the XDG cache
+/// directory
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_dir.join("citadel").join("filters"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "\
+[Adblock Plus 2.0]
+! Version: 202610150900
+! Title: Test list
+||ads.example^
+||tracker.test^$third-party
+@@||ok.ads.example^
+0.0.0.0 metrics.test # analytics
+# hosts comment
+plain.test
+/banner/*.gif
+example.com##.promo
+";
+
+    #[test]
+    fn test_compiled_list_matches_domains_and_subdomains() {
+        let list = CompiledFilterList::compile(LIST);
+        assert_eq!(list.version(), Some("202610150900"));
+        assert_eq!(list.blocked_domains(), 3);
+        assert!(list.blocks("ads.example"));
+        assert!(list.blocks("cdn.ADS.example."));
+        assert!(list.blocks("metrics.test"));
+        assert!(list.blocks("plain.test"));
+        assert!(!list.blocks("tracker.test"));
+        assert!(!list.blocks("badads.example"));
+        assert!(!list.blocks("example"));
+        assert!(list.excepts("ok.ads.example"));
+        assert_eq!(
+            list.blocking_domain("cdn.ads.example").as_deref(),
+            Some("ads.example")
+        );
+        assert_eq!(list.cosmetic_rules().len(), 1);
+        assert_eq!(list.network_rules().len(), 2);
+
+        let mut out = Encoder::default();
+        list.encode(&mut out);
+        let decoded = CompiledFilterList::decode(&mut Decoder(&out.0)).unwrap();
+        assert_eq!(decoded, list);
+        assert!(CompiledFilterList::decode(&mut Decoder(&out.0[..out.0.len() - 1])).is_none());
+    }
+
+    #[test]
+    fn test_refresh_recompiles_only_changed_lists() {
+        let root = std::env::temp_dir().join(format!("citadel-filters-{}", uuid::Uuid::new_v4()));
+        let (dir, cache) = (root.join("lists"), FilterListCache::new(root.join("cache")));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "||ads.example^\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "@@||ok.ads.example^\n##.banner\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "||ignored.test^\n").unwrap();
+
+        let lists = FilterLists::new();
+        assert!(lists.refresh(&dir, &cache));
+        assert_eq!(lists.len(), 2);
+        assert!(lists.blocks_host("x.ads.example"));
+        assert!(!lists.blocks_host("ok.ads.example"));
+        assert!(!lists.blocks_host("ignored.test"));
+        let mut filter = CosmeticFilter::new();
+        lists.add_cosmetic_rules(&mut filter);
+        assert_eq!(filter.len(), 1);
+
+        // Cached entries are used as they are
+        let cached = cache.load(&dir.join("a.txt")).unwrap();
+        assert!(cached.blocks("ads.example"));
+        assert!(!lists.refresh(&dir, &cache));
+
+        std::fs::write(dir.join("a.txt"), "||other.test^\n||more.test^\n").unwrap();
+        std::fs::remove_file(dir.join("b.txt")).unwrap();
+        assert!(lists.refresh(&dir, &cache));
+        assert_eq!(lists.len(), 1);
+        assert!(!lists.blocks_host("ads.example"));
+        assert!(lists.blocks_host("more.test"));
+        assert_eq!(cache.load(&dir.join("a.txt")).unwrap().blocked_domains(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
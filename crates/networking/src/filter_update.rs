@@ -0,0 +1,594 @@
+//! Signed filter list updates
+//!
+//! Filter lists can be kept current from URLs the user subscribes to. The
+//! updater is off unless the settings file turns it on:
+//!
+//! ```json
+//! {
+//!   "enabled": true,
+//!   "interval_hours": 24,
+//!   "keep_versions": 3,
+//!   "subscriptions": [
+//!     {
+//!       "name": "easylist",
+//!       "url": "https://lists.example/easylist.txt",
+//!       "public_key": "<base64 Ed25519 public key>"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Every list comes with a detached Ed25519 signature over its bytes, fetched
+//! from `signature_url` (by default the list's URL with `.sig` appended) as
+//! base64. A list is installed only when its signature verifies against the
+//! subscription's key, and never over a list that declares a newer
+//! `! Version:`, so an old signed list cannot be replayed. Verified lists
+//! are kept by version so a bad update can be rolled back; the newest
+//! `keep_versions` are kept.
+//!
+//! Fetches carry nothing that tells users apart: the URLs are canonicalized
+//! with tracking parameters stripped, credentials in them are refused, no
+//! cookies are sent, no TLS session is resumed, and through a SOCKS proxy
+//! they run on a circuit of their own.
+//!
+//! Installed lists are written to the filter list directory
+//! ([`filter_list::default_dir`]), where the next
+//! [`FilterLists::refresh`](crate::filter_list::FilterLists::refresh) picks
+//! them up.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose, Engine as _};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::NetworkError;
+use crate::filter_list::{self, CompiledFilterList, LIST_EXTENSION};
+use crate::proxy::SocksProxy;
+use crate::url_canon;
+
+/// Environment variable overriding where update settings are read from
+pub const FILTER_UPDATES_FILE_ENV: &str = "CITADEL_FILTER_UPDATES_FILE";
+
+/// How often the browser asks the updater for due subscriptions
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Stream isolation token for update fetches through a SOCKS proxy
+const PROXY_ISOLATION: &str = "filter-updates";
+
+/// Largest signature file accepted, in bytes
+const MAX_SIGNATURE_BYTES: usize = 1024;
+
+/// Name of the file recording when a subscription was last checked
+const CHECKED_FILE: &str = "checked";
+
+/// The user's update settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Whether lists are fetched at all
+    pub enabled: bool,
+    /// Hours between checks of one subscription
+    pub interval_hours: u64,
+    /// Verified versions kept per subscription for rollback
+    pub keep_versions: usize,
+    pub subscriptions: Vec<Subscription>,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: 24,
+            keep_versions: 3,
+            subscriptions: Vec::new(),
+        }
+    }
+}
+
+impl UpdateSettings {
+    /// Settings from the JSON of the settings file
+    pub fn parse(json: &[u8]) -> Result<Self, NetworkError> {
+        Ok(serde_json::from_slice(json)?)
+    }
+
+    /// Time between checks of one subscription; at least an hour
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_hours.max(1).saturating_mul(60 * 60))
+    }
+}
+
+/// A list the user subscribed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Installed as `<name>.txt`; ASCII letters, digits, `-` and `_` only
+    pub name: String,
+    pub url: String,
+    /// Where the detached signature is; `<url>.sig` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_url: Option<String>,
+    /// Base64 Ed25519 public key the list is signed with
+    pub public_key: String,
+}
+
+impl Subscription {
+    /// Check the name, the URLs and the key before anything is fetched
+    pub fn validate(&self) -> Result<(), NetworkError> {
+        let valid_name = !self.name.is_empty()
+            && self.name.len() <= 64
+            && self
+                .name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid_name {
+            return Err(NetworkError::ResourceError(format!(
+                "Invalid filter list name '{}'",
+                self.name
+            )));
+        }
+        self.list_url()?;
+        self.signature_url()?;
+        self.public_key()?;
+        Ok(())
+    }
+
+    /// Where the list is fetched from
+    pub fn list_url(&self) -> Result<Url, NetworkError> {
+        fetch_url(&self.url)
+    }
+
+    /// Where the list's signature is fetched from
+    pub fn signature_url(&self) -> Result<Url, NetworkError> {
+        match &self.signature_url {
+            Some(url) => fetch_url(url),
+            None => {
+                let mut url = self.list_url()?;
+                url.set_path(&format!("{}.sig", url.path()));
+                Ok(url)
+            }
+        }
+    }
+
+    fn public_key(&self) -> Result<Vec<u8>, NetworkError> {
+        general_purpose::STANDARD
+            .decode(self.public_key.trim())
+            .ok()
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| {
+                NetworkError::SignatureError(format!(
+                    "Public key of filter list '{}' is not a base64 Ed25519 key",
+                    self.name
+                ))
+            })
+    }
+
+    /// Check the detached `signature` (base64) over `list`
+    pub fn verify(&self, list: &[u8], signature: &[u8]) -> Result<(), NetworkError> {
+        let invalid = || {
+            NetworkError::SignatureError(format!(
+                "Signature of filter list '{}' does not verify",
+                self.name
+            ))
+        };
+        if signature.len() > MAX_SIGNATURE_BYTES {
+            return Err(invalid());
+        }
+        let signature = general_purpose::STANDARD
+            .decode(String::from_utf8_lossy(signature).trim())
+            .map_err(|_| invalid())?;
+        UnparsedPublicKey::new(&ED25519, self.public_key()?)
+            .verify(list, &signature)
+            .map_err(|_| invalid())
+    }
+}
+
+/// An HTTPS URL without credentials, canonicalized with tracking parameters
+/// and the fragment dropped
+fn fetch_url(url: &str) -> Result<Url, NetworkError> {
+    let mut url = url_canon::canonicalize_str(url)?;
+    if url.scheme() != "https" {
+        return Err(NetworkError::HttpsEnforcementError(format!(
+            "Filter lists are only fetched over HTTPS: {}",
+            url
+        )));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(NetworkError::PrivacyViolationError(
+            "Filter list URLs must not carry credentials".to_string(),
+        ));
+    }
+    url.set_fragment(None);
+    Ok(url)
+}
+
+/// What an update of one subscription did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// A new version was verified and installed
+    Installed { version: u64 },
+    /// The fetched list is the installed one
+    Unchanged,
+}
+
+/// Fetches, verifies, installs and rolls back subscribed lists
+#[derive(Debug, Clone)]
+pub struct FilterUpdater {
+    settings: UpdateSettings,
+    lists_dir: PathBuf,
+    versions_dir: PathBuf,
+    proxy: Option<SocksProxy>,
+}
+
+impl FilterUpdater {
+    /// An updater installing into `lists_dir` and keeping versions under
+    /// `versions_dir`
+    pub fn new(
+        settings: UpdateSettings,
+        lists_dir: impl Into<PathBuf>,
+        versions_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            settings,
+            lists_dir: lists_dir.into(),
+            versions_dir: versions_dir.into(),
+            proxy: None,
+        }
+    }
+
+    /// Fetch through a SOCKS proxy, as page loads do
+    pub fn with_proxy(mut self, proxy: Option<SocksProxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn settings(&self) -> &UpdateSettings {
+        &self.settings
+    }
+
+    /// Update every subscription whose interval has passed; returns how
+    /// many new versions were installed. Failures are logged and leave the
+    /// installed list in place.
+    pub async fn update_due(&self) -> usize {
+        if !self.settings.enabled {
+            return 0;
+        }
+        let mut installed = 0;
+        for subscription in &self.settings.subscriptions {
+            if !self.is_due(subscription, SystemTime::now()) {
+                continue;
+            }
+            match self.update(subscription).await {
+                Ok(UpdateOutcome::Installed { version }) => {
+                    log::info!(
+                        "🧩 Installed filter list '{}' version {}",
+                        subscription.name,
+                        version
+                    );
+                    installed += 1;
+                }
+                Ok(UpdateOutcome::Unchanged) => {}
+                Err(e) => log::warn!(
+                    "Failed to update filter list '{}': {}",
+                    subscription.name,
+                    e
+                ),
+            }
+        }
+        installed
+    }
+
+    /// Whether the subscription was last checked an interval before `now`
+    pub fn is_due(&self, subscription: &Subscription, now: SystemTime) -> bool {
+        let checked = std::fs::read_to_string(self.version_dir(subscription).join(CHECKED_FILE))
+            .ok()
+            .and_then(|secs| secs.trim().parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        match checked {
+            Some(checked) => now
+                .duration_since(checked)
+                .is_ok_and(|elapsed| elapsed >= self.settings.interval()),
+            None => true,
+        }
+    }
+
+    /// Fetch, verify and install the subscription's list now
+    pub async fn update(&self, subscription: &Subscription) -> Result<UpdateOutcome, NetworkError> {
+        subscription.validate()?;
+        let list = self.fetch(&subscription.list_url()?).await?;
+        let signature = self.fetch(&subscription.signature_url()?).await?;
+        let outcome = self.apply(subscription, &list, &signature);
+        // A failed check is retried at the next interval, not every tick
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let dir = self.version_dir(subscription);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(CHECKED_FILE), now.to_string())?;
+        outcome
+    }
+
+    /// GET `url` without cookies, session resumption or extra headers
+    async fn fetch(&self, url: &Url) -> Result<Vec<u8>, NetworkError> {
+        let response = match &self.proxy {
+            Some(proxy) => {
+                crate::http::fetch_via_proxy(url, &[], proxy, Some(PROXY_ISOLATION)).await?
+            }
+            None => crate::http::fetch(url, &[]).await?,
+        };
+        if !(200..300).contains(&response.status) {
+            return Err(NetworkError::HttpStatus(response.status));
+        }
+        Ok(response.body)
+    }
+
+    /// Verify a fetched list and its signature, keep it as a new version
+    /// and install it
+    pub fn apply(
+        &self,
+        subscription: &Subscription,
+        list: &[u8],
+        signature: &[u8],
+    ) -> Result<UpdateOutcome, NetworkError> {
+        subscription.verify(list, signature)?;
+
+        let installed = self.installed_path(subscription);
+        let current = std::fs::read(&installed).ok();
+        if current.as_deref() == Some(list) {
+            return Ok(UpdateOutcome::Unchanged);
+        }
+        if let Some(current) = &current {
+            let version = |list: &[u8]| {
+                CompiledFilterList::compile(&String::from_utf8_lossy(list))
+                    .version()
+                    .and_then(|version| version.parse::<u64>().ok())
+            };
+            if let (Some(fetched), Some(installed)) = (version(list), version(current)) {
+                if fetched < installed {
+                    return Err(NetworkError::SignatureError(format!(
+                        "Filter list '{}' version {} is older than the installed {}",
+                        subscription.name, fetched, installed
+                    )));
+                }
+            }
+        }
+
+        let dir = self.version_dir(subscription);
+        std::fs::create_dir_all(&dir)?;
+        let version = self
+            .versions(subscription)
+            .last()
+            .map_or(1, |last| last + 1);
+        write_atomic(&dir.join(version_file(version, LIST_EXTENSION)), list)?;
+        write_atomic(&dir.join(version_file(version, "sig")), signature)?;
+        self.install(subscription, list)?;
+        self.prune(subscription)?;
+        Ok(UpdateOutcome::Installed { version })
+    }
+
+    /// Kept versions of the subscription's list, oldest first
+    pub fn versions(&self, subscription: &Subscription) -> Vec<u64> {
+        let Ok(entries) = std::fs::read_dir(self.version_dir(subscription)) else {
+            return Vec::new();
+        };
+        let mut versions: Vec<u64> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != LIST_EXTENSION {
+                    return None;
+                }
+                path.file_stem()?.to_str()?.parse().ok()
+            })
+            .collect();
+        versions.sort_unstable();
+        versions
+    }
+
+    /// Drop the newest version and reinstall the one before it, checking
+    /// its signature again; returns the version now installed
+    pub fn rollback(&self, subscription: &Subscription) -> Result<u64, NetworkError> {
+        let versions = self.versions(subscription);
+        let [.., previous, newest] = versions[..] else {
+            return Err(NetworkError::ResourceError(format!(
+                "No earlier version of filter list '{}' to roll back to",
+                subscription.name
+            )));
+        };
+        let dir = self.version_dir(subscription);
+        let list = std::fs::read(dir.join(version_file(previous, LIST_EXTENSION)))?;
+        let signature = std::fs::read(dir.join(version_file(previous, "sig")))?;
+        subscription.verify(&list, &signature)?;
+
+        self.install(subscription, &list)?;
+        for extension in [LIST_EXTENSION, "sig"] {
+            std::fs::remove_file(dir.join(version_file(newest, extension)))?;
+        }
+        log::info!(
+            "🧩 Rolled filter list '{}' back to version {}",
+            subscription.name,
+            previous
+        );
+        Ok(previous)
+    }
+
+    fn install(&self, subscription: &Subscription, list: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.lists_dir)?;
+        write_atomic(&self.installed_path(subscription), list)
+    }
+
+    /// Keep only the newest `keep_versions` versions
+    fn prune(&self, subscription: &Subscription) -> std::io::Result<()> {
+        let versions = self.versions(subscription);
+        let excess = versions
+            .len()
+            .saturating_sub(self.settings.keep_versions.max(1));
+        let dir = self.version_dir(subscription);
+        for version in &versions[..excess] {
+            for extension in [LIST_EXTENSION, "sig"] {
+                std::fs::remove_file(dir.join(version_file(*version, extension)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn installed_path(&self, subscription: &Subscription) -> PathBuf {
+        self.lists_dir
+            .join(format!("{}.{}", subscription.name, LIST_EXTENSION))
+    }
+
+    fn version_dir(&self, subscription: &Subscription) -> PathBuf {
+        self.versions_dir.join(&subscription.name)
+    }
+}
+
+fn version_file(version: u64, extension: &str) -> String {
+    format!("{version:010}.{extension}")
+}
+
+/// Readers never see a partly written file
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let partial = path.with_extension("tmp");
+    std::fs::write(&partial, bytes)?;
+    std::fs::rename(&partial, path)
+}
+
+/// Where update settings live: `$CITADEL_FILTER_UPDATES_FILE`, otherwise
+/// `citadel/filter-updates.json` under the XDG config directory
+pub fn default_settings_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(FILTER_UPDATES_FILE_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("citadel").join("filter-updates.json"))
+}
+
+/// Where verified versions are kept: `citadel/filter-versions` under the XDG
+/// data directory, next to the installed lists
+pub fn default_versions_dir() -> Option<PathBuf> {
+    let lists_dir = filter_list::default_dir()?;
+    Some(lists_dir.parent()?.join("filter-versions"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn signed(key: &Ed25519KeyPair, list: &str) -> (Vec<u8>, Vec<u8>) {
+        let signature = general_purpose::STANDARD.encode(key.sign(list.as_bytes()));
+        (
+            list.as_bytes().to_vec(),
+            format!("{signature}\n").into_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_updates_verify_keep_versions_and_roll_back() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let subscription = Subscription {
+            name: "test-list".to_string(),
+            url: "https://lists.example/test.txt?utm_source=citadel#top".to_string(),
+            signature_url: None,
+            public_key: general_purpose::STANDARD.encode(key.public_key().as_ref()),
+        };
+        subscription.validate().unwrap();
+        assert_eq!(
+            subscription.signature_url().unwrap().as_str(),
+            "https://lists.example/test.txt.sig"
+        );
+
+        let root = std::env::temp_dir().join(format!("citadel-updates-{}", uuid::Uuid::new_v4()));
+        let settings = UpdateSettings {
+            enabled: true,
+            keep_versions: 2,
+            subscriptions: vec![subscription.clone()],
+            ..UpdateSettings::default()
+        };
+        let updater = FilterUpdater::new(settings, root.join("lists"), root.join("versions"));
+        let installed = root.join("lists").join("test-list.txt");
+
+        let (v1, sig1) = signed(&key, "! Version: 100\n||ads.example^\n");
+        let (v2, sig2) = signed(&key, "! Version: 200\n||ads.example^\n||more.test^\n");
+        let (v3, sig3) = signed(&key, "! Version: 300\n||other.test^\n");
+
+        // Tampered lists and foreign signatures are refused
+        let (forged, forged_sig) = signed(
+            &Ed25519KeyPair::from_pkcs8(
+                Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                    .unwrap()
+                    .as_ref(),
+            )
+            .unwrap(),
+            "||ads.example^\n",
+        );
+        assert!(updater.apply(&subscription, &forged, &forged_sig).is_err());
+        assert!(updater.apply(&subscription, &v2, &sig1).is_err());
+        assert!(!installed.exists());
+
+        assert_eq!(
+            updater.apply(&subscription, &v1, &sig1).unwrap(),
+            UpdateOutcome::Installed { version: 1 }
+        );
+        assert_eq!(
+            updater.apply(&subscription, &v1, &sig1).unwrap(),
+            UpdateOutcome::Unchanged
+        );
+        updater.apply(&subscription, &v2, &sig2).unwrap();
+        // An older signed list is not replayed over a newer one
+        assert!(updater.apply(&subscription, &v1, &sig1).is_err());
+        updater.apply(&subscription, &v3, &sig3).unwrap();
+        assert_eq!(updater.versions(&subscription), [2, 3]);
+        assert_eq!(std::fs::read(&installed).unwrap(), v3);
+
+        assert_eq!(updater.rollback(&subscription).unwrap(), 2);
+        assert_eq!(std::fs::read(&installed).unwrap(), v2);
+        assert_eq!(updater.versions(&subscription), [2]);
+        assert!(updater.rollback(&subscription).is_err());
+
+        assert!(updater.is_due(&subscription, SystemTime::now()));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_settings_and_subscription_checks() {
+        let settings = UpdateSettings::parse(
+            br#"{ "enabled": true, "subscriptions": [
+                { "name": "easylist", "url": "https://lists.example/easylist.txt",
+                  "public_key": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=" } ] }"#,
+        )
+        .unwrap();
+        assert!(settings.enabled);
+        assert_eq!(settings.interval(), Duration::from_secs(24 * 60 * 60));
+        assert_eq!(settings.keep_versions, 3);
+        assert!(settings.subscriptions[0].validate().is_ok());
+        assert!(!UpdateSettings::default().enabled);
+
+        let subscription = |name: &str, url: &str| Subscription {
+            name: name.to_string(),
+            url: url.to_string(),
+            ..settings.subscriptions[0].clone()
+        };
+        assert!(subscription("../escape", "https://lists.example/a.txt")
+            .validate()
+            .is_err());
+        assert!(subscription("plain", "http://lists.example/a.txt")
+            .validate()
+            .is_err());
+        assert!(subscription("creds", "https://user:pw@lists.example/a.txt")
+            .validate()
+            .is_err());
+        assert!(Subscription {
+            public_key: "short".to_string(),
+            ..settings.subscriptions[0].clone()
+        }
+        .validate()
+        .is_err());
+    }
+}
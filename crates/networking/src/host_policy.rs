@@ -0,0 +1,156 @@
+//! Per-container host policies
+//!
+//! A container can restrict which hosts its tabs reach: a "work" container
+//! that only talks to corporate domains, or a "banking" container that never
+//! loads social media. Policies are checked against the URL before anything
+//! is resolved or sent, so a blocked hostname never reaches DNS.
+//!
+//! Patterns are `*` for every host, `example.com` for one host, and
+//! `*.example.com` for a host and its subdomains. Blocks win over allows; a
+//! non-empty allow list blocks every host it does not match.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+/// Hosts a container may and may not reach
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostPolicy {
+    /// If non-empty, the only hosts allowed
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Hosts never allowed
+    #[serde(default)]
+    pub block: Vec<String>,
+}
+
+impl HostPolicy {
+    /// Policy allowing only hosts matching `patterns`
+    pub fn allow_only<S: Into<String>>(patterns: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            allow: patterns.into_iter().map(Into::into).collect(),
+            block: Vec::new(),
+        }
+    }
+
+    /// Why a host is blocked, or `None` if it is allowed
+    pub fn check(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(pattern) = self.block.iter().find(|p| host_matches(p, &host)) {
+            return Some(format!(
+                "{} is blocked by container policy ({})",
+                host, pattern
+            ));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| host_matches(p, &host)) {
+            return Some(format!("{} is not allowed by container policy", host));
+        }
+        None
+    }
+}
+
+/// Whether a host matches a policy pattern
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        }
+        None => host == pattern,
+    }
+}
+
+/// Host policies of every container, shared between loaders
+#[derive(Debug, Clone, Default)]
+pub struct ContainerPolicies {
+    policies: Arc<RwLock<HashMap<Uuid, HostPolicy>>>,
+}
+
+impl ContainerPolicies {
+    /// No policies: every container reaches every host
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a container's policy, replacing any previous one
+    pub fn set(&self, container_id: Uuid, policy: HostPolicy) {
+        if let Ok(mut policies) = self.policies.write() {
+            policies.insert(container_id, policy);
+        }
+    }
+
+    /// Remove a container's policy
+    pub fn remove(&self, container_id: Uuid) -> Option<HostPolicy> {
+        self.policies.write().ok()?.remove(&container_id)
+    }
+
+    /// A container's policy
+    pub fn get(&self, container_id: Uuid) -> Option<HostPolicy> {
+        self.policies.read().ok()?.get(&container_id).cloned()
+    }
+
+    /// Why a container may not load `url`, or `None` if it may. URLs without
+    /// a host (`data:`, `about:`) are not subject to host policies.
+    pub fn check(&self, container_id: Uuid, url: &Url) -> Option<String> {
+        let host = url.host_str()?;
+        self.policies.read().ok()?.get(&container_id)?.check(host)
+    }
+}
+
+impl From<HashMap<Uuid, HostPolicy>> for ContainerPolicies {
+    fn from(policies: HashMap<Uuid, HostPolicy>) -> Self {
+        Self {
+            policies: Arc::new(RwLock::new(policies)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_and_block_patterns() {
+        let policy = HostPolicy {
+            allow: vec!["*.corp.example".into(), "docs.vendor.test".into()],
+            block: vec!["hr.corp.example".into()],
+        };
+        assert!(policy.check("corp.example").is_none());
+        assert!(policy.check("wiki.corp.example").is_none());
+        assert!(policy.check("DOCS.vendor.test").is_none());
+        assert!(policy.check("evilcorp.example").is_some());
+        assert!(policy.check("vendor.test").is_some());
+        assert!(policy.check("hr.corp.example").unwrap().contains("blocked"));
+        assert!(HostPolicy::default().check("anything.test").is_none());
+    }
+
+    #[test]
+    fn test_policies_apply_per_container() {
+        let policies = ContainerPolicies::new();
+        let work = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        policies.set(work, HostPolicy::allow_only(["*.corp.example"]));
+
+        let news = Url::parse("https://news.test/").unwrap();
+        assert!(policies.check(work, &news).is_some());
+        assert!(policies.check(other, &news).is_none());
+        assert!(policies
+            .check(work, &Url::parse("https://mail.corp.example/").unwrap())
+            .is_none());
+        assert!(policies
+            .check(work, &Url::parse("data:text/plain,hi").unwrap())
+            .is_none());
+
+        policies.remove(work);
+        assert!(policies.check(work, &news).is_none());
+    }
+}
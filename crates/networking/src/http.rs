@@ -5,8 +5,10 @@
 //! - TLS is rustls 0.23 with bundled Mozilla roots — we never hand-roll crypto.
 //! - `Connection: close` (no keep-alive pool) keeps the state machine tiny.
 //! - Response size is bounded (DoS) and redirects are capped.
-//! - Hostnames resolve via the std resolver (`TcpStream::connect`), so the DNS
-//!   library is no longer on the page-fetch hot path.
+//! - Hostnames resolve via the std resolver, so the DNS library is no longer
+//!   on the page-fetch hot path; resolved addresses are raced happy-eyeballs
+//!   style (see [`crate::connection`]). With a SOCKS proxy the proxy resolves
+//!   instead (see [`crate::proxy`]).
 //!
 //! **Request-shape uniformity.** Every Citadel user emits the *same* browser-like
 //! request — identical header set, order, casing, and values — so the HTTP-layer
@@ -23,16 +25,24 @@
 
 use std::io::Read;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::Resumption;
 use tokio_rustls::rustls::pki_types::ServerName;
 use tokio_rustls::rustls::{ClientConfig, RootCertStore};
 use tokio_rustls::TlsConnector;
 use url::Url;
 
+use crate::connection::{self, HappyEyeballs};
 use crate::error::NetworkError;
+use crate::proxy::SocksProxy;
+use crate::request::Method;
+use crate::response::ResponseTiming;
+use crate::tls_session::{NetworkPartitionKey, TlsSessionCache};
 
 /// Maximum response body we will buffer (DoS bound). Also caps *decompressed*
 /// output so a small gzip body cannot expand into a memory-exhaustion bomb.
@@ -53,7 +63,7 @@ const MAX_REDIRECTS: u8 = 5;
 // ---------------------------------------------------------------------------
 
 /// Chrome 120 on Windows — matches the JS navigator identity exactly.
-const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 /// Chrome's default top-level navigation `Accept`.
 const ACCEPT: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7";
 /// Matches the normalized `navigator.languages` (en-US, en).
@@ -84,14 +94,23 @@ const MANAGED_HEADERS: &[&str] = &[
     "sec-ch-ua-platform",
 ];
 
+/// Header or trailer fields in wire order.
+pub type Fields = Vec<(String, String)>;
+
 /// A parsed HTTP response.
 #[derive(Debug, Clone)]
 pub struct HttpResponse {
     pub status: u16,
-    pub headers: Vec<(String, String)>,
+    pub headers: Fields,
     pub body: Vec<u8>,
+    /// Trailer fields after a chunked body.
+    pub trailers: Fields,
     /// The URL the response was ultimately served from (after redirects).
     pub final_url: String,
+    /// URLs redirected from, in order, before `final_url`.
+    pub redirect_chain: Vec<String>,
+    /// Timing of the final request (redirect hops excluded).
+    pub timing: ResponseTiming,
 }
 
 impl HttpResponse {
@@ -112,14 +131,107 @@ impl HttpResponse {
 /// Fetch a URL over HTTPS, following up to `MAX_REDIRECTS` redirects.
 ///
 /// `extra_headers` are appended to the request (Host/Connection are managed here;
-/// CRLF-bearing entries are dropped to prevent header injection).
+/// CRLF-bearing entries are dropped to prevent header injection). No TLS
+/// session is resumed or kept.
 pub async fn fetch(
     url: &Url,
     extra_headers: &[(String, String)],
+) -> Result<HttpResponse, NetworkError> {
+    let mut config = client_config();
+    config.resumption = Resumption::disabled();
+    fetch_with_config(url, extra_headers, Arc::new(config), Route::Direct).await
+}
+
+/// Like [`fetch`], but resuming TLS sessions from (and storing new tickets
+/// in) `partition` of `sessions`. Redirects stay in the same partition.
+pub async fn fetch_partitioned(
+    url: &Url,
+    extra_headers: &[(String, String)],
+    sessions: &TlsSessionCache,
+    partition: &NetworkPartitionKey,
+) -> Result<HttpResponse, NetworkError> {
+    let mut config = client_config();
+    sessions.configure(&mut config, partition, &Method::GET);
+    fetch_with_config(url, extra_headers, Arc::new(config), Route::Direct).await
+}
+
+/// Like [`fetch`], but connecting through a SOCKS proxy that resolves the
+/// hostname itself. `isolation` keeps this stream's circuit apart from those
+/// of other tokens (see [`SocksProxy::connect`]).
+pub async fn fetch_via_proxy(
+    url: &Url,
+    extra_headers: &[(String, String)],
+    proxy: &SocksProxy,
+    isolation: Option<&str>,
+) -> Result<HttpResponse, NetworkError> {
+    let mut config = client_config();
+    config.resumption = Resumption::disabled();
+    let route = Route::Socks { proxy, isolation };
+    fetch_with_config(url, extra_headers, Arc::new(config), route).await
+}
+
+/// How a request reaches the server
+#[derive(Debug, Clone, Copy)]
+enum Route<'a> {
+    /// Resolve locally and connect happy-eyeballs style
+    Direct,
+    /// Tunnel through a SOCKS proxy
+    Socks {
+        proxy: &'a SocksProxy,
+        isolation: Option<&'a str>,
+    },
+}
+
+/// rustls configuration trusting the bundled Mozilla roots (and, in builds
+/// for tests, the local test server's root)
+fn client_config() -> ClientConfig {
+    let mut root_store = RootCertStore::empty();
+    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    #[cfg(feature = "test-support")]
+    let _ = root_store.add(crate::test_server::root_certificate());
+    ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth()
+}
+
+/// A TLS connection to `host:port` trusting the same roots as the fetches,
+/// made the way `route` says. For connections that outlive one request, such
+/// as WebSockets; connecting (not what follows) is bounded by the request
+/// timeout.
+pub(crate) async fn connect_tls(
+    host: &str,
+    port: u16,
+    route: StreamRoute<'_>,
+) -> Result<TlsStream<TcpStream>, NetworkError> {
+    let (config, route) = stream_config(route);
+    let connector = TlsConnector::from(Arc::new(config));
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| NetworkError::TlsError(format!("invalid server name '{host}': {e}")))?;
+    tokio::time::timeout(REQUEST_TIMEOUT, async {
+        let tcp = match route {
+            Route::Direct => HappyEyeballs::shared().connect(host, port).await?,
+            // The proxy resolves the name; nothing is looked up here
+            Route::Socks { proxy, isolation } => proxy.connect(host, port, isolation).await?,
+        };
+        connector
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| NetworkError::TlsError(format!("handshake with {host} failed: {e}")))
+    })
+    .await
+    .map_err(|_| NetworkError::TimeoutError(REQUEST_TIMEOUT))?
+}
+
+async fn fetch_with_config(
+    url: &Url,
+    extra_headers: &[(String, String)],
+    config: Arc<ClientConfig>,
+    route: Route<'_>,
 ) -> Result<HttpResponse, NetworkError> {
     let mut current = url.clone();
+    let mut redirect_chain = Vec::new();
     for _ in 0..=MAX_REDIRECTS {
-        let resp = request_once(&current, extra_headers).await?;
+        let mut resp = request_once(&current, extra_headers, config.clone(), route).await?;
         if (300..400).contains(&resp.status) && resp.status != 304 {
             if let Some(location) = resp.header("location") {
                 let next = current.join(location).map_err(NetworkError::UrlError)?;
@@ -128,10 +240,11 @@ pub async fn fetch(
                         "redirect to non-HTTPS URL: {next}"
                     )));
                 }
-                current = next;
+                redirect_chain.push(std::mem::replace(&mut current, next).into());
                 continue;
             }
         }
+        resp.redirect_chain = redirect_chain;
         return Ok(resp);
     }
     Err(NetworkError::ConnectionError("too many redirects".into()))
@@ -193,10 +306,25 @@ fn build_request(target: &str, host: &str, extra_headers: &[(String, String)]) -
     request
 }
 
+/// Request target = path + query (default "/").
+pub(crate) fn request_target(url: &Url) -> String {
+    let mut target = String::from(url.path());
+    if target.is_empty() {
+        target.push('/');
+    }
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    target
+}
+
 /// Perform a single HTTPS GET (no redirect following).
 async fn request_once(
     url: &Url,
     extra_headers: &[(String, String)],
+    config: Arc<ClientConfig>,
+    route: Route<'_>,
 ) -> Result<HttpResponse, NetworkError> {
     if url.scheme() != "https" {
         return Err(NetworkError::HttpsEnforcementError(format!(
@@ -208,81 +336,503 @@ async fn request_once(
         .ok_or_else(|| NetworkError::ConnectionError("missing host".into()))?;
     let port = url.port().unwrap_or(443);
 
-    // Request target = path + query (default "/").
-    let mut target = String::from(url.path());
-    if target.is_empty() {
-        target.push('/');
-    }
-    if let Some(query) = url.query() {
-        target.push('?');
-        target.push_str(query);
-    }
+    let request = build_request(&request_target(url), host, extra_headers);
 
-    let request = build_request(&target, host, extra_headers);
-
-    let raw = tokio::time::timeout(REQUEST_TIMEOUT, async {
-        let mut root_store = RootCertStore::empty();
-        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-        let connector = TlsConnector::from(Arc::new(config));
+    let started = Instant::now();
+    let (raw, mut timing) = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        // Early data is only enabled on configs for replay-safe requests
+        let connector = TlsConnector::from(config.clone()).early_data(config.enable_early_data);
         let server_name = ServerName::try_from(host.to_string())
             .map_err(|e| NetworkError::TlsError(format!("invalid server name '{host}': {e}")))?;
 
-        let tcp = TcpStream::connect((host, port)).await?;
-        let mut tls = connector.connect(server_name, tcp).await?;
+        let (tcp, resolved) = match route {
+            Route::Direct => {
+                let addrs = connection::resolve(host, port).await?;
+                let resolved = started.elapsed();
+                (
+                    HappyEyeballs::shared().connect_addrs(host, &addrs).await?,
+                    resolved,
+                )
+            }
+            // The proxy resolves, so there is no local DNS phase
+            Route::Socks { proxy, isolation } => {
+                (proxy.connect(host, port, isolation).await?, Duration::ZERO)
+            }
+        };
+        let connected = started.elapsed();
+        // A refused certificate is a TLS failure, not a dropped connection
+        let mut tls = connector
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| NetworkError::TlsError(format!("handshake with {host} failed: {e}")))?;
+        let handshaken = started.elapsed();
         tls.write_all(request.as_bytes()).await?;
         tls.flush().await?;
 
         let mut buf = Vec::new();
+        let mut first_byte = None;
+        let mut chunk = [0u8; 16 * 1024];
+        let mut body = tls.take(MAX_RESPONSE_BYTES);
         // Many HTTPS/1.1 servers (especially with `Connection: close`) close the
         // TCP socket without sending a TLS close_notify. rustls reports that as
         // `UnexpectedEof`; for HTTP it is a normal end-of-stream, so accept the
         // bytes received rather than failing the load. Truncation is still caught
         // downstream by HTTP framing (Content-Length / chunked) at the parse layer.
-        match tls.take(MAX_RESPONSE_BYTES).read_to_end(&mut buf).await {
-            Ok(_) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {}
-            Err(e) => return Err(e.into()),
+        loop {
+            match body.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    first_byte.get_or_insert_with(|| started.elapsed());
+                    buf.extend_from_slice(chunk.get(..n).unwrap_or(&[]));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let timing = ResponseTiming {
+            dns: resolved,
+            connect: connected.saturating_sub(resolved),
+            tls: handshaken.saturating_sub(connected),
+            ttfb: first_byte.unwrap_or(handshaken).saturating_sub(handshaken),
+            total: Duration::ZERO,
+        };
+        Ok::<_, NetworkError>((buf, timing))
+    })
+    .await
+    .map_err(|_| NetworkError::TimeoutError(REQUEST_TIMEOUT))??;
+
+    let mut response = parse_response(&raw, url.as_str())?;
+    timing.total = started.elapsed();
+    response.timing = timing;
+    Ok(response)
+}
+
+// ---------------------------------------------------------------------------
+// Streamed responses
+//
+// Downloads can be far larger than `MAX_RESPONSE_BYTES`, so their bodies are
+// handed out as they arrive instead of being buffered. The request is the
+// same uniform shape; only the read side differs.
+// ---------------------------------------------------------------------------
+
+/// Largest response head read before a streamed body
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+/// Longest chunk-size line accepted in a streamed chunked body
+const MAX_CHUNK_HEADER_BYTES: usize = 1024;
+
+/// How a streamed request reaches the server, as the buffered fetches do
+#[derive(Debug, Clone, Copy)]
+pub enum StreamRoute<'a> {
+    /// Resolve locally; no TLS session is resumed or kept, as in [`fetch`]
+    Direct,
+    /// Resume TLS sessions from a partition, as in [`fetch_partitioned`]
+    Partitioned {
+        sessions: &'a TlsSessionCache,
+        partition: &'a NetworkPartitionKey,
+    },
+    /// Tunnel through a SOCKS proxy, as in [`fetch_via_proxy`]
+    Proxy {
+        proxy: &'a SocksProxy,
+        isolation: Option<&'a str>,
+    },
+}
+
+/// The client configuration and connection route of a [`StreamRoute`]
+fn stream_config(route: StreamRoute<'_>) -> (ClientConfig, Route<'_>) {
+    let mut config = client_config();
+    let route = match route {
+        StreamRoute::Direct => {
+            config.resumption = Resumption::disabled();
+            Route::Direct
+        }
+        StreamRoute::Partitioned {
+            sessions,
+            partition,
+        } => {
+            sessions.configure(&mut config, partition, &Method::GET);
+            Route::Direct
+        }
+        StreamRoute::Proxy { proxy, isolation } => {
+            config.resumption = Resumption::disabled();
+            Route::Socks { proxy, isolation }
+        }
+    };
+    (config, route)
+}
+
+/// Fetch a URL over HTTPS like [`fetch`], but hand out the body as it
+/// arrives instead of buffering it, so it is not held to the buffered size
+/// bound. Redirects are followed as there.
+pub async fn fetch_streaming(
+    url: &Url,
+    extra_headers: &[(String, String)],
+    route: StreamRoute<'_>,
+) -> Result<StreamingResponse, NetworkError> {
+    let (config, route) = stream_config(route);
+    let config = Arc::new(config);
+
+    let mut current = url.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = open_stream(&current, extra_headers, config.clone(), route).await?;
+        if (300..400).contains(&response.status) && response.status != 304 {
+            if let Some(location) = response.header("location") {
+                let next = current.join(location).map_err(NetworkError::UrlError)?;
+                if next.scheme() != "https" {
+                    return Err(NetworkError::HttpsEnforcementError(format!(
+                        "redirect to non-HTTPS URL: {next}"
+                    )));
+                }
+                current = next;
+                continue;
+            }
         }
-        Ok::<Vec<u8>, NetworkError>(buf)
+        return Ok(response);
+    }
+    Err(NetworkError::ConnectionError("too many redirects".into()))
+}
+
+/// Send a single HTTPS GET and read the response head.
+async fn open_stream(
+    url: &Url,
+    extra_headers: &[(String, String)],
+    config: Arc<ClientConfig>,
+    route: Route<'_>,
+) -> Result<StreamingResponse, NetworkError> {
+    if url.scheme() != "https" {
+        return Err(NetworkError::HttpsEnforcementError(format!(
+            "non-HTTPS URL: {url}"
+        )));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| NetworkError::ConnectionError("missing host".into()))?;
+    let port = url.port().unwrap_or(443);
+    let request = build_request(&request_target(url), host, extra_headers);
+
+    let tls = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        let connector = TlsConnector::from(config.clone()).early_data(config.enable_early_data);
+        let server_name = ServerName::try_from(host.to_string())
+            .map_err(|e| NetworkError::TlsError(format!("invalid server name '{host}': {e}")))?;
+        let tcp = match route {
+            Route::Direct => {
+                let addrs = connection::resolve(host, port).await?;
+                HappyEyeballs::shared().connect_addrs(host, &addrs).await?
+            }
+            Route::Socks { proxy, isolation } => proxy.connect(host, port, isolation).await?,
+        };
+        let mut tls = connector
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| NetworkError::TlsError(format!("handshake with {host} failed: {e}")))?;
+        tls.write_all(request.as_bytes()).await?;
+        tls.flush().await?;
+        Ok::<_, NetworkError>(tls)
     })
     .await
     .map_err(|_| NetworkError::TimeoutError(REQUEST_TIMEOUT))??;
 
-    parse_response(&raw, url.as_str())
+    StreamingResponse::read_head(Box::new(tls), url.as_str()).await
+}
+
+/// A response whose body is read as it arrives
+pub struct StreamingResponse {
+    pub status: u16,
+    pub headers: Fields,
+    /// The URL the response is served from (after redirects).
+    pub final_url: String,
+    stream: Box<dyn AsyncRead + Send + Unpin>,
+    /// Bytes read from the connection and not handed out yet
+    pending: Vec<u8>,
+    framing: Framing,
+    decoder: Option<Decoder>,
+    content_length: Option<u64>,
+    done: bool,
+}
+
+impl std::fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .field("final_url", &self.final_url)
+            .finish()
+    }
+}
+
+/// How the end of a streamed body is found
+#[derive(Debug, Clone, Copy)]
+enum Framing {
+    /// This many bytes of a `Content-Length` body remain
+    Length(u64),
+    /// Chunked: bytes left of the current chunk, 0 at a chunk-size line
+    Chunked(u64),
+    /// The body runs until the server closes the connection
+    Close,
+}
+
+/// Payload decoding applied as a streamed body arrives
+enum Decoder {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibDecoder<Vec<u8>>),
+}
+
+impl Decoder {
+    /// Decode the next part of the payload
+    fn decode(&mut self, bytes: &[u8]) -> Result<Vec<u8>, NetworkError> {
+        use std::io::Write;
+        let (written, output) = match self {
+            Decoder::Gzip(decoder) => (decoder.write_all(bytes), decoder.get_mut()),
+            Decoder::Deflate(decoder) => (decoder.write_all(bytes), decoder.get_mut()),
+        };
+        let output = std::mem::take(output);
+        written.map_err(|e| NetworkError::ResourceError(format!("body decode failed: {e}")))?;
+        Ok(output)
+    }
+
+    /// What remains once the payload has all been seen
+    fn finish(&mut self) -> Result<Vec<u8>, NetworkError> {
+        let finished = match self {
+            Decoder::Gzip(decoder) => decoder.try_finish(),
+            Decoder::Deflate(decoder) => decoder.try_finish(),
+        };
+        finished.map_err(|e| NetworkError::ResourceError(format!("body decode failed: {e}")))?;
+        self.decode(&[])
+    }
+}
+
+impl StreamingResponse {
+    /// Read the response head from `stream`; the body is left to [`Self::chunk`].
+    async fn read_head(
+        mut stream: Box<dyn AsyncRead + Send + Unpin>,
+        final_url: &str,
+    ) -> Result<Self, NetworkError> {
+        let mut pending = Vec::new();
+        let mut buf = [0u8; 4096];
+        let sep = loop {
+            if let Some(sep) = find_subslice(&pending, b"\r\n\r\n") {
+                break sep;
+            }
+            if pending.len() > MAX_HEAD_BYTES {
+                return Err(NetworkError::ResourceError(
+                    "response head too large".into(),
+                ));
+            }
+            let n = read_timed(&mut stream, &mut buf).await?;
+            if n == 0 {
+                return Err(NetworkError::ResourceError(
+                    "malformed response (no header end)".into(),
+                ));
+            }
+            pending.extend_from_slice(buf.get(..n).unwrap_or(&[]));
+        };
+        let (status, headers) = parse_head(pending.get(..sep).unwrap_or(&[]))?;
+        pending.drain(..sep.saturating_add(4));
+
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.trim().to_ascii_lowercase())
+        };
+        let declared_length = header("content-length").and_then(|v| v.parse::<u64>().ok());
+        let framing = if status == 204 || status == 304 {
+            Framing::Length(0)
+        } else if header("transfer-encoding").is_some_and(|v| v.contains("chunked")) {
+            Framing::Chunked(0)
+        } else if let Some(length) = declared_length {
+            Framing::Length(length)
+        } else {
+            Framing::Close
+        };
+        let decoder = match header("content-encoding").as_deref() {
+            Some("gzip") | Some("x-gzip") => {
+                Some(Decoder::Gzip(flate2::write::GzDecoder::new(Vec::new())))
+            }
+            Some("deflate") => Some(Decoder::Deflate(
+                flate2::write::ZlibDecoder::new(Vec::new()),
+            )),
+            _ => None,
+        };
+        let content_length = match (framing, &decoder) {
+            (Framing::Length(length), None) => Some(length),
+            _ => None,
+        };
+
+        Ok(Self {
+            status,
+            headers,
+            final_url: final_url.to_string(),
+            stream,
+            pending,
+            framing,
+            decoder,
+            content_length,
+            done: false,
+        })
+    }
+
+    /// Case-insensitive header lookup.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Length of the body [`Self::chunk`] hands out, when the server gave it
+    /// and no payload decoding changes it
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Whether the payload is decoded on the way, so its bytes are not the
+    /// ones a `Range` request counts
+    pub fn is_encoded(&self) -> bool {
+        self.decoder.is_some()
+    }
+
+    /// The next part of the body, decoded; `None` once it has all been read.
+    /// A body cut short by the connection closing is an error.
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>, NetworkError> {
+        while !self.done {
+            let framed = self.framed_chunk().await?;
+            let decoded = match (&mut self.decoder, framed) {
+                (None, framed) => {
+                    self.done = framed.is_none();
+                    return Ok(framed);
+                }
+                (Some(decoder), Some(framed)) => decoder.decode(&framed)?,
+                (Some(decoder), None) => {
+                    self.done = true;
+                    decoder.finish()?
+                }
+            };
+            if !decoded.is_empty() {
+                return Ok(Some(decoded));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The body as a stream of [`Self::chunk`]s
+    pub fn into_stream(self) -> impl Stream<Item = Result<Vec<u8>, NetworkError>> + Send {
+        futures::stream::try_unfold(self, |mut response| async move {
+            Ok(response.chunk().await?.map(|chunk| (chunk, response)))
+        })
+    }
+
+    /// The next part of the body as framed on the wire
+    async fn framed_chunk(&mut self) -> Result<Option<Vec<u8>>, NetworkError> {
+        loop {
+            match self.framing {
+                Framing::Length(0) => return Ok(None),
+                Framing::Length(remaining) => {
+                    let chunk = self.take_pending(remaining).await?;
+                    self.framing = Framing::Length(remaining - chunk.len() as u64);
+                    return Ok(Some(chunk));
+                }
+                Framing::Chunked(0) => {
+                    // The CRLF closing the previous chunk comes first
+                    let size = loop {
+                        match find_subslice(&self.pending, b"\r\n") {
+                            Some(0) => {
+                                self.pending.drain(..2);
+                            }
+                            Some(end) => {
+                                let line = self.pending.get(..end).unwrap_or(&[]);
+                                let size = parse_chunk_size(line)?;
+                                self.pending.drain(..end.saturating_add(2));
+                                break size;
+                            }
+                            None if self.pending.len() > MAX_CHUNK_HEADER_BYTES => {
+                                return Err(NetworkError::ResourceError(
+                                    "malformed chunk header".into(),
+                                ));
+                            }
+                            None => {
+                                if self.read_more().await? == 0 {
+                                    return Err(truncated_body());
+                                }
+                            }
+                        }
+                    };
+                    // Trailer fields after the last chunk are not read
+                    self.framing = if size == 0 {
+                        Framing::Length(0)
+                    } else {
+                        Framing::Chunked(size)
+                    };
+                }
+                Framing::Chunked(remaining) => {
+                    let chunk = self.take_pending(remaining).await?;
+                    self.framing = Framing::Chunked(remaining - chunk.len() as u64);
+                    return Ok(Some(chunk));
+                }
+                Framing::Close => {
+                    if self.pending.is_empty() && self.read_more().await? == 0 {
+                        return Ok(None);
+                    }
+                    return Ok(Some(std::mem::take(&mut self.pending)));
+                }
+            }
+        }
+    }
+
+    /// Up to `limit` bytes, reading more first if none are pending
+    async fn take_pending(&mut self, limit: u64) -> Result<Vec<u8>, NetworkError> {
+        if self.pending.is_empty() && self.read_more().await? == 0 {
+            return Err(truncated_body());
+        }
+        let n = usize::try_from(limit)
+            .unwrap_or(usize::MAX)
+            .min(self.pending.len());
+        Ok(self.pending.drain(..n).collect())
+    }
+
+    /// Read what the connection has next into `pending`; 0 at its end
+    async fn read_more(&mut self) -> Result<usize, NetworkError> {
+        let mut buf = [0u8; 16 * 1024];
+        let n = read_timed(&mut self.stream, &mut buf).await?;
+        self.pending.extend_from_slice(buf.get(..n).unwrap_or(&[]));
+        Ok(n)
+    }
+}
+
+/// One read, bounded by the request timeout so a stalled server cannot hold
+/// a stream open forever. A close without TLS close_notify is an end of
+/// stream, as in [`request_once`].
+async fn read_timed(
+    stream: &mut (dyn AsyncRead + Send + Unpin),
+    buf: &mut [u8],
+) -> Result<usize, NetworkError> {
+    match tokio::time::timeout(REQUEST_TIMEOUT, stream.read(buf)).await {
+        Err(_) => Err(NetworkError::TimeoutError(REQUEST_TIMEOUT)),
+        Ok(Ok(n)) => Ok(n),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(0),
+        Ok(Err(e)) => Err(e.into()),
+    }
+}
+
+fn truncated_body() -> NetworkError {
+    NetworkError::ResourceError("response body cut short".into())
 }
 
 /// Parse a raw HTTP/1.1 response into status, headers, and (de-chunked) body.
 fn parse_response(raw: &[u8], final_url: &str) -> Result<HttpResponse, NetworkError> {
     let sep = find_subslice(raw, b"\r\n\r\n")
         .ok_or_else(|| NetworkError::ResourceError("malformed response (no header end)".into()))?;
-    let head = String::from_utf8_lossy(raw.get(..sep).unwrap_or(&[]));
+    let (status, headers) = parse_head(raw.get(..sep).unwrap_or(&[]))?;
     let body_start = sep.saturating_add(4);
 
-    let mut lines = head.split("\r\n");
-    let status_line = lines
-        .next()
-        .ok_or_else(|| NetworkError::ResourceError("empty response".into()))?;
-    let status = parse_status(status_line)?;
-
-    let mut headers = Vec::new();
-    for line in lines {
-        if let Some((k, v)) = line.split_once(':') {
-            headers.push((k.trim().to_string(), v.trim().to_string()));
-        }
-    }
-
     let raw_body = raw.get(body_start..).unwrap_or(&[]);
     let is_chunked = headers.iter().any(|(k, v)| {
         k.eq_ignore_ascii_case("transfer-encoding") && v.to_ascii_lowercase().contains("chunked")
     });
     // Transport framing (chunked) first, then payload encoding (gzip/deflate).
-    let framed = if is_chunked {
+    let (framed, trailers) = if is_chunked {
         dechunk(raw_body)?
     } else {
-        raw_body.to_vec()
+        (raw_body.to_vec(), Vec::new())
     };
     let content_encoding = headers
         .iter()
@@ -299,10 +849,31 @@ fn parse_response(raw: &[u8], final_url: &str) -> Result<HttpResponse, NetworkEr
         status,
         headers,
         body,
+        trailers,
         final_url: final_url.to_string(),
+        redirect_chain: Vec::new(),
+        timing: ResponseTiming::default(),
     })
 }
 
+/// Parse the status line and header fields of a response head.
+pub(crate) fn parse_head(head: &[u8]) -> Result<(u16, Fields), NetworkError> {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+    let status_line = lines
+        .next()
+        .ok_or_else(|| NetworkError::ResourceError("empty response".into()))?;
+    let status = parse_status(status_line)?;
+
+    let mut headers = Vec::new();
+    for line in lines {
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    Ok((status, headers))
+}
+
 /// Parse the numeric status code out of a status line like `HTTP/1.1 200 OK`.
 fn parse_status(line: &str) -> Result<u16, NetworkError> {
     line.split_whitespace()
@@ -311,21 +882,25 @@ fn parse_status(line: &str) -> Result<u16, NetworkError> {
         .ok_or_else(|| NetworkError::ResourceError(format!("bad status line: {line}")))
 }
 
-/// Decode a chunked transfer-encoded body.
-fn dechunk(mut data: &[u8]) -> Result<Vec<u8>, NetworkError> {
+/// Decode a chunked transfer-encoded body into the body and its trailer fields.
+fn dechunk(mut data: &[u8]) -> Result<(Vec<u8>, Fields), NetworkError> {
     let mut out = Vec::new();
     loop {
         let nl = find_subslice(data, b"\r\n")
             .ok_or_else(|| NetworkError::ResourceError("malformed chunk header".into()))?;
-        let size_field = std::str::from_utf8(data.get(..nl).unwrap_or(&[]))
-            .map_err(|_| NetworkError::ResourceError("non-utf8 chunk size".into()))?;
-        // A chunk size may carry extensions after ';'.
-        let size_hex = size_field.split(';').next().unwrap_or("").trim();
-        let size = usize::from_str_radix(size_hex, 16)
+        let size = parse_chunk_size(data.get(..nl).unwrap_or(&[]))?;
+        let size = usize::try_from(size)
             .map_err(|_| NetworkError::ResourceError("bad chunk size".into()))?;
         data = data.get(nl.saturating_add(2)..).unwrap_or(&[]);
         if size == 0 {
-            break;
+            // Trailer fields follow the last chunk, up to an empty line.
+            let trailers = String::from_utf8_lossy(data)
+                .split("\r\n")
+                .take_while(|line| !line.is_empty())
+                .filter_map(|line| line.split_once(':'))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .collect();
+            return Ok((out, trailers));
         }
         let chunk = data
             .get(..size)
@@ -337,7 +912,15 @@ fn dechunk(mut data: &[u8]) -> Result<Vec<u8>, NetworkError> {
             return Err(NetworkError::ResourceError("response too large".into()));
         }
     }
-    Ok(out)
+}
+
+/// Parse a chunk-size line; a chunk size may carry extensions after ';'.
+fn parse_chunk_size(line: &[u8]) -> Result<u64, NetworkError> {
+    let size_field = std::str::from_utf8(line)
+        .map_err(|_| NetworkError::ResourceError("non-utf8 chunk size".into()))?;
+    let size_hex = size_field.split(';').next().unwrap_or("").trim();
+    u64::from_str_radix(size_hex, 16)
+        .map_err(|_| NetworkError::ResourceError("bad chunk size".into()))
 }
 
 /// Inflate a gzip body, bounding output to `MAX_RESPONSE_BYTES` (bomb guard).
@@ -388,6 +971,18 @@ mod tests {
         assert_eq!(r.status, 200);
         assert_eq!(r.header("content-type"), Some("text/html"));
         assert_eq!(r.body_text(), "Hello World");
+        assert!(r.trailers.is_empty());
+    }
+
+    #[test]
+    fn parses_chunked_trailers() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\nServer-Timing: db;dur=53\r\n\r\n";
+        let r = parse_response(raw, "https://x.example/").unwrap();
+        assert_eq!(r.body_text(), "hi");
+        assert_eq!(
+            r.trailers,
+            vec![("Server-Timing".to_string(), "db;dur=53".to_string())]
+        );
     }
 
     #[test]
@@ -464,4 +1059,50 @@ mod tests {
         let r = parse_response(&raw, "https://x/").unwrap();
         assert_eq!(r.body_text(), "Hello World");
     }
+
+    #[tokio::test]
+    async fn streams_chunked_and_encoded_bodies() {
+        async fn body(raw: Vec<u8>) -> (StreamingResponse, Vec<u8>) {
+            let reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(std::io::Cursor::new(raw));
+            let mut response = StreamingResponse::read_head(reader, "https://x/")
+                .await
+                .unwrap();
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.unwrap() {
+                body.extend_from_slice(&chunk);
+            }
+            (response, body)
+        }
+
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nHello\r\n6\r\n World\r\n0\r\n\r\n";
+        let (response, streamed) = body(raw.to_vec()).await;
+        assert_eq!(streamed, b"Hello World");
+        assert_eq!(response.content_length(), None);
+
+        let raw = b"HTTP/1.1 206 Partial Content\r\nContent-Length: 3\r\n\r\nabcdef";
+        let (response, streamed) = body(raw.to_vec()).await;
+        assert_eq!(streamed, b"abc");
+        assert_eq!(response.content_length(), Some(3));
+
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(&[7u8; 100_000]).unwrap();
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        raw.extend_from_slice(&enc.finish().unwrap());
+        let (response, streamed) = body(raw).await;
+        assert!(response.is_encoded());
+        assert_eq!(streamed, vec![7u8; 100_000]);
+
+        // A body cut short before its declared length fails
+        let reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(std::io::Cursor::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc".to_vec(),
+        ));
+        let mut response = StreamingResponse::read_head(reader, "https://x/")
+            .await
+            .unwrap();
+        assert_eq!(response.chunk().await.unwrap().unwrap(), b"abc");
+        assert!(response.chunk().await.is_err());
+    }
 }
@@ -0,0 +1,220 @@
+//! Internationalized domain name display policy
+//!
+//! URLs travel on the wire in ASCII (`xn--` punycode) form. For the address bar
+//! a host is shown decoded only when every label is free of homograph tricks:
+//! mixed scripts, whole-script Latin lookalikes (`аррӏе.com` spelled in
+//! Cyrillic), Latin letters that mimic ASCII ones, and invisible or
+//! dot/slash-like characters. Anything suspicious stays in punycode so the user
+//! sees that it is not the domain it looks like.
+
+use unicode_script::{Script, UnicodeScript};
+use url::{Position, Url};
+
+/// Cyrillic letters that render like Latin letters
+const CYRILLIC_LATIN_LOOKALIKES: &str = "аысԁеԍһіюјӏорԗԛѕԝхуъьҽпгѵѡк";
+
+/// Greek letters that render like Latin letters
+const GREEK_LATIN_LOOKALIKES: &str = "αικνορτυχϲϳ";
+
+/// Non-ASCII Latin letters that render like plain ASCII letters
+const LATIN_ASCII_LOOKALIKES: &str = "ıȷɑɡɩɪɾʀʟƅɒǀᴄᴅᴇᴋᴍᴏᴘᴛᴜᴠᴡᴢ";
+
+/// Characters that are invisible or look like URL punctuation (`.`, `/`, `:`)
+const DECEPTIVE_CHARS: &[char] = &[
+    '\u{00AD}', '\u{01C0}', '\u{01C3}', '\u{02D0}', '\u{0589}', '\u{05C3}', '\u{05F4}', '\u{0609}',
+    '\u{06D4}', '\u{0701}', '\u{1735}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{200E}', '\u{200F}',
+    '\u{2027}', '\u{2044}', '\u{2215}', '\u{2236}', '\u{2E30}', '\u{3033}', '\u{3034}', '\u{3035}',
+    '\u{30FB}', '\u{FE0F}', '\u{FEFF}',
+];
+
+/// Host as it should be shown to the user: decoded Unicode when every label
+/// passes [`is_label_safe`], otherwise the ASCII (`xn--`) form unchanged.
+pub fn display_host(host: &str) -> String {
+    if !host
+        .split('.')
+        .any(|label| label.len() >= 4 && label[..4].eq_ignore_ascii_case("xn--"))
+    {
+        return host.to_string();
+    }
+
+    let (unicode, result) = idna::domain_to_unicode(host);
+    if result.is_err() {
+        return host.to_string();
+    }
+
+    let labels: Vec<&str> = unicode.split('.').collect();
+    let tld_script = labels
+        .iter()
+        .rev()
+        .find(|label| !label.is_empty())
+        .and_then(|label| label_scripts(label).into_iter().next());
+
+    if labels
+        .iter()
+        .all(|label| label.is_ascii() || is_label_safe(label, tld_script))
+    {
+        unicode
+    } else {
+        log::debug!("🔤 Keeping punycode for suspicious host {}", host);
+        host.to_string()
+    }
+}
+
+/// URL string for the address bar, with the host rendered per [`display_host`].
+/// Input that does not parse as a URL is returned untouched.
+pub fn display_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => display_parsed_url(&parsed),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// [`display_url`] for an already parsed URL
+pub fn display_parsed_url(url: &Url) -> String {
+    match url.host_str() {
+        Some(host) => format!(
+            "{}{}{}",
+            &url[..Position::BeforeHost],
+            display_host(host),
+            &url[Position::AfterHost..]
+        ),
+        None => url.to_string(),
+    }
+}
+
+/// Whether a decoded label can be shown in Unicode without homograph risk.
+/// `tld_script` is the script of the top-level domain; whole-script lookalike
+/// labels are allowed under a TLD of the same script (e.g. `.рф`).
+pub fn is_label_safe(label: &str, tld_script: Option<Script>) -> bool {
+    if label.chars().any(|ch| DECEPTIVE_CHARS.contains(&ch)) {
+        return false;
+    }
+
+    let scripts = label_scripts(label);
+    if !is_allowed_script_mix(&scripts) {
+        return false;
+    }
+
+    if label.chars().any(|ch| LATIN_ASCII_LOOKALIKES.contains(ch)) {
+        return false;
+    }
+
+    match scripts.as_slice() {
+        [script @ (Script::Cyrillic | Script::Greek)] => {
+            let script = *script;
+            let lookalikes = if script == Script::Cyrillic {
+                CYRILLIC_LATIN_LOOKALIKES
+            } else {
+                GREEK_LATIN_LOOKALIKES
+            };
+            let whole_script_lookalike = label
+                .chars()
+                .filter(|ch| !is_neutral(*ch))
+                .all(|ch| lookalikes.contains(ch));
+            !whole_script_lookalike || tld_script == Some(script)
+        }
+        _ => true,
+    }
+}
+
+/// Distinct scripts used by a label in order of appearance, ignoring digits,
+/// hyphens and marks
+fn label_scripts(label: &str) -> Vec<Script> {
+    let mut scripts = Vec::new();
+    for script in label
+        .chars()
+        .filter(|ch| !is_neutral(*ch))
+        .map(|ch| ch.script())
+    {
+        if !matches!(script, Script::Common | Script::Inherited) && !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    scripts
+}
+
+/// Characters that do not count towards a label's script
+fn is_neutral(ch: char) -> bool {
+    ch.is_ascii_digit() || ch == '-'
+}
+
+/// Single script, or one of the mixes that are normal in East Asian names
+/// (optionally alongside ASCII Latin)
+fn is_allowed_script_mix(scripts: &[Script]) -> bool {
+    if scripts.len() <= 1 {
+        return true;
+    }
+    let allowed: &[&[Script]] = &[
+        &[
+            Script::Latin,
+            Script::Han,
+            Script::Hiragana,
+            Script::Katakana,
+        ],
+        &[Script::Latin, Script::Han, Script::Bopomofo],
+        &[Script::Latin, Script::Han, Script::Hangul],
+    ];
+    allowed
+        .iter()
+        .any(|set| scripts.iter().all(|script| set.contains(script)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whole_script_cyrillic_homographs_stay_punycode() {
+        // "аррӏе.com" and "раураӏ.com" spelled entirely in Cyrillic
+        assert_eq!(display_host("xn--80ak6aa92e.com"), "xn--80ak6aa92e.com");
+        assert_eq!(display_host("xn--80aa0cbo65f.com"), "xn--80aa0cbo65f.com");
+        assert_eq!(display_host("xn--e1argc3h.net"), "xn--e1argc3h.net");
+    }
+
+    #[test]
+    fn test_mixed_script_labels_stay_punycode() {
+        // Latin "pypal"/"pple"/"ggle" with Cyrillic "а"/"о" mixed in
+        assert_eq!(display_host("xn--pypal-4ve.com"), "xn--pypal-4ve.com");
+        assert_eq!(display_host("xn--pple-43d.com"), "xn--pple-43d.com");
+        assert_eq!(display_host("xn--ggle-55da.com"), "xn--ggle-55da.com");
+    }
+
+    #[test]
+    fn test_latin_lookalikes_stay_punycode() {
+        // Dotless "ı" in "ıbm"
+        assert_eq!(display_host("xn--bm-gpa.com"), "xn--bm-gpa.com");
+        // Greek-only lookalike label under a Latin TLD
+        assert_eq!(display_host("xn--0xaej.com"), "xn--0xaej.com");
+    }
+
+    #[test]
+    fn test_legitimate_idns_are_decoded() {
+        assert_eq!(display_host("xn--mnchen-3ya.de"), "münchen.de");
+        assert_eq!(display_host("xn--wgv71a119e.jp"), "日本語.jp");
+        assert_eq!(display_host("xn--r8jz45g.jp"), "例え.jp");
+        assert_eq!(display_host("xn--80adxhks.com"), "москва.com");
+        // Lookalike-only Cyrillic is fine under a Cyrillic TLD
+        assert_eq!(display_host("xn--e1afmkfd.xn--p1ai"), "пример.рф");
+        assert_eq!(display_host("xn--80ak6aa92e.xn--p1ai"), "аррӏе.рф");
+    }
+
+    #[test]
+    fn test_ascii_and_invalid_hosts_are_untouched() {
+        assert_eq!(display_host("example.com"), "example.com");
+        assert_eq!(display_host("xn--zz.com"), "xn--zz.com");
+    }
+
+    #[test]
+    fn test_display_url_rewrites_only_the_host() {
+        assert_eq!(
+            display_url("https://xn--mnchen-3ya.de:8443/pfad?q=1#top"),
+            "https://münchen.de:8443/pfad?q=1#top"
+        );
+        assert_eq!(
+            display_url("https://xn--80ak6aa92e.com/login"),
+            "https://xn--80ak6aa92e.com/login"
+        );
+        assert_eq!(display_url("not a url"), "not a url");
+        assert_eq!(display_url("about:blank"), "about:blank");
+    }
+}
@@ -392,6 +392,143 @@ impl Default for IntegrityValidator {
     }
 }
 
+/// Integrity check of content too large to hold in memory, such as a
+/// download, fed as it arrives. The expected value is SRI metadata
+/// (`sha384-...`, several allowed) or a bare hex SHA-256 digest as download
+/// pages publish them; like [`IntegrityValidator::verify_integrity`], any
+/// listed digest matching is enough.
+pub struct IntegrityHasher {
+    expected: Vec<(HashAlgorithm, Vec<u8>)>,
+    running: Vec<RunningHash>,
+}
+
+/// A digest being computed
+enum RunningHash {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl RunningHash {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => RunningHash::Sha256(Sha256::new()),
+            HashAlgorithm::Sha384 => RunningHash::Sha384(Sha384::new()),
+            HashAlgorithm::Sha512 => RunningHash::Sha512(Sha512::new()),
+        }
+    }
+
+    fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            RunningHash::Sha256(_) => HashAlgorithm::Sha256,
+            RunningHash::Sha384(_) => HashAlgorithm::Sha384,
+            RunningHash::Sha512(_) => HashAlgorithm::Sha512,
+        }
+    }
+
+    fn update(&mut self, content: &[u8]) {
+        match self {
+            RunningHash::Sha256(hasher) => hasher.update(content),
+            RunningHash::Sha384(hasher) => hasher.update(content),
+            RunningHash::Sha512(hasher) => hasher.update(content),
+        }
+    }
+
+    fn finish(self) -> (HashAlgorithm, Vec<u8>) {
+        let algorithm = self.algorithm();
+        let digest = match self {
+            RunningHash::Sha256(hasher) => hasher.finalize().to_vec(),
+            RunningHash::Sha384(hasher) => hasher.finalize().to_vec(),
+            RunningHash::Sha512(hasher) => hasher.finalize().to_vec(),
+        };
+        (algorithm, digest)
+    }
+}
+
+impl std::fmt::Debug for IntegrityHasher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IntegrityHasher")
+            .field("expected", &self.expected.len())
+            .finish()
+    }
+}
+
+impl IntegrityHasher {
+    /// A hasher checking content against `expected`, or the reason it
+    /// cannot be checked
+    pub fn new(expected: &str) -> Result<Self, IntegrityResult> {
+        let expected = expected.trim();
+        let digests = if expected.len() == 64 && expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+            vec![(HashAlgorithm::Sha256, decode_hex(expected))]
+        } else {
+            parse_metadata(expected)?
+        };
+        if digests.is_empty() {
+            return Err(IntegrityResult::NotProvided);
+        }
+
+        let mut running: Vec<RunningHash> = Vec::new();
+        for (algorithm, _) in &digests {
+            if !running.iter().any(|hash| hash.algorithm() == *algorithm) {
+                running.push(RunningHash::new(*algorithm));
+            }
+        }
+        Ok(Self {
+            expected: digests,
+            running,
+        })
+    }
+
+    /// Feed the next part of the content
+    pub fn update(&mut self, content: &[u8]) {
+        for hash in &mut self.running {
+            hash.update(content);
+        }
+    }
+
+    /// Whether the content fed matches
+    pub fn finish(self) -> IntegrityResult {
+        let computed: Vec<_> = self.running.into_iter().map(RunningHash::finish).collect();
+        if self.expected.iter().any(|digest| computed.contains(digest)) {
+            IntegrityResult::Valid
+        } else {
+            IntegrityResult::Invalid
+        }
+    }
+}
+
+/// Digests listed in SRI metadata; options after `?` are ignored
+fn parse_metadata(metadata: &str) -> Result<Vec<(HashAlgorithm, Vec<u8>)>, IntegrityResult> {
+    metadata
+        .split_whitespace()
+        .map(|token| {
+            let (algorithm, value) = token
+                .split_once('-')
+                .ok_or(IntegrityResult::MalformedAttribute)?;
+            let algorithm = match algorithm {
+                "sha256" => HashAlgorithm::Sha256,
+                "sha384" => HashAlgorithm::Sha384,
+                "sha512" => HashAlgorithm::Sha512,
+                "md5" | "sha1" => return Err(IntegrityResult::UnsupportedAlgorithm),
+                _ => return Err(IntegrityResult::MalformedAttribute),
+            };
+            let value = value.split('?').next().unwrap_or_default();
+            let digest = general_purpose::STANDARD
+                .decode(value)
+                .map_err(|_| IntegrityResult::MalformedAttribute)?;
+            Ok((algorithm, digest))
+        })
+        .collect()
+}
+
+/// Bytes of a hex string already checked to be an even run of hex digits
+fn decode_hex(hex: &str) -> Vec<u8> {
+    hex.as_bytes()
+        .chunks(2)
+        .filter_map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,4 +609,27 @@ mod tests {
 
         assert!(violation.is_none());
     }
+
+    #[test]
+    fn test_incremental_hashing_of_large_content() {
+        let check = |expected: &str| {
+            let mut hasher = IntegrityHasher::new(expected)?;
+            hasher.update(b"Hello, ");
+            hasher.update(b"World!");
+            Ok::<_, IntegrityResult>(hasher.finish())
+        };
+        let hex = "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f";
+        assert_eq!(check(hex), Ok(IntegrityResult::Valid));
+        assert_eq!(check(&hex.to_uppercase()), Ok(IntegrityResult::Valid));
+        assert_eq!(
+            check("sha512-AAAA sha384-VIXMmzNltDBd+06DN+ClmKV0+CQr8XKJ4N1sIKPNRKCJ3harSrMI9j5EsRcOtfUV"),
+            Ok(IntegrityResult::Valid)
+        );
+        assert_eq!(check(&hex.replace('d', "e")), Ok(IntegrityResult::Invalid));
+        assert_eq!(
+            check("md5-abcd").unwrap_err(),
+            IntegrityResult::UnsupportedAlgorithm
+        );
+        assert_eq!(check("").unwrap_err(), IntegrityResult::NotProvided);
+    }
 }
@@ -0,0 +1,114 @@
+//! Request interception hooks
+//!
+//! One interception point shared by everything that needs to look at traffic
+//! (the tracker blocker, privacy header rewriting, future extensions) instead
+//! of ad hoc checks scattered through the loaders. Interceptors registered on
+//! a [`ResourceManager`](crate::ResourceManager) run in registration order:
+//! on the outgoing request before the cache is consulted, and on the response
+//! before it is cached. Any interceptor can block; later ones then do not run.
+
+use futures::future::BoxFuture;
+use url::Url;
+
+use crate::blocklist::{BlocklistEngine, RuleRequest, Verdict};
+use crate::request::Request;
+use crate::resource::ResourceType;
+use crate::response::Response;
+use crate::tracker_blocking::TrackerBlockingEngine;
+
+/// What an interceptor decided
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interception {
+    /// Let the request or response through (possibly modified)
+    Continue,
+    /// Stop the load; the reason is reported to the caller
+    Block(String),
+}
+
+/// What an interceptor knows about a load besides the request itself
+#[derive(Debug, Clone)]
+pub struct InterceptContext {
+    /// Kind of resource being loaded
+    pub resource_type: ResourceType,
+    /// Top-level document of the page making the load, if known
+    pub main_frame: Option<Url>,
+}
+
+/// Inspect, modify or block requests and responses
+///
+/// Both hooks default to [`Interception::Continue`], so an interceptor only
+/// implements the side it cares about.
+pub trait RequestInterceptor: Send + Sync {
+    /// Name shown in logs and block reasons
+    fn name(&self) -> &str;
+
+    /// Called before the cache is consulted and the request is sent
+    fn on_request<'a>(
+        &'a self,
+        _request: &'a mut Request,
+        _context: &'a InterceptContext,
+    ) -> BoxFuture<'a, Interception> {
+        Box::pin(async { Interception::Continue })
+    }
+
+    /// Called on network responses before they are cached
+    fn on_response<'a>(
+        &'a self,
+        _response: &'a mut Response,
+        _context: &'a InterceptContext,
+    ) -> BoxFuture<'a, Interception> {
+        Box::pin(async { Interception::Continue })
+    }
+}
+
+impl RequestInterceptor for TrackerBlockingEngine {
+    fn name(&self) -> &str {
+        "tracker-blocking"
+    }
+
+    fn on_request<'a>(
+        &'a self,
+        request: &'a mut Request,
+        context: &'a InterceptContext,
+    ) -> BoxFuture<'a, Interception> {
+        Box::pin(async move {
+            match self
+                .should_block_url(request.url().as_str(), Some(context.resource_type))
+                .await
+            {
+                Some(blocked) => {
+                    let reason = blocked.reason.clone();
+                    self.record_blocked_request(blocked).await;
+                    Interception::Block(reason)
+                }
+                None => Interception::Continue,
+            }
+        })
+    }
+}
+
+impl RequestInterceptor for BlocklistEngine {
+    fn name(&self) -> &str {
+        "filter-lists"
+    }
+
+    fn on_request<'a>(
+        &'a self,
+        request: &'a mut Request,
+        context: &'a InterceptContext,
+    ) -> BoxFuture<'a, Interception> {
+        Box::pin(async move {
+            let rule_request = RuleRequest::new(
+                request.url(),
+                context.resource_type,
+                context.main_frame.as_ref(),
+            );
+            match self.check(&rule_request) {
+                Verdict::Blocked(rule) => {
+                    Interception::Block(format!("Blocked by filter list rule {}", rule))
+                }
+                _ => Interception::Continue,
+            }
+        })
+    }
+}
@@ -1,40 +1,97 @@
 pub mod advanced_loader;
+pub mod blocklist;
+pub mod budget;
 pub mod cache;
+pub mod cache_storage;
+pub mod connection;
+pub mod cookie_jar;
+pub mod cosmetic;
+pub mod csp;
+pub mod csp_report;
 pub mod dns;
 pub mod error;
+pub mod filter_list;
+pub mod filter_update;
+pub mod headers;
+pub mod host_policy;
 pub mod http;
+pub mod idn;
 pub mod integrity;
+pub mod interceptor;
 pub mod performance;
+pub mod preload_scanner;
 pub mod privacy_engine;
+pub mod proxy;
 pub mod request;
+pub mod request_ledger;
 pub mod resource;
 pub mod resource_discovery;
 pub mod resource_loader;
 pub mod resource_manager;
 pub mod response;
+pub mod security_headers;
+#[cfg(feature = "test-support")]
+pub mod test_server;
+pub mod tls_session;
 pub mod tracker_blocking;
+pub mod url_canon;
+pub mod websocket;
 
 pub use advanced_loader::{
     AdvancedResourceLoader, BandwidthTracker, LoadingStrategy, NetworkCondition, Priority,
 };
-pub use cache::{CacheConfig, CacheEntry, ResourceCache};
+pub use blocklist::{BlocklistEngine, BlocklistStats, NetworkRule, RuleRequest, Verdict};
+pub use budget::{BudgetPermit, BudgetUsage, RequestBudget, TabBudget, TabBudgets};
+pub use cache::{
+    BodyStore, CacheConfig, CacheEntry, CacheEntryInfo, ResourceCache, ResourceCaches,
+};
+pub use cache_storage::{CachePartition, CacheStorage, DiskStorage, MemoryStorage, StoredResponse};
+pub use connection::{AddressFamily, HappyEyeballs};
+pub use cookie_jar::{Cookie, CookieJar, CookieStoreId, SameSite, SavedCookie, ThirdPartyCookies};
+pub use cosmetic::{hiding_stylesheet, CosmeticFilter, CosmeticRule, SCROLL_RESTORE_CSS};
+pub use csp::{CspPolicies, EnforcedPolicy};
+pub use csp_report::{BlockedResource, CspReport, ReportOnlyPolicy};
 /// Re-export common types for easier usage
-pub use dns::{CitadelDnsResolver, DnsMode, DohProviders};
-pub use error::NetworkError;
-pub use http::{fetch as https_fetch, HttpResponse};
-pub use integrity::{CSPViolation, HashAlgorithm, IntegrityResult, IntegrityValidator};
+pub use dns::{CitadelDnsResolver, DnsCacheRecord, DnsMode, DohProviders};
+pub use error::{LoadErrorCategory, NetworkError, RetryPolicy};
+pub use filter_list::{CompiledFilterList, FilterListCache, FilterLists};
+pub use filter_update::{FilterUpdater, Subscription, UpdateOutcome, UpdateSettings};
+pub use headers::HeaderMap;
+pub use host_policy::{ContainerPolicies, HostPolicy};
+pub use http::{
+    fetch as https_fetch, fetch_partitioned as https_fetch_partitioned,
+    fetch_streaming as https_fetch_streaming, fetch_via_proxy as https_fetch_via_proxy,
+    HttpResponse, StreamRoute, StreamingResponse,
+};
+pub use idn::{display_host, display_url};
+pub use integrity::{
+    CSPViolation, HashAlgorithm, IntegrityHasher, IntegrityResult, IntegrityValidator,
+};
+pub use interceptor::{InterceptContext, Interception, RequestInterceptor};
+pub use preload_scanner::PreloadScanner;
 pub use privacy_engine::{CitadelPrivacyEngine, PrivacyStats};
-pub use request::{Method, Request};
+pub use proxy::{is_onion_host, is_onion_url, SocksProxy};
+pub use request::{BodyStream, Method, RedirectPolicy, Request, RequestBuilder};
+pub use request_ledger::{
+    BlockingPolicy, LedgerSummary, RequestLedger, RequestOutcome, RequestRecord,
+};
 pub use resource::Resource;
 pub use resource_discovery::{ResourceContext, ResourceDiscovery, ResourceRef};
 pub use resource_loader::{LoadOptions, LoadProgress, LoadResult, ResourceLoader};
 pub use resource_manager::{
     CachePolicy, OriginType, ResourceManager, ResourceManagerConfig, ResourcePolicy, ResourceStats,
 };
-pub use response::Response;
+pub use response::{Response, ResponseTiming, StatusCode};
+pub use security_headers::{HeaderFinding, HeaderStatus, SecurityGrade, SecurityHeaderReport};
+pub use tls_session::{NetworkPartitionKey, TlsSessionCache};
 pub use tracker_blocking::{
     BlockedRequest, BlockingLevel, BlocklistConfig, TrackerBlockingEngine, TrackerBlockingStats,
 };
+pub use url_canon::{canonicalize, canonicalize_str};
+pub use websocket::{
+    WebSocketConfig, WebSocketConnection, WebSocketMessage, WebSocketPermissions,
+    DEFAULT_MAX_MESSAGE_BYTES,
+};
 
 /// Types of privacy level configurations for the networking layer
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -64,6 +121,9 @@ pub struct NetworkConfig {
     pub strip_tracking_params: bool,
     /// Tracker blocking configuration
     pub tracker_blocking: tracker_blocking::BlocklistConfig,
+    /// SOCKS proxy (e.g. Tor) all page loads go through; `None` connects
+    /// directly. `.onion` sites are only reachable through one.
+    pub socks_proxy: Option<proxy::SocksProxy>,
 }
 
 impl Default for NetworkConfig {
@@ -75,6 +135,7 @@ impl Default for NetworkConfig {
             randomize_user_agent: true,
             strip_tracking_params: true,
             tracker_blocking: tracker_blocking::BlocklistConfig::default(),
+            socks_proxy: None,
         }
     }
 }
@@ -0,0 +1,381 @@
+//! Speculative preload scanner
+//!
+//! Runs ahead of the real parser over raw document bytes as they arrive and
+//! picks out stylesheets, images and fonts so their fetches can start before
+//! the whole document is in. It only understands start tags, comments and the
+//! raw text of `<script>`/`<style>`; each candidate tag or inline style block
+//! is handed to [`ResourceDiscovery`] for URL resolution, the scheme checks
+//! and the type-based priorities. Getting it wrong is cheap: a reference it
+//! misses is found again once the document is parsed.
+
+use std::collections::HashSet;
+
+use url::Url;
+
+use crate::resource::ResourceType;
+use crate::resource_discovery::{ResourceContext, ResourceDiscovery, ResourceRef};
+
+/// A tag longer than this is skipped rather than buffered
+const MAX_TAG_BYTES: usize = 8 * 1024;
+
+/// Inline CSS kept per `<style>` block when looking for fonts and imports
+const MAX_STYLE_BYTES: usize = 256 * 1024;
+
+/// Priority for stylesheets that do not apply to the screen (`media=print`)
+const OFF_SCREEN_STYLESHEET_PRIORITY: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Data,
+    Comment,
+    /// Inside an element whose content is not markup, up to `</name`
+    RawText(&'static str),
+}
+
+/// Incremental scanner over a document that is still arriving
+#[derive(Debug)]
+pub struct PreloadScanner {
+    context: ResourceContext,
+    /// Bytes not yet scanned: an unfinished tag, comment end or end tag
+    pending: Vec<u8>,
+    state: State,
+    /// Text of the `<style>` block being read
+    style: Vec<u8>,
+    seen: HashSet<Url>,
+}
+
+impl PreloadScanner {
+    /// Scanner for the document at `base_url`
+    pub fn new(base_url: Url) -> Self {
+        let context = ResourceContext::new(base_url).allowed_types(vec![
+            ResourceType::Css,
+            ResourceType::Font,
+            ResourceType::Image,
+        ]);
+        Self {
+            context,
+            pending: Vec::new(),
+            state: State::Data,
+            style: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.context.base_url
+    }
+
+    /// Resources found so far
+    pub fn discovered(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Scan the next chunk of the document, returning resources not seen in
+    /// earlier chunks. Tags split across chunks are picked up once complete.
+    pub fn feed(&mut self, chunk: &[u8], discovery: &ResourceDiscovery) -> Vec<ResourceRef> {
+        self.pending.extend_from_slice(chunk);
+        let mut found = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            let len = self.pending.len();
+            match self.state {
+                State::Data => {
+                    let Some(offset) = self.pending[pos..].iter().position(|&b| b == b'<') else {
+                        pos = len;
+                        break;
+                    };
+                    let start = pos + offset;
+                    let rest = &self.pending[start..];
+                    if rest.len() < 4 && b"<!--".starts_with(rest) {
+                        // Could still become a comment
+                        pos = start;
+                        break;
+                    }
+                    if rest.starts_with(b"<!--") {
+                        self.state = State::Comment;
+                        pos = start + 4;
+                        continue;
+                    }
+                    if !rest.get(1).is_some_and(|&b| {
+                        b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?')
+                    }) {
+                        // A stray '<' in text
+                        pos = start + 1;
+                        continue;
+                    }
+                    match tag_end(rest) {
+                        Some(end) => {
+                            let tag = String::from_utf8_lossy(&rest[..=end]).into_owned();
+                            pos = start + end + 1;
+                            self.start_tag(&tag, discovery, &mut found);
+                        }
+                        None if rest.len() > MAX_TAG_BYTES => pos = start + 1,
+                        None => {
+                            pos = start;
+                            break;
+                        }
+                    }
+                }
+                State::Comment => match find(&self.pending[pos..], b"-->") {
+                    Some(offset) => {
+                        pos += offset + 3;
+                        self.state = State::Data;
+                    }
+                    None => {
+                        // Keep what could be the start of "-->"
+                        pos = len.saturating_sub(2).max(pos);
+                        break;
+                    }
+                },
+                State::RawText(name) => {
+                    match find_end_tag(&self.pending[pos..], name) {
+                        Some(offset) => {
+                            if name == "style" {
+                                self.push_style(pos, pos + offset);
+                                found.extend(self.inline_style(discovery));
+                            }
+                            pos += offset + 2 + name.len();
+                            self.state = State::Data;
+                        }
+                        None => {
+                            // Keep what could be the start of the end tag
+                            let keep = len.saturating_sub(name.len() + 1).max(pos);
+                            if name == "style" {
+                                self.push_style(pos, keep);
+                            }
+                            pos = keep;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.pending.drain(..pos);
+        found
+    }
+
+    /// End of the document. An unterminated `<style>` block is still looked
+    /// through; anything else left over is dropped.
+    pub fn finish(&mut self, discovery: &ResourceDiscovery) -> Vec<ResourceRef> {
+        let mut found = Vec::new();
+        if self.state == State::RawText("style") {
+            self.push_style(0, self.pending.len());
+            found = self.inline_style(discovery);
+        }
+        self.pending.clear();
+        self.state = State::Data;
+        found
+    }
+
+    fn start_tag(
+        &mut self,
+        tag: &str,
+        discovery: &ResourceDiscovery,
+        found: &mut Vec<ResourceRef>,
+    ) {
+        let name: String = tag[1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "script" => self.state = State::RawText("script"),
+            "style" => {
+                self.style.clear();
+                self.state = State::RawText("style");
+            }
+            "textarea" => self.state = State::RawText("textarea"),
+            "title" => self.state = State::RawText("title"),
+            "link" | "img" => {
+                let resources = match discovery.discover_from_html(tag, &self.context) {
+                    Ok(resources) => resources,
+                    Err(e) => {
+                        log::debug!("Preload scanner skipped a tag: {}", e);
+                        return;
+                    }
+                };
+                let rel = attribute(tag, "rel");
+                let off_screen = attribute(tag, "media").is_some_and(|media| {
+                    !media.split(',').any(|query| {
+                        let query = query.trim().to_ascii_lowercase();
+                        query.is_empty() || query.starts_with("all") || query.starts_with("screen")
+                    })
+                });
+                for mut resource in resources {
+                    resource = resource.with_metadata("source", "preload-scanner");
+                    if let Some(rel) = &rel {
+                        resource = resource.with_metadata("rel", rel);
+                    }
+                    if off_screen && resource.resource_type == ResourceType::Css {
+                        resource = resource
+                            .with_priority(OFF_SCREEN_STYLESHEET_PRIORITY)
+                            .with_critical(false);
+                    }
+                    self.keep(resource, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Fonts and imported stylesheets of the `<style>` block just read
+    fn inline_style(&mut self, discovery: &ResourceDiscovery) -> Vec<ResourceRef> {
+        let css = String::from_utf8_lossy(&std::mem::take(&mut self.style)).into_owned();
+        let context = self
+            .context
+            .clone()
+            .allowed_types(vec![ResourceType::Css, ResourceType::Font]);
+        let mut found = Vec::new();
+        match discovery.discover_from_css(&css, &context) {
+            Ok(resources) => {
+                for resource in resources {
+                    self.keep(
+                        resource.with_metadata("source", "preload-scanner"),
+                        &mut found,
+                    );
+                }
+            }
+            Err(e) => log::debug!("Preload scanner skipped a style block: {}", e),
+        }
+        found
+    }
+
+    fn push_style(&mut self, from: usize, to: usize) {
+        let room = MAX_STYLE_BYTES.saturating_sub(self.style.len());
+        let end = to.min(from + room);
+        if from < end {
+            self.style.extend_from_slice(&self.pending[from..end]);
+        }
+    }
+
+    fn keep(&mut self, resource: ResourceRef, found: &mut Vec<ResourceRef>) {
+        let limit = self.context.max_resources.unwrap_or(usize::MAX);
+        if self.seen.len() < limit && self.seen.insert(resource.url.clone()) {
+            found.push(resource);
+        }
+    }
+}
+
+/// Index of the '>' closing the tag at the start of `bytes`, skipping over
+/// quoted attribute values
+fn tag_end(bytes: &[u8]) -> Option<usize> {
+    let mut quote = None;
+    let mut after_equals = false;
+    for (i, &b) in bytes.iter().enumerate().skip(1) {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'>' => return Some(i),
+                b'"' | b'\'' if after_equals => quote = Some(b),
+                b'=' => after_equals = true,
+                b if b.is_ascii_whitespace() => {}
+                _ => after_equals = false,
+            },
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Offset of `</name` in `bytes`, ignoring case
+fn find_end_tag(bytes: &[u8], name: &str) -> Option<usize> {
+    let needle_len = name.len() + 2;
+    bytes.windows(needle_len).position(|window| {
+        window.starts_with(b"</") && window[2..].eq_ignore_ascii_case(name.as_bytes())
+    })
+}
+
+/// Value of a quoted attribute in a start tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(offset) = lower[from..].find(name) {
+        let start = from + offset;
+        from = start + name.len();
+        let preceded_by_space = lower[..start]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lower[from..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value = rest[1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| value[..end].trim().to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(chunks: &[&str]) -> Vec<ResourceRef> {
+        let discovery = ResourceDiscovery::new().unwrap();
+        let mut scanner = PreloadScanner::new(Url::parse("https://example.com/news/").unwrap());
+        let mut found = Vec::new();
+        for chunk in chunks {
+            found.extend(scanner.feed(chunk.as_bytes(), &discovery));
+        }
+        found.extend(scanner.finish(&discovery));
+        found
+    }
+
+    #[test]
+    fn test_finds_resources_across_chunk_boundaries() {
+        let found = scan(&[
+            "<html><head><link rel=\"styles",
+            "heet\" href=\"/site.css\"><link rel=\"stylesheet\" media=\"print\" href=\"print.css\">",
+            "<style>@font-face { font-family: Body; src: url('/fonts/body.woff2'); }</sty",
+            "le></head><body><!-- <img src=\"/hidden.png\"> -",
+            "-><script>var s = '<img src=\"/from-script.png\">';</script>",
+            "<img src=\"hero.jpg\" alt='a > b'><img src=\"hero.jpg\"></body></html>",
+        ]);
+        let urls: Vec<&str> = found.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/site.css",
+                "https://example.com/news/print.css",
+                "https://example.com/fonts/body.woff2",
+                "https://example.com/news/hero.jpg",
+            ]
+        );
+
+        assert!(found[0].is_critical);
+        assert_eq!(found[0].priority, 1);
+        assert_eq!(
+            found[0].metadata.get("rel").map(String::as_str),
+            Some("stylesheet")
+        );
+        assert!(!found[1].is_critical);
+        assert_eq!(found[1].priority, OFF_SCREEN_STYLESHEET_PRIORITY);
+        assert_eq!(found[2].resource_type, ResourceType::Font);
+        assert_eq!(found[3].resource_type, ResourceType::Image);
+        assert!(found
+            .iter()
+            .all(|r| r.metadata.get("source").map(String::as_str) == Some("preload-scanner")));
+    }
+
+    #[test]
+    fn test_skips_unsafe_and_unterminated_input() {
+        let found = scan(&[
+            "<img src=\"javascript:alert(1)\"> 3 < 4 <link rel=\"stylesheet\" href=\"/a.css\"",
+        ]);
+        assert!(found.is_empty());
+
+        let found = scan(&["<style>@import url(\"/base.css\");"]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].url.as_str(), "https://example.com/base.css");
+    }
+}
@@ -42,6 +42,7 @@ impl CitadelPrivacyEngine {
         // Create resource manager with tracker blocking
         let resource_config = ResourceManagerConfig {
             network_config: network_config.clone(),
+            request_budget: crate::RequestBudget::for_privacy_level(network_config.privacy_level),
             ..ResourceManagerConfig::default()
         };
 
@@ -0,0 +1,289 @@
+//! SOCKS5 proxying and onion service handling
+//!
+//! With a SOCKS proxy configured (typically a local Tor client), connections
+//! are opened through the proxy and the hostname is handed to it unresolved,
+//! so no DNS query leaves this machine. `.onion` names are never resolved
+//! locally at all: without a proxy they fail before any lookup.
+//!
+//! Tor keeps streams that authenticate with different SOCKS credentials on
+//! different circuits. A per-tab isolation token is sent as the username, so
+//! two tabs never share a circuit (and so an exit or onion service cannot
+//! link them by it).
+
+use std::net::IpAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::error::NetworkError;
+
+const SOCKS_VERSION: u8 = 0x05;
+const AUTH_NONE: u8 = 0x00;
+const AUTH_USERNAME_PASSWORD: u8 = 0x02;
+const AUTH_NO_ACCEPTABLE: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Password sent with isolation usernames; Tor only compares credentials
+const ISOLATION_PASSWORD: &str = "citadel";
+
+/// Whether a host is an onion service address
+pub fn is_onion_host(host: &str) -> bool {
+    host.trim_end_matches('.')
+        .to_ascii_lowercase()
+        .ends_with(".onion")
+}
+
+/// Whether a URL points at an onion service
+pub fn is_onion_url(url: &Url) -> bool {
+    url.host_str().is_some_and(is_onion_host)
+}
+
+/// Whether a page may trigger loads the user did not ask for (favicons,
+/// `rel=prefetch`). Onion pages may not: each speculative load is another
+/// stream an observer can correlate with the visit.
+pub fn speculative_loads_allowed(page: &Url) -> bool {
+    !is_onion_url(page)
+}
+
+/// A SOCKS5 proxy, e.g. Tor's `127.0.0.1:9050`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocksProxy {
+    /// `host:port` of the proxy
+    pub address: String,
+    /// Send per-tab credentials so the proxy keeps tabs on separate circuits
+    pub isolate_streams: bool,
+}
+
+impl SocksProxy {
+    /// A proxy with stream isolation enabled
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            isolate_streams: true,
+        }
+    }
+
+    /// Open a stream to `host:port` through the proxy. The proxy resolves
+    /// `host`; `isolation` keys the stream's circuit when isolation is on.
+    pub async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        isolation: Option<&str>,
+    ) -> Result<TcpStream, NetworkError> {
+        let mut stream = TcpStream::connect(&self.address).await.map_err(|e| {
+            NetworkError::ConnectionError(format!("SOCKS proxy {}: {}", self.address, e))
+        })?;
+        let credentials = isolation.filter(|_| self.isolate_streams);
+        handshake(&mut stream, host, port, credentials)
+            .await
+            .map_err(|e| match e {
+                NetworkError::IoError(e) => {
+                    NetworkError::ConnectionError(format!("SOCKS proxy {}: {}", self.address, e))
+                }
+                e => e,
+            })?;
+        Ok(stream)
+    }
+}
+
+/// Run the SOCKS5 greeting, optional username/password authentication
+/// (RFC 1929) and CONNECT
+async fn handshake(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    isolation: Option<&str>,
+) -> Result<(), NetworkError> {
+    // Only offer authentication when isolating, so a proxy cannot silently
+    // downgrade to a shared circuit
+    let method = if isolation.is_some() {
+        AUTH_USERNAME_PASSWORD
+    } else {
+        AUTH_NONE
+    };
+    stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(socks_error("not a SOCKS5 proxy"));
+    }
+    match (reply[1], isolation) {
+        (AUTH_USERNAME_PASSWORD, Some(username)) => authenticate(stream, username).await?,
+        (AUTH_NONE, None) => {}
+        (AUTH_NO_ACCEPTABLE, Some(_)) => {
+            return Err(socks_error("proxy does not support stream isolation"))
+        }
+        _ => {
+            return Err(socks_error(
+                "proxy chose an unsupported authentication method",
+            ))
+        }
+    }
+
+    let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0x00];
+    match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let name = u8::try_from(host.len())
+                .map_err(|_| socks_error("hostname longer than 255 bytes"))?;
+            request.push(ATYP_DOMAIN);
+            request.push(name);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(socks_error(reply_message(reply[1])));
+    }
+    // Skip the bound address the proxy reports
+    let address_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        _ => return Err(socks_error("malformed CONNECT reply")),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+async fn authenticate(stream: &mut TcpStream, username: &str) -> Result<(), NetworkError> {
+    let username_len =
+        u8::try_from(username.len()).map_err(|_| socks_error("isolation token too long"))?;
+    let mut request = vec![0x01, username_len];
+    request.extend_from_slice(username.as_bytes());
+    request.push(ISOLATION_PASSWORD.len() as u8);
+    request.extend_from_slice(ISOLATION_PASSWORD.as_bytes());
+    stream.write_all(&request).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(socks_error("proxy rejected isolation credentials"));
+    }
+    Ok(())
+}
+
+/// Error for an attempt to resolve an onion name locally
+pub(crate) fn onion_resolution_error(host: &str) -> NetworkError {
+    NetworkError::DnsError(format!(
+        "{}: onion services are only reachable through a SOCKS proxy",
+        host
+    ))
+}
+
+fn socks_error(message: &str) -> NetworkError {
+    NetworkError::ConnectionError(format!("SOCKS: {}", message))
+}
+
+/// Meaning of a SOCKS5 CONNECT reply code (RFC 1928 §6)
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general proxy failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown proxy error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Accept one SOCKS5 client and return (username, requested host, port)
+    async fn fake_proxy(listener: TcpListener) -> (Option<String>, String, u16) {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut greeting = [0u8; 3];
+        client.read_exact(&mut greeting).await.unwrap();
+        let mut username = None;
+        if greeting[2] == AUTH_USERNAME_PASSWORD {
+            client
+                .write_all(&[SOCKS_VERSION, AUTH_USERNAME_PASSWORD])
+                .await
+                .unwrap();
+            let mut auth = [0u8; 2];
+            client.read_exact(&mut auth).await.unwrap();
+            let mut user = vec![0u8; auth[1] as usize];
+            client.read_exact(&mut user).await.unwrap();
+            let mut pass = vec![0u8; client.read_u8().await.unwrap() as usize];
+            client.read_exact(&mut pass).await.unwrap();
+            client.write_all(&[0x01, 0x00]).await.unwrap();
+            username = Some(String::from_utf8(user).unwrap());
+        } else {
+            client.write_all(&[SOCKS_VERSION, AUTH_NONE]).await.unwrap();
+        }
+        let mut head = [0u8; 5];
+        client.read_exact(&mut head).await.unwrap();
+        assert_eq!(head[3], ATYP_DOMAIN, "hostnames are never resolved locally");
+        let mut host = vec![0u8; head[4] as usize];
+        client.read_exact(&mut host).await.unwrap();
+        let port = client.read_u16().await.unwrap();
+        client
+            .write_all(&[SOCKS_VERSION, 0x00, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        (username, String::from_utf8(host).unwrap(), port)
+    }
+
+    #[test]
+    fn test_onion_detection() {
+        assert!(is_onion_host(
+            "duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion"
+        ));
+        assert!(is_onion_host("www.Example.ONION."));
+        assert!(!is_onion_host("onion.example.com"));
+        let onion = Url::parse("https://abc.onion/").unwrap();
+        assert!(!speculative_loads_allowed(&onion));
+        assert!(speculative_loads_allowed(
+            &Url::parse("https://example.com/").unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_sends_hostname_and_isolation_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = SocksProxy::new(listener.local_addr().unwrap().to_string());
+        let server = tokio::spawn(fake_proxy(listener));
+
+        proxy
+            .connect("abc.onion", 443, Some("tab-1"))
+            .await
+            .unwrap();
+        let (username, host, port) = server.await.unwrap();
+        assert_eq!(username.as_deref(), Some("tab-1"));
+        assert_eq!((host.as_str(), port), ("abc.onion", 443));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let shared = SocksProxy {
+            isolate_streams: false,
+            ..SocksProxy::new(listener.local_addr().unwrap().to_string())
+        };
+        let server = tokio::spawn(fake_proxy(listener));
+        shared
+            .connect("example.com", 443, Some("tab-1"))
+            .await
+            .unwrap();
+        assert_eq!(server.await.unwrap().0, None);
+    }
+}
@@ -1,13 +1,25 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt};
 use rand::Rng;
 use serde::Serialize;
 use url::Url;
+use uuid::Uuid;
 
+use crate::advanced_loader::Priority;
 use crate::error::NetworkError;
+use crate::resource_manager::CachePolicy;
+use crate::tls_session::NetworkPartitionKey;
 use crate::PrivacyLevel;
 
+/// Default request timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default redirect limit
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
 /// Common HTTP methods supported by the Citadel browser
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Method {
@@ -38,6 +50,177 @@ impl std::fmt::Display for Method {
     }
 }
 
+/// What to do with a redirect response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Follow up to `max` redirects
+    Follow { max: usize },
+    /// Return the redirect response to the caller
+    Manual,
+}
+
+/// A request body produced incrementally. It can be consumed once; clones of
+/// the request share it.
+#[derive(Clone)]
+pub struct BodyStream(Arc<Mutex<Option<BoxStream<'static, Bytes>>>>);
+
+impl BodyStream {
+    /// Wrap a stream of body chunks
+    pub fn new(stream: impl Stream<Item = Bytes> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Some(stream.boxed()))))
+    }
+
+    /// Take the stream; `None` once it has been taken
+    pub fn take(&self) -> Option<BoxStream<'static, Bytes>> {
+        self.0.lock().ok().and_then(|mut stream| stream.take())
+    }
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BodyStream { .. }")
+    }
+}
+
+/// Builds a [`Request`], validating it once in [`build`](Self::build)
+#[derive(Debug, Clone, Default)]
+pub struct RequestBuilder {
+    method: Option<Method>,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+    body_stream: Option<BodyStream>,
+    timeout: Option<Duration>,
+    privacy_level: Option<PrivacyLevel>,
+    redirect_policy: Option<RedirectPolicy>,
+    priority: Option<Priority>,
+    cache_mode: Option<CachePolicy>,
+    partition_key: Option<NetworkPartitionKey>,
+    container: Option<Uuid>,
+}
+
+impl RequestBuilder {
+    /// HTTP method; `GET` when not set
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Target URL, parsed and checked by [`build`](Self::build)
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set a header, replacing an earlier value case-insensitively
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set several headers
+    pub fn headers<'a>(self, headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        headers
+            .into_iter()
+            .fold(self, |builder, (name, value)| builder.header(name, value))
+    }
+
+    /// Buffered body
+    pub fn body(mut self, body: impl AsRef<[u8]>) -> Self {
+        self.body = Some(body.as_ref().to_vec());
+        self
+    }
+
+    /// Streamed body, for uploads that should not be buffered
+    pub fn body_stream(mut self, stream: impl Stream<Item = Bytes> + Send + 'static) -> Self {
+        self.body_stream = Some(BodyStream::new(stream));
+        self
+    }
+
+    /// Timeout for the whole request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Privacy level applied by [`Request::prepare`]
+    pub fn privacy_level(mut self, level: PrivacyLevel) -> Self {
+        self.privacy_level = Some(level);
+        self
+    }
+
+    /// How redirects are handled
+    pub fn redirect(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = Some(policy);
+        self
+    }
+
+    /// Load priority, overriding the one derived from the resource type
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Cache mode, overriding the origin's cache policy
+    pub fn cache_mode(mut self, mode: CachePolicy) -> Self {
+        self.cache_mode = Some(mode);
+        self
+    }
+
+    /// Network partition whose connection state the request may use
+    pub fn partition_key(mut self, key: NetworkPartitionKey) -> Self {
+        self.partition_key = Some(key);
+        self
+    }
+
+    /// Container whose host policy the request is subject to
+    pub fn container(mut self, container_id: Uuid) -> Self {
+        self.container = Some(container_id);
+        self
+    }
+
+    /// Validate the URL (HTTPS is required) and build the request
+    pub fn build(self) -> Result<Request, NetworkError> {
+        let url = self
+            .url
+            .ok_or_else(|| NetworkError::ResourceError("request has no URL".to_string()))?;
+        let url = Url::parse(&url).map_err(NetworkError::UrlError)?;
+
+        // Ensure HTTPS by default for privacy
+        if url.scheme() != "https" && url.scheme() != "data" && url.scheme() != "about" {
+            return Err(NetworkError::HttpsEnforcementError(format!(
+                "Non-HTTPS URL: {}. HTTPS is required for privacy and security.",
+                url
+            )));
+        }
+
+        let (follow_redirects, max_redirects) =
+            match self.redirect_policy.unwrap_or(RedirectPolicy::Follow {
+                max: DEFAULT_MAX_REDIRECTS,
+            }) {
+                RedirectPolicy::Follow { max } => (true, max),
+                RedirectPolicy::Manual => (false, 0),
+            };
+
+        Ok(Request {
+            method: self.method.unwrap_or(Method::GET),
+            url,
+            headers: self.headers.into_iter().collect(),
+            body: self.body,
+            body_stream: self.body_stream,
+            timeout: Some(self.timeout.unwrap_or(DEFAULT_TIMEOUT)),
+            privacy_level: self.privacy_level.unwrap_or(PrivacyLevel::High),
+            follow_redirects,
+            max_redirects,
+            priority: self.priority,
+            cache_mode: self.cache_mode,
+            partition_key: self.partition_key,
+            container: self.container,
+        })
+    }
+}
+
 /// Privacy-preserving HTTP request
 #[derive(Debug, Clone)]
 pub struct Request {
@@ -64,32 +247,32 @@ pub struct Request {
 
     /// Maximum number of redirects to follow
     max_redirects: usize,
+
+    /// Streamed body, used instead of `body` when set
+    body_stream: Option<BodyStream>,
+
+    /// Per-request load priority override
+    priority: Option<Priority>,
+
+    /// Per-request cache mode override
+    cache_mode: Option<CachePolicy>,
+
+    /// Network partition the request belongs to
+    partition_key: Option<NetworkPartitionKey>,
+
+    /// Container the request is made from
+    container: Option<Uuid>,
 }
 
 impl Request {
-    /// Create a new request with the specified method and URL
-    pub fn new(method: Method, url: &str) -> Result<Self, NetworkError> {
-        // Parse and validate the URL
-        let url = Url::parse(url).map_err(NetworkError::UrlError)?;
-
-        // Ensure HTTPS by default for privacy
-        if url.scheme() != "https" && url.scheme() != "data" && url.scheme() != "about" {
-            return Err(NetworkError::HttpsEnforcementError(format!(
-                "Non-HTTPS URL: {}. HTTPS is required for privacy and security.",
-                url
-            )));
-        }
+    /// Start building a request
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
 
-        Ok(Self {
-            method,
-            url,
-            headers: HashMap::new(),
-            body: None,
-            timeout: Some(Duration::from_secs(30)),
-            privacy_level: PrivacyLevel::High,
-            follow_redirects: true,
-            max_redirects: 10,
-        })
+    /// Shorthand for a builder with only a method and URL
+    pub fn new(method: Method, url: &str) -> Result<Self, NetworkError> {
+        Self::builder().method(method).url(url).build()
     }
 
     /// Set the request body
@@ -113,6 +296,22 @@ impl Request {
         self
     }
 
+    /// Set or replace a header in place, matching its name case-insensitively
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.remove_header(name);
+        self.headers.insert(name.to_string(), value.to_string());
+    }
+
+    /// Remove a header, matching its name case-insensitively
+    pub fn remove_header(&mut self, name: &str) -> Option<String> {
+        let key = self
+            .headers
+            .keys()
+            .find(|k| k.eq_ignore_ascii_case(name))?
+            .clone();
+        self.headers.remove(&key)
+    }
+
     /// Set the request timeout
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
@@ -253,44 +452,7 @@ impl Request {
 
     /// Strip common tracking parameters from the URL
     fn strip_tracking_params(&mut self) {
-        let tracking_params = [
-            "utm_source",
-            "utm_medium",
-            "utm_campaign",
-            "utm_term",
-            "utm_content",
-            "fbclid",
-            "gclid",
-            "msclkid",
-            "mc_eid",
-            "yclid",
-            "_ga",
-            "_gl",
-            "ref",
-            "referrer",
-            "source",
-            "xtor",
-            "ICID",
-            "dicbo",
-            "fbcid",
-        ];
-
-        let pairs: Vec<(String, String)> = self
-            .url
-            .query_pairs()
-            .filter(|(k, _)| !tracking_params.contains(&k.as_ref()))
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
-
-        // Clear query string and rebuild without tracking params
-        self.url.set_query(None);
-
-        if !pairs.is_empty() {
-            let mut serializer = self.url.query_pairs_mut();
-            for (key, value) in pairs {
-                serializer.append_pair(&key, &value);
-            }
-        }
+        crate::url_canon::strip_tracking_params(&mut self.url);
     }
 
     /// Generate a random User-Agent to prevent fingerprinting
@@ -365,6 +527,42 @@ impl Request {
     pub fn get_max_redirects(&self) -> usize {
         self.max_redirects
     }
+
+    /// Get the redirect policy
+    pub fn redirect_policy(&self) -> RedirectPolicy {
+        if self.follow_redirects {
+            RedirectPolicy::Follow {
+                max: self.max_redirects,
+            }
+        } else {
+            RedirectPolicy::Manual
+        }
+    }
+
+    /// Get the streamed body, if the request has one
+    pub fn body_stream(&self) -> Option<&BodyStream> {
+        self.body_stream.as_ref()
+    }
+
+    /// Get the load priority override
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    /// Get the cache mode override
+    pub fn cache_mode(&self) -> Option<CachePolicy> {
+        self.cache_mode
+    }
+
+    /// Get the network partition
+    pub fn partition_key(&self) -> Option<&NetworkPartitionKey> {
+        self.partition_key.as_ref()
+    }
+
+    /// Get the container the request is made from
+    pub fn container(&self) -> Option<Uuid> {
+        self.container
+    }
 }
 
 #[cfg(test)]
@@ -378,6 +576,63 @@ mod tests {
         assert_eq!(request.url().as_str(), "https://example.com/");
     }
 
+    #[test]
+    fn test_builder_overrides() {
+        let key = NetworkPartitionKey::for_url(&Url::parse("https://example.com/").unwrap());
+        let request = Request::builder()
+            .method(Method::POST)
+            .url("https://api.example.com/upload")
+            .header("Content-Type", "text/plain")
+            .header("content-type", "application/json")
+            .body_stream(futures::stream::iter(vec![
+                Bytes::from("a"),
+                Bytes::from("b"),
+            ]))
+            .timeout(Duration::from_secs(5))
+            .redirect(RedirectPolicy::Manual)
+            .priority(Priority::Low)
+            .cache_mode(CachePolicy::NeverCache)
+            .partition_key(key.clone().unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(request.method(), &Method::POST);
+        assert_eq!(request.headers().len(), 1);
+        assert_eq!(request.headers()["content-type"], "application/json");
+        assert_eq!(request.timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(request.redirect_policy(), RedirectPolicy::Manual);
+        assert!(!request.follows_redirects());
+        assert_eq!(request.priority(), Some(Priority::Low));
+        assert_eq!(request.cache_mode(), Some(CachePolicy::NeverCache));
+        assert_eq!(request.partition_key(), key.as_ref());
+
+        let copy = request.clone();
+        let stream = request.body_stream().unwrap().take().unwrap();
+        let chunks: Vec<Bytes> = futures::executor::block_on(stream.collect());
+        assert_eq!(chunks.concat(), b"ab");
+        assert!(
+            copy.body_stream().unwrap().take().is_none(),
+            "consumed once"
+        );
+
+        let defaults = Request::builder()
+            .url("https://example.com")
+            .build()
+            .unwrap();
+        assert_eq!(defaults.method(), &Method::GET);
+        assert_eq!(
+            defaults.redirect_policy(),
+            RedirectPolicy::Follow {
+                max: DEFAULT_MAX_REDIRECTS
+            }
+        );
+        assert!(Request::builder().build().is_err());
+        assert!(Request::builder()
+            .url("http://example.com")
+            .build()
+            .is_err());
+    }
+
     #[test]
     fn test_https_enforcement() {
         let result = Request::new(Method::GET, "http://example.com");
@@ -0,0 +1,283 @@
+//! Per-tab request log
+//!
+//! Every request a tab makes through the [`ResourceManager`] is written to
+//! the [`RequestLedger`]: what was asked for, whether it went out, and what
+//! came back or which policy stopped it. The ledger is shared like the
+//! budgets and CSP policies, so whoever fetches for a tab and whoever shows
+//! its log see the same entries. Each tab keeps its latest
+//! [`MAX_ENTRIES_PER_TAB`] requests until it is closed. The log stays in
+//! memory; it is never written to disk.
+//!
+//! [`ResourceManager`]: crate::ResourceManager
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use url::Url;
+use uuid::Uuid;
+
+use crate::request::Method;
+use crate::resource::ResourceType;
+
+/// Requests kept per tab; older ones are dropped first
+pub const MAX_ENTRIES_PER_TAB: usize = 500;
+
+/// What refused a request before it was sent, or its response after
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockingPolicy {
+    /// A request interceptor, by name: the tracker blocker, filter lists
+    /// or an extension
+    Interceptor(String),
+    /// The host allow/block list of the tab's container
+    ContainerHosts,
+    /// The resource manager's resource policy (tracking, third-party,
+    /// text-only)
+    ResourcePolicy,
+    /// The page's Content-Security-Policy
+    ContentSecurityPolicy,
+    /// The tab's request budget
+    RequestBudget,
+}
+
+impl std::fmt::Display for BlockingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockingPolicy::Interceptor(name) => write!(f, "{}", name),
+            BlockingPolicy::ContainerHosts => write!(f, "container hosts"),
+            BlockingPolicy::ResourcePolicy => write!(f, "resource policy"),
+            BlockingPolicy::ContentSecurityPolicy => write!(f, "Content-Security-Policy"),
+            BlockingPolicy::RequestBudget => write!(f, "request budget"),
+        }
+    }
+}
+
+/// How a request ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// A response came back, from the network or the cache
+    Completed {
+        status: u16,
+        bytes: u64,
+        from_cache: bool,
+    },
+    /// A policy refused it
+    Blocked {
+        policy: BlockingPolicy,
+        reason: String,
+    },
+    /// It was sent but failed
+    Failed(String),
+}
+
+/// One request of a tab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestRecord {
+    pub url: Url,
+    pub method: Method,
+    pub resource_type: ResourceType,
+    pub outcome: RequestOutcome,
+    /// When it ended
+    pub at: SystemTime,
+}
+
+impl RequestRecord {
+    pub fn new(
+        url: Url,
+        method: Method,
+        resource_type: ResourceType,
+        outcome: RequestOutcome,
+    ) -> Self {
+        Self {
+            url,
+            method,
+            resource_type,
+            outcome,
+            at: SystemTime::now(),
+        }
+    }
+
+    /// Status of the response, if one came back
+    pub fn status(&self) -> Option<u16> {
+        match self.outcome {
+            RequestOutcome::Completed { status, .. } => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Body bytes received
+    pub fn bytes(&self) -> u64 {
+        match self.outcome {
+            RequestOutcome::Completed { bytes, .. } => bytes,
+            _ => 0,
+        }
+    }
+
+    /// The policy that refused the request, if one did
+    pub fn blocked_by(&self) -> Option<&BlockingPolicy> {
+        match &self.outcome {
+            RequestOutcome::Blocked { policy, .. } => Some(policy),
+            _ => None,
+        }
+    }
+}
+
+/// Totals over a tab's logged requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LedgerSummary {
+    pub requests: usize,
+    pub blocked: usize,
+    pub failed: usize,
+    /// Body bytes of completed requests, cached ones included
+    pub bytes: u64,
+}
+
+/// Request logs of every open tab
+#[derive(Debug, Clone, Default)]
+pub struct RequestLedger {
+    tabs: Arc<RwLock<HashMap<Uuid, VecDeque<RequestRecord>>>>,
+}
+
+impl RequestLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log a request of `tab_id`
+    pub fn record(&self, tab_id: Uuid, record: RequestRecord) {
+        let mut tabs = self.tabs.write().unwrap_or_else(|e| e.into_inner());
+        let log = tabs.entry(tab_id).or_default();
+        if log.len() == MAX_ENTRIES_PER_TAB {
+            log.pop_front();
+        }
+        log.push_back(record);
+    }
+
+    /// A tab's logged requests, oldest first
+    pub fn requests(&self, tab_id: Uuid) -> Vec<RequestRecord> {
+        self.tabs
+            .read()
+            .ok()
+            .and_then(|tabs| tabs.get(&tab_id).map(|log| log.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+
+    /// Totals over a tab's logged requests
+    pub fn summary(&self, tab_id: Uuid) -> LedgerSummary {
+        let Ok(tabs) = self.tabs.read() else {
+            return LedgerSummary::default();
+        };
+        let Some(log) = tabs.get(&tab_id) else {
+            return LedgerSummary::default();
+        };
+        log.iter().fold(
+            LedgerSummary {
+                requests: log.len(),
+                ..LedgerSummary::default()
+            },
+            |mut summary, record| {
+                match &record.outcome {
+                    RequestOutcome::Completed { bytes, .. } => summary.bytes += bytes,
+                    RequestOutcome::Blocked { .. } => summary.blocked += 1,
+                    RequestOutcome::Failed(_) => summary.failed += 1,
+                }
+                summary
+            },
+        )
+    }
+
+    /// Forget a closed tab's log
+    pub fn remove(&self, tab_id: Uuid) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.remove(&tab_id);
+        }
+    }
+
+    /// Forget every tab's log
+    pub fn clear(&self) {
+        if let Ok(mut tabs) = self.tabs.write() {
+            tabs.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, outcome: RequestOutcome) -> RequestRecord {
+        RequestRecord::new(
+            Url::parse("https://site.test/")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            Method::GET,
+            ResourceType::Other,
+            outcome,
+        )
+    }
+
+    #[test]
+    fn test_logs_are_per_tab_and_bounded() {
+        let ledger = RequestLedger::new();
+        let (tab, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        ledger.record(
+            tab,
+            record(
+                "/",
+                RequestOutcome::Completed {
+                    status: 200,
+                    bytes: 1200,
+                    from_cache: false,
+                },
+            ),
+        );
+        ledger.record(
+            tab,
+            record(
+                "/ads.js",
+                RequestOutcome::Blocked {
+                    policy: BlockingPolicy::Interceptor("filter-lists".into()),
+                    reason: "matched ||ads.".into(),
+                },
+            ),
+        );
+        ledger.record(
+            other,
+            record("/x", RequestOutcome::Failed("timed out".into())),
+        );
+
+        let requests = ledger.requests(tab);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].status(), Some(200));
+        assert_eq!(
+            requests[1].blocked_by(),
+            Some(&BlockingPolicy::Interceptor("filter-lists".into()))
+        );
+        assert_eq!(
+            ledger.summary(tab),
+            LedgerSummary {
+                requests: 2,
+                blocked: 1,
+                failed: 0,
+                bytes: 1200,
+            }
+        );
+        assert_eq!(ledger.summary(other).failed, 1);
+
+        for n in 0..MAX_ENTRIES_PER_TAB {
+            ledger.record(
+                tab,
+                record(&format!("/{}", n), RequestOutcome::Failed("x".into())),
+            );
+        }
+        let requests = ledger.requests(tab);
+        assert_eq!(requests.len(), MAX_ENTRIES_PER_TAB);
+        assert_eq!(requests[0].url.path(), "/0");
+
+        ledger.remove(tab);
+        assert!(ledger.requests(tab).is_empty());
+        assert_eq!(ledger.requests(other).len(), 1);
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use bytes::Bytes;
+use url::Url;
 
 use crate::dns::CitadelDnsResolver;
 use crate::error::NetworkError;
@@ -29,6 +30,10 @@ pub enum ResourceType {
     Text,
     /// Binary data
     Binary,
+    /// Data a page script requested with `fetch()`
+    Fetch,
+    /// A WebSocket a page opened
+    WebSocket,
     /// Other/unknown type
     Other,
 }
@@ -54,6 +59,17 @@ impl Resource {
 
     /// Fetch a resource with the provided request, via the in-house HTTPS client.
     pub async fn fetch(&self, request: Request) -> Result<Response, NetworkError> {
+        self.fetch_isolated(request, None).await
+    }
+
+    /// Like [`Resource::fetch`], but with a SOCKS proxy configured the
+    /// stream goes through it on the circuit of `isolation` (a tab id), so a
+    /// page's subresources never leave from the user's own address
+    pub async fn fetch_isolated(
+        &self,
+        request: Request,
+        isolation: Option<&str>,
+    ) -> Result<Response, NetworkError> {
         // Apply privacy enhancements based on current settings.
         let prepared_request = if self.config.privacy_level == request.privacy_level() {
             request.prepare()
@@ -76,16 +92,30 @@ impl Resource {
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
-        let http_response = crate::http::fetch(&final_url, &headers).await?;
+        let http_response = match &self.config.socks_proxy {
+            Some(proxy) => {
+                crate::http::fetch_via_proxy(&final_url, &headers, proxy, isolation).await?
+            }
+            None => crate::http::fetch(&final_url, &headers).await?,
+        };
 
-        let header_map = http_response.headers.into_iter().collect();
+        let served_from = Url::parse(&http_response.final_url).unwrap_or(final_url);
         let mut response = Response::new(
             http_response.status,
-            header_map,
+            http_response.headers,
             Bytes::from(http_response.body),
-            final_url,
+            served_from,
             method,
         );
+        response.set_trailers(http_response.trailers.into());
+        response.set_redirect_chain(
+            http_response
+                .redirect_chain
+                .iter()
+                .filter_map(|url| Url::parse(url).ok())
+                .collect(),
+        );
+        response.set_timing(http_response.timing);
 
         // Flag any tracking attempts based on the response URL.
         self.detect_tracking_attempts(&mut response);
@@ -239,8 +239,18 @@ impl ResourceDiscovery {
                         Ok(resolved_url) => {
                             // Skip data URLs and javascript URLs for security
                             if self.is_safe_url(&resolved_url) {
-                                let resource_ref = ResourceRef::new(resolved_url, *resource_type)
-                                    .with_critical(*is_critical);
+                                let mut resource_ref =
+                                    ResourceRef::new(resolved_url, *resource_type)
+                                        .with_critical(*is_critical);
+
+                                // Scripts that never hold up rendering
+                                if *resource_type == ResourceType::Script {
+                                    let tag_start = captures.get(0).map_or(0, |m| m.start());
+                                    if let Some(loading) = script_loading(&html[tag_start..]) {
+                                        resource_ref =
+                                            resource_ref.with_metadata("loading", loading);
+                                    }
+                                }
 
                                 resources.push(resource_ref);
                                 resource_count += 1;
@@ -377,19 +387,7 @@ impl ResourceDiscovery {
 
     /// Resolve a potentially relative URL against a base URL
     fn resolve_url(&self, url_str: &str, base_url: &Url) -> Result<Url, NetworkError> {
-        // Handle absolute URLs
-        if url_str.starts_with("http://") || url_str.starts_with("https://") {
-            return Url::parse(url_str).map_err(NetworkError::UrlError);
-        }
-
-        // Handle protocol-relative URLs
-        if url_str.starts_with("//") {
-            let full_url = format!("{}:{}", base_url.scheme(), url_str);
-            return Url::parse(&full_url).map_err(NetworkError::UrlError);
-        }
-
-        // Handle relative URLs
-        base_url.join(url_str).map_err(NetworkError::UrlError)
+        crate::url_canon::resolve(base_url, url_str)
     }
 
     /// Check if a URL is safe to load (no javascript: or data: schemes for security)
@@ -431,6 +429,28 @@ impl ResourceDiscovery {
     }
 }
 
+/// `async` or `defer` when the script tag at the start of `html` has either
+/// attribute
+fn script_loading(html: &str) -> Option<&'static str> {
+    let tag = html.split('>').next().unwrap_or(html).to_ascii_lowercase();
+    let has = |attribute: &str| {
+        tag.split(|c: char| c.is_ascii_whitespace() || c == '/')
+            .any(|token| {
+                token == attribute
+                    || token
+                        .strip_prefix(attribute)
+                        .is_some_and(|rest| rest.starts_with('='))
+            })
+    };
+    if has("async") {
+        Some("async")
+    } else if has("defer") {
+        Some("defer")
+    } else {
+        None
+    }
+}
+
 impl Default for ResourceDiscovery {
     fn default() -> Self {
         Self::new().expect("Failed to create ResourceDiscovery with default patterns")
@@ -8,7 +8,7 @@ use tokio::time::timeout;
 use url::Url;
 
 use crate::cache::{CacheConfig, ResourceCache};
-use crate::error::NetworkError;
+use crate::error::{LoadErrorCategory, NetworkError};
 use crate::resource::{Resource, ResourceType};
 use crate::resource_discovery::{ResourceContext, ResourceDiscovery, ResourceRef};
 use crate::response::Response;
@@ -104,6 +104,8 @@ pub struct ResourceLoadResult {
     pub success: bool,
     /// Error message if load failed
     pub error: Option<String>,
+    /// What kind of failure it was
+    pub error_category: Option<LoadErrorCategory>,
     /// Whether the resource was served from cache
     pub from_cache: bool,
     /// Size of the loaded resource in bytes
@@ -163,6 +165,25 @@ pub struct LoadResult {
     pub total_time: Duration,
 }
 
+impl LoadResult {
+    /// Category of the error each failed URL ended with
+    pub fn error_categories(&self) -> HashMap<&Url, LoadErrorCategory> {
+        self.errors
+            .iter()
+            .map(|(url, error)| (url, error.category()))
+            .collect()
+    }
+
+    /// Number of failed URLs per category
+    pub fn failures_by_category(&self) -> HashMap<LoadErrorCategory, usize> {
+        let mut counts = HashMap::new();
+        for error in self.errors.values() {
+            *counts.entry(error.category()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
 /// Progress callback type
 type ProgressCallback = Arc<dyn Fn(&LoadProgress) + Send + Sync>;
 
@@ -405,6 +426,7 @@ impl ResourceLoader {
                                 resource_type: resource_ref.resource_type,
                                 success: true,
                                 error: None,
+                                error_category: None,
                                 from_cache: response.from_cache(),
                                 size_bytes: response.body().len(),
                                 load_time: Duration::from_millis(0), // Would be tracked in real implementation
@@ -415,6 +437,7 @@ impl ResourceLoader {
                                 resource_type: resource_ref.resource_type,
                                 success: false,
                                 error: Some(e.to_string()),
+                                error_category: Some(e.category()),
                                 from_cache: false,
                                 size_bytes: 0,
                                 load_time: Duration::from_millis(0),
@@ -599,11 +622,6 @@ impl ResourceLoader {
                         attempt + 1,
                         last_error
                     );
-
-                    // Don't retry on certain errors
-                    if !last_error.is_retryable() {
-                        break;
-                    }
                 }
                 Err(_) => {
                     last_error = NetworkError::TimeoutError(options.request_timeout);
@@ -611,17 +629,19 @@ impl ResourceLoader {
                 }
             }
 
-            // Wait before retry (exponential backoff)
-            if attempt < options.max_retries {
-                let delay = Duration::from_millis(100 * (1 << attempt));
-                tokio::time::sleep(delay).await;
+            // Wait before retry, as long as the failure's category allows one
+            let retry_delay = last_error.category().retry_policy().delay(attempt as u32);
+            match retry_delay {
+                Some(delay) if attempt < options.max_retries => tokio::time::sleep(delay).await,
+                _ => break,
             }
         }
 
         log::debug!(
-            "Failed to load {} after {} attempts",
+            "Failed to load {} ({:?}): {}",
             url,
-            options.max_retries + 1
+            last_error.category(),
+            last_error
         );
         Err(last_error)
     }
@@ -1,15 +1,28 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
+use citadel_security::privacy::{PrivacyEvent, PrivacyEventSender};
 use tokio::sync::Mutex;
 use url::Url;
+use uuid::Uuid;
 
+use crate::budget::{BudgetUsage, RequestBudget, TabBudgets};
+use crate::cache::{ResourceCache, ResourceCaches};
+use crate::cache_storage::CachePartition;
+use crate::cookie_jar::{CookieJar, CookieStoreId};
+use crate::csp::{CspPolicies, EnforcedPolicy};
 use crate::error::NetworkError;
+use crate::host_policy::ContainerPolicies;
+use crate::http::StreamRoute;
+use crate::interceptor::{InterceptContext, Interception, RequestInterceptor};
 use crate::request::{Method, Request};
+use crate::request_ledger::{BlockingPolicy, RequestLedger, RequestOutcome, RequestRecord};
 use crate::resource::{Resource, ResourceType};
 use crate::response::Response;
 use crate::tracker_blocking::TrackerBlockingEngine;
+use crate::websocket::{WebSocketConfig, WebSocketConnection, WebSocketPermissions};
 use crate::NetworkConfig;
 use crate::PrivacyLevel;
 
@@ -24,6 +37,9 @@ pub enum ResourcePolicy {
     BlockThirdParty,
     /// Block tracking resources (aggressive)
     BlockTracking,
+    /// Text-only browsing: fetch documents and data, never stylesheets,
+    /// scripts, images or fonts. Requests themselves look like any other.
+    TextOnly,
     /// Custom policy
     Custom,
 }
@@ -58,19 +74,6 @@ pub enum OriginType {
     Service,
 }
 
-/// Resource cache entry
-#[derive(Debug, Clone)]
-struct CacheEntry {
-    /// Cached response
-    response: Response,
-    /// When this entry expires
-    expires: Instant,
-    /// Entity tag for conditional requests
-    etag: Option<String>,
-    /// Last modified timestamp
-    last_modified: Option<String>,
-}
-
 /// Configuration for the ResourceManager
 #[derive(Debug, Clone)]
 pub struct ResourceManagerConfig {
@@ -84,12 +87,16 @@ pub struct ResourceManagerConfig {
     pub max_cache_size_mb: usize,
     /// Default cache TTL
     pub default_cache_ttl: Duration,
+    /// Per-tab request budget
+    pub request_budget: RequestBudget,
 }
 
 impl Default for ResourceManagerConfig {
     fn default() -> Self {
+        let network_config = NetworkConfig::default();
         Self {
-            network_config: NetworkConfig::default(),
+            request_budget: RequestBudget::for_privacy_level(network_config.privacy_level),
+            network_config,
             resource_policy: ResourcePolicy::BlockTracking,
             cache_policy: CachePolicy::Normal,
             max_cache_size_mb: 50, // 50MB default cache size
@@ -103,8 +110,8 @@ pub struct ResourceManager {
     /// Resource fetcher
     resource: Arc<Resource>,
 
-    /// Resource cache
-    cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    /// Response caches, one per partition
+    caches: ResourceCaches,
 
     /// Current configuration
     pub config: ResourceManagerConfig,
@@ -115,11 +122,32 @@ pub struct ResourceManager {
     /// Integrated tracker blocking engine
     tracker_blocker: Option<Arc<TrackerBlockingEngine>>,
 
+    /// Request/response interceptors, in registration order
+    interceptors: Arc<RwLock<Vec<Arc<dyn RequestInterceptor>>>>,
+
     /// Resource load stats for the current session
     load_stats: Arc<Mutex<ResourceStats>>,
 
     /// Main frame URL (top-level document)
     main_frame_url: Arc<RwLock<Option<Url>>>,
+
+    /// Per-tab request budgets
+    budgets: TabBudgets,
+
+    /// Host allow/block lists of containers
+    container_policies: ContainerPolicies,
+
+    /// Cookies, per store and top-level site
+    cookies: CookieJar,
+
+    /// Content-Security-Policy of each tab's page
+    csp_policies: CspPolicies,
+
+    /// Where blocked CSP violations are reported
+    privacy_sender: Option<PrivacyEventSender>,
+
+    /// Log of each tab's requests
+    ledger: RequestLedger,
 }
 
 /// Statistics about resource loading
@@ -194,14 +222,29 @@ impl ResourceManager {
             tracker_domains.insert(domain.to_string(), OriginType::SocialMedia);
         }
 
+        let budgets = TabBudgets::new(config.request_budget);
+
+        // The tracker blocker is the first interceptor
+        let mut interceptors: Vec<Arc<dyn RequestInterceptor>> = Vec::new();
+        if let Some(blocker) = &_tracker_blocker {
+            interceptors.push(blocker.clone());
+        }
+
         Ok(Self {
             resource,
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            caches: ResourceCaches::default(),
             config,
             tracker_domains: Arc::new(RwLock::new(tracker_domains)),
             load_stats: Arc::new(Mutex::new(ResourceStats::default())),
             main_frame_url: Arc::new(RwLock::new(None)),
             tracker_blocker: _tracker_blocker,
+            interceptors: Arc::new(RwLock::new(interceptors)),
+            budgets,
+            container_policies: ContainerPolicies::new(),
+            cookies: CookieJar::new(),
+            csp_policies: CspPolicies::new(),
+            privacy_sender: None,
+            ledger: RequestLedger::new(),
         })
     }
 
@@ -212,29 +255,91 @@ impl ResourceManager {
         }
     }
 
-    /// Check if a resource should be blocked based on policy (async version with tracker blocking)
-    async fn should_block_resource_advanced(
+    /// Register an interceptor after the existing ones
+    pub fn add_interceptor(&self, interceptor: Arc<dyn RequestInterceptor>) {
+        if let Ok(mut interceptors) = self.interceptors.write() {
+            log::info!("🔌 Request interceptor registered: {}", interceptor.name());
+            interceptors.push(interceptor);
+        }
+    }
+
+    /// Names of the registered interceptors, in the order they run
+    pub fn interceptor_names(&self) -> Vec<String> {
+        self.interceptor_chain()
+            .iter()
+            .map(|interceptor| interceptor.name().to_string())
+            .collect()
+    }
+
+    /// Snapshot of the interceptors, so no lock is held across their awaits
+    fn interceptor_chain(&self) -> Vec<Arc<dyn RequestInterceptor>> {
+        self.interceptors
+            .read()
+            .map(|interceptors| interceptors.clone())
+            .unwrap_or_default()
+    }
+
+    /// Run the request interceptors; returns the first to block and why
+    async fn intercept_request(
         &self,
-        url: &Url,
-        resource_type: ResourceType,
-    ) -> Option<String> {
-        // Use advanced tracker blocking engine if available
-        if let Some(ref blocker) = self.tracker_blocker {
-            if let Some(blocked) = blocker
-                .should_block_url(url.as_str(), Some(resource_type))
-                .await
-            {
-                // Record the blocked request
-                blocker.record_blocked_request(blocked.clone()).await;
-                return Some(blocked.reason);
+        request: &mut Request,
+        context: &InterceptContext,
+    ) -> Option<(BlockingPolicy, String)> {
+        for interceptor in self.interceptor_chain() {
+            if let Interception::Block(reason) = interceptor.on_request(request, context).await {
+                log::debug!("🔌 {} blocked a request", interceptor.name());
+                return Some((
+                    BlockingPolicy::Interceptor(interceptor.name().to_string()),
+                    reason,
+                ));
+            }
+        }
+        None
+    }
+
+    /// Run the response interceptors; returns the first to block and why
+    async fn intercept_response(
+        &self,
+        response: &mut Response,
+        context: &InterceptContext,
+    ) -> Option<(BlockingPolicy, String)> {
+        for interceptor in self.interceptor_chain() {
+            if let Interception::Block(reason) = interceptor.on_response(response, context).await {
+                log::debug!("🔌 {} blocked a response", interceptor.name());
+                return Some((
+                    BlockingPolicy::Interceptor(interceptor.name().to_string()),
+                    reason,
+                ));
             }
         }
+        None
+    }
 
-        // Fall back to basic policy checking
-        self.should_block_resource_basic(url, resource_type)
+    /// Count a blocked load
+    fn record_blocked(&self, reason: &str) {
+        if let Ok(mut stats) = self.load_stats.try_lock() {
+            *stats.blocked.entry(reason.to_string()).or_insert(0) += 1;
+            stats.failed_requests += 1;
+        }
     }
 
     /// Check if a resource should be blocked based on policy (basic version)
+    /// Why a request may not be sent, checking the container's host policy
+    /// and then the resource policy. Nothing here resolves the hostname.
+    fn should_block(
+        &self,
+        request: &Request,
+        resource_type: ResourceType,
+    ) -> Option<(BlockingPolicy, String)> {
+        if let Some(container_id) = request.container() {
+            if let Some(reason) = self.container_policies.check(container_id, request.url()) {
+                return Some((BlockingPolicy::ContainerHosts, reason));
+            }
+        }
+        self.should_block_resource_basic(request.url(), resource_type)
+            .map(|reason| (BlockingPolicy::ResourcePolicy, reason))
+    }
+
     fn should_block_resource_basic(
         &self,
         url: &Url,
@@ -285,6 +390,16 @@ impl ResourceManager {
                 }
             }
 
+            ResourcePolicy::TextOnly => match resource_type {
+                ResourceType::Css
+                | ResourceType::Script
+                | ResourceType::Image
+                | ResourceType::Font => {
+                    Some(format!("{:?} skipped in text-only mode", resource_type))
+                }
+                _ => None,
+            },
+
             ResourcePolicy::Custom => {
                 // Custom policies would be implemented here
                 None
@@ -338,140 +453,57 @@ impl ResourceManager {
         OriginType::ThirdParty
     }
 
-    /// Extract the base domain from a host
-    fn extract_domain(host: &str) -> String {
-        let parts: Vec<&str> = host.split('.').collect();
-
-        // For hosts with enough parts, take the last two (e.g., example.com from www.example.com)
-        if parts.len() >= 2 {
-            let domain = format!("{}.{}", parts[parts.len() - 2], parts[parts.len() - 1]);
-
-            // Handle special cases like co.uk
-            if parts.len() >= 3
-                && ((parts[parts.len() - 1] == "uk" && parts[parts.len() - 2] == "co") ||
-                (parts[parts.len() - 1] == "au" && parts[parts.len() - 2] == "com") || 
-                // Add other special TLDs as needed
-                false)
-            {
-                return format!(
-                    "{}.{}.{}",
-                    parts[parts.len() - 3],
-                    parts[parts.len() - 2],
-                    parts[parts.len() - 1]
-                );
-            }
-
-            return domain;
+    /// The site of a host: its registrable domain by the public suffix
+    /// list (`example.co.uk` for `www.example.co.uk`, `alice.github.io` for
+    /// itself). An IP address, or a host that is itself a public suffix, is
+    /// its own site.
+    pub(crate) fn extract_domain(host: &str) -> String {
+        if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+            return host.to_string();
         }
-
-        // If we can't determine, return the original
-        host.to_string()
+        psl::domain_str(host).unwrap_or(host).to_string()
     }
 
-    /// Check if a resource is in the cache
-    fn check_cache(&self, url: &Url) -> Option<Response> {
-        // Apply cache policy
-        if self.config.cache_policy == CachePolicy::NeverCache {
-            return None;
-        }
-
-        if let Ok(cache) = self.cache.read() {
-            let key = url.as_str();
-
-            if let Some(entry) = cache.get(key) {
-                // Check if expired
-                if entry.expires > Instant::now()
-                    || self.config.cache_policy == CachePolicy::PreferCache
-                {
-                    // Update stats
-                    if let Ok(mut stats) = self.load_stats.try_lock() {
-                        stats.cache_hits += 1;
-                    }
-
-                    return Some(entry.response.clone());
-                }
-            }
-        }
-
-        None
+    /// Whether `domain` is a public suffix such as `com`, `co.uk` or
+    /// `github.io`, under which every name is a separate site
+    pub(crate) fn is_public_suffix(domain: &str) -> bool {
+        psl::suffix_str(domain) == Some(domain)
     }
 
-    /// Update the cache with a new response
-    fn update_cache(&self, url: &Url, response: Response) {
-        // Don't cache if policy is NeverCache
-        if self.config.cache_policy == CachePolicy::NeverCache {
-            return;
-        }
-
-        // Get cache control headers
-        let cache_control = response.header("cache-control").map(|s| s.to_lowercase());
-
-        // Check for no-store directive
-        if let Some(cc) = &cache_control {
-            if cc.contains("no-store") {
-                return;
-            }
-        }
-
-        // Calculate TTL
-        let ttl = if let Some(cc) = &cache_control {
-            if cc.contains("no-cache") && self.config.cache_policy != CachePolicy::PreferCache {
-                // Honor no-cache unless we're set to prefer cache
-                return;
-            }
-
-            // Try to parse max-age
-            if let Some(max_age_pos) = cc.find("max-age=") {
-                let max_age_str = &cc[max_age_pos + 8..];
-                if let Some(end_pos) = max_age_str.find(|c: char| !c.is_ascii_digit()) {
-                    if let Ok(seconds) = max_age_str[..end_pos].parse::<u64>() {
-                        Duration::from_secs(seconds)
-                    } else {
-                        self.config.default_cache_ttl
-                    }
-                } else if let Ok(seconds) = max_age_str.parse::<u64>() {
-                    Duration::from_secs(seconds)
-                } else {
-                    self.config.default_cache_ttl
-                }
-            } else {
-                self.config.default_cache_ttl
-            }
-        } else {
-            self.config.default_cache_ttl
-        };
-
-        // Extract ETag
-        let etag = response.header("etag").cloned();
-
-        // Extract Last-Modified
-        let last_modified = response.header("last-modified").cloned();
-
-        // Create cache entry
-        let entry = CacheEntry {
-            response: response.clone(),
-            expires: Instant::now() + ttl,
-            etag,
-            last_modified,
+    /// Cache a request's response is kept in: its partition's, or for
+    /// requests made for no tab, the one of the site it addresses. A tab's
+    /// request that names no partition is not cached, since which tabs may
+    /// share its response is unknown.
+    fn cache_for(&self, tab_id: Option<Uuid>, request: &Request) -> Option<Arc<ResourceCache>> {
+        let partition = match tab_id {
+            Some(_) => CachePartition::for_request(request)?,
+            None => CachePartition::for_request(request)
+                .or_else(|| CachePartition::for_url(request.url()))?,
         };
+        Some(self.caches.for_partition(partition))
+    }
 
-        // Update cache
-        if let Ok(mut cache) = self.cache.write() {
-            cache.insert(url.as_str().to_string(), entry);
-
-            // Implement cache size management (simple version)
-            // A real implementation would track memory usage and evict oldest entries
-            if cache.len() > self.config.max_cache_size_mb * 20 {
-                // rough estimate
-                // Remove oldest 25% of entries
-                let keys_to_remove: Vec<String> =
-                    cache.keys().take(cache.len() / 4).cloned().collect();
-
-                for key in keys_to_remove {
-                    cache.remove(&key);
-                }
-            }
+    /// A cached response for `url`, expired ones included when the policy
+    /// prefers the cache
+    fn check_cache(
+        &self,
+        cache: &ResourceCache,
+        url: &Url,
+        cache_policy: CachePolicy,
+    ) -> Option<Response> {
+        let mut response = match cache_policy {
+            CachePolicy::NeverCache => return None,
+            CachePolicy::PreferCache => cache
+                .get_for_validation(url)
+                .map(|entry| entry.response)
+                .or_else(|| cache.get(url)),
+            CachePolicy::Normal | CachePolicy::AlwaysValidate => cache.get(url),
+        }?;
+        if let Ok(mut stats) = self.load_stats.try_lock() {
+            stats.cache_hits += 1;
         }
+        response.set_from_cache(true);
+        Some(response)
     }
 
     /// Fetch a resource with privacy protections
@@ -480,61 +512,102 @@ impl ResourceManager {
         url: &str,
         resource_type: Option<ResourceType>,
     ) -> Result<Response, NetworkError> {
-        let url = Url::parse(url).map_err(NetworkError::UrlError)?;
+        // Determine resource type if not specified
+        let resource_type = resource_type.unwrap_or(ResourceType::Other);
+        let request = Self::typed_request(url, resource_type)?;
+        self.fetch_request(request, resource_type).await
+    }
+
+    /// A GET request for `url` accepting what `resource_type` is
+    fn typed_request(url: &str, resource_type: ResourceType) -> Result<Request, NetworkError> {
+        let builder = Request::builder().method(Method::GET).url(url);
+        match resource_type {
+            ResourceType::Html => builder.header("Accept", "text/html,application/xhtml+xml"),
+            ResourceType::Css => builder.header("Accept", "text/css"),
+            ResourceType::Script => {
+                builder.header("Accept", "application/javascript,text/javascript")
+            }
+            ResourceType::Image => builder.header("Accept", "image/*"),
+            ResourceType::Font => builder.header("Accept", "font/*,application/font-*"),
+            ResourceType::Json => builder.header("Accept", "application/json"),
+            ResourceType::Xml => builder.header("Accept", "application/xml,text/xml"),
+            ResourceType::Text => builder.header("Accept", "text/plain"),
+            _ => builder,
+        }
+        .build()
+    }
+
+    /// Fetch a prepared request with privacy protections. The request's cache
+    /// mode, when set, overrides the configured cache policy.
+    pub async fn fetch_request(
+        &self,
+        request: Request,
+        resource_type: ResourceType,
+    ) -> Result<Response, NetworkError> {
+        self.fetch_logged(None, request, resource_type).await
+    }
+
+    /// Fetch a prepared request, logging how it went to `tab_id`'s ledger
+    /// when it is made for a tab
+    async fn fetch_logged(
+        &self,
+        tab_id: Option<Uuid>,
+        mut request: Request,
+        resource_type: ResourceType,
+    ) -> Result<Response, NetworkError> {
+        let url = request.url().clone();
+        let method = request.method().clone();
+        let cache_policy = request.cache_mode().unwrap_or(self.config.cache_policy);
+        let log = |outcome: RequestOutcome| {
+            if let Some(tab_id) = tab_id {
+                self.ledger.record(
+                    tab_id,
+                    RequestRecord::new(url.clone(), method.clone(), resource_type, outcome),
+                );
+            }
+        };
+        let blocked = |policy: BlockingPolicy, reason: String| {
+            self.record_blocked(&reason);
+            log(RequestOutcome::Blocked {
+                policy,
+                reason: reason.clone(),
+            });
+            NetworkError::PrivacyViolationError(reason)
+        };
 
         // Update stats
         if let Ok(mut stats) = self.load_stats.try_lock() {
             stats.total_requests += 1;
         }
 
-        // Determine resource type if not specified
-        let resource_type = resource_type.unwrap_or(ResourceType::Other);
-
-        // Check policy before loading (async)
-        if let Some(block_reason) = self
-            .should_block_resource_advanced(&url, resource_type)
-            .await
-        {
-            // Update blocked stats
-            if let Ok(mut stats) = self.load_stats.try_lock() {
-                let counter = stats.blocked.entry(block_reason.clone()).or_insert(0);
-                *counter += 1;
-                stats.failed_requests += 1;
-            }
+        // Interceptors (tracker blocker first) see the request before anything else
+        let context = InterceptContext {
+            resource_type,
+            main_frame: self.main_frame_url.read().ok().and_then(|url| url.clone()),
+        };
+        if let Some((policy, reason)) = self.intercept_request(&mut request, &context).await {
+            return Err(blocked(policy, reason));
+        }
 
-            return Err(NetworkError::PrivacyViolationError(block_reason));
+        // Then the container's host policy and the resource policy
+        if let Some((policy, reason)) = self.should_block(&request, resource_type) {
+            return Err(blocked(policy, reason));
         }
 
-        // Check cache first
-        if let Some(cached) = self.check_cache(&url) {
+        // Check the request's partition of the cache first
+        let cache = self.cache_for(tab_id, &request);
+        if let Some(cached) = cache
+            .as_ref()
+            .and_then(|cache| self.check_cache(cache, &url, cache_policy))
+        {
+            log(RequestOutcome::Completed {
+                status: cached.status(),
+                bytes: cached.body().len() as u64,
+                from_cache: true,
+            });
             return Ok(cached);
         }
 
-        // If not in cache, create a request based on resource type
-        let request = match resource_type {
-            ResourceType::Html => Request::new(Method::GET, url.as_str())?
-                .with_header("Accept", "text/html,application/xhtml+xml"),
-            ResourceType::Css => {
-                Request::new(Method::GET, url.as_str())?.with_header("Accept", "text/css")
-            }
-            ResourceType::Script => Request::new(Method::GET, url.as_str())?
-                .with_header("Accept", "application/javascript,text/javascript"),
-            ResourceType::Image => {
-                Request::new(Method::GET, url.as_str())?.with_header("Accept", "image/*")
-            }
-            ResourceType::Font => Request::new(Method::GET, url.as_str())?
-                .with_header("Accept", "font/*,application/font-*"),
-            ResourceType::Json => {
-                Request::new(Method::GET, url.as_str())?.with_header("Accept", "application/json")
-            }
-            ResourceType::Xml => Request::new(Method::GET, url.as_str())?
-                .with_header("Accept", "application/xml,text/xml"),
-            ResourceType::Text => {
-                Request::new(Method::GET, url.as_str())?.with_header("Accept", "text/plain")
-            }
-            _ => Request::new(Method::GET, url.as_str())?,
-        };
-
         // Set privacy level based on origin type
         let origin_type = self.classify_origin(&url, None);
         let privacy_level = match origin_type {
@@ -552,50 +625,79 @@ impl ResourceManager {
         };
 
         // Add cache validation headers if needed
-        let request_with_validation = if self.config.cache_policy == CachePolicy::AlwaysValidate {
-            if let Ok(cache) = self.cache.read() {
-                if let Some(entry) = cache.get(url.as_str()) {
-                    let mut req = request;
-
-                    // Add ETag if available
-                    if let Some(etag) = &entry.etag {
-                        req = req.with_header("If-None-Match", etag);
-                    }
-
-                    // Add Last-Modified if available
-                    if let Some(last_modified) = &entry.last_modified {
-                        req = req.with_header("If-Modified-Since", last_modified);
-                    }
+        let validating = cache
+            .as_ref()
+            .filter(|_| cache_policy == CachePolicy::AlwaysValidate)
+            .and_then(|cache| cache.get_for_validation(&url));
+        let request_with_validation = match validating {
+            Some(entry) => {
+                let mut req = request;
+
+                // Add ETag if available
+                if let Some(etag) = &entry.etag {
+                    req = req.with_header("If-None-Match", etag);
+                }
 
-                    req
-                } else {
-                    request
+                // Add Last-Modified if available
+                if let Some(last_modified) = &entry.last_modified {
+                    req = req.with_header("If-Modified-Since", last_modified);
                 }
-            } else {
-                request
+
+                req
             }
-        } else {
-            request
+            None => request,
         };
 
-        // Prepare the request with the appropriate privacy level
-        let final_request = request_with_validation
+        // Prepare the request with the appropriate privacy level. Cookies
+        // go on afterwards, so privacy levels that strip headers keep them.
+        let mut final_request = request_with_validation
             .with_privacy_level(privacy_level)
             .prepare();
-
-        // Fetch the resource
-        let result = self.resource.fetch(final_request).await;
+        let top_level = context.main_frame.clone().unwrap_or_else(|| url.clone());
+        self.cookies.attach(&mut final_request, &top_level);
+        let cookie_store = CookieStoreId::for_request(&final_request);
+
+        // Fetch the resource, through the proxy on the tab's own circuit
+        // when one is configured
+        let isolation = tab_id.map(|id| id.to_string());
+        let result = self
+            .resource
+            .fetch_isolated(final_request, isolation.as_deref())
+            .await;
 
         match result {
-            Ok(response) => {
+            Ok(mut response) => {
                 // Update stats
                 if let Ok(mut stats) = self.load_stats.try_lock() {
                     stats.successful_requests += 1;
                     stats.bytes_transferred += response.body().len();
                 }
 
+                // Interceptors see the response before it is cached
+                if let Some((policy, reason)) =
+                    self.intercept_response(&mut response, &context).await
+                {
+                    return Err(blocked(policy, reason));
+                }
+                log(RequestOutcome::Completed {
+                    status: response.status(),
+                    bytes: response.body().len() as u64,
+                    from_cache: false,
+                });
+
+                self.cookies.set_cookies(
+                    cookie_store,
+                    &top_level,
+                    response.url(),
+                    response.headers().get_all("set-cookie"),
+                );
+
                 // Update cache
-                self.update_cache(&url, response.clone());
+                if let Some(cache) = cache.filter(|_| cache_policy != CachePolicy::NeverCache) {
+                    if let Err(e) = cache.put(&url, response.clone()) {
+                        log::debug!("Not caching {}: {}", url, e);
+                    }
+                }
 
                 Ok(response)
             }
@@ -604,6 +706,7 @@ impl ResourceManager {
                 if let Ok(mut stats) = self.load_stats.try_lock() {
                     stats.failed_requests += 1;
                 }
+                log(RequestOutcome::Failed(e.to_string()));
 
                 Err(e)
             }
@@ -622,11 +725,292 @@ impl ResourceManager {
         self.fetch(url, Some(ResourceType::Html)).await
     }
 
-    /// Clear the resource cache
-    pub fn clear_cache(&self) {
-        if let Ok(mut cache) = self.cache.write() {
-            cache.clear();
+    /// Refuse a tab's request that its page's Content-Security-Policy does
+    /// not allow, reporting the violation
+    fn enforce_csp(
+        &self,
+        tab_id: Uuid,
+        url: &Url,
+        resource_type: ResourceType,
+    ) -> Result<(), NetworkError> {
+        let Some(report) = self.csp_policies.check(tab_id, url, resource_type) else {
+            return Ok(());
+        };
+        log::info!(
+            "🛡️ CSP {} blocked {}",
+            report.effective_directive,
+            report.blocked
+        );
+        if let Some(sender) = &self.privacy_sender {
+            sender.emit(PrivacyEvent::CspViolation {
+                directive: report.effective_directive.clone(),
+                blocked_uri: report.blocked.to_string(),
+            });
+        }
+        let reason = format!(
+            "Blocked by Content-Security-Policy {}",
+            report.violated_directive
+        );
+        self.record_blocked(&reason);
+        Err(NetworkError::PrivacyViolationError(reason))
+    }
+
+    /// Fetch a resource on behalf of a tab, enforcing the tab's request budget
+    /// and its page's Content-Security-Policy
+    pub async fn fetch_for_tab(
+        &self,
+        tab_id: Uuid,
+        url: &str,
+        resource_type: Option<ResourceType>,
+    ) -> Result<Response, NetworkError> {
+        let resource_type = resource_type.unwrap_or(ResourceType::Other);
+        let request = Self::typed_request(url, resource_type)?;
+        self.fetch_request_for_tab(tab_id, request, resource_type)
+            .await
+    }
+
+    /// Fetch a prepared request on behalf of a tab, enforcing the tab's
+    /// request budget and its page's Content-Security-Policy, the latter on
+    /// every URL a redirect passed through as well
+    pub async fn fetch_request_for_tab(
+        &self,
+        tab_id: Uuid,
+        request: Request,
+        resource_type: ResourceType,
+    ) -> Result<Response, NetworkError> {
+        let url = request.url().clone();
+        let method = request.method().clone();
+        let refused = |url: &Url, policy: BlockingPolicy, error: NetworkError| {
+            self.ledger.record(
+                tab_id,
+                RequestRecord::new(
+                    url.clone(),
+                    method.clone(),
+                    resource_type,
+                    RequestOutcome::Blocked {
+                        policy,
+                        reason: error.to_string(),
+                    },
+                ),
+            );
+            error
+        };
+        self.enforce_csp(tab_id, &url, resource_type)
+            .map_err(|e| refused(&url, BlockingPolicy::ContentSecurityPolicy, e))?;
+        let budget = self.budgets.tab(tab_id);
+        let _permit = budget
+            .begin(&url, resource_type)
+            .await
+            .map_err(|e| refused(&url, BlockingPolicy::RequestBudget, e))?;
+
+        let response = self
+            .fetch_logged(Some(tab_id), request, resource_type)
+            .await?;
+        if !response.from_cache() {
+            budget.record_bytes(response.body().len() as u64)?;
+        }
+        // Redirects are followed below the policy; none may end up where the
+        // page could not have asked for directly
+        for hop in response.redirect_chain().iter().chain([response.url()]) {
+            self.enforce_csp(tab_id, hop, resource_type)
+                .map_err(|e| refused(hop, BlockingPolicy::ContentSecurityPolicy, e))?;
         }
+        Ok(response)
+    }
+
+    /// Open a WebSocket to `url` for a tab's page at `page`, held to what the
+    /// tab's fetches are: its page's CSP `connect-src`, the interceptors
+    /// (blocklists among them), the container's host policy and the resource
+    /// policy, which see the handshake as the `https:` request it is on the
+    /// wire. Then `permissions` must grant it. With a SOCKS proxy configured
+    /// the socket goes through it, on the tab's own circuit.
+    pub async fn open_websocket_for_tab(
+        &self,
+        tab_id: Uuid,
+        container: Option<Uuid>,
+        url: &Url,
+        page: &Url,
+        permissions: &WebSocketPermissions,
+        config: &WebSocketConfig,
+    ) -> Result<WebSocketConnection, NetworkError> {
+        let resource_type = ResourceType::WebSocket;
+        let log = |outcome: RequestOutcome| {
+            self.ledger.record(
+                tab_id,
+                RequestRecord::new(url.clone(), Method::GET, resource_type, outcome),
+            );
+        };
+        let blocked = |policy: BlockingPolicy, reason: String| {
+            self.record_blocked(&reason);
+            log(RequestOutcome::Blocked {
+                policy,
+                reason: reason.clone(),
+            });
+            NetworkError::PrivacyViolationError(reason)
+        };
+
+        self.enforce_csp(tab_id, url, resource_type).map_err(|e| {
+            log(RequestOutcome::Blocked {
+                policy: BlockingPolicy::ContentSecurityPolicy,
+                reason: e.to_string(),
+            });
+            e
+        })?;
+        let mut handshake = url.clone();
+        let scheme = if url.scheme() == "ws" {
+            "http"
+        } else {
+            "https"
+        };
+        handshake
+            .set_scheme(scheme)
+            .map_err(|_| NetworkError::ConnectionError(format!("{url} is not a WebSocket URL")))?;
+        let mut builder = Request::builder()
+            .method(Method::GET)
+            .url(handshake.as_str());
+        if let Some(container_id) = container {
+            builder = builder.container(container_id);
+        }
+        let mut request = builder.build()?;
+        let context = InterceptContext {
+            resource_type,
+            main_frame: Some(page.clone()),
+        };
+        if let Some((policy, reason)) = self.intercept_request(&mut request, &context).await {
+            return Err(blocked(policy, reason));
+        }
+        if let Some((policy, reason)) = self.should_block(&request, resource_type) {
+            return Err(blocked(policy, reason));
+        }
+
+        let isolation = tab_id.to_string();
+        let route = match &self.config.network_config.socks_proxy {
+            Some(proxy) => StreamRoute::Proxy {
+                proxy,
+                isolation: Some(isolation.as_str()),
+            },
+            None => StreamRoute::Direct,
+        };
+        match WebSocketConnection::connect(url, page, permissions, config, route).await {
+            Ok(socket) => {
+                log(RequestOutcome::Completed {
+                    status: 101,
+                    bytes: 0,
+                    from_cache: false,
+                });
+                Ok(socket)
+            }
+            Err(e) => {
+                log(RequestOutcome::Failed(e.to_string()));
+                Err(e)
+            }
+        }
+    }
+
+    /// Fetch a tab's top-level document, starting a fresh budget for the page
+    /// and enforcing the document's Content-Security-Policy, from its headers
+    /// and `<meta>` tags, from then on
+    pub async fn fetch_html_for_tab(
+        &self,
+        tab_id: Uuid,
+        url: &str,
+    ) -> Result<Response, NetworkError> {
+        let parsed_url = Url::parse(url).map_err(NetworkError::UrlError)?;
+        self.set_main_frame_url(parsed_url.clone());
+        self.budgets.tab(tab_id).start_navigation(&parsed_url);
+        self.csp_policies.set(tab_id, &parsed_url, None);
+
+        let response = self
+            .fetch_for_tab(tab_id, url, Some(ResourceType::Html))
+            .await?;
+        self.csp_policies.set(
+            tab_id,
+            response.url(),
+            EnforcedPolicy::from_response(response.headers(), response.body()),
+        );
+        Ok(response)
+    }
+
+    /// Log of each tab's requests
+    pub fn request_ledger(&self) -> &RequestLedger {
+        &self.ledger
+    }
+
+    /// Log tabs' requests to `ledger`, e.g. one the browser also reads
+    pub fn with_request_ledger(mut self, ledger: RequestLedger) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// Content-Security-Policy of each tab's page
+    pub fn csp_policies(&self) -> &CspPolicies {
+        &self.csp_policies
+    }
+
+    /// Enforce the policies in `policies`, e.g. those a browser engine
+    /// records for the pages it loads itself
+    pub fn with_csp_policies(mut self, policies: CspPolicies) -> Self {
+        self.csp_policies = policies;
+        self
+    }
+
+    /// Report blocked CSP violations to `sender`, for the privacy scoreboard
+    pub fn with_privacy_sender(mut self, sender: PrivacyEventSender) -> Self {
+        self.privacy_sender = Some(sender);
+        self
+    }
+
+    /// Per-tab request budgets
+    pub fn budgets(&self) -> &TabBudgets {
+        &self.budgets
+    }
+
+    /// Host policies of containers, checked for requests carrying a container
+    pub fn container_policies(&self) -> &ContainerPolicies {
+        &self.container_policies
+    }
+
+    /// Check requests against shared container policies, such as the
+    /// engine's, instead of a set of its own
+    pub fn with_container_policies(mut self, policies: ContainerPolicies) -> Self {
+        self.container_policies = policies;
+        self
+    }
+
+    /// Cookies of every store
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// Keep cookies in a shared jar, such as the engine's, instead of one
+    /// of its own
+    pub fn with_cookie_jar(mut self, cookies: CookieJar) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    /// Account tabs' requests against shared budgets, such as the engine's,
+    /// instead of ones of its own
+    pub fn with_budgets(mut self, budgets: TabBudgets) -> Self {
+        self.budgets = budgets;
+        self
+    }
+
+    /// Budget consumption of a tab
+    pub fn budget_usage(&self, tab_id: Uuid) -> Option<BudgetUsage> {
+        self.budgets.usage(tab_id)
+    }
+
+    /// Keep responses in shared partitioned caches, such as the engine's,
+    /// instead of ones of its own
+    pub fn with_resource_caches(mut self, caches: ResourceCaches) -> Self {
+        self.caches = caches;
+        self
+    }
+
+    /// Clear every partition of the resource cache
+    pub fn clear_cache(&self) {
+        self.caches.clear();
     }
 
     /// Get current resource stats
@@ -670,6 +1054,13 @@ impl ResourceManager {
 
     /// Set the tracker blocking engine
     pub fn set_tracker_blocker(&mut self, tracker_blocker: Arc<TrackerBlockingEngine>) {
+        if let Ok(mut interceptors) = self.interceptors.write() {
+            // Replace a previously set blocker in its slot at the front
+            if self.tracker_blocker.is_some() && !interceptors.is_empty() {
+                interceptors.remove(0);
+            }
+            interceptors.insert(0, tracker_blocker.clone());
+        }
         self.tracker_blocker = Some(tracker_blocker);
         log::info!("🛡️ Tracker blocking engine integrated with resource manager");
     }
@@ -754,5 +1145,376 @@ mod tests {
             ResourceManager::extract_domain("cdn.assets.example.com"),
             "example.com"
         );
+        assert_eq!(
+            ResourceManager::extract_domain("www.example.com.au"),
+            "example.com.au"
+        );
+        assert_eq!(
+            ResourceManager::extract_domain("alice.github.io"),
+            "alice.github.io"
+        );
+        assert_eq!(ResourceManager::extract_domain("10.0.0.1"), "10.0.0.1");
+        assert_eq!(ResourceManager::extract_domain("[::1]"), "[::1]");
+        assert!(ResourceManager::is_public_suffix("co.uk"));
+        assert!(!ResourceManager::is_public_suffix("example.co.uk"));
+    }
+
+    /// Tags every request with a header
+    struct Tagger;
+
+    impl RequestInterceptor for Tagger {
+        fn name(&self) -> &str {
+            "tagger"
+        }
+
+        fn on_request<'a>(
+            &'a self,
+            request: &'a mut Request,
+            _context: &'a InterceptContext,
+        ) -> futures::future::BoxFuture<'a, Interception> {
+            Box::pin(async move {
+                request.set_header("X-Tagged", "1");
+                Interception::Continue
+            })
+        }
+    }
+
+    /// Blocks requests carrying the tagger's header
+    struct BlockTagged;
+
+    impl RequestInterceptor for BlockTagged {
+        fn name(&self) -> &str {
+            "block-tagged"
+        }
+
+        fn on_request<'a>(
+            &'a self,
+            request: &'a mut Request,
+            context: &'a InterceptContext,
+        ) -> futures::future::BoxFuture<'a, Interception> {
+            Box::pin(async move {
+                match request.headers().get("X-Tagged") {
+                    Some(_) => Interception::Block(format!("tagged {:?}", context.resource_type)),
+                    None => Interception::Continue,
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_run_in_order_and_can_block() {
+        let config = ResourceManagerConfig {
+            resource_policy: ResourcePolicy::AllowAll,
+            ..ResourceManagerConfig::default()
+        };
+        let manager = ResourceManager::with_config(config).await.unwrap();
+        manager.add_interceptor(Arc::new(Tagger));
+        manager.add_interceptor(Arc::new(BlockTagged));
+        assert_eq!(manager.interceptor_names(), vec!["tagger", "block-tagged"]);
+
+        let err = manager
+            .fetch("https://example.com/app.js", Some(ResourceType::Script))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NetworkError::PrivacyViolationError(reason) if reason == "tagged Script"
+        ));
+        let stats = manager.get_stats().await;
+        assert_eq!(stats.blocked.get("tagged Script"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_text_only_policy_skips_page_resources() {
+        let config = ResourceManagerConfig {
+            resource_policy: ResourcePolicy::TextOnly,
+            ..ResourceManagerConfig::default()
+        };
+        let manager = ResourceManager::with_config(config).await.unwrap();
+        for resource_type in [ResourceType::Css, ResourceType::Image, ResourceType::Font] {
+            let err = manager
+                .fetch("https://example.com/asset", Some(resource_type))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, NetworkError::PrivacyViolationError(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tracker_blocker_is_an_interceptor() {
+        let manager = ResourceManager::with_tracker_blocking(ResourceManagerConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(manager.interceptor_names(), vec!["tracker-blocking"]);
+
+        let err = manager
+            .fetch("https://doubleclick.net/ad.js", Some(ResourceType::Script))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, NetworkError::PrivacyViolationError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_container_host_policy_blocks_before_fetching() {
+        let manager = ResourceManager::new().await.unwrap();
+        let work = Uuid::new_v4();
+        manager
+            .container_policies()
+            .set(work, crate::HostPolicy::allow_only(["*.corp.example"]));
+
+        let request = Request::builder()
+            .url("https://news.test/")
+            .container(work)
+            .build()
+            .unwrap();
+        let err = manager
+            .fetch_request(request, ResourceType::Html)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NetworkError::PrivacyViolationError(reason) if reason.contains("news.test")
+        ));
+        assert_eq!(manager.get_stats().await.successful_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_page_csp_blocks_tab_requests_before_fetching() {
+        let (sender, mut receiver) = citadel_security::create_privacy_channel();
+        let manager = ResourceManager::new()
+            .await
+            .unwrap()
+            .with_privacy_sender(sender);
+        let tab = Uuid::new_v4();
+        let page = Url::parse("https://news.test/").unwrap();
+        manager.csp_policies().set(
+            tab,
+            &page,
+            crate::EnforcedPolicy::parse("script-src 'self'"),
+        );
+
+        let err = manager
+            .fetch_for_tab(tab, "https://cdn.test/app.js", Some(ResourceType::Script))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NetworkError::PrivacyViolationError(reason) if reason.contains("script-src")
+        ));
+        assert_eq!(manager.get_stats().await.successful_requests, 0);
+        match receiver.try_recv() {
+            Ok(PrivacyEvent::CspViolation {
+                directive,
+                blocked_uri,
+            }) => {
+                assert_eq!(directive, "script-src");
+                assert_eq!(blocked_uri, "https://cdn.test/app.js");
+            }
+            other => panic!("expected a CSP violation, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websockets_are_held_to_tab_policies_and_the_proxy() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = crate::SocksProxy::new(listener.local_addr().unwrap().to_string());
+        let config = ResourceManagerConfig {
+            network_config: NetworkConfig {
+                socks_proxy: Some(proxy),
+                ..NetworkConfig::default()
+            },
+            ..ResourceManagerConfig::default()
+        };
+        let manager = ResourceManager::with_config(config).await.unwrap();
+        let page = Url::parse("https://chat.test/room").unwrap();
+        let permissions = WebSocketPermissions::new();
+        permissions.grant(
+            &page,
+            crate::HostPolicy::allow_only(["chat.test", "other.test"]),
+        );
+        let url = |s: &str| Url::parse(s).unwrap();
+        let open = |tab: Uuid, container: Option<Uuid>, target: Url| {
+            let (manager, page, permissions) = (&manager, &page, &permissions);
+            async move {
+                manager
+                    .open_websocket_for_tab(
+                        tab,
+                        container,
+                        &target,
+                        page,
+                        permissions,
+                        &WebSocketConfig::default(),
+                    )
+                    .await
+                    .unwrap_err()
+            }
+        };
+        let blocked_by = |tab: Uuid| {
+            manager
+                .request_ledger()
+                .requests(tab)
+                .last()
+                .and_then(|record| record.blocked_by().cloned())
+        };
+
+        // The page's connect-src, where 'self' covers its own wss: endpoint
+        let tab = Uuid::new_v4();
+        manager.csp_policies().set(
+            tab,
+            &page,
+            crate::EnforcedPolicy::parse("connect-src 'self'"),
+        );
+        open(tab, None, url("wss://other.test/")).await;
+        assert_eq!(blocked_by(tab), Some(BlockingPolicy::ContentSecurityPolicy));
+
+        // The container's hosts
+        let (other_tab, work) = (Uuid::new_v4(), Uuid::new_v4());
+        manager
+            .container_policies()
+            .set(work, crate::HostPolicy::allow_only(["chat.test"]));
+        open(other_tab, Some(work), url("wss://other.test/")).await;
+        assert_eq!(blocked_by(other_tab), Some(BlockingPolicy::ContainerHosts));
+
+        // Allowed sockets go through the proxy, which resolves the host
+        let proxied = tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            client.read_u8().await.unwrap()
+        });
+        open(tab, None, url("wss://chat.test/socket")).await;
+        assert_eq!(proxied.await.unwrap(), 5, "a SOCKS5 greeting");
+    }
+
+    #[tokio::test]
+    async fn test_tab_fetches_go_through_the_proxy() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ResourceManagerConfig {
+            network_config: NetworkConfig {
+                socks_proxy: Some(crate::SocksProxy::new(
+                    listener.local_addr().unwrap().to_string(),
+                )),
+                ..NetworkConfig::default()
+            },
+            ..ResourceManagerConfig::default()
+        };
+        let manager = ResourceManager::with_config(config).await.unwrap();
+        let proxied = tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            client.read_u8().await.unwrap()
+        });
+        let result = manager
+            .fetch_for_tab(
+                Uuid::new_v4(),
+                "https://cdn.test/logo.png",
+                Some(ResourceType::Image),
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(proxied.await.unwrap(), 5, "a SOCKS5 greeting");
+    }
+
+    #[tokio::test]
+    async fn test_tab_requests_count_against_shared_budgets() {
+        // A proxy that refuses connections fails the fetch without the network
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = ResourceManagerConfig {
+            network_config: NetworkConfig {
+                socks_proxy: Some(crate::SocksProxy::new(
+                    closed.local_addr().unwrap().to_string(),
+                )),
+                ..NetworkConfig::default()
+            },
+            ..ResourceManagerConfig::default()
+        };
+        drop(closed);
+        let budgets = TabBudgets::default();
+        let manager = ResourceManager::with_config(config)
+            .await
+            .unwrap()
+            .with_budgets(budgets.clone());
+        let tab = Uuid::new_v4();
+        let result = manager
+            .fetch_for_tab(tab, "https://cdn.test/app.js", Some(ResourceType::Script))
+            .await;
+        assert!(result.is_err());
+
+        let usage = budgets.usage(tab).unwrap();
+        assert_eq!(usage.total_requests, 1);
+        assert_eq!(usage.active_requests, 0);
+        budgets.clear();
+        assert!(manager.budget_usage(tab).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tab_responses_are_cached_per_partition() {
+        use crate::NetworkPartitionKey;
+
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = ResourceManagerConfig {
+            network_config: NetworkConfig {
+                socks_proxy: Some(crate::SocksProxy::new(
+                    closed.local_addr().unwrap().to_string(),
+                )),
+                ..NetworkConfig::default()
+            },
+            ..ResourceManagerConfig::default()
+        };
+        drop(closed);
+        let caches = ResourceCaches::default();
+        let manager = ResourceManager::with_config(config)
+            .await
+            .unwrap()
+            .with_resource_caches(caches.clone());
+        let script = Url::parse("https://cdn.test/app.js").unwrap();
+        let news =
+            NetworkPartitionKey::for_url(&Url::parse("https://news.test/").unwrap()).unwrap();
+        let request = |key: NetworkPartitionKey| {
+            Request::builder()
+                .method(Method::GET)
+                .url(script.as_str())
+                .partition_key(key)
+                .build()
+                .unwrap()
+        };
+        caches
+            .for_partition(CachePartition::Shared {
+                top_level_site: "news.test".to_string(),
+            })
+            .put(
+                &script,
+                Response::new(
+                    200,
+                    HashMap::new(),
+                    "cached".into(),
+                    script.clone(),
+                    Method::GET,
+                ),
+            )
+            .unwrap();
+
+        let tab = Uuid::new_v4();
+        let cached = manager
+            .fetch_request_for_tab(tab, request(news.clone()), ResourceType::Script)
+            .await
+            .unwrap();
+        assert!(cached.from_cache());
+        assert_eq!(cached.body_text().unwrap(), "cached");
+
+        // Another site's pages and ephemeral tabs do not see the response
+        let shop =
+            NetworkPartitionKey::for_url(&Url::parse("https://shop.test/").unwrap()).unwrap();
+        let other_tab = Uuid::new_v4();
+        for key in [shop, news.in_ephemeral_tab(other_tab)] {
+            assert!(manager
+                .fetch_request_for_tab(other_tab, request(key), ResourceType::Script)
+                .await
+                .is_err());
+        }
+
+        manager.clear_cache();
+        assert!(caches.snapshot().is_empty());
     }
 }
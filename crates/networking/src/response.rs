@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::time::Duration;
 
 use bytes::Bytes;
+use futures::stream::{self, Stream};
 use url::Url;
 
 use crate::error::NetworkError;
+use crate::headers::HeaderMap;
 use crate::request::Method;
+use crate::security_headers::{self, SecurityHeaderReport};
 
 /// HTTP response status code categories
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,14 +26,101 @@ pub enum StatusCategory {
     Unknown,
 }
 
+/// A typed HTTP status code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    pub const OK: Self = Self(200);
+    pub const NOT_MODIFIED: Self = Self(304);
+    pub const NOT_FOUND: Self = Self(404);
+
+    /// The numeric code
+    pub fn as_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Category of the code
+    pub fn category(self) -> StatusCategory {
+        match self.0 {
+            100..=199 => StatusCategory::Informational,
+            200..=299 => StatusCategory::Success,
+            300..=399 => StatusCategory::Redirection,
+            400..=499 => StatusCategory::ClientError,
+            500..=599 => StatusCategory::ServerError,
+            _ => StatusCategory::Unknown,
+        }
+    }
+
+    /// Standard reason phrase for common codes
+    pub fn reason(self) -> Option<&'static str> {
+        Some(match self.0 {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            206 => "Partial Content",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => return None,
+        })
+    }
+}
+
+impl From<u16> for StatusCode {
+    fn from(code: u16) -> Self {
+        Self(code)
+    }
+}
+
+impl std::fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason() {
+            Some(reason) => write!(f, "{} {}", self.0, reason),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Where the time of a network load went. Phases that did not happen (a
+/// cache hit, a reused connection) are zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseTiming {
+    /// Resolving the host
+    pub dns: Duration,
+    /// Establishing the TCP connection
+    pub connect: Duration,
+    /// TLS handshake
+    pub tls: Duration,
+    /// From sending the request to the first response byte
+    pub ttfb: Duration,
+    /// Whole load, from resolving to the last byte
+    pub total: Duration,
+}
+
 /// HTTP response wrapper with privacy enhancements
 #[derive(Debug, Clone)]
 pub struct Response {
     /// HTTP status code
-    status: u16,
+    status: StatusCode,
 
     /// Response headers
-    headers: HashMap<String, String>,
+    headers: HeaderMap,
+
+    /// Trailer fields sent after a chunked body
+    trailers: HeaderMap,
 
     /// Response body
     body: Bytes,
@@ -46,28 +136,52 @@ pub struct Response {
 
     /// Tracking attempts detected and blocked
     tracking_blocked: Vec<String>,
+
+    /// URLs redirected from, in order, before `url`
+    redirect_chain: Vec<Url>,
+
+    /// Timing breakdown of the network load
+    timing: ResponseTiming,
 }
 
 impl Response {
     /// Creates a new Response object
     pub fn new(
-        status: u16,
-        headers: HashMap<String, String>,
+        status: impl Into<StatusCode>,
+        headers: impl Into<HeaderMap>,
         body: Bytes,
         url: Url,
         request_method: Method,
     ) -> Self {
         Self {
-            status,
-            headers,
+            status: status.into(),
+            headers: headers.into(),
+            trailers: HeaderMap::new(),
             body,
             url,
             request_method,
             from_cache: false,
             tracking_blocked: Vec::new(),
+            redirect_chain: Vec::new(),
+            timing: ResponseTiming::default(),
         }
     }
 
+    /// Set the trailer fields
+    pub fn set_trailers(&mut self, trailers: HeaderMap) {
+        self.trailers = trailers;
+    }
+
+    /// Set the URLs redirected from before reaching `url`
+    pub fn set_redirect_chain(&mut self, chain: Vec<Url>) {
+        self.redirect_chain = chain;
+    }
+
+    /// Set the timing breakdown
+    pub fn set_timing(&mut self, timing: ResponseTiming) {
+        self.timing = timing;
+    }
+
     /// Set whether the response was served from cache
     pub fn set_from_cache(&mut self, from_cache: bool) {
         self.from_cache = from_cache;
@@ -80,19 +194,17 @@ impl Response {
 
     /// Get the HTTP status code
     pub fn status(&self) -> u16 {
+        self.status.as_u16()
+    }
+
+    /// Get the typed HTTP status
+    pub fn status_code(&self) -> StatusCode {
         self.status
     }
 
     /// Get the HTTP status category
     pub fn status_category(&self) -> StatusCategory {
-        match self.status {
-            100..=199 => StatusCategory::Informational,
-            200..=299 => StatusCategory::Success,
-            300..=399 => StatusCategory::Redirection,
-            400..=499 => StatusCategory::ClientError,
-            500..=599 => StatusCategory::ServerError,
-            _ => StatusCategory::Unknown,
-        }
+        self.status.category()
     }
 
     /// Check if the response was successful (2xx status code)
@@ -112,17 +224,33 @@ impl Response {
     }
 
     /// Get all response headers
-    pub fn headers(&self) -> &HashMap<String, String> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
-    /// Get a specific header value
-    pub fn header(&self, name: &str) -> Option<&String> {
-        let name_lower = name.to_lowercase();
-        self.headers
-            .iter()
-            .find(|(k, _)| k.to_lowercase() == name_lower)
-            .map(|(_, v)| v)
+    /// Get the first value of a header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Set or replace a header, matching its name case-insensitively
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.insert(name, value);
+    }
+
+    /// Remove a header, matching its name case-insensitively
+    pub fn remove_header(&mut self, name: &str) -> Option<String> {
+        self.headers.remove(name)
+    }
+
+    /// Trailer fields sent after a chunked body
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    /// Replace the response body
+    pub fn set_body(&mut self, body: Bytes) {
+        self.body = body;
     }
 
     /// Get the response body as bytes
@@ -130,6 +258,18 @@ impl Response {
         &self.body
     }
 
+    /// Stream the body in chunks of at most `chunk_size` bytes. The chunks
+    /// share the body's buffer rather than copying it.
+    pub fn body_stream(&self, chunk_size: usize) -> impl Stream<Item = Bytes> {
+        let body = self.body.clone();
+        let chunk_size = chunk_size.max(1);
+        stream::iter(
+            (0..body.len())
+                .step_by(chunk_size)
+                .map(move |start| body.slice(start..(start + chunk_size).min(body.len()))),
+        )
+    }
+
     /// Get the response body as a string
     pub fn body_text(&self) -> Result<String, NetworkError> {
         String::from_utf8(self.body.to_vec()).map_err(|_| {
@@ -147,6 +287,16 @@ impl Response {
         &self.url
     }
 
+    /// URLs redirected from, in order, before the final URL
+    pub fn redirect_chain(&self) -> &[Url] {
+        &self.redirect_chain
+    }
+
+    /// Timing breakdown of the network load
+    pub fn timing(&self) -> &ResponseTiming {
+        &self.timing
+    }
+
     /// Get the original request method
     pub fn request_method(&self) -> &Method {
         &self.request_method
@@ -168,7 +318,7 @@ impl Response {
     }
 
     /// Get the content type of the response
-    pub fn content_type(&self) -> Option<&String> {
+    pub fn content_type(&self) -> Option<&str> {
         self.header("content-type")
     }
 
@@ -210,6 +360,11 @@ impl Response {
             .unwrap_or(false)
     }
 
+    /// Graded audit of the response's security headers
+    pub fn security_audit(&self) -> SecurityHeaderReport {
+        security_headers::audit(&self.headers, &self.url)
+    }
+
     /// Check for security headers and return warnings for missing ones
     pub fn security_header_warnings(&self) -> Vec<String> {
         let mut warnings = Vec::new();
@@ -246,12 +401,9 @@ mod tests {
     use crate::request::Method;
 
     fn create_test_response() -> Response {
-        let mut headers = HashMap::new();
-        headers.insert(
-            "content-type".to_string(),
-            "text/html; charset=utf-8".to_string(),
-        );
-        headers.insert("server".to_string(), "test-server".to_string());
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "text/html; charset=utf-8");
+        headers.insert("server", "test-server");
 
         Response::new(
             200,
@@ -287,6 +439,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typed_status_and_streamed_body() {
+        use futures::StreamExt;
+
+        let mut response = create_test_response();
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(StatusCode::from(404).to_string(), "404 Not Found");
+        assert_eq!(StatusCode::from(299).to_string(), "299");
+
+        response.set_redirect_chain(vec![Url::parse("https://example.com/old").unwrap()]);
+        assert_eq!(response.redirect_chain()[0].path(), "/old");
+
+        let chunks: Vec<Bytes> = futures::executor::block_on(response.body_stream(16).collect());
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), response.body().to_vec());
+    }
+
     #[test]
     fn test_security_headers() {
         let response = create_test_response();
@@ -0,0 +1,334 @@
+//! Security header audit
+//!
+//! Grades how well a page protects itself with response headers: CSP, HSTS,
+//! X-Frame-Options, Referrer-Policy and cross-origin isolation (COOP/COEP).
+//! [`audit`] is a pure function of the headers and the page URL, so the
+//! security panel and tests share one implementation.
+
+use url::Url;
+
+use crate::headers::HeaderMap;
+
+/// HSTS max-age below which the policy counts as weak (180 days)
+const MIN_HSTS_MAX_AGE: u64 = 180 * 24 * 60 * 60;
+
+/// How well a page uses one header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderStatus {
+    /// Present and strict
+    Good,
+    /// Present but permissive
+    Weak,
+    /// Not sent
+    Missing,
+}
+
+/// The audit of one header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderFinding {
+    /// Header name as shown to the user
+    pub header: &'static str,
+    pub status: HeaderStatus,
+    /// One-line explanation
+    pub detail: String,
+    /// Points this header is worth
+    weight: u32,
+}
+
+/// Letter grade of a page's security headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecurityGrade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl std::fmt::Display for SecurityGrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Findings for every audited header and the resulting grade
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeaderReport {
+    pub findings: Vec<HeaderFinding>,
+}
+
+impl SecurityHeaderReport {
+    /// Score out of 100: full weight for good headers, half for weak ones
+    pub fn score(&self) -> u32 {
+        self.findings
+            .iter()
+            .map(|finding| match finding.status {
+                HeaderStatus::Good => finding.weight,
+                HeaderStatus::Weak => finding.weight / 2,
+                HeaderStatus::Missing => 0,
+            })
+            .sum()
+    }
+
+    /// Letter grade of the score
+    pub fn grade(&self) -> SecurityGrade {
+        match self.score() {
+            90.. => SecurityGrade::A,
+            75..=89 => SecurityGrade::B,
+            60..=74 => SecurityGrade::C,
+            40..=59 => SecurityGrade::D,
+            _ => SecurityGrade::F,
+        }
+    }
+
+    /// The finding for a header
+    pub fn finding(&self, header: &str) -> Option<&HeaderFinding> {
+        self.findings
+            .iter()
+            .find(|finding| finding.header.eq_ignore_ascii_case(header))
+    }
+}
+
+/// Audit the security headers a page was served with
+pub fn audit(headers: &HeaderMap, url: &Url) -> SecurityHeaderReport {
+    let csp = headers.get("content-security-policy");
+    SecurityHeaderReport {
+        findings: vec![
+            audit_csp(csp),
+            audit_hsts(headers.get("strict-transport-security"), url),
+            audit_frame_options(headers.get("x-frame-options"), csp),
+            audit_referrer_policy(headers.get("referrer-policy")),
+            audit_coop(headers.get("cross-origin-opener-policy")),
+            audit_coep(headers.get("cross-origin-embedder-policy")),
+        ],
+    }
+}
+
+fn finding(header: &'static str, weight: u32, status: HeaderStatus, detail: &str) -> HeaderFinding {
+    HeaderFinding {
+        header,
+        status,
+        detail: detail.to_string(),
+        weight,
+    }
+}
+
+/// Sources of a CSP directive, lowercased
+fn csp_directive(csp: &str, name: &str) -> Option<Vec<String>> {
+    csp.split(';').find_map(|directive| {
+        let mut parts = directive.split_whitespace();
+        parts
+            .next()
+            .filter(|n| n.eq_ignore_ascii_case(name))
+            .map(|_| parts.map(str::to_ascii_lowercase).collect())
+    })
+}
+
+fn audit_csp(csp: Option<&str>) -> HeaderFinding {
+    const NAME: &str = "Content-Security-Policy";
+    let Some(csp) = csp else {
+        return finding(NAME, 25, HeaderStatus::Missing, "No script restrictions");
+    };
+    let Some(sources) =
+        csp_directive(csp, "script-src").or_else(|| csp_directive(csp, "default-src"))
+    else {
+        return finding(NAME, 25, HeaderStatus::Weak, "No script-src or default-src");
+    };
+    let weak = [
+        "'unsafe-inline'",
+        "'unsafe-eval'",
+        "*",
+        "http:",
+        "https:",
+        "data:",
+    ];
+    match sources.iter().find(|s| weak.contains(&s.as_str())) {
+        // 'unsafe-inline' is ignored when a nonce or hash is present
+        Some(source)
+            if source == "'unsafe-inline'"
+                && sources
+                    .iter()
+                    .any(|s| s.starts_with("'nonce-") || s.starts_with("'sha")) =>
+        {
+            finding(
+                NAME,
+                25,
+                HeaderStatus::Good,
+                "Scripts limited by nonce or hash",
+            )
+        }
+        Some(source) => finding(
+            NAME,
+            25,
+            HeaderStatus::Weak,
+            &format!("Scripts allowed from {}", source),
+        ),
+        None => finding(NAME, 25, HeaderStatus::Good, "Scripts restricted"),
+    }
+}
+
+fn audit_hsts(hsts: Option<&str>, url: &Url) -> HeaderFinding {
+    const NAME: &str = "Strict-Transport-Security";
+    if url.scheme() != "https" {
+        return finding(
+            NAME,
+            25,
+            HeaderStatus::Missing,
+            "Page not served over HTTPS",
+        );
+    }
+    let Some(hsts) = hsts else {
+        return finding(NAME, 25, HeaderStatus::Missing, "HTTPS not pinned");
+    };
+    let max_age = hsts.split(';').find_map(|directive| {
+        let (name, value) = directive.trim().split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("max-age")
+            .then(|| value.trim().trim_matches('"').parse::<u64>().ok())?
+    });
+    match max_age {
+        Some(age) if age >= MIN_HSTS_MAX_AGE => finding(
+            NAME,
+            25,
+            HeaderStatus::Good,
+            &format!("HTTPS pinned for {} days", age / 86_400),
+        ),
+        Some(age) => finding(
+            NAME,
+            25,
+            HeaderStatus::Weak,
+            &format!("max-age of {} days is short", age / 86_400),
+        ),
+        None => finding(NAME, 25, HeaderStatus::Weak, "No valid max-age"),
+    }
+}
+
+fn audit_frame_options(xfo: Option<&str>, csp: Option<&str>) -> HeaderFinding {
+    const NAME: &str = "X-Frame-Options";
+    if csp.is_some_and(|csp| csp_directive(csp, "frame-ancestors").is_some()) {
+        return finding(
+            NAME,
+            15,
+            HeaderStatus::Good,
+            "Framing set by CSP frame-ancestors",
+        );
+    }
+    match xfo.map(|v| v.trim().to_ascii_uppercase()) {
+        Some(v) if v == "DENY" || v == "SAMEORIGIN" => {
+            finding(NAME, 15, HeaderStatus::Good, "Framing restricted")
+        }
+        Some(_) => finding(NAME, 15, HeaderStatus::Weak, "Unrecognised value"),
+        None => finding(NAME, 15, HeaderStatus::Missing, "Page can be framed"),
+    }
+}
+
+fn audit_referrer_policy(policy: Option<&str>) -> HeaderFinding {
+    const NAME: &str = "Referrer-Policy";
+    // The last recognised token wins, as in browsers
+    let policy = policy.and_then(|p| {
+        p.split(',')
+            .map(|t| t.trim().to_ascii_lowercase())
+            .rfind(|t| !t.is_empty())
+    });
+    match policy.as_deref() {
+        Some(
+            "no-referrer" | "same-origin" | "strict-origin" | "strict-origin-when-cross-origin",
+        ) => finding(NAME, 15, HeaderStatus::Good, "Referrer trimmed cross-site"),
+        Some(other) => finding(
+            NAME,
+            15,
+            HeaderStatus::Weak,
+            &format!("{} leaks the full URL", other),
+        ),
+        None => finding(NAME, 15, HeaderStatus::Missing, "Browser default applies"),
+    }
+}
+
+fn audit_coop(coop: Option<&str>) -> HeaderFinding {
+    const NAME: &str = "Cross-Origin-Opener-Policy";
+    match coop.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        Some("same-origin") => finding(NAME, 10, HeaderStatus::Good, "Window isolated"),
+        Some("same-origin-allow-popups") => finding(
+            NAME,
+            10,
+            HeaderStatus::Weak,
+            "Popups share the window group",
+        ),
+        Some(_) => finding(NAME, 10, HeaderStatus::Weak, "Not isolated"),
+        None => finding(NAME, 10, HeaderStatus::Missing, "Not isolated"),
+    }
+}
+
+fn audit_coep(coep: Option<&str>) -> HeaderFinding {
+    const NAME: &str = "Cross-Origin-Embedder-Policy";
+    match coep.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        Some("require-corp" | "credentialless") => {
+            finding(NAME, 10, HeaderStatus::Good, "Embeds must opt in")
+        }
+        Some(_) => finding(NAME, 10, HeaderStatus::Weak, "Embeds unrestricted"),
+        None => finding(NAME, 10, HeaderStatus::Missing, "Embeds unrestricted"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit_of(headers: &[(&str, &str)]) -> SecurityHeaderReport {
+        audit(
+            &headers.iter().copied().collect(),
+            &Url::parse("https://example.com/").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_strict_headers_grade_a() {
+        let report = audit_of(&[
+            (
+                "Content-Security-Policy",
+                "default-src 'self'; frame-ancestors 'none'",
+            ),
+            (
+                "Strict-Transport-Security",
+                "max-age=31536000; includeSubDomains",
+            ),
+            ("Referrer-Policy", "no-referrer"),
+            ("Cross-Origin-Opener-Policy", "same-origin"),
+            ("Cross-Origin-Embedder-Policy", "require-corp"),
+        ]);
+        assert_eq!(report.score(), 100);
+        assert_eq!(report.grade(), SecurityGrade::A);
+        assert_eq!(
+            report.finding("x-frame-options").unwrap().status,
+            HeaderStatus::Good
+        );
+    }
+
+    #[test]
+    fn test_weak_and_missing_headers() {
+        let report = audit_of(&[
+            ("Content-Security-Policy", "script-src 'self' 'unsafe-eval'"),
+            ("Strict-Transport-Security", "max-age=3600"),
+            ("X-Frame-Options", "ALLOW-FROM https://a.test"),
+            ("Referrer-Policy", "unsafe-url"),
+        ]);
+        let status = |h: &str| report.finding(h).unwrap().status;
+        assert_eq!(status("Content-Security-Policy"), HeaderStatus::Weak);
+        assert_eq!(status("Strict-Transport-Security"), HeaderStatus::Weak);
+        assert_eq!(status("X-Frame-Options"), HeaderStatus::Weak);
+        assert_eq!(status("Referrer-Policy"), HeaderStatus::Weak);
+        assert_eq!(status("Cross-Origin-Opener-Policy"), HeaderStatus::Missing);
+        assert_eq!(report.score(), 38);
+        assert_eq!(report.grade(), SecurityGrade::F);
+
+        assert_eq!(audit_of(&[]).score(), 0);
+        let nonce = audit_of(&[(
+            "Content-Security-Policy",
+            "script-src 'nonce-abc' 'unsafe-inline'",
+        )]);
+        assert_eq!(
+            nonce.finding("Content-Security-Policy").unwrap().status,
+            HeaderStatus::Good
+        );
+    }
+}
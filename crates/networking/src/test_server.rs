@@ -0,0 +1,368 @@
+//! A local HTTPS server with fixture pages, for integration tests
+//!
+//! The client is HTTPS-only and trusts only the bundled roots, so a test
+//! server needs a certificate the client accepts. Builds with the
+//! `test-support` feature add the fixed "Citadel Test Root" to the roots the
+//! client trusts (see `test-fixtures/tls`), and [`TestServer`] presents a
+//! `localhost` certificate it signed. The feature is for tests only: nothing
+//! that ships may enable it.
+//!
+//! Each path is answered with a [`Fixture`]: a page, a redirect, an error
+//! status, any headers (CSP and the like) and, for slow responses, a delay
+//! before the answer. [`TestServer::untrusted`] presents a self-signed
+//! certificate instead, for testing that bad TLS fails closed. Every request
+//! the server receives is kept, so tests can check what the client sent.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use url::Url;
+
+/// The test root, trusted by builds with the `test-support` feature
+const ROOT_CERT: &[u8] = include_bytes!("../test-fixtures/tls/root.der");
+/// `localhost` leaf signed by the test root
+const LOCALHOST_CERT: &[u8] = include_bytes!("../test-fixtures/tls/localhost.der");
+const LOCALHOST_KEY: &[u8] = include_bytes!("../test-fixtures/tls/localhost-key.der");
+/// Self-signed `localhost` leaf no client trusts
+const UNTRUSTED_CERT: &[u8] = include_bytes!("../test-fixtures/tls/untrusted.der");
+const UNTRUSTED_KEY: &[u8] = include_bytes!("../test-fixtures/tls/untrusted-key.der");
+
+/// Largest request head the server reads
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+/// Largest request body the server keeps
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// The test root certificate
+pub fn root_certificate() -> CertificateDer<'static> {
+    CertificateDer::from(ROOT_CERT)
+}
+
+/// What the server answers a path with
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,
+}
+
+impl Fixture {
+    /// A 200 HTML page
+    pub fn html(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/html; charset=utf-8".to_string(),
+            )],
+            body: body.into().into_bytes(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// A redirect to `location`, relative or absolute
+    pub fn redirect(status: u16, location: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("Location".to_string(), location.to_string())],
+            body: Vec::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// An empty response with `status`
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Also send `name: value`
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Answer only after `delay`
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// A request the server received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// Path and query, as sent
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    /// The path, without the query
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Builds a [`TestServer`]
+#[derive(Debug, Default)]
+pub struct TestServerBuilder {
+    routes: HashMap<String, Fixture>,
+    untrusted: bool,
+}
+
+impl TestServerBuilder {
+    /// Answer `path` with `fixture`; other paths get a 404
+    pub fn route(mut self, path: &str, fixture: Fixture) -> Self {
+        self.routes.insert(path.to_string(), fixture);
+        self
+    }
+
+    /// Present the self-signed certificate instead of the trusted one
+    pub fn untrusted(mut self) -> Self {
+        self.untrusted = true;
+        self
+    }
+
+    /// Listen on a free loopback port. The server runs until dropped.
+    pub async fn start(self) -> std::io::Result<TestServer> {
+        let (cert, key) = if self.untrusted {
+            (UNTRUSTED_CERT, UNTRUSTED_KEY)
+        } else {
+            (LOCALHOST_CERT, LOCALHOST_KEY)
+        };
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(cert)],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key)),
+            )
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let routes = Arc::new(self.routes);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let routes = routes.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    // A client refusing the certificate ends the handshake
+                    if let Ok(tls) = acceptor.accept(tcp).await {
+                        let _ = serve(tls, &routes, &received).await;
+                    }
+                });
+            }
+        });
+
+        Ok(TestServer {
+            addr,
+            requests,
+            task,
+        })
+    }
+}
+
+/// A local HTTPS server answering with fixtures
+#[derive(Debug)]
+pub struct TestServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<ReceivedRequest>>>,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+    /// A server presenting the trusted certificate, answering `routes`
+    pub async fn start(routes: &[(&str, Fixture)]) -> std::io::Result<Self> {
+        routes
+            .iter()
+            .fold(Self::builder(), |builder, (path, fixture)| {
+                builder.route(path, fixture.clone())
+            })
+            .start()
+            .await
+    }
+
+    /// A server presenting the self-signed certificate, answering every
+    /// path it is asked for (if a client ever gets that far) with a page
+    pub async fn untrusted() -> std::io::Result<Self> {
+        Self::builder()
+            .untrusted()
+            .route("/", Fixture::html("<title>Untrusted</title>"))
+            .start()
+            .await
+    }
+
+    /// `https://localhost:<port><path>`
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("https://localhost:{}{}", self.addr.port(), path))
+            .expect("fixture URLs are valid")
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+
+    /// Paths requested so far, in order
+    pub fn paths(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .map(|request| request.path().to_string())
+            .collect()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Read one request from `stream`, record it and answer it
+async fn serve<S>(
+    mut stream: S,
+    routes: &HashMap<String, Fixture>,
+    received: &Mutex<Vec<ReceivedRequest>>,
+) -> std::io::Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_end = loop {
+        if let Some(at) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break at;
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return Ok(());
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let Some(mut request) = parse_head(&buf[..head_end]) else {
+        return respond(&mut stream, &Fixture::status(400)).await;
+    };
+
+    let length = request
+        .header("content-length")
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_BODY_BYTES);
+    let mut body = buf.split_off(head_end + 4);
+    while body.len() < length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(length);
+    request.body = body;
+
+    let fixture = routes
+        .get(request.path())
+        .cloned()
+        .unwrap_or_else(|| Fixture::status(404));
+    if let Ok(mut requests) = received.lock() {
+        requests.push(request);
+    }
+    tokio::time::sleep(fixture.delay).await;
+    respond(&mut stream, &fixture).await
+}
+
+/// Request line and headers; `None` when malformed
+fn parse_head(head: &[u8]) -> Option<ReceivedRequest> {
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    Some(ReceivedRequest {
+        method,
+        target,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+async fn respond<S>(stream: &mut S, fixture: &Fixture) -> std::io::Result<()>
+where
+    S: tokio::io::AsyncWrite + Unpin,
+{
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        fixture.status,
+        reason(fixture.status),
+        fixture.body.len()
+    );
+    for (name, value) in &fixture.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&fixture.body).await?;
+    stream.flush().await?;
+    // Sends close_notify
+    stream.shutdown().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
@@ -0,0 +1,187 @@
+//! Partitioned TLS session resumption
+//!
+//! Resuming a TLS session skips a full handshake, but a session ticket is also
+//! an identifier the server handed out: presenting it from another site's
+//! page would let the server link the two visits. Tickets are therefore kept
+//! per [`NetworkPartitionKey`] (the top-level site, plus the tab for
+//! ephemeral tabs) and never shared across partitions. An ephemeral tab's
+//! tickets are dropped when it closes.
+//!
+//! 0-RTT early data can be replayed by an attacker on the path, so it is only
+//! offered for idempotent requests (see [`early_data_allowed`]).
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use tokio_rustls::rustls::client::{ClientSessionMemoryCache, Resumption};
+use tokio_rustls::rustls::ClientConfig;
+use url::Url;
+use uuid::Uuid;
+
+use crate::request::Method;
+use crate::resource_manager::ResourceManager;
+
+/// Sessions remembered per partition
+const SESSIONS_PER_PARTITION: usize = 32;
+
+/// Which network state a load may share
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkPartitionKey {
+    /// Registrable domain of the top-level page
+    pub top_level_site: String,
+    /// Ephemeral tab owning the partition; `None` for state shared by every
+    /// persistent tab on the site
+    pub ephemeral_tab: Option<Uuid>,
+}
+
+impl NetworkPartitionKey {
+    /// Partition of a top-level page. `None` for URLs without a host.
+    pub fn for_url(top_level: &Url) -> Option<Self> {
+        let host = top_level.host_str()?.to_ascii_lowercase();
+        Some(Self {
+            top_level_site: ResourceManager::extract_domain(&host),
+            ephemeral_tab: None,
+        })
+    }
+
+    /// Confine the partition to one ephemeral tab
+    pub fn in_ephemeral_tab(mut self, tab_id: Uuid) -> Self {
+        self.ephemeral_tab = Some(tab_id);
+        self
+    }
+}
+
+/// Whether a request may be sent as TLS 1.3 early data. Early data can be
+/// replayed, so only methods without side effects qualify.
+pub fn early_data_allowed(method: &Method) -> bool {
+    matches!(method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Session ticket stores, one per partition
+#[derive(Debug, Clone, Default)]
+pub struct TlsSessionCache {
+    partitions: Arc<RwLock<HashMap<NetworkPartitionKey, Arc<ClientSessionMemoryCache>>>>,
+}
+
+impl TlsSessionCache {
+    /// An empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Point `config` at the partition's ticket store and allow early data
+    /// only for replay-safe methods
+    pub fn configure(
+        &self,
+        config: &mut ClientConfig,
+        partition: &NetworkPartitionKey,
+        method: &Method,
+    ) {
+        config.resumption = Resumption::store(self.store(partition));
+        config.enable_early_data = early_data_allowed(method);
+    }
+
+    /// Number of partitions holding a ticket store
+    pub fn partition_count(&self) -> usize {
+        self.partitions.read().map(|p| p.len()).unwrap_or(0)
+    }
+
+    /// Drop the tickets of an ephemeral tab's partitions
+    pub fn clear_tab(&self, tab_id: Uuid) {
+        if let Ok(mut partitions) = self.partitions.write() {
+            partitions.retain(|key, _| key.ephemeral_tab != Some(tab_id));
+        }
+    }
+
+    /// Drop the tickets of every partition of a top-level site
+    pub fn clear_site(&self, top_level_site: &str) {
+        if let Ok(mut partitions) = self.partitions.write() {
+            partitions.retain(|key, _| key.top_level_site != top_level_site);
+        }
+    }
+
+    /// Drop every ticket
+    pub fn clear(&self) {
+        if let Ok(mut partitions) = self.partitions.write() {
+            partitions.clear();
+        }
+    }
+
+    fn store(&self, partition: &NetworkPartitionKey) -> Arc<ClientSessionMemoryCache> {
+        if let Some(store) = self
+            .partitions
+            .read()
+            .ok()
+            .and_then(|p| p.get(partition).cloned())
+        {
+            return store;
+        }
+        match self.partitions.write() {
+            Ok(mut partitions) => partitions
+                .entry(partition.clone())
+                .or_insert_with(|| Arc::new(ClientSessionMemoryCache::new(SESSIONS_PER_PARTITION)))
+                .clone(),
+            // A poisoned lock only costs resumption, never isolation
+            Err(_) => Arc::new(ClientSessionMemoryCache::new(SESSIONS_PER_PARTITION)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_rustls::rustls::RootCertStore;
+
+    fn config() -> ClientConfig {
+        ClientConfig::builder()
+            .with_root_certificates(RootCertStore::empty())
+            .with_no_client_auth()
+    }
+
+    fn key(url: &str) -> NetworkPartitionKey {
+        NetworkPartitionKey::for_url(&Url::parse(url).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_partition_key_is_the_top_level_site() {
+        assert_eq!(
+            key("https://www.example.com/a"),
+            key("https://EXAMPLE.com/b")
+        );
+        assert_ne!(key("https://example.com/"), key("https://other.test/"));
+        let tab = Uuid::new_v4();
+        assert_ne!(
+            key("https://example.com/"),
+            key("https://example.com/").in_ephemeral_tab(tab)
+        );
+        assert!(NetworkPartitionKey::for_url(&Url::parse("data:text/plain,x").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_partitions_and_early_data_policy() {
+        let cache = TlsSessionCache::new();
+        let tab = Uuid::new_v4();
+        let site = key("https://example.com/");
+        let private = key("https://example.com/").in_ephemeral_tab(tab);
+
+        let mut get = config();
+        cache.configure(&mut get, &site, &Method::GET);
+        assert!(get.enable_early_data);
+
+        let mut post = config();
+        cache.configure(&mut post, &site, &Method::POST);
+        assert!(!post.enable_early_data);
+
+        cache.configure(&mut config(), &key("https://other.test/"), &Method::GET);
+        cache.configure(&mut config(), &private, &Method::GET);
+        assert_eq!(cache.partition_count(), 3, "same site shares one store");
+
+        cache.clear_tab(tab);
+        assert_eq!(cache.partition_count(), 2);
+        cache.clear_site(&site.top_level_site);
+        assert_eq!(cache.partition_count(), 1);
+        cache.configure(&mut config(), &site, &Method::GET);
+        cache.clear();
+        assert_eq!(cache.partition_count(), 0);
+    }
+}
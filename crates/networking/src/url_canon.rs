@@ -0,0 +1,221 @@
+//! URL canonicalization
+//!
+//! One place that decides what "the same URL" means. The omnibox, link
+//! resolution, the resource cache and tab history all run URLs through
+//! [`canonicalize`] so a page is not stored under several spellings of its
+//! address. On top of the WHATWG parsing the `url` crate already does (scheme
+//! and host lowercasing, default port removal, dot-segment resolution) this
+//! normalizes percent-encoding, drops a trailing dot on the host and an empty
+//! query, and strips known tracking parameters.
+
+use url::Url;
+
+use crate::error::NetworkError;
+
+/// Query parameters that only exist to track the user across sites
+pub const TRACKING_PARAMS: &[&str] = &[
+    "utm_source",
+    "utm_medium",
+    "utm_campaign",
+    "utm_term",
+    "utm_content",
+    "fbclid",
+    "gclid",
+    "msclkid",
+    "mc_eid",
+    "yclid",
+    "_ga",
+    "_gl",
+    "ref",
+    "referrer",
+    "source",
+    "xtor",
+    "ICID",
+    "dicbo",
+    "fbcid",
+];
+
+/// Canonical form of an already parsed URL (the fragment is kept)
+pub fn canonicalize(url: &Url) -> Url {
+    let mut canonical = url.clone();
+
+    if let Some(host) = canonical.host_str() {
+        if host.len() > 1 && host.ends_with('.') {
+            let trimmed = host.trim_end_matches('.').to_string();
+            let _ = canonical.set_host(Some(&trimmed));
+        }
+    }
+
+    if !canonical.cannot_be_a_base() {
+        let path = normalize_percent_encoding(canonical.path());
+        if path != canonical.path() {
+            canonical.set_path(&path);
+        }
+    }
+
+    strip_tracking_params(&mut canonical);
+    if let Some(query) = canonical.query() {
+        let query = normalize_percent_encoding(query);
+        canonical.set_query(if query.is_empty() { None } else { Some(&query) });
+    }
+
+    if let Some(fragment) = canonical.fragment() {
+        let fragment = normalize_percent_encoding(fragment);
+        canonical.set_fragment(Some(&fragment));
+    }
+
+    canonical
+}
+
+/// Parse and canonicalize an absolute URL string
+pub fn canonicalize_str(input: &str) -> Result<Url, NetworkError> {
+    Ok(canonicalize(&Url::parse(input.trim())?))
+}
+
+/// Resolve a (possibly relative) reference against a base URL and canonicalize
+/// the result
+pub fn resolve(base: &Url, reference: &str) -> Result<Url, NetworkError> {
+    Ok(canonicalize(&base.join(reference.trim())?))
+}
+
+/// Key under which a URL is cached: the canonical URL without its fragment,
+/// which never reaches the server
+pub fn cache_key(url: &Url) -> String {
+    let mut key = canonicalize(url);
+    key.set_fragment(None);
+    key.into()
+}
+
+/// Remove [`TRACKING_PARAMS`] from the query, leaving the encoding of the
+/// remaining parameters untouched
+pub fn strip_tracking_params(url: &mut Url) {
+    let Some(query) = url.query() else {
+        return;
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            url::form_urlencoded::parse(pair.as_bytes())
+                .next()
+                .is_some_and(|(key, _)| !TRACKING_PARAMS.contains(&key.as_ref()))
+        })
+        .collect();
+
+    let stripped = kept.join("&");
+    if stripped != query {
+        url.set_query(if stripped.is_empty() {
+            None
+        } else {
+            Some(&stripped)
+        });
+    }
+}
+
+/// Decode percent-escapes of unreserved characters (RFC 3986 §6.2.2.2) and
+/// uppercase the hex digits of all others
+fn normalize_percent_encoding(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            let hex = &input[i + 1..i + 3];
+            if let Ok(value) = u8::from_str_radix(hex, 16) {
+                if value.is_ascii_alphanumeric() || matches!(value, b'-' | b'.' | b'_' | b'~') {
+                    out.push(value as char);
+                } else {
+                    out.push('%');
+                    out.push_str(&hex.to_ascii_uppercase());
+                }
+                i += 3;
+                continue;
+            }
+        }
+        // Input is a serialized URL component, so it is ASCII
+        out.push(bytes[i] as char);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canon(input: &str) -> String {
+        canonicalize_str(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_equivalent_spellings_share_one_form() {
+        let expected = "https://example.com/a/c?q=1";
+        for spelling in [
+            "https://example.com/a/c?q=1",
+            "HTTPS://Example.COM:443/a/c?q=1",
+            "https://example.com./a/./b/../c?q=1",
+            "https://example.com/%61/%63?q=1&utm_source=news",
+            "https://example.com/a/c?fbclid=x&q=1&",
+        ] {
+            assert_eq!(canon(spelling), expected, "{}", spelling);
+        }
+    }
+
+    #[test]
+    fn test_percent_encoding_is_normalized() {
+        assert_eq!(
+            canon("https://example.com/a%2fb%7Ec?x=%e2%82%ac"),
+            "https://example.com/a%2Fb~c?x=%E2%82%AC"
+        );
+        // Malformed escapes are left alone
+        assert_eq!(
+            canon("https://example.com/%zz%4"),
+            "https://example.com/%zz%4"
+        );
+    }
+
+    #[test]
+    fn test_tracking_params_are_stripped_without_reencoding() {
+        let mut url = Url::parse("https://example.com/?a=b+c&gclid=1&d=%20").unwrap();
+        strip_tracking_params(&mut url);
+        assert_eq!(url.query(), Some("a=b+c&d=%20"));
+
+        let mut url = Url::parse("https://example.com/?utm_medium=x").unwrap();
+        strip_tracking_params(&mut url);
+        assert_eq!(url.as_str(), "https://example.com/");
+    }
+
+    #[test]
+    fn test_cache_key_ignores_fragment() {
+        let a = Url::parse("https://example.com/page#top").unwrap();
+        let b = Url::parse("https://EXAMPLE.com:443/page?utm_campaign=x").unwrap();
+        assert_eq!(cache_key(&a), cache_key(&b));
+        assert_eq!(canonicalize(&a).fragment(), Some("top"));
+    }
+
+    #[test]
+    fn test_resolve_relative_references() {
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        assert_eq!(
+            resolve(&base, "../img/%7Elogo.png").unwrap().as_str(),
+            "https://example.com/img/~logo.png"
+        );
+        assert_eq!(
+            resolve(&base, "//cdn.example.com/x.js").unwrap().as_str(),
+            "https://cdn.example.com/x.js"
+        );
+    }
+
+    #[test]
+    fn test_non_hierarchical_urls_are_preserved() {
+        assert_eq!(canon("about:blank"), "about:blank");
+        assert_eq!(
+            canon("data:text/plain,hi%20there"),
+            "data:text/plain,hi%20there"
+        );
+    }
+}
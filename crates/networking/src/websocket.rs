@@ -0,0 +1,836 @@
+//! WebSocket client (RFC 6455) for persistent connections
+//!
+//! Built on the HTTPS client's TLS stack and held to the same rules:
+//! - `wss:` only: a `ws:` URL is refused like a plain `http:` one.
+//! - A page may open sockets only once its origin is granted in
+//!   [`WebSocketPermissions`], and only to the hosts the grant allows.
+//!   Sockets of a tab's page are opened through
+//!   [`ResourceManager::open_websocket_for_tab`], which holds them to the
+//!   tab's fetch policies too and routes them through the SOCKS proxy.
+//! - With a proxy route the proxy resolves the host: there is no local DNS
+//!   lookup and no direct connection.
+//! - Messages are bounded both ways by [`WebSocketConfig::max_message_bytes`].
+//!   A peer sending a bigger one is closed with 1009 ("message too big").
+//! - The handshake sends the uniform User-Agent, the page's `Origin` and
+//!   nothing else: no cookies, and no extensions, so there is no per-message
+//!   compression to inflate.
+//!
+//! [`WebSocketConnection`] sends and receives through `&self`, so one task can
+//! wait in [`receive`](WebSocketConnection::receive) while others send through
+//! an `Arc`. Pings are answered inside `receive`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::error::NetworkError;
+use crate::host_policy::HostPolicy;
+use crate::http::{self, StreamRoute};
+
+/// Largest message sent or received by default, in bytes
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+/// Close code of a normal closure
+pub const CLOSE_NORMAL: u16 = 1000;
+
+/// Largest handshake response read
+const MAX_HANDSHAKE_BYTES: usize = 16 * 1024;
+/// Time for the server to accept the upgrade once connected
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Appended to the key to derive `Sec-WebSocket-Accept` (RFC 6455 §1.3)
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Longest control frame payload (RFC 6455 §5.5)
+const MAX_CONTROL_PAYLOAD: usize = 125;
+
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Page origins allowed to open WebSockets, and the hosts each may reach
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketPermissions {
+    grants: Arc<RwLock<HashMap<String, HostPolicy>>>,
+}
+
+impl WebSocketPermissions {
+    /// No grants: no page may open a WebSocket
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let pages on `page`'s origin open WebSockets to the hosts `hosts`
+    /// allows, replacing any previous grant
+    pub fn grant(&self, page: &Url, hosts: HostPolicy) {
+        if let Ok(mut grants) = self.grants.write() {
+            grants.insert(page.origin().ascii_serialization(), hosts);
+        }
+    }
+
+    /// Withdraw the grant of `page`'s origin
+    pub fn revoke(&self, page: &Url) -> Option<HostPolicy> {
+        self.grants
+            .write()
+            .ok()?
+            .remove(&page.origin().ascii_serialization())
+    }
+
+    /// Why a page at `page` may not open a WebSocket to `target`, or `None`
+    /// if it may. Pages with an opaque origin never may.
+    pub fn check(&self, page: &Url, target: &Url) -> Option<String> {
+        let origin = page.origin();
+        if !origin.is_tuple() {
+            return Some(format!("{} has an opaque origin", page));
+        }
+        let origin = origin.ascii_serialization();
+        let Ok(grants) = self.grants.read() else {
+            return Some("WebSocket permissions are unavailable".to_string());
+        };
+        let Some(hosts) = grants.get(&origin) else {
+            return Some(format!("{} may not open WebSockets", origin));
+        };
+        let Some(host) = target.host_str() else {
+            return Some(format!("{} has no host", target));
+        };
+        hosts
+            .check(host)
+            .map(|_| format!("{} may not open WebSockets to {}", origin, host))
+    }
+}
+
+/// Limits and options of a WebSocket connection
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    /// Largest message sent or received, in bytes
+    pub max_message_bytes: usize,
+    /// Subprotocols offered in `Sec-WebSocket-Protocol`, preferred first.
+    /// Names that are not HTTP tokens are left out.
+    pub protocols: Vec<String>,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            protocols: Vec::new(),
+        }
+    }
+}
+
+/// A complete WebSocket message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSocketMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl WebSocketMessage {
+    /// Payload size in bytes
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Binary(data) => data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// One frame off the wire, unmasked
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// A failure reading from the peer: the close code to send it, if the
+/// connection is still up, and the error
+type Failure = (Option<u16>, NetworkError);
+
+type Reader = BufReader<Box<dyn AsyncRead + Send + Unpin>>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// An open WebSocket connection
+pub struct WebSocketConnection {
+    url: Url,
+    /// Subprotocol the server picked
+    protocol: Option<String>,
+    max_message_bytes: usize,
+    reader: Mutex<Reader>,
+    writer: Mutex<Writer>,
+    /// A close frame was sent; nothing more will be
+    close_sent: AtomicBool,
+    /// The peer closed, or the connection failed
+    closed: AtomicBool,
+}
+
+impl std::fmt::Debug for WebSocketConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketConnection")
+            .field("url", &self.url.as_str())
+            .field("protocol", &self.protocol)
+            .field("max_message_bytes", &self.max_message_bytes)
+            .field("closed", &self.is_closed())
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebSocketConnection {
+    /// Open a WebSocket to `url` for the page at `page`, connecting the way
+    /// `route` says, if `permissions` allow it. The page's origin is sent as
+    /// `Origin`.
+    pub async fn connect(
+        url: &Url,
+        page: &Url,
+        permissions: &WebSocketPermissions,
+        config: &WebSocketConfig,
+        route: StreamRoute<'_>,
+    ) -> Result<Self, NetworkError> {
+        match url.scheme() {
+            "wss" => {}
+            "ws" => {
+                return Err(NetworkError::HttpsEnforcementError(format!(
+                    "non-TLS WebSocket URL: {url}"
+                )))
+            }
+            other => {
+                return Err(NetworkError::ConnectionError(format!(
+                    "{other}: is not a WebSocket scheme"
+                )))
+            }
+        }
+        if url.fragment().is_some() {
+            return Err(NetworkError::ConnectionError(
+                "WebSocket URLs cannot have a fragment".into(),
+            ));
+        }
+        if let Some(reason) = permissions.check(page, url) {
+            return Err(NetworkError::PrivacyViolationError(reason));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| NetworkError::ConnectionError("missing host".into()))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+        let tls = http::connect_tls(host, port, route).await?;
+        Self::handshake(tls, url, page, config).await
+    }
+
+    /// Upgrade `stream` to a WebSocket
+    async fn handshake<S>(
+        stream: S,
+        url: &Url,
+        page: &Url,
+        config: &WebSocketConfig,
+    ) -> Result<Self, NetworkError>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        let mut reader: Reader = BufReader::new(Box::new(reader));
+        let mut writer: Writer = Box::new(writer);
+        let key = general_purpose::STANDARD.encode(rand::random::<[u8; 16]>());
+        let request = handshake_request(url, page, &key, &config.protocols);
+
+        let protocol = tokio::time::timeout(HANDSHAKE_TIMEOUT, async {
+            writer.write_all(request.as_bytes()).await?;
+            writer.flush().await?;
+            let head = read_head(&mut reader).await?;
+            accept_handshake(&head, &key, &config.protocols)
+        })
+        .await
+        .map_err(|_| NetworkError::TimeoutError(HANDSHAKE_TIMEOUT))??;
+
+        Ok(Self {
+            url: url.clone(),
+            protocol,
+            max_message_bytes: config.max_message_bytes,
+            reader: Mutex::new(reader),
+            writer: Mutex::new(writer),
+            close_sent: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Subprotocol the server picked from those offered
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Whether either side has closed the connection, or it failed
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.close_sent.load(Ordering::SeqCst)
+    }
+
+    /// Send a message. Fails once the connection is closing, and for a
+    /// message over the size limit, which leaves the connection open.
+    pub async fn send(&self, message: WebSocketMessage) -> Result<(), NetworkError> {
+        if self.is_closed() {
+            return Err(NetworkError::ConnectionError("WebSocket is closed".into()));
+        }
+        if message.len() > self.max_message_bytes {
+            return Err(NetworkError::ResourceError(format!(
+                "WebSocket message of {} bytes is over the {}-byte limit",
+                message.len(),
+                self.max_message_bytes
+            )));
+        }
+        match &message {
+            WebSocketMessage::Text(text) => self.write_frame(OP_TEXT, text.as_bytes()).await,
+            WebSocketMessage::Binary(data) => self.write_frame(OP_BINARY, data).await,
+        }
+    }
+
+    /// The next message, or `None` once the connection has closed. Pings
+    /// are answered and pongs dropped on the way. A message over the size
+    /// limit, or one breaking the protocol, closes the connection and is an
+    /// error.
+    pub async fn receive(&self) -> Result<Option<WebSocketMessage>, NetworkError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let mut reader = self.reader.lock().await;
+        // A fragmented message being put back together: its opcode and data
+        let mut message: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let frame = match read_frame(&mut *reader, self.max_message_bytes).await {
+                Ok(frame) => frame,
+                Err(failure) => return Err(self.fail(failure).await),
+            };
+            match frame.opcode {
+                OP_PING => {
+                    if !self.close_sent.load(Ordering::SeqCst) {
+                        self.write_frame(OP_PONG, &frame.payload).await?;
+                    }
+                }
+                OP_PONG => {}
+                OP_CLOSE => {
+                    self.closed.store(true, Ordering::SeqCst);
+                    // Echo the peer's code to complete the closing handshake
+                    let code = match frame.payload[..] {
+                        [high, low, ..] => u16::from_be_bytes([high, low]),
+                        _ => CLOSE_NORMAL,
+                    };
+                    if !self.close_sent.swap(true, Ordering::SeqCst) {
+                        let _ = self.write_frame(OP_CLOSE, &code.to_be_bytes()).await;
+                    }
+                    let _ = self.writer.lock().await.shutdown().await;
+                    return Ok(None);
+                }
+                OP_CONTINUATION => match message.as_mut() {
+                    Some((_, data))
+                        if data.len() + frame.payload.len() <= self.max_message_bytes =>
+                    {
+                        data.extend_from_slice(&frame.payload);
+                    }
+                    Some(_) => return Err(self.fail(too_big(self.max_message_bytes)).await),
+                    None => {
+                        let failure = protocol_error("continuation without a message");
+                        return Err(self.fail(failure).await);
+                    }
+                },
+                _ if message.is_some() => {
+                    let failure = protocol_error("new message inside a fragmented one");
+                    return Err(self.fail(failure).await);
+                }
+                _ => message = Some((frame.opcode, frame.payload)),
+            }
+            // Pings and pongs may come between the fragments of a message
+            if frame.opcode >= OP_CLOSE || !frame.fin {
+                continue;
+            }
+            match message.take() {
+                Some((OP_TEXT, data)) => {
+                    return match String::from_utf8(data) {
+                        Ok(text) => Ok(Some(WebSocketMessage::Text(text))),
+                        Err(_) => {
+                            let failure = (
+                                Some(CLOSE_INVALID_DATA),
+                                NetworkError::ResourceError(
+                                    "WebSocket text message is not UTF-8".into(),
+                                ),
+                            );
+                            Err(self.fail(failure).await)
+                        }
+                    };
+                }
+                Some((_, data)) => return Ok(Some(WebSocketMessage::Binary(data))),
+                None => {}
+            }
+        }
+    }
+
+    /// Start the closing handshake with `code` and `reason`, cut to fit a
+    /// control frame. Sends fail from now on; [`receive`](Self::receive)
+    /// returns what the peer still sends, then `None` once it confirms.
+    pub async fn close(&self, code: u16, reason: &str) -> Result<(), NetworkError> {
+        if self.close_sent.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut end = reason.len().min(MAX_CONTROL_PAYLOAD - 2);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason[..end].as_bytes());
+        self.write_frame(OP_CLOSE, &payload).await
+    }
+
+    /// Mark the connection closed after `failure`, telling the peer why if
+    /// it can still hear it
+    async fn fail(&self, (code, error): Failure) -> NetworkError {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Some(code) = code {
+            if !self.close_sent.swap(true, Ordering::SeqCst) {
+                let _ = self.write_frame(OP_CLOSE, &code.to_be_bytes()).await;
+            }
+        }
+        error
+    }
+
+    /// Write one final frame, masked with a fresh key as clients must
+    async fn write_frame(&self, opcode: u8, payload: &[u8]) -> Result<(), NetworkError> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask: [u8; 4] = rand::random();
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// The upgrade request: the uniform User-Agent, the page's origin, the key
+/// and the offered subprotocols, and no other headers
+fn handshake_request(url: &Url, page: &Url, key: &str, protocols: &[String]) -> String {
+    let host = url.host_str().unwrap_or_default();
+    let host = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {host}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+         User-Agent: {}\r\nOrigin: {}\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: {key}\r\n",
+        http::request_target(url),
+        http::USER_AGENT,
+        page.origin().ascii_serialization(),
+    );
+    let protocols: Vec<&str> = protocols
+        .iter()
+        .map(String::as_str)
+        .filter(|protocol| is_token(protocol))
+        .collect();
+    if !protocols.is_empty() {
+        request.push_str("Sec-WebSocket-Protocol: ");
+        request.push_str(&protocols.join(", "));
+        request.push_str("\r\n");
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// An HTTP token (RFC 9110 §5.6.2), so no CRLF or separators
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Read the response head, up to and including the blank line, and no further
+async fn read_head<R>(reader: &mut R) -> Result<Vec<u8>, NetworkError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        let limit = (MAX_HANDSHAKE_BYTES - head.len()) as u64;
+        let read = (&mut *reader)
+            .take(limit)
+            .read_until(b'\n', &mut head)
+            .await?;
+        if head.len() >= MAX_HANDSHAKE_BYTES {
+            return Err(NetworkError::ResourceError(
+                "WebSocket handshake response is too large".into(),
+            ));
+        }
+        if read == 0 {
+            return Err(NetworkError::ConnectionError(
+                "connection closed during the WebSocket handshake".into(),
+            ));
+        }
+        if head[start..] == *b"\r\n" {
+            return Ok(head);
+        }
+    }
+}
+
+/// Check the server accepted the upgrade for `key`; returns the subprotocol
+/// it picked
+fn accept_handshake(
+    head: &[u8],
+    key: &str,
+    offered: &[String],
+) -> Result<Option<String>, NetworkError> {
+    let (status, headers) = http::parse_head(head)?;
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+    if status != 101 {
+        return Err(NetworkError::HttpStatus(status));
+    }
+    let upgraded = header("upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+        && header("connection").is_some_and(|v| {
+            v.split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        });
+    if !upgraded {
+        return Err(NetworkError::ConnectionError(
+            "server did not upgrade to WebSocket".into(),
+        ));
+    }
+    if header("sec-websocket-accept") != Some(accept_key(key).as_str()) {
+        return Err(NetworkError::ConnectionError(
+            "server sent a wrong Sec-WebSocket-Accept".into(),
+        ));
+    }
+    if header("sec-websocket-extensions").is_some() {
+        return Err(NetworkError::ConnectionError(
+            "server enabled WebSocket extensions that were not offered".into(),
+        ));
+    }
+    match header("sec-websocket-protocol") {
+        None => Ok(None),
+        Some(picked) if offered.iter().any(|p| p == picked) => Ok(Some(picked.to_string())),
+        Some(picked) => Err(NetworkError::ConnectionError(format!(
+            "server picked subprotocol {picked:?}, which was not offered"
+        ))),
+    }
+}
+
+/// `Sec-WebSocket-Accept` for `key`
+fn accept_key(key: &str) -> String {
+    let digest = ring::digest::digest(
+        &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{key}{ACCEPT_GUID}").as_bytes(),
+    );
+    general_purpose::STANDARD.encode(digest)
+}
+
+/// Read one frame from the server
+async fn read_frame<R>(reader: &mut R, max_message_bytes: usize) -> Result<Frame, Failure>
+where
+    R: AsyncRead + Unpin,
+{
+    let broken = |e: std::io::Error| (None, NetworkError::IoError(e));
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await.map_err(broken)?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    if head[0] & 0x70 != 0 {
+        return Err(protocol_error("reserved bits set"));
+    }
+    if head[1] & 0x80 != 0 {
+        return Err(protocol_error("server frames must not be masked"));
+    }
+    if !matches!(
+        opcode,
+        OP_CONTINUATION | OP_TEXT | OP_BINARY | OP_CLOSE | OP_PING | OP_PONG
+    ) {
+        return Err(protocol_error("unknown opcode"));
+    }
+    let len = match head[1] & 0x7F {
+        126 => reader.read_u16().await.map_err(broken)? as u64,
+        127 => reader.read_u64().await.map_err(broken)?,
+        len => len as u64,
+    };
+    if opcode >= OP_CLOSE && (!fin || len > MAX_CONTROL_PAYLOAD as u64) {
+        return Err(protocol_error("malformed control frame"));
+    }
+    if len > max_message_bytes as u64 {
+        return Err(too_big(max_message_bytes));
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await.map_err(broken)?;
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+fn protocol_error(what: &str) -> Failure {
+    (
+        Some(CLOSE_PROTOCOL_ERROR),
+        NetworkError::ConnectionError(format!("WebSocket protocol error: {what}")),
+    )
+}
+
+fn too_big(max_message_bytes: usize) -> Failure {
+    (
+        Some(CLOSE_TOO_BIG),
+        NetworkError::ResourceError(format!(
+            "WebSocket message is over the {max_message_bytes}-byte limit"
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// Answer the client's upgrade request, picking `protocol`
+    async fn accept(server: &mut DuplexStream, protocol: Option<&str>) -> String {
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(server.read_u8().await.unwrap());
+        }
+        let request = String::from_utf8(request).unwrap();
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let mut response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n",
+            accept_key(key)
+        );
+        if let Some(protocol) = protocol {
+            response.push_str(&format!("Sec-WebSocket-Protocol: {protocol}\r\n"));
+        }
+        response.push_str("\r\n");
+        server.write_all(response.as_bytes()).await.unwrap();
+        request
+    }
+
+    /// An unmasked server frame
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![(if fin { 0x80 } else { 0 }) | opcode];
+        assert!(payload.len() < 126);
+        frame.push(payload.len() as u8);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Read a client frame, checking it is masked
+    async fn client_frame(server: &mut DuplexStream) -> (u8, Vec<u8>) {
+        let head = [
+            server.read_u8().await.unwrap(),
+            server.read_u8().await.unwrap(),
+        ];
+        assert_eq!(head[1] & 0x80, 0x80, "client frames are masked");
+        let len = match head[1] & 0x7F {
+            126 => server.read_u16().await.unwrap() as usize,
+            len => len as usize,
+        };
+        let mut mask = [0u8; 4];
+        server.read_exact(&mut mask).await.unwrap();
+        let mut payload = vec![0u8; len];
+        server.read_exact(&mut payload).await.unwrap();
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        (head[0] & 0x0F, payload)
+    }
+
+    fn urls() -> (Url, Url) {
+        (
+            Url::parse("wss://chat.app.example/socket?room=1").unwrap(),
+            Url::parse("https://app.example/page").unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_plain_ws_and_ungranted_origins_are_refused() {
+        let permissions = WebSocketPermissions::new();
+        let page = Url::parse("https://app.example/").unwrap();
+        let socket = Url::parse("wss://chat.app.example/").unwrap();
+        assert!(permissions.check(&page, &socket).is_some());
+
+        permissions.grant(&page, HostPolicy::allow_only(["*.app.example"]));
+        assert!(permissions.check(&page, &socket).is_none());
+        let tracker = Url::parse("wss://tracker.test/").unwrap();
+        assert!(permissions.check(&page, &tracker).is_some());
+        let other = Url::parse("https://other.example/").unwrap();
+        assert!(permissions.check(&other, &socket).is_some());
+        let opaque = Url::parse("data:text/html,hi").unwrap();
+        assert!(permissions.check(&opaque, &socket).is_some());
+
+        // Refused before any connection is attempted
+        let config = WebSocketConfig::default();
+        let plain = Url::parse("ws://chat.app.example/").unwrap();
+        let err =
+            WebSocketConnection::connect(&plain, &page, &permissions, &config, StreamRoute::Direct)
+                .await
+                .unwrap_err();
+        assert!(
+            matches!(err, NetworkError::HttpsEnforcementError(_)),
+            "{err}"
+        );
+        let err = WebSocketConnection::connect(
+            &tracker,
+            &page,
+            &permissions,
+            &config,
+            StreamRoute::Direct,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, NetworkError::PrivacyViolationError(_)),
+            "{err}"
+        );
+
+        assert!(permissions.revoke(&page).is_some());
+        assert!(permissions.check(&page, &socket).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_messages_and_closing() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let (url, page) = urls();
+        let config = WebSocketConfig {
+            protocols: vec!["chat".into(), "bad\r\nX-Injected: 1".into()],
+            ..WebSocketConfig::default()
+        };
+        let server = tokio::spawn(async move {
+            let request = accept(&mut server, Some("chat")).await;
+            assert!(request.starts_with("GET /socket?room=1 HTTP/1.1\r\n"));
+            assert!(request.contains("\r\nOrigin: https://app.example\r\n"));
+            assert!(request.contains("\r\nSec-WebSocket-Protocol: chat\r\n"));
+            assert!(!request.contains("X-Injected") && !request.contains("Cookie"));
+
+            server.write_all(&frame(true, OP_PING, b"p")).await.unwrap();
+            server
+                .write_all(&frame(false, OP_TEXT, b"hel"))
+                .await
+                .unwrap();
+            server
+                .write_all(&frame(true, OP_CONTINUATION, b"lo"))
+                .await
+                .unwrap();
+            assert_eq!(client_frame(&mut server).await, (OP_PONG, b"p".to_vec()));
+            assert_eq!(client_frame(&mut server).await, (OP_TEXT, b"hi".to_vec()));
+            server
+                .write_all(&frame(true, OP_BINARY, &[1, 2, 3]))
+                .await
+                .unwrap();
+            server
+                .write_all(&frame(true, OP_CLOSE, &CLOSE_NORMAL.to_be_bytes()))
+                .await
+                .unwrap();
+            assert_eq!(
+                client_frame(&mut server).await,
+                (OP_CLOSE, CLOSE_NORMAL.to_be_bytes().to_vec())
+            );
+        });
+
+        let socket = WebSocketConnection::handshake(client, &url, &page, &config)
+            .await
+            .unwrap();
+        assert_eq!(socket.protocol(), Some("chat"));
+        assert_eq!(
+            socket.receive().await.unwrap(),
+            Some(WebSocketMessage::Text("hello".into()))
+        );
+        socket
+            .send(WebSocketMessage::Text("hi".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            socket.receive().await.unwrap(),
+            Some(WebSocketMessage::Binary(vec![1, 2, 3]))
+        );
+        assert_eq!(socket.receive().await.unwrap(), None);
+        assert!(socket.is_closed());
+        assert!(socket
+            .send(WebSocketMessage::Text("late".into()))
+            .await
+            .is_err());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_are_refused_both_ways() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let (url, page) = urls();
+        let config = WebSocketConfig {
+            max_message_bytes: 8,
+            ..WebSocketConfig::default()
+        };
+        let server = tokio::spawn(async move {
+            accept(&mut server, None).await;
+            // Within the limit frame by frame, over it once put together
+            server
+                .write_all(&frame(false, OP_BINARY, &[0; 6]))
+                .await
+                .unwrap();
+            server
+                .write_all(&frame(true, OP_CONTINUATION, &[0; 6]))
+                .await
+                .unwrap();
+            assert_eq!(
+                client_frame(&mut server).await,
+                (OP_CLOSE, CLOSE_TOO_BIG.to_be_bytes().to_vec())
+            );
+        });
+
+        let socket = WebSocketConnection::handshake(client, &url, &page, &config)
+            .await
+            .unwrap();
+        assert_eq!(socket.protocol(), None);
+        let err = socket
+            .send(WebSocketMessage::Binary(vec![0; 9]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, NetworkError::ResourceError(_)), "{err}");
+        assert!(!socket.is_closed());
+
+        let err = socket.receive().await.unwrap_err();
+        assert!(matches!(err, NetworkError::ResourceError(_)), "{err}");
+        assert!(socket.is_closed());
+        assert_eq!(socket.receive().await.unwrap(), None);
+        server.await.unwrap();
+    }
+}
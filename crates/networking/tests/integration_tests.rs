@@ -6,7 +6,7 @@ use citadel_networking::{
     resource_discovery::{ResourceContext, ResourceDiscovery},
     resource_loader::{LoadOptions, ResourceLoader},
     resource_manager::{CachePolicy, ResourceManager, ResourceManagerConfig, ResourcePolicy},
-    NetworkConfig, PrivacyLevel, Request, Resource,
+    NetworkConfig, PrivacyLevel, Request, RequestBudget, Resource,
 };
 use url::Url;
 
@@ -348,6 +348,7 @@ async fn test_resource_manager_privacy_policies() {
         cache_policy: CachePolicy::AlwaysValidate,
         max_cache_size_mb: 25,
         default_cache_ttl: Duration::from_secs(1800),
+        request_budget: RequestBudget::for_privacy_level(PrivacyLevel::Maximum),
     };
 
     let manager = ResourceManager::with_config(config)
@@ -0,0 +1,135 @@
+//! The HTTPS client against the local fixture server (`test-support`).
+//!
+//! Unlike `https_client.rs` these need no network: redirects, header shape
+//! and certificate failures are checked against fixtures on loopback.
+
+use citadel_networking::resource::ResourceType;
+use citadel_networking::test_server::{Fixture, TestServer};
+use citadel_networking::{
+    https_fetch, BlockingPolicy, LoadErrorCategory, NetworkError, ResourceManager,
+};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn follows_and_records_redirects() {
+    let server = TestServer::start(&[
+        ("/old", Fixture::redirect(302, "/new")),
+        ("/new", Fixture::html("<title>New</title>")),
+    ])
+    .await
+    .expect("start server");
+
+    let resp = https_fetch(&server.url("/old"), &[]).await.expect("fetch");
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body_text(), "<title>New</title>");
+    assert_eq!(resp.final_url, server.url("/new").as_str());
+    assert_eq!(resp.redirect_chain, vec![server.url("/old").to_string()]);
+    assert_eq!(server.paths(), ["/old", "/new"]);
+}
+
+#[tokio::test]
+async fn refuses_redirects_off_https() {
+    let server = TestServer::start(&[("/", Fixture::redirect(301, "http://localhost/plain"))])
+        .await
+        .expect("start server");
+
+    let err = https_fetch(&server.url("/"), &[]).await.unwrap_err();
+    assert_eq!(err.category(), LoadErrorCategory::InsecureConnection);
+}
+
+#[tokio::test]
+async fn sends_the_uniform_request_shape() {
+    let server = TestServer::start(&[("/", Fixture::html("ok"))])
+        .await
+        .expect("start server");
+
+    https_fetch(
+        &server.url("/?q=1"),
+        &[
+            ("User-Agent".to_string(), "custom/1.0".to_string()),
+            ("X-Fixture".to_string(), "kept".to_string()),
+        ],
+    )
+    .await
+    .expect("fetch");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.target, "/?q=1");
+    // Managed headers cannot be overridden; others pass through
+    assert!(request
+        .header("user-agent")
+        .is_some_and(|ua| ua.starts_with("Mozilla/5.0 (Windows NT 10.0")));
+    assert_eq!(request.header("x-fixture"), Some("kept"));
+    assert_eq!(request.header("connection"), Some("close"));
+}
+
+#[tokio::test]
+async fn untrusted_certificate_is_a_tls_failure() {
+    let server = TestServer::untrusted().await.expect("start server");
+
+    let err = https_fetch(&server.url("/"), &[]).await.unwrap_err();
+    assert!(matches!(err, NetworkError::TlsError(_)), "{err:?}");
+    assert_eq!(err.category(), LoadErrorCategory::Tls);
+    assert!(server.requests().is_empty(), "nothing sent over bad TLS");
+}
+
+#[tokio::test]
+async fn csp_holds_across_redirects() {
+    let elsewhere = TestServer::start(&[("/pixel.gif", Fixture::html("GIF89a"))])
+        .await
+        .expect("start server");
+    let pixel = elsewhere.url("/pixel.gif");
+    let server = TestServer::start(&[
+        (
+            "/",
+            Fixture::html(
+                "<head><meta http-equiv=\"Content-Security-Policy\" \
+                 content=\"img-src 'self'\"></head><body>Page</body>",
+            ),
+        ),
+        ("/logo.png", Fixture::redirect(302, pixel.as_str())),
+    ])
+    .await
+    .expect("start server");
+    let manager = ResourceManager::new().await.expect("manager starts");
+    let tab = Uuid::new_v4();
+
+    manager
+        .fetch_html_for_tab(tab, server.url("/").as_str())
+        .await
+        .expect("page loads");
+    // The <meta> policy refuses other origins outright...
+    let err = manager
+        .fetch_for_tab(tab, pixel.as_str(), Some(ResourceType::Image))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, NetworkError::PrivacyViolationError(_)),
+        "{err:?}"
+    );
+    assert!(elsewhere.requests().is_empty());
+
+    // ...and through a redirect from its own
+    let err = manager
+        .fetch_for_tab(
+            tab,
+            server.url("/logo.png").as_str(),
+            Some(ResourceType::Image),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, NetworkError::PrivacyViolationError(_)),
+        "{err:?}"
+    );
+    assert_eq!(elsewhere.paths(), ["/pixel.gif"]);
+    let log = manager.request_ledger().requests(tab);
+    let blocked = log.iter().rev().find(|record| record.url == pixel).unwrap();
+    assert_eq!(
+        blocked.blocked_by(),
+        Some(&BlockingPolicy::ContentSecurityPolicy)
+    );
+}
@@ -1,6 +1,6 @@
 use citadel_networking::{
-    CachePolicy, NetworkConfig, OriginType, PrivacyLevel, ResourceManager, ResourceManagerConfig,
-    ResourcePolicy,
+    CachePolicy, NetworkConfig, OriginType, PrivacyLevel, RequestBudget, ResourceManager,
+    ResourceManagerConfig, ResourcePolicy,
 };
 use std::time::Duration;
 
@@ -21,6 +21,7 @@ async fn test_resource_manager_creation() {
         cache_policy: CachePolicy::PreferCache,
         max_cache_size_mb: 100,
         default_cache_ttl: Duration::from_secs(7200),
+        request_budget: RequestBudget::for_privacy_level(PrivacyLevel::Maximum),
     };
 
     let custom_manager = ResourceManager::with_config(config)
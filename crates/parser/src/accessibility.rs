@@ -0,0 +1,697 @@
+//! Accessibility tree derivation
+//!
+//! Builds a platform-neutral accessibility tree (roles, accessible names and
+//! states) from a parsed [`Dom`] and, when available, its computed
+//! [`LayoutResult`]. The browser crate bridges this tree to the OS
+//! accessibility APIs; the parser only decides what assistive technology
+//! is allowed to see.
+
+use std::collections::HashMap;
+
+use crate::dom::{Dom, Node, NodeData};
+use crate::layout::{LayoutRect, LayoutResult};
+
+/// Maximum length of an accessible name derived from subtree text
+const MAX_NAME_LENGTH: usize = 256;
+
+/// Semantic role of an accessibility node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Document,
+    Article,
+    Banner,
+    Button,
+    Cell,
+    CheckBox,
+    ColumnHeader,
+    ComboBox,
+    Complementary,
+    ContentInfo,
+    Figure,
+    Form,
+    Generic,
+    Heading(u8),
+    Image,
+    Link,
+    List,
+    ListItem,
+    Main,
+    Navigation,
+    Paragraph,
+    RadioButton,
+    Region,
+    Row,
+    Separator,
+    StaticText,
+    Table,
+    TextInput,
+}
+
+impl Role {
+    /// Derive the implicit role for an element, honouring an explicit `role` attribute
+    fn for_element(tag: &str, node: &Node) -> Role {
+        if let Some(explicit) = node
+            .as_element()
+            .and_then(|e| e.get_attribute("role"))
+            .and_then(|r| Role::from_aria(&r))
+        {
+            return explicit;
+        }
+
+        match tag {
+            "a" if node.as_element().is_some_and(|e| e.has_attribute("href")) => Role::Link,
+            "article" => Role::Article,
+            "aside" => Role::Complementary,
+            "button" => Role::Button,
+            "figure" => Role::Figure,
+            "footer" => Role::ContentInfo,
+            "form" => Role::Form,
+            "h1" => Role::Heading(1),
+            "h2" => Role::Heading(2),
+            "h3" => Role::Heading(3),
+            "h4" => Role::Heading(4),
+            "h5" => Role::Heading(5),
+            "h6" => Role::Heading(6),
+            "header" => Role::Banner,
+            "hr" => Role::Separator,
+            "img" => Role::Image,
+            "input" => Self::for_input(node),
+            "li" => Role::ListItem,
+            "main" => Role::Main,
+            "nav" => Role::Navigation,
+            "ol" | "ul" => Role::List,
+            "p" => Role::Paragraph,
+            "section" => Role::Region,
+            "select" => Role::ComboBox,
+            "table" => Role::Table,
+            "td" => Role::Cell,
+            "textarea" => Role::TextInput,
+            "th" => Role::ColumnHeader,
+            "tr" => Role::Row,
+            _ => Role::Generic,
+        }
+    }
+
+    /// Role for an `<input>` based on its type
+    fn for_input(node: &Node) -> Role {
+        let input_type = node
+            .as_element()
+            .and_then(|e| e.get_attribute("type"))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match input_type.as_str() {
+            "checkbox" => Role::CheckBox,
+            "radio" => Role::RadioButton,
+            "button" | "submit" | "reset" => Role::Button,
+            _ => Role::TextInput,
+        }
+    }
+
+    /// Map an ARIA `role` attribute value to a role
+    fn from_aria(value: &str) -> Option<Role> {
+        // Only the first token counts; later tokens are fallbacks
+        let role = value.split_whitespace().next()?.to_ascii_lowercase();
+        Some(match role.as_str() {
+            "article" => Role::Article,
+            "banner" => Role::Banner,
+            "button" => Role::Button,
+            "cell" | "gridcell" => Role::Cell,
+            "checkbox" => Role::CheckBox,
+            "columnheader" => Role::ColumnHeader,
+            "combobox" => Role::ComboBox,
+            "complementary" => Role::Complementary,
+            "contentinfo" => Role::ContentInfo,
+            "figure" => Role::Figure,
+            "form" => Role::Form,
+            "heading" => Role::Heading(2),
+            "img" | "image" => Role::Image,
+            "link" => Role::Link,
+            "list" => Role::List,
+            "listitem" => Role::ListItem,
+            "main" => Role::Main,
+            "navigation" => Role::Navigation,
+            "paragraph" => Role::Paragraph,
+            "radio" => Role::RadioButton,
+            "region" => Role::Region,
+            "row" => Role::Row,
+            "separator" => Role::Separator,
+            "table" | "grid" => Role::Table,
+            "textbox" | "searchbox" => Role::TextInput,
+            "none" | "presentation" | "generic" => Role::Generic,
+            _ => return None,
+        })
+    }
+
+    /// Whether the accessible name of this role is computed from its content
+    fn name_from_content(&self) -> bool {
+        matches!(
+            self,
+            Role::Button
+                | Role::Cell
+                | Role::ColumnHeader
+                | Role::Heading(_)
+                | Role::Link
+                | Role::ListItem
+                | Role::RadioButton
+                | Role::CheckBox
+        )
+    }
+
+    /// Whether the role is interactive and should be keyboard focusable
+    pub fn is_interactive(&self) -> bool {
+        matches!(
+            self,
+            Role::Button
+                | Role::CheckBox
+                | Role::ComboBox
+                | Role::Link
+                | Role::RadioButton
+                | Role::TextInput
+        )
+    }
+
+    /// Short human readable label used in screen reader output
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Document => "document",
+            Role::Article => "article",
+            Role::Banner => "banner",
+            Role::Button => "button",
+            Role::Cell => "cell",
+            Role::CheckBox => "checkbox",
+            Role::ColumnHeader => "column header",
+            Role::ComboBox => "combo box",
+            Role::Complementary => "complementary",
+            Role::ContentInfo => "content info",
+            Role::Figure => "figure",
+            Role::Form => "form",
+            Role::Generic => "group",
+            Role::Heading(_) => "heading",
+            Role::Image => "image",
+            Role::Link => "link",
+            Role::List => "list",
+            Role::ListItem => "list item",
+            Role::Main => "main",
+            Role::Navigation => "navigation",
+            Role::Paragraph => "paragraph",
+            Role::RadioButton => "radio button",
+            Role::Region => "region",
+            Role::Row => "row",
+            Role::Separator => "separator",
+            Role::StaticText => "text",
+            Role::Table => "table",
+            Role::TextInput => "edit text",
+        }
+    }
+}
+
+/// Dynamic state of an accessibility node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessibilityState {
+    /// Node can receive keyboard focus
+    pub focusable: bool,
+    /// Node is disabled
+    pub disabled: bool,
+    /// Checked state for checkboxes and radio buttons
+    pub checked: Option<bool>,
+    /// Form control must be filled in
+    pub required: bool,
+}
+
+/// A single node in the accessibility tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityNode {
+    /// DOM node ID (same ID space as [`LayoutResult::node_layouts`])
+    pub id: u32,
+    /// Semantic role
+    pub role: Role,
+    /// Accessible name
+    pub name: String,
+    /// Current value for form controls
+    pub value: Option<String>,
+    /// Link target for links
+    pub url: Option<String>,
+    /// Absolute bounds in document coordinates, if laid out
+    pub bounds: Option<LayoutRect>,
+    /// Dynamic state
+    pub state: AccessibilityState,
+    /// Child node IDs in document order
+    pub children: Vec<u32>,
+}
+
+/// Accessibility tree for a single document
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityTree {
+    root: u32,
+    nodes: HashMap<u32, AccessibilityNode>,
+}
+
+impl AccessibilityTree {
+    /// Build the accessibility tree for a DOM and optional computed layout
+    pub fn build(dom: &Dom, layout: Option<&LayoutResult>) -> Self {
+        let mut builder = TreeBuilder {
+            layout,
+            nodes: HashMap::new(),
+        };
+
+        let root_handle = dom.root();
+        let root = match root_handle.read() {
+            Ok(root_node) => {
+                let root_id = root_node.id();
+                let origin = (0.0, 0.0);
+                let bounds = builder.absolute_bounds(root_id, origin);
+                let offset = bounds.as_ref().map(|b| (b.x, b.y)).unwrap_or(origin);
+                let mut children = Vec::new();
+                for child in root_node.children() {
+                    if let Ok(child_node) = child.read() {
+                        builder.visit(&child_node, offset, &mut children);
+                    }
+                }
+                builder.nodes.insert(
+                    root_id,
+                    AccessibilityNode {
+                        id: root_id,
+                        role: Role::Document,
+                        name: dom.get_title(),
+                        value: None,
+                        url: None,
+                        bounds,
+                        state: AccessibilityState::default(),
+                        children,
+                    },
+                );
+                root_id
+            }
+            Err(_) => 0,
+        };
+
+        Self {
+            root,
+            nodes: builder.nodes,
+        }
+    }
+
+    /// ID of the document node
+    pub fn root(&self) -> u32 {
+        self.root
+    }
+
+    /// Look up a node by ID
+    pub fn get(&self, id: u32) -> Option<&AccessibilityNode> {
+        self.nodes.get(&id)
+    }
+
+    /// Iterate over all nodes in unspecified order
+    pub fn nodes(&self) -> impl Iterator<Item = &AccessibilityNode> {
+        self.nodes.values()
+    }
+
+    /// Number of nodes in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree contains only the document node (or nothing)
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() <= 1
+    }
+
+    /// Focusable node IDs in document order
+    pub fn focusable_nodes(&self) -> Vec<u32> {
+        let mut out = Vec::new();
+        self.walk(self.root, &mut |node, _| {
+            if node.state.focusable {
+                out.push(node.id);
+            }
+        });
+        out
+    }
+
+    /// Linearized screen reader output, one announcement per line
+    pub fn to_screen_reader_text(&self) -> String {
+        let mut lines = Vec::new();
+        self.walk(self.root, &mut |node, depth| {
+            if node.role == Role::Generic || (node.role == Role::StaticText && depth == 0) {
+                return;
+            }
+            let mut line = match node.role {
+                Role::StaticText => node.name.clone(),
+                Role::Heading(level) => format!("heading level {}: {}", level, node.name),
+                role if node.name.is_empty() => role.label().to_string(),
+                role => format!("{}: {}", role.label(), node.name),
+            };
+            if let Some(value) = &node.value {
+                line.push_str(&format!(", value {}", value));
+            }
+            match node.state.checked {
+                Some(true) => line.push_str(", checked"),
+                Some(false) => line.push_str(", not checked"),
+                None => {}
+            }
+            if node.state.disabled {
+                line.push_str(", disabled");
+            }
+            if node.state.required {
+                line.push_str(", required");
+            }
+            lines.push(format!("{}{}", "  ".repeat(depth), line));
+        });
+        lines.join("\n")
+    }
+
+    /// Depth-first pre-order traversal
+    fn walk(&self, id: u32, visit: &mut dyn FnMut(&AccessibilityNode, usize)) {
+        fn inner(
+            tree: &AccessibilityTree,
+            id: u32,
+            depth: usize,
+            visit: &mut dyn FnMut(&AccessibilityNode, usize),
+        ) {
+            if let Some(node) = tree.nodes.get(&id) {
+                visit(node, depth);
+                for child in &node.children {
+                    inner(tree, *child, depth + 1, visit);
+                }
+            }
+        }
+        inner(self, id, 0, visit);
+    }
+}
+
+/// Recursive DOM walker that accumulates accessibility nodes
+struct TreeBuilder<'a> {
+    layout: Option<&'a LayoutResult>,
+    nodes: HashMap<u32, AccessibilityNode>,
+}
+
+impl TreeBuilder<'_> {
+    /// Layout rects are parent-relative; convert to document coordinates
+    fn absolute_bounds(&self, id: u32, parent_origin: (f32, f32)) -> Option<LayoutRect> {
+        self.layout
+            .and_then(|l| l.node_layouts.get(&id))
+            .map(|rect| {
+                LayoutRect::new(
+                    parent_origin.0 + rect.x,
+                    parent_origin.1 + rect.y,
+                    rect.width,
+                    rect.height,
+                )
+            })
+    }
+
+    /// Visit a DOM node, pushing the IDs of any accessibility nodes it produces
+    fn visit(&mut self, node: &Node, parent_origin: (f32, f32), out: &mut Vec<u32>) {
+        match &node.data {
+            NodeData::Text(text) => {
+                let text = collapse_whitespace(text);
+                if text.is_empty() {
+                    return;
+                }
+                let id = node.id();
+                let bounds = self.absolute_bounds(id, parent_origin);
+                self.nodes.insert(
+                    id,
+                    AccessibilityNode {
+                        id,
+                        role: Role::StaticText,
+                        name: text,
+                        value: None,
+                        url: None,
+                        bounds,
+                        state: AccessibilityState::default(),
+                        children: Vec::new(),
+                    },
+                );
+                out.push(id);
+            }
+            NodeData::Element(element) => {
+                let tag = element.local_name().to_ascii_lowercase();
+                if is_excluded(&tag, node) {
+                    return;
+                }
+
+                let id = node.id();
+                let bounds = self.absolute_bounds(id, parent_origin);
+                let origin = bounds.as_ref().map(|b| (b.x, b.y)).unwrap_or(parent_origin);
+                let role = Role::for_element(&tag, node);
+
+                let mut children = Vec::new();
+                // Images, inputs and separators are leaves for assistive tech
+                if !matches!(
+                    role,
+                    Role::Image
+                        | Role::Separator
+                        | Role::TextInput
+                        | Role::CheckBox
+                        | Role::RadioButton
+                ) {
+                    for child in node.children() {
+                        if let Ok(child_node) = child.read() {
+                            self.visit(&child_node, origin, &mut children);
+                        }
+                    }
+                }
+
+                let name = self.accessible_name(node, role, &children);
+
+                // Nameless generic wrappers are flattened so the tree stays readable
+                if role == Role::Generic && name.is_empty() {
+                    out.extend(children);
+                    return;
+                }
+
+                let state = element_state(&tag, node, role);
+                let value = match role {
+                    Role::TextInput => element.get_attribute("value").or_else(|| {
+                        (tag == "textarea").then(|| collapse_whitespace(&node.text_content()))
+                    }),
+                    _ => None,
+                };
+                let url = match role {
+                    Role::Link => element.get_attribute("href"),
+                    _ => None,
+                };
+
+                self.nodes.insert(
+                    id,
+                    AccessibilityNode {
+                        id,
+                        role,
+                        name,
+                        value,
+                        url,
+                        bounds,
+                        state,
+                        children,
+                    },
+                );
+                out.push(id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Compute the accessible name (simplified accname algorithm)
+    fn accessible_name(&self, node: &Node, role: Role, children: &[u32]) -> String {
+        let element = match node.as_element() {
+            Some(element) => element,
+            None => return String::new(),
+        };
+
+        if let Some(label) = element.get_attribute("aria-label") {
+            let label = collapse_whitespace(&label);
+            if !label.is_empty() {
+                return truncate_name(label);
+            }
+        }
+
+        if role == Role::Image {
+            if let Some(alt) = element.get_attribute("alt") {
+                return truncate_name(collapse_whitespace(&alt));
+            }
+        }
+
+        if matches!(role, Role::TextInput | Role::ComboBox) {
+            if let Some(placeholder) = element.get_attribute("placeholder") {
+                return truncate_name(collapse_whitespace(&placeholder));
+            }
+        }
+
+        if role == Role::Button && element.local_name().eq_ignore_ascii_case("input") {
+            if let Some(value) = element.get_attribute("value") {
+                return truncate_name(collapse_whitespace(&value));
+            }
+        }
+
+        if role.name_from_content() {
+            let text: Vec<&str> = children
+                .iter()
+                .filter_map(|id| self.nodes.get(id))
+                .filter(|n| !n.name.is_empty())
+                .map(|n| n.name.as_str())
+                .collect();
+            if !text.is_empty() {
+                return truncate_name(text.join(" "));
+            }
+        }
+
+        element
+            .get_attribute("title")
+            .map(|t| truncate_name(collapse_whitespace(&t)))
+            .unwrap_or_default()
+    }
+}
+
+/// Elements and subtrees that never appear in the accessibility tree
+fn is_excluded(tag: &str, node: &Node) -> bool {
+    if matches!(
+        tag,
+        "head" | "script" | "style" | "template" | "noscript" | "meta" | "link" | "title"
+    ) {
+        return true;
+    }
+    node.as_element().is_some_and(|e| {
+        e.has_attribute("hidden")
+            || e.get_attribute("aria-hidden")
+                .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+            || (tag == "input"
+                && e.get_attribute("type")
+                    .is_some_and(|t| t.eq_ignore_ascii_case("hidden")))
+    })
+}
+
+/// Derive the dynamic state of an element
+fn element_state(tag: &str, node: &Node, role: Role) -> AccessibilityState {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return AccessibilityState::default(),
+    };
+
+    let disabled = element.has_attribute("disabled")
+        || element
+            .get_attribute("aria-disabled")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    let checked = match role {
+        Role::CheckBox | Role::RadioButton => Some(
+            element.has_attribute("checked")
+                || element
+                    .get_attribute("aria-checked")
+                    .is_some_and(|v| v.eq_ignore_ascii_case("true")),
+        ),
+        _ => None,
+    };
+    let explicit_tabindex = element
+        .get_attribute("tabindex")
+        .and_then(|t| t.trim().parse::<i32>().ok());
+    let focusable = !disabled
+        && match explicit_tabindex {
+            Some(index) => index >= 0,
+            None => role.is_interactive() || (tag == "a" && role == Role::Link),
+        };
+
+    AccessibilityState {
+        focusable,
+        disabled,
+        checked,
+        required: element.has_attribute("required"),
+    }
+}
+
+/// Collapse runs of whitespace into single spaces and trim
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cap derived names so huge subtrees do not flood screen readers
+fn truncate_name(name: String) -> String {
+    if name.chars().count() <= MAX_NAME_LENGTH {
+        return name;
+    }
+    name.chars().take(MAX_NAME_LENGTH).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutSize;
+    use crate::parse_html;
+    use crate::security::SecurityContext;
+    use std::sync::Arc;
+
+    fn build(html: &str) -> AccessibilityTree {
+        let dom = parse_html(html, Arc::new(SecurityContext::new(10))).unwrap();
+        AccessibilityTree::build(&dom, None)
+    }
+
+    #[test]
+    fn test_roles_and_names() {
+        let tree = build(
+            "<html><head><title>Doc</title></head><body>\
+             <h2>Section title</h2>\
+             <nav><a href=\"https://example.com/\">Example link</a></nav>\
+             <img src=\"https://example.com/a.png\" alt=\"A cat\">\
+             </body></html>",
+        );
+
+        let root = tree.get(tree.root()).unwrap();
+        assert_eq!(root.role, Role::Document);
+        assert_eq!(root.name, "Doc");
+
+        let heading = tree.nodes().find(|n| n.role == Role::Heading(2)).unwrap();
+        assert_eq!(heading.name, "Section title");
+
+        let link = tree.nodes().find(|n| n.role == Role::Link).unwrap();
+        assert_eq!(link.name, "Example link");
+        assert_eq!(link.url.as_deref(), Some("https://example.com/"));
+        assert!(link.state.focusable);
+
+        let image = tree.nodes().find(|n| n.role == Role::Image).unwrap();
+        assert_eq!(image.name, "A cat");
+        assert!(tree.nodes().any(|n| n.role == Role::Navigation));
+    }
+
+    #[test]
+    fn test_generic_wrappers_are_flattened() {
+        let tree = build("<html><body><div><div><p>Hello   world</p></div></div></body></html>");
+        assert!(tree.nodes().all(|n| n.role != Role::Generic));
+        let root = tree.get(tree.root()).unwrap();
+        let paragraph = tree.get(root.children[0]).unwrap();
+        assert_eq!(paragraph.role, Role::Paragraph);
+        assert_eq!(tree.get(paragraph.children[0]).unwrap().name, "Hello world");
+    }
+
+    #[test]
+    fn test_screen_reader_output() {
+        let tree =
+            build("<html><body><h1>Welcome</h1><ul><li>One</li><li>Two</li></ul></body></html>");
+        let output = tree.to_screen_reader_text();
+        assert!(output.contains("heading level 1: Welcome"));
+        assert!(output.contains("list item: One"));
+        assert!(output.contains("list item: Two"));
+    }
+
+    #[test]
+    fn test_bounds_are_absolute() {
+        let dom = parse_html(
+            "<html><body><p>Text</p></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+        let mut node_layouts = HashMap::new();
+        let body_id = dom.get_body().unwrap().read().unwrap().id();
+        let p_id = dom.get_elements_by_tag_name("p")[0].read().unwrap().id();
+        node_layouts.insert(body_id, LayoutRect::new(8.0, 8.0, 100.0, 50.0));
+        node_layouts.insert(p_id, LayoutRect::new(0.0, 16.0, 100.0, 20.0));
+        let layout = LayoutResult {
+            node_layouts,
+            document_size: LayoutSize::new(800.0, 600.0),
+            metrics: Default::default(),
+            cache_key: 0,
+            dirty_regions: Vec::new(),
+        };
+
+        let tree = AccessibilityTree::build(&dom, Some(&layout));
+        let bounds = tree.get(p_id).unwrap().bounds.clone().unwrap();
+        assert_eq!((bounds.x, bounds.y), (8.0, 24.0));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::SecurityLevel;
+use crate::error::{ParserError, ParserResult};
+use crate::{ParseMode, SecurityLevel};
 use html5ever::tokenizer::TokenizerOpts;
 use html5ever::tree_builder::TreeBuilderOpts;
 
@@ -7,10 +8,18 @@ use html5ever::tree_builder::TreeBuilderOpts;
 pub struct ParserConfig {
     /// Security level
     pub security_level: SecurityLevel,
+    /// Whether DOM limits truncate the document or fail the parse
+    pub parse_mode: ParseMode,
     /// Maximum depth for nested elements
     pub max_depth: usize,
-    /// Maximum length for attribute values
+    /// Maximum length for attribute values, in bytes
     pub max_attr_length: usize,
+    /// Maximum number of tokens (elements, text runs, comments) per document
+    pub max_tokens: usize,
+    /// Maximum size of a document, in bytes, checked before parsing
+    pub max_document_bytes: usize,
+    /// Maximum number of elements built into the DOM
+    pub max_elements: usize,
     /// Whether to allow comments
     pub allow_comments: bool,
     /// Whether to allow processing instructions
@@ -27,21 +36,71 @@ pub struct ParserConfig {
 
 impl Default for ParserConfig {
     fn default() -> Self {
+        Self::for_level(SecurityLevel::default())
+    }
+}
+
+impl ParserConfig {
+    /// The limits each security level parses with. Stricter levels take
+    /// shallower trees, shorter attribute values, fewer tokens, smaller
+    /// documents, less CSS and drop comments, which only ever carry content
+    /// the page did not mean to show. `Custom` starts from the balanced
+    /// limits; the browser applies the user's overrides on top.
+    pub fn for_level(security_level: SecurityLevel) -> Self {
+        let (max_depth, max_attr_length, allow_comments, max_nesting_depth, max_css_size) =
+            match security_level {
+                SecurityLevel::Maximum => (64, 512, false, 16, 256 * 1024),
+                SecurityLevel::High => (80, 1024, false, 24, 512 * 1024),
+                SecurityLevel::Balanced | SecurityLevel::Custom => {
+                    (100, 1024, true, 32, 1024 * 1024)
+                }
+            };
+        let (max_tokens, max_document_bytes, max_elements) = match security_level {
+            SecurityLevel::Maximum => (100_000, 2 * 1024 * 1024, 25_000),
+            SecurityLevel::High => (250_000, 5 * 1024 * 1024, 50_000),
+            SecurityLevel::Balanced | SecurityLevel::Custom => (500_000, 10 * 1024 * 1024, 100_000),
+        };
         Self {
-            security_level: SecurityLevel::default(),
-            max_depth: 100,
-            max_attr_length: 1024,
-            allow_comments: true,
+            security_level,
+            parse_mode: ParseMode::default(),
+            max_depth,
+            max_attr_length,
+            max_tokens,
+            max_document_bytes,
+            max_elements,
+            allow_comments,
             allow_processing_instructions: false,
             allow_scripts: false,
             allow_external_resources: false,
-            max_nesting_depth: 32,     // Reasonable default for nesting depth
-            max_css_size: 1024 * 1024, // 1MB default CSS size limit
+            max_nesting_depth,
+            max_css_size,
         }
     }
-}
 
-impl ParserConfig {
+    /// Fail once more than `max_tokens` tokens have been seen
+    pub fn check_token_count(&self, tokens: usize) -> ParserResult<()> {
+        if tokens > self.max_tokens {
+            return Err(ParserError::TooManyTokens(self.max_tokens));
+        }
+        Ok(())
+    }
+
+    /// Fail for an attribute value longer than `max_attr_length`
+    pub fn check_attribute_length(&self, value: &str) -> ParserResult<()> {
+        if value.len() > self.max_attr_length {
+            return Err(ParserError::AttributeTooLong(value.len()));
+        }
+        Ok(())
+    }
+
+    /// Fail for a document larger than `max_document_bytes`
+    pub fn check_document_size(&self, document: &str) -> ParserResult<()> {
+        if document.len() > self.max_document_bytes {
+            return Err(ParserError::DocumentTooLarge(document.len()));
+        }
+        Ok(())
+    }
+
     /// Create tree builder options based on configuration
     pub fn tree_builder_opts(&self) -> TreeBuilderOpts {
         TreeBuilderOpts {
@@ -154,6 +213,61 @@ mod tests {
         assert_eq!(config.max_nesting_depth(), 32);
     }
 
+    #[test]
+    fn test_profiles_tighten_with_level() {
+        let levels = [
+            SecurityLevel::Balanced,
+            SecurityLevel::High,
+            SecurityLevel::Maximum,
+        ];
+        for pair in levels.windows(2) {
+            let (looser, stricter) = (
+                ParserConfig::for_level(pair[0]),
+                ParserConfig::for_level(pair[1]),
+            );
+            assert_eq!(stricter.security_level, pair[1]);
+            assert!(stricter.max_depth <= looser.max_depth);
+            assert!(stricter.max_attr_length <= looser.max_attr_length);
+            assert!(stricter.max_tokens < looser.max_tokens);
+            assert!(stricter.max_document_bytes < looser.max_document_bytes);
+            assert!(stricter.max_elements < looser.max_elements);
+            assert!(stricter.max_nesting_depth < looser.max_nesting_depth);
+            assert!(stricter.max_css_size < looser.max_css_size);
+            assert!(!stricter.allow_comments);
+        }
+
+        let custom = ParserConfig::for_level(SecurityLevel::Custom);
+        assert_eq!(custom.security_level, SecurityLevel::Custom);
+        assert_eq!(custom.max_depth, ParserConfig::default().max_depth);
+    }
+
+    #[test]
+    fn test_limits_fail_with_their_own_errors() {
+        let config = ParserConfig::default();
+        assert!(config.check_token_count(config.max_tokens).is_ok());
+        assert!(matches!(
+            config.check_token_count(config.max_tokens + 1),
+            Err(ParserError::TooManyTokens(limit)) if limit == config.max_tokens
+        ));
+
+        let value = "x".repeat(config.max_attr_length + 1);
+        assert!(config.check_attribute_length(&value[1..]).is_ok());
+        assert!(matches!(
+            config.check_attribute_length(&value),
+            Err(ParserError::AttributeTooLong(length)) if length == value.len()
+        ));
+
+        let small = ParserConfig {
+            max_document_bytes: 8,
+            ..ParserConfig::default()
+        };
+        assert!(small.check_document_size("<p>a</p>").is_ok());
+        assert!(matches!(
+            small.check_document_size("<p>ab</p>"),
+            Err(ParserError::DocumentTooLarge(9))
+        ));
+    }
+
     #[test]
     fn test_element_security_levels() {
         let mut config = ParserConfig::default();
@@ -1,26 +1,217 @@
 use std::sync::Arc;
 
-use cssparser::{Parser as CssParserImpl, ToCss, Token};
+use cssparser::{Delimiter, Parser as CssParserImpl, ToCss, Token};
 use taffy::{AlignItems, Display, FlexDirection, JustifyContent, Style};
 
+use crate::css_diagnostics::StylesheetDiagnostics;
 use crate::error::{ParserError, ParserResult};
 use crate::metrics::ParserMetrics;
 use crate::security::SecurityContext;
+use crate::selector::{SelectorElement, SelectorList};
 use crate::{Parser, ParserConfig};
 
 /// Enhanced CSS stylesheet with Servo integration
 #[derive(Debug, Clone)]
 pub struct CitadelStylesheet {
     pub rules: Vec<StyleRule>,
+    /// Web fonts offered by `@font-face` rules, in source order
+    pub font_faces: Vec<FontFaceRule>,
     pub security_context: Arc<SecurityContext>,
+    /// Recoverable errors in the source the rules were parsed from
+    pub diagnostics: StylesheetDiagnostics,
+}
+
+/// Dynamic element state consulted by state pseudo-classes such as `:focus`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ElementState {
+    /// Element has keyboard focus
+    pub focused: bool,
+}
+
+/// An element as the cascade sees it: a tag, classes and an id, outside any
+/// tree
+struct LoneElement<'a> {
+    tag: String,
+    class: String,
+    id: Option<&'a str>,
+}
+
+impl SelectorElement for LoneElement<'_> {
+    fn local_name(&self) -> &str {
+        &self.tag
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        match name {
+            "class" => Some(&self.class),
+            "id" => self.id,
+            _ => None,
+        }
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn has_content(&self) -> bool {
+        false
+    }
 }
 
 /// CSS rule with enhanced capabilities
 #[derive(Debug, Clone)]
 pub struct StyleRule {
+    /// One complex selector; the parser splits selector lists into a rule
+    /// per selector
     pub selectors: String,
     pub declarations: Vec<Declaration>,
+    /// Packed (ids, classes, types) specificity of `selectors`
     pub specificity: u32,
+    /// Who wrote the rule; user rules cascade after author rules
+    pub origin: CascadeOrigin,
+}
+
+/// Origin of a style rule in the cascade
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CascadeOrigin {
+    /// The page's own stylesheets
+    #[default]
+    Author,
+    /// The user's per-site stylesheet; overrides the page regardless of
+    /// specificity
+    User,
+}
+
+/// An `@font-face` rule: a font the page offers under a family name
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFaceRule {
+    /// Family name `font-family` declarations use for the font
+    pub family: String,
+    /// Where to get the font from, in order of preference. `local()`
+    /// sources are left out: installed fonts are not offered to pages.
+    pub sources: Vec<FontSource>,
+    /// Weight of the face, 400 unless the rule says otherwise
+    pub weight: u16,
+    /// Whether the face is italic or oblique
+    pub italic: bool,
+}
+
+/// One `url()` of an `@font-face` rule's `src`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSource {
+    /// As written, relative to the stylesheet
+    pub url: String,
+    /// The `format()` hint, lowercased, if there was one
+    pub format: Option<String>,
+}
+
+impl FontFaceRule {
+    /// The rule a `@font-face` block's declarations describe; `None`
+    /// without a family or a source
+    fn from_declarations(declarations: &[Declaration]) -> Option<Self> {
+        let value = |name: &str| {
+            declarations
+                .iter()
+                .rev()
+                .find(|declaration| declaration.property.eq_ignore_ascii_case(name))
+                .map(|declaration| declaration.value.as_str())
+        };
+        let family = unquote(value("font-family")?);
+        let sources = parse_font_sources(value("src")?);
+        if family.is_empty() || sources.is_empty() {
+            return None;
+        }
+        Some(Self {
+            family: family.to_string(),
+            sources,
+            // A weight range counts as its lower end
+            weight: value("font-weight")
+                .and_then(|weight| weight.split_whitespace().next())
+                .and_then(font_weight_value)
+                .unwrap_or(400),
+            italic: value("font-style").is_some_and(|style| {
+                let style = style.to_ascii_lowercase();
+                style.starts_with("italic") || style.starts_with("oblique")
+            }),
+        })
+    }
+}
+
+/// Numeric weight of a `font-weight` value: a number from 1 to 1000 or a
+/// keyword. Relative keywords count from normal.
+pub fn font_weight_value(value: &str) -> Option<u16> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "normal" => Some(400),
+        "bold" | "bolder" => Some(700),
+        "lighter" => Some(300),
+        number => number
+            .parse::<f32>()
+            .ok()
+            .filter(|weight| (1.0..=1000.0).contains(weight))
+            .map(|weight| weight.round() as u16),
+    }
+}
+
+/// The family names of a `font-family` list, unquoted, in order
+pub fn font_family_names(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(unquote)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+        .trim()
+}
+
+/// The `url()` sources of an `@font-face` `src` list. Sources that do not
+/// parse are skipped rather than failing the list.
+fn parse_font_sources(value: &str) -> Vec<FontSource> {
+    let mut input = cssparser::ParserInput::new(value);
+    let mut parser = CssParserImpl::new(&mut input);
+    let mut sources = Vec::new();
+    while !parser.is_exhausted() {
+        let source = parser.parse_until_after(Delimiter::Comma, |source| {
+            let url = source.expect_url()?.to_string();
+            let format = source
+                .try_parse(|hint| {
+                    hint.expect_function_matching("format")?;
+                    hint.parse_nested_block(|hint| {
+                        Ok::<_, cssparser::ParseError<()>>(
+                            hint.expect_ident_or_string()?.to_ascii_lowercase(),
+                        )
+                    })
+                })
+                .ok();
+            // tech() and anything newer is not consulted
+            while source.next().is_ok() {}
+            Ok::<_, cssparser::ParseError<()>>(FontSource { url, format })
+        });
+        if let Ok(source) = source {
+            sources.push(source);
+        }
+    }
+    sources
 }
 
 /// CSS declaration
@@ -147,6 +338,8 @@ pub struct ComputedStyle {
     pub background_color: Option<ColorValue>,
     pub font_size: Option<LengthValue>,
     pub font_weight: Option<String>,
+    /// The `font-family` list as written
+    pub font_family: Option<String>,
     pub border_width: Option<LengthValue>,
     pub border_color: Option<ColorValue>,
     pub border_radius: Option<LengthValue>,
@@ -280,6 +473,7 @@ impl Default for ComputedStyle {
             background_color: None,
             font_size: None,
             font_weight: None,
+            font_family: None,
             border_width: None,
             border_color: None,
             border_radius: None,
@@ -426,307 +620,172 @@ impl CitadelCssParser {
             ));
         }
 
-        // Use cssparser for more robust parsing
         let mut input = cssparser::ParserInput::new(content);
         let mut parser = CssParserImpl::new(&mut input);
+        let (rules, font_faces) = self.parse_rules(&mut parser);
 
-        let mut rules = Vec::new();
-
-        while !parser.is_exhausted() {
-            // Skip whitespace and comments
-            parser.skip_whitespace();
-
-            if parser.is_exhausted() {
-                break;
-            }
-
-            match self.parse_rule(&mut parser) {
-                Ok(rule) => {
-                    rules.push(rule);
-                }
-                Err(_e) => {
-                    // Try to skip to next rule on error
-                    self.skip_to_next_rule(&mut parser);
-                }
-            }
-        }
-
-        // If the cssparser-based approach returned no rules but input is non-empty,
-        // fall back to the simple string-splitting parser which handles basic CSS reliably.
-        if rules.is_empty() && !content.trim().is_empty() {
-            rules = self.parse_css_simple(content).unwrap_or_default();
+        let diagnostics = StylesheetDiagnostics::analyze(content);
+        if !diagnostics.is_empty() {
+            tracing::debug!("{} recoverable CSS errors", diagnostics.len());
         }
 
         Ok(CitadelStylesheet {
             rules,
+            font_faces,
             security_context: self.security_context.clone(),
+            diagnostics,
         })
     }
 
-    /// Simple CSS parser for basic rules — fallback when cssparser returns empty
-    fn parse_css_simple(&self, content: &str) -> ParserResult<Vec<StyleRule>> {
+    /// Parse the top level of a stylesheet. Each selector of a rule's
+    /// selector list becomes a rule of its own, with its own specificity.
+    /// `@font-face` rules are collected; other at-rules are skipped, as
+    /// there is no media evaluation to hand their contents to.
+    fn parse_rules(&self, parser: &mut CssParserImpl) -> (Vec<StyleRule>, Vec<FontFaceRule>) {
         let mut rules = Vec::new();
+        let mut font_faces = Vec::new();
 
-        // Split by closing braces to get rules
-        let rule_parts: Vec<&str> = content.split('}').collect();
-
-        for rule_part in rule_parts {
-            let rule_part = rule_part.trim();
-            if rule_part.is_empty() {
-                continue;
-            }
-
-            // Find the opening brace
-            if let Some(brace_pos) = rule_part.find('{') {
-                let selector = rule_part[..brace_pos].trim().to_string();
-                let declarations_str = rule_part[brace_pos + 1..].trim();
-
-                if selector.is_empty() {
+        loop {
+            let start = parser.state();
+            match parser.next() {
+                Err(_) => break,
+                Ok(Token::CDO | Token::CDC) => continue,
+                Ok(Token::AtKeyword(name)) => {
+                    let font_face = name.eq_ignore_ascii_case("font-face");
+                    // Up to the end of its statement or block
+                    while let Ok(token) = parser.next() {
+                        match token {
+                            Token::Semicolon => break,
+                            Token::CurlyBracketBlock => {
+                                if font_face {
+                                    let declarations = parser
+                                        .parse_nested_block(|block| {
+                                            Ok::<_, cssparser::ParseError<()>>(
+                                                self.parse_declaration_block(block),
+                                            )
+                                        })
+                                        .unwrap_or_default();
+                                    font_faces
+                                        .extend(FontFaceRule::from_declarations(&declarations));
+                                }
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
                     continue;
                 }
-
-                // Security validation
-                if self.is_dangerous_selector(&selector) {
-                    return Err(ParserError::SecurityViolation(format!(
-                        "Dangerous selector detected: {}",
-                        selector
-                    )));
-                }
-
-                // Parse declarations
-                let declarations = self.parse_declarations_simple(declarations_str)?;
-
-                let rule = StyleRule {
-                    selectors: selector.clone(),
-                    declarations,
-                    specificity: self.calculate_specificity(&selector),
-                };
-
-                rules.push(rule);
-                self.metrics.increment_elements();
+                Ok(_) => parser.reset(&start),
+            }
+
+            let prelude = parser.parse_until_before(Delimiter::CurlyBracketBlock, |prelude| {
+                prelude.parse_comma_separated(|selector| {
+                    let mut text = String::new();
+                    serialize_tokens(selector, &mut text)?;
+                    Ok::<_, cssparser::ParseError<()>>(text)
+                })
+            });
+            if !matches!(parser.next(), Ok(Token::CurlyBracketBlock)) {
+                // A prelude running to the end of the input has no rule
+                break;
             }
-        }
-
-        Ok(rules)
-    }
-
-    /// Simple declaration parser
-    #[allow(dead_code)] // Will be used when implementing fallback CSS parsing
-    fn parse_declarations_simple(&self, declarations_str: &str) -> ParserResult<Vec<Declaration>> {
-        let mut declarations = Vec::new();
+            let declarations = parser
+                .parse_nested_block(|block| {
+                    Ok::<_, cssparser::ParseError<()>>(self.parse_declaration_block(block))
+                })
+                .unwrap_or_default();
 
-        // Split by semicolons
-        for decl_str in declarations_str.split(';') {
-            let decl_str = decl_str.trim();
-            if decl_str.is_empty() {
+            // One bad selector invalidates the whole list
+            let Ok(selectors) = prelude else {
+                continue;
+            };
+            if selectors
+                .iter()
+                .any(|selector| selector.is_empty() || self.is_dangerous_selector(selector))
+            {
+                self.metrics.increment_violations();
+                tracing::warn!("Dropping CSS rule with selectors {:?}", selectors);
                 continue;
             }
 
-            // Find the colon
-            if let Some(colon_pos) = decl_str.find(':') {
-                let property = decl_str[..colon_pos].trim().to_string();
-                let value_part = decl_str[colon_pos + 1..].trim();
-
-                // Check for !important
-                let (value, important) = if value_part.ends_with("!important") {
-                    (value_part[..value_part.len() - 10].trim().to_string(), true)
-                } else {
-                    (value_part.to_string(), false)
-                };
-
-                // Security validation
-                if self.is_dangerous_property_value(&property, &value) {
-                    self.metrics.increment_sanitizations();
-                    tracing::warn!("Blocking dangerous CSS property: {} = {}", property, value);
-                    continue;
-                }
-
-                declarations.push(Declaration {
-                    property,
-                    value,
-                    important,
+            for selector in selectors {
+                rules.push(StyleRule {
+                    specificity: self.calculate_specificity(&selector),
+                    selectors: selector,
+                    declarations: declarations.clone(),
+                    origin: CascadeOrigin::Author,
                 });
-
-                self.metrics.increment_attributes();
-            }
-        }
-
-        Ok(declarations)
-    }
-
-    /// Parse a single CSS rule
-    fn parse_rule(&self, parser: &mut CssParserImpl) -> ParserResult<StyleRule> {
-        // Parse selectors - collect all tokens until we find a curly bracket
-        let mut selector_tokens = Vec::new();
-
-        while !parser.is_exhausted() {
-            match parser.next() {
-                Ok(Token::CurlyBracketBlock) => {
-                    // Found the opening brace - now parse the declarations
-                    let selectors = selector_tokens
-                        .iter()
-                        .map(|t: &Token| t.to_css_string())
-                        .collect::<Vec<_>>()
-                        .join("")
-                        .trim()
-                        .to_string();
-
-                    // Security validation
-                    if self.is_dangerous_selector(&selectors) {
-                        return Err(ParserError::SecurityViolation(format!(
-                            "Dangerous selector detected: {}",
-                            selectors
-                        )));
-                    }
-
-                    // Extract declaration text from the block and parse with the reliable simple parser.
-                    // The cssparser API's parse_nested_block + expect_ident approach fails to
-                    // find properties in many valid CSS blocks.
-                    let declarations = parser
-                        .parse_nested_block(|block_parser| {
-                            // Collect all tokens as a CSS string
-                            let mut decl_text = String::new();
-                            while !block_parser.is_exhausted() {
-                                match block_parser.next() {
-                                    Ok(token) => {
-                                        decl_text.push_str(&token.to_css_string());
-                                    }
-                                    Err(_) => break,
-                                }
-                            }
-                            Ok(self
-                                .parse_declarations_simple(&decl_text)
-                                .unwrap_or_default())
-                        })
-                        .map_err(|e: cssparser::ParseError<()>| {
-                            ParserError::CssError(format!("Error parsing declarations: {:?}", e))
-                        })?;
-
-                    let specificity = self.calculate_specificity(&selectors);
-                    return Ok(StyleRule {
-                        selectors,
-                        declarations,
-                        specificity,
-                    });
-                }
-                Ok(token) => {
-                    selector_tokens.push(token.clone());
-                }
-                Err(e) => {
-                    return Err(ParserError::CssError(format!(
-                        "Error parsing selector: {:?}",
-                        e
-                    )));
-                }
+                self.metrics.increment_elements();
             }
         }
 
-        Err(ParserError::CssError(
-            "Unexpected end of input while parsing rule".to_string(),
-        ))
+        (rules, font_faces)
     }
 
-    /// Parse CSS declarations
-    fn parse_declarations(&self, parser: &mut CssParserImpl) -> ParserResult<Vec<Declaration>> {
+    /// Parse the declarations of a rule's block, dropping malformed ones and
+    /// ones that could run script or load behaviour
+    fn parse_declaration_block(&self, parser: &mut CssParserImpl) -> Vec<Declaration> {
         let mut declarations = Vec::new();
 
         while !parser.is_exhausted() {
-            // Skip whitespace
-            if parser.expect_whitespace().is_ok() {
-                continue;
-            }
-
-            if let Ok(property) = parser.expect_ident() {
-                let property = property.to_string();
-
-                // Expect colon
-                if parser.expect_colon().is_err() {
-                    continue;
-                }
-
-                // Parse value
-                let mut value = String::new();
-                let mut important = false;
-
-                while !parser.is_exhausted() {
-                    match parser.next() {
-                        Ok(Token::Semicolon) => break,
-                        Ok(Token::Delim('!')) => {
-                            if let Ok(ident) = parser.expect_ident() {
-                                if ident.eq_ignore_ascii_case("important") {
-                                    important = true;
-                                }
-                            }
-                        }
-                        Ok(token) => {
-                            if !value.is_empty() {
-                                value.push(' ');
-                            }
-                            value.push_str(&token.to_css_string());
-                        }
-                        Err(_) => break,
-                    }
-                }
-
-                // Security validation
-                if self.is_dangerous_property_value(&property, &value) {
+            match parser.parse_until_after(Delimiter::Semicolon, |declaration| {
+                Self::parse_declaration(declaration)
+            }) {
+                Ok(declaration)
+                    if self
+                        .is_dangerous_property_value(&declaration.property, &declaration.value) =>
+                {
                     self.metrics.increment_sanitizations();
-                    tracing::warn!("Blocking dangerous CSS property: {} = {}", property, value);
-                    continue;
+                    tracing::warn!(
+                        "Blocking dangerous CSS property: {} = {}",
+                        declaration.property,
+                        declaration.value
+                    );
                 }
-
-                declarations.push(Declaration {
-                    property,
-                    value: value.trim().to_string(),
-                    important,
-                });
-
-                self.metrics.increment_attributes();
-            } else {
-                // Skip to next declaration
-                while !parser.is_exhausted() {
-                    if matches!(parser.next(), Ok(Token::Semicolon)) {
-                        break;
-                    }
+                Ok(declaration) => {
+                    declarations.push(declaration);
+                    self.metrics.increment_attributes();
                 }
+                // Malformed; the diagnostics pass reports it
+                Err(_) => {}
             }
         }
 
-        Ok(declarations)
+        declarations
     }
 
-    /// Skip to the next CSS rule after an error
-    fn skip_to_next_rule(&self, parser: &mut CssParserImpl) {
-        let mut brace_count = 0;
-
-        while !parser.is_exhausted() {
-            match parser.next() {
-                Ok(Token::CurlyBracketBlock) => {
-                    brace_count += 1;
-                }
-                Ok(_) if brace_count == 0 => continue,
-                _ => {
-                    if brace_count > 0 {
-                        brace_count -= 1;
-                        if brace_count == 0 {
-                            break;
-                        }
-                    }
-                }
-            }
+    /// Parse one `property: value [!important]` declaration. Names and values
+    /// come out with escapes resolved, so `beh\61vior` is checked as the
+    /// `behavior` it is.
+    fn parse_declaration<'i>(
+        parser: &mut CssParserImpl<'i, '_>,
+    ) -> Result<Declaration, cssparser::ParseError<'i, ()>> {
+        let property = parser.expect_ident()?.to_string();
+        parser.expect_colon()?;
+
+        let mut value = String::new();
+        serialize_tokens(parser, &mut value)?;
+        let (value, important) = match strip_important(&value) {
+            Some(value) => (value.to_string(), true),
+            None => (value, false),
+        };
+        if value.is_empty() {
+            return Err(parser.new_custom_error(()));
         }
+
+        Ok(Declaration {
+            property,
+            value,
+            important,
+        })
     }
 
-    /// Calculate specificity for CSS selectors
+    /// Specificity of one complex selector, packed as `ids << 16 |
+    /// classes << 8 | types` so that comparing the numbers compares the
+    /// (a, b, c) triples. Each count saturates at 255.
     fn calculate_specificity(&self, selector: &str) -> u32 {
-        let mut specificity = 0;
-
-        // Simple specificity calculation
-        specificity += selector.matches('#').count() as u32 * 100; // IDs
-        specificity += selector.matches('.').count() as u32 * 10; // Classes
-        specificity += selector.matches(|c: char| c.is_alphabetic()).count() as u32; // Elements
-
-        specificity
+        let (ids, classes, types) = selector_specificity(selector);
+        (ids.min(255) << 16) | (classes.min(255) << 8) | types.min(255)
     }
 
     /// Check for dangerous CSS patterns
@@ -795,7 +854,9 @@ impl CitadelStylesheet {
     pub fn new(security_context: Arc<SecurityContext>) -> Self {
         Self {
             rules: Vec::new(),
+            font_faces: Vec::new(),
             security_context,
+            diagnostics: StylesheetDiagnostics::default(),
         }
     }
 
@@ -809,27 +870,59 @@ impl CitadelStylesheet {
         self.rules.push(rule);
     }
 
+    /// Parse user CSS and append it with user-origin priority
+    pub fn add_user_css(&mut self, css: &str) -> ParserResult<()> {
+        let user = crate::parse_css(css, self.security_context.clone())?;
+        self.font_faces.extend(user.font_faces);
+        self.rules
+            .extend(user.rules.into_iter().map(|rule| StyleRule {
+                origin: CascadeOrigin::User,
+                ..rule
+            }));
+        Ok(())
+    }
+
     /// Compute styles for an element using Taffy layout engine
     pub fn compute_styles(
         &self,
         element_tag: &str,
         element_classes: &[String],
         element_id: Option<&str>,
+    ) -> ComputedStyle {
+        self.compute_styles_with_state(
+            element_tag,
+            element_classes,
+            element_id,
+            ElementState::default(),
+        )
+    }
+
+    /// Compute styles for an element in a given interaction state (`:focus` rules
+    /// only apply when the element is focused)
+    pub fn compute_styles_with_state(
+        &self,
+        element_tag: &str,
+        element_classes: &[String],
+        element_id: Option<&str>,
+        state: ElementState,
     ) -> ComputedStyle {
         let mut computed = ComputedStyle::default();
         let mut matched_rules = Vec::new();
 
         // Find matching rules
         for rule in &self.rules {
-            if self.selector_matches(&rule.selectors, element_tag, element_classes, element_id) {
-                matched_rules.push((rule, rule.specificity));
+            let Some(selector) = Self::strip_state_pseudo(&rule.selectors, state) else {
+                continue;
+            };
+            if self.selector_matches(selector, element_tag, element_classes, element_id) {
+                matched_rules.push((rule, (rule.origin, rule.specificity)));
             }
         }
 
-        // Sort by specificity
-        matched_rules.sort_by_key(|(_, specificity)| *specificity);
+        // Sort by origin, then specificity
+        matched_rules.sort_by_key(|(_, priority)| *priority);
 
-        // Apply declarations in specificity order
+        // Apply declarations in cascade order
         for (rule, _) in matched_rules {
             for declaration in &rule.declarations {
                 self.apply_declaration(&mut computed, declaration);
@@ -839,7 +932,24 @@ impl CitadelStylesheet {
         computed
     }
 
-    /// Check if a selector matches an element
+    /// Strip a trailing focus pseudo-class from a selector. Returns `None` when
+    /// the element state does not satisfy it.
+    fn strip_state_pseudo(selector: &str, state: ElementState) -> Option<&str> {
+        let selector = selector.trim();
+        for pseudo in [":focus-visible", ":focus-within", ":focus"] {
+            if let Some(base) = selector.strip_suffix(pseudo) {
+                if !state.focused {
+                    return None;
+                }
+                return Some(if base.is_empty() { "*" } else { base });
+            }
+        }
+        Some(selector)
+    }
+
+    /// Check if a selector matches an element. The cascade only knows the
+    /// element itself, so selectors that look at its ancestors, siblings or
+    /// children never match here.
     fn selector_matches(
         &self,
         selector: &str,
@@ -847,43 +957,18 @@ impl CitadelStylesheet {
         classes: &[String],
         id: Option<&str>,
     ) -> bool {
-        let selector = selector.trim();
-
-        // Universal selector
-        if selector == "*" {
-            return true;
-        }
-
-        // Tag selector
-        if selector == tag {
-            return true;
-        }
-
-        // ID selector
-        if let Some(element_id) = id {
-            if selector.starts_with('#') && selector[1..] == *element_id {
-                return true;
-            }
-        }
-
-        // Class selector
-        if selector.starts_with('.') {
-            let class_name = &selector[1..];
-            if classes.contains(&class_name.to_string()) {
-                return true;
-            }
-        }
-
-        // Compound selectors (simplified)
-        if selector.contains('.') && !selector.starts_with('.') {
-            let parts: Vec<&str> = selector.split('.').collect();
-            if parts.len() == 2 && parts[0] == tag {
-                let class_name = parts[1];
-                return classes.contains(&class_name.to_string());
-            }
-        }
-
-        false
+        let Ok(selectors) = SelectorList::parse(selector.trim()) else {
+            return false;
+        };
+        let element = LoneElement {
+            tag: tag.to_ascii_lowercase(),
+            class: classes.join(" "),
+            id,
+        };
+        selectors
+            .0
+            .iter()
+            .any(|selector| !selector.needs_tree() && selector.matches(&element))
     }
 
     /// Apply a CSS declaration to computed styles with advanced property support
@@ -917,6 +1002,9 @@ impl CitadelStylesheet {
             "font-weight" => {
                 computed.font_weight = Some(declaration.value.clone());
             }
+            "font-family" => {
+                computed.font_family = Some(declaration.value.clone());
+            }
             "border-width" => {
                 computed.border_width = self.parse_length_value(&declaration.value);
             }
@@ -1683,6 +1771,230 @@ impl Parser for CitadelCssParser {
     }
 }
 
+/// Append the tokens left in `parser` to `out` as CSS: comments dropped,
+/// whitespace collapsed, escapes resolved and nested blocks included
+fn serialize_tokens<'i>(
+    parser: &mut CssParserImpl<'i, '_>,
+    out: &mut String,
+) -> Result<(), cssparser::ParseError<'i, ()>> {
+    while let Ok(token) = parser.next_including_whitespace() {
+        let close = match token {
+            Token::WhiteSpace(_) => {
+                if !out.is_empty() && !out.ends_with([' ', '(', '[']) {
+                    out.push(' ');
+                }
+                continue;
+            }
+            Token::BadUrl(_) | Token::BadString(_) => return Err(parser.new_custom_error(())),
+            Token::Function(_) | Token::ParenthesisBlock => Some(')'),
+            Token::SquareBracketBlock => Some(']'),
+            Token::CurlyBracketBlock => Some('}'),
+            _ => None,
+        };
+        out.push_str(&token.to_css_string());
+        if let Some(close) = close {
+            parser.parse_nested_block(|nested| serialize_tokens(nested, out))?;
+            out.push(close);
+        }
+    }
+    if out.ends_with(' ') {
+        out.pop();
+    }
+    Ok(())
+}
+
+/// The value before a trailing `!important`, if it has one
+fn strip_important(value: &str) -> Option<&str> {
+    let cut = value.len().checked_sub("important".len())?;
+    if !value.is_char_boundary(cut) || !value[cut..].eq_ignore_ascii_case("important") {
+        return None;
+    }
+    value[..cut].trim_end().strip_suffix('!').map(str::trim_end)
+}
+
+/// The (ids, classes, types) counts of one complex selector.
+/// Attribute selectors and pseudo-classes count as classes, pseudo-elements
+/// as types; `:is()`, `:not()` and `:has()` count their most specific
+/// argument and `:where()` counts nothing.
+fn selector_specificity(selector: &str) -> (u32, u32, u32) {
+    let chars: Vec<char> = selector.chars().collect();
+    let (mut ids, mut classes, mut types) = (0, 0, 0);
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '#' => {
+                ids += 1;
+                i = name_end(&chars, i + 1);
+            }
+            '.' => {
+                classes += 1;
+                i = name_end(&chars, i + 1);
+            }
+            '[' => {
+                classes += 1;
+                i = block_end(&chars, i) + 1;
+            }
+            ':' => {
+                let element = chars.get(i + 1) == Some(&':');
+                let start = i + 1 + usize::from(element);
+                let end = name_end(&chars, start);
+                let name = chars[start..end]
+                    .iter()
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                i = end;
+                if chars.get(end) == Some(&'(') {
+                    let close = block_end(&chars, end);
+                    let argument: String = chars[end + 1..close].iter().collect();
+                    i = close + 1;
+                    match name.as_str() {
+                        "is" | "not" | "has" | "matches" => {
+                            let (a, b, c) = split_top_level(&argument)
+                                .into_iter()
+                                .map(selector_specificity)
+                                .max()
+                                .unwrap_or_default();
+                            ids += a;
+                            classes += b;
+                            types += c;
+                            continue;
+                        }
+                        "where" => continue,
+                        _ => {}
+                    }
+                }
+                let legacy_element = matches!(
+                    name.as_str(),
+                    "before" | "after" | "first-line" | "first-letter"
+                );
+                if element || legacy_element {
+                    types += 1;
+                } else {
+                    classes += 1;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '\\' || !c.is_ascii() => {
+                types += 1;
+                i = name_end(&chars, i);
+            }
+            // `*`, combinators and whitespace
+            _ => i += 1,
+        }
+    }
+
+    (ids, classes, types)
+}
+
+/// Index just past the identifier starting at `i`
+fn name_end(chars: &[char], mut i: usize) -> usize {
+    while let Some(&c) = chars.get(i) {
+        if c == '\\' {
+            i += 2;
+        } else if c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii() {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i.min(chars.len())
+}
+
+/// Index of the bracket closing the one at `open`, or the end of `chars`
+fn block_end(chars: &[char], open: usize) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut i = open;
+    while i < chars.len() {
+        let c = chars[i];
+        match (quote, c) {
+            (_, '\\') => i += 1,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// `list` split at its top-level commas
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut escaped, mut start) = (0, None, false, 0);
+    for (i, c) in list.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (_, '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&list[start..]);
+    parts
+}
+
+/// `css` with every `url()` reference resolved against `base`. Empty and
+/// fragment-only references, and ones that do not resolve, are left as
+/// written.
+pub fn resolve_css_urls(css: &str, base: &url::Url) -> String {
+    // ASCII lowercasing keeps byte offsets, so matches index `css` directly
+    let lower = css.to_ascii_lowercase();
+    let mut resolved = String::with_capacity(css.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some(found) = lower[search..].find("url(") {
+        let open = search + found + "url(".len();
+        let Some(length) = css[open..].find(')') else {
+            break;
+        };
+        let close = open + length;
+        let inner = css[open..close].trim();
+        let (quote, reference) = match inner.chars().next() {
+            Some(quote @ ('"' | '\'')) if inner.len() >= 2 && inner.ends_with(quote) => {
+                (Some(quote), inner[1..inner.len() - 1].trim())
+            }
+            _ => (None, inner),
+        };
+        if !reference.is_empty() && !reference.starts_with('#') {
+            if let Ok(url) = base.join(reference) {
+                resolved.push_str(&css[copied..open]);
+                match quote {
+                    Some(quote) => {
+                        resolved.push(quote);
+                        resolved.push_str(url.as_str());
+                        resolved.push(quote);
+                    }
+                    None => resolved.push_str(url.as_str()),
+                }
+                copied = close;
+            }
+        }
+        search = close;
+    }
+    resolved.push_str(&css[copied..]);
+    resolved
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1773,6 +2085,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resolve_css_urls_against_base() {
+        let base = url::Url::parse("https://cdn.example/css/").unwrap();
+        assert_eq!(
+            resolve_css_urls(
+                "background: URL( 'img/a.png' ) no-repeat; mask: url(#m); cursor: url(/c.cur), auto",
+                &base
+            ),
+            "background: URL('https://cdn.example/css/img/a.png') no-repeat; mask: url(#m); cursor: url(https://cdn.example/c.cur), auto"
+        );
+        assert_eq!(resolve_css_urls("color: red", &base), "color: red");
+        assert_eq!(
+            resolve_css_urls("background: url(a.png", &base),
+            "background: url(a.png"
+        );
+    }
+
     #[test]
     fn test_dangerous_css_blocked() {
         let config = ParserConfig::default();
@@ -1901,6 +2230,47 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_focus_pseudo_class() {
+        let config = ParserConfig::default();
+        let metrics = Arc::new(ParserMetrics::default());
+        let parser = CitadelCssParser::new(config, metrics);
+
+        let css = "a { color: blue; } a:focus { color: red; }";
+        let stylesheet = parser.parse_stylesheet(css).unwrap();
+
+        let normal = stylesheet.compute_styles("a", &[], None);
+        assert_eq!(normal.color, Some(ColorValue::Named("blue".to_string())));
+
+        let focused =
+            stylesheet.compute_styles_with_state("a", &[], None, ElementState { focused: true });
+        assert_eq!(focused.color, Some(ColorValue::Named("red".to_string())));
+    }
+
+    #[test]
+    fn test_user_css_overrides_author_rules() {
+        let config = ParserConfig::default();
+        let metrics = Arc::new(ParserMetrics::default());
+        let parser = CitadelCssParser::new(config, metrics);
+
+        let mut stylesheet = parser
+            .parse_stylesheet("#main { color: white; } .ad { display: block; }")
+            .unwrap();
+        stylesheet
+            .add_user_css("div { color: black; } .ad { display: none; }")
+            .unwrap();
+
+        // A plain type selector from the user beats the page's ID selector
+        let main = stylesheet.compute_styles("div", &[], Some("main"));
+        assert_eq!(main.color, Some(ColorValue::Named("black".to_string())));
+        let ad = stylesheet.compute_styles("div", &["ad".to_string()], None);
+        assert_eq!(ad.display, DisplayType::None);
+        assert!(stylesheet
+            .rules()
+            .iter()
+            .any(|rule| rule.origin == CascadeOrigin::User));
+    }
+
     #[test]
     fn test_specificity_calculation() {
         let config = ParserConfig::default();
@@ -1913,5 +2283,105 @@ mod tests {
         assert!(
             parser.calculate_specificity("div.highlight") > parser.calculate_specificity("div")
         );
+
+        // Counts compare in order, however many classes there are
+        let classes = ".a".repeat(11);
+        assert!(parser.calculate_specificity("#main") > parser.calculate_specificity(&classes));
+        assert_eq!(
+            parser.calculate_specificity("div:where(#main, .a)"),
+            parser.calculate_specificity("div")
+        );
+        assert_eq!(
+            parser.calculate_specificity("a:not(#x, .y)::before"),
+            parser.calculate_specificity("#x a::after")
+        );
+    }
+
+    #[test]
+    fn test_font_face_rules_and_font_family() {
+        let config = ParserConfig::default();
+        let metrics = Arc::new(ParserMetrics::default());
+        let parser = CitadelCssParser::new(config, metrics);
+
+        let css = r#"
+            @font-face {
+                font-family: "Brand Sans";
+                src: local(Brand), url(/fonts/brand.woff2) format("woff2"),
+                     url('/fonts/brand.ttf') format(truetype);
+                font-weight: 300 700;
+                font-style: italic;
+            }
+            @font-face { font-family: Empty; }
+            p { font-family: "Brand Sans", Georgia, serif; font-weight: 600; }
+        "#;
+        let stylesheet = parser.parse_stylesheet(css).unwrap();
+        assert_eq!(stylesheet.font_faces.len(), 1);
+        let face = &stylesheet.font_faces[0];
+        assert_eq!(face.family, "Brand Sans");
+        assert_eq!((face.weight, face.italic), (300, true));
+        let sources: Vec<(&str, Option<&str>)> = face
+            .sources
+            .iter()
+            .map(|source| (source.url.as_str(), source.format.as_deref()))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                ("/fonts/brand.woff2", Some("woff2")),
+                ("/fonts/brand.ttf", Some("truetype"))
+            ]
+        );
+
+        let paragraph = stylesheet.compute_styles("p", &[], None);
+        let family = paragraph.font_family.unwrap();
+        assert_eq!(
+            font_family_names(&family),
+            ["Brand Sans", "Georgia", "serif"]
+        );
+        assert_eq!(font_weight_value("600"), Some(600));
+        assert_eq!(font_weight_value("heavy"), None);
+    }
+
+    #[test]
+    fn test_tokenized_rules_and_filtering() {
+        let config = ParserConfig::default();
+        let metrics = Arc::new(ParserMetrics::default());
+        let parser = CitadelCssParser::new(config, metrics);
+
+        // Escapes get past the pre-scan; the tokenizer resolves them
+        let css = r#"
+            /* layout */
+            @media print { p { color: red; } }
+            h1, .title > a:not(.x) {
+                color: rgb(1, 2, 3) !important;
+                background: url("a;b.png");
+            }
+            p {
+                background: url("j\61vascript:alert(1)");
+                beh\61vior: url(x.htc);
+                color: ;
+                margin: 0
+            }
+        "#;
+        let stylesheet = parser.parse_stylesheet(css).unwrap();
+        let selectors: Vec<&str> = stylesheet
+            .rules()
+            .iter()
+            .map(|rule| rule.selectors.as_str())
+            .collect();
+        assert_eq!(selectors, ["h1", ".title > a:not(.x)", "p"]);
+        assert!(stylesheet.rules()[1].specificity > stylesheet.rules()[0].specificity);
+
+        let heading = &stylesheet.rules()[0].declarations;
+        assert_eq!(heading[0].value, "rgb(1, 2, 3)");
+        assert!(heading[0].important);
+        assert_eq!(heading[1].value, r#"url("a;b.png")"#);
+
+        let paragraph = &stylesheet.rules()[2].declarations;
+        assert_eq!(paragraph.len(), 1);
+        assert_eq!(paragraph[0].property, "margin");
+        let text = stylesheet.to_string();
+        assert!(!text.contains("javascript:"));
+        assert!(!text.contains("behavior:"));
     }
 }
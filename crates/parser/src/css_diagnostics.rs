@@ -0,0 +1,786 @@
+//! Recoverable CSS errors with source positions
+//!
+//! The stylesheet parser recovers from bad input by dropping what it cannot
+//! use, which is right for rendering but hides why a page looks wrong. This
+//! pass walks the same source and records each unknown property, invalid
+//! value and malformed selector or declaration with its line, column and the
+//! rule it belongs to, for devtools to show.
+
+use std::fmt;
+
+use cssparser::{Parser as CssParserImpl, ParserInput, SourceLocation, ToCss, Token};
+
+/// Most diagnostics kept per stylesheet; the rest are only counted
+pub const MAX_DIAGNOSTICS: usize = 200;
+
+/// Longest rule context kept with a diagnostic
+const MAX_CONTEXT_LEN: usize = 80;
+
+/// Keywords every property accepts
+const GLOBAL_KEYWORDS: &[&str] = &["inherit", "initial", "unset", "revert", "revert-layer"];
+
+/// Units of `<length>` values
+const LENGTH_UNITS: &[&str] = &[
+    "px", "em", "rem", "ex", "ch", "lh", "rlh", "vw", "vh", "vmin", "vmax", "svw", "svh", "lvw",
+    "lvh", "dvw", "dvh", "cqw", "cqh", "cqi", "cqb", "cqmin", "cqmax", "cm", "mm", "q", "in", "pt",
+    "pc",
+];
+
+/// Properties taking lengths, percentages or keywords
+const LENGTH_PROPERTIES: &[&str] = &[
+    "width",
+    "height",
+    "min-width",
+    "min-height",
+    "max-width",
+    "max-height",
+    "margin",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "padding",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "top",
+    "right",
+    "bottom",
+    "left",
+    "font-size",
+    "border-width",
+    "border-radius",
+    "letter-spacing",
+    "word-spacing",
+    "gap",
+    "row-gap",
+    "column-gap",
+    "grid-gap",
+    "flex-basis",
+    "text-indent",
+    "outline-width",
+    "outline-offset",
+];
+
+/// Properties taking a single color
+const COLOR_PROPERTIES: &[&str] = &[
+    "color",
+    "background-color",
+    "border-color",
+    "outline-color",
+    "text-decoration-color",
+    "caret-color",
+    "accent-color",
+];
+
+/// Keyword-only properties and the values they accept
+const KEYWORD_PROPERTIES: &[(&str, &[&str])] = &[
+    (
+        "display",
+        &[
+            "none",
+            "block",
+            "inline",
+            "inline-block",
+            "flex",
+            "inline-flex",
+            "grid",
+            "inline-grid",
+            "table",
+            "inline-table",
+            "table-row",
+            "table-cell",
+            "table-column",
+            "table-caption",
+            "table-row-group",
+            "table-header-group",
+            "table-footer-group",
+            "table-column-group",
+            "list-item",
+            "contents",
+            "flow-root",
+            "run-in",
+        ],
+    ),
+    (
+        "position",
+        &["static", "relative", "absolute", "fixed", "sticky"],
+    ),
+    ("visibility", &["visible", "hidden", "collapse"]),
+    (
+        "float",
+        &["none", "left", "right", "inline-start", "inline-end"],
+    ),
+    (
+        "clear",
+        &[
+            "none",
+            "left",
+            "right",
+            "both",
+            "inline-start",
+            "inline-end",
+        ],
+    ),
+    ("box-sizing", &["content-box", "border-box"]),
+    (
+        "text-align",
+        &[
+            "left",
+            "right",
+            "center",
+            "justify",
+            "start",
+            "end",
+            "match-parent",
+        ],
+    ),
+    (
+        "overflow",
+        &["visible", "hidden", "clip", "scroll", "auto", "overlay"],
+    ),
+    (
+        "flex-direction",
+        &["row", "row-reverse", "column", "column-reverse"],
+    ),
+    ("flex-wrap", &["nowrap", "wrap", "wrap-reverse"]),
+];
+
+/// Standard properties beyond those with checked values
+const OTHER_PROPERTIES: &[&str] = &[
+    "align-content",
+    "align-items",
+    "align-self",
+    "all",
+    "animation",
+    "animation-delay",
+    "animation-direction",
+    "animation-duration",
+    "animation-fill-mode",
+    "animation-iteration-count",
+    "animation-name",
+    "animation-play-state",
+    "animation-timing-function",
+    "appearance",
+    "aspect-ratio",
+    "backdrop-filter",
+    "backface-visibility",
+    "background",
+    "background-attachment",
+    "background-blend-mode",
+    "background-clip",
+    "background-image",
+    "background-origin",
+    "background-position",
+    "background-repeat",
+    "background-size",
+    "block-size",
+    "border",
+    "border-bottom",
+    "border-bottom-color",
+    "border-bottom-left-radius",
+    "border-bottom-right-radius",
+    "border-bottom-style",
+    "border-bottom-width",
+    "border-collapse",
+    "border-image",
+    "border-left",
+    "border-left-color",
+    "border-left-style",
+    "border-left-width",
+    "border-right",
+    "border-right-color",
+    "border-right-style",
+    "border-right-width",
+    "border-spacing",
+    "border-style",
+    "border-top",
+    "border-top-color",
+    "border-top-left-radius",
+    "border-top-right-radius",
+    "border-top-style",
+    "border-top-width",
+    "box-shadow",
+    "break-inside",
+    "caption-side",
+    "clip",
+    "clip-path",
+    "color-scheme",
+    "column-count",
+    "column-width",
+    "columns",
+    "contain",
+    "content",
+    "counter-increment",
+    "counter-reset",
+    "cursor",
+    "direction",
+    "empty-cells",
+    "fill",
+    "filter",
+    "flex",
+    "flex-flow",
+    "flex-grow",
+    "flex-shrink",
+    "font",
+    "font-family",
+    "font-feature-settings",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "grid",
+    "grid-area",
+    "grid-auto-columns",
+    "grid-auto-flow",
+    "grid-auto-rows",
+    "grid-column",
+    "grid-column-end",
+    "grid-column-start",
+    "grid-row",
+    "grid-row-end",
+    "grid-row-start",
+    "grid-template",
+    "grid-template-areas",
+    "grid-template-columns",
+    "grid-template-rows",
+    "hyphens",
+    "image-rendering",
+    "inline-size",
+    "inset",
+    "isolation",
+    "justify-content",
+    "justify-items",
+    "justify-self",
+    "line-height",
+    "list-style",
+    "list-style-image",
+    "list-style-position",
+    "list-style-type",
+    "margin-block",
+    "margin-block-end",
+    "margin-block-start",
+    "margin-inline",
+    "margin-inline-end",
+    "margin-inline-start",
+    "mask",
+    "mix-blend-mode",
+    "object-fit",
+    "object-position",
+    "opacity",
+    "order",
+    "outline",
+    "outline-style",
+    "overflow-wrap",
+    "overflow-x",
+    "overflow-y",
+    "overscroll-behavior",
+    "padding-block",
+    "padding-inline",
+    "place-content",
+    "place-items",
+    "place-self",
+    "pointer-events",
+    "quotes",
+    "resize",
+    "scroll-behavior",
+    "scroll-margin",
+    "scroll-padding",
+    "scroll-snap-align",
+    "scroll-snap-type",
+    "scrollbar-color",
+    "scrollbar-width",
+    "stroke",
+    "stroke-width",
+    "tab-size",
+    "table-layout",
+    "text-decoration",
+    "text-decoration-line",
+    "text-decoration-style",
+    "text-overflow",
+    "text-rendering",
+    "text-shadow",
+    "text-transform",
+    "text-underline-offset",
+    "touch-action",
+    "transform",
+    "transform-origin",
+    "transform-style",
+    "transition",
+    "transition-delay",
+    "transition-duration",
+    "transition-property",
+    "transition-timing-function",
+    "unicode-bidi",
+    "user-select",
+    "vertical-align",
+    "white-space",
+    "will-change",
+    "word-break",
+    "word-wrap",
+    "writing-mode",
+    "z-index",
+];
+
+/// What is wrong
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssIssueKind {
+    /// A property no browser knows; vendor-prefixed and custom properties
+    /// are never reported
+    UnknownProperty,
+    /// A known property with a value it cannot take
+    InvalidValue,
+    /// A selector that cannot match anything as written
+    MalformedSelector,
+    /// Tokens in a block that do not form a `name: value` declaration
+    MalformedDeclaration,
+}
+
+impl fmt::Display for CssIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CssIssueKind::UnknownProperty => "unknown property",
+            CssIssueKind::InvalidValue => "invalid value",
+            CssIssueKind::MalformedSelector => "malformed selector",
+            CssIssueKind::MalformedDeclaration => "malformed declaration",
+        })
+    }
+}
+
+/// One recoverable error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssDiagnostic {
+    pub kind: CssIssueKind,
+    /// 1-based line in the stylesheet source
+    pub line: u32,
+    /// 1-based column in the stylesheet source
+    pub column: u32,
+    /// Selector of the enclosing rule, shortened
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for CssDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.kind, self.message
+        )?;
+        if let Some(rule) = &self.rule {
+            write!(f, " (in `{}`)", rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recoverable errors found in one stylesheet, in source order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StylesheetDiagnostics {
+    pub diagnostics: Vec<CssDiagnostic>,
+    /// Errors found past [`MAX_DIAGNOSTICS`]
+    pub omitted: usize,
+}
+
+impl StylesheetDiagnostics {
+    /// Check a stylesheet's source
+    pub fn analyze(css: &str) -> Self {
+        let mut input = ParserInput::new(css);
+        let mut parser = CssParserImpl::new(&mut input);
+        let mut diagnostics = Self::default();
+        diagnose_rules(&mut parser, &mut diagnostics);
+        diagnostics
+    }
+
+    /// Total errors found, omitted ones included
+    pub fn len(&self) -> usize {
+        self.diagnostics.len() + self.omitted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CssDiagnostic> {
+        self.diagnostics.iter()
+    }
+
+    fn push(
+        &mut self,
+        kind: CssIssueKind,
+        location: SourceLocation,
+        rule: Option<&str>,
+        message: String,
+    ) {
+        if self.diagnostics.len() >= MAX_DIAGNOSTICS {
+            self.omitted += 1;
+            return;
+        }
+        self.diagnostics.push(CssDiagnostic {
+            kind,
+            line: location.line + 1,
+            column: location.column,
+            rule: rule.map(shorten),
+            message,
+        });
+    }
+}
+
+/// Top level: qualified rules and at-rules
+fn diagnose_rules(parser: &mut CssParserImpl, out: &mut StylesheetDiagnostics) {
+    let mut prelude = String::new();
+    let mut start = parser.current_source_location();
+    loop {
+        if prelude.trim().is_empty() {
+            prelude.clear();
+            start = parser.current_source_location();
+        }
+        let token = match parser.next_including_whitespace() {
+            Ok(token) => token.clone(),
+            Err(_) => break,
+        };
+        match token {
+            Token::AtKeyword(_) if prelude.trim().is_empty() => skip_at_rule(parser),
+            Token::CurlyBracketBlock => {
+                let selector = prelude.trim().to_string();
+                if let Some(problem) = selector_problem(&selector) {
+                    out.push(
+                        CssIssueKind::MalformedSelector,
+                        start,
+                        Some(&selector),
+                        problem,
+                    );
+                }
+                let _: Result<(), cssparser::ParseError<()>> = parser.parse_nested_block(|block| {
+                    diagnose_declarations(block, &selector, out);
+                    Ok(())
+                });
+                prelude.clear();
+            }
+            Token::CloseCurlyBracket | Token::BadString(_) | Token::BadUrl(_) => {
+                out.push(
+                    CssIssueKind::MalformedSelector,
+                    start,
+                    None,
+                    format!("unexpected `{}`", token.to_css_string()),
+                );
+                prelude.clear();
+            }
+            Token::Semicolon => {
+                out.push(
+                    CssIssueKind::MalformedSelector,
+                    start,
+                    None,
+                    format!("`{}` has no declaration block", prelude.trim()),
+                );
+                prelude.clear();
+            }
+            Token::Function(name) => {
+                // The arguments are a nested block the tokenizer skips
+                prelude.push_str(&name);
+                prelude.push_str("(…)");
+            }
+            token => prelude.push_str(&token.to_css_string()),
+        }
+    }
+    if !prelude.trim().is_empty() {
+        out.push(
+            CssIssueKind::MalformedSelector,
+            start,
+            None,
+            format!("`{}` has no declaration block", prelude.trim()),
+        );
+    }
+}
+
+/// Skip an at-rule's prelude and block; their contents are not checked
+fn skip_at_rule(parser: &mut CssParserImpl) {
+    while let Ok(token) = parser.next() {
+        if matches!(token, Token::Semicolon | Token::CurlyBracketBlock) {
+            break;
+        }
+    }
+}
+
+/// Declarations inside one rule's block
+fn diagnose_declarations(parser: &mut CssParserImpl, rule: &str, out: &mut StylesheetDiagnostics) {
+    loop {
+        let start = parser.current_source_location();
+        let token = match parser.next() {
+            Ok(token) => token.clone(),
+            Err(_) => break,
+        };
+        let property = match token {
+            Token::Semicolon => continue,
+            Token::Ident(name) => name.to_ascii_lowercase(),
+            token => {
+                out.push(
+                    CssIssueKind::MalformedDeclaration,
+                    start,
+                    Some(rule),
+                    format!(
+                        "expected a property name, found `{}`",
+                        token.to_css_string()
+                    ),
+                );
+                skip_declaration(parser);
+                continue;
+            }
+        };
+        if !matches!(parser.next(), Ok(Token::Colon)) {
+            out.push(
+                CssIssueKind::MalformedDeclaration,
+                start,
+                Some(rule),
+                format!("expected `:` after `{}`", property),
+            );
+            skip_declaration(parser);
+            continue;
+        }
+
+        let value_start = parser.current_source_location();
+        let mut value = Vec::new();
+        loop {
+            match parser.next() {
+                Ok(Token::Semicolon) | Err(_) => break,
+                Ok(Token::Delim('!')) => match parser.next() {
+                    Ok(Token::Ident(flag)) if flag.eq_ignore_ascii_case("important") => {}
+                    _ => out.push(
+                        CssIssueKind::MalformedDeclaration,
+                        value_start,
+                        Some(rule),
+                        format!("stray `!` in `{}`", property),
+                    ),
+                },
+                Ok(token) => value.push(token.clone()),
+            }
+        }
+
+        if !is_known_property(&property) {
+            out.push(
+                CssIssueKind::UnknownProperty,
+                start,
+                Some(rule),
+                format!("`{}`", property),
+            );
+        } else if let Some(problem) = value_problem(&property, &value) {
+            out.push(CssIssueKind::InvalidValue, value_start, Some(rule), problem);
+        }
+    }
+}
+
+fn skip_declaration(parser: &mut CssParserImpl) {
+    while let Ok(token) = parser.next() {
+        if matches!(token, Token::Semicolon) {
+            break;
+        }
+    }
+}
+
+fn is_known_property(property: &str) -> bool {
+    property.starts_with('-')
+        || LENGTH_PROPERTIES.contains(&property)
+        || COLOR_PROPERTIES.contains(&property)
+        || KEYWORD_PROPERTIES.iter().any(|(name, _)| *name == property)
+        || OTHER_PROPERTIES.contains(&property)
+}
+
+/// Why a value does not fit its property, if it does not
+fn value_problem(property: &str, value: &[Token]) -> Option<String> {
+    let [first, ..] = value else {
+        return Some(format!("`{}` has no value", property));
+    };
+    if let Token::Ident(keyword) = first {
+        if value.len() == 1 && GLOBAL_KEYWORDS.contains(&&*keyword.to_ascii_lowercase()) {
+            return None;
+        }
+    }
+    // Values computed at use time cannot be checked here
+    if value
+        .iter()
+        .any(|token| matches!(token, Token::Function(name) if name.eq_ignore_ascii_case("var")))
+    {
+        return None;
+    }
+
+    if LENGTH_PROPERTIES.contains(&property) {
+        return value.iter().find_map(|token| match token {
+            Token::Dimension { unit, .. }
+                if !LENGTH_UNITS.contains(&&*unit.to_ascii_lowercase()) =>
+            {
+                Some(format!("unknown unit `{}` in `{}`", unit, property))
+            }
+            Token::Number { value, .. } if *value != 0.0 => Some(format!(
+                "`{}` needs a unit in `{}`",
+                token.to_css_string(),
+                property
+            )),
+            Token::Dimension { .. }
+            | Token::Percentage { .. }
+            | Token::Number { .. }
+            | Token::Ident(_)
+            | Token::Function(_)
+            | Token::Delim('/')
+            | Token::Comma => None,
+            token => Some(format!(
+                "unexpected `{}` in `{}`",
+                token.to_css_string(),
+                property
+            )),
+        });
+    }
+
+    if COLOR_PROPERTIES.contains(&property) {
+        return match value {
+            [Token::Hash(hex) | Token::IDHash(hex)]
+                if matches!(hex.len(), 3 | 4 | 6 | 8)
+                    && hex.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                None
+            }
+            [Token::Hash(hex) | Token::IDHash(hex)] => {
+                Some(format!("`#{}` is not a hex color", hex))
+            }
+            [Token::Ident(_) | Token::Function(_)] => None,
+            _ => Some(format!(
+                "`{}` is not a color",
+                value
+                    .iter()
+                    .map(|token| token.to_css_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )),
+        };
+    }
+
+    if let Some((_, allowed)) = KEYWORD_PROPERTIES
+        .iter()
+        .find(|(name, _)| *name == property)
+    {
+        return match value {
+            [Token::Ident(keyword)] if allowed.contains(&&*keyword.to_ascii_lowercase()) => None,
+            // `display: inline flex` and similar multi-keyword forms
+            tokens
+                if property == "display"
+                    && tokens.len() > 1
+                    && tokens.iter().all(|token| {
+                        matches!(token, Token::Ident(keyword)
+                            if allowed.contains(&&*keyword.to_ascii_lowercase()))
+                    }) =>
+            {
+                None
+            }
+            _ => Some(format!(
+                "`{}` is not a value of `{}`",
+                value
+                    .iter()
+                    .map(|token| token.to_css_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                property
+            )),
+        };
+    }
+    None
+}
+
+/// Why a selector cannot match as written, if it cannot
+fn selector_problem(selector: &str) -> Option<String> {
+    if selector.is_empty() {
+        return Some("rule has no selector".to_string());
+    }
+    for part in selector.split(',').map(str::trim) {
+        if part.is_empty() {
+            return Some("empty selector in list".to_string());
+        }
+        if part.starts_with(['>', '+', '~']) || part.ends_with(['>', '+', '~']) {
+            return Some(format!("dangling combinator in `{}`", part));
+        }
+        if let Some(c) = part
+            .chars()
+            .find(|c| matches!(c, ';' | '!' | '?' | '{' | '<'))
+        {
+            return Some(format!("unexpected `{}` in `{}`", c, part));
+        }
+        let mut chars = part.chars().peekable();
+        while let Some(c) = chars.next() {
+            if matches!(c, '.' | '#')
+                && !chars
+                    .peek()
+                    .is_some_and(|next| next.is_alphanumeric() || matches!(next, '-' | '_' | '\\'))
+            {
+                let what = if c == '.' { "class" } else { "id" };
+                return Some(format!("missing {} name in `{}`", what, part));
+            }
+        }
+    }
+    None
+}
+
+fn shorten(rule: &str) -> String {
+    if rule.chars().count() <= MAX_CONTEXT_LEN {
+        return rule.to_string();
+    }
+    let mut short: String = rule.chars().take(MAX_CONTEXT_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_errors_with_positions_and_rule_context() {
+        let diagnostics = StylesheetDiagnostics::analyze(
+            "body { colour: red; margin: 10px; }\n\
+             .card {\n  width: 12;\n  padding: 4pz;\n  color: #ggg;\n  display: flexy;\n}\n\
+             div > { color: blue; }\n\
+             p { : x; --brand: teal; -webkit-box-flex: 1; height: calc(100% - 1px) }\n",
+        );
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.kind, d.line, d.rule.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (CssIssueKind::UnknownProperty, 1, Some("body")),
+                (CssIssueKind::InvalidValue, 3, Some(".card")),
+                (CssIssueKind::InvalidValue, 4, Some(".card")),
+                (CssIssueKind::InvalidValue, 5, Some(".card")),
+                (CssIssueKind::InvalidValue, 6, Some(".card")),
+                (CssIssueKind::MalformedSelector, 8, Some("div >")),
+                (CssIssueKind::MalformedDeclaration, 9, Some("p")),
+            ]
+        );
+        let colour = &diagnostics.diagnostics[0];
+        assert_eq!(colour.column, 8);
+        assert_eq!(
+            colour.to_string(),
+            "1:8: unknown property: `colour` (in `body`)"
+        );
+        assert!(diagnostics.diagnostics[2].message.contains("`pz`"));
+    }
+
+    #[test]
+    fn test_valid_stylesheets_are_clean() {
+        let diagnostics = StylesheetDiagnostics::analyze(
+            "@media (max-width: 600px) { .x { colr: red } }\n\
+             html, body { margin: 0; padding: 0 1em; color: #fff; }\n\
+             a:hover, .nav > li + li { color: rgb(0, 0, 0) !important; display: inline flex; }\n\
+             #main { width: var(--w); height: inherit; position: sticky; top: 0 }\n",
+        );
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_caps_the_number_kept() {
+        let css = "p { nope: 1; }\n".repeat(MAX_DIAGNOSTICS + 5);
+        let diagnostics = StylesheetDiagnostics::analyze(&css);
+        assert_eq!(diagnostics.diagnostics.len(), MAX_DIAGNOSTICS);
+        assert_eq!(diagnostics.omitted, 5);
+        assert_eq!(diagnostics.len(), MAX_DIAGNOSTICS + 5);
+    }
+}
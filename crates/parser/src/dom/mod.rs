@@ -2,11 +2,25 @@
 //!
 //! This module defines the core structures like Node, Element, and Attribute,
 //! along with builders and metrics collection, emphasizing privacy and security.
+//!
+//! # Thread safety
+//!
+//! [`Dom`] and [`Node`] are `Send + Sync`: nodes are shared as
+//! [`NodeHandle`]s (`Arc<RwLock<Node>>`) and every other field is plain owned
+//! data, so a parsed document can move into worker tasks and be read from
+//! several threads at once (e.g. layout and accessibility in parallel).
+//! Locking discipline:
+//!
+//! - Lock a parent before its children, never a child and then its parent.
+//! - Hold at most one write lock; release it before locking another node.
+//! - Never hold a node guard across an `.await`.
+//! - Treat a poisoned lock as a missing subtree, not a panic.
 
 // Declare submodules
 pub mod error;
 pub mod metrics;
 pub mod node;
+mod query;
 
 // Re-export key types for easier access from outside the dom module
 pub use error::DomError;
@@ -16,6 +30,16 @@ pub use node::{Attribute, Element, Node, NodeBuilder, NodeData, NodeHandle};
 use html5ever::namespace_url;
 use std::sync::Arc;
 
+use crate::error::ParserResult;
+use crate::selector::SelectorList;
+use query::ElementIndex;
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Dom>();
+    assert_send_sync::<Node>();
+};
+
 /// Represents the top-level DOM structure for a parsed document.
 #[derive(Debug)]
 pub struct Dom {
@@ -25,6 +49,12 @@ pub struct Dom {
     pub metrics: Arc<DomMetrics>,
     /// The security context applied during DOM construction.
     pub security_context: Arc<crate::security::SecurityContext>,
+    /// The base URL set by the document's `<base href>`, if one was honored
+    pub base_url: Option<url::Url>,
+    /// The default browsing context set by the document's `<base target>`
+    pub base_target: Option<String>,
+    /// Whether DOM limits left part of the document out
+    pub truncated: bool,
     // Potentially add QuirksMode or other document-level properties here
 }
 
@@ -42,9 +72,22 @@ impl Dom {
             document_node_handle: root_handle,
             metrics,
             security_context,
+            base_url: None,
+            base_target: None,
+            truncated: false,
         }
     }
 
+    /// Resolve `reference` against the document's `<base href>`, or against
+    /// `document_url` when the document set none
+    pub fn resolve_url(&self, document_url: &url::Url, reference: &str) -> Option<url::Url> {
+        self.base_url
+            .as_ref()
+            .unwrap_or(document_url)
+            .join(reference.trim())
+            .ok()
+    }
+
     /// Get the root document node handle.
     pub fn root(&self) -> NodeHandle {
         self.document_node_handle.clone()
@@ -177,7 +220,7 @@ impl Dom {
                                                     tracing::info!("        HTML child {}: <{}> with {} children", j, he.local_name(), html_child_node.children.len());
                                                 }
                                                 crate::dom::node::NodeData::Text(t) => {
-                                                    tracing::info!("        HTML child {}: TEXT '{}' ({} chars)", j, t.trim(), t.len());
+                                                    tracing::debug!("        HTML child {}: TEXT '{}' ({} chars)", j, t.trim(), t.len());
                                                 }
                                                 _ => {
                                                     tracing::info!(
@@ -245,7 +288,7 @@ impl Dom {
             } else {
                 final_content.clone()
             };
-            tracing::info!("📚 Final content preview: '{}'", preview);
+            tracing::debug!("📚 Final content preview: '{}'", preview);
         }
 
         final_content
@@ -473,38 +516,27 @@ impl Dom {
         }
     }
 
-    /// Basic querySelector implementation (ID and tag name only for now)
+    /// The first element matching a CSS selector list, in document order;
+    /// `None` also when the selector is invalid
     pub fn query_selector(&self, selector: &str) -> Option<NodeHandle> {
-        if selector.starts_with('#') {
-            // ID selector
-            let id = &selector[1..];
-            self.get_element_by_id(id)
-        } else if selector.starts_with('.') {
-            // Class selector - return first match
-            let class_name = &selector[1..];
-            self.get_elements_by_class_name(class_name)
-                .into_iter()
-                .next()
-        } else {
-            // Tag selector - return first match
-            self.get_elements_by_tag_name(selector).into_iter().next()
-        }
+        self.try_query(selector, true).ok()?.into_iter().next()
     }
 
-    /// Basic querySelectorAll implementation
+    /// Every element matching a CSS selector list, in document order; empty
+    /// also when the selector is invalid
     pub fn query_selector_all(&self, selector: &str) -> Vec<NodeHandle> {
-        if selector.starts_with('#') {
-            // ID selector - return at most one element
-            let id = &selector[1..];
-            self.get_element_by_id(id).into_iter().collect()
-        } else if selector.starts_with('.') {
-            // Class selector
-            let class_name = &selector[1..];
-            self.get_elements_by_class_name(class_name)
-        } else {
-            // Tag selector
-            self.get_elements_by_tag_name(selector)
-        }
+        self.try_query(selector, false).unwrap_or_default()
+    }
+
+    /// Elements matching a CSS selector list, or why the selector is
+    /// invalid. See [`crate::selector`] for what is supported.
+    pub fn try_query_selector_all(&self, selector: &str) -> ParserResult<Vec<NodeHandle>> {
+        self.try_query(selector, false)
+    }
+
+    fn try_query(&self, selector: &str, first_only: bool) -> ParserResult<Vec<NodeHandle>> {
+        let selectors = SelectorList::parse(selector)?;
+        Ok(ElementIndex::build(&self.document_node_handle).query(&selectors, first_only))
     }
 
     /// Create a new element and add it to the DOM
@@ -565,6 +597,19 @@ impl Dom {
         self.count_elements_recursive(&self.document_node_handle)
     }
 
+    /// A copy of the document that later changes to this one do not
+    /// reach, e.g. a partial DOM handed to the renderer while parsing goes on
+    pub fn snapshot(&self) -> Dom {
+        Dom {
+            document_node_handle: copy_subtree(&self.document_node_handle),
+            metrics: self.metrics.clone(),
+            security_context: self.security_context.clone(),
+            base_url: self.base_url.clone(),
+            base_target: self.base_target.clone(),
+            truncated: self.truncated,
+        }
+    }
+
     /// Recursively count elements
     fn count_elements_recursive(&self, node_handle: &NodeHandle) -> usize {
         let mut count = 0;
@@ -583,6 +628,18 @@ impl Dom {
     }
 }
 
+/// Deep copy of a node; poisoned nodes are copied as empty documents
+fn copy_subtree(handle: &NodeHandle) -> NodeHandle {
+    let copy = match handle.read() {
+        Ok(node) => Node {
+            data: node.data.clone(),
+            children: node.children.iter().map(copy_subtree).collect(),
+        },
+        Err(_) => Node::new(NodeData::Document),
+    };
+    Arc::new(std::sync::RwLock::new(copy))
+}
+
 // Example of creating a minimal DOM (e.g., for testing or empty documents)
 #[allow(dead_code)] // Keep function for potential use even if not called directly here
 fn create_minimal_dom() -> Result<Dom, DomError> {
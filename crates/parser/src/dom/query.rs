@@ -0,0 +1,191 @@
+//! Selector matching over the DOM
+//!
+//! Nodes only know their children, while selectors look at parents and
+//! siblings. A query therefore first indexes the subtree's elements in
+//! document order with those links, then matches every element against the
+//! selector.
+
+use crate::dom::node::{NodeData, NodeHandle};
+use crate::selector::{SelectorElement, SelectorList};
+
+/// Elements of a subtree in document order
+#[derive(Debug, Default)]
+pub(crate) struct ElementIndex {
+    elements: Vec<IndexedElement>,
+}
+
+#[derive(Debug)]
+struct IndexedElement {
+    handle: NodeHandle,
+    local_name: String,
+    attributes: Vec<(String, String)>,
+    parent: Option<usize>,
+    prev_sibling: Option<usize>,
+    next_sibling: Option<usize>,
+    has_content: bool,
+}
+
+impl ElementIndex {
+    /// Index the elements under `root`, and `root` itself if it is one
+    pub(crate) fn build(root: &NodeHandle) -> Self {
+        let mut index = Self::default();
+        index.visit(root, None);
+        index
+    }
+
+    /// Index a node and its subtree; returns the node's index if it is an
+    /// element
+    fn visit(&mut self, handle: &NodeHandle, parent: Option<usize>) -> Option<usize> {
+        // Poisoned nodes count as missing subtrees
+        let node = handle.read().ok()?;
+        let NodeData::Element(element) = &node.data else {
+            // The document (or a fragment): its children have no parent
+            // element
+            self.visit_children(&node.children, parent);
+            return None;
+        };
+
+        let at = self.elements.len();
+        self.elements.push(IndexedElement {
+            handle: handle.clone(),
+            local_name: element.local_name().to_ascii_lowercase(),
+            attributes: element
+                .attributes
+                .iter()
+                .map(|attr| (attr.name.local.to_ascii_lowercase(), attr.value.clone()))
+                .collect(),
+            parent,
+            prev_sibling: None,
+            next_sibling: None,
+            has_content: node.children.iter().any(|child| {
+                child.read().is_ok_and(|child| match &child.data {
+                    NodeData::Element(_) => true,
+                    NodeData::Text(text) => !text.is_empty(),
+                    _ => false,
+                })
+            }),
+        });
+        self.visit_children(&node.children, Some(at));
+        Some(at)
+    }
+
+    fn visit_children(&mut self, children: &[NodeHandle], parent: Option<usize>) {
+        let mut previous: Option<usize> = None;
+        for child in children {
+            if let Some(at) = self.visit(child, parent) {
+                if let Some(previous) = previous {
+                    self.elements[previous].next_sibling = Some(at);
+                    self.elements[at].prev_sibling = Some(previous);
+                }
+                previous = Some(at);
+            }
+        }
+    }
+
+    /// Elements matching the selectors, in document order; at most one
+    /// with `first_only`
+    pub(crate) fn query(&self, selectors: &SelectorList, first_only: bool) -> Vec<NodeHandle> {
+        let mut matches = (0..self.elements.len())
+            .filter(|at| {
+                selectors.matches(&IndexedRef {
+                    index: self,
+                    at: *at,
+                })
+            })
+            .map(|at| self.elements[at].handle.clone());
+        match first_only {
+            true => matches.next().into_iter().collect(),
+            false => matches.collect(),
+        }
+    }
+}
+
+/// One element of an [`ElementIndex`]
+#[derive(Clone, Copy)]
+struct IndexedRef<'a> {
+    index: &'a ElementIndex,
+    at: usize,
+}
+
+impl IndexedRef<'_> {
+    fn element(&self) -> &IndexedElement {
+        &self.index.elements[self.at]
+    }
+
+    fn follow(&self, at: Option<usize>) -> Option<Self> {
+        at.map(|at| Self {
+            index: self.index,
+            at,
+        })
+    }
+}
+
+impl SelectorElement for IndexedRef<'_> {
+    fn local_name(&self) -> &str {
+        &self.element().local_name
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.element()
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        self.follow(self.element().parent)
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.follow(self.element().prev_sibling)
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.follow(self.element().next_sibling)
+    }
+
+    fn has_content(&self) -> bool {
+        self.element().has_content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_html;
+    use crate::security::SecurityContext;
+    use std::sync::Arc;
+
+    fn ids(dom: &crate::Dom, selector: &str) -> Vec<String> {
+        dom.query_selector_all(selector)
+            .iter()
+            .filter_map(|handle| handle.read().ok()?.element_id())
+            .collect()
+    }
+
+    #[test]
+    fn test_query_combinators_attributes_and_nth() {
+        let dom = parse_html(
+            "<html><body><ul id=\"list\">\
+             <li id=\"a\"><a id=\"x\" href=\"https://example.com/\">x</a></li>\
+             <li id=\"b\" lang=\"en-GB\"></li>\
+             <li id=\"c\"><a id=\"y\" href=\"/local\">y</a></li>\
+             </ul><p id=\"after\"></p></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+
+        assert_eq!(ids(&dom, "ul > li:nth-child(odd)"), ["a", "c"]);
+        assert_eq!(ids(&dom, "li + li"), ["b", "c"]);
+        assert_eq!(ids(&dom, "ul ~ p, li:empty"), ["b", "after"]);
+        assert_eq!(ids(&dom, "a[href^=\"https:\"]"), ["x"]);
+        assert_eq!(ids(&dom, "[lang|=en]"), ["b"]);
+        assert_eq!(ids(&dom, "body > li"), Vec::<String>::new());
+        assert_eq!(ids(&dom, "li:not(:first-child) a"), ["y"]);
+
+        let first = dom.query_selector("#list li:last-of-type").unwrap();
+        assert_eq!(first.read().unwrap().element_id().as_deref(), Some("c"));
+        assert!(dom.query_selector("li >").is_none());
+        assert!(dom.try_query_selector_all("li:bogus").is_err());
+    }
+}
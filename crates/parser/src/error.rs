@@ -1,3 +1,4 @@
+use citadel_errors::{Classify, ErrorKind};
 use std::error::Error;
 use std::fmt;
 use url::ParseError as UrlParseError;
@@ -17,6 +18,10 @@ pub enum ParserError {
     NestingTooDeep(usize),
     /// Too many tokens
     TooManyTokens(usize),
+    /// Attribute value longer than the limit
+    AttributeTooLong(usize),
+    /// Document larger than the limit, in bytes
+    DocumentTooLarge(usize),
     /// IO Error
     IoError(String),
     /// JavaScript execution error
@@ -38,6 +43,10 @@ impl fmt::Display for ParserError {
             ParserError::SecurityViolation(msg) => write!(f, "Security violation: {}", msg),
             ParserError::NestingTooDeep(depth) => write!(f, "Nesting too deep: {}", depth),
             ParserError::TooManyTokens(count) => write!(f, "Too many tokens: {}", count),
+            ParserError::AttributeTooLong(length) => {
+                write!(f, "Attribute value too long: {} bytes", length)
+            }
+            ParserError::DocumentTooLarge(size) => write!(f, "Document too large: {} bytes", size),
             ParserError::IoError(msg) => write!(f, "IO Error: {}", msg),
             ParserError::JsError(msg) => write!(f, "JavaScript error: {}", msg),
             ParserError::LayoutError(msg) => write!(f, "Layout error: {}", msg),
@@ -58,6 +67,44 @@ impl Error for ParserError {
     }
 }
 
+impl Classify for ParserError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ParserError::HtmlParseError(_)
+            | ParserError::CssError(_)
+            | ParserError::InvalidUrl(_)
+            | ParserError::JsError(_) => ErrorKind::Content,
+            ParserError::SecurityViolation(_) => ErrorKind::Security,
+            ParserError::NestingTooDeep(_)
+            | ParserError::TooManyTokens(_)
+            | ParserError::AttributeTooLong(_)
+            | ParserError::DocumentTooLarge(_)
+            | ParserError::ResourceLimitExceeded(_) => ErrorKind::Resource,
+            ParserError::IoError(_) | ParserError::LayoutError(_) | ParserError::Unknown(_) => {
+                ErrorKind::Internal
+            }
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ParserError::HtmlParseError(_) => "PARSE_HTML",
+            ParserError::CssError(_) => "PARSE_CSS",
+            ParserError::InvalidUrl(_) => "PARSE_INVALID_URL",
+            ParserError::SecurityViolation(_) => "PARSE_SECURITY_VIOLATION",
+            ParserError::NestingTooDeep(_) => "PARSE_NESTING_TOO_DEEP",
+            ParserError::TooManyTokens(_) => "PARSE_TOO_MANY_TOKENS",
+            ParserError::AttributeTooLong(_) => "PARSE_ATTRIBUTE_TOO_LONG",
+            ParserError::DocumentTooLarge(_) => "PARSE_DOCUMENT_TOO_LARGE",
+            ParserError::IoError(_) => "PARSE_IO",
+            ParserError::JsError(_) => "PARSE_JS",
+            ParserError::LayoutError(_) => "PARSE_LAYOUT",
+            ParserError::ResourceLimitExceeded(_) => "PARSE_RESOURCE_LIMIT",
+            ParserError::Unknown(_) => "PARSE_UNKNOWN",
+        }
+    }
+}
+
 /// Result type for parser operations
 pub type ParserResult<T> = Result<T, ParserError>;
 
@@ -80,6 +127,19 @@ mod tests {
         assert_eq!(err.to_string(), "Nesting too deep: 100");
     }
 
+    #[test]
+    fn test_error_classification() {
+        let err = ParserError::NestingTooDeep(100);
+        assert_eq!(err.kind(), ErrorKind::Resource);
+        assert_eq!(err.code(), "PARSE_NESTING_TOO_DEEP");
+
+        let err = citadel_errors::CitadelError::from(ParserError::SecurityViolation(
+            "script tag not allowed".to_string(),
+        ));
+        assert_eq!(err.severity(), citadel_errors::Severity::Critical);
+        assert!(err.source().is_some());
+    }
+
     #[test]
     fn test_error_source() {
         let url_err = url::ParseError::EmptyHost;
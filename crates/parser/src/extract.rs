@@ -0,0 +1,573 @@
+//! Structured extraction of links, headings and metadata
+//!
+//! Typed views over a parsed [`Dom`] for reader mode, text-only browsing,
+//! link previews and embedders, so they do not each re-walk raw nodes. Link and metadata URLs
+//! are resolved against the page's `<base href>`, or else the page URL;
+//! links to schemes a user cannot
+//! navigate to (`javascript:`, `data:`...) are left out.
+
+use url::Url;
+
+use crate::dom::{Dom, NodeData, NodeHandle};
+
+/// Schemes an extracted link may point to
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto", "tel"];
+
+/// Maximum length of link and heading text
+const MAX_TEXT_LENGTH: usize = 512;
+
+/// Maximum length of a paragraph of text-only content
+const MAX_BLOCK_LENGTH: usize = 16 * 1024;
+
+/// Elements whose text reads as one paragraph
+const PARAGRAPH_TAGS: &[&str] = &[
+    "p",
+    "li",
+    "blockquote",
+    "pre",
+    "dt",
+    "dd",
+    "td",
+    "th",
+    "caption",
+    "figcaption",
+    "address",
+];
+
+/// Elements with no readable text
+const TEXTLESS_TAGS: &[&str] = &[
+    "head", "script", "style", "template", "noscript", "svg", "math", "select",
+];
+
+/// A hyperlink on the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLink {
+    /// Absolute target URL
+    pub url: Url,
+    /// Link text with whitespace collapsed
+    pub text: String,
+    /// Lowercased `rel` tokens, e.g. `nofollow` or `noopener`
+    pub rel: Vec<String>,
+    pub title: Option<String>,
+}
+
+impl PageLink {
+    /// Whether the link's `rel` contains `token`
+    pub fn has_rel(&self, token: &str) -> bool {
+        self.rel.iter().any(|rel| rel.eq_ignore_ascii_case(token))
+    }
+
+    /// Whether the link targets a different host than `page`
+    pub fn is_external(&self, page: &Url) -> bool {
+        self.url.host_str() != page.host_str()
+    }
+}
+
+/// An entry of the page's heading outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// 1 for `<h1>` through 6 for `<h6>`
+    pub level: u8,
+    pub text: String,
+    /// The heading's `id`, usable as a fragment to jump to it
+    pub id: Option<String>,
+}
+
+/// A `<meta name|property content>` tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaTag {
+    /// Lowercased `name` or `property`
+    pub name: String,
+    pub content: String,
+}
+
+/// OpenGraph (`og:*`) properties
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OpenGraph {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// `og:type`, e.g. `article` or `website`
+    pub kind: Option<String>,
+    pub site_name: Option<String>,
+    pub url: Option<Url>,
+    pub image: Option<Url>,
+}
+
+/// Document-level metadata from `<head>`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PageMetadata {
+    /// The `<title>` text
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub keywords: Vec<String>,
+    /// `<link rel="canonical">` target
+    pub canonical: Option<Url>,
+    pub open_graph: OpenGraph,
+    /// Every named meta tag in document order
+    pub meta: Vec<MetaTag>,
+}
+
+impl PageMetadata {
+    /// Content of the first meta tag called `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.meta
+            .iter()
+            .find(|tag| tag.name.eq_ignore_ascii_case(name))
+            .map(|tag| tag.content.as_str())
+    }
+
+    /// Best title for previews: OpenGraph first, then `<title>`
+    pub fn display_title(&self) -> Option<&str> {
+        self.open_graph.title.as_deref().or(self.title.as_deref())
+    }
+
+    /// Best description for previews: OpenGraph first, then `description`
+    pub fn display_description(&self) -> Option<&str> {
+        self.open_graph
+            .description
+            .as_deref()
+            .or(self.description.as_deref())
+    }
+}
+
+/// A block of the page's readable text
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextBlock {
+    /// An `<h1>`-`<h6>`
+    Heading { level: u8, text: String },
+    /// A paragraph, list item, cell or loose run of text
+    Paragraph(String),
+    /// A link, placed after the paragraph it appears in
+    Link(PageLink),
+}
+
+/// Links, outline and metadata of a page in one pass
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PageExtract {
+    pub links: Vec<PageLink>,
+    pub headings: Vec<Heading>,
+    pub metadata: PageMetadata,
+}
+
+impl Dom {
+    /// Every `<a href>` and `<area href>` with its target resolved against
+    /// the document's base URL or `base`, in document order
+    pub fn links(&self, base: &Url) -> Vec<PageLink> {
+        let base = self.base_url.as_ref().unwrap_or(base);
+        let mut links = Vec::new();
+        walk(&self.document_node_handle, &mut |handle, tag| {
+            if tag != "a" && tag != "area" {
+                return;
+            }
+            let Ok(node) = handle.read() else {
+                return;
+            };
+            let Some(element) = node.as_element() else {
+                return;
+            };
+            let Some(url) = element
+                .get_attribute("href")
+                .and_then(|href| resolve(base, &href))
+                .filter(|url| LINK_SCHEMES.contains(&url.scheme()))
+            else {
+                return;
+            };
+            links.push(PageLink {
+                url,
+                text: clean_text(&node.text_content()),
+                rel: rel_tokens(element.get_attribute("rel").as_deref()),
+                title: element
+                    .get_attribute("title")
+                    .map(|title| clean_text(&title))
+                    .filter(|title| !title.is_empty()),
+            });
+        });
+        links
+    }
+
+    /// The `<h1>`-`<h6>` outline in document order, skipping empty headings
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        walk(&self.document_node_handle, &mut |handle, tag| {
+            let Some(level) = heading_level(tag) else {
+                return;
+            };
+            let Ok(node) = handle.read() else {
+                return;
+            };
+            let text = clean_text(&node.text_content());
+            if text.is_empty() {
+                return;
+            }
+            headings.push(Heading {
+                level,
+                text,
+                id: node.element_id(),
+            });
+        });
+        headings
+    }
+
+    /// Title, meta tags, canonical link and OpenGraph properties, with URLs
+    /// resolved against the document's base URL or `base`
+    pub fn metadata(&self, base: &Url) -> PageMetadata {
+        let base = self.base_url.as_ref().unwrap_or(base);
+        let mut metadata = PageMetadata::default();
+        let title = self.get_title();
+        metadata.title = Some(clean_text(&title)).filter(|title| !title.is_empty());
+
+        walk(&self.document_node_handle, &mut |handle, tag| {
+            let Ok(node) = handle.read() else {
+                return;
+            };
+            let Some(element) = node.as_element() else {
+                return;
+            };
+            match tag {
+                "meta" => {
+                    let name = element
+                        .get_attribute("property")
+                        .or_else(|| element.get_attribute("name"));
+                    if let (Some(name), Some(content)) = (name, element.get_attribute("content")) {
+                        let name = name.trim().to_ascii_lowercase();
+                        let content = content.trim().to_string();
+                        if !name.is_empty() && !content.is_empty() {
+                            metadata.meta.push(MetaTag { name, content });
+                        }
+                    }
+                }
+                "link" if metadata.canonical.is_none() => {
+                    let canonical = rel_tokens(element.get_attribute("rel").as_deref())
+                        .iter()
+                        .any(|rel| rel == "canonical");
+                    if canonical {
+                        metadata.canonical = element
+                            .get_attribute("href")
+                            .and_then(|href| resolve(base, &href))
+                            .filter(|url| matches!(url.scheme(), "http" | "https"));
+                    }
+                }
+                _ => {}
+            }
+        });
+
+        let first = |name: &str| metadata.get(name).map(str::to_string);
+        let url = |name: &str| {
+            metadata
+                .get(name)
+                .and_then(|value| resolve(base, value))
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+        };
+        let open_graph = OpenGraph {
+            title: first("og:title"),
+            description: first("og:description"),
+            kind: first("og:type"),
+            site_name: first("og:site_name"),
+            url: url("og:url"),
+            image: url("og:image").or_else(|| url("og:image:url")),
+        };
+        let description = first("description");
+        let author = first("author");
+        let keywords = metadata
+            .get("keywords")
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        metadata.open_graph = open_graph;
+        metadata.description = description;
+        metadata.author = author;
+        metadata.keywords = keywords;
+        metadata
+    }
+
+    /// The page's headings, paragraphs and links in document order, for
+    /// rendering it as plain text. Link targets are resolved and filtered
+    /// like [`Self::links`].
+    pub fn text_blocks(&self, base: &Url) -> Vec<TextBlock> {
+        let base = self.base_url.as_ref().unwrap_or(base);
+        let mut blocks = Vec::new();
+        collect_text_blocks(&self.document_node_handle, base, false, &mut blocks);
+        blocks
+    }
+
+    /// Links, heading outline and metadata together
+    pub fn extract(&self, base: &Url) -> PageExtract {
+        PageExtract {
+            links: self.links(base),
+            headings: self.headings(),
+            metadata: self.metadata(base),
+        }
+    }
+}
+
+/// Visit every element below `handle` in document order with its lowercased
+/// tag name, skipping `<template>` contents. The node is not locked during
+/// the callback.
+pub(crate) fn walk(handle: &NodeHandle, visit: &mut dyn FnMut(&NodeHandle, &str)) {
+    let (tag, children) = match handle.read() {
+        Ok(node) => {
+            let tag = match &node.data {
+                NodeData::Element(element) => Some(element.local_name().to_ascii_lowercase()),
+                _ => None,
+            };
+            (tag, node.children.clone())
+        }
+        Err(_) => return,
+    };
+    if let Some(tag) = &tag {
+        visit(handle, tag);
+        if tag == "template" {
+            return;
+        }
+    }
+    for child in &children {
+        walk(child, visit);
+    }
+}
+
+/// Append the text blocks below `handle`. Inside a heading or paragraph,
+/// only links are added; their text is already part of the block.
+fn collect_text_blocks(
+    handle: &NodeHandle,
+    base: &Url,
+    in_block: bool,
+    blocks: &mut Vec<TextBlock>,
+) {
+    let Ok(node) = handle.read() else {
+        return;
+    };
+    let mut in_block = in_block;
+    match &node.data {
+        NodeData::Text(text) if !in_block => {
+            let text = collapse(text, MAX_BLOCK_LENGTH);
+            if !text.is_empty() {
+                blocks.push(TextBlock::Paragraph(text));
+            }
+        }
+        NodeData::Element(element) => {
+            let tag = element.local_name().to_ascii_lowercase();
+            if TEXTLESS_TAGS.contains(&tag.as_str()) {
+                return;
+            }
+            if tag == "a" || tag == "area" {
+                let url = element
+                    .get_attribute("href")
+                    .and_then(|href| resolve(base, &href))
+                    .filter(|url| LINK_SCHEMES.contains(&url.scheme()));
+                if let Some(url) = url {
+                    blocks.push(TextBlock::Link(PageLink {
+                        url,
+                        text: clean_text(&node.text_content()),
+                        rel: rel_tokens(element.get_attribute("rel").as_deref()),
+                        title: None,
+                    }));
+                    return;
+                }
+            }
+            if !in_block {
+                if let Some(level) = heading_level(&tag) {
+                    let text = clean_text(&node.text_content());
+                    if !text.is_empty() {
+                        blocks.push(TextBlock::Heading { level, text });
+                    }
+                    in_block = true;
+                } else if PARAGRAPH_TAGS.contains(&tag.as_str()) {
+                    let text = collapse(&node.text_content(), MAX_BLOCK_LENGTH);
+                    if !text.is_empty() {
+                        blocks.push(TextBlock::Paragraph(text));
+                    }
+                    in_block = true;
+                }
+            }
+        }
+        _ => {}
+    }
+    let children = node.children.clone();
+    drop(node);
+    for child in &children {
+        collect_text_blocks(child, base, in_block, blocks);
+    }
+}
+
+/// Heading level of an `h1`-`h6` tag
+fn heading_level(tag: &str) -> Option<u8> {
+    match tag {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// `href` resolved against `base`, ignoring empty and fragment-only
+/// references to the page itself
+fn resolve(base: &Url, href: &str) -> Option<Url> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') {
+        return None;
+    }
+    base.join(href).ok()
+}
+
+/// Lowercased, deduplicated tokens of a `rel` attribute
+fn rel_tokens(rel: Option<&str>) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for token in rel.unwrap_or_default().split_ascii_whitespace() {
+        let token = token.to_ascii_lowercase();
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// Whitespace collapsed and capped at [`MAX_TEXT_LENGTH`] characters
+fn clean_text(text: &str) -> String {
+    collapse(text, MAX_TEXT_LENGTH)
+}
+
+/// Whitespace collapsed and capped at `max_chars` characters
+fn collapse(text: &str, max_chars: usize) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= max_chars {
+        return collapsed;
+    }
+    collapsed.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_html;
+    use crate::security::SecurityContext;
+    use std::sync::Arc;
+
+    fn dom(html: &str) -> Dom {
+        parse_html(html, Arc::new(SecurityContext::new(10))).unwrap()
+    }
+
+    #[test]
+    fn test_links_are_resolved_and_filtered() {
+        let dom = dom(r##"<html><body>
+            <a href="/about" rel="NoFollow noopener nofollow" title=" About ">About   us</a>
+            <a href="https://other.example/x">Elsewhere</a>
+            <a href="#top">Top</a>
+            <a href="javascript:alert(1)">Evil</a>
+            <a href="mailto:me@example.com">Mail</a>
+            <a>No target</a>
+            </body></html>"##);
+        let base = Url::parse("https://example.com/blog/post").unwrap();
+        let links = dom.links(&base);
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].url.as_str(), "https://example.com/about");
+        assert_eq!(links[0].text, "About us");
+        assert_eq!(links[0].rel, vec!["nofollow", "noopener"]);
+        assert!(links[0].has_rel("nofollow"));
+        assert_eq!(links[0].title.as_deref(), Some("About"));
+        assert!(!links[0].is_external(&base));
+        assert!(links[1].is_external(&base));
+        assert_eq!(links[2].url.scheme(), "mailto");
+    }
+
+    #[test]
+    fn test_heading_outline() {
+        let dom = dom(
+            "<html><body><h1 id=\"intro\">Intro</h1><h3>  Deep\n detail </h3>\
+             <h2></h2><h2>Second</h2></body></html>",
+        );
+        let headings = dom.headings();
+        assert_eq!(
+            headings,
+            vec![
+                Heading {
+                    level: 1,
+                    text: "Intro".to_string(),
+                    id: Some("intro".to_string()),
+                },
+                Heading {
+                    level: 3,
+                    text: "Deep detail".to_string(),
+                    id: None,
+                },
+                Heading {
+                    level: 2,
+                    text: "Second".to_string(),
+                    id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_blocks_in_document_order() {
+        let dom = dom(r#"<html><head><title>Not body text</title></head><body>
+            <h1>Title</h1>
+            <p>Read <a href="/more">more</a> here.</p>
+            <div>Loose text<ul><li>One <p>nested</p></li></ul></div>
+            <a href="javascript:alert(1)">Evil</a>
+            </body></html>"#);
+        let base = Url::parse("https://example.com/").unwrap();
+        let blocks = dom.text_blocks(&base);
+
+        assert_eq!(
+            blocks[0],
+            TextBlock::Heading {
+                level: 1,
+                text: "Title".to_string()
+            }
+        );
+        assert_eq!(
+            blocks[1],
+            TextBlock::Paragraph("Read more here.".to_string())
+        );
+        assert!(
+            matches!(&blocks[2], TextBlock::Link(link) if link.url.as_str() == "https://example.com/more")
+        );
+        assert_eq!(blocks[3], TextBlock::Paragraph("Loose text".to_string()));
+        assert_eq!(blocks[4], TextBlock::Paragraph("One nested".to_string()));
+        assert_eq!(blocks.len(), 5, "{:?}", blocks);
+    }
+
+    #[test]
+    fn test_metadata_and_open_graph() {
+        let dom = dom(r#"<html><head>
+            <title> Plain title </title>
+            <meta name="Description" content="A page about things">
+            <meta name="keywords" content="rust, browser, , privacy">
+            <meta property="og:title" content="Shared title">
+            <meta property="og:image" content="/img/card.png">
+            <meta property="og:url" content="javascript:alert(1)">
+            <link rel="canonical" href="/post">
+            </head><body></body></html>"#);
+        let base = Url::parse("https://example.com/post?utm_source=x").unwrap();
+        let metadata = dom.metadata(&base);
+
+        assert_eq!(metadata.title.as_deref(), Some("Plain title"));
+        assert_eq!(metadata.description.as_deref(), Some("A page about things"));
+        assert_eq!(metadata.keywords, vec!["rust", "browser", "privacy"]);
+        assert_eq!(
+            metadata.canonical.as_ref().map(Url::as_str),
+            Some("https://example.com/post")
+        );
+        assert_eq!(metadata.display_title(), Some("Shared title"));
+        assert_eq!(metadata.display_description(), Some("A page about things"));
+        assert_eq!(
+            metadata.open_graph.image.as_ref().map(Url::as_str),
+            Some("https://example.com/img/card.png")
+        );
+        assert_eq!(metadata.open_graph.url, None);
+        assert_eq!(metadata.get("og:title"), Some("Shared title"));
+    }
+}
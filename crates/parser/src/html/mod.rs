@@ -1,15 +1,19 @@
 //! HTML parsing implementation for Citadel, focusing on security and privacy.
 
+mod stream;
 mod tree_sink;
 
+pub use stream::{HtmlStreamParser, SNAPSHOT_INTERVAL};
+
 // Re-export necessary types from html5ever
 use html5ever::{parse_document, tendril::TendrilSink};
 
+use crate::config::ParserConfig;
 use crate::dom::Dom;
 use crate::error::ParserError;
 use crate::metrics::DocumentMetrics;
 use crate::security::SecurityContext;
-use std::default::Default;
+use crate::UrlResolver;
 use std::io::Cursor;
 use std::sync::Arc;
 
@@ -19,10 +23,60 @@ pub fn parse_html(html: &str, security_context: Arc<SecurityContext>) -> Result<
     let html_sink = tree_sink::create_html_sink(security_context, metrics);
 
     // Use TendrilSink trait to parse HTML
-    let parser = parse_document(html_sink, Default::default());
+    let opts = html_sink.parse_opts();
+    let parser = parse_document(html_sink, opts);
+
+    // Parse the HTML - parser.one() fails only when the document broke a
+    // DOM limit of the security context in a failing parse mode
+    let (dom, _quirks_mode) = parser.one(html)?;
+    Ok(dom)
+}
+
+/// Parse an HTML string under a parser profile: comments are dropped unless
+/// the profile allows them, and attributes longer than its limit are left out.
+/// The profile's parse mode decides whether the DOM limits of the security
+/// context truncate the document or fail the parse.
+/// Documents over the profile's size limit are refused before parsing, and
+/// documents over its token limit fail once the tree builder has stopped.
+pub fn parse_html_with_config(
+    html: &str,
+    security_context: Arc<SecurityContext>,
+    config: &ParserConfig,
+) -> Result<Dom, ParserError> {
+    parse_with_sink(html, config, |metrics| {
+        tree_sink::create_html_sink(security_context, metrics).with_config(config)
+    })
+}
 
-    // Parse the HTML - parser.one() returns (Dom, QuirksMode) directly, not a Result
-    let (dom, _quirks_mode) = parser.one(html);
+/// [`parse_html_with_config`], with URL attributes rewritten through
+/// `resolver`: relative URLs become absolute, and URLs it blocks are
+/// dropped along with their attribute
+pub fn parse_html_with_resolver(
+    html: &str,
+    security_context: Arc<SecurityContext>,
+    config: &ParserConfig,
+    resolver: Arc<dyn UrlResolver + Send + Sync>,
+) -> Result<Dom, ParserError> {
+    parse_with_sink(html, config, |metrics| {
+        tree_sink::create_html_sink(security_context, metrics)
+            .with_config(config)
+            .with_url_resolver(resolver)
+    })
+}
+
+fn parse_with_sink(
+    html: &str,
+    config: &ParserConfig,
+    sink: impl FnOnce(Arc<DocumentMetrics>) -> tree_sink::HtmlTreeSink,
+) -> Result<Dom, ParserError> {
+    config.check_document_size(html)?;
+
+    let metrics = Arc::new(DocumentMetrics::new());
+    let html_sink = sink(metrics.clone());
+    let opts = html_sink.parse_opts();
+    let parser = parse_document(html_sink, opts);
+    let (dom, _quirks_mode) = parser.one(html)?;
+    config.check_token_count(metrics.total_tokens())?;
     Ok(dom)
 }
 
@@ -42,13 +96,15 @@ pub fn parse_html_from_reader<R: std::io::Read>(
     let mut cursor = Cursor::new(buffer);
 
     // Use parse_document from html5ever, providing our custom sink
-    let dom_result = parse_document(html_sink, Default::default())
+    let opts = html_sink.parse_opts();
+    let dom_result = parse_document(html_sink, opts)
         .from_utf8()
         .read_from(&mut cursor);
 
     // Handle potential errors from read_from
     match dom_result {
-        Ok((dom, _)) => Ok(dom),
+        Ok(Ok((dom, _))) => Ok(dom),
+        Ok(Err(e)) => Err(e),
         Err(e) => Err(ParserError::HtmlParseError(e.to_string())),
     }
 }
@@ -0,0 +1,199 @@
+//! Incremental HTML parsing
+//!
+//! [`HtmlStreamParser`] builds the DOM from chunks of bytes as they arrive
+//! instead of waiting for the whole document. Between chunks it hands out
+//! copies of the partial DOM, so the top of a large page can be laid out
+//! and painted while the rest is still downloading. Bytes are decoded as
+//! UTF-8, with a sequence split across chunks joined up and invalid bytes
+//! replaced.
+//!
+//! The parser profile applies as in [`parse_html_with_config`](super::parse_html_with_config):
+//! a document growing past the size limit fails the next [`feed`](HtmlStreamParser::feed),
+//! and the token limit is checked by [`finish`](HtmlStreamParser::finish).
+
+use std::sync::Arc;
+
+use html5ever::driver::Parser;
+use html5ever::parse_document;
+use html5ever::tendril::{ByteTendril, TendrilSink, Utf8LossyDecoder};
+
+use super::tree_sink::{self, HtmlTreeSink};
+use crate::config::ParserConfig;
+use crate::dom::Dom;
+use crate::error::{ParserError, ParserResult};
+use crate::metrics::DocumentMetrics;
+use crate::security::SecurityContext;
+use crate::UrlResolver;
+
+/// Bytes parsed between the partial DOMs [`HtmlStreamParser::take_snapshot`]
+/// hands out by default
+pub const SNAPSHOT_INTERVAL: usize = 16 * 1024;
+
+/// Parses an HTML document fed in chunks
+pub struct HtmlStreamParser {
+    parser: Utf8LossyDecoder<Parser<HtmlTreeSink>>,
+    metrics: Arc<DocumentMetrics>,
+    max_document_bytes: usize,
+    max_tokens: usize,
+    /// Bytes fed so far
+    bytes: usize,
+    /// `bytes` when the last snapshot was taken
+    snapshot_at: usize,
+    snapshot_interval: usize,
+}
+
+impl HtmlStreamParser {
+    /// A parser for a document under `config`
+    pub fn new(security_context: Arc<SecurityContext>, config: &ParserConfig) -> Self {
+        Self::with_sink(config, |metrics| {
+            tree_sink::create_html_sink(security_context, metrics).with_config(config)
+        })
+    }
+
+    /// A parser that also rewrites URL attributes through `resolver`, as
+    /// [`parse_html_with_resolver`](super::parse_html_with_resolver) does
+    pub fn with_resolver(
+        security_context: Arc<SecurityContext>,
+        config: &ParserConfig,
+        resolver: Arc<dyn UrlResolver + Send + Sync>,
+    ) -> Self {
+        Self::with_sink(config, |metrics| {
+            tree_sink::create_html_sink(security_context, metrics)
+                .with_config(config)
+                .with_url_resolver(resolver)
+        })
+    }
+
+    fn with_sink(
+        config: &ParserConfig,
+        sink: impl FnOnce(Arc<DocumentMetrics>) -> HtmlTreeSink,
+    ) -> Self {
+        let metrics = Arc::new(DocumentMetrics::new());
+        let html_sink = sink(metrics.clone());
+        let opts = html_sink.parse_opts();
+        Self {
+            parser: parse_document(html_sink, opts).from_utf8(),
+            metrics,
+            max_document_bytes: config.max_document_bytes,
+            max_tokens: config.max_tokens,
+            bytes: 0,
+            snapshot_at: 0,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+        }
+    }
+
+    /// Hand out partial DOMs every `bytes` parsed instead of every
+    /// [`SNAPSHOT_INTERVAL`]
+    pub fn with_snapshot_interval(mut self, bytes: usize) -> Self {
+        self.snapshot_interval = bytes;
+        self
+    }
+
+    /// Parse the next chunk of the document. Fails once the document is
+    /// larger than the profile allows; the chunk is then left out.
+    pub fn feed(&mut self, chunk: &[u8]) -> ParserResult<()> {
+        let bytes = self.bytes.saturating_add(chunk.len());
+        if bytes > self.max_document_bytes {
+            return Err(ParserError::DocumentTooLarge(bytes));
+        }
+        self.bytes = bytes;
+        // After a DOM limit failed the parse nothing more is attached
+        if !chunk.is_empty() && !self.sink().has_failed() {
+            self.parser.process(ByteTendril::from_slice(chunk));
+        }
+        Ok(())
+    }
+
+    /// Bytes fed so far
+    pub fn bytes_fed(&self) -> usize {
+        self.bytes
+    }
+
+    /// A copy of the DOM built so far. Elements still open hold the content
+    /// parsed up to now.
+    pub fn snapshot(&self) -> Dom {
+        self.sink().dom().snapshot()
+    }
+
+    /// A copy of the DOM built so far, if the snapshot interval was parsed
+    /// since the last one taken
+    pub fn take_snapshot(&mut self) -> Option<Dom> {
+        if self.bytes - self.snapshot_at < self.snapshot_interval.max(1) {
+            return None;
+        }
+        self.snapshot_at = self.bytes;
+        Some(self.snapshot())
+    }
+
+    /// Parse what remains and return the complete DOM. After a refused
+    /// chunk this is the document up to it.
+    pub fn finish(self) -> ParserResult<Dom> {
+        let (dom, _quirks_mode) = self.parser.finish()?;
+        if self.metrics.total_tokens() > self.max_tokens {
+            return Err(ParserError::TooManyTokens(self.max_tokens));
+        }
+        Ok(dom)
+    }
+
+    fn sink(&self) -> &HtmlTreeSink {
+        &self.parser.inner_sink.tokenizer.sink.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::parse_html_with_config;
+
+    fn context() -> Arc<SecurityContext> {
+        Arc::new(SecurityContext::new(32))
+    }
+
+    #[test]
+    fn test_chunks_build_the_same_dom_with_partial_snapshots() {
+        let html = "<html><head><title>Stream</title></head><body>\
+                    <h1 id=\"top\">Caf\u{e9}</h1><p id=\"first\">Above the fold</p>\
+                    <p id=\"second\">Below</p></body></html>";
+        let config = ParserConfig::default();
+        let mut parser = HtmlStreamParser::new(context(), &config).with_snapshot_interval(1);
+
+        let mut snapshots = Vec::new();
+        // Seven-byte chunks split the two-byte é
+        for chunk in html.as_bytes().chunks(7) {
+            parser.feed(chunk).unwrap();
+            if let Some(snapshot) = parser.take_snapshot() {
+                snapshots.push(snapshot);
+            }
+        }
+        assert_eq!(parser.bytes_fed(), html.len());
+        assert!(snapshots.len() > 1);
+        let early = snapshots
+            .iter()
+            .find(|dom| dom.get_element_by_id("first").is_some())
+            .unwrap();
+        assert!(early.get_element_by_id("second").is_none());
+
+        let dom = parser.finish().unwrap();
+        let whole = parse_html_with_config(html, context(), &config).unwrap();
+        assert_eq!(dom.get_text_content(), whole.get_text_content());
+        assert!(dom.get_text_content().contains("Café"));
+        assert!(dom.get_element_by_id("second").is_some());
+        // Snapshots do not change as parsing goes on
+        assert!(early.get_element_by_id("second").is_none());
+    }
+
+    #[test]
+    fn test_document_size_limit_applies_across_chunks() {
+        let config = ParserConfig {
+            max_document_bytes: 16,
+            ..ParserConfig::default()
+        };
+        let mut parser = HtmlStreamParser::new(context(), &config);
+        parser.feed(b"<p>0123456789").unwrap();
+        assert!(matches!(
+            parser.feed(b"abcdef"),
+            Err(ParserError::DocumentTooLarge(19))
+        ));
+        assert!(parser.finish().is_ok());
+    }
+}
@@ -5,8 +5,8 @@
 
 use html5ever::{
     tendril::StrTendril,
-    tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink},
-    Attribute as HtmlAttribute, QualName,
+    tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeBuilderOpts, TreeSink},
+    Attribute as HtmlAttribute, ParseOpts, QualName,
 };
 use markup5ever::ExpandedName;
 use std::borrow::Cow;
@@ -19,10 +19,15 @@ lazy_static::lazy_static! {
     static ref EMPTY_LOCAL_NAME: markup5ever::LocalName = markup5ever::LocalName::from("");
 }
 
+use crate::config::ParserConfig;
+use crate::css::resolve_css_urls;
 use crate::dom::metrics::DomMetrics;
 use crate::dom::{Attribute, Dom, NodeBuilder, NodeHandle};
+use crate::error::ParserError;
 use crate::metrics::DocumentMetrics;
+use crate::security::policy::is_url_attribute;
 use crate::security::SecurityContext;
+use crate::{document_base, ParseMode, SecurityLevel, UrlResolver};
 
 /// Minimal working TreeSink implementation for html5ever
 ///
@@ -35,14 +40,46 @@ pub struct HtmlTreeSink {
     node_builder: Arc<NodeBuilder>,
     /// Security context for policy enforcement
     security_context: Arc<SecurityContext>,
-    /// Document parsing metrics (unused in minimal implementation)
-    _doc_metrics: Arc<DocumentMetrics>,
+    /// Document parsing metrics, shared with the caller for the token count
+    doc_metrics: Arc<DocumentMetrics>,
     /// Document quirks mode
     quirks_mode: QuirksMode,
     /// Document root handle
     document_handle: NodeHandle,
     /// Map of node handles to their element names (CRITICAL for html5ever)
     element_names: HashMap<usize, QualName>,
+    /// Longest attribute value kept; `None` keeps all
+    max_attr_length: Option<usize>,
+    /// Tokens built into the DOM; later ones are dropped. `None` keeps all
+    max_tokens: Option<usize>,
+    /// Whether comments make it into the DOM
+    allow_comments: bool,
+    /// Whether `style` attributes are dropped even when the policy allows them
+    strip_styles: bool,
+    /// Rewrites URL attributes; without one they are kept as written
+    url_resolver: Option<Arc<dyn UrlResolver + Send + Sync>>,
+    /// Security level `<base href>` is checked at
+    security_level: SecurityLevel,
+    /// Whether a `<base href>` was seen; only the first one counts, even
+    /// when it is rejected
+    base_href_seen: bool,
+    /// Nodes handed to html5ever that are never attached. Holding them keeps
+    /// their addresses from being reused by later nodes.
+    dropped: HashMap<usize, NodeHandle>,
+    /// `<noscript>` elements left out of the DOM because scripts are off,
+    /// mapped to the node that takes their children instead
+    promoted: HashMap<usize, NodeHandle>,
+    /// Depth of every attached element; the document is at depth 0
+    depths: HashMap<usize, usize>,
+    /// Elements attached so far
+    elements: usize,
+    /// Bytes of text attached so far
+    text_bytes: usize,
+    /// Whether DOM limits truncate the document or fail the parse
+    parse_mode: ParseMode,
+    /// The first DOM limit broken, when the parse mode fails on limits.
+    /// Nothing is attached after it.
+    limit_error: Option<ParserError>,
     /// Next ID for handles (using pointer addresses as unique IDs)
     #[allow(dead_code)] // Will be used when implementing unique node ID generation
     next_id: usize,
@@ -60,46 +97,310 @@ impl HtmlTreeSink {
             dom,
             node_builder,
             security_context,
-            _doc_metrics: doc_metrics,
+            doc_metrics,
             quirks_mode: QuirksMode::NoQuirks,
             document_handle,
             element_names: HashMap::new(),
+            max_attr_length: None,
+            max_tokens: None,
+            allow_comments: true,
+            strip_styles: false,
+            url_resolver: None,
+            security_level: SecurityLevel::default(),
+            base_href_seen: false,
+            dropped: HashMap::new(),
+            promoted: HashMap::new(),
+            depths: HashMap::new(),
+            elements: 0,
+            text_bytes: 0,
+            parse_mode: ParseMode::default(),
+            limit_error: None,
             next_id: 1,
         }
     }
 
+    /// Apply a parser profile's comment policy, attribute length limit,
+    /// token limit and parse mode. The strict levels also drop inline styles.
+    pub fn with_config(mut self, config: &ParserConfig) -> Self {
+        self.max_attr_length = Some(config.max_attr_length);
+        self.max_tokens = Some(config.max_tokens);
+        self.allow_comments = config.allow_comments;
+        self.strip_styles = matches!(
+            config.security_level,
+            SecurityLevel::Maximum | SecurityLevel::High
+        );
+        self.parse_mode = config.parse_mode;
+        self.security_level = config.security_level;
+        self
+    }
+
+    /// Rewrite URL attributes through `resolver`
+    pub fn with_url_resolver(mut self, resolver: Arc<dyn UrlResolver + Send + Sync>) -> Self {
+        self.url_resolver = Some(resolver);
+        self
+    }
+
+    /// html5ever options for this sink. With scripts off the tree builder
+    /// parses `<noscript>` content as markup rather than raw text, so the
+    /// fallback can be promoted into the DOM.
+    pub fn parse_opts(&self) -> ParseOpts {
+        ParseOpts {
+            tree_builder: TreeBuilderOpts {
+                scripting_enabled: self.security_context.allows_scripts(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// The DOM built so far
+    pub fn dom(&self) -> &Dom {
+        &self.dom
+    }
+
+    /// Whether a DOM limit failed the parse; nothing more is attached
+    pub fn has_failed(&self) -> bool {
+        self.limit_error.is_some()
+    }
+
+    /// Whether `handle` is a `<noscript>` whose children stand in for it
+    fn is_promoted_noscript(&self, handle: &NodeHandle) -> bool {
+        !self.security_context.allows_scripts()
+            && self
+                .element_names
+                .get(&self.get_handle_id(handle))
+                .is_some_and(|name| name.local.as_ref() == "noscript")
+    }
+
+    /// The node that takes children appended to `parent`: promoted
+    /// `<noscript>` elements pass theirs on to their own parent
+    fn child_target(&self, parent: &NodeHandle) -> NodeHandle {
+        let mut target = parent.clone();
+        while let Some(next) = self.promoted.get(&self.get_handle_id(&target)) {
+            target = next.clone();
+        }
+        target
+    }
+
+    /// Leave a `<noscript>` out of the DOM; its children go to `target`
+    fn promote_noscript(&mut self, noscript: &NodeHandle, target: &NodeHandle) {
+        if self.node_depth(target).is_some() && self.limit_error.is_none() {
+            self.promoted
+                .insert(self.get_handle_id(noscript), target.clone());
+        } else {
+            self.drop_node(noscript);
+        }
+    }
+
+    /// Record a broken DOM limit; only fails the parse in a mode that does
+    fn exceed(&mut self, error: ParserError) {
+        self.dom.truncated = true;
+        if self.parse_mode.fails_on_limits() && self.limit_error.is_none() {
+            self.limit_error = Some(error);
+        }
+    }
+
+    /// Whether `child` may be attached at `depth`, checking the nesting
+    /// depth and element count of the security context. Refused nodes are
+    /// dropped along with everything later appended to them.
+    fn admit_node(&mut self, child: &NodeHandle, depth: Option<usize>) -> bool {
+        let child_id = self.get_handle_id(child);
+        if self.dropped.contains_key(&child_id) {
+            return false;
+        }
+        let Some(depth) = depth.filter(|_| self.limit_error.is_none()) else {
+            self.drop_node(child);
+            return false;
+        };
+        if !self.element_names.contains_key(&child_id) {
+            return true;
+        }
+
+        let max_depth = self.security_context.max_nesting_depth();
+        let max_elements = self.security_context.max_elements();
+        if depth > max_depth {
+            self.exceed(ParserError::NestingTooDeep(max_depth));
+        } else if self.depths.contains_key(&child_id) {
+            // Moved by the tree builder; it was counted when first attached
+            self.depths.insert(child_id, depth);
+            return true;
+        } else if self.elements >= max_elements {
+            self.exceed(ParserError::ResourceLimitExceeded(format!(
+                "more than {} elements",
+                max_elements
+            )));
+        } else {
+            self.elements += 1;
+            self.depths.insert(child_id, depth);
+            return true;
+        }
+        self.drop_node(child);
+        false
+    }
+
+    /// Depth a node appended to `parent` takes, or `None` when the parent
+    /// was dropped
+    fn child_depth(&self, parent: &NodeHandle) -> Option<usize> {
+        self.node_depth(parent).map(|depth| depth + 1)
+    }
+
+    /// Depth of an attached node, or `None` when it was dropped. Nodes
+    /// the sink never placed (the document, template contents) are roots.
+    fn node_depth(&self, node: &NodeHandle) -> Option<usize> {
+        let id = self.get_handle_id(node);
+        if self.dropped.contains_key(&id) {
+            return None;
+        }
+        Some(self.depths.get(&id).copied().unwrap_or(0))
+    }
+
+    /// The part of `text` that fits the security context's text budget
+    fn admit_text(&mut self, text: &str) -> Option<String> {
+        if self.limit_error.is_some() {
+            return None;
+        }
+        let max_text_bytes = self.security_context.max_text_bytes();
+        let budget = max_text_bytes.saturating_sub(self.text_bytes);
+        if text.len() <= budget {
+            self.text_bytes += text.len();
+            return Some(text.to_string());
+        }
+
+        self.exceed(ParserError::ResourceLimitExceeded(format!(
+            "more than {} bytes of text",
+            max_text_bytes
+        )));
+        if self.limit_error.is_some() {
+            return None;
+        }
+        let mut end = budget;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.text_bytes += end;
+        Some(text[..end].to_string()).filter(|text| !text.is_empty())
+    }
+
+    /// Count a token; false once the token limit is passed, after which
+    /// nothing more is built into the DOM
+    fn count_token(&self) -> bool {
+        let tokens = self.doc_metrics.increment_tokens();
+        self.max_tokens.is_none_or(|limit| tokens <= limit)
+    }
+
+    /// Hand html5ever a node that is never attached
+    fn drop_node(&mut self, handle: &NodeHandle) {
+        self.dropped
+            .insert(self.get_handle_id(handle), handle.clone());
+    }
+
     /// Get a unique ID for a handle (using Arc pointer address)
     fn get_handle_id(&self, handle: &NodeHandle) -> usize {
         Arc::as_ptr(handle) as *const _ as usize
     }
 
-    /// Convert html5ever attributes to Citadel attributes with security filtering
-    fn convert_attributes(&self, attrs: Vec<HtmlAttribute>) -> Vec<Attribute> {
+    /// Convert html5ever attributes to Citadel attributes with security
+    /// filtering: attributes must be allowed on `element`, URL values must
+    /// pass the scheme check and are rewritten through the URL resolver
+    fn convert_attributes(&self, element: &str, attrs: Vec<HtmlAttribute>) -> Vec<Attribute> {
+        let policy = self.security_context.policy();
         attrs
             .into_iter()
             .filter_map(|attr| {
                 let attr_name = attr.name.local.as_ref();
 
+                let too_long = self
+                    .max_attr_length
+                    .is_some_and(|limit| attr.value.len() > limit);
+                let stripped = self.strip_styles && attr_name == "style";
+
                 // Apply security filtering
-                if self.security_context.is_attribute_allowed(attr_name) {
-                    Some(Attribute {
-                        name: attr.name,
-                        value: attr.value.to_string(),
-                    })
-                } else {
-                    None
+                if too_long
+                    || stripped
+                    || !policy.is_attribute_value_allowed_on(element, attr_name, &attr.value)
+                {
+                    return None;
                 }
+                let value = if is_url_attribute(attr_name) {
+                    self.rewrite_url(&attr.value)?
+                } else if attr_name == "style" {
+                    self.rewrite_style(&attr.value)
+                } else {
+                    attr.value.to_string()
+                };
+                Some(Attribute {
+                    name: attr.name,
+                    value,
+                })
             })
             .collect()
     }
+
+    /// The absolute form of a URL attribute value, or `None` when the
+    /// resolver blocks it or it does not resolve. Relative values resolve
+    /// against the document's `<base href>` before the resolver's base.
+    fn rewrite_url(&self, value: &str) -> Option<String> {
+        let url = match (&self.dom.base_url, &self.url_resolver) {
+            (Some(base), _) => base.join(value.trim()).ok()?,
+            (None, Some(resolver)) => resolver.resolve(value.trim()).ok()?,
+            (None, None) => return Some(value.to_string()),
+        };
+        let allowed = self.security_context.policy().is_url_allowed(url.as_str());
+        let blocked = self
+            .url_resolver
+            .as_ref()
+            .is_some_and(|resolver| resolver.should_block(&url));
+        (allowed && !blocked).then(|| url.into())
+    }
+
+    /// `url()` references in an inline style, resolved like URL attributes
+    fn rewrite_style(&self, value: &str) -> String {
+        let base = match (&self.dom.base_url, &self.url_resolver) {
+            (Some(base), _) => Some(base),
+            (None, Some(resolver)) => resolver.document_url(),
+            (None, None) => None,
+        };
+        match base {
+            Some(base) => resolve_css_urls(value, base),
+            None => value.to_string(),
+        }
+    }
+
+    /// Record the document's base URL and target from a `<base>` element.
+    /// Read before attribute filtering, since `<base>` itself never makes
+    /// it into the DOM with its attributes.
+    fn read_base(&mut self, attrs: &[HtmlAttribute]) {
+        let attribute = |name: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.name.local.as_ref() == name)
+                .map(|attr| attr.value.trim().to_string())
+        };
+        if !self.base_href_seen {
+            if let Some(href) = attribute("href") {
+                self.base_href_seen = true;
+                let document_url = self
+                    .url_resolver
+                    .as_ref()
+                    .and_then(|resolver| resolver.document_url());
+                self.dom.base_url = document_base(document_url, &href, self.security_level);
+            }
+        }
+        if self.dom.base_target.is_none() {
+            self.dom.base_target = attribute("target").filter(|target| !target.is_empty());
+        }
+    }
 }
 
 impl TreeSink for HtmlTreeSink {
-    type Output = (Dom, QuirksMode);
+    type Output = Result<(Dom, QuirksMode), ParserError>;
     type Handle = NodeHandle;
 
     fn finish(self) -> Self::Output {
-        (self.dom, self.quirks_mode)
+        match self.limit_error {
+            Some(error) => Err(error),
+            None => Ok((self.dom, self.quirks_mode)),
+        }
     }
 
     fn parse_error(&mut self, _msg: Cow<'static, str>) {
@@ -152,11 +453,14 @@ impl TreeSink for HtmlTreeSink {
         _flags: ElementFlags,
     ) -> Self::Handle {
         let tag_name = name.local.as_ref();
+        if tag_name == "base" {
+            self.read_base(&attrs);
+        }
 
         // For parsing compatibility, create ALL elements but apply security filtering to content
         // This prevents html5ever parsing errors while maintaining security
         let safe_attrs = if self.security_context.is_element_allowed(tag_name) {
-            self.convert_attributes(attrs)
+            self.convert_attributes(tag_name, attrs)
         } else {
             // For blocked elements, strip all attributes to minimize attack surface
             Vec::new()
@@ -171,6 +475,9 @@ impl TreeSink for HtmlTreeSink {
                 // CRITICAL: Store the element name for elem_name() method
                 let handle_id = self.get_handle_id(&handle);
                 self.element_names.insert(handle_id, name);
+                if !self.count_token() {
+                    self.drop_node(&handle);
+                }
                 handle
             }
             Err(_) => {
@@ -186,7 +493,13 @@ impl TreeSink for HtmlTreeSink {
     }
 
     fn create_comment(&mut self, text: StrTendril) -> Self::Handle {
-        self.node_builder.comment(text.to_string())
+        if self.allow_comments && self.count_token() {
+            return self.node_builder.comment(text.to_string());
+        }
+        // html5ever needs a handle back; an empty one that is never attached
+        let handle = self.node_builder.comment(String::new());
+        self.drop_node(&handle);
+        handle
     }
 
     fn create_pi(&mut self, target: StrTendril, data: StrTendril) -> Self::Handle {
@@ -195,15 +508,27 @@ impl TreeSink for HtmlTreeSink {
     }
 
     fn append(&mut self, parent: &Self::Handle, child: NodeOrText<Self::Handle>) {
+        let parent = &self.child_target(parent);
         match child {
             NodeOrText::AppendNode(child_handle) => {
-                self.dom.append_child(parent, child_handle);
+                if self.is_promoted_noscript(&child_handle) {
+                    self.promote_noscript(&child_handle, parent);
+                    return;
+                }
+                let depth = self.child_depth(parent);
+                if self.admit_node(&child_handle, depth) {
+                    self.dom.append_child(parent, child_handle);
+                }
             }
             NodeOrText::AppendText(text) => {
                 // For parsing compatibility, allow text content but apply minimal sanitization
                 // More comprehensive sanitization happens at render time
-                let text_str = text.to_string();
-                self.dom.append_text(parent, text_str);
+                if self.node_depth(parent).is_none() || !self.count_token() {
+                    return;
+                }
+                if let Some(text_str) = self.admit_text(&text) {
+                    self.dom.append_text(parent, text_str);
+                }
             }
         }
     }
@@ -215,11 +540,24 @@ impl TreeSink for HtmlTreeSink {
     ) {
         match new_node {
             NodeOrText::AppendNode(node_handle) => {
-                self.dom.insert_before(sibling, node_handle);
+                if self.is_promoted_noscript(&node_handle) {
+                    // Nodes inserted before a sibling land under the document
+                    let document = self.document_handle.clone();
+                    self.promote_noscript(&node_handle, &document);
+                    return;
+                }
+                let depth = self.node_depth(sibling);
+                if self.admit_node(&node_handle, depth) {
+                    self.dom.insert_before(sibling, node_handle);
+                }
             }
             NodeOrText::AppendText(text) => {
-                let text_str = text.to_string();
-                self.dom.insert_text_before(sibling, &text_str);
+                if self.node_depth(sibling).is_none() || !self.count_token() {
+                    return;
+                }
+                if let Some(text_str) = self.admit_text(&text) {
+                    self.dom.insert_text_before(sibling, &text_str);
+                }
             }
         }
     }
@@ -250,7 +588,12 @@ impl TreeSink for HtmlTreeSink {
     }
 
     fn add_attrs_if_missing(&mut self, target: &Self::Handle, attrs: Vec<HtmlAttribute>) {
-        let safe_attrs = self.convert_attributes(attrs);
+        let element = self
+            .element_names
+            .get(&self.get_handle_id(target))
+            .map(|name| name.local.to_string())
+            .unwrap_or_default();
+        let safe_attrs = self.convert_attributes(&element, attrs);
 
         if let Some(mut node_guard) = self.dom.get_node_mut(target) {
             if let Some(current_attrs) = node_guard.element_attributes_mut() {
@@ -0,0 +1,650 @@
+//! The mirror DOM page scripts see, and the way back into the Rust DOM.
+//!
+//! Scripts never touch the parsed [`Dom`] directly. The host takes a bounded
+//! [`DocumentSnapshot`] of it, the cage builds a JS mirror from the snapshot
+//! ([`install_dom`]) with the usual DOM API — `document.createElement`,
+//! `appendChild`, `setAttribute`, `innerText` and the rest — and once scripts
+//! and the event loop are done, [`write_back`] replaces the document's content
+//! with the mirror's, so layout sees what the scripts did.
+//!
+//! What comes back is untrusted and goes through the same sanitizer rules as
+//! parsed markup: elements keep only attributes the policy allows on them,
+//! event handlers and disallowed URL schemes are dropped, and elements the
+//! policy blocks are kept bare and counted as blocked, exactly as the parser
+//! keeps them. Styles carrying `url()` are dropped, since no URL resolver
+//! checks them here. A snapshot the limits cut short is never written back: the
+//! document would lose whatever did not fit. Nothing is written either when the
+//! scripts left the tree as they found it; when something is, the document's
+//! comments (which the mirror does not carry) are gone.
+
+use boa_engine::property::Attribute as JsAttribute;
+use boa_engine::{js_string, Context, JsNativeError, JsResult, JsValue, NativeFunction, Source};
+use html5ever::{namespace_url, ns, LocalName, QualName};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use crate::dom::{Attribute, Dom, NodeBuilder, NodeData, NodeHandle};
+use crate::security::SecurityContext;
+use crate::selector::SelectorList;
+
+/// Most nodes a snapshot holds
+const SNAPSHOT_MAX_NODES: usize = 8000;
+/// Deepest a snapshot (and what is written back) nests
+const SNAPSHOT_MAX_DEPTH: usize = 64;
+/// Longest text node a snapshot holds, in characters
+const SNAPSHOT_TEXT_CAP: usize = 16384;
+
+/// Hard cap on live JS DOM nodes a page may create, so a hostile script cannot
+/// exhaust memory via `createElement`/`appendChild` loops (the loop-iteration
+/// limit alone wouldn't bound per-node allocation). Availability is a security
+/// property.
+const DOM_MAX_LIVE_NODES: usize = 20000;
+
+/// Authored, sandboxed **mirror DOM** for page scripts.
+///
+/// Seeded from a bounded JSON snapshot of the already-parsed (and sanitized) Rust
+/// DOM, passed in via `__CITADEL_DOM_JSON__`. It is a *mirror*: scripts get a
+/// real, mutable DOM API (query/read/mutate, `document`, `window`, events) and it
+/// is internally consistent. Once scripts and the event loop are done, the host
+/// reads the tree back through `__citadelExportDom__` and writes it into the
+/// Rust DOM ([`write_back`]). Canvas creation delegates to the prior
+/// (fingerprint-poisoned) `document` so `createElement('canvas')` stays
+/// poisoned; such canvases are not written back.
+///
+/// Selectors are parsed natively by [`crate::selector`] (through
+/// `__citadelParseSelector`, removed once captured) and matched by the shim, so
+/// `querySelector`, `matches` and `closest` take the same selectors as the Rust
+/// DOM; invalid ones throw a `SyntaxError`. Interaction-state pseudo-classes
+/// never match.
+///
+/// Deliberate limits (documented, not hidden): `innerHTML` is get-only (set falls back to text — no HTML sub-parser in the
+/// cage); rAF/idle callbacks are no-ops (timers are the event loop's); window
+/// metrics are normalized (uniform) values.
+const DOM_SHIM: &str = r##"
+(function (MAX_NODES, MAX_DEPTH) {
+  var TREE;
+  try { TREE = JSON.parse(globalThis.__CITADEL_DOM_JSON__ || ""); }
+  catch (e) { TREE = { tag: "#document", children: [] }; }
+  try { delete globalThis.__CITADEL_DOM_JSON__; } catch (e2) {}
+  var priorDoc = globalThis.document; // fingerprint-poisoned canvas vehicle (M4)
+  var nodeCount = 0;
+
+  function splitWs(s) { return String(s).split(/\s+/).filter(Boolean); }
+  function hasClass(el, c) { return el.nodeType === 1 && splitWs(el._attrs["class"] || "").indexOf(c) >= 0; }
+  function textOf(n) {
+    if (n.nodeType === 3) { return n.data; }
+    var s = ""; for (var i = 0; i < n.childNodes.length; i++) { s += textOf(n.childNodes[i]); } return s;
+  }
+  function detach(c) {
+    var p = c.parentNode; if (!p) { return; }
+    var i = p.childNodes.indexOf(c); if (i >= 0) { p.childNodes.splice(i, 1); } c.parentNode = null;
+  }
+  function sibling(el, dir, elementOnly) {
+    var p = el.parentNode; if (!p) { return null; }
+    for (var j = p.childNodes.indexOf(el) + dir; j >= 0 && j < p.childNodes.length; j += dir) {
+      if (!elementOnly || p.childNodes[j].nodeType === 1) { return p.childNodes[j]; }
+    }
+    return null;
+  }
+  function fire(target, type, ev) {
+    if (!type) { return true; }
+    ev = ev || { type: type }; if (!ev.target) { ev.target = target; } ev.currentTarget = target;
+    var a = target._listeners && target._listeners[type];
+    if (a) { var copy = a.slice(); for (var i = 0; i < copy.length; i++) { try { copy[i].call(target, ev); } catch (e) {} } }
+    var on = target["on" + type];
+    if (typeof on === "function") { try { on.call(target, ev); } catch (e3) {} }
+    return true;
+  }
+
+  function makeText(text) {
+    return { nodeType: 3, nodeName: "#text", data: String(text), nodeValue: String(text),
+             childNodes: [], children: [], parentNode: null, get textContent() { return this.data; } };
+  }
+
+  // Selectors are parsed natively (crate::selector) and matched here against
+  // the mirror tree; parsed selectors are cached per source string.
+  var parseNative = globalThis.__citadelParseSelector;
+  try { delete globalThis.__citadelParseSelector; } catch (e4) {}
+  var selectorCache = {}, selectorCacheSize = 0;
+  function parseSelector(sel) {
+    sel = String(sel);
+    if (!Object.prototype.hasOwnProperty.call(selectorCache, sel)) {
+      if (++selectorCacheSize > 256) { selectorCache = {}; selectorCacheSize = 1; }
+      selectorCache[sel] = JSON.parse(parseNative(sel));
+    }
+    return selectorCache[sel];
+  }
+  function parentEl(el) { var p = el.parentNode; return p && p.nodeType === 1 ? p : null; }
+  function prevEl(el) { return sibling(el, -1, true); }
+  function matchAttr(el, a) {
+    if (!Object.prototype.hasOwnProperty.call(el._attrs, a.name)) { return false; }
+    if (!a.operator) { return true; }
+    var v = el._attrs[a.name], x = a.operator[1];
+    if (a.case_insensitive) { v = v.toLowerCase(); x = x.toLowerCase(); }
+    switch (a.operator[0]) {
+      case "equals": return v === x;
+      case "includes": return x !== "" && !/\s/.test(x) && splitWs(v).indexOf(x) >= 0;
+      case "dash_match": return v === x || v.indexOf(x + "-") === 0;
+      case "prefix": return x !== "" && v.indexOf(x) === 0;
+      case "suffix": return x !== "" && v.length >= x.length && v.slice(v.length - x.length) === x;
+      case "substring": return x !== "" && v.indexOf(x) >= 0;
+    }
+    return false;
+  }
+  function position(el, dir, ofType) {
+    var n = 1;
+    for (var s = sibling(el, dir, true); s; s = sibling(s, dir, true)) { if (!ofType || s.localName === el.localName) { n++; } }
+    return n;
+  }
+  function nth(f, p) { var d = p - f.b; return f.a === 0 ? d === 0 : d % f.a === 0 && d / f.a >= 0; }
+  function matchPseudo(el, pc) {
+    if (pc === "root") { return !parentEl(el); }
+    if (pc === "empty") { return !el.childNodes.some(function (c) { return c.nodeType === 1 || (c.nodeType === 3 && c.data !== ""); }); }
+    var k = Object.keys(pc)[0], v = pc[k];
+    switch (k) {
+      case "nth_child": return nth(v, position(el, -1, false));
+      case "nth_last_child": return nth(v, position(el, 1, false));
+      case "nth_of_type": return nth(v, position(el, -1, true));
+      case "nth_last_of_type": return nth(v, position(el, 1, true));
+      case "not": return !matchList(el, v);
+      case "is": return matchList(el, v);
+    }
+    return false; // interaction states: nothing is hovered or focused in the mirror
+  }
+  function matchCompound(el, c) {
+    if (el.nodeType !== 1 || c.pseudo_element) { return false; }
+    if (c.tag !== null && el.localName !== c.tag) { return false; }
+    var i;
+    for (i = 0; i < c.ids.length; i++) { if ((el._attrs.id || "") !== c.ids[i]) { return false; } }
+    for (i = 0; i < c.classes.length; i++) { if (!hasClass(el, c.classes[i])) { return false; } }
+    for (i = 0; i < c.attributes.length; i++) { if (!matchAttr(el, c.attributes[i])) { return false; } }
+    for (i = 0; i < c.pseudo_classes.length; i++) { if (!matchPseudo(el, c.pseudo_classes[i])) { return false; } }
+    return true;
+  }
+  function matchFrom(el, sel, i) {
+    if (!matchCompound(el, sel.compounds[i])) { return false; }
+    if (i === 0) { return true; }
+    var comb = sel.combinators[i - 1], n;
+    if (comb === "child") { n = parentEl(el); return !!n && matchFrom(n, sel, i - 1); }
+    if (comb === "next_sibling") { n = prevEl(el); return !!n && matchFrom(n, sel, i - 1); }
+    var step = comb === "descendant" ? parentEl : prevEl;
+    for (n = step(el); n; n = step(n)) { if (matchFrom(n, sel, i - 1)) { return true; } }
+    return false;
+  }
+  function matchList(el, list) {
+    for (var p = 0; p < list.length; p++) { if (matchFrom(el, list[p], list[p].compounds.length - 1)) { return true; } }
+    return false;
+  }
+  function matchesSel(el, sel) { return matchList(el, parseSelector(sel)); }
+  function descend(root, pred, firstOnly) {
+    var out = [];
+    (function walk(n) {
+      for (var i = 0; i < n.childNodes.length; i++) {
+        var c = n.childNodes[i];
+        if (c.nodeType === 1) {
+          if (pred(c)) { out.push(c); if (firstOnly) { return; } }
+          walk(c); if (firstOnly && out.length) { return; }
+        }
+      }
+    })(root);
+    return out;
+  }
+  function query(root, sel, firstOnly) {
+    var list = parseSelector(sel);
+    return descend(root, function (el) { return matchList(el, list); }, firstOnly);
+  }
+
+  function makeElement(tag) {
+    if (++nodeCount > MAX_NODES) { throw new Error("Citadel DOM node budget exceeded"); }
+    var lname = String(tag).toLowerCase();
+    var el = {
+      nodeType: 1, tagName: String(tag).toUpperCase(), nodeName: String(tag).toUpperCase(),
+      localName: lname, _attrs: {}, childNodes: [], parentNode: null, _listeners: {}, _style: {}
+    };
+    function def(name, get, set) { Object.defineProperty(el, name, { get: get, set: set, configurable: true }); }
+    def("children", function () { return el.childNodes.filter(function (n) { return n.nodeType === 1; }); });
+    def("childElementCount", function () { return el.children.length; });
+    def("firstChild", function () { return el.childNodes[0] || null; });
+    def("lastChild", function () { return el.childNodes[el.childNodes.length - 1] || null; });
+    def("firstElementChild", function () { return el.children[0] || null; });
+    def("parentElement", function () { return el.parentNode && el.parentNode.nodeType === 1 ? el.parentNode : null; });
+    def("nextSibling", function () { return sibling(el, 1, false); });
+    def("previousSibling", function () { return sibling(el, -1, false); });
+    def("nextElementSibling", function () { return sibling(el, 1, true); });
+    def("previousElementSibling", function () { return sibling(el, -1, true); });
+    def("id", function () { return el._attrs.id || ""; }, function (v) { el._attrs.id = String(v); });
+    def("className", function () { return el._attrs["class"] || ""; }, function (v) { el._attrs["class"] = String(v); });
+    def("attributes", function () { return Object.keys(el._attrs).map(function (k) { return { name: k, value: el._attrs[k] }; }); });
+    def("style", function () { return el._style; });
+    def("classList", function () {
+      function lst() { return splitWs(el._attrs["class"] || ""); }
+      function save(a) { el._attrs["class"] = a.join(" "); }
+      return {
+        add: function () { var a = lst(); for (var i = 0; i < arguments.length; i++) { if (a.indexOf(arguments[i]) < 0) { a.push(arguments[i]); } } save(a); },
+        remove: function () { var a = lst(); for (var i = 0; i < arguments.length; i++) { var k = a.indexOf(arguments[i]); if (k >= 0) { a.splice(k, 1); } } save(a); },
+        toggle: function (c) { var a = lst(), k = a.indexOf(c); if (k >= 0) { a.splice(k, 1); save(a); return false; } a.push(c); save(a); return true; },
+        contains: function (c) { return lst().indexOf(c) >= 0; },
+        item: function (i) { return lst()[i] || null; }
+      };
+    });
+    def("textContent", function () { return textOf(el); }, function (v) { el.childNodes = [makeText(v)]; el.childNodes[0].parentNode = el; });
+    def("innerText", function () { return textOf(el); }, function (v) { el.childNodes = [makeText(v)]; el.childNodes[0].parentNode = el; });
+    def("innerHTML", function () { return serializeHTML(el); }, function (v) { el.childNodes = [makeText(v)]; el.childNodes[0].parentNode = el; });
+    def("outerHTML", function () { return openTag(el) + serializeHTML(el) + "</" + el.localName + ">"; });
+    def("value", function () { return el._attrs.value || ""; }, function (v) { el._attrs.value = String(v); });
+
+    el.getAttribute = function (n) { n = String(n).toLowerCase(); return Object.prototype.hasOwnProperty.call(el._attrs, n) ? el._attrs[n] : null; };
+    el.setAttribute = function (n, v) { el._attrs[String(n).toLowerCase()] = String(v); };
+    el.setAttributeNS = function (ns, n, v) { el.setAttribute(n, v); };
+    el.removeAttribute = function (n) { delete el._attrs[String(n).toLowerCase()]; };
+    el.hasAttribute = function (n) { return Object.prototype.hasOwnProperty.call(el._attrs, String(n).toLowerCase()); };
+    el.appendChild = function (c) { detach(c); c.parentNode = el; el.childNodes.push(c); return c; };
+    el.append = function () { for (var i = 0; i < arguments.length; i++) { var a = arguments[i]; el.appendChild(typeof a === "string" ? makeText(a) : a); } };
+    el.removeChild = function (c) { var i = el.childNodes.indexOf(c); if (i >= 0) { el.childNodes.splice(i, 1); c.parentNode = null; } return c; };
+    el.remove = function () { detach(el); };
+    el.insertBefore = function (c, ref) { detach(c); var i = ref ? el.childNodes.indexOf(ref) : -1; if (i < 0) { el.childNodes.push(c); } else { el.childNodes.splice(i, 0, c); } c.parentNode = el; return c; };
+    el.replaceChild = function (nw, old) { var i = el.childNodes.indexOf(old); if (i >= 0) { detach(nw); el.childNodes[i] = nw; nw.parentNode = el; old.parentNode = null; } return old; };
+    el.cloneNode = function (deep) {
+      var c = makeElement(el.localName); Object.keys(el._attrs).forEach(function (k) { c._attrs[k] = el._attrs[k]; });
+      if (deep) { for (var i = 0; i < el.childNodes.length; i++) { var ch = el.childNodes[i]; c.appendChild(ch.nodeType === 3 ? makeText(ch.data) : ch.cloneNode(true)); } }
+      return c;
+    };
+    el.contains = function (n) { while (n) { if (n === el) { return true; } n = n.parentNode; } return false; };
+    el.getElementsByTagName = function (t) { t = String(t).toLowerCase(); return descend(el, function (n) { return t === "*" || n.localName === t; }, false); };
+    el.getElementsByClassName = function (c) { var cls = splitWs(c); return descend(el, function (n) { return cls.every(function (x) { return hasClass(n, x); }); }, false); };
+    el.querySelector = function (s) { return query(el, s, true)[0] || null; };
+    el.querySelectorAll = function (s) { return query(el, s, false); };
+    el.matches = function (s) { return matchesSel(el, s); };
+    el.closest = function (s) { var n = el; while (n && n.nodeType === 1) { if (matchesSel(n, s)) { return n; } n = n.parentNode; } return null; };
+    el.addEventListener = function (t, fn) { (el._listeners[t] = el._listeners[t] || []).push(fn); };
+    el.removeEventListener = function (t, fn) { var a = el._listeners[t]; if (a) { var i = a.indexOf(fn); if (i >= 0) { a.splice(i, 1); } } };
+    el.dispatchEvent = function (ev) { return fire(el, ev && ev.type, ev); };
+    el.click = function () { fire(el, "click", { type: "click", target: el }); };
+    el.focus = function () {}; el.blur = function () {};
+    el.getBoundingClientRect = function () { return { x: 0, y: 0, top: 0, left: 0, right: 0, bottom: 0, width: 0, height: 0 }; };
+    return el;
+  }
+
+  function openTag(el) {
+    var s = "<" + el.localName;
+    Object.keys(el._attrs).forEach(function (k) { s += " " + k + '="' + el._attrs[k] + '"'; });
+    return s + ">";
+  }
+  function serializeHTML(el) {
+    var s = "";
+    for (var i = 0; i < el.childNodes.length; i++) {
+      var c = el.childNodes[i];
+      s += c.nodeType === 3 ? c.data : openTag(c) + serializeHTML(c) + "</" + c.localName + ">";
+    }
+    return s;
+  }
+
+  function build(node, parent) {
+    if (node.text !== undefined) { var t = makeText(node.text); t.parentNode = parent; return t; }
+    var el = makeElement(node.tag || "div");
+    if (node.attrs) { Object.keys(node.attrs).forEach(function (k) { el._attrs[String(k).toLowerCase()] = String(node.attrs[k]); }); }
+    el.parentNode = parent;
+    if (node.children) { for (var i = 0; i < node.children.length; i++) { var ch = build(node.children[i], el); if (ch) { el.childNodes.push(ch); } } }
+    return el;
+  }
+  function findTag(nodes, tag) {
+    for (var i = 0; i < nodes.length; i++) {
+      if (nodes[i].nodeType === 1 && nodes[i].localName === tag) { return nodes[i]; }
+      var r = findTag(nodes[i].childNodes || [], tag); if (r) { return r; }
+    }
+    return null;
+  }
+
+  var roots = [];
+  if (TREE.children) { for (var i = 0; i < TREE.children.length; i++) { roots.push(build(TREE.children[i], null)); } }
+  var elementRoots = roots.filter(function (n) { return n.nodeType === 1; });
+  var docEl = findTag(roots, "html") || elementRoots[0] || makeElement("html");
+  var headEl = findTag([docEl], "head") || makeElement("head");
+  var bodyEl = findTag([docEl], "body") || makeElement("body");
+
+  // ----- location (minimal parse; navigation is inert) --------------------
+  function parseURL(u) {
+    var m = /^([a-zA-Z][a-zA-Z0-9+.-]*:)\/\/([^\/:?#]+)(:[0-9]+)?([^?#]*)(\?[^#]*)?(#.*)?$/.exec(u || "") || [];
+    var protocol = m[1] || "https:", host = m[2] || "localhost", port = (m[3] || "").replace(":", "");
+    return {
+      href: u || "https://localhost/", protocol: protocol, hostname: host, host: host + (m[3] || ""),
+      port: port, pathname: m[4] || "/", search: m[5] || "", hash: m[6] || "",
+      origin: protocol + "//" + host + (m[3] || ""),
+      assign: function () {}, replace: function () {}, reload: function () {}, toString: function () { return this.href; }
+    };
+  }
+  var location = parseURL(TREE.url);
+
+  // ----- document ---------------------------------------------------------
+  var docListeners = {};
+  var document = {
+    nodeType: 9, nodeName: "#document", documentElement: docEl, head: headEl, body: bodyEl,
+    readyState: "complete", location: location, characterSet: "UTF-8", compatMode: "CSS1Compat",
+    getElementById: function (id) {
+      if ((docEl._attrs.id || "") === id) { return docEl; }
+      return descend(docEl, function (el) { return el._attrs.id === id; }, true)[0] || null;
+    },
+    getElementsByTagName: function (t) { return docEl.getElementsByTagName(t); },
+    getElementsByClassName: function (c) { return docEl.getElementsByClassName(c); },
+    querySelector: function (s) { return matchesSel(docEl, s) ? docEl : (query(docEl, s, true)[0] || null); },
+    querySelectorAll: function (s) { var r = query(docEl, s, false); if (matchesSel(docEl, s)) { r.unshift(docEl); } return r; },
+    createElement: function (t) {
+      if (String(t).toLowerCase() === "canvas" && priorDoc && typeof priorDoc.createElement === "function") {
+        return priorDoc.createElement("canvas"); // keep fingerprint-poisoned canvas
+      }
+      return makeElement(t);
+    },
+    createElementNS: function (ns, t) { return makeElement(t); },
+    createTextNode: function (t) { return makeText(t); },
+    createDocumentFragment: function () { var f = makeElement("#fragment"); f.nodeType = 11; return f; },
+    createComment: function (t) { var c = makeText(t); c.nodeType = 8; c.nodeName = "#comment"; return c; },
+    createEvent: function () { return { type: "", initEvent: function (t) { this.type = t; } }; },
+    addEventListener: function (t, fn) { (docListeners[t] = docListeners[t] || []).push(fn); },
+    removeEventListener: function (t, fn) { var a = docListeners[t]; if (a) { var i = a.indexOf(fn); if (i >= 0) { a.splice(i, 1); } } },
+    dispatchEvent: function (ev) { return fire({ _listeners: docListeners }, ev && ev.type, ev); }
+  };
+  Object.defineProperty(document, "cookie", { get: function () { return ""; }, set: function () {}, configurable: true });
+  Object.defineProperty(document, "title", {
+    get: function () { var t = findTag([headEl], "title"); return t ? textOf(t) : ""; },
+    set: function () {}, configurable: true
+  });
+  globalThis.document = document;
+
+  // ----- window (== globalThis) ; normalized metrics ----------------------
+  var win = globalThis;
+  win.window = win; win.self = win; win.top = win; win.parent = win; win.frames = win;
+  win.document = document; win.location = location; win.name = "";
+  win.innerWidth = 1920; win.innerHeight = 1080; win.outerWidth = 1920; win.outerHeight = 1080;
+  win.devicePixelRatio = 1; win.scrollX = 0; win.scrollY = 0; win.pageXOffset = 0; win.pageYOffset = 0;
+  var winListeners = {};
+  win.addEventListener = function (t, fn) { (winListeners[t] = winListeners[t] || []).push(fn); };
+  win.removeEventListener = function (t, fn) { var a = winListeners[t]; if (a) { var i = a.indexOf(fn); if (i >= 0) { a.splice(i, 1); } } };
+  win.dispatchEvent = function (ev) { return fire({ _listeners: winListeners }, ev && ev.type, ev); };
+  win.getComputedStyle = function (el) { return (el && el._style) || {}; };
+  win.matchMedia = function (q) { return { matches: false, media: String(q), addListener: function () {}, removeListener: function () {}, addEventListener: function () {}, removeEventListener: function () {} }; };
+  win.scrollTo = function () {}; win.scroll = function () {}; win.scrollBy = function () {};
+  win.alert = function () {}; win.confirm = function () { return false; }; win.prompt = function () { return null; };
+  win.open = function () { return null; }; win.close = function () {}; win.focus = function () {}; win.blur = function () {};
+  // Timers are the event loop's (already on globalThis); nothing is painted
+  // frame by frame, so rAF/idle callbacks stay inert.
+  win.requestAnimationFrame = function () { return 0; }; win.cancelAnimationFrame = function () {};
+  win.requestIdleCallback = function () { return 0; }; win.cancelIdleCallback = function () {};
+
+  // ----- fire ready events (called by the host after all scripts run) -----
+  globalThis.__citadelFireReady__ = function () {
+    document.readyState = "complete";
+    fire({ _listeners: docListeners }, "DOMContentLoaded", { type: "DOMContentLoaded", target: document });
+    fire({ _listeners: docListeners }, "readystatechange", { type: "readystatechange", target: document });
+    fire({ _listeners: winListeners }, "load", { type: "load", target: win });
+    fire({ _listeners: winListeners }, "DOMContentLoaded", { type: "DOMContentLoaded", target: win });
+  };
+
+  // ----- export (called by the host to write the tree back) ---------------
+  // Same shape as the snapshot it was built from. Fragments contribute their
+  // children, as appending one would; comments and foreign objects (the
+  // poisoned canvas) are left out. Deeper than the snapshot may go throws, and
+  // then nothing is written back.
+  function exportChildren(n, depth) {
+    if (depth > MAX_DEPTH) { throw new Error("Citadel DOM export too deep"); }
+    var out = [];
+    for (var i = 0; i < n.childNodes.length; i++) {
+      var c = n.childNodes[i];
+      if (c.nodeType === 3) { out.push({ text: String(c.data) }); }
+      else if (c.nodeType === 11) { out.push.apply(out, exportChildren(c, depth)); }
+      else if (c.nodeType === 1 && c._attrs) {
+        var attrs = {};
+        Object.keys(c._attrs).forEach(function (k) { attrs[k] = String(c._attrs[k]); });
+        out.push({ tag: c.localName, attrs: attrs, children: exportChildren(c, depth + 1) });
+      }
+    }
+    return out;
+  }
+  Object.defineProperty(globalThis, "__citadelExportDom__", {
+    value: function () {
+      var top = roots.length ? roots : [docEl];
+      return JSON.stringify({ tag: "#document", children: exportChildren({ childNodes: top }, 1) });
+    },
+    writable: false, enumerable: false, configurable: false
+  });
+})(NODECAP_PLACEHOLDER, DEPTH_PLACEHOLDER);
+"##;
+
+/// Install the sandboxed mirror DOM ([`DOM_SHIM`]) from a bounded JSON snapshot of
+/// the parsed document. The JSON is passed as a JS *string value* (not embedded in
+/// the shim source), so no escaping/injection is possible; the shim `JSON.parse`s
+/// it and deletes the global. Call this AFTER `bindings::install` (it
+/// delegates canvas creation to the fingerprint-poisoned `document` that
+/// `install` set up).
+pub fn install_dom(ctx: &mut Context, document_json: &str) -> JsResult<()> {
+    // Selector parsing for the shim; it answers with the parsed selector as JSON
+    let parse_selector = NativeFunction::from_fn_ptr(|_this, args, ctx| {
+        let source = args
+            .first()
+            .cloned()
+            .unwrap_or_default()
+            .to_string(ctx)?
+            .to_std_string_escaped();
+        let selectors = SelectorList::parse(&source)
+            .map_err(|e| JsNativeError::syntax().with_message(e.to_string()))?;
+        let json = serde_json::to_string(&selectors)
+            .map_err(|e| JsNativeError::error().with_message(e.to_string()))?;
+        Ok(JsValue::from(js_string!(json.as_str())))
+    });
+    ctx.register_global_callable(js_string!("__citadelParseSelector"), 1, parse_selector)?;
+    ctx.register_global_property(
+        js_string!("__CITADEL_DOM_JSON__"),
+        js_string!(document_json),
+        JsAttribute::all(),
+    )?;
+    let shim = DOM_SHIM
+        .replace("NODECAP_PLACEHOLDER", &DOM_MAX_LIVE_NODES.to_string())
+        .replace("DEPTH_PLACEHOLDER", &SNAPSHOT_MAX_DEPTH.to_string());
+    ctx.eval(Source::from_bytes(&shim))?;
+    Ok(())
+}
+
+/// A bounded JSON copy of a parsed document, for the mirror DOM
+#[derive(Debug, Clone)]
+pub struct DocumentSnapshot {
+    /// `{ "url", "tag":"#document", "children":[ {tag,attrs,children} | {text} ] }`
+    json: String,
+    /// The document's children as they were snapshotted
+    children: Value,
+    /// Whether the whole document fit within the limits
+    complete: bool,
+}
+
+impl DocumentSnapshot {
+    /// Snapshot `dom`, loaded from `url`
+    pub fn of(dom: &Dom, url: &str) -> Self {
+        let mut budget = SNAPSHOT_MAX_NODES;
+        let mut complete = true;
+        let children =
+            match snapshot_node(&dom.root(), SNAPSHOT_MAX_DEPTH, &mut budget, &mut complete) {
+                Some(Value::Object(mut document)) => document.remove("children"),
+                _ => None,
+            }
+            .unwrap_or_else(|| Value::Array(Vec::new()));
+        let mut document = Map::new();
+        document.insert("url".to_string(), Value::String(url.to_string()));
+        document.insert("tag".to_string(), Value::String("#document".to_string()));
+        document.insert("children".to_string(), children.clone());
+        let json = serde_json::to_string(&Value::Object(document))
+            .unwrap_or_else(|_| "{\"tag\":\"#document\",\"children\":[]}".to_string());
+        Self {
+            json,
+            children,
+            complete,
+        }
+    }
+
+    /// The snapshot as the JSON [`install_dom`] takes
+    pub fn json(&self) -> &str {
+        &self.json
+    }
+
+    /// Whether the whole document fit; only then can it be written back
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// `handle` as snapshot JSON; `complete` is cleared when anything is cut
+fn snapshot_node(
+    handle: &NodeHandle,
+    depth: usize,
+    budget: &mut usize,
+    complete: &mut bool,
+) -> Option<Value> {
+    if *budget == 0 || depth == 0 {
+        *complete = false;
+        return None;
+    }
+    let node = handle.read().ok()?;
+    *budget -= 1;
+    match &node.data {
+        NodeData::Document | NodeData::Element(_) => {
+            let mut children = Vec::new();
+            for child in node.children() {
+                if let Some(value) = snapshot_node(child, depth - 1, budget, complete) {
+                    children.push(value);
+                }
+            }
+            let mut map = Map::new();
+            if let NodeData::Element(element) = &node.data {
+                map.insert(
+                    "tag".to_string(),
+                    Value::String(element.local_name().to_ascii_lowercase()),
+                );
+                let attrs = element
+                    .attributes
+                    .iter()
+                    .map(|attr| {
+                        (
+                            attr.name.local.to_string(),
+                            Value::String(attr.value.clone()),
+                        )
+                    })
+                    .collect();
+                map.insert("attrs".to_string(), Value::Object(attrs));
+            } else {
+                map.insert("tag".to_string(), Value::String("#document".to_string()));
+            }
+            map.insert("children".to_string(), Value::Array(children));
+            Some(Value::Object(map))
+        }
+        NodeData::Text(text) => {
+            if text.chars().count() > SNAPSHOT_TEXT_CAP {
+                *complete = false;
+            }
+            let text: String = text.chars().take(SNAPSHOT_TEXT_CAP).collect();
+            let mut map = Map::new();
+            map.insert("text".to_string(), Value::String(text));
+            Some(Value::Object(map))
+        }
+        _ => None,
+    }
+}
+
+/// Write the mirror DOM in `ctx` back into `dom`, filtered through
+/// `security_context`'s sanitizer policy. The document's doctype stays; its
+/// other content is replaced. Returns whether anything changed: nothing is
+/// written when the scripts left the tree as snapshotted, when `snapshot` was
+/// cut short, or when the mirror cannot be read back.
+pub(crate) fn write_back(
+    ctx: &mut Context,
+    snapshot: &DocumentSnapshot,
+    dom: &Dom,
+    security_context: &Arc<SecurityContext>,
+) -> bool {
+    if !snapshot.complete {
+        return false;
+    }
+    let Ok(exported) = ctx.eval(Source::from_bytes("__citadelExportDom__()")) else {
+        return false;
+    };
+    let Some(json) = exported.as_string().map(|s| s.to_std_string_escaped()) else {
+        return false;
+    };
+    let Ok(Value::Object(mut document)) = serde_json::from_str::<Value>(&json) else {
+        return false;
+    };
+    let Some(Value::Array(children)) = document.remove("children") else {
+        return false;
+    };
+    if Value::Array(children.clone()) == snapshot.children {
+        return false;
+    }
+
+    let builder = NodeBuilder::new(dom.metrics.clone(), security_context.clone());
+    let nodes: Vec<NodeHandle> = children
+        .iter()
+        .filter_map(|child| build_node(child, &builder, security_context, SNAPSHOT_MAX_DEPTH))
+        .collect();
+    let root = dom.root();
+    let Ok(mut root) = root.write() else {
+        return false;
+    };
+    root.children.retain(|child| {
+        child
+            .read()
+            .is_ok_and(|child| matches!(child.data, NodeData::Doctype { .. }))
+    });
+    root.children.extend(nodes);
+    true
+}
+
+/// A node of the exported tree, sanitized; `None` for anything malformed
+fn build_node(
+    value: &Value,
+    builder: &NodeBuilder,
+    security_context: &SecurityContext,
+    depth: usize,
+) -> Option<NodeHandle> {
+    if depth == 0 {
+        return None;
+    }
+    if let Some(text) = value.get("text") {
+        let text: String = text.as_str()?.chars().take(SNAPSHOT_TEXT_CAP).collect();
+        return Some(builder.create_text_node(text));
+    }
+    let tag = value.get("tag")?.as_str()?.to_ascii_lowercase();
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    let attrs = if security_context.is_element_allowed(&tag) {
+        let policy = security_context.policy();
+        value
+            .get("attrs")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, value)| {
+                let value = value.as_str()?;
+                let url_in_style = name == "style" && value.to_ascii_lowercase().contains("url(");
+                (!url_in_style && policy.is_attribute_value_allowed_on(&tag, name, value)).then(
+                    || Attribute {
+                        name: QualName::new(None, ns!(), LocalName::from(name.as_str())),
+                        value: value.to_string(),
+                    },
+                )
+            })
+            .collect()
+    } else {
+        // Blocked elements keep no attributes, as when parsed
+        Vec::new()
+    };
+    let name = QualName::new(None, ns!(html), LocalName::from(tag.as_str()));
+    let node = builder.create_element_node(name, attrs).ok()?;
+    let children: Vec<NodeHandle> = value
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| build_node(child, builder, security_context, depth - 1))
+        .collect();
+    if let Ok(mut element) = node.write() {
+        element.children = children;
+    }
+    Some(node)
+}
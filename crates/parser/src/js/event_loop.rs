@@ -0,0 +1,213 @@
+//! The page's event loop: timers, microtasks and promise jobs.
+//!
+//! Scripts run to completion first, as in a browser. Then the loop takes over:
+//! pending microtasks and promise reactions drain, then the earliest due timer
+//! runs, then microtasks drain again, and so on until nothing is left, the
+//! budget is spent or the tab goes away.
+//!
+//! Timers run on a **virtual clock**. Rendering cannot wait out a page's real
+//! delays, so the clock jumps straight to the next due timer: callbacks fire in
+//! the order and at the (virtual) times a browser would fire them, without the
+//! waiting. `performance.now()` is not affected — it stays the clamped real
+//! clock the timing binding installs.
+//!
+//! Every resource the loop hands out is bounded by an [`EventLoopBudget`]: how
+//! many timer callbacks run, how far the virtual clock may advance, how long
+//! the loop may take in real time, and how many timers may be pending at once.
+//! A [`Cancellation`] is checked between tasks, so a tab whose VM is terminated
+//! stops running its page's callbacks at the next one. A single callback is
+//! bounded by the engine's runtime limits, not by the loop; promise reactions
+//! are drained by the engine to completion after each task, as a browser does.
+//!
+//! A page with `fetch()` has its requests made before the next timer runs:
+//! they are network tasks, and the virtual clock does not advance for them.
+//! Requests keep being settled while the page's timers are paused.
+
+use boa_engine::{Context, JsResult, Source};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::fetch::FetchGate;
+
+/// Timers a page may have pending at once; `setTimeout` beyond it returns 0
+/// and schedules nothing.
+const MAX_PENDING_TIMERS: usize = 1000;
+/// Timer callbacks a foreground page may run after its scripts.
+pub const DEFAULT_MAX_TIMER_TASKS: usize = 1000;
+/// How far a foreground page's virtual clock may advance, in milliseconds.
+pub const DEFAULT_MAX_VIRTUAL_MS: u64 = 30_000;
+/// Real time a foreground page's event loop may take, in milliseconds.
+pub const DEFAULT_MAX_WALL_MS: u64 = 5_000;
+
+/// Authored timer shim. The timer table lives in a closure, out of the page's
+/// reach; the host drives it through `__citadelRunTimer__`, which is
+/// non-writable so the page cannot replace it. String handlers are refused
+/// (they are `eval` by another name). Intervals under 4 ms are clamped to 4 ms,
+/// as browsers clamp nested timers.
+const TIMER_SHIM: &str = r#"
+(function (MAX_PENDING) {
+  var timers = Object.create(null);
+  var pending = 0, nextId = 1, seq = 0, now = 0;
+  function schedule(fn, delay, args, repeat) {
+    if (typeof fn !== "function" || pending >= MAX_PENDING) { return 0; }
+    delay = Math.floor(Number(delay));
+    if (!(delay > 0)) { delay = 0; }
+    if (delay > 2147483647) { delay = 0; }
+    if (repeat && delay < 4) { delay = 4; }
+    var id = nextId++;
+    timers[id] = { id: id, fn: fn, args: args, delay: delay, due: now + delay, seq: seq++, repeat: repeat };
+    pending++;
+    return id;
+  }
+  function cancel(id) {
+    if (Object.prototype.hasOwnProperty.call(timers, id)) { delete timers[id]; pending--; }
+  }
+  globalThis.setTimeout = function (fn, delay) {
+    return schedule(fn, delay, Array.prototype.slice.call(arguments, 2), false);
+  };
+  globalThis.setInterval = function (fn, delay) {
+    return schedule(fn, delay, Array.prototype.slice.call(arguments, 2), true);
+  };
+  globalThis.clearTimeout = cancel;
+  globalThis.clearInterval = cancel;
+  globalThis.queueMicrotask = function (fn) {
+    if (typeof fn !== "function") { throw new TypeError("queueMicrotask: argument is not a function"); }
+    Promise.resolve().then(function () { fn(); });
+  };
+  // Run the earliest due timer if it is due by `limit`; false when none is.
+  Object.defineProperty(globalThis, "__citadelRunTimer__", {
+    value: function (limit) {
+      var next = null;
+      for (var k in timers) {
+        var t = timers[k];
+        if (next === null || t.due < next.due || (t.due === next.due && t.seq < next.seq)) { next = t; }
+      }
+      if (next === null || next.due > limit) { return false; }
+      now = next.due;
+      if (next.repeat) { next.due = now + next.delay; next.seq = seq++; } else { cancel(next.id); }
+      next.fn.apply(globalThis, next.args);
+      return true;
+    },
+    writable: false, enumerable: false, configurable: false
+  });
+})(MAX_PENDING_PLACEHOLDER);
+"#;
+
+/// What a page's event loop may spend after its scripts have run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventLoopBudget {
+    /// Whether timers fire at all; off for background tabs, whose pending
+    /// microtasks still drain.
+    pub timers: bool,
+    /// Timer callbacks that may run.
+    pub max_timer_tasks: usize,
+    /// How far the virtual clock may advance, in milliseconds.
+    pub max_virtual_ms: u64,
+    /// Real time the loop may take, in milliseconds.
+    pub max_wall_ms: u64,
+}
+
+impl Default for EventLoopBudget {
+    /// The foreground budget.
+    fn default() -> Self {
+        Self {
+            timers: true,
+            max_timer_tasks: DEFAULT_MAX_TIMER_TASKS,
+            max_virtual_ms: DEFAULT_MAX_VIRTUAL_MS,
+            max_wall_ms: DEFAULT_MAX_WALL_MS,
+        }
+    }
+}
+
+impl EventLoopBudget {
+    /// A budget whose timers stay paused, with `max_wall_ms` for microtasks.
+    pub fn paused(max_wall_ms: u64) -> Self {
+        Self {
+            timers: false,
+            max_wall_ms,
+            ..Self::default()
+        }
+    }
+}
+
+/// Tells the event loop to stop. Checked between tasks.
+#[derive(Clone)]
+pub struct Cancellation(Option<Arc<dyn Fn() -> bool + Send + Sync>>);
+
+impl Cancellation {
+    /// Never cancelled.
+    pub fn never() -> Self {
+        Self(None)
+    }
+
+    /// Cancelled once `check` returns true, e.g. once a tab's VM channel closed.
+    pub fn when(check: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(check)))
+    }
+
+    /// Whether the loop should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.as_ref().is_some_and(|check| check())
+    }
+}
+
+impl Default for Cancellation {
+    fn default() -> Self {
+        Self::never()
+    }
+}
+
+impl fmt::Debug for Cancellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cancellation")
+            .field(&self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Install `setTimeout`/`setInterval`, their `clear*` and `queueMicrotask`.
+pub(crate) fn install(ctx: &mut Context) -> JsResult<()> {
+    let shim = TIMER_SHIM.replace("MAX_PENDING_PLACEHOLDER", &MAX_PENDING_TIMERS.to_string());
+    ctx.eval(Source::from_bytes(&shim))?;
+    Ok(())
+}
+
+/// Run the loop until it is idle, `budget` is spent or `cancel` fires, making
+/// the page's `fetch()` requests through `fetches` when it has them. Returns
+/// the timer callbacks run. A callback that throws counts as run and does not
+/// stop the loop; its error is dropped unlogged (it can carry page data).
+pub(crate) fn run(
+    ctx: &mut Context,
+    budget: &EventLoopBudget,
+    cancel: &Cancellation,
+    mut fetches: Option<&mut FetchGate>,
+) -> usize {
+    if cancel.is_cancelled() {
+        return 0;
+    }
+    let started = Instant::now();
+    let wall = Duration::from_millis(budget.max_wall_ms);
+    // A rejected job is the page's own error, like a throwing callback
+    let _ = ctx.run_jobs();
+
+    let step = format!("__citadelRunTimer__({})", budget.max_virtual_ms);
+    let mut ran = 0;
+    while started.elapsed() < wall && !cancel.is_cancelled() {
+        if let Some(gate) = fetches.as_deref_mut() {
+            if gate.settle_pending(ctx) > 0 {
+                let _ = ctx.run_jobs();
+                continue;
+            }
+        }
+        if !budget.timers || ran >= budget.max_timer_tasks {
+            break;
+        }
+        match ctx.eval(Source::from_bytes(step.as_str())) {
+            Ok(value) if value.as_boolean() == Some(false) => break,
+            _ => ran += 1,
+        }
+        let _ = ctx.run_jobs();
+    }
+    ran
+}
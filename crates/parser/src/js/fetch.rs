@@ -0,0 +1,287 @@
+//! `fetch()` for page scripts, gated by policy and served by the host.
+//!
+//! Without a host fetcher the network gate's `fetch` stays in place and every
+//! call fails like a blocked request. With one ([`CitadelJSEngine::with_fetcher`]),
+//! the page gets a working `fetch` whose requests leave the cage only between
+//! event loop tasks, as network tasks in a browser do:
+//!
+//! 1. `fetch(url, init)` queues the request inside the authored shim and
+//!    returns a pending promise.
+//! 2. The event loop takes the queue ([`FetchGate::settle_pending`]) and
+//!    checks each request here, in Rust: HTTPS, same origin as the document,
+//!    no credentials in the URL, `GET` or `HEAD` without a body, at most
+//!    [`MAX_FETCHES_PER_PAGE`] per page. Only safelisted headers pass.
+//! 3. Allowed requests go to the [`ScriptFetcher`], which the embedder routes
+//!    through its own network policy (the tab's broker applies CSP
+//!    `connect-src`, blocklists and budgets). Bodies over
+//!    [`MAX_FETCH_RESPONSE_BYTES`] are refused, not truncated.
+//! 4. A response the fetcher was redirected to from another origin is
+//!    refused as well; the embedder's fetcher says where it ended up.
+//! 5. The promise settles with a `Response`-like object, or rejects with the
+//!    same `TypeError("Failed to fetch")` whatever the reason, so a page
+//!    cannot tell a policy refusal from a network failure.
+//!
+//! `XMLHttpRequest` stays inert.
+//!
+//! [`CitadelJSEngine::with_fetcher`]: super::CitadelJSEngine::with_fetcher
+
+use boa_engine::{Context, JsResult, Source};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use url::Url;
+
+/// Requests a page may make with `fetch()`; later ones fail
+pub const MAX_FETCHES_PER_PAGE: usize = 50;
+/// Largest response body handed to a page, in bytes
+pub const MAX_FETCH_RESPONSE_BYTES: usize = 1024 * 1024;
+/// Requests the shim queues between two event loop tasks; later ones fail
+const MAX_QUEUED_FETCHES: usize = 100;
+/// Request headers a page may set; the rest are dropped
+const SAFELISTED_HEADERS: [&str; 3] = ["accept", "accept-language", "content-language"];
+
+/// Authored fetch shim. The queue and the pending promises live in a closure,
+/// out of the page's reach; the host drains the queue through
+/// `__citadelTakeFetches__` and settles through `__citadelSettleFetch__`,
+/// both non-writable. `JSON.stringify` and `Promise` are captured before any
+/// page script runs.
+const FETCH_SHIM: &str = r#"
+(function (MAX_QUEUED) {
+  var stringify = JSON.stringify, parse = JSON.parse, P = Promise;
+  var queue = [], pending = Object.create(null), nextId = 1;
+  function failed() { return new TypeError("Failed to fetch"); }
+  function headerList(init) {
+    var list = [];
+    if (init && typeof init.forEach === "function" && !Array.isArray(init)) {
+      init.forEach(function (value, name) { list.push([String(name), String(value)]); });
+    } else if (Array.isArray(init)) {
+      for (var i = 0; i < init.length; i++) { list.push([String(init[i][0]), String(init[i][1])]); }
+    } else if (init && typeof init === "object") {
+      var keys = Object.keys(init);
+      for (var j = 0; j < keys.length; j++) { list.push([keys[j], String(init[keys[j]])]); }
+    }
+    return list;
+  }
+  function makeHeaders(list) {
+    var map = Object.create(null);
+    for (var i = 0; i < list.length; i++) { map[String(list[i][0]).toLowerCase()] = list[i][1]; }
+    return {
+      get: function (name) { var k = String(name).toLowerCase(); return k in map ? map[k] : null; },
+      has: function (name) { return String(name).toLowerCase() in map; },
+      forEach: function (fn, thisArg) { for (var k in map) { fn.call(thisArg, map[k], k, this); } }
+    };
+  }
+  function makeResponse(r) {
+    var used = false;
+    function body() {
+      if (used) { return P.reject(new TypeError("Body has already been consumed.")); }
+      used = true;
+      return P.resolve(r.body);
+    }
+    var response = {
+      ok: r.status >= 200 && r.status < 300,
+      status: r.status,
+      statusText: "",
+      url: r.url,
+      type: "basic",
+      redirected: r.redirected,
+      headers: makeHeaders(r.contentType ? [["content-type", r.contentType]] : []),
+      text: body,
+      json: function () { return body().then(function (text) { return parse(text); }); },
+      clone: function () { return makeResponse(r); }
+    };
+    Object.defineProperty(response, "bodyUsed", { get: function () { return used; } });
+    return response;
+  }
+  globalThis.fetch = function (input, init) {
+    init = init || {};
+    var url = (input && typeof input === "object" && "url" in input) ? input.url : input;
+    if (queue.length >= MAX_QUEUED) { return P.reject(failed()); }
+    var id = nextId++;
+    queue.push({
+      id: id,
+      url: String(url),
+      method: String(init.method || "GET").toUpperCase(),
+      headers: headerList(init.headers),
+      hasBody: init.body !== undefined && init.body !== null
+    });
+    return new P(function (resolve, reject) { pending[id] = { resolve: resolve, reject: reject }; });
+  };
+  Object.defineProperty(globalThis, "__citadelTakeFetches__", {
+    value: function () { var taken = queue; queue = []; return stringify(taken); },
+    writable: false, enumerable: false, configurable: false
+  });
+  // `r` is the response, or null for a failure.
+  Object.defineProperty(globalThis, "__citadelSettleFetch__", {
+    value: function (id, r) {
+      var p = pending[id];
+      if (!p) { return; }
+      delete pending[id];
+      if (r === null) { p.reject(failed()); } else { p.resolve(makeResponse(r)); }
+    },
+    writable: false, enumerable: false, configurable: false
+  });
+})(MAX_QUEUED_PLACEHOLDER);
+"#;
+
+/// A page's `fetch()` request, checked against the cage's policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchRequest {
+    /// Absolute HTTPS URL on the document's origin
+    pub url: Url,
+    /// `GET` or `HEAD`
+    pub method: String,
+    /// Safelisted headers the page set
+    pub headers: Vec<(String, String)>,
+    /// Largest body the page will be given; a fetcher may stop reading past it
+    pub max_bytes: usize,
+}
+
+/// What the host fetched for a page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+    /// Where redirects led, if the body is not from the request's URL
+    pub redirected_to: Option<Url>,
+}
+
+/// The host's side of `fetch()`: makes a checked request under the host's
+/// own network policy. Called between event loop tasks; blocks until the
+/// answer is in. An `Err` reaches the page as a plain network error, and its
+/// text does not reach the page at all. A fetcher that follows redirects
+/// says where they led in [`FetchResponse::redirected_to`].
+pub trait ScriptFetcher: Send + Sync {
+    fn fetch(&self, request: &FetchRequest) -> Result<FetchResponse, String>;
+}
+
+/// A request as the shim queued it
+#[derive(Debug, Deserialize)]
+struct QueuedFetch {
+    id: u64,
+    url: String,
+    method: String,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    #[serde(default, rename = "hasBody")]
+    has_body: bool,
+}
+
+/// A response as the shim settles it
+#[derive(Serialize)]
+struct SettledFetch<'a> {
+    status: u16,
+    url: &'a str,
+    redirected: bool,
+    #[serde(rename = "contentType")]
+    content_type: &'a str,
+    body: String,
+}
+
+/// One page's `fetch()`: its document, its fetcher and how many it has made
+pub(crate) struct FetchGate {
+    document: Url,
+    fetcher: Arc<dyn ScriptFetcher>,
+    started: usize,
+}
+
+impl FetchGate {
+    /// Install the fetch shim in `ctx`, in place of the network gate's
+    /// `fetch`, for the document at `document`
+    pub(crate) fn install(
+        ctx: &mut Context,
+        document: Url,
+        fetcher: Arc<dyn ScriptFetcher>,
+    ) -> JsResult<Self> {
+        let shim = FETCH_SHIM.replace("MAX_QUEUED_PLACEHOLDER", &MAX_QUEUED_FETCHES.to_string());
+        ctx.eval(Source::from_bytes(&shim))?;
+        Ok(Self {
+            document,
+            fetcher,
+            started: 0,
+        })
+    }
+
+    /// Check, make and settle every queued request. Returns how many were
+    /// settled; the caller drains the promise jobs they queued.
+    pub(crate) fn settle_pending(&mut self, ctx: &mut Context) -> usize {
+        let Ok(taken) = ctx.eval(Source::from_bytes("__citadelTakeFetches__()")) else {
+            return 0;
+        };
+        let Some(json) = taken.as_string().map(|s| s.to_std_string_escaped()) else {
+            return 0;
+        };
+        let Ok(queued) = serde_json::from_str::<Vec<QueuedFetch>>(&json) else {
+            return 0;
+        };
+        for fetch in &queued {
+            let settled = self
+                .check(fetch)
+                .and_then(|request| {
+                    let response = self.make(&request)?;
+                    let body = if request.method == "HEAD" {
+                        String::new()
+                    } else {
+                        String::from_utf8_lossy(&response.body).into_owned()
+                    };
+                    let served_from = response.redirected_to.as_ref();
+                    serde_json::to_string(&SettledFetch {
+                        status: response.status,
+                        url: served_from.unwrap_or(&request.url).as_str(),
+                        redirected: served_from.is_some(),
+                        content_type: &response.content_type,
+                        body,
+                    })
+                    .ok()
+                })
+                .unwrap_or_else(|| "null".to_string());
+            let settle = format!("__citadelSettleFetch__({}, {})", fetch.id, settled);
+            // A throwing handler is the page's own error
+            let _ = ctx.eval(Source::from_bytes(settle.as_str()));
+        }
+        queued.len()
+    }
+
+    /// The request `fetch` may make, if policy allows it
+    fn check(&self, fetch: &QueuedFetch) -> Option<FetchRequest> {
+        if self.started >= MAX_FETCHES_PER_PAGE {
+            return None;
+        }
+        if !matches!(fetch.method.as_str(), "GET" | "HEAD") || fetch.has_body {
+            return None;
+        }
+        let url = self.document.join(&fetch.url).ok()?;
+        if !self.same_origin(&url) || !url.username().is_empty() || url.password().is_some() {
+            return None;
+        }
+        let headers = fetch
+            .headers
+            .iter()
+            .filter(|(name, _)| SAFELISTED_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+            .cloned()
+            .collect();
+        Some(FetchRequest {
+            url,
+            method: fetch.method.clone(),
+            headers,
+            max_bytes: MAX_FETCH_RESPONSE_BYTES,
+        })
+    }
+
+    /// Hand `request` to the fetcher; `None` for a failure, an oversized
+    /// body or one a redirect fetched from another origin
+    fn make(&mut self, request: &FetchRequest) -> Option<FetchResponse> {
+        self.started += 1;
+        let response = self.fetcher.fetch(request).ok()?;
+        let stayed = response
+            .redirected_to
+            .as_ref()
+            .is_none_or(|url| self.same_origin(url));
+        (stayed && response.body.len() <= MAX_FETCH_RESPONSE_BYTES).then_some(response)
+    }
+
+    /// Whether `url` is HTTPS on the document's origin
+    fn same_origin(&self, url: &Url) -> bool {
+        url.scheme() == "https" && url.origin() == self.document.origin()
+    }
+}
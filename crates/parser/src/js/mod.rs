@@ -11,14 +11,28 @@
 //! (`SecurityContext::allows_scripts`), inside the per-tab ZK boundary.
 
 mod bindings;
+mod dom_bindings;
+mod event_loop;
+mod fetch;
 
 pub use bindings::PrivacyProfile;
+pub use dom_bindings::DocumentSnapshot;
+pub use event_loop::{
+    Cancellation, EventLoopBudget, DEFAULT_MAX_TIMER_TASKS, DEFAULT_MAX_VIRTUAL_MS,
+    DEFAULT_MAX_WALL_MS,
+};
+pub use fetch::{
+    FetchRequest, FetchResponse, ScriptFetcher, MAX_FETCHES_PER_PAGE, MAX_FETCH_RESPONSE_BYTES,
+};
 
+use crate::dom::Dom;
 use crate::error::{ParserError, ParserResult};
 use crate::security::SecurityContext;
 use boa_engine::{Context, JsValue, Source};
+use fetch::FetchGate;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use url::Url;
 
 /// Bound untrusted page JS so a runaway loop throws instead of hanging the
 /// renderer (Boa's default loop limit is unlimited). Generous enough for real
@@ -50,6 +64,12 @@ pub struct CitadelJSEngine {
     profile: PrivacyProfile,
     /// Whether the engine is running inside ZKVM isolation.
     zkvm_isolated: bool,
+    /// What the event loop may spend after scripts run.
+    event_loop: EventLoopBudget,
+    /// Stops the event loop, e.g. when the tab's VM is terminated.
+    cancellation: Cancellation,
+    /// Makes page scripts' `fetch()` requests; without it they all fail.
+    fetcher: Option<Arc<dyn ScriptFetcher>>,
     /// Total scripts executed.
     scripts_executed: AtomicU64,
     /// Security/eval errors observed.
@@ -65,6 +85,9 @@ impl CitadelJSEngine {
             security_context,
             profile: PrivacyProfile::normalized(),
             zkvm_isolated: false,
+            event_loop: EventLoopBudget::default(),
+            cancellation: Cancellation::never(),
+            fetcher: None,
             scripts_executed: AtomicU64::new(0),
             security_violations: AtomicU64::new(0),
             sandboxed_executions: AtomicU64::new(0),
@@ -85,22 +108,62 @@ impl CitadelJSEngine {
         Ok(())
     }
 
+    /// Run the event loop within `budget`, stopping early once `cancellation`
+    /// fires.
+    pub fn with_event_loop(mut self, budget: EventLoopBudget, cancellation: Cancellation) -> Self {
+        self.event_loop = budget;
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Let page scripts run against a document `fetch()` same-origin
+    /// resources through `fetcher` (see the `fetch` module for the rules).
+    /// Content scripts never get `fetch`.
+    pub fn with_fetcher(mut self, fetcher: Arc<dyn ScriptFetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
     /// Build a fresh, caged context with the privacy binding layer installed.
     ///
     /// Per-call isolation: every execution gets a new context. The context starts
     /// bare (no browser APIs) and we install only our authored, gated bindings.
     fn caged_context(&self) -> ParserResult<Context> {
+        let mut ctx = Self::bounded_context()?;
+        bindings::install(&mut ctx, &self.profile)
+            .map_err(|e| ParserError::JsError(format!("privacy binding install failed: {e}")))?;
+        Ok(ctx)
+    }
+
+    /// A context with the DoS guards set and only timers bound.
+    fn bounded_context() -> ParserResult<Context> {
         let mut ctx = Context::default();
         // DoS guard FIRST: bound CPU/stack before any untrusted code can run.
         ctx.runtime_limits_mut()
             .set_loop_iteration_limit(MAX_LOOP_ITERATIONS);
         ctx.runtime_limits_mut()
             .set_recursion_limit(MAX_RECURSION_DEPTH);
-        bindings::install(&mut ctx, &self.profile)
-            .map_err(|e| ParserError::JsError(format!("privacy binding install failed: {e}")))?;
+        event_loop::install(&mut ctx)
+            .map_err(|e| ParserError::JsError(format!("timer install failed: {e}")))?;
         Ok(ctx)
     }
 
+    /// Install `fetch()` for the document at `url` in a caged context, when
+    /// the engine has a fetcher and `url` is absolute.
+    fn install_fetch(&self, ctx: &mut Context, url: &str) -> ParserResult<Option<FetchGate>> {
+        let (Some(fetcher), Ok(document)) = (&self.fetcher, Url::parse(url)) else {
+            return Ok(None);
+        };
+        FetchGate::install(ctx, document, fetcher.clone())
+            .map(Some)
+            .map_err(|e| ParserError::JsError(format!("fetch install failed: {e}")))
+    }
+
+    /// Run the event loop until idle, within the engine's budget.
+    fn run_event_loop(&self, ctx: &mut Context, fetches: Option<&mut FetchGate>) -> usize {
+        event_loop::run(ctx, &self.event_loop, &self.cancellation, fetches)
+    }
+
     /// Evaluate each script in `ctx`, counting per-script results. Errors are
     /// caught and counted, never propagated (one broken script must not abort the
     /// page) and never logged (they can carry page data).
@@ -121,6 +184,26 @@ impl CitadelJSEngine {
         outcome
     }
 
+    /// Install the mirror DOM from `document_json` in `ctx`, run `scripts`
+    /// against it, fire the ready events and run the event loop.
+    fn run_against_document(
+        &self,
+        ctx: &mut Context,
+        document_json: &str,
+        scripts: &[String],
+        fetches: Option<&mut FetchGate>,
+    ) -> ParserResult<PageScriptOutcome> {
+        dom_bindings::install_dom(ctx, document_json)
+            .map_err(|e| ParserError::JsError(format!("DOM install failed: {e}")))?;
+        let mut outcome = self.run_in_context(ctx, scripts);
+        // Fire ready events to whatever listeners the scripts registered.
+        let _ = ctx.eval(Source::from_bytes(
+            "if(typeof __citadelFireReady__==='function'){__citadelFireReady__();}",
+        ));
+        outcome.timers_run = self.run_event_loop(ctx, fetches);
+        Ok(outcome)
+    }
+
     /// Run a page's inline scripts in a single shared caged context — a real page
     /// shares one global across its `<script>` tags, so later scripts see earlier
     /// ones' globals. No DOM is installed (use [`Self::run_page_scripts_with_document`]
@@ -130,12 +213,15 @@ impl CitadelJSEngine {
             return Ok(PageScriptOutcome::default());
         }
         let mut ctx = self.caged_context()?;
-        Ok(self.run_in_context(&mut ctx, scripts))
+        let mut outcome = self.run_in_context(&mut ctx, scripts);
+        outcome.timers_run = self.run_event_loop(&mut ctx, None);
+        Ok(outcome)
     }
 
     /// Like [`Self::run_page_scripts`], but first installs the sandboxed mirror
     /// DOM built from `document_json` (a bounded snapshot of the parsed document),
-    /// then fires `DOMContentLoaded`/`load` after all scripts run.
+    /// then fires `DOMContentLoaded`/`load` after all scripts run, before the
+    /// event loop.
     pub fn run_page_scripts_with_document(
         &self,
         document_json: &str,
@@ -145,19 +231,72 @@ impl CitadelJSEngine {
             return Ok(PageScriptOutcome::default());
         }
         let mut ctx = self.caged_context()?;
-        bindings::install_dom(&mut ctx, document_json)
-            .map_err(|e| ParserError::JsError(format!("DOM install failed: {e}")))?;
-        let outcome = self.run_in_context(&mut ctx, scripts);
-        // Fire ready events to whatever listeners the scripts registered.
-        let _ = ctx.eval(Source::from_bytes(
-            "if(typeof __citadelFireReady__==='function'){__citadelFireReady__();}",
-        ));
+        self.run_against_document(&mut ctx, document_json, scripts, None)
+    }
+
+    /// Run a page's inline scripts against `dom`, the parsed document loaded
+    /// from `url`, and write what they did to the mirror DOM back into it, so
+    /// layout shows the page as its scripts left it. With a fetcher, the
+    /// scripts may `fetch()` from `url`'s origin.
+    pub fn run_page_scripts_on_dom(
+        &self,
+        url: &str,
+        dom: &Dom,
+        scripts: &[String],
+    ) -> ParserResult<PageScriptOutcome> {
+        if !self.security_context.allows_scripts() {
+            return Ok(PageScriptOutcome::default());
+        }
+        let snapshot = DocumentSnapshot::of(dom, url);
+        let mut ctx = self.caged_context()?;
+        let mut fetches = self.install_fetch(&mut ctx, url)?;
+        let outcome =
+            self.run_against_document(&mut ctx, snapshot.json(), scripts, fetches.as_mut())?;
+        dom_bindings::write_back(&mut ctx, &snapshot, dom, &self.security_context);
+        Ok(outcome)
+    }
+
+    /// Run extension content scripts at document-ready against their own mirror
+    /// DOM built from `document_json`.
+    ///
+    /// The only capabilities a content script gets are the DOM and timers:
+    /// unlike page scripts, no navigator, network gate, storage or fingerprint
+    /// surface is installed, so there is nothing to exfiltrate through. Content scripts do
+    /// not share globals with the page's scripts.
+    pub fn run_content_scripts(
+        &self,
+        document_json: &str,
+        scripts: &[String],
+    ) -> ParserResult<PageScriptOutcome> {
+        if !self.security_context.allows_scripts() {
+            return Ok(PageScriptOutcome::default());
+        }
+        let mut ctx = Self::bounded_context()?;
+        self.run_against_document(&mut ctx, document_json, scripts, None)
+    }
+
+    /// Like [`Self::run_content_scripts`], but against `dom`, the parsed
+    /// document loaded from `url`, with the scripts' changes written back.
+    pub fn run_content_scripts_on_dom(
+        &self,
+        url: &str,
+        dom: &Dom,
+        scripts: &[String],
+    ) -> ParserResult<PageScriptOutcome> {
+        if !self.security_context.allows_scripts() {
+            return Ok(PageScriptOutcome::default());
+        }
+        let snapshot = DocumentSnapshot::of(dom, url);
+        let mut ctx = Self::bounded_context()?;
+        let outcome = self.run_against_document(&mut ctx, snapshot.json(), scripts, None)?;
+        dom_bindings::write_back(&mut ctx, &snapshot, dom, &self.security_context);
         Ok(outcome)
     }
 
     /// Evaluate one expression against a freshly built mirror DOM and return its
-    /// string value. For tests/tools that need to observe DOM behavior; the render
-    /// path uses [`Self::run_page_scripts_with_document`] (which returns counts).
+    /// string value, read once the event loop is idle. For tests/tools that need
+    /// to observe DOM behavior; the render path uses
+    /// [`Self::run_page_scripts_on_dom`] (which returns counts).
     pub fn evaluate_with_document(&self, document_json: &str, code: &str) -> ParserResult<String> {
         if !self.security_context.allows_scripts() {
             return Err(ParserError::SecurityViolation(
@@ -165,15 +304,20 @@ impl CitadelJSEngine {
             ));
         }
         let mut ctx = self.caged_context()?;
-        bindings::install_dom(&mut ctx, document_json)
+        dom_bindings::install_dom(&mut ctx, document_json)
             .map_err(|e| ParserError::JsError(format!("DOM install failed: {e}")))?;
         match ctx.eval(Source::from_bytes(code)) {
-            Ok(value) => Ok(js_value_to_string(&value, &mut ctx)),
+            Ok(value) => {
+                self.run_event_loop(&mut ctx, None);
+                Ok(js_value_to_string(&value, &mut ctx))
+            }
             Err(e) => Err(ParserError::JsError(format!("JS execution error: {e}"))),
         }
     }
 
-    /// Run the page's JS in the cage and return its result as a string.
+    /// Run the page's JS in the cage and return its result as a string, read
+    /// once the event loop is idle (so an object the code returns shows what
+    /// its timers and promises did to it).
     pub fn execute_simple(&mut self, code: &str) -> ParserResult<String> {
         if !self.security_context.allows_scripts() {
             return Err(ParserError::SecurityViolation(
@@ -185,6 +329,7 @@ impl CitadelJSEngine {
         match ctx.eval(Source::from_bytes(code)) {
             Ok(value) => {
                 self.scripts_executed.fetch_add(1, Ordering::Relaxed);
+                self.run_event_loop(&mut ctx, None);
                 Ok(js_value_to_string(&value, &mut ctx))
             }
             Err(e) => {
@@ -201,20 +346,26 @@ impl CitadelJSEngine {
         Ok(result)
     }
 
-    /// Run JS with DOM context. DOM bindings are not wired yet (future milestone);
-    /// for now this runs the script in the same caged context.
-    pub fn execute_browser_script(
-        &self,
-        code: &str,
-        _dom: &crate::dom::Dom,
-    ) -> ParserResult<String> {
+    /// Run JS against the mirror of `dom` and return its result as a string,
+    /// read once the event loop is idle. What the script did to the mirror is
+    /// written back into `dom`, even when it threw part way through. With a
+    /// fetcher, the script may `fetch()` from the document's origin.
+    pub fn execute_browser_script(&self, code: &str, dom: &Dom) -> ParserResult<String> {
         if !self.security_context.allows_scripts() {
             return Err(ParserError::SecurityViolation(
                 "JavaScript is disabled by security policy (explicit opt-in required)".to_string(),
             ));
         }
+        let url = dom.base_url.as_ref().map_or("", |url| url.as_str());
+        let snapshot = DocumentSnapshot::of(dom, url);
         let mut ctx = self.caged_context()?;
-        match ctx.eval(Source::from_bytes(code)) {
+        let mut fetches = self.install_fetch(&mut ctx, url)?;
+        dom_bindings::install_dom(&mut ctx, snapshot.json())
+            .map_err(|e| ParserError::JsError(format!("DOM install failed: {e}")))?;
+        let result = ctx.eval(Source::from_bytes(code));
+        self.run_event_loop(&mut ctx, fetches.as_mut());
+        dom_bindings::write_back(&mut ctx, &snapshot, dom, &self.security_context);
+        match result {
             Ok(value) => Ok(js_value_to_string(&value, &mut ctx)),
             Err(e) => Err(ParserError::JsError(format!("JS execution error: {e}"))),
         }
@@ -223,7 +374,7 @@ impl CitadelJSEngine {
     /// Run JS with secure DOM bindings (delegates to `execute_browser_script`).
     pub fn execute_with_secure_dom(
         &mut self,
-        dom: &crate::dom::Dom,
+        dom: &Dom,
         script: &str,
     ) -> Result<String, ParserError> {
         self.execute_browser_script(script, dom)
@@ -258,6 +409,8 @@ pub struct PageScriptOutcome {
     pub executed: usize,
     /// Scripts that threw (caught and counted, not propagated).
     pub errored: usize,
+    /// Timer callbacks the event loop ran after the scripts.
+    pub timers_run: usize,
 }
 
 /// Statistics for JavaScript engine usage.
@@ -627,6 +780,42 @@ mod tests {
             .contains("Hello"));
     }
 
+    #[test]
+    fn dom_mirror_matches_combinators_attributes_and_nth() {
+        let e = engine();
+        // Child, next-sibling and attribute selectors.
+        assert_eq!(
+            e.evaluate_with_document(
+                DOM_DOC,
+                "[document.querySelectorAll('body > h1 + p').length, \
+                 document.querySelectorAll('[class~=head]').length, \
+                 document.querySelectorAll('html > p').length].join()"
+            )
+            .unwrap(),
+            "1,1,0"
+        );
+        // Structural pseudo-classes, :not() and matches().
+        assert_eq!(
+            e.evaluate_with_document(
+                DOM_DOC,
+                "document.querySelector('body :nth-child(2)').tagName + \
+                 document.querySelector(':not(html, body, h1)').tagName + \
+                 document.getElementById('title').matches('h1:first-child')"
+            )
+            .unwrap(),
+            "PPtrue"
+        );
+        // Invalid selectors throw, as in browsers.
+        assert_eq!(
+            e.evaluate_with_document(
+                DOM_DOC,
+                "try { document.querySelector('p >'); 'no' } catch (err) { err.name }"
+            )
+            .unwrap(),
+            "SyntaxError"
+        );
+    }
+
     #[test]
     fn dom_events_window_and_canvas_delegation() {
         let e = engine();
@@ -663,4 +852,213 @@ mod tests {
             .unwrap()
             .starts_with("data:image/png;base64,"));
     }
+
+    #[test]
+    fn content_scripts_get_the_dom_and_nothing_else() {
+        let e = engine();
+        let scripts = vec![
+            "document.getElementById('title').textContent = 'Patched';".to_string(),
+            "if (document.getElementById('title').textContent !== 'Patched') { throw 1; }"
+                .to_string(),
+            "if (typeof fetch !== 'undefined' || typeof localStorage !== 'undefined' \
+             || typeof navigator !== 'undefined') { throw new Error('capability leak'); }"
+                .to_string(),
+            "document.addEventListener('DOMContentLoaded', function () {});".to_string(),
+        ];
+        let outcome = e.run_content_scripts(DOM_DOC, &scripts).unwrap();
+        assert_eq!(
+            outcome,
+            PageScriptOutcome {
+                executed: 4,
+                errored: 0,
+                timers_run: 0
+            }
+        );
+
+        let sc = SecurityContext::new(10); // scripts NOT enabled
+        let off = CitadelJSEngine::new(Arc::new(sc)).unwrap();
+        assert_eq!(
+            off.run_content_scripts(DOM_DOC, &scripts).unwrap(),
+            PageScriptOutcome::default()
+        );
+    }
+
+    #[test]
+    fn script_mutations_are_written_back_to_the_dom() {
+        let dom = crate::parse_html(
+            "<html><body><h1 id=\"title\">Hello</h1></body></html>",
+            Arc::new(SecurityContext::new(10)),
+        )
+        .unwrap();
+        let scripts = vec![
+            "var p = document.createElement('p'); p.setAttribute('class', 'added'); \
+             p.setAttribute('onclick', 'steal()'); p.innerText = 'Added by script'; \
+             document.body.appendChild(p);"
+                .to_string(),
+            "var a = document.createElement('a'); a.setAttribute('href', 'javascript:steal()'); \
+             a.textContent = 'link'; document.body.appendChild(a);"
+                .to_string(),
+            // Timers see the mirror too; their changes land as well
+            "setTimeout(function () { document.getElementById('title').innerText = 'Renamed'; }, 50);"
+                .to_string(),
+        ];
+        let outcome = engine()
+            .run_page_scripts_on_dom("https://x.example/", &dom, &scripts)
+            .unwrap();
+        assert_eq!(outcome.executed, 3);
+
+        let added = dom.query_selector("p.added").expect("appended element");
+        let added = added.read().unwrap();
+        assert!(added
+            .as_element()
+            .unwrap()
+            .get_attribute("onclick")
+            .is_none());
+        let link = dom.query_selector("a").expect("appended link");
+        assert!(!link
+            .read()
+            .unwrap()
+            .as_element()
+            .unwrap()
+            .has_attribute("href"));
+        let text = dom.get_text_content();
+        assert!(text.contains("Added by script"), "{text}");
+        assert!(
+            text.contains("Renamed") && !text.contains("Hello"),
+            "{text}"
+        );
+
+        // A script that throws part way still leaves what it did
+        let dom = crate::parse_html("<body></body>", Arc::new(SecurityContext::new(10))).unwrap();
+        let err = engine().execute_browser_script(
+            "document.body.appendChild(document.createElement('section')); missing();",
+            &dom,
+        );
+        assert!(err.is_err());
+        assert!(dom.query_selector("section").is_some());
+    }
+
+    #[test]
+    fn timers_microtasks_and_promises_run_in_order() {
+        let mut e = engine();
+        // Sync code first, then microtasks (queueMicrotask and promise
+        // reactions, in queueing order), then timers by due time; an interval
+        // runs until cleared and a cleared or string timer never runs.
+        assert_eq!(
+            e.execute_simple(
+                "var log = []; \
+                 setTimeout(function () { log.push('t20'); }, 20); \
+                 setTimeout(function (a) { log.push(a); \
+                   Promise.resolve().then(function () { log.push('p-in-t10'); }); }, 10, 't10'); \
+                 clearTimeout(setTimeout(function () { log.push('cleared'); }, 5)); \
+                 setTimeout(\"log.push('string')\", 1); \
+                 var n = 0, i = setInterval(function () { log.push('i' + (++n)); \
+                   if (n === 3) { clearInterval(i); } }, 6); \
+                 queueMicrotask(function () { log.push('m'); }); \
+                 Promise.resolve('p').then(function (v) { log.push(v); }); \
+                 log.push('sync'); log"
+            )
+            .unwrap(),
+            "sync,m,p,i1,t10,p-in-t10,i2,i3,t20"
+        );
+    }
+
+    #[test]
+    fn event_loop_is_budgeted_and_cancellable() {
+        let forever = vec!["setInterval(function () {}, 0);".to_string()];
+        let budget = EventLoopBudget {
+            max_timer_tasks: 50,
+            ..EventLoopBudget::default()
+        };
+        let bounded = engine().with_event_loop(budget, Cancellation::never());
+        assert_eq!(bounded.run_page_scripts(&forever).unwrap().timers_run, 50);
+
+        // The virtual clock stops at its limit: 4 ms intervals, 100 ms of time
+        let budget = EventLoopBudget {
+            max_virtual_ms: 100,
+            ..EventLoopBudget::default()
+        };
+        let bounded = engine().with_event_loop(budget, Cancellation::never());
+        assert_eq!(bounded.run_page_scripts(&forever).unwrap().timers_run, 25);
+
+        // Background tabs run no timers; a terminated tab runs nothing more
+        let paused = engine().with_event_loop(EventLoopBudget::paused(50), Cancellation::never());
+        assert_eq!(paused.run_page_scripts(&forever).unwrap().timers_run, 0);
+        let cancelled =
+            engine().with_event_loop(EventLoopBudget::default(), Cancellation::when(|| true));
+        assert_eq!(cancelled.run_page_scripts(&forever).unwrap().timers_run, 0);
+    }
+
+    /// Answers every request with a fixed JSON body, as if redirected for
+    /// `/moved` and `/away`, and records what it saw
+    struct RecordingFetcher(std::sync::Mutex<Vec<FetchRequest>>);
+
+    impl ScriptFetcher for RecordingFetcher {
+        fn fetch(&self, request: &FetchRequest) -> Result<FetchResponse, String> {
+            self.0.lock().unwrap().push(request.clone());
+            let redirected_to = match request.url.path() {
+                "/moved" => Some("https://x.example/data.json"),
+                "/away" => Some("https://other.example/data.json"),
+                _ => None,
+            };
+            Ok(FetchResponse {
+                status: 200,
+                content_type: "application/json".to_string(),
+                body: br#"{"msg":"fetched"}"#.to_vec(),
+                redirected_to: redirected_to.map(|url| Url::parse(url).unwrap()),
+            })
+        }
+    }
+
+    #[test]
+    fn fetch_is_same_origin_get_only_and_settles_in_the_event_loop() {
+        let dom = crate::parse_html("<body></body>", Arc::new(SecurityContext::new(10))).unwrap();
+        let fetcher = Arc::new(RecordingFetcher(Default::default()));
+        let scripts = vec![
+            "function note(text) { var p = document.createElement('p'); p.innerText = text; \
+               document.body.appendChild(p); } \
+             fetch('/data.json', { headers: { 'Accept': 'application/json', 'X-Track': '1' } }) \
+               .then(function (r) { return r.json(); }) \
+               .then(function (d) { note('got ' + d.msg); }); \
+             fetch('https://other.example/x').catch(function (e) { note('cross ' + e.message); }); \
+             fetch('/submit', { method: 'POST', body: 'x' }) \
+               .catch(function (e) { note('post ' + e.message); }); \
+             fetch('/moved').then(function (r) { note('moved ' + r.redirected + ' ' + r.url); }); \
+             fetch('/away').catch(function (e) { note('away ' + e.message); });"
+                .to_string(),
+        ];
+        engine()
+            .with_fetcher(fetcher.clone())
+            .run_page_scripts_on_dom("https://x.example/page", &dom, &scripts)
+            .unwrap();
+
+        let text = dom.get_text_content();
+        assert!(text.contains("got fetched"), "{text}");
+        assert!(text.contains("cross Failed to fetch"), "{text}");
+        assert!(text.contains("post Failed to fetch"), "{text}");
+        // A redirect within the origin shows; one off it fails the fetch
+        assert!(
+            text.contains("moved true https://x.example/data.json"),
+            "{text}"
+        );
+        assert!(text.contains("away Failed to fetch"), "{text}");
+
+        // Only the same-origin GETs left the cage, with safelisted headers
+        let seen = fetcher.0.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].url.as_str(), "https://x.example/data.json");
+        assert_eq!(seen[0].method, "GET");
+        assert_eq!(
+            seen[0].headers,
+            [("Accept".to_string(), "application/json".to_string())]
+        );
+
+        // Without a fetcher, fetch stays a blocked request
+        let dom = crate::parse_html("<body></body>", Arc::new(SecurityContext::new(10))).unwrap();
+        engine()
+            .run_page_scripts_on_dom("https://x.example/", &dom, &scripts)
+            .unwrap();
+        let text = dom.get_text_content();
+        assert!(!text.contains("got fetched"), "{text}");
+    }
 }
@@ -0,0 +1,341 @@
+//! Local language detection and encoding hints
+//!
+//! Works out the language of a page from its `<html lang>` attribute, or from
+//! its extracted text with `whatlang` when the page does not declare one, and
+//! the character encoding from the `Content-Type` header or `<meta charset>`.
+//! Everything runs locally: page text is never sent to a detection service.
+//!
+//! The result drives reader mode line breaking, spellcheck dictionary
+//! selection and font fallback.
+
+/// Fewest letters of text a detection is attempted on; shorter text (menus,
+/// error pages) gives unreliable guesses
+pub const MIN_DETECTION_CHARS: usize = 40;
+
+/// Most characters of text handed to the detector
+const MAX_DETECTION_CHARS: usize = 4096;
+
+/// Languages written without spaces between words
+const UNSPACED_LANGUAGES: &[&str] = &["zh", "ja", "th", "lo", "km", "my"];
+
+/// Where a page's language came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LanguageSource {
+    /// The page gave no language and its text was too short or ambiguous
+    #[default]
+    Unknown,
+    /// The `lang` attribute of the `<html>` element
+    Declared,
+    /// Detected locally from the page text
+    Detected,
+}
+
+/// Language and encoding metadata of a page
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LanguageHints {
+    /// BCP 47 tag, e.g. `en-GB` or `ja`
+    pub language: Option<String>,
+    pub source: LanguageSource,
+    /// Dominant writing system of the text, e.g. `Latin` or `Cyrillic`
+    pub script: Option<String>,
+    /// Detector confidence between 0 and 1; 1 for declared languages
+    pub confidence: f64,
+    /// Character encoding label, lowercased
+    pub charset: Option<String>,
+}
+
+impl LanguageHints {
+    /// Hints for a page from its raw HTML, its extracted text and the
+    /// response's `Content-Type` header
+    pub fn analyze(html: &str, text: &str, content_type: Option<&str>) -> Self {
+        let sample: String = text.chars().take(MAX_DETECTION_CHARS).collect();
+        let script = whatlang::detect_script(&sample).map(|script| script.name().to_string());
+        let charset = content_type
+            .and_then(charset_parameter)
+            .or_else(|| meta_charset(html));
+
+        if let Some(language) = declared_language(html) {
+            return Self {
+                language: Some(language),
+                source: LanguageSource::Declared,
+                script,
+                confidence: 1.0,
+                charset,
+            };
+        }
+
+        let letters = sample.chars().filter(|c| c.is_alphabetic()).count();
+        let detected = (letters >= MIN_DETECTION_CHARS)
+            .then(|| whatlang::detect(&sample))
+            .flatten()
+            .filter(|info| info.is_reliable());
+        match detected {
+            Some(info) => Self {
+                language: Some(iso_639_1(info.lang()).to_string()),
+                source: LanguageSource::Detected,
+                script: Some(info.script().name().to_string()),
+                confidence: info.confidence(),
+                charset,
+            },
+            None => Self {
+                script,
+                charset,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Value for the page's `Content-Language` metadata
+    pub fn content_language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Primary language subtag, lowercased (`pt` for `pt-BR`)
+    pub fn primary_language(&self) -> Option<String> {
+        let language = self.language.as_deref()?;
+        let primary = language.split(['-', '_']).next()?;
+        Some(primary.to_ascii_lowercase())
+    }
+
+    /// Hunspell-style dictionary name for spellchecking form fields on the
+    /// page, e.g. `en_GB` or `de`
+    pub fn spellcheck_dictionary(&self) -> Option<String> {
+        let language = self.language.as_deref()?;
+        let mut subtags = language.split(['-', '_']);
+        let primary = subtags.next()?.to_ascii_lowercase();
+        let region = subtags
+            .find(|subtag| subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()));
+        Some(match region {
+            Some(region) => format!("{}_{}", primary, region.to_ascii_uppercase()),
+            None => primary,
+        })
+    }
+
+    /// Whether the language is written without spaces between words, so
+    /// reader mode must not insert them when joining runs of text
+    pub fn breaks_without_spaces(&self) -> bool {
+        self.primary_language()
+            .is_some_and(|primary| UNSPACED_LANGUAGES.contains(&primary.as_str()))
+    }
+
+    /// Writing system fallback fonts should cover
+    pub fn font_fallback_script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+}
+
+/// The `charset` parameter of a `Content-Type` value
+fn charset_parameter(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']).to_ascii_lowercase())
+            .filter(|charset| !charset.is_empty())
+    })
+}
+
+/// The encoding declared by a `<meta charset>` or `<meta http-equiv>` tag
+/// in the first kilobytes of the document, as the HTML prescan does
+fn meta_charset(html: &str) -> Option<String> {
+    let head = prefix(html, 1024);
+    let lower = head.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<meta") {
+        let start = offset + start;
+        let end = lower[start..]
+            .find('>')
+            .map_or(lower.len(), |end| start + end);
+        let tag = &lower[start..end];
+        if let Some(charset) = attribute(tag, "charset") {
+            return Some(charset);
+        }
+        if let Some(content) = attribute(tag, "content") {
+            if let Some(charset) = charset_parameter(&format!("text/html;{}", content)) {
+                return Some(charset);
+            }
+        }
+        offset = end;
+    }
+    None
+}
+
+/// The `lang` attribute of the document's `<html>` start tag
+fn declared_language(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.match_indices("<html").find_map(|(index, _)| {
+        let next = lower[index + 5..].chars().next()?;
+        (next.is_ascii_whitespace() || next == '>').then_some(index)
+    })?;
+    let end = lower[start..].find('>')? + start;
+    let language = attribute(&html[start..end], "lang")?;
+    let valid = language.len() <= 35
+        && language.split('-').all(|subtag| {
+            !subtag.is_empty()
+                && subtag.len() <= 8
+                && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    valid.then_some(language)
+}
+
+/// Value of `name` in a start tag's source, unquoted
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(found) = lower[offset..].find(name) {
+        let index = offset + found;
+        offset = index + name.len();
+        let boundary = lower[..index]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lower[offset..].trim_start();
+        if !boundary || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let value = match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+                .next()?,
+        };
+        let value = value.trim();
+        return (!value.is_empty()).then(|| value.to_string());
+    }
+    None
+}
+
+/// At most `max` bytes of `text`, cut at a character boundary
+fn prefix(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Two-letter code of a detected language (`nb` and `fa` for the
+/// detector's Bokmål and Western Persian)
+fn iso_639_1(lang: whatlang::Lang) -> &'static str {
+    use whatlang::Lang::*;
+    match lang {
+        Epo => "eo",
+        Eng => "en",
+        Rus => "ru",
+        Cmn => "zh",
+        Spa => "es",
+        Por => "pt",
+        Ita => "it",
+        Ben => "bn",
+        Fra => "fr",
+        Deu => "de",
+        Ukr => "uk",
+        Kat => "ka",
+        Ara => "ar",
+        Hin => "hi",
+        Jpn => "ja",
+        Heb => "he",
+        Yid => "yi",
+        Pol => "pl",
+        Amh => "am",
+        Jav => "jv",
+        Kor => "ko",
+        Nob => "nb",
+        Dan => "da",
+        Swe => "sv",
+        Fin => "fi",
+        Tur => "tr",
+        Nld => "nl",
+        Hun => "hu",
+        Ces => "cs",
+        Ell => "el",
+        Bul => "bg",
+        Bel => "be",
+        Mar => "mr",
+        Kan => "kn",
+        Ron => "ro",
+        Slv => "sl",
+        Hrv => "hr",
+        Srp => "sr",
+        Mkd => "mk",
+        Lit => "lt",
+        Lav => "lv",
+        Est => "et",
+        Tam => "ta",
+        Vie => "vi",
+        Urd => "ur",
+        Tha => "th",
+        Guj => "gu",
+        Uzb => "uz",
+        Pan => "pa",
+        Aze => "az",
+        Ind => "id",
+        Tel => "te",
+        Pes => "fa",
+        Mal => "ml",
+        Ori => "or",
+        Mya => "my",
+        Nep => "ne",
+        Sin => "si",
+        Khm => "km",
+        Tuk => "tk",
+        Aka => "ak",
+        Zul => "zu",
+        Sna => "sn",
+        Afr => "af",
+        Lat => "la",
+        Slk => "sk",
+        Cat => "ca",
+        Tgl => "tl",
+        Hye => "hy",
+        Cym => "cy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_language_wins_over_detection() {
+        let html = r#"<!DOCTYPE html><html class="no-js" LANG="pt-BR"><head><meta charset='ISO-8859-1'></head>"#;
+        let hints = LanguageHints::analyze(html, "The quick brown fox jumps", None);
+        assert_eq!(hints.content_language(), Some("pt-BR"));
+        assert_eq!(hints.source, LanguageSource::Declared);
+        assert_eq!(hints.spellcheck_dictionary().as_deref(), Some("pt_BR"));
+        assert_eq!(hints.charset.as_deref(), Some("iso-8859-1"));
+        assert_eq!(hints.font_fallback_script(), Some("Latin"));
+
+        // The header beats the meta tag; a bogus lang is ignored
+        let hints = LanguageHints::analyze(
+            r#"<html lang="<script>"><meta http-equiv="Content-Type" content="text/html; charset=koi8-r">"#,
+            "",
+            Some("text/html; charset=\"UTF-8\""),
+        );
+        assert_eq!(hints.charset.as_deref(), Some("utf-8"));
+        assert_eq!(hints.language, None);
+        assert_eq!(hints.source, LanguageSource::Unknown);
+    }
+
+    #[test]
+    fn test_detects_language_from_text() {
+        let german = "Die Würde des Menschen ist unantastbar. Sie zu achten und zu schützen \
+                      ist Verpflichtung aller staatlichen Gewalt.";
+        let hints = LanguageHints::analyze("<html><body>", german, None);
+        assert_eq!(hints.content_language(), Some("de"));
+        assert_eq!(hints.source, LanguageSource::Detected);
+        assert!(!hints.breaks_without_spaces());
+
+        let japanese = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。\
+                        何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。";
+        let hints = LanguageHints::analyze("<html>", japanese, None);
+        assert_eq!(hints.content_language(), Some("ja"));
+        assert!(hints.breaks_without_spaces());
+
+        // Too little text to guess from
+        let hints = LanguageHints::analyze("<html>", "Home | About", None);
+        assert_eq!(hints.language, None);
+    }
+}
@@ -15,7 +15,7 @@ use taffy::{
 };
 
 use crate::css::{CitadelStylesheet, ComputedStyle, DisplayType, LengthValue};
-use crate::dom::{Dom, Node};
+use crate::dom::{Dom, Node, NodeData};
 use crate::error::{ParserError, ParserResult};
 use crate::security::SecurityContext;
 
@@ -152,10 +152,12 @@ pub struct CitadelLayoutEngine {
     last_dom_hash: Option<u64>,
     /// Last CSS hash for change detection
     last_css_hash: Option<u64>,
+    /// Decoded sizes of images, by `src` as written in the document
+    intrinsic_sizes: HashMap<String, LayoutSize>,
 }
 
 /// Simple layout rectangle
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LayoutRect {
     pub x: f32,
     pub y: f32,
@@ -245,9 +247,20 @@ impl CitadelLayoutEngine {
             viewport_culling_enabled: true,
             last_dom_hash: None,
             last_css_hash: None,
+            intrinsic_sizes: HashMap::new(),
         }
     }
 
+    /// Lay out images at their decoded sizes, by `src` as written in the
+    /// document. Images not in `sizes` are sized by their attributes alone.
+    pub fn set_intrinsic_sizes(&mut self, sizes: HashMap<String, LayoutSize>) {
+        self.intrinsic_sizes = sizes;
+        // Cached layouts used the old sizes
+        self.layout_cache.clear();
+        self.last_dom_hash = None;
+        self.last_css_hash = None;
+    }
+
     /// Create a new layout engine with custom cache size
     pub fn new_with_cache_size(security_context: Arc<SecurityContext>, cache_size: usize) -> Self {
         let mut engine = Self::new(security_context);
@@ -274,6 +287,7 @@ impl CitadelLayoutEngine {
             viewport_culling_enabled: true,
             last_dom_hash: None,
             last_css_hash: None,
+            intrinsic_sizes: HashMap::new(),
         }
     }
 
@@ -297,6 +311,7 @@ impl CitadelLayoutEngine {
             viewport_culling_enabled: true,
             last_dom_hash: None,
             last_css_hash: None,
+            intrinsic_sizes: HashMap::new(),
         }
     }
 
@@ -533,6 +548,7 @@ impl CitadelLayoutEngine {
         let taffy_node = if layout_children.is_empty() {
             // Leaf node - measure text content if present
             let measured_style = self.apply_text_measurement(taffy_style, dom_node);
+            let measured_style = self.apply_replaced_size(measured_style, dom_node);
             self.taffy.new_leaf(measured_style).map_err(|e| {
                 ParserError::LayoutError(format!("Failed to create leaf node: {:?}", e))
             })?
@@ -1066,6 +1082,49 @@ impl CitadelLayoutEngine {
         style
     }
 
+    /// Size an `<img>` whose CSS leaves it auto: by its `width` and `height`
+    /// attributes, else by its decoded size. With one dimension given the
+    /// other follows the image's aspect ratio.
+    fn apply_replaced_size(&self, mut style: Style, node: &Node) -> Style {
+        let NodeData::Element(element) = &node.data else {
+            return style;
+        };
+        if !element.local_name().eq_ignore_ascii_case("img") {
+            return style;
+        }
+        let attribute = |name: &str| {
+            element
+                .get_attribute(name)
+                .and_then(|value| value.trim().trim_end_matches("px").parse::<f32>().ok())
+                .filter(|value| value.is_finite() && *value > 0.0)
+        };
+        let intrinsic = element
+            .get_attribute("src")
+            .and_then(|src| self.intrinsic_sizes.get(&src).cloned())
+            .filter(|size| size.width > 0.0 && size.height > 0.0);
+        let ratio = intrinsic.as_ref().map(|size| size.width / size.height);
+        let (width, height) = match (attribute("width"), attribute("height")) {
+            (Some(width), Some(height)) => (Some(width), Some(height)),
+            (Some(width), None) => (Some(width), ratio.map(|ratio| width / ratio)),
+            (None, Some(height)) => (ratio.map(|ratio| height * ratio), Some(height)),
+            (None, None) => (
+                intrinsic.as_ref().map(|size| size.width),
+                intrinsic.as_ref().map(|size| size.height),
+            ),
+        };
+
+        if let (Dimension::Auto, Some(width)) = (style.size.width, width) {
+            style.size.width = Dimension::Length(width);
+        }
+        if let (Dimension::Auto, Some(height)) = (style.size.height, height) {
+            style.size.height = Dimension::Length(height);
+        }
+        if style.aspect_ratio.is_none() {
+            style.aspect_ratio = ratio;
+        }
+        style
+    }
+
     /// Measure text content dimensions
     fn measure_text(&self, text: &str) -> LayoutSize {
         let lines: Vec<&str> = text.lines().collect();
@@ -1090,7 +1149,13 @@ impl CitadelLayoutEngine {
     fn measure_text_width(&self, text: &str) -> f32 {
         let mut width = 0.0;
 
-        for ch in text.chars() {
+        // One advance per grapheme cluster: combining marks, Indic conjuncts and
+        // Arabic harakat are shaped onto their base and add no width of their own.
+        // Bidi reordering does not change the total width of a line.
+        for cluster in crate::text::clusters(text) {
+            let Some(ch) = cluster.chars().next() else {
+                continue;
+            };
             width += self
                 .text_measurement
                 .char_widths
@@ -1648,7 +1713,7 @@ pub struct PerformanceStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::css::{Declaration, StyleRule};
+    use crate::css::{CascadeOrigin, Declaration, StyleRule};
     use crate::dom::*;
     use crate::security::SecurityContext;
 
@@ -1764,6 +1829,7 @@ mod tests {
                 },
             ],
             specificity: 1,
+            origin: CascadeOrigin::Author,
         });
 
         stylesheet
@@ -1794,6 +1860,7 @@ mod tests {
                 },
             ],
             specificity: 10,
+            origin: CascadeOrigin::Author,
         });
 
         stylesheet
@@ -1842,6 +1909,7 @@ mod tests {
                 },
             ],
             specificity: 10,
+            origin: CascadeOrigin::Author,
         });
 
         // Test that comprehensive styles are computed correctly
@@ -2029,6 +2097,7 @@ mod tests {
                 },
             ],
             specificity: 10,
+            origin: CascadeOrigin::Author,
         });
 
         // Test layout computation
@@ -2043,4 +2112,38 @@ mod tests {
         assert_eq!(layout_result.document_size.width, 1200.0);
         assert_eq!(layout_result.document_size.height, 800.0);
     }
+
+    #[test]
+    fn test_images_take_intrinsic_and_attribute_sizes() {
+        let dom = crate::parse_html(
+            "<html><body>\
+             <img id=\"natural\" src=\"/a.png\">\
+             <img id=\"scaled\" src=\"/a.png\" width=\"80\">\
+             <img id=\"unknown\" src=\"/b.png\" height=\"15\">\
+             </body></html>",
+            create_test_security_context(),
+        )
+        .unwrap();
+        let mut sizes = HashMap::new();
+        sizes.insert("/a.png".to_string(), LayoutSize::new(40.0, 20.0));
+        let mut layout_engine = CitadelLayoutEngine::new(create_test_security_context());
+        layout_engine.set_intrinsic_sizes(sizes);
+
+        let result = layout_engine
+            .compute_layout(
+                &dom,
+                &CitadelStylesheet::new(create_test_security_context()),
+                LayoutSize::new(800.0, 600.0),
+            )
+            .unwrap();
+        let size = |id: &str| {
+            let node = dom.get_element_by_id(id).unwrap();
+            let node_id = node.read().unwrap().id();
+            let rect = &result.node_layouts[&node_id];
+            (rect.width, rect.height)
+        };
+        assert_eq!(size("natural"), (40.0, 20.0));
+        assert_eq!(size("scaled"), (80.0, 40.0));
+        assert_eq!(size("unknown").1, 15.0);
+    }
 }
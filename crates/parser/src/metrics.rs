@@ -74,6 +74,8 @@ pub struct DocumentMetrics {
     attributes: AtomicUsize,
     /// Amount of text content (in bytes)
     text_content: AtomicUsize,
+    /// Tokens the tree builder received: elements, text runs and comments
+    tokens: AtomicUsize,
 }
 
 impl DocumentMetrics {
@@ -97,6 +99,11 @@ impl DocumentMetrics {
         self.text_content.fetch_add(size, Ordering::Relaxed);
     }
 
+    /// Count a token, returning the total so far
+    pub fn increment_tokens(&self) -> usize {
+        self.tokens.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
     /// Get the total number of elements
     pub fn total_elements(&self) -> usize {
         self.elements.load(Ordering::Relaxed)
@@ -111,6 +118,11 @@ impl DocumentMetrics {
     pub fn total_text_content(&self) -> usize {
         self.text_content.load(Ordering::Relaxed)
     }
+
+    /// Get the total number of tokens
+    pub fn total_tokens(&self) -> usize {
+        self.tokens.load(Ordering::Relaxed)
+    }
 }
 
 /// Timer for measuring parse operations
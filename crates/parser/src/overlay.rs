@@ -0,0 +1,179 @@
+//! Heuristic detection of full-screen overlays
+//!
+//! Paywalls, newsletter nags and consent walls usually share a shape: a
+//! `position: fixed` layer covering the viewport above everything else, often
+//! with `overflow: hidden` on the body so the page underneath cannot scroll.
+//! This pass finds such layers in the styled DOM and returns selectors for
+//! them; the browser hides them through its cosmetic filtering and restores
+//! scrolling. Nothing is removed from the DOM here.
+
+use crate::css::{CitadelStylesheet, ComputedStyle, LengthValue, PositionType};
+use crate::dom::Dom;
+use crate::extract::walk;
+
+/// Lowest `z-index` treated as "above the page"
+pub const MIN_OVERLAY_Z_INDEX: i32 = 100;
+
+/// Id and class fragments typical of overlays
+const OVERLAY_HINTS: &[&str] = &[
+    "backdrop",
+    "interstitial",
+    "modal",
+    "overlay",
+    "paywall",
+    "regwall",
+    "subscribe",
+];
+
+/// Overlays found on a page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlayReport {
+    /// Selectors of the overlay elements, in document order
+    pub selectors: Vec<String>,
+    /// Whether `html` or `body` is styled `overflow: hidden`
+    pub scroll_locked: bool,
+}
+
+impl OverlayReport {
+    /// Whether nothing needs cleaning up
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty() && !self.scroll_locked
+    }
+}
+
+/// Find full-screen overlays and a locked body scroll in a styled document
+pub fn detect_overlays(dom: &Dom, stylesheet: &CitadelStylesheet) -> OverlayReport {
+    let mut report = OverlayReport::default();
+    walk(&dom.root(), &mut |handle, tag| {
+        let Ok(node) = handle.read() else {
+            return;
+        };
+        let classes = node.class_list();
+        let id = node.element_id();
+        let style = stylesheet.compute_styles(tag, &classes, id.as_deref());
+
+        if tag == "html" || tag == "body" {
+            report.scroll_locked |= style
+                .overflow
+                .as_deref()
+                .is_some_and(|overflow| overflow.trim().starts_with("hidden"));
+            return;
+        }
+        if !is_overlay(&style, id.as_deref(), &classes) {
+            return;
+        }
+        if let Some(selector) = selector_for(tag, id.as_deref(), &classes) {
+            if !report.selectors.contains(&selector) {
+                report.selectors.push(selector);
+            }
+        }
+    });
+    report
+}
+
+/// A fixed layer above the page that covers the viewport, or one named like
+/// an overlay
+fn is_overlay(style: &ComputedStyle, id: Option<&str>, classes: &[String]) -> bool {
+    if style.position != PositionType::Fixed {
+        return false;
+    }
+    let raised = style.z_index.is_some_and(|z| z >= MIN_OVERLAY_Z_INDEX);
+    let hinted = id
+        .into_iter()
+        .chain(classes.iter().map(String::as_str))
+        .any(|name| {
+            let name = name.to_ascii_lowercase();
+            OVERLAY_HINTS.iter().any(|hint| name.contains(hint))
+        });
+    let covers = ((is_zero(&style.top) && is_zero(&style.bottom)) || is_full(&style.height))
+        && ((is_zero(&style.left) && is_zero(&style.right)) || is_full(&style.width));
+    (covers && (raised || hinted)) || (raised && hinted)
+}
+
+fn is_zero(length: &Option<LengthValue>) -> bool {
+    match length {
+        Some(LengthValue::Zero) => true,
+        Some(
+            LengthValue::Px(value)
+            | LengthValue::Em(value)
+            | LengthValue::Rem(value)
+            | LengthValue::Percent(value)
+            | LengthValue::Vh(value)
+            | LengthValue::Vw(value),
+        ) => *value == 0.0,
+        _ => false,
+    }
+}
+
+fn is_full(length: &Option<LengthValue>) -> bool {
+    matches!(
+        length,
+        Some(LengthValue::Percent(value) | LengthValue::Vh(value) | LengthValue::Vw(value))
+            if *value >= 100.0
+    )
+}
+
+/// `#id`, or the tag with its classes; `None` when the element has neither
+/// a usable id nor classes, since hiding by tag alone would hide too much
+fn selector_for(tag: &str, id: Option<&str>, classes: &[String]) -> Option<String> {
+    if let Some(id) = id.filter(|id| is_css_identifier(id)) {
+        return Some(format!("#{}", id));
+    }
+    let classes: Vec<&str> = classes
+        .iter()
+        .map(String::as_str)
+        .filter(|class| is_css_identifier(class))
+        .collect();
+    (!classes.is_empty()).then(|| format!("{}.{}", tag, classes.join(".")))
+}
+
+/// An identifier that needs no escaping in a selector
+fn is_css_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::SecurityContext;
+    use crate::{parse_css, parse_html};
+    use std::sync::Arc;
+
+    fn detect(html: &str, css: &str) -> OverlayReport {
+        let context = Arc::new(SecurityContext::new(10));
+        let dom = parse_html(html, context.clone()).unwrap();
+        let stylesheet = parse_css(css, context).unwrap();
+        detect_overlays(&dom, &stylesheet)
+    }
+
+    #[test]
+    fn test_detects_covering_fixed_layers_and_scroll_lock() {
+        let report = detect(
+            "<html><body><article>Story</article>\
+             <div id=\"gate\">Subscribe to read</div>\
+             <div class=\"tp-backdrop dim\"></div>\
+             <div class=\"toolbar\">Menu</div></body></html>",
+            "body { overflow: hidden; }\
+             #gate { position: fixed; top: 0; left: 0; width: 100%; height: 100vh; z-index: 9999; }\
+             .tp-backdrop { position: fixed; top: 0; right: 0; bottom: 0; left: 0; }\
+             .toolbar { position: fixed; top: 0; width: 100%; z-index: 500; }",
+        );
+        assert_eq!(report.selectors, vec!["#gate", "div.tp-backdrop.dim"]);
+        assert!(report.scroll_locked);
+    }
+
+    #[test]
+    fn test_ordinary_pages_have_no_overlays() {
+        let report = detect(
+            "<html><body><div class=\"modal\">Static</div><p id=\"x\">Text</p></body></html>",
+            ".modal { position: absolute; top: 0; left: 0; width: 100%; height: 100%; z-index: 1000; }\
+             p { z-index: 5000; }",
+        );
+        assert!(report.is_empty());
+    }
+}
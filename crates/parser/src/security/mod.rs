@@ -1,19 +1,22 @@
 use ammonia::Builder;
-use std::collections::HashSet;
 
 use crate::error::ParserResult;
 
+pub mod policy;
+
+pub use policy::SanitizerPolicy;
+
 /// Security context for DOM nodes
 #[derive(Debug, Clone)]
 pub struct SecurityContext {
     /// Maximum allowed nesting depth
     max_nesting_depth: usize,
-    /// Allowed HTML elements
-    allowed_elements: HashSet<String>,
-    /// Allowed HTML attributes
-    allowed_attributes: HashSet<String>,
-    /// Allowed URL schemes
-    allowed_schemes: HashSet<String>,
+    /// Maximum number of elements in a document
+    max_elements: usize,
+    /// Maximum text content of a document, in bytes
+    max_text_bytes: usize,
+    /// Allowed elements, attributes and URL schemes
+    policy: SanitizerPolicy,
     /// Whether to allow JavaScript
     allow_scripts: bool,
     /// Whether to allow external resources
@@ -25,122 +28,62 @@ pub struct SecurityContext {
 impl SecurityContext {
     /// Create a new security context with default settings
     pub fn new(max_nesting_depth: usize) -> Self {
-        let mut allowed_elements = HashSet::new();
-        allowed_elements.extend(
-            [
-                // Essential HTML structure elements
-                "html",
-                "head",
-                "body",
-                "title",
-                "meta",
-                "link",
-                "style",
-                // Basic content elements
-                "a",
-                "abbr",
-                "article",
-                "aside",
-                "b",
-                "blockquote",
-                "br",
-                "caption",
-                "code",
-                "col",
-                "colgroup",
-                "dd",
-                "del",
-                "details",
-                "div",
-                "dl",
-                "dt",
-                "em",
-                "figcaption",
-                "figure",
-                "footer",
-                "h1",
-                "h2",
-                "h3",
-                "h4",
-                "h5",
-                "h6",
-                "header",
-                "hr",
-                "i",
-                "img",
-                "ins",
-                "li",
-                "main",
-                "mark",
-                "nav",
-                "ol",
-                "p",
-                "pre",
-                "q",
-                "s",
-                "section",
-                "small",
-                "span",
-                "strong",
-                "sub",
-                "sup",
-                "table",
-                "tbody",
-                "td",
-                "tfoot",
-                "th",
-                "thead",
-                "time",
-                "tr",
-                "u",
-                "ul",
-            ]
-            .iter()
-            .map(|s| s.to_string()),
-        );
-
-        let mut allowed_attributes = HashSet::new();
-        allowed_attributes.extend(
-            [
-                "alt", "class", "colspan", "datetime", "dir", "height", "href", "id", "lang",
-                "rowspan", "src", "title", "width",
-            ]
-            .iter()
-            .map(|s| s.to_string()),
-        );
-
-        let mut allowed_schemes = HashSet::new();
-        allowed_schemes.extend(["https", "data", "mailto"].iter().map(|s| s.to_string()));
+        Self::with_policy(max_nesting_depth, SanitizerPolicy::default())
+    }
 
+    /// Create a security context around an explicit sanitizer policy
+    pub fn with_policy(max_nesting_depth: usize, policy: SanitizerPolicy) -> Self {
         Self {
             max_nesting_depth,
-            allowed_elements,
-            allowed_attributes,
-            allowed_schemes,
+            max_elements: 100_000,
+            max_text_bytes: 16 * 1024 * 1024,
+            policy,
             allow_scripts: false,
             allow_external_content: false,
             content_security_policy: Some("default-src 'self'".to_string()),
         }
     }
 
+    /// Replace the element count and text size limits
+    pub fn with_content_limits(mut self, max_elements: usize, max_text_bytes: usize) -> Self {
+        self.max_elements = max_elements;
+        self.max_text_bytes = max_text_bytes;
+        self
+    }
+
+    /// The element, attribute and URL-scheme policy
+    pub fn policy(&self) -> &SanitizerPolicy {
+        &self.policy
+    }
+
     /// Get the maximum allowed nesting depth
     pub fn max_nesting_depth(&self) -> usize {
         self.max_nesting_depth
     }
 
+    /// Get the maximum number of elements in a document
+    pub fn max_elements(&self) -> usize {
+        self.max_elements
+    }
+
+    /// Get the maximum text content of a document, in bytes
+    pub fn max_text_bytes(&self) -> usize {
+        self.max_text_bytes
+    }
+
     /// Check if an element is allowed
     pub fn is_element_allowed(&self, element: &str) -> bool {
-        self.allowed_elements.contains(element)
+        self.policy.is_element_allowed(element)
     }
 
     /// Check if an attribute is allowed
     pub fn is_attribute_allowed(&self, attribute: &str) -> bool {
-        self.allowed_attributes.contains(attribute)
+        self.policy.is_attribute_allowed(attribute)
     }
 
     /// Check if a URL scheme is allowed
     pub fn is_scheme_allowed(&self, scheme: &str) -> bool {
-        self.allowed_schemes.contains(scheme)
+        self.policy.is_scheme_allowed(scheme)
     }
 
     /// Check if JavaScript is allowed
@@ -167,51 +110,26 @@ impl SecurityContext {
     pub fn can_append_child(&self, child_context: &SecurityContext) -> bool {
         // Child context should be at least as restrictive as parent
         self.max_nesting_depth >= child_context.max_nesting_depth
-            && self
-                .allowed_elements
-                .is_superset(&child_context.allowed_elements)
-            && self
-                .allowed_attributes
-                .is_superset(&child_context.allowed_attributes)
-            && self
-                .allowed_schemes
-                .is_superset(&child_context.allowed_schemes)
+            && self.max_elements >= child_context.max_elements
+            && self.max_text_bytes >= child_context.max_text_bytes
+            && child_context.policy.is_subset_of(&self.policy)
             && (!self.allow_scripts || child_context.allow_scripts)
             && (!self.allow_external_content || child_context.allow_external_content)
     }
 
     /// Sanitize HTML content according to security rules
     pub fn sanitize_html(&self, content: &str) -> ParserResult<String> {
-        // Start with a basic safe configuration
         let mut builder = Builder::default();
-
-        // Only add the tags we explicitly allow
-        let safe_tags: HashSet<&str> = self
-            .allowed_elements
-            .iter()
-            .map(|s| s.as_str())
-            .filter(|&tag| {
-                // Filter out dangerous tags that we never want, even if accidentally allowed
-                !matches!(tag, "script" | "iframe" | "object" | "embed" | "frame")
-            })
-            .collect();
-
-        let safe_attributes: HashSet<&str> = self
-            .allowed_attributes
-            .iter()
-            .map(|s| s.as_str())
-            .filter(|&attr| {
-                // Filter out dangerous attributes
-                !attr.starts_with("on") // Remove all event handlers
-            })
-            .collect();
-
-        let url_schemes: HashSet<&str> = self.allowed_schemes.iter().map(|s| s.as_str()).collect();
-
         builder
-            .tags(safe_tags)
-            .generic_attributes(safe_attributes)
-            .url_schemes(url_schemes);
+            .tags(self.policy.elements().collect())
+            .generic_attributes(self.policy.attributes().collect())
+            .tag_attributes(
+                self.policy
+                    .element_attributes()
+                    .map(|(element, attributes)| (element, attributes.into_iter().collect()))
+                    .collect(),
+            )
+            .url_schemes(self.policy.schemes().collect());
 
         Ok(builder.clean(content).to_string())
     }
@@ -0,0 +1,398 @@
+//! Dependency-light sanitizer core
+//!
+//! The allowlists and URL-scheme checks behind [`SecurityContext`], as plain
+//! data and functions over `&str`. This module uses only `core` and `alloc`
+//! (no tokio, no html5ever types), so server-side code can apply Citadel's
+//! policy to user-generated content with whatever HTML parser it already has,
+//! and the module can be lifted into a `no_std` crate unchanged.
+//!
+//! [`SecurityContext`]: super::SecurityContext
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+
+/// Elements allowed by the default policy
+pub const DEFAULT_ALLOWED_ELEMENTS: &[&str] = &[
+    // Essential HTML structure elements
+    "html",
+    "head",
+    "body",
+    "title",
+    "meta",
+    "link",
+    "style",
+    // Basic content elements
+    "a",
+    "abbr",
+    "article",
+    "aside",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "li",
+    "main",
+    "mark",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "section",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Attributes allowed on every element by the default policy
+pub const DEFAULT_ALLOWED_ATTRIBUTES: &[&str] = &[
+    "aria-hidden",
+    "aria-label",
+    "class",
+    "dir",
+    "hidden",
+    "id",
+    "lang",
+    "role",
+    "title",
+];
+
+/// Attributes the default policy allows only on particular elements
+pub const DEFAULT_ELEMENT_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href", "name", "rel"]),
+    ("col", &["width"]),
+    ("colgroup", &["width"]),
+    ("del", &["datetime"]),
+    ("img", &["alt", "height", "src", "width"]),
+    ("ins", &["datetime"]),
+    ("link", &["href", "rel"]),
+    ("meta", &["content", "name", "property"]),
+    ("table", &["width"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan"]),
+    ("time", &["datetime"]),
+];
+
+/// URL schemes allowed by the default policy
+pub const DEFAULT_ALLOWED_SCHEMES: &[&str] = &["https", "data", "mailto"];
+
+/// Elements that are never emitted, even if a policy allowlists them
+pub const FORBIDDEN_ELEMENTS: &[&str] = &["script", "iframe", "object", "embed", "frame"];
+
+/// Attributes whose value is a URL and must pass the scheme check
+pub const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "formaction",
+    "href",
+    "poster",
+    "src",
+    "xlink:href",
+];
+
+/// Whether an attribute is an inline event handler (`onclick`, `onerror`, ...)
+pub fn is_event_handler(attribute: &str) -> bool {
+    attribute
+        .get(..2)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("on"))
+}
+
+/// Whether an attribute holds a URL
+pub fn is_url_attribute(attribute: &str) -> bool {
+    URL_ATTRIBUTES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(attribute))
+}
+
+/// Whether an element is on the hard deny list
+pub fn is_forbidden_element(element: &str) -> bool {
+    FORBIDDEN_ELEMENTS
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(element))
+}
+
+/// The lowercased scheme of a URL, or `None` for a relative URL.
+///
+/// Follows the URL parser's leniency: leading C0 controls and spaces are
+/// skipped and tabs/newlines are ignored, so `" java\tscript:"` is seen as
+/// `javascript` just as a browser would see it.
+pub fn url_scheme(url: &str) -> Option<String> {
+    let mut scheme = String::new();
+    for c in url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+    {
+        match c {
+            ':' if !scheme.is_empty() => return Some(scheme),
+            c if c.is_ascii_alphabetic() => scheme.push(c.to_ascii_lowercase()),
+            c if !scheme.is_empty() && (c.is_ascii_digit() || matches!(c, '+' | '-' | '.')) => {
+                scheme.push(c)
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Which elements, attributes and URL schemes survive sanitization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizerPolicy {
+    allowed_elements: BTreeSet<String>,
+    allowed_attributes: BTreeSet<String>,
+    element_attributes: BTreeMap<String, BTreeSet<String>>,
+    allowed_schemes: BTreeSet<String>,
+}
+
+impl SanitizerPolicy {
+    /// A policy that allows nothing
+    pub fn empty() -> Self {
+        Self {
+            allowed_elements: BTreeSet::new(),
+            allowed_attributes: BTreeSet::new(),
+            element_attributes: BTreeMap::new(),
+            allowed_schemes: BTreeSet::new(),
+        }
+    }
+
+    /// Allow elements
+    pub fn allow_elements<'a>(mut self, elements: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_elements
+            .extend(elements.into_iter().map(str::to_ascii_lowercase));
+        self
+    }
+
+    /// Allow attributes on every element
+    pub fn allow_attributes<'a>(mut self, attributes: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_attributes
+            .extend(attributes.into_iter().map(str::to_ascii_lowercase));
+        self
+    }
+
+    /// Allow attributes on one element only
+    pub fn allow_element_attributes<'a>(
+        mut self,
+        element: &str,
+        attributes: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.element_attributes
+            .entry(element.to_ascii_lowercase())
+            .or_default()
+            .extend(attributes.into_iter().map(str::to_ascii_lowercase));
+        self
+    }
+
+    /// Allow URL schemes
+    pub fn allow_schemes<'a>(mut self, schemes: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_schemes
+            .extend(schemes.into_iter().map(str::to_ascii_lowercase));
+        self
+    }
+
+    /// Check if an element is allowed
+    pub fn is_element_allowed(&self, element: &str) -> bool {
+        self.allowed_elements.contains(element)
+    }
+
+    /// Check if an attribute is allowed on at least one element
+    pub fn is_attribute_allowed(&self, attribute: &str) -> bool {
+        self.allowed_attributes.contains(attribute)
+            || self
+                .element_attributes
+                .values()
+                .any(|attributes| attributes.contains(attribute))
+    }
+
+    /// Check if an attribute is allowed on `element`: allowed everywhere,
+    /// or on that element in particular
+    pub fn is_attribute_allowed_on(&self, element: &str, attribute: &str) -> bool {
+        self.allowed_attributes.contains(attribute)
+            || self
+                .element_attributes
+                .get(element)
+                .is_some_and(|attributes| attributes.contains(attribute))
+    }
+
+    /// Check if a URL scheme is allowed
+    pub fn is_scheme_allowed(&self, scheme: &str) -> bool {
+        self.allowed_schemes.contains(scheme)
+    }
+
+    /// Check if a URL may be kept. Relative URLs inherit the page's scheme
+    /// and are allowed.
+    pub fn is_url_allowed(&self, url: &str) -> bool {
+        url_scheme(url).is_none_or(|scheme| self.allowed_schemes.contains(&scheme))
+    }
+
+    /// Check if an attribute may be kept with this value: it must be
+    /// allowlisted, not an event handler, and URL values must pass
+    /// [`Self::is_url_allowed`]
+    pub fn is_attribute_value_allowed(&self, attribute: &str, value: &str) -> bool {
+        if is_event_handler(attribute) || !self.is_attribute_allowed(attribute) {
+            return false;
+        }
+        !is_url_attribute(attribute) || self.is_url_allowed(value)
+    }
+
+    /// [`Self::is_attribute_value_allowed`], with the attribute checked
+    /// against `element`'s allowlist
+    pub fn is_attribute_value_allowed_on(
+        &self,
+        element: &str,
+        attribute: &str,
+        value: &str,
+    ) -> bool {
+        if is_event_handler(attribute) || !self.is_attribute_allowed_on(element, attribute) {
+            return false;
+        }
+        !is_url_attribute(attribute) || self.is_url_allowed(value)
+    }
+
+    /// Allowed elements, minus the hard deny list
+    pub fn elements(&self) -> impl Iterator<Item = &str> {
+        self.allowed_elements
+            .iter()
+            .map(String::as_str)
+            .filter(|element| !is_forbidden_element(element))
+    }
+
+    /// Attributes allowed on every element, minus event handlers
+    pub fn attributes(&self) -> impl Iterator<Item = &str> {
+        self.allowed_attributes
+            .iter()
+            .map(String::as_str)
+            .filter(|attribute| !is_event_handler(attribute))
+    }
+
+    /// Elements with attributes of their own, and those attributes minus
+    /// event handlers
+    pub fn element_attributes(&self) -> impl Iterator<Item = (&str, Vec<&str>)> {
+        self.element_attributes.iter().map(|(element, attributes)| {
+            let attributes = attributes
+                .iter()
+                .map(String::as_str)
+                .filter(|attribute| !is_event_handler(attribute))
+                .collect();
+            (element.as_str(), attributes)
+        })
+    }
+
+    /// Allowed URL schemes
+    pub fn schemes(&self) -> impl Iterator<Item = &str> {
+        self.allowed_schemes.iter().map(String::as_str)
+    }
+
+    /// Whether everything this policy allows is also allowed by `other`
+    pub fn is_subset_of(&self, other: &SanitizerPolicy) -> bool {
+        self.allowed_elements.is_subset(&other.allowed_elements)
+            && self.allowed_attributes.is_subset(&other.allowed_attributes)
+            && self.element_attributes.iter().all(|(element, attributes)| {
+                attributes
+                    .iter()
+                    .all(|attribute| other.is_attribute_allowed_on(element, attribute))
+            })
+            && self.allowed_schemes.is_subset(&other.allowed_schemes)
+    }
+}
+
+impl Default for SanitizerPolicy {
+    fn default() -> Self {
+        DEFAULT_ELEMENT_ATTRIBUTES.iter().fold(
+            Self::empty()
+                .allow_elements(DEFAULT_ALLOWED_ELEMENTS.iter().copied())
+                .allow_attributes(DEFAULT_ALLOWED_ATTRIBUTES.iter().copied())
+                .allow_schemes(DEFAULT_ALLOWED_SCHEMES.iter().copied()),
+            |policy, (element, attributes)| {
+                policy.allow_element_attributes(element, attributes.iter().copied())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_scheme_parsing() {
+        assert_eq!(url_scheme("https://example.com").as_deref(), Some("https"));
+        assert_eq!(
+            url_scheme("JavaScript:alert(1)").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            url_scheme(" \u{1}java\tscr\nipt:alert(1)").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(url_scheme("/path/to:thing"), None);
+        assert_eq!(url_scheme("page.html"), None);
+        assert_eq!(url_scheme("1http://x"), None);
+    }
+
+    #[test]
+    fn test_attribute_values() {
+        let policy = SanitizerPolicy::default();
+        assert!(policy.is_attribute_value_allowed("href", "https://example.com/"));
+        assert!(policy.is_attribute_value_allowed("href", "/relative"));
+        assert!(!policy.is_attribute_value_allowed("href", "javascript:alert(1)"));
+        assert!(!policy.is_attribute_value_allowed("href", "jav\tascript:alert(1)"));
+        assert!(policy.is_attribute_value_allowed("class", "javascript:"));
+        assert!(!policy.is_attribute_value_allowed("style", "color: red"));
+
+        assert!(policy.is_attribute_value_allowed_on("a", "href", "/relative"));
+        assert!(!policy.is_attribute_value_allowed_on("div", "href", "/relative"));
+        assert!(policy.is_attribute_value_allowed_on("div", "title", "hello"));
+        assert!(policy.is_attribute_value_allowed_on("img", "width", "10"));
+        assert!(!policy.is_attribute_value_allowed_on("p", "width", "10"));
+        assert!(!policy.is_attribute_value_allowed_on("img", "onerror", "go()"));
+
+        let lax = SanitizerPolicy::default().allow_attributes(["onclick"]);
+        assert!(!lax.is_attribute_value_allowed("onclick", "go()"));
+        assert!(lax.attributes().all(|a| a != "onclick"));
+        assert!(SanitizerPolicy::default().is_subset_of(&lax));
+        assert!(!lax.is_subset_of(&SanitizerPolicy::default()));
+
+        let images = SanitizerPolicy::empty().allow_element_attributes("img", ["src"]);
+        assert!(images.is_subset_of(&SanitizerPolicy::default()));
+        let anywhere = SanitizerPolicy::empty().allow_attributes(["src"]);
+        assert!(!anywhere.is_subset_of(&SanitizerPolicy::default()));
+    }
+}
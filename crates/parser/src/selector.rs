@@ -0,0 +1,900 @@
+//! CSS selector engine
+//!
+//! Parses selector lists and matches them against elements in a tree. The
+//! DOM's `querySelector`, the JS mirror DOM and the cascade all go through it.
+//! Supported:
+//!
+//! - type, universal, `#id` and `.class` selectors and compounds of them
+//!   (`a.external#top`),
+//! - attribute selectors `[attr]`, `[attr=v]`, `~=`, `|=`, `^=`, `$=` and
+//!   `*=`, with an `i` flag for ASCII case-insensitive values,
+//! - descendant (` `), child (`>`), next-sibling (`+`) and
+//!   subsequent-sibling (`~`) combinators,
+//! - `:first-child`, `:last-child`, `:only-child`, `:nth-child()`,
+//!   `:nth-last-child()` and their `-of-type` forms, `:empty`, `:root`,
+//!   `:not()`, `:is()` and `:where()`,
+//! - interaction states (`:hover`, `:focus`, `:checked`, ...), which match
+//!   what the element reports.
+//!
+//! Selectors with pseudo-elements parse but match no element. Unknown
+//! pseudo-classes and malformed selectors are errors, as in browsers.
+
+use serde::Serialize;
+
+use crate::error::{ParserError, ParserResult};
+
+/// Longest selector accepted, in bytes
+pub const MAX_SELECTOR_LEN: usize = 4096;
+
+/// Deepest `:not()`/`:is()` nesting accepted
+const MAX_NESTING: usize = 8;
+
+/// Pseudo-classes for interaction states
+const STATE_PSEUDO_CLASSES: &[&str] = &[
+    "hover",
+    "active",
+    "focus",
+    "focus-visible",
+    "focus-within",
+    "visited",
+    "link",
+    "any-link",
+    "checked",
+    "disabled",
+    "enabled",
+    "target",
+];
+
+/// Pseudo-elements that may be written with a single colon
+const LEGACY_PSEUDO_ELEMENTS: &[&str] = &["before", "after", "first-line", "first-letter"];
+
+/// Comma-separated selectors; an element matches if any of them does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectorList(pub Vec<ComplexSelector>);
+
+/// Compound selectors joined by combinators
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComplexSelector {
+    /// Left to right
+    pub compounds: Vec<CompoundSelector>,
+    /// `combinators[i]` joins `compounds[i]` and `compounds[i + 1]`
+    pub combinators: Vec<Combinator>,
+}
+
+/// How two compound selectors relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Combinator {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+    /// `a + b`
+    NextSibling,
+    /// `a ~ b`
+    SubsequentSibling,
+}
+
+/// Simple selectors that must all match one element
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CompoundSelector {
+    /// Lowercased type; `None` for `*` or no type
+    pub tag: Option<String>,
+    pub ids: Vec<String>,
+    pub classes: Vec<String>,
+    pub attributes: Vec<AttributeSelector>,
+    pub pseudo_classes: Vec<PseudoClass>,
+    /// Selects a pseudo-element, which is never an element in the tree
+    pub pseudo_element: bool,
+}
+
+/// `[name]` or `[name op "value" i]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttributeSelector {
+    /// Lowercased attribute name
+    pub name: String,
+    /// Operator and value; `None` only checks presence
+    pub operator: Option<(AttributeOperator, String)>,
+    pub case_insensitive: bool,
+}
+
+/// Attribute value comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeOperator {
+    /// `=`
+    Equals,
+    /// `~=`, one of the whitespace-separated words
+    Includes,
+    /// `|=`, the value or the value followed by `-`
+    DashMatch,
+    /// `^=`
+    Prefix,
+    /// `$=`
+    Suffix,
+    /// `*=`
+    Substring,
+}
+
+/// Supported pseudo-classes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    NthChild(Nth),
+    NthLastChild(Nth),
+    NthOfType(Nth),
+    NthLastOfType(Nth),
+    Not(SelectorList),
+    /// `:is()` and `:where()`
+    Is(SelectorList),
+    /// An interaction state such as `hover`
+    State(String),
+}
+
+/// An `An+B` position pattern; positions count from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Nth {
+    pub a: i32,
+    pub b: i32,
+}
+
+impl Nth {
+    /// `:first-child` and `:last-child` are `:nth-child(1)` from either end
+    pub const FIRST: Self = Self { a: 0, b: 1 };
+
+    /// Parse `odd`, `even`, `B`, `An` or `An+B`
+    pub fn parse(argument: &str) -> Option<Self> {
+        let argument: String = argument
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match argument.as_str() {
+            "odd" => return Some(Self { a: 2, b: 1 }),
+            "even" => return Some(Self { a: 2, b: 0 }),
+            _ => {}
+        }
+        let Some((a, b)) = argument.split_once('n') else {
+            return Some(Self {
+                a: 0,
+                b: argument.parse().ok()?,
+            });
+        };
+        let a = match a {
+            "" | "+" => 1,
+            "-" => -1,
+            a => a.parse().ok()?,
+        };
+        let b = match b {
+            "" => 0,
+            b if b.starts_with(['+', '-']) => b.parse().ok()?,
+            _ => return None,
+        };
+        Some(Self { a, b })
+    }
+
+    /// Whether the pattern selects a 1-based position
+    pub fn matches(&self, position: i32) -> bool {
+        let offset = i64::from(position) - i64::from(self.b);
+        match i64::from(self.a) {
+            0 => offset == 0,
+            a => offset % a == 0 && offset / a >= 0,
+        }
+    }
+}
+
+/// An element as selector matching sees it
+pub trait SelectorElement: Sized {
+    /// Lowercased tag name
+    fn local_name(&self) -> &str;
+
+    /// Value of an attribute, by lowercased name
+    fn attribute(&self, name: &str) -> Option<&str>;
+
+    /// The parent, if it is an element
+    fn parent_element(&self) -> Option<Self>;
+
+    /// The closest earlier sibling element
+    fn prev_sibling_element(&self) -> Option<Self>;
+
+    /// The closest later sibling element
+    fn next_sibling_element(&self) -> Option<Self>;
+
+    /// Whether the element has child elements or text
+    fn has_content(&self) -> bool;
+
+    /// Whether the element is in an interaction state such as `hover`
+    fn in_state(&self, _state: &str) -> bool {
+        false
+    }
+}
+
+impl SelectorList {
+    /// Parse a selector list
+    pub fn parse(selectors: &str) -> ParserResult<Self> {
+        if selectors.len() > MAX_SELECTOR_LEN {
+            return Err(ParserError::CssError(format!(
+                "Selector longer than {} bytes",
+                MAX_SELECTOR_LEN
+            )));
+        }
+        let mut parser = Parser {
+            chars: selectors.chars().collect(),
+            at: 0,
+        };
+        let list = parser.list(0).map_err(|e| {
+            ParserError::CssError(format!("Invalid selector '{}': {}", selectors, e))
+        })?;
+        match parser.peek() {
+            None => Ok(list),
+            Some(c) => Err(ParserError::CssError(format!(
+                "Invalid selector '{}': unexpected '{}'",
+                selectors, c
+            ))),
+        }
+    }
+
+    /// Whether any selector of the list matches the element
+    pub fn matches<E: SelectorElement>(&self, element: &E) -> bool {
+        self.0.iter().any(|selector| selector.matches(element))
+    }
+
+    /// Whether matching needs more than the element's own tag and
+    /// attributes: its ancestors, siblings or children
+    pub fn needs_tree(&self) -> bool {
+        self.0.iter().any(ComplexSelector::needs_tree)
+    }
+}
+
+impl ComplexSelector {
+    /// Whether the selector matches the element
+    pub fn matches<E: SelectorElement>(&self, element: &E) -> bool {
+        self.matches_from(self.compounds.len() - 1, element)
+    }
+
+    /// Whether `compounds[..=index]` match with `compounds[index]` on the
+    /// element
+    fn matches_from<E: SelectorElement>(&self, index: usize, element: &E) -> bool {
+        if !self.compounds[index].matches(element) {
+            return false;
+        }
+        let Some(previous) = index.checked_sub(1) else {
+            return true;
+        };
+        match self.combinators[previous] {
+            Combinator::Child => element
+                .parent_element()
+                .is_some_and(|parent| self.matches_from(previous, &parent)),
+            Combinator::NextSibling => element
+                .prev_sibling_element()
+                .is_some_and(|sibling| self.matches_from(previous, &sibling)),
+            Combinator::Descendant => {
+                let mut ancestor = element.parent_element();
+                while let Some(current) = ancestor {
+                    if self.matches_from(previous, &current) {
+                        return true;
+                    }
+                    ancestor = current.parent_element();
+                }
+                false
+            }
+            Combinator::SubsequentSibling => {
+                let mut sibling = element.prev_sibling_element();
+                while let Some(current) = sibling {
+                    if self.matches_from(previous, &current) {
+                        return true;
+                    }
+                    sibling = current.prev_sibling_element();
+                }
+                false
+            }
+        }
+    }
+
+    /// Whether matching needs the element's ancestors, siblings or children
+    pub fn needs_tree(&self) -> bool {
+        !self.combinators.is_empty() || self.compounds.iter().any(CompoundSelector::needs_tree)
+    }
+}
+
+impl CompoundSelector {
+    /// Whether every simple selector matches the element
+    pub fn matches<E: SelectorElement>(&self, element: &E) -> bool {
+        if self.pseudo_element {
+            return false;
+        }
+        if let Some(tag) = &self.tag {
+            if !element.local_name().eq_ignore_ascii_case(tag) {
+                return false;
+            }
+        }
+        if !self.ids.is_empty()
+            && !self
+                .ids
+                .iter()
+                .all(|id| element.attribute("id") == Some(id.as_str()))
+        {
+            return false;
+        }
+        if !self.classes.is_empty() {
+            let classes = element.attribute("class").unwrap_or_default();
+            let has = |class: &String| classes.split_ascii_whitespace().any(|c| c == class);
+            if !self.classes.iter().all(has) {
+                return false;
+            }
+        }
+        self.attributes
+            .iter()
+            .all(|attribute| attribute.matches(element.attribute(&attribute.name)))
+            && self
+                .pseudo_classes
+                .iter()
+                .all(|pseudo| pseudo.matches(element))
+    }
+
+    fn needs_tree(&self) -> bool {
+        self.pseudo_classes.iter().any(|pseudo| match pseudo {
+            PseudoClass::State(_) => false,
+            PseudoClass::Not(list) | PseudoClass::Is(list) => list.needs_tree(),
+            _ => true,
+        })
+    }
+}
+
+impl AttributeSelector {
+    /// Whether the attribute's value, if present, matches
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        let Some((operator, expected)) = &self.operator else {
+            return true;
+        };
+        let (value, expected) = if self.case_insensitive {
+            (value.to_ascii_lowercase(), expected.to_ascii_lowercase())
+        } else {
+            (value.to_string(), expected.clone())
+        };
+        match operator {
+            AttributeOperator::Equals => value == expected,
+            AttributeOperator::Includes => {
+                !expected.is_empty()
+                    && !expected.contains(char::is_whitespace)
+                    && value.split_ascii_whitespace().any(|word| word == expected)
+            }
+            AttributeOperator::DashMatch => {
+                value == expected
+                    || value
+                        .strip_prefix(expected.as_str())
+                        .is_some_and(|rest| rest.starts_with('-'))
+            }
+            AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+            AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+            AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected),
+        }
+    }
+}
+
+impl PseudoClass {
+    fn matches<E: SelectorElement>(&self, element: &E) -> bool {
+        match self {
+            Self::Root => element.parent_element().is_none(),
+            Self::Empty => !element.has_content(),
+            Self::NthChild(nth) => nth.matches(position(element, E::prev_sibling_element, false)),
+            Self::NthLastChild(nth) => {
+                nth.matches(position(element, E::next_sibling_element, false))
+            }
+            Self::NthOfType(nth) => nth.matches(position(element, E::prev_sibling_element, true)),
+            Self::NthLastOfType(nth) => {
+                nth.matches(position(element, E::next_sibling_element, true))
+            }
+            Self::Not(list) => !list.matches(element),
+            Self::Is(list) => list.matches(element),
+            Self::State(state) => element.in_state(state),
+        }
+    }
+}
+
+/// 1-based position of the element counting siblings in one direction,
+/// optionally only those of its type
+fn position<E: SelectorElement>(element: &E, step: fn(&E) -> Option<E>, of_type: bool) -> i32 {
+    let mut position = 1;
+    let mut sibling = step(element);
+    while let Some(current) = sibling {
+        if !of_type || current.local_name() == element.local_name() {
+            position += 1;
+        }
+        sibling = step(&current);
+    }
+    position
+}
+
+/// Recursive descent over the selector's characters
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        self.at += usize::from(found);
+        found
+    }
+
+    /// Skip whitespace; returns whether there was any
+    fn whitespace(&mut self) -> bool {
+        let start = self.at;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+        self.at > start
+    }
+
+    fn list(&mut self, depth: usize) -> Result<SelectorList, String> {
+        if depth > MAX_NESTING {
+            return Err("nested too deeply".into());
+        }
+        let mut selectors = vec![self.complex(depth)?];
+        while self.eat(',') {
+            selectors.push(self.complex(depth)?);
+        }
+        Ok(SelectorList(selectors))
+    }
+
+    fn complex(&mut self, depth: usize) -> Result<ComplexSelector, String> {
+        self.whitespace();
+        let mut selector = ComplexSelector {
+            compounds: vec![self.compound(depth)?],
+            combinators: Vec::new(),
+        };
+        loop {
+            let spaced = self.whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                Some(',' | ')') | None => break,
+                Some(_) if spaced => Combinator::Descendant,
+                Some(c) => return Err(format!("unexpected '{}'", c)),
+            };
+            if combinator != Combinator::Descendant {
+                self.at += 1;
+                self.whitespace();
+            }
+            if selector.compounds.last().is_some_and(|c| c.pseudo_element) {
+                return Err("pseudo-elements must come last".into());
+            }
+            selector.combinators.push(combinator);
+            selector.compounds.push(self.compound(depth)?);
+        }
+        Ok(selector)
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<CompoundSelector, String> {
+        let start = self.at;
+        let mut compound = CompoundSelector::default();
+        if !self.eat('*') {
+            if let Some(tag) = self.ident() {
+                compound.tag = Some(tag.to_ascii_lowercase());
+            }
+        }
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.at += 1;
+                    compound.ids.push(self.ident().ok_or("expected an id")?);
+                }
+                Some('.') => {
+                    self.at += 1;
+                    compound
+                        .classes
+                        .push(self.ident().ok_or("expected a class name")?);
+                }
+                Some('[') => {
+                    self.at += 1;
+                    compound.attributes.push(self.attribute()?);
+                }
+                Some(':') => {
+                    self.at += 1;
+                    self.pseudo(&mut compound, depth)?;
+                }
+                _ => break,
+            }
+        }
+        if self.at == start {
+            return Err(match self.peek() {
+                Some(c) => format!("unexpected '{}'", c),
+                None => "expected a selector".into(),
+            });
+        }
+        Ok(compound)
+    }
+
+    fn attribute(&mut self) -> Result<AttributeSelector, String> {
+        self.whitespace();
+        let name = self
+            .ident()
+            .ok_or("expected an attribute name")?
+            .to_ascii_lowercase();
+        self.whitespace();
+        let mut selector = AttributeSelector {
+            name,
+            operator: None,
+            case_insensitive: false,
+        };
+        if self.eat(']') {
+            return Ok(selector);
+        }
+
+        let operator = match self.peek() {
+            Some('=') => AttributeOperator::Equals,
+            Some('~') => AttributeOperator::Includes,
+            Some('|') => AttributeOperator::DashMatch,
+            Some('^') => AttributeOperator::Prefix,
+            Some('$') => AttributeOperator::Suffix,
+            Some('*') => AttributeOperator::Substring,
+            _ => return Err("expected an attribute operator".into()),
+        };
+        self.at += 1;
+        if operator != AttributeOperator::Equals && !self.eat('=') {
+            return Err("expected '='".into());
+        }
+        self.whitespace();
+        let value = match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.at += 1;
+                self.string(quote)?
+            }
+            _ => self.ident().ok_or("expected an attribute value")?,
+        };
+        selector.operator = Some((operator, value));
+        self.whitespace();
+        if let Some(flag) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+            self.at += 1;
+            match flag.to_ascii_lowercase() {
+                'i' => selector.case_insensitive = true,
+                's' => {}
+                _ => return Err(format!("unknown attribute flag '{}'", flag)),
+            }
+            self.whitespace();
+        }
+        if !self.eat(']') {
+            return Err("expected ']'".into());
+        }
+        Ok(selector)
+    }
+
+    fn pseudo(&mut self, compound: &mut CompoundSelector, depth: usize) -> Result<(), String> {
+        let element = self.eat(':');
+        let name = self
+            .ident()
+            .ok_or("expected a pseudo-class name")?
+            .to_ascii_lowercase();
+        if element || LEGACY_PSEUDO_ELEMENTS.contains(&name.as_str()) {
+            if self.eat('(') {
+                self.argument()?;
+            }
+            compound.pseudo_element = true;
+            return Ok(());
+        }
+        if compound.pseudo_element {
+            return Err("pseudo-elements must come last".into());
+        }
+
+        let pseudo = if self.eat('(') {
+            match name.as_str() {
+                "not" | "is" | "where" | "matches" => {
+                    let list = self.list(depth + 1)?;
+                    if !self.eat(')') {
+                        return Err("expected ')'".into());
+                    }
+                    match name.as_str() {
+                        "not" => PseudoClass::Not(list),
+                        _ => PseudoClass::Is(list),
+                    }
+                }
+                "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                    let argument = self.argument()?;
+                    let nth = Nth::parse(&argument)
+                        .ok_or_else(|| format!("invalid :{}({})", name, argument))?;
+                    match name.as_str() {
+                        "nth-child" => PseudoClass::NthChild(nth),
+                        "nth-last-child" => PseudoClass::NthLastChild(nth),
+                        "nth-of-type" => PseudoClass::NthOfType(nth),
+                        _ => PseudoClass::NthLastOfType(nth),
+                    }
+                }
+                _ => return Err(format!("unsupported pseudo-class :{}()", name)),
+            }
+        } else {
+            match name.as_str() {
+                "root" => PseudoClass::Root,
+                "empty" => PseudoClass::Empty,
+                "first-child" => PseudoClass::NthChild(Nth::FIRST),
+                "last-child" => PseudoClass::NthLastChild(Nth::FIRST),
+                "first-of-type" => PseudoClass::NthOfType(Nth::FIRST),
+                "last-of-type" => PseudoClass::NthLastOfType(Nth::FIRST),
+                "only-child" => {
+                    compound
+                        .pseudo_classes
+                        .push(PseudoClass::NthChild(Nth::FIRST));
+                    PseudoClass::NthLastChild(Nth::FIRST)
+                }
+                "only-of-type" => {
+                    compound
+                        .pseudo_classes
+                        .push(PseudoClass::NthOfType(Nth::FIRST));
+                    PseudoClass::NthLastOfType(Nth::FIRST)
+                }
+                state if STATE_PSEUDO_CLASSES.contains(&state) => {
+                    PseudoClass::State(state.to_string())
+                }
+                _ => return Err(format!("unsupported pseudo-class :{}", name)),
+            }
+        };
+        compound.pseudo_classes.push(pseudo);
+        Ok(())
+    }
+
+    /// Raw text up to the closing parenthesis, which is consumed
+    fn argument(&mut self) -> Result<String, String> {
+        let start = self.at;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    let argument = self.chars[start..self.at].iter().collect();
+                    self.at += 1;
+                    return Ok(argument);
+                }
+                ')' => depth -= 1,
+                _ => {}
+            }
+            self.at += 1;
+        }
+        Err("expected ')'".into())
+    }
+
+    /// A quoted string whose opening quote was consumed
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err("unterminated string".into()),
+                Some(c) if c == quote => {
+                    self.at += 1;
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    self.at += 1;
+                    if let Some(c) = self.escape() {
+                        value.push(c);
+                    }
+                }
+                Some(c) => {
+                    self.at += 1;
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    /// A CSS identifier, with escapes resolved
+    fn ident(&mut self) -> Option<String> {
+        let start = self.at;
+        let mut ident = String::new();
+        while let Some(c) = self.peek() {
+            let starts_ident = c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+            let continues_ident = c.is_ascii_digit() || c == '-';
+            if c == '\\' {
+                self.at += 1;
+                match self.escape() {
+                    Some(escaped) => ident.push(escaped),
+                    None => {
+                        self.at = start;
+                        return None;
+                    }
+                }
+            } else if starts_ident || (continues_ident && (self.at > start || c == '-')) {
+                self.at += 1;
+                ident.push(c);
+            } else {
+                break;
+            }
+        }
+        // A lone `-` or one followed by a digit does not start an identifier
+        let valid = !ident.is_empty()
+            && ident != "-"
+            && !(ident.starts_with('-') && ident[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if valid {
+            Some(ident)
+        } else {
+            self.at = start;
+            None
+        }
+    }
+
+    /// The character after a backslash: up to six hex digits and an
+    /// optional space, or the character itself
+    fn escape(&mut self) -> Option<char> {
+        let start = self.at;
+        while self.at - start < 6 && self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.at += 1;
+        }
+        if self.at > start {
+            let hex: String = self.chars[start..self.at].iter().collect();
+            if self.peek().is_some_and(char::is_whitespace) {
+                self.at += 1;
+            }
+            let code = u32::from_str_radix(&hex, 16).ok()?;
+            return Some(
+                char::from_u32(code)
+                    .filter(|c| *c != '\0')
+                    .unwrap_or('\u{FFFD}'),
+            );
+        }
+        let c = self.peek()?;
+        self.at += 1;
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Entry = (
+        &'static str,
+        Vec<(&'static str, &'static str)>,
+        Option<usize>,
+    );
+
+    /// A small tree of elements: tag, attributes and parent index
+    struct Tree {
+        nodes: Vec<Entry>,
+    }
+
+    #[derive(Clone, Copy)]
+    struct Node<'a>(&'a Tree, usize);
+
+    impl Node<'_> {
+        fn entry(&self) -> &Entry {
+            &self.0.nodes[self.1]
+        }
+
+        fn siblings(&self) -> Vec<usize> {
+            let parent = self.entry().2;
+            (0..self.0.nodes.len())
+                .filter(|i| self.0.nodes[*i].2 == parent)
+                .collect()
+        }
+
+        fn sibling(&self, step: isize) -> Option<Self> {
+            let siblings = self.siblings();
+            let at = siblings.iter().position(|i| *i == self.1)? as isize + step;
+            let index = *siblings.get(usize::try_from(at).ok()?)?;
+            Some(Node(self.0, index))
+        }
+    }
+
+    impl SelectorElement for Node<'_> {
+        fn local_name(&self) -> &str {
+            self.entry().0
+        }
+
+        fn attribute(&self, name: &str) -> Option<&str> {
+            let attributes = &self.entry().1;
+            attributes.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+        }
+
+        fn parent_element(&self) -> Option<Self> {
+            self.entry().2.map(|parent| Node(self.0, parent))
+        }
+
+        fn prev_sibling_element(&self) -> Option<Self> {
+            self.sibling(-1)
+        }
+
+        fn next_sibling_element(&self) -> Option<Self> {
+            self.sibling(1)
+        }
+
+        fn has_content(&self) -> bool {
+            self.0
+                .nodes
+                .iter()
+                .any(|(_, _, parent)| *parent == Some(self.1))
+        }
+    }
+
+    fn tree() -> Tree {
+        Tree {
+            nodes: vec![
+                ("html", vec![], None),
+                ("body", vec![], Some(0)),
+                ("nav", vec![("class", "menu top")], Some(1)),
+                (
+                    "a",
+                    vec![("href", "https://a.test/x"), ("lang", "en-GB")],
+                    Some(2),
+                ),
+                ("a", vec![("href", "/local"), ("class", "active")], Some(2)),
+                ("p", vec![("id", "intro")], Some(1)),
+                ("span", vec![], Some(5)),
+                ("p", vec![("data-kind", "Note")], Some(1)),
+            ],
+        }
+    }
+
+    fn select(selector: &str) -> Vec<usize> {
+        let tree = tree();
+        let list = SelectorList::parse(selector).unwrap();
+        (0..tree.nodes.len())
+            .filter(|i| list.matches(&Node(&tree, *i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_combinators_and_compounds() {
+        assert_eq!(select("nav.menu.top > a"), vec![3, 4]);
+        assert_eq!(select("body a.active"), vec![4]);
+        assert_eq!(select("html > a"), Vec::<usize>::new());
+        assert_eq!(select("nav + p"), vec![5]);
+        assert_eq!(select("nav ~ p"), vec![5, 7]);
+        assert_eq!(select("#intro span, NAV"), vec![2, 6]);
+        assert_eq!(select("*:root"), vec![0]);
+    }
+
+    #[test]
+    fn test_attribute_selectors() {
+        assert_eq!(select("[href]"), vec![3, 4]);
+        assert_eq!(select("a[href^='https:']"), vec![3]);
+        assert_eq!(select("[href$=\"/local\"]"), vec![4]);
+        assert_eq!(select("[href*=a\\.test]"), vec![3]);
+        assert_eq!(select("[lang|=en]"), vec![3]);
+        assert_eq!(select("[class~=top]"), vec![2]);
+        assert_eq!(select("[data-kind=note]"), Vec::<usize>::new());
+        assert_eq!(select("[data-kind=note i]"), vec![7]);
+    }
+
+    #[test]
+    fn test_structural_pseudo_classes() {
+        assert_eq!(select("a:first-child"), vec![3]);
+        assert_eq!(select("body > :last-child"), vec![7]);
+        assert_eq!(select("p:nth-of-type(2)"), vec![7]);
+        assert_eq!(select("body > :nth-child(2n+1)"), vec![2, 7]);
+        assert_eq!(select("span:only-child"), vec![6]);
+        assert_eq!(select("p:empty"), vec![7]);
+        assert_eq!(select("a:not(.active)"), vec![3]);
+        assert_eq!(select(":is(nav, span):not(:root)"), vec![2, 6]);
+        assert_eq!(select("a:hover, p::before"), Vec::<usize>::new());
+
+        assert!(!SelectorList::parse("a").unwrap().needs_tree());
+        assert!(SelectorList::parse("nav a").unwrap().needs_tree());
+        assert!(SelectorList::parse("li:first-child").unwrap().needs_tree());
+
+        for invalid in [
+            "",
+            "a >",
+            "a,",
+            "[href",
+            ":bogus",
+            "a::before b",
+            "p:nth-child(x)",
+            "#",
+        ] {
+            assert!(SelectorList::parse(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(Nth::parse("-n+3"), Some(Nth { a: -1, b: 3 }));
+        assert!(Nth::parse("-n+3").unwrap().matches(3));
+        assert!(!Nth::parse("-n+3").unwrap().matches(4));
+    }
+}
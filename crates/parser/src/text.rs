@@ -0,0 +1,132 @@
+//! Bidirectional text and complex-script helpers
+//!
+//! Resolves paragraph direction per UAX #9 and flags text that needs a shaping
+//! engine (Arabic joining, Indic conjuncts, Thai clusters...). The renderer's
+//! advanced shaper reorders mixed-direction runs itself.
+//! Layout uses grapheme clusters rather than `char`s for width estimation so
+//! combining marks do not inflate measured text.
+
+use unicode_bidi::Direction;
+use unicode_script::{Script, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Inline base direction of a run of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl TextDirection {
+    /// Parse an HTML `dir` attribute. `auto` (or anything unknown) yields `None`,
+    /// meaning the direction is detected from the text itself.
+    pub fn from_dir_attribute(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ltr" => Some(TextDirection::Ltr),
+            "rtl" => Some(TextDirection::Rtl),
+            _ => None,
+        }
+    }
+
+    /// Whether this is right-to-left
+    pub fn is_rtl(&self) -> bool {
+        *self == TextDirection::Rtl
+    }
+}
+
+/// Detect the base direction from the first strong character (UAX #9 P2/P3).
+/// Text without strong characters is treated as left-to-right.
+pub fn base_direction(text: &str) -> TextDirection {
+    match unicode_bidi::get_base_direction(text) {
+        Direction::Rtl => TextDirection::Rtl,
+        Direction::Ltr | Direction::Mixed => TextDirection::Ltr,
+    }
+}
+
+/// Whether the text contains scripts that a basic glyph-per-char renderer
+/// cannot display correctly
+pub fn requires_complex_shaping(text: &str) -> bool {
+    text.chars().any(|ch| is_complex_script(ch.script()))
+}
+
+/// Number of user-perceived characters (extended grapheme clusters)
+pub fn cluster_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Iterate over grapheme clusters
+pub fn clusters(text: &str) -> impl Iterator<Item = &str> {
+    text.graphemes(true)
+}
+
+/// Scripts needing contextual shaping, reordering or mark positioning
+fn is_complex_script(script: Script) -> bool {
+    matches!(
+        script,
+        Script::Arabic
+            | Script::Hebrew
+            | Script::Syriac
+            | Script::Thaana
+            | Script::Nko
+            | Script::Devanagari
+            | Script::Bengali
+            | Script::Gurmukhi
+            | Script::Gujarati
+            | Script::Oriya
+            | Script::Tamil
+            | Script::Telugu
+            | Script::Kannada
+            | Script::Malayalam
+            | Script::Sinhala
+            | Script::Thai
+            | Script::Lao
+            | Script::Tibetan
+            | Script::Myanmar
+            | Script::Khmer
+            | Script::Mongolian
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_direction() {
+        assert_eq!(base_direction("Hello world"), TextDirection::Ltr);
+        assert_eq!(base_direction("שלום עולם"), TextDirection::Rtl);
+        assert_eq!(base_direction("123 مرحبا"), TextDirection::Rtl);
+        assert_eq!(base_direction("123"), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn test_dir_attribute() {
+        assert_eq!(
+            TextDirection::from_dir_attribute("RTL"),
+            Some(TextDirection::Rtl)
+        );
+        assert_eq!(
+            TextDirection::from_dir_attribute("ltr"),
+            Some(TextDirection::Ltr)
+        );
+        assert_eq!(TextDirection::from_dir_attribute("auto"), None);
+    }
+
+    #[test]
+    fn test_complex_shaping_detection() {
+        assert!(requires_complex_shaping("مرحبا"));
+        assert!(requires_complex_shaping("नमस्ते"));
+        assert!(requires_complex_shaping("สวัสดี"));
+        assert!(!requires_complex_shaping("hello"));
+        assert!(!requires_complex_shaping("日本語"));
+    }
+
+    #[test]
+    fn test_cluster_count_ignores_combining_marks() {
+        // "नमस्ते" is 6 code points but 3 user-perceived characters
+        assert_eq!("नमस्ते".chars().count(), 6);
+        assert_eq!(cluster_count("नमस्ते"), 3);
+        assert_eq!(cluster_count("e\u{301}"), 1);
+    }
+}
@@ -108,18 +108,12 @@ fn test_deep_nesting_protection() {
     html.push_str("</body></html>");
 
     let security_context = create_strict_security_context(); // Low limit
-    let result = parse_html(&html, security_context);
+    let dom = parse_html(&html, security_context).expect("Deep nesting is truncated, not rejected");
 
-    match result {
-        Ok(_dom) => {
-            // If parsing succeeds, the security context should have limited depth
-            println!("Deep nesting was handled by parser limits");
-        }
-        Err(e) => {
-            // Parser rejecting deep nesting is also acceptable
-            println!("Deep nesting properly rejected: {:?}", e);
-        }
-    }
+    // html and body take two levels, so three divs survive and the paragraph is dropped
+    assert!(dom.get_element_by_id("level2").is_some());
+    assert!(dom.get_element_by_id("level3").is_none());
+    assert!(!dom.get_text_content().contains("Deep content"));
 }
 
 #[test]
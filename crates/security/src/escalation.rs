@@ -0,0 +1,325 @@
+//! Escalation on repeated security violations
+//!
+//! A page that keeps tripping the sanitizer, its CSP or the script policy is
+//! either broken or hostile, and in both cases the browser should stop
+//! giving it the benefit of the doubt. [`ViolationEscalator`] counts the
+//! violations of each page (keyed by whatever the caller uses, such as a tab
+//! id) over a sliding window and maps the count onto an [`Escalation`]:
+//! first a warning, then scripts paused, then text-only rendering. Levels
+//! only go up while violations keep coming; once a page has been quiet for
+//! the cooldown it drops back to the level its remaining count warrants.
+
+use crate::context::SecurityViolation;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Where a violation was caught
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ViolationSource {
+    /// The HTML sanitizer removed an element or attribute
+    Sanitizer,
+    /// The Content Security Policy blocked a load
+    Csp,
+    /// The script policy refused to run a script or API call
+    ScriptPolicy,
+    /// A network-level block, such as a mixed-content or host rule
+    Network,
+    /// A resource limit was exceeded
+    Memory,
+}
+
+impl From<&SecurityViolation> for ViolationSource {
+    fn from(violation: &SecurityViolation) -> Self {
+        match violation {
+            SecurityViolation::CspViolation { .. } => Self::Csp,
+            SecurityViolation::BlockedElement { .. }
+            | SecurityViolation::BlockedAttribute { .. } => Self::Sanitizer,
+            SecurityViolation::SuspiciousActivity { .. } => Self::ScriptPolicy,
+            SecurityViolation::MemoryExhaustion { .. } => Self::Memory,
+            SecurityViolation::NetworkSecurity { .. } => Self::Network,
+        }
+    }
+}
+
+/// How hard the browser clamps down on a page
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Escalation {
+    /// Nothing unusual
+    #[default]
+    None,
+    /// Show the user a warning
+    Warn,
+    /// Warn, and run no scripts on the page
+    ScriptsPaused,
+    /// Warn, run no scripts and render the page as plain text
+    TextOnly,
+}
+
+impl Escalation {
+    /// Whether scripts may run at this level
+    pub fn allows_scripts(self) -> bool {
+        self < Self::ScriptsPaused
+    }
+
+    /// Whether the page is rendered as plain text at this level
+    pub fn text_only(self) -> bool {
+        self == Self::TextOnly
+    }
+}
+
+/// When to escalate, in violations within the window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EscalationThresholds {
+    /// Length of the sliding window, in seconds
+    pub window_secs: u64,
+    /// Violations before a warning is shown
+    pub warn_after: u32,
+    /// Violations before scripts are paused
+    pub pause_scripts_after: u32,
+    /// Violations before the page is rendered as plain text
+    pub text_only_after: u32,
+    /// Quiet seconds before an escalated page is reconsidered
+    pub cooldown_secs: u64,
+}
+
+impl Default for EscalationThresholds {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            warn_after: 50,
+            pause_scripts_after: 150,
+            text_only_after: 400,
+            cooldown_secs: 120,
+        }
+    }
+}
+
+impl EscalationThresholds {
+    /// The level `count` violations within the window call for
+    pub fn level_for(&self, count: u32) -> Escalation {
+        if count >= self.text_only_after {
+            Escalation::TextOnly
+        } else if count >= self.pause_scripts_after {
+            Escalation::ScriptsPaused
+        } else if count >= self.warn_after {
+            Escalation::Warn
+        } else {
+            Escalation::None
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs)
+    }
+
+    fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
+}
+
+/// Violations of one page
+#[derive(Debug)]
+struct PageRecord {
+    /// Batches of violations still in the window, oldest first
+    hits: VecDeque<(Instant, ViolationSource, u32)>,
+    level: Escalation,
+    last_violation: Instant,
+}
+
+impl PageRecord {
+    fn prune(&mut self, window: Duration, now: Instant) {
+        while let Some(&(at, _, _)) = self.hits.front() {
+            if now.saturating_duration_since(at) < window {
+                break;
+            }
+            self.hits.pop_front();
+        }
+    }
+
+    fn count(&self) -> u32 {
+        self.hits
+            .iter()
+            .fold(0u32, |total, &(_, _, n)| total.saturating_add(n))
+    }
+}
+
+/// Sliding-window escalation policy over the violations of many pages
+#[derive(Debug)]
+pub struct ViolationEscalator<K> {
+    thresholds: EscalationThresholds,
+    pages: HashMap<K, PageRecord>,
+}
+
+impl<K: Hash + Eq> Default for ViolationEscalator<K> {
+    fn default() -> Self {
+        Self::new(EscalationThresholds::default())
+    }
+}
+
+impl<K: Hash + Eq> ViolationEscalator<K> {
+    /// An escalator with the given thresholds
+    pub fn new(thresholds: EscalationThresholds) -> Self {
+        Self {
+            thresholds,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// The thresholds in force
+    pub fn thresholds(&self) -> &EscalationThresholds {
+        &self.thresholds
+    }
+
+    /// Record `count` violations from `source` against `key`; returns the
+    /// page's level afterwards
+    pub fn record(
+        &mut self,
+        key: K,
+        source: ViolationSource,
+        count: u32,
+        now: Instant,
+    ) -> Escalation {
+        if count == 0 {
+            return self.level(&key, now);
+        }
+        let window = self.thresholds.window();
+        let record = self.pages.entry(key).or_insert_with(|| PageRecord {
+            hits: VecDeque::new(),
+            level: Escalation::None,
+            last_violation: now,
+        });
+        record.prune(window, now);
+        record.hits.push_back((now, source, count));
+        record.last_violation = now;
+        record.level = record.level.max(self.thresholds.level_for(record.count()));
+        record.level
+    }
+
+    /// The level of `key`, after letting a cooldown that has run out lower it
+    pub fn level(&mut self, key: &K, now: Instant) -> Escalation {
+        let window = self.thresholds.window();
+        let cooldown = self.thresholds.cooldown();
+        let Some(record) = self.pages.get_mut(key) else {
+            return Escalation::None;
+        };
+        record.prune(window, now);
+        if now.saturating_duration_since(record.last_violation) >= cooldown {
+            record.level = self.thresholds.level_for(record.count());
+        }
+        record.level
+    }
+
+    /// Violations of `key` still within the window
+    pub fn recent(&mut self, key: &K, now: Instant) -> u32 {
+        let window = self.thresholds.window();
+        self.pages.get_mut(key).map_or(0, |record| {
+            record.prune(window, now);
+            record.count()
+        })
+    }
+
+    /// Violations of `key` within the window, by source
+    pub fn recent_by_source(&mut self, key: &K, now: Instant) -> HashMap<ViolationSource, u32> {
+        let window = self.thresholds.window();
+        let mut counts = HashMap::new();
+        if let Some(record) = self.pages.get_mut(key) {
+            record.prune(window, now);
+            for &(_, source, n) in &record.hits {
+                let total: &mut u32 = counts.entry(source).or_default();
+                *total = total.saturating_add(n);
+            }
+        }
+        counts
+    }
+
+    /// Forget the violations of `key`, as when the user chooses to trust
+    /// the page; it starts again from [`Escalation::None`]
+    pub fn remove(&mut self, key: &K) {
+        self.pages.remove(key);
+    }
+
+    /// Keep only the pages whose key passes `keep`, as when a tab closes
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.pages.retain(|key, _| keep(key));
+    }
+
+    /// Forget every page
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalates_within_window_and_cools_down() {
+        let thresholds = EscalationThresholds {
+            window_secs: 10,
+            warn_after: 3,
+            pause_scripts_after: 5,
+            text_only_after: 8,
+            cooldown_secs: 30,
+        };
+        let mut escalator = ViolationEscalator::new(thresholds);
+        let start = Instant::now();
+
+        assert_eq!(
+            escalator.record(1, ViolationSource::Csp, 2, start),
+            Escalation::None
+        );
+        assert_eq!(
+            escalator.record(1, ViolationSource::Sanitizer, 1, start),
+            Escalation::Warn
+        );
+        let level = escalator.record(1, ViolationSource::ScriptPolicy, 2, start);
+        assert_eq!(level, Escalation::ScriptsPaused);
+        assert!(!level.allows_scripts());
+        assert_eq!(
+            escalator.recent_by_source(&1, start)[&ViolationSource::Csp],
+            2
+        );
+
+        // Violations spread wider than the window never add up.
+        let later = start + Duration::from_secs(11);
+        assert_eq!(escalator.recent(&1, later), 0);
+        assert_eq!(
+            escalator.record(2, ViolationSource::Csp, 2, start),
+            Escalation::None
+        );
+        assert_eq!(
+            escalator.record(2, ViolationSource::Csp, 2, later),
+            Escalation::None
+        );
+
+        // The level holds until the page has been quiet for the cooldown.
+        assert_eq!(escalator.level(&1, later), Escalation::ScriptsPaused);
+        let cooled = start + Duration::from_secs(30);
+        assert_eq!(escalator.level(&1, cooled), Escalation::None);
+
+        assert!(escalator
+            .record(3, ViolationSource::Sanitizer, 9, start)
+            .text_only());
+        escalator.retain(|&key| key != 3);
+        assert_eq!(escalator.level(&3, start), Escalation::None);
+    }
+
+    #[test]
+    fn test_violation_sources() {
+        let violation = SecurityViolation::BlockedAttribute {
+            attribute_name: "onclick".into(),
+            element_name: "div".into(),
+            source_url: "https://example.com".into(),
+        };
+        assert_eq!(
+            ViolationSource::from(&violation),
+            ViolationSource::Sanitizer
+        );
+    }
+}
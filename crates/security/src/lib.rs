@@ -5,8 +5,10 @@
 
 pub mod context;
 pub mod error;
+pub mod escalation;
 pub mod memory;
 pub mod privacy;
+pub mod secrets;
 // pub mod policy; // Potential future module
 
 pub use context::{
@@ -15,6 +17,7 @@ pub use context::{
     SecurityViolation, UrlScheme,
 };
 pub use error::{SecurityError, SecurityResult, SecuritySeverity};
+pub use escalation::{Escalation, EscalationThresholds, ViolationEscalator, ViolationSource};
 pub use memory::{
     AttackPattern, MemoryProtectionBuilder, MemoryProtectionConfig, MemoryProtectionError,
     MemoryProtectionResult, MemoryProtectionSystem, ResourcePoolConfig, ResourcePoolStats,
@@ -24,3 +27,4 @@ pub use privacy::{
     create_privacy_channel, create_privacy_channel_with_capacity, PrivacyEvent,
     PrivacyEventReceiver, PrivacyEventSender, PrivacyStats, TrackerCategory,
 };
+pub use secrets::{ct_eq, Redacted, SecretBytes, SecretKey, SecretString};
@@ -0,0 +1,184 @@
+//! Handling of secrets in memory
+//!
+//! Keys, tokens and passphrases are held in types that zeroize themselves
+//! when dropped and never print their contents: [`SecretKey`] for 32-byte
+//! keys, [`SecretBytes`] and [`SecretString`] for everything else. They
+//! compare in constant time, as does [`ct_eq`] for secrets that arrive as
+//! plain bytes, such as a MAC taken off the wire. [`Redacted`] hides any
+//! other value from `Debug` output, for structs that derive it.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Whether `a` and `b` are equal, taking the same time wherever they
+/// differ. Only their lengths are compared in variable time.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// A 32-byte key, zeroized on drop
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+    /// Take ownership of `bytes`
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// A fresh random key
+    pub fn generate() -> Self {
+        let mut key = Self([0u8; 32]);
+        rand::thread_rng().fill_bytes(&mut key.0);
+        key
+    }
+
+    /// The key bytes, for handing to a cipher
+    pub fn expose(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The key bytes, for filling in place
+    pub fn expose_mut(&mut self) -> &mut [u8; 32] {
+        &mut self.0
+    }
+}
+
+impl From<[u8; 32]> for SecretKey {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// A secret byte string of any length, zeroized on drop
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    /// Take ownership of `bytes`
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// The secret bytes
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no bytes
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+/// A secret string, such as a token or passphrase, zeroized on drop.
+/// Serializes as the plain string, for stores that seal it themselves.
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Take ownership of `secret`
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// The secret text
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the secret is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+macro_rules! secret_traits {
+    ($name:ident) => {
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, concat!(stringify!($name), "([REDACTED])"))
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                ct_eq(
+                    AsRef::<[u8]>::as_ref(&self.0),
+                    AsRef::<[u8]>::as_ref(&other.0),
+                )
+            }
+        }
+
+        impl Eq for $name {}
+    };
+}
+
+secret_traits!(SecretKey);
+secret_traits!(SecretBytes);
+secret_traits!(SecretString);
+
+/// Hides a value from `Debug` output; it is otherwise used as is
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Redacted<T>(pub T);
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T> std::ops::Deref for Redacted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_compare_and_print_safely() {
+        assert!(ct_eq(b"token", b"token"));
+        assert!(!ct_eq(b"token", b"tokem"));
+        assert!(!ct_eq(b"token", b"token!"));
+
+        let key = SecretKey::from_bytes([0x42; 32]);
+        assert_eq!(key, SecretKey::from([0x42; 32]));
+        assert_ne!(key, SecretKey::generate());
+        assert_eq!(format!("{:?}", key), "SecretKey([REDACTED])");
+
+        let password = SecretString::new("hunter2".into());
+        assert!(!format!("{:?}", password).contains("hunter2"));
+        assert_eq!(password.expose(), "hunter2");
+
+        let bytes = SecretBytes::from(b"cookie".to_vec());
+        assert_eq!(bytes.len(), 6);
+        assert!(!format!("{:?}", Redacted(bytes.expose())).contains("99"));
+    }
+}
@@ -15,7 +15,7 @@
 
 use citadel_tabs::zkvm_renderer::spawn_zkvm_renderer;
 use citadel_tabs::{DisplayKind, RenderRequest, RenderedContent};
-use citadel_zkvm::{Channel, ChannelMessage};
+use citadel_zkvm::{ChannelMessage, MuxChannel, StreamId, DEFAULT_STREAM_WINDOW};
 use std::time::Duration;
 
 /// Verbatim structure of the example.com document.
@@ -50,7 +50,7 @@ async fn render_in_zk_tab(
     url: &str,
     html: String,
 ) -> Result<RenderedContent, Box<dyn std::error::Error>> {
-    let (mut host_side, vm_side) = Channel::new()?;
+    let (host_side, vm_side) = MuxChannel::pair(DEFAULT_STREAM_WINDOW)?;
     tokio::spawn(async move {
         if let Err(e) = spawn_zkvm_renderer(vm_side).await {
             eprintln!("renderer task error: {e}");
@@ -62,15 +62,25 @@ async fn render_in_zk_tab(
         html,
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     };
     host_side
-        .send(ChannelMessage::Control {
-            command: "render_page".to_string(),
-            params: serde_json::to_string(&request)?,
-        })
+        .send_on(
+            StreamId::Renderer,
+            ChannelMessage::Control {
+                command: "render_page".to_string(),
+                params: serde_json::to_string(&request)?,
+            },
+        )
         .await?;
 
-    let message = tokio::time::timeout(Duration::from_secs(15), host_side.receive()).await??;
+    let message = tokio::time::timeout(
+        Duration::from_secs(15),
+        host_side.receive(StreamId::Renderer),
+    )
+    .await??;
     match message {
         ChannelMessage::Control { command, params } if command == "rendered_content" => {
             Ok(serde_json::from_str(&params)?)
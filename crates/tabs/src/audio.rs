@@ -0,0 +1,112 @@
+//! Tab audio state
+//!
+//! Citadel cannot play media yet, but tabs already track whether their page
+//! would make sound and whether the user muted them:
+//!
+//! - The renderer reports a page with media elements through an
+//!   [`AUDIO_STATE_COMMAND`] control message, which the tab manager records
+//!   as [`TabState::is_audible`](crate::TabState::is_audible).
+//! - Muting a tab records [`TabState::is_muted`](crate::TabState::is_muted)
+//!   and sends [`SET_MUTED_COMMAND`] into the tab's VM. The media pipeline
+//!   must check [`may_play_audio`] before producing any sound.
+
+#[cfg(feature = "zkvm-isolation")]
+use citadel_zkvm::ChannelMessage;
+
+use crate::TabState;
+
+/// Renderer → host: the page would start (or stopped) playing audio
+pub const AUDIO_STATE_COMMAND: &str = "audio_state";
+
+/// Host → renderer: the user muted or unmuted the tab
+pub const SET_MUTED_COMMAND: &str = "set_muted";
+
+/// Elements that play sound once media support lands
+pub const MEDIA_ELEMENTS: &[&str] = &["audio", "video"];
+
+/// Message reporting whether the page would play audio
+#[cfg(feature = "zkvm-isolation")]
+pub fn audio_state_message(audible: bool) -> ChannelMessage {
+    ChannelMessage::Control {
+        command: AUDIO_STATE_COMMAND.to_string(),
+        params: serde_json::json!({ "audible": audible }).to_string(),
+    }
+}
+
+/// Message telling the tab's VM whether it is muted
+#[cfg(feature = "zkvm-isolation")]
+pub fn set_muted_message(muted: bool) -> ChannelMessage {
+    ChannelMessage::Control {
+        command: SET_MUTED_COMMAND.to_string(),
+        params: serde_json::json!({ "muted": muted }).to_string(),
+    }
+}
+
+/// The audible flag of an [`AUDIO_STATE_COMMAND`] message
+#[cfg(feature = "zkvm-isolation")]
+pub fn parse_audio_state(message: &ChannelMessage) -> Option<bool> {
+    control_flag(message, AUDIO_STATE_COMMAND, "audible")
+}
+
+/// The muted flag of a [`SET_MUTED_COMMAND`] message
+#[cfg(feature = "zkvm-isolation")]
+pub fn parse_set_muted(message: &ChannelMessage) -> Option<bool> {
+    control_flag(message, SET_MUTED_COMMAND, "muted")
+}
+
+#[cfg(feature = "zkvm-isolation")]
+fn control_flag(message: &ChannelMessage, expected: &str, key: &str) -> Option<bool> {
+    let ChannelMessage::Control { command, params } = message else {
+        return None;
+    };
+    if command != expected {
+        return None;
+    }
+    serde_json::from_str::<serde_json::Value>(params)
+        .ok()?
+        .get(key)?
+        .as_bool()
+}
+
+/// Whether the media pipeline may produce sound in this tab
+pub fn may_play_audio(tab: &TabState) -> bool {
+    !tab.is_muted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "zkvm-isolation")]
+    #[test]
+    fn test_audio_messages_round_trip() {
+        assert_eq!(parse_audio_state(&audio_state_message(true)), Some(true));
+        assert_eq!(parse_set_muted(&set_muted_message(false)), Some(false));
+        assert_eq!(parse_audio_state(&set_muted_message(true)), None);
+        assert_eq!(
+            parse_audio_state(&ChannelMessage::Control {
+                command: AUDIO_STATE_COMMAND.to_string(),
+                params: "not json".to_string(),
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_renderer_detects_media() {
+        let render = |html: &str| {
+            crate::render_in_isolation(&crate::RenderRequest {
+                url: "https://radio.example/".to_string(),
+                html: html.to_string(),
+                viewport_width: 800.0,
+                enable_scripts: false,
+                user_css: String::new(),
+                content_scripts: Vec::new(),
+                compat_script: String::new(),
+            })
+        };
+        assert!(render("<p>Live</p><audio src=\"stream.ogg\" autoplay></audio>").plays_audio);
+        assert!(render("<div><VIDEO></VIDEO></div>").plays_audio);
+        assert!(!render("<p>Silent page</p>").plays_audio);
+    }
+}
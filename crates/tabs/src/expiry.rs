@@ -0,0 +1,123 @@
+//! Idle expiry for ephemeral tabs
+//!
+//! Ephemeral tabs left untouched in the background for longer than the idle
+//! timeout are terminated: their VM is zeroized and the tab is replaced by an
+//! "expired" placeholder the user can reopen. Container tabs and the active tab
+//! never expire.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::{PageContent, TabState, TabType};
+
+/// How often the reaper runs at most
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When idle ephemeral tabs are terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    /// Whether idle tabs expire at all
+    pub enabled: bool,
+    /// Time since the tab was last active after which it expires
+    pub idle_timeout: Duration,
+}
+
+impl ExpiryPolicy {
+    /// Policy with the given idle timeout
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            enabled: true,
+            idle_timeout,
+        }
+    }
+
+    /// Policy that never expires tabs
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    /// How often the reaper should look for idle tabs
+    pub fn check_interval(&self) -> Duration {
+        (self.idle_timeout / 4).clamp(Duration::from_millis(100), MAX_CHECK_INTERVAL)
+    }
+
+    /// Whether a tab is due to expire at `now`
+    pub fn should_expire(&self, tab: &TabState, now: DateTime<Utc>) -> bool {
+        if !self.enabled || tab.is_active || tab.tab_type != TabType::Ephemeral {
+            return false;
+        }
+        if matches!(tab.content, PageContent::Expired { .. }) {
+            return false;
+        }
+        tab.idle_for(now)
+            .to_std()
+            .is_ok_and(|idle| idle >= self.idle_timeout)
+    }
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn tab(tab_type: TabType, is_active: bool, idle: chrono::Duration) -> TabState {
+        let now = Utc::now();
+        TabState {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            url: "https://example.com".to_string(),
+            tab_type,
+            is_active,
+            created_at: now - idle,
+            last_active_at: now - idle,
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content: PageContent::Empty,
+        }
+    }
+
+    #[test]
+    fn test_only_idle_background_ephemeral_tabs_expire() {
+        let policy = ExpiryPolicy::new(Duration::from_secs(600));
+        let now = Utc::now();
+        let long = chrono::Duration::minutes(11);
+        let short = chrono::Duration::minutes(5);
+
+        assert!(policy.should_expire(&tab(TabType::Ephemeral, false, long), now));
+        assert!(!policy.should_expire(&tab(TabType::Ephemeral, false, short), now));
+        assert!(!policy.should_expire(&tab(TabType::Ephemeral, true, long), now));
+
+        let container = TabType::Container {
+            container_id: Uuid::new_v4(),
+        };
+        assert!(!policy.should_expire(&tab(container, false, long), now));
+
+        let mut expired = tab(TabType::Ephemeral, false, long);
+        expired.content = PageContent::Expired {
+            url: expired.url.clone(),
+        };
+        assert!(!policy.should_expire(&expired, now));
+
+        assert!(!ExpiryPolicy::disabled().should_expire(&tab(TabType::Ephemeral, false, long), now));
+    }
+
+    #[test]
+    fn test_check_interval_is_bounded() {
+        assert_eq!(ExpiryPolicy::default().check_interval(), MAX_CHECK_INTERVAL);
+        assert_eq!(
+            ExpiryPolicy::new(Duration::from_secs(8)).check_interval(),
+            Duration::from_secs(2)
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Tab manager for builds without `zkvm-isolation`
+//!
+//! Without tab VMs there is nothing to start, throttle or terminate per tab:
+//! pages are rendered in-process by the host through
+//! [`render_in_isolation`](crate::render_in_isolation). This manager keeps
+//! the same tab states, activation, expiry and grouping as the VM-backed one,
+//! under the same name and methods, so the browser drives either the same
+//! way.
+
+use crate::{
+    ExpiryPolicy, PageContent, TabError, TabMatch, TabResult, TabShutdown, TabState, TabType,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// In-process stand-in for the VM-backed tab manager
+#[derive(Clone)]
+pub struct SendSafeTabManager {
+    tab_states: Arc<RwLock<Vec<TabState>>>,
+    policy: Arc<Mutex<ExpiryPolicy>>,
+}
+
+impl SendSafeTabManager {
+    /// Create a new tab manager
+    pub fn new() -> Self {
+        Self::with_expiry_policy(ExpiryPolicy::default())
+    }
+
+    /// Create a tab manager that expires idle ephemeral tabs per `policy`
+    pub fn with_expiry_policy(policy: ExpiryPolicy) -> Self {
+        let manager = Self {
+            tab_states: Arc::new(RwLock::new(Vec::new())),
+            policy: Arc::new(Mutex::new(policy)),
+        };
+
+        // Reaper: periodically expire idle tabs; stops once the manager is dropped
+        if policy.enabled {
+            let states = Arc::downgrade(&manager.tab_states);
+            let expiry = manager.policy.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(policy.check_interval());
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let Some(states) = states.upgrade() else {
+                        break;
+                    };
+                    let policy = *expiry.lock();
+                    Self::reap(&mut states.write().await, &policy);
+                }
+            });
+        }
+
+        manager
+    }
+
+    /// Expire the tabs `policy` says are idle; returns their ids
+    fn reap(states: &mut [TabState], policy: &ExpiryPolicy) -> Vec<Uuid> {
+        let now = chrono::Utc::now();
+        let mut expired = Vec::new();
+        for state in states
+            .iter_mut()
+            .filter(|state| policy.should_expire(state, now))
+        {
+            Self::expire(state);
+            log::info!("⏳ Expired idle ephemeral tab {}", state.id);
+            expired.push(state.id);
+        }
+        expired
+    }
+
+    /// Drop a tab's page and leave it expired, to be reopened
+    fn expire(state: &mut TabState) {
+        state.content = PageContent::Expired {
+            url: state.url.clone(),
+        };
+        state.title = "Expired".to_string();
+        state.is_audible = false;
+    }
+
+    /// Run `f` on a tab's state, or fail if there is no such tab
+    async fn with_tab<T>(
+        &self,
+        tab_id: Uuid,
+        f: impl FnOnce(&mut TabState) -> TabResult<T>,
+    ) -> TabResult<T> {
+        let mut states = self.tab_states.write().await;
+        match states.iter_mut().find(|state| state.id == tab_id) {
+            Some(state) => f(state),
+            None => Err(TabError::NotFound(tab_id)),
+        }
+    }
+
+    /// Open a new tab; the first tab opened becomes the active one
+    pub async fn open_tab(&self, url: String, tab_type: TabType) -> TabResult<Uuid> {
+        let mut states = self.tab_states.write().await;
+        let state = TabState {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            url: url.clone(),
+            tab_type,
+            is_active: states.is_empty(),
+            created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content: PageContent::Loading { url },
+        };
+        let tab_id = state.id;
+        states.push(state);
+        log::info!("Created in-process tab {}", tab_id);
+        Ok(tab_id)
+    }
+
+    /// Close a tab
+    pub async fn close_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let mut states = self.tab_states.write().await;
+        let index = states
+            .iter()
+            .position(|state| state.id == tab_id)
+            .ok_or(TabError::NotFound(tab_id))?;
+        let was_active = states.remove(index).is_active;
+
+        // If we closed the active tab, make the first remaining tab active
+        if let (true, Some(first)) = (was_active, states.first_mut()) {
+            first.is_active = true;
+        }
+        log::info!("Closed in-process tab {}", tab_id);
+        Ok(())
+    }
+
+    /// Forget all tabs without persisting any state. Returns the ids of the
+    /// wiped tabs.
+    pub async fn wipe_all_tabs(&self) -> TabResult<Vec<Uuid>> {
+        let mut states = self.tab_states.write().await;
+        let wiped: Vec<Uuid> = states.iter().map(|state| state.id).collect();
+        states.clear();
+        log::warn!("Wiped {} tabs", wiped.len());
+        Ok(wiped)
+    }
+
+    /// Forget every tab. No VM runs, so nothing is terminated or waited
+    /// for and the report is empty.
+    pub async fn shutdown(&self, _timeout: Duration) -> TabResult<TabShutdown> {
+        self.tab_states.write().await.clear();
+        Ok(TabShutdown::default())
+    }
+
+    /// Expire idle tabs per `policy` from now on
+    pub async fn set_expiry_policy(&self, policy: ExpiryPolicy) -> TabResult<()> {
+        *self.policy.lock() = policy;
+        Ok(())
+    }
+
+    /// Accepted for parity with the VM-backed manager; with no VMs there is
+    /// no tab budget to tighten
+    pub async fn set_power_saving(&self, _enabled: bool) -> TabResult<()> {
+        Ok(())
+    }
+
+    /// Make a tab the active one
+    pub async fn switch_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let mut states = self.tab_states.write().await;
+        if !states.iter().any(|state| state.id == tab_id) {
+            return Err(TabError::NotFound(tab_id));
+        }
+        for state in states.iter_mut() {
+            state.is_active = state.id == tab_id;
+            if state.is_active {
+                state.touch();
+            }
+        }
+        Ok(())
+    }
+
+    /// Tab states for synchronous callers such as view code; empty while
+    /// the states are being written
+    pub fn get_tab_states(&self) -> Vec<TabState> {
+        match self.tab_states.try_read() {
+            Ok(states) => states.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Open tabs whose title or URL fuzzy-matches `query`, best first,
+    /// including expired tabs
+    pub fn search_tabs(&self, query: &str, limit: usize) -> Vec<TabMatch> {
+        crate::switcher::search(&self.get_tab_states(), query, limit)
+    }
+
+    /// Convert a tab to a container
+    pub async fn convert_to_container(&self, tab_id: Uuid) -> TabResult<()> {
+        self.with_tab(tab_id, |state| match state.tab_type {
+            TabType::Ephemeral => {
+                state.tab_type = TabType::Container {
+                    container_id: Uuid::new_v4(),
+                };
+                Ok(())
+            }
+            TabType::Container { .. } => Err(TabError::InvalidOperation(
+                "Tab is already a container".into(),
+            )),
+        })
+        .await
+    }
+
+    /// Update page content for a tab
+    pub async fn update_page_content(&self, tab_id: Uuid, content: PageContent) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            // A new page starts silent
+            if matches!(content, PageContent::Loading { .. }) {
+                state.is_audible = false;
+            }
+            if let PageContent::Loaded { title, .. } = &content {
+                state.title = title.clone();
+            }
+            state.content = content;
+            state.touch();
+            Ok(())
+        })
+        .await
+    }
+
+    /// Expire idle ephemeral tabs now rather than waiting for the reaper.
+    /// Returns the ids of the tabs that expired.
+    pub async fn expire_idle_tabs(&self) -> TabResult<Vec<Uuid>> {
+        let policy = *self.policy.lock();
+        Ok(Self::reap(&mut self.tab_states.write().await, &policy))
+    }
+
+    /// Bring back an expired tab; the caller then navigates it to its URL
+    pub async fn reopen_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            if !matches!(state.content, PageContent::Expired { .. }) {
+                return Err(TabError::InvalidOperation("Tab has not expired".into()));
+            }
+            state.content = PageContent::Empty;
+            state.title = String::new();
+            state.touch();
+            log::info!("Reopened expired tab {}", tab_id);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Expire a background tab now, as the reaper would once it idled,
+    /// whatever its type; [`reopen_tab`](Self::reopen_tab) brings it back
+    pub async fn hibernate_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            if state.is_active || matches!(state.content, PageContent::Expired { .. }) {
+                return Err(TabError::InvalidOperation(
+                    "Only a background tab can hibernate".into(),
+                ));
+            }
+            Self::expire(state);
+            log::info!("💤 Hibernated tab {}", tab_id);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Put a tab in the named group, or take it out of its group with `None`
+    pub async fn set_tab_group(&self, tab_id: Uuid, group: Option<String>) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            state.group = group;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Record whether a tab's page would be playing sound
+    pub async fn set_audible(&self, tab_id: Uuid, audible: bool) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            state.is_audible = audible;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Mute or unmute a tab
+    pub async fn set_muted(&self, tab_id: Uuid, muted: bool) -> TabResult<()> {
+        self.with_tab(tab_id, |state| {
+            state.is_muted = muted;
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl Default for SendSafeTabManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tabs_activate_expire_and_reopen() {
+        let manager = SendSafeTabManager::with_expiry_policy(ExpiryPolicy::disabled());
+        let first = manager
+            .open_tab("https://a.test/".into(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let second = manager
+            .open_tab("https://b.test/".into(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let active = |manager: &SendSafeTabManager| {
+            manager
+                .get_tab_states()
+                .into_iter()
+                .find(|state| state.is_active)
+                .map(|state| state.id)
+        };
+        assert_eq!(active(&manager), Some(first));
+
+        manager.hibernate_tab(second).await.unwrap();
+        assert!(manager.hibernate_tab(first).await.is_err());
+        manager.reopen_tab(second).await.unwrap();
+        assert!(manager.reopen_tab(second).await.is_err());
+
+        manager.switch_tab(second).await.unwrap();
+        manager.close_tab(second).await.unwrap();
+        assert_eq!(active(&manager), Some(first));
+        assert!(matches!(
+            manager.close_tab(second).await,
+            Err(TabError::NotFound(_))
+        ));
+    }
+}
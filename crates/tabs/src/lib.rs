@@ -3,27 +3,60 @@
 //! This module implements privacy-focused tab management with ZKVM isolation.
 //! Each tab runs in its own Zero-Knowledge Virtual Machine, providing cryptographic
 //! guarantees of isolation between tabs.
-
+//!
+//! The VMs come with the `zkvm-isolation` feature. Without it the crate is
+//! the rendering core and tab bookkeeping only: pages are rendered in-process
+//! with [`render_in_isolation`], and [`SendSafeTabManager`] tracks tabs
+//! without starting a VM for any of them.
+
+pub mod audio;
+mod expiry;
+#[cfg(not(feature = "zkvm-isolation"))]
+mod in_process;
+mod policy;
+#[cfg(feature = "zkvm-isolation")]
+mod resource_broker;
+#[cfg(feature = "zkvm-isolation")]
 mod send_safe_tab_manager;
+mod switcher;
 mod ui;
+#[cfg(feature = "zkvm-isolation")]
+mod vm_pool;
+mod vm_reuse;
 pub mod zkvm_renderer;
 
-use citadel_zkvm::{Channel, ChannelMessage, ZkVm};
+use citadel_errors::{Classify, ErrorKind, Severity};
+#[cfg(feature = "zkvm-isolation")]
+use citadel_zkvm::{ChannelMessage, ExecutionBudget, MuxChannel, ZkVm};
 use parking_lot::RwLock as ParkingLotRwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use thiserror::Error;
+#[cfg(feature = "zkvm-isolation")]
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+pub use expiry::ExpiryPolicy;
+pub use policy::VmPolicy;
 // Re-export UI components
-pub use ui::{Message as TabMessage, TabBar};
+pub use ui::{speaker_indicator, Message as TabMessage, TabBar};
 
 // Re-export the Send-safe tab manager for browser use
+#[cfg(not(feature = "zkvm-isolation"))]
+pub use in_process::SendSafeTabManager;
+#[cfg(feature = "zkvm-isolation")]
 pub use send_safe_tab_manager::SendSafeTabManager;
+// The host side of the tabs' resource requests
+#[cfg(feature = "zkvm-isolation")]
+pub use resource_broker::{ResourceBroker, RESPONSE_CHUNK_SIZE};
+pub use switcher::TabMatch;
+#[cfg(feature = "zkvm-isolation")]
+use vm_pool::WarmVm;
+pub use vm_reuse::{VmReuse, DEFAULT_TABS_PER_VM};
 // Re-export zkvm_renderer types
 pub use zkvm_renderer::{
-    render_in_isolation, DisplayItem, DisplayKind, RenderRequest, RenderedContent, SecurityMetadata,
+    render_in_isolation, DisplayItem, DisplayKind, FocusStyle, RenderRequest, RenderedContent,
+    SecurityMetadata,
 };
 
 /// Errors that can occur during tab operations
@@ -32,9 +65,13 @@ pub enum TabError {
     #[error("Tab not found: {0}")]
     NotFound(Uuid),
 
+    #[cfg(feature = "zkvm-isolation")]
     #[error("VM error: {0}")]
     VmError(#[from] citadel_zkvm::ZkVmError),
 
+    #[error("Parser error: {0}")]
+    ParserError(#[from] citadel_parser::ParserError),
+
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
@@ -42,6 +79,39 @@ pub enum TabError {
     PersistenceError(String),
 }
 
+impl Classify for TabError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "zkvm-isolation")]
+            TabError::VmError(e) => e.kind(),
+            TabError::ParserError(e) => e.kind(),
+            TabError::NotFound(_)
+            | TabError::InvalidOperation(_)
+            | TabError::PersistenceError(_) => ErrorKind::Internal,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "zkvm-isolation")]
+            TabError::VmError(e) => e.code(),
+            TabError::ParserError(e) => e.code(),
+            TabError::NotFound(_) => "TAB_NOT_FOUND",
+            TabError::InvalidOperation(_) => "TAB_INVALID_OPERATION",
+            TabError::PersistenceError(_) => "TAB_PERSISTENCE",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self {
+            #[cfg(feature = "zkvm-isolation")]
+            TabError::VmError(e) => e.severity(),
+            TabError::ParserError(e) => e.severity(),
+            _ => self.kind().default_severity(),
+        }
+    }
+}
+
 /// Result type for tab operations
 pub type TabResult<T> = Result<T, TabError>;
 
@@ -72,6 +142,8 @@ pub enum PageContent {
     },
     /// Page failed to load
     Error { url: String, error: String },
+    /// Ephemeral tab was terminated after being idle; can be reopened
+    Expired { url: String },
     /// Empty tab
     Empty,
 }
@@ -91,18 +163,54 @@ pub struct TabState {
     pub is_active: bool,
     /// Tab creation timestamp
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// When the tab was last active or updated, for idle expiry
+    #[serde(default = "chrono::Utc::now")]
+    pub last_active_at: chrono::DateTime<chrono::Utc>,
+    /// Whether the page would be playing sound
+    #[serde(default)]
+    pub is_audible: bool,
+    /// Whether the user muted the tab; the media pipeline must stay silent
+    #[serde(default)]
+    pub is_muted: bool,
+    /// Tab group the user put the tab in, by name
+    #[serde(default)]
+    pub group: Option<String>,
     /// Page content state
     pub content: PageContent,
 }
 
+impl TabState {
+    /// Time since the tab was last active
+    pub fn idle_for(&self, now: chrono::DateTime<chrono::Utc>) -> chrono::Duration {
+        now - self.last_active_at
+    }
+
+    /// Record activity on the tab
+    pub fn touch(&mut self) {
+        self.last_active_at = chrono::Utc::now();
+    }
+}
+
+/// How the tab manager's shutdown went
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TabShutdown {
+    /// Tabs whose VM was terminated, zeroizing its memory
+    pub terminated: usize,
+    /// Tabs whose container state could not be flushed in time
+    pub unflushed: Vec<Uuid>,
+    /// Tabs whose VM did not terminate in time
+    pub timed_out: Vec<Uuid>,
+}
+
 /// Represents a browser tab with ZKVM isolation
+#[cfg(feature = "zkvm-isolation")]
 pub struct Tab {
     /// Tab state
     state: Arc<RwLock<TabState>>,
     /// The ZKVM instance for this tab
     vm: Arc<ZkVm>,
-    /// Communication channel to the VM
-    channel: Channel,
+    /// The host's end of the VM's channel, shared with the tab manager
+    channel: MuxChannel,
 }
 
 /// Simple tab implementation for browser compatibility
@@ -252,6 +360,10 @@ impl Default for SimpleTabManager {
 }
 
 /// Manages all browser tabs
+///
+/// Locks are always taken in the order `active_tab`, `tabs`, then a tab's
+/// own state, and none is held across a VM termination.
+#[cfg(feature = "zkvm-isolation")]
 pub struct TabManager {
     /// All active tabs
     tabs: Arc<RwLock<Vec<Tab>>>,
@@ -259,17 +371,26 @@ pub struct TabManager {
     active_tab: Arc<RwLock<Option<Uuid>>>,
 }
 
+#[cfg(feature = "zkvm-isolation")]
 impl Tab {
     /// Create a new tab
-    pub async fn new(url: String, tab_type: TabType) -> TabResult<(Self, Channel)> {
-        // Create a new ZKVM instance for this tab
-        let (vm, _host_channel) = ZkVm::new().await?;
-
-        // Create a channel pair for tab-host communication
-        let (tab_channel, _host_channel) = Channel::new()?;
+    pub async fn new(url: String, tab_type: TabType) -> TabResult<(Self, MuxChannel)> {
+        Self::with_vm(url, tab_type, WarmVm::new().await?, &VmPolicy::default()).await
+    }
 
-        // Create another channel pair for renderer communication
-        let (renderer_vm_channel, renderer_host_channel) = Channel::new()?;
+    /// Create a tab around a never-used VM, such as one from the warm pool,
+    /// started on a capsule of `policy`. Returns the tab with a handle to the
+    /// host's end of its channel, which carries the control, renderer and
+    /// resource streams.
+    pub(crate) async fn with_vm(
+        url: String,
+        tab_type: TabType,
+        warm: WarmVm,
+        policy: &VmPolicy,
+    ) -> TabResult<(Self, MuxChannel)> {
+        let WarmVm { vm, host_channel } = warm;
+        // The renderer runs on the VM's end
+        let guest_channel = vm.guest_channel();
 
         let state = TabState {
             id: Uuid::new_v4(),
@@ -278,6 +399,10 @@ impl Tab {
             tab_type,
             is_active: false,
             created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
             content: PageContent::Loading { url },
         };
 
@@ -286,21 +411,53 @@ impl Tab {
         let tab = Self {
             state: Arc::new(RwLock::new(state)),
             vm: Arc::new(vm),
-            channel: tab_channel,
+            channel: host_channel.clone(),
         };
 
-        // Start the VM
-        tab.vm.start().await?;
+        // Start the VM on its signed policy; the renderer enforces the policy
+        // the VM verified, not the host's copy
+        tab.vm.start(tab.vm.seal_policy(policy)?).await?;
+        let policy: VmPolicy = tab.vm.policy().await?;
 
         // Spawn the ZKVM renderer task
         tokio::spawn(async move {
             log::info!("Starting ZKVM renderer for tab {}", tab_id);
-            if let Err(e) = zkvm_renderer::spawn_zkvm_renderer(renderer_vm_channel).await {
+            if let Err(e) =
+                zkvm_renderer::spawn_zkvm_renderer_with_policy(guest_channel, policy).await
+            {
                 log::error!("ZKVM renderer error for tab {}: {}", tab_id, e);
             }
         });
 
-        Ok((tab, renderer_host_channel))
+        Ok((tab, host_channel))
+    }
+
+    /// Create a tab in a VM other tabs already run in, as the VM reuse
+    /// policy allows. The VM is running its renderer already.
+    pub(crate) fn sharing(
+        url: String,
+        tab_type: TabType,
+        vm: Arc<ZkVm>,
+        channel: MuxChannel,
+    ) -> Self {
+        let state = TabState {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            url: url.clone(),
+            tab_type,
+            is_active: false,
+            created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content: PageContent::Loading { url },
+        };
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            vm,
+            channel,
+        }
     }
 
     /// Convert tab type (with user warning)
@@ -348,17 +505,70 @@ impl Tab {
         Ok(())
     }
 
+    /// Throttle the tab's VM while it is in the background, or restore the full
+    /// budget when it becomes active again
+    pub async fn set_background(&self, background: bool) {
+        self.set_budget(if background {
+            ExecutionBudget::background()
+        } else {
+            ExecutionBudget::foreground()
+        })
+        .await;
+    }
+
+    /// Run the tab's VM under `budget`, such as the power-saving budget for
+    /// a background tab on battery
+    pub async fn set_budget(&self, budget: ExecutionBudget) {
+        if self.vm.execution_budget().await != budget {
+            self.vm.set_execution_budget(budget).await;
+            log::debug!(
+                "Tab {} {}",
+                self.state.read().await.id,
+                if budget.is_throttled() {
+                    "throttled"
+                } else {
+                    "restored"
+                }
+            );
+        }
+    }
+
+    /// Budget the tab's VM currently runs with
+    pub async fn execution_budget(&self) -> ExecutionBudget {
+        self.vm.execution_budget().await
+    }
+
+    /// Whether the tab's VM currently runs with a throttled budget
+    pub async fn is_throttled(&self) -> bool {
+        self.vm.execution_budget().await.is_throttled()
+    }
+
     /// Close the tab
     pub async fn close(&self) -> TabResult<()> {
         // Terminate the VM
         self.vm.terminate().await?;
 
         // If this is a container tab, persist its state
-        let state = self.state.read().await;
-        if let TabType::Container { container_id } = state.tab_type {
+        self.flush().await
+    }
+
+    /// Persist a container tab's state; ephemeral tabs have none to keep
+    pub async fn flush(&self) -> TabResult<()> {
+        let tab_type = self.state.read().await.tab_type;
+        if let TabType::Container { container_id } = tab_type {
             self.persist_container_state(container_id).await?;
         }
+        Ok(())
+    }
 
+    /// Terminate the VM without persisting anything, and drop the page
+    /// from the tab's state
+    pub async fn wipe(&self) -> TabResult<()> {
+        self.vm.terminate().await?;
+        let mut state = self.state.write().await;
+        state.content = PageContent::Empty;
+        state.title.clear();
+        state.url.clear();
         Ok(())
     }
 
@@ -369,10 +579,9 @@ impl Tab {
     }
 }
 
+#[cfg(feature = "zkvm-isolation")]
 impl TabManager {
     /// Create a new tab manager
-    /// NOTE: This implementation is currently disabled due to Send safety issues.
-    /// Use SendSafeTabManager instead for browser integration.
     pub fn new() -> Self {
         Self {
             tabs: Arc::new(RwLock::new(Vec::new())),
@@ -380,10 +589,97 @@ impl TabManager {
         }
     }
 
-    /// Get all tab states (simplified version for compatibility)
+    /// Open a ZKVM tab and return its id with the host end of its channel.
+    /// The first tab opened becomes the active one.
+    pub async fn open_tab(&self, url: String, tab_type: TabType) -> TabResult<(Uuid, MuxChannel)> {
+        let (tab, renderer_channel) = Tab::new(url, tab_type).await?;
+        let tab_id = tab.state.read().await.id;
+
+        let mut active_tab = self.active_tab.write().await;
+        if active_tab.is_none() {
+            *active_tab = Some(tab_id);
+            tab.state.write().await.is_active = true;
+        } else {
+            tab.set_background(true).await;
+        }
+        self.tabs.write().await.push(tab);
+        Ok((tab_id, renderer_channel))
+    }
+
+    /// Close a tab and terminate its VM
+    pub async fn close_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let tab = {
+            let mut tabs = self.tabs.write().await;
+            let mut index = None;
+            for (i, tab) in tabs.iter().enumerate() {
+                if tab.state.read().await.id == tab_id {
+                    index = Some(i);
+                    break;
+                }
+            }
+            tabs.remove(index.ok_or(TabError::NotFound(tab_id))?)
+        };
+        // Terminate outside the tabs lock so other tabs stay usable meanwhile
+        tab.close().await?;
+
+        let mut active_tab = self.active_tab.write().await;
+        if *active_tab == Some(tab_id) {
+            let next = match self.tabs.read().await.first() {
+                Some(next) => Some(next.state.read().await.id),
+                None => None,
+            };
+            *active_tab = None;
+            drop(active_tab);
+            if let Some(next) = next {
+                self.switch_tab(next).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Make a tab active, throttling every other tab
+    pub async fn switch_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let mut active_tab = self.active_tab.write().await;
+        let tabs = self.tabs.read().await;
+        let mut found = false;
+        for tab in tabs.iter() {
+            let is_target = tab.state.read().await.id == tab_id;
+            found |= is_target;
+            let mut state = tab.state.write().await;
+            state.is_active = is_target;
+            if is_target {
+                state.touch();
+            }
+            drop(state);
+            tab.set_background(!is_target).await;
+        }
+        if !found {
+            return Err(TabError::NotFound(tab_id));
+        }
+        *active_tab = Some(tab_id);
+        Ok(())
+    }
+
+    /// Snapshot of every tab's state
+    pub async fn tab_states(&self) -> Vec<TabState> {
+        let tabs = self.tabs.read().await;
+        let mut states = Vec::with_capacity(tabs.len());
+        for tab in tabs.iter() {
+            states.push(tab.state.read().await.clone());
+        }
+        states
+    }
+
+    /// Tab states for synchronous callers such as view code. Tabs whose
+    /// state is being written at that moment are left out rather than
+    /// blocking.
     pub fn get_tab_states(&self) -> Vec<TabState> {
-        // Return empty for now - the actual implementation requires async context
-        Vec::new()
+        let Ok(tabs) = self.tabs.try_read() else {
+            return Vec::new();
+        };
+        tabs.iter()
+            .filter_map(|tab| tab.state.try_read().ok().map(|state| state.clone()))
+            .collect()
     }
 
     /// Get the number of open tabs
@@ -400,9 +696,13 @@ impl TabManager {
 
     /// Set the active tab
     pub async fn set_active_tab(&self, tab_id: Option<Uuid>) -> bool {
-        let mut active_tab = self.active_tab.write().await;
-        *active_tab = tab_id;
-        true
+        match tab_id {
+            Some(tab_id) => self.switch_tab(tab_id).await.is_ok(),
+            None => {
+                *self.active_tab.write().await = None;
+                true
+            }
+        }
     }
 
     /// Check if there are any tabs open
@@ -411,8 +711,12 @@ impl TabManager {
     }
 }
 
-// NOTE: The full TabManager implementation is disabled to avoid Send safety issues.
-// Use SendSafeTabManager for production browser code.
+#[cfg(feature = "zkvm-isolation")]
+impl Default for TabManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -427,6 +731,10 @@ mod tests {
             tab_type: TabType::Ephemeral,
             is_active: false,
             created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
             content: PageContent::Loading {
                 url: "https://example.com".to_string(),
             },
@@ -461,4 +769,31 @@ mod tests {
         assert!(matches!(error, PageContent::Error { .. }));
         assert!(matches!(empty, PageContent::Empty));
     }
+
+    #[cfg(feature = "zkvm-isolation")]
+    #[tokio::test]
+    async fn test_tab_manager_tracks_active_tab() {
+        let manager = TabManager::new();
+        let (first, _) = manager
+            .open_tab("https://a.test".into(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let (second, _) = manager
+            .open_tab("https://b.test".into(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        assert_eq!(manager.get_active_tab_id().await, Some(first));
+
+        manager.switch_tab(second).await.unwrap();
+        let states = manager.tab_states().await;
+        assert!(states.iter().all(|t| t.is_active == (t.id == second)));
+        assert_eq!(manager.get_tab_states().len(), 2);
+
+        manager.close_tab(second).await.unwrap();
+        assert_eq!(manager.get_active_tab_id().await, Some(first));
+        assert!(matches!(
+            manager.close_tab(second).await,
+            Err(TabError::NotFound(_))
+        ));
+    }
 }
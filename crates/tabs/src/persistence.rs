@@ -183,6 +183,7 @@ mod tests {
                 tab_type: crate::TabType::Container { container_id },
                 is_active: true,
                 created_at: chrono::Utc::now(),
+                last_active_at: chrono::Utc::now(),
             };
             
             // Save state
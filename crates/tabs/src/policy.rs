@@ -0,0 +1,112 @@
+//! Policy a tab's VM runs under
+//!
+//! The host seals the tab's security, fingerprint and network settings into
+//! the VM's policy capsule at start. The renderer inside reads them back from
+//! the VM and holds the page to them itself: the parser's limits, whether
+//! scripts may run, how the JS engine presents itself and which hosts the
+//! page may ask the host to fetch from.
+
+use citadel_networking::HostPolicy;
+use citadel_parser::security::SecurityContext as ParserSecurityContext;
+use citadel_security::SecurityContext;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Settings sealed into a tab VM's policy capsule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmPolicy {
+    /// Deepest element nesting the parser accepts
+    pub max_nesting_depth: usize,
+    /// Most elements a document may have
+    pub max_elements: usize,
+    /// Most text a document may have, in bytes
+    pub max_text_bytes: usize,
+    /// Whether page scripts may run at all; a render request still has to
+    /// opt in
+    pub scripts: bool,
+    /// Whether canvas and audio readback get per-site noise
+    pub fingerprint_noise: bool,
+    /// Hosts the page may fetch resources from
+    pub hosts: HostPolicy,
+}
+
+impl Default for VmPolicy {
+    fn default() -> Self {
+        let parser = ParserSecurityContext::new(15);
+        Self {
+            max_nesting_depth: parser.max_nesting_depth(),
+            max_elements: parser.max_elements(),
+            max_text_bytes: parser.max_text_bytes(),
+            scripts: true,
+            fingerprint_noise: true,
+            hosts: HostPolicy::default(),
+        }
+    }
+}
+
+impl VmPolicy {
+    /// The policy of the browser's security context
+    pub fn from_security_context(context: &SecurityContext) -> Self {
+        let fingerprint = context.fingerprint_protection();
+        Self {
+            max_nesting_depth: context.max_nesting_depth(),
+            scripts: context.allows_scripts(),
+            fingerprint_noise: fingerprint.canvas_noise || fingerprint.audio_noise,
+            ..Self::default()
+        }
+    }
+
+    /// The same policy, reaching only the hosts `hosts` allows
+    pub fn with_hosts(mut self, hosts: HostPolicy) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Security context for the in-VM parser and JS engine
+    pub fn parser_context(&self) -> ParserSecurityContext {
+        let mut context = ParserSecurityContext::new(self.max_nesting_depth)
+            .with_content_limits(self.max_elements, self.max_text_bytes);
+        if self.scripts {
+            context.enable_scripts();
+        }
+        context
+    }
+
+    /// Why a resource request from the page is refused, if it is
+    pub fn check_request(&self, url: &str) -> Option<String> {
+        let Ok(url) = Url::parse(url) else {
+            return Some(format!("{} is not a valid URL", url));
+        };
+        if url.scheme() != "https" {
+            return Some(format!("{} is not fetched over HTTPS", url));
+        }
+        match url.host_str() {
+            Some(host) => self.hosts.check(host),
+            None => Some(format!("{} has no host", url)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_follows_security_context() {
+        let mut context = SecurityContext::new(8);
+        context.disable_scripts();
+        let policy = VmPolicy::from_security_context(&context)
+            .with_hosts(HostPolicy::allow_only(["*.corp.test"]));
+        assert_eq!(policy.max_nesting_depth, 8);
+        assert!(!policy.scripts);
+        assert!(!policy.parser_context().allows_scripts());
+
+        assert!(policy
+            .check_request("https://wiki.corp.test/a.css")
+            .is_none());
+        assert!(policy.check_request("https://news.test/a.css").is_some());
+        assert!(policy
+            .check_request("http://wiki.corp.test/a.css")
+            .is_some());
+    }
+}
@@ -0,0 +1,440 @@
+//! Host side of the zero-knowledge fetch path
+//!
+//! A tab's VM has no network access of its own. It asks for a resource with
+//! a `ResourceRequest` on the resource stream of its channel; the broker on
+//! the host builds the request under the tab's network policy, fetches it
+//! through the [`ResourceManager`] and streams the body back in
+//! `ResourceResponse` chunks, the last one marked. A refused or failed fetch
+//! is answered with a `ResourceError`.
+//!
+//! The tab's policy is read when each request arrives, so a tab converted to
+//! a container is held to the container's host policy from its next request.
+//! Only HTTPS is fetched, only a few content-negotiation headers from the VM
+//! are forwarded, and every fetch counts against the tab's request budget.
+//! A tab's requests are served one at a time: chunks of two responses never
+//! interleave, and the stream's flow-control window paces the body to the
+//! VM's reading.
+//!
+//! Every request that reaches the resource manager is logged to its
+//! request ledger under the tab, blocked ones with the policy that blocked
+//! them; [`ResourceBroker::request_log`] reads a tab's log back.
+
+use crate::{TabState, TabType};
+use citadel_networking::{
+    resource::ResourceType, HostPolicy, LedgerSummary, Method, NetworkPartitionKey, Request,
+    RequestRecord, ResourceManager,
+};
+use citadel_zkvm::{ChannelMessage, MuxChannel, StreamId, ZkVmResult};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use url::Url;
+use uuid::Uuid;
+
+/// Bytes of a body sent per `ResourceResponse`
+pub const RESPONSE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Headers a VM may set on its requests; everything else, cookies and
+/// credentials included, is the host's business
+const FORWARDED_HEADERS: [&str; 3] = ["accept", "accept-language", "range"];
+
+/// Fetches resources for tab VMs
+#[derive(Clone)]
+pub struct ResourceBroker {
+    manager: Arc<ResourceManager>,
+}
+
+/// A tab being served; dropping it stops the broker for that tab
+pub(crate) struct Brokered(JoinHandle<()>);
+
+impl Drop for Brokered {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl ResourceBroker {
+    /// Broker fetches through `manager`, whose policies and budgets apply
+    pub fn new(manager: Arc<ResourceManager>) -> Self {
+        Self { manager }
+    }
+
+    /// Serve the resource requests arriving on a tab's channel until the
+    /// channel closes or the returned handle is dropped. The tab's type and
+    /// page are looked up in `states` for each request.
+    pub(crate) fn serve(
+        &self,
+        tab_id: Uuid,
+        channel: MuxChannel,
+        states: Arc<RwLock<Vec<TabState>>>,
+    ) -> Brokered {
+        let broker = self.clone();
+        Brokered(tokio::spawn(async move {
+            loop {
+                let message = match channel.receive(StreamId::Resource).await {
+                    Ok(message) => message,
+                    Err(e) => {
+                        log::debug!("Resource broker for tab {} stopping: {}", tab_id, e);
+                        break;
+                    }
+                };
+                let ChannelMessage::ResourceRequest { url, headers } = message else {
+                    log::warn!(
+                        "Tab {} sent a resource stream message that is no request",
+                        tab_id
+                    );
+                    continue;
+                };
+                let tab = states
+                    .read()
+                    .await
+                    .iter()
+                    .find(|state| state.id == tab_id)
+                    .map(|state| (state.tab_type, state.url.clone()));
+                let Some((tab_type, document)) = tab else {
+                    break;
+                };
+                if let Err(e) = broker
+                    .respond(&channel, tab_id, tab_type, &document, url, headers)
+                    .await
+                {
+                    log::debug!("Resource broker for tab {} stopping: {}", tab_id, e);
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// Hosts the container's tabs may reach, if it has a policy of its own
+    pub(crate) fn container_hosts(&self, container_id: Uuid) -> Option<HostPolicy> {
+        self.manager.container_policies().get(container_id)
+    }
+
+    /// Requests a tab has made, oldest first
+    pub fn request_log(&self, tab_id: Uuid) -> Vec<RequestRecord> {
+        self.manager.request_ledger().requests(tab_id)
+    }
+
+    /// Totals over a tab's requests
+    pub fn request_summary(&self, tab_id: Uuid) -> LedgerSummary {
+        self.manager.request_ledger().summary(tab_id)
+    }
+
+    /// Forget a closed tab's requests
+    pub(crate) fn forget_requests(&self, tab_id: Uuid) {
+        self.manager.request_ledger().remove(tab_id);
+    }
+
+    /// Fetch one resource and stream the answer back
+    async fn respond(
+        &self,
+        channel: &MuxChannel,
+        tab_id: Uuid,
+        tab_type: TabType,
+        document: &str,
+        url: String,
+        headers: Vec<(String, String)>,
+    ) -> ZkVmResult<()> {
+        let request = match build_request(tab_id, tab_type, document, &url, &headers) {
+            Ok(request) => request,
+            Err(reason) => return refuse(channel, url, reason).await,
+        };
+        let resource_type = resource_type(&headers);
+        let response = match self
+            .manager
+            .fetch_request_for_tab(tab_id, request, resource_type)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                log::debug!("Brokered fetch of {} for tab {} failed: {}", url, tab_id, e);
+                return refuse(channel, url, e.to_string()).await;
+            }
+        };
+        // A script's fetch is same-origin only, wherever redirects took it
+        if resource_type == ResourceType::Fetch {
+            let origin = Url::parse(document).ok().map(|document| document.origin());
+            if let Some(hop) = response
+                .redirect_chain()
+                .iter()
+                .chain([response.url()])
+                .find(|hop| origin.as_ref() != Some(&hop.origin()))
+            {
+                let reason = format!("redirected off the page's origin to {}", hop);
+                log::debug!("Brokered fetch of {} for tab {} {}", url, tab_id, reason);
+                return refuse(channel, url, reason).await;
+            }
+        }
+
+        let served_from = response.url().to_string();
+        let status = response.status();
+        let content_type = response.content_type().unwrap_or_default().to_string();
+        let body = response.body();
+        let mut chunks = body.chunks(RESPONSE_CHUNK_SIZE).peekable();
+        if chunks.peek().is_none() {
+            return channel
+                .send(ChannelMessage::ResourceResponse {
+                    url,
+                    served_from,
+                    status,
+                    content_type,
+                    data: Vec::new(),
+                    last: true,
+                })
+                .await;
+        }
+        while let Some(chunk) = chunks.next() {
+            channel
+                .send(ChannelMessage::ResourceResponse {
+                    url: url.clone(),
+                    served_from: served_from.clone(),
+                    status,
+                    content_type: content_type.clone(),
+                    data: chunk.to_vec(),
+                    last: chunks.peek().is_none(),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Tell the VM its request was refused
+async fn refuse(channel: &MuxChannel, url: String, reason: String) -> ZkVmResult<()> {
+    channel
+        .send(ChannelMessage::ResourceError { url, reason })
+        .await
+}
+
+/// The request a tab's VM asked for, made under the tab's network policy
+fn build_request(
+    tab_id: Uuid,
+    tab_type: TabType,
+    document: &str,
+    url: &str,
+    headers: &[(String, String)],
+) -> Result<Request, String> {
+    let mut builder = Request::builder().method(Method::GET).url(url);
+    for (name, value) in headers {
+        if FORWARDED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            builder = builder.header(name, value);
+        } else {
+            log::debug!("Dropping header {} from tab {}'s request", name, tab_id);
+        }
+    }
+    // The container's host policy is checked by the resource manager
+    if let TabType::Container { container_id } = tab_type {
+        builder = builder.container(container_id);
+    }
+    // Connections are partitioned by the tab's page, not the resource's host
+    if let Some(key) = Url::parse(document)
+        .ok()
+        .and_then(|document| NetworkPartitionKey::for_url(&document))
+    {
+        builder = builder.partition_key(match tab_type {
+            TabType::Ephemeral => key.in_ephemeral_tab(tab_id),
+            TabType::Container { .. } => key,
+        });
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// What kind of resource the VM is after: a page script's `fetch()` when it
+/// says `Sec-Fetch-Dest: empty`, otherwise from its Accept header
+fn resource_type(headers: &[(String, String)]) -> ResourceType {
+    let header = |wanted: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+    if header("sec-fetch-dest") == "empty" {
+        return ResourceType::Fetch;
+    }
+    let accept = header("accept");
+    if accept.contains("text/html") {
+        ResourceType::Html
+    } else if accept.contains("text/css") {
+        ResourceType::Css
+    } else if accept.contains("javascript") {
+        ResourceType::Script
+    } else if accept.contains("image/") {
+        ResourceType::Image
+    } else if accept.contains("font/") {
+        ResourceType::Font
+    } else if accept.contains("application/json") {
+        ResourceType::Json
+    } else {
+        ResourceType::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PageContent;
+    use citadel_zkvm::DEFAULT_STREAM_WINDOW;
+
+    fn tab_state(id: Uuid, tab_type: TabType) -> TabState {
+        TabState {
+            id,
+            title: String::new(),
+            url: "https://news.test/".to_string(),
+            tab_type,
+            is_active: true,
+            created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content: PageContent::Empty,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_outside_the_tab_policy_are_refused() {
+        let container_id = Uuid::new_v4();
+        let manager = ResourceManager::new().await.unwrap();
+        manager
+            .container_policies()
+            .set(container_id, HostPolicy::allow_only(["*.corp.test"]));
+        let broker = ResourceBroker::new(Arc::new(manager));
+
+        let tab_id = Uuid::new_v4();
+        let states = Arc::new(RwLock::new(vec![tab_state(
+            tab_id,
+            TabType::Container { container_id },
+        )]));
+        let (host, vm) = MuxChannel::pair(DEFAULT_STREAM_WINDOW).unwrap();
+        let _serving = broker.serve(tab_id, host, states);
+
+        for url in [
+            "http://intranet.corp.test/",
+            "https://tracker.test/pixel.gif",
+        ] {
+            vm.send(ChannelMessage::ResourceRequest {
+                url: url.to_string(),
+                headers: vec![("Cookie".to_string(), "session=1".to_string())],
+            })
+            .await
+            .unwrap();
+            let answer = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                vm.receive(StreamId::Resource),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            assert!(
+                matches!(&answer, ChannelMessage::ResourceError { url: refused, .. } if refused == url),
+                "{:?}",
+                answer
+            );
+        }
+
+        // The host policy's refusal is in the tab's log, naming the policy
+        let log = broker.request_log(tab_id);
+        let refused = log
+            .iter()
+            .find(|record| record.url.as_str() == "https://tracker.test/pixel.gif")
+            .unwrap();
+        assert_eq!(
+            refused.blocked_by(),
+            Some(&citadel_networking::BlockingPolicy::ContainerHosts)
+        );
+        assert!(broker.request_summary(tab_id).blocked >= 1);
+        assert!(broker.request_log(Uuid::new_v4()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_script_fetches_may_not_be_redirected_off_the_page_origin() {
+        use citadel_networking::test_server::{Fixture, TestServer};
+
+        let elsewhere = TestServer::start(&[("/data.json", Fixture::html("{}"))])
+            .await
+            .unwrap();
+        let server = TestServer::start(&[
+            ("/moved", Fixture::redirect(302, "/data.json")),
+            ("/data.json", Fixture::html("{}")),
+            (
+                "/away",
+                Fixture::redirect(302, elsewhere.url("/data.json").as_str()),
+            ),
+        ])
+        .await
+        .unwrap();
+        let broker = ResourceBroker::new(Arc::new(ResourceManager::new().await.unwrap()));
+        let tab_id = Uuid::new_v4();
+        let mut tab = tab_state(tab_id, TabType::Ephemeral);
+        tab.url = server.url("/").to_string();
+        let (host, vm) = MuxChannel::pair(DEFAULT_STREAM_WINDOW).unwrap();
+        let _serving = broker.serve(tab_id, host, Arc::new(RwLock::new(vec![tab])));
+
+        let fetch = |path: &str| {
+            let url = server.url(path).to_string();
+            let vm = &vm;
+            async move {
+                vm.send(ChannelMessage::ResourceRequest {
+                    url,
+                    headers: vec![("Sec-Fetch-Dest".to_string(), "empty".to_string())],
+                })
+                .await
+                .unwrap();
+                tokio::time::timeout(
+                    std::time::Duration::from_secs(5),
+                    vm.receive(StreamId::Resource),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+            }
+        };
+
+        // Within the origin, the VM learns where the body came from
+        let answer = fetch("/moved").await;
+        assert!(
+            matches!(
+                &answer,
+                ChannelMessage::ResourceResponse { served_from, .. }
+                    if *served_from == server.url("/data.json").as_str()
+            ),
+            "{:?}",
+            answer
+        );
+        let answer = fetch("/away").await;
+        assert!(
+            matches!(&answer, ChannelMessage::ResourceError { reason, .. } if reason.contains("origin")),
+            "{:?}",
+            answer
+        );
+    }
+
+    #[test]
+    fn test_script_fetches_are_typed_apart_from_subresources() {
+        let headers = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            resource_type(&headers(&[
+                ("Accept", "application/json"),
+                ("Sec-Fetch-Dest", "empty")
+            ])),
+            ResourceType::Fetch
+        );
+        assert_eq!(
+            resource_type(&headers(&[("Accept", "application/json")])),
+            ResourceType::Json
+        );
+        assert_eq!(
+            resource_type(&headers(&[
+                ("accept", "image/*"),
+                ("sec-fetch-dest", "image")
+            ])),
+            ResourceType::Image
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use parking_lot::RwLock;
 use serde::{Serialize, Deserialize};
-use citadel_zkvm::{ZkVm, Channel, ChannelMessage};
+use citadel_zkvm::{ZkVm, ChannelMessage, MuxChannel, StreamId};
 use uuid::Uuid;
 use crate::{TabError, TabResult, TabState};
 
@@ -21,7 +21,7 @@ pub struct TabSearch {
     /// The ZKVM instance for search operations
     vm: Arc<ZkVm>,
     /// Communication channel to the VM
-    channel: Channel,
+    channel: MuxChannel,
     /// Search index
     index: Arc<RwLock<Vec<SearchEntry>>>,
 }
@@ -48,7 +48,7 @@ impl TabSearch {
         };
         
         // Start the VM
-        search.vm.start().await?;
+        search.vm.start(search.vm.seal_policy(&())?).await?;
         
         Ok(search)
     }
@@ -131,7 +131,7 @@ impl TabSearch {
         }).await?;
         
         // Receive results
-        match self.channel.receive().await? {
+        match self.channel.receive(StreamId::Control).await? {
             ChannelMessage::Control { command, params } if command == "search_results" => {
                 // Deserialize results
                 let results: Vec<SearchResult> = serde_json::from_value(params)?;
@@ -190,6 +190,7 @@ mod tests {
                 tab_type: crate::TabType::Ephemeral,
                 is_active: true,
                 created_at: chrono::Utc::now(),
+                last_active_at: chrono::Utc::now(),
             };
             
             let tab2 = TabState {
@@ -199,6 +200,7 @@ mod tests {
                 tab_type: crate::TabType::Ephemeral,
                 is_active: false,
                 created_at: chrono::Utc::now(),
+                last_active_at: chrono::Utc::now(),
             };
             
             // Index tabs
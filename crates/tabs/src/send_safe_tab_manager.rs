@@ -3,10 +3,18 @@
 //! This module provides a Send-safe interface to the ZKVM TabManager
 //! by using message passing and async operations.
 
-use crate::{PageContent, Tab, TabError, TabResult, TabState, TabType};
-use citadel_zkvm::{Channel as ZkVmChannel, ChannelMessage};
+use crate::resource_broker::{Brokered, ResourceBroker};
+use crate::vm_pool::{VmPool, DEFAULT_WARM_VMS};
+use crate::vm_reuse::SharedVms;
+use crate::{
+    audio, ExpiryPolicy, PageContent, Tab, TabError, TabMatch, TabResult, TabShutdown, TabState,
+    TabType, VmPolicy, VmReuse,
+};
+use citadel_networking::RequestRecord;
+use citadel_zkvm::{ChannelMessage, ExecutionBudget, MuxChannel, StreamId};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot, RwLock};
 use uuid::Uuid;
 
@@ -43,6 +51,66 @@ pub enum TabManagerCommand {
         message: ChannelMessage,
         response: oneshot::Sender<TabResult<()>>,
     },
+    ReapIdleTabs {
+        response: oneshot::Sender<Vec<Uuid>>,
+    },
+    ReopenTab {
+        tab_id: Uuid,
+        response: oneshot::Sender<TabResult<()>>,
+    },
+    HibernateTab {
+        tab_id: Uuid,
+        response: oneshot::Sender<TabResult<()>>,
+    },
+    SetGroup {
+        tab_id: Uuid,
+        group: Option<String>,
+        response: oneshot::Sender<TabResult<()>>,
+    },
+    WipeAllTabs {
+        response: oneshot::Sender<Vec<Uuid>>,
+    },
+    SetAudible {
+        tab_id: Uuid,
+        audible: bool,
+        response: oneshot::Sender<TabResult<()>>,
+    },
+    SetMuted {
+        tab_id: Uuid,
+        muted: bool,
+        response: oneshot::Sender<TabResult<()>>,
+    },
+    Shutdown {
+        timeout: Duration,
+        response: oneshot::Sender<TabShutdown>,
+    },
+    SetResourceBroker {
+        broker: ResourceBroker,
+        response: oneshot::Sender<()>,
+    },
+    SetExpiryPolicy {
+        policy: ExpiryPolicy,
+        response: oneshot::Sender<()>,
+    },
+    SetPowerSaving {
+        enabled: bool,
+        response: oneshot::Sender<()>,
+    },
+    WarmVmPool {
+        response: oneshot::Sender<()>,
+    },
+    SetVmPolicy {
+        policy: VmPolicy,
+        response: oneshot::Sender<()>,
+    },
+    SetVmReuse {
+        reuse: VmReuse,
+        response: oneshot::Sender<()>,
+    },
+    RequestLog {
+        tab_id: Uuid,
+        response: oneshot::Sender<Vec<RequestRecord>>,
+    },
 }
 
 /// Send-safe wrapper for TabManager
@@ -55,15 +123,42 @@ pub struct SendSafeTabManager {
 impl SendSafeTabManager {
     /// Create a new Send-safe tab manager
     pub fn new() -> Self {
+        Self::with_expiry_policy(ExpiryPolicy::default())
+    }
+
+    /// Create a tab manager that expires idle ephemeral tabs per `policy`
+    pub fn with_expiry_policy(policy: ExpiryPolicy) -> Self {
         let (command_sender, command_receiver) = mpsc::unbounded_channel();
         let tab_states = Arc::new(RwLock::new(Vec::new()));
 
         // Spawn the background task that handles tab management
         let manager_states = tab_states.clone();
         tokio::spawn(async move {
-            Self::handle_commands(command_receiver, manager_states).await;
+            Self::handle_commands(command_receiver, manager_states, policy).await;
         });
 
+        // Reaper: periodically expire idle tabs; stops once the manager is dropped
+        if policy.enabled {
+            let reaper_sender = command_sender.downgrade();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(policy.check_interval());
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let Some(sender) = reaper_sender.upgrade() else {
+                        break;
+                    };
+                    let (response, _) = oneshot::channel();
+                    if sender
+                        .send(TabManagerCommand::ReapIdleTabs { response })
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
         Self {
             command_sender,
             tab_states,
@@ -74,11 +169,24 @@ impl SendSafeTabManager {
     async fn handle_commands(
         mut receiver: mpsc::UnboundedReceiver<TabManagerCommand>,
         states: Arc<RwLock<Vec<TabState>>>,
+        mut policy: ExpiryPolicy,
     ) {
         // Store actual Tab instances with ZKVM
         let mut tabs: HashMap<Uuid, Tab> = HashMap::new();
-        // Store ZKVM channels for each tab
-        let mut tab_channels: HashMap<Uuid, ZkVmChannel> = HashMap::new();
+        // Host end of each tab's multiplexed ZKVM channel
+        let mut tab_channels: HashMap<Uuid, MuxChannel> = HashMap::new();
+        // Fetches the tabs' resource requests, once the browser provides it
+        let mut broker: Option<ResourceBroker> = None;
+        let mut brokered: HashMap<Uuid, Brokered> = HashMap::new();
+        // Policy sealed into the VM of each tab opened from now on
+        let mut vm_policy = VmPolicy::default();
+        // Background tabs are throttled harder while the device saves power
+        let mut power_saving = false;
+        // Never-used VMs, so opening a tab skips VM setup. Warming starts
+        // when asked, so it stays out of the way of browser startup.
+        let mut pool = VmPool::new(0);
+        // VMs tabs share, when the reuse policy lets them
+        let mut shared = SharedVms::new(VmReuse::default());
         while let Some(command) = receiver.recv().await {
             match command {
                 TabManagerCommand::OpenTab {
@@ -86,20 +194,44 @@ impl SendSafeTabManager {
                     tab_type,
                     response,
                 } => {
-                    // Create a real ZKVM tab
-                    match Tab::new(url.clone(), tab_type).await {
-                        Ok((tab, renderer_channel)) => {
-                            let tab_id = tab.state.read().await.id;
+                    // Create a real ZKVM tab around a warm VM, or one its
+                    // tabs share
+                    let policy = Self::tab_policy(&vm_policy, broker.as_ref(), tab_type);
+                    let created =
+                        Self::create_tab(None, url, tab_type, &policy, &pool, &mut shared).await;
+                    match created {
+                        Ok((tab_id, tab, renderer_channel, joined)) => {
                             let tab_state = tab.state.read().await.clone();
 
-                            // Store the ZKVM channel for renderer communication
+                            // Store the ZKVM channel for renderer communication;
+                            // a shared VM is served for the tab that started it
+                            if let (Some(broker), false) = (&broker, joined) {
+                                brokered.insert(
+                                    tab_id,
+                                    broker.serve(tab_id, renderer_channel.clone(), states.clone()),
+                                );
+                            }
                             tab_channels.insert(tab_id, renderer_channel);
 
+                            let mut states_guard = states.write().await;
+
+                            // Tabs opened behind the active one start throttled,
+                            // unless they share its VM
+                            if !states_guard.is_empty()
+                                && !Self::runs_active_tab(&shared.group(tab_id), &states_guard)
+                            {
+                                Self::set_tab_background(
+                                    &tab,
+                                    tab_channels.get(&tab_id),
+                                    true,
+                                    power_saving,
+                                )
+                                .await;
+                            }
+
                             // Store the tab instance
                             tabs.insert(tab_id, tab);
 
-                            let mut states_guard = states.write().await;
-
                             // If this is the first tab, make it active
                             if states_guard.is_empty() {
                                 let mut active_state = tab_state;
@@ -119,15 +251,19 @@ impl SendSafeTabManager {
                     }
                 }
                 TabManagerCommand::CloseTab { tab_id, response } => {
-                    // Close the ZKVM tab first
-                    if let Some(tab) = tabs.remove(&tab_id) {
-                        // Close the tab (this will terminate the ZKVM)
-                        if let Err(e) = tab.close().await {
-                            log::error!("Failed to close ZKVM for tab {}: {}", tab_id, e);
-                        }
-
-                        // Remove the channel
-                        tab_channels.remove(&tab_id);
+                    // Close the ZKVM tab first (this terminates the ZKVM
+                    // unless other tabs share it)
+                    Self::release_tab(
+                        tab_id,
+                        &mut tabs,
+                        &mut tab_channels,
+                        &mut brokered,
+                        &mut shared,
+                        broker.as_ref().map(|broker| (broker, &states)),
+                    )
+                    .await;
+                    if let Some(broker) = &broker {
+                        broker.forget_requests(tab_id);
                     }
 
                     let mut states_guard = states.write().await;
@@ -159,6 +295,22 @@ impl SendSafeTabManager {
                     // Update active states
                     for state in states_guard.iter_mut() {
                         state.is_active = state.id == tab_id;
+                        if state.is_active {
+                            state.touch();
+                        }
+                    }
+
+                    // Throttle every tab except the newly active one, and
+                    // the ones sharing its VM
+                    let active_vm = shared.group(tab_id);
+                    for (id, tab) in tabs.iter() {
+                        Self::set_tab_background(
+                            tab,
+                            tab_channels.get(id),
+                            !active_vm.contains(id),
+                            power_saving,
+                        )
+                        .await;
                     }
 
                     let _ = response.send(Ok(()));
@@ -194,7 +346,7 @@ impl SendSafeTabManager {
                     response,
                 } => {
                     // Send content update through ZKVM channel
-                    if let Some(channel) = tab_channels.get_mut(&tab_id) {
+                    if let Some(channel) = tab_channels.get(&tab_id) {
                         // Send rendering data through secure channel
                         let message = ChannelMessage::Control {
                             command: "update_content".to_string(),
@@ -202,7 +354,7 @@ impl SendSafeTabManager {
                                 .unwrap_or_else(|_| "{}".to_string()),
                         };
 
-                        if let Err(e) = channel.send(message).await {
+                        if let Err(e) = channel.send_on(StreamId::Renderer, message).await {
                             log::error!("Failed to send content to ZKVM tab {}: {}", tab_id, e);
                         }
                     }
@@ -210,8 +362,12 @@ impl SendSafeTabManager {
                     let mut states_guard = states.write().await;
 
                     if let Some(state) = states_guard.iter_mut().find(|t| t.id == tab_id) {
-                        // Update page content
+                        // Update page content; a new page starts silent
+                        if matches!(content, PageContent::Loading { .. }) {
+                            state.is_audible = false;
+                        }
                         state.content = content.clone();
+                        state.touch();
 
                         // Update tab title if we have loaded content
                         if let PageContent::Loaded { title, .. } = &content {
@@ -230,7 +386,7 @@ impl SendSafeTabManager {
                     response,
                 } => {
                     // Send message through ZKVM channel
-                    if let Some(channel) = tab_channels.get_mut(&tab_id) {
+                    if let Some(channel) = tab_channels.get(&tab_id) {
                         if let Err(e) = channel.send(message).await {
                             log::error!("Failed to send message to tab {}: {}", tab_id, e);
                             let _ = response.send(Err(TabError::InvalidOperation(
@@ -243,6 +399,443 @@ impl SendSafeTabManager {
                         let _ = response.send(Err(TabError::NotFound(tab_id)));
                     }
                 }
+                TabManagerCommand::ReapIdleTabs { response } => {
+                    let now = chrono::Utc::now();
+                    let mut states_guard = states.write().await;
+                    let mut expired = Vec::new();
+
+                    for state in states_guard
+                        .iter_mut()
+                        .filter(|state| policy.should_expire(state, now))
+                    {
+                        Self::expire_tab(
+                            state,
+                            &mut tabs,
+                            &mut tab_channels,
+                            &mut brokered,
+                            &mut shared,
+                            broker.as_ref().map(|broker| (broker, &states)),
+                        )
+                        .await;
+                        log::info!("⏳ Expired idle ephemeral tab {}", state.id);
+                        expired.push(state.id);
+                    }
+
+                    let _ = response.send(expired);
+                }
+                TabManagerCommand::WipeAllTabs { response } => {
+                    let mut states_guard = states.write().await;
+                    let wiped: Vec<Uuid> = states_guard.iter().map(|state| state.id).collect();
+                    // Container tabs are not persisted on the way out
+                    for (tab_id, tab) in tabs.drain() {
+                        if let Err(e) = tab.wipe().await {
+                            log::error!("Failed to wipe ZKVM for tab {}: {}", tab_id, e);
+                        }
+                    }
+                    tab_channels.clear();
+                    brokered.clear();
+                    shared.clear();
+                    states_guard.clear();
+                    // Pooled VMs never held a page, but a wipe leaves none behind
+                    pool.drain();
+                    pool.replenish();
+                    log::warn!("Wiped {} tabs", wiped.len());
+                    let _ = response.send(wiped);
+                }
+                TabManagerCommand::ReopenTab { tab_id, response } => {
+                    let mut states_guard = states.write().await;
+                    let Some(state) = states_guard.iter_mut().find(|t| t.id == tab_id) else {
+                        let _ = response.send(Err(TabError::NotFound(tab_id)));
+                        continue;
+                    };
+                    if !matches!(state.content, PageContent::Expired { .. }) {
+                        let _ = response.send(Err(TabError::InvalidOperation(
+                            "Tab has not expired".into(),
+                        )));
+                        continue;
+                    }
+
+                    // A fresh VM, or one its tabs share, under the same tab
+                    // id, so the UI keeps its place
+                    let policy = Self::tab_policy(&vm_policy, broker.as_ref(), state.tab_type);
+                    let created = Self::create_tab(
+                        Some(tab_id),
+                        state.url.clone(),
+                        state.tab_type,
+                        &policy,
+                        &pool,
+                        &mut shared,
+                    )
+                    .await;
+                    match created {
+                        Ok((_, tab, renderer_channel, joined)) => {
+                            if !state.is_active && !joined {
+                                Self::set_tab_background(
+                                    &tab,
+                                    Some(&renderer_channel),
+                                    true,
+                                    power_saving,
+                                )
+                                .await;
+                            }
+                            // The fresh VM stays muted if the tab was
+                            if state.is_muted {
+                                if let Err(e) =
+                                    renderer_channel.send(audio::set_muted_message(true)).await
+                                {
+                                    log::warn!("Failed to mute reopened tab {}: {}", tab_id, e);
+                                }
+                            }
+                            tabs.insert(tab_id, tab);
+                            if let (Some(broker), false) = (&broker, joined) {
+                                brokered.insert(
+                                    tab_id,
+                                    broker.serve(tab_id, renderer_channel.clone(), states.clone()),
+                                );
+                            }
+                            tab_channels.insert(tab_id, renderer_channel);
+
+                            state.content = PageContent::Empty;
+                            state.title = String::new();
+                            state.touch();
+                            log::info!("Reopened expired tab {}", tab_id);
+                            let _ = response.send(Ok(()));
+                        }
+                        Err(e) => {
+                            log::error!("Failed to reopen tab {}: {}", tab_id, e);
+                            let _ = response.send(Err(e));
+                        }
+                    }
+                }
+                TabManagerCommand::HibernateTab { tab_id, response } => {
+                    let mut states_guard = states.write().await;
+                    let Some(state) = states_guard.iter_mut().find(|t| t.id == tab_id) else {
+                        let _ = response.send(Err(TabError::NotFound(tab_id)));
+                        continue;
+                    };
+                    if state.is_active || matches!(state.content, PageContent::Expired { .. }) {
+                        let _ = response.send(Err(TabError::InvalidOperation(
+                            "Only a background tab can hibernate".into(),
+                        )));
+                        continue;
+                    }
+                    Self::expire_tab(
+                        state,
+                        &mut tabs,
+                        &mut tab_channels,
+                        &mut brokered,
+                        &mut shared,
+                        broker.as_ref().map(|broker| (broker, &states)),
+                    )
+                    .await;
+                    log::info!("💤 Hibernated tab {}", tab_id);
+                    let _ = response.send(Ok(()));
+                }
+                TabManagerCommand::SetGroup {
+                    tab_id,
+                    group,
+                    response,
+                } => {
+                    let mut states_guard = states.write().await;
+                    match states_guard.iter_mut().find(|t| t.id == tab_id) {
+                        Some(state) => {
+                            state.group = group;
+                            let _ = response.send(Ok(()));
+                        }
+                        None => {
+                            let _ = response.send(Err(TabError::NotFound(tab_id)));
+                        }
+                    }
+                }
+                TabManagerCommand::SetAudible {
+                    tab_id,
+                    audible,
+                    response,
+                } => {
+                    let mut states_guard = states.write().await;
+                    match states_guard.iter_mut().find(|t| t.id == tab_id) {
+                        Some(state) => {
+                            state.is_audible = audible;
+                            let _ = response.send(Ok(()));
+                        }
+                        None => {
+                            let _ = response.send(Err(TabError::NotFound(tab_id)));
+                        }
+                    }
+                }
+                TabManagerCommand::SetMuted {
+                    tab_id,
+                    muted,
+                    response,
+                } => {
+                    let mut states_guard = states.write().await;
+                    let Some(state) = states_guard.iter_mut().find(|t| t.id == tab_id) else {
+                        let _ = response.send(Err(TabError::NotFound(tab_id)));
+                        continue;
+                    };
+                    state.is_muted = muted;
+
+                    // The VM's media pipeline enforces the mute
+                    if let Some(channel) = tab_channels.get(&tab_id) {
+                        if let Err(e) = channel.send(audio::set_muted_message(muted)).await {
+                            log::warn!("Failed to update mute for tab {}: {}", tab_id, e);
+                        }
+                    }
+                    let _ = response.send(Ok(()));
+                }
+                TabManagerCommand::Shutdown { timeout, response } => {
+                    let deadline = tokio::time::Instant::now() + timeout;
+                    let mut report = TabShutdown::default();
+
+                    // Containers are flushed while every VM is still alive
+                    for (tab_id, tab) in tabs.iter() {
+                        match tokio::time::timeout_at(deadline, tab.flush()).await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => {
+                                log::error!("Failed to flush tab {}: {}", tab_id, e);
+                                report.unflushed.push(*tab_id);
+                            }
+                            Err(_) => report.unflushed.push(*tab_id),
+                        }
+                    }
+
+                    pool.drain();
+                    for (tab_id, tab) in tabs.drain() {
+                        match tokio::time::timeout_at(deadline, tab.vm.terminate()).await {
+                            Ok(Ok(())) => report.terminated += 1,
+                            Ok(Err(e)) => {
+                                log::error!("Failed to terminate ZKVM for tab {}: {}", tab_id, e);
+                                report.timed_out.push(tab_id);
+                            }
+                            Err(_) => {
+                                // Dropping the VM would block on the same
+                                // termination, so it is left to finish, or
+                                // not, off the exit path
+                                log::warn!("ZKVM for tab {} did not terminate in time", tab_id);
+                                report.timed_out.push(tab_id);
+                                std::thread::spawn(move || drop(tab));
+                            }
+                        }
+                    }
+                    tab_channels.clear();
+                    brokered.clear();
+                    shared.clear();
+                    states.write().await.clear();
+
+                    log::info!(
+                        "Shut down {} tabs, {} timed out",
+                        report.terminated,
+                        report.timed_out.len()
+                    );
+                    let _ = response.send(report);
+                    // No tab opens after shutdown; later commands fail
+                    break;
+                }
+                TabManagerCommand::SetResourceBroker {
+                    broker: new_broker,
+                    response,
+                } => {
+                    // Tabs already open are served from now on too
+                    brokered = tab_channels
+                        .iter()
+                        .map(|(tab_id, channel)| {
+                            let serving =
+                                new_broker.serve(*tab_id, channel.clone(), states.clone());
+                            (*tab_id, serving)
+                        })
+                        .collect();
+                    broker = Some(new_broker);
+                    let _ = response.send(());
+                }
+                TabManagerCommand::SetExpiryPolicy {
+                    policy: new_policy,
+                    response,
+                } => {
+                    policy = new_policy;
+                    let _ = response.send(());
+                }
+                TabManagerCommand::WarmVmPool { response } => {
+                    pool.set_capacity(DEFAULT_WARM_VMS);
+                    let _ = response.send(());
+                }
+                TabManagerCommand::SetPowerSaving { enabled, response } => {
+                    power_saving = enabled;
+                    let states_guard = states.read().await;
+                    for (id, tab) in tabs.iter() {
+                        let background = !Self::runs_active_tab(&shared.group(*id), &states_guard);
+                        Self::set_tab_background(
+                            tab,
+                            tab_channels.get(id),
+                            background,
+                            power_saving,
+                        )
+                        .await;
+                    }
+                    let _ = response.send(());
+                }
+                TabManagerCommand::SetVmPolicy { policy, response } => {
+                    // A running VM keeps the capsule it started with
+                    vm_policy = policy;
+                    let _ = response.send(());
+                }
+                TabManagerCommand::SetVmReuse { reuse, response } => {
+                    // VMs already shared keep their tabs
+                    shared.set_reuse(reuse);
+                    log::info!("Tab VM reuse: {:?}", reuse);
+                    let _ = response.send(());
+                }
+                TabManagerCommand::RequestLog { tab_id, response } => {
+                    let log = broker
+                        .as_ref()
+                        .map(|broker| broker.request_log(tab_id))
+                        .unwrap_or_default();
+                    let _ = response.send(log);
+                }
+            }
+        }
+    }
+
+    /// Terminate a tab's VM, which zeroizes its memory, and leave the tab
+    /// expired; reopening it gives it a fresh VM. A VM other tabs share
+    /// keeps running for them.
+    async fn expire_tab(
+        state: &mut TabState,
+        tabs: &mut HashMap<Uuid, Tab>,
+        tab_channels: &mut HashMap<Uuid, MuxChannel>,
+        brokered: &mut HashMap<Uuid, Brokered>,
+        shared: &mut SharedVms,
+        serve: Option<(&ResourceBroker, &Arc<RwLock<Vec<TabState>>>)>,
+    ) {
+        Self::release_tab(state.id, tabs, tab_channels, brokered, shared, serve).await;
+
+        state.content = PageContent::Expired {
+            url: state.url.clone(),
+        };
+        state.title = "Expired".to_string();
+        state.is_audible = false;
+    }
+
+    /// Open a tab in a VM its tabs share under the reuse policy, or else in
+    /// a never-used VM from the pool started on `policy`. The tab gets `id`
+    /// if one is given. Returns the tab's id, the tab, the host end of its
+    /// VM's channel and whether it joined a running VM.
+    async fn create_tab(
+        id: Option<Uuid>,
+        url: String,
+        tab_type: TabType,
+        policy: &VmPolicy,
+        pool: &VmPool,
+        shared: &mut SharedVms,
+    ) -> TabResult<(Uuid, Tab, MuxChannel, bool)> {
+        let key = shared.key_for(tab_type, &url);
+        let (tab, channel, joined) = match key.as_ref().and_then(|key| shared.find(key, policy)) {
+            Some((vm, channel)) => (
+                Tab::sharing(url, tab_type, vm, channel.clone()),
+                channel,
+                true,
+            ),
+            None => {
+                let (tab, channel) =
+                    Tab::with_vm(url, tab_type, pool.take().await?, policy).await?;
+                (tab, channel, false)
+            }
+        };
+        if let Some(id) = id {
+            tab.state.write().await.id = id;
+        }
+        let tab_id = tab.state.read().await.id;
+        if let Some(key) = key {
+            shared.add(key, tab_id, &tab.vm, &channel, policy);
+        }
+        if joined {
+            log::info!("Tab {} shares a running VM", tab_id);
+        }
+        Ok((tab_id, tab, channel, joined))
+    }
+
+    /// Take a tab out of the manager and close it. Its VM is terminated,
+    /// zeroizing its memory, unless other tabs still run in it; the VM's
+    /// requests are then brokered for the first of those.
+    async fn release_tab(
+        tab_id: Uuid,
+        tabs: &mut HashMap<Uuid, Tab>,
+        tab_channels: &mut HashMap<Uuid, MuxChannel>,
+        brokered: &mut HashMap<Uuid, Brokered>,
+        shared: &mut SharedVms,
+        serve: Option<(&ResourceBroker, &Arc<RwLock<Vec<TabState>>>)>,
+    ) {
+        let remaining = shared.remove(tab_id);
+        if let Some(tab) = tabs.remove(&tab_id) {
+            let closed = if remaining.is_empty() {
+                tab.close().await
+            } else {
+                tab.flush().await
+            };
+            if let Err(e) = closed {
+                log::error!("Failed to close ZKVM for tab {}: {}", tab_id, e);
+            }
+        }
+        tab_channels.remove(&tab_id);
+        let was_served = brokered.remove(&tab_id).is_some();
+
+        if let (true, Some(next), Some((broker, states))) = (was_served, remaining.first(), serve) {
+            if let Some(channel) = tab_channels.get(next) {
+                brokered.insert(*next, broker.serve(*next, channel.clone(), states.clone()));
+            }
+        }
+    }
+
+    /// Whether any of `group`, tabs sharing a VM, is the active tab
+    fn runs_active_tab(group: &[Uuid], states: &[TabState]) -> bool {
+        states
+            .iter()
+            .any(|state| state.is_active && group.contains(&state.id))
+    }
+
+    /// The policy for a new tab's VM: the browser's, reaching only the hosts
+    /// the tab's container allows
+    fn tab_policy(base: &VmPolicy, broker: Option<&ResourceBroker>, tab_type: TabType) -> VmPolicy {
+        match (tab_type, broker) {
+            (TabType::Container { container_id }, Some(broker)) => {
+                match broker.container_hosts(container_id) {
+                    Some(hosts) => base.clone().with_hosts(hosts),
+                    None => base.clone(),
+                }
+            }
+            _ => base.clone(),
+        }
+    }
+
+    /// Apply the background (throttled, harder while saving power) or
+    /// foreground budget to a tab's VM and tell its renderer, which pauses
+    /// timers and spaces out work while throttled
+    async fn set_tab_background(
+        tab: &Tab,
+        channel: Option<&MuxChannel>,
+        background: bool,
+        power_saving: bool,
+    ) {
+        let budget = match (background, power_saving) {
+            (false, _) => ExecutionBudget::foreground(),
+            (true, false) => ExecutionBudget::background(),
+            (true, true) => ExecutionBudget::power_saving(),
+        };
+        if tab.execution_budget().await == budget {
+            return;
+        }
+        tab.set_budget(budget).await;
+
+        if let Some(channel) = channel {
+            let message = ChannelMessage::Control {
+                command: "set_background".to_string(),
+                params: serde_json::json!({
+                    "background": background,
+                    "power_saving": power_saving,
+                })
+                .to_string(),
+            };
+            if let Err(e) = channel.send(message).await {
+                log::warn!("Failed to update renderer budget: {}", e);
             }
         }
     }
@@ -284,6 +877,161 @@ impl SendSafeTabManager {
             .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
     }
 
+    /// Terminate every tab's VM, zeroizing its memory, and forget all tabs
+    /// without persisting any state. Returns the ids of the wiped tabs.
+    pub async fn wipe_all_tabs(&self) -> TabResult<Vec<Uuid>> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::WipeAllTabs {
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Stop the tab manager: flush container tabs, then terminate every VM,
+    /// giving both together at most `timeout`. The manager accepts no
+    /// commands afterwards.
+    pub async fn shutdown(&self, timeout: Duration) -> TabResult<TabShutdown> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::Shutdown {
+                timeout,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Fetch the resources tab VMs request through `broker`, for open tabs
+    /// and those opened later. Until a broker is set, requests go unanswered.
+    pub async fn set_resource_broker(&self, broker: ResourceBroker) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::SetResourceBroker {
+                broker,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Expire idle tabs per `policy` from now on. The reaper keeps checking
+    /// at the pace of the policy the manager was created with, so a policy
+    /// enabled later takes effect only if that one was enabled too; tabs
+    /// can always be expired at once with
+    /// [`expire_idle_tabs`](Self::expire_idle_tabs).
+    pub async fn set_expiry_policy(&self, policy: ExpiryPolicy) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::SetExpiryPolicy {
+                policy,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Start keeping VMs ready for new tabs. Until then each tab creates its
+    /// VM when it opens; browsers call this once their window is up.
+    pub async fn warm_vm_pool(&self) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::WarmVmPool {
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Throttle background tabs harder while the device saves power, or
+    /// return them to the usual background budget
+    pub async fn set_power_saving(&self, enabled: bool) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::SetPowerSaving {
+                enabled,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Seal `policy` into the VMs of tabs opened or reopened from now on.
+    /// Tabs already running keep the policy their VM started with.
+    pub async fn set_vm_policy(&self, policy: VmPolicy) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::SetVmPolicy {
+                policy,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Decide from now on whether new tabs may join a running VM. Tabs
+    /// already sharing one keep it.
+    pub async fn set_vm_reuse(&self, reuse: VmReuse) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::SetVmReuse {
+                reuse,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Requests a tab's VM has made through the resource broker, oldest
+    /// first; empty until a broker is set
+    pub async fn request_log(&self, tab_id: Uuid) -> TabResult<Vec<RequestRecord>> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(TabManagerCommand::RequestLog {
+                tab_id,
+                response: response_sender,
+            })
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
     /// Switch to a different tab
     pub async fn switch_tab(&self, tab_id: Uuid) -> TabResult<()> {
         let (response_sender, response_receiver) = oneshot::channel();
@@ -312,6 +1060,12 @@ impl SendSafeTabManager {
         }
     }
 
+    /// Open tabs whose title or URL fuzzy-matches `query`, best first,
+    /// including expired tabs
+    pub fn search_tabs(&self, query: &str, limit: usize) -> Vec<TabMatch> {
+        crate::switcher::search(&self.get_tab_states(), query, limit)
+    }
+
     /// Convert a tab to a container
     pub async fn convert_to_container(&self, tab_id: Uuid) -> TabResult<()> {
         let (response_sender, response_receiver) = oneshot::channel();
@@ -349,6 +1103,132 @@ impl SendSafeTabManager {
             .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
     }
 
+    /// Expire idle ephemeral tabs now rather than waiting for the reaper.
+    /// Returns the ids of the tabs that expired.
+    pub async fn expire_idle_tabs(&self) -> TabResult<Vec<Uuid>> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::ReapIdleTabs {
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))
+    }
+
+    /// Give an expired tab a fresh VM; the caller then navigates it to its URL
+    pub async fn reopen_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::ReopenTab {
+            tab_id,
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
+    }
+
+    /// Expire a background tab now, as the reaper would once it idled,
+    /// whatever its type. Its VM is terminated; [`reopen_tab`](Self::reopen_tab)
+    /// brings it back.
+    pub async fn hibernate_tab(&self, tab_id: Uuid) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::HibernateTab {
+            tab_id,
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
+    }
+
+    /// Put a tab in the named group, or take it out of its group with `None`
+    pub async fn set_tab_group(&self, tab_id: Uuid, group: Option<String>) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::SetGroup {
+            tab_id,
+            group,
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
+    }
+
+    /// Record whether a tab's page would be playing sound
+    pub async fn set_audible(&self, tab_id: Uuid, audible: bool) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::SetAudible {
+            tab_id,
+            audible,
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
+    }
+
+    /// Mute or unmute a tab, in its state and in its VM
+    pub async fn set_muted(&self, tab_id: Uuid, muted: bool) -> TabResult<()> {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = TabManagerCommand::SetMuted {
+            tab_id,
+            muted,
+            response: response_sender,
+        };
+
+        self.command_sender
+            .send(command)
+            .map_err(|_| TabError::InvalidOperation("TabManager channel closed".into()))?;
+
+        response_receiver
+            .await
+            .map_err(|_| TabError::InvalidOperation("Response channel closed".into()))?
+    }
+
+    /// Apply a message a tab's renderer sent across the boundary. Returns
+    /// whether the message was one the tab manager tracks.
+    pub async fn handle_renderer_message(
+        &self,
+        tab_id: Uuid,
+        message: &ChannelMessage,
+    ) -> TabResult<bool> {
+        match audio::parse_audio_state(message) {
+            Some(audible) => self.set_audible(tab_id, audible).await.map(|()| true),
+            None => Ok(false),
+        }
+    }
+
     /// Send a message to a specific tab's ZKVM channel
     pub async fn send_message_to_tab(
         &self,
@@ -373,6 +1253,10 @@ impl SendSafeTabManager {
     }
 }
 
-// Implement Send and Sync for the wrapper
-unsafe impl Send for SendSafeTabManager {}
-unsafe impl Sync for SendSafeTabManager {}
+// The manager is shared across tasks; Send + Sync come from its fields, so a
+// non-thread-safe field fails to compile here instead of being papered over
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SendSafeTabManager>();
+    assert_send_sync::<crate::TabManager>();
+};
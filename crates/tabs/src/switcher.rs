@@ -0,0 +1,164 @@
+//! Fuzzy search over open tabs for the quick switcher
+//!
+//! Every whitespace-separated term of the query must match the tab's title
+//! or URL as a case-insensitive subsequence. Matches at word starts and runs
+//! of consecutive characters score higher, so `gh iss` finds
+//! "GitHub · Issues". An empty query lists every tab, most recently active
+//! first. Expired (hibernated) tabs are included and flagged, so the caller
+//! can wake them on selection.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{PageContent, TabState};
+
+/// Most characters of page text shown as a match's preview
+const PREVIEW_CHARS: usize = 160;
+
+/// An open tab matching a switcher query
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabMatch {
+    pub tab_id: Uuid,
+    pub title: String,
+    pub url: String,
+    /// Ranking score; higher is better
+    pub score: u32,
+    /// Whether the tab's VM was terminated while idle
+    pub hibernated: bool,
+    /// Start of the page's text, or its error
+    pub preview: String,
+    last_active_at: DateTime<Utc>,
+}
+
+/// Score of `term` (lowercase) as a subsequence of `text`, or `None` if it
+/// does not occur
+pub fn fuzzy_score(term: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in term.chars() {
+        let found = position + text[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Tabs matching `query`, best first
+pub fn search(tabs: &[TabState], query: &str, limit: usize) -> Vec<TabMatch> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut matches: Vec<TabMatch> = tabs
+        .iter()
+        .filter_map(|tab| {
+            let score = terms.iter().try_fold(0, |total, term| {
+                let best = fuzzy_score(term, &tab.title).max(fuzzy_score(term, &tab.url))?;
+                Some(total + best)
+            })?;
+            Some(TabMatch {
+                tab_id: tab.id,
+                title: tab.title.clone(),
+                url: tab.url.clone(),
+                score,
+                hibernated: matches!(tab.content, PageContent::Expired { .. }),
+                preview: preview(&tab.content),
+                last_active_at: tab.last_active_at,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.last_active_at.cmp(&a.last_active_at))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+fn preview(content: &PageContent) -> String {
+    let text = match content {
+        PageContent::Loaded { content, .. } => content.as_str(),
+        PageContent::Error { error, .. } => error.as_str(),
+        _ => "",
+    };
+    let mut preview: String = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(PREVIEW_CHARS)
+        .collect();
+    if preview.chars().count() == PREVIEW_CHARS {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TabType;
+
+    fn tab(title: &str, url: &str, content: PageContent, idle_minutes: i64) -> TabState {
+        let at = Utc::now() - chrono::Duration::minutes(idle_minutes);
+        TabState {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            url: url.to_string(),
+            tab_type: TabType::Ephemeral,
+            is_active: false,
+            created_at: at,
+            last_active_at: at,
+            is_audible: false,
+            is_muted: false,
+            group: None,
+            content,
+        }
+    }
+
+    #[test]
+    fn test_switcher_ranks_word_starts_and_flags_hibernated_tabs() {
+        let issues = tab(
+            "GitHub · Issues",
+            "https://github.com/issues",
+            PageContent::Expired {
+                url: "https://github.com/issues".to_string(),
+            },
+            30,
+        );
+        let news = tab(
+            "Hacker News",
+            "https://news.ycombinator.com/",
+            PageContent::Loaded {
+                url: "https://news.ycombinator.com/".to_string(),
+                title: "Hacker News".to_string(),
+                content: "  Top   stories\n today ".to_string(),
+                element_count: 1,
+                size_bytes: 1,
+            },
+            1,
+        );
+        let tabs = vec![news.clone(), issues.clone()];
+
+        let found = search(&tabs, "gh iss", 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].tab_id, issues.id);
+        assert!(found[0].hibernated);
+
+        assert!(search(&tabs, "zzz", 10).is_empty());
+        assert!(fuzzy_score("hn", "hacker news") > fuzzy_score("hn", "python"));
+
+        // An empty query lists every tab, most recent first
+        let all = search(&tabs, "  ", 10);
+        assert_eq!(all[0].tab_id, news.id);
+        assert_eq!(all[0].preview, "Top stories today");
+        assert_eq!(search(&tabs, "", 1).len(), 1);
+    }
+}
@@ -9,6 +9,7 @@ use uuid::Uuid;
 pub enum Message {
     TabSelected(Uuid),
     TabClosed(Uuid),
+    MuteToggled(Uuid),
     ConvertToContainerRequested(Uuid),
     ConvertToContainerConfirmed(Uuid),
     ConvertToContainerCancelled,
@@ -42,6 +43,17 @@ impl TabBar {
                     let _ = manager.close_tab(id).await;
                 });
             }
+            Message::MuteToggled(id) => {
+                let muted = self
+                    .manager
+                    .get_tab_states()
+                    .iter()
+                    .any(|tab| tab.id == id && tab.is_muted);
+                let manager = self.manager.clone();
+                tokio::spawn(async move {
+                    let _ = manager.set_muted(id, !muted).await;
+                });
+            }
             Message::ConvertToContainerRequested(id) => {
                 self.show_conversion_dialog = Some(id);
             }
@@ -81,11 +93,15 @@ impl TabBar {
 
         let close_button = button("×").on_press(Message::TabClosed(tab.id)).padding(5);
 
-        let mut tab_content = Row::new()
-            .spacing(10)
-            .padding(Padding::new(10.0))
-            .push(title)
-            .push(close_button);
+        let mut tab_content = Row::new().spacing(10).padding(Padding::new(10.0));
+        if let Some(indicator) = speaker_indicator(tab) {
+            tab_content = tab_content.push(
+                button(indicator)
+                    .on_press(Message::MuteToggled(tab.id))
+                    .padding(5),
+            );
+        }
+        tab_content = tab_content.push(title).push(close_button);
 
         // Add convert button for ephemeral tabs
         if matches!(tab.tab_type, TabType::Ephemeral) {
@@ -195,3 +211,13 @@ mod theme {
         }
     }
 }
+
+/// Speaker icon for a tab that plays sound or is muted; clicking it toggles
+/// the mute
+pub fn speaker_indicator(tab: &TabState) -> Option<&'static str> {
+    match (tab.is_muted, tab.is_audible) {
+        (true, _) => Some("🔇"),
+        (false, true) => Some("🔊"),
+        (false, false) => None,
+    }
+}
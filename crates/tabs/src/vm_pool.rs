@@ -0,0 +1,168 @@
+//! Warm pool of tab VMs
+//!
+//! Creating a VM and its channels is the slow part of opening a tab, so the
+//! tab manager keeps a few ready ahead of time. A pooled VM has never been
+//! started and its channel has never carried a message; it is checked for
+//! that again when it leaves the pool and is replaced by a new one if the
+//! check fails. VMs only ever leave the pool: a closed tab's VM is terminated,
+//! never put back.
+
+use crate::TabResult;
+use citadel_zkvm::{MuxChannel, ZkVm};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// VMs kept ready unless configured otherwise
+pub(crate) const DEFAULT_WARM_VMS: usize = 2;
+
+/// A VM with the host's end of its channel, created but never used
+pub(crate) struct WarmVm {
+    pub(crate) vm: ZkVm,
+    pub(crate) host_channel: MuxChannel,
+}
+
+impl WarmVm {
+    /// Create a VM and its channel
+    pub(crate) async fn new() -> TabResult<Self> {
+        let (vm, host_channel) = ZkVm::new().await?;
+        Ok(Self { vm, host_channel })
+    }
+}
+
+/// Pre-created VMs handed out to new tabs and refilled in the background
+pub(crate) struct VmPool {
+    capacity: usize,
+    state: Arc<Mutex<PoolState>>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    warm: VecDeque<WarmVm>,
+    /// VMs being created to refill the pool
+    pending: usize,
+}
+
+impl VmPool {
+    /// A pool keeping up to `capacity` VMs ready; zero disables it
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Arc::new(Mutex::new(PoolState::default())),
+        }
+    }
+
+    /// Keep up to `capacity` VMs ready from now on and start filling up;
+    /// VMs already warm beyond it are kept until handed out
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.replenish();
+    }
+
+    /// A never-used VM for a new tab: a pooled one if there is one, otherwise
+    /// a new one. Starts refilling the pool either way.
+    pub(crate) async fn take(&self) -> TabResult<WarmVm> {
+        let pooled = self.state.lock().ok().and_then(|mut s| s.warm.pop_front());
+        self.replenish();
+        if let Some(warm) = pooled {
+            if warm.vm.is_pristine().await {
+                return Ok(warm);
+            }
+            // Dropping it terminates the VM and wipes its memory
+            log::warn!("Discarding a pooled VM that was not pristine");
+        }
+        WarmVm::new().await
+    }
+
+    /// Create VMs in the background until the pool is full
+    pub(crate) fn replenish(&self) {
+        let missing = match self.state.lock() {
+            Ok(mut state) => {
+                let missing = self
+                    .capacity
+                    .saturating_sub(state.warm.len() + state.pending);
+                state.pending += missing;
+                missing
+            }
+            Err(_) => return,
+        };
+        for _ in 0..missing {
+            let state = self.state.clone();
+            tokio::spawn(async move {
+                let created = WarmVm::new().await;
+                let Ok(mut state) = state.lock() else {
+                    return;
+                };
+                state.pending -= 1;
+                match created {
+                    Ok(warm) => state.warm.push_back(warm),
+                    Err(e) => log::warn!("Failed to warm a tab VM: {}", e),
+                }
+            });
+        }
+    }
+
+    /// Terminate every pooled VM
+    pub(crate) fn drain(&self) {
+        let drained = self
+            .state
+            .lock()
+            .map(|mut state| std::mem::take(&mut state.warm))
+            .unwrap_or_default();
+        drop(drained);
+    }
+
+    /// VMs ready to hand out
+    pub(crate) fn ready(&self) -> usize {
+        self.state.lock().map_or(0, |state| state.warm.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VmPolicy;
+    use std::time::Duration;
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    #[tokio::test]
+    async fn test_pool_hands_out_only_pristine_vms() {
+        let pool = VmPool::new(2);
+        pool.replenish();
+        settle().await;
+        assert_eq!(pool.ready(), 2);
+
+        // A VM that ran is never handed out, even if it ends up in the pool
+        let used = pool.take().await.unwrap();
+        used.vm
+            .start(used.vm.seal_policy(&VmPolicy::default()).unwrap())
+            .await
+            .unwrap();
+        settle().await;
+        pool.drain();
+        pool.state.lock().unwrap().warm.push_back(used);
+        let fresh = pool.take().await.unwrap();
+        assert!(fresh.vm.is_pristine().await);
+
+        settle().await;
+        assert_eq!(pool.ready(), 2);
+        pool.drain();
+        assert_eq!(pool.ready(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_pool_warms_once_given_a_capacity() {
+        let mut pool = VmPool::new(0);
+        pool.replenish();
+        let warm = pool.take().await.unwrap();
+        assert!(warm.vm.is_pristine().await);
+        settle().await;
+        assert_eq!(pool.ready(), 0);
+
+        pool.set_capacity(1);
+        settle().await;
+        assert_eq!(pool.ready(), 1);
+    }
+}
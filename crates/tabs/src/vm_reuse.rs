@@ -0,0 +1,319 @@
+//! How tabs are spread over VMs
+//!
+//! Every tab's page runs in a ZKVM. [`VmReuse`] decides whether a new tab
+//! starts a VM of its own or joins one already running:
+//!
+//! - [`VmReuse::Strict`], the default: one VM per tab. Nothing a page does
+//!   can reach another tab's memory, and closing a tab zeroizes its VM.
+//! - [`VmReuse::SiteKeyed`]: container tabs opened on the same site, in the
+//!   same container, share one VM. Pages of one site in one container
+//!   already share cookies and storage, so this gives up the least.
+//! - [`VmReuse::Pooled`]: container tabs of the same container share VMs
+//!   whatever their site, up to `tabs_per_vm` tabs each. This saves the
+//!   most memory and isolates the least.
+//!
+//! Ephemeral tabs get a VM of their own under every policy: they promise
+//! that nothing of them outlives them, which a VM still running other tabs
+//! would not keep. Tabs of different containers never share either, since
+//! their VMs are sealed with different host policies. A VM is only joined
+//! while it runs on the policy the new tab would be started on.
+//!
+//! # What sharing gives up
+//!
+//! Tabs in one VM share its memory: a page that subverts the renderer can
+//! read the other pages in it, where a VM of its own would stop it. A tab
+//! keeps the VM it opened in when it navigates elsewhere, so under
+//! [`VmReuse::SiteKeyed`] a VM can end up holding other sites too. Closing
+//! or hibernating one of the tabs wipes nothing; the VM is terminated, and
+//! its memory zeroized, only when its last tab goes. Until then it runs at
+//! the foreground budget while any of its tabs is in front, a mute mutes
+//! all of them, and the resource requests it makes are fetched and logged
+//! for the first of its tabs still open.
+//!
+//! A change of policy applies to tabs opened afterwards; VMs already shared
+//! keep their tabs.
+
+#[cfg(feature = "zkvm-isolation")]
+use std::sync::Arc;
+
+use citadel_networking::NetworkPartitionKey;
+#[cfg(feature = "zkvm-isolation")]
+use citadel_zkvm::{MuxChannel, ZkVm};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::TabType;
+#[cfg(feature = "zkvm-isolation")]
+use crate::VmPolicy;
+
+/// Tabs a pooled VM holds unless configured otherwise
+pub const DEFAULT_TABS_PER_VM: usize = 4;
+
+/// Whether new tabs may join a running VM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VmReuse {
+    /// One VM per tab
+    #[default]
+    Strict,
+    /// Container tabs of one site in one container share a VM
+    SiteKeyed,
+    /// Container tabs of one container share VMs, up to `tabs_per_vm` each
+    Pooled { tabs_per_vm: usize },
+}
+
+impl VmReuse {
+    /// Pooled VMs holding [`DEFAULT_TABS_PER_VM`] tabs
+    pub fn pooled() -> Self {
+        Self::Pooled {
+            tabs_per_vm: DEFAULT_TABS_PER_VM,
+        }
+    }
+
+    /// Most tabs one VM may hold
+    pub fn tabs_per_vm(&self) -> usize {
+        match self {
+            Self::Strict => 1,
+            Self::SiteKeyed => usize::MAX,
+            Self::Pooled { tabs_per_vm } => (*tabs_per_vm).max(1),
+        }
+    }
+
+    /// What a tab of `tab_type` opening `url` may share a VM by; `None`
+    /// when it gets one of its own
+    #[cfg_attr(not(feature = "zkvm-isolation"), allow(dead_code))]
+    pub(crate) fn share_key(&self, tab_type: TabType, url: &str) -> Option<ShareKey> {
+        let TabType::Container { container_id } = tab_type else {
+            return None;
+        };
+        match self {
+            Self::Strict => None,
+            Self::SiteKeyed => {
+                let site = Url::parse(url)
+                    .ok()
+                    .and_then(|url| NetworkPartitionKey::for_url(&url))?
+                    .top_level_site;
+                Some(ShareKey::Site { container_id, site })
+            }
+            Self::Pooled { .. } => {
+                (self.tabs_per_vm() > 1).then_some(ShareKey::Container(container_id))
+            }
+        }
+    }
+}
+
+/// What tabs sharing a VM have in common
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "zkvm-isolation"), allow(dead_code))]
+pub(crate) enum ShareKey {
+    Site { container_id: Uuid, site: String },
+    Container(Uuid),
+}
+
+/// A VM tabs were put in under a sharing policy
+#[cfg(feature = "zkvm-isolation")]
+struct SharedVm {
+    key: ShareKey,
+    vm: Arc<ZkVm>,
+    channel: MuxChannel,
+    /// The policy the VM was started on
+    policy: VmPolicy,
+    /// Its tabs, in the order they joined; the first one's requests are the
+    /// ones brokered
+    tabs: Vec<Uuid>,
+}
+
+/// The VMs tabs share, per the current [`VmReuse`]
+#[cfg(feature = "zkvm-isolation")]
+pub(crate) struct SharedVms {
+    reuse: VmReuse,
+    vms: Vec<SharedVm>,
+}
+
+#[cfg(feature = "zkvm-isolation")]
+impl SharedVms {
+    pub(crate) fn new(reuse: VmReuse) -> Self {
+        Self {
+            reuse,
+            vms: Vec::new(),
+        }
+    }
+
+    /// Share by `reuse` from now on
+    pub(crate) fn set_reuse(&mut self, reuse: VmReuse) {
+        self.reuse = reuse;
+    }
+
+    /// What a new tab may share a VM by
+    pub(crate) fn key_for(&self, tab_type: TabType, url: &str) -> Option<ShareKey> {
+        self.reuse.share_key(tab_type, url)
+    }
+
+    /// A VM of `key` with room for another tab, running on `policy`
+    pub(crate) fn find(
+        &self,
+        key: &ShareKey,
+        policy: &VmPolicy,
+    ) -> Option<(Arc<ZkVm>, MuxChannel)> {
+        let room = self.reuse.tabs_per_vm();
+        self.vms
+            .iter()
+            .find(|shared| {
+                shared.key == *key && shared.policy == *policy && shared.tabs.len() < room
+            })
+            .map(|shared| (shared.vm.clone(), shared.channel.clone()))
+    }
+
+    /// Record that `tab_id` runs in `vm`, under `key`
+    pub(crate) fn add(
+        &mut self,
+        key: ShareKey,
+        tab_id: Uuid,
+        vm: &Arc<ZkVm>,
+        channel: &MuxChannel,
+        policy: &VmPolicy,
+    ) {
+        match self
+            .vms
+            .iter_mut()
+            .find(|shared| Arc::ptr_eq(&shared.vm, vm))
+        {
+            Some(shared) => shared.tabs.push(tab_id),
+            None => self.vms.push(SharedVm {
+                key,
+                vm: vm.clone(),
+                channel: channel.clone(),
+                policy: policy.clone(),
+                tabs: vec![tab_id],
+            }),
+        }
+    }
+
+    /// Forget `tab_id`. Returns the tabs still running in its VM, empty
+    /// when it had the VM to itself.
+    pub(crate) fn remove(&mut self, tab_id: Uuid) -> Vec<Uuid> {
+        let Some(at) = self
+            .vms
+            .iter()
+            .position(|shared| shared.tabs.contains(&tab_id))
+        else {
+            return Vec::new();
+        };
+        let shared = &mut self.vms[at];
+        shared.tabs.retain(|tab| *tab != tab_id);
+        let remaining = shared.tabs.clone();
+        if remaining.is_empty() {
+            self.vms.remove(at);
+        }
+        remaining
+    }
+
+    /// The tabs running in the same VM as `tab_id`, itself included
+    pub(crate) fn group(&self, tab_id: Uuid) -> Vec<Uuid> {
+        self.vms
+            .iter()
+            .find(|shared| shared.tabs.contains(&tab_id))
+            .map_or_else(|| vec![tab_id], |shared| shared.tabs.clone())
+    }
+
+    /// Forget every shared VM
+    pub(crate) fn clear(&mut self) {
+        self.vms.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_keys() {
+        let container_id = Uuid::new_v4();
+        let container = TabType::Container { container_id };
+
+        assert_eq!(
+            VmReuse::Strict.share_key(container, "https://a.example/"),
+            None
+        );
+        assert_eq!(
+            VmReuse::SiteKeyed.share_key(TabType::Ephemeral, "https://a.example/"),
+            None
+        );
+        assert_eq!(
+            VmReuse::pooled().share_key(TabType::Ephemeral, "https://a.example/"),
+            None
+        );
+
+        // Subdomains of one site share; other sites and containers do not
+        let mail = VmReuse::SiteKeyed.share_key(container, "https://mail.a.example/inbox");
+        assert_eq!(
+            mail,
+            VmReuse::SiteKeyed.share_key(container, "https://docs.a.example/")
+        );
+        assert_ne!(
+            mail,
+            VmReuse::SiteKeyed.share_key(container, "https://b.example/")
+        );
+        assert_ne!(
+            mail,
+            VmReuse::SiteKeyed.share_key(
+                TabType::Container {
+                    container_id: Uuid::new_v4()
+                },
+                "https://mail.a.example/"
+            )
+        );
+        assert_eq!(VmReuse::SiteKeyed.share_key(container, ""), None);
+
+        assert_eq!(
+            VmReuse::pooled().share_key(container, "https://b.example/"),
+            Some(ShareKey::Container(container_id))
+        );
+        assert_eq!(
+            VmReuse::Pooled { tabs_per_vm: 1 }.share_key(container, "https://b.example/"),
+            None
+        );
+
+        let settings: VmReuse = serde_json::from_str(r#"{"pooled": {"tabs_per_vm": 3}}"#).unwrap();
+        assert_eq!(settings.tabs_per_vm(), 3);
+        assert_eq!(
+            serde_json::from_str::<VmReuse>(r#""site_keyed""#).unwrap(),
+            VmReuse::SiteKeyed
+        );
+    }
+
+    #[cfg(feature = "zkvm-isolation")]
+    #[tokio::test]
+    async fn test_pooled_vms_fill_up_and_empty() {
+        let container = TabType::Container {
+            container_id: Uuid::new_v4(),
+        };
+        let policy = VmPolicy::default();
+        let mut shared = SharedVms::new(VmReuse::Pooled { tabs_per_vm: 2 });
+        let key = shared.key_for(container, "https://a.example/").unwrap();
+        assert!(shared.find(&key, &policy).is_none());
+
+        let (vm, channel) = ZkVm::new().await.unwrap();
+        let vm = Arc::new(vm);
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        shared.add(key.clone(), first, &vm, &channel, &policy);
+        let (joined, _) = shared.find(&key, &policy).unwrap();
+        assert!(Arc::ptr_eq(&joined, &vm));
+
+        // A VM on another policy is not joined
+        let other_policy = VmPolicy {
+            scripts: false,
+            ..VmPolicy::default()
+        };
+        assert!(shared.find(&key, &other_policy).is_none());
+
+        shared.add(key.clone(), second, &vm, &channel, &policy);
+        assert!(shared.find(&key, &policy).is_none());
+        assert_eq!(shared.group(first), [first, second]);
+
+        assert_eq!(shared.remove(first), [second]);
+        assert_eq!(shared.remove(second), Vec::<Uuid>::new());
+        assert_eq!(shared.group(second), [second]);
+    }
+}
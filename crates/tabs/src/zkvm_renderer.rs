@@ -1,20 +1,27 @@
 //! ZKVM-isolated renderer for secure tab content processing.
 //!
 //! This module runs *inside* the ZKVM isolation boundary. Untrusted page bytes
-//! enter only through the encrypted [`Channel`]; the renderer parses, sanitizes,
-//! and lays them out here, and emits a serializable display list back across the
-//! boundary. The host never touches the raw markup — it only paints the sanitized
+//! enter only through the tab's encrypted `MuxChannel`; the renderer parses,
+//! sanitizes, and lays them out here, and emits a serializable display list back
+//! across the boundary on the renderer stream. The host never touches the raw markup — it only paints the sanitized
 //! display list. That is the "zero-knowledge tab" property in practice.
 
+use crate::{audio, VmPolicy};
+#[cfg(feature = "zkvm-isolation")]
 use crate::{TabError, TabResult};
-use citadel_parser::css::{ColorValue, LengthValue};
+use citadel_parser::css::{ColorValue, DisplayType, LengthValue};
 use citadel_parser::{
-    dom::NodeData, dom::NodeHandle, parse_css, parse_html,
-    security::SecurityContext as ParserSecurityContext, CitadelStylesheet,
+    dom::NodeData,
+    dom::NodeHandle,
+    parse_css, parse_html,
+    text::{self, TextDirection},
+    CitadelStylesheet, ElementState,
 };
-use citadel_zkvm::{Channel, ChannelMessage};
+#[cfg(feature = "zkvm-isolation")]
+use citadel_zkvm::{ChannelMessage, ExecutionBudget, MuxChannel, StreamId};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+#[cfg(feature = "zkvm-isolation")]
 use tokio::sync::RwLock;
 
 /// A request to render a page, sent from the host into the isolation boundary.
@@ -34,6 +41,21 @@ pub struct RenderRequest {
     /// older serialized requests (without the field) deserializable.
     #[serde(default)]
     pub enable_scripts: bool,
+    /// The user's CSS for this page, cascaded after the page's own rules with
+    /// user-origin priority. Empty when no user stylesheet matches.
+    #[serde(default)]
+    pub user_css: String,
+    /// Sources of locally installed extension content scripts matching this
+    /// page, run at document-ready with DOM access only. Unlike page scripts
+    /// they do not need `enable_scripts`: installing the extension is the
+    /// opt-in.
+    #[serde(default)]
+    pub content_scripts: Vec<String>,
+    /// Site compatibility prelude run before the page's own scripts, in the
+    /// same context, when scripts are enabled. Defines missing globals only;
+    /// empty for sites without shims.
+    #[serde(default)]
+    pub compat_script: String,
 }
 
 /// Kind of a rendered primitive, used by the host painter to pick styling.
@@ -86,6 +108,28 @@ pub struct DisplayItem {
     pub margin_top: f32,
     /// Outer bottom margin in logical pixels (transparent gap below the box).
     pub margin_bottom: f32,
+    /// Style applied while the item has keyboard focus (links only).
+    #[serde(default)]
+    pub focus_style: Option<FocusStyle>,
+    /// Right-to-left base direction (from `dir` or the text's first strong
+    /// character); the host aligns the run to the right edge.
+    #[serde(default)]
+    pub rtl: bool,
+    /// Text contains scripts that need a shaping engine (Arabic, Indic, Thai...).
+    #[serde(default)]
+    pub complex_shaping: bool,
+}
+
+/// Colours a focusable item switches to while it has keyboard focus, resolved
+/// from the page's `:focus` CSS inside the boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FocusStyle {
+    /// Text colour while focused.
+    pub color: [u8; 3],
+    /// Background colour while focused, if the page sets one.
+    pub background: Option<[u8; 3]>,
+    /// Focus ring colour.
+    pub outline: [u8; 3],
 }
 
 /// Security metadata describing what the isolation boundary blocked.
@@ -106,6 +150,12 @@ pub struct SecurityMetadata {
     /// External `<script src=...>` not executed (subresource fetch is future work).
     #[serde(default)]
     pub external_scripts_skipped: usize,
+    /// Extension content scripts that ran against the page's DOM.
+    #[serde(default)]
+    pub content_scripts_executed: usize,
+    /// Extension content scripts that threw (caught at the boundary).
+    #[serde(default)]
+    pub content_scripts_errored: usize,
 }
 
 /// Fully rendered, sanitized content ready for the host to paint.
@@ -127,6 +177,9 @@ pub struct RenderedContent {
     pub content_width: f32,
     /// What the boundary sanitized.
     pub security_metadata: SecurityMetadata,
+    /// Whether the page has media elements that would play sound.
+    #[serde(default)]
+    pub plays_audio: bool,
 }
 
 /// CSS resolution context threaded through the DOM walk inside the boundary.
@@ -152,31 +205,52 @@ const INLINE_TAGS: &[&str] = &[
     "s",
 ];
 
+/// Focus ring colour used when the page does not style `:focus`.
+const DEFAULT_FOCUS_RING: [u8; 3] = [26, 115, 232];
+
 /// ZKVM renderer that processes content in complete isolation.
+#[cfg(feature = "zkvm-isolation")]
 pub struct ZkVmRenderer {
-    /// Channel for receiving rendering requests and returning results.
-    channel: Arc<RwLock<Channel>>,
+    /// The VM's end of the tab channel: requests in, results out.
+    channel: MuxChannel,
+    /// Policy the VM was started with; every page is held to it.
+    policy: VmPolicy,
     /// Current rendering state.
     state: Arc<RwLock<RendererState>>,
 }
 
 /// Internal renderer state.
+#[cfg(feature = "zkvm-isolation")]
 #[derive(Debug)]
 struct RendererState {
     /// Whether the renderer is active.
     active: bool,
     /// Current tab ID being processed.
     current_tab_id: Option<uuid::Uuid>,
+    /// CPU and timer allowance (throttled while the tab is in the background).
+    budget: ExecutionBudget,
+    /// Whether the user muted the tab; no sound may be produced while set.
+    muted: bool,
 }
 
+#[cfg(feature = "zkvm-isolation")]
 impl ZkVmRenderer {
-    /// Create a new ZKVM renderer with an isolated communication channel.
-    pub fn new(channel: Channel) -> Self {
+    /// Create a new ZKVM renderer with an isolated communication channel,
+    /// under the default policy.
+    pub fn new(channel: MuxChannel) -> Self {
+        Self::with_policy(channel, VmPolicy::default())
+    }
+
+    /// Create a renderer held to the policy its VM was started with.
+    pub fn with_policy(channel: MuxChannel, policy: VmPolicy) -> Self {
         Self {
-            channel: Arc::new(RwLock::new(channel)),
+            channel,
+            policy,
             state: Arc::new(RwLock::new(RendererState {
                 active: true,
                 current_tab_id: None,
+                budget: ExecutionBudget::foreground(),
+                muted: false,
             })),
         }
     }
@@ -199,15 +273,26 @@ impl ZkVmRenderer {
         state.current_tab_id
     }
 
+    /// Current execution budget.
+    pub async fn execution_budget(&self) -> ExecutionBudget {
+        self.state.read().await.budget
+    }
+
+    /// Whether the tab is muted. The media pipeline checks this before
+    /// producing sound.
+    pub async fn is_muted(&self) -> bool {
+        self.state.read().await.muted
+    }
+
     /// Start the isolated renderer loop.
     pub async fn run(&self) -> TabResult<()> {
         log::info!("🔒 ZKVM renderer starting in isolated environment");
 
         loop {
-            let mut channel = self.channel.write().await;
-            match channel.receive().await {
-                Ok(message) => {
-                    drop(channel);
+            // Control messages are served first, so a mute or throttle is not
+            // stuck behind queued pages
+            match self.channel.receive_any().await {
+                Ok((_, message)) => {
                     if let Err(e) = self.handle_message(message).await {
                         log::error!("🚨 ZKVM message handling error: {}", e);
                     }
@@ -234,6 +319,11 @@ impl ZkVmRenderer {
         match message {
             ChannelMessage::Control { command, params } => match command.as_str() {
                 "render_page" => {
+                    // Background tabs get their work spaced out
+                    let interval = self.state.read().await.budget.slice_interval_ms;
+                    if interval > 0 {
+                        tokio::time::sleep(std::time::Duration::from_millis(interval)).await;
+                    }
                     let request: RenderRequest = serde_json::from_str(&params).map_err(|e| {
                         TabError::InvalidOperation(format!(
                             "ZKVM render request parse failed: {}",
@@ -245,22 +335,64 @@ impl ZkVmRenderer {
                         request.url,
                         request.html.len()
                     );
-                    let rendered = render_in_isolation(&request);
+                    let budget = self.state.read().await.budget;
+                    let rendered =
+                        render_scheduled(&request, &self.policy, &budget, Some(&self.channel));
                     log::info!(
                         "✅ ZKVM: produced {} display items, {} elements blocked",
                         rendered.display_list.len(),
                         rendered.security_metadata.blocked_elements
                     );
+                    // Media would start playing: let the host show it
+                    if rendered.plays_audio {
+                        self.channel
+                            .send(audio::audio_state_message(true))
+                            .await
+                            .map_err(|e| {
+                                TabError::InvalidOperation(format!(
+                                    "ZKVM boundary send failed: {}",
+                                    e
+                                ))
+                            })?;
+                    }
                     let response = ChannelMessage::Control {
                         command: "rendered_content".to_string(),
                         params: serde_json::to_string(&rendered).map_err(|e| {
                             TabError::InvalidOperation(format!("ZKVM serialize failed: {}", e))
                         })?,
                     };
-                    let channel = self.channel.write().await;
-                    channel.send(response).await.map_err(|e| {
-                        TabError::InvalidOperation(format!("ZKVM boundary send failed: {}", e))
-                    })?;
+                    // Waits while the host has a full window of pages unread
+                    self.channel
+                        .send_on(StreamId::Renderer, response)
+                        .await
+                        .map_err(|e| {
+                            TabError::InvalidOperation(format!("ZKVM boundary send failed: {}", e))
+                        })?;
+                }
+                "set_background" => {
+                    let params = serde_json::from_str::<serde_json::Value>(&params).ok();
+                    let flag = |name: &str| {
+                        params
+                            .as_ref()
+                            .and_then(|v| v.get(name).and_then(|b| b.as_bool()))
+                            .unwrap_or(false)
+                    };
+                    let background = flag("background");
+                    self.state.write().await.budget = match (background, flag("power_saving")) {
+                        (false, _) => ExecutionBudget::foreground(),
+                        (true, false) => ExecutionBudget::background(),
+                        (true, true) => ExecutionBudget::power_saving(),
+                    };
+                    log::debug!(
+                        "🔒 ZKVM: renderer {}",
+                        if background { "throttled" } else { "restored" }
+                    );
+                }
+                audio::SET_MUTED_COMMAND => {
+                    let message = ChannelMessage::Control { command, params };
+                    let muted = audio::parse_set_muted(&message).unwrap_or(true);
+                    self.state.write().await.muted = muted;
+                    log::debug!("🔒 ZKVM: tab {}", if muted { "muted" } else { "unmuted" });
                 }
                 "shutdown" => {
                     log::info!("🔒 ZKVM: shutdown");
@@ -268,8 +400,33 @@ impl ZkVmRenderer {
                 }
                 other => log::warn!("🚨 ZKVM: unknown command: {}", other),
             },
-            ChannelMessage::ResourceRequest { url, .. } => {
+            ChannelMessage::ResourceRequest { url, headers } => {
+                // The VM's own policy is checked before the host's
+                if let Some(reason) = self.policy.check_request(&url) {
+                    log::warn!("🚫 ZKVM: refused resource request: {}", reason);
+                    return Ok(());
+                }
+                // The VM has no network of its own: the host's broker fetches
                 log::debug!("🌐 ZKVM: resource request for {} (forwarded to host)", url);
+                self.channel
+                    .send(ChannelMessage::ResourceRequest { url, headers })
+                    .await
+                    .map_err(|e| {
+                        TabError::InvalidOperation(format!("ZKVM boundary send failed: {}", e))
+                    })?;
+            }
+            ChannelMessage::ResourceResponse {
+                url, data, last, ..
+            } => {
+                log::debug!(
+                    "🌐 ZKVM: {} bytes of {}{}",
+                    data.len(),
+                    url,
+                    if last { " (complete)" } else { "" }
+                );
+            }
+            ChannelMessage::ResourceError { url, reason } => {
+                log::debug!("🌐 ZKVM: host refused {}: {}", url, reason);
             }
             _ => log::warn!("🚨 ZKVM: unexpected message type"),
         }
@@ -279,13 +436,46 @@ impl ZkVmRenderer {
 
 /// Parse, sanitize, and lay out untrusted HTML entirely within the boundary.
 ///
-/// The display list is a deterministic function of the bytes (same HTML in, same
-/// display list out) — page JS cannot touch it (no DOM bindings yet). When the
-/// request opts in, the page's scripts additionally run through the privacy cage
-/// here inside the boundary; only execution *counts* (not script content) leave.
+/// Without scripts the display list is a deterministic function of the bytes
+/// (same HTML in, same display list out). When the request opts in, the page's
+/// scripts first run through the privacy cage here inside the boundary and the
+/// display list is laid out from the document as they left it; only execution
+/// *counts* (not script content) leave.
 pub fn render_in_isolation(request: &RenderRequest) -> RenderedContent {
+    render_with_policy(request, &VmPolicy::default())
+}
+
+/// [`render_in_isolation`] under a tab VM's policy: its parser limits, whether
+/// page scripts may run and how the JS engine presents itself.
+pub fn render_with_policy(request: &RenderRequest, policy: &VmPolicy) -> RenderedContent {
+    render_on_schedule(request, policy, &ScriptSchedule::unscheduled())
+}
+
+/// [`render_with_policy`] for a tab's VM: page scripts' timers run within
+/// `budget` (not at all while its timers are paused), and stop at the next
+/// task once `vm_channel` closes, i.e. once the VM is terminated.
+#[cfg(feature = "zkvm-isolation")]
+pub fn render_scheduled(
+    request: &RenderRequest,
+    policy: &VmPolicy,
+    budget: &ExecutionBudget,
+    vm_channel: Option<&MuxChannel>,
+) -> RenderedContent {
+    let schedule = ScriptSchedule {
+        budget: *budget,
+        vm_channel: vm_channel.cloned(),
+    };
+    render_on_schedule(request, policy, &schedule)
+}
+
+/// The render itself, with page scripts run on `schedule`
+fn render_on_schedule(
+    request: &RenderRequest,
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> RenderedContent {
     // Parse the untrusted bytes inside the boundary with a bounded-depth context.
-    let security_context = Arc::new(ParserSecurityContext::new(15));
+    let security_context = Arc::new(policy.parser_context());
     let vw = request.viewport_width.max(120.0);
     let vh = vw * 0.75; // No explicit viewport height crosses the boundary; approximate.
     let mut blocked: usize = 0;
@@ -309,18 +499,56 @@ pub fn render_in_isolation(request: &RenderRequest) -> RenderedContent {
                     scripts_executed: 0,
                     scripts_errored: 0,
                     external_scripts_skipped: 0,
+                    content_scripts_executed: 0,
+                    content_scripts_errored: 0,
                 },
+                plays_audio: false,
             };
         }
     };
 
+    // Run the page's own JS — only when explicitly opted in — through the privacy
+    // cage, here inside the isolation boundary, before styles and layout: what
+    // the scripts do to the document is written back into `dom`, so the display
+    // list shows the page as they left it. Counts only (no script content)
+    // cross the boundary.
+    let scripts_enabled = request.enable_scripts && policy.scripts;
+    let (scripts_executed, scripts_errored, external_scripts_skipped) = if scripts_enabled {
+        run_page_scripts_in_cage(&request.url, &dom, &request.compat_script, policy, schedule)
+    } else {
+        (0, 0, 0)
+    };
+    let (content_scripts_executed, content_scripts_errored) = if request.content_scripts.is_empty()
+    {
+        (0, 0)
+    } else {
+        run_content_scripts(
+            &request.url,
+            &dom,
+            &request.content_scripts,
+            policy,
+            schedule,
+        )
+    };
+    if scripts_enabled {
+        log::info!(
+            "🔒 ZKVM: page JS in cage — {} ran, {} errored, {} external skipped",
+            scripts_executed,
+            scripts_errored,
+            external_scripts_skipped
+        );
+    }
+
     // Parse the page's own <style> CSS inside the boundary and cascade it.
     let mut css = String::new();
     extract_css(&dom.root(), &mut css);
-    let sheet = parse_css(&css, security_context.clone()).unwrap_or_else(|_| CitadelStylesheet {
-        rules: Vec::new(),
-        security_context,
-    });
+    let mut sheet = parse_css(&css, security_context.clone())
+        .unwrap_or_else(|_| CitadelStylesheet::new(security_context));
+    if !request.user_css.is_empty() {
+        if let Err(e) = sheet.add_user_css(&request.user_css) {
+            log::warn!("Ignoring user stylesheet for {}: {}", request.url, e);
+        }
+    }
     let ctx = StyleCtx {
         sheet: &sheet,
         vw,
@@ -337,28 +565,9 @@ pub fn render_in_isolation(request: &RenderRequest) -> RenderedContent {
     let content_width = resolve_content_width(&body, vw, vh);
 
     let mut items = Vec::new();
-    collect_blocks(&dom.root(), &mut items, &mut blocked, false, &ctx);
+    collect_blocks(&dom.root(), &mut items, &mut blocked, false, None, &ctx);
     let (_w, height) = layout_blocks(&mut items, content_width);
 
-    // Run the page's own JS — only when explicitly opted in — through the privacy
-    // cage, here inside the isolation boundary. No DOM bindings yet, so scripts
-    // cannot alter the display list above; this proves the cage applies to a real
-    // page load and is the seam the DOM bindings will hook onto. Counts only (no
-    // script content) cross the boundary.
-    let (scripts_executed, scripts_errored, external_scripts_skipped) = if request.enable_scripts {
-        run_page_scripts_in_cage(&request.url, &dom)
-    } else {
-        (0, 0, 0)
-    };
-    if request.enable_scripts {
-        log::info!(
-            "🔒 ZKVM: page JS in cage — {} ran, {} errored, {} external skipped",
-            scripts_executed,
-            scripts_errored,
-            external_scripts_skipped
-        );
-    }
-
     RenderedContent {
         url: request.url.clone(),
         title: dom.get_title(),
@@ -374,119 +583,314 @@ pub fn render_in_isolation(request: &RenderRequest) -> RenderedContent {
             scripts_executed,
             scripts_errored,
             external_scripts_skipped,
+            content_scripts_executed,
+            content_scripts_errored,
         },
+        plays_audio: contains_media(&dom.root()),
+    }
+}
+
+/// When and for how long scripts' event loop may run. Outside a tab's VM
+/// there is neither a budget nor a channel, and the loop runs on the JS
+/// engine's defaults.
+#[cfg_attr(not(feature = "js-engine"), allow(dead_code))]
+struct ScriptSchedule {
+    /// The tab's budget when the render started
+    #[cfg(feature = "zkvm-isolation")]
+    budget: ExecutionBudget,
+    /// The VM's end of its channel; the loop stops once it is closed
+    #[cfg(feature = "zkvm-isolation")]
+    vm_channel: Option<MuxChannel>,
+}
+
+impl ScriptSchedule {
+    /// The schedule of a render outside any tab's VM
+    fn unscheduled() -> Self {
+        Self {
+            #[cfg(feature = "zkvm-isolation")]
+            budget: ExecutionBudget::foreground(),
+            #[cfg(feature = "zkvm-isolation")]
+            vm_channel: None,
+        }
+    }
+
+    /// Give the engine's event loop the tab's time slice, cancelled once
+    /// the VM is gone, and let page scripts `fetch()` through the VM's
+    /// channel
+    #[cfg(all(feature = "js-engine", feature = "zkvm-isolation"))]
+    fn apply(
+        &self,
+        engine: citadel_parser::js::CitadelJSEngine,
+        policy: &VmPolicy,
+    ) -> citadel_parser::js::CitadelJSEngine {
+        use citadel_parser::js::{Cancellation, EventLoopBudget};
+
+        let budget = if self.budget.timers_paused {
+            EventLoopBudget::paused(self.budget.time_slice_ms)
+        } else {
+            EventLoopBudget {
+                max_wall_ms: self.budget.time_slice_ms,
+                ..EventLoopBudget::default()
+            }
+        };
+        let cancellation = match self.vm_channel.clone() {
+            Some(channel) => Cancellation::when(move || channel.is_closed()),
+            None => Cancellation::never(),
+        };
+        let engine = engine.with_event_loop(budget, cancellation);
+        match self.vm_channel.clone() {
+            Some(channel) => engine.with_fetcher(Arc::new(BrokeredFetch {
+                channel,
+                policy: policy.clone(),
+            })),
+            None => engine,
+        }
     }
 }
 
 /// Extract the page's inline scripts and run them through the JS privacy cage.
 ///
-/// Returns `(executed, errored, external_skipped)`. The engine is per-origin (so
+/// Returns `(executed, errored, external_skipped)`, where a non-empty compat
+/// prelude counts as one more script. The engine is per-origin (so
 /// fingerprint noise/storage are first-party-isolated) and scripts-enabled (the
 /// caller already checked the opt-in). Any failure to build the engine fails
 /// closed: the scripts are reported as errored, never run unguarded.
-fn run_page_scripts_in_cage(url: &str, dom: &citadel_parser::Dom) -> (usize, usize, usize) {
+fn run_page_scripts_in_cage(
+    url: &str,
+    dom: &citadel_parser::Dom,
+    compat_script: &str,
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> (usize, usize, usize) {
     let mut scripts = Vec::new();
     let mut external_skipped = 0usize;
     extract_scripts(&dom.root(), &mut scripts, &mut external_skipped);
     if scripts.is_empty() {
         return (0, 0, external_skipped);
     }
+    if !compat_script.is_empty() {
+        scripts.insert(0, compat_script.to_string());
+    }
+    let (executed, errored) = execute_page_scripts(url, dom, &scripts, policy, schedule);
+    (executed, errored, external_skipped)
+}
 
-    let mut sc = ParserSecurityContext::new(15);
-    sc.enable_scripts();
-    let engine = match citadel_parser::js::CitadelJSEngine::for_origin(Arc::new(sc), url) {
+#[cfg(feature = "js-engine")]
+fn execute_page_scripts(
+    url: &str,
+    dom: &citadel_parser::Dom,
+    scripts: &[String],
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> (usize, usize) {
+    let engine = match caged_engine(url, policy, schedule) {
         Ok(engine) => engine,
         Err(e) => {
             log::error!("🚨 ZKVM: JS engine init failed (failing closed): {}", e);
-            return (0, scripts.len(), external_skipped);
+            return (0, scripts.len());
         }
     };
-    // Mirror DOM: a bounded JSON snapshot of the parsed document so scripts can
-    // query/read/mutate it inside the cage instead of throwing on first access.
-    let document_json = serialize_dom(url, dom);
-    match engine.run_page_scripts_with_document(&document_json, &scripts) {
-        Ok(outcome) => (outcome.executed, outcome.errored, external_skipped),
+    // Mirror DOM: a bounded snapshot of the parsed document that scripts can
+    // query/read/mutate inside the cage; their changes are written back to `dom`.
+    match engine.run_page_scripts_on_dom(url, dom, scripts) {
+        Ok(outcome) => (outcome.executed, outcome.errored),
         Err(e) => {
             log::error!("🚨 ZKVM: page script execution failed: {}", e);
-            (0, scripts.len(), external_skipped)
+            (0, scripts.len())
         }
     }
 }
 
-/// Max nodes / depth / text length the DOM snapshot serializes, so a hostile page
-/// cannot make the JSON snapshot (or the resulting JS DOM) unboundedly large.
-const DOM_SNAPSHOT_MAX_NODES: usize = 8000;
-const DOM_SNAPSHOT_MAX_DEPTH: usize = 64;
-const DOM_SNAPSHOT_TEXT_CAP: usize = 16384;
-
-/// Serialize the parsed DOM to a bounded JSON snapshot for the JS mirror DOM:
-/// `{ "url", "tag":"#document", "children":[ {tag,attrs,children} | {text} ] }`.
-fn serialize_dom(url: &str, dom: &citadel_parser::Dom) -> String {
-    use serde_json::Value;
-    let mut budget = DOM_SNAPSHOT_MAX_NODES;
-    let value = serialize_node(&dom.root(), DOM_SNAPSHOT_MAX_DEPTH, &mut budget);
-    let mut obj = match value {
-        Some(Value::Object(m)) => m,
-        _ => {
-            let mut m = serde_json::Map::new();
-            m.insert("tag".to_string(), Value::String("#document".to_string()));
-            m.insert("children".to_string(), Value::Array(Vec::new()));
-            m
+/// Built without the JS engine: nothing runs, and every script counts as
+/// errored, as when the engine fails to start.
+#[cfg(not(feature = "js-engine"))]
+fn execute_page_scripts(
+    _url: &str,
+    _dom: &citadel_parser::Dom,
+    scripts: &[String],
+    _policy: &VmPolicy,
+    _schedule: &ScriptSchedule,
+) -> (usize, usize) {
+    log::warn!(
+        "🔒 ZKVM: built without the JS engine; {} scripts not run",
+        scripts.len()
+    );
+    (0, scripts.len())
+}
+
+/// Run extension content scripts against the page's mirror DOM.
+///
+/// Returns `(executed, errored)`. Like page scripts, any engine failure fails
+/// closed and counts every content script as errored.
+#[cfg(feature = "js-engine")]
+fn run_content_scripts(
+    url: &str,
+    dom: &citadel_parser::Dom,
+    scripts: &[String],
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> (usize, usize) {
+    let engine = match caged_engine(url, policy, schedule) {
+        Ok(engine) => engine,
+        Err(e) => {
+            log::error!("🚨 ZKVM: JS engine init failed (failing closed): {}", e);
+            return (0, scripts.len());
         }
     };
-    obj.insert("url".to_string(), Value::String(url.to_string()));
-    serde_json::to_string(&Value::Object(obj))
-        .unwrap_or_else(|_| "{\"tag\":\"#document\",\"children\":[]}".to_string())
+    match engine.run_content_scripts_on_dom(url, dom, scripts) {
+        Ok(outcome) => (outcome.executed, outcome.errored),
+        Err(e) => {
+            log::error!("🚨 ZKVM: content script execution failed: {}", e);
+            (0, scripts.len())
+        }
+    }
 }
 
-/// Recursively serialize one node (depth/budget bounded). Elements become
-/// `{tag, attrs, children}`, text nodes `{text}`; comments/others are dropped.
-fn serialize_node(
-    handle: &NodeHandle,
-    depth: usize,
-    budget: &mut usize,
-) -> Option<serde_json::Value> {
-    use serde_json::Value;
-    if *budget == 0 || depth == 0 {
-        return None;
+#[cfg(not(feature = "js-engine"))]
+fn run_content_scripts(
+    url: &str,
+    dom: &citadel_parser::Dom,
+    scripts: &[String],
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> (usize, usize) {
+    execute_page_scripts(url, dom, scripts, policy, schedule)
+}
+
+/// A scripts-enabled JS engine within the policy's parser limits. With
+/// fingerprint noise on, canvas and audio readback are seeded per site;
+/// without it every site sees the shared normalized identity. Its event loop
+/// gets the tab's time slice, and no timers while the tab's are paused. In a
+/// tab's VM, page scripts may `fetch()` through the host's resource broker.
+#[cfg(feature = "js-engine")]
+fn caged_engine(
+    url: &str,
+    policy: &VmPolicy,
+    schedule: &ScriptSchedule,
+) -> citadel_parser::error::ParserResult<citadel_parser::js::CitadelJSEngine> {
+    use citadel_parser::js::CitadelJSEngine;
+
+    let mut sc = policy.parser_context();
+    sc.enable_scripts();
+    let engine = if policy.fingerprint_noise {
+        CitadelJSEngine::for_origin(Arc::new(sc), url)?
+    } else {
+        CitadelJSEngine::new(Arc::new(sc))?
+    };
+    #[cfg(feature = "zkvm-isolation")]
+    let engine = schedule.apply(engine, policy);
+    #[cfg(not(feature = "zkvm-isolation"))]
+    let _ = schedule;
+    Ok(engine)
+}
+
+/// How long a page script's `fetch()` waits for the host's answer
+#[cfg(all(feature = "js-engine", feature = "zkvm-isolation"))]
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Page scripts' `fetch()` through the tab's resource broker: the VM's
+/// policy is checked here, then the host applies CSP `connect-src`,
+/// blocklists and the tab's budgets as for any other resource.
+#[cfg(all(feature = "js-engine", feature = "zkvm-isolation"))]
+struct BrokeredFetch {
+    channel: MuxChannel,
+    policy: VmPolicy,
+}
+
+#[cfg(all(feature = "js-engine", feature = "zkvm-isolation"))]
+impl citadel_parser::js::ScriptFetcher for BrokeredFetch {
+    fn fetch(
+        &self,
+        request: &citadel_parser::js::FetchRequest,
+    ) -> Result<citadel_parser::js::FetchResponse, String> {
+        use tokio::runtime::{Handle, RuntimeFlavor};
+
+        let url = request.url.to_string();
+        if let Some(reason) = self.policy.check_request(&url) {
+            log::warn!("🚫 ZKVM: refused script fetch: {}", reason);
+            return Err(reason);
+        }
+        // Scripts run synchronously inside the renderer's task; waiting on the
+        // broker must leave the runtime's other workers free to answer
+        let handle = Handle::try_current().map_err(|e| e.to_string())?;
+        if handle.runtime_flavor() != RuntimeFlavor::MultiThread {
+            return Err("script fetches need a multi-threaded runtime".to_string());
+        }
+        let mut headers = request.headers.clone();
+        headers.push(("Sec-Fetch-Dest".to_string(), "empty".to_string()));
+        tokio::task::block_in_place(|| {
+            handle.block_on(async {
+                tokio::time::timeout(
+                    FETCH_TIMEOUT,
+                    self.exchange(url.clone(), headers, request.max_bytes),
+                )
+                .await
+                .unwrap_or_else(|_| Err(format!("{} timed out", url)))
+            })
+        })
     }
-    let node = handle.read().ok()?;
-    *budget = budget.saturating_sub(1);
-    match &node.data {
-        NodeData::Document | NodeData::Element(_) => {
-            let mut kids = Vec::new();
-            for child in node.children() {
-                if *budget == 0 {
-                    break;
-                }
-                if let Some(v) = serialize_node(child, depth.saturating_sub(1), budget) {
-                    kids.push(v);
-                }
-            }
-            let mut m = serde_json::Map::new();
-            if let NodeData::Element(el) = &node.data {
-                m.insert(
-                    "tag".to_string(),
-                    Value::String(el.local_name().to_ascii_lowercase()),
-                );
-                let mut attrs = serde_json::Map::new();
-                for a in &el.attributes {
-                    attrs.insert(a.name.local.to_string(), Value::String(a.value.clone()));
+}
+
+#[cfg(all(feature = "js-engine", feature = "zkvm-isolation"))]
+impl BrokeredFetch {
+    /// Ask the broker for `url` and collect its answer
+    async fn exchange(
+        &self,
+        url: String,
+        headers: Vec<(String, String)>,
+        max_bytes: usize,
+    ) -> Result<citadel_parser::js::FetchResponse, String> {
+        self.channel
+            .send(ChannelMessage::ResourceRequest {
+                url: url.clone(),
+                headers,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut response = citadel_parser::js::FetchResponse::default();
+        let mut oversized = false;
+        loop {
+            match self
+                .channel
+                .receive(StreamId::Resource)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                ChannelMessage::ResourceResponse {
+                    url: answered,
+                    served_from,
+                    status,
+                    content_type,
+                    data,
+                    last,
+                } if answered == url => {
+                    // Read an oversized body to its end, so none of it is
+                    // left on the stream for the next request
+                    oversized |= response.body.len() + data.len() > max_bytes;
+                    if !oversized {
+                        response.status = status;
+                        response.content_type = content_type;
+                        response.redirected_to = url::Url::parse(&served_from)
+                            .ok()
+                            .filter(|served_from| served_from.as_str() != url);
+                        response.body.extend_from_slice(&data);
+                    }
+                    if last {
+                        break;
+                    }
                 }
-                m.insert("attrs".to_string(), Value::Object(attrs));
-            } else {
-                m.insert("tag".to_string(), Value::String("#document".to_string()));
+                ChannelMessage::ResourceError {
+                    url: answered,
+                    reason,
+                } if answered == url => return Err(reason),
+                // The answer to a request nobody is waiting on any more
+                _ => continue,
             }
-            m.insert("children".to_string(), Value::Array(kids));
-            Some(Value::Object(m))
         }
-        NodeData::Text(t) => {
-            let s: String = t.chars().take(DOM_SNAPSHOT_TEXT_CAP).collect();
-            let mut m = serde_json::Map::new();
-            m.insert("text".to_string(), Value::String(s));
-            Some(Value::Object(m))
+        if oversized {
+            return Err(format!("{} is over {} bytes", url, max_bytes));
         }
-        _ => None,
+        Ok(response)
     }
 }
 
@@ -534,6 +938,7 @@ fn applied_policies() -> Vec<String> {
         "script_style_pruning".to_string(),
         "dangerous_scheme_blocking".to_string(),
         "css_cascade_in_boundary".to_string(),
+        "vm_policy_capsule".to_string(),
     ]
 }
 
@@ -579,6 +984,24 @@ fn extract_css(handle: &NodeHandle, out: &mut String) {
     }
 }
 
+/// Whether the document has an element that would play sound.
+fn contains_media(handle: &NodeHandle) -> bool {
+    let Ok(node) = handle.read() else {
+        return false;
+    };
+    match &node.data {
+        NodeData::Element(el)
+            if audio::MEDIA_ELEMENTS
+                .iter()
+                .any(|tag| el.local_name().eq_ignore_ascii_case(tag)) =>
+        {
+            true
+        }
+        NodeData::Element(_) | NodeData::Document => node.children().iter().any(contains_media),
+        _ => false,
+    }
+}
+
 /// Concatenate direct text children (used for `<style>` contents).
 fn collect_raw_text(children: &[NodeHandle], out: &mut String) {
     for child in children {
@@ -699,6 +1122,7 @@ fn resolve_block_style(
     if let Some(m) = c.margin_bottom.as_ref().and_then(|l| px(l)) {
         s.margin_bottom = m.max(0.0);
     }
+    s.hidden = c.display == DisplayType::None;
     s
 }
 
@@ -761,6 +1185,10 @@ struct BlockStyle {
     padding: f32,
     margin_top: f32,
     margin_bottom: f32,
+    /// Explicit or inherited `dir`; `None` means detect from the text.
+    direction: Option<TextDirection>,
+    /// `display: none` — the element and its subtree are not painted.
+    hidden: bool,
 }
 
 /// Default block style from the tag alone (browser-like font sizes + margins),
@@ -804,6 +1232,8 @@ fn default_block_style(tag: &str, inherited_bold: bool) -> BlockStyle {
         padding: 0.0,
         margin_top: margin,
         margin_bottom: margin,
+        direction: None,
+        hidden: false,
     }
 }
 
@@ -815,6 +1245,10 @@ fn flush_inline(inline: &mut String, out: &mut Vec<DisplayItem>, style: &BlockSt
     if text.is_empty() {
         return;
     }
+    let direction = style
+        .direction
+        .unwrap_or_else(|| text::base_direction(&text));
+    let complex_shaping = text::requires_complex_shaping(&text);
     out.push(DisplayItem {
         kind: style.kind,
         text,
@@ -832,6 +1266,9 @@ fn flush_inline(inline: &mut String, out: &mut Vec<DisplayItem>, style: &BlockSt
         padding: style.padding,
         margin_top: style.margin_top,
         margin_bottom: style.margin_bottom,
+        focus_style: None,
+        rtl: direction.is_rtl(),
+        complex_shaping,
     });
 }
 
@@ -849,6 +1286,7 @@ fn push_link(
     href: Option<String>,
     out: &mut Vec<DisplayItem>,
     ctx: &StyleCtx,
+    inherited_dir: Option<TextDirection>,
 ) {
     let mut text = String::new();
     collect_text(handle, &mut text);
@@ -856,6 +1294,10 @@ fn push_link(
     if text.is_empty() {
         return;
     }
+    let direction = element_direction(handle)
+        .or(inherited_dir)
+        .unwrap_or_else(|| text::base_direction(&text));
+    let complex_shaping = text::requires_complex_shaping(&text);
     let (classes, id) = element_selectors(handle);
     let computed = ctx.sheet.compute_styles("a", &classes, id.as_deref());
     let color = computed
@@ -869,6 +1311,25 @@ fn push_link(
         .and_then(|l| length_to_px(l, ctx.vw, ctx.vh))
         .filter(|s| *s > 0.0)
         .unwrap_or(16.0);
+    let focused = ctx.sheet.compute_styles_with_state(
+        "a",
+        &classes,
+        id.as_deref(),
+        ElementState { focused: true },
+    );
+    let focus_style = FocusStyle {
+        color: focused
+            .color
+            .as_ref()
+            .and_then(color_to_rgb)
+            .unwrap_or(color),
+        background: focused.background_color.as_ref().and_then(color_to_rgb),
+        outline: focused
+            .border_color
+            .as_ref()
+            .and_then(color_to_rgb)
+            .unwrap_or(DEFAULT_FOCUS_RING),
+    };
     out.push(DisplayItem {
         kind: DisplayKind::Link,
         text,
@@ -886,9 +1347,19 @@ fn push_link(
         padding: 0.0,
         margin_top: 4.0,
         margin_bottom: 4.0,
+        focus_style: Some(focus_style),
+        rtl: direction.is_rtl(),
+        complex_shaping,
     });
 }
 
+/// Explicit `dir="ltr|rtl"` on an element (`auto` and absent yield `None`).
+fn element_direction(handle: &NodeHandle) -> Option<TextDirection> {
+    let node = handle.read().ok()?;
+    let dir = node.as_element()?.get_attribute("dir")?;
+    TextDirection::from_dir_attribute(&dir)
+}
+
 /// Walk the DOM, emitting block-level display items in document order.
 ///
 /// Non-visual / dangerous subtrees are pruned (counted in `blocked`). Inline text
@@ -898,6 +1369,7 @@ fn collect_blocks(
     out: &mut Vec<DisplayItem>,
     blocked: &mut usize,
     inherited_bold: bool,
+    inherited_dir: Option<TextDirection>,
     ctx: &StyleCtx,
 ) {
     let Ok(node) = handle.read() else {
@@ -908,13 +1380,14 @@ fn collect_blocks(
     match &node.data {
         NodeData::Document => {
             for child in node.children() {
-                collect_blocks(child, out, blocked, inherited_bold, ctx);
+                collect_blocks(child, out, blocked, inherited_bold, inherited_dir, ctx);
             }
         }
         NodeData::Text(t) => {
             let text = collapse_ws(t);
             if !text.is_empty() {
-                let style = resolve_block_style(ctx, "p", &[], None, inherited_bold);
+                let mut style = resolve_block_style(ctx, "p", &[], None, inherited_bold);
+                style.direction = inherited_dir;
                 flush_inline(&mut text.clone(), out, &style);
             }
         }
@@ -928,14 +1401,21 @@ fn collect_blocks(
             // A link element becomes a single sanitized link run.
             if tag == "a" {
                 let href = sanitize_href(el.get_attribute("href"), blocked);
-                push_link(handle, href, out, ctx);
+                push_link(handle, href, out, ctx, inherited_dir);
                 return;
             }
 
             // Cascade this element's style once and reuse it for its inline runs.
             let classes = node.classes().unwrap_or_default();
             let id = node.element_id();
-            let style = resolve_block_style(ctx, &tag, &classes, id.as_deref(), inherited_bold);
+            let mut style = resolve_block_style(ctx, &tag, &classes, id.as_deref(), inherited_bold);
+            if style.hidden {
+                return;
+            }
+            style.direction = el
+                .get_attribute("dir")
+                .and_then(|dir| TextDirection::from_dir_attribute(&dir))
+                .or(inherited_dir);
             let mut inline = String::new();
 
             for child in node.children() {
@@ -954,14 +1434,14 @@ fn collect_blocks(
                         if child_tag == "a" {
                             flush_inline(&mut inline, out, &style);
                             let href = sanitize_href(child_el.get_attribute("href"), blocked);
-                            push_link(child, href, out, ctx);
+                            push_link(child, href, out, ctx, style.direction);
                         } else if INLINE_TAGS.contains(&child_tag.as_str()) {
                             collect_text(child, &mut inline);
                         } else {
                             // Block-level child: flush the current inline run, then recurse.
                             flush_inline(&mut inline, out, &style);
                             drop(child_node);
-                            collect_blocks(child, out, blocked, style.bold, ctx);
+                            collect_blocks(child, out, blocked, style.bold, style.direction, ctx);
                         }
                     }
                     _ => {}
@@ -989,7 +1469,8 @@ fn layout_blocks(items: &mut [DisplayItem], content_width: f32) -> (f32, f32) {
         let line_height = item.font_size * 1.4;
         let avg_char = (item.font_size * 0.52).max(1.0);
         let chars_per_line = ((text_width / avg_char).floor() as usize).max(1);
-        let n_chars = item.text.chars().count().max(1);
+        // Grapheme clusters, not code points: combining marks take no extra room.
+        let n_chars = text::cluster_count(&item.text).max(1);
         let lines = n_chars.div_ceil(chars_per_line).max(1);
         let text_height = (lines as f32) * line_height + item.font_size * 0.4;
         let box_height = text_height + inset * 2.0;
@@ -1006,7 +1487,17 @@ fn layout_blocks(items: &mut [DisplayItem], content_width: f32) -> (f32, f32) {
 }
 
 /// Create and run a ZKVM renderer task with full isolation.
-pub async fn spawn_zkvm_renderer(channel: Channel) -> TabResult<()> {
-    let renderer = ZkVmRenderer::new(channel);
+#[cfg(feature = "zkvm-isolation")]
+pub async fn spawn_zkvm_renderer(channel: MuxChannel) -> TabResult<()> {
+    spawn_zkvm_renderer_with_policy(channel, VmPolicy::default()).await
+}
+
+/// Create and run a ZKVM renderer task held to its VM's policy.
+#[cfg(feature = "zkvm-isolation")]
+pub async fn spawn_zkvm_renderer_with_policy(
+    channel: MuxChannel,
+    policy: VmPolicy,
+) -> TabResult<()> {
+    let renderer = ZkVmRenderer::with_policy(channel, policy);
     renderer.run().await
 }
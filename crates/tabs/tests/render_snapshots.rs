@@ -0,0 +1,69 @@
+//! Renderer output for the fixture pages in `tests/fixtures/render`, checked
+//! against the goldens in `tests/snapshots`.
+//!
+//! Each `.html` fixture is one case: adding a page adds a golden on the first
+//! run. See `snapshot` for how differences are reviewed and blessed.
+
+mod snapshot;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use citadel_tabs::{render_in_isolation, RenderRequest, RenderedContent};
+use snapshot::{assert_snapshot, render_snapshot};
+
+/// Viewport the fixtures are laid out in
+const VIEWPORT_WIDTH: f32 = 800.0;
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/render");
+    let mut pages: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("fixture directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect();
+    pages.sort();
+    pages
+}
+
+fn render(name: &str, html: String) -> RenderedContent {
+    render_in_isolation(&RenderRequest {
+        url: format!("https://fixtures.test/{name}.html"),
+        html,
+        viewport_width: VIEWPORT_WIDTH,
+        enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
+    })
+}
+
+#[test]
+fn fixture_pages_match_their_goldens() {
+    let pages = fixtures();
+    assert!(!pages.is_empty(), "no fixture pages");
+
+    // Every page is checked before failing, so one run shows every change
+    let failures: Vec<String> = pages
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?;
+            let html = fs::read_to_string(path).expect("fixture page");
+            assert_snapshot(name, &render_snapshot(&render(name, html))).err()
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn snapshots_are_stable_across_renders() {
+    for path in fixtures() {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let html = fs::read_to_string(&path).expect("fixture page");
+        assert_eq!(
+            render_snapshot(&render(&name, html.clone())),
+            render_snapshot(&render(&name, html)),
+            "{name} renders differently each time"
+        );
+    }
+}
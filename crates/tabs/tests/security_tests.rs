@@ -17,8 +17,8 @@ use tokio::time::timeout;
 use uuid::Uuid;
 
 use citadel_tabs::{
-    PageContent, SendSafeTabManager, SimpleTab, SimpleTabManager, Tab, TabError, TabResult,
-    TabState, TabType,
+    ExpiryPolicy, PageContent, SendSafeTabManager, SimpleTab, SimpleTabManager, Tab, TabError,
+    TabResult, TabState, TabType,
 };
 use citadel_zkvm::{Channel, ChannelMessage};
 
@@ -35,6 +35,10 @@ mod test_utils {
             tab_type,
             is_active,
             created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
             content: PageContent::Loading {
                 url: url.to_string(),
             },
@@ -169,6 +173,10 @@ mod tab_state_tests {
             tab_type: TabType::Ephemeral,
             is_active: true,
             created_at: chrono::Utc::now(),
+            last_active_at: chrono::Utc::now(),
+            is_audible: false,
+            is_muted: false,
+            group: None,
             content: create_sensitive_page_content(),
         };
 
@@ -336,6 +344,240 @@ mod send_safe_tab_manager_tests {
         assert!(tab_state.is_active); // First tab should be active
     }
 
+    #[tokio::test]
+    async fn test_idle_ephemeral_tabs_expire_and_reopen() {
+        let manager =
+            SendSafeTabManager::with_expiry_policy(ExpiryPolicy::new(Duration::from_millis(50)));
+
+        let active_id = manager
+            .open_tab("https://active.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let idle_id = manager
+            .open_tab("https://idle.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let container_id = manager
+            .open_tab("https://kept.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        manager.convert_to_container(container_id).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let expired = manager.expire_idle_tabs().await.unwrap();
+        // The reaper may already have run; either way only the idle tab expires
+        let states = manager.get_tab_states();
+        let state = |id: Uuid| states.iter().find(|t| t.id == id).unwrap().clone();
+        assert!(expired.iter().all(|id| *id == idle_id));
+        assert_eq!(
+            state(idle_id).content,
+            PageContent::Expired {
+                url: "https://idle.com".to_string()
+            }
+        );
+        assert!(!matches!(
+            state(active_id).content,
+            PageContent::Expired { .. }
+        ));
+        assert!(!matches!(
+            state(container_id).content,
+            PageContent::Expired { .. }
+        ));
+
+        manager.reopen_tab(idle_id).await.unwrap();
+        let reopened = manager.get_tab_states();
+        let reopened = reopened.iter().find(|t| t.id == idle_id).unwrap();
+        assert_eq!(reopened.content, PageContent::Empty);
+        assert!(manager.reopen_tab(idle_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expiry_policy_can_change_at_runtime() {
+        let manager = SendSafeTabManager::with_expiry_policy(ExpiryPolicy::disabled());
+        manager
+            .open_tab("https://active.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let idle_id = manager
+            .open_tab("https://idle.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(manager.expire_idle_tabs().await.unwrap().is_empty());
+
+        manager
+            .set_expiry_policy(ExpiryPolicy::new(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        assert_eq!(manager.expire_idle_tabs().await.unwrap(), vec![idle_id]);
+    }
+
+    #[tokio::test]
+    async fn test_wipe_all_tabs_leaves_nothing() {
+        let manager = SendSafeTabManager::new();
+        let ephemeral = manager
+            .open_tab("https://bank.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let container = manager
+            .open_tab("https://mail.com".to_string(), create_container_tab_type())
+            .await
+            .unwrap();
+        manager
+            .update_page_content(ephemeral, create_sensitive_page_content())
+            .await
+            .unwrap();
+
+        let mut wiped = manager.wipe_all_tabs().await.unwrap();
+        wiped.sort();
+        let mut opened = vec![ephemeral, container];
+        opened.sort();
+        assert_eq!(wiped, opened);
+
+        // No state, VM or channel of either tab survives
+        assert!(manager.get_tab_states().is_empty());
+        for tab_id in opened {
+            assert!(matches!(
+                manager.switch_tab(tab_id).await,
+                Err(TabError::NotFound(_))
+            ));
+            assert!(manager
+                .send_message_to_tab(tab_id, create_malicious_zkvm_message())
+                .await
+                .is_err());
+            assert!(manager.reopen_tab(tab_id).await.is_err());
+        }
+
+        // The manager is usable again afterwards
+        manager
+            .open_tab("https://fresh.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        assert_eq!(manager.get_tab_states().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_terminates_tabs_and_refuses_new_ones() {
+        let manager = SendSafeTabManager::new();
+        manager
+            .open_tab("https://bank.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        manager
+            .open_tab("https://mail.com".to_string(), create_container_tab_type())
+            .await
+            .unwrap();
+
+        let report = manager.shutdown(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report.terminated, 2);
+        assert!(report.unflushed.is_empty());
+        assert!(report.timed_out.is_empty());
+        assert!(manager.get_tab_states().is_empty());
+
+        // Nothing opens once the manager has shut down
+        assert!(manager
+            .open_tab("https://late.com".to_string(), TabType::Ephemeral)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_background_tabs_hibernate_and_join_groups() {
+        let manager = SendSafeTabManager::with_expiry_policy(ExpiryPolicy::disabled());
+        let active_id = manager
+            .open_tab("https://active.com".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let background_id = manager
+            .open_tab(
+                "https://kept.com".to_string(),
+                TabType::Container {
+                    container_id: Uuid::new_v4(),
+                },
+            )
+            .await
+            .unwrap();
+        let state = |id: Uuid| {
+            manager
+                .get_tab_states()
+                .into_iter()
+                .find(|t| t.id == id)
+                .unwrap()
+        };
+
+        // Containers hibernate on request even though they never idle out
+        manager.hibernate_tab(background_id).await.unwrap();
+        assert_eq!(
+            state(background_id).content,
+            PageContent::Expired {
+                url: "https://kept.com".to_string()
+            }
+        );
+        assert!(manager.hibernate_tab(background_id).await.is_err());
+        assert!(manager.hibernate_tab(active_id).await.is_err());
+        manager.reopen_tab(background_id).await.unwrap();
+
+        manager
+            .set_tab_group(active_id, Some("Work".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(state(active_id).group.as_deref(), Some("Work"));
+        manager.set_tab_group(active_id, None).await.unwrap();
+        assert_eq!(state(active_id).group, None);
+        assert!(matches!(
+            manager.set_tab_group(Uuid::new_v4(), None).await,
+            Err(TabError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tab_audio_state_and_mute() {
+        let manager = SendSafeTabManager::new();
+        let tab_id = manager
+            .open_tab("https://radio.example".to_string(), TabType::Ephemeral)
+            .await
+            .unwrap();
+        let state = || {
+            manager
+                .get_tab_states()
+                .into_iter()
+                .find(|t| t.id == tab_id)
+                .unwrap()
+        };
+
+        // The renderer reports that audio would start
+        let handled = manager
+            .handle_renderer_message(tab_id, &citadel_tabs::audio::audio_state_message(true))
+            .await
+            .unwrap();
+        assert!(handled);
+        assert!(state().is_audible);
+        assert!(citadel_tabs::audio::may_play_audio(&state()));
+
+        manager.set_muted(tab_id, true).await.unwrap();
+        assert!(state().is_muted);
+        assert!(!citadel_tabs::audio::may_play_audio(&state()));
+
+        // A new page starts silent but stays muted
+        manager
+            .update_page_content(
+                tab_id,
+                PageContent::Loading {
+                    url: "https://other.example".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!state().is_audible);
+        assert!(state().is_muted);
+
+        assert!(matches!(
+            manager.set_muted(Uuid::new_v4(), true).await,
+            Err(TabError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_zkvm_tab_isolation() {
         let manager = SendSafeTabManager::new();
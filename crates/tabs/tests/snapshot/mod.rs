@@ -0,0 +1,162 @@
+//! Golden snapshots of what the renderer sends out of the boundary.
+//!
+//! A snapshot is the rendered page as text, not pixels: title, sizes and what
+//! the boundary sanitized, then one line per display item with the fields it
+//! sets. Lengths are rounded to a tenth of a pixel and colours written as hex,
+//! so a snapshot diff reads as a layout change.
+//!
+//! [`assert_snapshot`] compares against `tests/snapshots/<name>.snap`:
+//!
+//! - When they differ, the new output goes to `<name>.snap.new` beside the
+//!   golden and the check fails with a diff. Review it, then bless it by
+//!   rerunning with `CITADEL_BLESS=1`, which overwrites the goldens.
+//! - A missing golden is recorded from the output, to be reviewed and
+//!   committed with the fixture. Under CI (`CI` set) it fails instead.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use citadel_tabs::RenderedContent;
+use pretty_assertions::StrComparison;
+use serde_json::Value;
+
+/// Set to overwrite the goldens with the current output
+const BLESS_VAR: &str = "CITADEL_BLESS";
+
+/// Where the goldens live
+pub fn snapshot_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots")
+}
+
+/// `rendered` as snapshot text
+pub fn render_snapshot(rendered: &RenderedContent) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "title {:?}", rendered.title);
+    let _ = writeln!(
+        out,
+        "page {} x {} content={} background={}{}",
+        length(rendered.width.into()),
+        length(rendered.height.into()),
+        length(rendered.content_width.into()),
+        hex(&rendered.background),
+        if rendered.plays_audio {
+            " plays_audio"
+        } else {
+            ""
+        }
+    );
+    let metadata = serde_json::to_value(&rendered.security_metadata).unwrap_or_default();
+    let _ = writeln!(out, "security{}", fields(&metadata, &[]));
+    for item in &rendered.display_list {
+        let item = serde_json::to_value(item).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{} {}{}",
+            item["kind"].as_str().unwrap_or("?"),
+            item["text"],
+            fields(&item, &["kind", "text"])
+        );
+    }
+    out
+}
+
+/// Compare `actual` with the golden called `name`, as described above
+pub fn assert_snapshot(name: &str, actual: &str) -> Result<(), String> {
+    let dir = snapshot_dir();
+    let golden = dir.join(format!("{name}.snap"));
+    let pending = dir.join(format!("{name}.snap.new"));
+    let write = |path: &PathBuf| {
+        fs::create_dir_all(&dir)
+            .and_then(|()| fs::write(path, actual))
+            .map_err(|e| format!("{name}: cannot write {}: {e}", path.display()))
+    };
+
+    if std::env::var_os(BLESS_VAR).is_some() {
+        let _ = fs::remove_file(&pending);
+        return write(&golden);
+    }
+    let Ok(expected) = fs::read_to_string(&golden) else {
+        if std::env::var_os("CI").is_some() {
+            return Err(format!("{name}: no golden at {}", golden.display()));
+        }
+        eprintln!("{name}: recorded a new golden at {}", golden.display());
+        return write(&golden);
+    };
+    if expected == actual {
+        let _ = fs::remove_file(&pending);
+        return Ok(());
+    }
+    write(&pending)?;
+    Err(format!(
+        "{name}: output differs from {} (new output in {}; bless with {BLESS_VAR}=1)\n{}",
+        golden.display(),
+        pending.display(),
+        StrComparison::new(&expected, actual)
+    ))
+}
+
+/// ` key=value` for each field of `value` that is set, by name. Fields that
+/// are unset, false, zero or empty are left out, so new fields only show up
+/// where they are used.
+fn fields(value: &Value, skip: &[&str]) -> String {
+    let Some(object) = value.as_object() else {
+        return String::new();
+    };
+    let mut names: Vec<&String> = object
+        .keys()
+        .filter(|name| !skip.contains(&name.as_str()))
+        .collect();
+    names.sort();
+    let mut out = String::new();
+    for name in names {
+        match &object[name] {
+            Value::Null | Value::Bool(false) => {}
+            Value::Bool(true) => {
+                let _ = write!(out, " {name}");
+            }
+            Value::Number(n) if n.as_f64() == Some(0.0) => {}
+            Value::Array(a) if a.is_empty() => {}
+            Value::String(s) if s.is_empty() => {}
+            other => {
+                let _ = write!(out, " {name}={}", field(other));
+            }
+        }
+    }
+    out
+}
+
+fn field(value: &Value) -> String {
+    match value {
+        Value::Number(n) => length(n.as_f64().unwrap_or_default()),
+        Value::Array(a) => match colour(a) {
+            Some(rgb) => hex(&rgb),
+            None => format!("[{}]", a.iter().map(field).collect::<Vec<_>>().join(",")),
+        },
+        Value::Object(_) => format!("{{{} }}", fields(value, &[])),
+        other => other.to_string(),
+    }
+}
+
+/// An RGB triple
+fn colour(values: &[Value]) -> Option<[u8; 3]> {
+    let [r, g, b] = values else {
+        return None;
+    };
+    let channel = |v: &Value| v.as_u64().and_then(|n| u8::try_from(n).ok());
+    Some([channel(r)?, channel(g)?, channel(b)?])
+}
+
+fn hex(rgb: &[u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// To a tenth of a pixel, without a trailing `.0`
+fn length(px: f64) -> String {
+    let rounded = (px * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{rounded:.1}")
+    }
+}
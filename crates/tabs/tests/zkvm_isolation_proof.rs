@@ -20,6 +20,9 @@ fn req(html: &str) -> RenderRequest {
         html: html.to_string(),
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     }
 }
 
@@ -8,7 +8,7 @@
 
 use citadel_tabs::zkvm_renderer::spawn_zkvm_renderer;
 use citadel_tabs::{render_in_isolation, DisplayKind, RenderRequest, RenderedContent};
-use citadel_zkvm::{Channel, ChannelMessage};
+use citadel_zkvm::{ChannelMessage, MuxChannel, StreamId, DEFAULT_STREAM_WINDOW};
 use std::time::Duration;
 
 /// The canonical example.com document (verbatim structure of the live page).
@@ -119,6 +119,9 @@ fn example_com_renders_fully_pure() {
         html: EXAMPLE_COM_HTML.to_string(),
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     };
     let rendered = render_in_isolation(&request);
     assert_example_com_fully_rendered(&rendered);
@@ -129,7 +132,8 @@ fn example_com_renders_fully_pure() {
 #[tokio::test]
 async fn example_com_renders_fully_over_encrypted_channel() {
     // A real AES-256-GCM encrypted channel pair: host end and VM end.
-    let (mut host_side, vm_side) = Channel::new().expect("create encrypted channel");
+    let (host_side, vm_side) =
+        MuxChannel::pair(DEFAULT_STREAM_WINDOW).expect("create encrypted channel");
 
     // The isolated renderer owns the VM end and shares nothing else with the host.
     let renderer = tokio::spawn(async move {
@@ -142,20 +146,29 @@ async fn example_com_renders_fully_over_encrypted_channel() {
         html: EXAMPLE_COM_HTML.to_string(),
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     };
     host_side
-        .send(ChannelMessage::Control {
-            command: "render_page".to_string(),
-            params: serde_json::to_string(&request).expect("serialize request"),
-        })
+        .send_on(
+            StreamId::Renderer,
+            ChannelMessage::Control {
+                command: "render_page".to_string(),
+                params: serde_json::to_string(&request).expect("serialize request"),
+            },
+        )
         .await
         .expect("send render_page across boundary");
 
     // Host receives ONLY the sanitized display list back.
-    let message = tokio::time::timeout(Duration::from_secs(10), host_side.receive())
-        .await
-        .expect("renderer responded before timeout")
-        .expect("receive rendered content");
+    let message = tokio::time::timeout(
+        Duration::from_secs(10),
+        host_side.receive(StreamId::Renderer),
+    )
+    .await
+    .expect("renderer responded before timeout")
+    .expect("receive rendered content");
 
     let rendered: RenderedContent = match message {
         ChannelMessage::Control { command, params } => {
@@ -189,6 +202,9 @@ fn boundary_blocks_scripts_and_dangerous_urls() {
         html: malicious.to_string(),
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     });
 
     // No script source survived into any visible run.
@@ -257,6 +273,9 @@ fn css_cascade_drives_colors_background_and_width() {
         html: html.to_string(),
         viewport_width: 1000.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     });
 
     // Page background from `body { background-color: #eeeeee }`.
@@ -295,6 +314,129 @@ fn css_cascade_drives_colors_background_and_width() {
     assert_eq!(link.color, [0x38, 0x48, 0x8f], "a colour from CSS");
 }
 
+/// User CSS rides in with the page, cascades over the page's own rules even
+/// against a more specific selector, and can hide elements outright.
+#[test]
+fn user_css_overrides_page_styles() {
+    let html = r#"<!doctype html><html><head><title>Dark</title>
+        <style>
+        body { background-color: #000000; }
+        #article { color: #111111; }
+        </style></head><body>
+        <p id="article">Unreadable text.</p>
+        <div class="banner"><p>Subscribe now!</p></div>
+        </body></html>"#;
+
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://dark.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 800.0,
+        enable_scripts: false,
+        user_css: "p { color: #eeeeee; } .banner { display: none; }".to_string(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
+    });
+
+    let para = r
+        .display_list
+        .iter()
+        .find(|i| i.text.contains("Unreadable"))
+        .expect("paragraph present");
+    assert_eq!(para.color, [0xee, 0xee, 0xee], "user colour wins over #id");
+    assert!(
+        !r.display_list.iter().any(|i| i.text.contains("Subscribe")),
+        "user display:none hides the banner"
+    );
+}
+
+/// `:focus` CSS is resolved inside the boundary and shipped with each link, so
+/// the host can paint keyboard focus without ever seeing the stylesheet.
+#[test]
+fn focus_css_is_resolved_for_links() {
+    let html = r#"<!doctype html><html><head><title>Focus</title>
+        <style>
+        a { color: #38488f; }
+        a:focus { color: #ffffff; background-color: #000000; border-color: #ff8800; }
+        </style></head><body>
+        <p><a href="https://ok.example/">link</a></p>
+        <p>Plain text.</p>
+        </body></html>"#;
+
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://focus.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 1000.0,
+        enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
+    });
+
+    let link = r
+        .display_list
+        .iter()
+        .find(|i| i.kind == DisplayKind::Link)
+        .expect("link present");
+    assert_eq!(link.color, [0x38, 0x48, 0x8f], "unfocused colour unchanged");
+    let focus = link.focus_style.expect("links carry a focus style");
+    assert_eq!(focus.color, [0xff, 0xff, 0xff]);
+    assert_eq!(focus.background, Some([0, 0, 0]));
+    assert_eq!(focus.outline, [0xff, 0x88, 0x00]);
+
+    let para = r
+        .display_list
+        .iter()
+        .find(|i| i.text.contains("Plain text"))
+        .expect("paragraph present");
+    assert!(para.focus_style.is_none(), "plain text is not focusable");
+}
+
+/// RTL and complex-script runs are flagged inside the boundary so the host can
+/// right-align them and route them through the shaping engine.
+#[test]
+fn rtl_and_complex_scripts_are_flagged() {
+    let html = r#"<!doctype html><html><head><title>Bidi</title></head><body>
+        <p>English paragraph.</p>
+        <p>שלום עולם</p>
+        <div dir="rtl"><p>123 numbers first</p></div>
+        <p>नमस्ते दुनिया</p>
+        </body></html>"#;
+
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://bidi.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 1000.0,
+        enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
+    });
+
+    let find = |needle: &str| {
+        r.display_list
+            .iter()
+            .find(|i| i.text.contains(needle))
+            .unwrap_or_else(|| panic!("{} present", needle))
+    };
+
+    let english = find("English");
+    assert!(!english.rtl && !english.complex_shaping);
+
+    let hebrew = find("שלום");
+    assert!(
+        hebrew.rtl,
+        "Hebrew detected as RTL from its first strong char"
+    );
+    assert!(hebrew.complex_shaping);
+
+    let inherited = find("numbers first");
+    assert!(inherited.rtl, "dir=rtl is inherited by descendant blocks");
+
+    let hindi = find("नमस्ते");
+    assert!(!hindi.rtl);
+    assert!(hindi.complex_shaping, "Devanagari needs shaping");
+}
+
 /// Stage B2: CSS box decoration (background / border / padding / margin) on a
 /// text-bearing block is carried on its display item.
 #[test]
@@ -316,6 +458,9 @@ fn css_box_styling_is_applied_to_block() {
         html: html.to_string(),
         viewport_width: 1000.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     });
 
     let card = r
@@ -353,9 +498,10 @@ fn css_box_styling_is_applied_to_block() {
 
 /// M7: with the explicit opt-in, the page's inline scripts run through the JS
 /// privacy cage *inside the boundary* — proving the cage applies during a real
-/// render, not only in unit tests. The display list is unchanged (no DOM
-/// bindings yet); only execution counts cross back out.
+/// render, not only in unit tests. The script only reads the document, so the
+/// display list is unchanged; only execution counts cross back out.
 #[test]
+#[cfg(feature = "js-engine")]
 fn opt_in_runs_page_scripts_in_the_cage() {
     // The inline script uses the mirror DOM (document.querySelector); if the DOM
     // were not wired into the render path it would throw and count as errored.
@@ -371,6 +517,9 @@ fn opt_in_runs_page_scripts_in_the_cage() {
         html: html.to_string(),
         viewport_width: 800.0,
         enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     });
     assert_eq!(
         off.security_metadata.scripts_executed, 0,
@@ -384,6 +533,9 @@ fn opt_in_runs_page_scripts_in_the_cage() {
         html: html.to_string(),
         viewport_width: 800.0,
         enable_scripts: true,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
     });
     assert_eq!(
         on.security_metadata.scripts_executed, 1,
@@ -395,7 +547,7 @@ fn opt_in_runs_page_scripts_in_the_cage() {
         "external (empty-body) script skipped"
     );
 
-    // The visible render is identical with or without scripts (no DOM bindings).
+    // The visible render is identical with or without a script that only reads.
     assert_eq!(
         off.display_list.len(),
         on.display_list.len(),
@@ -403,3 +555,114 @@ fn opt_in_runs_page_scripts_in_the_cage() {
     );
     assert!(on.display_list.iter().any(|i| i.text == "Heading"));
 }
+
+/// What page scripts do to the document is what gets laid out.
+#[test]
+#[cfg(feature = "js-engine")]
+fn script_dom_mutations_reach_the_display_list() {
+    let html = r#"<!doctype html><html><body>
+        <h1 id="title">Loading</h1>
+        <script>
+          document.getElementById('title').innerText = 'Ready';
+          var p = document.createElement('p');
+          p.innerText = 'Inserted by script';
+          document.body.appendChild(p);
+        </script>
+        </body></html>"#;
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://dom.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 800.0,
+        enable_scripts: true,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: String::new(),
+    });
+    assert_eq!(r.security_metadata.scripts_errored, 0);
+    let texts: Vec<&str> = r.display_list.iter().map(|i| i.text.as_str()).collect();
+    assert!(texts.contains(&"Ready"), "{texts:?}");
+    assert!(texts.contains(&"Inserted by script"), "{texts:?}");
+    assert!(!texts.contains(&"Loading"), "{texts:?}");
+}
+
+/// A site's compat prelude runs first, in the page's own context, so a page
+/// script that assumes a missing global no longer throws.
+#[test]
+#[cfg(feature = "js-engine")]
+fn compat_prelude_defines_globals_for_page_scripts() {
+    let html = r#"<!doctype html><html><body>
+        <script>if (typeof dataLayer === 'undefined') { throw new Error('missing'); } dataLayer.push(1);</script>
+        </body></html>"#;
+    let request = |compat_script: &str| RenderRequest {
+        url: "https://compat.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 800.0,
+        enable_scripts: true,
+        user_css: String::new(),
+        content_scripts: Vec::new(),
+        compat_script: compat_script.to_string(),
+    };
+
+    let broken = render_in_isolation(&request(""));
+    assert_eq!(broken.security_metadata.scripts_errored, 1);
+
+    let shimmed = render_in_isolation(&request(
+        "if(typeof globalThis[\"dataLayer\"]==='undefined'){globalThis[\"dataLayer\"]=[];}",
+    ));
+    assert_eq!(
+        shimmed.security_metadata.scripts_executed, 2,
+        "prelude + page"
+    );
+    assert_eq!(shimmed.security_metadata.scripts_errored, 0);
+}
+
+/// Built without the JS engine, opted-in scripts fail closed: none run and
+/// each counts as errored.
+#[test]
+#[cfg(not(feature = "js-engine"))]
+fn scripts_fail_closed_without_js_engine() {
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://js.example/".to_string(),
+        html: "<body><script>var x = 1;</script></body>".to_string(),
+        viewport_width: 800.0,
+        enable_scripts: true,
+        user_css: String::new(),
+        content_scripts: vec!["var y = 2;".to_string()],
+        compat_script: String::new(),
+    });
+    assert_eq!(r.security_metadata.scripts_executed, 0);
+    assert_eq!(r.security_metadata.scripts_errored, 1);
+    assert_eq!(r.security_metadata.content_scripts_errored, 1);
+}
+
+/// Extension content scripts run inside the boundary even with page JS off,
+/// and only their counts cross back out.
+#[test]
+#[cfg(feature = "js-engine")]
+fn content_scripts_run_without_page_js_opt_in() {
+    let html = r#"<!doctype html><html><body>
+        <h1 id="title">Heading</h1>
+        <script>globalThis.pageRan = true;</script>
+        </body></html>"#;
+
+    let r = render_in_isolation(&RenderRequest {
+        url: "https://ext.example/".to_string(),
+        html: html.to_string(),
+        viewport_width: 800.0,
+        enable_scripts: false,
+        user_css: String::new(),
+        content_scripts: vec![
+            "document.getElementById('title').textContent = 'Renamed';".to_string(),
+            "if (typeof pageRan !== 'undefined') { throw new Error('shared global'); }".to_string(),
+            "fetch('https://exfil.example/');".to_string(),
+        ],
+        compat_script: String::new(),
+    });
+
+    assert_eq!(r.security_metadata.scripts_executed, 0, "page JS stays off");
+    assert_eq!(r.security_metadata.content_scripts_executed, 2);
+    assert_eq!(
+        r.security_metadata.content_scripts_errored, 1,
+        "no fetch capability"
+    );
+}
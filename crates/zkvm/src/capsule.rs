@@ -0,0 +1,64 @@
+//! Policy capsules
+//!
+//! The host hands each VM its policy as a capsule: the policy serialized and
+//! signed with a key only the host and that VM share. The VM checks the
+//! signature when it starts and refuses to run on a capsule that was altered
+//! or sealed for another VM, so the code inside enforces the policy the host
+//! meant for it, not one slipped in along the way.
+
+use crate::{ZkVmError, ZkVmResult};
+use citadel_security::secrets::{ct_eq, SecretKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// A policy as delivered to a VM: serialized, with its signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyCapsule {
+    payload: Vec<u8>,
+    signature: [u8; 32],
+}
+
+impl PolicyCapsule {
+    /// Serialize `policy` and sign it with `key`
+    pub(crate) fn seal<T: Serialize>(policy: &T, key: &CapsuleKey) -> ZkVmResult<Self> {
+        let payload = serde_json::to_vec(policy).map_err(|e| {
+            ZkVmError::InvalidOperation(format!("Policy serialization failed: {}", e))
+        })?;
+        let signature = key.sign(&payload);
+        Ok(Self { payload, signature })
+    }
+
+    /// The payload, if `key` signed it
+    pub(crate) fn verify(&self, key: &CapsuleKey) -> ZkVmResult<&[u8]> {
+        if !ct_eq(&key.sign(&self.payload), &self.signature) {
+            return Err(ZkVmError::CryptoError(
+                "Policy capsule signature mismatch".into(),
+            ));
+        }
+        Ok(&self.payload)
+    }
+
+    /// Decode a verified payload
+    pub(crate) fn decode<T: DeserializeOwned>(payload: &[u8]) -> ZkVmResult<T> {
+        serde_json::from_slice(payload)
+            .map_err(|e| ZkVmError::InvalidOperation(format!("Malformed policy capsule: {}", e)))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn payload_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.payload
+    }
+}
+
+/// Signing key of one VM's capsules
+pub(crate) struct CapsuleKey(SecretKey);
+
+impl CapsuleKey {
+    /// A fresh random key
+    pub(crate) fn generate() -> Self {
+        Self(SecretKey::generate())
+    }
+
+    fn sign(&self, payload: &[u8]) -> [u8; 32] {
+        *blake3::keyed_hash(self.0.expose(), payload).as_bytes()
+    }
+}
@@ -4,8 +4,9 @@ use aes_gcm::{
     Aes256Gcm,
 };
 use blake3::Hash;
+use citadel_security::secrets::{SecretBytes, SecretKey};
 use rand::RngCore;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 
@@ -17,8 +18,18 @@ pub enum ChannelMessage {
         url: String,
         headers: Vec<(String, String)>,
     },
-    /// Resource response
-    ResourceResponse { data: Vec<u8>, content_type: String },
+    /// A chunk of a resource's body; `last` marks the final one. `served_from`
+    /// is where redirects led from `url`, or `url` itself.
+    ResourceResponse {
+        url: String,
+        served_from: String,
+        status: u16,
+        content_type: String,
+        data: Vec<u8>,
+        last: bool,
+    },
+    /// A resource request that was refused or failed
+    ResourceError { url: String, reason: String },
     /// UI event
     UiEvent {
         event_type: String,
@@ -38,7 +49,7 @@ pub struct Channel {
     /// Receiver end of the channel
     receiver: mpsc::Receiver<EncryptedMessage>,
     /// Channel encryption key
-    key: Arc<[u8; 32]>,
+    key: Arc<SecretKey>,
     /// Channel state
     state: Arc<RwLock<ChannelState>>,
 }
@@ -69,13 +80,13 @@ struct ChannelState {
 #[derive(Debug)]
 pub struct SecureChannel {
     /// Encryption key
-    key: [u8; 32],
+    key: SecretKey,
 }
 
 impl SecureChannel {
     /// Create a new secure channel with the given key
     pub fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self { key: key.into() }
     }
 
     /// Encrypt a message
@@ -86,7 +97,7 @@ impl SecureChannel {
         let nonce = aes_gcm::Nonce::from_slice(&nonce);
 
         // Create cipher instance
-        let cipher = Aes256Gcm::new_from_slice(&self.key)
+        let cipher = Aes256Gcm::new_from_slice(self.key.expose())
             .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
 
         // Encrypt the plaintext
@@ -110,7 +121,7 @@ impl SecureChannel {
         let encrypted_data = &ciphertext[12..];
 
         // Create cipher instance
-        let cipher = Aes256Gcm::new_from_slice(&self.key)
+        let cipher = Aes256Gcm::new_from_slice(self.key.expose())
             .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
 
         // Decrypt the ciphertext
@@ -123,12 +134,15 @@ impl SecureChannel {
 impl Channel {
     /// Create a new secure channel pair
     pub fn new() -> ZkVmResult<(Self, Self)> {
-        let mut key = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut key);
-        let key = Arc::new(key);
+        Self::with_capacity(32)
+    }
 
-        let (tx1, rx1) = mpsc::channel(32);
-        let (tx2, rx2) = mpsc::channel(32);
+    /// A channel pair queueing at most `capacity` messages each way
+    pub(crate) fn with_capacity(capacity: usize) -> ZkVmResult<(Self, Self)> {
+        let key = Arc::new(SecretKey::generate());
+
+        let (tx1, rx1) = mpsc::channel(capacity);
+        let (tx2, rx2) = mpsc::channel(capacity);
 
         let channel1 = Self {
             sender: tx1,
@@ -161,39 +175,10 @@ impl Channel {
         if !state.active {
             return Err(ZkVmError::ChannelError("Channel is closed".into()));
         }
-
-        // Serialize the message
-        let message_bytes = bincode::serialize(&message)
-            .map_err(|e| ZkVmError::ChannelError(format!("Serialization failed: {}", e)))?;
-
-        // Generate nonce
-        let mut nonce = [0u8; 12];
-        rand::thread_rng().fill_bytes(&mut nonce);
-
-        // Encrypt the message
-        let cipher = aes_gcm::Aes256Gcm::new_from_slice(self.key.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        let encrypted = cipher
-            .encrypt(aes_gcm::Nonce::from_slice(&nonce), message_bytes.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        // Calculate MAC
-        let mac = blake3::hash(&encrypted);
-
-        // Create encrypted message
-        let encrypted_message = EncryptedMessage {
-            content: encrypted,
-            mac,
-            nonce,
-        };
-
-        // Send the message
         self.sender
-            .send(encrypted_message)
+            .send(seal(&self.key, &message)?)
             .await
             .map_err(|e| ZkVmError::ChannelError(format!("Send failed: {}", e)))?;
-
         state.messages_sent += 1;
         Ok(())
     }
@@ -211,29 +196,7 @@ impl Channel {
             .recv()
             .await
             .ok_or_else(|| ZkVmError::ChannelError("Channel closed".into()))?;
-
-        // Verify MAC
-        let calculated_mac = blake3::hash(&encrypted_message.content);
-        if calculated_mac != encrypted_message.mac {
-            return Err(ZkVmError::ChannelError(
-                "Message authentication failed".into(),
-            ));
-        }
-
-        // Decrypt the message
-        let cipher = aes_gcm::Aes256Gcm::new_from_slice(self.key.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        let decrypted = cipher
-            .decrypt(
-                aes_gcm::Nonce::from_slice(&encrypted_message.nonce),
-                encrypted_message.content.as_ref(),
-            )
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        // Deserialize the message
-        let message = bincode::deserialize(&decrypted)
-            .map_err(|e| ZkVmError::ChannelError(format!("Deserialization failed: {}", e)))?;
+        let message = open(&self.key, encrypted_message)?;
 
         state.messages_received += 1;
         Ok(message)
@@ -244,6 +207,136 @@ impl Channel {
         let mut state = self.state.write().await;
         state.active = false;
     }
+
+    /// Split into a sending and a receiving half that can be used at once
+    pub(crate) fn split(self) -> (ChannelSender, ChannelReceiver) {
+        (
+            ChannelSender {
+                sender: self.sender,
+                key: self.key.clone(),
+                state: self.state.clone(),
+            },
+            ChannelReceiver {
+                receiver: self.receiver,
+                key: self.key,
+                state: self.state,
+            },
+        )
+    }
+}
+
+/// Sending half of a [`Channel`], carrying any serializable frame
+pub(crate) struct ChannelSender {
+    sender: mpsc::Sender<EncryptedMessage>,
+    key: Arc<SecretKey>,
+    state: Arc<RwLock<ChannelState>>,
+}
+
+impl ChannelSender {
+    /// Encrypt and send a frame; waits while the peer's queue is full
+    pub(crate) async fn send<T: Serialize>(&self, frame: &T) -> ZkVmResult<()> {
+        if !self.state.read().await.active {
+            return Err(ZkVmError::ChannelError("Channel is closed".into()));
+        }
+        self.sender
+            .send(seal(&self.key, frame)?)
+            .await
+            .map_err(|e| ZkVmError::ChannelError(format!("Send failed: {}", e)))?;
+        self.state.write().await.messages_sent += 1;
+        Ok(())
+    }
+
+    /// Refuse further sends and receives on both halves
+    pub(crate) async fn close(&self) {
+        self.state.write().await.active = false;
+    }
+}
+
+/// Receiving half of a [`Channel`]
+pub(crate) struct ChannelReceiver {
+    receiver: mpsc::Receiver<EncryptedMessage>,
+    key: Arc<SecretKey>,
+    state: Arc<RwLock<ChannelState>>,
+}
+
+impl ChannelReceiver {
+    /// Wait for the next frame and authenticate it. Unlike
+    /// [`Channel::receive`] no lock is held while waiting, so the sending
+    /// half stays usable. Cancel-safe: a dropped call loses no frame.
+    pub(crate) async fn receive<T: DeserializeOwned>(&mut self) -> ZkVmResult<T> {
+        if !self.state.read().await.active {
+            return Err(ZkVmError::ChannelError("Channel is closed".into()));
+        }
+        let encrypted_message = self
+            .receiver
+            .recv()
+            .await
+            .ok_or_else(|| ZkVmError::ChannelError("Channel closed".into()))?;
+        let frame = open(&self.key, encrypted_message)?;
+        self.state.write().await.messages_received += 1;
+        Ok(frame)
+    }
+}
+
+/// Serialize, encrypt and MAC a frame
+fn seal<T: Serialize>(key: &SecretKey, frame: &T) -> ZkVmResult<EncryptedMessage> {
+    // Serialize the message
+    let message_bytes = SecretBytes::new(
+        bincode::serialize(frame)
+            .map_err(|e| ZkVmError::ChannelError(format!("Serialization failed: {}", e)))?,
+    );
+
+    // Generate nonce
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    // Encrypt the message
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(key.expose())
+        .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+
+    let encrypted = cipher
+        .encrypt(aes_gcm::Nonce::from_slice(&nonce), message_bytes.expose())
+        .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+
+    // Calculate MAC
+    let mac = blake3::hash(&encrypted);
+
+    Ok(EncryptedMessage {
+        content: encrypted,
+        mac,
+        nonce,
+    })
+}
+
+/// Verify, decrypt and deserialize a frame
+fn open<T: DeserializeOwned>(
+    key: &SecretKey,
+    encrypted_message: EncryptedMessage,
+) -> ZkVmResult<T> {
+    // Verify MAC
+    let calculated_mac = blake3::hash(&encrypted_message.content);
+    if calculated_mac != encrypted_message.mac {
+        return Err(ZkVmError::ChannelError(
+            "Message authentication failed".into(),
+        ));
+    }
+
+    // Decrypt the message
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(key.expose())
+        .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+
+    let decrypted = SecretBytes::new(
+        cipher
+            .decrypt(
+                aes_gcm::Nonce::from_slice(&encrypted_message.nonce),
+                encrypted_message.content.as_ref(),
+            )
+            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?,
+    );
+
+    // Deserialize the message
+    bincode::deserialize(decrypted.expose())
+        .map_err(|e| ZkVmError::ChannelError(format!("Deserialization failed: {}", e)))
 }
 
 #[cfg(test)]
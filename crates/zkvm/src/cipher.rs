@@ -0,0 +1,178 @@
+//! Page encryption algorithms
+//!
+//! VM memory pages are sealed with an AEAD cipher behind [`PageCipher`], so
+//! the algorithm can be picked per VM. AES-256-GCM is fast where the CPU has
+//! AES instructions; XChaCha20-Poly1305 is fast everywhere else, and its
+//! 24-byte nonces are safe to pick at random for any number of seals. A
+//! sealed page carries its nonce in front of the ciphertext, and each page
+//! remembers the cipher it was sealed with, so pages sealed under an older
+//! choice still open after the default changes.
+
+use crate::{ZkVmError, ZkVmResult};
+use aes_gcm::{
+    aead::{generic_array::typenum::Unsigned, Aead, AeadCore, KeyInit, Nonce},
+    Aes256Gcm,
+};
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+
+/// An AEAD cipher for VM memory pages
+pub trait PageCipher: Send + Sync {
+    /// Algorithm name, for diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Bytes of nonce in front of each sealed page
+    fn nonce_size(&self) -> usize;
+
+    /// Encrypt `plaintext` under a fresh random nonce; returns the nonce
+    /// followed by the ciphertext and tag
+    fn seal(&self, key: &[u8; 32], plaintext: &[u8]) -> ZkVmResult<Vec<u8>>;
+
+    /// Decrypt what [`Self::seal`] produced under the same key
+    fn open(&self, key: &[u8; 32], sealed: &[u8]) -> ZkVmResult<Vec<u8>>;
+}
+
+/// The page ciphers there are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageCipherKind {
+    /// AES-256-GCM, 12-byte nonces
+    Aes256Gcm,
+    /// XChaCha20-Poly1305, 24-byte nonces
+    XChaCha20Poly1305,
+}
+
+impl PageCipherKind {
+    /// The cipher for a new VM on this machine: AES-256-GCM where the CPU
+    /// accelerates AES, XChaCha20-Poly1305 otherwise. Builds with the
+    /// `prefer-xchacha20` feature always take XChaCha20-Poly1305.
+    pub fn preferred() -> Self {
+        if cfg!(feature = "prefer-xchacha20") || !aes_accelerated() {
+            Self::XChaCha20Poly1305
+        } else {
+            Self::Aes256Gcm
+        }
+    }
+
+    /// The implementation of this cipher
+    pub fn cipher(self) -> &'static dyn PageCipher {
+        match self {
+            Self::Aes256Gcm => &AesGcmPages,
+            Self::XChaCha20Poly1305 => &XChaChaPages,
+        }
+    }
+}
+
+impl Default for PageCipherKind {
+    fn default() -> Self {
+        Self::preferred()
+    }
+}
+
+/// Whether the CPU has AES instructions
+fn aes_accelerated() -> bool {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        std::arch::is_x86_feature_detected!("aes")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("aes")
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+/// AES-256-GCM pages
+struct AesGcmPages;
+
+/// XChaCha20-Poly1305 pages
+struct XChaChaPages;
+
+impl PageCipher for AesGcmPages {
+    fn name(&self) -> &'static str {
+        "AES-256-GCM"
+    }
+
+    fn nonce_size(&self) -> usize {
+        <Aes256Gcm as AeadCore>::NonceSize::USIZE
+    }
+
+    fn seal(&self, key: &[u8; 32], plaintext: &[u8]) -> ZkVmResult<Vec<u8>> {
+        seal_with::<Aes256Gcm>(key, plaintext)
+    }
+
+    fn open(&self, key: &[u8; 32], sealed: &[u8]) -> ZkVmResult<Vec<u8>> {
+        open_with::<Aes256Gcm>(key, sealed)
+    }
+}
+
+impl PageCipher for XChaChaPages {
+    fn name(&self) -> &'static str {
+        "XChaCha20-Poly1305"
+    }
+
+    fn nonce_size(&self) -> usize {
+        <XChaCha20Poly1305 as AeadCore>::NonceSize::USIZE
+    }
+
+    fn seal(&self, key: &[u8; 32], plaintext: &[u8]) -> ZkVmResult<Vec<u8>> {
+        seal_with::<XChaCha20Poly1305>(key, plaintext)
+    }
+
+    fn open(&self, key: &[u8; 32], sealed: &[u8]) -> ZkVmResult<Vec<u8>> {
+        open_with::<XChaCha20Poly1305>(key, sealed)
+    }
+}
+
+fn seal_with<C: Aead + AeadCore + KeyInit>(
+    key: &[u8; 32],
+    plaintext: &[u8],
+) -> ZkVmResult<Vec<u8>> {
+    let cipher = C::new_from_slice(key).map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+    let nonce = C::generate_nonce(&mut rand::thread_rng());
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open_with<C: Aead + AeadCore + KeyInit>(key: &[u8; 32], sealed: &[u8]) -> ZkVmResult<Vec<u8>> {
+    let nonce_size = C::NonceSize::USIZE;
+    if sealed.len() < nonce_size {
+        return Err(ZkVmError::CryptoError(
+            "Sealed page shorter than its nonce".into(),
+        ));
+    }
+    let cipher = C::new_from_slice(key).map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+    let (nonce, ciphertext) = sealed.split_at(nonce_size);
+    cipher
+        .decrypt(Nonce::<C>::from_slice(nonce), ciphertext)
+        .map_err(|e| ZkVmError::CryptoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_round_trip_and_reject_tampering() {
+        let key = [7u8; 32];
+        for kind in [PageCipherKind::Aes256Gcm, PageCipherKind::XChaCha20Poly1305] {
+            let cipher = kind.cipher();
+            let mut sealed = cipher.seal(&key, b"page contents").unwrap();
+            assert_eq!(sealed.len(), cipher.nonce_size() + 13 + 16);
+            assert_eq!(cipher.open(&key, &sealed).unwrap(), b"page contents");
+
+            assert!(cipher.open(&[8u8; 32], &sealed).is_err());
+            let last = sealed.len() - 1;
+            sealed[last] ^= 1;
+            assert!(cipher.open(&key, &sealed).is_err());
+            assert!(cipher.open(&key, &sealed[..4]).is_err());
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Step debugging of the executor
+//!
+//! Only built with the `debug` feature. A debugger loads a program into a
+//! running VM, sets breakpoints at instruction addresses, steps or resumes
+//! execution and inspects the registers and memory. Memory is dumped a page
+//! at a time and only from readable segments, so a debugger sees no more of
+//! the VM than the code inside it can. A terminated VM's memory is wiped and
+//! cannot be debugged.
+
+use crate::{PagePermissions, ZkVm, ZkVmError, ZkVmResult, ZkVmState};
+use serde::{Deserialize, Serialize};
+
+/// The executor's registers at a point in execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registers {
+    /// Program counter: the address of the next instruction
+    pub pc: u64,
+    /// Stack pointer
+    pub sp: u64,
+    /// Base pointer
+    pub bp: u64,
+    /// Status flags
+    pub flags: u32,
+    /// General purpose registers R0-R15
+    pub general: [u32; 16],
+}
+
+/// Why execution stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// One instruction ran
+    Stepped,
+    /// Execution reached the breakpoint at this address
+    Breakpoint(u64),
+    /// The next instruction is a halt
+    Halted,
+    /// The step limit of a resume ran out
+    StepLimit,
+}
+
+/// A page of VM memory, copied out for inspection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageDump {
+    /// Address of the first byte
+    pub base: u64,
+    /// Permissions of the segment the page belongs to
+    pub permissions: PagePermissions,
+    /// The page's contents, cut to its segment
+    pub bytes: Vec<u8>,
+}
+
+impl ZkVm {
+    /// Load `code` into a fresh executable segment and point the program
+    /// counter at it. Returns the address of its first instruction.
+    pub async fn load_program(&self, code: &[u8]) -> ZkVmResult<u64> {
+        self.require_running().await?;
+        self.executor.lock().await.load_program(code)
+    }
+
+    /// Execute the instruction at the program counter
+    pub async fn single_step(&self) -> ZkVmResult<Stop> {
+        self.require_running().await?;
+        self.executor.lock().await.debug_step()
+    }
+
+    /// Execute until a breakpoint or halt, at most `max_steps` instructions
+    pub async fn resume(&self, max_steps: usize) -> ZkVmResult<Stop> {
+        self.require_running().await?;
+        self.executor.lock().await.resume(max_steps)
+    }
+
+    /// Stop execution at the instruction at `addr`
+    pub async fn set_breakpoint(&self, addr: u64) -> ZkVmResult<()> {
+        self.require_running().await?;
+        self.executor.lock().await.set_breakpoint(addr)
+    }
+
+    /// Remove the breakpoint at `addr`; whether there was one
+    pub async fn clear_breakpoint(&self, addr: u64) -> bool {
+        self.executor.lock().await.clear_breakpoint(addr)
+    }
+
+    /// Addresses with a breakpoint, in order
+    pub async fn breakpoints(&self) -> Vec<u64> {
+        self.executor.lock().await.breakpoints()
+    }
+
+    /// The executor's registers
+    pub async fn registers(&self) -> ZkVmResult<Registers> {
+        self.require_live().await?;
+        Ok(self.executor.lock().await.registers())
+    }
+
+    /// Copy out the memory page holding `addr`, which must be readable
+    pub async fn dump_page(&self, addr: u64) -> ZkVmResult<PageDump> {
+        self.require_live().await?;
+        self.executor.lock().await.dump_page(addr)
+    }
+
+    async fn require_running(&self) -> ZkVmResult<()> {
+        if *self.state.read().await != ZkVmState::Running {
+            return Err(ZkVmError::InvalidOperation(
+                "VM must be running to be debugged".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn require_live(&self) -> ZkVmResult<()> {
+        if *self.state.read().await == ZkVmState::Terminated {
+            return Err(ZkVmError::InvalidOperation(
+                "Terminated VM has no state to inspect".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::block_on;
+
+    const NOP: [u8; 4] = [0, 0, 0, 0x00];
+    const HALT: [u8; 4] = [0, 0, 0, 0xFF];
+
+    #[test]
+    fn test_step_to_breakpoint_and_halt() {
+        block_on(async {
+            let (vm, _host) = ZkVm::new().await.unwrap();
+            assert!(vm.load_program(&NOP).await.is_err());
+            vm.start(vm.seal_policy(&()).unwrap()).await.unwrap();
+
+            let program = [NOP, NOP, NOP, HALT].concat();
+            let entry = vm.load_program(&program).await.unwrap();
+            assert_eq!(vm.registers().await.unwrap().pc, entry);
+            assert_eq!(vm.dump_page(entry).await.unwrap().bytes[..16], program[..]);
+
+            vm.set_breakpoint(entry + 8).await.unwrap();
+            assert_eq!(vm.single_step().await.unwrap(), Stop::Stepped);
+            assert_eq!(vm.resume(100).await.unwrap(), Stop::Breakpoint(entry + 8));
+            assert_eq!(vm.resume(100).await.unwrap(), Stop::Halted);
+            assert_eq!(vm.registers().await.unwrap().pc, entry + 12);
+
+            assert!(vm.clear_breakpoint(entry + 8).await);
+            assert!(vm.breakpoints().await.is_empty());
+
+            vm.terminate().await.unwrap();
+            assert!(vm.dump_page(entry).await.is_err());
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use citadel_errors::{Classify, ErrorKind};
 use thiserror::Error;
 
 /// Errors that can occur during ZKVM operations
@@ -18,3 +19,22 @@ pub enum ZkVmError {
     #[error("Execution error: {0}")]
     ExecutionError(String),
 }
+
+impl Classify for ZkVmError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ZkVmError::MemoryError(_) => ErrorKind::Resource,
+            _ => ErrorKind::Isolation,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ZkVmError::MemoryError(_) => "VM_MEMORY",
+            ZkVmError::CryptoError(_) => "VM_CRYPTO",
+            ZkVmError::InvalidOperation(_) => "VM_INVALID_OPERATION",
+            ZkVmError::ChannelError(_) => "VM_CHANNEL",
+            ZkVmError::ExecutionError(_) => "VM_EXECUTION",
+        }
+    }
+}
@@ -1,8 +1,13 @@
+#[cfg(feature = "debug")]
+use crate::debug::{PageDump, Registers, Stop};
 use crate::{PagePermissions, ZkVmError, ZkVmResult};
 use parking_lot::RwLock;
 use std::cmp;
 use std::collections::BTreeMap;
+#[cfg(feature = "debug")]
+use std::collections::BTreeSet;
 use std::sync::Arc;
+use zeroize::Zeroize;
 
 /// Represents a secure execution context within the ZKVM
 pub struct Executor {
@@ -13,6 +18,9 @@ pub struct Executor {
     /// Execution flags and settings
     #[allow(dead_code)] // Will be used when implementing execution time limits and JIT
     flags: ExecutionFlags,
+    /// Instruction addresses execution stops at when resumed by a debugger
+    #[cfg(feature = "debug")]
+    breakpoints: BTreeSet<u64>,
 }
 
 /// Represents the state of code execution
@@ -172,6 +180,55 @@ struct AddressSpace {
     allocated: usize,
     /// Maximum allowed memory allocation
     max_memory: usize,
+    /// Backing bytes, one page per key, created on first write; pages never
+    /// written read as zero
+    pages: BTreeMap<u64, Box<[u8]>>,
+}
+
+impl AddressSpace {
+    /// Copy `buf.len()` bytes starting at `addr` out of memory
+    fn read_bytes(&self, addr: u64, buf: &mut [u8]) {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let at = addr + i as u64;
+            *byte = self
+                .pages
+                .get(&page_base(at))
+                .map_or(0, |page| page[page_offset(at)]);
+        }
+    }
+
+    /// Copy `bytes` into memory starting at `addr`
+    fn write_bytes(&mut self, addr: u64, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            let at = addr + i as u64;
+            let page = self
+                .pages
+                .entry(page_base(at))
+                .or_insert_with(|| vec![0; PAGE_SIZE].into_boxed_slice());
+            page[page_offset(at)] = *byte;
+        }
+    }
+
+    /// Zeroize and drop the backing pages in `[base, end)`
+    fn release_pages(&mut self, base: u64, end: u64) {
+        let released: Vec<u64> = self.pages.range(base..end).map(|(at, _)| *at).collect();
+        for at in released {
+            if let Some(mut page) = self.pages.remove(&at) {
+                page.zeroize();
+            }
+        }
+    }
+}
+
+/// Size of a backing page; segments start on page boundaries
+const PAGE_SIZE: usize = MIN_SEGMENT_SIZE;
+
+fn page_base(addr: u64) -> u64 {
+    addr & !(PAGE_SIZE as u64 - 1)
+}
+
+fn page_offset(addr: u64) -> usize {
+    (addr & (PAGE_SIZE as u64 - 1)) as usize
 }
 
 /// A segment of memory in the address space
@@ -306,6 +363,7 @@ impl Executor {
                 segments: BTreeMap::new(),
                 allocated: 0,
                 max_memory: memory_limit,
+                pages: BTreeMap::new(),
             })),
             state: ExecutorState {
                 pc: 0,
@@ -319,6 +377,8 @@ impl Executor {
                 memory_limit,
                 time_limit: 5000, // 5 seconds default
             },
+            #[cfg(feature = "debug")]
+            breakpoints: BTreeSet::new(),
         })
     }
 
@@ -450,18 +510,57 @@ impl Executor {
         self.state.pc = entry_point;
 
         loop {
-            // Fetch instruction
-            let instruction = self.fetch_instruction()?;
+            self.step()?;
+        }
+    }
 
-            // Decode instruction
-            let decoded = self.decode_instruction(instruction)?;
+    /// Execute the instruction at the program counter
+    fn step(&mut self) -> ZkVmResult<()> {
+        // Fetch instruction
+        let instruction = self.fetch_instruction()?;
 
-            // Execute instruction
-            self.execute_instruction(decoded)?;
+        // Decode instruction
+        let decoded = self.decode_instruction(instruction)?;
+
+        // Execute instruction
+        self.execute_instruction(decoded)?;
+
+        // Check execution limits
+        self.check_limits()
+    }
+
+    /// Copy `code` into memory at `addr`, on the host's authority: the
+    /// segment's write permission does not apply, only that it is mapped
+    pub fn load(&mut self, addr: u64, code: &[u8]) -> ZkVmResult<()> {
+        let mut address_space = self.address_space.write();
+        let end = addr
+            .checked_add(code.len() as u64)
+            .ok_or_else(|| ZkVmError::MemoryError("Integer overflow in program load".into()))?;
+        let segment = address_space
+            .segments
+            .range(..=addr)
+            .next_back()
+            .map(|(_, segment)| segment)
+            .ok_or_else(|| ZkVmError::MemoryError(format!("Address 0x{:x} not mapped", addr)))?;
+        if !segment.contains_address(addr)? || end > segment.end_address()? {
+            return Err(ZkVmError::MemoryError(format!(
+                "Program of {} bytes at 0x{:x} does not fit its segment",
+                code.len(),
+                addr
+            )));
+        }
+        address_space.write_bytes(addr, code);
+        Ok(())
+    }
 
-            // Check execution limits
-            self.check_limits()?;
+    /// Zeroize all memory contents, keeping the segments mapped
+    pub fn wipe(&mut self) {
+        let mut address_space = self.address_space.write();
+        for page in address_space.pages.values_mut() {
+            page.zeroize();
         }
+        address_space.pages.clear();
+        self.state.registers.zeroize();
     }
 
     /// Fetch the next instruction with bounds checking
@@ -501,9 +600,9 @@ impl Executor {
                     )));
                 }
 
-                // For now, return a NOP instruction (0x00000000)
-                // In a real implementation, this would read from actual memory
-                return Ok(0x00000000);
+                let mut word = [0u8; 4];
+                address_space.read_bytes(pc, &mut word);
+                return Ok(u32::from_le_bytes(word));
             }
         }
 
@@ -682,9 +781,9 @@ impl Executor {
                     )));
                 }
 
-                // In a real implementation, this would read from actual memory
-                // For now, return a placeholder value
-                return Ok(0);
+                let mut word = [0u8; 4];
+                address_space.read_bytes(addr_u64, &mut word);
+                return Ok(u32::from_le_bytes(word));
             }
         }
 
@@ -695,8 +794,8 @@ impl Executor {
     }
 
     /// Write a 32-bit value to memory with bounds checking
-    fn write_memory(&mut self, addr: u32, _value: u32) -> ZkVmResult<()> {
-        let address_space = self.address_space.read();
+    fn write_memory(&mut self, addr: u32, value: u32) -> ZkVmResult<()> {
+        let mut address_space = self.address_space.write();
         let addr_u64 = addr as u64;
 
         // Validate address is not in null page
@@ -729,8 +828,7 @@ impl Executor {
                     )));
                 }
 
-                // In a real implementation, this would write to actual memory
-                // For now, just return success
+                address_space.write_bytes(addr_u64, &value.to_le_bytes());
                 return Ok(());
             }
         }
@@ -829,6 +927,7 @@ impl Executor {
         let mut address_space = self.address_space.write();
 
         if let Some(segment) = address_space.segments.remove(&base) {
+            address_space.release_pages(segment.base, segment.end_address()?);
             // Use checked subtraction to prevent underflow
             address_space.allocated = address_space
                 .allocated
@@ -846,6 +945,123 @@ impl Executor {
     }
 }
 
+#[cfg(feature = "debug")]
+impl Executor {
+    /// Allocate a readable, executable segment for `code`, load it there and
+    /// point the program counter at its first instruction
+    pub(crate) fn load_program(&mut self, code: &[u8]) -> ZkVmResult<u64> {
+        let size = cmp::max(code.len(), MIN_SEGMENT_SIZE);
+        let base = self.allocate_segment(
+            size,
+            PagePermissions {
+                read: true,
+                write: false,
+                execute: true,
+            },
+        )?;
+        self.load(base, code)?;
+        self.state.pc = base;
+        Ok(base)
+    }
+
+    /// Execute one instruction. A halt is reported rather than executed, so
+    /// the program counter stays on it.
+    pub(crate) fn debug_step(&mut self) -> ZkVmResult<Stop> {
+        let decoded = self.decode_instruction(self.fetch_instruction()?)?;
+        if matches!(decoded.instruction_type, InstructionType::Halt) {
+            return Ok(Stop::Halted);
+        }
+        self.execute_instruction(decoded)?;
+        self.check_limits()?;
+        if self.breakpoints.contains(&self.state.pc) {
+            Ok(Stop::Breakpoint(self.state.pc))
+        } else {
+            Ok(Stop::Stepped)
+        }
+    }
+
+    /// Step until a breakpoint or halt, at most `max_steps` instructions.
+    /// The instruction at the program counter runs even if it has a
+    /// breakpoint, so resuming from one moves on.
+    pub(crate) fn resume(&mut self, max_steps: usize) -> ZkVmResult<Stop> {
+        for _ in 0..max_steps {
+            match self.debug_step()? {
+                Stop::Stepped => continue,
+                stop => return Ok(stop),
+            }
+        }
+        Ok(Stop::StepLimit)
+    }
+
+    /// Snapshot of the registers
+    pub(crate) fn registers(&self) -> Registers {
+        Registers {
+            pc: self.state.pc,
+            sp: self.state.sp,
+            bp: self.state.bp,
+            flags: self.state.flags,
+            general: self.state.registers,
+        }
+    }
+
+    /// Stop at the instruction at `addr`, which must be executable
+    pub(crate) fn set_breakpoint(&mut self, addr: u64) -> ZkVmResult<()> {
+        let address_space = self.address_space.read();
+        let executable = match address_space.segments.range(..=addr).next_back() {
+            Some((_, segment)) => segment.contains_address(addr)? && segment.permissions.execute,
+            None => false,
+        };
+        if !executable {
+            return Err(ZkVmError::InvalidOperation(format!(
+                "No executable code at 0x{:x}",
+                addr
+            )));
+        }
+        self.breakpoints.insert(addr);
+        Ok(())
+    }
+
+    /// Remove the breakpoint at `addr`; whether there was one
+    pub(crate) fn clear_breakpoint(&mut self, addr: u64) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Addresses with a breakpoint, in order
+    pub(crate) fn breakpoints(&self) -> Vec<u64> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    /// Copy out the page holding `addr`, cut to its segment. The segment
+    /// must be readable: a debugger sees no more than the code does.
+    pub(crate) fn dump_page(&self, addr: u64) -> ZkVmResult<PageDump> {
+        let address_space = self.address_space.read();
+        let segment = match address_space.segments.range(..=addr).next_back() {
+            Some((_, segment)) if segment.contains_address(addr)? => segment,
+            _ => {
+                return Err(ZkVmError::MemoryError(format!(
+                    "Address 0x{:x} not mapped",
+                    addr
+                )))
+            }
+        };
+        if !segment.permissions.read {
+            return Err(ZkVmError::MemoryError(format!(
+                "No read permission at address 0x{:x}",
+                addr
+            )));
+        }
+        let base = cmp::max(page_base(addr), segment.base);
+        let end = cmp::min(page_base(addr) + PAGE_SIZE as u64, segment.end_address()?);
+        let mut bytes = vec![0; (end - base) as usize];
+        address_space.read_bytes(base, &mut bytes);
+        Ok(PageDump {
+            base,
+            permissions: segment.permissions,
+            bytes,
+        })
+    }
+}
+
 /// Represents a decoded instruction
 #[derive(Debug)]
 struct DecodedInstruction {
@@ -993,7 +1209,6 @@ mod tests {
                 }
             }
         }
-        drop(address_space);
 
         // Allocate second segment - should not overlap
         let _base2 = executor.allocate_segment(4096, perms).unwrap();
@@ -1109,7 +1324,6 @@ mod tests {
 
         let address_space = executor.address_space.read();
         assert_eq!(address_space.allocated, 4096 + 8192 + 16384);
-        drop(address_space);
 
         // Deallocate one segment
         executor.deallocate_segment(base2).unwrap();
@@ -1234,7 +1448,6 @@ mod tests {
         assert!(address_space.allocated + segment_size > address_space.max_memory);
 
         // Verify memory integrity
-        drop(address_space);
         executor.validate_memory_integrity().unwrap();
     }
 
@@ -1343,4 +1556,56 @@ mod tests {
         .unwrap();
         assert!(seg1.overlaps_with(&seg7).unwrap());
     }
+
+    #[test]
+    fn test_memory_holds_written_values() {
+        let mut executor = Executor::new(1024 * 1024).unwrap();
+        let perms = PagePermissions {
+            read: true,
+            write: true,
+            execute: false,
+        };
+        let base = executor
+            .allocate_segment(2 * MIN_SEGMENT_SIZE, perms)
+            .unwrap();
+
+        // Untouched memory reads as zero; a write across a page boundary reads back
+        let straddling = (base + MIN_SEGMENT_SIZE as u64 - 2) as u32;
+        assert_eq!(executor.read_memory(straddling).unwrap(), 0);
+        executor.write_memory(straddling, 0x12345678).unwrap();
+        assert_eq!(executor.read_memory(straddling).unwrap(), 0x12345678);
+
+        // A segment mapped again at the same place starts out zeroed
+        executor.deallocate_segment(base).unwrap();
+        assert_eq!(
+            executor
+                .allocate_segment(2 * MIN_SEGMENT_SIZE, perms)
+                .unwrap(),
+            base
+        );
+        assert_eq!(executor.read_memory(straddling).unwrap(), 0);
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_page_dump_requires_read_permission() {
+        let mut executor = Executor::new(1024 * 1024).unwrap();
+        let hidden = executor
+            .allocate_segment(
+                MIN_SEGMENT_SIZE,
+                PagePermissions {
+                    read: false,
+                    write: true,
+                    execute: false,
+                },
+            )
+            .unwrap();
+        let code = executor.load_program(&[0, 0, 0, 0xFF]).unwrap();
+
+        assert!(executor.dump_page(hidden).is_err());
+        let dump = executor.dump_page(code + 2).unwrap();
+        assert_eq!(dump.base, code);
+        assert_eq!(&dump.bytes[..4], &[0, 0, 0, 0xFF]);
+        assert!(executor.set_breakpoint(hidden).is_err());
+    }
 }
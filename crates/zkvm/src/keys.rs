@@ -0,0 +1,115 @@
+//! Key hierarchy of VM memory
+//!
+//! Each VM holds one random [`MasterKey`]. Page keys are never stored
+//! anywhere else: they are derived from the master key with HKDF-SHA256,
+//! salted with the VM's id and bound to the page's id and key generation.
+//! A page whose key has opened it [`ROTATION_USES`] times, or that has kept
+//! the same key for [`ROTATION_INTERVAL`], is re-sealed under the next
+//! generation the next time it is sealed. That way a page that stays hot
+//! does not pile up ciphertexts under one key. Keys are [`SecretKey`]s, so
+//! freeing a page zeroizes its key along with its contents, and dropping
+//! the VM zeroizes the master key.
+
+use crate::{ZkVmError, ZkVmResult};
+use citadel_security::secrets::SecretKey;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::time::{Duration, Instant};
+
+/// Opens of a page under one key before it is rotated
+pub const ROTATION_USES: u32 = 1024;
+
+/// Longest a page keeps one key while in use
+pub const ROTATION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// HKDF info prefix of page keys
+const PAGE_KEY_INFO: &[u8] = b"citadel-zkvm page key v1";
+
+/// Root of one VM's page keys
+pub(crate) struct MasterKey {
+    key: SecretKey,
+    salt: [u8; 32],
+}
+
+impl MasterKey {
+    /// A fresh random master key for the VM with id `vm_id`
+    pub(crate) fn generate(vm_id: &[u8; 32]) -> Self {
+        Self {
+            key: SecretKey::generate(),
+            salt: *vm_id,
+        }
+    }
+
+    /// The key of generation `generation` of page `page_id`
+    pub(crate) fn page_key(&self, page_id: usize, generation: u32) -> ZkVmResult<PageKey> {
+        let mut info = PAGE_KEY_INFO.to_vec();
+        info.extend_from_slice(&(page_id as u64).to_le_bytes());
+        info.extend_from_slice(&generation.to_le_bytes());
+
+        let mut key = SecretKey::from_bytes([0u8; 32]);
+        Hkdf::<Sha256>::new(Some(&self.salt), self.key.expose())
+            .expand(&info, key.expose_mut())
+            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
+        Ok(PageKey {
+            key,
+            generation,
+            uses: 0,
+            derived_at: Instant::now(),
+        })
+    }
+}
+
+/// A page's current key and how much it has been used
+#[derive(Debug)]
+pub(crate) struct PageKey {
+    key: SecretKey,
+    generation: u32,
+    uses: u32,
+    derived_at: Instant,
+}
+
+impl PageKey {
+    /// The key bytes
+    pub(crate) fn bytes(&self) -> &[u8; 32] {
+        self.key.expose()
+    }
+
+    /// Generation of the page's key; 0 until it is first rotated
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Count one opening of the page
+    pub(crate) fn record_use(&mut self) {
+        self.uses = self.uses.saturating_add(1);
+    }
+
+    /// Whether the page should be re-sealed under the next generation
+    pub(crate) fn rotation_due(&self) -> bool {
+        self.uses >= ROTATION_USES || self.derived_at.elapsed() >= ROTATION_INTERVAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_keys_are_bound_to_vm_page_and_generation() {
+        let master = MasterKey::generate(&[1u8; 32]);
+        let key = master.page_key(3, 0).unwrap();
+        assert_eq!(key.bytes(), master.page_key(3, 0).unwrap().bytes());
+        assert_ne!(key.bytes(), master.page_key(4, 0).unwrap().bytes());
+        assert_ne!(key.bytes(), master.page_key(3, 1).unwrap().bytes());
+
+        let other = MasterKey::generate(&[1u8; 32]);
+        assert_ne!(key.bytes(), other.page_key(3, 0).unwrap().bytes());
+
+        let mut key = key;
+        assert!(!key.rotation_due());
+        for _ in 0..ROTATION_USES {
+            key.record_use();
+        }
+        assert!(key.rotation_due());
+    }
+}
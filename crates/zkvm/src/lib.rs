@@ -4,23 +4,31 @@
 //! of isolation between browser tabs. Each VM instance operates with zero knowledge of
 //! other VMs or the host system, while still allowing controlled communication channels.
 
+pub mod capsule;
 pub mod channel;
+pub mod cipher;
+#[cfg(feature = "debug")]
+pub mod debug;
 pub mod error;
 mod executor;
+pub mod keys;
+pub mod mux;
 
-use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm,
-};
+use capsule::CapsuleKey;
+use keys::{MasterKey, PageKey};
 use rand::RngCore;
+use serde::{de::DeserializeOwned, Serialize};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use zeroize::Zeroize;
 
 // Re-export important types
+pub use capsule::PolicyCapsule;
 pub use channel::{Channel, ChannelMessage};
+pub use cipher::{PageCipher, PageCipherKind};
 pub use error::ZkVmError;
 pub use executor::Executor;
+pub use mux::{MuxChannel, StreamId, DEFAULT_STREAM_WINDOW};
 
 /// Result type for ZKVM operations
 pub type ZkVmResult<T> = Result<T, ZkVmError>;
@@ -38,6 +46,59 @@ pub enum ZkVmState {
     Terminated,
 }
 
+/// CPU and timer allowance granted to a VM. Background tabs run with a reduced
+/// budget and paused timers; activating the tab restores the foreground budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionBudget {
+    /// Maximum execution time per slice in milliseconds
+    pub time_slice_ms: u64,
+    /// Minimum delay between slices in milliseconds
+    pub slice_interval_ms: u64,
+    /// Whether page timers (refresh, media, script timers) are suspended
+    pub timers_paused: bool,
+}
+
+impl ExecutionBudget {
+    /// Full budget for the active tab
+    pub const fn foreground() -> Self {
+        Self {
+            time_slice_ms: 5000,
+            slice_interval_ms: 0,
+            timers_paused: false,
+        }
+    }
+
+    /// Throttled budget for tabs that are not visible
+    pub const fn background() -> Self {
+        Self {
+            time_slice_ms: 50,
+            slice_interval_ms: 1000,
+            timers_paused: true,
+        }
+    }
+
+    /// Budget for tabs that are not visible while the device saves power:
+    /// shorter slices, further apart
+    pub const fn power_saving() -> Self {
+        Self {
+            time_slice_ms: 20,
+            slice_interval_ms: 5000,
+            timers_paused: true,
+        }
+    }
+
+    /// Whether this budget is throttled
+    pub fn is_throttled(&self) -> bool {
+        *self != Self::foreground()
+    }
+}
+
+impl Default for ExecutionBudget {
+    fn default() -> Self {
+        Self::foreground()
+    }
+}
+
 /// Memory page permissions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PagePermissions {
@@ -54,48 +115,39 @@ struct MemoryPage {
     /// Permissions for this page
     #[allow(dead_code)] // Will be used when implementing full memory access controls
     permissions: PagePermissions,
-    /// Cryptographic key for this page
-    key: Arc<[u8; 32]>,
+    /// Current key of the page, derived from the VM's master key
+    key: PageKey,
+    /// Cipher the page is sealed with
+    cipher: PageCipherKind,
 }
 
 impl MemoryPage {
     /// Create a new memory page with given permissions
-    fn new(size: usize, permissions: PagePermissions) -> ZkVmResult<Self> {
-        let mut key = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut key);
-
-        Ok(Self {
+    fn new(
+        size: usize,
+        permissions: PagePermissions,
+        cipher: PageCipherKind,
+        key: PageKey,
+    ) -> Self {
+        Self {
             data: vec![0; size],
             permissions,
-            key: Arc::new(key),
-        })
+            key,
+            cipher,
+        }
     }
 
-    /// Encrypt the page contents
+    /// Encrypt the page contents; the nonce is kept in front of them
     fn encrypt(&mut self) -> ZkVmResult<()> {
-        let cipher = Aes256Gcm::new_from_slice(self.key.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::thread_rng());
-        let ciphertext = cipher
-            .encrypt(&nonce, self.data.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        self.data = ciphertext;
+        let sealed = self.cipher.cipher().seal(self.key.bytes(), &self.data)?;
+        self.data.zeroize();
+        self.data = sealed;
         Ok(())
     }
 
     /// Decrypt the page contents
     fn decrypt(&mut self) -> ZkVmResult<()> {
-        let cipher = Aes256Gcm::new_from_slice(self.key.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::thread_rng());
-        let plaintext = cipher
-            .decrypt(&nonce, self.data.as_ref())
-            .map_err(|e| ZkVmError::CryptoError(e.to_string()))?;
-
-        self.data = plaintext;
+        self.data = self.cipher.cipher().open(self.key.bytes(), &self.data)?;
         Ok(())
     }
 
@@ -118,36 +170,63 @@ impl MemoryPage {
     }
 }
 
+impl Drop for MemoryPage {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
 /// Core ZKVM implementation
 pub struct ZkVm {
     /// Current state of the VM
     state: RwLock<ZkVmState>,
-    /// Memory pages
-    memory: Mutex<Vec<MemoryPage>>,
+    /// Memory pages by id; freed pages leave an empty slot
+    memory: Mutex<Vec<Option<MemoryPage>>>,
     /// Unique identifier for this VM instance
     id: Arc<[u8; 32]>,
-    /// Communication channel to the host
-    channel: Channel,
+    /// The VM's end of its multiplexed channel to the host
+    channel: MuxChannel,
+    /// CPU and timer allowance
+    budget: RwLock<ExecutionBudget>,
+    /// Signs and checks the VM's policy capsule
+    capsule_key: CapsuleKey,
+    /// Policy the VM was started with, once its signature checked out
+    policy: RwLock<Option<Vec<u8>>>,
     /// Executor for running code
-    #[allow(dead_code)] // Will be used when implementing full VM execution
-    executor: Executor,
+    executor: Mutex<Executor>,
+    /// Cipher new memory pages are sealed with
+    cipher: PageCipherKind,
+    /// Root of the memory pages' keys
+    master_key: MasterKey,
 }
 
 impl ZkVm {
-    /// Create a new ZKVM instance
-    pub async fn new() -> ZkVmResult<(Self, Channel)> {
+    /// Create a new ZKVM instance with the host's end of its channel,
+    /// sealing memory with the cipher this machine runs fastest
+    pub async fn new() -> ZkVmResult<(Self, MuxChannel)> {
+        Self::with_cipher(PageCipherKind::preferred()).await
+    }
+
+    /// Create a new ZKVM instance that seals its memory pages with `cipher`
+    pub async fn with_cipher(cipher: PageCipherKind) -> ZkVmResult<(Self, MuxChannel)> {
         let mut id = [0u8; 32];
         rand::thread_rng().fill_bytes(&mut id);
 
-        let (vm_channel, host_channel) = Channel::new()?;
+        let (host_channel, vm_channel) = MuxChannel::pair(DEFAULT_STREAM_WINDOW)?;
         let executor = Executor::new(1024 * 1024 * 32)?; // 32MB default memory limit
 
+        let master_key = MasterKey::generate(&id);
         let vm = Self {
             state: RwLock::new(ZkVmState::Ready),
             memory: Mutex::new(Vec::new()),
             id: Arc::new(id),
             channel: vm_channel,
-            executor,
+            budget: RwLock::new(ExecutionBudget::foreground()),
+            capsule_key: CapsuleKey::generate(),
+            policy: RwLock::new(None),
+            executor: Mutex::new(executor),
+            cipher,
+            master_key,
         };
 
         Ok((vm, host_channel))
@@ -159,18 +238,26 @@ impl ZkVm {
         size: usize,
         permissions: PagePermissions,
     ) -> ZkVmResult<usize> {
-        let page = MemoryPage::new(size, permissions)?;
         let mut memory = self.memory.lock().await;
         let page_id = memory.len();
-        memory.push(page);
+        let key = self.master_key.page_key(page_id, 0)?;
+        memory.push(Some(MemoryPage::new(size, permissions, self.cipher, key)));
         Ok(page_id)
     }
 
-    /// Start the VM
-    pub async fn start(&self) -> ZkVmResult<()> {
+    /// Seal `policy` into a capsule for this VM, on the host side
+    pub fn seal_policy<T: Serialize>(&self, policy: &T) -> ZkVmResult<PolicyCapsule> {
+        PolicyCapsule::seal(policy, &self.capsule_key)
+    }
+
+    /// Start the VM under the policy in `capsule`, which must have been
+    /// sealed for this VM by [`Self::seal_policy`] and not altered since
+    pub async fn start(&self, capsule: PolicyCapsule) -> ZkVmResult<()> {
         let mut state = self.state.write().await;
         match *state {
             ZkVmState::Ready => {
+                let payload = capsule.verify(&self.capsule_key)?;
+                *self.policy.write().await = Some(payload.to_vec());
                 *state = ZkVmState::Running;
                 Ok(())
             }
@@ -202,52 +289,111 @@ impl ZkVm {
         data: Vec<u8>,
         permissions: PagePermissions,
     ) -> ZkVmResult<usize> {
-        let size = data.len();
-        let mut page = MemoryPage::new(size, permissions)?;
+        let mut memory = self.memory.lock().await;
+        let page_id = memory.len();
+        let key = self.master_key.page_key(page_id, 0)?;
+        let mut page = MemoryPage::new(0, permissions, self.cipher, key);
         page.data = data;
         page.encrypt()?;
 
-        let mut memory = self.memory.lock().await;
-        let page_id = memory.len();
-        memory.push(page);
+        memory.push(Some(page));
         Ok(page_id)
     }
 
-    /// Access a decrypted page temporarily
+    /// Access a decrypted page temporarily. A page that is due for key
+    /// rotation is re-sealed under its next key generation afterwards.
     pub async fn with_decrypted_page<F, R>(&self, page_id: usize, f: F) -> ZkVmResult<R>
     where
         F: FnOnce(&[u8]) -> R,
     {
         let mut memory = self.memory.lock().await;
-        if page_id >= memory.len() {
-            return Err(ZkVmError::MemoryError("Invalid page ID".into()));
-        }
+        let page = memory
+            .get_mut(page_id)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| ZkVmError::MemoryError("Invalid page ID".into()))?;
 
-        let page = &mut memory[page_id];
         page.decrypt()?;
+        page.key.record_use();
         let result = f(&page.data);
+        if page.key.rotation_due() {
+            page.key = self
+                .master_key
+                .page_key(page_id, page.key.generation() + 1)?;
+        }
         page.encrypt()?;
 
         Ok(result)
     }
 
+    /// Free a page, zeroizing its contents and key. Its id is not reused.
+    pub async fn free_page(&self, page_id: usize) -> ZkVmResult<()> {
+        let mut memory = self.memory.lock().await;
+        match memory.get_mut(page_id).and_then(Option::take) {
+            // Dropping the page and its key zeroizes both
+            Some(_page) => Ok(()),
+            None => Err(ZkVmError::MemoryError("Invalid page ID".into())),
+        }
+    }
+
     /// Stop the VM and securely wipe all memory
     pub async fn terminate(&self) -> ZkVmResult<()> {
         let mut state = self.state.write().await;
         let mut memory = self.memory.lock().await;
 
         // Securely wipe all memory pages
-        for page in memory.iter_mut() {
+        for page in memory.iter_mut().flatten() {
             page.data.zeroize();
         }
 
         // Close the communication channel
         self.channel.close().await;
+        if let Some(mut policy) = self.policy.write().await.take() {
+            policy.zeroize();
+        }
+        self.executor.lock().await.wipe();
 
         *state = ZkVmState::Terminated;
         Ok(())
     }
 
+    /// Replace the VM's execution budget
+    pub async fn set_execution_budget(&self, budget: ExecutionBudget) {
+        *self.budget.write().await = budget;
+    }
+
+    /// Current execution budget
+    pub async fn execution_budget(&self) -> ExecutionBudget {
+        *self.budget.read().await
+    }
+
+    /// Whether the VM has never run: still `Ready`, with no memory pages and
+    /// the default budget
+    pub async fn is_pristine(&self) -> bool {
+        *self.state.read().await == ZkVmState::Ready
+            && self.memory.lock().await.is_empty()
+            && *self.budget.read().await == ExecutionBudget::foreground()
+    }
+
+    /// The policy the VM was started with, for the code running inside it
+    pub async fn policy<T: DeserializeOwned>(&self) -> ZkVmResult<T> {
+        match self.policy.read().await.as_deref() {
+            Some(payload) => PolicyCapsule::decode(payload),
+            None => Err(ZkVmError::InvalidOperation(
+                "VM was not started with a policy".into(),
+            )),
+        }
+    }
+
+    /// Cipher the VM seals its memory pages with
+    pub fn cipher(&self) -> PageCipherKind {
+        self.cipher
+    }
+
+    /// The VM's end of its host channel, for the code running inside it
+    pub fn guest_channel(&self) -> MuxChannel {
+        self.channel.clone()
+    }
+
     /// Get the VM's unique identifier
     pub fn id(&self) -> Arc<[u8; 32]> {
         self.id.clone()
@@ -292,12 +438,57 @@ mod tests {
         block_on(async {
             let (vm, _host_channel) = ZkVm::new().await.unwrap();
             assert!(matches!(*vm.state.read().await, ZkVmState::Ready));
+            assert!(vm.is_pristine().await);
 
-            vm.start().await.unwrap();
+            let capsule = vm
+                .seal_policy(&serde_json::json!({"scripts": false}))
+                .unwrap();
+            vm.start(capsule).await.unwrap();
             assert!(matches!(*vm.state.read().await, ZkVmState::Running));
+            assert!(!vm.is_pristine().await);
+            let policy: serde_json::Value = vm.policy().await.unwrap();
+            assert_eq!(policy["scripts"], false);
 
+            let guest = vm.guest_channel();
+            assert!(!guest.is_closed());
             vm.terminate().await.unwrap();
             assert!(matches!(*vm.state.read().await, ZkVmState::Terminated));
+            assert!(guest.is_closed());
+        });
+    }
+
+    #[test]
+    fn test_vm_refuses_altered_or_foreign_policy() {
+        block_on(async {
+            let (vm, _) = ZkVm::new().await.unwrap();
+            let (other, _) = ZkVm::new().await.unwrap();
+            let policy = serde_json::json!({"scripts": false});
+
+            let mut altered = vm.seal_policy(&policy).unwrap();
+            *altered.payload_mut() = br#"{"scripts":true}"#.to_vec();
+            assert!(vm.start(altered).await.is_err());
+            let foreign = other.seal_policy(&policy).unwrap();
+            assert!(vm.start(foreign).await.is_err());
+            assert!(vm.is_pristine().await);
+            assert!(vm.policy::<serde_json::Value>().await.is_err());
+
+            vm.start(vm.seal_policy(&policy).unwrap()).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_execution_budget_throttling() {
+        block_on(async {
+            let (vm, _) = ZkVm::new().await.unwrap();
+            assert!(!vm.execution_budget().await.is_throttled());
+
+            vm.set_execution_budget(ExecutionBudget::background()).await;
+            let budget = vm.execution_budget().await;
+            assert!(budget.is_throttled());
+            assert!(budget.timers_paused);
+
+            vm.set_execution_budget(ExecutionBudget::foreground()).await;
+            assert!(!vm.execution_budget().await.timers_paused);
         });
     }
 
@@ -316,14 +507,79 @@ mod tests {
 
             let memory = vm.memory.lock().await;
             assert_eq!(memory.len(), 1);
-            assert_eq!(memory[0].data.len(), 4096);
+            assert_eq!(memory[0].as_ref().unwrap().data.len(), 4096);
+        });
+    }
+
+    #[test]
+    fn test_encrypted_pages_open_under_either_cipher() {
+        block_on(async {
+            for cipher in [PageCipherKind::Aes256Gcm, PageCipherKind::XChaCha20Poly1305] {
+                let (vm, _) = ZkVm::with_cipher(cipher).await.unwrap();
+                assert_eq!(vm.cipher(), cipher);
+                let perms = PagePermissions {
+                    read: true,
+                    write: false,
+                    execute: false,
+                };
+
+                let page_id = vm
+                    .load_encrypted_page(b"secret".to_vec(), perms)
+                    .await
+                    .unwrap();
+                assert_ne!(
+                    vm.memory.lock().await[page_id].as_ref().unwrap().data,
+                    b"secret"
+                );
+                let seen = vm
+                    .with_decrypted_page(page_id, |data| data.to_vec())
+                    .await
+                    .unwrap();
+                assert_eq!(seen, b"secret");
+            }
+        });
+    }
+
+    #[test]
+    fn test_hot_pages_rotate_keys_and_freed_pages_are_gone() {
+        block_on(async {
+            let (vm, _) = ZkVm::new().await.unwrap();
+            let perms = PagePermissions {
+                read: true,
+                write: false,
+                execute: false,
+            };
+            let page_id = vm
+                .load_encrypted_page(b"hot".to_vec(), perms)
+                .await
+                .unwrap();
+            let generation =
+                |memory: &[Option<MemoryPage>]| memory[page_id].as_ref().unwrap().key.generation();
+
+            for _ in 0..keys::ROTATION_USES {
+                vm.with_decrypted_page(page_id, |_| ()).await.unwrap();
+            }
+            assert_eq!(generation(&vm.memory.lock().await), 1);
+            let seen = vm
+                .with_decrypted_page(page_id, |data| data.to_vec())
+                .await
+                .unwrap();
+            assert_eq!(seen, b"hot");
+
+            vm.free_page(page_id).await.unwrap();
+            assert!(vm.memory.lock().await[page_id].is_none());
+            assert!(vm.with_decrypted_page(page_id, |_| ()).await.is_err());
+            assert!(vm.free_page(page_id).await.is_err());
+
+            let next = vm.allocate_page(64, perms).await.unwrap();
+            assert_ne!(next, page_id);
         });
     }
 
     #[test]
     fn test_channel_communication() {
         block_on(async {
-            let (mut vm, mut host_channel) = ZkVm::new().await.unwrap();
+            let (vm, host_channel) = ZkVm::new().await.unwrap();
 
             // Send a message from host to VM
             let message = ChannelMessage::Control {
@@ -334,7 +590,7 @@ mod tests {
             host_channel.send(message.clone()).await.unwrap();
 
             // VM should receive the message
-            let received = vm.channel.receive().await.unwrap();
+            let received = vm.channel.receive(StreamId::Control).await.unwrap();
             match received {
                 ChannelMessage::Control { command, params } => {
                     assert_eq!(command, "test");
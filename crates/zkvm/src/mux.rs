@@ -0,0 +1,350 @@
+//! Stream multiplexing with flow control over a single channel
+//!
+//! A tab talks to its VM over one encrypted [`Channel`] pair. The pair
+//! carries three streams: control (budgets, mute, container conversion),
+//! renderer (pages in, display lists out) and resource (requests and
+//! responses). Each stream has a flow-control window: a sender may have at
+//! most `window` messages on a stream that the peer has not yet read, and
+//! waits for the peer to return credit before sending more. A chatty page
+//! therefore stalls itself instead of growing a queue on the host, and a
+//! busy stream cannot starve the others.
+//!
+//! No task runs in the background. Whichever caller is waiting, to receive
+//! or for credit to send, reads the next frame off the channel and routes
+//! it: data to its stream's inbox, credit to its stream's window.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::futures::Notified;
+use tokio::sync::{Mutex, MutexGuard, Notify, Semaphore};
+
+use crate::channel::{ChannelReceiver, ChannelSender};
+use crate::{Channel, ChannelMessage, ZkVmResult};
+
+/// Messages a stream may have unread unless configured otherwise
+pub const DEFAULT_STREAM_WINDOW: u32 = 8;
+
+/// A stream of a multiplexed channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StreamId {
+    /// Budgets, mute state and other commands about the VM itself
+    Control,
+    /// Pages to render and what the renderer made of them
+    Renderer,
+    /// Resource requests and responses
+    Resource,
+}
+
+impl StreamId {
+    /// Every stream, in the order a receiver serves them
+    pub const ALL: [Self; 3] = [Self::Control, Self::Renderer, Self::Resource];
+
+    /// The stream a message travels on unless sent on another explicitly
+    pub fn of(message: &ChannelMessage) -> Self {
+        match message {
+            ChannelMessage::Control { .. } => Self::Control,
+            ChannelMessage::UiEvent { .. } => Self::Renderer,
+            ChannelMessage::ResourceRequest { .. }
+            | ChannelMessage::ResourceResponse { .. }
+            | ChannelMessage::ResourceError { .. } => Self::Resource,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// What travels over the underlying channel
+#[derive(Debug, Serialize, Deserialize)]
+enum Frame {
+    /// A message on a stream
+    Data {
+        stream: StreamId,
+        message: ChannelMessage,
+    },
+    /// The peer read this many messages of a stream; they may be sent again
+    Credit { stream: StreamId, credits: u32 },
+}
+
+/// One end of a multiplexed channel. Clones are handles to the same end.
+#[derive(Clone)]
+pub struct MuxChannel {
+    inner: Arc<MuxInner>,
+}
+
+struct MuxInner {
+    window: u32,
+    sender: ChannelSender,
+    /// Held by whichever caller is reading frames off the channel
+    receiver: Mutex<ChannelReceiver>,
+    streams: [StreamState; 3],
+    /// Woken when a frame was routed or the receiver was let go
+    routed: Notify,
+    /// Set once this end was closed
+    closed: AtomicBool,
+}
+
+struct StreamState {
+    /// Messages that may still be sent before the peer returns credit
+    credits: Semaphore,
+    /// Messages received and not yet read; never more than the window
+    inbox: StdMutex<VecDeque<ChannelMessage>>,
+    /// Messages read since credit was last returned
+    consumed: AtomicU32,
+}
+
+impl MuxChannel {
+    /// Create a connected pair, each stream with a window of `window`
+    /// messages (at least one)
+    pub fn pair(window: u32) -> ZkVmResult<(Self, Self)> {
+        let window = window.max(1);
+        // Room for every stream's full window plus the credit returned for
+        // it, so a sender only ever waits on a window, never on the channel
+        let capacity = StreamId::ALL.len() * window as usize * 2;
+        let (host, guest) = Channel::with_capacity(capacity)?;
+        Ok((Self::new(host, window), Self::new(guest, window)))
+    }
+
+    fn new(channel: Channel, window: u32) -> Self {
+        let (sender, receiver) = channel.split();
+        let stream = || StreamState {
+            credits: Semaphore::new(window as usize),
+            inbox: StdMutex::new(VecDeque::new()),
+            consumed: AtomicU32::new(0),
+        };
+        Self {
+            inner: Arc::new(MuxInner {
+                window,
+                sender,
+                receiver: Mutex::new(receiver),
+                streams: [stream(), stream(), stream()],
+                routed: Notify::new(),
+                closed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Send a message on the stream its kind belongs to
+    pub async fn send(&self, message: ChannelMessage) -> ZkVmResult<()> {
+        self.send_on(StreamId::of(&message), message).await
+    }
+
+    /// Send a message on `stream`, waiting while its window is full
+    pub async fn send_on(&self, stream: StreamId, message: ChannelMessage) -> ZkVmResult<()> {
+        let credits = &self.inner.streams[stream.index()].credits;
+        loop {
+            let routed = self.inner.routed.notified();
+            tokio::pin!(routed);
+            routed.as_mut().enable();
+            if let Ok(permit) = credits.try_acquire() {
+                permit.forget();
+                break;
+            }
+            // Credit only arrives by reading the channel
+            self.pump(routed).await?;
+        }
+        self.inner
+            .sender
+            .send(&Frame::Data { stream, message })
+            .await
+    }
+
+    /// The next message on `stream`
+    pub async fn receive(&self, stream: StreamId) -> ZkVmResult<ChannelMessage> {
+        self.receive_from(&[stream])
+            .await
+            .map(|(_, message)| message)
+    }
+
+    /// The next message on any stream, control first, with its stream
+    pub async fn receive_any(&self) -> ZkVmResult<(StreamId, ChannelMessage)> {
+        self.receive_from(&StreamId::ALL).await
+    }
+
+    /// Close this end; its sends and receives fail from now on
+    pub async fn close(&self) {
+        self.inner.closed.store(true, Ordering::Release);
+        self.inner.sender.close().await;
+        self.inner.routed.notify_waiters();
+    }
+
+    /// Whether this end was closed. Needs no lock, so work that cannot
+    /// await, such as a page's scripts, can check it to stop early.
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::Acquire)
+    }
+
+    /// Messages on `stream` received and not yet read
+    pub fn pending(&self, stream: StreamId) -> usize {
+        self.inner.streams[stream.index()]
+            .inbox
+            .lock()
+            .map_or(0, |inbox| inbox.len())
+    }
+
+    async fn receive_from(&self, streams: &[StreamId]) -> ZkVmResult<(StreamId, ChannelMessage)> {
+        loop {
+            let routed = self.inner.routed.notified();
+            tokio::pin!(routed);
+            routed.as_mut().enable();
+            if let Some(received) = self.take(streams) {
+                self.return_credit(received.0).await?;
+                return Ok(received);
+            }
+            self.pump(routed).await?;
+        }
+    }
+
+    /// Read one frame off the channel and route it, or, while another caller
+    /// is reading, wait for that caller to route one
+    async fn pump(&self, routed: Pin<&mut Notified<'_>>) -> ZkVmResult<()> {
+        let Ok(receiver) = self.inner.receiver.try_lock() else {
+            routed.await;
+            return Ok(());
+        };
+        let mut reading = Reading {
+            inner: &self.inner,
+            receiver: Some(receiver),
+        };
+        let frame = match reading.receiver.as_mut() {
+            Some(receiver) => receiver.receive().await?,
+            None => return Ok(()),
+        };
+        drop(reading);
+        self.route(frame);
+        Ok(())
+    }
+
+    /// Pop the first waiting message of `streams`
+    fn take(&self, streams: &[StreamId]) -> Option<(StreamId, ChannelMessage)> {
+        streams.iter().find_map(|stream| {
+            let mut inbox = self.inner.streams[stream.index()].inbox.lock().ok()?;
+            inbox.pop_front().map(|message| (*stream, message))
+        })
+    }
+
+    /// File a frame read off the channel and wake everyone waiting on one
+    fn route(&self, frame: Frame) {
+        match frame {
+            Frame::Data { stream, message } => {
+                let state = &self.inner.streams[stream.index()];
+                if let Ok(mut inbox) = state.inbox.lock() {
+                    if inbox.len() < self.inner.window as usize {
+                        inbox.push_back(message);
+                    } else {
+                        // Only a peer ignoring its window gets here
+                        log::warn!("Dropped a {:?} message sent past its window", stream);
+                    }
+                }
+            }
+            Frame::Credit { stream, credits } => {
+                let state = &self.inner.streams[stream.index()];
+                // A peer cannot grant more than the window it was given
+                let room =
+                    (self.inner.window as usize).saturating_sub(state.credits.available_permits());
+                state.credits.add_permits((credits as usize).min(room));
+            }
+        }
+        self.inner.routed.notify_waiters();
+    }
+
+    /// Count a message of `stream` as read, returning credit to the peer once
+    /// half the window has been read
+    async fn return_credit(&self, stream: StreamId) -> ZkVmResult<()> {
+        let consumed = &self.inner.streams[stream.index()].consumed;
+        let batch = (self.inner.window / 2).max(1);
+        if consumed.fetch_add(1, Ordering::AcqRel) + 1 < batch {
+            return Ok(());
+        }
+        let credits = consumed.swap(0, Ordering::AcqRel);
+        if credits == 0 {
+            return Ok(());
+        }
+        self.inner
+            .sender
+            .send(&Frame::Credit { stream, credits })
+            .await
+    }
+}
+
+/// The receiver, held while reading a frame. Letting it go, however the
+/// read ends, wakes the callers waiting for their turn to read.
+struct Reading<'a> {
+    inner: &'a MuxInner,
+    receiver: Option<MutexGuard<'a, ChannelReceiver>>,
+}
+
+impl Drop for Reading<'_> {
+    fn drop(&mut self) {
+        // Released before waking anyone, so the woken can take it
+        self.receiver.take();
+        self.inner.routed.notify_waiters();
+    }
+}
+
+impl std::fmt::Debug for MuxChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MuxChannel")
+            .field("window", &self.inner.window)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn control(command: &str) -> ChannelMessage {
+        ChannelMessage::Control {
+            command: command.to_string(),
+            params: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_are_independent_and_windows_bound_senders() {
+        let (host, guest) = MuxChannel::pair(2).unwrap();
+
+        // A full renderer window stalls the sender...
+        host.send_on(StreamId::Renderer, control("page-1"))
+            .await
+            .unwrap();
+        host.send_on(StreamId::Renderer, control("page-2"))
+            .await
+            .unwrap();
+        let third = {
+            let host = host.clone();
+            tokio::spawn(async move { host.send_on(StreamId::Renderer, control("page-3")).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!third.is_finished());
+
+        // ...but not the other streams, which are read past the waiting pages
+        host.send(control("set_background")).await.unwrap();
+        let message = guest.receive(StreamId::Control).await.unwrap();
+        assert!(
+            matches!(message, ChannelMessage::Control { command, .. } if command == "set_background")
+        );
+        assert_eq!(guest.pending(StreamId::Renderer), 2);
+
+        // Reading the renderer stream returns credit and lets the sender go on
+        for expected in ["page-1", "page-2", "page-3"] {
+            let message =
+                tokio::time::timeout(Duration::from_secs(1), guest.receive(StreamId::Renderer))
+                    .await
+                    .unwrap()
+                    .unwrap();
+            assert!(
+                matches!(message, ChannelMessage::Control { command, .. } if command == expected)
+            );
+        }
+        third.await.unwrap().unwrap();
+        assert_eq!(guest.pending(StreamId::Renderer), 0);
+    }
+}